@@ -0,0 +1,216 @@
+use crate::DebugInfo;
+use probe_rs::{Core, MemoryInterface, RegisterValue};
+use std::time::{Duration, Instant};
+
+/// The interval at which [`wait_for_symbol_value`] samples the target memory.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A failed assertion on the state of the target.
+#[derive(Debug, thiserror::Error)]
+pub enum AssertionError {
+    /// The memory content did not match the expected value.
+    #[error(
+        "Memory at {address:#010x} does not match: first difference at offset {offset:#x} (expected {expected:02x?}, found {actual:02x?})"
+    )]
+    MemoryMismatch {
+        /// The start address of the compared memory.
+        address: u64,
+        /// The offset of the first byte which differs.
+        offset: usize,
+        /// The expected memory content.
+        expected: Vec<u8>,
+        /// The memory content which was read from the target.
+        actual: Vec<u8>,
+    },
+    /// The register did not contain the expected value.
+    #[error("Register {register} has value {actual}, expected {expected}")]
+    RegisterMismatch {
+        /// The name of the register.
+        register: String,
+        /// The expected register value.
+        expected: RegisterValue,
+        /// The register value which was read from the target.
+        actual: RegisterValue,
+    },
+    /// The register name is not known for the core.
+    #[error("Register {0} is not available on this core")]
+    UnknownRegister(String),
+    /// The symbol could not be found in the debug information.
+    #[error("Symbol {0} was not found in the ELF symbol table")]
+    SymbolNotFound(String),
+    /// The expected value is larger than the symbol.
+    #[error(
+        "Expected value of {value_len} bytes does not fit into symbol {symbol} of {symbol_len} bytes"
+    )]
+    SizeMismatch {
+        /// The name of the symbol.
+        symbol: String,
+        /// The size of the symbol in bytes.
+        symbol_len: u64,
+        /// The size of the expected value in bytes.
+        value_len: usize,
+    },
+    /// The symbol did not reach the expected value in time.
+    #[error(
+        "Symbol {symbol} did not reach value {expected:02x?} within {timeout:?}, last value was {last:02x?}"
+    )]
+    Timeout {
+        /// The name of the symbol.
+        symbol: String,
+        /// The expected memory content of the symbol.
+        expected: Vec<u8>,
+        /// The last memory content which was read from the target.
+        last: Vec<u8>,
+        /// The timeout which elapsed.
+        timeout: Duration,
+    },
+    /// An error occurred while accessing the target.
+    #[error("Error accessing the target")]
+    Probe(#[from] probe_rs::Error),
+}
+
+/// Asserts that the target memory at `address` matches `expected`.
+pub fn assert_mem_eq(
+    memory: &mut impl MemoryInterface,
+    address: u64,
+    expected: &[u8],
+) -> Result<(), AssertionError> {
+    let mut actual = vec![0; expected.len()];
+    memory.read(address, &mut actual)?;
+
+    compare_memory(address, expected, actual)
+}
+
+/// Asserts that the core register with the given name contains `expected`.
+///
+/// The register is looked up by its primary name (e.g. `R0`, `x10`, `a2`), or by a special
+/// role such as `PC`, `SP` or `LR`.
+pub fn assert_reg(
+    core: &mut Core<'_>,
+    register: &str,
+    expected: impl Into<RegisterValue>,
+) -> Result<(), AssertionError> {
+    let expected = expected.into();
+
+    let register_id = core
+        .registers()
+        .all_registers()
+        .find(|r| r.name().eq_ignore_ascii_case(register))
+        .or_else(|| {
+            core.registers().all_registers().find(|r| {
+                r.roles
+                    .iter()
+                    .any(|role| role.to_string().eq_ignore_ascii_case(register))
+            })
+        })
+        .map(|r| r.id())
+        .ok_or_else(|| AssertionError::UnknownRegister(register.to_string()))?;
+
+    let actual: RegisterValue = core.read_core_reg(register_id)?;
+
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(AssertionError::RegisterMismatch {
+            register: register.to_string(),
+            expected,
+            actual,
+        })
+    }
+}
+
+/// Repeatedly samples the memory of `symbol` until it equals `value`, or `timeout` elapses.
+///
+/// The symbol is resolved using the ELF symbol table of `debug_info`. If the symbol size is known,
+/// `value` must not be larger than the symbol. The core is not halted while sampling, so this
+/// requires a target which supports background memory access.
+pub fn wait_for_symbol_value(
+    core: &mut Core<'_>,
+    debug_info: &DebugInfo,
+    symbol: &str,
+    value: &[u8],
+    timeout: Duration,
+) -> Result<(), AssertionError> {
+    let elf_symbol = debug_info
+        .find_symbol(symbol)
+        .ok_or_else(|| AssertionError::SymbolNotFound(symbol.to_string()))?;
+
+    if elf_symbol.size != 0 && (value.len() as u64) > elf_symbol.size {
+        return Err(AssertionError::SizeMismatch {
+            symbol: symbol.to_string(),
+            symbol_len: elf_symbol.size,
+            value_len: value.len(),
+        });
+    }
+
+    let start = Instant::now();
+    let mut last = vec![0; value.len()];
+
+    loop {
+        core.read(elf_symbol.address, &mut last)?;
+
+        if last == value {
+            return Ok(());
+        }
+
+        if start.elapsed() >= timeout {
+            return Err(AssertionError::Timeout {
+                symbol: symbol.to_string(),
+                expected: value.to_vec(),
+                last,
+                timeout,
+            });
+        }
+
+        std::thread::sleep(SAMPLE_INTERVAL);
+    }
+}
+
+fn compare_memory(address: u64, expected: &[u8], actual: Vec<u8>) -> Result<(), AssertionError> {
+    match expected.iter().zip(actual.iter()).position(|(e, a)| e != a) {
+        None => Ok(()),
+        Some(offset) => Err(AssertionError::MemoryMismatch {
+            address,
+            offset,
+            expected: expected.to_vec(),
+            actual,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use probe_rs::test::MockMemory;
+
+    #[test]
+    fn memory_matches() {
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, vec![1, 2, 3, 4]);
+
+        assert_mem_eq(&mut memory, 0x2000_0000, &[1, 2, 3, 4]).unwrap();
+        assert_mem_eq(&mut memory, 0x2000_0001, &[2, 3]).unwrap();
+    }
+
+    #[test]
+    fn memory_mismatch_reports_first_difference() {
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, vec![1, 2, 3, 4]);
+
+        let error = assert_mem_eq(&mut memory, 0x2000_0000, &[1, 2, 0, 0]).unwrap_err();
+
+        match error {
+            AssertionError::MemoryMismatch {
+                address,
+                offset,
+                actual,
+                ..
+            } => {
+                assert_eq!(address, 0x2000_0000);
+                assert_eq!(offset, 2);
+                assert_eq!(actual, vec![1, 2, 3, 4]);
+            }
+            other => panic!("Unexpected error: {other:?}"),
+        }
+    }
+}
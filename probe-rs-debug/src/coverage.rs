@@ -0,0 +1,279 @@
+use crate::DebugInfo;
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    num::NonZeroU64,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A contiguous range of instructions which belongs to a single source line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineRange {
+    /// The first address of the range.
+    pub start: u64,
+    /// The address after the last instruction of the range.
+    pub end: u64,
+    /// The source file the instructions belong to.
+    pub path: String,
+    /// The line in the source file.
+    pub line: u64,
+}
+
+/// Collects line coverage information for a program, based on PC samples captured from the target.
+///
+/// The coverage is approximated: only lines which were sampled at least once are reported as covered.
+#[derive(Debug, Clone)]
+pub struct CoverageCollector {
+    /// All instrumentable line ranges, sorted by start address.
+    line_ranges: Vec<LineRange>,
+    /// Hit counts per file and line.
+    hits: BTreeMap<String, BTreeMap<u64, u64>>,
+}
+
+impl CoverageCollector {
+    /// Create a new collector, using the line tables from `debug_info` to determine which lines are instrumentable.
+    pub fn new(debug_info: &DebugInfo) -> Self {
+        Self::from_line_ranges(line_ranges(debug_info))
+    }
+
+    /// Create a new collector from a list of line ranges.
+    pub fn from_line_ranges(mut line_ranges: Vec<LineRange>) -> Self {
+        line_ranges.sort_by_key(|range| range.start);
+
+        let mut hits: BTreeMap<String, BTreeMap<u64, u64>> = BTreeMap::new();
+        for range in &line_ranges {
+            hits.entry(range.path.clone())
+                .or_default()
+                .entry(range.line)
+                .or_insert(0);
+        }
+
+        Self { line_ranges, hits }
+    }
+
+    /// Record that the instruction at `pc` was sampled `count` times.
+    pub fn record_pc_sample(&mut self, pc: u64, count: u64) {
+        self.record_range(pc, pc + 1, count);
+    }
+
+    /// Returns the hit counts, grouped by file and line.
+    pub fn hits(&self) -> &BTreeMap<String, BTreeMap<u64, u64>> {
+        &self.hits
+    }
+
+    fn record_range(&mut self, start: u64, end: u64, count: u64) {
+        // The ranges are sorted by start address, so we can skip all the ranges which start after `end`.
+        let last = self.line_ranges.partition_point(|range| range.start < end);
+
+        for range in self.line_ranges[..last].iter().rev() {
+            if range.end <= start {
+                // Line ranges can overlap for inlined code, so we can't stop here.
+                continue;
+            }
+
+            if let Some(hits) = self
+                .hits
+                .get_mut(&range.path)
+                .and_then(|lines| lines.get_mut(&range.line))
+            {
+                *hits += count;
+            }
+        }
+    }
+
+    /// Export the coverage data in the LCOV tracefile format, as used by `genhtml` and most CI services.
+    pub fn to_lcov(&self, test_name: &str) -> String {
+        let mut output = String::new();
+
+        for (path, lines) in &self.hits {
+            let _ = writeln!(output, "TN:{test_name}");
+            let _ = writeln!(output, "SF:{path}");
+            for (line, hits) in lines {
+                let _ = writeln!(output, "DA:{line},{hits}");
+            }
+            let _ = writeln!(output, "LH:{}", lines.values().filter(|&&h| h > 0).count());
+            let _ = writeln!(output, "LF:{}", lines.len());
+            let _ = writeln!(output, "end_of_record");
+        }
+
+        output
+    }
+
+    /// Export the coverage data in the Cobertura XML format.
+    pub fn to_cobertura(&self) -> String {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        let (lines_valid, lines_covered) = self.line_totals();
+
+        let mut output = String::new();
+        let _ = writeln!(output, r#"<?xml version="1.0" ?>"#);
+        let _ = writeln!(
+            output,
+            r#"<coverage line-rate="{}" branch-rate="0" lines-covered="{lines_covered}" lines-valid="{lines_valid}" branches-covered="0" branches-valid="0" complexity="0" version="probe-rs" timestamp="{timestamp}">"#,
+            line_rate(lines_covered, lines_valid)
+        );
+        let _ = writeln!(output, "  <packages>");
+        let _ = writeln!(
+            output,
+            r#"    <package name="target" line-rate="{}" branch-rate="0" complexity="0">"#,
+            line_rate(lines_covered, lines_valid)
+        );
+        let _ = writeln!(output, "      <classes>");
+
+        for (path, lines) in &self.hits {
+            let covered = lines.values().filter(|&&h| h > 0).count();
+            let path = xml_escape(path);
+            let _ = writeln!(
+                output,
+                r#"        <class name="{path}" filename="{path}" line-rate="{}" branch-rate="0" complexity="0">"#,
+                line_rate(covered, lines.len())
+            );
+            let _ = writeln!(output, "          <methods/>");
+            let _ = writeln!(output, "          <lines>");
+            for (line, hits) in lines {
+                let _ = writeln!(
+                    output,
+                    r#"            <line number="{line}" hits="{hits}" branch="false"/>"#
+                );
+            }
+            let _ = writeln!(output, "          </lines>");
+            let _ = writeln!(output, "        </class>");
+        }
+
+        let _ = writeln!(output, "      </classes>");
+        let _ = writeln!(output, "    </package>");
+        let _ = writeln!(output, "  </packages>");
+        let _ = writeln!(output, "</coverage>");
+
+        output
+    }
+
+    /// Returns the number of instrumentable lines, and the number of covered lines.
+    fn line_totals(&self) -> (usize, usize) {
+        self.hits.values().fold((0, 0), |(valid, covered), lines| {
+            (
+                valid + lines.len(),
+                covered + lines.values().filter(|&&h| h > 0).count(),
+            )
+        })
+    }
+}
+
+/// Collect the address ranges of all source lines from the DWARF line tables.
+fn line_ranges(debug_info: &DebugInfo) -> Vec<LineRange> {
+    let mut line_ranges = Vec::new();
+
+    for unit_info in &debug_info.unit_infos {
+        let unit = &unit_info.unit;
+
+        let Some(line_program) = unit.line_program.clone() else {
+            continue;
+        };
+
+        let (program, sequences) = match line_program.sequences() {
+            Ok(value) => value,
+            Err(error) => {
+                tracing::warn!("Failed to read line program sequences: {:?}", error);
+                continue;
+            }
+        };
+
+        for sequence in &sequences {
+            let mut rows = program.resume_from(sequence);
+            let mut previous_row: Option<gimli::LineRow> = None;
+
+            while let Ok(Some((_, row))) = rows.next_row() {
+                if let Some(previous) = previous_row.take() {
+                    let line = previous.line().map(NonZeroU64::get);
+                    let path = debug_info.find_file_and_directory(unit, previous.file_index());
+
+                    if let (Some(line), Some(path)) = (line, path) {
+                        if previous.address() < row.address() {
+                            line_ranges.push(LineRange {
+                                start: previous.address(),
+                                end: row.address(),
+                                path: path.to_path().display().to_string(),
+                                line,
+                            });
+                        }
+                    }
+                }
+
+                if !row.end_sequence() {
+                    previous_row = Some(*row);
+                }
+            }
+        }
+    }
+
+    line_ranges
+}
+
+fn line_rate(covered: usize, valid: usize) -> f64 {
+    if valid == 0 {
+        0.0
+    } else {
+        covered as f64 / valid as f64
+    }
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn collector() -> CoverageCollector {
+        CoverageCollector::from_line_ranges(vec![
+            LineRange {
+                start: 0x100,
+                end: 0x104,
+                path: "src/main.rs".to_string(),
+                line: 10,
+            },
+            LineRange {
+                start: 0x104,
+                end: 0x10a,
+                path: "src/main.rs".to_string(),
+                line: 11,
+            },
+            LineRange {
+                start: 0x200,
+                end: 0x208,
+                path: "src/lib.rs".to_string(),
+                line: 3,
+            },
+        ])
+    }
+
+    #[test]
+    fn pc_samples_mark_lines() {
+        let mut coverage = collector();
+        coverage.record_pc_sample(0x106, 3);
+
+        assert_eq!(coverage.hits()["src/main.rs"][&10], 0);
+        assert_eq!(coverage.hits()["src/main.rs"][&11], 3);
+        assert_eq!(coverage.hits()["src/lib.rs"][&3], 0);
+    }
+
+    #[test]
+    fn lcov_export() {
+        let mut coverage = collector();
+        coverage.record_pc_sample(0x200, 1);
+
+        assert_eq!(
+            coverage.to_lcov("test"),
+            "TN:test\nSF:src/lib.rs\nDA:3,1\nLH:1\nLF:1\nend_of_record\n\
+             TN:test\nSF:src/main.rs\nDA:10,0\nDA:11,0\nLH:0\nLF:2\nend_of_record\n"
+        );
+    }
+}
@@ -1,17 +1,22 @@
 use super::{
     DebugError, DebugRegisters, StackFrame, VariableCache,
-    exception_handling::ExceptionInterface,
+    exception_handling::{ExceptionInterface, exception_handler_for_core},
     function_die::{Die, FunctionDie},
     get_object_reference,
     unit_info::UnitInfo,
     variable::*,
 };
-use crate::{SourceLocation, VerifiedBreakpoint, stack_frame::StackFrameInfo, unit_info::RangeExt};
+use crate::{
+    SourceLocation, VerifiedBreakpoint, stack_frame::StackFrameInfo,
+    type_recognizer::TypeRecognizers, unit_info::RangeExt,
+};
 use gimli::{
     BaseAddresses, DebugFrame, RunTimeEndian, UnwindContext, UnwindSection, UnwindTableRow,
 };
-use object::read::{Object, ObjectSection};
-use probe_rs::{Error, MemoryInterface, RegisterDataType, RegisterRole, RegisterValue, UnwindRule};
+use object::read::{Object, ObjectSection, ObjectSymbol};
+use probe_rs::{
+    CoreDump, Error, MemoryInterface, RegisterDataType, RegisterRole, RegisterValue, UnwindRule,
+};
 use probe_rs_target::InstructionSet;
 use std::{
     borrow, cmp::Ordering, num::NonZeroU64, ops::ControlFlow, path::Path, rc::Rc, str::from_utf8,
@@ -38,6 +43,108 @@ pub struct DebugInfo {
     pub(crate) endianness: gimli::RunTimeEndian,
 
     pub(crate) addr2line: Option<addr2line::Loader>,
+
+    pub(crate) symbols: Vec<ElfSymbol>,
+
+    pub(crate) type_recognizers: TypeRecognizers,
+}
+
+/// A defined entry of the ELF symbol table, such as a static variable or a function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElfSymbol {
+    /// The raw (possibly mangled) name of the symbol.
+    pub name: String,
+    /// The demangled name of the symbol, if the raw name was mangled.
+    pub demangled_name: Option<String>,
+    /// The address of the symbol.
+    pub address: u64,
+    /// The size of the symbol in bytes. This is zero if the size is unknown.
+    pub size: u64,
+}
+
+impl ElfSymbol {
+    /// Returns `true` if `name` refers to this symbol, either by its raw or its demangled name.
+    ///
+    /// Demangled Rust names are also matched without their trailing hash (`::h0123456789abcdef`).
+    pub fn matches(&self, name: &str) -> bool {
+        if self.name == name {
+            return true;
+        }
+
+        let Some(demangled) = self.demangled_name.as_deref() else {
+            return false;
+        };
+
+        demangled == name
+            || demangled
+                .strip_prefix(name)
+                .and_then(|suffix| suffix.strip_prefix("::h"))
+                .is_some_and(|hash| hash.chars().all(|c| c.is_ascii_hexdigit()))
+    }
+
+    /// Returns the name to display for this symbol, which is the demangled name without a trailing
+    /// Rust hash, or the raw name if it was not mangled.
+    pub fn display_name(&self) -> &str {
+        match self.demangled_name.as_deref() {
+            Some(demangled) => strip_rust_hash(demangled),
+            None => &self.name,
+        }
+    }
+
+    /// Returns `true` if this symbol is an instance of the function `function_name`. This is more
+    /// lenient than [`Self::matches`]:
+    /// - Leading path segments may be omitted, e.g. `init` matches `app::board::init`.
+    /// - Generic arguments and C++ parameter lists may be omitted, e.g. `foo` matches `foo::<u32>` and `foo(int)`.
+    pub fn matches_function(&self, function_name: &str) -> bool {
+        self.matches(function_name) || function_name_matches(self.display_name(), function_name)
+    }
+}
+
+/// Strip the trailing Rust hash (`::h0123456789abcdef`) of a demangled name.
+pub(crate) fn strip_rust_hash(demangled: &str) -> &str {
+    match demangled.rsplit_once("::h") {
+        Some((name, hash)) if !hash.is_empty() && hash.chars().all(|c| c.is_ascii_hexdigit()) => {
+            name
+        }
+        _ => demangled,
+    }
+}
+
+/// Returns `true` if the qualified function name `name` refers to `function_name`, following the
+/// rules of [`ElfSymbol::matches_function`].
+fn function_name_matches(name: &str, function_name: &str) -> bool {
+    let name = match name.split_once('(') {
+        Some((without_parameters, _)) => without_parameters,
+        None => name,
+    };
+    let name = strip_generic_arguments(name);
+
+    name == function_name
+        || name
+            .strip_suffix(function_name)
+            .is_some_and(|path| path.ends_with("::"))
+}
+
+/// Strip the trailing generic arguments of a demangled name, e.g. `foo::<u32>` becomes `foo`.
+fn strip_generic_arguments(name: &str) -> &str {
+    if !name.ends_with('>') {
+        return name;
+    }
+
+    let mut depth = 0;
+    for (position, character) in name.char_indices().rev() {
+        match character {
+            '>' => depth += 1,
+            '<' => {
+                depth -= 1;
+                if depth == 0 {
+                    return name[..position].trim_end_matches("::");
+                }
+            }
+            _ => {}
+        }
+    }
+    name
 }
 
 impl DebugInfo {
@@ -102,6 +209,25 @@ impl DebugInfo {
             };
         }
 
+        let symbols = object
+            .symbols()
+            .filter(|symbol| symbol.is_definition())
+            .filter_map(|symbol| {
+                let name = symbol.name().ok()?;
+                if name.is_empty() {
+                    return None;
+                }
+
+                Some(ElfSymbol {
+                    name: name.to_string(),
+                    demangled_name: addr2line::demangle(name, gimli::DW_LANG_Rust)
+                        .or_else(|| addr2line::demangle(name, gimli::DW_LANG_C_plus_plus)),
+                    address: symbol.address(),
+                    size: symbol.size(),
+                })
+            })
+            .collect();
+
         Ok(DebugInfo {
             dwarf: dwarf_cow,
             frame_section,
@@ -111,9 +237,36 @@ impl DebugInfo {
             unit_infos,
             endianness,
             addr2line: None,
+            symbols,
+            type_recognizers: TypeRecognizers::default(),
         })
     }
 
+    /// The recognizers used to summarize variables, e.g. to show the content of a `String`.
+    ///
+    /// Use this to register recognizers for the types of your own program.
+    pub fn type_recognizers_mut(&mut self) -> &mut TypeRecognizers {
+        &mut self.type_recognizers
+    }
+
+    /// The defined entries of the ELF symbol table.
+    pub fn symbols(&self) -> &[ElfSymbol] {
+        &self.symbols
+    }
+
+    /// Look up a symbol in the ELF symbol table by its raw or demangled name.
+    pub fn find_symbol(&self, name: &str) -> Option<&ElfSymbol> {
+        self.symbols.iter().find(|symbol| symbol.matches(name))
+    }
+
+    /// Look up the symbol that starts at `address`, e.g. to label the first instruction of a
+    /// function. Thumb function symbols, which have the lowest address bit set, are matched too.
+    pub fn symbol_at(&self, address: u64) -> Option<&ElfSymbol> {
+        self.symbols
+            .iter()
+            .find(|symbol| symbol.address == address || symbol.address == address | 1)
+    }
+
     /// Try get the [`SourceLocation`] for a given address.
     pub fn get_source_location(&self, address: u64) -> Option<SourceLocation> {
         for unit_info in &self.unit_infos {
@@ -546,6 +699,24 @@ impl DebugInfo {
         self.unwind_impl(initial_registers, core, exception_handler, instruction_set)
     }
 
+    /// Performs the logical unwind of the stack captured in a [`CoreDump`], using the registers
+    /// and the core type stored in the dump. See [`DebugInfo::unwind`] for details.
+    pub fn unwind_core_dump(
+        &self,
+        core_dump: &mut CoreDump,
+    ) -> Result<Vec<StackFrame>, probe_rs::Error> {
+        let initial_registers = DebugRegisters::from_coredump(core_dump);
+        let exception_handler = exception_handler_for_core(core_dump.core_type());
+        let instruction_set = core_dump.instruction_set();
+
+        self.unwind_impl(
+            initial_registers,
+            core_dump,
+            exception_handler.as_ref(),
+            Some(instruction_set),
+        )
+    }
+
     pub(crate) fn unwind_impl(
         &self,
         initial_registers: DebugRegisters,
@@ -830,6 +1001,78 @@ impl DebugInfo {
         VerifiedBreakpoint::for_source_location(self, path, line, column)
     }
 
+    /// Find the breakpoint locations of all instances of the function `function_name`. There can be
+    /// more than one, because generic functions are monomorphized, and functions can be inlined.
+    /// - See [`ElfSymbol::matches_function`] for how the name is matched against the symbol table.
+    /// - Inlined instances are matched by the demangled linkage name of the inlined function, with
+    ///   the same rules. Without a linkage name, the DWARF name has to be equal to `function_name`.
+    pub fn get_function_breakpoint_locations(
+        &self,
+        function_name: &str,
+    ) -> Result<Vec<VerifiedBreakpoint>, DebugError> {
+        // Thumb function symbols have the lowest address bit set.
+        let mut entry_addresses: Vec<u64> = self
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.matches_function(function_name))
+            .map(|symbol| symbol.address & !1)
+            .collect();
+
+        for unit_info in &self.unit_infos {
+            let mut entries_cursor = unit_info.unit.entries();
+            while let Ok(Some((_depth, current))) = entries_cursor.next_dfs() {
+                if current.tag() != gimli::DW_TAG_inlined_subroutine {
+                    continue;
+                }
+                let Ok(Some(entry_range)) = self
+                    .dwarf
+                    .die_ranges(&unit_info.unit, current)
+                    .and_then(|mut ranges| ranges.next())
+                else {
+                    continue;
+                };
+                let Ok(Some(function_die)) =
+                    FunctionDie::new(current.clone(), unit_info, self, entry_range.begin)
+                else {
+                    continue;
+                };
+                let is_match = match function_die.qualified_function_name(self) {
+                    Some(name) => function_name_matches(&name, function_name),
+                    None => function_die.function_name(self).as_deref() == Some(function_name),
+                };
+                if is_match {
+                    entry_addresses.push(entry_range.begin);
+                }
+            }
+        }
+        entry_addresses.sort_unstable();
+        entry_addresses.dedup();
+
+        let mut locations: Vec<VerifiedBreakpoint> = Vec::new();
+        for address in entry_addresses {
+            match VerifiedBreakpoint::for_address(self, address) {
+                Ok(location) => {
+                    if !locations
+                        .iter()
+                        .any(|existing| existing.address == location.address)
+                    {
+                        locations.push(location);
+                    }
+                }
+                Err(error) => tracing::debug!(
+                    "No breakpoint location for `{function_name}` at {address:#010x}: {error}"
+                ),
+            }
+        }
+
+        if locations.is_empty() {
+            return Err(DebugError::Other(format!(
+                "Could not find a function named `{function_name}`"
+            )));
+        }
+        Ok(locations)
+    }
+
     /// Get the path for an entry in a line program header, using the compilation unit's directory and file entries.
     // TODO: Determine if it is necessary to navigate the include directories to find the file absolute path for C files.
     pub(crate) fn get_path(
@@ -1434,7 +1677,7 @@ mod test {
     use std::path::{Path, PathBuf};
     use test_case::test_case;
 
-    use super::unwind_register_using_rule;
+    use super::{ElfSymbol, unwind_register_using_rule};
 
     /// Get the full path to a file in the `tests` directory.
     fn get_path_for_test_files(relative_file: &str) -> PathBuf {
@@ -1453,6 +1696,80 @@ mod test {
         })
     }
 
+    #[test]
+    fn elf_symbol_display_name() {
+        let symbol = |name: &str, demangled_name: Option<&str>| ElfSymbol {
+            name: name.to_string(),
+            demangled_name: demangled_name.map(str::to_string),
+            address: 0x1000,
+            size: 4,
+        };
+
+        assert_eq!(symbol("main", None).display_name(), "main");
+        assert_eq!(
+            symbol(
+                "_ZN4test4main17h0123456789abcdefE",
+                Some("test::main::h0123456789abcdef")
+            )
+            .display_name(),
+            "test::main"
+        );
+        assert_eq!(
+            symbol("_ZN4test3foo2hiE", Some("test::foo::hi")).display_name(),
+            "test::foo::hi"
+        );
+    }
+
+    #[test]
+    fn elf_symbol_matches_function() {
+        let symbol = |demangled_name: &str| ElfSymbol {
+            name: "_ZN_mangled".to_string(),
+            demangled_name: Some(demangled_name.to_string()),
+            address: 0x1000,
+            size: 4,
+        };
+
+        let rust_symbol = symbol("app::board::init::h0123456789abcdef");
+        assert!(rust_symbol.matches_function("init"));
+        assert!(rust_symbol.matches_function("board::init"));
+        assert!(rust_symbol.matches_function("app::board::init"));
+        assert!(rust_symbol.matches_function("_ZN_mangled"));
+        assert!(!rust_symbol.matches_function("it"));
+        assert!(!rust_symbol.matches_function("board"));
+
+        assert!(symbol("app::read::<u32>").matches_function("read"));
+        assert!(symbol("<app::Led as app::Toggle>::toggle").matches_function("toggle"));
+        assert!(symbol("ns::process(int, char const*)").matches_function("process"));
+        assert!(symbol("ns::process(int, char const*)").matches_function("ns::process"));
+    }
+
+    #[test]
+    fn inlined_function_breakpoints_match_qualified_names() {
+        let debug_info = load_test_elf_as_debug_info("inlined-functions");
+
+        for function_name in [
+            "disable",
+            "interrupt::disable",
+            "cortex_m::interrupt::disable",
+            "cortex_m::interrupt::free",
+            "Timer<T,U>::delay",
+        ] {
+            assert!(
+                debug_info
+                    .get_function_breakpoint_locations(function_name)
+                    .is_ok(),
+                "no breakpoint location for `{function_name}`"
+            );
+        }
+
+        // Only the last path segment is equal, the rest of the path is not.
+        assert!(
+            debug_info
+                .get_function_breakpoint_locations("peripheral::disable")
+                .is_err()
+        );
+    }
+
     #[test]
     fn unwinding_first_instruction_after_exception() {
         let debug_info = load_test_elf_as_debug_info("exceptions");
@@ -1936,18 +2253,7 @@ mod test {
         let mut adapter = CoreDump::load_raw(coredump).unwrap();
         let debug_info = DebugInfo::from_file(elf).unwrap();
 
-        let initial_registers = DebugRegisters::from_coredump(&adapter);
-        let exception_handler = exception_handler_for_core(adapter.core_type());
-        let instruction_set = adapter.instruction_set();
-
-        let stack_frames = debug_info
-            .unwind(
-                &mut adapter,
-                initial_registers,
-                exception_handler.as_ref(),
-                Some(instruction_set),
-            )
-            .unwrap();
+        let stack_frames = debug_info.unwind_core_dump(&mut adapter).unwrap();
 
         let printed_backtrace = stack_frames
             .into_iter()
@@ -22,6 +22,9 @@ pub enum SteppingMode {
     IntoStatement,
     /// Step to the calling statement, immediately after the current function returns.
     OutOfStatement,
+    /// Step into the function at the given address, e.g. one of several calls in the current statement,
+    /// and halt at the first statement after its prologue.
+    IntoTarget(u64),
 }
 
 impl SteppingMode {
@@ -69,6 +72,15 @@ impl SteppingMode {
                 SteppingMode::BreakPoint => {
                     self.get_halt_location(core, debug_info, program_counter, None)
                 }
+                SteppingMode::IntoTarget(function_address) => {
+                    (core_status, program_counter) =
+                        run_to_address(program_counter, *function_address, core)?;
+                    if program_counter != *function_address {
+                        // Something else (e.g. a user breakpoint) halted the core before we reached the target.
+                        return Ok((core_status, program_counter));
+                    }
+                    return SteppingMode::BreakPoint.step(core, debug_info);
+                }
                 SteppingMode::IntoStatement
                 | SteppingMode::OverStatement
                 | SteppingMode::OutOfStatement => {
@@ -185,10 +197,31 @@ impl SteppingMode {
                 //    -- If there is one, it means the step over target is in the current sequence,
                 //       so we get the valid breakpoint location for this next location.
                 //    -- If there is not one, the step over target is the same as the step out target.
-                return VerifiedBreakpoint::for_address(
-                    debug_info,
-                    program_counter.saturating_add(1),
-                )
+                // - Calls to inlined functions have no call instruction to step over. Instead, if the next halt
+                //   location is inside an inlined function that is not active at the program counter, we continue
+                //   the search after the end of that inlined function.
+                let mut next_address = program_counter.saturating_add(1);
+                return loop {
+                    match VerifiedBreakpoint::for_address(debug_info, next_address) {
+                        Ok(next_breakpoint) => {
+                            match entered_inlined_function_end(
+                                debug_info,
+                                program_counter,
+                                next_breakpoint.address,
+                            ) {
+                                Some(inlined_function_end) => {
+                                    tracing::debug!(
+                                        "Step Over target: Skipping inlined function at {:#010x}",
+                                        next_breakpoint.address
+                                    );
+                                    next_address = inlined_function_end;
+                                }
+                                None => break Ok(next_breakpoint),
+                            }
+                        }
+                        Err(error) => break Err(error),
+                    }
+                }
                 .or_else(|_| {
                     // If we cannot find a valid breakpoint in the current sequence, we will step out of the current sequence.
                     SteppingMode::OutOfStatement.get_halt_location(
@@ -212,11 +245,22 @@ impl SteppingMode {
                 //          (a.ii) An interrupt handler diverted the processing.
                 //   (b) We hit a PC at the address of the identified next instruction location,
                 //       which means there was nothing to step into, so the target is now halted (correctly) at the next statement.
+                // If the next statement is the first statement of an inlined function, there is no call instruction
+                // to step into, so we halt at that statement instead, to enter the inlined function.
+                let mut enters_inlined_function = false;
                 let target_pc = match VerifiedBreakpoint::for_address(
                     debug_info,
                     program_counter.saturating_add(1),
                 ) {
-                    Ok(identified_next_breakpoint) => identified_next_breakpoint.address,
+                    Ok(identified_next_breakpoint) => {
+                        enters_inlined_function = entered_inlined_function_end(
+                            debug_info,
+                            program_counter,
+                            identified_next_breakpoint.address,
+                        )
+                        .is_some();
+                        identified_next_breakpoint.address
+                    }
                     Err(DebugError::WarnAndContinue { .. }) => {
                         // There are no next statements in this sequence, so we will use the return address as the target.
                         if let Some(return_address) = return_address {
@@ -232,7 +276,12 @@ impl SteppingMode {
                     }
                 };
 
-                let (core_status, new_pc) = step_to_address(program_counter..=target_pc, core)?;
+                let step_range = if enters_inlined_function {
+                    program_counter..=target_pc.saturating_sub(1)
+                } else {
+                    program_counter..=target_pc
+                };
+                let (core_status, new_pc) = step_to_address(step_range, core)?;
                 if (program_counter..=target_pc).contains(&new_pc) {
                     // We have halted at an address after the current instruction (either in the same sequence,
                     // or at the return address of the current function),
@@ -306,7 +355,7 @@ impl SteppingMode {
                 }
             }
             _ => {
-                // SteppingMode::StepInstruction is handled in the `step()` method.
+                // SteppingMode::StepInstruction and SteppingMode::IntoTarget are handled in the `step()` method.
             }
         }
 
@@ -316,6 +365,35 @@ impl SteppingMode {
     }
 }
 
+/// Returns the end of the address range of the outermost inlined function at `address`, which is not active at
+/// `program_counter`, i.e. an inlined function that is entered when the program advances from `program_counter` to
+/// `address`.
+fn entered_inlined_function_end(
+    debug_info: &DebugInfo,
+    program_counter: u64,
+    address: u64,
+) -> Option<u64> {
+    let active_functions = debug_info
+        .get_function_dies(program_counter)
+        .map(|(_, functions)| functions)
+        .unwrap_or_default();
+    let (_, functions) = debug_info.get_function_dies(address).ok()?;
+
+    let entered_function = functions.iter().find(|function| {
+        function.is_inline()
+            && !active_functions.iter().any(|active_function| {
+                std::ptr::eq(active_function.unit_info, function.unit_info)
+                    && active_function.function_die.offset() == function.function_die.offset()
+            })
+    })?;
+
+    entered_function
+        .ranges
+        .iter()
+        .find(|range| range.contains(&address))
+        .map(|range| range.end)
+}
+
 /// Run the target to the desired address. If available, we will use a breakpoint, otherwise we will use single step.
 /// Returns the program counter at the end of the step, when any of the following conditions are met:
 /// - We reach the `target_address_range.end()` (inclusive)
@@ -439,3 +517,58 @@ fn step_to_address(
             .try_into()?,
     ))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn debug_info() -> DebugInfo {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/debug-unwind-tests/nRF52833_xxAA_full_unwind.elf");
+        DebugInfo::from_file(&path).unwrap()
+    }
+
+    #[test]
+    fn entering_an_inlined_function() {
+        let debug_info = debug_info();
+
+        // `__cortex_m_rt_main` calls the inlined `shared_loop_processing` at 0x314.
+        assert_eq!(
+            entered_inlined_function_end(&debug_info, 0x312, 0x314),
+            Some(0x3b0)
+        );
+    }
+
+    #[test]
+    fn entering_nested_inlined_functions() {
+        let debug_info = debug_info();
+
+        // `new_display<u8>` is inlined into `shared_loop_processing`, which is already active.
+        assert_eq!(
+            entered_inlined_function_end(&debug_info, 0x33c, 0x33e),
+            Some(0x358)
+        );
+        // `add` and `wrapping_add`, inlined into `add`, both start at 0x24c. The outermost one
+        // is entered.
+        assert_eq!(
+            entered_inlined_function_end(&debug_info, 0x24a, 0x24c),
+            Some(0x252)
+        );
+    }
+
+    #[test]
+    fn staying_in_the_same_function() {
+        let debug_info = debug_info();
+
+        // Inside the inlined `shared_loop_processing`.
+        assert_eq!(
+            entered_inlined_function_end(&debug_info, 0x316, 0x318),
+            None
+        );
+        // Inside `__cortex_m_rt_main`, before the first inlined function.
+        assert_eq!(
+            entered_inlined_function_end(&debug_info, 0x2b0, 0x2b2),
+            None
+        );
+    }
+}
@@ -1,6 +1,9 @@
 use crate::{DebugError, DebugRegisters, StackFrame, get_object_reference};
 use bitfield::bitfield;
-use probe_rs::{Error, MemoryInterface, RegisterRole, RegisterValue};
+use probe_rs::{
+    Error, MemoryInterface, MemoryMappedRegister, RegisterRole, RegisterValue,
+    memory_mapped_bitfield_register,
+};
 
 use super::{ExceptionInfo, ExceptionInterface};
 
@@ -24,6 +27,57 @@ pub(crate) static EXCEPTION_STACK_REGISTERS: &[RegisterRole] = &[
     RegisterRole::ProcessorStatus,
 ];
 
+/// Floating point registers which are stored on the stack after the [`EXCEPTION_STACK_REGISTERS`], when the
+/// exception frame is an extended frame. They are followed by a reserved word.
+pub(crate) static EXCEPTION_STACK_FP_REGISTERS: &[RegisterRole] = &[
+    RegisterRole::Core("S0"),
+    RegisterRole::Core("S1"),
+    RegisterRole::Core("S2"),
+    RegisterRole::Core("S3"),
+    RegisterRole::Core("S4"),
+    RegisterRole::Core("S5"),
+    RegisterRole::Core("S6"),
+    RegisterRole::Core("S7"),
+    RegisterRole::Core("S8"),
+    RegisterRole::Core("S9"),
+    RegisterRole::Core("S10"),
+    RegisterRole::Core("S11"),
+    RegisterRole::Core("S12"),
+    RegisterRole::Core("S13"),
+    RegisterRole::Core("S14"),
+    RegisterRole::Core("S15"),
+    RegisterRole::FloatingPointStatus,
+];
+
+/// The size of an exception frame containing only the [`EXCEPTION_STACK_REGISTERS`].
+pub(crate) const STANDARD_FRAME_SIZE: u64 = 0x20;
+
+/// The size of an exception frame which also contains the [`EXCEPTION_STACK_FP_REGISTERS`].
+pub(crate) const EXTENDED_FRAME_SIZE: u64 = 0x68;
+
+memory_mapped_bitfield_register! {
+    /// FPCCR - Floating-point Context Control Register
+    pub struct Fpccr(u32);
+    0xE000EF34, "FPCCR",
+    impl From;
+    /// Lazy state preservation is enabled.
+    lazy_state_preservation_enabled, _: 30;
+    /// The floating point context of the Secure state is stacked on exception entry (ARMv8-M only).
+    pub treat_as_secure, _: 26;
+    /// Lazy state preservation is active: Space for the floating point registers was allocated on the stack
+    /// when the exception was taken, but the registers have not been saved there yet.
+    lazy_state_preservation_active, _: 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// FPCAR - Floating-point Context Address Register
+    ///
+    /// The address of the space allocated for the floating point registers of the active lazy state preservation.
+    pub struct Fpcar(u32);
+    0xE000EF38, "FPCAR",
+    impl From;
+}
+
 bitfield! {
     /// The EXC_RETURN value (The value of the link address register) is used to
     /// determine the stack to return to when returning from an exception.
@@ -41,7 +95,7 @@ bitfield! {
     // - 0xD: Return to Thread mode using Process SP.
     /// If true, return to Thread mode, else Handler mode.
     pub return_to_thread, _: 3;
-    /// If true, return to PSP, else MSP. We can ignore this, because the processor banks the correct value in the SP register.
+    /// If true, return to PSP, else MSP. The exception frame is stored on the stack we return to.
     pub use_process_stack, _: 2;
     /// When `is_exception_flag` is 0xF, then the last two bits are always 0b01
     pub always_0b01, _: 1,0;
@@ -114,13 +168,10 @@ pub(crate) fn exception_details(
 
     let exception_frame_pc = registers.get_register_mut_by_role(&RegisterRole::ProgramCounter)?;
 
-    // TODO: If this was not a precise exception, the program counter will be the address of the next instruction.
-    //       We have to adjust the program counter to point to the instruction that caused the exception.
-
     // unwrap: We know that we have a PC value, unwinding an exception will retrieve it from the stack
     let pc_value = exception_frame_pc.value.unwrap();
 
-    let mut handler_frame = StackFrame {
+    let handler_frame = StackFrame {
         id: get_object_reference(),
         function_name: description.clone(),
         source_location: None,
@@ -132,25 +183,6 @@ pub(crate) fn exception_details(
         canonical_frame_address: None,
     };
 
-    // Now we can update the stack pointer also, but
-    // first we have to determine the size of the exception data on the stack.
-    // See <https://developer.arm.com/documentation/ddi0403/d/System-Level-Architecture/System-Level-Programmers--Model/ARMv7-M-exception-model/Exception-entry-behavior?lang=en>
-    let frame_size = if frame_return_address.use_standard_stackframe() {
-        // This is a standard exception frame.
-        0x20usize
-    } else {
-        // This is an extended frame that includes FPU registers.
-        0x68
-    };
-    // // Now we can update the registers with the new stack pointer.
-    let sp = handler_frame
-        .registers
-        .get_register_mut_by_role(&RegisterRole::StackPointer)?;
-    if let Some(sp_value) = sp.value.as_mut() {
-        sp_value.increment_address(frame_size)?;
-    }
-    //}
-
     Ok(Some(ExceptionInfo {
         raw_exception,
         description,
@@ -203,28 +235,351 @@ pub(crate) fn raw_exception(stackframe_registers: &crate::DebugRegisters) -> Res
 
 /// The calling frame registers are a predefined set of registers that are stored on the stack when an exception occurs.
 /// The registers are stored in that list in the order they are defined in the `EXCEPTION_STACK_REGISTERS` array.
-/// This function will read the values of the registers from the stack and update the passed `stackframe_registers` with the new values.
-// TODO: probe-rs does not currently do anything with the floating point registers. When support is added, please note that the list of registers to read is different for cores that have the floating point extension.
+/// This function will read the values of the registers from the stack and return the `stackframe_registers` updated
+/// with the new values, including the stack pointer of the calling frame.
 pub(crate) fn calling_frame_registers(
     memory: &mut dyn MemoryInterface,
     stackframe_registers: &crate::DebugRegisters,
 ) -> Result<crate::DebugRegisters, probe_rs::Error> {
-    let exception_context_address: u32 =
-        stackframe_registers.get_register_value_by_role(&RegisterRole::StackPointer)? as u32;
+    let exc_return = ExcReturn(get_stack_frame_return_address(stackframe_registers)?);
+    let use_process_stack = exc_return.use_process_stack();
+    let exception_context_address =
+        exception_frame_address(stackframe_registers, use_process_stack)?;
+
+    let mut calling_frame_registers = stackframe_registers.clone();
+    restore_standard_frame(
+        memory,
+        &mut calling_frame_registers,
+        exception_context_address,
+    )?;
+
+    // See <https://developer.arm.com/documentation/ddi0403/d/System-Level-Architecture/System-Level-Programmers--Model/ARMv7-M-exception-model/Exception-entry-behavior?lang=en>
+    let frame_size = if exc_return.use_standard_stackframe() {
+        STANDARD_FRAME_SIZE
+    } else {
+        restore_fp_registers(
+            memory,
+            &mut calling_frame_registers,
+            exception_context_address + STANDARD_FRAME_SIZE,
+            &[],
+        )?;
+        EXTENDED_FRAME_SIZE
+    };
+
+    let calling_frame_sp =
+        exception_context_address + frame_size + realignment(&calling_frame_registers)?;
+    set_stack_pointer(
+        &mut calling_frame_registers,
+        calling_frame_sp,
+        use_process_stack,
+    );
+
+    Ok(calling_frame_registers)
+}
+
+/// The address of the exception frame.
+///
+/// If the exception was taken from Thread mode using the process stack, the frame is stored on the process stack.
+/// Otherwise, the frame is stored on the main stack, which is the active stack of the exception handler.
+pub(crate) fn exception_frame_address(
+    stackframe_registers: &DebugRegisters,
+    use_process_stack: bool,
+) -> Result<u64, Error> {
+    if use_process_stack {
+        if let Ok(psp) =
+            stackframe_registers.get_register_value_by_role(&RegisterRole::ProcessStackPointer)
+        {
+            return Ok(psp);
+        }
+    }
 
+    // The handler code does not change the main stack pointer register while we unwind it, but it does update the
+    // stack pointer.
+    stackframe_registers.get_register_value_by_role(&RegisterRole::StackPointer)
+}
+
+/// Reads the [`EXCEPTION_STACK_REGISTERS`] of the exception frame at `frame_address`.
+pub(crate) fn restore_standard_frame(
+    memory: &mut dyn MemoryInterface,
+    calling_frame_registers: &mut DebugRegisters,
+    frame_address: u64,
+) -> Result<(), Error> {
     // Get the values of the registers pushed onto the stack.
     let mut calling_stack_registers = vec![0u32; EXCEPTION_STACK_REGISTERS.len()];
-    memory.read_32(
-        (exception_context_address).into(),
-        &mut calling_stack_registers,
-    )?;
+    memory.read_32(frame_address, &mut calling_stack_registers)?;
 
-    let mut calling_frame_registers = stackframe_registers.clone();
-    for (i, register_role) in EXCEPTION_STACK_REGISTERS.iter().enumerate() {
+    for (register_role, value) in EXCEPTION_STACK_REGISTERS
+        .iter()
+        .zip(calling_stack_registers)
+    {
         calling_frame_registers
             .get_register_mut_by_role(register_role)?
-            .value = Some(RegisterValue::U32(calling_stack_registers[i]));
+            .value = Some(RegisterValue::U32(value));
     }
 
-    Ok(calling_frame_registers)
+    Ok(())
+}
+
+/// Reads the floating point registers of an extended exception frame, starting at `fp_context_address`.
+///
+/// With lazy state preservation, the processor only allocates space for the floating point registers on exception
+/// entry, and saves them there when the exception handler first uses the FPU. Until then, the registers still hold
+/// the values of the calling frame, and the content of the stack is undefined.
+///
+/// The `secure_fp_registers` are stored after the [`EXCEPTION_STACK_FP_REGISTERS`] and the reserved word, when the
+/// floating point context of the ARMv8-M Secure state is stacked. Floating point registers which are not part of
+/// `calling_frame_registers` are skipped.
+pub(crate) fn restore_fp_registers(
+    memory: &mut dyn MemoryInterface,
+    calling_frame_registers: &mut DebugRegisters,
+    fp_context_address: u64,
+    secure_fp_registers: &[RegisterRole],
+) -> Result<(), Error> {
+    if read_fpccr(memory).is_some_and(|fpccr| fpccr.lazy_state_preservation_active()) {
+        match memory.read_word_32(Fpcar::get_mmio_address()) {
+            Ok(fpcar) if u64::from(fpcar & !0b111) == fp_context_address => {
+                tracing::debug!(
+                    "UNWIND: Lazy floating point state preservation is active, using the current FPU registers."
+                );
+                return Ok(());
+            }
+            Ok(_) => {}
+            Err(error) => {
+                tracing::debug!(
+                    "UNWIND: Failed to read FPCAR ({error}), assuming the floating point registers are stacked."
+                );
+            }
+        }
+    }
+
+    // The reserved word after FPSCR is read too, but not used.
+    let mut values = vec![0u32; EXCEPTION_STACK_FP_REGISTERS.len() + 1 + secure_fp_registers.len()];
+    memory.read_32(fp_context_address, &mut values)?;
+
+    let fp_registers = EXCEPTION_STACK_FP_REGISTERS
+        .iter()
+        .map(Some)
+        .chain(std::iter::once(None))
+        .chain(secure_fp_registers.iter().map(Some));
+    for (register_role, value) in fp_registers.zip(values) {
+        let Some(register_role) = register_role else {
+            continue;
+        };
+        if let Ok(register) = calling_frame_registers.get_register_mut_by_role(register_role) {
+            register.value = Some(RegisterValue::U32(value));
+        }
+    }
+
+    Ok(())
+}
+
+/// Read the FPCCR register, which describes how the floating point context is stacked.
+///
+/// Returns `None` if the register can not be read, e.g. because a core dump does not contain the
+/// System Control Block. The floating point context is then treated as not lazily stacked.
+pub(crate) fn read_fpccr(memory: &mut dyn MemoryInterface) -> Option<Fpccr> {
+    match memory.read_word_32(Fpccr::get_mmio_address()) {
+        Ok(fpccr) => Some(Fpccr(fpccr)),
+        Err(error) => {
+            tracing::debug!(
+                "UNWIND: Failed to read FPCCR ({error}), assuming the floating point registers are stacked."
+            );
+            None
+        }
+    }
+}
+
+/// The padding word which was inserted above the exception frame to align the stack to 8 bytes, if any.
+///
+/// `calling_frame_registers` must contain the xPSR value which was stored in the exception frame.
+pub(crate) fn realignment(calling_frame_registers: &DebugRegisters) -> Result<u64, Error> {
+    let xpsr = Xpsr(
+        calling_frame_registers.get_register_value_by_role(&RegisterRole::ProcessorStatus)? as u32,
+    );
+
+    Ok(if xpsr.stack_was_realigned() { 4 } else { 0 })
+}
+
+/// Sets the stack pointer of the calling frame, and the banked stack pointer it was using.
+pub(crate) fn set_stack_pointer(
+    calling_frame_registers: &mut DebugRegisters,
+    stack_pointer: u64,
+    use_process_stack: bool,
+) {
+    let banked_stack_pointer = if use_process_stack {
+        RegisterRole::ProcessStackPointer
+    } else {
+        RegisterRole::MainStackPointer
+    };
+
+    for register_role in [RegisterRole::StackPointer, banked_stack_pointer] {
+        if let Ok(register) = calling_frame_registers.get_register_mut_by_role(&register_role) {
+            register.value = Some(RegisterValue::U32(stack_pointer as u32));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use probe_rs::{
+        CORE_DUMP_FORMAT_VERSION, CoreDump, CoreType, InstructionSet, RegisterRole, RegisterValue,
+        architecture::arm::core::registers::cortex_m::CORTEX_M_WITH_FP_CORE_REGISTERS,
+        test::MockMemory,
+    };
+    use std::collections::HashMap;
+
+    use super::calling_frame_registers;
+    use crate::{DebugRegister, DebugRegisters};
+
+    const EXCEPTION_FRAME: [u32; 8] = [0, 1, 2, 3, 12, 0x0800_0101, 0x0800_0200, 0x0100_0000];
+
+    fn registers(values: &[(RegisterRole, u32)]) -> DebugRegisters {
+        DebugRegisters(
+            CORTEX_M_WITH_FP_CORE_REGISTERS
+                .all_registers()
+                .map(|core_register| DebugRegister {
+                    core_register,
+                    dwarf_id: None,
+                    value: values
+                        .iter()
+                        .find(|(role, _)| core_register.register_has_role(*role))
+                        .map(|(_, value)| RegisterValue::U32(*value)),
+                })
+                .collect(),
+        )
+    }
+
+    fn value(registers: &DebugRegisters, role: RegisterRole) -> u64 {
+        registers.get_register_value_by_role(&role).unwrap()
+    }
+
+    #[test]
+    fn standard_frame_on_process_stack() {
+        let mut memory = MockMemory::new();
+        let mut frame = EXCEPTION_FRAME;
+        // The stack was realigned on exception entry.
+        frame[7] |= 1 << 9;
+        memory.add_word_range(0x2000_1000, &frame);
+
+        let registers = registers(&[
+            (RegisterRole::ReturnAddress, 0xFFFF_FFFD),
+            (RegisterRole::StackPointer, 0x2000_0100),
+            (RegisterRole::ProcessStackPointer, 0x2000_1000),
+        ]);
+
+        let calling_frame = calling_frame_registers(&mut memory, &registers).unwrap();
+
+        assert_eq!(
+            value(&calling_frame, RegisterRole::ProgramCounter),
+            0x0800_0200
+        );
+        assert_eq!(
+            value(&calling_frame, RegisterRole::ReturnAddress),
+            0x0800_0101
+        );
+        assert_eq!(
+            value(&calling_frame, RegisterRole::StackPointer),
+            0x2000_1024
+        );
+        assert_eq!(
+            value(&calling_frame, RegisterRole::ProcessStackPointer),
+            0x2000_1024
+        );
+    }
+
+    fn extended_frame(fpccr: u32) -> DebugRegisters {
+        let mut memory = MockMemory::new();
+        memory.add_word_range(0x2000_0100, &EXCEPTION_FRAME);
+        // S0-S15, FPSCR and the reserved word.
+        let mut fp_context = [0u32; 18];
+        fp_context[16] = 0xF000_0000;
+        memory.add_word_range(0x2000_0120, &fp_context);
+        memory.add_word_range(0xE000_EF34, &[fpccr, 0x2000_0120]);
+
+        let registers = registers(&[
+            (RegisterRole::ReturnAddress, 0xFFFF_FFE9),
+            (RegisterRole::StackPointer, 0x2000_0100),
+            (RegisterRole::MainStackPointer, 0x2000_0100),
+            (RegisterRole::FloatingPointStatus, 0x0000_0010),
+        ]);
+
+        calling_frame_registers(&mut memory, &registers).unwrap()
+    }
+
+    #[test]
+    fn extended_frame_with_stacked_fp_state() {
+        let calling_frame = extended_frame(0xC000_0000);
+
+        assert_eq!(
+            value(&calling_frame, RegisterRole::FloatingPointStatus),
+            0xF000_0000
+        );
+        assert_eq!(
+            value(&calling_frame, RegisterRole::StackPointer),
+            0x2000_0168
+        );
+        assert_eq!(
+            value(&calling_frame, RegisterRole::MainStackPointer),
+            0x2000_0168
+        );
+    }
+
+    #[test]
+    fn extended_frame_in_core_dump_without_scb() {
+        // S0-S15, FPSCR and the reserved word follow the standard frame.
+        let mut fp_context = [0u32; 18];
+        fp_context[16] = 0xF000_0000;
+        let stack = EXCEPTION_FRAME
+            .iter()
+            .chain(fp_context.iter())
+            .flat_map(|word| word.to_le_bytes())
+            .collect::<Vec<_>>();
+
+        // The core dump only contains the stack, so FPCCR and FPCAR can not be read.
+        let mut core_dump = CoreDump {
+            format_version: CORE_DUMP_FORMAT_VERSION,
+            registers: HashMap::new(),
+            data: vec![(0x2000_0100..0x2000_0168, stack)],
+            instruction_set: InstructionSet::Thumb2,
+            supports_native_64bit_access: false,
+            core_type: CoreType::Armv7em,
+            fpu_support: true,
+            floating_point_register_count: Some(32),
+            regions: Vec::new(),
+            peripherals: Vec::new(),
+        };
+
+        let registers = registers(&[
+            (RegisterRole::ReturnAddress, 0xFFFF_FFE9),
+            (RegisterRole::StackPointer, 0x2000_0100),
+            (RegisterRole::MainStackPointer, 0x2000_0100),
+            (RegisterRole::FloatingPointStatus, 0x0000_0010),
+        ]);
+
+        let calling_frame = calling_frame_registers(&mut core_dump, &registers).unwrap();
+
+        assert_eq!(
+            value(&calling_frame, RegisterRole::FloatingPointStatus),
+            0xF000_0000
+        );
+        assert_eq!(
+            value(&calling_frame, RegisterRole::StackPointer),
+            0x2000_0168
+        );
+    }
+
+    #[test]
+    fn extended_frame_with_lazy_fp_state() {
+        let calling_frame = extended_frame(0xC000_0001);
+
+        assert_eq!(
+            value(&calling_frame, RegisterRole::FloatingPointStatus),
+            0x0000_0010
+        );
+        assert_eq!(
+            value(&calling_frame, RegisterRole::StackPointer),
+            0x2000_0168
+        );
+    }
 }
@@ -7,9 +7,54 @@ use probe_rs::{
 
 use super::{
     ExceptionInfo, ExceptionInterface,
-    armv6m_armv7m_shared::{EXCEPTION_STACK_REGISTERS, Xpsr},
+    armv6m_armv7m_shared::{
+        EXTENDED_FRAME_SIZE, STANDARD_FRAME_SIZE, Xpsr, exception_frame_address, read_fpccr,
+        realignment, restore_fp_registers, restore_standard_frame, set_stack_pointer,
+    },
 };
 
+/// Callee saved registers which are stored on the stack below the [`EXCEPTION_STACK_REGISTERS`], as part of the
+/// additional state context. This happens when a Non-secure exception interrupts Secure code, so that the Secure
+/// register values are not visible to the Non-secure handler.
+///
+/// The registers are preceded by the integrity signature and a reserved word.
+///
+/// [`EXCEPTION_STACK_REGISTERS`]: super::armv6m_armv7m_shared::EXCEPTION_STACK_REGISTERS
+static ADDITIONAL_STATE_CONTEXT_REGISTERS: &[RegisterRole] = &[
+    RegisterRole::Core("R4"),
+    RegisterRole::Core("R5"),
+    RegisterRole::Core("R6"),
+    RegisterRole::Core("R7"),
+    RegisterRole::Core("R8"),
+    RegisterRole::Core("R9"),
+    RegisterRole::Core("R10"),
+    RegisterRole::Core("R11"),
+];
+
+/// The size of the additional state context, including the integrity signature and the reserved word.
+const ADDITIONAL_STATE_CONTEXT_SIZE: u64 = 0x28;
+
+/// Floating point registers which are stored after the standard floating point registers of an extended frame, when
+/// the floating point context of the Secure state is stacked (FPCCR.TS is set).
+static SECURE_EXCEPTION_STACK_FP_REGISTERS: &[RegisterRole] = &[
+    RegisterRole::Core("S16"),
+    RegisterRole::Core("S17"),
+    RegisterRole::Core("S18"),
+    RegisterRole::Core("S19"),
+    RegisterRole::Core("S20"),
+    RegisterRole::Core("S21"),
+    RegisterRole::Core("S22"),
+    RegisterRole::Core("S23"),
+    RegisterRole::Core("S24"),
+    RegisterRole::Core("S25"),
+    RegisterRole::Core("S26"),
+    RegisterRole::Core("S27"),
+    RegisterRole::Core("S28"),
+    RegisterRole::Core("S29"),
+    RegisterRole::Core("S30"),
+    RegisterRole::Core("S31"),
+];
+
 bitfield! {
     /// The EXC_RETURN value (The value of the link address register) is used to
     /// determine the stack to return to when returning from an exception.
@@ -394,48 +439,76 @@ impl ExceptionInterface for ArmV8MExceptionHandler {
         stackframe_registers: &DebugRegisters,
         _raw_exception: u32,
     ) -> Result<DebugRegisters, DebugError> {
-        let mut calling_stack_registers = vec![0u32; EXCEPTION_STACK_REGISTERS.len()];
         let stack_frame_return_address: u32 = get_stack_frame_return_address(stackframe_registers)?;
         let exc_return = ExcReturn(stack_frame_return_address);
+        let use_process_stack = exc_return.stack_pointer_selection();
 
-        let sp_value = if exc_return.is_exception_flag() == 0xFF {
-            let stack_info = (
-                exc_return.use_secure_stack(),
-                exc_return.stack_pointer_selection(),
-            );
-
-            let sp_reg_id = match stack_info {
-                (false, false) => 0b00011000, // non-secure, main stack pointer
-                (false, true) => 0b00011001,  // non-secure, process stack pointer
-                (true, false) => 0b00011010,  // secure, main stack pointer
-                (true, true) => 0b00011011,   // secure, process stack pointer
-            };
-            stackframe_registers
-                .get_register(sp_reg_id.into())
-                .ok_or_else(|| {
-                    Error::Register(
-                        "No Stack Pointer register. Please report this as a bug.".to_string(),
-                    )
-                })?
-                .value
-                .ok_or_else(|| {
-                    Error::Register(
-                        "No value for Stack Pointer register. Please report this as a bug."
-                            .to_string(),
-                    )
-                })?
-                .try_into()?
-        } else {
-            stackframe_registers.get_register_value_by_role(&RegisterRole::StackPointer)?
+        let sp_reg_id = match (exc_return.use_secure_stack(), use_process_stack) {
+            (false, false) => 0b00011000, // non-secure, main stack pointer
+            (false, true) => 0b00011001,  // non-secure, process stack pointer
+            (true, false) => 0b00011010,  // secure, main stack pointer
+            (true, true) => 0b00011011,   // secure, process stack pointer
+        };
+        // The banked stack pointers of both security states are not available for all cores, in which case the
+        // stack pointers of the current security state are used.
+        let mut frame_address: u64 = match stackframe_registers
+            .get_register(sp_reg_id.into())
+            .and_then(|register| register.value)
+        {
+            Some(sp_value) => sp_value.try_into()?,
+            None => exception_frame_address(stackframe_registers, use_process_stack)?,
         };
 
-        memory_interface.read_32(sp_value, &mut calling_stack_registers)?;
         let mut calling_frame_registers = stackframe_registers.clone();
-        for (i, register_role) in EXCEPTION_STACK_REGISTERS.iter().enumerate() {
-            calling_frame_registers
-                .get_register_mut_by_role(register_role)?
-                .value = Some(RegisterValue::U32(calling_stack_registers[i]));
+
+        if !exc_return.use_default_register_stacking() {
+            let mut callee_registers = vec![0u32; ADDITIONAL_STATE_CONTEXT_REGISTERS.len()];
+            // Skip the integrity signature and the reserved word.
+            memory_interface.read_32(frame_address + 8, &mut callee_registers)?;
+            for (register_role, value) in ADDITIONAL_STATE_CONTEXT_REGISTERS
+                .iter()
+                .zip(callee_registers)
+            {
+                calling_frame_registers
+                    .get_register_mut_by_role(register_role)?
+                    .value = Some(RegisterValue::U32(value));
+            }
+            frame_address += ADDITIONAL_STATE_CONTEXT_SIZE;
         }
+
+        restore_standard_frame(
+            memory_interface,
+            &mut calling_frame_registers,
+            frame_address,
+        )?;
+
+        let frame_size = if exc_return.use_standard_stackframe() {
+            STANDARD_FRAME_SIZE
+        } else {
+            let fp_context_address = frame_address + STANDARD_FRAME_SIZE;
+            let treat_as_secure =
+                read_fpccr(memory_interface).is_some_and(|fpccr| fpccr.treat_as_secure());
+            let secure_fp_registers = if exc_return.use_secure_stack() && treat_as_secure {
+                SECURE_EXCEPTION_STACK_FP_REGISTERS
+            } else {
+                &[]
+            };
+            restore_fp_registers(
+                memory_interface,
+                &mut calling_frame_registers,
+                fp_context_address,
+                secure_fp_registers,
+            )?;
+            EXTENDED_FRAME_SIZE + 4 * secure_fp_registers.len() as u64
+        };
+
+        let calling_frame_sp = frame_address + frame_size + realignment(&calling_frame_registers)?;
+        set_stack_pointer(
+            &mut calling_frame_registers,
+            calling_frame_sp,
+            use_process_stack,
+        );
+
         Ok(calling_frame_registers)
     }
 
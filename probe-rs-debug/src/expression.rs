@@ -0,0 +1,1161 @@
+use crate::{
+    DebugError, DebugInfo, ObjectRef, Variable, VariableCache, VariableName, VariableType,
+    stack_frame::StackFrameInfo,
+};
+use probe_rs::MemoryInterface;
+
+/// The maximum number of elements of a slice like `buf[4..16]`.
+const MAX_SLICE_ELEMENTS: usize = 1024;
+
+/// A primitive type which can be used in a cast, e.g. `(uint32_t*)` or `as u8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrimitiveType {
+    /// An unsigned 8 bit integer.
+    U8,
+    /// An unsigned 16 bit integer.
+    U16,
+    /// An unsigned 32 bit integer.
+    U32,
+    /// An unsigned 64 bit integer.
+    U64,
+    /// A signed 8 bit integer.
+    I8,
+    /// A signed 16 bit integer.
+    I16,
+    /// A signed 32 bit integer.
+    I32,
+    /// A signed 64 bit integer.
+    I64,
+    /// A 32 bit floating point number.
+    F32,
+    /// A 64 bit floating point number.
+    F64,
+    /// A boolean, stored as a single byte.
+    Bool,
+}
+
+impl PrimitiveType {
+    /// Looks up a Rust or C type name, e.g. `u32` or `uint32_t`.
+    fn from_name(name: &str) -> Option<Self> {
+        let primitive = match name {
+            "u8" | "uint8_t" => Self::U8,
+            "u16" | "uint16_t" => Self::U16,
+            "u32" | "uint32_t" => Self::U32,
+            "u64" | "uint64_t" => Self::U64,
+            "i8" | "int8_t" | "char" => Self::I8,
+            "i16" | "int16_t" | "short" => Self::I16,
+            "i32" | "int32_t" | "int" | "long" => Self::I32,
+            "i64" | "int64_t" => Self::I64,
+            "f32" | "float" => Self::F32,
+            "f64" | "double" => Self::F64,
+            "bool" | "_Bool" => Self::Bool,
+            _ => return None,
+        };
+        Some(primitive)
+    }
+
+    /// The Rust name of the type.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::U8 => "u8",
+            Self::U16 => "u16",
+            Self::U32 => "u32",
+            Self::U64 => "u64",
+            Self::I8 => "i8",
+            Self::I16 => "i16",
+            Self::I32 => "i32",
+            Self::I64 => "i64",
+            Self::F32 => "f32",
+            Self::F64 => "f64",
+            Self::Bool => "bool",
+        }
+    }
+
+    /// The size of the type in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            Self::U8 | Self::I8 | Self::Bool => 1,
+            Self::U16 | Self::I16 => 2,
+            Self::U32 | Self::I32 | Self::F32 => 4,
+            Self::U64 | Self::I64 | Self::F64 => 8,
+        }
+    }
+
+    /// Converts an integer to this type, with the truncation and sign extension of an `as` cast.
+    fn convert(self, value: i128) -> ExpressionValue {
+        let value = match self {
+            Self::U8 => value as u8 as i128,
+            Self::U16 => value as u16 as i128,
+            Self::U32 => value as u32 as i128,
+            Self::U64 => value as u64 as i128,
+            Self::I8 => value as i8 as i128,
+            Self::I16 => value as i16 as i128,
+            Self::I32 => value as i32 as i128,
+            Self::I64 => value as i64 as i128,
+            Self::Bool => (value != 0) as i128,
+            Self::F32 | Self::F64 => {
+                return ExpressionValue::Float {
+                    value: value as f64,
+                    type_: self,
+                };
+            }
+        };
+        ExpressionValue::Integer {
+            value,
+            type_: Some(self),
+        }
+    }
+
+    /// Reads a little endian value of this type from `address`.
+    fn read(
+        self,
+        memory: &mut dyn MemoryInterface,
+        address: u64,
+    ) -> Result<ExpressionValue, DebugError> {
+        let mut bytes = [0u8; 8];
+        memory.read(address, &mut bytes[..self.size()])?;
+
+        Ok(self.decode(&bytes))
+    }
+
+    /// Reads `count` consecutive little endian values of this type from `address`, in a single
+    /// memory access.
+    fn read_slice(
+        self,
+        memory: &mut dyn MemoryInterface,
+        address: u64,
+        count: usize,
+    ) -> Result<Vec<ExpressionValue>, DebugError> {
+        let mut bytes = vec![0u8; count * self.size()];
+        memory.read(address, &mut bytes)?;
+
+        Ok(bytes
+            .chunks_exact(self.size())
+            .map(|element| self.decode(element))
+            .collect())
+    }
+
+    /// Decodes a little endian value of this type from the start of `bytes`.
+    fn decode(self, bytes: &[u8]) -> ExpressionValue {
+        let mut buffer = [0u8; 8];
+        buffer[..self.size()].copy_from_slice(&bytes[..self.size()]);
+
+        let value = u64::from_le_bytes(buffer);
+        match self {
+            Self::F32 => ExpressionValue::Float {
+                value: f32::from_bits(value as u32) as f64,
+                type_: self,
+            },
+            Self::F64 => ExpressionValue::Float {
+                value: f64::from_bits(value),
+                type_: self,
+            },
+            _ => self.convert(value as i128),
+        }
+    }
+}
+
+/// The result of [`DebugInfo::evaluate_expression`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpressionValue {
+    /// A variable of the program, stored in the [`VariableCache`] the expression was evaluated in.
+    Variable(Box<Variable>),
+    /// A range of elements, selected with a slice like `buf[4..16]`.
+    Slice(Vec<ExpressionValue>),
+    /// An integer, e.g. a literal or the result of a cast.
+    Integer {
+        /// The value of the integer.
+        value: i128,
+        /// The type the value was cast to or read as, if any.
+        type_: Option<PrimitiveType>,
+    },
+    /// A floating point number, e.g. read through a `(float*)` pointer.
+    Float {
+        /// The value of the number.
+        value: f64,
+        /// The type the value was read as.
+        type_: PrimitiveType,
+    },
+    /// A pointer created by a cast, e.g. `(uint32_t*)0x2000_0000`.
+    Pointer {
+        /// The address the pointer points to.
+        address: u64,
+        /// The type of the value the pointer points to.
+        target: PrimitiveType,
+    },
+}
+
+impl From<Variable> for ExpressionValue {
+    fn from(variable: Variable) -> Self {
+        Self::Variable(Box::new(variable))
+    }
+}
+
+impl ExpressionValue {
+    /// Formats the value for display, using `variable_cache` to format the value of variables.
+    pub fn to_string(&self, variable_cache: &VariableCache) -> String {
+        match self {
+            Self::Variable(variable) => variable.to_string(variable_cache),
+            Self::Slice(values) => {
+                let values = values
+                    .iter()
+                    .map(|value| value.to_string(variable_cache))
+                    .collect::<Vec<_>>();
+                format!("[{}]", values.join(", "))
+            }
+            Self::Integer {
+                value,
+                type_: Some(PrimitiveType::Bool),
+            } => (*value != 0).to_string(),
+            Self::Integer { value, .. } => value.to_string(),
+            Self::Float { value, .. } => value.to_string(),
+            Self::Pointer { address, .. } => format!("{address:#010x}"),
+        }
+    }
+
+    /// Returns the readable name of the type of the value.
+    pub fn type_name(&self) -> String {
+        match self {
+            Self::Variable(variable) => variable.type_name(),
+            Self::Slice(values) => match values.first() {
+                Some(first) => format!("[{}; {}]", first.type_name(), values.len()),
+                None => "[]".to_string(),
+            },
+            Self::Integer {
+                type_: Some(type_), ..
+            }
+            | Self::Float { type_, .. } => type_.name().to_string(),
+            Self::Integer { type_: None, .. } => "integer".to_string(),
+            Self::Pointer { target, .. } => format!("*const {}", target.name()),
+        }
+    }
+}
+
+impl DebugInfo {
+    /// Evaluates `expression` against the variables in `variable_cache`, and the registers of
+    /// the stack frame described by `frame_info`.
+    ///
+    /// In addition to the names of variables and registers (prefixed with `$`), expressions can
+    /// contain:
+    /// - Field and dereference chains, e.g. `config.pins[2].mode`, `*ptr` or `ctx->state`.
+    /// - Array elements and slices, e.g. `buf[3]` or `buf[4..16]`.
+    /// - C and Rust style casts of primitive types, e.g. `*(uint32_t*)0x2000_0000` or
+    ///   `*(0x2000_0000 as *const u32)`.
+    /// - Integer arithmetic and bitwise operators with the precedence of C and Rust, e.g.
+    ///   `(foo + 1) << 4`.
+    /// - Pointer addition and subtraction, e.g. `(uint16_t*)&buf + 2`.
+    ///
+    /// Deferred variables are resolved on demand and added to `variable_cache`.
+    pub fn evaluate_expression(
+        &self,
+        expression: &str,
+        memory: &mut dyn MemoryInterface,
+        variable_cache: &mut VariableCache,
+        frame_info: StackFrameInfo<'_>,
+    ) -> Result<ExpressionValue, DebugError> {
+        let tokens = tokenize(expression)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+        };
+        let parsed = parser.parse_expression()?;
+        if let Some(token) = parser.tokens.get(parser.position) {
+            return Err(DebugError::Other(format!(
+                "Unexpected {token:?} in {expression:?}"
+            )));
+        }
+
+        Evaluator {
+            debug_info: self,
+            memory,
+            variable_cache,
+            frame_info,
+        }
+        .evaluate(&parsed)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(i128),
+    Identifier(String),
+    Punctuation(&'static str),
+}
+
+const PUNCTUATION: &[&str] = &[
+    "->", "..", "<<", ">>", "(", ")", "[", "]", ".", "*", "/", "%", "&", "|", "^", "~", "+", "-",
+];
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, DebugError> {
+    let mut tokens = Vec::new();
+    let mut remaining = expression.trim_start();
+    while let Some(character) = remaining.chars().next() {
+        if character.is_ascii_digit() {
+            let end = remaining
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(remaining.len());
+            let word = remaining[..end].replace('_', "");
+            tokens.push(Token::Number(parse_int::parse::<i128>(&word).map_err(
+                |error| DebugError::Other(format!("Invalid number {word:?}: {error}")),
+            )?));
+            remaining = &remaining[end..];
+        } else if character.is_ascii_alphabetic() || matches!(character, '_' | '$') {
+            let mut end = 0;
+            let bytes = remaining.as_bytes();
+            while end < bytes.len() {
+                if bytes[end].is_ascii_alphanumeric() || matches!(bytes[end], b'_' | b'$') {
+                    end += 1;
+                } else if remaining[end..].starts_with("::") {
+                    end += 2;
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token::Identifier(remaining[..end].to_string()));
+            remaining = &remaining[end..];
+        } else if let Some(punctuation) = PUNCTUATION
+            .iter()
+            .find(|punctuation| remaining.starts_with(**punctuation))
+        {
+            tokens.push(Token::Punctuation(punctuation));
+            remaining = &remaining[punctuation.len()..];
+        } else {
+            return Err(DebugError::Other(format!(
+                "Unexpected character {character:?} in {expression:?}"
+            )));
+        }
+        remaining = remaining.trim_start();
+    }
+    Ok(tokens)
+}
+
+/// The target type of a cast.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CastType {
+    primitive: PrimitiveType,
+    pointer: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expression {
+    Number(i128),
+    Identifier(String),
+    Field(Box<Expression>, String),
+    Index(Box<Expression>, Box<Expression>),
+    Slice(Box<Expression>, Box<Expression>, Box<Expression>),
+    Deref(Box<Expression>),
+    AddressOf(Box<Expression>),
+    Negate(Box<Expression>),
+    Not(Box<Expression>),
+    Cast(CastType, Box<Expression>),
+    Binary(BinaryOperator, Box<Expression>, Box<Expression>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOperator {
+    Or,
+    Xor,
+    And,
+    ShiftLeft,
+    ShiftRight,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+impl BinaryOperator {
+    fn from_punctuation(punctuation: &str) -> Option<Self> {
+        let operator = match punctuation {
+            "|" => Self::Or,
+            "^" => Self::Xor,
+            "&" => Self::And,
+            "<<" => Self::ShiftLeft,
+            ">>" => Self::ShiftRight,
+            "+" => Self::Add,
+            "-" => Self::Subtract,
+            "*" => Self::Multiply,
+            "/" => Self::Divide,
+            "%" => Self::Remainder,
+            _ => return None,
+        };
+        Some(operator)
+    }
+
+    /// Operator precedence, from lowest to highest, as in C and Rust.
+    fn precedence(self) -> u8 {
+        match self {
+            Self::Or => 1,
+            Self::Xor => 2,
+            Self::And => 3,
+            Self::ShiftLeft | Self::ShiftRight => 4,
+            Self::Add | Self::Subtract => 5,
+            Self::Multiply | Self::Divide | Self::Remainder => 6,
+        }
+    }
+
+    fn apply(self, left: i128, right: i128) -> Result<i128, DebugError> {
+        let shift = || {
+            u32::try_from(right)
+                .map_err(|_| DebugError::Other(format!("Shift by {right} is out of range")))
+        };
+        let value = match self {
+            Self::Or => Some(left | right),
+            Self::Xor => Some(left ^ right),
+            Self::And => Some(left & right),
+            Self::ShiftLeft => left.checked_shl(shift()?),
+            Self::ShiftRight => left.checked_shr(shift()?),
+            Self::Add => left.checked_add(right),
+            Self::Subtract => left.checked_sub(right),
+            Self::Multiply => left.checked_mul(right),
+            Self::Divide | Self::Remainder if right == 0 => {
+                return Err(DebugError::Other("Division by zero".to_string()));
+            }
+            Self::Divide => left.checked_div(right),
+            Self::Remainder => left.checked_rem(right),
+        };
+        value.ok_or_else(|| DebugError::Other("Overflow".to_string()))
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, punctuation: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Punctuation(p)) if *p == punctuation) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punctuation: &str) -> Result<(), DebugError> {
+        if self.eat(punctuation) {
+            Ok(())
+        } else {
+            Err(DebugError::Other(format!(
+                "Expected {punctuation:?}, found {:?}",
+                self.peek()
+            )))
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Identifier(identifier)) if identifier == keyword) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, DebugError> {
+        match self.next() {
+            Some(Token::Identifier(identifier)) => Ok(identifier),
+            other => Err(DebugError::Other(format!(
+                "Expected a name, found {other:?}"
+            ))),
+        }
+    }
+
+    fn parse_expression(&mut self) -> Result<Expression, DebugError> {
+        self.parse_binary(0)
+    }
+
+    /// Parses binary operators which bind tighter than `minimum_precedence`.
+    fn parse_binary(&mut self, minimum_precedence: u8) -> Result<Expression, DebugError> {
+        let mut left = self.parse_as()?;
+        while let Some(Token::Punctuation(punctuation)) = self.peek() {
+            let Some(operator) = BinaryOperator::from_punctuation(punctuation)
+                .filter(|operator| operator.precedence() > minimum_precedence)
+            else {
+                break;
+            };
+            self.position += 1;
+            let right = self.parse_binary(operator.precedence())?;
+            left = Expression::Binary(operator, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    /// Rust style casts, e.g. `0x2000_0000 as *const u32`.
+    fn parse_as(&mut self) -> Result<Expression, DebugError> {
+        let mut value = self.parse_unary()?;
+        while self.eat_keyword("as") {
+            let pointer = if self.eat("*") {
+                if !(self.eat_keyword("const") || self.eat_keyword("mut")) {
+                    return Err(DebugError::Other(
+                        "Expected `const` or `mut` after `*`".to_string(),
+                    ));
+                }
+                true
+            } else {
+                self.eat("&")
+            };
+            let name = self.identifier()?;
+            let primitive = PrimitiveType::from_name(&name)
+                .ok_or_else(|| DebugError::Other(format!("Unsupported type {name:?} in cast")))?;
+            value = Expression::Cast(CastType { primitive, pointer }, Box::new(value));
+        }
+        Ok(value)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression, DebugError> {
+        if self.eat("*") {
+            Ok(Expression::Deref(Box::new(self.parse_unary()?)))
+        } else if self.eat("&") {
+            Ok(Expression::AddressOf(Box::new(self.parse_unary()?)))
+        } else if self.eat("-") {
+            Ok(Expression::Negate(Box::new(self.parse_unary()?)))
+        } else if self.eat("~") {
+            Ok(Expression::Not(Box::new(self.parse_unary()?)))
+        } else if let Some(cast_type) = self.try_parse_c_cast() {
+            Ok(Expression::Cast(cast_type, Box::new(self.parse_unary()?)))
+        } else {
+            self.parse_postfix()
+        }
+    }
+
+    /// C style casts, e.g. `(uint32_t*)` or `(const volatile int *)`. Restores the position if
+    /// the parentheses don't contain a type.
+    fn try_parse_c_cast(&mut self) -> Option<CastType> {
+        let start = self.position;
+        let cast_type = self.parse_c_cast();
+        if cast_type.is_none() {
+            self.position = start;
+        }
+        cast_type
+    }
+
+    fn parse_c_cast(&mut self) -> Option<CastType> {
+        if !self.eat("(") {
+            return None;
+        }
+        while self.eat_keyword("const") || self.eat_keyword("volatile") {}
+        let Some(Token::Identifier(name)) = self.next() else {
+            return None;
+        };
+        let primitive = PrimitiveType::from_name(&name)?;
+        while self.eat_keyword("const") || self.eat_keyword("volatile") {}
+        let pointer = self.eat("*");
+        self.eat(")").then_some(CastType { primitive, pointer })
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expression, DebugError> {
+        let mut value = self.parse_primary()?;
+        loop {
+            if self.eat(".") {
+                value = Expression::Field(Box::new(value), self.field_name()?);
+            } else if self.eat("->") {
+                value = Expression::Field(
+                    Box::new(Expression::Deref(Box::new(value))),
+                    self.field_name()?,
+                );
+            } else if self.eat("[") {
+                let start = self.parse_expression()?;
+                if self.eat("..") {
+                    let end = self.parse_expression()?;
+                    value = Expression::Slice(Box::new(value), Box::new(start), Box::new(end));
+                } else {
+                    value = Expression::Index(Box::new(value), Box::new(start));
+                }
+                self.expect("]")?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Field names can also be numbers, e.g. the fields of a tuple.
+    fn field_name(&mut self) -> Result<String, DebugError> {
+        match self.next() {
+            Some(Token::Identifier(identifier)) => Ok(identifier),
+            Some(Token::Number(number)) => Ok(number.to_string()),
+            other => Err(DebugError::Other(format!(
+                "Expected a field name, found {other:?}"
+            ))),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, DebugError> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Expression::Number(number)),
+            Some(Token::Identifier(identifier)) => Ok(Expression::Identifier(identifier)),
+            Some(Token::Punctuation("(")) => {
+                let value = self.parse_expression()?;
+                self.expect(")")?;
+                Ok(value)
+            }
+            Some(other) => Err(DebugError::Other(format!("Unexpected {other:?}"))),
+            None => Err(DebugError::Other(
+                "Unexpected end of expression".to_string(),
+            )),
+        }
+    }
+}
+
+struct Evaluator<'a> {
+    debug_info: &'a DebugInfo,
+    memory: &'a mut dyn MemoryInterface,
+    variable_cache: &'a mut VariableCache,
+    frame_info: StackFrameInfo<'a>,
+}
+
+impl Evaluator<'_> {
+    fn evaluate(&mut self, expression: &Expression) -> Result<ExpressionValue, DebugError> {
+        match expression {
+            Expression::Number(value) => Ok(ExpressionValue::Integer {
+                value: *value,
+                type_: None,
+            }),
+            Expression::Identifier(name) => self.identifier(name),
+            Expression::Field(base, field) => {
+                let mut parent = self.variable(base)?;
+                // Fields of references and pointers are accessed through the pointer, like in Rust.
+                if matches!(parent.type_name, VariableType::Pointer(_)) {
+                    parent = self.pointee(parent)?;
+                }
+                self.child(&parent, &VariableName::Named(field.clone()))
+                    .ok_or_else(|| {
+                        DebugError::Other(format!("{} has no field {field:?}", parent.name))
+                    })
+                    .map(ExpressionValue::from)
+            }
+            Expression::Index(base, index) => {
+                let index = self.evaluate(index)?;
+                let index = self.integer(&index)?;
+                let base = self.evaluate(base)?;
+                self.element(&base, index)
+            }
+            Expression::Slice(base, start, end) => {
+                let start = self.evaluate(start)?;
+                let start = self.integer(&start)?;
+                let end = self.evaluate(end)?;
+                let end = self.integer(&end)?;
+                let base = self.evaluate(base)?;
+                self.slice(&base, start, end).map(ExpressionValue::Slice)
+            }
+            Expression::Deref(pointer) => match self.evaluate(pointer)? {
+                ExpressionValue::Pointer { address, target } => target.read(self.memory, address),
+                ExpressionValue::Variable(variable)
+                    if matches!(variable.type_name, VariableType::Pointer(_)) =>
+                {
+                    self.pointee(*variable).map(ExpressionValue::from)
+                }
+                other => Err(DebugError::Other(format!(
+                    "Cannot dereference a value of type {}, cast it to a pointer first",
+                    other.type_name()
+                ))),
+            },
+            Expression::AddressOf(value) => {
+                let variable = self.variable(value)?;
+                Ok(ExpressionValue::Integer {
+                    value: variable.memory_location.memory_address()? as i128,
+                    type_: None,
+                })
+            }
+            Expression::Negate(value) => {
+                let value = self.evaluate(value)?;
+                Ok(ExpressionValue::Integer {
+                    value: -self.integer(&value)?,
+                    type_: None,
+                })
+            }
+            Expression::Not(value) => {
+                let value = self.evaluate(value)?;
+                Ok(ExpressionValue::Integer {
+                    value: !self.integer(&value)?,
+                    type_: None,
+                })
+            }
+            Expression::Cast(cast_type, value) => {
+                let value = self.evaluate(value)?;
+                let value = self.integer(&value)?;
+                if cast_type.pointer {
+                    Ok(ExpressionValue::Pointer {
+                        address: value as u64,
+                        target: cast_type.primitive,
+                    })
+                } else {
+                    Ok(cast_type.primitive.convert(value))
+                }
+            }
+            Expression::Binary(BinaryOperator::Add, left, right) => self.offset(left, right, 1),
+            Expression::Binary(BinaryOperator::Subtract, left, right) => {
+                self.offset(left, right, -1)
+            }
+            Expression::Binary(operator, left, right) => {
+                let left = self.evaluate(left)?;
+                let left = self.integer(&left)?;
+                let right = self.evaluate(right)?;
+                let right = self.integer(&right)?;
+                Ok(ExpressionValue::Integer {
+                    value: operator.apply(left, right)?,
+                    type_: None,
+                })
+            }
+        }
+    }
+
+    /// Adds or subtracts `right` from `left`. Pointers are offset by elements, like in C.
+    fn offset(
+        &mut self,
+        left: &Expression,
+        right: &Expression,
+        sign: i128,
+    ) -> Result<ExpressionValue, DebugError> {
+        let left = self.evaluate(left)?;
+        let right = self.evaluate(right)?;
+        let right = self.integer(&right)? * sign;
+        match left {
+            ExpressionValue::Pointer { address, target } => Ok(ExpressionValue::Pointer {
+                address: (address as i128 + right * target.size() as i128) as u64,
+                target,
+            }),
+            left => Ok(ExpressionValue::Integer {
+                value: self
+                    .integer(&left)?
+                    .checked_add(right)
+                    .ok_or_else(|| DebugError::Other("Overflow".to_string()))?,
+                type_: None,
+            }),
+        }
+    }
+
+    fn identifier(&mut self, name: &str) -> Result<ExpressionValue, DebugError> {
+        if let Some(register_name) = name.strip_prefix('$') {
+            return self.register(register_name);
+        }
+
+        if self.variable_cache.len() == 1 {
+            // The root of a scope doesn't have cached children by default, so we need to resolve them first.
+            let root_variable = self.variable_cache.root_variable().clone();
+            self.resolve_children(root_variable)?;
+        }
+
+        let variable_name = VariableName::Named(name.to_string());
+        let root_key = self.variable_cache.root_variable().variable_key();
+        if let Some(variable) = self
+            .variable_cache
+            .get_variable_by_name_and_parent(&variable_name, root_key)
+            .or_else(|| self.variable_cache.get_variable_by_name(&variable_name))
+        {
+            return Ok(ExpressionValue::from(variable));
+        }
+
+        self.register(name)
+            .map_err(|_| DebugError::Other(format!("No variable named {name:?} in scope")))
+    }
+
+    fn register(&self, name: &str) -> Result<ExpressionValue, DebugError> {
+        let value = self
+            .frame_info
+            .registers
+            .get_register_by_name(name)
+            .and_then(|register| register.value)
+            .ok_or_else(|| DebugError::Other(format!("No register named {name:?}")))?;
+
+        Ok(ExpressionValue::Integer {
+            value: TryInto::<u128>::try_into(value)? as i128,
+            type_: None,
+        })
+    }
+
+    /// Evaluates `expression`, which must result in a variable.
+    fn variable(&mut self, expression: &Expression) -> Result<Variable, DebugError> {
+        match self.evaluate(expression)? {
+            ExpressionValue::Variable(variable) => Ok(*variable),
+            other => Err(DebugError::Other(format!(
+                "Expected a variable, found a value of type {}",
+                other.type_name()
+            ))),
+        }
+    }
+
+    /// Returns the element at `index` of an array variable, or of a cast pointer.
+    fn element(
+        &mut self,
+        base: &ExpressionValue,
+        index: i128,
+    ) -> Result<ExpressionValue, DebugError> {
+        match base {
+            ExpressionValue::Pointer { address, target } => {
+                let address = (*address as i128 + index * target.size() as i128) as u64;
+                target.read(self.memory, address)
+            }
+            ExpressionValue::Variable(array) if array.type_name.is_array() => {
+                let index = u64::try_from(index)?;
+                self.child(array, &VariableName::Indexed(index))
+                    .map(ExpressionValue::from)
+                    .ok_or_else(|| {
+                        DebugError::Other(format!(
+                            "Index {index} is out of bounds of {}",
+                            array.name
+                        ))
+                    })
+            }
+            other => Err(DebugError::Other(format!(
+                "Cannot index a value of type {}",
+                other.type_name()
+            ))),
+        }
+    }
+
+    /// Returns the elements `start..end` of an array variable, or of a cast pointer.
+    fn slice(
+        &mut self,
+        base: &ExpressionValue,
+        start: i128,
+        end: i128,
+    ) -> Result<Vec<ExpressionValue>, DebugError> {
+        if end < start {
+            return Err(DebugError::Other(format!(
+                "The range {start}..{end} is reversed"
+            )));
+        }
+        if end - start > MAX_SLICE_ELEMENTS as i128 {
+            return Err(DebugError::Other(format!(
+                "The range {start}..{end} has more than {MAX_SLICE_ELEMENTS} elements"
+            )));
+        }
+
+        match base {
+            ExpressionValue::Pointer { address, target } => {
+                let address = (*address as i128 + start * target.size() as i128) as u64;
+                target.read_slice(self.memory, address, (end - start) as usize)
+            }
+            base => (start..end)
+                .map(|index| self.element(base, index))
+                .collect(),
+        }
+    }
+
+    /// Returns the variable a pointer variable points to.
+    fn pointee(&mut self, pointer: Variable) -> Result<Variable, DebugError> {
+        let key = pointer.variable_key();
+        let name = pointer.name.clone();
+        self.resolve_children(pointer)?;
+        self.first_child(key)
+            .ok_or_else(|| DebugError::Other(format!("Failed to dereference {name}")))
+    }
+
+    fn child(&mut self, parent: &Variable, name: &VariableName) -> Option<Variable> {
+        if let Err(error) = self.resolve_children(parent.clone()) {
+            tracing::warn!("Failed to resolve the children of {}: {error}", parent.name);
+        }
+        self.variable_cache
+            .get_variable_by_name_and_parent(name, parent.variable_key())
+    }
+
+    fn first_child(&self, parent_key: ObjectRef) -> Option<Variable> {
+        self.variable_cache.get_children(parent_key).next().cloned()
+    }
+
+    fn resolve_children(&mut self, mut variable: Variable) -> Result<(), DebugError> {
+        self.debug_info.cache_deferred_variables(
+            self.variable_cache,
+            self.memory,
+            &mut variable,
+            self.frame_info,
+        )
+    }
+
+    /// Returns the integer value of `value`. Pointer variables evaluate to the address they point to.
+    fn integer(&mut self, value: &ExpressionValue) -> Result<i128, DebugError> {
+        match value {
+            ExpressionValue::Integer { value, .. } => Ok(*value),
+            ExpressionValue::Pointer { address, .. } => Ok(*address as i128),
+            ExpressionValue::Variable(variable)
+                if matches!(variable.type_name, VariableType::Pointer(_)) =>
+            {
+                let pointee = self.pointee(Variable::clone(variable))?;
+                Ok(pointee.memory_location.memory_address()? as i128)
+            }
+            ExpressionValue::Variable(variable) => {
+                let formatted = variable.to_string(self.variable_cache);
+                match formatted.trim() {
+                    "true" => Ok(1),
+                    "false" => Ok(0),
+                    trimmed => parse_int::parse::<i128>(trimmed).map_err(|_| {
+                        DebugError::Other(format!(
+                            "{} does not have an integer value",
+                            variable.name
+                        ))
+                    }),
+                }
+            }
+            other => Err(DebugError::Other(format!(
+                "A value of type {} is not an integer",
+                other.type_name()
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DebugRegisters;
+    use probe_rs::test::MockMemory;
+    use test_case::test_case;
+
+    fn parse(expression: &str) -> Expression {
+        let tokens = tokenize(expression).unwrap();
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+        };
+        let parsed = parser.parse_expression().unwrap();
+        assert_eq!(
+            parser.position,
+            tokens.len(),
+            "{expression} was not fully parsed"
+        );
+        parsed
+    }
+
+    fn identifier(name: &str) -> Box<Expression> {
+        Box::new(Expression::Identifier(name.to_string()))
+    }
+
+    #[test]
+    fn parse_c_cast() {
+        assert_eq!(
+            parse("*(const uint32_t *)0x2000_0000"),
+            Expression::Deref(Box::new(Expression::Cast(
+                CastType {
+                    primitive: PrimitiveType::U32,
+                    pointer: true,
+                },
+                Box::new(Expression::Number(0x2000_0000)),
+            )))
+        );
+    }
+
+    #[test]
+    fn parse_rust_cast() {
+        assert_eq!(
+            parse("*(0x2000_0000 as *const u16)"),
+            Expression::Deref(Box::new(Expression::Cast(
+                CastType {
+                    primitive: PrimitiveType::U16,
+                    pointer: true,
+                },
+                Box::new(Expression::Number(0x2000_0000)),
+            )))
+        );
+    }
+
+    #[test]
+    fn parse_field_chain() {
+        assert_eq!(
+            parse("ctx->pins[2].mode"),
+            Expression::Field(
+                Box::new(Expression::Index(
+                    Box::new(Expression::Field(
+                        Box::new(Expression::Deref(identifier("ctx"))),
+                        "pins".to_string()
+                    )),
+                    Box::new(Expression::Number(2)),
+                )),
+                "mode".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn parse_slice() {
+        assert_eq!(
+            parse("buf[4..16]"),
+            Expression::Slice(
+                identifier("buf"),
+                Box::new(Expression::Number(4)),
+                Box::new(Expression::Number(16)),
+            )
+        );
+    }
+
+    #[test]
+    fn parse_precedence() {
+        assert_eq!(
+            parse("a | b << 2 * c"),
+            Expression::Binary(
+                BinaryOperator::Or,
+                identifier("a"),
+                Box::new(Expression::Binary(
+                    BinaryOperator::ShiftLeft,
+                    identifier("b"),
+                    Box::new(Expression::Binary(
+                        BinaryOperator::Multiply,
+                        Box::new(Expression::Number(2)),
+                        identifier("c"),
+                    )),
+                )),
+            )
+        );
+    }
+
+    #[test]
+    fn parenthesized_variable_is_not_a_cast() {
+        assert_eq!(parse("(foo)"), Expression::Identifier("foo".to_string()));
+    }
+
+    #[test_case("(uint32_t*"; "unbalanced")]
+    #[test_case("buf[1..]"; "open slice")]
+    #[test_case("foo as String"; "unsupported type")]
+    #[test_case("a # b"; "invalid character")]
+    fn invalid(expression: &str) {
+        let parsed = tokenize(expression).and_then(|tokens| {
+            let mut parser = Parser {
+                tokens: &tokens,
+                position: 0,
+            };
+            parser.parse_expression()
+        });
+        assert!(parsed.is_err());
+    }
+
+    fn evaluate(expression: &str, memory: &mut MockMemory) -> ExpressionValue {
+        try_evaluate(expression, memory).unwrap()
+    }
+
+    fn try_evaluate(
+        expression: &str,
+        memory: &mut MockMemory,
+    ) -> Result<ExpressionValue, DebugError> {
+        let tokens = tokenize(expression).unwrap();
+        let parsed = Parser {
+            tokens: &tokens,
+            position: 0,
+        }
+        .parse_expression()
+        .unwrap();
+
+        let registers = DebugRegisters::default();
+        let debug_info = DebugInfo::from_file("./tests/gpio-hal-blinky/elf").unwrap();
+        let mut variable_cache = VariableCache::new_static_cache();
+        Evaluator {
+            debug_info: &debug_info,
+            memory,
+            variable_cache: &mut variable_cache,
+            frame_info: StackFrameInfo {
+                registers: &registers,
+                frame_base: None,
+                canonical_frame_address: None,
+            },
+        }
+        .evaluate(&parsed)
+    }
+
+    #[test]
+    fn read_through_cast_pointers() {
+        let mut memory = MockMemory::new();
+        memory.add_word_range(0x2000_0000, &[0x1234_5678, 0xFFFF_FFFE]);
+
+        assert_eq!(
+            evaluate("*(uint32_t*)0x2000_0000", &mut memory),
+            ExpressionValue::Integer {
+                value: 0x1234_5678,
+                type_: Some(PrimitiveType::U32),
+            }
+        );
+        assert_eq!(
+            evaluate("((uint16_t*)0x2000_0000)[1]", &mut memory),
+            ExpressionValue::Integer {
+                value: 0x1234,
+                type_: Some(PrimitiveType::U16),
+            }
+        );
+        assert_eq!(
+            evaluate("*((int32_t*)0x2000_0000 + 1)", &mut memory),
+            ExpressionValue::Integer {
+                value: -2,
+                type_: Some(PrimitiveType::I32),
+            }
+        );
+        assert_eq!(
+            evaluate("(0x2000_0000 as *const u8)[0..2]", &mut memory),
+            ExpressionValue::Slice(vec![
+                ExpressionValue::Integer {
+                    value: 0x78,
+                    type_: Some(PrimitiveType::U8),
+                },
+                ExpressionValue::Integer {
+                    value: 0x56,
+                    type_: Some(PrimitiveType::U8),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn integer_casts_truncate() {
+        let mut memory = MockMemory::new();
+        assert_eq!(
+            evaluate("(u8)0x1ff", &mut memory),
+            ExpressionValue::Integer {
+                value: 0xff,
+                type_: Some(PrimitiveType::U8),
+            }
+        );
+        assert_eq!(
+            evaluate("0xff as i8", &mut memory),
+            ExpressionValue::Integer {
+                value: -1,
+                type_: Some(PrimitiveType::I8),
+            }
+        );
+    }
+
+    #[test_case("0x20 + 0b11", 35; "radix")]
+    #[test_case("1 + 2 * 3", 7; "precedence")]
+    #[test_case("(1 + 2) * 3", 9; "parentheses")]
+    #[test_case("10 - 2 - 3", 5; "left associative")]
+    #[test_case("1 << 4 | 1", 17; "shift and or")]
+    #[test_case("0xff & 0x0f ^ 0x3", 0xc; "and before xor")]
+    #[test_case("-5 + ~0", -6; "unary")]
+    #[test_case("7 % 3 + 7 / 2", 4; "division")]
+    fn arithmetic(expression: &str, expected: i128) {
+        let mut memory = MockMemory::new();
+        assert_eq!(
+            evaluate(expression, &mut memory),
+            ExpressionValue::Integer {
+                value: expected,
+                type_: None,
+            }
+        );
+    }
+
+    #[test_case("1 / 0"; "division by zero")]
+    #[test_case("1 % 0"; "remainder by zero")]
+    #[test_case("1 << -1"; "negative shift")]
+    #[test_case("1 << 200"; "shift too far")]
+    fn invalid_arithmetic(expression: &str) {
+        let mut memory = MockMemory::new();
+        assert!(try_evaluate(expression, &mut memory).is_err());
+    }
+
+    #[test_case("((uint8_t*)0x2000_0000)[2..1]"; "reversed")]
+    #[test_case("((uint8_t*)0x2000_0000)[0..100000]"; "too long")]
+    fn invalid_slice(expression: &str) {
+        let mut memory = MockMemory::new();
+        memory.add_word_range(0x2000_0000, &[0x1234_5678]);
+
+        assert!(try_evaluate(expression, &mut memory).is_err());
+    }
+}
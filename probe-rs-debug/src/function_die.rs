@@ -151,6 +151,24 @@ impl<'a> FunctionDie<'a> {
         }
     }
 
+    /// Returns the qualified name of the function, e.g. `app::board::init`, from its demangled
+    /// linkage name. Returns `None` if the function has no linkage name.
+    pub(crate) fn qualified_function_name(&self, debug_info: &super::DebugInfo) -> Option<String> {
+        let linkage_name_attr = self
+            .attribute(debug_info, gimli::DW_AT_linkage_name)
+            .or_else(|| self.attribute(debug_info, gimli::DW_AT_MIPS_linkage_name))?;
+        let linkage_name = debug_info
+            .dwarf
+            .attr_string(&self.unit_info.unit, linkage_name_attr.value())
+            .ok()?;
+        let linkage_name = String::from_utf8_lossy(&linkage_name);
+
+        let demangled = addr2line::demangle(&linkage_name, gimli::DW_LANG_Rust)
+            .or_else(|| addr2line::demangle(&linkage_name, gimli::DW_LANG_C_plus_plus))?;
+
+        Some(debug_info::strip_rust_hash(&demangled).to_string())
+    }
+
     /// Get the call site of an inlined function.
     ///
     /// If this function is not inlined (`is_inline()` returns false),
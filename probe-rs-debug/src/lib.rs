@@ -3,20 +3,30 @@
 //! The `debug` module contains various debug functionality, which can be
 //! used to implement a debugger based on `probe-rs`.
 
+/// Assertions on the target state, for use in hardware-in-the-loop tests.
+pub mod assertions;
+/// Source line coverage from PC samples.
+pub mod coverage;
 /// Debug information which is parsed from DWARF debugging information.
 pub mod debug_info;
 /// Stepping through a program during debug, at various granularities.
 pub mod debug_step;
+/// Evaluation of debugger expressions, e.g. `*(uint32_t*)0x2000_0000` or `buf[4..16]`.
+pub mod expression;
 /// References to the DIE (debug information entry) of functions.
 pub mod function_die;
 /// Programming languages
 pub(crate) mod language;
 /// Target Register definitions, expanded from [`crate::core::registers::CoreRegister`] to include unwind specific information.
 pub mod registers;
+/// Awareness of the threads of real-time operating systems and async executors.
+pub mod rtos;
 /// The source statement information used while identifying haltpoints for debug stepping and breakpoints.
 pub(crate) mod source_instructions;
 /// The stack frame information used while unwinding the stack from a specific program counter.
 pub mod stack_frame;
+/// Single line summaries of variables with well known types, e.g. `String` or `Vec`.
+pub mod type_recognizer;
 /// Information about a Unit in the debug information.
 pub mod unit_info;
 /// Variable information used during debug.
@@ -27,9 +37,17 @@ pub mod variable_cache;
 pub(crate) mod exception_handling;
 
 pub use self::{
-    debug_info::*, debug_step::SteppingMode, exception_handling::exception_handler_for_core,
-    registers::*, source_instructions::SourceLocation, source_instructions::VerifiedBreakpoint,
-    stack_frame::StackFrame, variable::*, variable_cache::VariableCache,
+    debug_info::*,
+    debug_step::SteppingMode,
+    exception_handling::exception_handler_for_core,
+    expression::{ExpressionValue, PrimitiveType},
+    registers::*,
+    source_instructions::SourceLocation,
+    source_instructions::VerifiedBreakpoint,
+    stack_frame::StackFrame,
+    type_recognizer::{RecognizerContext, TypeRecognizer, TypeRecognizers},
+    variable::*,
+    variable_cache::VariableCache,
 };
 
 use probe_rs::{Core, MemoryInterface};
@@ -1,4 +1,4 @@
-use std::ops::Range;
+use std::{collections::HashMap, ops::Range};
 
 use probe_rs::{
     CoreDump, CoreInterface, CoreRegister, CoreRegisters, Error, RegisterDataType, RegisterId,
@@ -84,6 +84,28 @@ pub struct DebugRegisters(pub Vec<DebugRegister>);
 impl DebugRegisters {
     /// Read all registers defined in [`probe_rs::core::CoreRegisters`] from the given core.
     pub fn from_core(core: &mut impl CoreInterface) -> Self {
+        let register_ids = core
+            .registers()
+            .core_registers()
+            .filter(|core_register| is_debug_register(core_register))
+            .map(|core_register| core_register.id())
+            .collect::<Vec<_>>();
+
+        // Read all registers at once, and only fall back to reading them one by one to find out
+        // which of them can't be read.
+        match core.read_core_regs(&register_ids) {
+            Ok(values) => {
+                let values = register_ids
+                    .into_iter()
+                    .zip(values)
+                    .collect::<HashMap<_, _>>();
+                return Self::from_core_registers(core.registers(), |register_id| {
+                    values.get(register_id).copied()
+                });
+            }
+            Err(error) => tracing::debug!("Failed to read all registers at once: {error}"),
+        }
+
         Self::from_core_registers(core.registers(), |register_id| {
             core.read_core_reg(*register_id)
                 .inspect_err(|error| {
@@ -113,9 +135,7 @@ impl DebugRegisters {
     ) -> Self {
         let mut debug_registers = Vec::<DebugRegister>::new();
         for (dwarf_id, core_register) in regs.core_registers().enumerate() {
-            // Check to ensure the register type is compatible with u64.
-            if matches!(core_register.data_type(), RegisterDataType::UnsignedInteger(size_in_bits) if size_in_bits <= 64)
-            {
+            if is_debug_register(core_register) {
                 debug_registers.push(DebugRegister {
                     core_register,
                     // The DWARF register ID is only valid for the first 32 registers.
@@ -297,3 +317,8 @@ impl DebugRegisters {
             .cloned()
     }
 }
+
+/// Check to ensure the register type is compatible with u64.
+fn is_debug_register(core_register: &CoreRegister) -> bool {
+    matches!(core_register.data_type(), RegisterDataType::UnsignedInteger(size_in_bits) if size_in_bits <= 64)
+}
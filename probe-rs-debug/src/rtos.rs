@@ -0,0 +1,285 @@
+//! This module (and its children) contains the implementations of [`RtosAwareness`] for the supported real-time
+//! operating systems and async executors.
+
+use std::collections::HashMap;
+
+use gimli::Reader;
+use probe_rs::{MemoryInterface, RegisterRole, RegisterValue};
+
+use crate::{
+    DebugError, DebugInfo, DebugRegisters,
+    exception_handling::armv6m_armv7m_shared::{
+        EXTENDED_FRAME_SIZE, STANDARD_FRAME_SIZE, realignment, restore_fp_registers,
+        restore_standard_frame, set_stack_pointer,
+    },
+    extract_byte_size,
+};
+
+mod embassy;
+mod freertos;
+mod rtic;
+mod zephyr;
+
+pub use embassy::Embassy;
+pub use freertos::FreeRtos;
+pub use rtic::Rtic;
+pub use zephyr::Zephyr;
+
+/// Linked lists of threads are not followed further than this, in case they are corrupted.
+const MAX_THREADS: usize = 256;
+
+/// Callee saved registers, which a Cortex-M RTOS saves in software when it switches out a thread. The
+/// remaining registers are saved by the processor, in the exception frame of the context switch.
+static CORTEX_M_CALLEE_SAVED_REGISTERS: &[RegisterRole] = &[
+    RegisterRole::Core("R4"),
+    RegisterRole::Core("R5"),
+    RegisterRole::Core("R6"),
+    RegisterRole::Core("R7"),
+    RegisterRole::Core("R8"),
+    RegisterRole::Core("R9"),
+    RegisterRole::Core("R10"),
+    RegisterRole::Core("R11"),
+];
+
+/// The scheduling state of a thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadState {
+    /// The thread is executing on the core.
+    Running,
+    /// The thread is ready to run, and waits for the scheduler.
+    Ready,
+    /// The thread waits for an event, e.g. a timeout or a semaphore.
+    Blocked,
+    /// The thread was suspended, and is not scheduled until it is resumed.
+    Suspended,
+    /// The thread has not been started yet, or has terminated.
+    Inactive,
+    /// The state of the thread could not be determined.
+    Unknown,
+}
+
+impl std::fmt::Display for ThreadState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThreadState::Running => write!(f, "Running"),
+            ThreadState::Ready => write!(f, "Ready"),
+            ThreadState::Blocked => write!(f, "Blocked"),
+            ThreadState::Suspended => write!(f, "Suspended"),
+            ThreadState::Inactive => write!(f, "Inactive"),
+            ThreadState::Unknown => write!(f, "Unknown"),
+        }
+    }
+}
+
+/// A thread (or task) of an RTOS, read from the kernel structures in target memory.
+#[derive(Debug, Clone)]
+pub struct RtosThread {
+    /// Identifies the thread, usually the address of its control block.
+    pub id: u64,
+    /// The name of the thread.
+    pub name: String,
+    /// The scheduling state of the thread.
+    pub state: ThreadState,
+    /// The priority of the thread, if the RTOS has priorities. The meaning of the value is RTOS specific.
+    pub priority: Option<i64>,
+    /// The registers of the thread, reconstructed from the context it saved when it was switched out.
+    ///
+    /// This is `None` for the running thread, which uses the registers of the core, and for threads without a
+    /// context of their own, e.g. async tasks.
+    pub registers: Option<DebugRegisters>,
+}
+
+impl RtosThread {
+    /// Returns `true` if this thread is executing on the core.
+    pub fn is_running(&self) -> bool {
+        self.state == ThreadState::Running
+    }
+}
+
+/// Reads the threads of an RTOS from the target memory.
+pub trait RtosAwareness: Send + Sync {
+    /// The name of the RTOS.
+    fn name(&self) -> &'static str;
+
+    /// Reads the current list of threads.
+    ///
+    /// The `registers` are the registers of the halted core, which are used as the template for the registers of
+    /// the other threads.
+    fn threads(
+        &self,
+        debug_info: &DebugInfo,
+        memory: &mut dyn MemoryInterface,
+        registers: &DebugRegisters,
+    ) -> Result<Vec<RtosThread>, DebugError>;
+}
+
+/// Detects the RTOS used by the program, from the symbols in its ELF file.
+pub fn detect_rtos(debug_info: &DebugInfo) -> Option<Box<dyn RtosAwareness>> {
+    let rtos: Box<dyn RtosAwareness> = if let Some(freertos) = FreeRtos::detect(debug_info) {
+        Box::new(freertos)
+    } else if let Some(zephyr) = Zephyr::detect(debug_info) {
+        Box::new(zephyr)
+    } else if let Some(rtic) = Rtic::detect(debug_info) {
+        Box::new(rtic)
+    } else if let Some(embassy) = Embassy::detect(debug_info) {
+        Box::new(embassy)
+    } else {
+        return None;
+    };
+
+    tracing::info!("Detected {} in the program.", rtos.name());
+    Some(rtos)
+}
+
+/// The offsets of the members of a structure, read from the debug information.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StructLayout {
+    members: HashMap<String, u64>,
+    /// The size of the structure in bytes.
+    pub(crate) byte_size: Option<u64>,
+}
+
+impl StructLayout {
+    /// The offset of the member `name`, if it exists.
+    pub(crate) fn offset(&self, name: &str) -> Option<u64> {
+        self.members.get(name).copied()
+    }
+
+    /// The offset of the member `name`, or `default` if it is unknown, e.g. because the program was built without
+    /// debug information for the RTOS.
+    pub(crate) fn offset_or(&self, name: &str, default: u64) -> u64 {
+        self.offset(name).unwrap_or(default)
+    }
+}
+
+impl DebugInfo {
+    /// Finds the definition of the structure `name` in the debug information, and returns the offsets of its members.
+    pub(crate) fn struct_layout(&self, name: &str) -> Option<StructLayout> {
+        for unit_info in &self.unit_infos {
+            let mut entries = unit_info.unit.entries();
+            while let Ok(Some((_, entry))) = entries.next_dfs() {
+                if !matches!(
+                    entry.tag(),
+                    gimli::DW_TAG_structure_type | gimli::DW_TAG_class_type
+                ) || self.entry_name(unit_info, entry).as_deref() != Some(name)
+                {
+                    continue;
+                }
+                // Skip forward declarations.
+                let Some(byte_size) = extract_byte_size(entry) else {
+                    continue;
+                };
+
+                let mut members = HashMap::new();
+                let Ok(mut tree) = unit_info.unit.entries_tree(Some(entry.offset())) else {
+                    continue;
+                };
+                let Ok(root) = tree.root() else {
+                    continue;
+                };
+                let mut children = root.children();
+                while let Ok(Some(child)) = children.next() {
+                    let member = child.entry();
+                    if member.tag() != gimli::DW_TAG_member {
+                        continue;
+                    }
+                    let Some(member_name) = self.entry_name(unit_info, member) else {
+                        continue;
+                    };
+                    let offset = member
+                        .attr_value(gimli::DW_AT_data_member_location)
+                        .ok()
+                        .flatten()
+                        .and_then(|value| value.udata_value())
+                        .unwrap_or(0);
+                    members.insert(member_name, offset);
+                }
+
+                return Some(StructLayout {
+                    members,
+                    byte_size: Some(byte_size),
+                });
+            }
+        }
+
+        None
+    }
+
+    fn entry_name(
+        &self,
+        unit_info: &crate::unit_info::UnitInfo,
+        entry: &gimli::DebuggingInformationEntry<crate::debug_info::GimliReader>,
+    ) -> Option<String> {
+        let value = entry.attr_value(gimli::DW_AT_name).ok().flatten()?;
+        let name = self.dwarf.attr_string(&unit_info.unit, value).ok()?;
+        Some(name.to_string_lossy().ok()?.into_owned())
+    }
+}
+
+/// Reads a pointer sized value from 32-bit target memory.
+pub(crate) fn read_pointer(
+    memory: &mut dyn MemoryInterface,
+    address: u64,
+) -> Result<u64, DebugError> {
+    Ok(memory.read_word_32(address)?.into())
+}
+
+/// Reads a NUL terminated string of at most `max_length` bytes.
+pub(crate) fn read_c_string(
+    memory: &mut dyn MemoryInterface,
+    address: u64,
+    max_length: usize,
+) -> Result<String, DebugError> {
+    let mut buffer = vec![0; max_length];
+    memory.read_8(address, &mut buffer)?;
+    let length = buffer
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(max_length);
+    Ok(String::from_utf8_lossy(&buffer[..length]).into_owned())
+}
+
+/// Reconstructs the registers of a Cortex-M thread, which was switched out by the RTOS.
+///
+/// The context switch happens in an exception handler (usually PendSV), so the processor saved the caller saved
+/// registers in an exception frame on the process stack of the thread, and the RTOS saved the
+/// `callee_saved` registers R4-R11.
+///
+/// Returns `None` if the core is not a Cortex-M core.
+pub(crate) fn cortex_m_thread_registers(
+    memory: &mut dyn MemoryInterface,
+    core_registers: &DebugRegisters,
+    callee_saved: &[u32],
+    exception_frame_address: u64,
+    extended_frame: bool,
+) -> Result<Option<DebugRegisters>, DebugError> {
+    if core_registers
+        .get_register_by_role(&RegisterRole::ProcessStackPointer)
+        .is_err()
+    {
+        return Ok(None);
+    }
+
+    let mut registers = core_registers.clone();
+    for (register_role, value) in CORTEX_M_CALLEE_SAVED_REGISTERS.iter().zip(callee_saved) {
+        registers.get_register_mut_by_role(register_role)?.value = Some(RegisterValue::U32(*value));
+    }
+
+    restore_standard_frame(memory, &mut registers, exception_frame_address)?;
+    let frame_size = if extended_frame {
+        restore_fp_registers(
+            memory,
+            &mut registers,
+            exception_frame_address + STANDARD_FRAME_SIZE,
+            &[],
+        )?;
+        EXTENDED_FRAME_SIZE
+    } else {
+        STANDARD_FRAME_SIZE
+    };
+
+    let stack_pointer = exception_frame_address + frame_size + realignment(&registers)?;
+    set_stack_pointer(&mut registers, stack_pointer, true);
+
+    Ok(Some(registers))
+}
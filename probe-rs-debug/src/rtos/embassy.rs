@@ -0,0 +1,102 @@
+use probe_rs::MemoryInterface;
+
+use super::{RtosAwareness, RtosThread, ThreadState};
+use crate::{DebugError, DebugInfo, DebugRegisters};
+
+// The bits of `TaskHeader::state`, from `embassy_executor::raw::state_atomics`.
+const STATE_SPAWNED: u32 = 1 << 0;
+const STATE_RUN_QUEUED: u32 = 1 << 1;
+
+/// Awareness of the tasks of the [embassy](https://embassy.dev/) executor.
+///
+/// Every `#[embassy_executor::task]` function has a static `POOL` of tasks, which is found by its symbol. The tasks
+/// run on the stack of the executor, so they have no registers of their own, only a state.
+#[derive(Debug)]
+pub struct Embassy {
+    pools: Vec<(String, u64)>,
+    state_offset: u64,
+}
+
+impl Embassy {
+    /// Detects embassy from the symbols of the `embassy_executor` crate, and collects the task pools.
+    pub fn detect(debug_info: &DebugInfo) -> Option<Self> {
+        if !debug_info
+            .symbols()
+            .iter()
+            .any(|symbol| symbol.display_name().contains("embassy_executor"))
+        {
+            return None;
+        }
+
+        let pools = debug_info
+            .symbols()
+            .iter()
+            .filter_map(|symbol| {
+                let task = symbol.display_name().strip_suffix("::POOL")?;
+                let name = task.rsplit("::").next().unwrap_or(task);
+                Some((name.to_string(), symbol.address))
+            })
+            .collect();
+
+        Some(Self {
+            pools,
+            state_offset: debug_info
+                .struct_layout("TaskHeader")
+                .and_then(|layout| layout.offset("state"))
+                .unwrap_or(0),
+        })
+    }
+}
+
+fn task_state_from_bits(state: u32) -> ThreadState {
+    if state & STATE_SPAWNED == 0 {
+        ThreadState::Inactive
+    } else if state & STATE_RUN_QUEUED != 0 {
+        ThreadState::Ready
+    } else {
+        ThreadState::Blocked
+    }
+}
+
+impl RtosAwareness for Embassy {
+    fn name(&self) -> &'static str {
+        "embassy"
+    }
+
+    fn threads(
+        &self,
+        _debug_info: &DebugInfo,
+        memory: &mut dyn MemoryInterface,
+        _registers: &DebugRegisters,
+    ) -> Result<Vec<RtosThread>, DebugError> {
+        let mut threads = Vec::new();
+        for (name, address) in &self.pools {
+            // The first task of the pool. The size of a task depends on its future, so further tasks of pools with
+            // more than one task are not shown.
+            let state = memory.read_word_32(address + self.state_offset)?;
+            threads.push(RtosThread {
+                id: *address,
+                name: name.clone(),
+                state: task_state_from_bits(state),
+                priority: None,
+                registers: None,
+            });
+        }
+
+        Ok(threads)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case(0b00 => ThreadState::Inactive; "not spawned")]
+    #[test_case(0b01 => ThreadState::Blocked; "waiting")]
+    #[test_case(0b11 => ThreadState::Ready; "run queued")]
+    fn task_state(bits: u32) -> ThreadState {
+        task_state_from_bits(bits)
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashSet;
+
+use probe_rs::MemoryInterface;
+
+use super::{
+    MAX_THREADS, RtosAwareness, RtosThread, StructLayout, ThreadState, cortex_m_thread_registers,
+    read_c_string, read_pointer,
+};
+use crate::{DebugError, DebugInfo, DebugRegisters};
+
+/// The default of `configMAX_TASK_NAME_LEN`.
+const MAX_TASK_NAME_LENGTH: usize = 16;
+
+/// The size of `List_t` with `configUSE_MINI_LIST_ITEM` and 32-bit ticks.
+const DEFAULT_LIST_SIZE: u64 = 20;
+
+/// The lists of the FreeRTOS scheduler which are not ready lists, and the state of the tasks in them.
+const TASK_LISTS: &[(&str, ThreadState)] = &[
+    ("xPendingReadyList", ThreadState::Ready),
+    ("xDelayedTaskList1", ThreadState::Blocked),
+    ("xDelayedTaskList2", ThreadState::Blocked),
+    ("xSuspendedTaskList", ThreadState::Suspended),
+    ("xTasksWaitingTermination", ThreadState::Inactive),
+];
+
+/// Awareness of the tasks of [FreeRTOS](https://www.freertos.org/).
+///
+/// The tasks are read from the lists of the scheduler in `tasks.c`. The registers of a task are reconstructed from
+/// the context the Cortex-M ports save on its stack.
+#[derive(Debug)]
+pub struct FreeRtos {
+    current_tcb: u64,
+    ready_lists: u64,
+    ready_list_count: u64,
+    /// The `ARM_CM4F` and `ARM_CM7` ports save the return value of the exception (and the upper floating point
+    /// registers, if the task used the FPU) along with R4-R11.
+    saves_exc_return: bool,
+    tcb: StructLayout,
+    list: StructLayout,
+    list_item: StructLayout,
+    list_size: u64,
+    list_end: u64,
+}
+
+impl FreeRtos {
+    /// Detects FreeRTOS from the `pxCurrentTCB` and `pxReadyTasksLists` symbols.
+    pub fn detect(debug_info: &DebugInfo) -> Option<Self> {
+        let current_tcb = debug_info.find_symbol("pxCurrentTCB")?.address;
+        let ready_lists = debug_info.find_symbol("pxReadyTasksLists")?;
+
+        let list = debug_info.struct_layout("xLIST").unwrap_or_default();
+        let list_size = list.byte_size.unwrap_or(DEFAULT_LIST_SIZE).max(1);
+        let list_end = list.offset_or("xListEnd", 8);
+
+        Some(Self {
+            current_tcb,
+            ready_lists: ready_lists.address,
+            ready_list_count: (ready_lists.size / list_size).max(1),
+            saves_exc_return: debug_info.find_symbol("vPortEnableVFP").is_some(),
+            tcb: debug_info
+                .struct_layout("tskTaskControlBlock")
+                .unwrap_or_default(),
+            list,
+            list_item: debug_info.struct_layout("xLIST_ITEM").unwrap_or_default(),
+            list_size,
+            list_end,
+        })
+    }
+
+    /// Returns the owners of the items in the list at `list_address`, which are the addresses of the TCBs.
+    fn list_owners(
+        &self,
+        memory: &mut dyn MemoryInterface,
+        list_address: u64,
+    ) -> Result<Vec<u64>, DebugError> {
+        let number_of_items =
+            memory.read_word_32(list_address + self.list.offset_or("uxNumberOfItems", 0))?;
+        if number_of_items == 0 {
+            return Ok(Vec::new());
+        }
+
+        // The list is circular, `xListEnd` is the marker at its end.
+        let end_marker = list_address + self.list_end;
+        let next_offset = self.list_item.offset_or("pxNext", 4);
+        let owner_offset = self.list_item.offset_or("pvOwner", 12);
+
+        let mut owners = Vec::new();
+        let mut item = read_pointer(memory, end_marker + next_offset)?;
+        while item != end_marker && item != 0 && owners.len() < MAX_THREADS {
+            owners.push(read_pointer(memory, item + owner_offset)?);
+            item = read_pointer(memory, item + next_offset)?;
+        }
+
+        Ok(owners)
+    }
+
+    fn thread(
+        &self,
+        memory: &mut dyn MemoryInterface,
+        registers: &DebugRegisters,
+        tcb: u64,
+        state: ThreadState,
+    ) -> Result<RtosThread, DebugError> {
+        let name = read_c_string(
+            memory,
+            tcb + self.tcb.offset_or("pcTaskName", 52),
+            MAX_TASK_NAME_LENGTH,
+        )?;
+        let priority = memory.read_word_32(tcb + self.tcb.offset_or("uxPriority", 44))?;
+
+        let registers = if state == ThreadState::Running {
+            None
+        } else {
+            let top_of_stack = read_pointer(memory, tcb + self.tcb.offset_or("pxTopOfStack", 0))?;
+            self.saved_registers(memory, registers, top_of_stack)
+                .unwrap_or_else(|error| {
+                    tracing::debug!(
+                        "Failed to read the saved context of FreeRTOS task {name}: {error}"
+                    );
+                    None
+                })
+        };
+
+        Ok(RtosThread {
+            id: tcb,
+            name,
+            state,
+            priority: Some(priority.into()),
+            registers,
+        })
+    }
+
+    /// Reconstructs the registers of a task from the context at the top of its stack.
+    fn saved_registers(
+        &self,
+        memory: &mut dyn MemoryInterface,
+        registers: &DebugRegisters,
+        top_of_stack: u64,
+    ) -> Result<Option<DebugRegisters>, DebugError> {
+        // R4-R11, followed by EXC_RETURN in the ports which save it.
+        let mut saved = vec![0u32; if self.saves_exc_return { 9 } else { 8 }];
+        memory.read_32(top_of_stack, &mut saved)?;
+
+        let mut frame_address = top_of_stack + 4 * saved.len() as u64;
+        let mut extended_frame = false;
+        if self.saves_exc_return && saved[8] & (1 << 4) == 0 {
+            // S16-S31 were saved by the port, before the registers above.
+            frame_address += 16 * 4;
+            extended_frame = true;
+        }
+
+        cortex_m_thread_registers(
+            memory,
+            registers,
+            &saved[..8],
+            frame_address,
+            extended_frame,
+        )
+    }
+}
+
+impl RtosAwareness for FreeRtos {
+    fn name(&self) -> &'static str {
+        "FreeRTOS"
+    }
+
+    fn threads(
+        &self,
+        debug_info: &DebugInfo,
+        memory: &mut dyn MemoryInterface,
+        registers: &DebugRegisters,
+    ) -> Result<Vec<RtosThread>, DebugError> {
+        let current_tcb = read_pointer(memory, self.current_tcb)?;
+        if current_tcb == 0 {
+            // The scheduler was not started yet.
+            return Ok(Vec::new());
+        }
+
+        let mut tasks = vec![(current_tcb, ThreadState::Running)];
+        for priority in 0..self.ready_list_count {
+            let list_address = self.ready_lists + priority * self.list_size;
+            for tcb in self.list_owners(memory, list_address)? {
+                tasks.push((tcb, ThreadState::Ready));
+            }
+        }
+        for (symbol_name, state) in TASK_LISTS {
+            // Lists are missing if the features using them are disabled in `FreeRTOSConfig.h`.
+            let Some(symbol) = debug_info.find_symbol(symbol_name) else {
+                continue;
+            };
+            for tcb in self.list_owners(memory, symbol.address)? {
+                tasks.push((tcb, *state));
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let mut threads = Vec::new();
+        for (tcb, state) in tasks {
+            // The running task is also in a ready list.
+            if tcb == 0 || !seen.insert(tcb) {
+                continue;
+            }
+            threads.push(self.thread(memory, registers, tcb, state)?);
+        }
+
+        Ok(threads)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use probe_rs::test::MockMemory;
+
+    use super::*;
+
+    fn freertos() -> FreeRtos {
+        FreeRtos {
+            current_tcb: 0x2000_0000,
+            ready_lists: 0x2000_0100,
+            ready_list_count: 2,
+            saves_exc_return: false,
+            tcb: StructLayout::default(),
+            list: StructLayout::default(),
+            list_item: StructLayout::default(),
+            list_size: DEFAULT_LIST_SIZE,
+            list_end: 8,
+        }
+    }
+
+    #[test]
+    fn walk_list() {
+        let freertos = freertos();
+        let mut memory = MockMemory::new();
+
+        // A list with two items, at 0x2000_0200 and 0x2000_0300.
+        let list = 0x2000_0114;
+        let end_marker = list + 8;
+        memory.add_word_range(
+            list,
+            &[2, 0x2000_0200, 0xFFFF_FFFF, 0x2000_0200, 0x2000_0300],
+        );
+        memory.add_word_range(
+            0x2000_0200,
+            &[0, 0x2000_0300, end_marker as u32, 0x2000_1000],
+        );
+        memory.add_word_range(
+            0x2000_0300,
+            &[0, end_marker as u32, 0x2000_0200, 0x2000_2000],
+        );
+
+        assert_eq!(
+            freertos.list_owners(&mut memory, list).unwrap(),
+            vec![0x2000_1000, 0x2000_2000]
+        );
+    }
+
+    #[test]
+    fn empty_list() {
+        let freertos = freertos();
+        let mut memory = MockMemory::new();
+        memory.add_word_range(0x2000_0100, &[0, 0, 0xFFFF_FFFF, 0x2000_0108, 0x2000_0108]);
+
+        assert!(
+            freertos
+                .list_owners(&mut memory, 0x2000_0100)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use probe_rs::MemoryInterface;
+
+use super::{RtosAwareness, RtosThread, ThreadState};
+use crate::{DebugError, DebugInfo, DebugRegisters};
+
+/// Awareness of the software tasks of [RTIC](https://rtic.rs/).
+///
+/// RTIC generates a static `__rtic_internal_<task>_EXEC` for every software task, which is found by its symbol.
+/// Tasks run on the main stack, in the interrupt handler of their priority level, so they have no registers of their
+/// own. Their state is not tracked in a way that can be read from memory.
+#[derive(Debug)]
+pub struct Rtic {
+    tasks: Vec<(String, u64)>,
+}
+
+impl Rtic {
+    /// Detects RTIC from the symbols of its software tasks.
+    pub fn detect(debug_info: &DebugInfo) -> Option<Self> {
+        let tasks: Vec<_> = debug_info
+            .symbols()
+            .iter()
+            .filter_map(|symbol| {
+                let name = symbol.display_name();
+                let (_, task) = name.split_once("__rtic_internal_")?;
+                let task = task.strip_suffix("_EXEC")?;
+                Some((task.to_string(), symbol.address))
+            })
+            .collect();
+
+        if tasks.is_empty() {
+            return None;
+        }
+
+        Some(Self { tasks })
+    }
+}
+
+impl RtosAwareness for Rtic {
+    fn name(&self) -> &'static str {
+        "RTIC"
+    }
+
+    fn threads(
+        &self,
+        _debug_info: &DebugInfo,
+        _memory: &mut dyn MemoryInterface,
+        _registers: &DebugRegisters,
+    ) -> Result<Vec<RtosThread>, DebugError> {
+        Ok(self
+            .tasks
+            .iter()
+            .map(|(name, address)| RtosThread {
+                id: *address,
+                name: name.clone(),
+                state: ThreadState::Unknown,
+                priority: None,
+                registers: None,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,208 @@
+use probe_rs::MemoryInterface;
+
+use super::{
+    MAX_THREADS, RtosAwareness, RtosThread, StructLayout, ThreadState, cortex_m_thread_registers,
+    read_c_string, read_pointer,
+};
+use crate::{DebugError, DebugInfo, DebugRegisters};
+
+/// The default of `CONFIG_THREAD_MAX_NAME_LEN`.
+const MAX_THREAD_NAME_LENGTH: usize = 32;
+
+// The bits of `_thread_base::thread_state`, from `kernel_structs.h`.
+const THREAD_PENDING: u8 = 0x02;
+const THREAD_PRESTART: u8 = 0x04;
+const THREAD_DEAD: u8 = 0x08;
+const THREAD_SUSPENDED: u8 = 0x10;
+
+/// Awareness of the threads of the [Zephyr](https://zephyrproject.org/) kernel.
+///
+/// The threads are read from the list of threads in `_kernel`, which requires `CONFIG_THREAD_MONITOR`. Unlike the
+/// other RTOSes, the layout of the kernel structures depends so much on the configuration that it is always read
+/// from the debug information.
+#[derive(Debug)]
+pub struct Zephyr {
+    kernel: u64,
+    z_kernel: StructLayout,
+    cpu: StructLayout,
+    thread: StructLayout,
+    thread_base: StructLayout,
+    callee_saved: StructLayout,
+    thread_arch: StructLayout,
+}
+
+impl Zephyr {
+    /// Detects Zephyr from the `_kernel` symbol, and the kernel structures in the debug information.
+    pub fn detect(debug_info: &DebugInfo) -> Option<Self> {
+        let kernel = debug_info.find_symbol("_kernel")?.address;
+        let z_kernel = debug_info.struct_layout("z_kernel")?;
+        let thread = debug_info.struct_layout("k_thread")?;
+
+        // Without `CONFIG_THREAD_MONITOR`, the threads can not be listed.
+        if z_kernel.offset("threads").is_none() || thread.offset("next_thread").is_none() {
+            tracing::debug!(
+                "Zephyr was built without CONFIG_THREAD_MONITOR, threads are not available."
+            );
+            return None;
+        }
+
+        Some(Self {
+            kernel,
+            z_kernel,
+            cpu: debug_info.struct_layout("_cpu").unwrap_or_default(),
+            thread,
+            thread_base: debug_info.struct_layout("_thread_base").unwrap_or_default(),
+            callee_saved: debug_info
+                .struct_layout("_callee_saved")
+                .unwrap_or_default(),
+            thread_arch: debug_info.struct_layout("_thread_arch").unwrap_or_default(),
+        })
+    }
+
+    fn thread(
+        &self,
+        memory: &mut dyn MemoryInterface,
+        registers: &DebugRegisters,
+        address: u64,
+        running: bool,
+    ) -> Result<RtosThread, DebugError> {
+        let base = address + self.thread.offset_or("base", 0);
+        let mut thread_state = [0u8; 1];
+        memory.read_8(
+            base + self.thread_base.offset_or("thread_state", 0),
+            &mut thread_state,
+        )?;
+        let mut priority = [0u8; 1];
+        memory.read_8(base + self.thread_base.offset_or("prio", 0), &mut priority)?;
+
+        let name = match self.thread.offset("name") {
+            Some(offset) => read_c_string(memory, address + offset, MAX_THREAD_NAME_LENGTH)?,
+            None => String::new(),
+        };
+        let name = if name.is_empty() {
+            format!("{address:#010x}")
+        } else {
+            name
+        };
+
+        let state = if running {
+            ThreadState::Running
+        } else {
+            thread_state_from_bits(thread_state[0])
+        };
+
+        let registers = if running || state == ThreadState::Inactive {
+            None
+        } else {
+            self.saved_registers(memory, registers, address)
+                .unwrap_or_else(|error| {
+                    tracing::debug!(
+                        "Failed to read the saved context of Zephyr thread {name}: {error}"
+                    );
+                    None
+                })
+        };
+
+        Ok(RtosThread {
+            id: address,
+            name,
+            state,
+            priority: Some((priority[0] as i8).into()),
+            registers,
+        })
+    }
+
+    /// Reconstructs the registers of a thread from its `callee_saved` registers and the exception frame on its
+    /// stack.
+    fn saved_registers(
+        &self,
+        memory: &mut dyn MemoryInterface,
+        registers: &DebugRegisters,
+        address: u64,
+    ) -> Result<Option<DebugRegisters>, DebugError> {
+        let callee_saved = address + self.thread.offset_or("callee_saved", 0);
+        let mut saved = Vec::new();
+        for (index, name) in ["v1", "v2", "v3", "v4", "v5", "v6", "v7", "v8"]
+            .iter()
+            .enumerate()
+        {
+            let offset = self.callee_saved.offset_or(name, 4 * index as u64);
+            saved.push(memory.read_word_32(callee_saved + offset)?);
+        }
+        let psp = read_pointer(
+            memory,
+            callee_saved + self.callee_saved.offset_or("psp", 32),
+        )?;
+
+        // With `CONFIG_ARM_STORE_EXC_RETURN`, the thread records whether the processor stacked the FPU registers.
+        // The upper floating point registers are saved in the thread structure, not on the stack.
+        let mut extended_frame = false;
+        if let Some(offset) = self.thread_arch.offset("mode_exc_return") {
+            let mut exc_return = [0u8; 1];
+            memory.read_8(
+                address + self.thread.offset_or("arch", 0) + offset,
+                &mut exc_return,
+            )?;
+            extended_frame = exc_return[0] & (1 << 4) == 0;
+        }
+
+        cortex_m_thread_registers(memory, registers, &saved, psp, extended_frame)
+    }
+}
+
+fn thread_state_from_bits(thread_state: u8) -> ThreadState {
+    if thread_state & (THREAD_DEAD | THREAD_PRESTART) != 0 {
+        ThreadState::Inactive
+    } else if thread_state & THREAD_SUSPENDED != 0 {
+        ThreadState::Suspended
+    } else if thread_state & THREAD_PENDING != 0 {
+        ThreadState::Blocked
+    } else {
+        ThreadState::Ready
+    }
+}
+
+impl RtosAwareness for Zephyr {
+    fn name(&self) -> &'static str {
+        "Zephyr"
+    }
+
+    fn threads(
+        &self,
+        _debug_info: &DebugInfo,
+        memory: &mut dyn MemoryInterface,
+        registers: &DebugRegisters,
+    ) -> Result<Vec<RtosThread>, DebugError> {
+        // The current thread of the first CPU.
+        let cpu = self.kernel + self.z_kernel.offset_or("cpus", 0);
+        let current = read_pointer(memory, cpu + self.cpu.offset_or("current", 8))?;
+
+        let next_thread = self.thread.offset_or("next_thread", 0);
+        let mut threads = Vec::new();
+        let mut address =
+            read_pointer(memory, self.kernel + self.z_kernel.offset_or("threads", 0))?;
+        while address != 0 && threads.len() < MAX_THREADS {
+            threads.push(self.thread(memory, registers, address, address == current)?);
+            address = read_pointer(memory, address + next_thread)?;
+        }
+
+        Ok(threads)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case(0x00 => ThreadState::Ready; "ready")]
+    #[test_case(0x80 => ThreadState::Ready; "queued")]
+    #[test_case(0x02 => ThreadState::Blocked; "pending")]
+    #[test_case(0x12 => ThreadState::Suspended; "suspended while pending")]
+    #[test_case(0x04 => ThreadState::Inactive; "not started")]
+    #[test_case(0x08 => ThreadState::Inactive; "dead")]
+    fn thread_state(bits: u8) -> ThreadState {
+        thread_state_from_bits(bits)
+    }
+}
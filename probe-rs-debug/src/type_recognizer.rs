@@ -0,0 +1,281 @@
+use crate::{
+    DebugInfo, Variable, VariableCache, VariableName, VariableValue, stack_frame::StackFrameInfo,
+    unit_info::read_unsigned,
+};
+use probe_rs::MemoryInterface;
+use std::collections::VecDeque;
+
+/// Strings longer than this are truncated in summaries, to avoid reading large amounts of memory
+/// because of a corrupted length.
+const MAX_STRING_LENGTH: u64 = 200;
+
+/// Fields are searched at most this many levels below a variable.
+const MAX_FIELD_DEPTH: usize = 6;
+
+/// Summarizes variables of a recognized type in a single line, e.g. the content of a `String`.
+///
+/// The summary is shown as the value of the variable, its fields can still be expanded.
+pub trait TypeRecognizer {
+    /// Returns `true` if variables of the type `type_name` are summarized by this recognizer.
+    fn recognizes(&self, type_name: &str) -> bool;
+
+    /// Returns the summary of `variable`, or `None` to keep the default rendering.
+    fn summarize(&self, variable: &Variable, context: &mut RecognizerContext<'_>)
+    -> Option<String>;
+}
+
+/// Access to the fields of a variable and the memory of the target, for a [`TypeRecognizer`].
+pub struct RecognizerContext<'a> {
+    debug_info: &'a DebugInfo,
+    memory: &'a mut dyn MemoryInterface,
+    cache: &'a mut VariableCache,
+    frame_info: StackFrameInfo<'a>,
+}
+
+impl RecognizerContext<'_> {
+    /// Returns the fields of `variable`, resolving them on demand.
+    pub fn fields(&mut self, variable: &Variable) -> Vec<Variable> {
+        if let Err(error) = self.debug_info.cache_deferred_variables(
+            self.cache,
+            self.memory,
+            &mut variable.clone(),
+            self.frame_info,
+        ) {
+            tracing::debug!("Failed to resolve the fields of {}: {error}", variable.name);
+        }
+
+        self.cache
+            .get_children(variable.variable_key())
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the field `name` of `variable`.
+    pub fn field(&mut self, variable: &Variable, name: &str) -> Option<Variable> {
+        self.fields(variable).into_iter().find(
+            |field| matches!(&field.name, VariableName::Named(field_name) if field_name == name),
+        )
+    }
+
+    /// Searches for the field `name` in `variable` and the fields it contains, closest first.
+    ///
+    /// This is useful for types whose internal layout changes between versions of a library,
+    /// e.g. the length of a `Vec`.
+    pub fn find_field(&mut self, variable: &Variable, name: &str) -> Option<Variable> {
+        let mut queue = VecDeque::from([(variable.clone(), 0)]);
+        while let Some((parent, depth)) = queue.pop_front() {
+            for field in self.fields(&parent) {
+                if matches!(&field.name, VariableName::Named(field_name) if field_name == name) {
+                    return Some(field);
+                }
+                if depth + 1 < MAX_FIELD_DEPTH {
+                    queue.push_back((field, depth + 1));
+                }
+            }
+        }
+        None
+    }
+
+    /// Reads the raw value of an integer or pointer variable, e.g. a length or a data pointer.
+    pub fn read_unsigned(&mut self, variable: &Variable) -> Option<u64> {
+        read_unsigned(variable, self.memory, self.debug_info.endianness)
+    }
+
+    /// Reads `length` bytes of target memory, starting at `address`.
+    pub fn read_bytes(&mut self, address: u64, length: usize) -> Option<Vec<u8>> {
+        let mut bytes = vec![0; length];
+        self.memory.read(address, &mut bytes).ok()?;
+        Some(bytes)
+    }
+
+    /// Reads a UTF-8 string of `length` bytes at `address`, truncated to a reasonable length.
+    pub fn read_string(&mut self, address: u64, length: u64) -> Option<String> {
+        if length > MAX_STRING_LENGTH {
+            tracing::warn!(
+                "Very long string ({} bytes), truncating to {} bytes.",
+                length,
+                MAX_STRING_LENGTH
+            );
+        }
+        let bytes = self.read_bytes(address, length.min(MAX_STRING_LENGTH) as usize)?;
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+/// The registered [`TypeRecognizer`]s of a [`DebugInfo`].
+///
+/// The built-in recognizers summarize `&str`, `String` and `Vec`. Recognizers registered with
+/// [`TypeRecognizers::register`] take precedence over the built-in ones.
+pub struct TypeRecognizers {
+    recognizers: Vec<Box<dyn TypeRecognizer>>,
+}
+
+impl Default for TypeRecognizers {
+    fn default() -> Self {
+        Self {
+            recognizers: vec![
+                Box::new(StrRecognizer),
+                Box::new(StringRecognizer),
+                Box::new(VecRecognizer),
+            ],
+        }
+    }
+}
+
+impl TypeRecognizers {
+    /// Registers a recognizer, which is used before all previously registered recognizers.
+    pub fn register(&mut self, recognizer: impl TypeRecognizer + 'static) {
+        self.recognizers.insert(0, Box::new(recognizer));
+    }
+
+    /// Removes all recognizers, including the built-in ones.
+    pub fn clear(&mut self) {
+        self.recognizers.clear();
+    }
+
+    /// Sets the value of `variable` to the summary of the first recognizer for its type.
+    pub(crate) fn summarize(
+        &self,
+        type_name: &str,
+        variable: &mut Variable,
+        debug_info: &DebugInfo,
+        memory: &mut dyn MemoryInterface,
+        cache: &mut VariableCache,
+        frame_info: StackFrameInfo<'_>,
+    ) {
+        if !variable.memory_location.valid() {
+            return;
+        }
+
+        let Some(recognizer) = self
+            .recognizers
+            .iter()
+            .find(|recognizer| recognizer.recognizes(type_name))
+        else {
+            return;
+        };
+
+        let mut context = RecognizerContext {
+            debug_info,
+            memory,
+            cache,
+            frame_info,
+        };
+        if let Some(summary) = recognizer.summarize(variable, &mut context) {
+            variable.value = VariableValue::Valid(summary);
+        }
+    }
+}
+
+/// Summarizes a `&str` with its content.
+struct StrRecognizer;
+
+impl TypeRecognizer for StrRecognizer {
+    fn recognizes(&self, type_name: &str) -> bool {
+        type_name == "&str"
+    }
+
+    fn summarize(
+        &self,
+        variable: &Variable,
+        context: &mut RecognizerContext<'_>,
+    ) -> Option<String> {
+        let length = context.field(variable, "length")?;
+        let length = context.read_unsigned(&length)?;
+        let data_ptr = context.field(variable, "data_ptr")?;
+        let address = context.read_unsigned(&data_ptr)?;
+        context.read_string(address, length)
+    }
+}
+
+/// Summarizes a `String` with its content.
+struct StringRecognizer;
+
+impl TypeRecognizer for StringRecognizer {
+    fn recognizes(&self, type_name: &str) -> bool {
+        matches!(type_name, "String" | "alloc::string::String")
+    }
+
+    fn summarize(
+        &self,
+        variable: &Variable,
+        context: &mut RecognizerContext<'_>,
+    ) -> Option<String> {
+        let length = context.find_field(variable, "len")?;
+        let length = context.read_unsigned(&length)?;
+        let pointer = context.find_field(variable, "pointer")?;
+        let address = context.read_unsigned(&pointer)?;
+        context.read_string(address, length)
+    }
+}
+
+/// Summarizes a `Vec` with its length and capacity.
+struct VecRecognizer;
+
+impl TypeRecognizer for VecRecognizer {
+    fn recognizes(&self, type_name: &str) -> bool {
+        type_name.starts_with("Vec<") || type_name.starts_with("alloc::vec::Vec<")
+    }
+
+    fn summarize(
+        &self,
+        variable: &Variable,
+        context: &mut RecognizerContext<'_>,
+    ) -> Option<String> {
+        let length = context.field(variable, "len")?;
+        let length = context.read_unsigned(&length)?;
+        match context
+            .find_field(variable, "cap")
+            .and_then(|capacity| context.read_unsigned(&capacity))
+        {
+            Some(capacity) => Some(format!("len: {length}, cap: {capacity}")),
+            None => Some(format!("len: {length}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Fixed;
+
+    impl TypeRecognizer for Fixed {
+        fn recognizes(&self, type_name: &str) -> bool {
+            type_name == "String"
+        }
+
+        fn summarize(&self, _: &Variable, _: &mut RecognizerContext<'_>) -> Option<String> {
+            Some("fixed".to_string())
+        }
+    }
+
+    fn recognizer_index(recognizers: &TypeRecognizers, type_name: &str) -> Option<usize> {
+        recognizers
+            .recognizers
+            .iter()
+            .position(|recognizer| recognizer.recognizes(type_name))
+    }
+
+    #[test]
+    fn built_in_recognizers() {
+        let recognizers = TypeRecognizers::default();
+
+        assert_eq!(recognizer_index(&recognizers, "&str"), Some(0));
+        assert_eq!(recognizer_index(&recognizers, "String"), Some(1));
+        assert_eq!(
+            recognizer_index(&recognizers, "Vec<u8, alloc::alloc::Global>"),
+            Some(2)
+        );
+        assert_eq!(recognizer_index(&recognizers, "VecDeque<u8>"), None);
+    }
+
+    #[test]
+    fn registered_recognizers_take_precedence() {
+        let mut recognizers = TypeRecognizers::default();
+        recognizers.register(Fixed);
+
+        assert_eq!(recognizer_index(&recognizers, "String"), Some(0));
+        assert_eq!(recognizer_index(&recognizers, "&str"), Some(1));
+    }
+}
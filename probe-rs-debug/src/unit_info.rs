@@ -7,7 +7,7 @@ use super::{
 use crate::{language, stack_frame::StackFrameInfo};
 use gimli::{
     AttributeValue, DebugInfoOffset, DebuggingInformationEntry, EvaluationResult, Location,
-    UnitOffset,
+    RunTimeEndian, UnitOffset,
 };
 use probe_rs::MemoryInterface;
 
@@ -339,10 +339,11 @@ impl UnitInfo {
                                 frame_info,
                             )?;
 
+                            // The raw value is needed, because with niche optimizations, the discriminant
+                            // can be a field of another variant, e.g. the pointer of an `Option<&T>`.
                             let variant_part = if discriminant_variable.is_valid() {
-                                discriminant_variable
-                                    .to_string(cache)
-                                    .parse()
+                                read_unsigned(&discriminant_variable, memory, debug_info.endianness)
+                                    .or_else(|| discriminant_variable.to_string(cache).parse().ok())
                                     .unwrap_or(u64::MAX)
                             } else {
                                 u64::MAX
@@ -613,6 +614,18 @@ impl UnitInfo {
                     // At this point we have everything we need (It has updated the parent's `role`) from the
                     // child_variable, so elimnate it before we continue ...
                     cache.remove_cache_entry(child_variable.variable_key)?;
+                    // With niche optimizations, only some variants have a DW_AT_discr_value, and every other
+                    // value of the discriminant (e.g. a non-null pointer) selects the variant without one.
+                    if let VariantRole::VariantPart(discriminant) = parent_variable.role {
+                        if discriminant != u64::MAX
+                            && !self.has_variant_with_discriminant(
+                                child_node.entry().offset(),
+                                discriminant,
+                            )?
+                        {
+                            parent_variable.role = VariantRole::VariantPart(u64::MAX);
+                        }
+                    }
                     self.process_tree(
                         debug_info,
                         child_node,
@@ -642,10 +655,9 @@ impl UnitInfo {
                         )?;
                         if child_variable.is_valid() {
                             if let VariantRole::Variant(discriminant) = child_variable.role {
-                                // Only process the discriminant variants or when we eventually   encounter the default
-                                if parent_variable.role == VariantRole::VariantPart(discriminant)
-                                    || discriminant == u64::MAX
-                                {
+                                // Only process the variant matching the discriminant. The default variant has
+                                // already been selected by the DW_TAG_variant_part, if no other one matches.
+                                if parent_variable.role == VariantRole::VariantPart(discriminant) {
                                     self.process_memory_location(
                                         debug_info,
                                         child_node.entry(),
@@ -663,6 +675,7 @@ impl UnitInfo {
                                         cache,
                                         frame_info,
                                     )?;
+                                    self.remove_dataless_variant_fields(&child_variable, cache)?;
                                     if child_variable.is_valid() {
                                         // Eliminate intermediate DWARF nodes, but keep their children
                                         cache.adopt_grand_children(
@@ -926,7 +939,7 @@ impl UnitInfo {
         variable.role = match node.entry().attr(gimli::DW_AT_discr_value) {
             Ok(Some(discr_value_attr)) => {
                 let attr_value = discr_value_attr.value();
-                let variant = if let Some(const_value) = attr_value.udata_value() {
+                let variant = if let Some(const_value) = discriminant_value(&attr_value) {
                     const_value
                 } else {
                     variable.set_value(VariableValue::Error(format!(
@@ -954,6 +967,81 @@ impl UnitInfo {
         Ok(())
     }
 
+    /// Returns `true` if one of the DW_TAG_variant children of the DW_TAG_variant_part at `variant_part` has a
+    /// DW_AT_discr_value equal to `discriminant`.
+    fn has_variant_with_discriminant(
+        &self,
+        variant_part: UnitOffset,
+        discriminant: u64,
+    ) -> Result<bool, DebugError> {
+        let mut tree = self.unit.entries_tree(Some(variant_part))?;
+        let mut variants = tree.root()?.children();
+        while let Some(variant) = variants.next()? {
+            let entry = variant.entry();
+            if entry.tag() != gimli::DW_TAG_variant {
+                continue;
+            }
+            if let Some(discr_value) = entry.attr_value(gimli::DW_AT_discr_value)? {
+                if discriminant_value(&discr_value) == Some(discriminant) {
+                    return Ok(true);
+                }
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Removes the fields of the members of the selected DW_TAG_variant `variant`, if they are all zero sized, e.g.
+    /// the `()` of `None(())`. A variant without data then has no children.
+    fn remove_dataless_variant_fields(
+        &self,
+        variant: &Variable,
+        cache: &mut VariableCache,
+    ) -> Result<(), DebugError> {
+        let members = cache
+            .get_children(variant.variable_key)
+            .cloned()
+            .collect::<Vec<_>>();
+        for mut member in members {
+            let VariableNodeType::TypeOffset(header_offset, type_offset) =
+                member.variable_node_type
+            else {
+                continue;
+            };
+            if header_offset != self.debug_info_offset()?
+                || !self.is_dataless_struct(type_offset)?
+            {
+                continue;
+            }
+            cache.remove_cache_entry_children(member.variable_key)?;
+            member.variable_node_type = VariableNodeType::DoNotRecurse;
+            cache.update_variable(&member)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if all the DW_TAG_member children of the structure type at `struct_type` are zero sized.
+    fn is_dataless_struct(&self, struct_type: UnitOffset) -> Result<bool, DebugError> {
+        let mut tree = self.unit.entries_tree(Some(struct_type))?;
+        let mut members = tree.root()?.children();
+        while let Some(member) = members.next()? {
+            let entry = member.entry();
+            if entry.tag() != gimli::DW_TAG_member {
+                continue;
+            }
+            let Some(AttributeValue::UnitRef(member_type)) = entry.attr_value(gimli::DW_AT_type)?
+            else {
+                return Ok(false);
+            };
+            if extract_byte_size(&self.unit.entry(member_type)?) != Some(0) {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
     /// Compute the type (base to complex) of a variable. Only base types have values.
     /// Complex types are references to node trees, that require traversal in similar ways to other DIE's like functions.
     /// This means [`extract_type()`][e] will call the recursive [`process_tree()`][p] method to build an integrated
@@ -1310,7 +1398,18 @@ impl UnitInfo {
             memory,
             cache,
             frame_info,
-        )
+        )?;
+
+        debug_info.type_recognizers.summarize(
+            &type_name,
+            child_variable,
+            debug_info,
+            memory,
+            cache,
+            frame_info,
+        );
+
+        Ok(())
     }
 
     #[expect(clippy::too_many_arguments)]
@@ -2368,6 +2467,51 @@ fn provide_cfa(
     }
 }
 
+/// The value of a DW_AT_discr_value attribute.
+fn discriminant_value(attr_value: &AttributeValue<GimliReader>) -> Option<u64> {
+    attr_value
+        .udata_value()
+        .or_else(|| attr_value.sdata_value().map(|value| value as u64))
+}
+
+/// Reads the raw value of an integer or pointer variable, masked to its `byte_size`.
+///
+/// This is used for the discriminant of a DW_TAG_variant_part, which is compared against the
+/// DW_AT_discr_value of the variants, and by the type recognizers, e.g. for the length of a `Vec`.
+pub(crate) fn read_unsigned(
+    variable: &Variable,
+    memory: &mut dyn MemoryInterface,
+    endianness: RunTimeEndian,
+) -> Option<u64> {
+    let byte_size = variable
+        .byte_size
+        .filter(|byte_size| (1..=8).contains(byte_size))? as usize;
+
+    let value: u64 = match variable.memory_location {
+        VariableLocation::RegisterValue(value) => value.try_into().ok()?,
+        _ => {
+            let address = variable.memory_location.memory_address().ok()?;
+            let mut buff = [0u8; 8];
+            match endianness {
+                RunTimeEndian::Little => {
+                    memory.read(address, &mut buff[..byte_size]).ok()?;
+                    u64::from_le_bytes(buff)
+                }
+                RunTimeEndian::Big => {
+                    memory.read(address, &mut buff[8 - byte_size..]).ok()?;
+                    u64::from_be_bytes(buff)
+                }
+            }
+        }
+    };
+
+    Some(if byte_size == 8 {
+        value
+    } else {
+        value & ((1 << (byte_size * 8)) - 1)
+    })
+}
+
 /// Reads memory requested by the DWARF resolver.
 fn read_memory(
     size: u8,
@@ -2435,3 +2579,102 @@ impl RangeExt for gimli::Range {
         self.begin <= addr && addr < self.end
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use probe_rs::{RegisterValue, test::MockMemory};
+
+    fn variable(location: VariableLocation, byte_size: u64) -> Variable {
+        let mut variable = Variable::new(None);
+        variable.memory_location = location;
+        variable.byte_size = Some(byte_size);
+        variable
+    }
+
+    #[test]
+    fn option_reference_niche() {
+        // `Option<&T>` uses the null pointer as the niche for `None`, its discriminant is the
+        // pointer itself. Any other value selects the `Some` variant.
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, vec![0, 0, 0, 0]);
+        memory.add_range(0x2000_0004, vec![0x10, 0x00, 0x00, 0x20]);
+
+        let none = variable(VariableLocation::Address(0x2000_0000), 4);
+        let some = variable(VariableLocation::Address(0x2000_0004), 4);
+
+        assert_eq!(
+            read_unsigned(&none, &mut memory, RunTimeEndian::Little),
+            Some(0)
+        );
+        assert_eq!(
+            read_unsigned(&some, &mut memory, RunTimeEndian::Little),
+            Some(0x2000_0010)
+        );
+    }
+
+    #[test]
+    fn niche_is_masked_to_its_size() {
+        // `Option<bool>` stores `None` as 2 in the byte of the `bool`, the bytes after it belong
+        // to other fields.
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, vec![2, 0xff, 0xff, 0xff]);
+
+        let discriminant = variable(VariableLocation::Address(0x2000_0000), 1);
+
+        assert_eq!(
+            read_unsigned(&discriminant, &mut memory, RunTimeEndian::Little),
+            Some(2)
+        );
+        assert_eq!(
+            read_unsigned(&discriminant, &mut memory, RunTimeEndian::Big),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn big_endian_niche() {
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, vec![0x20, 0x00, 0x00, 0x10, 0xff, 0xff]);
+
+        let pointer = variable(VariableLocation::Address(0x2000_0000), 4);
+        let half_word = variable(VariableLocation::Address(0x2000_0000), 2);
+
+        assert_eq!(
+            read_unsigned(&pointer, &mut memory, RunTimeEndian::Big),
+            Some(0x2000_0010)
+        );
+        assert_eq!(
+            read_unsigned(&half_word, &mut memory, RunTimeEndian::Big),
+            Some(0x2000)
+        );
+    }
+
+    #[test]
+    fn register_discriminant() {
+        let mut memory = MockMemory::new();
+        let discriminant = variable(
+            VariableLocation::RegisterValue(RegisterValue::U32(0x1234_5602)),
+            1,
+        );
+
+        assert_eq!(
+            read_unsigned(&discriminant, &mut memory, RunTimeEndian::Little),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn unsupported_sizes() {
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, vec![0; 16]);
+
+        for byte_size in [0, 9, 16] {
+            let value = variable(VariableLocation::Address(0x2000_0000), byte_size);
+            assert_eq!(
+                read_unsigned(&value, &mut memory, RunTimeEndian::Little),
+                None
+            );
+        }
+    }
+}
@@ -1,7 +1,10 @@
 use std::collections::HashMap;
 
-use super::memory::MemoryRegion;
-use crate::{CoreType, serialize::hex_option};
+use super::memory::{AddressAlias, MemoryRegion};
+use crate::{
+    CoreType, DebugDescription, DebugFreezeBit, OptionBytes, ResetStrategy, Watchdog,
+    serialize::hex_option,
+};
 use serde::{Deserialize, Serialize};
 
 /// Represents a DAP scan chain element.
@@ -108,6 +111,33 @@ pub struct Chip {
     // TODO: rename to default_platform
     #[serde(default)]
     pub default_binary_format: Option<String>,
+    /// The option bytes of the chip, if they can be modified.
+    #[serde(default)]
+    pub option_bytes: Option<OptionBytes>,
+    /// Address ranges that alias other memory, e.g. the boot alias of the flash.
+    ///
+    /// Loadable data at an aliased address is programmed to the physical address.
+    #[serde(default)]
+    pub address_aliases: Vec<AddressAlias>,
+    /// The [UF2](https://github.com/microsoft/uf2) family IDs of the chip.
+    ///
+    /// Only the data for these families is programmed from UF2 files that contain several images.
+    #[serde(default)]
+    pub uf2_family_ids: Vec<u32>,
+    /// The debug sequences of the chip, from the debug description of its CMSIS-Pack.
+    #[serde(default)]
+    pub debug_description: Option<DebugDescription>,
+    /// The bits that stop peripherals, like watchdogs and timers, while a core is halted.
+    #[serde(default)]
+    pub debug_freeze: Vec<DebugFreezeBit>,
+    /// The ways to reset the chip, in the order they are tried until one succeeds.
+    ///
+    /// If empty, the `ResetSystem` debug sequence is used.
+    #[serde(default)]
+    pub reset_strategies: Vec<ResetStrategy>,
+    /// The watchdogs that have to be serviced while the chip is flashed.
+    #[serde(default)]
+    pub watchdogs: Vec<Watchdog>,
 }
 
 impl Chip {
@@ -131,6 +161,13 @@ impl Chip {
             rtt_scan_ranges: None,
             jtag: None,
             default_binary_format: None,
+            option_bytes: None,
+            address_aliases: vec![],
+            uf2_family_ids: vec![],
+            debug_description: None,
+            debug_freeze: vec![],
+            reset_strategies: vec![],
+            watchdogs: vec![],
         }
     }
 
@@ -205,6 +242,12 @@ pub struct ArmCoreAccessOptions {
     /// Required in ARMv8-A
     #[serde(serialize_with = "hex_option")]
     pub cti_base: Option<u64>,
+    /// The size of the address range in bytes in which the TAR of the memory AP auto-increments.
+    ///
+    /// Memory transfers are split at the boundaries of this range. It must be a power of two of
+    /// at least 1 KiB, which is the default and the minimum the ADI specification guarantees.
+    #[serde(serialize_with = "hex_option")]
+    pub tar_autoincrement_size: Option<u64>,
 
     /// The JTAG TAP index of the core's debug module
     pub jtag_tap: Option<usize>,
@@ -27,6 +27,12 @@ pub enum ChipDetectionMethod {
 
     /// Infineon PSOC silicon ID chip detection information.
     InfineonPsocSiid(InfineonPsocSiidDetection),
+
+    /// RISC-V IDCODE and CSR-based chip detection information.
+    RiscvId(RiscvIdDetection),
+
+    /// Xtensa IDCODE-based chip detection information.
+    XtensaId(XtensaIdDetection),
 }
 
 impl ChipDetectionMethod {
@@ -83,6 +89,24 @@ impl ChipDetectionMethod {
             None
         }
     }
+
+    /// Returns the RISC-V IDCODE detection information if available.
+    pub fn as_riscv_id(&self) -> Option<&RiscvIdDetection> {
+        if let Self::RiscvId(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the Xtensa IDCODE detection information if available.
+    pub fn as_xtensa_id(&self) -> Option<&XtensaIdDetection> {
+        if let Self::XtensaId(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
 }
 
 /// Microchip ATSAM chip detection information when the device contains a DSU.
@@ -185,3 +209,65 @@ pub struct InfineonPsocSiidDetection {
     #[serde(deserialize_with = "maps_duplicate_key_is_error::deserialize")]
     pub silicon_ids: IndexMap<u16, String>,
 }
+
+/// RISC-V chip detection information.
+///
+/// A chip matches if the IDCODE of its debug module, and the values of the listed CSRs match.
+/// The variant is then selected by the value of a memory mapped register, e.g. a device ID or
+/// flash size register.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RiscvIdDetection {
+    /// Debug module IDCODE. The version in bits \[31:28\] is ignored.
+    #[serde(serialize_with = "hex_u_int")]
+    pub idcode: u32,
+
+    /// CSR address => expected value, e.g. of `mvendorid` (0xF11), `marchid` (0xF12), `mimpid`
+    /// (0xF13) or vendor specific CSRs.
+    #[serde(default)]
+    #[serde(serialize_with = "hex_keys_indexmap")]
+    #[serde(deserialize_with = "maps_duplicate_key_is_error::deserialize")]
+    pub csrs: IndexMap<u16, u32>,
+
+    /// Address of the register which identifies the variant.
+    #[serde(serialize_with = "hex_u_int")]
+    pub variant_address: u64,
+
+    /// The bits of the variant register which identify the variant.
+    #[serde(default = "all_bits", serialize_with = "hex_u_int")]
+    pub variant_mask: u32,
+
+    /// Masked variant register value => Target name.
+    #[serde(serialize_with = "hex_keys_indexmap")]
+    #[serde(deserialize_with = "maps_duplicate_key_is_error::deserialize")]
+    pub variants: IndexMap<u32, String>,
+}
+
+/// Xtensa chip detection information.
+///
+/// A chip matches if the IDCODE of its debug module matches. The variant is then selected by the
+/// value of a memory mapped register.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct XtensaIdDetection {
+    /// Debug module IDCODE. The version in bits \[31:28\] is ignored.
+    #[serde(serialize_with = "hex_u_int")]
+    pub idcode: u32,
+
+    /// Address of the register which identifies the variant.
+    #[serde(serialize_with = "hex_u_int")]
+    pub variant_address: u64,
+
+    /// The bits of the variant register which identify the variant.
+    #[serde(default = "all_bits", serialize_with = "hex_u_int")]
+    pub variant_mask: u32,
+
+    /// Masked variant register value => Target name.
+    #[serde(serialize_with = "hex_keys_indexmap")]
+    #[serde(deserialize_with = "maps_duplicate_key_is_error::deserialize")]
+    pub variants: IndexMap<u32, String>,
+}
+
+fn all_bits() -> u32 {
+    u32::MAX
+}
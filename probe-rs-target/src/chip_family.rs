@@ -1,7 +1,7 @@
 use crate::memory::RegionMergeIterator as _;
 use crate::serialize::hex_jep106_option;
-use crate::{CoreAccessOptions, chip_detection::ChipDetectionMethod};
-use crate::{MemoryRange, MemoryRegion};
+use crate::{CoreAccessOptions, DebugFreezeBit, chip_detection::ChipDetectionMethod};
+use crate::{MemoryRange, MemoryRegion, ResetStrategy, Watchdog};
 
 use super::chip::Chip;
 use super::flash_algorithm::RawFlashAlgorithm;
@@ -206,6 +206,14 @@ pub struct ChipFamily {
     /// - `None` if this was not generated from a pack file, or has been modified since it was generated.
     #[serde(default)]
     pub pack_file_release: Option<String>,
+    /// The bits that stop peripherals while a core is halted, for all variants without their own
+    /// [`Chip::debug_freeze`] bits.
+    #[serde(default)]
+    pub debug_freeze: Vec<DebugFreezeBit>,
+    /// The watchdogs that have to be serviced while a chip is flashed, for all variants without
+    /// their own [`Chip::watchdogs`].
+    #[serde(default)]
+    pub watchdogs: Vec<Watchdog>,
     /// This vector holds all the variants of the family.
     pub variants: Vec<Chip>,
     /// This vector holds all available algorithms.
@@ -227,10 +235,15 @@ impl ChipFamily {
     pub fn validate(&self) -> Result<(), String> {
         self.reject_duplicate_target_names()?;
         self.ensure_algorithms_exist()?;
+        self.ensure_algorithm_cores_exist()?;
         self.ensure_at_least_one_core()?;
         self.reject_incorrect_core_access_options()?;
         self.validate_memory_regions()?;
         self.validate_rtt_scan_regions()?;
+        self.validate_option_bytes()?;
+        self.validate_address_aliases()?;
+        self.validate_reset_strategies()?;
+        self.validate_watchdogs()?;
 
         Ok(())
     }
@@ -276,6 +289,32 @@ impl ChipFamily {
         Ok(())
     }
 
+    /// Make sure the cores that the algorithms of a variant have to run on exist on the variant.
+    fn ensure_algorithm_cores_exist(&self) -> Result<(), String> {
+        for variant in &self.variants {
+            for algorithm in self
+                .flash_algorithms
+                .iter()
+                .filter(|algorithm| variant.flash_algorithms.contains(&algorithm.name))
+            {
+                let Some(core_index) = algorithm.core_index else {
+                    continue;
+                };
+
+                if core_index >= variant.cores.len() {
+                    return Err(format!(
+                        "The flash algorithm {algorithm_name} has to run on core {core_index}, \
+                        which does not exist on the chip variant {chip_name}.",
+                        algorithm_name = algorithm.name,
+                        chip_name = variant.name,
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     // Check that there is at least one core.
     fn ensure_at_least_one_core(&self) -> Result<(), String> {
         for variant in &self.variants {
@@ -339,6 +378,15 @@ impl ChipFamily {
                         if core.core_type == CoreType::Armv8a && options.cti_base.is_none() {
                             return Err(format!("Core {} requires setting cti_base", core.name));
                         }
+
+                        if let Some(size) = options.tar_autoincrement_size {
+                            if !size.is_power_of_two() || size < 0x400 {
+                                return Err(format!(
+                                    "The tar_autoincrement_size {size:#x} of core {} must be a power of two of at least 0x400",
+                                    core.name
+                                ));
+                            }
+                        }
                     }
                     _ => {}
                 }
@@ -386,6 +434,145 @@ impl ChipFamily {
         Ok(())
     }
 
+    /// Ensures that the option byte fields fit into the option bytes of the chip.
+    fn validate_option_bytes(&self) -> Result<(), String> {
+        for variant in &self.variants {
+            let Some(option_bytes) = &variant.option_bytes else {
+                continue;
+            };
+
+            if option_bytes.size % 4 != 0 {
+                return Err(format!(
+                    "The option bytes of {} are not a multiple of 4 bytes",
+                    variant.name
+                ));
+            }
+
+            for field in &option_bytes.fields {
+                if field.offset % 4 != 0
+                    || field.offset >= option_bytes.size
+                    || field.bit_width == 0
+                    || field.bit_offset as u32 + field.bit_width as u32 > 32
+                {
+                    return Err(format!(
+                        "The option byte field {} of {} is outside of the option bytes",
+                        field.name, variant.name
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ensures that address aliases are not empty and do not overlap each other.
+    fn validate_address_aliases(&self) -> Result<(), String> {
+        for variant in &self.variants {
+            for (i, alias) in variant.address_aliases.iter().enumerate() {
+                if alias.range.is_empty() {
+                    return Err(format!(
+                        "The address alias {:#010x?} of {} is empty",
+                        alias.range, variant.name
+                    ));
+                }
+
+                if variant.address_aliases[..i]
+                    .iter()
+                    .any(|other| other.range.intersects_range(&alias.range))
+                {
+                    return Err(format!(
+                        "The address alias {:#010x?} of {} overlaps another alias",
+                        alias.range, variant.name
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ensures that reset strategies are listed once, and that the cores support them.
+    fn validate_reset_strategies(&self) -> Result<(), String> {
+        for variant in &self.variants {
+            for (i, strategy) in variant.reset_strategies.iter().enumerate() {
+                if variant.reset_strategies[..i].contains(strategy) {
+                    return Err(format!(
+                        "The reset strategy {strategy:?} of {} is listed multiple times",
+                        variant.name
+                    ));
+                }
+
+                if *strategy == ResetStrategy::VectReset
+                    && !variant
+                        .cores
+                        .iter()
+                        .all(|core| matches!(core.core_type, CoreType::Armv7m | CoreType::Armv7em))
+                {
+                    return Err(format!(
+                        "The reset strategy {strategy:?} of {} requires ARMv7-M cores",
+                        variant.name
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ensures that watchdog writes have a valid width and alignment, and that sequences run on
+    /// the target fit the stub that writes them.
+    fn validate_watchdogs(&self) -> Result<(), String> {
+        for variant in &self.variants {
+            let watchdogs = if variant.watchdogs.is_empty() {
+                &self.watchdogs
+            } else {
+                &variant.watchdogs
+            };
+
+            for watchdog in watchdogs {
+                for write in watchdog.disable.iter().chain(&watchdog.refresh) {
+                    if !matches!(write.width, 8 | 16 | 32) {
+                        return Err(format!(
+                            "The watchdog {} of {} writes {} bits, which is not 8, 16 or 32",
+                            watchdog.name, variant.name, write.width
+                        ));
+                    }
+
+                    if write.address % (write.width as u64 / 8) != 0 {
+                        return Err(format!(
+                            "The watchdog {} of {} writes to the unaligned address {:#010x}",
+                            watchdog.name, variant.name, write.address
+                        ));
+                    }
+                }
+
+                if !watchdog.run_on_target {
+                    continue;
+                }
+
+                if !variant
+                    .cores
+                    .iter()
+                    .all(|core| core.core_type.is_cortex_m())
+                {
+                    return Err(format!(
+                        "The watchdog {} of {} can only run on Cortex-M cores",
+                        watchdog.name, variant.name
+                    ));
+                }
+
+                if watchdog.disable.len() > 4 || watchdog.refresh.len() > 4 {
+                    return Err(format!(
+                        "The watchdog {} of {} runs more than 4 writes on the target",
+                        watchdog.name, variant.name
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn validate_rtt_scan_regions(&self) -> Result<(), String> {
         for variant in &self.variants {
             let Some(rtt_scan_ranges) = &variant.rtt_scan_ranges else {
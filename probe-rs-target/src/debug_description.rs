@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+/// The debug sequences of a chip, taken from the debug description of its CMSIS-Pack.
+///
+/// probe-rs interprets these sequences in place of its default Arm debug sequences, unless the
+/// chip has a hand-written sequence implementation.
+///
+/// ref: `<https://open-cmsis-pack.github.io/Open-CMSIS-Pack-Spec/main/html/debug_description.html>`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DebugDescription {
+    /// The code of the `<debugvars>` element, which declares the debug access variables
+    /// available to all sequences.
+    #[serde(default)]
+    pub debug_vars: String,
+    /// The sequences defined for the chip.
+    #[serde(default)]
+    pub sequences: Vec<DebugSequenceDescription>,
+}
+
+impl DebugDescription {
+    /// Returns the sequence with the given name, for the processor `pname`.
+    ///
+    /// A sequence without a `pname` applies to all processors, but is overridden by one for the
+    /// specific processor. Without a `pname`, the first sequence with the name is used if there
+    /// is no generic one.
+    pub fn sequence(&self, name: &str, pname: Option<&str>) -> Option<&DebugSequenceDescription> {
+        let mut candidates = self
+            .sequences
+            .iter()
+            .filter(|sequence| sequence.name == name);
+
+        let specific = pname.and_then(|pname| {
+            candidates
+                .clone()
+                .find(|sequence| sequence.pname.as_deref() == Some(pname))
+        });
+
+        specific
+            .or_else(|| candidates.clone().find(|sequence| sequence.pname.is_none()))
+            .or_else(|| candidates.next().filter(|_| pname.is_none()))
+    }
+}
+
+/// A single `<sequence>` of a debug description.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DebugSequenceDescription {
+    /// The name of the sequence, e.g. `ResetSystem`.
+    pub name: String,
+    /// The processor the sequence applies to, for chips with several processors.
+    #[serde(default)]
+    pub pname: Option<String>,
+    /// A disabled sequence is replaced by an empty one, instead of the default implementation.
+    #[serde(default)]
+    pub disable: bool,
+    /// The body of the sequence.
+    #[serde(default)]
+    pub items: Vec<DebugSequenceItem>,
+}
+
+/// An element of the body of a debug sequence.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DebugSequenceItem {
+    /// A `<block>` of statements.
+    Block {
+        /// Whether the block has to be executed without interruption.
+        #[serde(default)]
+        atomic: bool,
+        /// The code of the block.
+        code: String,
+    },
+    /// A `<control>` element, which executes its items conditionally or in a loop.
+    Control {
+        /// The items are only executed if this expression is not zero.
+        #[serde(default, rename = "if")]
+        if_condition: Option<String>,
+        /// The items are executed repeatedly, while this expression is not zero.
+        #[serde(default, rename = "while")]
+        while_condition: Option<String>,
+        /// The maximum time in microseconds the `while` loop may run, 0 for no limit.
+        #[serde(default)]
+        timeout: u64,
+        /// The body of the control element.
+        #[serde(default)]
+        items: Vec<DebugSequenceItem>,
+    },
+}
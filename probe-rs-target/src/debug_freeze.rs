@@ -0,0 +1,46 @@
+use crate::serialize::hex_u_int;
+use serde::{Deserialize, Serialize};
+
+/// A bit that stops a peripheral while the core is halted, e.g. a bit of the `DBGMCU_APB1_FZ`
+/// register of STM32 devices.
+///
+/// Without it, watchdogs keep counting and reset the chip while it is halted at a breakpoint,
+/// and timers keep running, which makes stepping through timing sensitive code impossible.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DebugFreezeBit {
+    /// The name of the peripheral, e.g. `IWDG` or `TIM2`.
+    pub peripheral: String,
+    /// What kind of peripheral is stopped.
+    pub kind: DebugFreezeKind,
+    /// The address of the 32-bit register that contains the bit.
+    #[serde(serialize_with = "hex_u_int")]
+    pub address: u64,
+    /// The position of the bit in the register.
+    pub bit: u8,
+}
+
+impl DebugFreezeBit {
+    /// Returns the mask of the bit in its register.
+    pub fn mask(&self) -> u32 {
+        1 << self.bit
+    }
+}
+
+/// The kind of a peripheral that can be stopped while the core is halted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DebugFreezeKind {
+    /// An independent or window watchdog.
+    Watchdog,
+    /// A general purpose, advanced or low-power timer.
+    Timer,
+    /// A CAN controller. A stopped controller does not send error frames on the bus.
+    Can,
+    /// The SMBus timeout of an I2C controller.
+    I2c,
+    /// A real time clock.
+    Rtc,
+    /// Any other peripheral.
+    Other,
+}
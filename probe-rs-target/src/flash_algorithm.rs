@@ -69,6 +69,9 @@ pub struct RawFlashAlgorithm {
     /// Address of the `BlankCheck()` entry point. Optional.
     #[serde(serialize_with = "hex_option")]
     pub pc_blank_check: Option<u64>,
+    /// Address of the (non-standard) `Crc32()` entry point. Optional.
+    #[serde(serialize_with = "hex_option")]
+    pub pc_crc: Option<u64>,
     /// The offset from the start of RAM to the data section.
     #[serde(serialize_with = "hex_u_int")]
     pub data_section_offset: u64,
@@ -83,6 +86,12 @@ pub struct RawFlashAlgorithm {
     /// List of cores that can use this algorithm
     #[serde(default)]
     pub cores: Vec<String>,
+    /// The index of the core that has to run this algorithm.
+    ///
+    /// If not set, the algorithm runs on the first core that can access the flash region. The
+    /// other cores are halted while the algorithm runs.
+    #[serde(default)]
+    pub core_index: Option<usize>,
     /// The flash algorithm's stack size, in bytes.
     ///
     /// If not set, probe-rs selects a default value.
@@ -13,10 +13,15 @@
 mod chip;
 pub mod chip_detection;
 mod chip_family;
+mod debug_description;
+mod debug_freeze;
 mod flash_algorithm;
 mod flash_properties;
 mod memory;
+mod option_bytes;
+mod reset;
 pub(crate) mod serialize;
+mod watchdog;
 
 pub use chip::{
     ApAddress, ArmCoreAccessOptions, Chip, Core, CoreAccessOptions, Jtag, RiscvCoreAccessOptions,
@@ -25,9 +30,14 @@ pub use chip::{
 pub use chip_family::{
     Architecture, ChipFamily, CoreType, Endian, InstructionSet, TargetDescriptionSource,
 };
+pub use debug_description::{DebugDescription, DebugSequenceDescription, DebugSequenceItem};
+pub use debug_freeze::{DebugFreezeBit, DebugFreezeKind};
 pub use flash_algorithm::{RawFlashAlgorithm, TransferEncoding};
 pub use flash_properties::FlashProperties;
 pub use memory::{
-    GenericRegion, MemoryAccess, MemoryRange, MemoryRegion, NvmRegion, PageInfo, RamRegion,
-    RegionMergeIterator, SectorDescription, SectorInfo,
+    AddressAlias, GenericRegion, MemoryAccess, MemoryRange, MemoryRegion, NvmRegion, PageInfo,
+    RamRegion, RegionMergeIterator, SectorDescription, SectorInfo,
 };
+pub use option_bytes::{OptionBytes, OptionBytesField, OptionBytesStep};
+pub use reset::ResetStrategy;
+pub use watchdog::{Watchdog, WatchdogWrite};
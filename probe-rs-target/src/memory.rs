@@ -331,6 +331,36 @@ pub struct NvmInfo {
     pub rom_start: u64,
 }
 
+/// Maps an address range that aliases other memory to the physical addresses.
+///
+/// Images are sometimes linked for an alias, e.g. the boot alias of flash at `0x0000_0000`, or
+/// for a tightly coupled memory that is loaded from flash. The image loader uses the aliases to
+/// place such data at the addresses it has to be programmed to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AddressAlias {
+    /// The aliased address range.
+    #[serde(serialize_with = "hex_range")]
+    pub range: Range<u64>,
+    /// The physical address that corresponds to the start of `range`.
+    #[serde(serialize_with = "hex_u_int")]
+    pub target: u64,
+}
+
+impl AddressAlias {
+    /// Translates `range` to physical addresses, if the alias contains the whole range.
+    ///
+    /// Returns `None` if `range` is not contained in the alias, even if it overlaps it partially.
+    pub fn translate(&self, range: &Range<u64>) -> Option<Range<u64>> {
+        if !self.range.contains_range(range) {
+            return None;
+        }
+
+        let start = range.start - self.range.start + self.target;
+        Some(start..start + (range.end - range.start))
+    }
+}
+
 /// Enables the user to do range intersection testing.
 pub trait MemoryRange {
     /// Returns true if `self` contains `range` fully.
@@ -451,6 +481,27 @@ impl MemoryRegion {
 mod test {
     use super::*;
 
+    #[test]
+    fn address_alias_translate() {
+        let alias = AddressAlias {
+            range: 0x0000_0000..0x0010_0000,
+            target: 0x0800_0000,
+        };
+
+        assert_eq!(
+            alias.translate(&(0x0000_0000..0x0000_0100)),
+            Some(0x0800_0000..0x0800_0100)
+        );
+        assert_eq!(
+            alias.translate(&(0x0000_1234..0x0010_0000)),
+            Some(0x0800_1234..0x0810_0000)
+        );
+        assert_eq!(alias.translate(&(0x0010_0000..0x0010_0100)), None);
+        // A range crossing the end of the alias is not translated.
+        assert_eq!(alias.translate(&(0x000F_FF00..0x0010_0100)), None);
+        assert!(alias.range.intersects_range(&(0x000F_FF00..0x0010_0100)));
+    }
+
     #[test]
     fn contains_range1() {
         let range1 = 0..1;
@@ -0,0 +1,147 @@
+use crate::serialize::{hex_option, hex_u_int};
+use serde::{Deserialize, Serialize};
+
+/// The option bytes, or user configuration registers, of a chip.
+///
+/// Option bytes hold settings like the readout protection level, brown-out reset thresholds or
+/// whether the watchdog starts automatically. They are read from memory, and written by
+/// unlocking the flash controller, writing the new values and starting the programming sequence.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OptionBytes {
+    /// The address the option bytes are read from.
+    #[serde(serialize_with = "hex_u_int")]
+    pub address: u64,
+    /// The size of the option bytes in bytes. Must be a multiple of 4.
+    pub size: u32,
+    /// The address the modified option bytes are written to, if it differs from `address`.
+    #[serde(default, serialize_with = "hex_option")]
+    pub write_address: Option<u64>,
+    /// The fields of the option bytes.
+    pub fields: Vec<OptionBytesField>,
+    /// The steps that unlock writing the option bytes.
+    #[serde(default)]
+    pub unlock: Vec<OptionBytesStep>,
+    /// The steps that program the option bytes after the new values were written.
+    #[serde(default)]
+    pub program: Vec<OptionBytesStep>,
+    /// The steps that lock writing the option bytes again.
+    #[serde(default)]
+    pub lock: Vec<OptionBytesStep>,
+}
+
+impl OptionBytes {
+    /// Returns the field with the given name.
+    pub fn field(&self, name: &str) -> Option<&OptionBytesField> {
+        self.fields
+            .iter()
+            .find(|field| field.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A bitfield in the option bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OptionBytesField {
+    /// The name of the field, e.g. `RDP`.
+    pub name: String,
+    /// A description of the field.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// The offset of the 32-bit word that contains the field, relative to the start of the
+    /// option bytes.
+    #[serde(serialize_with = "hex_u_int")]
+    pub offset: u32,
+    /// The position of the lowest bit of the field in the word.
+    pub bit_offset: u8,
+    /// The number of bits of the field.
+    pub bit_width: u8,
+}
+
+impl OptionBytesField {
+    /// Returns the mask of the field in its word, before shifting.
+    pub fn mask(&self) -> u32 {
+        if self.bit_width >= 32 {
+            u32::MAX
+        } else {
+            (1 << self.bit_width) - 1
+        }
+    }
+
+    /// Extracts the value of the field from `word`.
+    pub fn extract(&self, word: u32) -> u32 {
+        (word >> self.bit_offset) & self.mask()
+    }
+
+    /// Replaces the value of the field in `word` with `value`.
+    pub fn insert(&self, word: u32, value: u32) -> u32 {
+        let mask = self.mask() << self.bit_offset;
+        (word & !mask) | ((value << self.bit_offset) & mask)
+    }
+}
+
+/// A register access that is part of the sequence to write the option bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum OptionBytesStep {
+    /// Writes `value` to the 32-bit register at `address`.
+    Write {
+        /// The address of the register.
+        #[serde(serialize_with = "hex_u_int")]
+        address: u64,
+        /// The value to write.
+        #[serde(serialize_with = "hex_u_int")]
+        value: u32,
+    },
+    /// Sets the bits of `mask` in the 32-bit register at `address` to the bits in `value`, and
+    /// leaves the other bits unchanged.
+    Modify {
+        /// The address of the register.
+        #[serde(serialize_with = "hex_u_int")]
+        address: u64,
+        /// The bits to change.
+        #[serde(serialize_with = "hex_u_int")]
+        mask: u32,
+        /// The new value of the bits in `mask`.
+        #[serde(serialize_with = "hex_u_int")]
+        value: u32,
+    },
+    /// Waits until the bits of `mask` in the 32-bit register at `address` equal the bits in
+    /// `value`.
+    WaitFor {
+        /// The address of the register.
+        #[serde(serialize_with = "hex_u_int")]
+        address: u64,
+        /// The bits to check.
+        #[serde(serialize_with = "hex_u_int")]
+        mask: u32,
+        /// The expected value of the bits in `mask`.
+        #[serde(serialize_with = "hex_u_int")]
+        value: u32,
+        /// How long to wait before giving up, in milliseconds.
+        timeout_ms: u32,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_insert_and_extract() {
+        let field = OptionBytesField {
+            name: "RDP".to_string(),
+            description: None,
+            offset: 0,
+            bit_offset: 8,
+            bit_width: 8,
+        };
+
+        let word = field.insert(0xFFFF_AAFF, 0xBB);
+        assert_eq!(word, 0xFFFF_BBFF);
+        assert_eq!(field.extract(word), 0xBB);
+
+        // Values wider than the field are truncated.
+        assert_eq!(field.insert(0, 0x1CC), 0xCC00);
+    }
+}
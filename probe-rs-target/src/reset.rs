@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+/// A way to reset the cores of a chip.
+///
+/// The default reset of a chip runs the `ResetSystem` debug sequence, which requests a system
+/// reset with AIRCR.SYSRESETREQ on Cortex-M cores. On some chips this also resets the debug logic,
+/// or the reset request is ignored, and on others nRST is not connected to the debug header. These
+/// chips list the strategies that work, in the order they should be tried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResetStrategy {
+    /// Run the `ResetSystem` debug sequence of the chip.
+    Sequence,
+    /// Request a system reset with AIRCR.SYSRESETREQ.
+    SysResetReq,
+    /// Reset only the core with AIRCR.VECTRESET, leaving peripherals and the debug logic untouched.
+    ///
+    /// This is only available on ARMv7-M cores.
+    VectReset,
+    /// Pulse the nRST line of the debug connector.
+    HardwareReset,
+}
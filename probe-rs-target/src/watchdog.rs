@@ -0,0 +1,43 @@
+use crate::serialize::hex_u_int;
+use serde::{Deserialize, Serialize};
+
+/// A watchdog that can reset the chip while it is being flashed, e.g. because it is enabled out of
+/// reset or was started by the firmware and cannot be stopped.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Watchdog {
+    /// The name of the watchdog, e.g. `WDOG` or `IWDG`.
+    pub name: String,
+    /// The register writes that disable the watchdog. Empty if it cannot be disabled.
+    #[serde(default)]
+    pub disable: Vec<WatchdogWrite>,
+    /// The register writes that refresh the watchdog.
+    #[serde(default)]
+    pub refresh: Vec<WatchdogWrite>,
+    /// Perform the writes with a small stub running on the core, instead of with the debugger.
+    ///
+    /// This is required for unlock sequences that must be written within a few bus cycles, like
+    /// the one of the Kinetis `WDOG`. Only supported on Cortex-M cores, with at most 4 writes per
+    /// sequence.
+    #[serde(default)]
+    pub run_on_target: bool,
+}
+
+/// A register write that is part of a watchdog sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatchdogWrite {
+    /// The address of the register.
+    #[serde(serialize_with = "hex_u_int")]
+    pub address: u64,
+    /// The value to write.
+    #[serde(serialize_with = "hex_u_int")]
+    pub value: u32,
+    /// The width of the register in bits, 8, 16 or 32.
+    #[serde(default = "default_width")]
+    pub width: u8,
+}
+
+fn default_width() -> u8 {
+    32
+}
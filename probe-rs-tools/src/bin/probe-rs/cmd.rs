@@ -1,24 +1,32 @@
 pub mod attach;
 pub mod benchmark;
+pub mod boundary_scan;
 pub mod cargo_embed;
 pub mod cargo_flash;
 pub mod chip;
 pub mod complete;
+pub mod coredump;
 pub mod dap_server;
 pub mod debug;
 pub mod download;
 pub mod erase;
+pub mod flash_algo;
 pub mod gdb_server;
 pub mod info;
 pub mod itm;
 pub mod list;
 pub mod mi;
+pub mod option_bytes;
+pub mod pins;
 pub mod profile;
 pub mod read;
 pub mod reset;
 pub mod run;
 #[cfg(feature = "remote")]
 pub mod serve;
+pub mod swd_trace;
+pub mod test;
 pub mod trace;
 pub mod verify;
+pub mod watch;
 pub mod write;
@@ -3,6 +3,7 @@ use time::UtcOffset;
 use crate::rpc::client::RpcClient;
 use crate::rpc::functions::monitor::{MonitorMode, MonitorOptions};
 use crate::util::cli::{self, connect_target_output_files, rtt_client};
+use crate::util::rtt::sinks::RttSinks;
 
 #[derive(clap::Parser)]
 #[group(skip)]
@@ -13,10 +14,11 @@ pub struct Cmd {
 
 impl Cmd {
     pub async fn run(self, client: RpcClient, utc_offset: UtcOffset) -> anyhow::Result<()> {
+        let rtt_config = self.run.shared_options.rtt_config();
         let session =
             cli::attach_probe(&client, self.run.shared_options.probe_options, true).await?;
 
-        let rtt_client = rtt_client(
+        let mut rtt_client = rtt_client(
             &session,
             &self.run.shared_options.path,
             match self.run.shared_options.rtt_scan_memory {
@@ -26,8 +28,14 @@ impl Cmd {
             self.run.shared_options.log_format,
             !self.run.shared_options.no_location,
             Some(utc_offset),
+            rtt_config,
         )
         .await?;
+        rtt_client.log_to(RttSinks::from_config(
+            &self.run.shared_options.rtt_log,
+            utc_offset,
+        )?);
+        rtt_client.set_itm_defmt_port(self.run.shared_options.swo.defmt_port);
 
         let mut target_output_files =
             connect_target_output_files(self.run.shared_options.target_output_file).await?;
@@ -43,6 +51,10 @@ impl Cmd {
                 catch_reset: !self.run.run_options.no_catch_reset,
                 catch_hardfault: !self.run.run_options.no_catch_hardfault,
                 rtt_client: Some(client_handle),
+                snapshot: self.run.run_options.snapshot_options(),
+                semihosting_root: self.run.run_options.semihosting_root_path(),
+                swo: self.run.shared_options.swo.clone(),
+                apptrace: self.run.shared_options.apptrace,
             },
             self.run.shared_options.always_print_stacktrace,
             &mut target_output_files,
@@ -4,13 +4,25 @@ use std::{
 };
 
 use anyhow::Context;
-use probe_rs::{MemoryInterface, config::Registry, probe::list::Lister};
+use probe_rs::{
+    Core, Session,
+    config::Registry,
+    flashing::DownloadOptions,
+    probe::{
+        bench::{self, AccessWidth},
+        list::Lister,
+    },
+};
 
 use crate::util::common_options::LoadedProbeOptions;
 use crate::util::common_options::ProbeOptions;
 
 const PROBE_SPEEDS: [u32; 10] = [320, 640, 960, 3200, 6400, 9600, 32000, 64000, 96000, 320000];
 const TEST_SIZES: [usize; 5] = [1, 8, 32, 512, 8192];
+/// The number of single reads the latency is averaged over.
+const LATENCY_ITERATIONS: u32 = 100;
+/// The number of bytes downloaded to RAM to measure the download speed.
+const DOWNLOAD_SIZE: usize = 32 * 1024;
 
 #[derive(clap::Parser)]
 pub struct Cmd {
@@ -38,12 +50,12 @@ pub struct Cmd {
     #[clap(long = "max-speed", value_parser= parse_int, default_value="0")]
     max_speed: u32,
 
-    /// Word size for read/write accesses.
+    /// Word sizes for read/write accesses.
     ///
-    /// Set the read/write word size to 8/32/64bits.
+    /// Set the read/write word sizes to 8/16/32/64bits, separated by commas.
     /// Note: not all chips/probes support all sizes. 32bit is a safe default
-    #[clap(long = "word-size", value_parser= parse_int, default_value="32")]
-    word_size: u32,
+    #[clap(long = "word-size", value_parser= parse_int, value_delimiter = ',', default_values_t = [8, 16, 32])]
+    word_sizes: Vec<u32>,
 
     /// Number of times to run each test
     ///
@@ -52,6 +64,11 @@ pub struct Cmd {
     /// both reduce the amount of jitter, and also quantify it (via standard deviation calcs)
     #[clap(long = "iterations", value_parser= parse_usize, default_value="5")]
     iterations: usize,
+
+    /// Also measure how fast data is downloaded to RAM at the start address, using the flash
+    /// loader.
+    #[clap(long = "download")]
+    download: bool,
 }
 
 fn parse_usize(src: &str) -> Result<usize, ParseIntError> {
@@ -66,26 +83,28 @@ fn parse_hex(src: &str) -> Result<u64, ParseIntError> {
     u64::from_str_radix(src.trim_start_matches("0x"), 16)
 }
 
-#[derive(Debug)]
-/// Provide different data arrays for each read/write stride size
-enum DataType {
-    U8(Vec<u8>, Vec<u8>),
-    U32(Vec<u32>, Vec<u32>),
-    U64(Vec<u64>, Vec<u64>),
+/// The results of the benchmarks at a single probe speed.
+struct SpeedResults {
+    speed: u32,
+    /// The average duration of a single 32-bit read.
+    latency: Duration,
+    accesses: Vec<AccessResults>,
+    /// The download throughput in bytes/s, if measured.
+    download: Option<f64>,
 }
 
-#[derive(Debug)]
-/// Configuration and results for a test run
-struct TestData {
-    address: u64,
-    word_qty: usize,
-    pub data_type: DataType,
+/// The throughput of all iterations of an access width and data length, in bytes/s.
+struct AccessResults {
+    width: AccessWidth,
+    length: usize,
+    read: Vec<f64>,
+    write: Vec<f64>,
 }
 
 impl Cmd {
     pub async fn run(self, registry: &mut Registry, lister: &Lister) -> anyhow::Result<()> {
         let speed = self.common.speed;
-        let common_options = self.common.load(registry)?;
+        let common_options = self.common.clone().load(registry)?;
         let mut max_speed = self.max_speed;
         let mut speeds = vec![];
         // if no max-speed specified, assume the user just wants to use a single speed (as per other cli cmds)
@@ -95,33 +114,35 @@ impl Cmd {
         } else {
             speeds.extend_from_slice(&PROBE_SPEEDS);
         };
+        let widths = self
+            .word_sizes
+            .iter()
+            .map(|bits| {
+                AccessWidth::from_bits(*bits).with_context(|| {
+                    format!("Invalid word size {bits}bit, expected 8, 16, 32 or 64")
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
         // if we can't print basic info, we're probably not going to succeed with testing so bubble up the error
         Cmd::print_info(&common_options, lister).await?;
 
+        let mut results = vec![];
         for speed in speeds
             .iter()
             .filter(|speed| (self.min_speed..=max_speed).contains(*speed))
         {
-            for size in TEST_SIZES {
-                let res = Cmd::benchmark(
-                    &common_options,
-                    lister,
-                    *speed,
-                    size,
-                    self.address,
-                    self.word_size,
-                    self.iterations,
-                )
-                .await;
-                if let Err(e) = res {
-                    println!(
-                        "Test failed for speed {} size {} word_size {}bit - {}",
-                        speed, size, self.word_size, e
-                    )
-                }
+            match self
+                .benchmark_speed(&common_options, lister, *speed, &widths)
+                .await
+            {
+                Ok(Some(speed_results)) => results.push(speed_results),
+                Ok(None) => println!("failed to set speed {speed}"),
+                Err(e) => println!("Test failed for speed {speed} - {e:#}"),
             }
         }
 
+        print_table(&results);
+
         Ok(())
     }
 
@@ -146,207 +167,186 @@ impl Cmd {
         Ok(())
     }
 
-    /// Run a specific benchmark
-    async fn benchmark(
+    /// Run all benchmarks at a specific speed. Returns `None` if the probe doesn't support the
+    /// speed.
+    async fn benchmark_speed(
+        &self,
         common_options: &LoadedProbeOptions<'_>,
         lister: &Lister,
         speed: u32,
-        size: usize,
-        address: u64,
-        word_size: u32,
-        iterations: usize,
-    ) -> Result<(), anyhow::Error> {
+        widths: &[AccessWidth],
+    ) -> anyhow::Result<Option<SpeedResults>> {
         let mut probe = common_options.attach_probe(lister).await?;
         let target = common_options.get_target_selector()?;
-        if probe.set_speed(speed).is_ok() {
-            let mut session = common_options.attach_session(probe, target)?;
-            let mut test = TestData::new(address, word_size, size);
-            println!(
-                "Test: Speed {}, Word size {}bit, Data length {} bytes, Number of iterations {}",
-                speed,
-                word_size,
-                test.data_type.size() * size,
-                iterations
-            );
-            let mut core = session.core(0).context("Failed to attach to core")?;
-            core.halt(Duration::from_millis(100))
-                .context("Halting failed")?;
-
-            let mut read_results = Vec::<f64>::with_capacity(iterations);
-            let mut write_results = Vec::<f64>::with_capacity(iterations);
-            'inner: for _ in 0..iterations {
-                let write_throughput = test.block_write(&mut core)?;
-                let read_throughput = test.block_read(&mut core)?;
-                let verify_success = test.block_verify();
-                if verify_success {
-                    read_results.push(read_throughput);
-                    write_results.push(write_throughput);
-                } else {
-                    eprintln!("Verification failed.");
-                    break 'inner;
-                }
-            }
-            println!(
-                "Results: Read: {:.2} bytes/s Std Dev {:.2}, Write: {:.2} bytes/s Std Dev {:.2}",
-                mean(&read_results).expect("invalid mean"),
-                std_deviation(&read_results).expect("invalid std deviation"),
-                mean(&write_results).expect("invalid mean"),
-                std_deviation(&write_results).expect("invalid std deviation")
-            );
-            if read_results.len() != iterations || write_results.len() != iterations {
-                println!(
-                    "Warning: {} reads and {} writes successful (out of {} iterations)",
-                    read_results.len(),
-                    write_results.len(),
-                    iterations
-                )
-            }
-            // Insert another blank line to visually seperate results
-            println!();
-        } else {
-            println!("failed to set speed {speed}");
+        if probe.set_speed(speed).is_err() {
+            return Ok(None);
         }
-        Ok(())
-    }
-}
 
-impl DataType {
-    pub fn new(word_size: u32) -> DataType {
-        match word_size {
-            8 => DataType::U8(Vec::new(), Vec::new()),
-            32 => DataType::U32(Vec::new(), Vec::new()),
-            64 => DataType::U64(Vec::new(), Vec::new()),
-            _ => panic!("Invalid word size"),
-        }
-    }
+        let mut session = common_options.attach_session(probe, target)?;
+        let mut core = session.core(0).context("Failed to attach to core")?;
+        core.halt(Duration::from_millis(100))
+            .context("Halting failed")?;
 
-    pub fn size(&self) -> usize {
-        match self {
-            DataType::U8(_, _) => 1,
-            DataType::U32(_, _) => 4,
-            DataType::U64(_, _) => 8,
-        }
-    }
+        let latency = bench::measure_latency(&mut core, self.address, LATENCY_ITERATIONS)
+            .context("Measuring the latency failed")?;
 
-    pub fn fill_data(&mut self, data_size_words: usize) {
-        let mut rng = fastrand::Rng::new();
-        match self {
-            DataType::U8(test_data, read_data) => {
-                *test_data = vec![0u8; data_size_words];
-                *read_data = vec![0u8; data_size_words];
-                rng.fill(&mut test_data[..]);
-            }
-            DataType::U32(test_data, read_data) => {
-                *test_data = vec![0u32; data_size_words];
-                *read_data = vec![0u32; data_size_words];
-                for out in test_data.iter_mut() {
-                    *out = rng.u32(..);
-                }
-            }
-            DataType::U64(test_data, read_data) => {
-                *test_data = vec![0u64; data_size_words];
-                *read_data = vec![0u64; data_size_words];
-                for out in test_data.iter_mut() {
-                    *out = rng.u64(..);
+        let mut accesses = vec![];
+        for width in widths {
+            for size in TEST_SIZES {
+                match self.benchmark_access(&mut core, speed, *width, size) {
+                    Ok(results) => accesses.push(results),
+                    Err(e) => println!(
+                        "Test failed for speed {} size {} word_size {}bit - {:#}",
+                        speed,
+                        size,
+                        width.bits(),
+                        e
+                    ),
                 }
             }
         }
+        drop(core);
+
+        let download = if self.download {
+            Some(self.benchmark_download(&mut session)?)
+        } else {
+            None
+        };
+
+        Ok(Some(SpeedResults {
+            speed,
+            latency,
+            accesses,
+            download,
+        }))
     }
 
-    pub fn compare_data(&self) -> Option<usize> {
-        fn compare_data_inner<T: PartialEq>(sample_data: &[T], read_data: &[T]) -> Option<usize> {
-            sample_data
+    /// Run a specific benchmark
+    fn benchmark_access(
+        &self,
+        core: &mut Core<'_>,
+        speed: u32,
+        width: AccessWidth,
+        size: usize,
+    ) -> anyhow::Result<AccessResults> {
+        let mut sample_data = vec![0u8; size * width.bytes()];
+        fastrand::Rng::new().fill(&mut sample_data);
+        let mut readback_data = vec![0u8; sample_data.len()];
+
+        println!(
+            "Test: Speed {}, Word size {}bit, Data length {} bytes, Number of iterations {}",
+            speed,
+            width.bits(),
+            sample_data.len(),
+            self.iterations
+        );
+
+        let mut read_results = Vec::<f64>::with_capacity(self.iterations);
+        let mut write_results = Vec::<f64>::with_capacity(self.iterations);
+        for _ in 0..self.iterations {
+            let write = bench::measure_write(core, self.address, width, &sample_data)
+                .context("Writing the sample data failed")?;
+            let read = bench::measure_read(core, self.address, width, &mut readback_data)
+                .context("Reading the sample data failed")?;
+
+            if let Some(mismatch) = sample_data
                 .iter()
-                .zip(read_data.iter())
+                .zip(readback_data.iter())
                 .position(|(sample, readback)| sample != readback)
-        }
-        match self {
-            DataType::U8(sample_data, read_data) => compare_data_inner(sample_data, read_data),
-            DataType::U32(sample_data, read_data) => compare_data_inner(sample_data, read_data),
-            DataType::U64(sample_data, read_data) => compare_data_inner(sample_data, read_data),
-        }
-    }
-
-    pub fn data_at_pos(&self, offset: usize) -> (u64, u64) {
-        match self {
-            DataType::U8(sample_data, read_data) => {
-                (sample_data[offset].into(), read_data[offset].into())
+            {
+                eprintln!(
+                    "Readback data differs at address {:08x}: expected byte {:02x}, got byte {:02x}",
+                    self.address + mismatch as u64,
+                    sample_data[mismatch],
+                    readback_data[mismatch]
+                );
+                eprintln!("Verification failed.");
+                break;
             }
-            DataType::U32(sample_data, read_data) => {
-                (sample_data[offset].into(), read_data[offset].into())
-            }
-            DataType::U64(sample_data, read_data) => (sample_data[offset], read_data[offset]),
-        }
-    }
-}
 
-impl TestData {
-    fn new(address: u64, word_size: u32, word_qty: usize) -> TestData {
-        let mut data_type = DataType::new(word_size);
-        data_type.fill_data(word_qty);
-
-        TestData {
-            address,
-            data_type,
-            word_qty,
+            read_results.push(read.bytes_per_second());
+            write_results.push(write.bytes_per_second());
         }
-    }
 
-    fn block_verify(&self) -> bool {
-        if let Some(mismatch) = self.data_type.compare_data() {
-            let (sample_data, readback_data) = self.data_type.data_at_pos(mismatch);
-            eprintln!(
-                "Readback data differs at address {:08x}: expected word {:08x}, got word {:08x}",
-                self.address + mismatch as u64,
-                sample_data,
-                readback_data
+        if let (Some(read_mean), Some(write_mean)) = (mean(&read_results), mean(&write_results)) {
+            println!(
+                "Results: Read: {:.2} bytes/s Std Dev {:.2}, Write: {:.2} bytes/s Std Dev {:.2}",
+                read_mean,
+                std_deviation(&read_results).expect("invalid std deviation"),
+                write_mean,
+                std_deviation(&write_results).expect("invalid std deviation")
             );
-            false
-        } else {
-            true
         }
+        if read_results.len() != self.iterations || write_results.len() != self.iterations {
+            println!(
+                "Warning: {} reads and {} writes successful (out of {} iterations)",
+                read_results.len(),
+                write_results.len(),
+                self.iterations
+            )
+        }
+        // Insert another blank line to visually seperate results
+        println!();
+
+        Ok(AccessResults {
+            width,
+            length: sample_data.len(),
+            read: read_results,
+            write: write_results,
+        })
     }
 
-    /// Read the requested block of data. Return data throughput, or error
-    fn block_read(&mut self, core: &mut probe_rs::Core) -> Result<f64, anyhow::Error> {
-        let read_start = Instant::now();
-        match &mut self.data_type {
-            DataType::U8(_, readback_data) => core
-                .read_8(self.address, readback_data)
-                .expect("Reading the sample data failed"),
-            DataType::U32(_, readback_data) => core
-                .read_32(self.address, readback_data)
-                .expect("Reading the sample data failed"),
-            DataType::U64(_, readback_data) => core
-                .read_64(self.address, readback_data)
-                .expect("Reading the sample data failed"),
-        }
-        let read_duration = read_start.elapsed();
-        let data_size_bytes = self.data_type.size() * self.word_qty;
-        let read_throughput = (data_size_bytes as f64) / read_duration.as_secs_f64();
+    /// Download a block of data to RAM at the start address, returning the throughput in bytes/s.
+    fn benchmark_download(&self, session: &mut Session) -> anyhow::Result<f64> {
+        let mut data = vec![0u8; DOWNLOAD_SIZE];
+        fastrand::Rng::new().fill(&mut data);
+
+        let mut loader = session.target().flash_loader();
+        loader
+            .add_data(self.address, &data)
+            .context("The start address can not be downloaded to")?;
 
-        Ok(read_throughput)
+        let start = Instant::now();
+        loader
+            .commit(session, DownloadOptions::default())
+            .context("Downloading the sample data failed")?;
+
+        Ok(DOWNLOAD_SIZE as f64 / start.elapsed().as_secs_f64())
     }
+}
 
-    /// Write the requested block of data. Return data throughput, or error
-    fn block_write(&mut self, core: &mut probe_rs::Core) -> Result<f64, anyhow::Error> {
-        let write_start = Instant::now();
-        match &self.data_type {
-            DataType::U8(test_data, _) => core
-                .write_8(self.address, test_data)
-                .context("Writing the sample data failed")?,
-            DataType::U32(test_data, _) => core
-                .write_32(self.address, test_data)
-                .context("Writing the sample data failed")?,
-            DataType::U64(test_data, _) => core
-                .write_64(self.address, test_data)
-                .context("Writing the sample data failed")?,
+/// Print a table comparing the mean throughput of all benchmarks.
+fn print_table(results: &[SpeedResults]) {
+    println!(
+        "{:>8} {:>10} {:>8} {:>16} {:>16} {:>12}",
+        "Speed", "Access", "Length", "Read (bytes/s)", "Write (bytes/s)", "Latency"
+    );
+
+    let format_mean = |data: &[f64]| {
+        mean(data)
+            .map(|mean| format!("{mean:.0}"))
+            .unwrap_or_else(|| "-".to_string())
+    };
+
+    for speed_results in results {
+        let latency = format!("{:.1?}", speed_results.latency);
+        for access in &speed_results.accesses {
+            println!(
+                "{:>8} {:>10} {:>8} {:>16} {:>16} {:>12}",
+                speed_results.speed,
+                format!("{}bit", access.width.bits()),
+                access.length,
+                format_mean(&access.read),
+                format_mean(&access.write),
+                latency
+            );
+        }
+        if let Some(download) = speed_results.download {
+            println!(
+                "{:>8} {:>10} {:>8} {:>16} {:>16.0} {:>12}",
+                speed_results.speed, "download", DOWNLOAD_SIZE, "-", download, latency
+            );
         }
-        let write_duration = write_start.elapsed();
-        let data_size_bytes = self.data_type.size() * self.word_qty;
-        let write_throughput = (data_size_bytes as f64) / write_duration.as_secs_f64();
-
-        Ok(write_throughput)
     }
 }
 
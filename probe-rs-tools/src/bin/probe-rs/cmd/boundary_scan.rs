@@ -0,0 +1,146 @@
+//! Sampling and driving the pins of a device with JTAG boundary scan.
+
+use std::path::PathBuf;
+
+use anyhow::Context;
+use probe_rs::config::Registry;
+use probe_rs::probe::WireProtocol;
+use probe_rs::probe::boundary_scan::{BoundaryScan, Bsdl, PortState};
+use probe_rs::probe::list::Lister;
+
+use crate::util::common_options::ProbeOptions;
+
+/// A pin to drive, e.g. `PA0=1`.
+#[derive(Clone, Debug)]
+struct PinDrive {
+    port: String,
+    /// The level to drive, or `None` to disable the output.
+    level: Option<bool>,
+}
+
+fn parse_pin_drive(drive: &str) -> Result<PinDrive, String> {
+    let Some((port, level)) = drive.split_once('=') else {
+        return Err(format!("expected PORT=LEVEL, found '{drive}'"));
+    };
+
+    let level = match level.to_ascii_lowercase().as_str() {
+        "0" | "low" => Some(false),
+        "1" | "high" => Some(true),
+        "z" => None,
+        _ => return Err(format!("invalid level '{level}', expected 0, 1 or z")),
+    };
+
+    Ok(PinDrive {
+        port: port.trim().to_string(),
+        level,
+    })
+}
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(flatten)]
+    common: ProbeOptions,
+
+    /// The BSDL file which describes the boundary scan register of the device.
+    #[clap(long)]
+    bsdl: PathBuf,
+
+    /// The position of the device on the JTAG scan chain.
+    #[clap(long, default_value_t = 0)]
+    tap: usize,
+
+    /// Drive a pin using EXTEST, e.g. `PA0=1`, `PA1=0`, or `PA2=z` to disable its output.
+    ///
+    /// This disconnects all pins from the device until it is reset. Pins which are not driven
+    /// explicitly are set to the safe values from the BSDL file.
+    #[clap(long, value_parser = parse_pin_drive)]
+    drive: Vec<PinDrive>,
+
+    /// Use EXTEST even if no pin is driven, which sets all pins to their safe values.
+    #[clap(long)]
+    extest: bool,
+}
+
+impl Cmd {
+    pub async fn run(self, registry: &mut Registry, lister: &Lister) -> anyhow::Result<()> {
+        let source = std::fs::read_to_string(&self.bsdl)
+            .with_context(|| format!("Failed to read {}", self.bsdl.display()))?;
+        let bsdl = Bsdl::parse(&source)
+            .with_context(|| format!("Failed to parse {}", self.bsdl.display()))?;
+
+        let common_options = self.common.load(registry)?;
+        let mut probe = common_options.attach_probe(lister).await?;
+        probe.select_protocol(WireProtocol::Jtag)?;
+        probe.attach_to_unspecified()?;
+
+        let chain = probe.scan_jtag_chain()?;
+        let Some(tap) = chain.get(self.tap) else {
+            anyhow::bail!(
+                "The JTAG scan chain has {} TAPs, there is no TAP {}",
+                chain.len(),
+                self.tap
+            );
+        };
+        anyhow::ensure!(
+            u32::from(tap.ir_len) == bsdl.instruction_length,
+            "The instruction register of TAP {} has {} bits, but {} bits are expected for {}",
+            self.tap,
+            tap.ir_len,
+            bsdl.instruction_length,
+            bsdl.entity
+        );
+        if let Some(idcode) = tap.idcode {
+            if !bsdl.matches_idcode(u32::from(idcode)) {
+                tracing::warn!(
+                    "The IDCODE {} of TAP {} does not match {}",
+                    idcode,
+                    self.tap,
+                    bsdl.entity
+                );
+            }
+        }
+
+        println!("Device: {} (TAP {})", bsdl.entity, self.tap);
+
+        let jtag = probe
+            .try_as_jtag_probe()
+            .context("The probe does not support JTAG")?;
+        jtag.select_target(self.tap)?;
+
+        let mut scan = BoundaryScan::new(bsdl);
+        for drive in &self.drive {
+            scan.drive(&drive.port, drive.level)?;
+        }
+
+        let ports = if self.drive.is_empty() && !self.extest {
+            println!("Mode: SAMPLE");
+            scan.sample(jtag)?
+        } else {
+            println!("Mode: EXTEST");
+            scan.extest(jtag)?
+        };
+
+        print_ports(&ports);
+
+        Ok(())
+    }
+}
+
+fn print_ports(ports: &[PortState]) {
+    let level = |level: Option<bool>| match level {
+        Some(true) => "high",
+        Some(false) => "low",
+        None => "-",
+    };
+
+    let width = ports.iter().map(|port| port.name.len()).max().unwrap_or(0);
+    println!("  {:<width$} {:<5} Driven", "Port", "Level");
+    for port in ports {
+        println!(
+            "  {:<width$} {:<5} {}",
+            port.name,
+            level(port.level),
+            level(port.driven)
+        );
+    }
+}
@@ -3,7 +3,7 @@ use figment::{
     Figment,
     providers::{Format, Json, Toml, Yaml},
 };
-use probe_rs::probe::WireProtocol;
+use probe_rs::{flashing::WatchdogMode, probe::WireProtocol};
 use serde::{Deserialize, Serialize};
 use std::{net::SocketAddr, path::PathBuf, time::Duration};
 
@@ -53,7 +53,9 @@ pub struct Flashing {
     pub do_chip_erase: bool,
     pub disable_double_buffering: bool,
     pub preverify: bool,
+    pub incremental: bool,
     pub verify: bool,
+    pub watchdog: WatchdogMode,
 }
 
 /// The reset config struct holding all the possible reset options.
@@ -123,6 +125,8 @@ pub struct Rtt {
     pub log_enabled: bool,
     /// Where to save rtt history buffer relative to manifest path.
     pub log_path: PathBuf,
+    /// Whether to read all up channels with a single memory transfer per poll.
+    pub batched_reads: bool,
 }
 
 impl Rtt {
@@ -6,12 +6,13 @@ use anyhow::{Context, Result, anyhow};
 use clap::Parser;
 use colored::Colorize;
 use parking_lot::FairMutex;
-use probe_rs::config::Registry;
 use probe_rs::flashing::{BootInfo, FormatKind};
 use probe_rs::probe::list::Lister;
 use probe_rs::rtt::ScanRegion;
-use probe_rs::{Session, probe::DebugProbeSelector};
+use probe_rs::{MemoryMapPolicy, Session, probe::DebugProbeSelector};
+use probe_rs_debug::DebugInfo;
 use std::ffi::OsString;
+use std::rc::Rc;
 use std::time::Instant;
 use std::{fs, thread};
 use std::{
@@ -31,6 +32,7 @@ use crate::util::flash::{build_loader, run_flash_download};
 use crate::util::logging::setup_logging;
 use crate::util::rtt::client::RttClient;
 use crate::util::rtt::{self, RttChannelConfig, RttConfig};
+use crate::util::settings::Settings;
 use crate::util::{cargo::build_artifact, common_options::CargoOptions, logging};
 
 #[derive(Debug, clap::Parser)]
@@ -137,7 +139,7 @@ async fn main_try(args: &[OsString], offset: UtcOffset) -> Result<()> {
 
     let _log_guard = setup_logging(None, config.general.log_level);
 
-    let mut registry = Registry::from_builtin_families();
+    let mut registry = Settings::load().registry();
 
     // Make sure we load the config given in the cli parameters.
     for cdp in &config.general.chip_descriptions {
@@ -212,9 +214,16 @@ async fn main_try(args: &[OsString], offset: UtcOffset) -> Result<()> {
         non_interactive: false,
         probe: selector,
         speed: config.probe.speed,
+        speed_floor: None,
+        hooks: None,
+        record_trace: None,
+        record_swd_trace: None,
         connect_under_reset: config.general.connect_under_reset,
         dry_run: false,
         allow_erase_all: config.flashing.enabled || config.gdb.enabled,
+        no_debug_freeze: false,
+        memory_map: MemoryMapPolicy::Allow,
+        reconnect_on_power_down: None,
     };
 
     let lister = Lister::new();
@@ -292,7 +301,10 @@ async fn main_try(args: &[OsString], offset: UtcOffset) -> Result<()> {
             restore_unwritten: config.flashing.restore_unwritten_bytes,
             flash_layout_output_path: None,
             preverify: config.flashing.preverify,
+            incremental: config.flashing.incremental,
+            resume: false,
             verify: config.flashing.verify,
+            watchdog: config.flashing.watchdog,
         };
         let format_options = FormatOptions::default();
         let loader = build_loader(&mut session, &path, format_options, image_instr_set)?;
@@ -342,6 +354,7 @@ async fn main_try(args: &[OsString], offset: UtcOffset) -> Result<()> {
     if config.gdb.enabled {
         let gdb_connection_string = config.gdb.gdb_connection_string.clone();
         let session = session.clone();
+        let elf = elf.clone();
 
         gdb_thread_handle = Some(thread::spawn(move || {
             let gdb_connection_string =
@@ -353,11 +366,19 @@ async fn main_try(args: &[OsString], offset: UtcOffset) -> Result<()> {
                 gdb_connection_string,
             ));
 
-            let instances = {
+            let mut instances = {
                 let session = session.lock();
                 GdbInstanceConfiguration::from_session(&session, Some(gdb_connection_string))
             };
 
+            // The debug info is used to report the threads of an RTOS to GDB.
+            let debug_info = elf
+                .and_then(|elf| DebugInfo::from_raw(&elf).ok())
+                .map(Rc::new);
+            for instance in instances.iter_mut() {
+                instance.debug_info = debug_info.clone();
+            }
+
             if let Err(e) = crate::cmd::gdb_server::run(&session, instances.iter(), None) {
                 logging::eprintln("During the execution of GDB an error was encountered:");
                 logging::eprintln(format!("{e:?}"));
@@ -497,6 +518,8 @@ fn create_rtt_config(config: &config::Config) -> RttConfig {
     let mut rtt_config = RttConfig {
         enabled: true,
         channels: vec![],
+        batched_reads: config.rtt.batched_reads,
+        server: Default::default(),
     };
 
     // Make sure our defaults are the same as the ones intended in the config struct.
@@ -520,6 +543,7 @@ fn create_rtt_config(config: &config::Config) -> RttConfig {
                 .clone()
                 .or_else(|| default_channel_config.log_format.clone()),
             mode: channel_config.mode.or(default_channel_config.mode),
+            down_channel: None,
         });
     }
     // In case we have down channels without up channels, add them separately.
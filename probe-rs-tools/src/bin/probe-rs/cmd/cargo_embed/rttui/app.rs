@@ -1,5 +1,6 @@
 use anyhow::{Context, Result, anyhow};
 use probe_rs::Core;
+use probe_rs::rtt::decoder::{DefmtDecoder, DefmtTable};
 use ratatui::{
     Terminal,
     backend::CrosstermBackend,
@@ -18,9 +19,7 @@ use time::UtcOffset;
 
 use crate::{
     cmd::cargo_embed::rttui::{channel::ChannelData, tab::TabConfig},
-    util::rtt::{
-        DataFormat, DefmtProcessor, DefmtState, RttChannelConfig, RttDecoder, client::RttClient,
-    },
+    util::rtt::{DataFormat, RttChannelConfig, RttDecoder, client::RttClient},
 };
 
 use super::super::config;
@@ -58,7 +57,7 @@ impl App {
         logname: String,
     ) -> Result<Self> {
         let defmt_data = if let Some(elf) = elf {
-            DefmtState::try_from_bytes(&elf)?
+            DefmtTable::try_from_bytes(&elf)?
         } else {
             None
         };
@@ -104,17 +103,14 @@ impl App {
             };
 
             let data_format = match channel_format {
-                DataFormat::String => RttDecoder::String {
-                    timestamp_offset: Some(timestamp_offset),
-                    last_line_done: false,
-                },
+                DataFormat::String => RttDecoder::string(Some(timestamp_offset)),
                 DataFormat::BinaryLE => RttDecoder::BinaryLE,
                 DataFormat::Defmt if defmt_data.is_none() => {
                     tracing::warn!("Defmt data not found in ELF file");
                     continue;
                 }
                 DataFormat::Defmt => RttDecoder::Defmt {
-                    processor: DefmtProcessor::new(
+                    processor: DefmtDecoder::new(
                         defmt_data.clone().unwrap(),
                         channel_config
                             .show_timestamps
@@ -2,6 +2,7 @@ use std::{
     cell::{Ref, RefCell},
     fmt::write,
     rc::Rc,
+    time::Duration,
 };
 
 use probe_rs::Core;
@@ -10,6 +11,9 @@ use crate::{cmd::cargo_embed::rttui::channel::ChannelData, util::rtt::client::Rt
 
 use super::channel::UpChannel;
 
+/// How long to wait for the target to read from a full down channel when sending input.
+const INPUT_WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct TabConfig {
     /// Which up channel to use.
@@ -102,7 +106,7 @@ impl Tab {
     pub fn send_input(&mut self, core: &mut Core, client: &mut RttClient) -> anyhow::Result<()> {
         if let Some((channel, input)) = self.down_channel.as_mut() {
             input.push('\n');
-            client.write_down_channel(core, *channel, input.as_str())?;
+            client.write_down_channel_all(core, *channel, input.as_str(), INPUT_WRITE_TIMEOUT)?;
             input.clear();
         }
 
@@ -14,6 +14,7 @@ use crate::util::common_options::{
 };
 use crate::util::flash;
 use crate::util::logging::{LevelFilter, setup_logging};
+use crate::util::settings::Settings;
 use crate::util::{cargo::build_artifact, logging};
 
 /// Common options when flashing a target device.
@@ -57,7 +58,7 @@ struct CliOptions {
 }
 
 pub async fn main(args: &[OsString]) {
-    let mut registry = Registry::from_builtin_families();
+    let mut registry = Settings::load().registry();
     match main_try(&mut registry, args).await {
         Ok(_) => (),
         Err(e) => {
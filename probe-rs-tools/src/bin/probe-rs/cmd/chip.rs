@@ -1,8 +1,19 @@
-use std::io::Write;
+use std::{io::Write, path::PathBuf};
 
+use anyhow::Context;
 use bytesize::ByteSize;
 
-use crate::rpc::{client::RpcClient, functions::chip::MemoryRegion};
+use crate::{
+    rpc::{
+        client::RpcClient,
+        functions::{
+            chip::{MemoryRegion, ScanChipRequest, SfdpTables},
+            info::{InfoEvent, TargetInfoRequest},
+            probe::WireProtocol,
+        },
+    },
+    util::{cli::select_probe, common_options::ProbeOptions, parse_u64},
+};
 
 #[derive(clap::Parser)]
 pub struct Cmd {
@@ -22,6 +33,37 @@ enum Subcommand {
         /// The name of the chip to display.
         name: String,
     },
+    /// Scans the JTAG chain of the connected target and shows the TAPs found on it
+    #[clap(name = "detect")]
+    Detect {
+        #[clap(flatten)]
+        common: ProbeOptions,
+    },
+    /// Reads what can be found out about the connected chip, and prints a target description
+    /// skeleton for it
+    #[clap(name = "scan")]
+    Scan {
+        #[clap(flatten)]
+        common: ProbeOptions,
+
+        /// The name of the chip in the target description.
+        #[clap(long, default_value = "Unknown")]
+        name: String,
+
+        /// The base address of a memory mapped NOR flash to query with CFI. Can be repeated.
+        ///
+        /// The query writes to this address, so only use it for flash.
+        #[clap(long = "cfi", value_parser = parse_u64)]
+        cfi_addresses: Vec<u64>,
+
+        /// The SFDP tables of a serial flash, as `<ADDRESS>=<FILE>`. Can be repeated.
+        ///
+        /// The file contains the SFDP data read from the flash, starting at SFDP address 0, and
+        /// the address is where the flash is mapped. Serial flashes can only be queried through
+        /// their chip specific flash controller, so the tables are not read by the scan.
+        #[clap(long = "sfdp", value_parser = parse_sfdp_file)]
+        sfdp_files: Vec<(u64, PathBuf)>,
+    },
 }
 
 impl Cmd {
@@ -31,6 +73,13 @@ impl Cmd {
         match self.subcommand {
             Subcommand::List => print_families(&client, output).await,
             Subcommand::Info { name } => print_chip_info(&client, output, &name).await,
+            Subcommand::Detect { common } => print_jtag_chain(&client, output, common).await,
+            Subcommand::Scan {
+                common,
+                name,
+                cfi_addresses,
+                sfdp_files,
+            } => print_scanned_chip(&client, output, common, name, cfi_addresses, sfdp_files).await,
         }
     }
 }
@@ -86,6 +135,92 @@ pub async fn print_chip_info(
     Ok(())
 }
 
+/// Print the TAPs found on the JTAG scan chain of the connected target.
+pub async fn print_jtag_chain(
+    client: &RpcClient,
+    mut output: impl Write,
+    common: ProbeOptions,
+) -> anyhow::Result<()> {
+    let probe = select_probe(client, common.probe).await?;
+
+    let request = TargetInfoRequest {
+        probe,
+        speed: common.speed,
+        connect_under_reset: common.connect_under_reset,
+        dry_run: common.dry_run,
+        target_sel: None,
+        protocol: WireProtocol::Jtag,
+        scan_chain_only: true,
+    };
+
+    let mut messages = vec![];
+    client
+        .info(request, async |message| messages.push(message))
+        .await?;
+
+    for message in messages {
+        if matches!(
+            message,
+            InfoEvent::JtagChain(_) | InfoEvent::Error { .. } | InfoEvent::Message(_)
+        ) {
+            write!(output, "{message}")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Print a target description skeleton for the connected chip.
+pub async fn print_scanned_chip(
+    client: &RpcClient,
+    mut output: impl Write,
+    common: ProbeOptions,
+    name: String,
+    cfi_addresses: Vec<u64>,
+    sfdp_files: Vec<(u64, PathBuf)>,
+) -> anyhow::Result<()> {
+    let sfdp_tables = sfdp_files
+        .into_iter()
+        .map(|(base_address, path)| {
+            let data = std::fs::read(&path).with_context(|| {
+                format!("Failed to read the SFDP tables from {}", path.display())
+            })?;
+            Ok(SfdpTables { base_address, data })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let probe = select_probe(client, common.probe).await?;
+
+    let request = ScanChipRequest {
+        probe,
+        speed: common.speed,
+        protocol: common.protocol.map(Into::into),
+        connect_under_reset: common.connect_under_reset,
+        name,
+        cfi_addresses,
+        sfdp_tables,
+    };
+
+    let scanned = client.scan_chip(request).await?;
+    if let Some(target) = scanned.known_target {
+        eprintln!("The chip is already known as {target}");
+    }
+
+    write!(output, "{}", scanned.target_description)?;
+
+    Ok(())
+}
+
+/// Parses an `<ADDRESS>=<FILE>` argument.
+fn parse_sfdp_file(input: &str) -> Result<(u64, PathBuf), String> {
+    let (address, path) = input
+        .split_once('=')
+        .ok_or_else(|| format!("expected `<ADDRESS>=<FILE>`, got `{input}`"))?;
+    let address = parse_u64(address).map_err(|error| format!("invalid address: {error}"))?;
+
+    Ok((address, PathBuf::from(path)))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -144,4 +279,14 @@ mod test {
         })
         .await;
     }
+
+    #[test]
+    fn sfdp_file_argument() {
+        assert_eq!(
+            parse_sfdp_file("0x90000000=flash.sfdp").unwrap(),
+            (0x9000_0000, PathBuf::from("flash.sfdp"))
+        );
+        assert!(parse_sfdp_file("flash.sfdp").is_err());
+        assert!(parse_sfdp_file("flash=flash.sfdp").is_err());
+    }
 }
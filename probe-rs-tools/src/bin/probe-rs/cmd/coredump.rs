@@ -0,0 +1,158 @@
+use std::ops::Range;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use probe_rs::config::Registry;
+use probe_rs::probe::list::Lister;
+use probe_rs::{Core, CoreDump, CoreDumpOptions};
+
+use crate::CoreOptions;
+use crate::util::common_options::ProbeOptions;
+use crate::util::snapshot::parse_range;
+
+/// Store the registers and memory of a core in a core dump, to analyze it offline.
+///
+/// The core is halted while the core dump is created, and resumed afterwards if it was running.
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(flatten)]
+    shared: CoreOptions,
+
+    #[clap(flatten)]
+    common: ProbeOptions,
+
+    /// A memory range to include, e.g. `0x20000000..0x20004000`. Can be used multiple times.
+    /// If not specified, all RAM regions of the target are included.
+    #[clap(long = "range", value_parser = parse_range)]
+    ranges: Vec<(u64, u64)>,
+
+    /// A 32 bit peripheral register to include, e.g. `SCB.CFSR=0xE000ED28`. Can be used multiple
+    /// times.
+    #[clap(long = "peripheral", value_parser = parse_peripheral)]
+    peripherals: Vec<(String, u64)>,
+
+    /// Where to store the core dump.
+    #[clap(long, short, default_value = "./coredump")]
+    output: PathBuf,
+}
+
+impl Cmd {
+    pub async fn run(self, registry: &mut Registry, lister: &Lister) -> anyhow::Result<()> {
+        let (mut session, _probe_options) = self.common.simple_attach(registry, lister).await?;
+
+        let ranges: Vec<Range<u64>> = if self.ranges.is_empty() {
+            session
+                .target()
+                .memory_map
+                .iter()
+                .filter(|region| region.is_ram())
+                .map(|region| region.address_range())
+                .collect()
+        } else {
+            self.ranges.iter().map(|&(start, end)| start..end).collect()
+        };
+
+        let mut core = session.core(self.shared.core)?;
+        let core_dump = dump_core(
+            &mut core,
+            CoreDumpOptions {
+                ranges,
+                peripherals: self.peripherals,
+            },
+        )?;
+
+        core_dump.store(&self.output)?;
+        println!("Stored a core dump at {}", self.output.display());
+
+        Ok(())
+    }
+}
+
+/// Creates a core dump of `core`, halting it while the core dump is created if it is running.
+fn dump_core(core: &mut Core<'_>, options: CoreDumpOptions) -> anyhow::Result<CoreDump> {
+    let was_halted = core.core_halted()?;
+    if !was_halted {
+        core.halt(Duration::from_millis(100))?;
+    }
+
+    let result = CoreDump::dump_core_with_options(core, options);
+
+    let resumed = if was_halted { Ok(()) } else { core.run() };
+    match result {
+        Ok(core_dump) => {
+            resumed?;
+            Ok(core_dump)
+        }
+        Err(error) => {
+            // The error of the core dump is the relevant one, don't hide it behind the cleanup.
+            if let Err(resume_error) = resumed {
+                tracing::warn!("Failed to resume the core: {resume_error}");
+            }
+            Err(error.into())
+        }
+    }
+}
+
+/// Parses a peripheral register given as `NAME=ADDRESS`.
+fn parse_peripheral(input: &str) -> Result<(String, u64), String> {
+    let (name, address) = input
+        .split_once('=')
+        .ok_or_else(|| format!("'{input}' is not a register, expected `NAME=ADDRESS`"))?;
+
+    let address = parse_int::parse::<u64>(address.trim()).map_err(|e| e.to_string())?;
+
+    Ok((name.trim().to_string(), address))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use probe_rs::{MemoryInterface, Permissions, integration::FakeProbe};
+
+    fn dump(halted: bool) -> (CoreDump, bool) {
+        let mut fake_probe = FakeProbe::with_mocked_core();
+        fake_probe
+            .add_memory_region(0x2000_0000..0x2000_0010, true)
+            .unwrap();
+        fake_probe.load_memory(0x2000_0000, &[1, 2, 3, 4]).unwrap();
+
+        let mut session = fake_probe
+            .into_probe()
+            .attach("nrf51822_xxAC", Permissions::default())
+            .unwrap();
+        let mut core = session.core(0).unwrap();
+        if halted {
+            core.halt(Duration::from_millis(100)).unwrap();
+        } else {
+            core.run().unwrap();
+        }
+
+        let loaded_word = 0x2000_0000..0x2000_0004;
+        let core_dump = dump_core(
+            &mut core,
+            CoreDumpOptions {
+                ranges: vec![loaded_word],
+                peripherals: vec![],
+            },
+        )
+        .unwrap();
+
+        (core_dump, core.core_halted().unwrap())
+    }
+
+    #[test]
+    fn running_core_is_resumed() {
+        let (mut core_dump, halted) = dump(false);
+        assert!(!halted);
+
+        let mut data = [0; 4];
+        core_dump.read_8(0x2000_0000, &mut data).unwrap();
+        assert_eq!(data, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn halted_core_stays_halted() {
+        let (_, halted) = dump(true);
+        assert!(halted);
+    }
+}
@@ -1,10 +1,12 @@
 use super::{
     core_status::DapStatus,
     dap_types,
+    expression::split_assignment,
     repl_commands_helpers::{build_expanded_commands, command_completions},
     request_helpers::{
-        disassemble_target_memory, get_dap_source, get_svd_variable_reference,
-        get_variable_reference, set_instruction_breakpoint,
+        disassemble_target_memory, get_dap_source, get_memory_reference,
+        get_register_memory_reference, get_step_in_targets, get_svd_variable_reference,
+        get_variable_reference, merge_breakpoints, set_data_breakpoint, set_instruction_breakpoint,
     },
 };
 use crate::cmd::dap_server::{
@@ -12,8 +14,8 @@ use crate::cmd::dap_server::{
     debug_adapter::protocol::{ProtocolAdapter, ProtocolHelper},
     server::{
         configuration::ConsoleLog,
-        core_data::CoreHandle,
-        session_data::{BreakpointType, SourceLocationScope},
+        core_data::{CoreData, CoreHandle},
+        session_data::{BreakpointType, SessionData, SourceLocationScope},
     },
 };
 use crate::util::rtt;
@@ -30,13 +32,13 @@ use probe_rs::{
     },
 };
 use probe_rs_debug::{
-    ColumnType, ObjectRef, SourceLocation, SteppingMode, VariableName, VerifiedBreakpoint,
-    stack_frame::StackFrameInfo,
+    ColumnType, ExpressionValue, ObjectRef, SourceLocation, SteppingMode, VariableLocation,
+    VariableName, VerifiedBreakpoint, stack_frame::StackFrameInfo,
 };
 use serde::{Serialize, de::DeserializeOwned};
-use typed_path::NativePathBuf;
+use typed_path::{NativePathBuf, TypedPathBuf};
 
-use std::{fmt::Display, str, time::Duration};
+use std::{fmt::Display, path::PathBuf, str, time::Duration};
 
 /// Progress ID used for progress reporting when the debug adapter protocol is used.
 type ProgressId = i64;
@@ -135,17 +137,21 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
 
     pub(crate) fn disconnect(
         &mut self,
-        target_core: &mut CoreHandle,
+        session_data: &mut SessionData,
         request: &Request,
     ) -> Result<()> {
         let arguments: DisconnectArguments = get_arguments(self, request)?;
 
-        // TODO: For now (until we do multicore), we will assume that both terminate and suspend translate to a halt of the core.
+        // We assume that both terminate and suspend translate to a halt of all cores.
         let must_halt_debuggee = arguments.terminate_debuggee.unwrap_or(false)
             || arguments.suspend_debuggee.unwrap_or(false);
 
         if must_halt_debuggee {
-            let _ = target_core.core.halt(Duration::from_millis(100));
+            for core_data in session_data.core_data.iter() {
+                if let Ok(mut core) = session_data.session.core(core_data.core_index) {
+                    let _ = core.halt(Duration::from_millis(100));
+                }
+            }
         }
 
         self.send_response::<DisconnectResponse>(request, Ok(None))
@@ -160,7 +166,8 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
 
         let memory_offset = arguments.offset.unwrap_or(0);
         let mut address: u64 = match parse::<u64>(arguments.memory_reference.as_ref()) {
-            Ok(address) => address + memory_offset as u64,
+            // The offset may be negative, e.g. to read memory in front of a variable.
+            Ok(address) => address.wrapping_add_signed(memory_offset),
             Err(err) => {
                 return self.send_response::<()>(
                     request,
@@ -171,7 +178,35 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
                 );
             }
         };
+        let start_address = address;
         let mut num_bytes_unread = arguments.count as usize;
+
+        // Non-volatile memory only changes when it is written through the debugger, so it can be
+        // served from the cache.
+        let end = address + num_bytes_unread as u64;
+        let in_nvm = target_core.core.memory_regions().any(|region| {
+            let range = region.address_range();
+            region.is_nvm() && range.start <= address && end <= range.end
+        });
+        if in_nvm && num_bytes_unread > 0 {
+            let mut data = vec![0; num_bytes_unread];
+            if let Ok(()) =
+                target_core
+                    .core_data
+                    .memory_cache
+                    .read(&mut target_core.core, address, &mut data)
+            {
+                return self.send_response(
+                    request,
+                    Ok(Some(ReadMemoryResponseBody {
+                        address: format!("{start_address:#010x}"),
+                        data: Some(base64_engine::STANDARD.encode(&data)),
+                        unreadable_bytes: None,
+                    })),
+                );
+            }
+        }
+
         // The probe-rs API does not return partially read data.
         // It either succeeds for the whole buffer or not. However, doing single byte reads is slow, so we will
         // do reads in larger chunks, until we get an error, and then do single byte reads for the last few bytes, to make
@@ -180,7 +215,7 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
         let large_read_byte_count = 8usize;
         let mut fast_buff = vec![0u8; large_read_byte_count];
         // Read as many large chunks as possible.
-        while num_bytes_unread > 0 {
+        while num_bytes_unread >= large_read_byte_count {
             if let Ok(()) = target_core.core.read(address, &mut fast_buff) {
                 result_buffer.extend_from_slice(&fast_buff);
                 address += large_read_byte_count as u64;
@@ -206,7 +241,7 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
             self.send_response(
                 request,
                 Ok(Some(ReadMemoryResponseBody {
-                    address: format!("{address:#010x}"),
+                    address: format!("{start_address:#010x}"),
                     data: Some(response),
                     unreadable_bytes: if num_bytes_unread == 0 {
                         None
@@ -266,6 +301,10 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
                 );
             }
         };
+        target_core
+            .core_data
+            .memory_cache
+            .invalidate_range(address..address + data_bytes.len() as u64);
         match target_core
             .core
             .write_8(address, &data_bytes)
@@ -306,12 +345,13 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
         let arguments: EvaluateArguments = get_arguments(self, request)?;
 
         // Various fields in the response_body will be updated before we return.
+        let invalid_expression = format!("<invalid expression {:?}>", arguments.expression);
         let mut response_body = EvaluateResponseBody {
             indexed_variables: None,
             memory_reference: None,
             named_variables: None,
             presentation_hint: None,
-            result: format!("<invalid expression {:?}>", arguments.expression),
+            result: invalid_expression.clone(),
             type_: None,
             variables_reference: 0,
             value_location_reference: None,
@@ -320,6 +360,31 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
         if let Some(context) = &arguments.context {
             if context == "clipboard" {
                 response_body.result = arguments.expression;
+            } else if let Some((target, value)) =
+                split_assignment(&arguments.expression).filter(|_| context == "repl")
+            {
+                // Assignments, e.g. `foo = 5` or `$r3 = 0x20`, are only allowed in the debug
+                // console. Hovers and watches are read-only, so they never write to the target.
+                match target_core.assign(target, value) {
+                    Ok(assigned_value) => {
+                        response_body.result = assigned_value;
+                        // The client needs to refresh any variables it has already displayed.
+                        self.send_event(
+                            "invalidated",
+                            Some(InvalidatedEventBody {
+                                areas: Some(vec!["variables".to_string()]),
+                                stack_frame_id: None,
+                                thread_id: Some(target_core.core.id() as i64),
+                            }),
+                        )?;
+                    }
+                    Err(error) => {
+                        response_body.result = match error {
+                            DebuggerError::UserMessage(message) => message,
+                            other_error => format!("{other_error:?}"),
+                        };
+                    }
+                }
             } else if context == "repl" {
                 match self.handle_repl(target_core, &arguments) {
                     Ok(repl_response) => {
@@ -381,11 +446,11 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
                 // Make sure we have a valid StackFrame
                 if let Some(stack_frame) =
                     match arguments.frame_id.map(ObjectRef::try_from).transpose() {
-                        Ok(Some(frame_id)) => target_core
-                            .core_data
-                            .stack_frames
-                            .iter_mut()
-                            .find(|stack_frame| stack_frame.id == frame_id),
+                        Ok(Some(frame_id)) => CoreData::all_stack_frames_mut(
+                            &mut target_core.core_data.stack_frames,
+                            &mut target_core.core_data.rtos_threads,
+                        )
+                        .find(|stack_frame| stack_frame.id == frame_id),
                         Ok(None) => {
                             // Use the current frame_id
                             target_core.core_data.stack_frames.first_mut()
@@ -449,7 +514,7 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
                             ) = get_variable_reference(&variable, variable_cache);
                             response_body.indexed_variables = Some(indexed_child_variables_cnt);
                             response_body.memory_reference =
-                                Some(variable.memory_location.to_string());
+                                get_memory_reference(&variable.memory_location);
                             response_body.named_variables = Some(named_child_variables_cnt);
                             response_body.result = variable.to_string(variable_cache);
                             response_body.type_ = Some(variable.type_name());
@@ -490,6 +555,55 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
                             }
                         }
                     }
+
+                    // Casts, slices, field chains and arithmetic, e.g. `*(uint32_t*)0x2000_0000`,
+                    // `buf[4..16]`, `ctx->pins[2].mode` or `$sp - 0x10`.
+                    if response_body.result == invalid_expression {
+                        let frame_info = StackFrameInfo {
+                            registers: &stack_frame.registers,
+                            frame_base: stack_frame.frame_base,
+                            canonical_frame_address: stack_frame.canonical_frame_address,
+                        };
+                        for variable_cache in [
+                            stack_frame.local_variables.as_mut(),
+                            target_core.core_data.static_variables.as_mut(),
+                        ]
+                        .into_iter()
+                        .flatten()
+                        {
+                            match target_core.core_data.debug_info.evaluate_expression(
+                                &expression,
+                                &mut target_core.core,
+                                variable_cache,
+                                frame_info,
+                            ) {
+                                Ok(ExpressionValue::Variable(variable)) => {
+                                    let (
+                                        variables_reference,
+                                        named_child_variables_cnt,
+                                        indexed_child_variables_cnt,
+                                    ) = get_variable_reference(&variable, variable_cache);
+                                    response_body.indexed_variables =
+                                        Some(indexed_child_variables_cnt);
+                                    response_body.memory_reference =
+                                        get_memory_reference(&variable.memory_location);
+                                    response_body.named_variables = Some(named_child_variables_cnt);
+                                    response_body.result = variable.to_string(variable_cache);
+                                    response_body.type_ = Some(variable.type_name());
+                                    response_body.variables_reference = variables_reference.into();
+                                    break;
+                                }
+                                Ok(value) => {
+                                    response_body.result = value.to_string(variable_cache);
+                                    response_body.type_ = Some(value.type_name());
+                                    break;
+                                }
+                                Err(error) => {
+                                    tracing::debug!("Failed to evaluate {expression:?}: {error}");
+                                }
+                            }
+                        }
+                    }
                 }
             }
         }
@@ -535,6 +649,38 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
         (repl_command.handler)(target_core, argument_string, arguments)
     }
 
+    /// Custom `coreDump` request, to store the registers and memory of a halted core, so that a crash
+    /// can be archived and analyzed offline.
+    pub(crate) fn core_dump(
+        &mut self,
+        target_core: &mut CoreHandle,
+        request: &Request,
+    ) -> Result<()> {
+        let arguments: CoreDumpArguments = get_arguments(self, request)?;
+        if let Some(thread_id) = arguments.thread_id {
+            // Requests for unknown threads are dispatched to the core that halted last.
+            if !target_core.core_data.has_thread(thread_id) {
+                return self.send_response::<()>(
+                    request,
+                    Err(&DebuggerError::UserMessage(format!(
+                        "There is no thread with the ID {thread_id}."
+                    ))),
+                );
+            }
+        }
+        let path = arguments
+            .path
+            .unwrap_or_else(|| PathBuf::from("./coredump"));
+
+        match target_core.store_core_dump(arguments.ranges, &path) {
+            Ok(ranges) => {
+                self.log_to_console(format!("Stored a core dump at {}", path.display()));
+                self.send_response(request, Ok(Some(CoreDumpResponseBody { path, ranges })))
+            }
+            Err(error) => self.send_response::<()>(request, Err(&error)),
+        }
+    }
+
     /// Works in tandem with the `evaluate` request, to provide possible completions in the Debug Console REPL window.
     pub(crate) fn completions(&mut self, _: &mut CoreHandle, request: &Request) -> Result<()> {
         // TODO: When variables appear in the `watch` context, they will not resolve correctly after a 'step' function. Consider doing the lazy load for 'either/or' of Variables vs. Evaluate
@@ -568,12 +714,57 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
         };
 
         // The arguments.variables_reference contains the reference of the variable container. This can be:
+        // - The key of an SVD peripheral or register, for the registers and fields of peripherals.
         // - The `StackFrame.id` for register variables - we will warn the user that updating these are not yet supported.
         // - The `Variable.parent_key` for a local or static variable - If these are base data types, we will attempt to update their value, otherwise we will warn the user that updating complex / structure variables are not yet supported.
         let parent_key: ObjectRef = arguments.variables_reference.into();
         let new_value = &arguments.value;
 
-        //TODO: Check for, and prevent SVD Peripheral/Register/Field values from being updated, until such time as we can do it safely.
+        if let Some(svd_cache) = target_core
+            .core_data
+            .core_peripherals
+            .as_ref()
+            .map(|core_peripherals| &core_peripherals.svd_variable_cache)
+        {
+            if let Some(svd_variable) =
+                svd_cache.get_variable_by_name_and_parent(&arguments.name, parent_key)
+            {
+                return match svd_cache.set_value(
+                    svd_variable.variable_key(),
+                    &mut target_core.core,
+                    new_value,
+                ) {
+                    Ok(value) => {
+                        let (variables_reference, named_child_variables_cnt) =
+                            get_svd_variable_reference(svd_variable, svd_cache);
+                        response_body.variables_reference = Some(variables_reference.into());
+                        response_body.named_variables = Some(named_child_variables_cnt);
+                        response_body.memory_reference = svd_variable.memory_reference();
+                        response_body.type_ = svd_variable.type_name();
+                        response_body.value = value;
+                        // The value of the register and its fields changed.
+                        self.send_event(
+                            "invalidated",
+                            Some(InvalidatedEventBody {
+                                areas: Some(vec!["variables".to_string()]),
+                                stack_frame_id: None,
+                                thread_id: Some(target_core.core.id() as i64),
+                            }),
+                        )?;
+                        self.send_response(request, Ok(Some(response_body)))
+                    }
+                    Err(error) => self.send_response::<SetVariableResponseBody>(
+                        request,
+                        Err(&DebuggerError::Other(anyhow!(
+                            "Failed to update {}, with new value {:?} : {}",
+                            arguments.name,
+                            new_value,
+                            error
+                        ))),
+                    ),
+                };
+            }
+        }
 
         match target_core
             .core_data
@@ -603,7 +794,10 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
                 // The parent_key refers to a local or static variable in one of the in-scope StackFrames.
                 let mut cache_variable: Option<probe_rs_debug::Variable> = None;
                 let mut variable_cache: Option<&mut probe_rs_debug::VariableCache> = None;
-                for search_frame in target_core.core_data.stack_frames.iter_mut() {
+                for search_frame in CoreData::all_stack_frames_mut(
+                    &mut target_core.core_data.stack_frames,
+                    &mut target_core.core_data.rtos_threads,
+                ) {
                     if let Some(search_cache) = &mut search_frame.local_variables {
                         if let Some(search_variable) =
                             search_cache.get_variable_by_name_and_parent(&variable_name, parent_key)
@@ -687,6 +881,7 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
         }
 
         target_core.reset_core_status(self);
+        target_core.core_data.memory_cache.invalidate();
 
         // Different code paths if we invoke this from a request, versus an internal function.
         if let Some(request) = request {
@@ -794,9 +989,17 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
     #[tracing::instrument(level = "debug", skip_all, name = "Handle configuration done")]
     pub(crate) fn configuration_done(
         &mut self,
-        target_core: &mut CoreHandle,
+        session_data: &mut SessionData,
         request: &Request,
     ) -> Result<()> {
+        session_data.for_each_core(|target_core| self.start_core(target_core, request))?;
+
+        self.configuration_done = true;
+        self.send_response::<()>(request, Ok(None))
+    }
+
+    /// Notify the client if the core is halted at a breakpoint (or `halt_after_reset` is set), otherwise resume it.
+    fn start_core(&mut self, target_core: &mut CoreHandle, request: &Request) -> Result<()> {
         let current_core_status = target_core.core.status()?;
 
         if current_core_status.is_halted() {
@@ -831,139 +1034,248 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
             }
         }
 
-        self.configuration_done = true;
-        self.send_response::<()>(request, Ok(None))
+        Ok(())
     }
 
+    /// Set the breakpoints on all cores. A breakpoint is reported as verified if at least one core could set it.
     pub(crate) fn set_breakpoints(
         &mut self,
-        target_core: &mut CoreHandle,
+        session_data: &mut SessionData,
         request: &Request,
     ) -> Result<()> {
         let args: SetBreakpointsArguments = get_arguments(self, request)?;
 
+        let Some(source_path) = args.source.path.as_ref() else {
+            return self.send_response::<()>(
+                request,
+                Err(&DebuggerError::Other(anyhow!(
+                    "Could not get a valid source path from arguments: {args:?}"
+                ))),
+            );
+        };
+
+        // Assume that the path is native to the current OS
+        let source_path = NativePathBuf::from(source_path).to_typed_path_buf();
+
         let mut created_breakpoints: Vec<Breakpoint> = Vec::new(); // For returning in the Response
+        let result = session_data.for_each_core(|target_core| {
+            let breakpoints = self.set_source_breakpoints(target_core, &args, &source_path)?;
+            merge_breakpoints(&mut created_breakpoints, breakpoints);
+            Ok(())
+        });
+
+        if let Err(error) = result {
+            return self.send_response::<()>(request, Err(&error));
+        }
+
+        let breakpoint_body = SetBreakpointsResponseBody {
+            breakpoints: created_breakpoints,
+        };
+        self.send_response(request, Ok(Some(breakpoint_body)))
+    }
 
-        if let Some(source_path) = args.source.path.as_ref() {
-            // Always clear existing breakpoints for the specified `[crate::debug_adapter::dap_types::Source]` before setting new ones.
-            // The DAP Specification doesn't make allowances for deleting and setting individual breakpoints for a specific `Source`.
-            match target_core.clear_breakpoints(BreakpointType::SourceBreakpoint {
+    /// Replace the breakpoints of `args.source` on a single core.
+    fn set_source_breakpoints(
+        &self,
+        target_core: &mut CoreHandle,
+        args: &SetBreakpointsArguments,
+        source_path: &TypedPathBuf,
+    ) -> Result<Vec<Breakpoint>> {
+        let mut created_breakpoints: Vec<Breakpoint> = Vec::new();
+
+        // Always clear existing breakpoints for the specified `[crate::debug_adapter::dap_types::Source]` before setting new ones.
+        // The DAP Specification doesn't make allowances for deleting and setting individual breakpoints for a specific `Source`.
+        target_core
+            .clear_breakpoints(BreakpointType::SourceBreakpoint {
                 source: Box::new(args.source.clone()),
                 location: SourceLocationScope::All,
-            }) {
-                Ok(_) => {}
-                Err(error) => {
-                    return self.send_response::<()>(
-                        request,
-                        Err(&DebuggerError::Other(anyhow!(
-                            "Failed to clear existing breakpoints before setting new ones : {}",
-                            error
-                        ))),
-                    );
-                }
+                log_message: None,
+            })
+            .context("Failed to clear existing breakpoints before setting new ones")?;
+
+        if let Some(requested_breakpoints) = args.breakpoints.as_ref() {
+            for bp in requested_breakpoints {
+                // Some overrides to improve breakpoint accuracy when `DebugInfo::get_breakpoint_location()` has to select the best from multiple options
+                let requested_breakpoint_line = if self.lines_start_at_1 {
+                    // If the debug client uses 1 based numbering, then we can use it as is.
+                    bp.line as u64
+                } else {
+                    // If the debug client uses 0 based numbering, then we bump the number by 1
+                    bp.line as u64 + 1
+                };
+                let requested_breakpoint_column = if self.columns_start_at_1 {
+                    // If the debug client uses 1 based numbering, then we can use it as is.
+                    Some(bp.column.unwrap_or(1) as u64)
+                } else {
+                    // If the debug client uses 0 based numbering, then we bump the number by 1
+                    Some(bp.column.unwrap_or(0) as u64 + 1)
+                };
+
+                match target_core.verify_and_set_breakpoint(
+                    source_path.to_path(),
+                    requested_breakpoint_line,
+                    requested_breakpoint_column,
+                    &args.source,
+                    bp.log_message.clone(),
+                ) {
+                    Ok(VerifiedBreakpoint {
+                        address,
+                        source_location,
+                    }) => created_breakpoints.push(Breakpoint {
+                        column: source_location.column.map(|col| match col {
+                            ColumnType::LeftEdge => 0_i64,
+                            ColumnType::Column(c) => c as i64,
+                        }),
+                        end_column: None,
+                        end_line: None,
+                        id: None,
+                        line: source_location.line.map(|line| line as i64),
+                        message: Some(if bp.log_message.is_some() {
+                            format!("Logpoint at memory address: {address:#010X}")
+                        } else {
+                            format!("Source breakpoint at memory address: {address:#010X}")
+                        }),
+                        source: Some(args.source.clone()),
+                        instruction_reference: Some(format!("{address:#010X}")),
+                        offset: None,
+                        verified: true,
+                        reason: None,
+                    }),
+                    Err(error) => created_breakpoints.push(Breakpoint {
+                        column: None,
+                        end_column: None,
+                        end_line: None,
+                        id: None,
+                        line: Some(bp.line),
+                        message: Some(error.to_string()),
+                        source: None,
+                        instruction_reference: None,
+                        offset: None,
+                        verified: false,
+                        reason: Some("failed".to_string()),
+                    }),
+                };
             }
+        }
 
-            // Assume that the path is native to the current OS
-            let source_path = NativePathBuf::from(source_path).to_typed_path_buf();
+        Ok(created_breakpoints)
+    }
 
-            if let Some(requested_breakpoints) = args.breakpoints.as_ref() {
-                for bp in requested_breakpoints {
-                    // Some overrides to improve breakpoint accuracy when `DebugInfo::get_breakpoint_location()` has to select the best from multiple options
-                    let requested_breakpoint_line = if self.lines_start_at_1 {
-                        // If the debug client uses 1 based numbering, then we can use it as is.
-                        bp.line as u64
-                    } else {
-                        // If the debug client uses 0 based numbering, then we bump the number by 1
-                        bp.line as u64 + 1
-                    };
-                    let requested_breakpoint_column = if self.columns_start_at_1 {
-                        // If the debug client uses 1 based numbering, then we can use it as is.
-                        Some(bp.column.unwrap_or(1) as u64)
-                    } else {
-                        // If the debug client uses 0 based numbering, then we bump the number by 1
-                        Some(bp.column.unwrap_or(0) as u64 + 1)
-                    };
-
-                    match target_core.verify_and_set_breakpoint(
-                        source_path.to_path(),
-                        requested_breakpoint_line,
-                        requested_breakpoint_column,
-                        &args.source,
-                    ) {
-                        Ok(VerifiedBreakpoint {
-                            address,
-                            source_location,
-                        }) => created_breakpoints.push(Breakpoint {
-                            column: source_location.column.map(|col| match col {
-                                ColumnType::LeftEdge => 0_i64,
-                                ColumnType::Column(c) => c as i64,
-                            }),
-                            end_column: None,
-                            end_line: None,
-                            id: None,
-                            line: source_location.line.map(|line| line as i64),
-                            message: Some(format!(
-                                "Source breakpoint at memory address: {address:#010X}"
-                            )),
-                            source: Some(args.source.clone()),
-                            instruction_reference: Some(format!("{address:#010X}")),
-                            offset: None,
-                            verified: true,
-                            reason: None,
-                        }),
-                        Err(error) => created_breakpoints.push(Breakpoint {
+    /// Set the function breakpoints on all cores. A breakpoint is reported as verified if at least one core could set it.
+    pub(crate) fn set_function_breakpoints(
+        &mut self,
+        session_data: &mut SessionData,
+        request: &Request,
+    ) -> Result<()> {
+        let arguments: SetFunctionBreakpointsArguments = get_arguments(self, request)?;
+
+        let mut breakpoints: Vec<Breakpoint> = Vec::new();
+        session_data.for_each_core(|target_core| {
+            // Always clear existing breakpoints before setting new ones.
+            if let Err(error) = target_core.clear_breakpoints(BreakpointType::FunctionBreakpoint {
+                name: String::new(),
+            }) {
+                tracing::warn!("Failed to clear function breakpoints. {}", error);
+            }
+
+            let core_breakpoints = arguments
+                .breakpoints
+                .iter()
+                .map(|requested_breakpoint| {
+                    match target_core.set_function_breakpoint(&requested_breakpoint.name) {
+                        Ok(locations) => {
+                            let addresses = locations
+                                .iter()
+                                .map(|location| format!("{:#010X}", location.address))
+                                .collect::<Vec<_>>()
+                                .join(", ");
+                            // The client can only show one location, so we report the first one.
+                            let source_location = &locations[0].source_location;
+                            Breakpoint {
+                                column: source_location.column.map(|col| match col {
+                                    ColumnType::LeftEdge => 0_i64,
+                                    ColumnType::Column(c) => c as i64,
+                                }),
+                                end_column: None,
+                                end_line: None,
+                                id: None,
+                                line: source_location.line.map(|line| line as i64),
+                                message: Some(format!(
+                                    "Function breakpoint on `{}` at memory address: {addresses}",
+                                    requested_breakpoint.name
+                                )),
+                                source: get_dap_source(source_location),
+                                instruction_reference: Some(format!(
+                                    "{:#010X}",
+                                    locations[0].address
+                                )),
+                                offset: None,
+                                verified: true,
+                                reason: None,
+                            }
+                        }
+                        Err(error) => Breakpoint {
                             column: None,
                             end_column: None,
                             end_line: None,
                             id: None,
-                            line: Some(bp.line),
+                            line: None,
                             message: Some(error.to_string()),
                             source: None,
                             instruction_reference: None,
                             offset: None,
                             verified: false,
                             reason: Some("failed".to_string()),
-                        }),
-                    };
+                        },
+                    }
+                })
+                .collect();
+            merge_breakpoints(&mut breakpoints, core_breakpoints);
+            Ok(())
+        })?;
+
+        for breakpoint_response in &breakpoints {
+            if !breakpoint_response.verified {
+                if let Some(message) = &breakpoint_response.message {
+                    self.log_to_console(format!("Warning: {message}"));
                 }
             }
-
-            let breakpoint_body = SetBreakpointsResponseBody {
-                breakpoints: created_breakpoints,
-            };
-            self.send_response(request, Ok(Some(breakpoint_body)))
-        } else {
-            self.send_response::<()>(
-                request,
-                Err(&DebuggerError::Other(anyhow!(
-                    "Could not get a valid source path from arguments: {args:?}"
-                ))),
-            )
         }
+
+        self.send_response(
+            request,
+            Ok(Some(SetFunctionBreakpointsResponseBody { breakpoints })),
+        )
     }
 
+    /// Set the instruction breakpoints on all cores. A breakpoint is reported as verified if at least one core could set it.
     pub(crate) fn set_instruction_breakpoints(
         &mut self,
-        target_core: &mut CoreHandle,
+        session_data: &mut SessionData,
         request: &Request,
     ) -> Result<()> {
         let arguments: SetInstructionBreakpointsArguments = get_arguments(self, request)?;
 
-        // Always clear existing breakpoints before setting new ones.
-        match target_core.clear_breakpoints(BreakpointType::InstructionBreakpoint) {
-            Ok(_) => {}
-            Err(error) => tracing::warn!("Failed to clear instruction breakpoints. {}", error),
-        }
+        let mut breakpoints: Vec<Breakpoint> = Vec::new();
+        session_data.for_each_core(|target_core| {
+            // Always clear existing breakpoints before setting new ones.
+            match target_core.clear_breakpoints(BreakpointType::InstructionBreakpoint) {
+                Ok(_) => {}
+                Err(error) => tracing::warn!("Failed to clear instruction breakpoints. {}", error),
+            }
 
-        let instruction_breakpoint_body = SetInstructionBreakpointsResponseBody {
-            breakpoints: arguments
+            let core_breakpoints = arguments
                 .breakpoints
-                .into_iter()
+                .iter()
                 .map(|requested_breakpoint| {
-                    set_instruction_breakpoint(requested_breakpoint, target_core)
+                    set_instruction_breakpoint(requested_breakpoint.clone(), target_core)
                 })
-                .collect(),
-        };
+                .collect();
+            merge_breakpoints(&mut breakpoints, core_breakpoints);
+            Ok(())
+        })?;
+
+        let instruction_breakpoint_body = SetInstructionBreakpointsResponseBody { breakpoints };
 
         // In addition to the response values, also show a message to users for any breakpoints that could not be verified.
         for breakpoint_response in &instruction_breakpoint_body.breakpoints {
@@ -978,28 +1290,147 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
         self.send_response(request, Ok(Some(instruction_breakpoint_body)))
     }
 
-    pub(crate) fn threads(
+    /// Describe the data that a data breakpoint can be set on, which is either a variable in memory,
+    /// or an address range if `asAddress` is set.
+    pub(crate) fn data_breakpoint_info(
         &mut self,
         target_core: &mut CoreHandle,
         request: &Request,
     ) -> Result<()> {
-        // TODO: Implement actual thread resolution. For now, we just use the core id as the thread id.
-        let current_core_status = target_core.core.status()?;
-        let mut threads: Vec<Thread> = vec![];
+        let arguments: DataBreakpointInfoArguments = get_arguments(self, request)?;
+
+        let data = if arguments.as_address == Some(true) {
+            let length = arguments.bytes.unwrap_or(4) as u64;
+            MemoryAddress::try_from(arguments.name.as_str())
+                .ok()
+                .map(|MemoryAddress(address)| (arguments.name.clone(), address, length))
+        } else if let Some(variables_reference) = arguments.variables_reference {
+            target_core
+                .core_data
+                .find_variable(variables_reference.into(), &arguments.name)
+                .and_then(|variable| match variable.memory_location {
+                    VariableLocation::Address(address) => {
+                        Some((variable.name.to_string(), address, variable.byte_size?))
+                    }
+                    _ => None,
+                })
+        } else {
+            None
+        };
+
+        let body = match (data, target_core.core.available_watchpoint_units()) {
+            (_, Err(_) | Ok(0)) => DataBreakpointInfoResponseBody {
+                data_id: None,
+                description: "The core does not support data breakpoints".to_string(),
+                access_types: None,
+                can_persist: None,
+            },
+            (None, _) => DataBreakpointInfoResponseBody {
+                data_id: None,
+                description: format!(
+                    "Data breakpoints can only be set on variables in memory, and `{}` is not",
+                    arguments.name
+                ),
+                access_types: None,
+                can_persist: None,
+            },
+            (Some((name, address, length)), Ok(units)) => DataBreakpointInfoResponseBody {
+                data_id: Some(format!("{address:#010x}:{length}")),
+                description: format!(
+                    "{name} ({length} bytes at {address:#010x}, the core has {units} data breakpoint units)"
+                ),
+                access_types: Some(vec![
+                    DataBreakpointAccessType::Write,
+                    DataBreakpointAccessType::Read,
+                    DataBreakpointAccessType::ReadWrite,
+                ]),
+                // Addresses remain valid across sessions, variables may move between builds.
+                can_persist: Some(arguments.as_address == Some(true)),
+            },
+        };
+
+        self.send_response(request, Ok(Some(body)))
+    }
+
+    /// Replace the data breakpoints on all cores. A breakpoint is reported as verified if at least one core could set it.
+    pub(crate) fn set_data_breakpoints(
+        &mut self,
+        session_data: &mut SessionData,
+        request: &Request,
+    ) -> Result<()> {
+        let arguments: SetDataBreakpointsArguments = get_arguments(self, request)?;
+
+        let mut breakpoints: Vec<Breakpoint> = Vec::new();
+        session_data.for_each_core(|target_core| {
+            target_core.clear_watchpoints()?;
+
+            let core_breakpoints = arguments
+                .breakpoints
+                .iter()
+                .map(|requested_breakpoint| set_data_breakpoint(requested_breakpoint, target_core))
+                .collect();
+            merge_breakpoints(&mut breakpoints, core_breakpoints);
+            Ok(())
+        })?;
+
+        // In addition to the response values, also show a message to users for any breakpoints that could not be verified.
+        for breakpoint in &breakpoints {
+            if !breakpoint.verified {
+                if let Some(message) = &breakpoint.message {
+                    self.log_to_console(format!("Warning: {message}"));
+                    self.show_message(MessageSeverity::Warning, message.clone());
+                }
+            }
+        }
+
+        self.send_response(
+            request,
+            Ok(Some(SetDataBreakpointsResponseBody { breakpoints })),
+        )
+    }
+
+    /// Each core is reported as a thread, with the core index as the thread id.
+    ///
+    /// If an RTOS was detected, the threads of the RTOS that are not running are reported as well. The running RTOS
+    /// thread is the core itself.
+    pub(crate) fn threads(&mut self, session_data: &SessionData, request: &Request) -> Result<()> {
         if self.configuration_is_done() {
             // We can handle this request normally.
-            let single_thread = Thread {
-                id: target_core.core.id() as i64,
-                name: target_core.core_data.target_name.clone(),
-            };
-            threads.push(single_thread);
+            let mut threads = Vec::new();
+            for core_data in &session_data.core_data {
+                let running_thread = core_data
+                    .rtos_threads
+                    .iter()
+                    .find(|rtos_thread| rtos_thread.thread.is_running());
+                threads.push(Thread {
+                    id: core_data.core_index as i64,
+                    name: match running_thread {
+                        Some(rtos_thread) => {
+                            format!("{} ({})", core_data.target_name, rtos_thread.thread.name)
+                        }
+                        None => core_data.target_name.clone(),
+                    },
+                });
+                threads.extend(
+                    core_data
+                        .rtos_threads
+                        .iter()
+                        .filter(|rtos_thread| !rtos_thread.thread.is_running())
+                        .map(|rtos_thread| Thread {
+                            id: rtos_thread.id,
+                            name: format!(
+                                "{} ({})",
+                                rtos_thread.thread.name, rtos_thread.thread.state
+                            ),
+                        }),
+                );
+            }
             return self.send_response(request, Ok(Some(ThreadsResponseBody { threads })));
         }
         self.send_response::<()>(
             request,
             Err(&DebuggerError::Other(anyhow!(
-                "Received request for `threads`, while last known core status was {:?}",
-                current_core_status
+                "Received request for `threads`, before the configuration is done"
             ))),
         )
     }
@@ -1026,9 +1457,12 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
         };
 
         let arguments: StackTraceArguments = get_arguments(self, request)?;
+        let stack_frames = target_core
+            .core_data
+            .thread_stack_frames(arguments.thread_id);
 
         // If the core is halted, and we have no available strackframes, we can get out of here early.
-        if target_core.core_data.stack_frames.is_empty() {
+        if stack_frames.is_empty() {
             let body = StackTraceResponseBody {
                 stack_frames: Vec::new(),
                 total_frames: Some(0),
@@ -1043,11 +1477,11 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
 
         // Update the `levels` to the number of available frames if it is 0.
         if levels == 0 {
-            levels = target_core.core_data.stack_frames.len() as i64;
+            levels = stack_frames.len() as i64;
         }
 
         // Determine the correct 'slice' of available [StackFrame]s to serve up ...
-        let total_frames = target_core.core_data.stack_frames.len() as i64;
+        let total_frames = stack_frames.len() as i64;
 
         // We need to copy some parts of StackFrame so that we can re-use it later without references to target_core.
         struct PartialStackFrameData {
@@ -1060,34 +1494,20 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
 
         let frame_set = if levels == 1 && start_frame == 0 {
             // Just the first frame - use the LHS of the split at `levels`
-            target_core
-                .core_data
-                .stack_frames
-                .split_at(levels as usize)
-                .0
+            stack_frames.split_at(levels as usize).0
         } else if total_frames <= 20 && start_frame >= 0 && start_frame <= total_frames {
             // When we have less than 20 frames - use the RHS of of the split at `start_frame`
-            target_core
-                .core_data
-                .stack_frames
-                .split_at(start_frame as usize)
-                .1
+            stack_frames.split_at(start_frame as usize).1
         } else if total_frames > 20 && start_frame + levels <= total_frames {
             // When we have more than 20 frames - we can safely split twice
-            target_core
-                .core_data
-                .stack_frames
+            stack_frames
                 .split_at(start_frame as usize)
                 .1
                 .split_at(levels as usize)
                 .0
         } else if total_frames > 20 && start_frame + levels > total_frames {
             // The MS DAP spec may also ask for more frames than what we reported.
-            target_core
-                .core_data
-                .stack_frames
-                .split_at(start_frame as usize)
-                .1
+            stack_frames.split_at(start_frame as usize).1
         } else {
             return self.send_response::<()>(
                 request,
@@ -1274,6 +1694,8 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
         instruction_offset: i64,
         // The EXACT number of instructions to return in the result.
         instruction_count: i64,
+        // Whether to label instructions with the symbols that start at their address.
+        resolve_symbols: bool,
     ) -> Result<Vec<dap_types::DisassembledInstruction>, DebuggerError> {
         let assembly_lines = disassemble_target_memory(
             target_core,
@@ -1281,6 +1703,7 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
             byte_offset,
             memory_reference as u64,
             instruction_count,
+            resolve_symbols,
         )?;
 
         if assembly_lines.is_empty() {
@@ -1328,6 +1751,7 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
                 arguments.offset.unwrap_or(0_i64),
                 arguments.instruction_offset.unwrap_or(0_i64),
                 arguments.instruction_count,
+                arguments.resolve_symbols.unwrap_or(false),
             ) {
                 Ok(disassembled_instructions) => self.send_response(
                     request,
@@ -1394,7 +1818,14 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
                             memory_reference: variable.memory_reference(),
                             indexed_variables: None,
                             named_variables: Some(named_child_variables_cnt),
-                            presentation_hint: None,
+                            presentation_hint: (!variable.is_writable()).then(|| {
+                                VariablePresentationHint {
+                                    attributes: Some(vec!["readOnly".to_string()]),
+                                    kind: None,
+                                    lazy: None,
+                                    visibility: None,
+                                }
+                            }),
                             type_: variable.type_name(),
                             value: {
                                 // The SVD cache is not automatically refreshed on every stack trace, and we only need to refresh the field values.
@@ -1440,7 +1871,10 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
         }
 
         if parent_variable.is_none() {
-            for stack_frame in target_core.core_data.stack_frames.iter_mut() {
+            for stack_frame in CoreData::all_stack_frames_mut(
+                &mut target_core.core_data.stack_frames,
+                &mut target_core.core_data.rtos_threads,
+            ) {
                 if let Some(search_cache) = &mut stack_frame.local_variables {
                     if let Some(search_variable) = search_cache.get_variable_by_key(variable_ref) {
                         parent_variable = Some(search_variable);
@@ -1464,7 +1898,10 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
                         .map(|register| Variable {
                             name: register.get_register_name(),
                             evaluate_name: Some(register.get_register_name()),
-                            memory_reference: None,
+                            memory_reference: get_register_memory_reference(
+                                &target_core.core,
+                                register.value,
+                            ),
                             indexed_variables: None,
                             named_variables: None,
                             presentation_hint: None, // TODO: Implement hint as Hex for registers
@@ -1536,7 +1973,7 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
                         // Do NOT use evaluate_name. It is impossible to distinguish between duplicate variable
                         // TODO: Implement qualified names.
                         evaluate_name: None,
-                        memory_reference: Some(variable.memory_location.to_string()),
+                        memory_reference: get_memory_reference(&variable.memory_location),
                         indexed_variables: Some(indexed_child_variables_cnt),
                         named_variables: Some(named_child_variables_cnt),
                         presentation_hint: None,
@@ -1629,6 +2066,7 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
     }
 
     /// Steps through the code at the requested granularity.
+    /// - [SteppingMode::IntoTarget]: If the user picked one of the targets returned by the `stepInTargets` request.
     /// - [SteppingMode::StepInstruction]: If MS DAP [SteppingGranularity::Instruction] (usually sent from the disassembly view)
     /// - [SteppingMode::IntoStatement]: In all other cases.
     pub(crate) fn step_in(
@@ -1638,13 +2076,44 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
     ) -> Result<()> {
         let arguments: StepInArguments = get_arguments(self, request)?;
 
-        let stepping_granularity = match arguments.granularity {
-            Some(SteppingGranularity::Instruction) => SteppingMode::StepInstruction,
-            _ => SteppingMode::IntoStatement,
+        let stepping_granularity = match (arguments.target_id, arguments.granularity) {
+            (Some(target_id), _) => SteppingMode::IntoTarget(target_id as u64),
+            (None, Some(SteppingGranularity::Instruction)) => SteppingMode::StepInstruction,
+            (None, _) => SteppingMode::IntoStatement,
         };
         self.debug_step(stepping_granularity, target_core, request)
     }
 
+    /// List the functions called by the current statement of the requested stack frame, so that
+    /// the user can choose which one to step into.
+    pub(crate) fn step_in_targets(
+        &mut self,
+        target_core: &mut CoreHandle,
+        request: &Request,
+    ) -> Result<()> {
+        let arguments: StepInTargetsArguments = get_arguments(self, request)?;
+
+        let frame_id = ObjectRef::from(arguments.frame_id);
+        let Some(program_counter): Option<u64> = target_core
+            .core_data
+            .stack_frames
+            .iter()
+            .find(|stack_frame| stack_frame.id == frame_id)
+            .and_then(|stack_frame| stack_frame.pc.try_into().ok())
+        else {
+            return self.send_response::<()>(
+                request,
+                Err(&DebuggerError::Other(anyhow!(
+                    "Unknown stack frame: {}",
+                    arguments.frame_id
+                ))),
+            );
+        };
+
+        let targets = get_step_in_targets(target_core, program_counter)?;
+        self.send_response(request, Ok(Some(StepInTargetsResponseBody { targets })))
+    }
+
     /// Steps through the code at the requested granularity.
     /// - [SteppingMode::StepInstruction]: If MS DAP [SteppingGranularity::Instruction] (usually sent from the disassembly view)
     /// - [SteppingMode::OutOfStatement]: In all other cases.
@@ -1790,16 +2259,19 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
     }
 
     /// Send a custom `probe-rs-rtt-channel-config` event to the MS DAP Client, to create a window for a specific RTT channel.
+    /// If `down_channel_number` is set, the window accepts input, which the client sends with the `rttWindowInput` request.
     pub fn rtt_window(
         &mut self,
         channel_number: u32,
         channel_name: String,
         data_format: rtt::DataFormat,
+        down_channel_number: Option<u32>,
     ) -> bool {
         let Ok(event_body) = serde_json::to_value(RttChannelEventBody {
             channel_number,
             channel_name,
             data_format,
+            down_channel_number,
         }) else {
             return false;
         };
@@ -26,13 +26,20 @@ impl DapStatus for CoreStatus {
                         }
                     ),
                 ),
-                HaltReason::Exception => (
+                HaltReason::Exception(Some(exception)) => ("exception", exception.to_string()),
+                HaltReason::Exception(None) => (
                     "exception",
                     "Core halted due to an exception, e.g. interupt handler".to_string(),
                 ),
-                HaltReason::Watchpoint => (
+                HaltReason::Watchpoint(address) => (
                     "data breakpoint",
-                    "Core halted due to a watchpoint or data breakpoint".to_string(),
+                    if let Some(address) = address {
+                        format!(
+                            "Core halted due to a watchpoint or data breakpoint on {address:#010x}"
+                        )
+                    } else {
+                        "Core halted due to a watchpoint or data breakpoint".to_string()
+                    },
                 ),
                 HaltReason::Step => (
                     "step",
@@ -4,7 +4,7 @@ use crate::util::rtt;
 use num_traits::Num;
 use parse_int::parse;
 use serde::{Deserialize, Serialize};
-use std::fmt::Display;
+use std::{fmt::Display, ops::Range, path::PathBuf};
 
 // Convert the MSDAP `debugAdaptor.json` file into Rust types.
 schemafy::schemafy!(root: debugserver_types "src/bin/probe-rs/cmd/dap_server/debug_adapter/dap/debugProtocol.json");
@@ -72,12 +72,64 @@ pub struct RttWindowOpenedArguments {
     pub window_is_open: bool,
 }
 
+/// Custom [`RttWindowInput`] request, so that VSCode can send the input typed into the window of an RTT channel to the target.
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub struct RttWindowInput {
+    /// Object containing arguments for the command.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<RttWindowInputArguments>,
+    /// The command to execute.
+    pub command: String,
+    /// Sequence number (also known as message ID). For protocol messages of type `request` this ID
+    /// can be used to cancel the request.
+    pub seq: i64,
+    /// Message type.
+    #[serde(rename = "type")]
+    pub type_: String,
+}
+///  Arguments for [`RttWindowInput`] request.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RttWindowInputArguments {
+    /// The RTT (up) channel number of the window.
+    pub channel_number: u32,
+    /// The input, which is written to the down channel as is.
+    pub data: String,
+}
+
+///  Arguments for the custom `coreDump` request, which stores a core dump of a halted core.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoreDumpArguments {
+    /// The core to dump. Defaults to the core that halted last.
+    pub thread_id: Option<i64>,
+    /// Where to store the core dump. Defaults to `./coredump`.
+    pub path: Option<PathBuf>,
+    /// The memory ranges to include, as `{ "start": ..., "end": ... }`. Defaults to the ranges in
+    /// the core configuration, or the memory referenced by the variables in scope.
+    #[serde(default)]
+    pub ranges: Vec<Range<u64>>,
+}
+
+///  Response to the custom `coreDump` request.
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoreDumpResponseBody {
+    /// Where the core dump was stored.
+    pub path: PathBuf,
+    /// The memory ranges included in the core dump.
+    pub ranges: Vec<Range<u64>>,
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RttChannelEventBody {
     pub channel_number: u32,
     pub channel_name: String,
     pub data_format: rtt::DataFormat,
+    /// The down channel that receives the input of the window, if the window accepts input.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub down_channel_number: Option<u32>,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
@@ -138,6 +190,9 @@ impl Display for Source {
 
 impl Display for DisassembledInstruction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(symbol) = &self.symbol {
+            writeln!(f, "<{symbol}>:")?;
+        }
         writeln!(
             f,
             "{} : [{:<12}] {:<40}  {}",
@@ -0,0 +1,49 @@
+/// The characters of operators which can precede `=` without it being an assignment.
+const OPERATOR_CHARACTERS: &[u8] = b"=!<>+-*/%&|^";
+
+/// Split an assignment expression like `foo = 5` into its target (`foo`) and value (`5`).
+/// Comparisons like `==`, `!=`, `<=` and `>=`, and compound assignments like `+=` or `<<=`, are not
+/// assignments.
+pub(crate) fn split_assignment(expression: &str) -> Option<(&str, &str)> {
+    let bytes = expression.as_bytes();
+    let position = bytes.iter().enumerate().position(|(index, &byte)| {
+        byte == b'='
+            && bytes.get(index + 1) != Some(&b'=')
+            && !index
+                .checked_sub(1)
+                .is_some_and(|previous| OPERATOR_CHARACTERS.contains(&bytes[previous]))
+    })?;
+
+    let target = expression[..position].trim();
+    let value = expression[position + 1..].trim();
+    (!target.is_empty() && !value.is_empty()).then_some((target, value))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("foo = 5", Some(("foo", "5")); "variable")]
+    #[test_case("$r3 = 0x20", Some(("$r3", "0x20")); "register")]
+    #[test_case("*ptr=1+2", Some(("*ptr", "1+2")); "pointer")]
+    #[test_case("foo == 5", None; "equality")]
+    #[test_case("foo <= 5", None; "comparison")]
+    #[test_case("foo != 5", None; "inequality")]
+    #[test_case("foo += 1", None; "compound addition")]
+    #[test_case("foo -= 1", None; "compound subtraction")]
+    #[test_case("foo *= 2", None; "compound multiplication")]
+    #[test_case("foo /= 2", None; "compound division")]
+    #[test_case("foo %= 2", None; "compound remainder")]
+    #[test_case("foo &= 1", None; "compound and")]
+    #[test_case("foo |= 1", None; "compound or")]
+    #[test_case("foo ^= 1", None; "compound xor")]
+    #[test_case("foo <<= 1", None; "compound left shift")]
+    #[test_case("foo >>= 1", None; "compound right shift")]
+    #[test_case("foo = -1", Some(("foo", "-1")); "negative value")]
+    #[test_case("foo", None; "no assignment")]
+    #[test_case("= 5", None; "missing target")]
+    fn split_assignments(expression: &str, expected: Option<(&str, &str)>) {
+        assert_eq!(split_assignment(expression), expected);
+    }
+}
@@ -11,7 +11,7 @@ use crate::cmd::dap_server::{
     DebuggerError, debug_adapter::dap::dap_types::Breakpoint, server::core_data::CoreHandle,
 };
 use itertools::Itertools;
-use probe_rs::{CoreDump, CoreInterface, CoreStatus, HaltReason, RegisterValue};
+use probe_rs::{CoreInterface, CoreStatus, HaltReason, MemoryInterface, RegisterValue};
 use probe_rs_debug::{ColumnType, ObjectRef, StackFrame, VariableName};
 use std::{
     fmt::{Display, Write as _},
@@ -421,6 +421,22 @@ pub(crate) static REPL_COMMANDS: &[ReplCommand<ReplHandler>] = &[
             get_local_variable(evaluate_arguments, target_core, variable_name, gdb_nuf)
         },
     },
+    ReplCommand {
+        command: "peripheral",
+        help_text: "Show the registers of a peripheral from the SVD file, or the fields of one of its registers.",
+        sub_commands: &[],
+        args: &[ReplCommandArgs::Required("peripheral[.register]")],
+        handler: |target_core, command_arguments, _| {
+            let Some(name) = command_arguments.split_whitespace().next() else {
+                return Err(DebuggerError::UserMessage(
+                    "Missing peripheral name. See the `help` command for more information."
+                        .to_string(),
+                ));
+            };
+
+            show_peripheral(target_core, name)
+        },
+    },
     ReplCommand {
         command: "x",
         help_text: "Examine Memory, using format specifications, at the specified address.",
@@ -496,6 +512,106 @@ pub(crate) static REPL_COMMANDS: &[ReplCommand<ReplHandler>] = &[
             memory_read(input_address, gdb_nuf, target_core)
         },
     },
+    ReplCommand {
+        command: "set",
+        help_text: "Write a value to memory at the specified address.",
+        sub_commands: &[],
+        args: &[
+            ReplCommandArgs::Optional("/u (u=unit[b|h|w|g], default w)"),
+            ReplCommandArgs::Required("address (hex)"),
+            ReplCommandArgs::Required("value"),
+        ],
+        handler: |target_core, command_arguments, _| {
+            let mut input_arguments = command_arguments.split_whitespace().peekable();
+
+            let mut unit = GdbUnit::Word;
+            if let Some(unit_string) = input_arguments
+                .peek()
+                .and_then(|argument| argument.strip_prefix('/'))
+            {
+                let mut unit_chars = unit_string.chars();
+                unit = match (unit_chars.next(), unit_chars.next()) {
+                    (Some(unit_char), None) => GdbUnit::try_from(&unit_char)?,
+                    _ => {
+                        return Err(DebuggerError::UserMessage(
+                            "The '/' specifier must be followed by a single unit [b|h|w|g]."
+                                .to_string(),
+                        ));
+                    }
+                };
+                input_arguments.next();
+            }
+
+            let (Some(address_str), Some(value_str), None) = (
+                input_arguments.next(),
+                input_arguments.next(),
+                input_arguments.next(),
+            ) else {
+                return Err(DebuggerError::UserMessage(
+                    "Invalid parameters. See the `help` command for more information.".to_string(),
+                ));
+            };
+            let MemoryAddress(address) = address_str.try_into()?;
+            let value = parse_int::parse::<u64>(value_str).map_err(|error| {
+                DebuggerError::UserMessage(format!("Invalid value {value_str}: {error}"))
+            })?;
+
+            let out_of_range = || {
+                DebuggerError::UserMessage(format!(
+                    "The value {value_str} does not fit into a {unit}."
+                ))
+            };
+            match unit {
+                GdbUnit::Byte => target_core
+                    .core
+                    .write_word_8(address, value.try_into().map_err(|_| out_of_range())?)?,
+                GdbUnit::HalfWord => target_core
+                    .core
+                    .write_word_16(address, value.try_into().map_err(|_| out_of_range())?)?,
+                GdbUnit::Word => target_core
+                    .core
+                    .write_word_32(address, value.try_into().map_err(|_| out_of_range())?)?,
+                GdbUnit::Giant => target_core.core.write_word_64(address, value)?,
+            }
+            target_core.core_data.memory_cache.invalidate();
+
+            Ok(Response {
+                command: "set".to_string(),
+                success: true,
+                message: Some(format!("Wrote {value:#x} to {address:#010x}")),
+                type_: "response".to_string(),
+                request_seq: 0,
+                seq: 0,
+                body: None,
+            })
+        },
+    },
+    ReplCommand {
+        command: "reset",
+        help_text: "Reset the target, and halt it at the first instruction.",
+        sub_commands: &[],
+        args: &[],
+        handler: |target_core, _, _| {
+            let core_info = target_core
+                .core
+                .reset_and_halt(Duration::from_millis(500))?;
+            target_core.core_data.memory_cache.invalidate();
+
+            Ok(Response {
+                command: "pause".to_string(),
+                success: true,
+                message: Some(
+                    CoreStatus::Halted(HaltReason::Request)
+                        .short_long_status(Some(core_info.pc))
+                        .1,
+                ),
+                type_: "response".to_string(),
+                request_seq: 0,
+                seq: 0,
+                body: None,
+            })
+        },
+    },
     ReplCommand {
         command: "dump",
         help_text: "Create a core dump at a target location. Specify memory ranges to dump, or leave blank to dump in-scope memory regions.",
@@ -520,10 +636,10 @@ pub(crate) static REPL_COMMANDS: &[ReplCommand<ReplHandler>] = &[
             );
 
             let ranges = if args.is_empty() {
-                // No specific memory ranges were requested, so we will dump the
-                // memory ranges we know are specifically referenced by the variables
-                // in the current scope.
-                target_core.get_memory_ranges()
+                // No specific memory ranges were requested, so we will dump the configured
+                // memory ranges, or the ones we know are specifically referenced by the
+                // variables in the current scope.
+                vec![]
             } else {
                 args
                 .chunks(2)
@@ -546,6 +662,7 @@ pub(crate) static REPL_COMMANDS: &[ReplCommand<ReplHandler>] = &[
                 })
                 .collect::<Result<Vec<Range<u64>>, _>>()?
             };
+            let ranges = target_core.store_core_dump(ranges, location)?;
             let mut range_string = String::new();
             for memory_range in &ranges {
                 if !range_string.is_empty() {
@@ -558,8 +675,6 @@ pub(crate) static REPL_COMMANDS: &[ReplCommand<ReplHandler>] = &[
             } else {
                 format!("(Includes memory ranges: {range_string})")
             };
-            CoreDump::dump_core(&mut target_core.core, ranges)?.store(location)?;
-
             Ok(Response {
                 command: "dump".to_string(),
                 success: true,
@@ -1,7 +1,9 @@
-use probe_rs::MemoryInterface;
+use probe_rs::{MemoryInterface, config::Register};
 use probe_rs_debug::{ObjectRef, VariableName};
 
-use crate::cmd::dap_server::{DebuggerError, server::core_data::CoreHandle};
+use crate::cmd::dap_server::{
+    DebuggerError, peripherals::svd_cache::read_register, server::core_data::CoreHandle,
+};
 
 use super::{
     dap_types::{
@@ -10,7 +12,7 @@ use super::{
     },
     repl_commands::{REPL_COMMANDS, ReplCommand, ReplHandler},
     repl_types::*,
-    request_helpers::disassemble_target_memory,
+    request_helpers::{disassemble_target_memory, get_memory_reference},
 };
 
 /// Format the `variable` and add it to the `response_body.result` for display to the user.
@@ -83,7 +85,7 @@ pub(crate) fn get_local_variable(
 
     for variable in variable_list {
         if gdb_nuf.format_specifier == GdbFormat::DapReference {
-            response_body.memory_reference = Some(variable.memory_location.to_string());
+            response_body.memory_reference = get_memory_reference(&variable.memory_location);
             response_body.result = format!(
                 "{} : {} ",
                 variable.name,
@@ -106,6 +108,118 @@ pub(crate) fn get_local_variable(
     Ok(response)
 }
 
+/// Show the registers of a peripheral, or the fields of one of its registers, with their current
+/// values. The `name` is either `PERIPHERAL` or `PERIPHERAL.REGISTER`.
+pub(crate) fn show_peripheral(
+    target_core: &mut CoreHandle,
+    name: &str,
+) -> Result<Response, DebuggerError> {
+    let Some(peripherals) = target_core
+        .core_data
+        .core_peripherals
+        .as_ref()
+        .map(|core_peripherals| core_peripherals.peripherals.clone())
+    else {
+        return Err(DebuggerError::UserMessage(
+            "No peripherals available. Specify an SVD file with `--svd`, or use a target description which references one.".to_string(),
+        ));
+    };
+
+    let (peripheral_name, register_name) = match name.split_once('.') {
+        Some((peripheral_name, register_name)) => (peripheral_name, Some(register_name)),
+        None => (name, None),
+    };
+    let Some(peripheral) = peripherals.peripheral(peripheral_name) else {
+        return Err(DebuggerError::UserMessage(format!(
+            "No peripheral named {peripheral_name:?}."
+        )));
+    };
+
+    let mut message = String::new();
+    match register_name {
+        None => {
+            message.push_str(&format!(
+                "{} @ {:#010x}",
+                peripheral.name, peripheral.base_address
+            ));
+            for register in &peripheral.registers {
+                let value = read_peripheral_register(target_core, register)?;
+                message.push_str(&format!(
+                    "\n  {:<20} @ {:#010x}: {}",
+                    register.name,
+                    register.address,
+                    format_register_value(register, value)
+                ));
+            }
+        }
+        Some(register_name) => {
+            let Some(register) = peripheral.register(register_name) else {
+                return Err(DebuggerError::UserMessage(format!(
+                    "No register named {register_name:?} in peripheral {}.",
+                    peripheral.name
+                )));
+            };
+
+            let value = read_peripheral_register(target_core, register)?;
+            message.push_str(&format!(
+                "{}.{} @ {:#010x}: {}",
+                peripheral.name,
+                register.name,
+                register.address,
+                format_register_value(register, value)
+            ));
+            for field in &register.fields {
+                message.push_str(&format!(
+                    "\n  {:<20} [{}:{}]",
+                    field.name,
+                    field.bit_offset + field.bit_width - 1,
+                    field.bit_offset
+                ));
+                if let Some(value) = value {
+                    let field_value = field.extract(value);
+                    message.push_str(&format!(": {field_value:#x}"));
+                    if let Some(enumerated_value) = field.enumerated_value(field_value) {
+                        message.push_str(&format!(" ({})", enumerated_value.name));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(Response {
+        command: "peripheral".to_string(),
+        success: true,
+        message: Some(message),
+        type_: "response".to_string(),
+        request_seq: 0,
+        seq: 0,
+        body: None,
+    })
+}
+
+/// Reads a peripheral register, unless reading it has side effects.
+fn read_peripheral_register(
+    target_core: &mut CoreHandle,
+    register: &Register,
+) -> Result<Option<u64>, DebuggerError> {
+    if register.read_action || register.access.is_some_and(|access| !access.can_read()) {
+        return Ok(None);
+    }
+
+    Ok(Some(read_register(
+        &mut target_core.core,
+        register.address,
+        register.size,
+    )?))
+}
+
+fn format_register_value(register: &Register, value: Option<u64>) -> String {
+    match value {
+        Some(value) => format!("{value:#0width$x}", width = register.size as usize / 4 + 2),
+        None => "<not read>".to_string(),
+    }
+}
+
 /// Read memory at the specified address (hex), using the [`GdbNuf`] specifiers to determine size and format.
 pub(crate) fn memory_read(
     address: u64,
@@ -128,6 +242,7 @@ pub(crate) fn memory_read(
             0_i64,
             address,
             gdb_nuf.unit_count as i64,
+            true,
         )?;
         if assembly_lines.is_empty() {
             return Err(DebuggerError::UserMessage(format!(
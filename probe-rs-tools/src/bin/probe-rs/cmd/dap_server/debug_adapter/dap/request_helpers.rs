@@ -7,16 +7,27 @@ use crate::cmd::dap_server::{
 use addr2line::gimli::RunTimeEndian;
 use anyhow::{Result, anyhow};
 use capstone::{
-    Endian, arch::arm::ArchMode as armArchMode, arch::arm64::ArchMode as aarch64ArchMode,
-    arch::riscv::ArchMode as riscvArchMode, prelude::*,
+    Endian, InsnGroupId, InsnGroupType,
+    arch::{
+        ArchOperand,
+        arm::{ArchMode as armArchMode, ArmOperand, ArmOperandType},
+        arm64::{ArchMode as aarch64ArchMode, Arm64Operand, Arm64OperandType},
+        riscv::{ArchMode as riscvArchMode, RiscVOperand},
+    },
+    prelude::*,
 };
 use itertools::Itertools;
-use probe_rs::{CoreType, Error, InstructionSet, MemoryInterface};
-use probe_rs_debug::{ColumnType, ObjectRef, SourceLocation};
-use std::{sync::LazyLock, time::Duration};
+use probe_rs::{
+    Core, CoreType, Error, InstructionSet, MemoryInterface, RegisterValue, WatchpointAccess,
+};
+use probe_rs_debug::{ColumnType, ObjectRef, SourceLocation, VariableLocation};
+use std::{ops::Range, sync::LazyLock, time::Duration};
 use typed_path::TypedPathBuf;
 
-use super::dap_types::{Breakpoint, InstructionBreakpoint, MemoryAddress};
+use super::dap_types::{
+    Breakpoint, DataBreakpoint, DataBreakpointAccessType, InstructionBreakpoint, MemoryAddress,
+    StepInTarget,
+};
 
 // Source file mapping for rustlib, e.g. Some(("/rustc/<hash>", "<sysroot>/lib/rustlib/src/rust"))
 // This can be None if rustc is not found or gives bad output
@@ -94,6 +105,7 @@ pub(crate) fn disassemble_target_memory(
     byte_offset: i64,
     memory_reference: u64,
     instruction_count: i64,
+    resolve_symbols: bool,
 ) -> Result<Vec<DisassembledInstruction>, DebuggerError> {
     let instruction_set = target_core.core.instruction_set()?;
     match instruction_set {
@@ -339,7 +351,16 @@ pub(crate) fn disassemble_target_memory(
                     ),
                     line,
                     location,
-                    symbol: None,
+                    // Label the first instruction of functions, and other symbols in the code.
+                    symbol: if resolve_symbols {
+                        target_core
+                            .core_data
+                            .debug_info
+                            .symbol_at(instruction.address())
+                            .map(|symbol| symbol.display_name().to_string())
+                    } else {
+                        None
+                    },
                     presentation_hint: None,
                 });
 
@@ -383,6 +404,152 @@ pub(crate) fn disassemble_target_memory(
     Ok(disassembled_instructions)
 }
 
+/// Find the functions that are called directly by the statement at `program_counter`, so that the
+/// user can choose which one to step into.
+/// - The statement is the sequence of instructions that share the source line of `program_counter`.
+/// - Indirect calls (e.g. through a register) are ignored, because their target is only known at runtime.
+pub(crate) fn get_step_in_targets(
+    target_core: &mut CoreHandle,
+    program_counter: u64,
+) -> Result<Vec<StepInTarget>, DebuggerError> {
+    // Statements with more instructions than this are unlikely, and would be a slow read.
+    const MAX_STATEMENT_INSTRUCTIONS: u64 = 64;
+
+    let Some(statement_location) = target_core
+        .core_data
+        .debug_info
+        .get_source_location(program_counter)
+    else {
+        return Ok(vec![]);
+    };
+
+    let instruction_set = target_core.core.instruction_set()?;
+    let min_instruction_size: u64 = instruction_set.get_minimum_instruction_size().into();
+    let max_instruction_size: u64 = instruction_set.get_maximum_instruction_size().into();
+
+    // Don't read past the end of the memory region or the function, which would fail, or
+    // disassemble unrelated code.
+    let debug_info = &target_core.core_data.debug_info;
+    let function_ranges = debug_info
+        .symbols()
+        .iter()
+        .filter(|symbol| symbol.size > 0)
+        .map(|symbol| {
+            let start = symbol.address & !1;
+            start..start + symbol.size
+        });
+    let region_ranges = target_core
+        .core
+        .memory_regions()
+        .map(|region| region.address_range());
+    let code_range = statement_code_range(
+        program_counter,
+        MAX_STATEMENT_INSTRUCTIONS * max_instruction_size,
+        function_ranges.chain(region_ranges),
+    );
+
+    let mut code = vec![0; (code_range.end - code_range.start) as usize];
+    target_core.core.read(program_counter, &mut code)?;
+    if target_core.core_data.debug_info.endianness() == RunTimeEndian::Big {
+        // See `disassemble_target_memory()` for why we swap per minimum sized instruction.
+        for instruction in code.chunks_mut(min_instruction_size as usize) {
+            instruction.reverse();
+        }
+    }
+
+    let mut cs_le = get_capstone_le(target_core)?;
+    cs_le
+        .set_detail(true)
+        .map_err(|err| anyhow!("Error configuring capstone: {:?}", err))?;
+    let instructions = cs_le
+        .disasm_all(&code, program_counter)
+        .map_err(|err| anyhow!("Error disassembling statement: {:?}", err))?;
+
+    let debug_info = &target_core.core_data.debug_info;
+    let call_group = InsnGroupId(InsnGroupType::CS_GRP_CALL as u8);
+    let mut step_in_targets: Vec<StepInTarget> = vec![];
+    for instruction in instructions.iter() {
+        let instruction_location = debug_info.get_source_location(instruction.address());
+        if instruction_location.as_ref().is_some_and(|location| {
+            location.path != statement_location.path || location.line != statement_location.line
+        }) {
+            // We have reached the next statement.
+            break;
+        }
+
+        let Ok(detail) = cs_le.insn_detail(instruction) else {
+            continue;
+        };
+        if !detail.groups().contains(&call_group) {
+            continue;
+        }
+
+        let immediate =
+            detail
+                .arch_detail()
+                .operands()
+                .into_iter()
+                .find_map(|operand| match operand {
+                    ArchOperand::ArmOperand(ArmOperand {
+                        op_type: ArmOperandType::Imm(immediate),
+                        ..
+                    }) => Some(immediate as u32 as u64),
+                    ArchOperand::Arm64Operand(Arm64Operand {
+                        op_type: Arm64OperandType::Imm(immediate),
+                        ..
+                    }) => Some(immediate as u64),
+                    ArchOperand::RiscVOperand(RiscVOperand::Imm(immediate)) => {
+                        Some(immediate as u64)
+                    }
+                    _ => None,
+                });
+        let Some(immediate) = immediate else {
+            continue;
+        };
+
+        // RISC-V reports the call target relative to the call instruction, while ARM reports the absolute address.
+        let target_address = [
+            immediate,
+            instruction.address().wrapping_add(immediate) & 0xFFFF_FFFF,
+        ]
+        .into_iter()
+        .find(|address| debug_info.symbol_at(*address).is_some())
+        .unwrap_or(immediate);
+
+        if step_in_targets
+            .iter()
+            .any(|step_in_target| step_in_target.id == target_address as i64)
+        {
+            continue;
+        }
+
+        let label = debug_info
+            .symbol_at(target_address)
+            .map(|symbol| symbol.display_name().to_string())
+            .unwrap_or_else(|| format!("{target_address:#010x}"));
+
+        step_in_targets.push(StepInTarget {
+            id: target_address as i64,
+            label,
+            line: instruction_location
+                .as_ref()
+                .and_then(|location| location.line)
+                .map(|line| line as i64),
+            column: instruction_location
+                .as_ref()
+                .and_then(|location| location.column)
+                .map(|column| match column {
+                    ColumnType::LeftEdge => 0_i64,
+                    ColumnType::Column(c) => c as i64,
+                }),
+            end_line: None,
+            end_column: None,
+        });
+    }
+
+    Ok(step_in_targets)
+}
+
 fn get_capstone_le(target_core: &mut CoreHandle) -> Result<Capstone, DebuggerError> {
     let mut cs = match target_core.core.instruction_set()? {
         InstructionSet::Thumb2 => {
@@ -567,6 +734,121 @@ pub(crate) fn get_svd_variable_reference(
     }
 }
 
+/// The DAP `memoryReference` of a variable, which is only available for variables stored in memory.
+pub(crate) fn get_memory_reference(memory_location: &VariableLocation) -> Option<String> {
+    match memory_location {
+        VariableLocation::Address(address) => Some(format!("{address:#010X}")),
+        _ => None,
+    }
+}
+
+/// The range of code that is read to find the calls of the statement at `program_counter`.
+///
+/// At most `max_length` bytes are read, and the range ends with the first of `bounds`, e.g. a
+/// function or a memory region, that contains `program_counter`.
+fn statement_code_range(
+    program_counter: u64,
+    max_length: u64,
+    bounds: impl IntoIterator<Item = Range<u64>>,
+) -> Range<u64> {
+    let end = bounds
+        .into_iter()
+        .filter(|bound| bound.contains(&program_counter))
+        .map(|bound| bound.end)
+        .fold(program_counter.saturating_add(max_length), u64::min);
+
+    program_counter..end
+}
+
+/// The DAP `memoryReference` of a register, if its value points into one of the memory regions of
+/// the core, e.g. the stack pointer. This allows the client to open the memory it points to.
+pub(crate) fn get_register_memory_reference(
+    core: &Core<'_>,
+    value: Option<RegisterValue>,
+) -> Option<String> {
+    let address: u64 = value?.try_into().ok()?;
+    core.memory_regions()
+        .any(|region| region.address_range().contains(&address))
+        .then(|| format!("{address:#010X}"))
+}
+
+/// Merge the breakpoints that were set on one core into the breakpoints of the previous cores,
+/// keeping the first verified result for each requested breakpoint.
+pub(crate) fn merge_breakpoints(merged: &mut Vec<Breakpoint>, breakpoints: Vec<Breakpoint>) {
+    if merged.is_empty() {
+        *merged = breakpoints;
+        return;
+    }
+
+    for (merged, breakpoint) in merged.iter_mut().zip(breakpoints) {
+        if !merged.verified && breakpoint.verified {
+            *merged = breakpoint;
+        }
+    }
+}
+
+/// Set a data breakpoint on the data identified by the `dataId` of a `dataBreakpointInfo` response,
+/// in the form `<address>:<length>`.
+pub(crate) fn set_data_breakpoint(
+    requested_breakpoint: &DataBreakpoint,
+    target_core: &mut CoreHandle,
+) -> Breakpoint {
+    let mut breakpoint_response = Breakpoint {
+        column: None,
+        end_column: None,
+        end_line: None,
+        id: None,
+        instruction_reference: None,
+        line: None,
+        message: None,
+        offset: None,
+        source: None,
+        verified: false,
+        reason: None,
+    };
+
+    let access = match requested_breakpoint.access_type {
+        Some(DataBreakpointAccessType::Read) => WatchpointAccess::Read,
+        Some(DataBreakpointAccessType::ReadWrite) => WatchpointAccess::ReadWrite,
+        Some(DataBreakpointAccessType::Write) | None => WatchpointAccess::Write,
+    };
+
+    let data = requested_breakpoint
+        .data_id
+        .split_once(':')
+        .and_then(|(address, length)| {
+            let MemoryAddress(address) = address.try_into().ok()?;
+            Some((address, length.parse::<usize>().ok()?))
+        });
+
+    match data {
+        Some((address, length)) => match target_core.set_watchpoint(address, length, access) {
+            Ok(()) => {
+                breakpoint_response.verified = true;
+                breakpoint_response.id = Some(address as i64);
+                breakpoint_response.message = Some(format!(
+                    "Data breakpoint on {length} bytes at memory address: {address:#010x}"
+                ));
+            }
+            Err(error) => {
+                breakpoint_response.message = Some(format!(
+                    "Failed to set data breakpoint at memory address: {address:#010x}: {error}"
+                ));
+                breakpoint_response.reason = Some("failed".to_string());
+            }
+        },
+        None => {
+            breakpoint_response.message = Some(format!(
+                "Invalid data breakpoint id: {:?}",
+                requested_breakpoint.data_id
+            ));
+            breakpoint_response.reason = Some("failed".to_string());
+        }
+    }
+
+    breakpoint_response
+}
+
 /// A helper function to set and return a [`Breakpoint`] struct from a [`InstructionBreakpoint`]
 pub(crate) fn set_instruction_breakpoint(
     requested_breakpoint: InstructionBreakpoint,
@@ -644,3 +926,97 @@ pub(crate) fn set_instruction_breakpoint(
     };
     breakpoint_response
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn breakpoint(line: i64, verified: bool) -> Breakpoint {
+        Breakpoint {
+            column: None,
+            end_column: None,
+            end_line: None,
+            id: None,
+            instruction_reference: None,
+            line: Some(line),
+            message: None,
+            offset: None,
+            source: None,
+            verified,
+            reason: None,
+        }
+    }
+
+    fn summary(breakpoints: &[Breakpoint]) -> Vec<(Option<i64>, bool)> {
+        breakpoints
+            .iter()
+            .map(|breakpoint| (breakpoint.line, breakpoint.verified))
+            .collect()
+    }
+
+    #[test]
+    fn first_core_is_taken_as_is() {
+        let mut merged = vec![];
+        merge_breakpoints(&mut merged, vec![breakpoint(1, true), breakpoint(2, false)]);
+
+        assert_eq!(summary(&merged), [(Some(1), true), (Some(2), false)]);
+    }
+
+    #[test]
+    fn verified_breakpoint_replaces_unverified() {
+        let mut merged = vec![breakpoint(1, false), breakpoint(2, false)];
+        merge_breakpoints(
+            &mut merged,
+            vec![breakpoint(11, true), breakpoint(12, false)],
+        );
+
+        assert_eq!(summary(&merged), [(Some(11), true), (Some(2), false)]);
+    }
+
+    #[test]
+    fn first_verified_breakpoint_is_kept() {
+        let mut merged = vec![breakpoint(1, true), breakpoint(2, false)];
+        merge_breakpoints(
+            &mut merged,
+            vec![breakpoint(11, true), breakpoint(12, true)],
+        );
+        merge_breakpoints(
+            &mut merged,
+            vec![breakpoint(21, true), breakpoint(22, true)],
+        );
+
+        assert_eq!(summary(&merged), [(Some(1), true), (Some(12), true)]);
+    }
+
+    #[test]
+    fn statement_code_is_read_up_to_the_maximum() {
+        assert_eq!(
+            statement_code_range(0x1000, 0x100, Some(0x0..0x8000)),
+            0x1000..0x1100
+        );
+    }
+
+    #[test]
+    fn statement_code_ends_with_the_memory_region() {
+        assert_eq!(
+            statement_code_range(0x7ff0, 0x100, [0x0..0x8000, 0x8000..0x10000]),
+            0x7ff0..0x8000
+        );
+    }
+
+    #[test]
+    fn statement_code_ends_with_the_function() {
+        assert_eq!(
+            statement_code_range(0x1010, 0x100, [0x0..0x8000, 0x1000..0x1020, 0x1020..0x1040]),
+            0x1010..0x1020
+        );
+    }
+
+    #[test]
+    fn statement_code_without_bounds() {
+        assert_eq!(
+            statement_code_range(0x1000, 0x100, Some(0x8000..0x9000)),
+            0x1000..0x1100
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use std::collections::BTreeMap;
 
+use parse_int::parse;
 use probe_rs::MemoryInterface;
 use probe_rs_debug::{DebugError, ObjectRef, get_object_reference};
 
@@ -101,6 +102,103 @@ impl SvdVariableCache {
         }
     }
 
+    /// Write `new_value` to the SVD register or field with `variable_key`, and return its new value.
+    ///
+    /// Fields are written with a read-modify-write of their register, which leaves the other fields
+    /// unchanged. Fields with `modifiedWriteValues` like `oneToClear` are written with the value that
+    /// doesn't modify them, instead of the value that was read.
+    pub fn set_value(
+        &self,
+        variable_key: ObjectRef,
+        memory: &mut dyn MemoryInterface,
+        new_value: &str,
+    ) -> Result<String, DebugError> {
+        let Some(variable) = self.get_variable_by_key(variable_key) else {
+            return Err(DebugError::Other(format!(
+                "SvdVariableCache: No variable with key {variable_key:?}"
+            )));
+        };
+
+        match &variable.variable_kind {
+            SvdVariable::SvdRegister {
+                address,
+                writable,
+                size,
+                ..
+            } => {
+                if !writable {
+                    return Err(DebugError::Other(format!(
+                        "Register {} is read-only",
+                        variable.name
+                    )));
+                }
+                let value = parse_field_value(new_value, &[], *size)?;
+                write_register(memory, *address, *size, value)?;
+            }
+            SvdVariable::SvdField {
+                address,
+                writable,
+                bit_range_lower_bound,
+                bit_range_upper_bound,
+                enumerated_values,
+                ..
+            } => {
+                if !writable {
+                    return Err(DebugError::Other(format!(
+                        "Field {} is read-only",
+                        variable.name
+                    )));
+                }
+                let Some(SvdVariable::SvdRegister {
+                    restricted_read,
+                    size,
+                    write_zero_mask,
+                    write_one_mask,
+                    ..
+                }) = self
+                    .get_variable_by_key(variable.parent_key)
+                    .map(|register| &register.variable_kind)
+                else {
+                    return Err(DebugError::Other(format!(
+                        "Field {} does not belong to a register. Please report this as a bug.",
+                        variable.name
+                    )));
+                };
+
+                let width = bit_range_upper_bound - bit_range_lower_bound;
+                let value = parse_field_value(new_value, enumerated_values, width)?;
+                let field_mask = bit_mask(width) << bit_range_lower_bound;
+
+                let other_fields = if field_mask == bit_mask(*size) {
+                    // The field is the whole register, so there is nothing to preserve.
+                    0
+                } else if *restricted_read {
+                    return Err(DebugError::Other(format!(
+                        "Field {} cannot be modified, because its register cannot be read without side effects",
+                        variable.name
+                    )));
+                } else {
+                    (read_register(memory, *address, *size)? & !write_zero_mask) | write_one_mask
+                };
+
+                write_register(
+                    memory,
+                    *address,
+                    *size,
+                    (other_fields & !field_mask) | (value << bit_range_lower_bound),
+                )?;
+            }
+            _ => {
+                return Err(DebugError::Other(format!(
+                    "{} is not a register or field, and cannot be written",
+                    variable.name
+                )));
+            }
+        }
+
+        Ok(variable.get_value(memory))
+    }
+
     pub fn add_variable(
         &mut self,
         parent_key: ObjectRef,
@@ -172,6 +270,15 @@ impl Variable {
         self.variable_key
     }
 
+    /// Whether the value of this variable can be changed with [`SvdVariableCache::set_value`].
+    pub fn is_writable(&self) -> bool {
+        matches!(
+            self.variable_kind,
+            SvdVariable::SvdRegister { writable: true, .. }
+                | SvdVariable::SvdField { writable: true, .. }
+        )
+    }
+
     /// Memory reference, compatible with DAP
     pub fn memory_reference(&self) -> Option<String> {
         match self.variable_kind {
@@ -216,6 +323,13 @@ pub enum SvdVariable {
 
         /// Size in bits of the register
         size: u32,
+
+        /// false if the register is read-only
+        writable: bool,
+        /// Bits that are written as 0 to leave them unchanged, e.g. of `oneToClear` fields
+        write_zero_mask: u64,
+        /// Bits that are written as 1 to leave them unchanged, e.g. of `zeroToClear` fields
+        write_one_mask: u64,
     },
     /// Field with address
     SvdField {
@@ -226,6 +340,11 @@ pub enum SvdVariable {
         bit_range_upper_bound: u32,
 
         description: Option<String>,
+
+        /// false if the field is read-only
+        writable: bool,
+        /// The names of the values of the field, from its `enumeratedValues`
+        enumerated_values: Vec<(u64, String)>,
     },
     /// Peripheral with peripheral base address
     SvdPeripheral {
@@ -289,6 +408,7 @@ impl SvdVariable {
                 restricted_read,
                 bit_range_lower_bound,
                 bit_range_upper_bound,
+                enumerated_values,
                 ..
             } => {
                 if *restricted_read {
@@ -306,14 +426,21 @@ impl SvdVariable {
                             let mut bit_value: u32 = register_u32_value;
                             bit_value <<= 32 - bit_range_upper_bound;
                             bit_value >>= 32 - (bit_range_upper_bound - bit_range_lower_bound);
-                            format!(
+                            let bits = format!(
                                 "{:0width$b} @ {:#010X}:{}..{}",
                                 bit_value,
                                 address,
                                 bit_range_lower_bound,
                                 bit_range_upper_bound,
                                 width = (*bit_range_upper_bound - *bit_range_lower_bound) as usize
-                            )
+                            );
+                            match enumerated_values
+                                .iter()
+                                .find(|(value, _)| *value == u64::from(bit_value))
+                            {
+                                Some((_, name)) => format!("{name} ({bits})"),
+                                None => bits,
+                            }
                         }
                         Err(error) => format!(
                             "Unable to read peripheral register field value @ {address:#010X} : {error:?}"
@@ -334,3 +461,84 @@ impl SvdVariable {
         }
     }
 }
+
+/// A mask of the lowest `width` bits.
+fn bit_mask(width: u32) -> u64 {
+    1_u64.checked_shl(width).unwrap_or(0).wrapping_sub(1)
+}
+
+/// Parse the value of a register or field, which can be a number, or the name of one of its
+/// enumerated values.
+fn parse_field_value(
+    new_value: &str,
+    enumerated_values: &[(u64, String)],
+    width: u32,
+) -> Result<u64, DebugError> {
+    let new_value = new_value.trim();
+    let value = match enumerated_values
+        .iter()
+        .find(|(_, name)| name.eq_ignore_ascii_case(new_value))
+    {
+        Some((value, _)) => *value,
+        None => parse::<u64>(new_value)
+            .map_err(|error| DebugError::Other(format!("Invalid value {new_value:?}: {error}")))?,
+    };
+
+    if value & !bit_mask(width) != 0 {
+        return Err(DebugError::Other(format!(
+            "The value {value:#X} does not fit in {width} bits"
+        )));
+    }
+
+    Ok(value)
+}
+
+pub(crate) fn read_register(
+    memory: &mut dyn MemoryInterface,
+    address: u64,
+    size: u32,
+) -> Result<u64, DebugError> {
+    let value = match size {
+        0..=8 => memory.read_word_8(address).map(u64::from),
+        9..=16 => memory.read_word_16(address).map(u64::from),
+        _ => memory.read_word_32(address).map(u64::from),
+    }?;
+    Ok(value)
+}
+
+fn write_register(
+    memory: &mut dyn MemoryInterface,
+    address: u64,
+    size: u32,
+    value: u64,
+) -> Result<(), DebugError> {
+    match size {
+        0..=8 => memory.write_word_8(address, value as u8),
+        9..=16 => memory.write_word_16(address, value as u16),
+        _ => memory.write_word_32(address, value as u32),
+    }?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn field_values() {
+        let enumerated_values = [(0, "DISABLED".to_string()), (1, "ENABLED".to_string())];
+
+        assert_eq!(
+            parse_field_value("enabled", &enumerated_values, 1).unwrap(),
+            1
+        );
+        assert_eq!(parse_field_value("0b11", &[], 2).unwrap(), 3);
+        assert!(parse_field_value("4", &[], 2).is_err());
+        assert!(parse_field_value("unknown", &enumerated_values, 1).is_err());
+        assert_eq!(
+            parse_field_value("0xFFFFFFFF", &[], 32).unwrap(),
+            0xFFFF_FFFF
+        );
+    }
+}
@@ -2,14 +2,16 @@ use crate::cmd::dap_server::{
     DebuggerError,
     debug_adapter::{dap::adapter::DebugAdapter, protocol::ProtocolAdapter},
 };
-use std::{fmt::Debug, fs::File, io::Read, path::Path};
-use svd_parser::Config;
+use probe_rs::config::{ModifiedWriteValues, Peripherals};
+use std::{fmt::Debug, sync::Arc};
 
 use super::svd_cache::{SvdVariable, SvdVariableCache};
 
 /// The SVD file contents and related data
 #[derive(Debug)]
 pub struct SvdCache {
+    /// The peripherals parsed from the SVD file.
+    pub(crate) peripherals: Arc<Peripherals>,
     /// The SVD contents and structure will be stored as variables, down to the Field level.
     /// Unlike other VariableCache instances, it will only be built once per DebugSession.
     /// After that, only the SVD fields values change values, and the data for these will be re-read
@@ -18,68 +20,38 @@ pub struct SvdCache {
 }
 
 impl SvdCache {
-    /// Create the SVD cache for a specific core. This function loads the file, parses it, and then builds the VariableCache.
+    /// Create the SVD cache for a specific core. The peripherals are parsed and cached by the
+    /// registry, so this only builds the VariableCache.
     pub(crate) fn new<P: ProtocolAdapter>(
-        svd_file: &Path,
+        peripherals: Arc<Peripherals>,
         debug_adapter: &mut DebugAdapter<P>,
         dap_request_id: i64,
     ) -> Result<Self, DebuggerError> {
-        let svd_xml = &mut String::new();
-
-        let mut svd_opened_file = File::open(svd_file)?;
-
         let progress_id = debug_adapter.start_progress(
-            format!("Loading SVD file: {}", svd_file.display()).as_str(),
+            format!("Loading peripherals of {}", peripherals.device).as_str(),
             Some(dap_request_id),
         )?;
 
-        let _ = svd_opened_file.read_to_string(svd_xml)?;
-
-        let svd_cache = match svd_parser::parse_with_config(
-            svd_xml,
-            &Config::default().expand(true).ignore_enums(true),
-        ) {
-            Ok(peripheral_device) => {
-                debug_adapter
-                    .update_progress(
-                        None,
-                        Some(format!("Done loading SVD file: {}", svd_file.display())),
-                        progress_id,
-                    )
-                    .ok();
-
-                Ok(SvdCache {
-                    svd_variable_cache: variable_cache_from_svd(
-                        peripheral_device,
-                        debug_adapter,
-                        progress_id,
-                    )?,
-                })
-            }
-            Err(error) => Err(DebuggerError::Other(anyhow::anyhow!(
-                "Unable to parse CMSIS-SVD file: {:?}. {:?}",
-                svd_file,
-                error,
-            ))),
-        };
+        let svd_variable_cache = variable_cache_from_svd(&peripherals, debug_adapter, progress_id);
         debug_adapter.end_progress(progress_id)?;
 
-        svd_cache
+        Ok(SvdCache {
+            peripherals,
+            svd_variable_cache: svd_variable_cache?,
+        })
     }
 }
 
-/// Create a [`SvdVariableCache`] from a Device that was parsed from a CMSIS-SVD file.
+/// Create a [`SvdVariableCache`] from the peripherals that were parsed from a CMSIS-SVD file.
 #[tracing::instrument(skip_all)]
 pub(crate) fn variable_cache_from_svd<P: ProtocolAdapter>(
-    peripheral_device: svd_parser::svd::Device,
+    peripheral_device: &Peripherals,
     debug_adapter: &mut DebugAdapter<P>,
     progress_id: i64,
 ) -> Result<SvdVariableCache, DebuggerError> {
     let mut svd_cache = SvdVariableCache::new_svd_cache();
     let device_root_variable_key = svd_cache.root_variable_key();
 
-    let device_default_access = peripheral_device.default_register_properties.access;
-
     for peripheral in &peripheral_device.peripherals {
         let current_peripheral_group_name = peripheral.group_name.as_ref();
 
@@ -134,38 +106,55 @@ pub(crate) fn variable_cache_from_svd<P: ProtocolAdapter>(
             },
         )?;
 
-        for register in peripheral.all_registers() {
-            let register_address = peripheral.base_address + register.address_offset as u64;
+        for register in &peripheral.registers {
+            let mut register_has_restricted_read =
+                register.read_action || register.access.is_none_or(|access| !access.can_read());
 
-            let mut register_has_restricted_read = register.read_action.is_some()
-                || register
-                    .properties
-                    .access
-                    .map(|a| !a.can_read())
-                    .or_else(|| device_default_access.map(|a| !a.can_read()))
-                    .unwrap_or(true);
+            let register_writable = register.access.is_none_or(|access| access.can_write());
 
             let register_name = format!("{}.{}", &peripheral_name, register.name);
 
             let mut field_variables = Vec::new();
+            // When a field is modified, the other fields are written with values that don't modify them.
+            let mut write_zero_mask = 0;
+            let mut write_one_mask = 0;
 
-            for field in register.fields() {
+            for field in &register.fields {
                 let field_has_restricted_read = register_has_restricted_read
-                    || field.read_action.is_some()
-                    || field
-                        .access
-                        .map(|a| !a.can_read())
-                        .or_else(|| device_default_access.map(|a| !a.can_read()))
-                        .unwrap_or(register_has_restricted_read);
+                    || field.read_action
+                    || field.access.is_some_and(|access| !access.can_read());
+
+                let field_mask = field.mask();
+                match field.modified_write_values {
+                    Some(
+                        ModifiedWriteValues::OneToClear
+                        | ModifiedWriteValues::OneToSet
+                        | ModifiedWriteValues::OneToToggle,
+                    ) => write_zero_mask |= field_mask,
+                    Some(
+                        ModifiedWriteValues::ZeroToClear
+                        | ModifiedWriteValues::ZeroToSet
+                        | ModifiedWriteValues::ZeroToToggle,
+                    ) => write_one_mask |= field_mask,
+                    _ => {}
+                }
+
+                let enumerated_values: Vec<(u64, String)> = field
+                    .enumerated_values
+                    .iter()
+                    .map(|value| (value.value, value.name.clone()))
+                    .collect();
 
                 let field_variable = (
                     format!("{}.{}", register_name, field.name),
                     SvdVariable::SvdField {
-                        address: register_address,
+                        address: register.address,
                         restricted_read: field_has_restricted_read,
-                        bit_range_lower_bound: field.bit_offset(),
-                        bit_range_upper_bound: (field.bit_offset() + field.bit_width()),
+                        bit_range_lower_bound: field.bit_offset,
+                        bit_range_upper_bound: (field.bit_offset + field.bit_width),
                         description: field.description.clone(),
+                        writable: field.access.is_none_or(|access| access.can_write()),
+                        enumerated_values,
                     },
                 );
 
@@ -179,15 +168,17 @@ pub(crate) fn variable_cache_from_svd<P: ProtocolAdapter>(
                 peripheral_key,
                 format!("{}.{}", &peripheral_name, register.name),
                 SvdVariable::SvdRegister {
-                    address: register_address,
+                    address: register.address,
                     restricted_read: register_has_restricted_read,
                     description: register.description.clone(),
-                    size: register.properties.size.unwrap_or(32),
+                    size: register.size,
+                    writable: register_writable,
+                    write_zero_mask,
+                    write_one_mask,
                 },
             )?;
 
             for (variable_name, variable) in field_variables {
-                // TODO: Extend the Variable definition, so that we can resolve the EnumeratedValues for fields.
                 svd_cache.add_variable(register_variable_key, variable_name, variable)?;
             }
         }
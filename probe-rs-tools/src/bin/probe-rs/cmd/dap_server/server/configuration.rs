@@ -1,10 +1,14 @@
 use crate::util::common_options::ProbeOptions;
-use crate::util::rtt;
+use crate::util::rtt::{self, sinks::RttLogConfig};
+use crate::util::swo::SwoLogConfig;
 use crate::{FormatOptions, cmd::dap_server::DebuggerError};
 use anyhow::{Result, anyhow};
-use probe_rs::probe::{DebugProbeSelector, WireProtocol};
+use probe_rs::{
+    MemoryMapPolicy,
+    probe::{DebugProbeSelector, WireProtocol},
+};
 use serde::{Deserialize, Serialize};
-use std::{env::current_dir, path::PathBuf};
+use std::{env::current_dir, ops::Range, path::PathBuf};
 
 use super::startup::TargetSessionType;
 
@@ -42,6 +46,20 @@ pub struct SessionConfig {
     #[serde(default)]
     pub(crate) allow_erase_all: bool,
 
+    /// Keep the peripherals running while a core is halted, instead of stopping them with the
+    /// debug freeze bits of the target.
+    #[serde(default)]
+    pub(crate) no_debug_freeze: bool,
+
+    /// How memory accesses outside the memory map of the target are handled.
+    #[serde(default)]
+    pub(crate) memory_map_policy: MemoryMapPolicy,
+
+    /// How often to reconnect when the target enters a low-power mode which powers down its
+    /// debug logic. If unset, the session fails instead.
+    #[serde(default)]
+    pub(crate) reconnect_on_power_down: Option<u32>,
+
     /// Flashing configuration
     #[serde(default)]
     pub(crate) flashing_config: FlashingConfig,
@@ -114,6 +132,20 @@ impl SessionConfig {
                         None
                     }
                 };
+            // Update the `semihosting_root`, it is validated when the session is created.
+            if target_core_config.semihosting_root.is_some() {
+                target_core_config.semihosting_root = Some(get_absolute_path(
+                    self.cwd.as_ref(),
+                    target_core_config.semihosting_root.as_ref(),
+                )?);
+            }
+            // Update the RTT log file path, the file is created when RTT is attached.
+            if target_core_config.rtt_log.path.is_some() {
+                target_core_config.rtt_log.path = Some(get_absolute_path(
+                    self.cwd.as_ref(),
+                    target_core_config.rtt_log.path.as_ref(),
+                )?);
+            }
         }
 
         self.chip_description_path =
@@ -164,9 +196,16 @@ impl SessionConfig {
             non_interactive: true,
             probe: self.probe.clone(),
             speed: self.speed,
+            speed_floor: None,
+            hooks: None,
+            record_trace: None,
+            record_swd_trace: None,
             connect_under_reset: self.connect_under_reset,
             dry_run: false,
             allow_erase_all: self.allow_erase_all,
+            no_debug_freeze: self.no_debug_freeze,
+            memory_map: self.memory_map_policy,
+            reconnect_on_power_down: self.reconnect_on_power_down,
         }
     }
 }
@@ -253,6 +292,24 @@ pub struct CoreConfig {
     /// Enable hardfault vector catch if its supported on the target.
     #[serde(default = "default_true")]
     pub(crate) catch_hardfault: bool,
+
+    /// Directory in which the target can access files using semihosting. Relative to `cwd`, or
+    /// fully qualified. File access is disabled if not set.
+    pub(crate) semihosting_root: Option<PathBuf>,
+
+    /// Log the decoded RTT output of this core to a file. The path is relative to `cwd`, or
+    /// fully qualified.
+    #[serde(default)]
+    pub(crate) rtt_log: RttLogConfig,
+
+    /// Receive the output of the ITM stimulus ports over SWO, and display it like RTT channels.
+    #[serde(default)]
+    pub(crate) swo: SwoLogConfig,
+
+    /// The memory ranges to include in core dumps, as `{ "start": ..., "end": ... }`. If empty,
+    /// core dumps include the memory that is referenced by the variables in scope.
+    #[serde(default)]
+    pub(crate) core_dump_ranges: Vec<Range<u64>>,
 }
 
 fn default_console_log() -> Option<ConsoleLog> {
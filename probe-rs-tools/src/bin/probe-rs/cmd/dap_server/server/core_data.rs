@@ -1,10 +1,12 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::num::NonZeroU32;
 use std::{ops::Range, path::Path};
 
 use super::session_data::{self, ActiveBreakpoint, BreakpointType, SourceLocationScope};
 use crate::util::rtt::client::RttClient;
-use crate::util::rtt::{self, DataFormat, DefmtProcessor, DefmtState};
+use crate::util::rtt::sinks::{RttLogConfig, RttSinks};
+use crate::util::rtt::{self, DataFormat};
+use crate::util::semihosting::{SemihostingFiles, SemihostingHost};
 use crate::{
     cmd::dap_server::{
         DebuggerError,
@@ -12,7 +14,10 @@ use crate::{
             dap::{
                 adapter::DebugAdapter,
                 core_status::DapStatus,
-                dap_types::{ContinuedEventBody, MessageSeverity, Source, StoppedEventBody},
+                dap_types::{
+                    Breakpoint, ContinuedEventBody, MessageSeverity, OutputEventBody, Source,
+                    StoppedEventBody,
+                },
             },
             protocol::ProtocolAdapter,
         },
@@ -22,14 +27,24 @@ use crate::{
     util::rtt::RttDecoder,
 };
 use anyhow::{Result, anyhow};
+use itertools::Itertools;
+use parse_int::parse;
 use probe_rs::BreakpointCause;
+use probe_rs::rtt::decoder::{DefmtDecoder, DefmtTable};
 use probe_rs::semihosting::SemihostingCommand;
-use probe_rs::{Core, CoreStatus, HaltReason, rtt::ScanRegion};
+use probe_rs::{
+    Core, CoreDump, CoreStatus, HaltReason, HaltTimestamp, ReadCache, RegisterValue,
+    WatchpointAccess, rtt::ScanRegion,
+};
 use probe_rs_debug::VerifiedBreakpoint;
 use probe_rs_debug::{
-    ColumnType, ObjectRef, VariableCache, debug_info::DebugInfo, stack_frame::StackFrameInfo,
+    ColumnType, DebugRegisters, ObjectRef, Variable, VariableCache, VariableName,
+    debug_info::DebugInfo,
+    exception_handler_for_core,
+    rtos::{RtosAwareness, RtosThread},
+    stack_frame::{StackFrame, StackFrameInfo},
 };
-use time::UtcOffset;
+use time::{OffsetDateTime, UtcOffset, macros::format_description};
 use typed_path::TypedPath;
 
 /// [CoreData] is used to cache data needed by the debugger, on a per-core basis.
@@ -49,14 +64,129 @@ pub struct CoreData {
     pub static_variables: Option<VariableCache>,
     pub core_peripherals: Option<SvdCache>,
     pub stack_frames: Vec<probe_rs_debug::stack_frame::StackFrame>,
+    /// The RTOS used by the program, if it was detected.
+    pub rtos: Option<Box<dyn RtosAwareness>>,
+    /// The threads of the RTOS, with the stack frames of the threads that are not running.
+    pub rtos_threads: Vec<RtosThreadFrames>,
     pub breakpoints: Vec<session_data::ActiveBreakpoint>,
+    /// The addresses watched by the data breakpoints that are set on the core.
+    pub watchpoints: Vec<u64>,
     pub rtt_scan_ranges: ScanRegion,
     pub rtt_connection: Option<debug_rtt::RttConnection>,
+    /// The output of the ITM stimulus ports, if SWO is configured.
+    pub itm: Option<debug_rtt::DebuggerItm>,
     pub rtt_client: Option<RttClient>,
     pub clear_rtt_header: bool,
     pub rtt_header_cleared: bool,
+    /// The memory ranges to include in core dumps, if configured.
+    pub core_dump_ranges: Vec<Range<u64>>,
     pub next_semihosting_handle: u32,
     pub semihosting_handles: HashMap<u32, SemihostingFile>,
+    /// Files on the host the target can access, if enabled.
+    pub semihosting_files: Option<SemihostingFiles>,
+    /// Answers the semihosting requests for the clock, and refuses to run commands on the host.
+    pub semihosting_host: SemihostingHost,
+    /// The time at which the core was last observed to be halted, used to order halts across cores.
+    pub last_halt: Option<HaltTimestamp>,
+    /// Caches reads of non-volatile memory, e.g. while scrolling through a memory view.
+    /// Must be invalidated whenever memory is written, or the core is reset.
+    pub memory_cache: ReadCache,
+}
+
+impl CoreData {
+    /// Whether `reference` refers to a stack frame or variable of this core.
+    pub(crate) fn has_reference(&self, reference: ObjectRef) -> bool {
+        let is_static_variable = self
+            .static_variables
+            .as_ref()
+            .is_some_and(|cache| cache.get_variable_by_key(reference).is_some());
+        let is_peripheral = self.core_peripherals.as_ref().is_some_and(|peripherals| {
+            peripherals
+                .svd_variable_cache
+                .get_variable_by_key(reference)
+                .is_some()
+        });
+
+        is_static_variable
+            || is_peripheral
+            || self.all_stack_frames().any(|frame| {
+                frame.id == reference
+                    || frame
+                        .local_variables
+                        .as_ref()
+                        .is_some_and(|cache| cache.get_variable_by_key(reference).is_some())
+            })
+    }
+
+    /// Find the child variable with the given name in the variable container `parent_key`.
+    pub(crate) fn find_variable(&self, parent_key: ObjectRef, name: &str) -> Option<Variable> {
+        self.static_variables
+            .iter()
+            .chain(
+                self.all_stack_frames()
+                    .filter_map(|frame| frame.local_variables.as_ref()),
+            )
+            .find_map(|cache| {
+                cache
+                    .get_children(parent_key)
+                    .find(|variable| variable.name.to_string() == name)
+                    .cloned()
+            })
+    }
+
+    /// The stack frames of the core, followed by the stack frames of the RTOS threads that are not running.
+    pub(crate) fn all_stack_frames(&self) -> impl Iterator<Item = &StackFrame> {
+        self.stack_frames.iter().chain(
+            self.rtos_threads
+                .iter()
+                .flat_map(|thread| thread.stack_frames.iter()),
+        )
+    }
+
+    /// Mutable version of [`CoreData::all_stack_frames`].
+    ///
+    /// This takes the fields instead of `&mut self`, so the other fields of [`CoreData`] can be
+    /// borrowed while the stack frames are in use.
+    pub(crate) fn all_stack_frames_mut<'a>(
+        stack_frames: &'a mut [StackFrame],
+        rtos_threads: &'a mut [RtosThreadFrames],
+    ) -> impl Iterator<Item = &'a mut StackFrame> {
+        stack_frames.iter_mut().chain(
+            rtos_threads
+                .iter_mut()
+                .flat_map(|thread| thread.stack_frames.iter_mut()),
+        )
+    }
+
+    /// Whether `thread_id` refers to the core, or one of its RTOS threads.
+    pub(crate) fn has_thread(&self, thread_id: i64) -> bool {
+        self.core_index as i64 == thread_id
+            || self
+                .rtos_threads
+                .iter()
+                .any(|thread| thread.id == thread_id)
+    }
+
+    /// The stack frames of the thread `thread_id`.
+    ///
+    /// The core itself is the thread which is running, so its id refers to the stack frames of the core.
+    pub(crate) fn thread_stack_frames(&self, thread_id: i64) -> &[StackFrame] {
+        self.rtos_threads
+            .iter()
+            .find(|thread| thread.id == thread_id)
+            .map(|thread| thread.stack_frames.as_slice())
+            .unwrap_or(&self.stack_frames)
+    }
+}
+
+/// A thread of the RTOS running on a core, which is reported to the debug client as a thread of its own.
+pub struct RtosThreadFrames {
+    /// The DAP thread id. The core index is in the upper 32 bits, to keep the ids of all cores unique.
+    pub id: i64,
+    pub thread: RtosThread,
+    /// The stack frames unwound from the saved registers of the thread. Empty for the running thread, whose frames
+    /// are those of the core.
+    pub stack_frames: Vec<StackFrame>,
 }
 
 /// File descriptor for files opened by the target.
@@ -120,6 +250,8 @@ impl CoreHandle<'_> {
         // Update this unconditionally, because halted() can have more than one variant.
         self.core_data.last_known_status = status;
 
+        self.core_data.last_halt = status.is_halted().then(|| self.core.halt_timestamp());
+
         match status {
             CoreStatus::Running | CoreStatus::Sleeping => {
                 let event_body = Some(ContinuedEventBody {
@@ -142,6 +274,13 @@ impl CoreHandle<'_> {
                 // is not handled or indicates that the core should halt.
             }
 
+            CoreStatus::Halted(HaltReason::Breakpoint(_))
+                if self.logpoint_at_program_counter().is_some() =>
+            {
+                // Logpoints are handled without a "stopped" event. The core will be resumed
+                // after the message is logged.
+            }
+
             CoreStatus::Halted(_) => self.notify_halted(debug_adapter, status)?,
             CoreStatus::LockedUp => {
                 let (_, description) = status.short_long_status(None);
@@ -166,8 +305,7 @@ impl CoreHandle<'_> {
         id: ObjectRef,
     ) -> Option<&probe_rs_debug::stack_frame::StackFrame> {
         self.core_data
-            .stack_frames
-            .iter()
+            .all_stack_frames()
             .find(|stack_frame| stack_frame.id == id)
     }
 
@@ -177,6 +315,7 @@ impl CoreHandle<'_> {
         debug_adapter: &mut DebugAdapter<P>,
         program_binary: &Path,
         rtt_config: &rtt::RttConfig,
+        rtt_log: &RttLogConfig,
         timestamp_offset: UtcOffset,
     ) -> Result<()> {
         // We're done already, don't process everything again for no good reason.
@@ -237,10 +376,7 @@ impl CoreHandle<'_> {
             let log_format = channel_config.log_format.clone();
 
             let channel_data_format = match channel_config.data_format {
-                DataFormat::String => RttDecoder::String {
-                    timestamp_offset: Some(timestamp_offset),
-                    last_line_done: false,
-                },
+                DataFormat::String => RttDecoder::string(Some(timestamp_offset)),
                 DataFormat::BinaryLE => RttDecoder::BinaryLE,
                 DataFormat::Defmt => {
                     let defmt_data = if let Some(data) = defmt_data.as_ref() {
@@ -250,7 +386,7 @@ impl CoreHandle<'_> {
                         let elf = std::fs::read(program_binary).map_err(|error| {
                             anyhow!("Error attempting to attach to RTT: {error}")
                         })?;
-                        defmt_data.insert(DefmtState::try_from_bytes(&elf)?)
+                        defmt_data.insert(DefmtTable::try_from_bytes(&elf)?)
                     };
                     let Some(defmt_data) = defmt_data.clone() else {
                         tracing::warn!("Defmt data not found in ELF file");
@@ -258,7 +394,7 @@ impl CoreHandle<'_> {
                     };
 
                     RttDecoder::Defmt {
-                        processor: DefmtProcessor::new(
+                        processor: DefmtDecoder::new(
                             defmt_data,
                             show_timestamps,
                             show_location,
@@ -268,23 +404,48 @@ impl CoreHandle<'_> {
                 }
             };
 
+            // Input from the client window is sent to the configured down channel, or the one with the same number.
+            let down_channel = channel_config.down_channel.unwrap_or(up_channel.number());
+            let down_channel = client
+                .down_channels()
+                .iter()
+                .any(|channel| channel.number() == down_channel)
+                .then_some(down_channel);
+
             debugger_rtt_channels.push(debug_rtt::DebuggerRttChannel {
                 channel_number: up_channel.number(),
+                channel_name: up_channel.channel_name(),
                 // This value will eventually be set to true by a VSCode client request "rttWindowOpened"
                 has_client_window: false,
                 channel_data_format,
+                down_channel,
+                terminal_windows: BTreeMap::new(),
             });
 
             debug_adapter.rtt_window(
                 up_channel.number(),
                 up_channel.channel_name(),
                 channel_config.data_format,
+                down_channel,
             );
         }
 
+        // Not being able to log the output should not prevent displaying it.
+        let sinks = match RttSinks::from_config(rtt_log, timestamp_offset) {
+            Ok(sinks) => sinks,
+            Err(error) => {
+                debug_adapter
+                    .show_error_message(&DebuggerError::Other(error))
+                    .ok();
+                RttSinks::default()
+            }
+        };
+
         self.core_data.rtt_connection = Some(debug_rtt::RttConnection {
             client,
             debugger_rtt_channels,
+            sinks,
+            pending_input: BTreeMap::new(),
         });
 
         Ok(())
@@ -340,6 +501,7 @@ impl CoreHandle<'_> {
     /// Clear all breakpoints of a specified [`super::session_data::BreakpointType`].
     /// Affects target configuration as well as [`CoreData::breakpoints`].
     /// If `breakpoint_type` is of type [`super::session_data::BreakpointType::SourceBreakpoint`], then all breakpoints for the contained [`Source`] will be cleared.
+    /// If `breakpoint_type` is of type [`super::session_data::BreakpointType::FunctionBreakpoint`], then all function breakpoints will be cleared.
     pub(crate) fn clear_breakpoints(
         &mut self,
         breakpoint_type: session_data::BreakpointType,
@@ -352,10 +514,14 @@ impl CoreHandle<'_> {
                 target_breakpoint.breakpoint_type == breakpoint_type
                  || matches!(
                         &target_breakpoint.breakpoint_type,
-                        BreakpointType::SourceBreakpoint{source: breakpoint_source, location: _}
+                        BreakpointType::SourceBreakpoint{source: breakpoint_source, ..}
                             if matches!(&breakpoint_type, BreakpointType::SourceBreakpoint{source: clear_breakpoint_source, ..}
                                 if clear_breakpoint_source == breakpoint_source)
                     )
+                 || matches!(
+                        (&target_breakpoint.breakpoint_type, &breakpoint_type),
+                        (BreakpointType::FunctionBreakpoint { .. }, BreakpointType::FunctionBreakpoint { .. })
+                    )
             })
             .map(|breakpoint| breakpoint.address)
             .collect::<Vec<u64>>();
@@ -365,6 +531,42 @@ impl CoreHandle<'_> {
         Ok(())
     }
 
+    /// Set a data breakpoint, which halts the core on `access` to the `length` bytes at `address`.
+    pub(crate) fn set_watchpoint(
+        &mut self,
+        address: u64,
+        length: usize,
+        access: WatchpointAccess,
+    ) -> Result<(), DebuggerError> {
+        self.core.set_hw_watchpoint(address, length, access)?;
+        if !self.core_data.watchpoints.contains(&address) {
+            self.core_data.watchpoints.push(address);
+        }
+        Ok(())
+    }
+
+    /// Clear all data breakpoints that were set by [`Self::set_watchpoint`].
+    ///
+    /// Every data breakpoint is cleared, even if clearing one of them fails. The data breakpoints
+    /// which could not be cleared are kept, and the first error is returned.
+    pub(crate) fn clear_watchpoints(&mut self) -> Result<(), DebuggerError> {
+        let mut first_error = None;
+        let mut remaining = Vec::new();
+        for address in std::mem::take(&mut self.core_data.watchpoints) {
+            if let Err(error) = self.core.clear_hw_watchpoint(address) {
+                tracing::warn!("Failed to clear data breakpoint at {address:#010x}: {error}");
+                remaining.push(address);
+                first_error.get_or_insert(error);
+            }
+        }
+        self.core_data.watchpoints = remaining;
+
+        match first_error {
+            Some(error) => Err(error.into()),
+            None => Ok(()),
+        }
+    }
+
     /// Set a breakpoint at the requested address. If the requested source location is not specific, or
     /// if the requested address is not a valid breakpoint location,
     /// the debugger will attempt to find the closest location to the requested location, and set a breakpoint there.
@@ -375,6 +577,7 @@ impl CoreHandle<'_> {
         requested_breakpoint_line: u64,
         requested_breakpoint_column: Option<u64>,
         requested_source: &Source,
+        log_message: Option<String>,
     ) -> Result<VerifiedBreakpoint, DebuggerError> {
         let VerifiedBreakpoint {
                  address,
@@ -393,6 +596,7 @@ impl CoreHandle<'_> {
             BreakpointType::SourceBreakpoint {
                 source: Box::new(requested_source.clone()),
                 location: SourceLocationScope::Specific(source_location.clone()),
+                log_message,
             },
         )?;
         Ok(VerifiedBreakpoint {
@@ -401,10 +605,55 @@ impl CoreHandle<'_> {
         })
     }
 
+    /// Set a breakpoint on every instance of the function `function_name`, for as long as there are
+    /// breakpoint units available. The Result<> contains the locations where breakpoints were set.
+    pub(crate) fn set_function_breakpoint(
+        &mut self,
+        function_name: &str,
+    ) -> Result<Vec<VerifiedBreakpoint>, DebuggerError> {
+        let locations = self
+            .core_data
+            .debug_info
+            .get_function_breakpoint_locations(function_name)
+            .map_err(|debug_error| {
+                DebuggerError::Other(anyhow!("Cannot set function breakpoint: {debug_error}"))
+            })?;
+
+        let mut verified_breakpoints = Vec::new();
+        for location in locations {
+            match self.set_breakpoint(
+                location.address,
+                BreakpointType::FunctionBreakpoint {
+                    name: function_name.to_string(),
+                },
+            ) {
+                Ok(()) => verified_breakpoints.push(location),
+                Err(error) => {
+                    tracing::warn!(
+                        "Failed to set breakpoint for `{function_name}` at {:#010x}: {error}",
+                        location.address
+                    );
+                }
+            }
+        }
+
+        if verified_breakpoints.is_empty() {
+            return Err(DebuggerError::Other(anyhow!(
+                "Failed to set a breakpoint on any instance of `{function_name}`"
+            )));
+        }
+        Ok(verified_breakpoints)
+    }
+
     /// In the case where a new binary is flashed as part of a restart, we need to recompute the breakpoint address,
-    /// for a specified source location, of any [`super::session_data::BreakpointType::SourceBreakpoint`].
+    /// for a specified source location, of any [`super::session_data::BreakpointType::SourceBreakpoint`],
+    /// and for the function name of any [`super::session_data::BreakpointType::FunctionBreakpoint`].
     /// This is because the address of the breakpoint may have changed based on changes in the source file that created the new binary.
-    pub(crate) fn recompute_breakpoints(&mut self) -> Result<(), DebuggerError> {
+    ///
+    /// A breakpoint which can no longer be set does not prevent the others from being recomputed.
+    /// It is returned as an unverified [`Breakpoint`] instead, so that the client can be informed.
+    pub(crate) fn recompute_breakpoints(&mut self) -> Result<Vec<Breakpoint>, DebuggerError> {
+        let mut unverified_breakpoints = Vec::new();
         let target_breakpoints = self.core_data.breakpoints.clone();
         for breakpoint in target_breakpoints
             .iter()
@@ -420,6 +669,7 @@ impl CoreHandle<'_> {
             if let BreakpointType::SourceBreakpoint {
                 source,
                 location: SourceLocationScope::Specific(source_location),
+                log_message,
             } = breakpoint.breakpoint_type
             {
                 let breakpoint_err = self.verify_and_set_breakpoint(
@@ -430,16 +680,90 @@ impl CoreHandle<'_> {
                         ColumnType::Column(c) => c,
                     }),
                     &source,
+                    log_message,
                 );
 
                 if let Err(breakpoint_error) = breakpoint_err {
-                    return Err(DebuggerError::Other(anyhow!(
+                    tracing::warn!(
                         "Failed to recompute breakpoint at {source_location:?} in {source:?}. Error: {breakpoint_error:?}"
-                    )));
+                    );
+                    unverified_breakpoints.push(Breakpoint {
+                        column: None,
+                        end_column: None,
+                        end_line: None,
+                        id: None,
+                        line: source_location.line.map(|line| line as i64),
+                        message: Some(breakpoint_error.to_string()),
+                        source: Some(*source),
+                        instruction_reference: None,
+                        offset: None,
+                        verified: false,
+                        reason: Some("failed".to_string()),
+                    });
                 }
             }
         }
-        Ok(())
+
+        let function_names = target_breakpoints
+            .into_iter()
+            .filter_map(|breakpoint| match breakpoint.breakpoint_type {
+                BreakpointType::FunctionBreakpoint { name } => Some(name),
+                _ => None,
+            })
+            .unique()
+            .collect::<Vec<_>>();
+        if !function_names.is_empty() {
+            self.clear_breakpoints(BreakpointType::FunctionBreakpoint {
+                name: String::new(),
+            })?;
+            for function_name in function_names {
+                if let Err(breakpoint_error) = self.set_function_breakpoint(&function_name) {
+                    tracing::warn!(
+                        "Failed to recompute function breakpoint on `{function_name}`. Error: {breakpoint_error:?}"
+                    );
+                    unverified_breakpoints.push(Breakpoint {
+                        column: None,
+                        end_column: None,
+                        end_line: None,
+                        id: None,
+                        line: None,
+                        message: Some(breakpoint_error.to_string()),
+                        source: None,
+                        instruction_reference: None,
+                        offset: None,
+                        verified: false,
+                        reason: Some("failed".to_string()),
+                    });
+                }
+            }
+        }
+        Ok(unverified_breakpoints)
+    }
+
+    /// Store a [`CoreDump`] of the halted core at `location`, and return the memory ranges it includes.
+    /// If no `ranges` are specified, the configured core dump ranges are used, or otherwise the
+    /// memory ranges of the variables in scope.
+    pub(crate) fn store_core_dump(
+        &mut self,
+        ranges: Vec<Range<u64>>,
+        location: &Path,
+    ) -> Result<Vec<Range<u64>>, DebuggerError> {
+        if !self.core.core_halted()? {
+            return Err(DebuggerError::UserMessage(
+                "The core must be halted before a core dump can be created.".to_string(),
+            ));
+        }
+
+        let ranges = if !ranges.is_empty() {
+            ranges
+        } else if !self.core_data.core_dump_ranges.is_empty() {
+            self.core_data.core_dump_ranges.clone()
+        } else {
+            self.get_memory_ranges()
+        };
+
+        CoreDump::dump_core(&mut self.core, ranges.clone())?.store(location)?;
+        Ok(ranges)
     }
 
     /// Traverse all the variables in the available stack frames, and return the memory ranges
@@ -495,11 +819,347 @@ impl CoreHandle<'_> {
         consolidate_memory_ranges(all_discrete_memory_ranges, 0x400)
     }
 
+    /// Unwind the stack of the halted core, and refresh the static variables.
+    pub(crate) fn update_stack_frames(&mut self) -> Result<(), DebuggerError> {
+        let _stackframe_span = tracing::debug_span!("Update Stack Frames").entered();
+        tracing::debug!("Updating the stack frame data for core #{}", self.core.id());
+
+        let initial_registers = DebugRegisters::from_core(&mut self.core);
+        let exception_interface = exception_handler_for_core(self.core.core_type());
+        let instruction_set = self.core.instruction_set().ok();
+
+        self.core_data.static_variables =
+            Some(self.core_data.debug_info.create_static_scope_cache());
+
+        self.core_data.stack_frames = self.core_data.debug_info.unwind(
+            &mut self.core,
+            initial_registers.clone(),
+            exception_interface.as_ref(),
+            instruction_set,
+        )?;
+
+        self.core_data.rtos_threads = self.rtos_threads(&initial_registers);
+        Ok(())
+    }
+
+    /// Read the threads of the RTOS, and unwind the stacks of the threads that are not running.
+    fn rtos_threads(&mut self, core_registers: &DebugRegisters) -> Vec<RtosThreadFrames> {
+        let Some(rtos) = self.core_data.rtos.as_ref() else {
+            return Vec::new();
+        };
+        let exception_interface = exception_handler_for_core(self.core.core_type());
+        let instruction_set = self.core.instruction_set().ok();
+
+        let threads = match rtos.threads(&self.core_data.debug_info, &mut self.core, core_registers)
+        {
+            Ok(threads) => threads,
+            Err(error) => {
+                tracing::warn!("Failed to read the threads of {}: {error}", rtos.name());
+                return Vec::new();
+            }
+        };
+
+        threads
+            .into_iter()
+            .enumerate()
+            .map(|(index, thread)| {
+                let stack_frames = match &thread.registers {
+                    Some(registers) if !thread.is_running() => self
+                        .core_data
+                        .debug_info
+                        .unwind(
+                            &mut self.core,
+                            registers.clone(),
+                            exception_interface.as_ref(),
+                            instruction_set,
+                        )
+                        .unwrap_or_else(|error| {
+                            tracing::warn!("Failed to unwind thread {}: {error}", thread.name);
+                            Vec::new()
+                        }),
+                    _ => Vec::new(),
+                };
+
+                RtosThreadFrames {
+                    id: ((self.core_data.core_index as i64 + 1) << 32) | index as i64,
+                    thread,
+                    stack_frames,
+                }
+            })
+            .collect()
+    }
+
+    /// The logpoint at the current program counter, if any.
+    pub(crate) fn logpoint_at_program_counter(&mut self) -> Option<ActiveBreakpoint> {
+        let program_counter: u64 = self.core.read_core_reg(self.core.program_counter()).ok()?;
+        self.core_data
+            .breakpoints
+            .iter()
+            .find(|breakpoint| {
+                breakpoint.address == program_counter
+                    && matches!(
+                        breakpoint.breakpoint_type,
+                        BreakpointType::SourceBreakpoint {
+                            log_message: Some(_),
+                            ..
+                        }
+                    )
+            })
+            .cloned()
+    }
+
+    /// Log the message of a logpoint to the debug console, and resume the core.
+    pub(crate) fn handle_logpoint<P: ProtocolAdapter>(
+        &mut self,
+        debug_adapter: &mut DebugAdapter<P>,
+        logpoint: &ActiveBreakpoint,
+    ) -> Result<CoreStatus, DebuggerError> {
+        let BreakpointType::SourceBreakpoint {
+            source,
+            location,
+            log_message: Some(log_message),
+        } = &logpoint.breakpoint_type
+        else {
+            return Ok(self.core.status()?);
+        };
+
+        // The expressions are evaluated in the context of the top stack frame.
+        self.update_stack_frames()?;
+        let output = interpolate_log_message(log_message, |expression| {
+            self.evaluate_expression(expression)
+                .unwrap_or_else(|| format!("<{expression}: not found>"))
+        });
+
+        let line = match location {
+            SourceLocationScope::Specific(source_location) => {
+                source_location.line.map(|line| line as i64)
+            }
+            SourceLocationScope::All => None,
+        };
+        debug_adapter.send_event(
+            "output",
+            Some(OutputEventBody {
+                output: format!("{output}\n"),
+                category: Some("console".to_owned()),
+                variables_reference: None,
+                source: Some(source.as_ref().clone()),
+                line,
+                column: None,
+                data: None,
+                group: None,
+                location_reference: None,
+            }),
+        )?;
+
+        self.core.run()?;
+        Ok(CoreStatus::Running)
+    }
+
+    /// Evaluate an expression in the context of the top stack frame. The expression can be the name
+    /// of a register (optionally prefixed with `$`), a local variable, or a static variable.
+    pub(crate) fn evaluate_expression(&mut self, expression: &str) -> Option<String> {
+        let register_name = expression.strip_prefix('$').unwrap_or(expression);
+        if let Some(register_value) = self
+            .core_data
+            .stack_frames
+            .first()?
+            .registers
+            .get_register_by_name(register_name)
+            .and_then(|register| register.value)
+        {
+            return Some(register_value.to_string());
+        }
+
+        self.with_variable(expression, |_, variable_cache, variable| {
+            variable.to_string(variable_cache)
+        })
+    }
+
+    /// Assign the value of `value_expression` to a register or variable in the context of the top
+    /// stack frame, and return the assigned value.
+    /// - Registers can be prefixed with `$`, e.g. `$r3 = 0x20`.
+    /// - Writes through pointers use the dereference operator, e.g. `*ptr = 5`.
+    /// - The value can be any expression of [`DebugInfo::evaluate_expression`], e.g. `foo = bar + 1`.
+    ///
+    /// [`DebugInfo::evaluate_expression`]: probe_rs_debug::DebugInfo::evaluate_expression
+    pub(crate) fn assign(
+        &mut self,
+        target: &str,
+        value_expression: &str,
+    ) -> Result<String, DebuggerError> {
+        if !self.core.core_halted()? {
+            return Err(DebuggerError::UserMessage(
+                "The target must be halted before values can be assigned.".to_string(),
+            ));
+        }
+
+        // Values which can't be evaluated, e.g. characters or enum variants, are parsed according
+        // to the type of the variable.
+        let value = self
+            .evaluate_value(value_expression)
+            .unwrap_or_else(|| value_expression.to_string());
+
+        let register_name = target.strip_prefix('$');
+        let register = self.core_data.stack_frames.first().and_then(|stack_frame| {
+            stack_frame
+                .registers
+                .get_register_by_name(register_name.unwrap_or(target))
+        });
+
+        if let Some(register) = register {
+            let new_value = parse::<i128>(&value).map_err(|_| {
+                DebuggerError::UserMessage(format!(
+                    "Registers can only be assigned integer values, not {value:?}"
+                ))
+            })?;
+            // Negative values are written as two's complement.
+            let register_value = match register.core_register.size_in_bits() {
+                0..=32 => RegisterValue::from(new_value as u32),
+                33..=64 => RegisterValue::from(new_value as u64),
+                _ => RegisterValue::from(new_value as u128),
+            };
+            self.core
+                .write_core_reg(register.core_register.id, register_value)?;
+        } else if let Some(register_name) = register_name {
+            return Err(DebuggerError::UserMessage(format!(
+                "No register named {register_name:?}"
+            )));
+        } else {
+            self.with_variable(target, |core, variable_cache, variable| {
+                variable.update_value(core, variable_cache, value.clone())
+            })
+            .ok_or_else(|| {
+                DebuggerError::UserMessage(format!(
+                    "No variable named {target:?} in the current scope"
+                ))
+            })?
+            .map_err(|error| {
+                DebuggerError::UserMessage(format!(
+                    "Failed to assign {value:?} to {target:?}: {error}"
+                ))
+            })?;
+        }
+
+        // Refresh the cached registers and variables, so that they show the new value.
+        self.update_stack_frames()?;
+        Ok(value)
+    }
+
+    /// Evaluate `expression` with the expression evaluator of the debug info, in the context of the
+    /// top stack frame, and format the result.
+    fn evaluate_value(&mut self, expression: &str) -> Option<String> {
+        let stack_frame = self.core_data.stack_frames.first_mut()?;
+        let frame_info = StackFrameInfo {
+            registers: &stack_frame.registers,
+            frame_base: stack_frame.frame_base,
+            canonical_frame_address: stack_frame.canonical_frame_address,
+        };
+
+        for variable_cache in [
+            stack_frame.local_variables.as_mut(),
+            self.core_data.static_variables.as_mut(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            match self.core_data.debug_info.evaluate_expression(
+                expression,
+                &mut self.core,
+                variable_cache,
+                frame_info,
+            ) {
+                Ok(value) => return Some(value.to_string(variable_cache)),
+                Err(error) => tracing::debug!("Failed to evaluate {expression:?}: {error}"),
+            }
+        }
+        None
+    }
+
+    /// Find the local or static variable `variable_name` in the context of the top stack frame, and
+    /// apply `action` to it. Dereferenced pointers (`*name`) are resolved on demand.
+    fn with_variable<T>(
+        &mut self,
+        variable_name: &str,
+        action: impl FnOnce(&mut Core<'_>, &mut VariableCache, Variable) -> T,
+    ) -> Option<T> {
+        let stack_frame = self.core_data.stack_frames.first_mut()?;
+        let frame_info = StackFrameInfo {
+            registers: &stack_frame.registers,
+            frame_base: stack_frame.frame_base,
+            canonical_frame_address: stack_frame.canonical_frame_address,
+        };
+
+        for variable_cache in [
+            stack_frame.local_variables.as_mut(),
+            self.core_data.static_variables.as_mut(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if variable_cache.len() == 1 {
+                // The root of a scope doesn't have cached children by default, so we need to resolve them first.
+                let mut root_variable = variable_cache.root_variable().clone();
+                if let Err(error) = self.core_data.debug_info.cache_deferred_variables(
+                    variable_cache,
+                    &mut self.core,
+                    &mut root_variable,
+                    frame_info,
+                ) {
+                    tracing::warn!("Failed to resolve variables: {error}");
+                    continue;
+                }
+            }
+
+            if let Some(pointer_name) = variable_name.strip_prefix('*') {
+                if let Some(mut pointer) = variable_cache
+                    .get_variable_by_name(&VariableName::Named(pointer_name.to_string()))
+                {
+                    if pointer.variable_node_type.is_deferred()
+                        && !variable_cache.has_children(&pointer)
+                    {
+                        if let Err(error) = self.core_data.debug_info.cache_deferred_variables(
+                            variable_cache,
+                            &mut self.core,
+                            &mut pointer,
+                            frame_info,
+                        ) {
+                            tracing::warn!("Failed to dereference {pointer_name}: {error}");
+                        }
+                    }
+                }
+            }
+
+            if let Some(variable) =
+                variable_cache.get_variable_by_name(&VariableName::Named(variable_name.to_string()))
+            {
+                return Some(action(&mut self.core, variable_cache, variable));
+            }
+        }
+
+        None
+    }
+
     pub fn handle_semihosting<P: ProtocolAdapter>(
         &mut self,
         debug_adapter: &mut DebugAdapter<P>,
         command: SemihostingCommand,
     ) -> Result<CoreStatus, DebuggerError> {
+        if let Some(files) = self.core_data.semihosting_files.as_mut() {
+            if files.handle(command, &mut self.core)? {
+                self.core.run()?;
+                return Ok(CoreStatus::Running);
+            }
+        }
+
+        if self
+            .core_data
+            .semihosting_host
+            .handle(command, &mut self.core)?
+        {
+            self.core.run()?;
+            return Ok(CoreStatus::Running);
+        }
+
         match command {
             SemihostingCommand::Open(request) => {
                 tracing::debug!("Semihosting request: open {request:?}");
@@ -554,7 +1214,7 @@ impl CoreHandle<'_> {
                         );
                         self.core_data.next_semihosting_handle += 1;
 
-                        if debug_adapter.rtt_window(handle, path.to_string(), format) {
+                        if debug_adapter.rtt_window(handle, path.to_string(), format, None) {
                             request.respond_with_handle(&mut self.core, nz_handle)?;
                         }
                     }
@@ -616,12 +1276,16 @@ impl CoreHandle<'_> {
     ) -> Result<(), DebuggerError> {
         let program_counter = self.core.read_core_reg(self.core.program_counter()).ok();
         let (reason, description) = status.short_long_status(program_counter);
+        let halt = *self
+            .core_data
+            .last_halt
+            .get_or_insert_with(|| self.core.halt_timestamp());
         let event_body = Some(StoppedEventBody {
             reason: reason.to_string(),
             description: Some(description),
             thread_id: Some(self.core.id() as i64),
             preserve_focus_hint: Some(false),
-            text: None,
+            text: Some(format_halt_timestamp(&halt)),
             all_threads_stopped: Some(debug_adapter.all_cores_halted),
             hit_breakpoint_ids: None,
         });
@@ -632,6 +1296,45 @@ impl CoreHandle<'_> {
     }
 }
 
+/// Replace the `{expression}` placeholders in the message of a logpoint with their values.
+/// Use `{{` and `}}` for literal braces.
+fn interpolate_log_message(message: &str, mut evaluate: impl FnMut(&str) -> String) -> String {
+    let mut output = String::with_capacity(message.len());
+    let mut characters = message.chars().peekable();
+    while let Some(character) = characters.next() {
+        match character {
+            '{' if characters.peek() == Some(&'{') => {
+                characters.next();
+                output.push('{');
+            }
+            '}' if characters.peek() == Some(&'}') => {
+                characters.next();
+                output.push('}');
+            }
+            '{' => {
+                let expression: String = characters.by_ref().take_while(|&c| c != '}').collect();
+                output.push_str(&evaluate(expression.trim()));
+            }
+            other => output.push(other),
+        }
+    }
+    output
+}
+
+/// Formats a halt timestamp for the `text` of a stopped event.
+fn format_halt_timestamp(halt: &HaltTimestamp) -> String {
+    let host = OffsetDateTime::from(halt.host)
+        .format(format_description!(
+            "[hour]:[minute]:[second].[subsecond digits:6] UTC"
+        ))
+        .unwrap_or_default();
+
+    match halt.cycle_count {
+        Some(cycles) => format!("Halted at {host}, cycle count {cycles}"),
+        None => format!("Halted at {host}"),
+    }
+}
+
 /// Return a Vec of memory ranges that consolidate the adjacent memory ranges of the input ranges.
 /// Note: The concept of "adjacent" is calculated to include a gap of up to specified number of bytes between ranges.
 /// This serves to consolidate memory ranges that are separated by a small gap, but are still close enough for the purpose of the caller.
@@ -747,3 +1450,46 @@ fn test_reversed_intersecting_ranges() {
     let result = consolidate_memory_ranges(input, 0);
     assert_eq!(result, expected);
 }
+
+#[cfg(test)]
+fn evaluate_test_expression(expression: &str) -> String {
+    match expression {
+        "counter" => "42".to_string(),
+        "state.mode" => "Idle".to_string(),
+        other => format!("<{other}: not found>"),
+    }
+}
+
+/// Expressions in braces are replaced with their values, surrounding whitespace is ignored.
+#[test]
+fn test_log_message_expressions() {
+    let output = interpolate_log_message(
+        "counter = {counter}, mode = { state.mode }",
+        evaluate_test_expression,
+    );
+    assert_eq!(output, "counter = 42, mode = Idle");
+}
+
+/// Double braces are literal braces, and are not evaluated.
+#[test]
+fn test_log_message_escaped_braces() {
+    let output = interpolate_log_message("{{counter}} is {counter}}}", |expression| {
+        assert_eq!(expression, "counter");
+        evaluate_test_expression(expression)
+    });
+    assert_eq!(output, "{counter} is 42}");
+}
+
+/// Expressions that can't be evaluated are replaced with a placeholder, the rest of the message is kept.
+#[test]
+fn test_log_message_unknown_expression() {
+    let output = interpolate_log_message("{missing} and {counter}", evaluate_test_expression);
+    assert_eq!(output, "<missing: not found> and 42");
+}
+
+/// A message without placeholders is not changed.
+#[test]
+fn test_log_message_without_expressions() {
+    let output = interpolate_log_message("Reached the loop", |_| unreachable!());
+    assert_eq!(output, "Reached the loop");
+}
@@ -1,13 +1,22 @@
-use crate::util::rtt::{RttDataHandler, client::RttClient};
+use std::collections::BTreeMap;
+
+use crate::util::rtt::{DataFormat, RttDataHandler, client::RttClient, sinks::RttSinks};
 use crate::{
     cmd::dap_server::{
         DebuggerError,
         debug_adapter::{dap::adapter::*, protocol::ProtocolAdapter},
     },
-    util::rtt::RttDecoder,
+    util::{rtt::RttDecoder, swo::ItmDecoder},
 };
 use anyhow::anyhow;
-use probe_rs::{Core, rtt};
+use probe_rs::{
+    Core,
+    rtt::{
+        self,
+        decoder::{DefmtDecoder, DefmtTable},
+    },
+};
+use time::UtcOffset;
 
 /// Manage the active RTT target for a specific SessionData, as well as provide methods to reliably move RTT from target, through the debug_adapter, to the client.
 pub struct RttConnection {
@@ -15,6 +24,10 @@ pub struct RttConnection {
     pub(crate) client: RttClient,
     /// Some status fields and methods to ensure continuity in flow of data from target to debugger to client.
     pub(crate) debugger_rtt_channels: Vec<DebuggerRttChannel>,
+    /// Where the decoded output is copied to, in addition to the client.
+    pub(crate) sinks: RttSinks,
+    /// Input from the client that did not fit into its down channel yet, by down channel.
+    pub(crate) pending_input: BTreeMap<u32, Vec<u8>>,
 }
 
 impl RttConnection {
@@ -25,15 +38,68 @@ impl RttConnection {
         debug_adapter: &mut DebugAdapter<P>,
         target_core: &mut Core<'probe>,
     ) -> bool {
+        if let Err(error) = self.write_pending_input(target_core) {
+            debug_adapter.show_error_message(&error).ok();
+        }
+
         let mut at_least_one_channel_had_data = false;
         for debugger_rtt_channel in self.debugger_rtt_channels.iter_mut() {
             at_least_one_channel_had_data |= debugger_rtt_channel
-                .poll_rtt_data(target_core, debug_adapter, &mut self.client)
+                .poll_rtt_data(
+                    target_core,
+                    debug_adapter,
+                    &mut self.client,
+                    &mut self.sinks,
+                )
                 .await
         }
         at_least_one_channel_had_data
     }
 
+    /// Writes the input from the client window of an up channel to the down channel that is paired
+    /// with it. Returns `false` if the channel does not accept input.
+    ///
+    /// The input which does not fit into the down channel is written on the next polls, once the
+    /// target has read from the channel.
+    pub fn write_input(
+        &mut self,
+        target_core: &mut Core,
+        channel_number: u32,
+        data: &str,
+    ) -> Result<bool, DebuggerError> {
+        let Some(down_channel) = self
+            .debugger_rtt_channels
+            .iter()
+            .find(|debugger_rtt_channel| debugger_rtt_channel.channel_number == channel_number)
+            .and_then(|debugger_rtt_channel| debugger_rtt_channel.down_channel)
+        else {
+            return Ok(false);
+        };
+
+        self.pending_input
+            .entry(down_channel)
+            .or_default()
+            .extend_from_slice(data.as_bytes());
+        self.write_pending_input(target_core)?;
+
+        Ok(true)
+    }
+
+    /// Writes as much of the pending input as fits into the down channels, without waiting for
+    /// the target.
+    fn write_pending_input(&mut self, target_core: &mut Core) -> Result<(), DebuggerError> {
+        for (down_channel, input) in self.pending_input.iter_mut() {
+            let written = self
+                .client
+                .write_down_channel(target_core, *down_channel, input.as_slice())
+                .map_err(|err| DebuggerError::Other(anyhow!(err)))?;
+            input.drain(..written);
+        }
+        self.pending_input.retain(|_, input| !input.is_empty());
+
+        Ok(())
+    }
+
     /// Clean up the RTT connection, restoring the state changes that we made.
     pub fn clean_up(&mut self, target_core: &mut Core) -> Result<(), DebuggerError> {
         self.client
@@ -43,11 +109,20 @@ impl RttConnection {
     }
 }
 
+/// Windows for virtual terminals are numbered from here, to not collide with up channel numbers
+/// and semihosting file handles, which start at 1024.
+const TERMINAL_WINDOW_BASE: u32 = 256;
+
 pub(crate) struct DebuggerRttChannel {
     pub(crate) channel_number: u32,
+    pub(crate) channel_name: String,
     // We will not poll target RTT channels until we have confirmation from the client that the output window has been opened.
     pub(crate) has_client_window: bool,
     pub(crate) channel_data_format: RttDecoder,
+    /// The down channel that receives the input from the client window, if any.
+    pub(crate) down_channel: Option<u32>,
+    /// The window numbers of the virtual terminals that received data, other than terminal 0.
+    pub(crate) terminal_windows: BTreeMap<u8, u32>,
 }
 
 impl DebuggerRttChannel {
@@ -59,12 +134,16 @@ impl DebuggerRttChannel {
         core: &mut Core<'probe>,
         debug_adapter: &mut DebugAdapter<P>,
         client: &mut RttClient,
+        sinks: &mut RttSinks,
     ) -> bool {
         if !self.has_client_window {
             return false;
         }
 
-        let mut out = StringCollector { data: None };
+        let mut out = StringCollector {
+            data: None,
+            terminals: vec![],
+        };
 
         match client.poll_channel(core, self.channel_number) {
             Ok(bytes) => self.channel_data_format.process(bytes, &mut out).await.ok(),
@@ -76,20 +155,140 @@ impl DebuggerRttChannel {
             }
         };
 
-        match out.data {
-            Some(data) => debug_adapter.rtt_output(self.channel_number, data),
+        let mut had_data = match out.data {
+            Some(data) => {
+                sinks.write(&self.channel_name, &data);
+                debug_adapter.rtt_output(self.channel_number, data)
+            }
             None => false,
+        };
+
+        for (terminal, data) in out.terminals {
+            sinks.write(&format!("{} terminal {terminal}", self.channel_name), &data);
+            let window = self.terminal_window(debug_adapter, terminal);
+            had_data |= debug_adapter.rtt_output(window, data);
+        }
+
+        had_data
+    }
+
+    /// Returns the number of the window for a virtual terminal, opening it on first use.
+    fn terminal_window<P: ProtocolAdapter>(
+        &mut self,
+        debug_adapter: &mut DebugAdapter<P>,
+        terminal: u8,
+    ) -> u32 {
+        *self.terminal_windows.entry(terminal).or_insert_with(|| {
+            let window = TERMINAL_WINDOW_BASE + self.channel_number * 16 + terminal as u32;
+            debug_adapter.rtt_window(
+                window,
+                format!("{} (terminal {terminal})", self.channel_name),
+                DataFormat::String,
+                None,
+            );
+            window
+        })
+    }
+}
+
+/// Windows for ITM stimulus ports are numbered from here, after the windows of virtual terminals.
+const ITM_WINDOW_BASE: u32 = 768;
+
+/// The output of the ITM stimulus ports, received over SWO and displayed like RTT channels.
+pub struct DebuggerItm {
+    decoder: ItmDecoder,
+    /// The stimulus port that carries defmt data, and the table to decode it.
+    defmt: Option<(u8, DefmtTable)>,
+    timestamp_offset: UtcOffset,
+    /// The decoders of the ports that received data. Their windows are opened on first use.
+    ports: BTreeMap<u8, RttDecoder>,
+}
+
+impl DebuggerItm {
+    pub(crate) fn new(defmt: Option<(u8, DefmtTable)>, timestamp_offset: UtcOffset) -> Self {
+        Self {
+            decoder: ItmDecoder::new(),
+            defmt,
+            timestamp_offset,
+            ports: BTreeMap::new(),
+        }
+    }
+
+    /// Reads the SWO data received by the probe and sends the output of the stimulus ports to the
+    /// client. Returns whether there was any output.
+    pub async fn process_swo_data<P: ProtocolAdapter>(
+        &mut self,
+        debug_adapter: &mut DebugAdapter<P>,
+        core: &mut Core<'_>,
+    ) -> bool {
+        let data = match core.read_swo() {
+            Ok(Some(data)) => data,
+            Ok(None) => return false,
+            Err(error) => {
+                debug_adapter
+                    .show_error_message(&DebuggerError::Other(anyhow!(error)))
+                    .ok();
+                return false;
+            }
+        };
+
+        let mut had_data = false;
+        for (port, bytes) in self.decoder.decode(&data) {
+            let decoder = self.ports.entry(port).or_insert_with(|| {
+                let (decoder, data_format) = match &self.defmt {
+                    Some((defmt_port, table)) if *defmt_port == port => (
+                        RttDecoder::Defmt {
+                            processor: DefmtDecoder::new(table.clone(), true, false, None),
+                        },
+                        DataFormat::Defmt,
+                    ),
+                    _ => (
+                        RttDecoder::string(Some(self.timestamp_offset)),
+                        DataFormat::String,
+                    ),
+                };
+                debug_adapter.rtt_window(
+                    ITM_WINDOW_BASE + port as u32,
+                    format!("ITM port {port}"),
+                    data_format,
+                    None,
+                );
+                decoder
+            });
+
+            let mut out = StringCollector {
+                data: None,
+                terminals: vec![],
+            };
+            decoder.process(&bytes, &mut out).await.ok();
+
+            // ITM ports have no virtual terminals, but display the output anyway.
+            let output = out
+                .data
+                .into_iter()
+                .chain(out.terminals.into_iter().map(|(_, data)| data));
+            for data in output {
+                had_data |= debug_adapter.rtt_output(ITM_WINDOW_BASE + port as u32, data);
+            }
         }
+
+        had_data
     }
 }
 
 struct StringCollector {
     data: Option<String>,
+    terminals: Vec<(u8, String)>,
 }
 
 impl RttDataHandler for StringCollector {
     async fn on_string_data(&mut self, data: String) -> Result<(), rtt::Error> {
-        self.data = Some(data);
+        self.data.get_or_insert_default().push_str(&data);
+        Ok(())
+    }
+
+    async fn on_terminal_data(&mut self, terminal: u8, data: String) -> Result<(), rtt::Error> {
+        self.terminals.push((terminal, data));
         Ok(())
     }
 }
@@ -11,9 +11,9 @@ use crate::{
             dap::{
                 adapter::{DebugAdapter, get_arguments},
                 dap_types::{
-                    Capabilities, DisconnectResponse, Event, ExitedEventBody,
-                    InitializeRequestArguments, MessageSeverity, Request, RttWindowOpenedArguments,
-                    TerminatedEventBody,
+                    BreakpointEventBody, Capabilities, CapabilitiesEventBody, DisconnectResponse,
+                    Event, ExitedEventBody, InitializeRequestArguments, MessageSeverity, Request,
+                    RttWindowInputArguments, RttWindowOpenedArguments, TerminatedEventBody,
                 },
                 request_helpers::halt_core,
             },
@@ -23,7 +23,7 @@ use crate::{
         server::configuration::SessionConfig,
     },
     rpc::functions::flash::Operation,
-    util::flash::build_loader,
+    util::{flash::build_loader, settings::Settings},
 };
 use anyhow::{Context, anyhow};
 use probe_rs::{
@@ -159,22 +159,54 @@ impl Debugger {
             return Ok(DebugSessionStatus::Continue(Duration::ZERO));
         }
 
-        // TODO: Currently, we only use `poll_cores()` results from the first core and need to expand to a multi-core implementation that understands which MS DAP requests are core specific.
-        let core_id = 0;
-
-        // Attach to the core. so that we have the handle available for processing the request.
+        // Some requests apply to all cores, and are handled without attaching to a specific core.
+        let result = match request.command.as_ref() {
+            "threads" => Some(debug_adapter.threads(session_data, &request)),
+            "configurationDone" => Some(debug_adapter.configuration_done(session_data, &request)),
+            "setBreakpoints" => Some(debug_adapter.set_breakpoints(session_data, &request)),
+            "setInstructionBreakpoints" => {
+                Some(debug_adapter.set_instruction_breakpoints(session_data, &request))
+            }
+            "setFunctionBreakpoints" => {
+                Some(debug_adapter.set_function_breakpoints(session_data, &request))
+            }
+            "setDataBreakpoints" => {
+                Some(debug_adapter.set_data_breakpoints(session_data, &request))
+            }
+            "rttWindowOpened" => Some(rtt_window_opened(session_data, debug_adapter, &request)),
+            "rttWindowInput" => Some(rtt_window_input(session_data, debug_adapter, &request)),
+            "disconnect" => {
+                debug_adapter
+                    .disconnect(session_data, &request)
+                    .map_err(|e| DebuggerError::Other(e.context("Error executing request.")))?;
+                return Ok(DebugSessionStatus::Terminate);
+            }
+            _ => None,
+        };
+        if let Some(result) = result {
+            result.map_err(|e| DebuggerError::Other(e.context("Error executing request.")))?;
+            return Ok(DebugSessionStatus::Continue(Duration::ZERO));
+        }
 
-        let Some(target_core_config) = self.config.core_configs.get(core_id) else {
+        // Attach to the core that the request refers to, so that we have the handle available for processing the request.
+        let Some(core_index) = session_data.core_index_for_request(&request) else {
             return Err(DebuggerError::Other(anyhow!(
-                "No core configuration found for core id {}",
-                core_id
+                "No core found to handle request '{}'",
+                request.command
             )));
         };
 
-        let new_status = core_statuses[core_id]; // Checked above
+        // `poll_cores()` reports the status of each core in the order of the core configurations.
+        let new_status = self
+            .config
+            .core_configs
+            .iter()
+            .position(|core_config| core_config.core_index == core_index)
+            .and_then(|position| core_statuses.get(position).copied())
+            .unwrap_or(CoreStatus::Unknown);
 
         let mut target_core = session_data
-            .attach_core(target_core_config.core_index)
+            .attach_core(core_index)
             .context("Unable to connect to target core")?;
 
         // For some operations, we need to make sure the core isn't sleeping, by calling `Core::halt()`.
@@ -183,17 +215,8 @@ impl Debugger {
         let mut unhalt_me = false;
 
         match request.command.as_ref() {
-            "configurationDone"
-            | "setBreakpoints"
-            | "setInstructionBreakpoints"
-            | "clearBreakpoint"
-            | "stackTrace"
-            | "threads"
-            | "scopes"
-            | "variables"
-            | "readMemory"
-            | "writeMemory"
-            | "disassemble" => {
+            "clearBreakpoint" | "dataBreakpointInfo" | "stackTrace" | "scopes" | "variables"
+            | "readMemory" | "writeMemory" | "disassemble" => {
                 if new_status == CoreStatus::Sleeping {
                     match target_core.core.halt(Duration::from_millis(100)) {
                         Ok(_) => unhalt_me = true,
@@ -212,46 +235,20 @@ impl Debugger {
 
         // Now we are ready to execute supported commands, or return an error if it isn't supported.
         let result = match request.command.as_ref() {
-            "rttWindowOpened" => {
-                if let Some(debugger_rtt_target) = target_core.core_data.rtt_connection.as_mut() {
-                    let arguments: RttWindowOpenedArguments =
-                        get_arguments(debug_adapter, &request)?;
-
-                    if let Some(rtt_channel) = debugger_rtt_target
-                        .debugger_rtt_channels
-                        .iter_mut()
-                        .find(|debugger_rtt_channel| {
-                            debugger_rtt_channel.channel_number == arguments.channel_number
-                        })
-                    {
-                        rtt_channel.has_client_window = arguments.window_is_open;
-                    }
-
-                    debug_adapter
-                        .send_response::<()>(&request, Ok(None))
-                        .map_err(|error| {
-                            DebuggerError::Other(anyhow!(
-                                "Could not deserialize arguments for RttWindowOpened : {:?}.",
-                                error
-                            ))
-                        })?;
-                }
-                Ok(())
-            }
-            "disconnect" => {
-                let result = debug_adapter.disconnect(&mut target_core, &request);
-                debug_session = DebugSessionStatus::Terminate;
-                result
-            }
             "next" => debug_adapter.next(&mut target_core, &request),
             "stepIn" => debug_adapter.step_in(&mut target_core, &request),
             "stepOut" => debug_adapter.step_out(&mut target_core, &request),
+            "stepInTargets" => debug_adapter.step_in_targets(&mut target_core, &request),
+            "stepBack" | "reverseContinue" => debug_adapter.send_response::<()>(
+                &request,
+                Err(&DebuggerError::Other(anyhow!(
+                    "Reverse execution is not supported, because the target cannot record its execution history"
+                ))),
+            ),
             "pause" => debug_adapter.pause(&mut target_core, &request),
             "readMemory" => debug_adapter.read_memory(&mut target_core, &request),
             "writeMemory" => debug_adapter.write_memory(&mut target_core, &request),
             "setVariable" => debug_adapter.set_variable(&mut target_core, &request),
-            "configurationDone" => debug_adapter.configuration_done(&mut target_core, &request),
-            "threads" => debug_adapter.threads(&mut target_core, &request),
             "restart" => {
                 let result = target_core
                     .core
@@ -262,10 +259,7 @@ impl Debugger {
                 debug_session = DebugSessionStatus::Restart(request);
                 result
             }
-            "setBreakpoints" => debug_adapter.set_breakpoints(&mut target_core, &request),
-            "setInstructionBreakpoints" => {
-                debug_adapter.set_instruction_breakpoints(&mut target_core, &request)
-            }
+            "dataBreakpointInfo" => debug_adapter.data_breakpoint_info(&mut target_core, &request),
             "stackTrace" => debug_adapter.stack_trace(&mut target_core, &request),
             "scopes" => debug_adapter.scopes(&mut target_core, &request),
             "disassemble" => debug_adapter.disassemble(&mut target_core, &request),
@@ -273,6 +267,7 @@ impl Debugger {
             "continue" => debug_adapter.r#continue(&mut target_core, &request),
             "evaluate" => debug_adapter.evaluate(&mut target_core, &request),
             "completions" => debug_adapter.completions(&mut target_core, &request),
+            "coreDump" => debug_adapter.core_dump(&mut target_core, &request),
             other_command => {
                 // Unimplemented command.
                 debug_adapter.send_response::<()>(
@@ -308,7 +303,7 @@ impl Debugger {
         mut debug_adapter: DebugAdapter<P>,
         lister: &Lister,
     ) -> Result<(), DebuggerError> {
-        let mut registry = Registry::from_builtin_families();
+        let mut registry = Settings::load().registry();
         // The DapClient startup process has a specific sequence.
         // Handle it here before starting a probe-rs session and looping through user generated requests.
         // Handling the initialize, and Attach/Launch requests here in this method,
@@ -483,44 +478,82 @@ impl Debugger {
             )?;
         }
 
-        // First, attach to the core
-        let mut target_core = session_data.attach_core(target_core_config.core_index)?;
+        let mut supports_watchpoints = false;
+        for (position, core_config) in self.config.core_configs.iter().enumerate() {
+            // The SVD file of the core configuration takes precedence over the one referenced by
+            // the target description. Both are parsed only once, and cached by the registry.
+            let peripherals = match &core_config.svd_file {
+                Some(svd_file) => registry
+                    .peripherals(&svd_file.to_string_lossy())
+                    .map(Some)
+                    .map_err(probe_rs::Error::Peripherals),
+                None => session_data.session.peripherals(),
+            };
 
-        // Immediately after attaching, halt the core, so that we can finish initalization without bumping into user code.
-        // Depending on supplied `config`, the core will be restarted at the end of initialization in the `configuration_done` request.
-        halt_core(&mut target_core.core)?;
-
-        // Before we complete, load the (optional) CMSIS-SVD file and its variable cache.
-        // Configure the [CorePeripherals].
-        if let Some(svd_file) = &target_core_config.svd_file {
-            target_core.core_data.core_peripherals =
-                match SvdCache::new(svd_file, debug_adapter, launch_attach_request.seq) {
-                    Ok(core_peripherals) => Some(core_peripherals),
-                    Err(error) => {
-                        // This is not a fatal error. We can continue the debug session without the SVD file.
-                        tracing::warn!("{:?}", error);
-                        None
+            // First, attach to the core
+            let mut target_core = session_data.attach_core(core_config.core_index)?;
+
+            // Immediately after attaching, halt the core, so that we can finish initalization without bumping into user code.
+            // Depending on supplied `config`, the core will be restarted at the end of initialization in the `configuration_done` request.
+            halt_core(&mut target_core.core)?;
+
+            // Before we complete, build the variable cache of the (optional) peripherals.
+            // Configure the [CorePeripherals].
+            target_core.core_data.core_peripherals = match peripherals {
+                Ok(Some(peripherals)) => {
+                    match SvdCache::new(peripherals, debug_adapter, launch_attach_request.seq) {
+                        Ok(core_peripherals) => Some(core_peripherals),
+                        Err(error) => {
+                            // This is not a fatal error. We can continue the debug session without the SVD file.
+                            tracing::warn!("{:?}", error);
+                            None
+                        }
                     }
-                };
-        }
+                }
+                Ok(None) => None,
+                Err(error) => {
+                    // This is not a fatal error. We can continue the debug session without the SVD file.
+                    tracing::warn!("{:?}", error);
+                    None
+                }
+            };
 
-        if requested_target_session_type == TargetSessionType::LaunchRequest {
-            // This will effectively do a `reset` and `halt` of the core, which is what we want until after the `configuration_done` request.
-            debug_adapter
-                .restart(&mut target_core, None)
-                .context("Failed to restart core")?;
-        } else {
-            // Ensure ebreak enters debug mode, this is necessary for soft breakpoints to work on architectures like RISC-V.
-            // For LaunchRequest, this is done in the `restart` above.
-            target_core.core.debug_on_sw_breakpoint(true)?;
-        }
+            // Only the first core is reset, the other cores are halted after the reset, as it may reset the whole target.
+            if requested_target_session_type == TargetSessionType::LaunchRequest && position == 0 {
+                // This will effectively do a `reset` and `halt` of the core, which is what we want until after the `configuration_done` request.
+                debug_adapter
+                    .restart(&mut target_core, None)
+                    .context("Failed to restart core")?;
+            } else {
+                // Ensure ebreak enters debug mode, this is necessary for soft breakpoints to work on architectures like RISC-V.
+                // For LaunchRequest, this is done in the `restart` above.
+                target_core.core.debug_on_sw_breakpoint(true)?;
+            }
 
-        drop(target_core);
+            supports_watchpoints |= matches!(
+                target_core.core.available_watchpoint_units(),
+                Ok(units) if units > 0
+            );
+        }
 
         // Poll cores once while still halted. This will ensure that the RTT control block is
         // cleared even when haltAfterReset = false.
         session_data.poll_cores(&self.config, debug_adapter).await?;
 
+        // The client can only set data breakpoints if at least one core has watchpoint units.
+        if supports_watchpoints {
+            debug_adapter.send_event(
+                "capabilities",
+                Some(CapabilitiesEventBody {
+                    capabilities: Capabilities {
+                        supports_data_breakpoints: Some(true),
+                        supports_data_breakpoint_bytes: Some(true),
+                        ..Default::default()
+                    },
+                }),
+            )?;
+        }
+
         debug_adapter.send_response::<()>(launch_attach_request, Ok(None))?;
         self.debug_logger.flush_to_dap(debug_adapter)?;
 
@@ -550,10 +583,21 @@ impl Debugger {
             if is_file_newer(&mut self.binary_timestamp, path_to_elf) {
                 // If there is a new binary as part of a restart, there are some key things that
                 // need to be 'reset' for things to work properly.
-                session_data.load_debug_info_for_core(target_core_config)?;
-                session_data
-                    .attach_core(target_core_config.core_index)
-                    .map(|mut target_core| target_core.recompute_breakpoints())??;
+                for core_config in self.config.core_configs.iter() {
+                    session_data.load_debug_info_for_core(core_config)?;
+                    let unverified_breakpoints = session_data
+                        .attach_core(core_config.core_index)
+                        .map(|mut target_core| target_core.recompute_breakpoints())??;
+                    for breakpoint in unverified_breakpoints {
+                        debug_adapter.send_event(
+                            "breakpoint",
+                            Some(BreakpointEventBody {
+                                breakpoint,
+                                reason: "changed".to_string(),
+                            }),
+                        )?;
+                    }
+                }
 
                 session_data.load_rtt_location(&self.config)?;
 
@@ -567,17 +611,16 @@ impl Debugger {
             }
         }
 
-        // First, attach to the core
-        let mut target_core = session_data.attach_core(target_core_config.core_index)?;
-
-        // Immediately after attaching, halt the core, so that we can finish restart logic without bumping into user code.
-        halt_core(&mut target_core.core)?;
+        for core_config in self.config.core_configs.iter() {
+            // First, attach to the core
+            let mut target_core = session_data.attach_core(core_config.core_index)?;
 
-        // Reset RTT so that the link can be re-established and the control block cleared.
-        target_core.core_data.rtt_connection = None;
+            // Immediately after attaching, halt the core, so that we can finish restart logic without bumping into user code.
+            halt_core(&mut target_core.core)?;
 
-        // We can't keep the reference for borrow checker reasons.
-        drop(target_core);
+            // Reset RTT so that the link can be re-established and the control block cleared.
+            target_core.core_data.rtt_connection = None;
+        }
 
         // Poll cores once while still halted. This will ensure that the RTT control block is
         // cleared even when haltAfterReset = false.
@@ -590,6 +633,20 @@ impl Debugger {
         debug_adapter
             .restart(&mut target_core, Some(request))
             .context("Failed to restart core")?;
+        drop(target_core);
+
+        // The other cores are not reset themselves, as resetting the first core may reset the whole target.
+        for core_config in self.config.core_configs.iter().skip(1) {
+            let mut target_core = session_data.attach_core(core_config.core_index)?;
+            halt_core(&mut target_core.core)?;
+            target_core.core.debug_on_sw_breakpoint(true)?;
+
+            if !debug_adapter.halt_after_reset {
+                debug_adapter
+                    .r#continue(&mut target_core, request)
+                    .context("Failed to resume core")?;
+            }
+        }
 
         Ok(())
     }
@@ -684,6 +741,7 @@ impl Debugger {
                     }
                     ProgressEvent::FlashLayoutReady { .. } => {}
                     ProgressEvent::DiagnosticMessage { .. } => {}
+                    ProgressEvent::PipelineStats(_) => {}
                 }
             })
         });
@@ -820,11 +878,15 @@ impl Debugger {
             supports_clipboard_context: Some(true),
             supports_disassemble_request: Some(true),
             supports_instruction_breakpoints: Some(true),
+            // Data breakpoints are announced with a `capabilities` event once we know that the
+            // cores have watchpoint units, see `handle_launch_attach`.
+            supports_log_points: Some(true),
+            supports_function_breakpoints: Some(true),
             supports_stepping_granularity: Some(true),
+            supports_step_in_targets_request: Some(true),
             supports_completions_request: Some(true),
             support_terminate_debuggee: Some(true),
             // supports_value_formatting_options: Some(true),
-            // supports_function_breakpoints: Some(true),
             // TODO: Use DEMCR register to implement exception breakpoints
             // supports_exception_options: Some(true),
             // supports_exception_filter_options: Some (true),
@@ -838,6 +900,69 @@ impl Debugger {
     }
 }
 
+/// Track whether the client has opened the window of an RTT channel, so that we only poll channels with a window.
+/// Windows are not associated with a core, so the channel is updated on every core that has it.
+fn rtt_window_opened<P: ProtocolAdapter>(
+    session_data: &mut SessionData,
+    debug_adapter: &mut DebugAdapter<P>,
+    request: &Request,
+) -> anyhow::Result<()> {
+    let arguments: RttWindowOpenedArguments = get_arguments(debug_adapter, request)?;
+
+    for core_data in session_data.core_data.iter_mut() {
+        let Some(debugger_rtt_target) = core_data.rtt_connection.as_mut() else {
+            continue;
+        };
+
+        if let Some(rtt_channel) =
+            debugger_rtt_target
+                .debugger_rtt_channels
+                .iter_mut()
+                .find(|debugger_rtt_channel| {
+                    debugger_rtt_channel.channel_number == arguments.channel_number
+                })
+        {
+            rtt_channel.has_client_window = arguments.window_is_open;
+        }
+    }
+
+    debug_adapter.send_response::<()>(request, Ok(None))
+}
+
+/// Write the input that the user typed into the window of an RTT channel to the down channel that is paired with it.
+/// Windows are not associated with a core, so the input goes to every core that has the channel.
+fn rtt_window_input<P: ProtocolAdapter>(
+    session_data: &mut SessionData,
+    debug_adapter: &mut DebugAdapter<P>,
+    request: &Request,
+) -> anyhow::Result<()> {
+    let arguments: RttWindowInputArguments = get_arguments(debug_adapter, request)?;
+
+    let mut has_down_channel = false;
+    session_data.for_each_core(|target_core| {
+        if let Some(debugger_rtt_target) = target_core.core_data.rtt_connection.as_mut() {
+            has_down_channel |= debugger_rtt_target.write_input(
+                &mut target_core.core,
+                arguments.channel_number,
+                &arguments.data,
+            )?;
+        }
+        Ok(())
+    })?;
+
+    if has_down_channel {
+        debug_adapter.send_response::<()>(request, Ok(None))
+    } else {
+        debug_adapter.send_response::<()>(
+            request,
+            Err(&DebuggerError::UserMessage(format!(
+                "RTT channel {} does not have a down channel for input.",
+                arguments.channel_number
+            ))),
+        )
+    }
+}
+
 pub(crate) fn is_file_newer(
     saved_binary_timestamp: &mut Option<Duration>,
     path_to_elf: &Path,
@@ -942,11 +1067,14 @@ mod test {
             supports_delayed_stack_trace_loading: Some(true),
             supports_disassemble_request: Some(true),
             supports_instruction_breakpoints: Some(true),
+            supports_log_points: Some(true),
+            supports_function_breakpoints: Some(true),
             supports_read_memory_request: Some(true),
             supports_write_memory_request: Some(true),
             supports_restart_request: Some(true),
             supports_set_variable: Some(true),
             supports_stepping_granularity: Some(true),
+            supports_step_in_targets_request: Some(true),
             support_terminate_debuggee: Some(true),
 
             ..Default::default()
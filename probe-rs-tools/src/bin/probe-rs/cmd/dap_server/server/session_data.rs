@@ -1,33 +1,41 @@
 use super::{
     configuration::{self, CoreConfig, SessionConfig},
     core_data::{CoreData, CoreHandle},
+    debug_rtt::DebuggerItm,
 };
 use crate::{
     FormatKind,
     cmd::dap_server::{
         DebuggerError,
         debug_adapter::{
-            dap::{adapter::DebugAdapter, dap_types::Source},
+            dap::{
+                adapter::DebugAdapter,
+                dap_types::{Request, Source},
+            },
             protocol::ProtocolAdapter,
         },
     },
-    util::{common_options::OperationError, rtt},
+    util::{
+        common_options::OperationError,
+        rtt,
+        semihosting::{SemihostingFiles, SemihostingHost},
+    },
 };
-use anyhow::{Result, anyhow};
+use anyhow::{Context, Result, anyhow};
 use probe_rs::{
-    BreakpointCause, CoreStatus, HaltReason, Session, VectorCatchCondition,
+    BreakpointCause, CoreStatus, HaltReason, ReadCache, Session, VectorCatchCondition,
+    architecture::arm::component::TraceSink,
     config::{Registry, TargetSelector},
     probe::list::Lister,
-    rtt::ScanRegion,
-};
-use probe_rs_debug::{
-    DebugRegisters, SourceLocation, debug_info::DebugInfo, exception_handler_for_core,
+    rtt::{ScanRegion, decoder::DefmtTable},
 };
+use probe_rs_debug::{SourceLocation, debug_info::DebugInfo, rtos::detect_rtos};
 use std::{collections::HashMap, env::set_current_dir, time::Duration};
 use time::UtcOffset;
 
 /// The supported breakpoint types
 #[derive(Clone, Debug, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 pub(crate) enum BreakpointType {
     /// A breakpoint was requested using an instruction address, and usually a result of a user requesting a
     /// breakpoint while in a 'disassembly' view.
@@ -36,7 +44,12 @@ pub(crate) enum BreakpointType {
     SourceBreakpoint {
         source: Box<Source>,
         location: SourceLocationScope,
+        /// If set, this is a logpoint: the message is logged, with `{expression}` placeholders replaced
+        /// by their values, and the core continues instead of halting.
+        log_message: Option<String>,
     },
+    /// A breakpoint on every instance of a function, requested by its name.
+    FunctionBreakpoint { name: String },
 }
 
 /// Breakpoint requests will either be refer to a specific `SourceLocation`, or unspecified, in which case it will refer to
@@ -56,7 +69,8 @@ pub struct ActiveBreakpoint {
 
 /// SessionData is designed to be similar to [probe_rs::Session], in as much that it provides handles to the [CoreHandle] instances for each of the available [probe_rs::Core] involved in the debug session.
 /// To get access to the [CoreHandle] for a specific [probe_rs::Core], the
-/// TODO: Adjust [SessionConfig] to allow multiple cores (and if appropriate, their binaries) to be specified.
+/// [SessionData::attach_core] method should be used. Each core is presented to the client as a DAP "thread",
+/// with the core index as the thread id.
 pub(crate) struct SessionData {
     pub(crate) session: Session,
     /// [SessionData] will manage one [CoreData] per target core, that is also present in [SessionConfig::core_configs]
@@ -80,7 +94,8 @@ impl SessionData {
         let options = config.probe_options().load(registry)?;
         let target_probe = options.attach_probe(lister).await?;
         let mut target_session = options
-            .attach_session(target_probe, target_selector)
+            .attach_session_with_speed_negotiation(lister, target_probe, target_selector)
+            .await
             .map_err(|operation_error| {
                 match operation_error {
                     OperationError::AttachingFailed {
@@ -116,10 +131,9 @@ impl SessionData {
         // `FlashingConfig` probe level initialization.
 
         // `CoreConfig` probe level initialization.
-        if config.core_configs.len() != 1 {
-            // TODO: For multi-core, allow > 1.
+        if config.core_configs.is_empty() {
             return Err(DebuggerError::Other(anyhow!(
-                "probe-rs-debugger requires that one, and only one, core be configured for debugging."
+                "probe-rs-debugger requires that at least one core be configured for debugging."
             )));
         }
 
@@ -164,6 +178,9 @@ impl SessionData {
                 }
             }
 
+            let debug_info = debug_info_from_binary(core_configuration)?;
+            let rtos = detect_rtos(&debug_info);
+
             core_data_vec.push(CoreData {
                 core_index: core_configuration.core_index,
                 last_known_status: CoreStatus::Unknown,
@@ -172,21 +189,30 @@ impl SessionData {
                     core_configuration.core_index,
                     target_session.target().name
                 ),
-                debug_info: debug_info_from_binary(core_configuration)?,
+                debug_info,
                 static_variables: None,
                 core_peripherals: None,
                 stack_frames: vec![],
+                rtos,
+                rtos_threads: vec![],
                 breakpoints: vec![],
+                watchpoints: vec![],
                 rtt_scan_ranges: ScanRegion::Ranges(vec![]),
                 rtt_connection: None,
+                itm: swo_output(&mut target_session, core_configuration, timestamp_offset)?,
                 rtt_client: None,
                 clear_rtt_header: false,
                 rtt_header_cleared: false,
+                core_dump_ranges: core_configuration.core_dump_ranges.clone(),
 
                 // We're abusing the RTT window machinery here for simplicity.
                 // Let's assume there are less than 1024 RTT channels.
                 next_semihosting_handle: 1024,
                 semihosting_handles: HashMap::new(),
+                semihosting_files: semihosting_files(core_configuration)?,
+                semihosting_host: SemihostingHost::new(),
+                last_halt: None,
+                memory_cache: ReadCache::new(),
             })
         }
 
@@ -288,6 +314,80 @@ impl SessionData {
         }
     }
 
+    /// Determine the core that a request refers to, using the `threadId`, `frameId` or `variablesReference` arguments.
+    ///
+    /// Requests that don't refer to a specific core (e.g. `readMemory`) are handled by the core that halted most recently.
+    pub(crate) fn core_index_for_request(&self, request: &Request) -> Option<usize> {
+        let argument = |name: &str| {
+            request
+                .arguments
+                .as_ref()
+                .and_then(|arguments| arguments.get(name))
+                .and_then(|value| value.as_i64())
+        };
+
+        let core_data = if let Some(thread_id) = argument("threadId") {
+            self.core_data
+                .iter()
+                .find(|core_data| core_data.has_thread(thread_id))
+        } else if let Some(frame_id) = argument("frameId") {
+            self.core_data.iter().find(|core_data| {
+                core_data
+                    .all_stack_frames()
+                    .any(|frame| i64::from(frame.id) == frame_id)
+            })
+        } else if let Some(variables_reference) = argument("variablesReference") {
+            self.core_data
+                .iter()
+                .find(|core_data| core_data.has_reference(variables_reference.into()))
+        } else {
+            None
+        };
+
+        core_data
+            .or_else(|| {
+                self.core_data
+                    .iter()
+                    .filter(|core_data| core_data.last_halt.is_some())
+                    .max_by_key(|core_data| core_data.last_halt.map(|halt| halt.host))
+            })
+            .or_else(|| self.core_data.first())
+            .map(|core_data| core_data.core_index)
+    }
+
+    /// Attach to each core in turn, to handle requests that apply to all cores (e.g. `setBreakpoints`).
+    ///
+    /// Sleeping cores are halted while `handler` runs, and resumed afterwards.
+    pub(crate) fn for_each_core(
+        &mut self,
+        mut handler: impl FnMut(&mut CoreHandle<'_>) -> Result<()>,
+    ) -> Result<(), DebuggerError> {
+        let core_indices = self
+            .core_data
+            .iter()
+            .map(|core_data| core_data.core_index)
+            .collect::<Vec<_>>();
+
+        for core_index in core_indices {
+            let mut target_core = self.attach_core(core_index)?;
+
+            let is_sleeping = target_core.core.status()? == CoreStatus::Sleeping;
+            if is_sleeping {
+                target_core.core.halt(Duration::from_millis(100))?;
+            }
+
+            let result = handler(&mut target_core);
+
+            if is_sleeping {
+                target_core.core.run()?;
+            }
+
+            result?;
+        }
+
+        Ok(())
+    }
+
     /// The target has no way of notifying the debug adapter when things changes, so we have to constantly poll it to determine:
     /// - Whether the target cores are running, and what their actual status is.
     /// - Whether the target cores have data in their RTT buffers that we need to read and pass to the client.
@@ -355,6 +455,7 @@ impl SessionData {
                         debug_adapter,
                         core_config.program_binary.as_ref().unwrap(),
                         &core_config.rtt_config,
+                        &core_config.rtt_log,
                         timestamp_offset,
                     ) {
                         debug_adapter
@@ -364,6 +465,16 @@ impl SessionData {
                 }
             }
 
+            // Forward the output of the ITM stimulus ports, if SWO is configured.
+            if let Some(itm) = &mut target_core.core_data.itm {
+                if itm
+                    .process_swo_data(debug_adapter, &mut target_core.core)
+                    .await
+                {
+                    suggest_delay_required = false;
+                }
+            }
+
             // Handle potential semihosting commands. If the command is handled,
             // the core will be resumed, so we need to update the status.
             // If the command is not handled, the core will remain halted and we
@@ -386,6 +497,18 @@ impl SessionData {
                 }
             }
 
+            // Logpoints don't halt the debug session. Log their message and resume the core.
+            if current_core_status != previous_core_status {
+                if let CoreStatus::Halted(HaltReason::Breakpoint(_)) = current_core_status {
+                    if let Some(logpoint) = target_core.logpoint_at_program_counter() {
+                        current_core_status =
+                            target_core.handle_logpoint(debug_adapter, &logpoint)?;
+                        suggest_delay_required = false;
+                        target_core.core_data.last_known_status = current_core_status;
+                    }
+                }
+            }
+
             // If the core is running, we set the flag to indicate that at least one core is not halted.
             // By setting it here, we ensure that RTT will be checked at least once after the core has halted.
             if !current_core_status.is_halted() {
@@ -393,25 +516,7 @@ impl SessionData {
             } else if !cores_halted_previously {
                 // If currently halted, and was previously running
                 // update the stack frames
-                let _stackframe_span = tracing::debug_span!("Update Stack Frames").entered();
-                tracing::debug!(
-                    "Updating the stack frame data for core #{}",
-                    target_core.core.id()
-                );
-
-                let initial_registers = DebugRegisters::from_core(&mut target_core.core);
-                let exception_interface = exception_handler_for_core(target_core.core.core_type());
-                let instruction_set = target_core.core.instruction_set().ok();
-
-                target_core.core_data.static_variables =
-                    Some(target_core.core_data.debug_info.create_static_scope_cache());
-
-                target_core.core_data.stack_frames = target_core.core_data.debug_info.unwind(
-                    &mut target_core.core,
-                    initial_registers,
-                    exception_interface.as_ref(),
-                    instruction_set,
-                )?;
+                target_core.update_stack_frames()?;
             }
             status_of_cores.push(current_core_status);
         }
@@ -449,3 +554,42 @@ fn debug_info_from_binary(core_configuration: &CoreConfig) -> anyhow::Result<Deb
 
     DebugInfo::from_file(binary_path).map_err(|error| anyhow!(error))
 }
+
+/// Configures SWO for the core, if enabled, to receive the output of the ITM stimulus ports.
+fn swo_output(
+    session: &mut Session,
+    core_configuration: &CoreConfig,
+    timestamp_offset: UtcOffset,
+) -> anyhow::Result<Option<DebuggerItm>> {
+    let Some(swo_config) = core_configuration.swo.swo_config()? else {
+        return Ok(None);
+    };
+
+    session
+        .setup_tracing(core_configuration.core_index, TraceSink::Swo(swo_config))
+        .context("Failed to configure SWO")?;
+
+    let defmt = match (
+        core_configuration.swo.defmt_port,
+        core_configuration.program_binary.as_ref(),
+    ) {
+        (Some(port), Some(binary)) => {
+            let elf = std::fs::read(binary)
+                .with_context(|| format!("Failed to read {}", binary.display()))?;
+            DefmtTable::try_from_bytes(&elf)?.map(|table| (port, table))
+        }
+        _ => None,
+    };
+
+    Ok(Some(DebuggerItm::new(defmt, timestamp_offset)))
+}
+
+fn semihosting_files(core_configuration: &CoreConfig) -> anyhow::Result<Option<SemihostingFiles>> {
+    let Some(ref root) = core_configuration.semihosting_root else {
+        return Ok(None);
+    };
+
+    SemihostingFiles::new(root)
+        .map(Some)
+        .map_err(|error| anyhow!("Invalid `semihosting_root` '{}': {error}", root.display()))
+}
@@ -4,7 +4,13 @@ use std::rc::Rc;
 
 use probe_rs::config::Registry;
 use probe_rs::probe::list::Lister;
-use rustyline::{DefaultEditor, error::ReadlineError};
+use probe_rs_debug::DebugInfo;
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper, error::ReadlineError};
 use time::UtcOffset;
 
 use crate::cmd::dap_server::debug_adapter::dap::adapter::DebugAdapter;
@@ -13,6 +19,8 @@ use crate::cmd::dap_server::debug_adapter::dap::dap_types::EvaluateArguments;
 use crate::cmd::dap_server::debug_adapter::dap::dap_types::EvaluateResponseBody;
 use crate::cmd::dap_server::debug_adapter::dap::dap_types::InitializeRequestArguments;
 use crate::cmd::dap_server::debug_adapter::dap::dap_types::OutputEventBody;
+use crate::cmd::dap_server::debug_adapter::dap::dap_types::RttDataEventBody;
+use crate::cmd::dap_server::debug_adapter::dap::repl_commands::REPL_COMMANDS;
 use crate::cmd::dap_server::debug_adapter::protocol::ProtocolAdapter;
 use crate::cmd::dap_server::server::configuration::ConsoleLog;
 use crate::cmd::dap_server::server::configuration::CoreConfig;
@@ -65,9 +73,17 @@ impl ProtocolAdapter for CliAdapter {
             "memory" => {}
             "stopped" => {}
             "breakpoint" => {}
-            // No RTT support (yet)
-            "probe-rs-rtt-channel-config" | "probe-rs-rtt-data" => {}
-            // No flashing support (yet)
+            "probe-rs-rtt-data" => {
+                let Some(body) = serialized_body else {
+                    return Ok(());
+                };
+
+                let rtt_data = serde_json::from_str::<RttDataEventBody>(&body)?;
+
+                print!("{}", rtt_data.data);
+            }
+            "probe-rs-rtt-channel-config" => {}
+            // Flashing progress is not shown
             "progressStart" | "progressEnd" | "progressUpdate" => {}
             // We can safely ignore "exited"
             "exited" => {}
@@ -95,7 +111,7 @@ impl ProtocolAdapter for CliAdapter {
                 println!("{}", body.result);
             }
             "initialize" => {}
-            "attach" => {}
+            "attach" | "launch" => {}
             _ => println!("{response:?}"),
         }
 
@@ -161,6 +177,90 @@ fn print_error(response: &Response) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Tab completion for REPL commands, and for symbol names from the debugged binary.
+struct ReplHelper {
+    symbols: Vec<String>,
+}
+
+impl ReplHelper {
+    fn new(exe: Option<&PathBuf>) -> Self {
+        let mut symbols = exe
+            .and_then(|exe| DebugInfo::from_file(exe).ok())
+            .map(|debug_info| {
+                debug_info
+                    .symbols()
+                    .iter()
+                    .map(|symbol| symbol.display_name().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        symbols.sort_unstable();
+        symbols.dedup();
+
+        Self { symbols }
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let line = &line[..pos];
+        let start = line.rfind(char::is_whitespace).map_or(0, |index| index + 1);
+        let word = &line[start..];
+
+        let mut preceding_words = line[..start].split_whitespace();
+        let candidates = match preceding_words.next() {
+            // Complete the command itself.
+            None => REPL_COMMANDS
+                .iter()
+                .map(|command| command.command)
+                .filter(|command| command.starts_with(word))
+                .map(str::to_string)
+                .collect(),
+            Some(command_name) => {
+                let sub_commands = REPL_COMMANDS
+                    .iter()
+                    .find(|command| command.command == command_name)
+                    .map(|command| command.sub_commands)
+                    .unwrap_or_default();
+
+                if preceding_words.next().is_none() && !sub_commands.is_empty() {
+                    sub_commands
+                        .iter()
+                        .map(|command| command.command)
+                        .filter(|command| command.starts_with(word))
+                        .map(str::to_string)
+                        .collect()
+                } else {
+                    self.symbols
+                        .iter()
+                        .filter(|symbol| symbol.starts_with(word))
+                        .cloned()
+                        .collect()
+                }
+            }
+        };
+
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
 #[derive(clap::Parser)]
 pub struct Cmd {
     #[clap(flatten)]
@@ -180,6 +280,19 @@ pub struct Cmd {
     /// Disable hardfault vector catch if its supported on the target.
     #[clap(long)]
     pub no_catch_hardfault: bool,
+
+    /// Flash the binary given with `--exe` before starting the debug session.
+    #[clap(long, requires = "exe")]
+    pub flash: bool,
+
+    /// Print RTT output of the target while the debug session is running.
+    #[clap(long)]
+    pub rtt: bool,
+
+    /// The SVD file describing the peripherals of the target, instead of the one referenced by
+    /// the target description.
+    #[clap(long, value_parser)]
+    pub svd: Option<PathBuf>,
 }
 
 impl Cmd {
@@ -227,8 +340,11 @@ impl Cmd {
         });
         debugger.handle_initialize(&mut debug_adapter)?;
 
+        let symbol_helper = ReplHelper::new(self.exe.as_ref());
+
         let attach_request = Request {
-            command: "attach".to_string(),
+            // Flashing is only done for a `launch` request.
+            command: if self.flash { "launch" } else { "attach" }.to_string(),
             arguments: serde_json::to_value(&SessionConfig {
                 console_log_level: None,
                 cwd: None,
@@ -239,17 +355,28 @@ impl Cmd {
                 speed: self.common.speed,
                 wire_protocol: self.common.protocol,
                 allow_erase_all: false,
-                flashing_config: FlashingConfig::default(),
+                no_debug_freeze: false,
+                memory_map_policy: self.common.memory_map,
+                reconnect_on_power_down: self.common.reconnect_on_power_down,
+                flashing_config: FlashingConfig {
+                    flashing_enabled: self.flash,
+                    halt_after_reset: self.flash,
+                    ..Default::default()
+                },
                 core_configs: vec![CoreConfig {
                     core_index: self.shared.core,
                     program_binary: self.exe,
-                    svd_file: None,
+                    svd_file: self.svd,
                     rtt_config: RttConfig {
-                        enabled: false,
+                        enabled: self.rtt,
                         channels: vec![],
+                        batched_reads: false,
+                        server: Default::default(),
                     },
                     catch_hardfault: !self.no_catch_hardfault,
                     catch_reset: !self.no_catch_reset,
+                    semihosting_root: None,
+                    ..Default::default()
                 }],
             })
             .ok(),
@@ -274,7 +401,15 @@ impl Cmd {
             .process_next_request(&mut session_data, &mut debug_adapter)
             .await?;
 
-        let mut rl = DefaultEditor::new()?;
+        let mut rl = Editor::<ReplHelper, DefaultHistory>::new()?;
+        rl.set_helper(Some(symbol_helper));
+
+        let history_path = directories::ProjectDirs::from("rs", "probe-rs", "probe-rs")
+            .map(|project_dirs| project_dirs.data_dir().join("debug_history"));
+        if let Some(history_path) = &history_path {
+            // A missing history file is expected on the first run.
+            let _ = rl.load_history(history_path);
+        }
 
         let mut seq = 3;
         while !shared.borrow().stop {
@@ -308,7 +443,7 @@ impl Cmd {
                         .await?;
                 }
                 // For end of file and ctrl-c, we just quit
-                Err(ReadlineError::Eof | ReadlineError::Interrupted) => return Ok(()),
+                Err(ReadlineError::Eof | ReadlineError::Interrupted) => break,
                 Err(actual_error) => {
                     // Show error message and quit
                     println!("Error handling input: {actual_error:?}");
@@ -317,6 +452,15 @@ impl Cmd {
             }
         }
 
+        if let Some(history_path) = &history_path {
+            if let Some(parent) = history_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            if let Err(error) = rl.save_history(history_path) {
+                tracing::warn!("Failed to save the command history: {error}");
+            }
+        }
+
         Ok(())
     }
 }
@@ -0,0 +1,208 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use probe_rs::config::RawFlashAlgorithm;
+use probe_rs::config::Registry;
+use probe_rs::flashing::simulator::{CallOutcome, SimulationReport, simulate_flash_algorithm};
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(subcommand)]
+    subcommand: Subcommand,
+}
+
+#[derive(clap::Subcommand)]
+enum Subcommand {
+    /// Runs flash algorithms on a simulated core, without a debug probe.
+    ///
+    /// Each algorithm erases and programs the first sector of its flash range. The
+    /// simulation reports failing calls, the stack usage, writes into the code of the
+    /// algorithm and the accessed peripheral registers.
+    Simulate {
+        /// The flash algorithm ELF file (FLM) to simulate. If omitted, the flash algorithms of the
+        /// chip's target description are simulated.
+        elf: Option<PathBuf>,
+        /// The chip whose memory map is used. Its flash algorithms are simulated if no ELF
+        /// file is given.
+        #[clap(long)]
+        chip: String,
+        /// A target description to load in addition to the built-in targets.
+        #[clap(long, value_name = "chip description file path")]
+        chip_description_path: Option<PathBuf>,
+        /// Only simulate the flash algorithm with this name.
+        #[clap(long, conflicts_with = "elf")]
+        algorithm: Option<String>,
+        /// The maximum number of instructions a single call may execute.
+        #[clap(long, default_value_t = 10_000_000)]
+        max_instructions: u64,
+    },
+}
+
+impl Cmd {
+    pub fn run(self, registry: &mut Registry) -> anyhow::Result<()> {
+        match self.subcommand {
+            Subcommand::Simulate {
+                elf,
+                chip,
+                chip_description_path,
+                algorithm,
+                max_instructions,
+            } => {
+                if let Some(path) = chip_description_path {
+                    let yaml = std::fs::read_to_string(&path).with_context(|| {
+                        format!("Failed to read chip description {}", path.display())
+                    })?;
+                    registry
+                        .add_target_family_from_yaml(&yaml)
+                        .with_context(|| format!("Failed to parse {}", path.display()))?;
+                }
+
+                let target = registry.get_target_by_name(&chip)?;
+
+                let from_elf = match &elf {
+                    Some(path) => Some(read_flash_algorithm(path)?),
+                    None => None,
+                };
+
+                let algorithms = match &from_elf {
+                    Some(raw) => vec![raw],
+                    None => target
+                        .flash_algorithms
+                        .iter()
+                        .filter(|raw| algorithm.as_ref().is_none_or(|name| raw.name == *name))
+                        .collect::<Vec<_>>(),
+                };
+                if algorithms.is_empty() {
+                    match algorithm {
+                        Some(name) => {
+                            anyhow::bail!("{} has no flash algorithm named {name}", target.name)
+                        }
+                        None => anyhow::bail!("{} has no flash algorithms", target.name),
+                    }
+                }
+
+                let mut failed = vec![];
+                for raw in algorithms {
+                    println!("{}", raw.name);
+                    let report = simulate_flash_algorithm(&target, raw, max_instructions)
+                        .with_context(|| format!("Failed to simulate {}", raw.name))?;
+                    print_report(&report);
+                    println!();
+
+                    if !report.is_success() {
+                        failed.push(raw.name.as_str());
+                    }
+                }
+
+                if !failed.is_empty() {
+                    anyhow::bail!("Simulation failed for {}", failed.join(", "));
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Extracts the flash algorithm from the ELF file at `path`.
+fn read_flash_algorithm(path: &Path) -> anyhow::Result<RawFlashAlgorithm> {
+    let buffer = std::fs::read(path)
+        .with_context(|| format!("Failed to read flash algorithm {}", path.display()))?;
+
+    target_gen::parser::extract_flash_algo(None, &buffer, path, true, false)
+}
+
+fn print_report(report: &SimulationReport) {
+    println!("  Loaded at {:#010x}", report.load_address);
+    for call in &report.calls {
+        let [r0, r1, r2] = call.arguments;
+        let outcome = match &call.outcome {
+            CallOutcome::Returned(value) if call.is_success() => format!("returned {value:#x}"),
+            CallOutcome::Returned(value) => {
+                format!("returned {value:#x}, expected {:#x}", call.expected)
+            }
+            CallOutcome::InstructionLimit => "did not return".to_string(),
+            CallOutcome::Fault(fault) => fault.to_string(),
+        };
+        println!(
+            "  {}({r0:#x}, {r1:#x}, {r2:#x}): {outcome} after {} instructions",
+            call.function, call.instructions
+        );
+    }
+
+    println!(
+        "  Stack usage: {} of {} bytes (top at {:#010x}){}",
+        report.max_stack_usage,
+        report.stack_size,
+        report.stack_top,
+        if report.stack_overflow() {
+            ", overflow"
+        } else {
+            ""
+        }
+    );
+
+    for issue in &report.layout_issues {
+        println!("  Layout: {issue}");
+    }
+
+    if !report.code_writes.is_empty() {
+        println!("  The algorithm wrote to its own code at:");
+        for address in &report.code_writes {
+            println!("    {address:#010x}");
+        }
+    }
+
+    if !report.peripheral_accesses.is_empty() {
+        println!("  Accessed peripheral registers:");
+        for address in &report.peripheral_accesses {
+            println!("    {address:#010x}");
+        }
+    }
+
+    match report.programmed_data_visible {
+        Some(true) => println!("  The programmed data is visible in flash."),
+        Some(false) => println!(
+            "  The programmed data is not visible in flash, the algorithm probably programs through peripheral registers."
+        ),
+        None => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/bin/probe-rs/cmd/test_data/nrf52xxx.flm")
+    }
+
+    #[test]
+    fn reads_flash_algorithm_from_elf() {
+        let raw = read_flash_algorithm(&fixture()).unwrap();
+
+        assert_eq!(raw.name, "nrf52xxx");
+        assert!(raw.pc_init.is_some());
+        assert!(!raw.instructions.is_empty());
+        assert!(raw.data_section_offset > 0);
+    }
+
+    #[test]
+    fn simulates_flash_algorithm_from_elf() {
+        let registry = Registry::from_builtin_families();
+        let target = registry.get_target_by_name("nRF52832_xxAA").unwrap();
+        let raw = read_flash_algorithm(&fixture()).unwrap();
+
+        let report = simulate_flash_algorithm(&target, &raw, 100_000).unwrap();
+
+        // `Init` waits for the NVMC to become ready, which the simulated peripherals never are.
+        assert_eq!(report.calls.len(), 1);
+        assert_eq!(report.calls[0].function, "Init");
+        assert_eq!(report.calls[0].outcome, CallOutcome::InstructionLimit);
+        assert!(report.peripheral_accesses.contains(&0x4001_e400));
+        // Writes to the data section of the algorithm are not writes to its code.
+        assert!(report.code_writes.is_empty());
+        assert!(report.layout_issues.is_empty());
+    }
+}
@@ -8,10 +8,12 @@ pub(crate) use stub::{GdbInstanceConfiguration, run};
 
 use std::path::PathBuf;
 use std::process::Command;
+use std::rc::Rc;
 use std::time::Duration;
 
 use parking_lot::FairMutex;
 use probe_rs::{config::Registry, probe::list::Lister};
+use probe_rs_debug::DebugInfo;
 
 use crate::util::common_options::ProbeOptions;
 
@@ -35,7 +37,8 @@ pub struct Cmd {
 
     /// The path to the ELF file to debug.
     ///
-    /// This only needs to be specified when using `--gdb`.
+    /// This is passed to the spawned GDB when using `--gdb`. If the program uses a supported
+    /// RTOS, its threads are reported to GDB.
     #[clap(index = 1)]
     path: Option<PathBuf>,
 
@@ -60,11 +63,27 @@ impl Cmd {
             .gdb_connection_string
             .unwrap_or_else(|| "localhost:1337".to_string());
 
-        let instances = crate::cmd::gdb_server::GdbInstanceConfiguration::from_session(
+        let mut instances = crate::cmd::gdb_server::GdbInstanceConfiguration::from_session(
             &session,
             Some(gdb_connection_string),
         );
 
+        if let Some(path) = &self.path {
+            match DebugInfo::from_file(path) {
+                Ok(debug_info) => {
+                    let debug_info = Rc::new(debug_info);
+                    for instance in instances.iter_mut() {
+                        instance.debug_info = Some(debug_info.clone());
+                    }
+                }
+                Err(error) => {
+                    tracing::warn!(
+                        "Failed to load the debug info, RTOS threads are not shown: {error}"
+                    )
+                }
+            }
+        }
+
         for instance in instances.iter() {
             println!(
                 "Firing up GDB stub for {:?} cores at {:?}",
@@ -1,9 +1,11 @@
 use anyhow::bail;
 use parking_lot::FairMutex;
 use probe_rs::{CoreType, Session};
+use probe_rs_debug::DebugInfo;
 
 use std::net::{SocketAddr, ToSocketAddrs};
 use std::process::Child;
+use std::rc::Rc;
 use std::time::Duration;
 
 use itertools::Itertools;
@@ -20,6 +22,8 @@ pub struct GdbInstanceConfiguration {
     pub cores: Vec<usize>,
     /// The list of [SocketAddr] addresses to bind to
     pub socket_addrs: Vec<SocketAddr>,
+    /// The debug information of the program, used to report the threads of an RTOS to GDB.
+    pub debug_info: Option<Rc<DebugInfo>>,
 }
 
 impl GdbInstanceConfiguration {
@@ -67,6 +71,7 @@ impl GdbInstanceConfiguration {
                 core_type,
                 cores,
                 socket_addrs: adjust_addrs(&addrs, i),
+                debug_info: None,
             })
             .collect()
     }
@@ -90,7 +95,12 @@ pub fn run<'a>(
     // Turn our group list into GDB targets
     let mut targets = instances
         .map(|instance| {
-            target::RuntimeTarget::new(session, instance.cores.to_vec(), &instance.socket_addrs[..])
+            target::RuntimeTarget::new(
+                session,
+                instance.cores.to_vec(),
+                &instance.socket_addrs[..],
+                instance.debug_info.clone(),
+            )
         })
         .collect::<Result<Vec<_>, _>>()?;
 
@@ -0,0 +1,243 @@
+use super::desc::{GdbRegister, GdbRegisterSource};
+use super::{GdbErrorExt, RuntimeTarget};
+use crate::cmd::gdb_server::arch::{RuntimeRegId, RuntimeRegisters};
+
+use gdbstub::common::Tid;
+use gdbstub::target::ext::base::multithread::{MultiThreadBase, MultiThreadResumeOps};
+use gdbstub::target::ext::base::single_register_access::{
+    SingleRegisterAccess, SingleRegisterAccessOps,
+};
+use gdbstub::target::ext::thread_extra_info::ThreadExtraInfoOps;
+use gdbstub::target::{TargetError, TargetResult};
+use probe_rs::{Error, MemoryInterface, RegisterId, RegisterValue};
+use probe_rs_debug::DebugRegisters;
+
+impl MultiThreadBase for RuntimeTarget<'_> {
+    fn read_registers(&mut self, regs: &mut RuntimeRegisters, tid: Tid) -> TargetResult<(), Self> {
+        let mut bytes = Vec::new();
+
+        if let Some(saved) = self.saved_registers(tid) {
+            regs.pc = saved
+                .get_program_counter()
+                .and_then(|pc| pc.value)
+                .unwrap_or_default()
+                .try_into()
+                .into_target_result()?;
+            for register in self.target_desc.get_registers_for_main_group() {
+                bytes.extend(read_register(register, saved_value(saved)).into_target_result()?);
+            }
+            regs.regs = bytes;
+
+            return Ok(());
+        }
+
+        let mut session = self.session.lock();
+        let mut core = session.core(self.thread_core(tid)).into_target_result()?;
+
+        regs.pc = core
+            .read_core_reg(core.program_counter())
+            .into_target_result()?;
+
+        for register in self.target_desc.get_registers_for_main_group() {
+            bytes
+                .extend(read_register(register, |id| core.read_core_reg(id)).into_target_result()?);
+        }
+        regs.regs = bytes;
+
+        Ok(())
+    }
+
+    fn write_registers(&mut self, regs: &RuntimeRegisters, tid: Tid) -> TargetResult<(), Self> {
+        if self.saved_registers(tid).is_some() {
+            // The saved context of a switched out thread can't be changed.
+            return Err(TargetError::NonFatal);
+        }
+
+        let mut session = self.session.lock();
+        let mut core = session.core(self.thread_core(tid)).into_target_result()?;
+
+        let mut remaining = regs.regs.as_slice();
+        for register in self.target_desc.get_registers_for_main_group() {
+            let size = register.size_in_bytes();
+            if remaining.len() < size {
+                // GDB may send fewer registers than described, the rest is left unchanged.
+                break;
+            }
+
+            let (value, rest) = remaining.split_at(size);
+            write_register(&mut core, register, value).into_target_result()?;
+            remaining = rest;
+        }
+
+        Ok(())
+    }
+
+    fn support_single_register_access(&mut self) -> Option<SingleRegisterAccessOps<'_, Tid, Self>> {
+        Some(self)
+    }
+
+    fn read_addrs(
+        &mut self,
+        start_addr: u64,
+        data: &mut [u8],
+        tid: Tid,
+    ) -> TargetResult<usize, Self> {
+        let mut session = self.session.lock();
+        let mut core = session.core(self.thread_core(tid)).into_target_result()?;
+
+        core.read_8(start_addr, data)
+            .into_target_result_non_fatal()?;
+
+        Ok(data.len())
+    }
+
+    fn write_addrs(&mut self, start_addr: u64, data: &[u8], tid: Tid) -> TargetResult<(), Self> {
+        let mut session = self.session.lock();
+        let mut core = session.core(self.thread_core(tid)).into_target_result()?;
+
+        core.write_8(start_addr, data)
+            .into_target_result_non_fatal()?;
+
+        Ok(())
+    }
+
+    fn list_active_threads(
+        &mut self,
+        thread_is_active: &mut dyn FnMut(Tid),
+    ) -> Result<(), Self::Error> {
+        if let Some(rtos) = self.rtos_threads() {
+            rtos.tids().for_each(thread_is_active);
+            return Ok(());
+        }
+
+        for core_id in &self.cores {
+            thread_is_active(tid(*core_id));
+        }
+
+        Ok(())
+    }
+
+    fn support_resume(&mut self) -> Option<MultiThreadResumeOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_thread_extra_info(&mut self) -> Option<ThreadExtraInfoOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SingleRegisterAccess<Tid> for RuntimeTarget<'_> {
+    fn read_register(
+        &mut self,
+        tid: Tid,
+        reg_id: RuntimeRegId,
+        buf: &mut [u8],
+    ) -> TargetResult<usize, Self> {
+        let Some(register) = self.target_desc.get_register(reg_id.into()) else {
+            return Err(TargetError::NonFatal);
+        };
+
+        let value = if let Some(saved) = self.saved_registers(tid) {
+            read_register(register, saved_value(saved)).into_target_result()?
+        } else {
+            let mut session = self.session.lock();
+            let mut core = session.core(self.thread_core(tid)).into_target_result()?;
+
+            read_register(register, |id| core.read_core_reg(id)).into_target_result()?
+        };
+        let size = value.len().min(buf.len());
+        buf[..size].copy_from_slice(&value[..size]);
+
+        Ok(size)
+    }
+
+    fn write_register(
+        &mut self,
+        tid: Tid,
+        reg_id: RuntimeRegId,
+        val: &[u8],
+    ) -> TargetResult<(), Self> {
+        let Some(register) = self.target_desc.get_register(reg_id.into()) else {
+            return Err(TargetError::NonFatal);
+        };
+        if self.saved_registers(tid).is_some() {
+            return Err(TargetError::NonFatal);
+        }
+
+        let mut session = self.session.lock();
+        let mut core = session.core(self.thread_core(tid)).into_target_result()?;
+
+        write_register(&mut core, register, val).into_target_result()
+    }
+}
+
+/// The probe-rs core ID of a GDB thread. Each core is reported as a thread, starting at 1.
+pub(crate) fn core_id(tid: Tid) -> usize {
+    tid.get() - 1
+}
+
+/// The GDB thread of a probe-rs core.
+pub(crate) fn tid(core_id: usize) -> Tid {
+    Tid::new(core_id + 1).expect("thread IDs start at 1")
+}
+
+/// Read the value of `register` with `read`, in the target byte order GDB expects.
+fn read_register(
+    register: &GdbRegister,
+    mut read: impl FnMut(RegisterId) -> Result<RegisterValue, Error>,
+) -> Result<Vec<u8>, Error> {
+    let mut bytes = match register.source() {
+        GdbRegisterSource::FromProbeRs(id) => match read(id)? {
+            RegisterValue::U32(value) => value.to_le_bytes().to_vec(),
+            RegisterValue::U64(value) => value.to_le_bytes().to_vec(),
+            RegisterValue::U128(value) => value.to_le_bytes().to_vec(),
+        },
+        GdbRegisterSource::TwoWordsFromProbeRs { low, high } => {
+            let low: u32 = read(low)?.try_into()?;
+            let high: u32 = read(high)?.try_into()?;
+
+            (u64::from(high) << 32 | u64::from(low))
+                .to_le_bytes()
+                .to_vec()
+        }
+    };
+
+    bytes.resize(register.size_in_bytes(), 0);
+
+    Ok(bytes)
+}
+
+/// Reads the registers saved by a switched out RTOS thread. Registers the RTOS doesn't save are
+/// reported as zero.
+fn saved_value(saved: &DebugRegisters) -> impl FnMut(RegisterId) -> Result<RegisterValue, Error> {
+    |id| {
+        Ok(saved
+            .get_register(id)
+            .and_then(|register| register.value)
+            .unwrap_or_default())
+    }
+}
+
+/// Write `value`, in the target byte order, to `register`.
+fn write_register(
+    core: &mut probe_rs::Core<'_>,
+    register: &GdbRegister,
+    value: &[u8],
+) -> Result<(), Error> {
+    let mut buf = [0u8; 16];
+    let size = value.len().min(buf.len());
+    buf[..size].copy_from_slice(&value[..size]);
+    let value = u128::from_le_bytes(buf);
+
+    match register.source() {
+        GdbRegisterSource::FromProbeRs(id) => match register.size_in_bytes() {
+            0..=4 => core.write_core_reg(id, value as u32),
+            5..=8 => core.write_core_reg(id, value as u64),
+            _ => core.write_core_reg(id, value),
+        },
+        GdbRegisterSource::TwoWordsFromProbeRs { low, high } => {
+            core.write_core_reg(low, value as u32)?;
+            core.write_core_reg(high, (value >> 32) as u32)
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use super::{GdbErrorExt, RuntimeTarget};
+
+use gdbstub::target::TargetResult;
+use gdbstub::target::ext::breakpoints::{Breakpoints, HwBreakpoint, HwBreakpointOps};
+
+impl Breakpoints for RuntimeTarget<'_> {
+    fn support_hw_breakpoint(&mut self) -> Option<HwBreakpointOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl HwBreakpoint for RuntimeTarget<'_> {
+    fn add_hw_breakpoint(&mut self, addr: u64, _kind: usize) -> TargetResult<bool, Self> {
+        let mut session = self.session.lock();
+
+        // All cores of the stub execute the same program, so the breakpoint is set on each of them.
+        for core_id in &self.cores {
+            let mut core = session.core(*core_id).into_target_result()?;
+            core.set_hw_breakpoint(addr)
+                .into_target_result_non_fatal()?;
+        }
+
+        Ok(true)
+    }
+
+    fn remove_hw_breakpoint(&mut self, addr: u64, _kind: usize) -> TargetResult<bool, Self> {
+        let mut session = self.session.lock();
+
+        for core_id in &self.cores {
+            let mut core = session.core(*core_id).into_target_result()?;
+            core.clear_hw_breakpoint(addr)
+                .into_target_result_non_fatal()?;
+        }
+
+        Ok(true)
+    }
+}
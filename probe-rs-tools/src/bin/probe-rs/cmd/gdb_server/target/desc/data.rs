@@ -0,0 +1,363 @@
+use std::fmt::Write;
+
+use probe_rs::{CoreRegister, CoreRegisters, CoreType, InstructionSet, RegisterId};
+
+/// Where the value of a GDB register is read from, and written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GdbRegisterSource {
+    /// The register is a probe-rs register.
+    FromProbeRs(RegisterId),
+    /// The register is composed of two 32-bit probe-rs registers, like the ARM `d0` register,
+    /// which overlays `s0` and `s1`.
+    TwoWordsFromProbeRs {
+        /// The register holding the lower 32 bits.
+        low: RegisterId,
+        /// The register holding the upper 32 bits.
+        high: RegisterId,
+    },
+}
+
+/// A register, as it is described to GDB.
+#[derive(Debug, Clone)]
+pub(crate) struct GdbRegister {
+    name: String,
+    size_in_bits: usize,
+    ty: Option<String>,
+    source: GdbRegisterSource,
+}
+
+impl GdbRegister {
+    /// Where the value of the register comes from.
+    pub fn source(&self) -> GdbRegisterSource {
+        self.source
+    }
+
+    /// The size of the register in bytes, as GDB expects it in the register packets.
+    pub fn size_in_bytes(&self) -> usize {
+        self.size_in_bits.div_ceil(8)
+    }
+}
+
+/// A target feature of GDB, which groups registers.
+#[derive(Debug, Clone)]
+struct GdbFeature {
+    name: String,
+    /// The GDB register numbers of the registers of this feature.
+    registers: Vec<usize>,
+}
+
+/// The target description sent to GDB, with the architecture and the registers of the target.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TargetDescription {
+    architecture: String,
+    features: Vec<GdbFeature>,
+    /// All registers, indexed by their GDB register number.
+    registers: Vec<GdbRegister>,
+}
+
+impl TargetDescription {
+    /// Create an empty description for `core_type`, executing `instruction_set`.
+    pub fn new(core_type: CoreType, instruction_set: InstructionSet) -> Self {
+        let architecture = match core_type {
+            CoreType::Armv6m => "armv6-m",
+            CoreType::Armv7m => "armv7-m",
+            CoreType::Armv7em => "armv7e-m",
+            CoreType::Armv8m => "armv8-m.main",
+            CoreType::Armv7a => "armv7",
+            CoreType::Armv8a => match instruction_set {
+                InstructionSet::A64 => "aarch64",
+                _ => "armv8-a",
+            },
+            CoreType::Riscv => "riscv:rv32",
+            CoreType::Xtensa => "xtensa",
+        };
+
+        Self {
+            architecture: architecture.to_string(),
+            features: Vec::new(),
+            registers: Vec::new(),
+        }
+    }
+
+    /// Start a new feature. Registers added afterwards belong to this feature.
+    pub fn add_gdb_feature(&mut self, name: &str) {
+        self.features.push(GdbFeature {
+            name: name.to_string(),
+            registers: Vec::new(),
+        });
+    }
+
+    /// Add a register to the current feature.
+    pub fn add_register_from_details(
+        &mut self,
+        name: impl Into<String>,
+        size_in_bits: usize,
+        id: RegisterId,
+    ) {
+        self.add_gdb_register(GdbRegister {
+            name: name.into(),
+            size_in_bits,
+            ty: None,
+            source: GdbRegisterSource::FromProbeRs(id),
+        });
+    }
+
+    /// Add a probe-rs register to the current feature, using its lower case name.
+    fn add_register(&mut self, register: &CoreRegister) {
+        self.add_register_from_details(
+            register.name().to_lowercase(),
+            register.size_in_bits(),
+            register.id(),
+        );
+    }
+
+    /// Add a 64-bit register composed of the 32-bit registers `low` and `high` to the current feature.
+    fn add_two_word_register(
+        &mut self,
+        name: impl Into<String>,
+        low: RegisterId,
+        high: RegisterId,
+    ) {
+        self.add_gdb_register(GdbRegister {
+            name: name.into(),
+            size_in_bits: 64,
+            ty: None,
+            source: GdbRegisterSource::TwoWordsFromProbeRs { low, high },
+        });
+    }
+
+    fn add_gdb_register(&mut self, register: GdbRegister) {
+        if self.features.is_empty() {
+            self.add_gdb_feature("org.probe-rs.core");
+        }
+
+        let regnum = self.registers.len();
+        self.registers.push(register);
+        if let Some(feature) = self.features.last_mut() {
+            feature.registers.push(regnum);
+        }
+    }
+
+    /// Rename the register `current_name` to `new_name`.
+    pub fn update_register_name(&mut self, current_name: &str, new_name: &str) {
+        if let Some(register) = self.register_by_name(current_name) {
+            register.name = new_name.to_string();
+        }
+    }
+
+    /// Set the GDB type of the register `name`, e.g. `code_ptr` or `ieee_single`.
+    pub fn update_register_type(&mut self, name: &str, ty: &str) {
+        if let Some(register) = self.register_by_name(name) {
+            register.ty = Some(ty.to_string());
+        }
+    }
+
+    fn register_by_name(&mut self, name: &str) -> Option<&mut GdbRegister> {
+        self.registers.iter_mut().find(|r| r.name == name)
+    }
+
+    /// The registers which are transferred with the `g` and `G` packets, in GDB register order.
+    pub fn get_registers_for_main_group(&self) -> impl Iterator<Item = &GdbRegister> {
+        self.registers.iter()
+    }
+
+    /// The register with the GDB register number `regnum`.
+    pub fn get_register(&self, regnum: usize) -> Option<&GdbRegister> {
+        self.registers.get(regnum)
+    }
+
+    /// Build the target description XML, which is sent to GDB as `target.xml`.
+    pub fn get_target_xml(&self) -> String {
+        let mut xml = String::from(
+            r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+"#,
+        );
+
+        let _ = writeln!(xml, "<architecture>{}</architecture>", self.architecture);
+
+        for feature in &self.features {
+            let _ = writeln!(xml, "<feature name=\"{}\">", feature.name);
+            for &regnum in &feature.registers {
+                let register = &self.registers[regnum];
+                let _ = write!(
+                    xml,
+                    "<reg name=\"{}\" bitsize=\"{}\" regnum=\"{}\"",
+                    register.name, register.size_in_bits, regnum
+                );
+                if let Some(ty) = &register.ty {
+                    let _ = write!(xml, " type=\"{ty}\"");
+                }
+                xml.push_str("/>\n");
+            }
+            xml.push_str("</feature>\n");
+        }
+
+        xml.push_str("</target>");
+
+        xml
+    }
+}
+
+/// Build the target description for a core, with the registers in the layout GDB expects for the
+/// architecture.
+pub(crate) fn build_target_description(
+    registers: &CoreRegisters,
+    core_type: CoreType,
+    instruction_set: InstructionSet,
+) -> TargetDescription {
+    let mut desc = TargetDescription::new(core_type, instruction_set);
+
+    match (core_type, instruction_set) {
+        (CoreType::Armv6m | CoreType::Armv7m | CoreType::Armv7em | CoreType::Armv8m, _) => {
+            build_cortex_m_registers(&mut desc, registers)
+        }
+        (CoreType::Armv8a, InstructionSet::A64) => build_aarch64_registers(&mut desc, registers),
+        (CoreType::Armv7a | CoreType::Armv8a, _) => build_aarch32_registers(&mut desc, registers),
+        (CoreType::Riscv, _) => build_riscv_registers(&mut desc, registers),
+        (CoreType::Xtensa, _) => {
+            desc.add_gdb_feature("org.gnu.gdb.xtensa.core");
+            for register in registers.all_registers() {
+                desc.add_register(register);
+            }
+        }
+    }
+
+    desc
+}
+
+/// Finds the register with the primary name `name`.
+fn find_register<'a>(registers: &'a CoreRegisters, name: &str) -> Option<&'a CoreRegister> {
+    registers.all_registers().find(|r| r.name() == name)
+}
+
+/// Adds the ARM general purpose registers `r0` to `pc` to the current feature.
+fn add_arm_general_purpose_registers(desc: &mut TargetDescription, registers: &CoreRegisters) {
+    for n in 0..=15 {
+        if let Some(register) = find_register(registers, &format!("R{n}")) {
+            desc.add_register(register);
+        }
+    }
+
+    desc.update_register_name("r13", "sp");
+    desc.update_register_type("sp", "data_ptr");
+    desc.update_register_name("r14", "lr");
+    desc.update_register_name("r15", "pc");
+    desc.update_register_type("pc", "code_ptr");
+}
+
+fn build_cortex_m_registers(desc: &mut TargetDescription, registers: &CoreRegisters) {
+    desc.add_gdb_feature("org.gnu.gdb.arm.m-profile");
+    add_arm_general_purpose_registers(desc, registers);
+    if let Some(xpsr) = find_register(registers, "XPSR") {
+        desc.add_register(xpsr);
+    }
+
+    desc.add_gdb_feature("org.gnu.gdb.arm.m-system");
+    for name in ["MSP", "PSP"] {
+        if let Some(register) = find_register(registers, name) {
+            desc.add_register(register);
+        }
+    }
+    desc.update_register_type("msp", "data_ptr");
+    desc.update_register_type("psp", "data_ptr");
+
+    // GDB describes the FPU of M-profile cores with the double precision registers `d0` to
+    // `d15`, which overlay the single precision registers `s0` to `s31`.
+    let Some(fpu_registers) = registers.fpu_registers() else {
+        return;
+    };
+    let single_registers = fpu_registers.collect::<Vec<_>>();
+
+    desc.add_gdb_feature("org.gnu.gdb.arm.vfp");
+    for (n, pair) in single_registers.chunks_exact(2).enumerate() {
+        desc.add_two_word_register(format!("d{n}"), pair[0].id(), pair[1].id());
+        desc.update_register_type(&format!("d{n}"), "ieee_double");
+    }
+    if let Some(fpscr) = registers.fpsr() {
+        desc.add_register(fpscr);
+    }
+}
+
+fn build_aarch32_registers(desc: &mut TargetDescription, registers: &CoreRegisters) {
+    desc.add_gdb_feature("org.gnu.gdb.arm.core");
+    add_arm_general_purpose_registers(desc, registers);
+    if let Some(cpsr) = find_register(registers, "CPSR") {
+        desc.add_register(cpsr);
+    }
+
+    let Some(fpu_registers) = registers.fpu_registers() else {
+        return;
+    };
+
+    desc.add_gdb_feature("org.gnu.gdb.arm.vfp");
+    for register in fpu_registers {
+        desc.add_register(register);
+        desc.update_register_type(&register.name().to_lowercase(), "ieee_double");
+    }
+    if let Some(fpscr) = registers.fpsr() {
+        desc.add_register(fpscr);
+    }
+}
+
+fn build_aarch64_registers(desc: &mut TargetDescription, registers: &CoreRegisters) {
+    desc.add_gdb_feature("org.gnu.gdb.aarch64.core");
+    for n in 0..=30 {
+        if let Some(register) = find_register(registers, &format!("X{n}")) {
+            desc.add_register(register);
+        }
+    }
+    for name in ["SP", "PC"] {
+        if let Some(register) = find_register(registers, name) {
+            desc.add_register(register);
+        }
+    }
+    desc.update_register_type("sp", "data_ptr");
+    desc.update_register_type("pc", "code_ptr");
+    // GDB expects the 32-bit `cpsr` register, which holds the PSTATE fields.
+    if let Some(pstate) = find_register(registers, "PSTATE") {
+        desc.add_register_from_details("cpsr", 32, pstate.id());
+    }
+
+    let Some(fpu_registers) = registers.fpu_registers() else {
+        return;
+    };
+
+    desc.add_gdb_feature("org.gnu.gdb.aarch64.fpu");
+    for register in fpu_registers {
+        desc.add_register(register);
+    }
+    for name in ["FPSR", "FPCR"] {
+        if let Some(register) = find_register(registers, name) {
+            desc.add_register(register);
+        }
+    }
+}
+
+fn build_riscv_registers(desc: &mut TargetDescription, registers: &CoreRegisters) {
+    desc.add_gdb_feature("org.gnu.gdb.riscv.cpu");
+    for n in 0..=31 {
+        if let Some(register) = find_register(registers, &format!("x{n}")) {
+            desc.add_register(register);
+        }
+    }
+    if let Some(pc) = registers.pc() {
+        desc.add_register(pc);
+        desc.update_register_type("pc", "code_ptr");
+    }
+
+    let Some(vector_registers) = registers.vector_registers() else {
+        return;
+    };
+
+    desc.add_gdb_feature("org.gnu.gdb.riscv.vector");
+    for register in vector_registers {
+        desc.add_register(register);
+    }
+
+    desc.add_gdb_feature("org.gnu.gdb.riscv.csr");
+    for register in registers.vector_status_registers() {
+        desc.add_register(register);
+    }
+}
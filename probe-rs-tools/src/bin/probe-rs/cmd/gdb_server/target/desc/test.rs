@@ -0,0 +1,51 @@
+use probe_rs::{CoreType, InstructionSet, config::Registry};
+
+use super::{TargetDescription, memory_map_xml};
+
+#[test]
+fn test_target_description_microbit() {
+    let target_desc = TargetDescription::new(CoreType::Armv6m, InstructionSet::Thumb2);
+    let description = target_desc.get_target_xml();
+
+    insta::assert_snapshot!(description);
+}
+
+#[test]
+fn test_target_with_features() {
+    let mut target_desc = TargetDescription::new(CoreType::Armv6m, InstructionSet::Thumb2);
+    target_desc.add_gdb_feature("org.probe-rs.feature1");
+    target_desc.add_register_from_details("r0", 32, 0.into());
+    target_desc.add_register_from_details("x1", 64, 1.into());
+    target_desc.add_register_from_details("t2", 64, 2.into());
+
+    target_desc.update_register_name("t2", "at2");
+    target_desc.update_register_type("at2", "special_reg");
+
+    target_desc.add_gdb_feature("org.probe-rs.feature2");
+    target_desc.add_register_from_details("v4", 128, 4.into());
+
+    let description = target_desc.get_target_xml();
+
+    insta::assert_snapshot!(description);
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn test_memory_map_with_flash() {
+    let registry = Registry::from_builtin_families();
+    let target = registry.get_target_by_name("nRF52833_xxAA").unwrap();
+
+    let memory_map = memory_map_xml(&target, false, 32);
+    let has_region = |region: &str| memory_map.lines().any(|line| line == region);
+
+    assert!(has_region(
+        r#"<memory type="ram" start="0x20000000" length="0x20000"/>"#
+    ));
+    assert!(has_region(
+        r#"<memory type="flash" start="0x0" length="0x80000"><property name="blocksize">0x1000</property></memory>"#
+    ));
+    // The UICR is programmed with the same flash algorithm, but is a separate region.
+    assert!(has_region(
+        r#"<memory type="flash" start="0x10001000" length="0x1000"><property name="blocksize">0x1000</property></memory>"#
+    ));
+}
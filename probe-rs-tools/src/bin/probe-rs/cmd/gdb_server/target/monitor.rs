@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use super::RuntimeTarget;
+use crate::util::rtt::client::RttClient;
+use crate::util::rtt::{RttConfig, server::RttServerConfig};
+use crate::util::swo::ItmDecoder;
+
+use gdbstub::target::ext::monitor_cmd::ConsoleOutput;
+use gdbstub::target::ext::monitor_cmd::MonitorCmd;
+use gdbstub::target::ext::monitor_cmd::outputln;
+use probe_rs::architecture::arm::{SwoConfig, component::TraceSink};
+use probe_rs::rtt::ScanRegion;
+
+/// The first TCP port RTT channels are forwarded on, if `rtt start` is not given a port.
+const DEFAULT_RTT_PORT: u16 = 19021;
+
+const HELP_TEXT: &str = r#"Supported Commands:
+
+    info - print session information
+    reset - reset target
+    reset halt - reset target and halt afterwards
+    rtt start [port] - forward RTT channel N over TCP on port + N (default 19021)
+    rtt stop - stop forwarding RTT channels
+    swo enable <baud> <clock> - receive SWO output, clock is the TPIU input clock in Hz
+    swo read - print the ITM stimulus port output received over SWO
+"#;
+
+impl MonitorCmd for RuntimeTarget<'_> {
+    fn handle_monitor_cmd(
+        &mut self,
+        cmd: &[u8],
+        mut out: ConsoleOutput<'_>,
+    ) -> Result<(), Self::Error> {
+        let cmd = String::from_utf8_lossy(cmd);
+        let args = cmd.split_whitespace().collect::<Vec<_>>();
+
+        match args.as_slice() {
+            ["info"] => outputln!(out, "Target info:\n\n{:#?}", self.session.lock().target()),
+            ["reset"] => {
+                outputln!(out, "Resetting target");
+                match self.session.lock().core(0)?.reset() {
+                    Ok(_) => outputln!(out, "Done"),
+                    Err(e) => outputln!(out, "Error while resetting target:\n\t{}", e),
+                }
+            }
+            ["reset", "halt"] => {
+                let timeout = Duration::from_secs(1);
+                outputln!(out, "Resetting and halting target");
+                match self.session.lock().core(0)?.reset_and_halt(timeout) {
+                    Ok(_) => outputln!(out, "Target halted"),
+                    Err(e) => outputln!(out, "Error while halting target:\n\t{}", e),
+                }
+            }
+            ["rtt", "start", port @ ..] if port.len() <= 1 => {
+                let port = match port.first().map(|port| port.parse::<u16>()) {
+                    None => DEFAULT_RTT_PORT,
+                    Some(Ok(port)) => port,
+                    Some(Err(e)) => {
+                        outputln!(out, "Invalid port:\n\t{}", e);
+                        return Ok(());
+                    }
+                };
+
+                self.stop_rtt()?;
+
+                let config = RttConfig {
+                    enabled: true,
+                    server: RttServerConfig {
+                        tcp_port: Some(port),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                };
+                let session = self.session.lock();
+                self.rtt_client = Some(RttClient::new(config, ScanRegion::Ram, session.target()));
+
+                outputln!(
+                    out,
+                    "Forwarding RTT channels on port {} and up, once the control block is found",
+                    port
+                );
+            }
+            ["rtt", "stop"] => {
+                self.stop_rtt()?;
+                outputln!(out, "Stopped forwarding RTT channels");
+            }
+            ["swo", "enable", baud, clock] => {
+                let (Ok(baud), Ok(clock)) = (baud.parse::<u32>(), clock.parse::<u32>()) else {
+                    outputln!(out, "The baud rate and clock must be numbers");
+                    return Ok(());
+                };
+
+                let config = SwoConfig::new(clock).set_baud(baud);
+                match self
+                    .session
+                    .lock()
+                    .setup_tracing(self.cores[0], TraceSink::Swo(config))
+                {
+                    Ok(()) => {
+                        self.swo_decoder = Some(ItmDecoder::new());
+                        outputln!(out, "SWO enabled at {} baud", baud);
+                    }
+                    Err(e) => outputln!(out, "Error while configuring SWO:\n\t{}", e),
+                }
+            }
+            ["swo", "read"] => {
+                let Some(decoder) = self.swo_decoder.as_mut() else {
+                    outputln!(out, "SWO is not enabled, use `monitor swo enable` first");
+                    return Ok(());
+                };
+
+                let data = match self.session.lock().core(self.cores[0])?.read_swo() {
+                    Ok(data) => data.unwrap_or_default(),
+                    Err(e) => {
+                        outputln!(out, "Error while reading SWO data:\n\t{}", e);
+                        return Ok(());
+                    }
+                };
+
+                for (port, bytes) in decoder.decode(&data) {
+                    outputln!(out, "[{}] {}", port, String::from_utf8_lossy(&bytes));
+                }
+            }
+            _ => outputln!(out, "{}", HELP_TEXT),
+        }
+
+        Ok(())
+    }
+}
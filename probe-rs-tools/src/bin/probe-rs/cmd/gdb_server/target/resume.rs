@@ -0,0 +1,74 @@
+use super::{ResumeAction, RuntimeTarget};
+
+use anyhow::anyhow;
+use gdbstub::common::{Signal, Tid};
+use gdbstub::target::ext::base::multithread::{
+    MultiThreadResume, MultiThreadSingleStep, MultiThreadSingleStepOps,
+};
+
+impl MultiThreadResume for RuntimeTarget<'_> {
+    fn resume(&mut self) -> Result<(), Self::Error> {
+        let mut session = self.session.lock();
+
+        match self.resume_action {
+            (_, ResumeAction::Unchanged) => {}
+            (_, ResumeAction::Resume) => {
+                for core_id in &self.cores {
+                    let mut core = session.core(*core_id)?;
+                    core.run()?;
+                }
+            }
+            (core_id, ResumeAction::Step) => {
+                // Only the stepped core is resumed, all other cores stay halted.
+                let mut core = session.core(core_id)?;
+                core.step()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn clear_resume_actions(&mut self) -> Result<(), Self::Error> {
+        // Threads without an explicit resume action are continued.
+        self.resume_action = (0, ResumeAction::Resume);
+
+        Ok(())
+    }
+
+    fn set_resume_action_continue(
+        &mut self,
+        tid: Tid,
+        signal: Option<Signal>,
+    ) -> Result<(), Self::Error> {
+        if signal.is_some() {
+            return Err(anyhow!("Resuming with a signal is not supported"));
+        }
+
+        // A pending step takes precedence, GDB continues all other threads with the step.
+        if !matches!(self.resume_action, (_, ResumeAction::Step)) {
+            self.resume_action = (self.thread_core(tid), ResumeAction::Resume);
+        }
+
+        Ok(())
+    }
+
+    fn support_single_step(&mut self) -> Option<MultiThreadSingleStepOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl MultiThreadSingleStep for RuntimeTarget<'_> {
+    fn set_resume_action_step(
+        &mut self,
+        tid: Tid,
+        signal: Option<Signal>,
+    ) -> Result<(), Self::Error> {
+        if signal.is_some() {
+            return Err(anyhow!("Stepping with a signal is not supported"));
+        }
+
+        self.resume_action = (self.thread_core(tid), ResumeAction::Step);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use super::RuntimeTarget;
+use super::base::{core_id, tid};
+use super::utils::copy_to_buf;
+
+use std::rc::Rc;
+
+use gdbstub::common::Tid;
+use gdbstub::target::ext::thread_extra_info::ThreadExtraInfo;
+use probe_rs::Core;
+use probe_rs_debug::{
+    DebugInfo, DebugRegisters,
+    rtos::{RtosAwareness, RtosThread, detect_rtos},
+};
+
+/// The threads of the RTOS used by the program, which are reported to GDB instead of the core.
+pub(crate) struct RtosThreads {
+    debug_info: Rc<DebugInfo>,
+    rtos: Box<dyn RtosAwareness>,
+    /// The threads when the core halted last. The GDB thread IDs are assigned in this order.
+    threads: Vec<RtosThread>,
+}
+
+impl RtosThreads {
+    /// Detects the RTOS from the symbols of the program.
+    pub fn detect(debug_info: Rc<DebugInfo>) -> Option<Self> {
+        let rtos = detect_rtos(&debug_info)?;
+
+        Some(Self {
+            debug_info,
+            rtos,
+            threads: Vec::new(),
+        })
+    }
+
+    /// Reads the threads from the memory of the halted core.
+    ///
+    /// Threads without registers of their own, e.g. async tasks, can't be shown by GDB and are
+    /// left out. If no threads are found, the core is reported as the only thread.
+    pub fn refresh(&mut self, core: &mut Core<'_>) {
+        let registers = DebugRegisters::from_core(core);
+        self.threads = match self.rtos.threads(&self.debug_info, core, &registers) {
+            Ok(threads) => threads
+                .into_iter()
+                .filter(|thread| thread.is_running() || thread.registers.is_some())
+                .collect(),
+            Err(error) => {
+                tracing::warn!(
+                    "Failed to read the threads of {}: {error}",
+                    self.rtos.name()
+                );
+                Vec::new()
+            }
+        };
+    }
+
+    /// The thread `tid`, if the RTOS threads are reported to GDB.
+    pub fn thread(&self, tid: Tid) -> Option<&RtosThread> {
+        self.threads.get(tid.get() - 1)
+    }
+
+    /// The GDB thread IDs of the threads.
+    pub fn tids(&self) -> impl Iterator<Item = Tid> {
+        (0..self.threads.len()).map(tid)
+    }
+
+    /// The GDB thread ID of the thread which is executing on the core.
+    pub fn running_tid(&self) -> Option<Tid> {
+        self.threads
+            .iter()
+            .position(RtosThread::is_running)
+            .map(tid)
+    }
+
+    /// Returns `true` if the RTOS threads are reported to GDB.
+    pub fn is_active(&self) -> bool {
+        !self.threads.is_empty()
+    }
+}
+
+impl RuntimeTarget<'_> {
+    /// The RTOS threads, if they are reported to GDB instead of the cores.
+    pub(crate) fn rtos_threads(&self) -> Option<&RtosThreads> {
+        self.rtos.as_ref().filter(|rtos| rtos.is_active())
+    }
+
+    /// The core which executes the thread `tid`.
+    pub(crate) fn thread_core(&self, tid: Tid) -> usize {
+        if self.rtos_threads().is_some() {
+            self.cores[0]
+        } else {
+            core_id(tid)
+        }
+    }
+
+    /// The registers saved by the RTOS thread `tid`, if it is switched out.
+    ///
+    /// Returns `None` for the running thread, which uses the registers of the core.
+    pub(crate) fn saved_registers(&self, tid: Tid) -> Option<&DebugRegisters> {
+        let thread = self.rtos_threads()?.thread(tid)?;
+        if thread.is_running() {
+            return None;
+        }
+
+        thread.registers.as_ref()
+    }
+
+    /// Reads the RTOS threads from the halted core.
+    pub(crate) fn refresh_threads(&mut self) -> Result<(), anyhow::Error> {
+        let Some(rtos) = self.rtos.as_mut() else {
+            return Ok(());
+        };
+
+        let mut session = self.session.lock();
+        let mut core = session.core(self.cores[0])?;
+        rtos.refresh(&mut core);
+
+        Ok(())
+    }
+}
+
+impl ThreadExtraInfo for RuntimeTarget<'_> {
+    fn thread_extra_info(&self, tid: Tid, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let description = match self.rtos_threads().and_then(|rtos| rtos.thread(tid)) {
+            Some(thread) => thread_description(thread),
+            None => format!("Core {}", core_id(tid)),
+        };
+
+        Ok(copy_to_buf(description.as_bytes(), buf))
+    }
+}
+
+/// The description of an RTOS thread, which GDB shows in the thread list.
+fn thread_description(thread: &RtosThread) -> String {
+    match thread.priority {
+        Some(priority) => format!("{} ({}, priority {priority})", thread.name, thread.state),
+        None => format!("{} ({})", thread.name, thread.state),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use probe_rs_debug::rtos::ThreadState;
+
+    fn thread(name: &str, state: ThreadState, priority: Option<i64>) -> RtosThread {
+        RtosThread {
+            id: 0x2000_0000,
+            name: name.to_string(),
+            state,
+            priority,
+            registers: None,
+        }
+    }
+
+    #[test]
+    fn thread_descriptions() {
+        assert_eq!(
+            thread_description(&thread("IDLE", ThreadState::Ready, Some(0))),
+            "IDLE (Ready, priority 0)"
+        );
+        assert_eq!(
+            thread_description(&thread("main", ThreadState::Running, None)),
+            "main (Running)"
+        );
+    }
+}
@@ -0,0 +1,29 @@
+use gdbstub::target::TargetError;
+
+/// Extension trait to convert probe-rs errors into [`TargetError`]s, which are understood by gdbstub.
+pub(crate) trait GdbErrorExt<T> {
+    /// Convert the error into a fatal [`TargetError`], which ends the GDB session.
+    fn into_target_result(self) -> Result<T, TargetError<anyhow::Error>>;
+
+    /// Convert the error into a non-fatal [`TargetError`], which is reported to GDB as a failed
+    /// command.
+    fn into_target_result_non_fatal(self) -> Result<T, TargetError<anyhow::Error>>;
+}
+
+impl<T, E> GdbErrorExt<T> for Result<T, E>
+where
+    E: Into<anyhow::Error>,
+{
+    fn into_target_result(self) -> Result<T, TargetError<anyhow::Error>> {
+        self.map_err(|e| TargetError::Fatal(e.into()))
+    }
+
+    fn into_target_result_non_fatal(self) -> Result<T, TargetError<anyhow::Error>> {
+        self.map_err(|e| {
+            let e: anyhow::Error = e.into();
+            tracing::debug!("Error for GDB client: {e:?}");
+
+            TargetError::NonFatal
+        })
+    }
+}
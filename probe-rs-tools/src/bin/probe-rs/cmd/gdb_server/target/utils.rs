@@ -0,0 +1,23 @@
+/// Copy the bytes of `data` in the range `offset..offset + length` into `buf`, as required by the
+/// `qXfer` packets.
+///
+/// Returns the number of bytes copied, which is zero if `offset` is past the end of `data`.
+pub(crate) fn copy_range_to_buf(data: &[u8], offset: u64, length: usize, buf: &mut [u8]) -> usize {
+    let Ok(offset) = usize::try_from(offset) else {
+        return 0;
+    };
+
+    let start = offset.min(data.len());
+    let end = offset.saturating_add(length).min(data.len());
+    let data = &data[start..end];
+
+    let copied = data.len().min(buf.len());
+    buf[..copied].copy_from_slice(&data[..copied]);
+
+    copied
+}
+
+/// Copy as many bytes of `data` as fit into `buf`, and return the number of bytes copied.
+pub(crate) fn copy_to_buf(data: &[u8], buf: &mut [u8]) -> usize {
+    copy_range_to_buf(data, 0, data.len(), buf)
+}
@@ -55,7 +55,7 @@ impl Cmd {
             vec![WireProtocol::Jtag, WireProtocol::Swd]
         };
 
-        let probe = select_probe(&client, self.common.probe.map(Into::into)).await?;
+        let probe = select_probe(&client, self.common.probe).await?;
 
         for protocol in protocols {
             let msg = format!("Probing target via {protocol}");
@@ -74,12 +74,15 @@ impl Cmd {
                 speed: self.common.speed,
                 connect_under_reset: self.common.connect_under_reset,
                 dry_run: self.common.dry_run,
+                scan_chain_only: false,
             };
 
             let result = client
                 .info(req, async |message| {
-                    let is_success =
-                        matches!(message, InfoEvent::Idcode { .. } | InfoEvent::ArmDp(_));
+                    let is_success = matches!(
+                        message,
+                        InfoEvent::Idcode { .. } | InfoEvent::ArmDp(_) | InfoEvent::JtagChain(_)
+                    );
 
                     if matches!(message, InfoEvent::Message(_)) {
                         successes.push(message.clone());
@@ -151,20 +154,8 @@ impl std::fmt::Display for InfoEvent {
                 architecture,
                 idcode: Some(idcode),
             } => {
-                let version = (idcode >> 28) & 0xf;
-                let part_number = (idcode >> 12) & 0xffff;
-                let manufacturer_id = (idcode >> 1) & 0x7ff;
-
-                let jep_cc = (manufacturer_id >> 7) & 0xf;
-                let jep_id = manufacturer_id & 0x7f;
-
-                let jep_id = jep106::JEP106Code::new(jep_cc as u8, jep_id as u8);
-
                 writeln!(f, "{architecture} Chip:")?;
-                writeln!(f, "  IDCODE: {idcode:010x}")?;
-                writeln!(f, "    Version:      {version}")?;
-                writeln!(f, "    Part:         {part_number}")?;
-                writeln!(f, "    Manufacturer: {manufacturer_id} ({jep_id})")
+                write_idcode(f, *idcode, "  ")
             }
             InfoEvent::Idcode {
                 architecture,
@@ -175,10 +166,42 @@ impl std::fmt::Display for InfoEvent {
             InfoEvent::ArmDp(dp_info) => {
                 writeln!(f, "{dp_info}")
             }
+            InfoEvent::JtagChain(taps) if taps.is_empty() => {
+                writeln!(f, "No TAPs found on the JTAG scan chain.")
+            }
+            InfoEvent::JtagChain(taps) => {
+                writeln!(f, "JTAG scan chain:")?;
+                for (index, tap) in taps.iter().enumerate() {
+                    writeln!(f, "  TAP {index}:")?;
+                    writeln!(f, "    IR length:    {}", tap.ir_len)?;
+                    match tap.idcode {
+                        Some(idcode) => write_idcode(f, idcode, "    ")?,
+                        None => writeln!(f, "    IDCODE:       none (BYPASS)")?,
+                    }
+                }
+                Ok(())
+            }
         }
     }
 }
 
+/// Writes the decoded fields of a JTAG IDCODE.
+fn write_idcode(f: &mut std::fmt::Formatter<'_>, idcode: u32, indent: &str) -> std::fmt::Result {
+    let version = (idcode >> 28) & 0xf;
+    let part_number = (idcode >> 12) & 0xffff;
+    let manufacturer_id = (idcode >> 1) & 0x7ff;
+
+    let jep_cc = (manufacturer_id >> 7) & 0xf;
+    let jep_id = manufacturer_id & 0x7f;
+
+    let jep_id = jep106::JEP106Code::new(jep_cc as u8, jep_id as u8);
+
+    writeln!(f, "{indent}IDCODE: {idcode:010x}")?;
+    writeln!(f, "{indent}  Version:      {version}")?;
+    writeln!(f, "{indent}  Part:         {part_number}")?;
+    writeln!(f, "{indent}  Manufacturer: {manufacturer_id} ({jep_id})")
+}
+
 impl From<&ComponentTreeNode> for Tree<String> {
     fn from(node: &ComponentTreeNode) -> Self {
         let mut tree = Tree::new(node.node.clone());
@@ -1,16 +1,28 @@
 use crate::rpc::client::RpcClient;
 
 #[derive(clap::Parser)]
-pub struct Cmd {}
+pub struct Cmd {
+    /// Open the probes to read their firmware version, and check whether it is outdated.
+    ///
+    /// This fails for probes which are in use by another program.
+    #[clap(long)]
+    firmware: bool,
+}
 
 impl Cmd {
     pub async fn run(self, client: RpcClient) -> anyhow::Result<()> {
-        let probes = client.list_probes().await?;
+        let probes = client.list_probes(self.firmware).await?;
 
         if !probes.is_empty() {
             println!("The following debug probes were found:");
             for (num, link) in probes.iter().enumerate() {
                 println!("[{num}]: {link}");
+                if let Some(firmware) = &link.firmware {
+                    println!("     Firmware: {firmware}");
+                }
+                if let Some(advisory) = &link.firmware_advisory {
+                    println!("     {advisory}");
+                }
             }
         } else {
             println!("No debug probes were found.");
@@ -0,0 +1,84 @@
+use anyhow::Context;
+
+use crate::{
+    rpc::{client::RpcClient, functions::option_bytes::OptionByteWrite},
+    util::{cli, common_options::ProbeOptions, parse_u32},
+};
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(subcommand)]
+    subcommand: Subcommand,
+}
+
+#[derive(clap::Subcommand)]
+/// Read and modify the option bytes of the target
+enum Subcommand {
+    /// Shows the values of all option byte fields
+    #[clap(name = "read")]
+    Read {
+        #[clap(flatten)]
+        common: ProbeOptions,
+    },
+    /// Changes option byte fields, and leaves the other fields unchanged
+    ///
+    /// e.g. probe-rs option-bytes write --chip STM32F407VGTx RDP=0xAA nWDG_SW=1
+    #[clap(name = "write", verbatim_doc_comment)]
+    Write {
+        #[clap(flatten)]
+        common: ProbeOptions,
+
+        /// The fields to change, as `NAME=VALUE`.
+        #[clap(value_parser = parse_field_value, required = true)]
+        values: Vec<(String, u32)>,
+    },
+}
+
+impl Cmd {
+    pub async fn run(self, client: RpcClient) -> anyhow::Result<()> {
+        match self.subcommand {
+            Subcommand::Read { common } => {
+                let session = cli::attach_probe(&client, common, false).await?;
+                let values = session.read_option_bytes().await?;
+
+                let name_width = values.iter().map(|value| value.name.len()).max();
+                for value in values {
+                    let hex_digits = value.bit_width.div_ceil(4) as usize;
+                    print!(
+                        "{:width$} = {:#0digits$x}",
+                        value.name,
+                        value.value,
+                        width = name_width.unwrap_or(0),
+                        digits = hex_digits + 2,
+                    );
+                    match value.description {
+                        Some(description) => println!("  {description}"),
+                        None => println!(),
+                    }
+                }
+            }
+            Subcommand::Write { common, values } => {
+                let session = cli::attach_probe(&client, common, false).await?;
+                session
+                    .write_option_bytes(
+                        values
+                            .into_iter()
+                            .map(|(name, value)| OptionByteWrite { name, value })
+                            .collect(),
+                    )
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_field_value(input: &str) -> anyhow::Result<(String, u32)> {
+    let (name, value) = input
+        .split_once('=')
+        .context("Expected a field value as `NAME=VALUE`")?;
+    let value = parse_u32(value.trim()).with_context(|| format!("Invalid value `{value}`"))?;
+
+    Ok((name.trim().to_string(), value))
+}
@@ -0,0 +1,82 @@
+//! Direct control of the debug probe's SWJ pins.
+
+use anyhow::Context;
+use probe_rs::architecture::arm::Pins;
+use probe_rs::config::Registry;
+use probe_rs::probe::list::Lister;
+
+use crate::util::common_options::ProbeOptions;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum PinLevel {
+    Low,
+    High,
+}
+
+impl PinLevel {
+    fn is_high(self) -> bool {
+        self == PinLevel::High
+    }
+}
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(flatten)]
+    common: ProbeOptions,
+
+    /// Drive the (active low) nRESET pin to the given level.
+    #[clap(long, value_enum)]
+    nreset: Option<PinLevel>,
+
+    /// Drive the (active low) JTAG nTRST pin to the given level.
+    #[clap(long, value_enum)]
+    ntrst: Option<PinLevel>,
+}
+
+impl Cmd {
+    pub async fn run(self, registry: &mut Registry, lister: &Lister) -> anyhow::Result<()> {
+        let common_options = self.common.load(registry)?;
+        let mut probe = common_options.attach_probe(lister).await?;
+
+        println!("Probe: {}", probe.get_name());
+
+        let mut pin_out = Pins(0);
+        let mut pin_select = Pins(0);
+        if let Some(level) = self.nreset {
+            pin_select.set_nreset(true);
+            pin_out.set_nreset(level.is_high());
+        }
+        if let Some(level) = self.ntrst {
+            pin_select.set_ntrst(true);
+            pin_out.set_ntrst(level.is_high());
+        }
+
+        if pin_select.0 != 0 {
+            probe
+                .control_pins(pin_out, pin_select)
+                .context("The selected probe cannot drive the requested pins")?;
+            println!("Pin control: supported");
+        }
+
+        // Probes which cannot read back the pins report all bits as set.
+        match probe.control_pins(Pins(0), Pins(0)) {
+            Ok(pins) if pins.0 != 0xFF => {
+                println!("Pin read-back: supported");
+                print_pin("SWCLK/TCK", pins.swclk_tck());
+                print_pin("SWDIO/TMS", pins.swdio_tms());
+                print_pin("TDI", pins.tdi());
+                print_pin("TDO/SWO", pins.tdo());
+                print_pin("nTRST", pins.ntrst());
+                print_pin("nRESET", pins.nreset());
+            }
+            Ok(_) => println!("Pin read-back: not supported"),
+            Err(error) => println!("Pin read-back: not supported ({error})"),
+        }
+
+        Ok(())
+    }
+}
+
+fn print_pin(name: &str, high: bool) {
+    println!("  {name:<10} {}", if high { "high" } else { "low" });
+}
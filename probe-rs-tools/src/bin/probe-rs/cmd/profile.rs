@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use std::time::Instant;
 
@@ -16,6 +16,7 @@ use probe_rs::{
     },
     probe::list::Lister,
 };
+use probe_rs_debug::{DebugInfo, coverage::CoverageCollector};
 
 use crate::util::flash::{build_loader, run_flash_download};
 use tracing::info;
@@ -39,6 +40,12 @@ pub struct ProfileCmd {
     /// Limit the number of entries to output
     #[clap(long, default_value_t = 25)]
     limit: usize,
+    /// Write source line coverage, approximated from the collected PC samples, to this file
+    #[clap(long)]
+    coverage: Option<PathBuf>,
+    /// Format of the coverage file
+    #[clap(long, value_enum, default_value_t = CoverageFormat::Lcov)]
+    coverage_format: CoverageFormat,
     /// Profile Method
     #[clap(subcommand)]
     method: ProfileMethod,
@@ -63,6 +70,14 @@ pub enum ProfileMethod {
     Pcsr,
 }
 
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoverageFormat {
+    /// LCOV tracefile
+    Lcov,
+    /// Cobertura XML
+    Cobertura,
+}
+
 impl std::fmt::Display for ProfileMethod {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         let s = format!("{self:?}");
@@ -178,6 +193,21 @@ impl ProfileCmd {
             }
         }
 
+        if let Some(coverage_path) = &self.coverage {
+            let debug_info = DebugInfo::from_file(file_location)?;
+            let mut coverage = CoverageCollector::new(&debug_info);
+            for (&pc, &count) in &samples {
+                coverage.record_pc_sample(pc as u64, count);
+            }
+
+            let output = match self.coverage_format {
+                CoverageFormat::Lcov => coverage.to_lcov("probe-rs"),
+                CoverageFormat::Cobertura => coverage.to_cobertura(),
+            };
+            std::fs::write(coverage_path, output)?;
+            info!("Coverage written to {}", coverage_path.display());
+        }
+
         let mut v = Vec::from_iter(samples);
         // sort by frequency
         v.sort_by(|&(_, a), &(_, b)| b.cmp(&a));
@@ -1,19 +1,21 @@
-use anyhow::Context;
-use ihex::Record;
-use itertools::Itertools;
-
 use crate::rpc::client::{CoreInterface, RpcClient};
 
 use crate::CoreOptions;
-use crate::util::cli;
 use crate::util::common_options::{ProbeOptions, ReadWriteBitWidth, ReadWriteOptions};
+use crate::util::{cli, image_file};
 use std::io::Write;
 use std::path::PathBuf;
 
 #[derive(clap::ValueEnum, Clone)]
 enum FileFormat {
+    /// Intel HEX
     Hex,
+    /// Motorola S-record
+    Srec,
+    /// Raw binary
     Binary,
+    /// Raw binary, with the address and size in a JSON file next to it
+    BinaryWithMetadata,
 }
 
 /// Read from target memory address
@@ -27,7 +29,7 @@ enum FileFormat {
 ///     00000000 00000000 (32-bit)
 ///     0000000000000000 0000000000000000 (64-bit)
 ///
-/// If the --output argument is provided, readback data is instead saved to a file as hex/bin.
+/// If the --output argument is provided, readback data is instead saved to a file as hex/srec/bin.
 /// In this case, the read word width has no effect except determining the total number of bytes
 ///
 /// NOTE: Only supports RAM addresses
@@ -109,30 +111,22 @@ impl Cmd {
 
         let data = core.read_memory_8(address, nbytes).await?;
 
-        let mut running_address = address;
         match format {
             FileFormat::Binary => {
                 std::fs::File::create(path)?.write_all(&data)?;
             }
+            FileFormat::BinaryWithMetadata => {
+                image_file::write_bin_with_metadata(&path, address, &data)?;
+            }
             FileFormat::Hex => {
-                let mut records = vec![];
-
-                for chunk in &data.into_iter().chunks(255) {
-                    let address_msbs: u16 = (running_address >> 16)
-                        .try_into()
-                        .context("Hex format only supports addressing up to 32 bits")?;
-
-                    records.push(Record::ExtendedLinearAddress(address_msbs));
-
-                    records.push(Record::Data {
-                        offset: (running_address & 0xFFFF) as u16,
-                        value: chunk.collect(),
-                    });
-                    running_address += 255;
-                }
-                records.push(Record::EndOfFile);
-                let hexdata = ihex::create_object_file_representation(&records)?;
-                std::fs::File::create(path)?.write_all(hexdata.as_bytes())?;
+                let mut output = Vec::new();
+                image_file::write_ihex(&mut output, address, &data)?;
+                std::fs::File::create(path)?.write_all(&output)?;
+            }
+            FileFormat::Srec => {
+                let mut output = Vec::new();
+                image_file::write_srec(&mut output, address, &data)?;
+                std::fs::File::create(path)?.write_all(&output)?;
             }
         }
         Ok(())
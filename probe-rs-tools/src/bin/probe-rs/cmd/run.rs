@@ -6,6 +6,12 @@ use crate::rpc::functions::monitor::{MonitorMode, MonitorOptions};
 use crate::FormatOptions;
 use crate::util::cli::{self, connect_target_output_files, rtt_client};
 use crate::util::common_options::{BinaryDownloadOptions, ProbeOptions};
+use crate::util::junit::TestReport;
+use crate::util::rtt::RttConfig;
+use crate::util::rtt::server::RttServerConfig;
+use crate::util::rtt::sinks::{RttLogConfig, RttSinks};
+use crate::util::snapshot::SnapshotOptions;
+use crate::util::swo::SwoLogConfig;
 
 use libtest_mimic::{Arguments, FormatSetting};
 use probe_rs::flashing::FileDownloadError;
@@ -28,6 +34,53 @@ pub struct NormalRunOptions {
     /// Disable hardfault vector catch if its supported on the target.
     #[clap(long, help_heading = "RUN OPTIONS")]
     pub no_catch_hardfault: bool,
+    /// Store a snapshot of the registers and memory in this directory when the firmware halts
+    /// unexpectedly.
+    #[clap(long, value_name = "DIRECTORY", help_heading = "RUN OPTIONS")]
+    pub snapshot_dir: Option<PathBuf>,
+    /// A memory range to include in snapshots, e.g. `0x20000000..0x20004000`. Can be used
+    /// multiple times. Defaults to all RAM regions of the target.
+    #[clap(
+        long,
+        value_name = "START..END",
+        value_parser = crate::util::snapshot::parse_range,
+        requires = "snapshot_dir",
+        help_heading = "RUN OPTIONS"
+    )]
+    pub snapshot_range: Vec<(u64, u64)>,
+    /// The number of snapshots to keep. Older snapshots are deleted.
+    #[clap(
+        long,
+        default_value_t = 10,
+        value_parser = clap::value_parser!(u32).range(1..),
+        help_heading = "RUN OPTIONS"
+    )]
+    pub snapshot_retention: u32,
+    /// Allow the firmware to open, read and write files in this directory using semihosting.
+    #[clap(long, value_name = "DIRECTORY", help_heading = "RUN OPTIONS")]
+    pub semihosting_root: Option<PathBuf>,
+}
+
+impl NormalRunOptions {
+    /// Returns the snapshot options, if snapshots are enabled.
+    pub fn snapshot_options(&self) -> Option<SnapshotOptions> {
+        let directory = self.snapshot_dir.as_ref()?;
+        let directory = std::path::absolute(directory).unwrap_or_else(|_| directory.clone());
+
+        Some(SnapshotOptions {
+            directory: directory.display().to_string(),
+            ranges: self.snapshot_range.clone(),
+            retention: self.snapshot_retention,
+        })
+    }
+
+    /// Returns the directory the firmware can access using semihosting, if any.
+    pub fn semihosting_root_path(&self) -> Option<String> {
+        let root = self.semihosting_root.as_ref()?;
+        let root = std::path::absolute(root).unwrap_or_else(|_| root.clone());
+
+        Some(root.display().to_string())
+    }
 }
 
 /// Options only used when in test run mode
@@ -168,6 +221,37 @@ pub struct SharedOptions {
     /// Scan the memory to find the RTT control block
     #[clap(long)]
     pub(crate) rtt_scan_memory: bool,
+
+    #[clap(flatten)]
+    pub(crate) rtt_log: RttLogConfig,
+
+    /// Read all RTT channels with a single memory transfer per poll. This increases the RTT
+    /// throughput on slow probes, like the ST-Link V2.
+    #[clap(long)]
+    pub(crate) rtt_batched_reads: bool,
+
+    #[clap(flatten)]
+    pub(crate) rtt_server: RttServerConfig,
+
+    #[clap(flatten)]
+    pub(crate) swo: SwoLogConfig,
+
+    /// Receive the application trace (`app_trace`) of ESP-IDF from Xtensa cores, and display it
+    /// like an RTT channel named `apptrace`.
+    #[clap(long)]
+    pub(crate) apptrace: bool,
+}
+
+impl SharedOptions {
+    /// The configuration of the RTT client. The channels are configured once they are known.
+    pub(crate) fn rtt_config(&self) -> RttConfig {
+        RttConfig {
+            enabled: true,
+            channels: vec![],
+            batched_reads: self.rtt_batched_reads,
+            server: self.rtt_server.clone(),
+        }
+    }
 }
 
 impl Cmd {
@@ -175,6 +259,19 @@ impl Cmd {
         // Detect run mode based on ELF file
         let run_mode = detect_run_mode(&self)?;
 
+        self.run_in_mode(client, utc_offset, run_mode, None).await
+    }
+
+    /// Flashes and runs the firmware. In test mode, the results of the tests are added to
+    /// `report`, if given.
+    pub(crate) async fn run_in_mode(
+        self,
+        client: RpcClient,
+        utc_offset: UtcOffset,
+        run_mode: RunMode,
+        report: Option<TestReport>,
+    ) -> anyhow::Result<()> {
+        let rtt_config = self.shared_options.rtt_config();
         let session = cli::attach_probe(&client, self.shared_options.probe_options, false).await?;
 
         let mut rtt_client = rtt_client(
@@ -187,8 +284,14 @@ impl Cmd {
             self.shared_options.log_format,
             !self.shared_options.no_location,
             Some(utc_offset),
+            rtt_config,
         )
         .await?;
+        rtt_client.log_to(RttSinks::from_config(
+            &self.shared_options.rtt_log,
+            utc_offset,
+        )?);
+        rtt_client.set_itm_defmt_port(self.shared_options.swo.defmt_port);
 
         let mut target_output_files =
             connect_target_output_files(self.shared_options.target_output_file).await?;
@@ -231,6 +334,7 @@ impl Cmd {
                 &self.shared_options.path,
                 Some(rtt_client),
                 &mut target_output_files,
+                report,
             )
             .await
         } else {
@@ -243,6 +347,10 @@ impl Cmd {
                     catch_reset: !self.run_options.no_catch_reset,
                     catch_hardfault: !self.run_options.no_catch_hardfault,
                     rtt_client: Some(client_handle),
+                    snapshot: self.run_options.snapshot_options(),
+                    semihosting_root: self.run_options.semihosting_root_path(),
+                    swo: self.shared_options.swo.clone(),
+                    apptrace: self.shared_options.apptrace,
                 },
                 self.shared_options.always_print_stacktrace,
                 &mut target_output_files,
@@ -253,12 +361,12 @@ impl Cmd {
 }
 
 #[derive(PartialEq)]
-enum RunMode {
+pub(crate) enum RunMode {
     Normal,
     Test,
 }
 
-fn elf_contains_test(path: &Path) -> anyhow::Result<bool> {
+pub(crate) fn elf_contains_test(path: &Path) -> anyhow::Result<bool> {
     let mut file = File::open(path).map_err(FileDownloadError::IO)?;
 
     let mut buffer = Vec::new();
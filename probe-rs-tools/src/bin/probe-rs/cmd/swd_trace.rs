@@ -0,0 +1,41 @@
+//! Printing of SWD traces recorded with `--record-swd-trace`.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use probe_rs::probe::swd_trace::load_swd_trace;
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    /// The trace file, recorded with `--record-swd-trace`.
+    path: PathBuf,
+
+    /// Only show the transfers which were not acknowledged with OK, or had an invalid parity.
+    #[clap(long)]
+    errors: bool,
+}
+
+impl Cmd {
+    pub fn run(self) -> anyhow::Result<()> {
+        let file = File::open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        let transfers = load_swd_trace(BufReader::new(file))
+            .with_context(|| format!("Failed to read the SWD trace {}", self.path.display()))?;
+
+        for transfer in &transfers {
+            if !self.errors || !transfer.is_ok() {
+                println!("{transfer}");
+            }
+        }
+
+        let failed = transfers
+            .iter()
+            .filter(|transfer| !transfer.is_ok())
+            .count();
+        println!("{} transfers, {} failed", transfers.len(), failed);
+
+        Ok(())
+    }
+}
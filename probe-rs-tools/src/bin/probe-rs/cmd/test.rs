@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use time::UtcOffset;
+
+use crate::cmd::run::{RunMode, elf_contains_test};
+use crate::rpc::client::RpcClient;
+use crate::util::junit::TestReport;
+
+#[derive(clap::Parser)]
+#[group(skip)]
+pub struct Cmd {
+    #[clap(flatten)]
+    pub(crate) run: crate::cmd::run::Cmd,
+
+    /// Write the results of the tests to this file as JUnit XML, e.g. for a CI system.
+    #[clap(long, value_name = "FILE", help_heading = "TEST OPTIONS")]
+    pub(crate) junit: Option<PathBuf>,
+}
+
+impl Cmd {
+    pub async fn run(self, client: RpcClient, utc_offset: UtcOffset) -> anyhow::Result<()> {
+        let path = self.run.shared_options.path.clone();
+        anyhow::ensure!(
+            elf_contains_test(&path)?,
+            "{} does not contain any tests. Tests are defined using embedded-test.",
+            path.display()
+        );
+
+        let report = self.junit.as_ref().map(|_| TestReport::default());
+
+        let result = self
+            .run
+            .run_in_mode(client, utc_offset, RunMode::Test, report.clone())
+            .await;
+
+        if let (Some(junit), Some(report)) = (self.junit, report) {
+            let suite = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            report.write(&junit, &suite)?;
+        }
+
+        result
+    }
+}
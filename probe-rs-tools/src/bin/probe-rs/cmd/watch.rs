@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use probe_rs::config::Registry;
+use probe_rs::probe::list::Lister;
+use probe_rs::{CoreStatus, Error, VectorCatchCondition};
+
+use crate::CoreOptions;
+use crate::util::common_options::ProbeOptions;
+use crate::util::snapshot::{SnapshotOptions, parse_range};
+
+/// Watch a running target, and store a snapshot every time the firmware halts.
+///
+/// The target is not flashed or reset when attaching. After each snapshot the target is reset,
+/// so an unattended test rig captures every crash until the command is stopped with Ctrl+C.
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(flatten)]
+    shared: CoreOptions,
+
+    #[clap(flatten)]
+    common: ProbeOptions,
+
+    /// The directory the snapshots are stored in.
+    #[clap(long, value_name = "DIRECTORY")]
+    snapshot_dir: PathBuf,
+
+    /// A memory range to include in snapshots, e.g. `0x20000000..0x20004000`. Can be used
+    /// multiple times. Defaults to all RAM regions of the target.
+    #[clap(long, value_name = "START..END", value_parser = parse_range)]
+    snapshot_range: Vec<(u64, u64)>,
+
+    /// The number of snapshots to keep. Older snapshots are deleted.
+    #[clap(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    snapshot_retention: u32,
+
+    /// How often the core is checked for a halt, in milliseconds.
+    #[clap(long, default_value_t = 100)]
+    poll_interval: u64,
+
+    /// Resume the firmware after a snapshot, instead of resetting the target.
+    #[clap(long)]
+    resume: bool,
+
+    /// Disable hardfault vector catch if its supported on the target.
+    #[clap(long)]
+    no_catch_hardfault: bool,
+}
+
+impl Cmd {
+    pub async fn run(self, registry: &mut Registry, lister: &Lister) -> anyhow::Result<()> {
+        let (mut session, _probe_options) = self.common.simple_attach(registry, lister).await?;
+
+        let directory =
+            std::path::absolute(&self.snapshot_dir).unwrap_or_else(|_| self.snapshot_dir.clone());
+        let snapshot = SnapshotOptions {
+            directory: directory.display().to_string(),
+            ranges: self.snapshot_range,
+            retention: self.snapshot_retention,
+        };
+        let ranges = snapshot.memory_ranges(&session);
+
+        let mut core = session.core(self.shared.core)?;
+
+        if !self.no_catch_hardfault {
+            match core.enable_vector_catch(VectorCatchCondition::HardFault) {
+                Ok(_) | Err(Error::NotImplemented(_)) => {}
+                Err(e) => tracing::error!("Failed to enable_vector_catch: {:?}", e),
+            }
+        }
+
+        println!(
+            "Watching the target, snapshots are stored in {}. Press Ctrl+C to stop.",
+            directory.display()
+        );
+
+        loop {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => break,
+                _ = tokio::time::sleep(Duration::from_millis(self.poll_interval)) => {}
+            }
+
+            let CoreStatus::Halted(halt_reason) = core.status()? else {
+                continue;
+            };
+
+            match snapshot.capture(&mut core, ranges.clone(), halt_reason) {
+                Ok(path) => println!(
+                    "The firmware halted ({halt_reason:?}), stored a snapshot in {}",
+                    path.display()
+                ),
+                Err(error) => {
+                    tracing::warn!("Failed to store a snapshot: {error:?}");
+                    println!("The firmware halted ({halt_reason:?}), storing a snapshot failed");
+                }
+            }
+
+            if self.resume {
+                core.run()?;
+            } else {
+                core.reset()?;
+            }
+        }
+
+        Ok(())
+    }
+}
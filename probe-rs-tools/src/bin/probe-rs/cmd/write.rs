@@ -2,13 +2,16 @@ use crate::rpc::client::RpcClient;
 
 use crate::CoreOptions;
 use crate::util::common_options::{ProbeOptions, ReadWriteBitWidth, ReadWriteOptions};
-use crate::util::{cli, parse_u64};
+use crate::util::{cli, parse_u32, parse_u64};
 
 /// Write to target memory address
 ///
 /// e.g. probe-rs write b32 0x400E1490 0xDEADBEEF 0xCAFEF00D
 ///      Writes 0xDEADBEEF to address 0x400E1490 and 0xCAFEF00D to address 0x400E1494
 ///
+/// e.g. probe-rs write b32 0x20000000 0 --fill 0x4000
+///      Writes 0 to the 0x4000 words starting at address 0x20000000
+///
 /// NOTE: Only supports RAM addresses
 #[derive(clap::Parser)]
 #[clap(verbatim_doc_comment)]
@@ -26,6 +29,11 @@ pub struct Cmd {
     /// Takes a list of integer values and can be specified in decimal (16), hexadecimal (0x10) or octal (0o20) format.
     #[clap(value_parser = parse_u64)]
     values: Vec<u64>,
+
+    /// Write the single value this many times, instead of writing each value once. Large fills
+    /// are done by a small program running on the core, if it is supported.
+    #[clap(long, value_parser = parse_u32, value_name = "COUNT")]
+    fill: Option<u32>,
 }
 
 fn ensure_data_in_range(data: &[u64], width: ReadWriteBitWidth) -> anyhow::Result<()> {
@@ -51,6 +59,21 @@ impl Cmd {
     pub async fn run(self, client: RpcClient) -> anyhow::Result<()> {
         ensure_data_in_range(&self.values, self.read_write_options.width)?;
 
+        if let Some(count) = self.fill {
+            if !matches!(self.read_write_options.width, ReadWriteBitWidth::B32) {
+                anyhow::bail!("--fill is only supported for 32 bit writes.");
+            }
+            let [pattern] = self.values[..] else {
+                anyhow::bail!("--fill takes exactly one value, got {}.", self.values.len());
+            };
+
+            let session = cli::attach_probe(&client, self.probe_options, false).await?;
+            let core = session.core(self.shared.core);
+            core.fill_memory_32(self.read_write_options.address, pattern as u32, count)
+                .await?;
+            return Ok(());
+        }
+
         let session = cli::attach_probe(&client, self.probe_options, false).await?;
         let core = session.core(self.shared.core);
 
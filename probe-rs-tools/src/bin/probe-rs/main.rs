@@ -7,6 +7,7 @@ use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use std::process::ExitCode;
 use std::str::FromStr;
 use std::{ffi::OsString, path::PathBuf};
 
@@ -25,6 +26,7 @@ use time::{OffsetDateTime, UtcOffset};
 
 use crate::rpc::client::RpcClient;
 use crate::rpc::functions::RpcApp;
+use crate::util::cli::FirmwareExitCode;
 use crate::util::logging::setup_logging;
 use crate::util::parse_u32;
 use crate::util::parse_u64;
@@ -70,7 +72,10 @@ struct Cli {
     )]
     report: Option<PathBuf>,
 
-    /// Remote host to connect to
+    /// Remote host to connect to, e.g. `remote://lab-pi:3000`
+    ///
+    /// The remote host runs `probe-rs serve`, and the command is executed there. Probes
+    /// are selected with `--probe` from the probes attached to the remote host.
     #[cfg(feature = "remote")]
     #[arg(
         long,
@@ -125,13 +130,21 @@ impl Cli {
             }
             Subcommand::Download(cmd) => cmd.run(client).await,
             Subcommand::Run(cmd) => cmd.run(client, utc_offset).await,
+            Subcommand::Test(cmd) => cmd.run(client, utc_offset).await,
             Subcommand::Attach(cmd) => cmd.run(client, utc_offset).await,
             Subcommand::Verify(cmd) => cmd.run(client).await,
             Subcommand::Erase(cmd) => cmd.run(client).await,
+            Subcommand::OptionBytes(cmd) => cmd.run(client).await,
+            Subcommand::FlashAlgo(cmd) => cmd.run(&mut *client.registry().await),
+            Subcommand::SwdTrace(cmd) => cmd.run(),
             Subcommand::Trace(cmd) => cmd.run(&mut *client.registry().await, &lister).await,
+            Subcommand::Coredump(cmd) => cmd.run(&mut *client.registry().await, &lister).await,
+            Subcommand::Watch(cmd) => cmd.run(&mut *client.registry().await, &lister).await,
             Subcommand::Itm(cmd) => cmd.run(&mut *client.registry().await, &lister).await,
             Subcommand::Chip(cmd) => cmd.run(client).await,
             Subcommand::Benchmark(cmd) => cmd.run(&mut *client.registry().await, &lister).await,
+            Subcommand::Pins(cmd) => cmd.run(&mut *client.registry().await, &lister).await,
+            Subcommand::BoundaryScan(cmd) => cmd.run(&mut *client.registry().await, &lister).await,
             Subcommand::Profile(cmd) => cmd.run(&mut *client.registry().await, &lister).await,
             Subcommand::Read(cmd) => cmd.run(client).await,
             Subcommand::Write(cmd) => cmd.run(client).await,
@@ -144,6 +157,7 @@ impl Cli {
         match self.subcommand {
             Subcommand::Download(ref cmd) => Some(cmd.path.clone()),
             Subcommand::Run(ref cmd) => Some(cmd.shared_options.path.clone()),
+            Subcommand::Test(ref cmd) => Some(cmd.run.shared_options.path.clone()),
             Subcommand::Attach(ref cmd) => Some(cmd.run.shared_options.path.clone()),
             Subcommand::Verify(ref cmd) => Some(cmd.path.clone()),
             _ => None,
@@ -165,15 +179,26 @@ enum Subcommand {
     Gdb(cmd::gdb_server::Cmd),
     /// Basic command line debugger
     Debug(cmd::debug::Cmd),
+    /// Store the registers and memory of a core in a core dump
+    Coredump(cmd::coredump::Cmd),
+    /// Store a snapshot every time the firmware halts, until stopped
+    Watch(cmd::watch::Cmd),
     /// Download memory to attached target
     Download(cmd::download::Cmd),
     /// Compare memory to attached target
     Verify(cmd::verify::Cmd),
     /// Erase all nonvolatile memory of attached target
     Erase(cmd::erase::Cmd),
+    /// Read and modify the option bytes of attached target
+    OptionBytes(cmd::option_bytes::Cmd),
+    /// Test flash algorithms without a target
+    FlashAlgo(cmd::flash_algo::Cmd),
     /// Flash and run an ELF program
     #[clap(name = "run")]
     Run(cmd::run::Cmd),
+    /// Flash an ELF program and run the tests it contains, one at a time
+    #[clap(name = "test")]
+    Test(cmd::test::Cmd),
     /// Attach to rtt logging
     #[clap(name = "attach")]
     Attach(cmd::attach::Cmd),
@@ -186,6 +211,12 @@ enum Subcommand {
     Chip(cmd::chip::Cmd),
     /// Measure the throughput of the selected debug probe
     Benchmark(cmd::benchmark::Cmd),
+    /// Drive the nRESET and nTRST pins of the debug probe, and read back the pin states
+    Pins(cmd::pins::Cmd),
+    /// Sample and drive the pins of a device with JTAG boundary scan, described by a BSDL file
+    BoundaryScan(cmd::boundary_scan::Cmd),
+    /// Print a log of SWD transfers, recorded with `--record-swd-trace`
+    SwdTrace(cmd::swd_trace::Cmd),
     /// Profile on-target runtime performance of target ELF program
     Profile(cmd::profile::ProfileCmd),
     /// Start a server that accepts remote connections
@@ -213,7 +244,9 @@ impl Subcommand {
                 | Self::Download(_)
                 | Self::Attach(_)
                 | Self::Run(_)
+                | Self::Test(_)
                 | Self::Erase(_)
+                | Self::OptionBytes(_)
                 | Self::Verify(_)
         )
     }
@@ -258,6 +291,49 @@ pub struct ElfCliOptions {
     /// considered when `elf` is selected as the format.
     #[clap(long, help_heading = "DOWNLOAD CONFIGURATION")]
     skip_section: Vec<String>,
+    /// Places data linked for an aliased address range at a different address, given as
+    /// `START..END=TARGET`, e.g. `0x0..0x100000=0x08000000`. This option may be specified
+    /// multiple times, and is applied in addition to the aliases of the target.
+    #[clap(long, value_parser = parse_address_remap, help_heading = "DOWNLOAD CONFIGURATION")]
+    remap: Vec<AddressRemap>,
+}
+
+/// An address range of an image that is placed at a different address.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Schema)]
+pub struct AddressRemap {
+    start: u64,
+    end: u64,
+    target: u64,
+}
+
+impl From<AddressRemap> for probe_rs::config::AddressAlias {
+    fn from(remap: AddressRemap) -> Self {
+        Self {
+            range: remap.start..remap.end,
+            target: remap.target,
+        }
+    }
+}
+
+fn parse_address_remap(input: &str) -> Result<AddressRemap> {
+    let (range, target) = input
+        .split_once('=')
+        .context("Expected an address remapping as `START..END=TARGET`")?;
+    let (start, end) = range
+        .split_once("..")
+        .context("Expected an address range as `START..END`")?;
+
+    let remap = AddressRemap {
+        start: parse_u64(start.trim()).with_context(|| format!("Invalid address `{start}`"))?,
+        end: parse_u64(end.trim()).with_context(|| format!("Invalid address `{end}`"))?,
+        target: parse_u64(target.trim()).with_context(|| format!("Invalid address `{target}`"))?,
+    };
+    anyhow::ensure!(
+        remap.start < remap.end,
+        "The address range `{range}` is empty"
+    );
+
+    Ok(remap)
 }
 
 #[derive(clap::Parser, Clone, Serialize, Deserialize, Debug, Default, Schema)]
@@ -427,8 +503,23 @@ fn multicall_check<'list>(args: &'list [OsString], want: &str) -> Option<&'list
     None
 }
 
+/// Returns the address of the remote host of a `--probe remote://host:port/<selector>` option of
+/// the subcommand, in the form which `--host` accepts.
+#[cfg(feature = "remote")]
+fn remote_probe_host(matches: &ArgMatches) -> Option<String> {
+    // Commands without a `--probe` option return an error, which means there is no remote probe.
+    let selector = matches
+        .try_get_one::<probe_rs::probe::DebugProbeSelector>("probe")
+        .ok()
+        .flatten();
+    match selector.and_then(|selector| selector.remote()) {
+        Some((address, _)) => Some(format!("remote://{address}")),
+        None => remote_probe_host(matches.subcommand()?.1),
+    }
+}
+
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
     // Determine the local offset as early as possible to avoid potential
     // issues with multiple threads and getting the offset.
     // FIXME: we should probably let the user know if we can't determine the offset. However,
@@ -440,11 +531,11 @@ async fn main() -> Result<()> {
     // Special-case `cargo-embed` and `cargo-flash`.
     if let Some(args) = multicall_check(&args, "cargo-flash") {
         cmd::cargo_flash::main(args).await;
-        return Ok(());
+        return Ok(ExitCode::SUCCESS);
     }
     if let Some(args) = multicall_check(&args, "cargo-embed") {
         cmd::cargo_embed::main(args, utc_offset).await;
-        return Ok(());
+        return Ok(ExitCode::SUCCESS);
     }
 
     let config = load_config().context("Failed to load configuration.")?;
@@ -488,8 +579,17 @@ async fn main() -> Result<()> {
     let elf = cli.elf();
     let report_path = cli.report.clone();
 
+    // A probe attached to a remote host is used like a probe attached to a `--host`.
+    #[cfg(feature = "remote")]
+    let host = match (cli.host.clone(), remote_probe_host(&matches)) {
+        (Some(_), Some(_)) => {
+            anyhow::bail!("A remote:// probe selector can not be combined with `--host`.")
+        }
+        (host, remote_probe_host) => host.or(remote_probe_host),
+    };
+
     #[cfg(feature = "remote")]
-    if let Some(host) = cli.host.as_deref() {
+    if let Some(host) = host.as_deref() {
         // Run the command remotely.
         let client = rpc::client::connect(host, cli.token.clone()).await?;
 
@@ -498,9 +598,13 @@ async fn main() -> Result<()> {
             "The subcommand is not supported in remote mode."
         );
 
-        cli.run(client, config, utc_offset).await?;
+        let result = cli.run(client, config, utc_offset).await;
+        if let Some(exit_code) = firmware_exit_code(&result) {
+            return Ok(exit_code);
+        }
+        result?;
         // TODO: handle the report
-        return Ok(());
+        return Ok(ExitCode::SUCCESS);
     }
 
     // Create a local server to run commands against.
@@ -514,7 +618,17 @@ async fn main() -> Result<()> {
     // Wait for the server to shut down
     _ = handle.await.unwrap();
 
-    compile_report(result, report_path, elf, log_path.as_deref())
+    let firmware_exit_code = firmware_exit_code(&result);
+    match (
+        compile_report(result, report_path, elf, log_path.as_deref()),
+        firmware_exit_code,
+    ) {
+        (Ok(()), None) => Ok(ExitCode::SUCCESS),
+        // The failure of the firmware was already printed, it is not an error of probe-rs.
+        (Ok(()), Some(exit_code)) => Ok(exit_code),
+        (Err(error), Some(exit_code)) if error.is::<FirmwareExitCode>() => Ok(exit_code),
+        (Err(error), _) => Err(error),
+    }
 }
 
 fn apply_config_preset(
@@ -568,6 +682,27 @@ fn apply_config_preset(
     Ok(args_modified)
 }
 
+/// The exit code of the firmware, if it failed.
+///
+/// A failing firmware is not an error of probe-rs, so it is not reported as one.
+fn firmware_exit_code(result: &Result<()>) -> Option<ExitCode> {
+    let error = result.as_ref().err()?;
+    let FirmwareExitCode(code) = error.downcast_ref()?;
+
+    Some(ExitCode::from(exit_status(*code)))
+}
+
+/// The exit status of the process for the exit code of the firmware.
+///
+/// Only the low 8 bits of the exit status are visible to the parent process, so a failure whose
+/// low 8 bits are zero is mapped to 1.
+fn exit_status(code: i32) -> u8 {
+    match (code & 0xFF) as u8 {
+        0 if code != 0 => 1,
+        status => status,
+    }
+}
+
 fn compile_report(
     result: Result<()>,
     path: Option<PathBuf>,
@@ -647,7 +782,74 @@ fn load_config() -> anyhow::Result<Config> {
 
 #[cfg(test)]
 mod test {
-    use crate::multicall_check;
+    use clap::CommandFactory;
+
+    use crate::{AddressRemap, Cli, exit_status, multicall_check, parse_address_remap};
+
+    #[cfg(feature = "remote")]
+    #[test]
+    fn remote_probe_host() {
+        let host = |args: &[&str]| crate::remote_probe_host(&Cli::command().get_matches_from(args));
+
+        assert_eq!(
+            host(&[
+                "probe-rs",
+                "info",
+                "--probe",
+                "remote://lab-pi:3000/1366:1015"
+            ])
+            .as_deref(),
+            Some("remote://lab-pi:3000")
+        );
+        assert_eq!(
+            host(&["probe-rs", "info", "--probe=remote://lab-pi:3000"]).as_deref(),
+            Some("remote://lab-pi:3000")
+        );
+        assert_eq!(
+            host(&[
+                "probe-rs",
+                "chip",
+                "detect",
+                "--probe",
+                "remote://lab-pi:3000"
+            ])
+            .as_deref(),
+            Some("remote://lab-pi:3000")
+        );
+        assert_eq!(host(&["probe-rs", "info", "--probe", "1366:1015"]), None);
+        assert_eq!(host(&["probe-rs", "list"]), None);
+    }
+
+    #[test]
+    fn cli_arguments_are_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn firmware_exit_status() {
+        assert_eq!(exit_status(0), 0);
+        assert_eq!(exit_status(3), 3);
+        assert_eq!(exit_status(134), 134);
+        assert_eq!(exit_status(0x102), 2);
+        // A failure must not become a successful exit status.
+        assert_eq!(exit_status(0x100), 1);
+        assert_eq!(exit_status(-256), 1);
+        assert_eq!(exit_status(-1), 255);
+    }
+
+    #[test]
+    fn address_remap_parsing() {
+        assert_eq!(
+            parse_address_remap("0x0..0x100000=0x08000000").unwrap(),
+            AddressRemap {
+                start: 0,
+                end: 0x10_0000,
+                target: 0x0800_0000,
+            }
+        );
+        assert!(parse_address_remap("0x1000..0x1000=0x0").is_err());
+        assert!(parse_address_remap("0x0=0x08000000").is_err());
+    }
 
     #[test]
     fn argument_preprocessing() {
@@ -29,23 +29,30 @@ use crate::{
         Key,
         functions::{
             AttachEndpoint, BuildEndpoint, ChipInfoEndpoint, CreateRttClientEndpoint,
-            CreateTempFileEndpoint, EraseEndpoint, FlashEndpoint, ListChipFamiliesEndpoint,
-            ListProbesEndpoint, ListTestsEndpoint, LoadChipFamilyEndpoint, MonitorEndpoint,
-            ProgressEventTopic, ReadMemory8Endpoint, ReadMemory16Endpoint, ReadMemory32Endpoint,
-            ReadMemory64Endpoint, ResetCoreEndpoint, ResumeAllCoresEndpoint, RpcResult,
-            RunTestEndpoint, SelectProbeEndpoint, TakeStackTraceEndpoint, TargetInfoDataTopic,
-            TargetInfoEndpoint, TempFileDataEndpoint, TokioSpawner, VerifyEndpoint,
-            WriteMemory8Endpoint, WriteMemory16Endpoint, WriteMemory32Endpoint,
-            WriteMemory64Endpoint,
-            chip::{ChipData, ChipFamily, ChipInfoRequest, LoadChipFamilyRequest},
+            CreateTempFileEndpoint, EraseEndpoint, FillMemory32Endpoint, FlashEndpoint,
+            ListChipFamiliesEndpoint, ListProbesEndpoint, ListTestsEndpoint,
+            LoadChipFamilyEndpoint, MonitorEndpoint, ProgressEventTopic, ReadMemory8Endpoint,
+            ReadMemory16Endpoint, ReadMemory32Endpoint, ReadMemory64Endpoint,
+            ReadOptionBytesEndpoint, ResetCoreEndpoint, ResumeAllCoresEndpoint, RpcResult,
+            RunTestEndpoint, ScanChipEndpoint, SelectProbeEndpoint, TakeStackTraceEndpoint,
+            TargetInfoDataTopic, TargetInfoEndpoint, TempFileDataEndpoint, TokioSpawner,
+            VerifyEndpoint, WriteMemory8Endpoint, WriteMemory16Endpoint, WriteMemory32Endpoint,
+            WriteMemory64Endpoint, WriteOptionBytesEndpoint,
+            chip::{
+                ChipData, ChipFamily, ChipInfoRequest, LoadChipFamilyRequest, ScanChipRequest,
+                ScannedChip,
+            },
             file::{AppendFileRequest, TempFile},
             flash::{
                 BootInfo, BuildRequest, BuildResult, DownloadOptions, EraseCommand, EraseRequest,
                 FlashRequest, ProgressEvent, VerifyRequest, VerifyResult,
             },
             info::{InfoEvent, TargetInfoRequest},
-            memory::{ReadMemoryRequest, WriteMemoryRequest},
+            memory::{FillMemoryRequest, ReadMemoryRequest, WriteMemoryRequest},
             monitor::{MonitorExitReason, MonitorMode, MonitorOptions, MonitorRequest},
+            option_bytes::{
+                OptionByteValue, OptionByteWrite, ReadOptionBytesRequest, WriteOptionBytesRequest,
+            },
             probe::{
                 AttachRequest, AttachResult, DebugProbeEntry, DebugProbeSelector,
                 ListProbesRequest, SelectProbeRequest, SelectProbeResult,
@@ -60,10 +67,26 @@ use crate::{
     },
     util::{
         cli::MonitorEvent,
-        rtt::{RttChannelConfig, client::RttClient},
+        rtt::{RttConfig, client::RttClient},
     },
 };
 
+/// Turns the `--host` argument into the websocket address of the server.
+///
+/// `probe-rs serve` speaks plain websockets, so `remote://host:port` and a bare `host:port` are
+/// accepted as well as `ws://` and `wss://` addresses.
+#[cfg(feature = "remote")]
+fn server_address(host: &str) -> String {
+    let host = host.trim_end_matches('/');
+    if let Some(address) = host.strip_prefix("remote://") {
+        format!("ws://{address}")
+    } else if host.contains("://") {
+        host.to_string()
+    } else {
+        format!("ws://{host}")
+    }
+}
+
 #[cfg(feature = "remote")]
 pub async fn connect(host: &str, token: Option<String>) -> anyhow::Result<RpcClient> {
     use crate::rpc::transport::websocket::{WebsocketRx, WebsocketTx};
@@ -79,7 +102,8 @@ pub async fn connect(host: &str, token: Option<String>) -> anyhow::Result<RpcCli
     };
     use tokio_util::bytes::Bytes;
 
-    let uri = Uri::from_str(&format!("{host}/worker")).context("Failed to parse server URI")?;
+    let uri = Uri::from_str(&format!("{}/worker", server_address(host)))
+        .context("Failed to parse server URI")?;
 
     let is_localhost = uri
         .host()
@@ -354,8 +378,8 @@ impl RpcClient {
         self.send_resp::<AttachEndpoint, _>(&request).await
     }
 
-    pub async fn list_probes(&self) -> anyhow::Result<Vec<DebugProbeEntry>> {
-        self.send_resp::<ListProbesEndpoint, _>(&ListProbesRequest::all())
+    pub async fn list_probes(&self, firmware: bool) -> anyhow::Result<Vec<DebugProbeEntry>> {
+        self.send_resp::<ListProbesEndpoint, _>(&ListProbesRequest::all().with_firmware(firmware))
             .await
     }
 
@@ -390,6 +414,10 @@ impl RpcClient {
             .await
     }
 
+    pub async fn scan_chip(&self, request: ScanChipRequest) -> anyhow::Result<ScannedChip> {
+        self.send_resp::<ScanChipEndpoint, _>(&request).await
+    }
+
     pub(crate) async fn registry(&self) -> MutexGuard<'_, Registry> {
         self.registry.lock().await
     }
@@ -496,6 +524,23 @@ impl SessionInterface {
             .await
     }
 
+    pub async fn read_option_bytes(&self) -> anyhow::Result<Vec<OptionByteValue>> {
+        self.client
+            .send_resp::<ReadOptionBytesEndpoint, _>(&ReadOptionBytesRequest {
+                sessid: self.sessid,
+            })
+            .await
+    }
+
+    pub async fn write_option_bytes(&self, values: Vec<OptionByteWrite>) -> anyhow::Result<()> {
+        self.client
+            .send_resp::<WriteOptionBytesEndpoint, _>(&WriteOptionBytesRequest {
+                sessid: self.sessid,
+                values,
+            })
+            .await
+    }
+
     pub async fn monitor(
         &self,
         mode: MonitorMode,
@@ -553,7 +598,7 @@ impl SessionInterface {
     pub async fn create_rtt_client(
         &self,
         scan_regions: ScanRegion,
-        config: Vec<RttChannelConfig>,
+        config: RttConfig,
     ) -> anyhow::Result<RttClientData> {
         self.client
             .send_resp::<CreateRttClientEndpoint, _>(&CreateRttClientRequest {
@@ -682,6 +727,23 @@ impl CoreInterface {
             .await
     }
 
+    pub async fn fill_memory_32(
+        &self,
+        address: u64,
+        pattern: u32,
+        count: u32,
+    ) -> anyhow::Result<()> {
+        self.client
+            .send_resp::<FillMemory32Endpoint, _>(&FillMemoryRequest {
+                sessid: self.sessid,
+                core: self.core,
+                address,
+                pattern,
+                count,
+            })
+            .await
+    }
+
     pub async fn reset(&self) -> anyhow::Result<()> {
         self.client
             .send_resp::<ResetCoreEndpoint, _>(&ResetCoreRequest {
@@ -759,3 +821,19 @@ where
         self.recv().await
     }
 }
+
+#[cfg(all(test, feature = "remote"))]
+mod test {
+    use super::server_address;
+
+    #[test]
+    fn server_addresses() {
+        assert_eq!(server_address("remote://lab-pi:3000"), "ws://lab-pi:3000");
+        assert_eq!(server_address("lab-pi:3000"), "ws://lab-pi:3000");
+        assert_eq!(server_address("ws://lab-pi:3000/"), "ws://lab-pi:3000");
+        assert_eq!(
+            server_address("wss://lab.example.com"),
+            "wss://lab.example.com"
+        );
+    }
+}
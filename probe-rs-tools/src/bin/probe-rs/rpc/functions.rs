@@ -4,21 +4,30 @@ use std::{convert::Infallible, future::Future};
 use crate::rpc::functions::file::{
     AppendFileRequest, CreateFileResponse, append_temp_file, create_temp_file,
 };
+use crate::util::hooks::HookScript;
 use crate::{
     rpc::{
         Key, SessionState,
         functions::{
             chip::{
                 ChipInfoRequest, ChipInfoResponse, ListFamiliesResponse, LoadChipFamilyRequest,
-                chip_info, list_families, load_chip_family,
+                ScanChipRequest, ScanChipResponse, chip_info, list_families, load_chip_family,
+                scan_chip,
             },
             flash::{
                 BuildRequest, BuildResponse, EraseRequest, FlashRequest, ProgressEvent,
                 VerifyRequest, VerifyResponse, build, erase, flash, verify,
             },
             info::{InfoEvent, TargetInfoRequest, target_info},
-            memory::{ReadMemoryRequest, WriteMemoryRequest, read_memory, write_memory},
+            memory::{
+                FillMemoryRequest, ReadMemoryRequest, WriteMemoryRequest, fill_memory, read_memory,
+                write_memory,
+            },
             monitor::{MonitorRequest, MonitorResponse, RttEvent, SemihostingEvent, monitor},
+            option_bytes::{
+                ReadOptionBytesRequest, ReadOptionBytesResponse, WriteOptionBytesRequest,
+                read_option_bytes, write_option_bytes,
+            },
             probe::{
                 AttachRequest, AttachResponse, ListProbesRequest, ListProbesResponse,
                 SelectProbeRequest, SelectProbeResponse, attach, list_probes, select_probe,
@@ -61,6 +70,7 @@ pub mod flash;
 pub mod info;
 pub mod memory;
 pub mod monitor;
+pub mod option_bytes;
 pub mod probe;
 pub mod reset;
 pub mod resume;
@@ -112,6 +122,12 @@ impl From<probe_rs::flashing::FlashError> for RpcError {
     }
 }
 
+impl From<probe_rs::flashing::option_bytes::OptionBytesError> for RpcError {
+    fn from(e: probe_rs::flashing::option_bytes::OptionBytesError) -> Self {
+        Self::from(anyhow!(e))
+    }
+}
+
 impl From<probe_rs::config::RegistryError> for RpcError {
     fn from(e: probe_rs::config::RegistryError) -> Self {
         Self::from(anyhow!(e))
@@ -213,6 +229,10 @@ impl RpcSpawnContext {
         self.state.dry_run(sessid)
     }
 
+    fn hooks(&self, sessid: Key<Session>) -> Option<HookScript> {
+        self.state.hooks(sessid)
+    }
+
     fn session_blocking(&self, sessid: Key<Session>) -> impl DerefMut<Target = Session> + use<> {
         self.state.session_blocking(sessid)
     }
@@ -375,8 +395,17 @@ impl RpcContext {
         self.state.store_object(obj).await
     }
 
-    pub async fn set_session(&mut self, session: Session, dry_run: bool) -> Key<Session> {
-        self.state.set_session(session, dry_run).await
+    pub async fn set_session(
+        &mut self,
+        session: Session,
+        dry_run: bool,
+        hooks: Option<HookScript>,
+    ) -> Key<Session> {
+        self.state.set_session(session, dry_run, hooks).await
+    }
+
+    pub fn hooks(&self, sessid: Key<Session>) -> Option<HookScript> {
+        self.state.hooks(sessid)
     }
 
     pub async fn session(
@@ -479,10 +508,14 @@ endpoints! {
     | ListChipFamiliesEndpoint  | ()                     | ListFamiliesResponse    | "chips/list"       |
     | ChipInfoEndpoint          | ChipInfoRequest        | ChipInfoResponse        | "chips/info"       |
     | LoadChipFamilyEndpoint    | LoadChipFamilyRequest  | NoResponse              | "chips/load"       |
+    | ScanChipEndpoint          | ScanChipRequest        | ScanChipResponse        | "chips/scan"       |
 
     | TargetInfoEndpoint        | TargetInfoRequest      | NoResponse              | "info"             |
     | ResetCoreEndpoint         | ResetCoreRequest       | NoResponse              | "reset"            |
 
+    | ReadOptionBytesEndpoint   | ReadOptionBytesRequest | ReadOptionBytesResponse | "option_bytes/read" |
+    | WriteOptionBytesEndpoint  | WriteOptionBytesRequest | NoResponse             | "option_bytes/write" |
+
     | ReadMemory8Endpoint       | ReadMemoryRequest      | ReadMemory8Response     | "memory/read8"     |
     | ReadMemory16Endpoint      | ReadMemoryRequest      | ReadMemory16Response    | "memory/read16"    |
     | ReadMemory32Endpoint      | ReadMemoryRequest      | ReadMemory32Response    | "memory/read32"    |
@@ -492,6 +525,7 @@ endpoints! {
     | WriteMemory16Endpoint     | WriteMemory16Request   | NoResponse              | "memory/write16"   |
     | WriteMemory32Endpoint     | WriteMemory32Request   | NoResponse              | "memory/write32"   |
     | WriteMemory64Endpoint     | WriteMemory64Request   | NoResponse              | "memory/write64"   |
+    | FillMemory32Endpoint      | FillMemoryRequest      | NoResponse              | "memory/fill32"    |
 }
 
 topics! {
@@ -547,10 +581,14 @@ postcard_rpc::define_dispatch! {
         | ListChipFamiliesEndpoint  | async     | list_families     |
         | ChipInfoEndpoint          | async     | chip_info         |
         | LoadChipFamilyEndpoint    | async     | load_chip_family  |
+        | ScanChipEndpoint          | async     | scan_chip         |
 
         | TargetInfoEndpoint        | async     | target_info       |
         | ResetCoreEndpoint         | async     | reset             |
 
+        | ReadOptionBytesEndpoint   | async     | read_option_bytes |
+        | WriteOptionBytesEndpoint  | async     | write_option_bytes |
+
         | ReadMemory8Endpoint       | async     | read_memory       |
         | ReadMemory16Endpoint      | async     | read_memory       |
         | ReadMemory32Endpoint      | async     | read_memory       |
@@ -560,6 +598,7 @@ postcard_rpc::define_dispatch! {
         | WriteMemory16Endpoint     | async     | write_memory      |
         | WriteMemory32Endpoint     | async     | write_memory      |
         | WriteMemory64Endpoint     | async     | write_memory      |
+        | FillMemory32Endpoint      | async     | fill_memory       |
     };
     topics_in: {
         list: TOPICS_IN_LIST;
@@ -1,11 +1,21 @@
-use std::ops::Range;
+use std::{fmt::Write, ops::Range};
 
+use bytesize::ByteSize;
 use postcard_rpc::header::VarHeader;
 use postcard_schema::Schema;
-use probe_rs::Target;
+use probe_rs::{
+    MemoryMapPolicy, Target,
+    config::{ChipScan, SfdpFlash},
+};
 use serde::{Deserialize, Serialize};
 
-use crate::rpc::functions::{NoResponse, RpcContext, RpcResult};
+use crate::{
+    rpc::functions::{
+        NoResponse, RpcContext, RpcResult,
+        probe::{DebugProbeEntry, WireProtocol},
+    },
+    util::common_options::ProbeOptions,
+};
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, Schema)]
 pub struct JEP106Code {
@@ -84,13 +94,7 @@ pub async fn list_families(
     _header: VarHeader,
     _req: (),
 ) -> ListFamiliesResponse {
-    Ok(ctx
-        .registry()
-        .await
-        .families()
-        .iter()
-        .map(|f| f.into())
-        .collect())
+    Ok(ctx.registry().await.families().map(|f| f.into()).collect())
 }
 
 #[derive(Serialize, Deserialize, Schema)]
@@ -349,3 +353,140 @@ pub async fn load_chip_family(
 
     Ok(())
 }
+
+#[derive(Serialize, Deserialize, Schema)]
+pub struct ScanChipRequest {
+    pub probe: DebugProbeEntry,
+    pub speed: Option<u32>,
+    pub protocol: Option<WireProtocol>,
+    pub connect_under_reset: bool,
+    /// The name of the chip in the generated target description.
+    pub name: String,
+    /// The base addresses of memory mapped flashes which are queried with CFI.
+    pub cfi_addresses: Vec<u64>,
+    /// The SFDP tables of serial flashes, which can't be read through the debug port.
+    pub sfdp_tables: Vec<SfdpTables>,
+}
+
+/// The SFDP address space of a serial flash, read by other means than the debug port.
+#[derive(Serialize, Deserialize, Schema)]
+pub struct SfdpTables {
+    /// The address at which the flash is mapped.
+    pub base_address: u64,
+    /// The SFDP data, starting at SFDP address 0.
+    pub data: Vec<u8>,
+}
+
+impl From<&ScanChipRequest> for ProbeOptions {
+    fn from(request: &ScanChipRequest) -> Self {
+        ProbeOptions {
+            chip: None,
+            chip_description_path: None,
+            protocol: request.protocol.map(Into::into),
+            non_interactive: true,
+            probe: Some(request.probe.selector().into()),
+            speed: request.speed,
+            speed_floor: None,
+            hooks: None,
+            record_trace: None,
+            record_swd_trace: None,
+            connect_under_reset: request.connect_under_reset,
+            dry_run: false,
+            allow_erase_all: false,
+            no_debug_freeze: false,
+            memory_map: MemoryMapPolicy::Allow,
+            reconnect_on_power_down: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Schema)]
+pub struct ScannedChip {
+    /// The name of the target, if the chip is already known.
+    pub known_target: Option<String>,
+    /// The generated target description, in YAML format.
+    pub target_description: String,
+}
+
+pub type ScanChipResponse = RpcResult<ScannedChip>;
+
+pub async fn scan_chip(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: ScanChipRequest,
+) -> ScanChipResponse {
+    let mut registry = ctx.registry().await;
+    let probe_options = ProbeOptions::from(&request).load(&mut registry)?;
+
+    let mut probe = probe_options.attach_probe(&ctx.lister()).await?;
+    if probe_options.connect_under_reset() {
+        probe.attach_to_unspecified_under_reset()?;
+    } else {
+        probe.attach_to_unspecified()?;
+    }
+
+    let (_probe, mut scan) =
+        probe_rs::vendor::scan_target(&registry, probe, &request.cfi_addresses)?;
+    for tables in &request.sfdp_tables {
+        scan.sfdp_flash
+            .push(SfdpFlash::parse(tables.base_address, &tables.data)?);
+    }
+
+    Ok(ScannedChip {
+        known_target: scan.known_target.clone(),
+        target_description: target_description(&scan, &request.name)?,
+    })
+}
+
+/// Renders the scan as a target description, with comments on what has to be checked.
+fn target_description(scan: &ChipScan, name: &str) -> anyhow::Result<String> {
+    let mut description = String::new();
+
+    writeln!(description, "# Generated by `probe-rs chip scan`.")?;
+    writeln!(
+        description,
+        "# Check the memory map, and add flash algorithms before using this target."
+    )?;
+    for core in &scan.cores {
+        writeln!(
+            description,
+            "# Found {} ({:?}) on {:?}",
+            core.cpu, core.core_type, core.access_port
+        )?;
+    }
+    for flash in &scan.cfi_flash {
+        writeln!(
+            description,
+            "# CFI flash at {:#010x}, {}:",
+            flash.base_address,
+            ByteSize(flash.size).display().iec()
+        )?;
+        for region in &flash.erase_regions {
+            writeln!(
+                description,
+                "#   {} blocks of {}",
+                region.blocks,
+                ByteSize(region.block_size).display().iec()
+            )?;
+        }
+    }
+
+    for flash in &scan.sfdp_flash {
+        writeln!(
+            description,
+            "# SFDP flash at {:#010x}, {}, erase sizes: {}",
+            flash.base_address,
+            ByteSize(flash.size).display().iec(),
+            flash
+                .erase_sizes
+                .iter()
+                .map(|&size| ByteSize(size).display().iec().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )?;
+    }
+
+    description.push_str(&serde_yaml::to_string(&scan.to_chip_family(name))?);
+
+    Ok(description)
+}
@@ -15,7 +15,11 @@ use crate::{
         Key,
         functions::{NoResponse, ProgressEventTopic, RpcContext, RpcResult, RpcSpawnContext},
     },
-    util::{flash::build_loader, rtt::client::RttClient},
+    util::{
+        flash::{build_loader, download_checkpoint_path},
+        hooks::{HookPoint, HookTarget},
+        rtt::client::RttClient,
+    },
 };
 
 #[derive(Serialize, Deserialize, Default, Schema)]
@@ -38,6 +42,44 @@ pub struct DownloadOptions {
     pub verify: bool,
     /// Disable double buffering when loading flash.
     pub disable_double_buffering: bool,
+    /// Only erase and program the sectors whose contents differ from the image.
+    pub incremental: bool,
+    /// Skip the sectors an interrupted download of the same image already programmed.
+    pub resume: bool,
+    /// How the watchdogs of the target are handled while flashing.
+    pub watchdog: WatchdogMode,
+}
+
+/// How the watchdogs of a target are handled while it is flashed.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Schema)]
+pub enum WatchdogMode {
+    /// Leave the watchdogs alone.
+    #[default]
+    Ignore,
+    /// Refresh the watchdogs between flash operations.
+    Refresh,
+    /// Disable the watchdogs between flash operations.
+    Disable,
+}
+
+impl From<flashing::WatchdogMode> for WatchdogMode {
+    fn from(value: flashing::WatchdogMode) -> Self {
+        match value {
+            flashing::WatchdogMode::Ignore => WatchdogMode::Ignore,
+            flashing::WatchdogMode::Refresh => WatchdogMode::Refresh,
+            flashing::WatchdogMode::Disable => WatchdogMode::Disable,
+        }
+    }
+}
+
+impl From<WatchdogMode> for flashing::WatchdogMode {
+    fn from(value: WatchdogMode) -> Self {
+        match value {
+            WatchdogMode::Ignore => flashing::WatchdogMode::Ignore,
+            WatchdogMode::Refresh => flashing::WatchdogMode::Refresh,
+            WatchdogMode::Disable => flashing::WatchdogMode::Disable,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Schema)]
@@ -87,6 +129,9 @@ impl FlashRequest {
         options.preverify = false;
         options.verify = self.options.verify;
         options.disable_double_buffering = self.options.disable_double_buffering;
+        options.incremental = self.options.incremental;
+        options.resume = self.options.resume;
+        options.watchdog = self.options.watchdog.into();
 
         options
     }
@@ -237,6 +282,14 @@ pub enum ProgressEvent {
         /// The message that was emitted.
         message: String,
     },
+
+    /// Programming with double buffering has finished.
+    PipelineStats {
+        /// The time spent transferring data into the RAM of the target, in microseconds.
+        transfer_time_us: u64,
+        /// The time spent waiting for the flash algorithm, in microseconds.
+        flash_wait_time_us: u64,
+    },
 }
 impl ProgressEvent {
     pub fn from_library_event(event: flashing::ProgressEvent, mut cb: impl FnMut(ProgressEvent)) {
@@ -266,6 +319,10 @@ impl ProgressEvent {
             flashing::ProgressEvent::DiagnosticMessage { message } => {
                 ProgressEvent::DiagnosticMessage { message }
             }
+            flashing::ProgressEvent::PipelineStats(stats) => ProgressEvent::PipelineStats {
+                transfer_time_us: stats.transfer_time.as_micros() as u64,
+                flash_wait_time_us: stats.flash_wait_time.as_micros() as u64,
+            },
         };
 
         cb(event);
@@ -345,6 +402,7 @@ fn flash_impl(
     sender: Sender<ProgressEvent>,
 ) -> NoResponse {
     let dry_run = ctx.dry_run(request.sessid);
+    let hooks = ctx.hooks(request.sessid);
     let mut session = ctx.session_blocking(request.sessid);
 
     let mut rtt_client = request
@@ -360,15 +418,38 @@ fn flash_impl(
 
     let mut options = request.download_options();
     options.dry_run = dry_run;
+    if options.resume {
+        options.checkpoint = download_checkpoint_path(&session.target().name);
+    }
     options.progress = Some(FlashProgress::new(move |event| {
         ProgressEvent::from_library_event(event, |event| sender.blocking_send(event).unwrap());
     }));
 
+    if let Some(hooks) = &hooks {
+        hooks.run(
+            HookPoint::PreFlash,
+            HookTarget::Session {
+                session: &mut session,
+                core: 0,
+            },
+        )?;
+    }
+
     // run flash download
     loader
         .commit(&mut session, options)
         .map_err(FileDownloadError::Flash)?;
 
+    if let Some(hooks) = &hooks {
+        hooks.run(
+            HookPoint::PostFlash,
+            HookTarget::Session {
+                session: &mut session,
+                core: 0,
+            },
+        )?;
+    }
+
     Ok(())
 }
 
@@ -6,23 +6,17 @@ use anyhow::anyhow;
 use postcard_rpc::header::{VarHeader, VarSeq};
 use postcard_schema::{Schema, schema};
 use probe_rs::{
+    MemoryMapPolicy,
     architecture::{
-        arm::{
-            self, ApAddress, ApV2Address, ArmDebugInterface,
-            ap::{ApClass, ApRegister, IDR},
-            component::Scs,
-            dp::{self, Ctrl, DLPIDR, DPIDR, DpRegister, TARGETID},
-            memory::{
-                ArmMemoryInterface, Component, ComponentId, CoresightComponent, PeripheralType,
-                romtable::{PeripheralID, RomTable},
-            },
-            sequences::DefaultArmSequence,
-        },
+        arm::{self, ArmDebugInterface, dp, sequences::DefaultArmSequence},
         riscv::communication_interface::RiscvCommunicationInterface,
         xtensa::communication_interface::{
             XtensaCommunicationInterface, XtensaDebugInterfaceState,
         },
     },
+    device_info::arm::{
+        AccessPortInfo, ComponentInfo, ComponentKind, MemoryApState, read_debug_port_info,
+    },
     probe::{Probe, WireProtocol as ProbeRsWireProtocol},
 };
 use serde::{Deserialize, Serialize};
@@ -44,6 +38,8 @@ pub struct TargetInfoRequest {
     pub dry_run: bool,
     pub target_sel: Option<u32>,
     pub protocol: WireProtocol,
+    /// Only scan the JTAG chain, without reading architecture specific information.
+    pub scan_chain_only: bool,
 }
 
 impl From<&TargetInfoRequest> for ProbeOptions {
@@ -58,9 +54,16 @@ impl From<&TargetInfoRequest> for ProbeOptions {
             non_interactive: true,
             probe: Some(request.probe.selector().into()),
             speed: request.speed,
+            speed_floor: None,
+            hooks: None,
+            record_trace: None,
+            record_swd_trace: None,
             connect_under_reset: request.connect_under_reset,
             dry_run: request.dry_run,
             allow_erase_all: false,
+            no_debug_freeze: false,
+            memory_map: MemoryMapPolicy::Allow,
+            reconnect_on_power_down: None,
         }
     }
 }
@@ -81,6 +84,7 @@ pub async fn target_info(
         request.protocol,
         probe_options.connect_under_reset(),
         request.target_sel,
+        request.scan_chain_only,
     )
     .await
     {
@@ -121,6 +125,14 @@ pub enum InfoEvent {
         idcode: Option<u32>,
     },
     ArmDp(DebugPortInfo),
+    JtagChain(Vec<JtagTapInfo>),
+}
+
+/// A TAP found on the JTAG scan chain.
+#[derive(Clone, Serialize, Deserialize, Schema)]
+pub struct JtagTapInfo {
+    pub idcode: Option<u32>,
+    pub ir_len: u8,
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Serialize, Deserialize, Schema)]
@@ -292,6 +304,7 @@ async fn try_show_info(
     protocol: WireProtocol,
     connect_under_reset: bool,
     target_sel: Option<u32>,
+    scan_chain_only: bool,
 ) -> anyhow::Result<()> {
     probe.select_protocol(ProbeRsWireProtocol::from(protocol))?;
 
@@ -301,6 +314,37 @@ async fn try_show_info(
         probe.attach_to_unspecified()?;
     }
 
+    if protocol == WireProtocol::Jtag {
+        match probe.scan_jtag_chain() {
+            Ok(chain) => {
+                let taps = chain
+                    .into_iter()
+                    .map(|tap| JtagTapInfo {
+                        idcode: tap.idcode.map(u32::from),
+                        ir_len: tap.ir_len,
+                    })
+                    .collect();
+
+                ctx.publish::<TargetInfoDataTopic>(VarSeq::Seq2(0), &InfoEvent::JtagChain(taps))
+                    .await?;
+            }
+            Err(error) => {
+                ctx.publish::<TargetInfoDataTopic>(
+                    VarSeq::Seq2(0),
+                    &InfoEvent::Error {
+                        architecture: "JTAG".to_string(),
+                        error: format!("{error:?}"),
+                    },
+                )
+                .await?;
+            }
+        }
+    }
+
+    if scan_chain_only {
+        return Ok(());
+    }
+
     if probe.has_arm_debug_interface() {
         let dp_addr = if let Some(target_sel) = target_sel {
             vec![dp::DpAddress::Multidrop(target_sel)]
@@ -480,37 +524,7 @@ async fn show_arm_info(
     interface: &mut dyn ArmDebugInterface,
     dp: dp::DpAddress,
 ) -> anyhow::Result<dp::DebugPortVersion> {
-    let dp_info = interface.read_raw_dp_register(dp, DPIDR::ADDRESS)?;
-    let dp_info = dp::DebugPortId::from(DPIDR(dp_info));
-
-    let dpinfo = if dp_info.version == dp::DebugPortVersion::DPv2 {
-        let targetid = interface.read_raw_dp_register(dp, TARGETID::ADDRESS)?;
-
-        // Read Instance ID
-        let dlpidr = interface.read_raw_dp_register(dp, DLPIDR::ADDRESS)?;
-
-        // Read from the CTRL/STAT register, to ensure that the dpbanksel field is set to zero.
-        // This helps with error handling later, because it means the CTRL/AP register can be
-        // read in case of an error.
-        let _ = interface.read_raw_dp_register(dp, Ctrl::ADDRESS)?;
-
-        DebugPortInfoNode {
-            dp_info: DebugPortId::from(&dp_info),
-            targetid,
-            dlpidr,
-        }
-    } else {
-        DebugPortInfoNode {
-            dp_info: DebugPortId::from(&dp_info),
-            targetid: 0,
-            dlpidr: 0,
-        }
-    };
-
-    let mut info = DebugPortInfo {
-        dp_info: dpinfo.clone(),
-        aps: vec![],
-    };
+    let dp_info = read_debug_port_info(interface, dp)?;
 
     ctx.publish::<TargetInfoDataTopic>(
         VarSeq::Seq2(0),
@@ -518,296 +532,149 @@ async fn show_arm_info(
     )
     .await?;
 
-    if dp_info.version != dp::DebugPortVersion::DPv3 {
-        let access_ports = interface.access_ports(dp)?;
-        for ap_address in access_ports {
-            match ap_address.ap() {
-                ApAddress::V1(_) => {
-                    let raw_idr = interface.read_raw_ap_register(&ap_address, IDR::ADDRESS)?;
-                    let idr: IDR = raw_idr.try_into()?;
-
-                    let ap_info = if idr.CLASS == ApClass::MemAp {
-                        let mut ap_nodes = ComponentTreeNode::new(format!(
-                            "{} MemoryAP ({:?})",
-                            ap_address.ap_v1()?,
-                            idr.TYPE
-                        ));
-                        if let Err(e) = handle_memory_ap(interface, &ap_address, &mut ap_nodes) {
-                            ap_nodes.push(format!("Error during access: {e}"));
-                        };
-                        ApInfo::MemoryAp {
-                            ap_addr: FullyQualifiedApAddress {
-                                dp: ap_address.dp().into(),
-                                ap: ap_address.ap().to_string(),
-                            },
-                            component_tree: ap_nodes,
-                        }
-                    } else {
-                        ApInfo::Unknown {
-                            ap_addr: FullyQualifiedApAddress {
-                                dp: ap_address.dp().into(),
-                                ap: ap_address.ap().to_string(),
-                            },
-                            idr: raw_idr,
-                        }
-                    };
-
-                    info.aps.push(ap_info);
-                }
-
-                ApAddress::V2(_) => {
-                    unreachable!("Ap V1 and V2 cannot be mixed.")
-                }
-            }
-        }
-    } else {
-        let fqa = arm::FullyQualifiedApAddress::v2_with_dp(dp, ApV2Address::root());
-        let root_rom_table = {
-            let mut root_memory = interface.memory_interface(&fqa)?;
-            let base_address = root_memory.base_address()?;
-            Component::try_parse(&mut *root_memory, base_address)?
-        };
-        let mut component_tree = ComponentTreeNode::new(String::new());
-        coresight_component_tree(interface, root_rom_table, &fqa, &mut component_tree)?;
-        info.aps.push(ApInfo::ApV2Root { component_tree });
-    }
+    let info = DebugPortInfo {
+        dp_info: DebugPortInfoNode {
+            dp_info: DebugPortId::from(&dp_info.id),
+            targetid: dp_info.targetid.unwrap_or(0),
+            dlpidr: dp_info.dlpidr.unwrap_or(0),
+        },
+        aps: dp_info.access_ports.iter().map(ApInfo::from).collect(),
+    };
 
     ctx.publish::<TargetInfoDataTopic>(VarSeq::Seq2(0), &InfoEvent::ArmDp(info))
         .await?;
 
-    Ok(dp_info.version)
-}
-
-fn handle_memory_ap(
-    interface: &mut dyn ArmDebugInterface,
-    access_port: &arm::FullyQualifiedApAddress,
-    parent: &mut ComponentTreeNode,
-) -> anyhow::Result<()> {
-    let component = {
-        let mut memory = interface.memory_interface(access_port)?;
-
-        // Check if the AP is accessible
-        let csw = memory.generic_status()?;
-        if !csw.DeviceEn {
-            *parent = ComponentTreeNode::new(
-                "Memory AP is not accessible, DeviceEn bit not set".to_string(),
-            );
-            return Ok(());
-        }
-
-        let base_address = memory.base_address()?;
-        Component::try_parse(&mut *memory, base_address)?
-    };
-    coresight_component_tree(interface, component, access_port, parent)
+    Ok(dp_info.id.version)
 }
 
-fn coresight_component_tree(
-    interface: &mut dyn ArmDebugInterface,
-    component: Component,
-    access_port: &arm::FullyQualifiedApAddress,
-    parent: &mut ComponentTreeNode,
-) -> anyhow::Result<()> {
-    match &component {
-        Component::GenericVerificationComponent(id) => {
-            parent.push(ComponentTreeNode::new(format!(
-                "{:#06x} Generic",
-                id.component_address()
-            )));
-        }
-        Component::Class1RomTable(id, table) => {
-            let peripheral_id = id.peripheral_id();
-
-            let root = if let Some(part) = peripheral_id.determine_part() {
-                format!("{} (ROM Table, Class 1)", part.name())
-            } else {
-                match peripheral_id.designer() {
-                    Some(designer) => format!("ROM Table (Class 1), Designer: {designer}"),
-                    None => "ROM Table (Class 1)".to_string(),
+impl From<&AccessPortInfo> for ApInfo {
+    fn from(access_port: &AccessPortInfo) -> Self {
+        match access_port {
+            AccessPortInfo::MemoryAp {
+                address,
+                ap_type,
+                state,
+            } => {
+                let ap = address
+                    .ap_v1()
+                    .map(|ap| ap.to_string())
+                    .unwrap_or_else(|_| address.ap().to_string());
+                let mut component_tree =
+                    ComponentTreeNode::new(format!("{ap} MemoryAP ({ap_type:?})"));
+                push_memory_ap_state(state, &mut component_tree);
+
+                ApInfo::MemoryAp {
+                    ap_addr: address.into(),
+                    component_tree,
                 }
-            };
-
-            let mut tree =
-                ComponentTreeNode::new(format!("{:#06x} {}", id.component_address(), root));
-            process_vendor_rom_tables(interface, id, table, access_port, &mut tree)?;
-            parent.push(tree);
-
-            for entry in table.entries() {
-                let component = entry.component().clone();
+            }
+            AccessPortInfo::ApV2Root { component } => {
+                let mut component_tree = ComponentTreeNode::new(String::new());
+                push_component(component, &mut component_tree);
 
-                coresight_component_tree(interface, component, access_port, parent)?;
+                ApInfo::ApV2Root { component_tree }
             }
+            AccessPortInfo::Other { address, idr } => ApInfo::Unknown {
+                ap_addr: address.into(),
+                idr: *idr,
+            },
         }
-        Component::CoresightComponent(id) => {
-            let peripheral_id = id.peripheral_id();
-            let part_info = peripheral_id.determine_part();
-
-            let component_description = if let Some(part_info) = part_info {
-                format!("{: <15} (Coresight Component)", part_info.name())
-            } else {
-                format!(
-                    "Coresight Component, Part: {:#06x}, Devtype: {:#04x}, Archid: {:#06x}, Designer: {}",
-                    peripheral_id.part(),
-                    peripheral_id.dev_type(),
-                    peripheral_id.arch_id(),
-                    peripheral_id.designer().unwrap_or("<unknown>"),
-                )
-            };
-
-            let mut tree = ComponentTreeNode::new(format!(
-                "{:#06x} {}",
-                id.component_address(),
-                component_description
-            ));
-            let is_rom = part_info
-                .map(|p| p.peripheral_type() == PeripheralType::Rom)
-                .unwrap_or(false);
-            process_component_entry(
-                if is_rom { &mut *parent } else { &mut tree },
-                interface,
-                peripheral_id,
-                &component,
-                access_port,
-            )?;
-            parent.push(tree);
-        }
+    }
+}
 
-        Component::PeripheralTestBlock(id) => {
-            parent.push(ComponentTreeNode::new(format!(
-                "{:#06x} Peripheral test block",
-                id.component_address()
-            )));
+impl From<&arm::FullyQualifiedApAddress> for FullyQualifiedApAddress {
+    fn from(address: &arm::FullyQualifiedApAddress) -> Self {
+        Self {
+            dp: address.dp().into(),
+            ap: address.ap().to_string(),
         }
-        Component::GenericIPComponent(id) => {
-            let peripheral_id = id.peripheral_id();
-
-            let desc = if let Some(part_desc) = peripheral_id.determine_part() {
-                format!("{: <15} (Generic IP component)", part_desc.name())
-            } else {
-                "Generic IP component".to_string()
-            };
+    }
+}
 
-            let mut tree = ComponentTreeNode::new(desc);
-            process_component_entry(&mut tree, interface, peripheral_id, &component, access_port)?;
+fn push_memory_ap_state(state: &MemoryApState, parent: &mut ComponentTreeNode) {
+    match state {
+        MemoryApState::Disabled => {
+            *parent = ComponentTreeNode::new(
+                "Memory AP is not accessible, DeviceEn bit not set".to_string(),
+            );
         }
+        MemoryApState::Accessible(component) => push_component(component, parent),
+        MemoryApState::Error(error) => parent.push(format!("Error during access: {error}")),
+    }
+}
 
-        Component::CoreLinkOrPrimeCellOrSystemComponent(id) => {
+fn push_component(component: &ComponentInfo, parent: &mut ComponentTreeNode) {
+    let peripheral_id = component.id.peripheral_id();
+    let part = peripheral_id.determine_part();
+
+    let description = match component.kind {
+        ComponentKind::GenericVerification => "Generic".to_string(),
+        ComponentKind::Class1RomTable => match (part, peripheral_id.designer()) {
+            (Some(part), _) => format!("{} (ROM Table, Class 1)", part.name()),
+            (None, Some(designer)) => format!("ROM Table (Class 1), Designer: {designer}"),
+            (None, None) => "ROM Table (Class 1)".to_string(),
+        },
+        ComponentKind::Class9RomTable => match peripheral_id.designer() {
+            Some(designer) => format!("ROM Table (Class 9), Designer: {designer}"),
+            None => "ROM Table (Class 9)".to_string(),
+        },
+        ComponentKind::CoreSight => match part {
+            Some(part) => format!("{: <15} (Coresight Component)", part.name()),
+            None => format!(
+                "Coresight Component, Part: {:#06x}, Devtype: {:#04x}, Archid: {:#06x}, Designer: {}, PIDR: {:#018x}, DEVARCH: {:#010x}",
+                peripheral_id.part(),
+                peripheral_id.dev_type(),
+                peripheral_id.arch_id(),
+                peripheral_id.designer().unwrap_or("<unknown>"),
+                peripheral_id.raw_pidr(),
+                peripheral_id.raw_devarch(),
+            ),
+        },
+        ComponentKind::PeripheralTestBlock => "Peripheral test block".to_string(),
+        ComponentKind::GenericIp => match part {
+            Some(part) => format!("{: <15} (Generic IP component)", part.name()),
+            None => format!(
+                "Generic IP component, PIDR: {:#018x}",
+                peripheral_id.raw_pidr()
+            ),
+        },
+        ComponentKind::CoreLinkOrPrimeCellOrSystem => {
             let desc = "Core Link / Prime Cell / System component";
-            let desc = if let Some(part_desc) = id.peripheral_id().determine_part() {
-                format!("{: <15} ({})", part_desc.name(), desc)
-            } else {
-                desc.to_string()
-            };
-
-            parent.push(ComponentTreeNode::new(format!(
-                "{:#06x} {}",
-                id.component_address(),
-                desc
-            )));
+            match part {
+                Some(part) => format!("{: <15} ({})", part.name(), desc),
+                None => desc.to_string(),
+            }
         }
     };
 
-    Ok(())
-}
-
-/// Processes information from/around manufacturer-specific ROM tables and adds them to the tree.
-///
-/// Some manufacturer-specific ROM tables contain more than just entries. This function tries
-/// to make sense of these tables.
-fn process_vendor_rom_tables(
-    interface: &mut dyn ArmDebugInterface,
-    id: &ComponentId,
-    _table: &RomTable,
-    access_port: &arm::FullyQualifiedApAddress,
-    tree: &mut ComponentTreeNode,
-) -> anyhow::Result<()> {
-    let peripheral_id = id.peripheral_id();
-    let Some(part_info) = peripheral_id.determine_part() else {
-        return Ok(());
-    };
-
-    if part_info.peripheral_type() == PeripheralType::Custom && part_info.name() == "Atmel DSU" {
-        use probe_rs::vendor::microchip::sequences::atsam::DsuDid;
+    let mut tree = ComponentTreeNode::new(format!(
+        "{:#06x} {}",
+        component.id.component_address(),
+        description
+    ));
 
-        // Read and parse the DID register
-        let did = DsuDid(
-            interface
-                .memory_interface(access_port)?
-                .read_word_32(DsuDid::ADDRESS)?,
-        );
-
-        tree.push(format!("Atmel device (DID = {:#010x})", did.0));
+    if let Some(did) = component.device_id {
+        tree.push(format!("Atmel device (DID = {did:#010x})"));
     }
 
-    Ok(())
-}
-
-/// Processes ROM table entries and adds them to the tree.
-fn process_component_entry(
-    tree: &mut ComponentTreeNode,
-    interface: &mut dyn ArmDebugInterface,
-    peripheral_id: &PeripheralID,
-    component: &Component,
-    access_port: &arm::FullyQualifiedApAddress,
-) -> anyhow::Result<()> {
-    let Some(part) = peripheral_id.determine_part() else {
-        return Ok(());
-    };
-
-    match part.peripheral_type() {
-        PeripheralType::Scs => {
-            let cc = &CoresightComponent::new(component.clone(), access_port.clone());
-            let scs = &mut Scs::new(interface, cc);
-            let cpu_tree = cpu_info_tree(scs)?;
-
-            tree.push(cpu_tree);
-        }
-        PeripheralType::MemAp => {
-            let dp = access_port.dp();
-            let ApAddress::V2(addr) = access_port.ap() else {
-                unreachable!("This should only happen on ap v2 addresses.");
-            };
-            if addr.0.is_some() {
-                return Err(anyhow::anyhow!("Nested memory APs are not yet supported."));
-            }
-            let addr = arm::FullyQualifiedApAddress::v2_with_dp(
-                dp,
-                arm::ApV2Address::new(component.id().component_address()),
-            );
-            handle_memory_ap(interface, &addr, tree)?;
-        }
-        PeripheralType::Rom => {
-            let id = component.id();
-            let mut memory = interface.memory_interface(access_port)?;
-            let rom_table = RomTable::try_parse(
-                memory.as_mut() as &mut dyn ArmMemoryInterface,
-                id.component_address(),
-            )?;
-            drop(memory);
-
-            process_vendor_rom_tables(interface, id, &rom_table, access_port, tree)?;
-            for entry in rom_table.entries() {
-                let component = entry.component().clone();
-
-                coresight_component_tree(interface, component, access_port, tree)?;
-            }
-        }
-        _ => {}
+    if let Some(cpu) = &component.cpu {
+        let mut cpu_tree = ComponentTreeNode::new("CPUID".into());
+        cpu_tree.push(format!("IMPLEMENTER: {}", cpu.implementer));
+        cpu_tree.push(format!("VARIANT: {}", cpu.variant));
+        cpu_tree.push(format!("PARTNO: {}", cpu.part));
+        cpu_tree.push(format!("REVISION: {}", cpu.revision));
+        tree.push(cpu_tree);
     }
 
-    Ok(())
-}
-
-fn cpu_info_tree(scs: &mut Scs) -> anyhow::Result<ComponentTreeNode> {
-    let mut tree = ComponentTreeNode::new("CPUID".into());
-
-    let cpuid = scs.cpuid()?;
+    if let Some(state) = &component.memory_ap {
+        push_memory_ap_state(state, &mut tree);
+    }
 
-    tree.push(format!("IMPLEMENTER: {}", cpuid.implementer_name()));
-    tree.push(format!("VARIANT: {}", cpuid.variant()));
-    tree.push(format!("PARTNO: {}", cpuid.part_name()));
-    tree.push(format!("REVISION: {}", cpuid.revision()));
+    for child in &component.children {
+        push_component(child, &mut tree);
+    }
 
-    Ok(tree)
+    parent.push(tree);
 }
 
 async fn show_riscv_info(
@@ -97,6 +97,26 @@ pub async fn write_memory<W: Word>(
     Ok(())
 }
 
+#[derive(Serialize, Deserialize, Schema)]
+pub struct FillMemoryRequest {
+    pub sessid: Key<Session>,
+    pub core: u32,
+    pub address: u64,
+    pub pattern: u32,
+    pub count: u32,
+}
+
+pub async fn fill_memory(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: FillMemoryRequest,
+) -> NoResponse {
+    let mut session = ctx.session(request.sessid).await;
+    let mut core = session.core(request.core as usize)?;
+    core.fill_32(request.address, request.pattern, request.count as usize)?;
+    Ok(())
+}
+
 #[derive(Serialize, Deserialize, Schema)]
 pub struct ReadMemoryRequest {
     pub sessid: Key<Session>,
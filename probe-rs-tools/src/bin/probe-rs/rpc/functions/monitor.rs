@@ -1,4 +1,4 @@
-use std::{num::NonZeroU32, time::Duration};
+use std::{num::NonZeroU32, ops::Range, path::Path, time::Duration};
 
 use crate::{
     rpc::{
@@ -9,13 +9,19 @@ use crate::{
         },
         utils::run_loop::{ReturnReason, RunLoop, RunLoopPoller},
     },
-    util::rtt::client::RttClient,
+    util::{
+        rtt::client::RttClient,
+        semihosting::{SemihostingFiles, SemihostingHost},
+        snapshot::SnapshotOptions,
+        swo::{ItmDecoder, SwoLogConfig},
+    },
 };
 use anyhow::Context;
 use postcard_rpc::{header::VarHeader, server::Sender};
 use postcard_schema::Schema;
 use probe_rs::{
     BreakpointCause, Core, HaltReason, Session,
+    architecture::arm::component::TraceSink,
     semihosting::{CloseRequest, OpenRequest, SemihostingCommand, WriteRequest},
 };
 use serde::{Deserialize, Serialize};
@@ -53,6 +59,14 @@ pub struct MonitorOptions {
     pub catch_hardfault: bool,
     /// RTT client if used.
     pub rtt_client: Option<Key<RttClient>>,
+    /// Store a snapshot of the core when the firmware halts unexpectedly.
+    pub snapshot: Option<SnapshotOptions>,
+    /// The directory on the host in which the firmware can access files using semihosting.
+    pub semihosting_root: Option<String>,
+    /// Receive the output of the ITM stimulus ports over SWO.
+    pub swo: SwoLogConfig,
+    /// Receive the application trace of ESP-IDF from Xtensa cores.
+    pub apptrace: bool,
 }
 
 /// Monitor in normal run mode.
@@ -112,6 +126,15 @@ pub enum RttEvent {
         channel: u32,
         bytes: Vec<u8>,
     },
+    /// Output of an ITM stimulus port, received over SWO.
+    ItmOutput {
+        port: u8,
+        bytes: Vec<u8>,
+    },
+    /// Output of the application trace of ESP-IDF.
+    AppTraceOutput {
+        bytes: Vec<u8>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Schema)]
@@ -195,6 +218,27 @@ fn monitor_impl(
 
     request.mode.prepare(&mut session, run_loop.core_id)?;
 
+    if let Some(root) = request.options.semihosting_root {
+        let files = SemihostingFiles::new(Path::new(&root))
+            .with_context(|| format!("{root} can not be used for semihosting"))?;
+        semihosting_sink.files = Some(files);
+    }
+
+    if let Some(snapshot) = request.options.snapshot {
+        let ranges = snapshot.memory_ranges(&session);
+        semihosting_sink.snapshot = Some((snapshot, ranges));
+    }
+
+    let itm = match request.options.swo.swo_config()? {
+        Some(config) => {
+            session
+                .setup_tracing(run_loop.core_id, TraceSink::Swo(config))
+                .context("Failed to configure SWO")?;
+            Some(ItmDecoder::new())
+        }
+        None => None,
+    };
+
     let mut core = session.core(run_loop.core_id)?;
     if request.mode.should_clear_rtt_header() {
         if let Some(rtt_client) = rtt_client.as_mut() {
@@ -204,6 +248,8 @@ fn monitor_impl(
 
     let poller = rtt_client.as_deref_mut().map(|client| RttPoller {
         rtt_client: client,
+        itm,
+        apptrace: request.options.apptrace,
         sender: |message| {
             sender
                 .send_rtt_event(message)
@@ -234,6 +280,10 @@ where
     S: 'c,
 {
     pub rtt_client: &'c mut RttClient,
+    /// Decodes the SWO output, if it is received.
+    pub itm: Option<ItmDecoder>,
+    /// Whether the application trace is received.
+    pub apptrace: bool,
     pub sender: S,
 }
 
@@ -284,10 +334,46 @@ where
             }
         }
 
+        if let Some(itm) = self.itm.as_mut() {
+            match core.read_swo()? {
+                Some(data) => {
+                    for (port, bytes) in itm.decode(&data) {
+                        next_poll = Duration::from_millis(1);
+
+                        (self.sender)(RttEvent::ItmOutput { port, bytes })
+                            .with_context(|| "Failed to send ITM output")?;
+                    }
+                }
+                None => {
+                    tracing::warn!("The core does not support SWO, not receiving ITM output");
+                    self.itm = None;
+                }
+            }
+        }
+
+        if self.apptrace {
+            match core.read_apptrace()? {
+                Some(bytes) if bytes.is_empty() => {}
+                Some(bytes) => {
+                    next_poll = Duration::from_millis(1);
+
+                    (self.sender)(RttEvent::AppTraceOutput { bytes })
+                        .with_context(|| "Failed to send apptrace output")?;
+                }
+                None => {
+                    tracing::warn!("The core has no application trace, not receiving it");
+                    self.apptrace = false;
+                }
+            }
+        }
+
         Ok(next_poll)
     }
 
     fn exit(&mut self, core: &mut Core<'_>) -> anyhow::Result<()> {
+        if self.apptrace {
+            core.stop_apptrace()?;
+        }
         self.rtt_client.clean_up(core)?;
         Ok(())
     }
@@ -296,6 +382,11 @@ where
 struct MonitorEventHandler<F: FnMut(SemihostingEvent)> {
     sender: F,
     semihosting_reader: SemihostingReader,
+    /// Where to store a snapshot on an unexpected halt, and the memory ranges to capture.
+    snapshot: Option<(SnapshotOptions, Vec<Range<u64>>)>,
+    /// Files on the host, if the firmware is allowed to access them.
+    files: Option<SemihostingFiles>,
+    host: SemihostingHost,
 }
 
 impl<F: FnMut(SemihostingEvent)> MonitorEventHandler<F> {
@@ -303,7 +394,26 @@ impl<F: FnMut(SemihostingEvent)> MonitorEventHandler<F> {
         Self {
             sender,
             semihosting_reader: SemihostingReader::new(),
+            snapshot: None,
+            files: None,
+            host: SemihostingHost::new(),
+        }
+    }
+
+    /// Builds the exit reason for an unexpected halt, storing a snapshot of the core if enabled.
+    fn unexpected_exit(&self, halt_reason: HaltReason, core: &mut Core<'_>) -> MonitorExitReason {
+        let mut reason = format!("{halt_reason:?}");
+
+        if let Some((snapshot, ranges)) = &self.snapshot {
+            match snapshot.capture(core, ranges.clone(), halt_reason) {
+                Ok(directory) => {
+                    reason.push_str(&format!(" (snapshot stored in {})", directory.display()))
+                }
+                Err(error) => tracing::warn!("Failed to store a snapshot: {error:?}"),
+            }
         }
+
+        MonitorExitReason::UnexpectedExit(reason)
     }
 
     fn handle_halt(
@@ -312,11 +422,19 @@ impl<F: FnMut(SemihostingEvent)> MonitorEventHandler<F> {
         core: &mut Core<'_>,
     ) -> anyhow::Result<Option<MonitorExitReason>> {
         let HaltReason::Breakpoint(BreakpointCause::Semihosting(cmd)) = halt_reason else {
-            return Ok(Some(MonitorExitReason::UnexpectedExit(format!(
-                "{halt_reason:?}"
-            ))));
+            return Ok(Some(self.unexpected_exit(halt_reason, core)));
         };
 
+        if let Some(files) = self.files.as_mut() {
+            if files.handle(cmd, core)? {
+                return Ok(None);
+            }
+        }
+
+        if self.host.handle(cmd, core)? {
+            return Ok(None);
+        }
+
         match cmd {
             SemihostingCommand::ExitSuccess => Ok(Some(MonitorExitReason::SemihostingExit(Ok(())))), // Exit the run loop
             SemihostingCommand::ExitError(details) => Ok(Some(MonitorExitReason::SemihostingExit(
@@ -341,6 +459,17 @@ impl<F: FnMut(SemihostingEvent)> MonitorEventHandler<F> {
                 Ok(None) // Continue running
             }
             SemihostingCommand::Errno(_) => Ok(None),
+            SemihostingCommand::Read(_)
+            | SemihostingCommand::Seek(_)
+            | SemihostingCommand::FileLength(_)
+            | SemihostingCommand::Remove(_)
+            | SemihostingCommand::Rename(_)
+            | SemihostingCommand::TmpNam(_) => {
+                tracing::warn!(
+                    "Target wanted to access a file which is not open, or semihosting file access is not enabled. Continuing..."
+                );
+                Ok(None)
+            }
             other if SemihostingReader::is_io(other) => {
                 if let Some((stream, data)) = self.semihosting_reader.handle(other, core)? {
                     (self.sender)(SemihostingEvent::Output { stream, data });
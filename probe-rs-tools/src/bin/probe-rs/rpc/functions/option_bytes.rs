@@ -0,0 +1,73 @@
+use crate::rpc::{
+    Key,
+    functions::{NoResponse, RpcContext, RpcResult},
+};
+use postcard_rpc::header::VarHeader;
+use postcard_schema::Schema;
+use probe_rs::Session;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Schema)]
+pub struct ReadOptionBytesRequest {
+    pub sessid: Key<Session>,
+}
+
+/// The value of a field of the option bytes.
+#[derive(Serialize, Deserialize, Schema)]
+pub struct OptionByteValue {
+    pub name: String,
+    pub description: Option<String>,
+    pub value: u32,
+    pub bit_width: u8,
+}
+
+pub type ReadOptionBytesResponse = RpcResult<Vec<OptionByteValue>>;
+
+pub async fn read_option_bytes(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: ReadOptionBytesRequest,
+) -> ReadOptionBytesResponse {
+    let mut session = ctx.session(request.sessid).await;
+    let values = session.option_bytes()?.read()?;
+
+    Ok(values
+        .into_iter()
+        .map(|value| OptionByteValue {
+            name: value.name,
+            description: value.description,
+            value: value.value,
+            bit_width: value.bit_width,
+        })
+        .collect())
+}
+
+/// A new value for a field of the option bytes.
+#[derive(Serialize, Deserialize, Schema)]
+pub struct OptionByteWrite {
+    pub name: String,
+    pub value: u32,
+}
+
+#[derive(Serialize, Deserialize, Schema)]
+pub struct WriteOptionBytesRequest {
+    pub sessid: Key<Session>,
+    pub values: Vec<OptionByteWrite>,
+}
+
+pub async fn write_option_bytes(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: WriteOptionBytesRequest,
+) -> NoResponse {
+    let mut session = ctx.session(request.sessid).await;
+
+    let values = request
+        .values
+        .iter()
+        .map(|value| (value.name.as_str(), value.value))
+        .collect::<Vec<_>>();
+    session.option_bytes()?.write(&values)?;
+
+    Ok(())
+}
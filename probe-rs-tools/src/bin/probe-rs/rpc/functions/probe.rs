@@ -8,7 +8,10 @@ use crate::{
         Key,
         functions::{RpcContext, RpcResult},
     },
-    util::common_options::{OperationError, ProbeOptions},
+    util::{
+        common_options::{OperationError, ProbeOptions},
+        hooks::{HookPoint, HookScript, HookTarget},
+    },
 };
 
 use std::fmt::Display;
@@ -26,6 +29,10 @@ pub struct DebugProbeEntry {
     pub serial_number: String,
 
     pub probe_type: String,
+    /// The firmware version of the debug probe, if it was requested and the probe reports it.
+    pub firmware: Option<String>,
+    /// A message which asks the user to update the firmware, if it is outdated.
+    pub firmware_advisory: Option<String>,
 }
 
 impl Display for DebugProbeEntry {
@@ -46,6 +53,8 @@ impl From<DebugProbeInfo> for DebugProbeEntry {
             vendor_id: probe.vendor_id,
             product_id: probe.product_id,
             serial_number: probe.serial_number.unwrap_or_default(),
+            firmware: None,
+            firmware_advisory: None,
         }
     }
 }
@@ -66,6 +75,8 @@ pub struct ListProbesRequest {
     vid: Option<u16>,
     /// Product ID filter.
     pid: Option<u16>,
+    /// Open the probes to read their firmware version.
+    firmware: bool,
 }
 
 impl ListProbesRequest {
@@ -73,8 +84,13 @@ impl ListProbesRequest {
         Self {
             vid: None,
             pid: None,
+            firmware: false,
         }
     }
+
+    pub fn with_firmware(self, firmware: bool) -> Self {
+        Self { firmware, ..self }
+    }
 }
 
 pub type ListProbesResponse = RpcResult<Vec<DebugProbeEntry>>;
@@ -82,14 +98,30 @@ pub type ListProbesResponse = RpcResult<Vec<DebugProbeEntry>>;
 pub async fn list_probes(
     ctx: &mut RpcContext,
     _header: VarHeader,
-    _request: ListProbesRequest,
+    request: ListProbesRequest,
 ) -> ListProbesResponse {
     let lister = ctx.lister();
     let probes = lister.list_all().await;
 
     Ok(probes
         .into_iter()
-        .map(DebugProbeEntry::from)
+        .map(|probe| {
+            let firmware = if request.firmware {
+                probe.read_firmware_info().unwrap_or_else(|error| {
+                    tracing::warn!("Failed to read the firmware version of {probe}: {error}");
+                    None
+                })
+            } else {
+                None
+            };
+
+            let mut entry = DebugProbeEntry::from(probe);
+            if let Some(firmware) = firmware {
+                entry.firmware = Some(firmware.to_string());
+                entry.firmware_advisory = firmware.update_advisory();
+            }
+            entry
+        })
         .collect::<Vec<_>>())
 }
 
@@ -161,6 +193,33 @@ impl From<probe_rs::probe::WireProtocol> for WireProtocol {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Schema)]
+pub enum MemoryMapPolicy {
+    Allow,
+    Warn,
+    Enforce,
+}
+
+impl From<MemoryMapPolicy> for probe_rs::MemoryMapPolicy {
+    fn from(policy: MemoryMapPolicy) -> Self {
+        match policy {
+            MemoryMapPolicy::Allow => probe_rs::MemoryMapPolicy::Allow,
+            MemoryMapPolicy::Warn => probe_rs::MemoryMapPolicy::Warn,
+            MemoryMapPolicy::Enforce => probe_rs::MemoryMapPolicy::Enforce,
+        }
+    }
+}
+
+impl From<probe_rs::MemoryMapPolicy> for MemoryMapPolicy {
+    fn from(policy: probe_rs::MemoryMapPolicy) -> Self {
+        match policy {
+            probe_rs::MemoryMapPolicy::Allow => MemoryMapPolicy::Allow,
+            probe_rs::MemoryMapPolicy::Warn => MemoryMapPolicy::Warn,
+            probe_rs::MemoryMapPolicy::Enforce => MemoryMapPolicy::Enforce,
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Schema)]
 pub struct DebugProbeSelector {
     /// The the USB vendor id of the debug probe to be used.
@@ -200,7 +259,16 @@ pub struct AttachRequest {
     pub connect_under_reset: bool,
     pub dry_run: bool,
     pub allow_erase_all: bool,
+    pub no_debug_freeze: bool,
+    pub memory_map: MemoryMapPolicy,
+    pub reconnect_on_power_down: Option<u32>,
     pub resume_target: bool,
+    /// The source of the hook script to run on the server.
+    pub hook_script: Option<String>,
+    /// The file on the server to record the transactions of the probe to.
+    pub record_trace: Option<String>,
+    /// The file on the server to log the SWD transfers of the probe to.
+    pub record_swd_trace: Option<String>,
 }
 
 impl From<&AttachRequest> for ProbeOptions {
@@ -212,9 +280,16 @@ impl From<&AttachRequest> for ProbeOptions {
             non_interactive: true,
             probe: Some(request.probe.selector().into()),
             speed: request.speed,
+            speed_floor: None,
+            hooks: None,
+            record_trace: request.record_trace.as_ref().map(Into::into),
+            record_swd_trace: request.record_swd_trace.as_ref().map(Into::into),
             connect_under_reset: request.connect_under_reset,
             dry_run: request.dry_run,
             allow_erase_all: request.allow_erase_all,
+            no_debug_freeze: request.no_debug_freeze,
+            memory_map: request.memory_map.into(),
+            reconnect_on_power_down: request.reconnect_on_power_down,
         }
     }
 }
@@ -230,7 +305,9 @@ pub async fn attach(
     let common_options = ProbeOptions::from(&request).load(&mut registry)?;
     let target = common_options.get_target_selector()?;
 
-    let probe = match common_options.attach_probe(&ctx.lister()).await {
+    let hooks = request.hook_script.map(HookScript::new).transpose()?;
+
+    let mut probe = match common_options.attach_probe(&ctx.lister()).await {
         Ok(probe) => probe,
         Err(OperationError::NoProbesFound) => return Ok(AttachResult::ProbeNotFound),
         Err(error) => {
@@ -241,13 +318,21 @@ pub async fn attach(
         }
     };
 
-    let mut session = common_options.attach_session(probe, target)?;
+    if let Some(hooks) = &hooks {
+        hooks.run(HookPoint::PreAttach, HookTarget::Probe(&mut probe))?;
+    }
+
+    let mut session = common_options
+        .attach_session_with_speed_negotiation(&ctx.lister(), probe, target)
+        .await?;
 
     // attach_session halts the target, let's give the user the option
     // to resume it without a roundtrip
     if request.resume_target {
         session.resume_all_cores()?;
     }
-    let session_id = ctx.set_session(session, common_options.dry_run()).await;
+    let session_id = ctx
+        .set_session(session, common_options.dry_run(), hooks)
+        .await;
     Ok(AttachResult::Success(session_id))
 }
@@ -1,6 +1,9 @@
-use crate::rpc::{
-    Key,
-    functions::{NoResponse, RpcContext},
+use crate::{
+    rpc::{
+        Key,
+        functions::{NoResponse, RpcContext},
+    },
+    util::hooks::{HookPoint, HookTarget},
 };
 use postcard_rpc::header::VarHeader;
 use postcard_schema::Schema;
@@ -18,8 +21,19 @@ pub async fn reset(
     _header: VarHeader,
     request: ResetCoreRequest,
 ) -> NoResponse {
+    let hooks = ctx.hooks(request.sessid);
     let mut session = ctx.session(request.sessid).await;
-    let mut core = session.core(request.core as usize)?;
-    core.reset()?;
+    session.core(request.core as usize)?.reset()?;
+
+    if let Some(hooks) = hooks {
+        hooks.run(
+            HookPoint::PostReset,
+            HookTarget::Session {
+                session: &mut session,
+                core: request.core as usize,
+            },
+        )?;
+    }
+
     Ok(())
 }
@@ -3,7 +3,7 @@ use crate::{
         Key,
         functions::{RpcContext, RpcResult},
     },
-    util::rtt::{RttChannelConfig, RttConfig, client::RttClient},
+    util::rtt::{RttConfig, client::RttClient},
 };
 use postcard_rpc::header::VarHeader;
 use postcard_schema::Schema;
@@ -38,8 +38,8 @@ pub struct CreateRttClientRequest {
     /// Scan the memory to find the RTT control block
     pub scan_regions: ScanRegion,
 
-    /// Channel configuration.
-    pub config: Vec<RttChannelConfig>,
+    /// RTT configuration.
+    pub config: RttConfig,
 }
 
 #[derive(Serialize, Deserialize, Schema)]
@@ -68,7 +68,7 @@ pub async fn create_rtt_client(
     let client = RttClient::new(
         RttConfig {
             enabled: true,
-            channels: request.config,
+            ..request.config
         },
         rtt_scan_regions,
         session.target(),
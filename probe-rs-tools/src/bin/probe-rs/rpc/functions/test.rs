@@ -16,7 +16,7 @@ use crate::{
         },
         utils::run_loop::{ReturnReason, RunLoop},
     },
-    util::rtt::client::RttClient,
+    util::{rtt::client::RttClient, semihosting::SemihostingHost},
 };
 
 #[derive(Debug, Serialize, Deserialize, Schema)]
@@ -152,6 +152,8 @@ fn list_tests_impl(
 
     let poller = rtt_client.as_deref_mut().map(|client| RttPoller {
         rtt_client: client,
+        itm: None,
+        apptrace: false,
         sender: |message| {
             sender
                 .send_rtt_event(message)
@@ -244,6 +246,8 @@ fn run_test_impl(
 
     let poller = rtt_client.as_deref_mut().map(|client| RttPoller {
         rtt_client: client,
+        itm: None,
+        apptrace: false,
         sender: |message| {
             sender
                 .send_rtt_event(message)
@@ -352,6 +356,7 @@ fn read_test_list(
 
 struct RunEventHandler<F: FnMut(SemihostingEvent)> {
     semihosting_reader: SemihostingReader,
+    host: SemihostingHost,
     cmdline_requested: bool,
     test: Test,
     sender: F,
@@ -362,6 +367,7 @@ impl<F: FnMut(SemihostingEvent)> RunEventHandler<F> {
         Self {
             test,
             semihosting_reader: SemihostingReader::new(),
+            host: SemihostingHost::new(),
             cmdline_requested: false,
             sender,
         }
@@ -380,6 +386,10 @@ impl<F: FnMut(SemihostingEvent)> RunEventHandler<F> {
             ),
         };
 
+        if self.host.handle(cmd, core)? {
+            return Ok(None);
+        }
+
         match cmd {
             SemihostingCommand::GetCommandLine(request) if !self.cmdline_requested => {
                 let cmdline = format!("run {}", self.test.name);
@@ -14,6 +14,9 @@ use postcard_schema::{
     schema::{DataModelType, NamedType, NamedValue},
 };
 use probe_rs::{Session, config::Registry};
+
+use crate::util::hooks::HookScript;
+use crate::util::settings::Settings;
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 
@@ -122,6 +125,7 @@ impl ObjectStorage {
 #[derive(Clone)]
 pub struct SessionState {
     dry_run: bool,
+    hooks: Option<HookScript>,
     object_storage: Arc<Mutex<ObjectStorage>>,
     registry: Arc<Mutex<Registry>>,
 }
@@ -130,8 +134,9 @@ impl SessionState {
     pub fn new() -> Self {
         Self {
             dry_run: false,
+            hooks: None,
             object_storage: Arc::new(Mutex::new(ObjectStorage::new())),
-            registry: Arc::new(Mutex::new(Registry::from_builtin_families())),
+            registry: Arc::new(Mutex::new(Settings::load().registry())),
         }
     }
 
@@ -153,9 +158,15 @@ impl SessionState {
         self.object_storage.blocking_lock().object_mut_blocking(key)
     }
 
-    pub async fn set_session(&mut self, session: Session, dry_run: bool) -> Key<Session> {
+    pub async fn set_session(
+        &mut self,
+        session: Session,
+        dry_run: bool,
+        hooks: Option<HookScript>,
+    ) -> Key<Session> {
         let key = self.store_object(session).await;
         self.dry_run = dry_run;
+        self.hooks = hooks;
         key
     }
 
@@ -169,4 +180,8 @@ impl SessionState {
     pub fn dry_run(&self, _sid: Key<Session>) -> bool {
         self.dry_run
     }
+
+    pub fn hooks(&self, _sid: Key<Session>) -> Option<HookScript> {
+        self.hooks.clone()
+    }
 }
@@ -1,12 +1,13 @@
 //! CLI-specific building blocks.
 
-use std::{future::Future, ops::DerefMut, path::Path, time::Instant};
+use std::{collections::BTreeMap, future::Future, ops::DerefMut, path::Path, time::Instant};
 
 use anyhow::Context;
 use colored::Colorize;
 use libtest_mimic::{Failed, Trial};
 use postcard_rpc::host_client::HostClient;
 use postcard_schema::Schema;
+use probe_rs::rtt::decoder::{DefmtDecoder, DefmtTable};
 use serde::de::DeserializeOwned;
 use time::UtcOffset;
 use tokio::io::AsyncWriteExt;
@@ -34,10 +35,11 @@ use crate::{
     util::{
         common_options::{BinaryDownloadOptions, ProbeOptions},
         flash::CliProgressBars,
+        junit::{TestOutcome, TestRecord, TestReport},
         logging,
         rtt::{
-            self, DefmtProcessor, DefmtState, RttDataHandler, RttDecoder, RttSymbolError,
-            client::RttClient,
+            self, RttConfig, RttDataHandler, RttDecoder, RttSymbolError, client::RttClient,
+            sinks::RttSinks,
         },
     },
 };
@@ -67,7 +69,15 @@ pub async fn attach_probe(
         client.load_chip_family(file).await?;
     }
 
-    let probe = select_probe(client, probe_options.probe.map(Into::into)).await?;
+    let hook_script =
+        match probe_options.hooks.take() {
+            Some(path) => Some(tokio::fs::read_to_string(&path).await.with_context(|| {
+                format!("Failed to read the hook script from {}", path.display())
+            })?),
+            None => None,
+        };
+
+    let probe = select_probe(client, probe_options.probe).await?;
 
     let result = client
         .attach_probe(AttachRequest {
@@ -78,7 +88,17 @@ pub async fn attach_probe(
             connect_under_reset: probe_options.connect_under_reset,
             dry_run: probe_options.dry_run,
             allow_erase_all: probe_options.allow_erase_all,
+            no_debug_freeze: probe_options.no_debug_freeze,
+            memory_map: probe_options.memory_map.into(),
+            reconnect_on_power_down: probe_options.reconnect_on_power_down,
             resume_target,
+            hook_script,
+            record_trace: probe_options
+                .record_trace
+                .map(|path| path.display().to_string()),
+            record_swd_trace: probe_options
+                .record_swd_trace
+                .map(|path| path.display().to_string()),
         })
         .await?;
 
@@ -92,12 +112,25 @@ pub async fn attach_probe(
 
 pub async fn select_probe(
     client: &RpcClient,
-    probe: Option<DebugProbeSelector>,
+    probe: Option<probe_rs::probe::DebugProbeSelector>,
 ) -> anyhow::Result<DebugProbeEntry> {
     use anyhow::Context as _;
     use std::io::Write as _;
 
-    match client.select_probe(probe).await? {
+    // The client is connected to the host of a `remote://` selector, which selects one of its own
+    // probes.
+    let probe = match probe {
+        Some(probe) => match probe.remote() {
+            Some((_, probe)) => probe,
+            None => Some(probe),
+        },
+        None => None,
+    };
+
+    match client
+        .select_probe(probe.map(DebugProbeSelector::from))
+        .await?
+    {
         SelectProbeResult::Success(probe) => Ok(probe),
         SelectProbeResult::MultipleProbes(list) => {
             println!("Available Probes:");
@@ -155,6 +188,8 @@ pub(crate) enum ChannelIdentifier {
     Rtt(String),
     /// A named semihosting channel
     Semihosting(String),
+    /// An ITM stimulus port, received over SWO
+    Itm(String),
     /// Selector that matches any channel; depending on the context, this usually means "any
     /// channel that is not explicitly handled".
     CatchAll,
@@ -170,8 +205,9 @@ impl std::str::FromStr for ChannelIdentifier {
             [unqualified] => ChannelIdentifier::Unqualified(unqualified.to_string()),
             ["rtt", rtt] => ChannelIdentifier::Rtt(rtt.to_string()),
             ["semihosting", semihosting] => ChannelIdentifier::Semihosting(semihosting.to_string()),
+            ["itm", port] => ChannelIdentifier::Itm(port.to_string()),
             _ => anyhow::bail!(
-                "Channel identifiers with colons need to be qualified as `rtt:name` or `semihosting:name`."
+                "Channel identifiers with colons need to be qualified as `rtt:name`, `semihosting:name` or `itm:port`."
             ),
         })
     }
@@ -183,6 +219,7 @@ impl ChannelIdentifier {
         match self {
             ChannelIdentifier::Rtt(n) => Some(ChannelIdentifier::Unqualified(n.clone())),
             ChannelIdentifier::Semihosting(n) => Some(ChannelIdentifier::Unqualified(n.clone())),
+            ChannelIdentifier::Itm(n) => Some(ChannelIdentifier::Unqualified(n.clone())),
             _ => None,
         }
     }
@@ -251,6 +288,7 @@ pub async fn rtt_client(
     log_format: Option<String>,
     show_location: bool,
     timestamp_offset: Option<UtcOffset>,
+    config: RttConfig,
 ) -> anyhow::Result<CliRttClient> {
     let elf = tokio::fs::read(path)
         .await
@@ -270,14 +308,14 @@ pub async fn rtt_client(
     }
 
     let defmt_data = if load_defmt_data {
-        DefmtState::try_from_bytes(&elf)?
+        DefmtTable::try_from_bytes(&elf)?
     } else {
         None
     };
 
     // We don't really know what to configure here, so we just use the defaults: Defmt channels
     // will be set to BlockIfFull, others will not be changed.
-    let rtt_client = session.create_rtt_client(scan_regions, vec![]).await?;
+    let rtt_client = session.create_rtt_client(scan_regions, config).await?;
 
     // The actual data processor objects will be created once we have the channel names.
     Ok(CliRttClient {
@@ -285,8 +323,12 @@ pub async fn rtt_client(
         timestamp_offset,
         show_location,
         channel_processors: vec![],
+        itm_processors: BTreeMap::new(),
+        itm_defmt_port: None,
+        apptrace_processor: None,
         defmt_data,
         log_format,
+        sinks: RttSinks::default(),
     })
 }
 
@@ -307,6 +349,9 @@ pub async fn flash(
         skip_erase: false,
         verify: download_options.verify,
         disable_double_buffering: download_options.disable_double_buffering,
+        incremental: download_options.incremental,
+        resume: download_options.resume,
+        watchdog: download_options.watchdog.into(),
     };
 
     let loader = session
@@ -427,6 +472,15 @@ impl MultiSubscription for MonitorSubscription {
     }
 }
 
+/// The semihosting exit reason `ADP_Stopped_ApplicationExit`, the only one which carries the exit
+/// code of the firmware.
+const ADP_STOPPED_APPLICATION_EXIT: u32 = 0x20026;
+
+/// The firmware exited with a non-zero exit code, which becomes the exit status of probe-rs.
+#[derive(Debug, thiserror::Error)]
+#[error("Firmware exited with exit code {0}")]
+pub struct FirmwareExitCode(pub i32);
+
 pub async fn monitor(
     session: &SessionInterface,
     mode: MonitorMode,
@@ -476,7 +530,7 @@ pub async fn monitor(
                 0x20023 => String::from("Unknown runtime error"),
                 0x20024 => String::from("Internal error"),
                 0x20025 => String::from("User interruption"),
-                0x20026 => String::from("Application exit"),
+                ADP_STOPPED_APPLICATION_EXIT => String::from("Application exit"),
                 0x20027 => String::from("Stack overflow"),
                 0x20028 => String::from("Division by zero"),
                 0x20029 => String::from("OS specific error"),
@@ -484,7 +538,7 @@ pub async fn monitor(
             };
 
             let subcode = match details.reason {
-                0x20026 => match details.subcode {
+                ADP_STOPPED_APPLICATION_EXIT => match details.subcode {
                     Some(134) => String::from(" (Aborted)"),
                     Some(other) => format!(" (Unknown exit code {other})"),
                     None => String::from(""),
@@ -503,9 +557,20 @@ pub async fn monitor(
         display_stack_trace(session, path).await?;
     }
 
-    result.map(|_| ())
+    match result? {
+        MonitorExitReason::SemihostingExit(Err(details)) => {
+            // Only an application exit carries the exit code of the firmware.
+            let code = match (details.reason, details.subcode) {
+                (ADP_STOPPED_APPLICATION_EXIT, Some(code)) => code as i32,
+                _ => 1,
+            };
+            Err(FirmwareExitCode(code).into())
+        }
+        _ => Ok(()),
+    }
 }
 
+#[expect(clippy::too_many_arguments)]
 pub async fn test(
     session: &SessionInterface,
     boot_info: BootInfo,
@@ -514,6 +579,7 @@ pub async fn test(
     path: &Path,
     mut rtt_client: Option<CliRttClient>,
     target_output_files: &mut TargetOutputFiles,
+    report: Option<TestReport>,
 ) -> anyhow::Result<()> {
     tracing::info!("libtest args {:?}", libtest_args);
     let token = CancellationToken::new();
@@ -530,14 +596,41 @@ pub async fn test(
             return Ok(());
         }
 
+        // Ignored tests don't run, but are still listed in the report.
+        let ignored = tests
+            .tests
+            .iter()
+            .filter(|test| test.ignored && is_selected(&libtest_args, &test.name))
+            .map(|test| test.name.clone())
+            .collect::<Vec<_>>();
+
         let tests = tests
             .tests
             .into_iter()
-            .map(|test| create_trial(session, path, rtt_handle, sender.clone(), &token, test))
+            .map(|test| {
+                create_trial(
+                    session,
+                    path,
+                    rtt_handle,
+                    sender.clone(),
+                    &token,
+                    test,
+                    report.clone(),
+                )
+            })
             .collect::<Vec<_>>();
 
+        let report = report.clone();
         tokio::task::spawn_blocking(move || {
-            if libtest_mimic::run(&libtest_args, tests).has_failed() {
+            let conclusion = libtest_mimic::run(&libtest_args, tests);
+
+            if let Some(report) = report {
+                for name in ignored {
+                    report.record_ignored(&name);
+                }
+            }
+
+            if conclusion.has_failed() {
                 anyhow::bail!("Some tests failed");
             }
 
@@ -580,6 +673,7 @@ fn create_trial(
     sender: UnboundedSender<MonitorEvent>,
     token: &CancellationToken,
     test: Test,
+    report: Option<TestReport>,
 ) -> Trial {
     let name = test.name.clone();
     let ignored = test.ignored;
@@ -596,24 +690,40 @@ fn create_trial(
             }
 
             let handle = tokio::spawn(async move {
-                match session
+                let name = test.name.clone();
+                let started = Instant::now();
+
+                let outcome = match session
                     .run_test(test, rtt_client, async move |msg| sender.send(msg).unwrap())
                     .await
                 {
-                    Ok(TestResult::Success) => Ok(()),
+                    Ok(TestResult::Success) => TestOutcome::Passed,
                     Ok(TestResult::Cancelled) => {
                         eprintln!("Cancelled");
                         std::process::exit(0);
                     }
-                    Ok(TestResult::Failed(message)) => {
-                        display_stack_trace(&session, &path).await?;
-
-                        Err(Failed::from(message))
-                    }
+                    Ok(TestResult::Failed(message)) => TestOutcome::Failed(message),
                     Err(e) => {
                         eprintln!("Error: {e:?}");
                         std::process::exit(1);
                     }
+                };
+
+                // Record the result first, so it is reported even if the stack trace fails.
+                if let Some(report) = report {
+                    report.record(TestRecord {
+                        name,
+                        duration: started.elapsed(),
+                        outcome: outcome.clone(),
+                    });
+                }
+
+                match outcome {
+                    TestOutcome::Failed(message) => {
+                        display_stack_trace(&session, &path).await?;
+                        Err(Failed::from(message))
+                    }
+                    _ => Ok(()),
                 }
             });
 
@@ -623,6 +733,19 @@ fn create_trial(
     .with_ignored_flag(ignored)
 }
 
+/// Whether libtest selects the test `name` with the filters in `args`.
+fn is_selected(args: &libtest_mimic::Arguments, name: &str) -> bool {
+    let matches = |pattern: &String| {
+        if args.exact {
+            name == pattern
+        } else {
+            name.contains(pattern.as_str())
+        }
+    };
+
+    args.filter.as_ref().is_none_or(matches) && !args.skip.iter().any(matches)
+}
+
 async fn display_stack_trace(session: &SessionInterface, path: &Path) -> anyhow::Result<()> {
     let stack_trace = session.stack_trace(path.to_path_buf()).await?;
 
@@ -661,12 +784,21 @@ where
 pub struct CliRttClient {
     handle: Key<RttClient>,
     channel_processors: Vec<Channel>,
+    /// The processors of the ITM stimulus ports that received data, by port.
+    itm_processors: BTreeMap<u8, Channel>,
+    /// The ITM stimulus port that carries defmt data.
+    itm_defmt_port: Option<u8>,
+    /// The processor of the application trace, once it received data.
+    apptrace_processor: Option<Channel>,
 
     // Data necessary to create the channel processors once we know the channel names.
     log_format: Option<String>,
     show_location: bool,
     timestamp_offset: Option<UtcOffset>,
-    defmt_data: Option<DefmtState>,
+    defmt_data: Option<DefmtTable>,
+
+    /// Where the decoded output is copied to.
+    sinks: RttSinks,
 }
 
 impl CliRttClient {
@@ -674,6 +806,49 @@ impl CliRttClient {
         self.handle
     }
 
+    /// Copies the decoded output of all channels to `sinks`.
+    pub fn log_to(&mut self, sinks: RttSinks) {
+        self.sinks = sinks;
+    }
+
+    /// Decodes the output of ITM stimulus port `port` as defmt.
+    pub fn set_itm_defmt_port(&mut self, port: Option<u8>) {
+        self.itm_defmt_port = port;
+    }
+
+    fn open_itm_channel(&mut self, port: u8) {
+        if self.itm_processors.contains_key(&port) {
+            return;
+        }
+
+        let decoder = match self.defmt_data.clone() {
+            Some(defmt_data) if self.itm_defmt_port == Some(port) => RttDecoder::Defmt {
+                processor: DefmtDecoder::new(
+                    defmt_data,
+                    self.timestamp_offset.is_some(),
+                    self.show_location,
+                    self.log_format.as_deref(),
+                ),
+            },
+            _ => RttDecoder::string(self.timestamp_offset),
+        };
+
+        let mut channel = Channel::new(format!("itm:{port}"), decoder);
+        channel.print_channel_name(0);
+        self.itm_processors.insert(port, channel);
+    }
+
+    fn open_apptrace_channel(&mut self) {
+        if self.apptrace_processor.is_some() {
+            return;
+        }
+
+        let decoder = RttDecoder::string(self.timestamp_offset);
+        let mut channel = Channel::new(String::from("apptrace"), decoder);
+        channel.print_channel_name(0);
+        self.apptrace_processor = Some(channel);
+    }
+
     fn on_channels_discovered(&mut self, up_channels: &[String]) {
         // Already configured.
         if !self.channel_processors.is_empty() {
@@ -686,7 +861,7 @@ impl CliRttClient {
                 if channel == "defmt" || (self.defmt_data.is_some() && up_channels.len() == 1) {
                     if let Some(defmt_data) = self.defmt_data.clone() {
                         RttDecoder::Defmt {
-                            processor: DefmtProcessor::new(
+                            processor: DefmtDecoder::new(
                                 defmt_data,
                                 self.timestamp_offset.is_some(),
                                 self.show_location,
@@ -698,10 +873,7 @@ impl CliRttClient {
                         RttDecoder::BinaryLE
                     }
                 } else {
-                    RttDecoder::String {
-                        timestamp_offset: self.timestamp_offset,
-                        last_line_done: false,
-                    }
+                    RttDecoder::string(self.timestamp_offset)
                 };
 
             self.channel_processors
@@ -737,7 +909,12 @@ async fn print_monitor_event(
             };
 
             let channel = channel as usize;
-            let Some(processor) = client.channel_processors.get_mut(channel) else {
+            let CliRttClient {
+                channel_processors,
+                sinks,
+                ..
+            } = &mut **client;
+            let Some(processor) = channel_processors.get_mut(channel) else {
                 return;
             };
 
@@ -749,6 +926,54 @@ async fn print_monitor_event(
                     // matter, and again, ease of maintenance beats theoretical performance unless
                     // benchmarked otherwise.
                     ChannelIdentifier::Rtt(processor.channel.clone()).find_in(target_output_files),
+                    sinks,
+                )
+                .await;
+        }
+        MonitorEvent::Rtt(RttEvent::ItmOutput { port, bytes }) => {
+            let Some(client) = rtt_client else {
+                return;
+            };
+
+            client.open_itm_channel(port);
+            let CliRttClient {
+                itm_processors,
+                sinks,
+                ..
+            } = &mut **client;
+            let Some(processor) = itm_processors.get_mut(&port) else {
+                return;
+            };
+
+            processor
+                .process(
+                    &bytes,
+                    ChannelIdentifier::Itm(port.to_string()).find_in(target_output_files),
+                    sinks,
+                )
+                .await;
+        }
+        MonitorEvent::Rtt(RttEvent::AppTraceOutput { bytes }) => {
+            let Some(client) = rtt_client else {
+                return;
+            };
+
+            client.open_apptrace_channel();
+            let CliRttClient {
+                apptrace_processor: Some(processor),
+                sinks,
+                ..
+            } = &mut **client
+            else {
+                return;
+            };
+
+            processor
+                .process(
+                    &bytes,
+                    ChannelIdentifier::Unqualified(String::from("apptrace"))
+                        .find_in(target_output_files),
+                    sinks,
                 )
                 .await;
         }
@@ -788,22 +1013,47 @@ impl Channel {
         self.printer_prefix = format!("[{:width$}] ", self.channel, width = width);
     }
 
-    async fn process(&mut self, bytes: &[u8], copy_to: Option<&mut tokio::fs::File>) {
+    async fn process(
+        &mut self,
+        bytes: &[u8],
+        copy_to: Option<&mut tokio::fs::File>,
+        sinks: &mut RttSinks,
+    ) {
         let mut printer = Printer {
+            channel: &self.channel,
             prefix: &self.printer_prefix,
             copy_to,
+            sinks,
         };
         let _ = self.decoder.process(bytes, &mut printer).await;
     }
 }
 
 struct Printer<'a> {
+    channel: &'a str,
     prefix: &'a str,
     copy_to: Option<&'a mut tokio::fs::File>,
+    sinks: &'a mut RttSinks,
 }
 impl RttDataHandler for Printer<'_> {
     async fn on_string_data(&mut self, data: String) -> Result<(), probe_rs::rtt::Error> {
         print!("{}{}", self.prefix, data);
+        self.sinks.write(self.channel, &data);
+        if let Some(copy_to) = &mut self.copy_to {
+            // Silently discarding output file errors
+            _ = copy_to.write_all(data.as_bytes()).await;
+        }
+        Ok(())
+    }
+
+    async fn on_terminal_data(
+        &mut self,
+        terminal: u8,
+        data: String,
+    ) -> Result<(), probe_rs::rtt::Error> {
+        print!("{}[terminal {terminal}] {}", self.prefix, data);
+        self.sinks
+            .write(&format!("{} terminal {terminal}", self.channel), &data);
         if let Some(copy_to) = &mut self.copy_to {
             // Silently discarding output file errors
             _ = copy_to.write_all(data.as_bytes()).await;
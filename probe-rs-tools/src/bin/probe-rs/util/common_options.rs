@@ -1,18 +1,27 @@
-use std::{io::Write, path::PathBuf};
+use std::{fs::File, io::Write, path::PathBuf, time::Duration};
 
 use super::cargo::ArtifactError;
+use super::settings::Settings;
 use crate::util::parse_u64;
 use probe_rs::{
-    Permissions, Session, Target,
+    MemoryMapPolicy, Permissions, PowerDownRecovery, Session, Target,
     config::{Registry, RegistryError, TargetSelector},
-    flashing::{FileDownloadError, FlashError},
+    flashing::{FileDownloadError, FlashError, WatchdogMode},
     integration::FakeProbe,
     probe::{
         DebugProbeError, DebugProbeInfo, DebugProbeSelector, Probe, WireProtocol, list::Lister,
+        swd_trace::SwdTraceRecorder, trace::TraceRecorder,
     },
 };
 use serde::{Deserialize, Serialize};
 
+/// The lowest protocol speed used when lowering the speed on an unstable connection, unless
+/// configured otherwise.
+const DEFAULT_SPEED_FLOOR_KHZ: u32 = 100;
+
+/// The time to wait between two attempts to reconnect to a target in a low-power mode.
+const POWER_DOWN_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+
 /// Common options when flashing a target device.
 #[derive(Debug, clap::Parser)]
 pub struct BinaryDownloadOptions {
@@ -35,9 +44,26 @@ pub struct BinaryDownloadOptions {
     /// Before flashing, read back all the flashed data to skip flashing if the device is up to date.
     #[arg(long, help_heading = "DOWNLOAD CONFIGURATION")]
     pub preverify: bool,
+    /// Before flashing, read back the flash contents and only erase and program the sectors that changed.
+    #[arg(long, help_heading = "DOWNLOAD CONFIGURATION")]
+    pub incremental: bool,
+    /// Record the progress of the download, and resume an interrupted download of the same image
+    /// which was also started with `--resume`, skipping the sectors it already programmed.
+    #[arg(long, help_heading = "DOWNLOAD CONFIGURATION")]
+    pub resume: bool,
     /// After flashing, read back all the flashed data to verify it has been written correctly.
     #[arg(long, help_heading = "DOWNLOAD CONFIGURATION")]
     pub verify: bool,
+    /// How the watchdogs of the target are handled while flashing: 'ignore' leaves them alone,
+    /// 'refresh' refreshes them between flash operations, 'disable' disables them. A refreshed
+    /// watchdog can still reset the target during a single long operation, like a chip erase.
+    #[arg(
+        long,
+        default_value = "ignore",
+        value_name = "ignore|refresh|disable",
+        help_heading = "DOWNLOAD CONFIGURATION"
+    )]
+    pub watchdog: WatchdogMode,
 }
 
 /// Supported bit-widths for read/write commands (not every device may support each width).
@@ -94,11 +120,22 @@ pub struct ProbeOptions {
     ///
     /// Use '--probe VID:PID' or '--probe VID:PID:Serial' if you have more than one
     /// probe with the same VID:PID.",
+    ///
+    /// Use '--probe remote://host:port/VID:PID' to select a probe attached to a host
+    /// running `probe-rs serve`.
     #[arg(long, env = "PROBE_RS_PROBE", help_heading = "PROBE CONFIGURATION")]
     pub probe: Option<DebugProbeSelector>,
     /// The protocol speed in kHz.
     #[arg(long, env = "PROBE_RS_SPEED", help_heading = "PROBE CONFIGURATION")]
     pub speed: Option<u32>,
+    /// The lowest protocol speed in kHz used when the speed is lowered automatically because
+    /// of communication errors.
+    #[arg(
+        long,
+        env = "PROBE_RS_SPEED_FLOOR",
+        help_heading = "PROBE CONFIGURATION"
+    )]
+    pub speed_floor: Option<u32>,
     /// Use this flag to assert the nreset & ntrst pins during attaching the probe to
     /// the chip.
     #[arg(
@@ -117,6 +154,59 @@ pub struct ProbeOptions {
         help_heading = "PROBE CONFIGURATION"
     )]
     pub allow_erase_all: bool,
+    /// Keep the peripherals, like the timers and watchdogs, running while a core is halted,
+    /// instead of stopping them with the debug freeze bits of the target.
+    #[arg(
+        long,
+        env = "PROBE_RS_NO_DEBUG_FREEZE",
+        help_heading = "PROBE CONFIGURATION"
+    )]
+    pub no_debug_freeze: bool,
+    /// How memory accesses outside the memory map of the target description are handled:
+    /// 'allow' performs them, 'warn' performs them and logs a warning, 'enforce' rejects them.
+    #[arg(
+        long,
+        default_value = "allow",
+        env = "PROBE_RS_MEMORY_MAP",
+        help_heading = "PROBE CONFIGURATION"
+    )]
+    pub memory_map: MemoryMapPolicy,
+    /// Reconnect up to this many times when the target enters a low-power mode which powers down
+    /// its debug logic, instead of failing. The debug state of the cores, like the hardware
+    /// breakpoints, is restored after reconnecting.
+    #[arg(
+        value_name = "attempts",
+        long,
+        env = "PROBE_RS_RECONNECT_ON_POWER_DOWN",
+        help_heading = "PROBE CONFIGURATION"
+    )]
+    pub reconnect_on_power_down: Option<u32>,
+    /// A Rhai script with hooks to run before attaching, after a reset, and before and after
+    /// flashing, e.g. to unlock the target or to set up its clocks.
+    #[arg(
+        value_name = "hook script path",
+        long,
+        env = "PROBE_RS_HOOKS",
+        help_heading = "PROBE CONFIGURATION"
+    )]
+    pub hooks: Option<PathBuf>,
+    /// Record all transactions of the debug probe to this file, e.g. to attach it to a bug report.
+    #[arg(
+        value_name = "trace file path",
+        long,
+        env = "PROBE_RS_RECORD_TRACE",
+        help_heading = "PROBE CONFIGURATION"
+    )]
+    pub record_trace: Option<PathBuf>,
+    /// Log every SWD transfer to this file, to debug the SWD protocol. Print the log with
+    /// `probe-rs swd-trace`.
+    #[arg(
+        value_name = "SWD trace file path",
+        long,
+        env = "PROBE_RS_RECORD_SWD_TRACE",
+        help_heading = "PROBE CONFIGURATION"
+    )]
+    pub record_swd_trace: Option<PathBuf>,
 }
 
 impl ProbeOptions {
@@ -135,7 +225,9 @@ impl ProbeOptions {
 
         let target = common_options.get_target_selector()?;
         let probe = common_options.attach_probe(lister).await?;
-        let session = common_options.attach_session(probe, target)?;
+        let session = common_options
+            .attach_session_with_speed_negotiation(lister, probe, target)
+            .await?;
 
         Ok((session, common_options))
     }
@@ -191,7 +283,7 @@ impl<'r> LoadedProbeOptions<'r> {
                 }
             })?;
 
-            TargetSelector::Specified(target)
+            TargetSelector::from(target)
         } else {
             TargetSelector::Auto
         };
@@ -255,6 +347,24 @@ impl<'r> LoadedProbeOptions<'r> {
             }
         };
 
+        if let Some(path) = &self.0.record_trace {
+            probe.record_trace(TraceRecorder::new(File::create(path)?))?;
+        }
+
+        if let Some(path) = &self.0.record_swd_trace {
+            probe.record_swd_trace(SwdTraceRecorder::new(File::create(path)?))?;
+        }
+
+        match probe.firmware_info() {
+            Ok(Some(firmware)) => {
+                if let Some(advisory) = firmware.update_advisory() {
+                    tracing::warn!("{advisory}");
+                }
+            }
+            Ok(None) => {}
+            Err(error) => tracing::debug!("Failed to read the probe firmware version: {error}"),
+        }
+
         if let Some(protocol) = self.0.protocol {
             // Select protocol and speed
             probe.select_protocol(protocol).map_err(|error| {
@@ -303,8 +413,11 @@ impl<'r> LoadedProbeOptions<'r> {
         if self.0.allow_erase_all {
             permissions = permissions.allow_erase_all();
         }
+        if self.0.no_debug_freeze {
+            permissions = permissions.disable_debug_freeze();
+        }
 
-        let session = if self.0.connect_under_reset {
+        let mut session = if self.0.connect_under_reset {
             probe.attach_under_reset(target, permissions)
         } else {
             probe.attach(target, permissions)
@@ -313,10 +426,109 @@ impl<'r> LoadedProbeOptions<'r> {
             source: error,
             connect_under_reset: self.0.connect_under_reset,
         })?;
+        session.set_memory_map_policy(self.0.memory_map);
+        session.set_speed_fallback(Some(self.speed_floor()));
+        if let Some(attempts) = self.0.reconnect_on_power_down {
+            session.set_power_down_recovery(PowerDownRecovery::Reconnect {
+                attempts,
+                delay: POWER_DOWN_RECONNECT_DELAY,
+            });
+        }
 
         Ok(session)
     }
 
+    /// The lowest protocol speed in kHz the speed is lowered to on an unstable connection.
+    fn speed_floor(&self) -> u32 {
+        self.0.speed_floor.unwrap_or(DEFAULT_SPEED_FLOOR_KHZ)
+    }
+
+    /// Attaches to target device session like [Self::attach_session], lowering the protocol
+    /// speed if the connection is unstable.
+    ///
+    /// If attaching fails because of communication errors, the probe is opened again at half
+    /// the speed, down to the speed floor. During the session, the speed is lowered further if
+    /// transfers keep failing. The speed which worked is stored in the [Settings],
+    /// and used for the same probe and chip next time, unless a speed is specified.
+    pub async fn attach_session_with_speed_negotiation(
+        &self,
+        lister: &Lister,
+        mut probe: Probe,
+        target: TargetSelector,
+    ) -> Result<Session, OperationError> {
+        let board = self.board_key(&probe);
+        let floor = self.speed_floor();
+        let mut settings = Settings::load();
+
+        if self.0.speed.is_none() {
+            if let Some(&speed) = settings.negotiated_speeds.get(&board) {
+                tracing::info!("Using previously negotiated protocol speed of {speed} kHz");
+                set_speed(&mut probe, speed)?;
+            }
+        }
+
+        let initial_speed = probe.speed_khz();
+
+        loop {
+            let speed = probe.speed_khz();
+
+            match self.attach_session(probe, target.clone()) {
+                Ok(session) => {
+                    if speed != initial_speed {
+                        tracing::warn!(
+                            "The connection is unstable, using a protocol speed of {speed} kHz. \
+                            This speed will be used for this board from now on, unless --speed is given."
+                        );
+
+                        settings.negotiated_speeds.insert(board, speed);
+                        if let Err(error) = settings.store() {
+                            tracing::warn!("Failed to store the negotiated speed: {error:?}");
+                        }
+                    }
+
+                    return Ok(session);
+                }
+                Err(OperationError::AttachingFailed {
+                    source,
+                    connect_under_reset,
+                }) if source.is_link_error() => {
+                    let Some(lower) = lower_speed(speed, floor) else {
+                        return Err(OperationError::AttachingFailed {
+                            source,
+                            connect_under_reset,
+                        });
+                    };
+                    tracing::warn!(
+                        "Attaching at {speed} kHz failed ({source}), retrying at {lower} kHz"
+                    );
+
+                    probe = self.attach_probe(lister).await?;
+                    set_speed(&mut probe, lower)?;
+
+                    // The probe might not support a lower speed.
+                    if probe.speed_khz() >= speed {
+                        return Err(OperationError::AttachingFailed {
+                            source,
+                            connect_under_reset,
+                        });
+                    }
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Identifies the board for storing the negotiated speed, by the probe and the chip.
+    fn board_key(&self, probe: &Probe) -> String {
+        let probe = match &self.0.probe {
+            Some(selector) => selector.to_string(),
+            None => probe.get_name(),
+        };
+        let chip = self.0.chip.as_deref().unwrap_or("auto");
+
+        format!("{probe}/{chip}")
+    }
+
     pub(crate) fn connect_under_reset(&self) -> bool {
         self.0.connect_under_reset
     }
@@ -330,6 +542,22 @@ impl<'r> LoadedProbeOptions<'r> {
     }
 }
 
+fn set_speed(probe: &mut Probe, speed: u32) -> Result<u32, OperationError> {
+    probe
+        .set_speed(speed)
+        .map_err(|error| OperationError::FailedToSelectProtocolSpeed {
+            source: error,
+            speed,
+        })
+}
+
+/// Returns the next lower speed to try on an unstable connection, or `None` if it would be
+/// below `floor`.
+fn lower_speed(speed: u32, floor: u32) -> Option<u32> {
+    let lower = speed / 2;
+    (lower >= floor).then_some(lower)
+}
+
 impl AsRef<ProbeOptions> for LoadedProbeOptions<'_> {
     fn as_ref(&self) -> &ProbeOptions {
         &self.0
@@ -562,6 +790,14 @@ impl From<std::io::Error> for OperationError {
 mod tests {
     use super::*;
 
+    #[test]
+    fn speed_is_halved_down_to_the_floor() {
+        assert_eq!(lower_speed(4000, 100), Some(2000));
+        assert_eq!(lower_speed(200, 100), Some(100));
+        assert_eq!(lower_speed(150, 100), None);
+        assert_eq!(lower_speed(100, 100), None);
+    }
+
     #[test]
     fn to_cargo_options() {
         assert_eq!(
@@ -19,6 +19,20 @@ use probe_rs::{
     flashing::{DownloadOptions, FileDownloadError, FlashLoader},
 };
 
+/// Returns the file in which the progress of downloads to `target_name` is recorded, so an
+/// interrupted download can be resumed with `--resume`.
+pub fn download_checkpoint_path(target_name: &str) -> Option<PathBuf> {
+    let project_dirs = directories::ProjectDirs::from("rs", "probe-rs", "probe-rs")?;
+    let file_name = sanitize_filename::sanitize(format!("{target_name}.yaml"));
+
+    Some(
+        project_dirs
+            .cache_dir()
+            .join("download-checkpoints")
+            .join(file_name),
+    )
+}
+
 /// Performs the flash download with the given loader. Ensure that the loader has the data to load already stored.
 /// This function also manages the update and display of progress bars.
 pub fn run_flash_download(
@@ -36,6 +50,12 @@ pub fn run_flash_download(
     options.disable_double_buffering = download_options.disable_double_buffering;
     options.verify = download_options.verify;
     options.preverify = download_options.preverify;
+    options.incremental = download_options.incremental;
+    options.resume = download_options.resume;
+    options.watchdog = download_options.watchdog;
+    if options.resume {
+        options.checkpoint = download_checkpoint_path(&session.target().name);
+    }
 
     let flash_layout_output_path = download_options.flash_layout_output_path.clone();
 
@@ -108,6 +128,12 @@ pub fn build_loader(
         FormatKind::Hex => Format::Hex,
         FormatKind::Elf => Format::Elf(ElfOptions {
             skip_sections: format_options.elf_options.skip_section,
+            address_aliases: format_options
+                .elf_options
+                .remap
+                .into_iter()
+                .map(Into::into)
+                .collect(),
         }),
         FormatKind::Uf2 => Format::Uf2,
         FormatKind::Idf => Format::Idf(IdfOptions {
@@ -292,6 +318,20 @@ impl CliProgressBars {
                 progress_bars.get_mut(operation).finish();
             }
             ProgressEvent::DiagnosticMessage { .. } => {}
+            ProgressEvent::PipelineStats {
+                transfer_time_us,
+                flash_wait_time_us,
+            } => {
+                // Let the user know that a faster probe, or a higher speed, would help.
+                if transfer_time_us > flash_wait_time_us {
+                    logging::eprintln(format!(
+                        "        {} Programming was limited by the debug probe connection ({:.02}s transferring data, {:.02}s waiting for the flash)",
+                        "Note".cyan().bold(),
+                        transfer_time_us as f32 / 1_000_000.0,
+                        flash_wait_time_us as f32 / 1_000_000.0,
+                    ));
+                }
+            }
         }
     }
 }
@@ -0,0 +1,342 @@
+//! User-defined hook scripts, run at fixed points while attaching, resetting and flashing.
+//!
+//! Hook scripts are written in [Rhai](https://rhai.rs). A script defines a function without
+//! parameters for each hook point it is interested in:
+//!
+//! ```rhai
+//! fn post_reset() {
+//!     // Unlock the watchdog, and enable the PLL.
+//!     write32(0x4000_0000, 0x5A5A);
+//!     write32(0x4000_1004, read32(0x4000_1004) | 1);
+//!     delay_ms(10);
+//! }
+//! ```
+//!
+//! The script can only access the target through the functions registered here:
+//!
+//! - `read8(address)`, `read32(address)`, `write8(address, value)`, `write32(address, value)`
+//! - `read_register(name)`, `write_register(name, value)`, on the selected core
+//! - `set_nreset(level)`, to drive the nRESET pin of the probe
+//! - `delay_ms(ms)`
+//!
+//! Memory and register access is not available in the `pre_attach` hook, because there is no
+//! session yet.
+
+use std::{
+    fmt,
+    rc::Rc,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Context, anyhow};
+use probe_rs::{
+    CoreRegister, CoreRegisters, MemoryInterface, Session, architecture::arm::Pins, probe::Probe,
+};
+use rhai::{AST, Engine, EvalAltResult, INT, Scope};
+
+/// The points at which hook scripts can run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookPoint {
+    /// After the probe has been opened, before attaching to the target.
+    PreAttach,
+    /// After a core has been reset.
+    PostReset,
+    /// Before flashing.
+    PreFlash,
+    /// After flashing.
+    PostFlash,
+}
+
+impl HookPoint {
+    /// The name of the script function implementing this hook.
+    fn function_name(self) -> &'static str {
+        match self {
+            HookPoint::PreAttach => "pre_attach",
+            HookPoint::PostReset => "post_reset",
+            HookPoint::PreFlash => "pre_flash",
+            HookPoint::PostFlash => "post_flash",
+        }
+    }
+}
+
+impl fmt::Display for HookPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.function_name())
+    }
+}
+
+/// What a hook script runs against.
+pub enum HookTarget<'a> {
+    /// The probe, before a session exists.
+    Probe(&'a mut Probe),
+    /// A core of an attached session.
+    Session {
+        session: &'a mut Session,
+        core: usize,
+    },
+}
+
+/// A request of the script to the host, which owns the target.
+enum HostCall {
+    Read8(u64),
+    Read32(u64),
+    Write8(u64, u8),
+    Write32(u64, u32),
+    ReadRegister(String),
+    WriteRegister(String, u64),
+    SetNreset(bool),
+}
+
+impl HookTarget<'_> {
+    fn execute(&mut self, call: HostCall) -> anyhow::Result<u64> {
+        let (session, core_index) = match self {
+            HookTarget::Probe(probe) => {
+                let HostCall::SetNreset(level) = call else {
+                    anyhow::bail!("The target can not be accessed before attaching to it");
+                };
+                set_nreset(level, |pin_out, pin_select| {
+                    Ok(probe.control_pins(pin_out, pin_select)?)
+                })?;
+                return Ok(0);
+            }
+            HookTarget::Session { session, core } => (session, *core),
+        };
+
+        if let HostCall::SetNreset(level) = call {
+            set_nreset(level, |pin_out, pin_select| {
+                Ok(session.control_pins(pin_out, pin_select)?)
+            })?;
+            return Ok(0);
+        }
+
+        let mut core = session.core(core_index)?;
+        let value = match call {
+            HostCall::Read8(address) => core.read_word_8(address)? as u64,
+            HostCall::Read32(address) => core.read_word_32(address)? as u64,
+            HostCall::Write8(address, value) => {
+                core.write_word_8(address, value)?;
+                0
+            }
+            HostCall::Write32(address, value) => {
+                core.write_word_32(address, value)?;
+                0
+            }
+            HostCall::ReadRegister(name) => {
+                let register = find_register(core.registers(), &name)?;
+                core.read_core_reg(register)?
+            }
+            HostCall::WriteRegister(name, value) => {
+                let register = find_register(core.registers(), &name)?;
+                if register.size_in_bits() > 32 {
+                    core.write_core_reg(register, value)?;
+                } else {
+                    let value = u32::try_from(value)
+                        .map_err(|_| anyhow!("{value:#x} does not fit into register {name}"))?;
+                    core.write_core_reg(register, value)?;
+                }
+                0
+            }
+            HostCall::SetNreset(_) => unreachable!(),
+        };
+
+        Ok(value)
+    }
+}
+
+fn set_nreset(
+    level: bool,
+    control_pins: impl FnOnce(Pins, Pins) -> anyhow::Result<Pins>,
+) -> anyhow::Result<()> {
+    let mut pin_out = Pins(0);
+    let mut pin_select = Pins(0);
+    pin_out.set_nreset(level);
+    pin_select.set_nreset(true);
+    control_pins(pin_out, pin_select)?;
+
+    Ok(())
+}
+
+fn find_register(
+    registers: &'static CoreRegisters,
+    name: &str,
+) -> anyhow::Result<&'static CoreRegister> {
+    registers
+        .all_registers()
+        .find(|register| register.name().eq_ignore_ascii_case(name))
+        .ok_or_else(|| anyhow!("Unknown register {name}"))
+}
+
+/// A user-provided hook script.
+#[derive(Debug, Clone)]
+pub struct HookScript {
+    source: String,
+}
+
+impl HookScript {
+    /// Compiles the script, to report syntax errors before any hook runs.
+    pub fn new(source: String) -> anyhow::Result<Self> {
+        Engine::new()
+            .compile(&source)
+            .map_err(|error| anyhow!("{error}"))
+            .context("Failed to compile the hook script")?;
+
+        Ok(Self { source })
+    }
+
+    /// Runs the function for `hook`, if the script defines it.
+    ///
+    /// The script runs on its own thread. Its accesses to the target are sent back to the
+    /// calling thread, which owns `target`.
+    pub fn run(&self, hook: HookPoint, mut target: HookTarget<'_>) -> anyhow::Result<()> {
+        let (call_tx, call_rx) = mpsc::channel::<HostCall>();
+        let (result_tx, result_rx) = mpsc::channel::<Result<u64, String>>();
+
+        let source = self.source.clone();
+        let script = thread::spawn(move || run_script(&source, hook, call_tx, result_rx));
+
+        // The script closes the channel when it is done.
+        for call in call_rx {
+            let result = target.execute(call).map_err(|error| format!("{error:#}"));
+            if result_tx.send(result).is_err() {
+                break;
+            }
+        }
+
+        script
+            .join()
+            .map_err(|_| anyhow!("The {hook} hook script panicked"))?
+            .map_err(|error| anyhow!("The {hook} hook script failed: {error}"))
+    }
+}
+
+struct Host {
+    calls: Sender<HostCall>,
+    results: Receiver<Result<u64, String>>,
+}
+
+impl Host {
+    fn call(&self, call: HostCall) -> Result<INT, Box<EvalAltResult>> {
+        self.calls
+            .send(call)
+            .map_err(|_| "The target is no longer available")?;
+        let value = self
+            .results
+            .recv()
+            .map_err(|_| "The target is no longer available")??;
+
+        Ok(value as INT)
+    }
+}
+
+fn run_script(
+    source: &str,
+    hook: HookPoint,
+    calls: Sender<HostCall>,
+    results: Receiver<Result<u64, String>>,
+) -> Result<(), String> {
+    let host = Rc::new(Host { calls, results });
+    let mut engine = Engine::new();
+
+    engine.on_print(|text| tracing::info!("{text}"));
+    engine.on_debug(|text, _, _| tracing::debug!("{text}"));
+
+    let h = host.clone();
+    engine.register_fn("read8", move |address: INT| {
+        h.call(HostCall::Read8(address as u64))
+    });
+    let h = host.clone();
+    engine.register_fn("read32", move |address: INT| {
+        h.call(HostCall::Read32(address as u64))
+    });
+    let h = host.clone();
+    engine.register_fn("write8", move |address: INT, value: INT| {
+        h.call(HostCall::Write8(address as u64, value as u8))
+            .map(|_| ())
+    });
+    let h = host.clone();
+    engine.register_fn("write32", move |address: INT, value: INT| {
+        h.call(HostCall::Write32(address as u64, value as u32))
+            .map(|_| ())
+    });
+    let h = host.clone();
+    engine.register_fn("read_register", move |name: &str| {
+        h.call(HostCall::ReadRegister(name.to_string()))
+    });
+    let h = host.clone();
+    engine.register_fn("write_register", move |name: &str, value: INT| {
+        h.call(HostCall::WriteRegister(name.to_string(), value as u64))
+            .map(|_| ())
+    });
+    let h = host.clone();
+    engine.register_fn("set_nreset", move |level: bool| {
+        h.call(HostCall::SetNreset(level)).map(|_| ())
+    });
+    engine.register_fn("delay_ms", |ms: INT| {
+        thread::sleep(Duration::from_millis(ms.max(0) as u64))
+    });
+
+    let ast: AST = engine.compile(source).map_err(|error| error.to_string())?;
+
+    let function_name = hook.function_name();
+    let defined = ast
+        .iter_functions()
+        .any(|function| function.name == function_name && function.params.is_empty());
+    if !defined {
+        return Ok(());
+    }
+
+    tracing::info!("Running the {hook} hook script");
+    engine
+        .call_fn::<()>(&mut Scope::new(), &ast, function_name, ())
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn compile_errors_are_reported() {
+        assert!(HookScript::new("fn post_reset() { write32(0, }".to_string()).is_err());
+    }
+
+    #[test]
+    fn missing_hooks_are_skipped() {
+        let script = HookScript::new("fn post_flash() { delay_ms(1); }".to_string()).unwrap();
+        let (calls, _) = mpsc::channel();
+        let (_, results) = mpsc::channel();
+
+        assert_eq!(
+            run_script(&script.source, HookPoint::PreFlash, calls, results),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn host_calls_are_forwarded() {
+        let (calls_tx, calls_rx) = mpsc::channel();
+        let (results_tx, results_rx) = mpsc::channel();
+
+        let script = thread::spawn(move || {
+            run_script(
+                "fn post_reset() { write32(0x20000000, read32(0x20000000) + 1); }",
+                HookPoint::PostReset,
+                calls_tx,
+                results_rx,
+            )
+        });
+
+        let Ok(HostCall::Read32(0x2000_0000)) = calls_rx.recv() else {
+            panic!("expected a read");
+        };
+        results_tx.send(Ok(41)).unwrap();
+        let Ok(HostCall::Write32(0x2000_0000, 42)) = calls_rx.recv() else {
+            panic!("expected a write of 42");
+        };
+        results_tx.send(Ok(0)).unwrap();
+
+        assert_eq!(script.join().unwrap(), Ok(()));
+    }
+}
@@ -0,0 +1,176 @@
+//! Writers for memory contents read back from a target.
+
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Context;
+use serde::Serialize;
+
+/// The number of data bytes per record of the text based formats.
+const RECORD_SIZE: usize = 16;
+
+/// Writes `data`, read from `address`, as an [Intel HEX](https://en.wikipedia.org/wiki/Intel_HEX)
+/// file.
+pub fn write_ihex(writer: &mut impl Write, address: u64, data: &[u8]) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        address + data.len() as u64 <= 1 << 32,
+        "The Intel HEX format only supports addresses up to 32 bits"
+    );
+
+    let mut records = vec![];
+    let mut upper_address = None;
+    let mut offset = 0;
+
+    while offset < data.len() {
+        let current = address + offset as u64;
+        if upper_address != Some(current >> 16) {
+            upper_address = Some(current >> 16);
+            records.push(ihex::Record::ExtendedLinearAddress((current >> 16) as u16));
+        }
+
+        // Records must not cross a 64 KiB boundary.
+        let to_boundary = (0x1_0000 - (current & 0xFFFF)) as usize;
+        let len = RECORD_SIZE.min(to_boundary).min(data.len() - offset);
+
+        records.push(ihex::Record::Data {
+            offset: current as u16,
+            value: data[offset..][..len].to_vec(),
+        });
+        offset += len;
+    }
+    records.push(ihex::Record::EndOfFile);
+
+    let hex = ihex::create_object_file_representation(&records)?;
+    writer.write_all(hex.as_bytes())?;
+
+    Ok(())
+}
+
+/// Writes `data`, read from `address`, as a [Motorola S-record](https://en.wikipedia.org/wiki/SREC_(file_format))
+/// file.
+///
+/// The smallest address width that fits all addresses is used.
+pub fn write_srec(writer: &mut impl Write, address: u64, data: &[u8]) -> anyhow::Result<()> {
+    let end = address + data.len() as u64;
+    let (data_type, end_type, address_bytes) = match end {
+        0..=0x1_0000 => (1, 9, 2),
+        0x1_0001..=0x100_0000 => (2, 8, 3),
+        0x100_0001..=0x1_0000_0000 => (3, 7, 4),
+        _ => anyhow::bail!("The S-record format only supports addresses up to 32 bits"),
+    };
+
+    write_srec_record(writer, 0, 0, 2, b"probe-rs")?;
+
+    let mut count = 0u32;
+    for (i, chunk) in data.chunks(RECORD_SIZE).enumerate() {
+        let record_address = address + (i * RECORD_SIZE) as u64;
+        write_srec_record(writer, data_type, record_address, address_bytes, chunk)?;
+        count += 1;
+    }
+
+    if count <= 0xFFFF {
+        write_srec_record(writer, 5, count as u64, 2, &[])?;
+    } else {
+        write_srec_record(writer, 6, count as u64, 3, &[])?;
+    }
+    write_srec_record(writer, end_type, 0, address_bytes, &[])?;
+
+    Ok(())
+}
+
+fn write_srec_record(
+    writer: &mut impl Write,
+    record_type: u8,
+    address: u64,
+    address_bytes: usize,
+    data: &[u8],
+) -> anyhow::Result<()> {
+    let mut bytes = vec![(address_bytes + data.len() + 1) as u8];
+    bytes.extend_from_slice(&address.to_be_bytes()[8 - address_bytes..]);
+    bytes.extend_from_slice(data);
+
+    let checksum = !bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+
+    write!(writer, "S{record_type}")?;
+    for byte in bytes {
+        write!(writer, "{byte:02X}")?;
+    }
+    writeln!(writer, "{checksum:02X}")?;
+
+    Ok(())
+}
+
+/// Describes where a raw binary dump was read from.
+#[derive(Debug, Serialize)]
+struct BinaryMetadata<'a> {
+    file: &'a str,
+    address: u64,
+    size: usize,
+}
+
+/// Writes `data`, read from `address`, as a raw binary file to `path`, and its address and size
+/// as JSON to `path` with a `.json` suffix appended.
+pub fn write_bin_with_metadata(path: &Path, address: u64, data: &[u8]) -> anyhow::Result<()> {
+    std::fs::write(path, data).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let mut metadata_path = path.as_os_str().to_owned();
+    metadata_path.push(".json");
+
+    let metadata = BinaryMetadata {
+        file: &path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default(),
+        address,
+        size: data.len(),
+    };
+    std::fs::write(&metadata_path, serde_json::to_string_pretty(&metadata)?)
+        .with_context(|| format!("Failed to write {}", Path::new(&metadata_path).display()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ihex_splits_records_at_64k_boundaries() {
+        let mut output = vec![];
+        write_ihex(&mut output, 0x0800_FFFE, &[1, 2, 3, 4]).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output)
+                .unwrap()
+                .lines()
+                .collect::<Vec<_>>(),
+            [
+                ":020000040800F2",
+                ":02FFFE000102FE",
+                ":020000040801F1",
+                ":020000000304F7",
+                ":00000001FF",
+            ]
+        );
+    }
+
+    #[test]
+    fn srec_uses_smallest_address_width() {
+        let mut output = vec![];
+        write_srec(&mut output, 0, &[1, 2]).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines[1], "S10500000102F7");
+        assert_eq!(lines[2], "S5030001FB");
+        assert_eq!(lines[3], "S9030000FC");
+
+        let mut output = vec![];
+        write_srec(&mut output, 0x2000_0000, &[0xFF]).unwrap();
+        assert!(
+            String::from_utf8(output)
+                .unwrap()
+                .contains("S30620000000FFDA")
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! JUnit XML reports of on-target test runs.
+//!
+//! Most CI systems can display the results of a test run from a JUnit XML file, so the results of
+//! each test are collected while the tests run and written once all tests are done.
+
+use std::{
+    fmt::Write as _,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Context;
+
+/// The result of a single test.
+#[derive(Debug, Clone)]
+pub struct TestRecord {
+    /// The name of the test.
+    pub name: String,
+    /// How long the test ran, including the reset of the target.
+    pub duration: Duration,
+    /// Whether the test passed.
+    pub outcome: TestOutcome,
+}
+
+/// The outcome of a single test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestOutcome {
+    /// The test passed.
+    Passed,
+    /// The test failed with the given message.
+    Failed(String),
+    /// The test is marked as ignored, and was not run.
+    Ignored,
+}
+
+/// Collects the results of the tests. Clones share the same results.
+#[derive(Debug, Clone, Default)]
+pub struct TestReport {
+    records: Arc<Mutex<Vec<TestRecord>>>,
+}
+
+impl TestReport {
+    /// Adds the result of a test.
+    pub fn record(&self, record: TestRecord) {
+        self.records.lock().unwrap().push(record);
+    }
+
+    /// Adds the test `name` as ignored, unless it was run and has a result already.
+    pub fn record_ignored(&self, name: &str) {
+        let mut records = self.records.lock().unwrap();
+        if records.iter().all(|record| record.name != name) {
+            records.push(TestRecord {
+                name: name.to_string(),
+                duration: Duration::ZERO,
+                outcome: TestOutcome::Ignored,
+            });
+        }
+    }
+
+    /// Formats the results as a JUnit XML document with a single test suite.
+    pub fn to_junit_xml(&self, suite: &str) -> String {
+        let records = self.records.lock().unwrap();
+        let failures = records
+            .iter()
+            .filter(|r| matches!(r.outcome, TestOutcome::Failed(_)))
+            .count();
+        let skipped = records
+            .iter()
+            .filter(|r| r.outcome == TestOutcome::Ignored)
+            .count();
+        let time: Duration = records.iter().map(|r| r.duration).sum();
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        writeln!(
+            xml,
+            "<testsuites tests=\"{}\" failures=\"{failures}\" skipped=\"{skipped}\" time=\"{:.3}\">",
+            records.len(),
+            time.as_secs_f64()
+        )
+        .unwrap();
+        writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{failures}\" skipped=\"{skipped}\" time=\"{:.3}\">",
+            escape(suite),
+            records.len(),
+            time.as_secs_f64()
+        )
+        .unwrap();
+
+        for record in records.iter() {
+            write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                escape(&record.name),
+                escape(suite),
+                record.duration.as_secs_f64()
+            )
+            .unwrap();
+
+            match &record.outcome {
+                TestOutcome::Passed => writeln!(xml, "/>").unwrap(),
+                TestOutcome::Failed(message) => {
+                    writeln!(xml, ">").unwrap();
+                    writeln!(xml, "      <failure message=\"{}\"/>", escape(message)).unwrap();
+                    writeln!(xml, "    </testcase>").unwrap();
+                }
+                TestOutcome::Ignored => {
+                    writeln!(xml, ">").unwrap();
+                    writeln!(xml, "      <skipped/>").unwrap();
+                    writeln!(xml, "    </testcase>").unwrap();
+                }
+            }
+        }
+
+        writeln!(xml, "  </testsuite>").unwrap();
+        writeln!(xml, "</testsuites>").unwrap();
+
+        xml
+    }
+
+    /// Writes the results to `path` as a JUnit XML document.
+    pub fn write(&self, path: &Path, suite: &str) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_junit_xml(suite))
+            .with_context(|| format!("Failed to write the test report to {}", path.display()))
+    }
+}
+
+/// Escapes the characters with a special meaning in XML attributes.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn junit_xml() {
+        let report = TestReport::default();
+        report.record(TestRecord {
+            name: "tests::passes".to_string(),
+            duration: Duration::from_millis(1500),
+            outcome: TestOutcome::Passed,
+        });
+        report.record(TestRecord {
+            name: "tests::fails".to_string(),
+            duration: Duration::from_millis(250),
+            outcome: TestOutcome::Failed(
+                "assertion `left == right` failed\n  left: 1 < 2".to_string(),
+            ),
+        });
+        report.record_ignored("tests::ignored");
+        // Ignored tests that were run anyway keep their result.
+        report.record_ignored("tests::passes");
+
+        assert_eq!(
+            report.to_junit_xml("firmware"),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites tests="3" failures="1" skipped="1" time="1.750">
+  <testsuite name="firmware" tests="3" failures="1" skipped="1" time="1.750">
+    <testcase name="tests::passes" classname="firmware" time="1.500"/>
+    <testcase name="tests::fails" classname="firmware" time="0.250">
+      <failure message="assertion `left == right` failed&#10;  left: 1 &lt; 2"/>
+    </testcase>
+    <testcase name="tests::ignored" classname="firmware" time="0.000">
+      <skipped/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+}
@@ -2,9 +2,16 @@ pub mod cargo;
 pub mod cli;
 pub mod common_options;
 pub mod flash;
+pub mod hooks;
+pub mod image_file;
+pub mod junit;
 pub mod logging;
 pub mod meta;
 pub mod rtt;
+pub mod semihosting;
+pub mod settings;
+pub mod snapshot;
+pub mod swo;
 pub mod visualizer;
 
 use std::num::ParseIntError;
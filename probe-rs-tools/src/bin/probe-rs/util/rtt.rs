@@ -1,10 +1,13 @@
 use postcard_schema::Schema;
-use probe_rs::rtt::{self, DownChannel, Error, Rtt, UpChannel};
+use probe_rs::rtt::{self, ControlBlockStatus, DownChannel, Error, Rtt, UpChannel};
 use probe_rs::{Core, MemoryInterface};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 pub(crate) mod client;
 pub(crate) mod processing;
+pub(crate) mod server;
+pub(crate) mod sinks;
 
 pub use processing::*;
 
@@ -72,6 +75,15 @@ pub struct RttConfig {
     /// Configure data_format and show_timestamps for select channels
     #[serde(default = "Vec::new", rename = "rttChannelFormats")]
     pub channels: Vec<RttChannelConfig>,
+
+    /// Read all up channels with a single large memory transfer per poll, instead of several
+    /// small ones per channel. This is faster on probes with a high per-transfer overhead.
+    #[serde(default, rename = "rttBatchedReads")]
+    pub batched_reads: bool,
+
+    /// Forward the channels over TCP or WebSocket, for other tools to consume.
+    #[serde(default, rename = "rttServer")]
+    pub server: server::RttServerConfig,
 }
 
 impl RttConfig {
@@ -106,6 +118,11 @@ pub struct RttChannelConfig {
     #[serde(default)]
     /// Controls the output format for DataFormat::Defmt.
     pub log_format: Option<String>,
+
+    #[serde(default)]
+    /// The down channel that receives the input typed into the window of this channel. Defaults to
+    /// the down channel with the same number, if the target has one.
+    pub down_channel: Option<u32>,
 }
 
 impl Default for RttChannelConfig {
@@ -117,6 +134,7 @@ impl Default for RttChannelConfig {
             show_timestamps: default_show_timestamps(),
             show_location: Default::default(),
             log_format: Default::default(),
+            down_channel: Default::default(),
         }
     }
 }
@@ -128,6 +146,9 @@ pub struct RttActiveUpChannel {
     rtt_buffer: Box<[u8]>,
     bytes_buffered: usize,
 
+    /// Whether the buffer holds data from a batched read that has not been returned yet.
+    pending: bool,
+
     /// If set, the original mode of the channel before we first changed it. Upon exit we should do
     /// our best to restore the original mode.
     original_mode: Option<rtt::ChannelMode>,
@@ -138,6 +159,7 @@ impl RttActiveUpChannel {
         Self {
             rtt_buffer: vec![0; up_channel.buffer_size().max(1)].into_boxed_slice(),
             bytes_buffered: 0,
+            pending: false,
             up_channel,
             original_mode: None,
         }
@@ -202,8 +224,21 @@ impl RttActiveDownChannel {
         self.down_channel.number() as u32
     }
 
-    pub fn write(&mut self, core: &mut Core<'_>, data: impl AsRef<[u8]>) -> Result<(), Error> {
-        self.down_channel.write(core, data.as_ref()).map(|_| ())
+    /// Writes as much of `data` to the channel as fits into its buffer, and returns the number of
+    /// bytes written.
+    pub fn write(&mut self, core: &mut Core<'_>, data: impl AsRef<[u8]>) -> Result<usize, Error> {
+        self.down_channel.write(core, data.as_ref())
+    }
+
+    /// Writes all of `data` to the channel, waiting at most `timeout` for the target to read from
+    /// a full buffer.
+    pub fn write_all_timeout(
+        &mut self,
+        core: &mut Core<'_>,
+        data: impl AsRef<[u8]>,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        self.down_channel.write_all(core, data.as_ref(), timeout)
     }
 }
 
@@ -223,11 +258,14 @@ pub struct RttConnection {
     control_block_addr: u64,
     pub active_up_channels: Vec<RttActiveUpChannel>,
     pub active_down_channels: Vec<RttActiveDownChannel>,
+
+    /// Whether to read all up channels at once, see [`RttConfig::batched_reads`].
+    batched_reads: bool,
 }
 
 impl RttConnection {
     /// RttActiveTarget collects references to all the `RttActiveChannel`s, for latter polling/pushing of data.
-    pub fn new(rtt: Rtt) -> Result<Self, Error> {
+    pub fn new(rtt: Rtt, batched_reads: bool) -> Result<Self, Error> {
         let control_block_addr = rtt.ptr();
 
         let active_up_channels = rtt
@@ -246,6 +284,7 @@ impl RttConnection {
             control_block_addr,
             active_up_channels,
             active_down_channels,
+            batched_reads,
         })
     }
 
@@ -253,11 +292,44 @@ impl RttConnection {
     /// An error on any channel will return an error instead of incomplete data.
     pub fn poll_channel(&mut self, core: &mut Core, channel_idx: u32) -> Result<(), Error> {
         let channel_idx = channel_idx as usize;
-        if let Some(channel) = self.active_up_channels.get_mut(channel_idx) {
-            channel.poll(core)
-        } else {
-            Err(Error::MissingChannel(channel_idx))
+        let Some(channel) = self.active_up_channels.get_mut(channel_idx) else {
+            return Err(Error::MissingChannel(channel_idx));
+        };
+
+        if !self.batched_reads {
+            return channel.poll(core);
+        }
+
+        // Return the data of the last batched read first, reading it again would lose it.
+        if !channel.pending {
+            self.poll_batched(core)?;
         }
+        self.active_up_channels[channel_idx].pending = false;
+
+        Ok(())
+    }
+
+    /// Reads all up channels whose data has been returned with a single batched read.
+    fn poll_batched(&mut self, core: &mut Core) -> Result<(), Error> {
+        let mut channels = self
+            .active_up_channels
+            .iter_mut()
+            .filter(|channel| !channel.pending)
+            .collect::<Vec<_>>();
+
+        let counts = rtt::read_up_channels_batched(
+            core,
+            channels
+                .iter_mut()
+                .map(|channel| (&mut channel.up_channel, &mut channel.rtt_buffer[..])),
+        )?;
+
+        for (channel, count) in channels.into_iter().zip(counts) {
+            channel.bytes_buffered = count;
+            channel.pending = true;
+        }
+
+        Ok(())
     }
 
     pub fn channel_data(&self, channel_idx: u32) -> Result<&[u8], Error> {
@@ -269,19 +341,35 @@ impl RttConnection {
         }
     }
 
-    /// Send data to a down channel.
+    /// Send data to a down channel, without waiting for space in the buffer. Returns the number
+    /// of bytes written.
     pub fn write_down_channel(
         &mut self,
         core: &mut Core,
         channel_idx: u32,
         data: impl AsRef<[u8]>,
+    ) -> Result<usize, Error> {
+        self.down_channel(channel_idx)?.write(core, data)
+    }
+
+    /// Send all of `data` to a down channel, waiting at most `timeout` for the target to read
+    /// from a full buffer.
+    pub fn write_down_channel_all(
+        &mut self,
+        core: &mut Core,
+        channel_idx: u32,
+        data: impl AsRef<[u8]>,
+        timeout: Duration,
     ) -> Result<(), Error> {
+        self.down_channel(channel_idx)?
+            .write_all_timeout(core, data, timeout)
+    }
+
+    fn down_channel(&mut self, channel_idx: u32) -> Result<&mut RttActiveDownChannel, Error> {
         let channel_idx = channel_idx as usize;
-        if let Some(channel) = self.active_down_channels.get_mut(channel_idx) {
-            channel.write(core, data)
-        } else {
-            Err(Error::MissingChannel(channel_idx))
-        }
+        self.active_down_channels
+            .get_mut(channel_idx)
+            .ok_or(Error::MissingChannel(channel_idx))
     }
 
     /// Clean up temporary changes made to the channels.
@@ -292,6 +380,16 @@ impl RttConnection {
         Ok(())
     }
 
+    /// Checks whether the target cleared or reinitialized the control block since attaching.
+    pub fn control_block_status(&self, core: &mut Core) -> Result<ControlBlockStatus, Error> {
+        rtt::control_block_status(
+            core,
+            self.control_block_addr,
+            self.active_up_channels.iter().map(|c| &c.up_channel),
+            self.active_down_channels.iter().map(|c| &c.down_channel),
+        )
+    }
+
     /// Overwrites the control block with zeros. This is useful after resets.
     pub fn clear_control_block(&mut self, core: &mut Core) -> Result<(), Error> {
         let zeros = vec![0; Rtt::control_block_size(core)];
@@ -1,16 +1,22 @@
 use crate::util::rtt::{
     ChannelMode, RttActiveDownChannel, RttActiveUpChannel, RttConfig, RttConnection,
+    server::{RttServer, RttServerConfig},
 };
 use probe_rs::{
     Core, MemoryInterface, Target,
     flashing::FlashLoader,
-    rtt::{Error, Rtt, ScanRegion},
+    rtt::{ControlBlockStatus, Error, Rtt, ScanRegion},
 };
+use std::time::{Duration, Instant};
+
+/// How often the control block is checked for being reinitialized, e.g. after a target reset.
+const CONTROL_BLOCK_CHECK_INTERVAL: Duration = Duration::from_millis(500);
 
 pub struct RttClient {
     pub scan_region: ScanRegion,
     channel_modes: Vec<Option<ChannelMode>>,
     need_configure: bool,
+    batched_reads: bool,
 
     /// The internal RTT handle, if we have successfully attached to the target.
     target: Option<RttConnection>,
@@ -29,6 +35,13 @@ pub struct RttClient {
 
     /// The core used to poll the target.
     core_id: usize,
+
+    /// When the control block was last checked for being reinitialized.
+    last_control_block_check: Option<Instant>,
+
+    /// Forwards the channels over the network, started once we're attached.
+    server_config: RttServerConfig,
+    server: Option<RttServer>,
 }
 
 impl RttClient {
@@ -43,6 +56,7 @@ impl RttClient {
             scan_region,
             channel_modes: config.channels.iter().map(|c| c.mode).collect(),
             need_configure: true,
+            batched_reads: config.batched_reads,
 
             target: None,
             last_control_block_address: None,
@@ -50,6 +64,9 @@ impl RttClient {
             try_attaching: true,
             polled_data: false,
             core_id,
+            last_control_block_check: None,
+            server_config: config.server,
+            server: None,
         }
     }
 
@@ -121,7 +138,7 @@ impl RttClient {
             Err(error) => return Err(error),
         };
 
-        match RttConnection::new(rtt) {
+        match RttConnection::new(rtt, self.batched_reads) {
             Ok(rtt) => self.target = Some(rtt),
             Err(Error::ControlBlockCorrupted(error)) => {
                 tracing::debug!("Failed to attach - control block corrupted: {}", error);
@@ -139,13 +156,97 @@ impl RttClient {
         if attached && self.need_configure {
             self.configure(core)?;
             self.need_configure = false;
+            self.start_server();
         }
 
         Ok(self.is_attached())
     }
 
+    /// Starts forwarding the channels over the network, if configured, or updates the forwarded
+    /// channels after re-attaching.
+    fn start_server(&mut self) {
+        if !self.server_config.is_enabled() {
+            return;
+        }
+
+        let up_channels = self.up_channels().len();
+        let down_channels = self.down_channels().len();
+
+        let server = match self.server.as_mut() {
+            Some(server) => server,
+            None => match RttServer::new(&self.server_config) {
+                Ok(server) => self.server.insert(server),
+                Err(error) => {
+                    tracing::warn!("Failed to start the RTT server: {error:#}");
+                    // Don't retry on every attach.
+                    self.server_config = RttServerConfig::default();
+                    return;
+                }
+            },
+        };
+
+        if let Err(error) = server.set_channels(up_channels, down_channels) {
+            tracing::warn!("Failed to forward the RTT channels: {error:#}");
+        }
+    }
+
+    /// Writes the data that network clients sent to the down channels.
+    fn write_server_input(&mut self, core: &mut Core) -> Result<(), Error> {
+        let (Some(server), Some(target)) = (self.server.as_mut(), self.target.as_mut()) else {
+            return Ok(());
+        };
+
+        while let Some((channel, input)) = server.take_input() {
+            let written = target.write_down_channel(core, channel, &input)?;
+            if written < input.len() {
+                tracing::warn!(
+                    "RTT down channel {channel} is full, dropped {} bytes of input",
+                    input.len() - written
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Drops the connection if the target has cleared or reinitialized the control block, so the
+    /// next attach detects the channels again and reapplies their configuration.
+    fn check_control_block(&mut self, core: &mut Core) -> Result<(), Error> {
+        let Some(ref target) = self.target else {
+            return Ok(());
+        };
+
+        if self
+            .last_control_block_check
+            .is_some_and(|last_check| last_check.elapsed() < CONTROL_BLOCK_CHECK_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.last_control_block_check = Some(Instant::now());
+
+        match target.control_block_status(core)? {
+            ControlBlockStatus::Unchanged => {}
+            ControlBlockStatus::Missing => {
+                tracing::info!(
+                    "RTT control block was cleared, waiting for the target to initialize it"
+                );
+                self.target = None;
+                self.polled_data = false;
+            }
+            ControlBlockStatus::Reinitialized => {
+                tracing::info!("RTT control block was reinitialized, re-attaching");
+                self.target = None;
+                self.polled_data = false;
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn poll_channel(&mut self, core: &mut Core, channel: u32) -> Result<&[u8], Error> {
+        self.check_control_block(core)?;
         self.try_attach(core)?;
+        self.write_server_input(core)?;
 
         if let Some(ref mut target) = self.target {
             match target.poll_channel(core, channel) {
@@ -171,17 +272,41 @@ impl RttClient {
         }
 
         if let Some(ref target) = self.target {
-            return target.channel_data(channel);
+            let data = target.channel_data(channel)?;
+            if let Some(server) = self.server.as_ref() {
+                server.publish(channel, data);
+            }
+            return Ok(data);
         }
 
         Ok(&[])
     }
 
+    /// Writes as much of `input` to the down channel as fits into its buffer, and returns the
+    /// number of bytes written.
     pub(crate) fn write_down_channel(
         &mut self,
         core: &mut Core,
         channel: u32,
         input: impl AsRef<[u8]>,
+    ) -> Result<usize, Error> {
+        self.try_attach(core)?;
+
+        let Some(target) = self.target.as_mut() else {
+            return Ok(0);
+        };
+
+        target.write_down_channel(core, channel, input)
+    }
+
+    /// Writes all of `input` to the down channel, waiting at most `timeout` for the target to read
+    /// from a full buffer.
+    pub(crate) fn write_down_channel_all(
+        &mut self,
+        core: &mut Core,
+        channel: u32,
+        input: impl AsRef<[u8]>,
+        timeout: Duration,
     ) -> Result<(), Error> {
         self.try_attach(core)?;
 
@@ -189,7 +314,7 @@ impl RttClient {
             return Ok(());
         };
 
-        target.write_down_channel(core, channel, input)
+        target.write_down_channel_all(core, channel, input, timeout)
     }
 
     pub fn clean_up(&mut self, core: &mut Core) -> Result<(), Error> {
@@ -0,0 +1,468 @@
+//! Forwards RTT channels over the network, so that external tools like plotters or custom user
+//! interfaces can consume the data while the target is running.
+//!
+//! Each channel `N` is available as a TCP port at `<tcp port> + N`, similar to SEGGER's RTT telnet
+//! server, and as a WebSocket endpoint at `ws://<address>:<websocket port>/N`. Clients receive the
+//! raw output of up channel `N`, and data sent by clients is written to down channel `N`.
+
+use std::{net::TcpListener as StdTcpListener, sync::Arc};
+
+use anyhow::Context;
+use parking_lot::Mutex;
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    runtime::Handle,
+    sync::{broadcast, mpsc},
+    task::JoinHandle,
+};
+
+/// The address the server listens on if none is configured.
+const DEFAULT_ADDRESS: &str = "127.0.0.1";
+
+/// The number of output chunks buffered for each client before older ones are dropped.
+const CLIENT_BUFFER: usize = 1024;
+
+/// Configuration for forwarding RTT channels over the network.
+///
+/// The server runs on the machine the probe is connected to, which is the remote host when
+/// connected to a `probe-rs serve` instance.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Schema, clap::Args)]
+#[serde(rename_all = "camelCase")]
+pub struct RttServerConfig {
+    /// Forward each RTT channel over TCP. Channel N is available on port `<PORT> + N`.
+    #[arg(
+        long = "rtt-tcp-port",
+        value_name = "PORT",
+        help_heading = "RTT SERVER"
+    )]
+    #[serde(default)]
+    pub tcp_port: Option<u16>,
+
+    /// Forward each RTT channel over WebSocket. Channel N is available at
+    /// `ws://<ADDRESS>:<PORT>/N`.
+    #[arg(
+        long = "rtt-websocket-port",
+        value_name = "PORT",
+        help_heading = "RTT SERVER"
+    )]
+    #[serde(default)]
+    pub websocket_port: Option<u16>,
+
+    /// The address to listen on. Defaults to 127.0.0.1, which only accepts local connections.
+    #[arg(
+        long = "rtt-server-address",
+        value_name = "ADDRESS",
+        help_heading = "RTT SERVER"
+    )]
+    #[serde(default)]
+    pub address: Option<String>,
+}
+
+impl RttServerConfig {
+    /// Returns whether any of the protocols is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.tcp_port.is_some() || self.websocket_port.is_some()
+    }
+
+    fn address(&self) -> &str {
+        self.address.as_deref().unwrap_or(DEFAULT_ADDRESS)
+    }
+}
+
+/// The channels of the target, shared with the connections.
+#[derive(Default)]
+struct Channels {
+    /// The output of each up channel, by channel number.
+    up: Vec<broadcast::Sender<Vec<u8>>>,
+    /// The number of down channels.
+    down: usize,
+}
+
+impl Channels {
+    fn subscribe(&self, channel: usize) -> Option<broadcast::Receiver<Vec<u8>>> {
+        self.up.get(channel).map(|sender| sender.subscribe())
+    }
+}
+
+type SharedChannels = Arc<Mutex<Channels>>;
+
+/// Data that a client sent to a down channel.
+type Input = (u32, Vec<u8>);
+
+/// Serves the RTT channels of a target over TCP and WebSocket.
+///
+/// The listeners run as tasks on the tokio runtime that the server was created on, and are
+/// stopped when the server is dropped.
+pub struct RttServer {
+    config: RttServerConfig,
+    runtime: Handle,
+    channels: SharedChannels,
+
+    input_sender: mpsc::UnboundedSender<Input>,
+    input: mpsc::UnboundedReceiver<Input>,
+
+    /// The TCP listener of each channel, by channel number.
+    tcp_listeners: Vec<JoinHandle<()>>,
+    websocket_listener: Option<JoinHandle<()>>,
+}
+
+impl RttServer {
+    /// Creates a server for `config`. Listeners for the channels are started by
+    /// [`set_channels`](Self::set_channels), once the channels are known.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn new(config: &RttServerConfig) -> anyhow::Result<Self> {
+        let runtime = Handle::try_current().context("The RTT server requires a tokio runtime")?;
+        let channels = SharedChannels::default();
+        let (input_sender, input) = mpsc::unbounded_channel();
+
+        let websocket_listener = match config.websocket_port {
+            Some(port) => Some(spawn_websocket_listener(
+                &runtime,
+                bind(config.address(), port)?,
+                channels.clone(),
+                input_sender.clone(),
+            )?),
+            None => None,
+        };
+
+        Ok(Self {
+            config: config.clone(),
+            runtime,
+            channels,
+            input_sender,
+            input,
+            tcp_listeners: vec![],
+            websocket_listener,
+        })
+    }
+
+    /// Updates the number of channels of the target, starting the listeners of new channels.
+    ///
+    /// Connected clients are kept when the channels are detected again, e.g. after the target was
+    /// reset.
+    pub fn set_channels(&mut self, up_channels: usize, down_channels: usize) -> anyhow::Result<()> {
+        {
+            let mut channels = self.channels.lock();
+            while channels.up.len() < up_channels {
+                channels.up.push(broadcast::channel(CLIENT_BUFFER).0);
+            }
+            channels.down = down_channels;
+        }
+
+        let Some(base_port) = self.config.tcp_port else {
+            return Ok(());
+        };
+
+        for channel in self.tcp_listeners.len()..up_channels.max(down_channels) {
+            let port = u16::try_from(base_port as usize + channel)
+                .with_context(|| format!("No TCP port left for RTT channel {channel}"))?;
+            let listener = bind(self.config.address(), port)?;
+            tracing::info!(
+                "Forwarding RTT channel {channel} on {}",
+                listener.local_addr()?
+            );
+
+            let _guard = self.runtime.enter();
+            let listener = TcpListener::from_std(listener)?;
+            self.tcp_listeners.push(self.runtime.spawn(accept_tcp(
+                listener,
+                channel,
+                self.channels.clone(),
+                self.input_sender.clone(),
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Sends the output of an up channel to all connected clients.
+    pub fn publish(&self, channel: u32, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+
+        let channels = self.channels.lock();
+        if let Some(sender) = channels.up.get(channel as usize) {
+            // Sending only fails if no client is connected.
+            _ = sender.send(data.to_vec());
+        }
+    }
+
+    /// Returns the next data that a client sent to a down channel, if any.
+    pub fn take_input(&mut self) -> Option<(u32, Vec<u8>)> {
+        self.input.try_recv().ok()
+    }
+}
+
+impl Drop for RttServer {
+    fn drop(&mut self) {
+        // Closing the output ends the connections.
+        self.channels.lock().up.clear();
+        for listener in self.tcp_listeners.iter() {
+            listener.abort();
+        }
+        if let Some(listener) = self.websocket_listener.as_ref() {
+            listener.abort();
+        }
+    }
+}
+
+fn bind(address: &str, port: u16) -> anyhow::Result<StdTcpListener> {
+    let listener = StdTcpListener::bind((address, port))
+        .with_context(|| format!("Failed to listen on {address}:{port}"))?;
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
+
+async fn accept_tcp(
+    listener: TcpListener,
+    channel: usize,
+    channels: SharedChannels,
+    input: mpsc::UnboundedSender<Input>,
+) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, peer)) => {
+                tracing::debug!("RTT channel {channel}: {peer} connected");
+                stream
+            }
+            Err(error) => {
+                tracing::warn!("RTT channel {channel}: failed to accept a connection: {error}");
+                continue;
+            }
+        };
+
+        let output = channels.lock().subscribe(channel);
+        tokio::spawn(serve_tcp(
+            stream,
+            channel,
+            output,
+            channels.clone(),
+            input.clone(),
+        ));
+    }
+}
+
+async fn serve_tcp(
+    mut stream: TcpStream,
+    channel: usize,
+    mut output: Option<broadcast::Receiver<Vec<u8>>>,
+    channels: SharedChannels,
+    input: mpsc::UnboundedSender<Input>,
+) {
+    let (mut reader, mut writer) = stream.split();
+    let mut buffer = [0; 1024];
+
+    loop {
+        tokio::select! {
+            data = next_output(&mut output, channel) => {
+                let Some(data) = data else { break };
+                if writer.write_all(&data).await.is_err() {
+                    break;
+                }
+            }
+            read = reader.read(&mut buffer) => {
+                match read {
+                    Ok(0) | Err(_) => break,
+                    Ok(count) => forward_input(&channels, &input, channel, &buffer[..count]),
+                }
+            }
+        }
+    }
+
+    tracing::debug!("RTT channel {channel}: connection closed");
+}
+
+/// Waits for the next output of an up channel. Returns `None` if the server was stopped.
+///
+/// Never returns for channels that are not up channels, so their connections can still be used
+/// for input.
+async fn next_output(
+    output: &mut Option<broadcast::Receiver<Vec<u8>>>,
+    channel: usize,
+) -> Option<Vec<u8>> {
+    let Some(receiver) = output.as_mut() else {
+        return std::future::pending().await;
+    };
+
+    loop {
+        match receiver.recv().await {
+            Ok(data) => return Some(data),
+            Err(broadcast::error::RecvError::Lagged(count)) => {
+                tracing::warn!("RTT channel {channel}: client is too slow, dropped {count} chunks");
+            }
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
+    }
+}
+
+fn forward_input(
+    channels: &SharedChannels,
+    input: &mpsc::UnboundedSender<Input>,
+    channel: usize,
+    data: &[u8],
+) {
+    if channel < channels.lock().down {
+        _ = input.send((channel as u32, data.to_vec()));
+    } else {
+        tracing::debug!("RTT channel {channel}: discarding input, there is no down channel");
+    }
+}
+
+#[cfg(feature = "remote")]
+fn spawn_websocket_listener(
+    runtime: &Handle,
+    listener: StdTcpListener,
+    channels: SharedChannels,
+    input: mpsc::UnboundedSender<Input>,
+) -> anyhow::Result<JoinHandle<()>> {
+    tracing::info!("Forwarding RTT channels on ws://{}", listener.local_addr()?);
+
+    let _guard = runtime.enter();
+    let listener = TcpListener::from_std(listener)?;
+    Ok(runtime.spawn(accept_websocket(listener, channels, input)))
+}
+
+#[cfg(not(feature = "remote"))]
+fn spawn_websocket_listener(
+    _runtime: &Handle,
+    _listener: StdTcpListener,
+    _channels: SharedChannels,
+    _input: mpsc::UnboundedSender<Input>,
+) -> anyhow::Result<JoinHandle<()>> {
+    anyhow::bail!(
+        "Forwarding RTT over WebSocket requires probe-rs to be built with the `remote` feature"
+    )
+}
+
+#[cfg(feature = "remote")]
+async fn accept_websocket(
+    listener: TcpListener,
+    channels: SharedChannels,
+    input: mpsc::UnboundedSender<Input>,
+) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                tracing::debug!("RTT WebSocket: {peer} connected");
+                tokio::spawn(serve_websocket(stream, channels.clone(), input.clone()));
+            }
+            Err(error) => {
+                tracing::warn!("RTT WebSocket: failed to accept a connection: {error}");
+            }
+        }
+    }
+}
+
+#[cfg(feature = "remote")]
+async fn serve_websocket(
+    stream: TcpStream,
+    channels: SharedChannels,
+    input: mpsc::UnboundedSender<Input>,
+) {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::{
+        Message,
+        handshake::server::{ErrorResponse, Request, Response},
+    };
+
+    let mut path = String::new();
+    // The error type of the handshake callback is defined by tungstenite.
+    #[expect(clippy::result_large_err)]
+    let websocket =
+        tokio_tungstenite::accept_hdr_async(stream, |request: &Request, response: Response| {
+            path = request.uri().path().to_string();
+            Ok::<_, ErrorResponse>(response)
+        })
+        .await;
+
+    let mut websocket = match websocket {
+        Ok(websocket) => websocket,
+        Err(error) => {
+            tracing::debug!("RTT WebSocket: handshake failed: {error}");
+            return;
+        }
+    };
+
+    let Ok(channel) = path.trim_start_matches('/').parse::<usize>() else {
+        tracing::debug!("RTT WebSocket: {path} is not a channel number");
+        _ = websocket.close(None).await;
+        return;
+    };
+
+    let mut output = channels.lock().subscribe(channel);
+    let (mut sender, mut receiver) = websocket.split();
+
+    loop {
+        tokio::select! {
+            data = next_output(&mut output, channel) => {
+                let Some(data) = data else { break };
+                if sender.send(Message::binary(data)).await.is_err() {
+                    break;
+                }
+            }
+            message = receiver.next() => {
+                match message {
+                    Some(Ok(Message::Binary(data))) => {
+                        forward_input(&channels, &input, channel, &data)
+                    }
+                    Some(Ok(Message::Text(text))) => {
+                        forward_input(&channels, &input, channel, text.as_bytes())
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                }
+            }
+        }
+    }
+
+    tracing::debug!("RTT WebSocket: connection to channel {channel} closed");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn forwards_channels_over_tcp() {
+        let port = bind(DEFAULT_ADDRESS, 0)
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let config = RttServerConfig {
+            tcp_port: Some(port),
+            websocket_port: None,
+            address: None,
+        };
+        let mut server = RttServer::new(&config).unwrap();
+        server.set_channels(1, 1).unwrap();
+
+        let mut client = TcpStream::connect((DEFAULT_ADDRESS, port)).await.unwrap();
+        client.write_all(b"input").await.unwrap();
+
+        // Once the input arrived, the connection is subscribed to the output.
+        let input = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Some(input) = server.take_input() {
+                    return input;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(input, (0, b"input".to_vec()));
+
+        server.publish(0, b"output");
+        let mut output = [0; 6];
+        client.read_exact(&mut output).await.unwrap();
+        assert_eq!(&output, b"output");
+    }
+}
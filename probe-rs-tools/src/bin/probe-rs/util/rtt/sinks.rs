@@ -0,0 +1,299 @@
+//! Destinations that decoded RTT output is copied to, in addition to the terminal or the debugger
+//! client.
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use time::{
+    OffsetDateTime, UtcOffset,
+    format_description::{self, OwnedFormatItem},
+};
+
+/// The timestamp format used if none is configured.
+const DEFAULT_TIMESTAMP_FORMAT: &str =
+    "[year]-[month]-[day] [hour repr:24]:[minute]:[second].[subsecond digits:3]";
+
+fn default_max_files() -> usize {
+    5
+}
+
+fn default_timestamp_format() -> String {
+    DEFAULT_TIMESTAMP_FORMAT.to_string()
+}
+
+/// Configuration for logging the decoded RTT output of all channels to a file.
+#[derive(Debug, Clone, Serialize, Deserialize, clap::Args)]
+#[serde(rename_all = "camelCase")]
+pub struct RttLogConfig {
+    /// Log the decoded RTT output to this file, in addition to displaying it.
+    #[arg(
+        long = "rtt-log-file",
+        id = "rtt_log_file",
+        value_name = "PATH",
+        help_heading = "RTT LOGGING"
+    )]
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+
+    /// Start a new log file once the current one has grown to this many bytes. The previous
+    /// files are kept as `<PATH>.1`, `<PATH>.2`, and so on.
+    #[arg(
+        long = "rtt-log-max-size",
+        id = "rtt_log_max_size",
+        value_name = "BYTES",
+        help_heading = "RTT LOGGING"
+    )]
+    #[serde(default)]
+    pub max_size: Option<u64>,
+
+    /// The number of previous log files to keep when rotating.
+    #[arg(long = "rtt-log-max-files",
+        id = "rtt_log_max_files", default_value_t = default_max_files(), help_heading = "RTT LOGGING")]
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+
+    /// The format of the timestamp at the start of each line, or an empty string for no
+    /// timestamps.
+    ///
+    /// See <https://time-rs.github.io/book/api/format-description.html> for the syntax.
+    #[arg(
+        long = "rtt-log-timestamp-format",
+        id = "rtt_log_timestamp_format",
+        default_value = DEFAULT_TIMESTAMP_FORMAT,
+        help_heading = "RTT LOGGING"
+    )]
+    #[serde(default = "default_timestamp_format")]
+    pub timestamp_format: String,
+}
+
+impl Default for RttLogConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            max_size: None,
+            max_files: default_max_files(),
+            timestamp_format: default_timestamp_format(),
+        }
+    }
+}
+
+/// A destination for decoded RTT output.
+pub trait RttSink {
+    /// Writes `data`, which was decoded from the channel named `channel`.
+    fn write(&mut self, channel: &str, data: &str) -> io::Result<()>;
+}
+
+/// Writes the output of all channels to a file, starting a new file when it grows too large.
+///
+/// Each line is prefixed with a timestamp and the name of the channel it came from.
+pub struct RotatingFileSink {
+    path: PathBuf,
+    max_size: Option<u64>,
+    max_files: usize,
+    timestamp_format: Option<OwnedFormatItem>,
+    timestamp_offset: UtcOffset,
+
+    file: File,
+    size: u64,
+    /// The channel of the last line, if that line is not complete yet.
+    open_line: Option<String>,
+}
+
+impl RotatingFileSink {
+    /// Creates the sink described by `config`, appending to an existing log file.
+    pub fn new(
+        path: &Path,
+        config: &RttLogConfig,
+        timestamp_offset: UtcOffset,
+    ) -> anyhow::Result<Self> {
+        let timestamp_format = if config.timestamp_format.is_empty() {
+            None
+        } else {
+            Some(
+                format_description::parse_owned::<2>(&config.timestamp_format).with_context(
+                    || format!("Invalid timestamp format '{}'", config.timestamp_format),
+                )?,
+            )
+        };
+
+        let (file, size) = Self::open(path)
+            .with_context(|| format!("Failed to open RTT log file {}", path.display()))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_size: config.max_size,
+            max_files: config.max_files,
+            timestamp_format,
+            timestamp_offset,
+            file,
+            size,
+            open_line: None,
+        })
+    }
+
+    fn open(path: &Path) -> io::Result<(File, u64)> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = File::options().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+
+        Ok((file, size))
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        PathBuf::from(path)
+    }
+
+    /// Moves the current file to `<path>.1`, shifting the older files and deleting the oldest.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        if self.max_files == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    std::fs::rename(from, self.rotated_path(index + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        (self.file, self.size) = Self::open(&self.path)?;
+        Ok(())
+    }
+
+    fn line_prefix(&self, channel: &str) -> String {
+        let Some(format) = self.timestamp_format.as_ref() else {
+            return format!("[{channel}] ");
+        };
+
+        match OffsetDateTime::now_utc()
+            .to_offset(self.timestamp_offset)
+            .format(format)
+        {
+            Ok(timestamp) => format!("{timestamp} [{channel}] "),
+            Err(_) => format!("[{channel}] "),
+        }
+    }
+}
+
+impl RttSink for RotatingFileSink {
+    fn write(&mut self, channel: &str, data: &str) -> io::Result<()> {
+        // Only rotate at line boundaries, so lines are not split across files.
+        if self.open_line.is_none() && self.max_size.is_some_and(|max_size| self.size >= max_size) {
+            self.rotate()?;
+        }
+
+        let mut formatted = String::with_capacity(data.len());
+
+        // Don't continue a line of another channel.
+        if self
+            .open_line
+            .as_deref()
+            .is_some_and(|open| open != channel)
+        {
+            formatted.push('\n');
+            self.open_line = None;
+        }
+
+        for line in data.split_inclusive('\n') {
+            if self.open_line.is_none() {
+                formatted.push_str(&self.line_prefix(channel));
+            }
+            formatted.push_str(line);
+
+            self.open_line = if line.ends_with('\n') {
+                None
+            } else {
+                Some(channel.to_string())
+            };
+        }
+
+        self.file.write_all(formatted.as_bytes())?;
+        self.size += formatted.len() as u64;
+
+        Ok(())
+    }
+}
+
+/// The sinks that decoded RTT output is copied to.
+#[derive(Default)]
+pub struct RttSinks {
+    sinks: Vec<Box<dyn RttSink + Send>>,
+    /// Whether an error was reported already, to not repeat it for every write.
+    reported_error: bool,
+}
+
+impl RttSinks {
+    /// Creates the sinks described by `config`.
+    pub fn from_config(config: &RttLogConfig, timestamp_offset: UtcOffset) -> anyhow::Result<Self> {
+        let mut sinks = Self::default();
+
+        if let Some(path) = config.path.as_deref() {
+            sinks.add(RotatingFileSink::new(path, config, timestamp_offset)?);
+        }
+
+        Ok(sinks)
+    }
+
+    pub fn add(&mut self, sink: impl RttSink + Send + 'static) {
+        self.sinks.push(Box::new(sink));
+    }
+
+    /// Copies `data` from the channel named `channel` to all sinks.
+    ///
+    /// Errors are logged, but don't interrupt the RTT output.
+    pub fn write(&mut self, channel: &str, data: &str) {
+        for sink in self.sinks.iter_mut() {
+            if let Err(error) = sink.write(channel, data) {
+                if !self.reported_error {
+                    tracing::warn!("Failed to log RTT output: {error}");
+                    self.reported_error = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotates_log_files() {
+        let dir = std::env::temp_dir().join(format!("probe-rs-rtt-log-{}", std::process::id()));
+        let path = dir.join("rtt.log");
+        let config = RttLogConfig {
+            path: Some(path.clone()),
+            max_size: Some(16),
+            max_files: 1,
+            timestamp_format: String::new(),
+        };
+
+        let mut sink = RotatingFileSink::new(&path, &config, UtcOffset::UTC).unwrap();
+        sink.write("a", "first\n").unwrap();
+        sink.write("b", "sec").unwrap();
+        sink.write("b", "ond\n").unwrap();
+        sink.write("a", "third\n").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[a] third\n");
+        assert_eq!(
+            std::fs::read_to_string(dir.join("rtt.log.1")).unwrap(),
+            "[a] first\n[b] second\n"
+        );
+        assert!(!dir.join("rtt.log.2").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,303 @@
+//! File access on the host for semihosting.
+//!
+//! The target can open, read and write files using semihosting calls. To make sure a target can't
+//! modify arbitrary files of the host, files can only be accessed inside of a configured root
+//! directory. Running commands on the host with `SYS_SYSTEM` is never allowed.
+
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    num::NonZeroU32,
+    path::{Component, Path, PathBuf},
+    time::Instant,
+};
+
+use probe_rs::{Core, semihosting::SemihostingCommand};
+
+/// The first handle given to files on the host.
+///
+/// Lower handles are used for the standard streams, and for the console windows of the debugger.
+const FIRST_HANDLE: u32 = 0x1_0000;
+
+/// The frequency of the ticks reported by `SYS_ELAPSED`, in Hz.
+const TICK_FREQUENCY: u32 = 1_000_000;
+
+/// Files on the host, opened by the target.
+#[derive(Debug)]
+pub struct SemihostingFiles {
+    root: PathBuf,
+    files: HashMap<u32, File>,
+    next_handle: u32,
+    /// The error of the last failed operation, reported to the target by `SYS_ERRNO`.
+    errno: i32,
+}
+
+impl SemihostingFiles {
+    /// Allows the target to access the files in `root`.
+    pub fn new(root: &Path) -> io::Result<Self> {
+        Ok(Self {
+            root: root.canonicalize()?,
+            files: HashMap::new(),
+            next_handle: FIRST_HANDLE,
+            errno: 0,
+        })
+    }
+
+    /// Handles a semihosting command, if it accesses a file on the host.
+    ///
+    /// Returns `false` if the command is not handled, e.g. because it accesses the console.
+    pub fn handle(
+        &mut self,
+        command: SemihostingCommand,
+        core: &mut Core<'_>,
+    ) -> anyhow::Result<bool> {
+        match command {
+            SemihostingCommand::Open(request) => {
+                let path = request.path(core)?;
+                if path == ":tt" {
+                    return Ok(false);
+                }
+
+                match self.open(&path, request.mode()) {
+                    Ok(handle) => request.respond_with_handle(core, handle)?,
+                    Err(error) => self.failed(&format!("open {path}"), error),
+                }
+            }
+            SemihostingCommand::Close(request) => {
+                let handle = request.file_handle(core)?;
+                if self.files.remove(&handle).is_none() {
+                    return Ok(false);
+                }
+                request.success(core)?;
+            }
+            SemihostingCommand::Read(request) => {
+                let Some(file) = self.files.get_mut(&request.file_handle()) else {
+                    return Ok(false);
+                };
+
+                let mut data = Vec::with_capacity(request.buffer_len() as usize);
+                let result = file
+                    .take(request.buffer_len() as u64)
+                    .read_to_end(&mut data);
+                match result {
+                    Ok(_) => request.write_data(core, &data)?,
+                    Err(error) => self.failed("read", error),
+                }
+            }
+            SemihostingCommand::Write(request) => {
+                let Some(file) = self.files.get_mut(&request.file_handle()) else {
+                    return Ok(false);
+                };
+
+                let data = request.read(core)?;
+                match file.write_all(&data) {
+                    Ok(()) => request.write_status(core, 0)?,
+                    Err(error) => self.failed("write", error),
+                }
+            }
+            SemihostingCommand::Seek(request) => {
+                let Some(file) = self.files.get_mut(&request.file_handle()) else {
+                    return Ok(false);
+                };
+
+                match file.seek(SeekFrom::Start(request.position() as u64)) {
+                    Ok(_) => request.success(core)?,
+                    Err(error) => self.failed("seek", error),
+                }
+            }
+            SemihostingCommand::FileLength(request) => {
+                let Some(file) = self.files.get(&request.file_handle(core)?) else {
+                    return Ok(false);
+                };
+
+                match file.metadata() {
+                    Ok(metadata) => request.write_length(core, metadata.len())?,
+                    Err(error) => self.failed("flen", error),
+                }
+            }
+            SemihostingCommand::Remove(request) => {
+                let path = request.path(core)?;
+                match self.resolve(&path).and_then(std::fs::remove_file) {
+                    Ok(()) => request.success(core)?,
+                    Err(error) => self.failed(&format!("remove {path}"), error),
+                }
+            }
+            SemihostingCommand::Rename(request) => {
+                let from = request.from_path(core)?;
+                let to = request.to_path(core)?;
+                let result = self
+                    .resolve(&from)
+                    .and_then(|from| Ok((from, self.resolve(&to)?)))
+                    .and_then(|(from, to)| std::fs::rename(from, to));
+                match result {
+                    Ok(()) => request.success(core)?,
+                    Err(error) => self.failed(&format!("rename {from} to {to}"), error),
+                }
+            }
+            SemihostingCommand::TmpNam(request) => {
+                // Relative to the root directory, so the target can open the file.
+                request.write_name(core, &format!("probe-rs-tmp-{:03}", request.id()))?;
+            }
+            SemihostingCommand::Errno(request) => request.write_errno(core, self.errno)?,
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    fn open(&mut self, path: &str, mode: &str) -> io::Result<NonZeroU32> {
+        let path = self.resolve(path)?;
+        let file = open_options(mode)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unsupported mode {mode}"),
+                )
+            })?
+            .open(&path)?;
+
+        tracing::debug!("Opened {} for the target", path.display());
+
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        self.files.insert(handle, file);
+
+        Ok(NonZeroU32::new(handle).unwrap())
+    }
+
+    /// Resolves a path of the target relative to the root directory.
+    fn resolve(&self, path: &str) -> io::Result<PathBuf> {
+        let denied = || {
+            io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{path} is outside of the semihosting directory"),
+            )
+        };
+
+        let relative = Path::new(path);
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(denied());
+        }
+
+        let resolved = self.root.join(relative);
+
+        // Symbolic links could still point outside of the root directory.
+        let existing = match resolved.canonicalize() {
+            Ok(existing) => existing,
+            Err(_) => match resolved.parent() {
+                Some(parent) => parent.canonicalize()?,
+                None => self.root.clone(),
+            },
+        };
+        if !existing.starts_with(&self.root) {
+            return Err(denied());
+        }
+
+        Ok(resolved)
+    }
+
+    /// Records the error of a failed operation. The target was already told that the operation
+    /// failed when the command was decoded.
+    fn failed(&mut self, operation: &str, error: io::Error) {
+        tracing::warn!("Semihosting {operation} failed: {error}");
+
+        self.errno = error.raw_os_error().unwrap_or(match error.kind() {
+            io::ErrorKind::NotFound => 2,
+            io::ErrorKind::PermissionDenied => 13,
+            io::ErrorKind::InvalidInput => 22,
+            _ => 5,
+        });
+    }
+}
+
+/// Semihosting operations which don't access files: the clock, and `SYS_SYSTEM`, which is refused.
+#[derive(Debug)]
+pub struct SemihostingHost {
+    started: Instant,
+}
+
+impl SemihostingHost {
+    /// Starts counting the ticks reported to the target.
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+        }
+    }
+
+    /// Handles a semihosting command, if it doesn't access a file or the console.
+    ///
+    /// Returns `false` if the command is not handled.
+    pub fn handle(&self, command: SemihostingCommand, core: &mut Core<'_>) -> anyhow::Result<bool> {
+        match command {
+            SemihostingCommand::Elapsed(request) => {
+                let ticks = self.started.elapsed().as_micros() as u64;
+                request.write_ticks(core, ticks)?;
+            }
+            SemihostingCommand::TickFreq(request) => {
+                request.write_frequency(core, TICK_FREQUENCY)?
+            }
+            SemihostingCommand::System(request) => {
+                // The target was already told that the command failed when it was decoded.
+                let command = request.command(core)?;
+                tracing::warn!("Refusing to run `{command}` on the host for the target");
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+}
+
+impl Default for SemihostingHost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Converts the `fopen` mode of a semihosting request.
+fn open_options(mode: &str) -> Option<OpenOptions> {
+    let mut options = OpenOptions::new();
+    match mode.trim_end_matches('b') {
+        "r" => options.read(true),
+        "r+" => options.read(true).write(true),
+        "w" => options.write(true).create(true).truncate(true),
+        "w+" => options.read(true).write(true).create(true).truncate(true),
+        "a" => options.append(true).create(true),
+        "a+" => options.read(true).append(true).create(true),
+        _ => return None,
+    };
+
+    Some(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_outside_of_root_are_rejected() {
+        let root = std::env::temp_dir();
+        let files = SemihostingFiles::new(&root).unwrap();
+
+        assert_eq!(
+            files.resolve("log.txt").unwrap(),
+            root.canonicalize().unwrap().join("log.txt")
+        );
+        assert!(files.resolve("../log.txt").is_err());
+        assert!(files.resolve("/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn modes() {
+        for mode in [
+            "r", "rb", "r+", "r+b", "w", "wb", "w+", "w+b", "a", "ab", "a+", "a+b",
+        ] {
+            assert!(open_options(mode).is_some(), "{mode} is not supported");
+        }
+        assert!(open_options("unknown").is_none());
+    }
+}
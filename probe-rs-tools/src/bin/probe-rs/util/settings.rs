@@ -0,0 +1,78 @@
+//! Settings which are remembered between invocations of the tools.
+
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use anyhow::Context;
+use probe_rs::config::Registry;
+use serde::{Deserialize, Serialize};
+
+/// The persistent settings, stored in the configuration directory of probe-rs.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Settings {
+    /// The protocol speed in kHz which was negotiated for a board with an unstable connection.
+    ///
+    /// The key identifies the probe and the chip, see
+    /// [`ProbeOptions`](super::common_options::ProbeOptions).
+    #[serde(default)]
+    pub negotiated_speeds: BTreeMap<String, u32>,
+
+    /// Additional directories and URLs of target descriptions.
+    ///
+    /// See [`Registry::add_search_path`].
+    #[serde(default)]
+    pub target_search_paths: Vec<String>,
+}
+
+impl Settings {
+    fn path() -> Option<PathBuf> {
+        directories::ProjectDirs::from("rs", "probe-rs", "probe-rs")
+            .map(|dirs| dirs.config_dir().join("settings.json"))
+    }
+
+    /// Creates a registry with the built-in targets, and the targets of the search paths from
+    /// the settings and the environment.
+    pub fn registry(&self) -> Registry {
+        let mut registry = Registry::from_builtin_families();
+
+        if let Some(dirs) = directories::ProjectDirs::from("rs", "probe-rs", "probe-rs") {
+            registry.set_cache_dir(dirs.cache_dir().join("targets"));
+        }
+
+        for search_path in &self.target_search_paths {
+            registry.add_search_path(search_path.as_str());
+        }
+        registry.add_search_paths_from_env();
+
+        registry
+    }
+
+    /// Loads the settings.
+    ///
+    /// If there are no stored settings, or they can not be read, the default settings are returned.
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|error| {
+                tracing::warn!("Ignoring invalid settings in {}: {error}", path.display());
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Stores the settings, replacing the previously stored settings.
+    pub fn store(&self) -> anyhow::Result<()> {
+        let path = Self::path().context("the configuration directory could not be determined")?;
+
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)
+                .with_context(|| format!("{} could not be created", directory.display()))?;
+        }
+
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("{} could not be written", path.display()))
+    }
+}
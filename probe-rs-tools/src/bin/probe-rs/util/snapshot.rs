@@ -0,0 +1,178 @@
+//! Snapshots of a halted core, to investigate crashes after the fact.
+//!
+//! When the firmware halts unexpectedly, the registers and a configurable set of memory regions
+//! are stored as a core dump in a new, timestamped directory. Only the most recent snapshots are
+//! kept, so a test rig can run unattended for a long time.
+
+use std::{
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use postcard_schema::Schema;
+use probe_rs::{Core, CoreDump, HaltReason, Session};
+use serde::{Deserialize, Serialize};
+use time::{OffsetDateTime, macros::format_description};
+
+/// The prefix of the snapshot directory names.
+const SNAPSHOT_PREFIX: &str = "snapshot-";
+
+/// Where snapshots are stored, and what they contain.
+#[derive(Debug, Clone, Serialize, Deserialize, Schema)]
+pub struct SnapshotOptions {
+    /// The directory the snapshot directories are created in.
+    pub directory: String,
+    /// The memory ranges to capture, as start and end address. If empty, all RAM regions of the
+    /// target are captured.
+    pub ranges: Vec<(u64, u64)>,
+    /// The number of snapshots to keep. Older snapshots are deleted. The newest snapshot is
+    /// always kept, even if this is 0.
+    pub retention: u32,
+}
+
+impl SnapshotOptions {
+    /// Returns the memory ranges to capture for the target of `session`.
+    pub fn memory_ranges(&self, session: &Session) -> Vec<Range<u64>> {
+        if self.ranges.is_empty() {
+            session
+                .target()
+                .memory_map
+                .iter()
+                .filter(|region| region.is_ram())
+                .map(|region| region.address_range())
+                .collect()
+        } else {
+            self.ranges.iter().map(|&(start, end)| start..end).collect()
+        }
+    }
+
+    /// Stores a snapshot of the halted `core`, and deletes snapshots exceeding the retention
+    /// limit.
+    ///
+    /// Returns the directory of the new snapshot.
+    pub fn capture(
+        &self,
+        core: &mut Core<'_>,
+        ranges: Vec<Range<u64>>,
+        halt_reason: HaltReason,
+    ) -> anyhow::Result<PathBuf> {
+        let timestamp = OffsetDateTime::now_utc().format(format_description!(
+            "[year][month][day]T[hour][minute][second].[subsecond digits:3]Z"
+        ))?;
+        let directory = Path::new(&self.directory).join(format!("{SNAPSHOT_PREFIX}{timestamp}"));
+
+        fs::create_dir_all(&directory)
+            .with_context(|| format!("{} could not be created", directory.display()))?;
+
+        fs::write(
+            directory.join("halt_reason.txt"),
+            format!("{halt_reason:?}\n"),
+        )?;
+        CoreDump::dump_core(core, ranges)?.store(&directory.join("core.dump"))?;
+
+        if let Err(error) = self.prune() {
+            tracing::warn!("Failed to delete old snapshots: {error:?}");
+        }
+
+        Ok(directory)
+    }
+
+    /// Deletes the oldest snapshots, so that at most `retention` snapshots are left.
+    fn prune(&self) -> anyhow::Result<()> {
+        let mut names = vec![];
+        for entry in fs::read_dir(&self.directory)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                names.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+
+        for name in expired_snapshots(names, self.retention as usize) {
+            let path = Path::new(&self.directory).join(name);
+            tracing::debug!("Deleting snapshot {}", path.display());
+            fs::remove_dir_all(&path)
+                .with_context(|| format!("{} could not be deleted", path.display()))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the snapshots which exceed the retention limit, oldest first.
+///
+/// Directories which are not snapshots are ignored. The timestamps in the names sort
+/// chronologically. The newest snapshot never expires, as it was just taken.
+fn expired_snapshots(mut names: Vec<String>, retention: usize) -> Vec<String> {
+    names.retain(|name| name.starts_with(SNAPSHOT_PREFIX));
+    names.sort();
+
+    let expired = names.len().saturating_sub(retention.max(1));
+    names.truncate(expired);
+    names
+}
+
+/// Parses a memory range given as `start..end`.
+pub fn parse_range(input: &str) -> Result<(u64, u64), String> {
+    let (start, end) = input
+        .split_once("..")
+        .ok_or_else(|| format!("'{input}' is not a range, expected `start..end`"))?;
+
+    let start = parse_int::parse::<u64>(start.trim()).map_err(|e| e.to_string())?;
+    let end = parse_int::parse::<u64>(end.trim()).map_err(|e| e.to_string())?;
+
+    if end <= start {
+        return Err(format!("The range '{input}' is empty"));
+    }
+
+    Ok((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oldest_snapshots_expire() {
+        let names = [
+            "snapshot-20240102T000000.000Z",
+            "notes",
+            "snapshot-20240101T000000.000Z",
+            "snapshot-20240103T000000.000Z",
+        ]
+        .map(String::from)
+        .to_vec();
+
+        assert_eq!(
+            expired_snapshots(names.clone(), 2),
+            ["snapshot-20240101T000000.000Z"]
+        );
+        assert!(expired_snapshots(names, 3).is_empty());
+    }
+
+    #[test]
+    fn newest_snapshot_is_kept() {
+        let names = [
+            "snapshot-20240101T000000.000Z",
+            "snapshot-20240102T000000.000Z",
+        ]
+        .map(String::from)
+        .to_vec();
+
+        assert_eq!(
+            expired_snapshots(names, 0),
+            ["snapshot-20240101T000000.000Z"]
+        );
+    }
+
+    #[test]
+    fn parse_ranges() {
+        assert_eq!(
+            parse_range("0x20000000..0x20001000"),
+            Ok((0x2000_0000, 0x2000_1000))
+        );
+        assert!(parse_range("0x20000000").is_err());
+        assert!(parse_range("0x100..0x100").is_err());
+    }
+}
@@ -0,0 +1,155 @@
+//! Receiving the output of ITM stimulus ports over SWO, which is displayed like RTT channels.
+
+use postcard_schema::Schema;
+use probe_rs::architecture::arm::SwoConfig;
+use serde::{Deserialize, Serialize};
+
+/// Configuration for receiving ITM stimulus port output over SWO.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Schema, clap::Args)]
+#[serde(rename_all = "camelCase")]
+pub struct SwoLogConfig {
+    /// Receive the output of the ITM stimulus ports over SWO, at this baud rate.
+    #[arg(long = "swo-baud", value_name = "BAUD", help_heading = "SWO")]
+    #[serde(default)]
+    pub baud: Option<u32>,
+
+    /// The frequency of the clock feeding the TPIU/SWO module in Hz, usually the core clock.
+    #[arg(long = "swo-clock", value_name = "HZ", help_heading = "SWO")]
+    #[serde(default)]
+    pub clock: Option<u32>,
+
+    /// The stimulus port that carries defmt encoded data, e.g. from `defmt-itm`. The other ports
+    /// are displayed as text.
+    #[arg(long = "swo-defmt-port", value_name = "PORT", help_heading = "SWO")]
+    #[serde(default)]
+    pub defmt_port: Option<u8>,
+}
+
+impl SwoLogConfig {
+    /// Returns the SWO configuration for the probe, if receiving SWO output is enabled.
+    pub fn swo_config(&self) -> anyhow::Result<Option<SwoConfig>> {
+        match (self.clock, self.baud) {
+            (Some(clock), Some(baud)) => Ok(Some(SwoConfig::new(clock).set_baud(baud))),
+            (None, None) => Ok(None),
+            _ => {
+                anyhow::bail!("Both the SWO clock and baud rate are required to receive SWO output")
+            }
+        }
+    }
+}
+
+/// Extracts the output of the ITM stimulus ports from an SWO data stream.
+///
+/// Packets of other sources, like DWT or timestamps, are skipped. Packets can be split across
+/// chunks, the incomplete end of a chunk is kept until the next one.
+#[derive(Debug, Default)]
+pub struct ItmDecoder {
+    buffer: Vec<u8>,
+}
+
+impl ItmDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes `data`, returning the output of each stimulus port in the order it was written.
+    pub fn decode(&mut self, data: &[u8]) -> Vec<(u8, Vec<u8>)> {
+        self.buffer.extend_from_slice(data);
+
+        let mut output: Vec<(u8, Vec<u8>)> = vec![];
+        let mut position = 0;
+        while position < self.buffer.len() {
+            let packet = &self.buffer[position..];
+            let Some(length) = packet_length(packet) else {
+                break;
+            };
+
+            let header = packet[0];
+            // Software source packets carry the stimulus port in the upper bits of the header.
+            if header & 0b11 != 0 && header & 0b100 == 0 {
+                let port = header >> 3;
+                let payload = &packet[1..length];
+                match output.last_mut() {
+                    Some((last, data)) if *last == port => data.extend_from_slice(payload),
+                    _ => output.push((port, payload.to_vec())),
+                }
+            }
+
+            position += length;
+        }
+
+        self.buffer.drain(..position);
+        output
+    }
+}
+
+/// Returns the length of the packet at the start of `data`, or `None` if it is incomplete.
+fn packet_length(data: &[u8]) -> Option<usize> {
+    let header = data[0];
+    let length = match header & 0b11 {
+        // Protocol packets. Sync, overflow and short local timestamps are just the header, the
+        // others are followed by payload bytes, where bit 7 marks that another byte follows.
+        0b00 => {
+            if header & 0x80 == 0 || header == 0x80 {
+                1
+            } else {
+                data[1..].iter().position(|byte| byte & 0x80 == 0)? + 2
+            }
+        }
+        0b01 => 2,
+        0b10 => 3,
+        _ => 5,
+    };
+
+    (data.len() >= length).then_some(length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_stimulus_ports() {
+        let mut decoder = ItmDecoder::new();
+
+        let data = [
+            // Sync packet
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x80, //
+            // Port 0, one byte each
+            0x01, b'h', 0x01, b'i', //
+            // Local timestamp with payload
+            0xc0, 0x81, 0x01, //
+            // Port 1, four bytes
+            0x0b, b'a', b'b', b'c', b'd', //
+            // DWT hardware packet
+            0x47, 0x12, 0x34, 0x56, 0x78, //
+            // Port 0, two bytes
+            0x02, b'!', b'\n',
+        ];
+
+        assert_eq!(
+            decoder.decode(&data),
+            vec![
+                (0, b"hi".to_vec()),
+                (1, b"abcd".to_vec()),
+                (0, b"!\n".to_vec())
+            ]
+        );
+    }
+
+    #[test]
+    fn packets_split_across_chunks() {
+        let mut decoder = ItmDecoder::new();
+
+        assert_eq!(
+            decoder.decode(&[0x01, b'a', 0x0b, b'b']),
+            vec![(0, b"a".to_vec())]
+        );
+        assert_eq!(decoder.decode(b"cd"), vec![]);
+        assert_eq!(decoder.decode(&[b'e', 0xc0]), vec![(1, b"bcde".to_vec())]);
+        assert_eq!(
+            decoder.decode(&[0x01, 0x01, b'f']),
+            vec![(0, b"f".to_vec())]
+        );
+    }
+}
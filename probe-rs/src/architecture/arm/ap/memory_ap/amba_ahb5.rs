@@ -3,7 +3,7 @@ use crate::architecture::arm::{
     ap::{AccessPortType, ApAccess, ApRegAccess, ApRegister, CFG, define_ap_register},
 };
 
-use super::{AddressIncrement, DataSize};
+use super::{AddressIncrement, DataSize, MemoryApConfig};
 
 /// Memory AP
 ///
@@ -88,6 +88,35 @@ impl super::MemoryApType for AmbaAhb5 {
     }
 }
 
+impl super::CachedCsw for AmbaAhb5 {
+    fn cached_csw(&mut self) -> &mut CSW {
+        &mut self.csw
+    }
+}
+
+impl super::MemoryApCsw for CSW {
+    fn address_increment(&self) -> AddressIncrement {
+        self.AddrInc
+    }
+
+    fn with_address_increment(self, increment: AddressIncrement) -> Self {
+        CSW {
+            AddrInc: increment,
+            ..self
+        }
+    }
+
+    fn with_config(self, config: &MemoryApConfig) -> Self {
+        CSW {
+            HNONSEC: config.non_secure.unwrap_or(self.HNONSEC),
+            Privileged: config.privileged.unwrap_or(self.Privileged),
+            CombinedHPROT346: config.cacheable.unwrap_or(self.CombinedHPROT346),
+            Bufferable: config.bufferable.unwrap_or(self.Bufferable),
+            ..self
+        }
+    }
+}
+
 impl AccessPortType for AmbaAhb5 {
     fn ap_address(&self) -> &FullyQualifiedApAddress {
         &self.address
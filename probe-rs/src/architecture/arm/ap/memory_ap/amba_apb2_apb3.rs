@@ -6,6 +6,8 @@ use crate::architecture::arm::{
     },
 };
 
+use super::MemoryApConfig;
+
 /// Memory AP
 ///
 /// The memory AP can be used to access a memory-mapped
@@ -75,6 +77,34 @@ impl super::MemoryApType for AmbaApb2Apb3 {
     }
 }
 
+impl super::CachedCsw for AmbaApb2Apb3 {
+    fn cached_csw(&mut self) -> &mut CSW {
+        &mut self.csw
+    }
+}
+
+impl super::MemoryApCsw for CSW {
+    fn address_increment(&self) -> AddressIncrement {
+        self.AddrInc
+    }
+
+    fn with_address_increment(self, increment: AddressIncrement) -> Self {
+        CSW {
+            AddrInc: increment,
+            ..self
+        }
+    }
+
+    fn with_config(self, config: &MemoryApConfig) -> Self {
+        // APB2 and APB3 have no protection signals, so there is nothing to configure.
+        tracing::debug!(
+            "Ignoring memory AP configuration {:?} for APB2/APB3 AP",
+            config
+        );
+        self
+    }
+}
+
 impl AccessPortType for AmbaApb2Apb3 {
     fn ap_address(&self) -> &FullyQualifiedApAddress {
         &self.address
@@ -6,6 +6,8 @@ use crate::architecture::arm::{
     },
 };
 
+use super::MemoryApConfig;
+
 /// Memory AP
 ///
 /// The memory AP can be used to access a memory-mapped
@@ -73,6 +75,33 @@ impl super::MemoryApType for AmbaApb4Apb5 {
     }
 }
 
+impl super::CachedCsw for AmbaApb4Apb5 {
+    fn cached_csw(&mut self) -> &mut CSW {
+        &mut self.csw
+    }
+}
+
+impl super::MemoryApCsw for CSW {
+    fn address_increment(&self) -> AddressIncrement {
+        self.AddrInc
+    }
+
+    fn with_address_increment(self, increment: AddressIncrement) -> Self {
+        CSW {
+            AddrInc: increment,
+            ..self
+        }
+    }
+
+    fn with_config(self, config: &MemoryApConfig) -> Self {
+        CSW {
+            NonSecure: config.non_secure.unwrap_or(self.NonSecure),
+            Privileged: config.privileged.unwrap_or(self.Privileged),
+            ..self
+        }
+    }
+}
+
 impl AccessPortType for AmbaApb4Apb5 {
     fn ap_address(&self) -> &FullyQualifiedApAddress {
         &self.address
@@ -3,7 +3,7 @@ use crate::architecture::arm::{
     ap::{AccessPortType, ApAccess, ApRegAccess, ApRegister, CFG, define_ap_register},
 };
 
-use super::{AddressIncrement, DataSize};
+use super::{AddressIncrement, DataSize, MemoryApConfig};
 
 /// Memory AP
 ///
@@ -85,6 +85,41 @@ impl super::MemoryApType for AmbaAxi5 {
     }
 }
 
+impl super::CachedCsw for AmbaAxi5 {
+    fn cached_csw(&mut self) -> &mut CSW {
+        &mut self.csw
+    }
+}
+
+impl super::MemoryApCsw for CSW {
+    fn address_increment(&self) -> AddressIncrement {
+        self.AddrInc
+    }
+
+    fn with_address_increment(self, increment: AddressIncrement) -> Self {
+        CSW {
+            AddrInc: increment,
+            ..self
+        }
+    }
+
+    fn with_config(self, config: &MemoryApConfig) -> Self {
+        let mut cache = self.CACHE;
+        if let Some(bufferable) = config.bufferable {
+            cache = (cache & !0b01) | u8::from(bufferable);
+        }
+        if let Some(cacheable) = config.cacheable {
+            cache = (cache & !0b10) | (u8::from(cacheable) << 1);
+        }
+        CSW {
+            NonSecure: config.non_secure.unwrap_or(self.NonSecure),
+            Privileged: config.privileged.unwrap_or(self.Privileged),
+            CACHE: cache,
+            ..self
+        }
+    }
+}
+
 impl AccessPortType for AmbaAxi5 {
     fn ap_address(&self) -> &FullyQualifiedApAddress {
         &self.address
@@ -12,6 +12,8 @@ use std::collections::HashMap;
 #[derive(Debug)]
 pub struct MockMemoryAp {
     pub memory: Vec<u8>,
+    /// Whether the AP supports packed transfers (`CSW.AddrInc == 0b10`).
+    pub packed_transfers: bool,
     store: HashMap<u64, u32>,
 }
 
@@ -46,9 +48,83 @@ impl MockMemoryAp {
         store.insert(DRW::ADDRESS, 0);
         Self {
             memory: std::iter::repeat(1..=255).flatten().take(size).collect(),
+            packed_transfers: false,
             store,
         }
     }
+
+    /// Performs a single bus read of `size` at `address`, placing the value in its byte lane
+    /// of `drw`.
+    ///
+    /// Returns the new DRW value and the number of bytes transferred.
+    fn read_transfer(&self, address: u32, size: DataSize, drw: u32) -> (u32, u32) {
+        let bit_offset = (address % 4) * 8;
+        let offset = address as usize;
+
+        match size {
+            DataSize::U32 => {
+                let bytes: [u8; 4] = self
+                    .memory
+                    .get(offset..offset + 4)
+                    .map(|v| v.try_into().unwrap())
+                    .unwrap_or([0u8; 4]);
+
+                (u32::from_le_bytes(bytes), 4)
+            }
+            DataSize::U16 => {
+                let bytes = self
+                    .memory
+                    .get(offset..offset + 2)
+                    .map(|v| v.try_into().unwrap())
+                    .unwrap_or([0u8; 2]);
+                let value = u16::from_le_bytes(bytes);
+                (
+                    drw & !(0xffff << bit_offset) | (u32::from(value) << bit_offset),
+                    2,
+                )
+            }
+            DataSize::U8 => {
+                let value = *self.memory.get(offset).unwrap_or(&0u8);
+                (
+                    drw & !(0xff << bit_offset) | (u32::from(value) << bit_offset),
+                    1,
+                )
+            }
+            _ => panic!("MockMemoryAp: unknown width"),
+        }
+    }
+
+    /// Performs a single bus write of `size` at `address`, taking the value from its byte lane
+    /// of `drw`.
+    ///
+    /// Returns the number of bytes transferred.
+    fn write_transfer(&mut self, address: u32, size: DataSize, drw: u32) -> u32 {
+        let access_width = size.to_byte_count() as u32;
+
+        if (address + access_width) as usize > self.memory.len() {
+            // Ignore out-of-bounds write
+            return access_width;
+        }
+
+        let bit_offset = (address % 4) * 8;
+        match size {
+            DataSize::U32 => {
+                self.memory[address as usize..address as usize + 4]
+                    .copy_from_slice(&drw.to_le_bytes());
+            }
+            DataSize::U16 => {
+                let value = drw >> bit_offset;
+                self.memory[address as usize] = value as u8;
+                self.memory[address as usize + 1] = (value >> 8) as u8;
+            }
+            DataSize::U8 => {
+                let value = drw >> bit_offset;
+                self.memory[address as usize] = value as u8;
+            }
+            _ => panic!("MockMemoryAp: unknown width"),
+        }
+        access_width
+    }
 }
 
 impl DapAccess for MockMemoryAp {
@@ -81,54 +157,31 @@ impl DapAccess for MockMemoryAp {
         tracing::debug!("Reading: addr {:x} store: {:x?}", addr, self.store);
 
         if addr == DRW::ADDRESS {
-            let drw = self.store[&DRW::ADDRESS];
-            let bit_offset = (address % 4) * 8;
-            let offset = address as usize;
             let csw = CSW::try_from(csw).unwrap();
 
-            let (new_drw, offset) = match csw.Size {
-                DataSize::U32 => {
-                    let bytes: [u8; 4] = self
-                        .memory
-                        .get(offset..offset + 4)
-                        .map(|v| v.try_into().unwrap())
-                        .unwrap_or([0u8; 4]);
-
-                    (u32::from_le_bytes(bytes), 4)
-                }
-                DataSize::U16 => {
-                    let bytes = self
-                        .memory
-                        .get(offset..offset + 2)
-                        .map(|v| v.try_into().unwrap())
-                        .unwrap_or([0u8; 2]);
-                    let value = u16::from_le_bytes(bytes);
-                    (
-                        drw & !(0xffff << bit_offset) | (u32::from(value) << bit_offset),
-                        2,
-                    )
+            let (new_drw, next_address) = match csw.AddrInc {
+                AddressIncrement::Packed => {
+                    let mut drw = self.store[&DRW::ADDRESS];
+                    let mut address = address;
+                    for _ in 0..4 / csw.Size.to_byte_count() {
+                        let (value, offset) = self.read_transfer(address, csw.Size, drw);
+                        drw = value;
+                        address += offset;
+                    }
+                    (drw, address)
                 }
-                DataSize::U8 => {
-                    let value = *self.memory.get(offset).unwrap_or(&0u8);
-                    (
-                        drw & !(0xff << bit_offset) | (u32::from(value) << bit_offset),
-                        1,
-                    )
+                increment => {
+                    let drw = self.store[&DRW::ADDRESS];
+                    let (drw, offset) = self.read_transfer(address, csw.Size, drw);
+                    match increment {
+                        AddressIncrement::Off => (drw, address),
+                        _ => (drw, address + offset),
+                    }
                 }
-                _ => panic!("MockMemoryAp: unknown width"),
             };
 
             self.store.insert(DRW::ADDRESS, new_drw);
-
-            match csw.AddrInc {
-                AddressIncrement::Single => {
-                    self.store.insert(TAR::ADDRESS, address + offset);
-                }
-                AddressIncrement::Off => (),
-                AddressIncrement::Packed => {
-                    unimplemented!();
-                }
-            }
+            self.store.insert(TAR::ADDRESS, next_address);
             tracing::debug!("Reading: new store: {:x?}", self.store);
 
             Ok(new_drw)
@@ -158,45 +211,33 @@ impl DapAccess for MockMemoryAp {
                 let csw = CSW::try_from(csw).unwrap();
                 tracing::debug!("csw: {:x?}", csw);
 
-                let access_width = csw.Size.to_byte_count() as u32;
-
-                if (address + access_width) as usize > self.memory.len() {
-                    // Ignore out-of-bounds write
-                    return Ok(());
-                }
-
-                let bit_offset = (address % 4) * 8;
-                match csw.Size {
-                    DataSize::U32 => {
-                        self.memory[address as usize..address as usize + 4]
-                            .copy_from_slice(&value.to_le_bytes());
-                        Ok(4)
-                    }
-                    DataSize::U16 => {
-                        let value = value >> bit_offset;
-                        self.memory[address as usize] = value as u8;
-                        self.memory[address as usize + 1] = (value >> 8) as u8;
-                        Ok(2)
+                let next_address = match csw.AddrInc {
+                    AddressIncrement::Packed => {
+                        let mut address = address;
+                        for _ in 0..4 / csw.Size.to_byte_count() {
+                            address += self.write_transfer(address, csw.Size, value);
+                        }
+                        address
                     }
-                    DataSize::U8 => {
-                        let value = value >> bit_offset;
-                        self.memory[address as usize] = value as u8;
-                        Ok(1)
+                    AddressIncrement::Off => {
+                        self.write_transfer(address, csw.Size, value);
+                        address
                     }
-                    _ => panic!("MockMemoryAp: unknown width"),
-                }
-                .map(|offset| match csw.AddrInc {
                     AddressIncrement::Single => {
-                        self.store.insert(TAR::ADDRESS, address + offset);
+                        address + self.write_transfer(address, csw.Size, value)
                     }
-                    AddressIncrement::Off => (),
-                    AddressIncrement::Packed => {
-                        unimplemented!();
-                    }
-                })
+                };
+
+                self.store.insert(TAR::ADDRESS, next_address);
+                Ok(())
             }
             CSW::ADDRESS => {
-                self.store.insert(CSW::ADDRESS, value);
+                let mut csw = CSW::try_from(value).unwrap();
+                if csw.AddrInc == AddressIncrement::Packed && !self.packed_transfers {
+                    // Emulate an AP without packed transfer support, which ignores the write.
+                    csw.AddrInc = AddressIncrement::Off;
+                }
+                self.store.insert(CSW::ADDRESS, csw.into());
                 Ok(())
             }
             TAR::ADDRESS => {
@@ -51,6 +51,47 @@ macro_rules! attached_regs_to_mem_ap {
 // Re-export the macro so that it can be used in this crate.
 pub(crate) use attached_regs_to_mem_ap;
 
+/// Bus access attributes which are applied to all transfers issued by a memory AP.
+///
+/// Fields which are `None` keep the value the memory AP was initialized with. Attributes which are
+/// not implemented by a specific memory AP type (e.g. `HPROT` on an APB2 memory AP) are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoryApConfig {
+    /// Mark accesses as non-secure (`HNONSEC` on AHB, `AxPROT[1]` on AXI, `PPROT[1]` on APB).
+    ///
+    /// Secure accesses are only possible if secure debug is enabled (`CSW.SPIDEN`).
+    pub non_secure: Option<bool>,
+    /// Mark accesses as privileged.
+    pub privileged: Option<bool>,
+    /// Mark accesses as cacheable (`HPROT[3]` on AHB, `AxCACHE[1]` on AXI).
+    pub cacheable: Option<bool>,
+    /// Mark accesses as bufferable (`HPROT[2]` on AHB, `AxCACHE[0]` on AXI).
+    pub bufferable: Option<bool>,
+}
+
+/// The CSW register of a specific memory AP type, with the fields used by the default methods
+/// of [`MemoryApType`].
+pub trait MemoryApCsw: ApRegister + Copy + PartialEq {
+    /// The address increment mode of DRW accesses.
+    fn address_increment(&self) -> AddressIncrement;
+
+    /// Returns this CSW with the address increment mode `increment`.
+    #[must_use]
+    fn with_address_increment(self, increment: AddressIncrement) -> Self;
+
+    /// Returns this CSW with the bus access attributes in `config`.
+    ///
+    /// Attributes the memory AP does not implement are ignored.
+    #[must_use]
+    fn with_config(self, config: &MemoryApConfig) -> Self;
+}
+
+/// A memory AP which caches its CSW register, to skip writes which would not change it.
+pub trait CachedCsw: MemoryApType<CSW: MemoryApCsw> + ApRegAccess<Self::CSW> {
+    /// The cached value of the CSW register.
+    fn cached_csw(&mut self) -> &mut Self::CSW;
+}
+
 /// Common trait for all memory access ports.
 pub trait MemoryApType:
     ApRegAccess<BASE> + ApRegAccess<BASE2> + ApRegAccess<TAR> + ApRegAccess<TAR2> + ApRegAccess<DRW>
@@ -81,6 +122,45 @@ pub trait MemoryApType:
         data_size: DataSize,
     ) -> Result<(), ArmError>;
 
+    /// Attempts to set the address increment mode used for DRW accesses.
+    ///
+    /// Returns `false` if the Memory AP does not implement the requested mode. This is
+    /// the case for [`AddressIncrement::Packed`] on Memory APs without packed transfer support.
+    fn try_set_address_increment<I: ApAccess>(
+        &mut self,
+        interface: &mut I,
+        increment: AddressIncrement,
+    ) -> Result<bool, ArmError>
+    where
+        Self: CachedCsw,
+    {
+        let csw = *self.cached_csw();
+        if increment != csw.address_increment() {
+            interface.write_ap_register(self, csw.with_address_increment(increment))?;
+            // Unsupported modes are not retained, so read back what the AP accepted.
+            *self.cached_csw() = interface.read_ap_register(self)?;
+        }
+        Ok(self.cached_csw().address_increment() == increment)
+    }
+
+    /// Applies the bus access attributes in `config` to all following transfers.
+    fn apply_config<I: ApAccess>(
+        &mut self,
+        interface: &mut I,
+        config: &MemoryApConfig,
+    ) -> Result<(), ArmError>
+    where
+        Self: CachedCsw,
+    {
+        let cached = *self.cached_csw();
+        let csw = cached.with_config(config);
+        if csw != cached {
+            interface.write_ap_register(self, csw)?;
+            *self.cached_csw() = csw;
+        }
+        Ok(())
+    }
+
     /// The current generic CSW (missing the memory AP specific fields).
     fn generic_status<I: ApAccess>(&mut self, interface: &mut I) -> Result<CSW, ArmError> {
         self.status(interface)?
@@ -281,3 +361,25 @@ impl MemoryApType for MemoryAp {
         mem_ap_forward!(self, generic_status(interface))
     }
 }
+
+// The CSW of the specific memory AP is cached by the memory AP itself, so these forward to it
+// instead of using the default methods of `MemoryApType`.
+impl MemoryAp {
+    /// See [`MemoryApType::try_set_address_increment`].
+    pub fn try_set_address_increment<I: ApAccess>(
+        &mut self,
+        interface: &mut I,
+        increment: AddressIncrement,
+    ) -> Result<bool, ArmError> {
+        mem_ap_forward!(self, try_set_address_increment(interface, increment))
+    }
+
+    /// See [`MemoryApType::apply_config`].
+    pub fn apply_config<I: ApAccess>(
+        &mut self,
+        interface: &mut I,
+        config: &MemoryApConfig,
+    ) -> Result<(), ArmError> {
+        mem_ap_forward!(self, apply_config(interface, config))
+    }
+}
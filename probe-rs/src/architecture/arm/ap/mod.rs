@@ -8,6 +8,7 @@ pub mod v2;
 
 pub use generic_ap::GenericAp;
 pub use memory_ap::MemoryAp;
+pub use memory_ap::MemoryApConfig;
 pub use memory_ap::MemoryApType;
 pub(crate) use registers::define_ap_register;
 pub use registers::{BASE, BASE2, BD0, BD1, BD2, BD3, CFG, CSW, DRW, IDR, MBT, TAR, TAR2};
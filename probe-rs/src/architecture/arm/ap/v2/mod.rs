@@ -6,10 +6,7 @@ use crate::architecture::arm::{
     ApAddress, ApV2Address, ArmCommunicationInterface, ArmDebugInterface, ArmError,
     FullyQualifiedApAddress,
     dp::DpAddress,
-    memory::{
-        ADIMemoryInterface, ArmMemoryInterface, Component, PeripheralType,
-        romtable::{CORESIGHT_ROM_TABLE_ARCHID, RomTable},
-    },
+    memory::{ADIMemoryInterface, ArmMemoryInterface, Component, PeripheralType},
 };
 
 mod root_memory_interface;
@@ -28,7 +25,10 @@ pub fn enumerate_access_ports<ADI: ArmDebugInterface>(
         base_addr,
     )?;
 
-    let result = process_root_component(&mut root_interface, &root_component)?;
+    // Nested ROM tables are parsed together with the root component, so all memory APs in the
+    // root address space can be collected from the component tree.
+    let mut result = BTreeSet::new();
+    collect_memory_aps(&root_component, &mut result);
 
     Ok(result
         .into_iter()
@@ -36,41 +36,16 @@ pub fn enumerate_access_ports<ADI: ArmDebugInterface>(
         .collect())
 }
 
-fn process_root_component<ADI: ArmDebugInterface>(
-    iface: &mut RootMemoryInterface<ADI>,
-    component: &Component,
-) -> Result<BTreeSet<ApV2Address>, ArmError> {
-    let mut result = BTreeSet::new();
-
+/// Recursively collects the memory APs of a component and of all the ROM tables nested in it.
+fn collect_memory_aps(component: &Component, result: &mut BTreeSet<ApV2Address>) {
     match component {
-        Component::CoresightComponent(c)
-            if c.peripheral_id().arch_id() == CORESIGHT_ROM_TABLE_ARCHID =>
-        {
-            let rom_table = RomTable::try_parse(iface, c.component_address())?;
+        Component::Class1RomTable(_, rom_table) | Component::Class9RomTable(_, rom_table) => {
             for e in rom_table.entries() {
-                if let Component::CoresightComponent(comp) = e.component() {
-                    if comp.peripheral_id().is_of_type(PeripheralType::MemAp) {
-                        let base_address = ApV2Address::new(comp.component_address());
-                        // TODO: Check this AP for further nested APs.
-                        result.insert(base_address);
-                    }
-                }
-            }
-        }
-        Component::Class1RomTable(_, rom_table) => {
-            for e in rom_table.entries() {
-                if let Component::CoresightComponent(comp) = e.component() {
-                    if comp.peripheral_id().is_of_type(PeripheralType::MemAp) {
-                        let base_address = ApV2Address::new(comp.component_address());
-                        // TODO: Check this AP for further nested APs.
-                        result.insert(base_address);
-                    }
-                }
+                collect_memory_aps(e.component(), result);
             }
         }
 
-        // If the root component is a memory AP, it's the only component in the system and we can
-        // return it immediately.
+        // A memory AP found in the root address space.
         Component::CoresightComponent(c) if c.peripheral_id().is_of_type(PeripheralType::MemAp) => {
             let base_address = ApV2Address::new(c.component_address());
             // TODO: Check this AP for further nested APs.
@@ -79,8 +54,6 @@ fn process_root_component<ADI: ArmDebugInterface>(
 
         _ => {}
     }
-
-    Ok(result)
 }
 
 /// Returns a Memory Interface accessing the Memory AP at the given `address` through the `iface`
@@ -96,6 +69,9 @@ pub fn new_memory_interface<'i>(
     if ap_address.0.is_none() {
         Ok(Box::new(RootMemoryInterface::new(iface, address.dp())?))
     } else {
-        Ok(Box::new(ADIMemoryInterface::new(iface, address)?))
+        let size = iface.tar_autoincrement_size(address);
+        Ok(Box::new(
+            ADIMemoryInterface::new(iface, address)?.with_tar_autoincrement_size(size),
+        ))
     }
 }
@@ -4,10 +4,13 @@ use crate::{
         ApAddress, ArmError, DapAccess, FullyQualifiedApAddress, RawDapAccess, RegisterAddress,
         SwoAccess, SwoConfig, ap,
         dp::{
-            Ctrl, DPIDR, DebugPortId, DebugPortVersion, DpAccess, DpAddress, DpRegisterAddress,
-            Select1, SelectV1, SelectV3,
+            Ctrl, DPIDR, DebugPortControl, DebugPortId, DebugPortVersion, DebugPowerConfig,
+            DpAccess, DpAddress, DpRegisterAddress, PowerDownRecovery, PowerStatus, Select1,
+            SelectV1, SelectV3, StickyErrors,
+        },
+        memory::{
+            ADIMemoryInterface, ArmMemoryInterface, Component, DEFAULT_TAR_AUTOINCREMENT_SIZE,
         },
-        memory::{ADIMemoryInterface, ArmMemoryInterface, Component},
         sequences::ArmDebugSequence,
     },
     probe::{DebugProbe, DebugProbeError, Probe, WireProtocol},
@@ -86,6 +89,40 @@ pub trait ArmDebugInterface: DapAccess + SwdSequence + SwoAccess + Send {
         &mut self,
         access_port: &FullyQualifiedApAddress,
     ) -> Result<Box<dyn ArmMemoryInterface + '_>, ArmError>;
+
+    /// Configures the debug power handshake of the debug ports, see [`DebugPowerConfig`].
+    ///
+    /// The power domains can be controlled explicitly with [`DebugPortControl`]. Interfaces whose
+    /// probe performs the power handshake itself ignore the configuration.
+    fn configure_debug_power(&mut self, config: DebugPowerConfig) {
+        let _ = config;
+    }
+
+    /// Lowers the protocol speed during the session when transfers keep failing because they
+    /// are corrupted on the wire, down to `floor_khz`. `None` disables lowering the speed.
+    ///
+    /// Interfaces whose probe doesn't report transfer errors ignore the configuration.
+    fn configure_speed_fallback(&mut self, floor_khz: Option<u32>) {
+        let _ = floor_khz;
+    }
+
+    /// Returns `true` once after the interface recovered a debug port which lost power, see
+    /// [`DebugPowerConfig::recovery`].
+    ///
+    /// The debug logic of the target was reset, so the caller has to restore the debug state.
+    fn take_power_loss(&mut self) -> bool {
+        false
+    }
+
+    /// Sets the size of the address range in which the TAR of the memory AP `access_port`
+    /// auto-increments, a power of two of at least 1 KiB.
+    ///
+    /// Memory transfers are split at the boundaries of this range, because the address wraps
+    /// around instead of incrementing beyond it. The ADI specification only guarantees 1 KiB, which
+    /// is used by default. Interfaces whose probe splits memory transfers itself ignore this.
+    fn set_tar_autoincrement_size(&mut self, access_port: &FullyQualifiedApAddress, size: u64) {
+        let _ = (access_port, size);
+    }
 }
 
 /// Read chip information from the ROM tables
@@ -175,6 +212,15 @@ pub struct ArmCommunicationInterface {
     current_dp: Option<DpAddress>,
     dps: HashMap<DpAddress, DpState>,
     use_overrun_detect: bool,
+    power: DebugPowerConfig,
+    /// A debug port lost power and was recovered since the last [`ArmDebugInterface::take_power_loss`].
+    power_lost: bool,
+    /// The lowest protocol speed in kHz the speed is lowered to when transfers keep failing.
+    speed_floor_khz: Option<u32>,
+    /// The number of access port accesses which failed in a row because of a corrupted transfer.
+    link_errors: u32,
+    /// The TAR auto-increment sizes of the memory APs which don't use the default size.
+    tar_autoincrement_sizes: HashMap<FullyQualifiedApAddress, u64>,
     sequence: Arc<dyn ArmDebugSequence>,
 }
 
@@ -205,10 +251,16 @@ impl ArmCommunicationInterface {
 
     /// Disconnect from all debug ports, by calling `debug_port_stop` on all DPs which we
     /// are connected to.
+    ///
+    /// The debug ports are left powered if [`DebugPowerConfig::power_down_on_detach`] is unset.
     fn disconnect(&mut self) {
         let probe = self.probe.as_deref_mut().unwrap();
 
-        if let Some(current_dp) = self.current_dp.take() {
+        if !self.power.power_down_on_detach {
+            if let Some(dp) = self.current_dp.take() {
+                tracing::info!("Leaving debug port {dp:x?} powered");
+            }
+        } else if let Some(current_dp) = self.current_dp.take() {
             let _stop_span = tracing::debug_span!("debug_port_stop").entered();
 
             // Stop the current DP, which may not be one of the known ones (i.e. RP2040 rescue DP).
@@ -260,8 +312,13 @@ impl ArmDebugInterface for ArmCommunicationInterface {
         access_port_address: &FullyQualifiedApAddress,
     ) -> Result<Box<dyn ArmMemoryInterface + '_>, ArmError> {
         let memory_interface = match access_port_address.ap() {
-            ApAddress::V1(_) => Box::new(ADIMemoryInterface::new(self, access_port_address)?)
-                as Box<dyn ArmMemoryInterface + '_>,
+            ApAddress::V1(_) => {
+                let size = self.tar_autoincrement_size(access_port_address);
+                Box::new(
+                    ADIMemoryInterface::new(self, access_port_address)?
+                        .with_tar_autoincrement_size(size),
+                ) as Box<dyn ArmMemoryInterface + '_>
+            }
             ApAddress::V2(_) => ap::v2::new_memory_interface(self, access_port_address)?,
         };
         Ok(memory_interface)
@@ -292,6 +349,23 @@ impl ArmDebugInterface for ArmCommunicationInterface {
         let _ = self.select_dp(dp)?;
         Ok(())
     }
+
+    fn configure_debug_power(&mut self, config: DebugPowerConfig) {
+        self.power = config;
+    }
+
+    fn configure_speed_fallback(&mut self, floor_khz: Option<u32>) {
+        self.speed_floor_khz = floor_khz;
+    }
+
+    fn take_power_loss(&mut self) -> bool {
+        std::mem::take(&mut self.power_lost)
+    }
+
+    fn set_tar_autoincrement_size(&mut self, access_port: &FullyQualifiedApAddress, size: u64) {
+        self.tar_autoincrement_sizes
+            .insert(access_port.clone(), size);
+    }
 }
 
 impl SwdSequence for ArmCommunicationInterface {
@@ -311,6 +385,10 @@ impl SwdSequence for ArmCommunicationInterface {
     }
 }
 
+/// The number of access port accesses which have to fail in a row because of corrupted transfers
+/// before the protocol speed is lowered.
+const LINK_ERRORS_BEFORE_SPEED_FALLBACK: u32 = 3;
+
 impl ArmCommunicationInterface {
     /// Create a new instance of the communication interface,
     /// which is not yet connected to a debug port.
@@ -324,12 +402,25 @@ impl ArmCommunicationInterface {
             current_dp: None,
             dps: Default::default(),
             use_overrun_detect,
+            power: DebugPowerConfig::default(),
+            power_lost: false,
+            speed_floor_khz: None,
+            link_errors: 0,
+            tar_autoincrement_sizes: HashMap::new(),
             sequence,
         };
 
         Box::new(interface)
     }
 
+    /// Returns the size of the address range in which the TAR of `access_port` auto-increments.
+    pub(crate) fn tar_autoincrement_size(&self, access_port: &FullyQualifiedApAddress) -> u64 {
+        self.tar_autoincrement_sizes
+            .get(access_port)
+            .copied()
+            .unwrap_or(DEFAULT_TAR_AUTOINCREMENT_SIZE)
+    }
+
     /// Inform the probe of the [`CoreStatus`] of the chip attached to the probe.
     pub fn core_status_notification(&mut self, state: CoreStatus) {
         self.probe_mut().core_status_notification(state).ok();
@@ -412,6 +503,127 @@ impl ArmCommunicationInterface {
         Ok(self.dps.get_mut(&dp).unwrap())
     }
 
+    /// Runs an AP `access`, and recovers the debug port if the access failed because the debug
+    /// port lost power.
+    ///
+    /// The failed access is not repeated, because the access port registers, like the TAR, were
+    /// reset together with the power domain.
+    ///
+    /// The protocol speed is lowered if accesses keep failing because of corrupted transfers,
+    /// see [`ArmDebugInterface::configure_speed_fallback`].
+    fn with_power_recovery<R>(
+        &mut self,
+        dp: DpAddress,
+        access: impl FnOnce(&mut Self) -> Result<R, ArmError>,
+    ) -> Result<R, ArmError> {
+        let error = match access(self) {
+            Err(error) if error.is_link_error() => {
+                self.link_errors += 1;
+                if self.link_errors >= LINK_ERRORS_BEFORE_SPEED_FALLBACK {
+                    self.link_errors = 0;
+                    self.lower_speed(dp, &error)?;
+                }
+                return Err(error);
+            }
+            Err(error) if error.is_power_down() => error,
+            result => {
+                self.link_errors = 0;
+                return result;
+            }
+        };
+
+        let PowerDownRecovery::Reconnect { attempts, delay } = self.power.recovery else {
+            return Err(error);
+        };
+
+        match self.read_dp_register::<Ctrl>(dp) {
+            Ok(ctrl) => {
+                if !ctrl.sticky_err() && PowerStatus::from_ctrl(&ctrl).is_powered() {
+                    return Err(error);
+                }
+
+                tracing::warn!("Debug port {dp:x?} lost power ({error}), powering it up again");
+                self.write_dp_register(dp, StickyErrors::from_ctrl(&ctrl).abort())?;
+                let timeout = self.power.timeout;
+                self.power_up(dp, timeout)?;
+                self.restore_select(dp)?;
+            }
+            Err(_) => {
+                tracing::warn!("Debug port {dp:x?} doesn't respond ({error}), reconnecting");
+                let mut result = Ok(());
+                for attempt in 0..attempts.max(1) {
+                    if attempt > 0 {
+                        std::thread::sleep(delay);
+                    }
+
+                    tracing::debug!("Reconnecting after a power-down, attempt {}", attempt + 1);
+                    result = self.reinitialize();
+                    if result.is_ok() {
+                        break;
+                    }
+                }
+                result?;
+            }
+        }
+
+        self.power_lost = true;
+
+        Err(error)
+    }
+
+    /// Halves the protocol speed, down to the speed floor, and reconnects to the debug port `dp`.
+    ///
+    /// The access that failed is not repeated, as a block transfer may have been partially
+    /// completed.
+    fn lower_speed(&mut self, dp: DpAddress, error: &ArmError) -> Result<(), ArmError> {
+        let Some(floor) = self.speed_floor_khz else {
+            return Ok(());
+        };
+
+        let speed = self.probe_mut().speed_khz();
+        if speed / 2 < floor {
+            tracing::debug!("Transfers are failing at the speed floor of {floor} kHz");
+            return Ok(());
+        }
+
+        let lowered = match self.probe_mut().set_speed(speed / 2) {
+            Ok(lowered) if lowered < speed => lowered,
+            Ok(_) => return Ok(()),
+            Err(set_speed_error) => {
+                tracing::debug!("Failed to lower the protocol speed: {set_speed_error}");
+                return Ok(());
+            }
+        };
+
+        tracing::warn!(
+            "Transfers keep failing at {speed} kHz ({error}), lowering the protocol speed to {lowered} kHz"
+        );
+
+        // The debug port stays powered, only the connection is set up again at the new speed.
+        let sequence = self.sequence.clone();
+        sequence.debug_port_connect(self.probe_mut(), dp)?;
+        let ctrl: Ctrl = self.read_dp_register(dp)?;
+        self.write_dp_register(dp, StickyErrors::from_ctrl(&ctrl).abort())?;
+        self.restore_select(dp)
+    }
+
+    /// Writes the cached SELECT value to the debug port, whose registers were reset by a power loss.
+    fn restore_select(&mut self, dp: DpAddress) -> Result<(), ArmError> {
+        let Some(state) = self.dps.get(&dp) else {
+            return Ok(());
+        };
+
+        match state.current_select {
+            SelectCache::DPv1(select) => self.write_dp_register(dp, select)?,
+            SelectCache::DPv3(select, select1) => {
+                self.write_dp_register(dp, select)?;
+                self.write_dp_register(dp, select1)?;
+            }
+        }
+
+        Ok(())
+    }
+
     fn select_dp_and_dp_bank(
         &mut self,
         dp: DpAddress,
@@ -542,13 +754,13 @@ impl DapAccess for ArmCommunicationInterface {
         ap: &FullyQualifiedApAddress,
         address: u64,
     ) -> Result<u32, ArmError> {
-        self.select_ap_and_ap_bank(ap, address)?;
+        self.with_power_recovery(ap.dp(), |interface| {
+            interface.select_ap_and_ap_bank(ap, address)?;
 
-        let result = self
-            .probe_mut()
-            .raw_read_register(RegisterAddress::ApRegister((address & 0xFF) as u8))?;
-
-        Ok(result)
+            interface
+                .probe_mut()
+                .raw_read_register(RegisterAddress::ApRegister((address & 0xFF) as u8))
+        })
     }
 
     fn read_raw_ap_register_repeated(
@@ -557,11 +769,13 @@ impl DapAccess for ArmCommunicationInterface {
         address: u64,
         values: &mut [u32],
     ) -> Result<(), ArmError> {
-        self.select_ap_and_ap_bank(ap, address)?;
+        self.with_power_recovery(ap.dp(), |interface| {
+            interface.select_ap_and_ap_bank(ap, address)?;
 
-        self.probe_mut()
-            .raw_read_block(RegisterAddress::ApRegister((address & 0xFF) as u8), values)?;
-        Ok(())
+            interface
+                .probe_mut()
+                .raw_read_block(RegisterAddress::ApRegister((address & 0xFF) as u8), values)
+        })
     }
 
     fn write_raw_ap_register(
@@ -570,12 +784,13 @@ impl DapAccess for ArmCommunicationInterface {
         address: u64,
         value: u32,
     ) -> Result<(), ArmError> {
-        self.select_ap_and_ap_bank(ap, address)?;
-
-        self.probe_mut()
-            .raw_write_register(RegisterAddress::ApRegister((address & 0xFF) as u8), value)?;
+        self.with_power_recovery(ap.dp(), |interface| {
+            interface.select_ap_and_ap_bank(ap, address)?;
 
-        Ok(())
+            interface
+                .probe_mut()
+                .raw_write_register(RegisterAddress::ApRegister((address & 0xFF) as u8), value)
+        })
     }
 
     fn write_raw_ap_register_repeated(
@@ -584,11 +799,13 @@ impl DapAccess for ArmCommunicationInterface {
         address: u64,
         values: &[u32],
     ) -> Result<(), ArmError> {
-        self.select_ap_and_ap_bank(ap, address)?;
+        self.with_power_recovery(ap.dp(), |interface| {
+            interface.select_ap_and_ap_bank(ap, address)?;
 
-        self.probe_mut()
-            .raw_write_block(RegisterAddress::ApRegister((address & 0xFF) as u8), values)?;
-        Ok(())
+            interface
+                .probe_mut()
+                .raw_write_block(RegisterAddress::ApRegister((address & 0xFF) as u8), values)
+        })
     }
 
     fn flush(&mut self) -> Result<(), ArmError> {
@@ -633,3 +850,280 @@ impl std::fmt::Display for ArmChipInfo {
         write!(f, "{} 0x{:04x}", manu, self.part)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        architecture::arm::{DapError, dp::DpRegister},
+        probe::fake_probe::FakeProbe,
+    };
+    use std::sync::Mutex;
+
+    /// The connect sequence needs SWJ sequences, which the fake probe doesn't support.
+    #[derive(Debug)]
+    struct NoSetupSequence;
+
+    impl ArmDebugSequence for NoSetupSequence {
+        fn debug_port_setup(
+            &self,
+            _interface: &mut dyn DapProbe,
+            _dp: DpAddress,
+        ) -> Result<(), ArmError> {
+            Ok(())
+        }
+
+        fn debug_port_connect(
+            &self,
+            _interface: &mut dyn DapProbe,
+            _dp: DpAddress,
+        ) -> Result<(), ArmError> {
+            Ok(())
+        }
+    }
+
+    /// A debug port whose power acknowledge follows the requests.
+    #[derive(Debug, Default)]
+    struct SimulatedDp {
+        /// The power requests and the lane mask written to CTRL/STAT.
+        ctrl: u32,
+        sticky_error: bool,
+        /// The number of transfers the debug port doesn't respond to.
+        unresponsive_transfers: usize,
+        /// The number of transfers which are corrupted on the wire.
+        corrupted_transfers: usize,
+    }
+
+    impl SimulatedDp {
+        fn transfer(&mut self) -> Result<(), ArmError> {
+            if self.unresponsive_transfers > 0 {
+                self.unresponsive_transfers -= 1;
+                return Err(ArmError::Dap(DapError::NoAcknowledge));
+            }
+            if self.corrupted_transfers > 0 {
+                self.corrupted_transfers -= 1;
+                return Err(ArmError::Dap(DapError::IncorrectParity));
+            }
+
+            Ok(())
+        }
+
+        fn powered(&self) -> bool {
+            PowerStatus::from_ctrl(&Ctrl(self.ctrl())).is_powered()
+        }
+
+        fn ctrl(&self) -> u32 {
+            // Mirror CDBGPWRUPREQ and CSYSPWRUPREQ to the acknowledge bits.
+            // STICKYERR is bit 5.
+            self.ctrl | (self.ctrl & 0x5000_0000) << 1 | u32::from(self.sticky_error) << 5
+        }
+
+        /// The target entered a low-power mode, which reset its debug power domain.
+        fn lose_power(&mut self) {
+            self.ctrl = 0;
+        }
+    }
+
+    fn interface(
+        dp: &Arc<Mutex<SimulatedDp>>,
+        recovery: PowerDownRecovery,
+    ) -> Box<dyn ArmDebugInterface> {
+        let mut probe = FakeProbe::new();
+
+        let read_dp = dp.clone();
+        probe.set_dap_register_read_handler(Box::new(move |address| {
+            let mut dp = read_dp.lock().unwrap();
+            dp.transfer()?;
+
+            match address {
+                RegisterAddress::DpRegister(address) if address == DPIDR::ADDRESS => {
+                    Ok(0x2ba0_1477)
+                }
+                RegisterAddress::DpRegister(address) if address == Ctrl::ADDRESS => Ok(dp.ctrl()),
+                RegisterAddress::DpRegister(_) => Ok(0),
+                RegisterAddress::ApRegister(_) if dp.powered() => Ok(0x1234_5678),
+                RegisterAddress::ApRegister(_) => {
+                    dp.sticky_error = true;
+                    Err(ArmError::Dap(DapError::FaultResponse))
+                }
+            }
+        }));
+
+        let write_dp = dp.clone();
+        probe.set_dap_register_write_handler(Box::new(move |address, value| {
+            let mut dp = write_dp.lock().unwrap();
+            dp.transfer()?;
+
+            match address {
+                // ABORT
+                RegisterAddress::DpRegister(address) if address.address == 0 => {
+                    // STKERRCLR is bit 2.
+                    if value & (1 << 2) != 0 {
+                        dp.sticky_error = false;
+                    }
+                }
+                RegisterAddress::DpRegister(address) if address == Ctrl::ADDRESS => {
+                    dp.ctrl = value & 0x5000_0f00;
+                }
+                _ => {}
+            }
+
+            Ok(())
+        }));
+
+        let mut interface =
+            ArmCommunicationInterface::create(Box::new(probe), Arc::new(NoSetupSequence), false);
+        interface.configure_debug_power(DebugPowerConfig {
+            timeout: Duration::from_millis(10),
+            recovery,
+            ..DebugPowerConfig::default()
+        });
+        interface.select_debug_port(DpAddress::Default).unwrap();
+
+        interface
+    }
+
+    const RECONNECT: PowerDownRecovery = PowerDownRecovery::Reconnect {
+        attempts: 3,
+        delay: Duration::ZERO,
+    };
+
+    #[test]
+    fn power_loss_is_recovered() {
+        let dp = Arc::new(Mutex::new(SimulatedDp::default()));
+        let mut interface = interface(&dp, RECONNECT);
+        let ap = FullyQualifiedApAddress::v1_with_default_dp(0);
+
+        assert_eq!(
+            interface.read_raw_ap_register(&ap, 0xFC).unwrap(),
+            0x1234_5678
+        );
+        assert!(!interface.take_power_loss());
+
+        dp.lock().unwrap().lose_power();
+
+        // The access itself fails, the access port registers were reset with the power domain.
+        assert!(interface.read_raw_ap_register(&ap, 0xFC).is_err());
+        {
+            let dp = dp.lock().unwrap();
+            assert!(dp.powered());
+            assert!(!dp.sticky_error);
+        }
+        assert!(interface.take_power_loss());
+        assert!(!interface.take_power_loss());
+
+        assert_eq!(
+            interface.read_raw_ap_register(&ap, 0xFC).unwrap(),
+            0x1234_5678
+        );
+    }
+
+    #[test]
+    fn unresponsive_debug_port_is_reconnected() {
+        let dp = Arc::new(Mutex::new(SimulatedDp::default()));
+        let mut interface = interface(&dp, RECONNECT);
+        let ap = FullyQualifiedApAddress::v1_with_default_dp(0);
+
+        {
+            let mut dp = dp.lock().unwrap();
+            dp.lose_power();
+            // The failed access, the CTRL/STAT read, powering down the debug port and the first
+            // reconnection attempt.
+            dp.unresponsive_transfers = 4;
+        }
+
+        assert!(interface.read_raw_ap_register(&ap, 0xFC).is_err());
+        assert!(dp.lock().unwrap().powered());
+        assert!(interface.take_power_loss());
+
+        assert_eq!(
+            interface.read_raw_ap_register(&ap, 0xFC).unwrap(),
+            0x1234_5678
+        );
+    }
+
+    #[test]
+    fn power_loss_fails_without_recovery() {
+        let dp = Arc::new(Mutex::new(SimulatedDp::default()));
+        let mut interface = interface(&dp, PowerDownRecovery::Fail);
+        let ap = FullyQualifiedApAddress::v1_with_default_dp(0);
+
+        dp.lock().unwrap().lose_power();
+
+        assert!(interface.read_raw_ap_register(&ap, 0xFC).is_err());
+        assert!(!dp.lock().unwrap().powered());
+        assert!(!interface.take_power_loss());
+    }
+
+    fn speed(interface: &dyn ArmDebugInterface) -> u32 {
+        interface.try_dap_probe().unwrap().speed_khz()
+    }
+
+    #[test]
+    fn speed_is_lowered_after_repeated_link_errors() {
+        let dp = Arc::new(Mutex::new(SimulatedDp::default()));
+        let mut interface = interface(&dp, PowerDownRecovery::Fail);
+        interface.configure_speed_fallback(Some(100));
+        let ap = FullyQualifiedApAddress::v1_with_default_dp(0);
+        assert_eq!(speed(&*interface), 1000);
+
+        dp.lock().unwrap().corrupted_transfers = LINK_ERRORS_BEFORE_SPEED_FALLBACK as usize;
+        for _ in 0..LINK_ERRORS_BEFORE_SPEED_FALLBACK {
+            assert!(interface.read_raw_ap_register(&ap, 0xFC).is_err());
+        }
+        assert_eq!(speed(&*interface), 500);
+
+        assert_eq!(
+            interface.read_raw_ap_register(&ap, 0xFC).unwrap(),
+            0x1234_5678
+        );
+    }
+
+    #[test]
+    fn speed_is_kept_after_occasional_link_errors() {
+        let dp = Arc::new(Mutex::new(SimulatedDp::default()));
+        let mut interface = interface(&dp, PowerDownRecovery::Fail);
+        interface.configure_speed_fallback(Some(100));
+        let ap = FullyQualifiedApAddress::v1_with_default_dp(0);
+
+        for _ in 0..2 {
+            dp.lock().unwrap().corrupted_transfers = LINK_ERRORS_BEFORE_SPEED_FALLBACK as usize - 1;
+            for _ in 1..LINK_ERRORS_BEFORE_SPEED_FALLBACK {
+                assert!(interface.read_raw_ap_register(&ap, 0xFC).is_err());
+            }
+            // A successful access resets the count.
+            interface.read_raw_ap_register(&ap, 0xFC).unwrap();
+        }
+
+        assert_eq!(speed(&*interface), 1000);
+    }
+
+    #[test]
+    fn speed_is_not_lowered_below_the_floor() {
+        let dp = Arc::new(Mutex::new(SimulatedDp::default()));
+        let mut interface = interface(&dp, PowerDownRecovery::Fail);
+        interface.configure_speed_fallback(Some(600));
+        let ap = FullyQualifiedApAddress::v1_with_default_dp(0);
+
+        dp.lock().unwrap().corrupted_transfers = LINK_ERRORS_BEFORE_SPEED_FALLBACK as usize;
+        for _ in 0..LINK_ERRORS_BEFORE_SPEED_FALLBACK {
+            assert!(interface.read_raw_ap_register(&ap, 0xFC).is_err());
+        }
+
+        assert_eq!(speed(&*interface), 1000);
+    }
+
+    #[test]
+    fn speed_is_not_lowered_without_fallback() {
+        let dp = Arc::new(Mutex::new(SimulatedDp::default()));
+        let mut interface = interface(&dp, PowerDownRecovery::Fail);
+        let ap = FullyQualifiedApAddress::v1_with_default_dp(0);
+
+        dp.lock().unwrap().corrupted_transfers = LINK_ERRORS_BEFORE_SPEED_FALLBACK as usize;
+        for _ in 0..LINK_ERRORS_BEFORE_SPEED_FALLBACK {
+            assert!(interface.read_raw_ap_register(&ap, 0xFC).is_err());
+        }
+
+        assert_eq!(speed(&*interface), 1000);
+    }
+}
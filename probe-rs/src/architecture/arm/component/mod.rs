@@ -134,7 +134,7 @@ pub fn get_arm_components(
                 components.push(component);
             }
             Err(e) => {
-                tracing::info!("Not counting AP {} because of: {}", ap_index.ap_v1()?, e);
+                tracing::info!("Not counting AP {:x?} because of: {}", ap_index.ap(), e);
             }
         }
     }
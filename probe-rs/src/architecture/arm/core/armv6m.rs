@@ -5,6 +5,7 @@ use crate::{
     Architecture, BreakpointCause, CoreInformation, CoreInterface, CoreRegister, CoreStatus,
     CoreType, HaltReason, InstructionSet, MemoryInterface, MemoryMappedRegister,
     architecture::arm::{ArmError, memory::ArmMemoryInterface, sequences::ArmDebugSequence},
+    config::ResetStrategy,
     core::{CoreRegisters, RegisterId, RegisterValue, VectorCatchCondition},
     error::Error,
     memory::{CoreMemoryInterface, valid_32bit_address},
@@ -624,6 +625,20 @@ impl CoreInterface for Armv6m<'_> {
         Ok(())
     }
 
+    fn reset_with(&mut self, strategy: ResetStrategy) -> Result<(), Error> {
+        self.state.semihosting_command = None;
+
+        super::cortex_m::reset_with(
+            &mut *self.memory,
+            &*self.sequence,
+            crate::CoreType::Armv6m,
+            strategy,
+        )?;
+        // Invalidate cached core status
+        self.set_core_status(CoreStatus::Unknown);
+        Ok(())
+    }
+
     fn reset_and_halt(&mut self, _timeout: Duration) -> Result<CoreInformation, Error> {
         self.reset_catch_set()?;
 
@@ -680,6 +695,12 @@ impl CoreInterface for Armv6m<'_> {
             None
         };
 
+        // A software breakpoint at the PC is replaced by the original instruction for the step.
+        let suspended_sw_breakpoint = self
+            .state
+            .sw_breakpoints
+            .suspend(&mut *self.memory, breakpoint_at_pc)?;
+
         let mut value = Dhcsr(0);
         // Leave halted state.
         // Step one instruction.
@@ -703,6 +724,8 @@ impl CoreInterface for Armv6m<'_> {
             self.halt(Duration::from_millis(100))?;
         }
 
+        suspended_sw_breakpoint.resume(&self.state.sw_breakpoints, &mut *self.memory)?;
+
         // Try to read the new program counter.
         let mut pc_after_step = self.read_core_reg(self.program_counter().into())?;
 
@@ -750,6 +773,28 @@ impl CoreInterface for Armv6m<'_> {
         }
     }
 
+    fn read_core_regs(&mut self, addresses: &[RegisterId]) -> Result<Vec<RegisterValue>, Error> {
+        if self.state.current_state.is_halted() {
+            let values = super::cortex_m::read_core_regs(&mut *self.memory, addresses)?;
+            Ok(values.into_iter().map(RegisterValue::from).collect())
+        } else {
+            Err(Error::Arm(ArmError::CoreNotHalted))
+        }
+    }
+
+    fn write_core_regs(&mut self, registers: &[(RegisterId, RegisterValue)]) -> Result<(), Error> {
+        if self.state.current_state.is_halted() {
+            let registers = registers
+                .iter()
+                .map(|&(address, value)| Ok((address, value.try_into()?)))
+                .collect::<Result<Vec<(RegisterId, u32)>, Error>>()?;
+            super::cortex_m::write_core_regs(&mut *self.memory, &registers)?;
+            Ok(())
+        } else {
+            Err(Error::Arm(ArmError::CoreNotHalted))
+        }
+    }
+
     fn available_breakpoint_units(&mut self) -> Result<u32, Error> {
         let result = self.memory.read_word_32(BpCtrl::get_mmio_address())?;
 
@@ -858,6 +903,18 @@ impl CoreInterface for Armv6m<'_> {
         self.state.hw_breakpoints_enabled
     }
 
+    fn set_sw_breakpoint(&mut self, address: u64) -> Result<(), Error> {
+        self.state.sw_breakpoints.set(&mut *self.memory, address)
+    }
+
+    fn clear_sw_breakpoint(&mut self, address: u64) -> Result<(), Error> {
+        self.state.sw_breakpoints.clear(&mut *self.memory, address)
+    }
+
+    fn sw_breakpoints(&self) -> Vec<u64> {
+        self.state.sw_breakpoints.addresses()
+    }
+
     fn architecture(&self) -> Architecture {
         Architecture::Arm
     }
@@ -221,17 +221,17 @@ impl Dbgdscr {
                 // Breakpoint debug event
                 0b0001 => HaltReason::Breakpoint(BreakpointCause::Hardware),
                 // Async watchpoint debug event
-                0b0010 => HaltReason::Watchpoint,
+                0b0010 => HaltReason::Watchpoint(None),
                 // BKPT instruction
                 0b0011 => HaltReason::Breakpoint(BreakpointCause::Software),
                 // External halt request
                 0b0100 => HaltReason::External,
                 // Vector catch
-                0b0101 => HaltReason::Exception,
+                0b0101 => HaltReason::Exception(None),
                 // OS Unlock vector catch
-                0b1000 => HaltReason::Exception,
+                0b1000 => HaltReason::Exception(None),
                 // Sync watchpoint debug event
-                0b1010 => HaltReason::Watchpoint,
+                0b1010 => HaltReason::Watchpoint(None),
                 // All other values are reserved
                 _ => HaltReason::Unknown,
             }
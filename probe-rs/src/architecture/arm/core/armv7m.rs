@@ -13,6 +13,7 @@ use crate::{
         ArmError, core::registers::cortex_m::XPSR, memory::ArmMemoryInterface,
         sequences::ArmDebugSequence,
     },
+    config::ResetStrategy,
     core::{
         Architecture, CoreInformation, CoreInterface, CoreRegisters, CoreStatus, HaltReason,
         MemoryMappedRegister, RegisterId, RegisterValue, VectorCatchCondition,
@@ -814,6 +815,20 @@ impl CoreInterface for Armv7m<'_> {
         Ok(())
     }
 
+    fn reset_with(&mut self, strategy: ResetStrategy) -> Result<(), Error> {
+        self.state.semihosting_command = None;
+
+        super::cortex_m::reset_with(
+            &mut *self.memory,
+            &*self.sequence,
+            crate::CoreType::Armv7m,
+            strategy,
+        )?;
+        // Invalidate cached core status
+        self.set_core_status(CoreStatus::Unknown);
+        Ok(())
+    }
+
     fn reset_and_halt(&mut self, _timeout: Duration) -> Result<CoreInformation, Error> {
         // Set the vc_corereset bit in the DEMCR register.
         // This will halt the core after reset.
@@ -872,6 +887,12 @@ impl CoreInterface for Armv7m<'_> {
             None
         };
 
+        // A software breakpoint at the PC is replaced by the original instruction for the step.
+        let suspended_sw_breakpoint = self
+            .state
+            .sw_breakpoints
+            .suspend(&mut *self.memory, breakpoint_at_pc)?;
+
         let mut dhcsr = Dhcsr(self.memory.read_word_32(Dhcsr::get_mmio_address())?);
 
         // Follow the rules of the ... ARMv7-M Architecture reference, C1.6 Debug System Registers - DHCSR, with respect to setting maskints
@@ -905,6 +926,8 @@ impl CoreInterface for Armv7m<'_> {
             self.halt(Duration::from_millis(100))?;
         }
 
+        suspended_sw_breakpoint.resume(&self.state.sw_breakpoints, &mut *self.memory)?;
+
         // Try to read the new program counter.
         let mut pc_after_step = self.read_core_reg(self.program_counter().into())?;
 
@@ -952,6 +975,28 @@ impl CoreInterface for Armv7m<'_> {
         }
     }
 
+    fn read_core_regs(&mut self, addresses: &[RegisterId]) -> Result<Vec<RegisterValue>, Error> {
+        if self.state.current_state.is_halted() {
+            let values = super::cortex_m::read_core_regs(&mut *self.memory, addresses)?;
+            Ok(values.into_iter().map(RegisterValue::from).collect())
+        } else {
+            Err(Error::Arm(ArmError::CoreNotHalted))
+        }
+    }
+
+    fn write_core_regs(&mut self, registers: &[(RegisterId, RegisterValue)]) -> Result<(), Error> {
+        if self.state.current_state.is_halted() {
+            let registers = registers
+                .iter()
+                .map(|&(address, value)| Ok((address, value.try_into()?)))
+                .collect::<Result<Vec<(RegisterId, u32)>, Error>>()?;
+            super::cortex_m::write_core_regs(&mut *self.memory, &registers)?;
+            Ok(())
+        } else {
+            Err(Error::Arm(ArmError::CoreNotHalted))
+        }
+    }
+
     fn available_breakpoint_units(&mut self) -> Result<u32, Error> {
         let raw_val = self.memory.read_word_32(FpCtrl::get_mmio_address())?;
 
@@ -1095,6 +1140,18 @@ impl CoreInterface for Armv7m<'_> {
         self.state.hw_breakpoints_enabled
     }
 
+    fn set_sw_breakpoint(&mut self, address: u64) -> Result<(), Error> {
+        self.state.sw_breakpoints.set(&mut *self.memory, address)
+    }
+
+    fn clear_sw_breakpoint(&mut self, address: u64) -> Result<(), Error> {
+        self.state.sw_breakpoints.clear(&mut *self.memory, address)
+    }
+
+    fn sw_breakpoints(&self) -> Vec<u64> {
+        self.state.sw_breakpoints.addresses()
+    }
+
     fn architecture(&self) -> Architecture {
         Architecture::Arm
     }
@@ -1181,6 +1238,14 @@ impl CoreInterface for Armv7m<'_> {
             .write_word_32(Demcr::get_mmio_address(), demcr.into())?;
         Ok(())
     }
+
+    fn cycle_count(&mut self) -> Result<Option<u64>, Error> {
+        Ok(super::cortex_m::read_cycle_count(&mut *self.memory)?)
+    }
+
+    fn read_swo(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        Ok(Some(super::cortex_m::read_swo(&mut *self.memory)?))
+    }
 }
 
 impl CoreMemoryInterface for Armv7m<'_> {
@@ -10,7 +10,7 @@ use super::{
 };
 use crate::{
     Architecture, CoreInformation, CoreInterface, CoreRegister, CoreStatus, CoreType,
-    InstructionSet, MemoryInterface,
+    InstructionSet, MemoryInterface, WatchpointAccess,
     architecture::arm::{
         ArmError, core::armv8a_debug_regs::*, memory::ArmMemoryInterface,
         sequences::ArmDebugSequence,
@@ -40,6 +40,10 @@ pub enum Armv8aError {
     /// Data Abort occurred
     #[error("A data abort occurred")]
     DataAbort,
+
+    /// The watchpoint can not be configured
+    #[error("A watchpoint of {1} bytes at {0:#x} is not supported")]
+    UnsupportedWatchpoint(u64, usize),
 }
 
 /// When in 32-bit mode the two words have to be placed in swapped
@@ -60,6 +64,8 @@ pub struct Armv8a<'probe> {
     sequence: Arc<dyn ArmDebugSequence>,
 
     num_breakpoints: Option<u32>,
+
+    num_watchpoints: Option<u32>,
 }
 
 impl<'probe> Armv8a<'probe> {
@@ -100,6 +106,7 @@ impl<'probe> Armv8a<'probe> {
             cti_address,
             sequence,
             num_breakpoints: None,
+            num_watchpoints: None,
         };
 
         if !core.state.initialized() {
@@ -1363,6 +1370,100 @@ impl CoreInterface for Armv8a<'_> {
         Ok(())
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        if self.num_watchpoints.is_none() {
+            let address = Eddfr::get_mmio_address_from_base(self.base_address)?;
+            let eddfr = Eddfr(self.memory.read_word_32(address)?);
+
+            self.num_watchpoints = Some(eddfr.wrps() + 1);
+        }
+        Ok(self.num_watchpoints.unwrap())
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<u64>>, Error> {
+        let mut watchpoints = vec![];
+        let num_hw_watchpoints = self.available_watchpoint_units()? as usize;
+
+        for wp_unit_index in 0..num_hw_watchpoints {
+            let wp_value_addr = Dbgwvr::get_mmio_address_from_base(self.base_address)?
+                + (wp_unit_index * 16) as u64;
+            let mut wp_value = self.memory.read_word_32(wp_value_addr)? as u64;
+            wp_value |= (self.memory.read_word_32(wp_value_addr + 4)? as u64) << 32;
+
+            let wp_control_addr = Dbgwcr::get_mmio_address_from_base(self.base_address)?
+                + (wp_unit_index * 16) as u64;
+            let wp_control = Dbgwcr(self.memory.read_word_32(wp_control_addr)?);
+
+            if wp_control.e() {
+                // The lowest set bit of BAS is the first watched byte of the doubleword.
+                let offset = wp_control.bas().trailing_zeros().min(7) as u64;
+                watchpoints.push(Some(wp_value + offset));
+            } else {
+                watchpoints.push(None);
+            }
+        }
+        Ok(watchpoints)
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        wp_unit_index: usize,
+        addr: u64,
+        length: usize,
+        access: WatchpointAccess,
+    ) -> Result<(), Error> {
+        // The watched bytes must be within a single naturally aligned doubleword.
+        let offset = (addr % 8) as usize;
+        if length == 0 || offset + length > 8 {
+            return Err(Error::Arm(
+                Armv8aError::UnsupportedWatchpoint(addr, length).into(),
+            ));
+        }
+
+        let wp_value_addr =
+            Dbgwvr::get_mmio_address_from_base(self.base_address)? + (wp_unit_index * 16) as u64;
+        let wp_control_addr =
+            Dbgwcr::get_mmio_address_from_base(self.base_address)? + (wp_unit_index * 16) as u64;
+        let mut wp_control = Dbgwcr(0);
+
+        // Match on all modes
+        wp_control.set_hmc(true);
+        wp_control.set_pac(0b11);
+        // Match on the watched bytes of the doubleword
+        wp_control.set_bas(((1 << length) - 1) << offset);
+        wp_control.set_lsc(match access {
+            WatchpointAccess::Read => 0b01,
+            WatchpointAccess::Write => 0b10,
+            WatchpointAccess::ReadWrite => 0b11,
+        });
+        // Enable
+        wp_control.set_e(true);
+
+        let aligned_addr = addr & !0b111;
+        let addr_low = aligned_addr as u32;
+        let addr_high = (aligned_addr >> 32) as u32;
+
+        self.memory.write_word_32(wp_value_addr, addr_low)?;
+        self.memory.write_word_32(wp_value_addr + 4, addr_high)?;
+        self.memory
+            .write_word_32(wp_control_addr, wp_control.into())?;
+
+        Ok(())
+    }
+
+    fn clear_hw_watchpoint(&mut self, wp_unit_index: usize) -> Result<(), Error> {
+        let wp_value_addr =
+            Dbgwvr::get_mmio_address_from_base(self.base_address)? + (wp_unit_index * 16) as u64;
+        let wp_control_addr =
+            Dbgwcr::get_mmio_address_from_base(self.base_address)? + (wp_unit_index * 16) as u64;
+
+        self.memory.write_word_32(wp_value_addr, 0)?;
+        self.memory.write_word_32(wp_value_addr + 4, 0)?;
+        self.memory.write_word_32(wp_control_addr, 0)?;
+
+        Ok(())
+    }
+
     fn registers(&self) -> &'static CoreRegisters {
         if self.state.is_64_bit {
             &AARCH64_CORE_REGISTERS
@@ -2842,6 +2943,113 @@ mod test {
         armv8a.clear_hw_breakpoint(0).unwrap();
     }
 
+    #[test]
+    fn armv8a_available_watchpoint_units() {
+        const WP_COUNT: u32 = 4;
+        let mut probe = MockProbe::new(false);
+        let mut state = CortexAState::new();
+
+        // Add expectations
+        add_status_expectations(&mut probe, true);
+
+        let mut eddfr = Eddfr(0);
+        eddfr.set_wrps(WP_COUNT - 1);
+        probe.expected_read(
+            Eddfr::get_mmio_address_from_base(TEST_BASE_ADDRESS).unwrap(),
+            eddfr.into(),
+        );
+
+        let mock_mem = Box::new(probe) as _;
+
+        let mut armv8a = Armv8a::new(
+            mock_mem,
+            &mut state,
+            TEST_BASE_ADDRESS,
+            TEST_CTI_ADDRESS,
+            DefaultArmSequence::create(),
+        )
+        .unwrap();
+
+        assert_eq!(WP_COUNT, armv8a.available_watchpoint_units().unwrap());
+    }
+
+    #[test]
+    fn armv8a_set_hw_watchpoint() {
+        const WP_VALUE: u64 = 0x2000_1004;
+        let mut probe = MockProbe::new(false);
+        let mut state = CortexAState::new();
+
+        // Add expectations
+        add_status_expectations(&mut probe, true);
+
+        // Update WP value and control
+        let mut dbgwcr = Dbgwcr(0);
+        // Match on all modes
+        dbgwcr.set_hmc(true);
+        dbgwcr.set_pac(0b11);
+        // Match on the upper word of the doubleword
+        dbgwcr.set_bas(0b1111_0000);
+        // Match on stores
+        dbgwcr.set_lsc(0b10);
+        // Enable
+        dbgwcr.set_e(true);
+
+        probe.expected_write(
+            Dbgwvr::get_mmio_address_from_base(TEST_BASE_ADDRESS).unwrap(),
+            0x2000_1000,
+        );
+        probe.expected_write(
+            Dbgwvr::get_mmio_address_from_base(TEST_BASE_ADDRESS).unwrap() + 4,
+            0,
+        );
+        probe.expected_write(
+            Dbgwcr::get_mmio_address_from_base(TEST_BASE_ADDRESS).unwrap(),
+            dbgwcr.into(),
+        );
+
+        let mock_mem = Box::new(probe) as _;
+
+        let mut armv8a = Armv8a::new(
+            mock_mem,
+            &mut state,
+            TEST_BASE_ADDRESS,
+            TEST_CTI_ADDRESS,
+            DefaultArmSequence::create(),
+        )
+        .unwrap();
+
+        armv8a
+            .set_hw_watchpoint(0, WP_VALUE, 4, WatchpointAccess::Write)
+            .unwrap();
+    }
+
+    #[test]
+    fn armv8a_set_hw_watchpoint_unaligned() {
+        let mut probe = MockProbe::new(false);
+        let mut state = CortexAState::new();
+
+        // Add expectations
+        add_status_expectations(&mut probe, true);
+
+        let mock_mem = Box::new(probe) as _;
+
+        let mut armv8a = Armv8a::new(
+            mock_mem,
+            &mut state,
+            TEST_BASE_ADDRESS,
+            TEST_CTI_ADDRESS,
+            DefaultArmSequence::create(),
+        )
+        .unwrap();
+
+        // Crosses a doubleword boundary
+        assert!(
+            armv8a
+                .set_hw_watchpoint(0, 0x2000_1006, 4, WatchpointAccess::Read)
+                .is_err()
+        );
+    }
+
     #[test]
     fn armv8a_read_word_32() {
         const MEMORY_VALUE: u32 = 0xBA5EBA11;
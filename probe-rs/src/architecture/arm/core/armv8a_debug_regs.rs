@@ -128,17 +128,17 @@ impl Edscr {
             0b011111 => HaltReason::Step,
             0b111011 => HaltReason::Step,
             // OS Unlock catch.
-            0b100011 => HaltReason::Exception,
+            0b100011 => HaltReason::Exception(None),
             // Reset catch.
-            0b100111 => HaltReason::Exception,
+            0b100111 => HaltReason::Exception(None),
             // Watchpoint
-            0b101011 => HaltReason::Watchpoint,
+            0b101011 => HaltReason::Watchpoint(None),
             // HLT instruction - causes entry into Debug state.
             0b101111 => HaltReason::Breakpoint(BreakpointCause::Software),
             // Software access to debug register.
-            0b110011 => HaltReason::Exception,
+            0b110011 => HaltReason::Exception(None),
             // Exception Catch.
-            0b110111 => HaltReason::Exception,
+            0b110111 => HaltReason::Exception(None),
             // All other values are reserved or running
             _ => HaltReason::Unknown,
         }
@@ -204,6 +204,50 @@ memory_mapped_bitfield_register! {
     pub e, set_e: 0;
 }
 
+memory_mapped_bitfield_register! {
+    /// DBGWVR - Watchpoint Value Register
+    pub struct Dbgwvr(u32);
+    0x800, "DBGWVR",
+    impl From;
+
+    /// Watchpoint address
+    pub value, set_value : 31, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// DBGWCR - Watchpoint Control Register
+    pub struct Dbgwcr(u32);
+    0x808, "DBGWCR",
+    impl From;
+
+    /// Address mask
+    pub mask, set_mask : 28, 24;
+
+    /// Watchpoint type
+    pub wt, set_wt : 20;
+
+    /// Linked breakpoint number
+    pub lbn, set_lbn : 19, 16;
+
+    /// Security state control
+    pub ssc, set_ssc : 15, 14;
+
+    /// Hyp mode control bit
+    pub hmc, set_hmc: 13;
+
+    /// Byte address select
+    pub bas, set_bas: 12, 5;
+
+    /// Load/store access control
+    pub lsc, set_lsc: 4, 3;
+
+    /// Privileged access control
+    pub pac, set_pac: 2, 1;
+
+    /// Watchpoint enable
+    pub e, set_e: 0;
+}
+
 memory_mapped_bitfield_register! {
     /// EDDFR - External Debug Feature Register
     pub struct Eddfr(u32);
@@ -214,7 +258,7 @@ memory_mapped_bitfield_register! {
     pub ctx_cmps, _: 31, 28;
 
     /// Number of watchpoints, minus 1.
-    pub wrps, _: 23, 20;
+    pub wrps, set_wrps: 23, 20;
 
     /// Number of breakpoints, minus 1
     pub brps, set_brps: 15, 12;
@@ -1,14 +1,24 @@
 //! Common functions and data types for Cortex-M core variants
 
+use super::armv7m::Aircr;
 use crate::{
-    CoreInterface, Error, MemoryMappedRegister,
-    architecture::arm::{ArmError, memory::ArmMemoryInterface},
+    CoreInterface, CoreType, Error, MemoryMappedRegister,
+    architecture::arm::{
+        ArmError,
+        memory::ArmMemoryInterface,
+        sequences::{ArmDebugSequence, cortex_m_wait_for_reset},
+    },
+    config::ResetStrategy,
     core::RegisterId,
     memory_mapped_bitfield_register,
+    probe::DebugProbeError,
     semihosting::SemihostingCommand,
     semihosting::decode_semihosting_syscall,
 };
-use std::time::{Duration, Instant};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
 
 memory_mapped_bitfield_register! {
     pub struct Vtor(u32);
@@ -129,6 +139,87 @@ impl IdPfr1 {
     }
 }
 
+memory_mapped_bitfield_register! {
+    /// DWT Control Register
+    pub struct DwtCtrl(u32);
+    0xE000_1000, "DWT/CTRL",
+    impl From;
+    /// No cycle counter implemented
+    pub nocyccnt, _: 25;
+    /// No profiling counters implemented
+    pub noprfcnt, _: 24;
+    /// Enable the folded instruction counter
+    pub foldevtena, set_foldevtena: 21;
+    /// Enable the LSU counter
+    pub lsuevtena, set_lsuevtena: 20;
+    /// Enable the sleep counter
+    pub sleepevtena, set_sleepevtena: 19;
+    /// Enable the CPI counter
+    pub cpievtena, set_cpievtena: 17;
+    /// Cycle counter enabled
+    pub cyccntena, set_cyccntena: 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// DWT Cycle Count Register
+    pub struct DwtCyccnt(u32);
+    0xE000_1004, "DWT/CYCCNT",
+    impl From;
+    pub count, _: 31, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// DWT CPI Count Register
+    pub struct DwtCpicnt(u32);
+    0xE000_1008, "DWT/CPICNT",
+    impl From;
+    pub u8, count, _: 7, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// DWT Sleep Count Register
+    pub struct DwtSleepcnt(u32);
+    0xE000_1010, "DWT/SLEEPCNT",
+    impl From;
+    pub u8, count, _: 7, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// DWT LSU Count Register
+    pub struct DwtLsucnt(u32);
+    0xE000_1014, "DWT/LSUCNT",
+    impl From;
+    pub u8, count, _: 7, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// DWT Folded-instruction Count Register
+    pub struct DwtFoldcnt(u32);
+    0xE000_1018, "DWT/FOLDCNT",
+    impl From;
+    pub u8, count, _: 7, 0;
+}
+
+/// Reads the DWT cycle counter, if it is implemented and enabled.
+pub(crate) fn read_cycle_count(
+    memory: &mut dyn ArmMemoryInterface,
+) -> Result<Option<u64>, ArmError> {
+    let ctrl = DwtCtrl(memory.read_word_32(DwtCtrl::get_mmio_address())?);
+    if ctrl.nocyccnt() || !ctrl.cyccntena() {
+        return Ok(None);
+    }
+
+    let cyccnt = DwtCyccnt(memory.read_word_32(DwtCyccnt::get_mmio_address())?);
+    Ok(Some(cyccnt.count() as u64))
+}
+
+/// Reads the SWO data that the probe received since the last read, without waiting for more.
+pub(crate) fn read_swo(memory: &mut dyn ArmMemoryInterface) -> Result<Vec<u8>, ArmError> {
+    memory
+        .get_arm_debug_interface()?
+        .read_swo_timeout(Duration::ZERO)
+}
+
 pub(crate) fn read_core_reg(
     memory: &mut dyn ArmMemoryInterface,
     addr: RegisterId,
@@ -166,6 +257,104 @@ pub(crate) fn write_core_reg(
     Ok(())
 }
 
+/// Reads multiple core registers, queueing the DCRSR writes and DCRDR reads.
+///
+/// A register transfer takes a few core cycles, while a single transfer of the probe takes much
+/// longer, so `S_REGRDY` is only checked once after all transfers. If it is not set, the registers
+/// are read again one by one.
+pub(crate) fn read_core_regs(
+    memory: &mut dyn ArmMemoryInterface,
+    addresses: &[RegisterId],
+) -> Result<Vec<u32>, ArmError> {
+    let mut values = Vec::with_capacity(addresses.len());
+    for &addr in addresses {
+        let mut dcrsr_val = Dcrsr(0);
+        dcrsr_val.set_regwnr(false); // Perform a read.
+        dcrsr_val.set_regsel(addr.into()); // The address of the register to read.
+
+        memory.write_word_32(Dcrsr::get_mmio_address(), dcrsr_val.into())?;
+        values.push(memory.read_word_32(Dcrdr::get_mmio_address())?);
+    }
+
+    if addresses.is_empty() || Dhcsr(memory.read_word_32(Dhcsr::get_mmio_address())?).s_regrdy() {
+        return Ok(values);
+    }
+
+    tracing::debug!("Queued core register reads did not complete, reading them one by one");
+    addresses
+        .iter()
+        .map(|&addr| read_core_reg(memory, addr))
+        .collect()
+}
+
+/// Writes multiple core registers, queueing the DCRDR and DCRSR writes.
+///
+/// Like in [`read_core_regs`], `S_REGRDY` is only checked once after all transfers.
+pub(crate) fn write_core_regs(
+    memory: &mut dyn ArmMemoryInterface,
+    registers: &[(RegisterId, u32)],
+) -> Result<(), ArmError> {
+    if registers.is_empty() {
+        return Ok(());
+    }
+
+    for &(addr, value) in registers {
+        memory.write_word_32(Dcrdr::get_mmio_address(), value)?;
+
+        let mut dcrsr_val = Dcrsr(0);
+        dcrsr_val.set_regwnr(true); // Perform a write.
+        dcrsr_val.set_regsel(addr.into()); // The address of the register to write.
+
+        memory.write_word_32(Dcrsr::get_mmio_address(), dcrsr_val.into())?;
+    }
+
+    wait_for_core_register_transfer(memory, Duration::from_millis(100))
+}
+
+/// Resets a Cortex-M core using `strategy`, and waits until the reset is done.
+pub(crate) fn reset_with(
+    memory: &mut dyn ArmMemoryInterface,
+    sequence: &dyn ArmDebugSequence,
+    core_type: CoreType,
+    strategy: ResetStrategy,
+) -> Result<(), ArmError> {
+    match strategy {
+        ResetStrategy::Sequence => return sequence.reset_system(memory, core_type, None),
+        ResetStrategy::SysResetReq => {
+            let mut aircr = Aircr(0);
+            aircr.vectkey();
+            aircr.set_sysresetreq(true);
+            memory.write_word_32(Aircr::get_mmio_address(), aircr.into())?;
+        }
+        ResetStrategy::VectReset => {
+            if !matches!(core_type, CoreType::Armv7m | CoreType::Armv7em) {
+                return Err(ArmError::ArchitectureRequired(&["ARMv7-M"]));
+            }
+
+            let mut aircr = Aircr(0);
+            aircr.vectkey();
+            aircr.set_vectreset(true);
+            memory.write_word_32(Aircr::get_mmio_address(), aircr.into())?;
+        }
+        ResetStrategy::HardwareReset => {
+            let ap = memory.fully_qualified_address();
+            let interface = memory.get_arm_debug_interface()?;
+            let Some(probe) = interface.try_dap_probe_mut() else {
+                return Err(DebugProbeError::CommandNotSupportedByProbe {
+                    command_name: "swj_pins",
+                }
+                .into());
+            };
+
+            sequence.reset_hardware_assert(probe)?;
+            thread::sleep(Duration::from_millis(10));
+            sequence.reset_hardware_deassert(interface, &ap)?;
+        }
+    }
+
+    cortex_m_wait_for_reset(memory)
+}
+
 /// Check if the current breakpoint is a semihosting call.
 ///
 /// Call this if you get some kind of breakpoint. Works on ARMv6-M, ARMv7-M and ARMv8-M.
@@ -224,3 +413,58 @@ fn wait_for_core_register_transfer(
     }
     Err(ArmError::Timeout)
 }
+
+#[cfg(all(test, feature = "builtin-targets"))]
+mod test {
+    use crate::{Permissions, RegisterValue, core::RegisterId, probe::fake_probe::FakeProbe};
+    use std::time::Duration;
+
+    #[test]
+    fn queued_core_register_access() {
+        for target in ["nrf51822_xxAC", "STM32F407VGTx"] {
+            let mut session = FakeProbe::with_mocked_core()
+                .into_probe()
+                .attach(target, Permissions::default())
+                .unwrap();
+            let mut core = session.core(0).unwrap();
+            core.halt(Duration::from_millis(100)).unwrap();
+
+            let registers = (0..4)
+                .map(|n| (RegisterId(n), RegisterValue::U32(0x1000 + u32::from(n))))
+                .collect::<Vec<_>>();
+            core.write_core_regs(&registers).unwrap();
+
+            for &(address, value) in &registers {
+                assert_eq!(core.read_core_reg::<RegisterValue>(address).unwrap(), value);
+            }
+
+            let addresses = registers
+                .iter()
+                .rev()
+                .map(|&(address, _)| address)
+                .collect::<Vec<_>>();
+            let values = core.read_core_regs(&addresses).unwrap();
+            assert_eq!(
+                values,
+                registers
+                    .iter()
+                    .rev()
+                    .map(|&(_, value)| value)
+                    .collect::<Vec<_>>(),
+                "{target}"
+            );
+        }
+    }
+
+    #[test]
+    fn queued_core_register_access_requires_halt() {
+        let mut session = FakeProbe::with_mocked_core()
+            .into_probe()
+            .attach("nrf51822_xxAC", Permissions::default())
+            .unwrap();
+        let mut core = session.core(0).unwrap();
+        core.run().unwrap();
+
+        assert!(core.read_core_regs(&[RegisterId(0)]).is_err());
+    }
+}
@@ -16,6 +16,8 @@ pub mod armv7a;
 pub mod armv7m;
 pub mod armv8a;
 pub mod armv8m;
+pub mod performance_counters;
+mod software_breakpoints;
 
 pub(crate) mod armv7a_debug_regs;
 pub(crate) mod armv8a_debug_regs;
@@ -101,11 +103,11 @@ impl Dfsr {
         } else if self.external() {
             HaltReason::External
         } else if self.dwttrap() {
-            HaltReason::Watchpoint
+            HaltReason::Watchpoint(None)
         } else if self.halted() {
             HaltReason::Request
         } else if self.vcatch() {
-            HaltReason::Exception
+            HaltReason::Exception(None)
         } else {
             // We check that exactly one bit is set, so we should hit one of the cases above.
             panic!("This should not happen. Please open a bug report.")
@@ -143,6 +145,8 @@ pub struct CortexMState {
 
     /// The semihosting command that was decoded at the current program counter
     semihosting_command: Option<SemihostingCommand>,
+
+    sw_breakpoints: software_breakpoints::SoftwareBreakpoints,
 }
 
 impl CortexMState {
@@ -153,6 +157,7 @@ impl CortexMState {
             current_state: CoreStatus::Unknown,
             fp_present: false,
             semihosting_command: None,
+            sw_breakpoints: Default::default(),
         }
     }
 
@@ -0,0 +1,249 @@
+//! Access to the profiling counters of the Cortex-M DWT unit.
+
+use super::{
+    armv7m::Demcr,
+    cortex_m::{DwtCpicnt, DwtCtrl, DwtCyccnt, DwtFoldcnt, DwtLsucnt, DwtSleepcnt},
+};
+use crate::{Core, CoreType, Error, MemoryInterface, MemoryMappedRegister};
+
+/// The values of the DWT profiling counters.
+///
+/// Apart from the cycle counter, all counters are 8 bits wide and wrap around on overflow.
+/// Counters which are not implemented read as zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PerformanceCounterValues {
+    /// CYCCNT, the number of processor cycles.
+    pub cycles: u32,
+    /// CPICNT, the additional cycles required to execute multi-cycle instructions and
+    /// instruction fetch stalls.
+    pub cpi: u8,
+    /// SLEEPCNT, the cycles spent sleeping.
+    pub sleep: u8,
+    /// LSUCNT, the additional cycles spent on load and store instructions.
+    pub lsu: u8,
+    /// FOLDCNT, the number of folded instructions.
+    pub folded: u8,
+}
+
+/// Controls the profiling counters of the DWT unit of a Cortex-M core.
+///
+/// Created by [`Core::performance_counters`].
+pub struct PerformanceCounters<'a, 'probe> {
+    core: &'a mut Core<'probe>,
+    cycle_counter: bool,
+    profiling_counters: bool,
+}
+
+impl<'a, 'probe> PerformanceCounters<'a, 'probe> {
+    /// Detects the implemented counters with the `NOCYCCNT` and `NOPRFCNT` bits of `DWT_CTRL`.
+    ///
+    /// Returns an error if neither the cycle counter nor the profiling counters are implemented.
+    pub(crate) fn new(core: &'a mut Core<'probe>) -> Result<Self, Error> {
+        let (cycle_counter, profiling_counters) = match core.core_type() {
+            CoreType::Armv7m | CoreType::Armv7em | CoreType::Armv8m => {
+                let ctrl = DwtCtrl(core.read_word_32(DwtCtrl::get_mmio_address())?);
+                (!ctrl.nocyccnt(), !ctrl.noprfcnt())
+            }
+            // The DWT of Armv6-M has no counters, its DWT_CTRL has no NOCYCCNT and NOPRFCNT bits.
+            // Other architectures have no DWT at all.
+            _ => (false, false),
+        };
+
+        if !cycle_counter && !profiling_counters {
+            return Err(Error::NotImplemented("performance counters"));
+        }
+
+        Ok(Self {
+            core,
+            cycle_counter,
+            profiling_counters,
+        })
+    }
+
+    /// Returns `true` if the cycle counter is implemented.
+    pub fn has_cycle_counter(&self) -> bool {
+        self.cycle_counter
+    }
+
+    /// Returns `true` if the profiling counters besides the cycle counter are implemented.
+    pub fn has_profiling_counters(&self) -> bool {
+        self.profiling_counters
+    }
+
+    /// Starts all implemented counters.
+    pub fn start(&mut self) -> Result<(), Error> {
+        let mut demcr = Demcr(self.core.read_word_32(Demcr::get_mmio_address())?);
+        if !demcr.trcena() {
+            demcr.set_trcena(true);
+            self.core
+                .write_word_32(Demcr::get_mmio_address(), demcr.into())?;
+        }
+
+        self.set_enabled(true)
+    }
+
+    /// Stops all counters. The counter values are kept.
+    pub fn stop(&mut self) -> Result<(), Error> {
+        self.set_enabled(false)
+    }
+
+    /// Reads the current counter values.
+    ///
+    /// Counters which are not implemented read as zero.
+    pub fn read(&mut self) -> Result<PerformanceCounterValues, Error> {
+        let mut values = PerformanceCounterValues::default();
+
+        if self.cycle_counter {
+            values.cycles =
+                DwtCyccnt(self.core.read_word_32(DwtCyccnt::get_mmio_address())?).count();
+        }
+
+        if self.profiling_counters {
+            values.cpi = DwtCpicnt(self.core.read_word_32(DwtCpicnt::get_mmio_address())?).count();
+            values.sleep =
+                DwtSleepcnt(self.core.read_word_32(DwtSleepcnt::get_mmio_address())?).count();
+            values.lsu = DwtLsucnt(self.core.read_word_32(DwtLsucnt::get_mmio_address())?).count();
+            values.folded =
+                DwtFoldcnt(self.core.read_word_32(DwtFoldcnt::get_mmio_address())?).count();
+        }
+
+        Ok(values)
+    }
+
+    /// Resets all counters to zero.
+    pub fn reset(&mut self) -> Result<(), Error> {
+        if self.cycle_counter {
+            self.core.write_word_32(DwtCyccnt::get_mmio_address(), 0)?;
+        }
+
+        if self.profiling_counters {
+            for address in [
+                DwtCpicnt::get_mmio_address(),
+                DwtSleepcnt::get_mmio_address(),
+                DwtLsucnt::get_mmio_address(),
+                DwtFoldcnt::get_mmio_address(),
+            ] {
+                self.core.write_word_32(address, 0)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), Error> {
+        let mut ctrl = DwtCtrl(self.core.read_word_32(DwtCtrl::get_mmio_address())?);
+
+        if self.cycle_counter {
+            ctrl.set_cyccntena(enabled);
+        }
+        if self.profiling_counters {
+            ctrl.set_cpievtena(enabled);
+            ctrl.set_sleepevtena(enabled);
+            ctrl.set_lsuevtena(enabled);
+            ctrl.set_foldevtena(enabled);
+        }
+
+        self.core
+            .write_word_32(DwtCtrl::get_mmio_address(), ctrl.into())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "builtin-targets"))]
+mod test {
+    use crate::{
+        Error, MemoryInterface, MemoryMappedRegister, Permissions, Session,
+        architecture::arm::core::cortex_m::{DwtCtrl, DwtCyccnt, DwtLsucnt},
+        probe::fake_probe::FakeProbe,
+    };
+
+    const DWT: std::ops::Range<u64> = 0xE000_1000..0xE000_1020;
+    const DEMCR: std::ops::Range<u64> = 0xE000_EDFC..0xE000_EE00;
+
+    /// A session for a Cortex-M4 core, whose DWT_CTRL has the value `ctrl`.
+    fn session(ctrl: u32) -> Session {
+        let mut fake_probe = FakeProbe::with_mocked_core();
+        fake_probe.add_memory_region(DWT, true).unwrap();
+        fake_probe.add_memory_region(DEMCR, true).unwrap();
+        fake_probe
+            .load_memory(DwtCtrl::get_mmio_address(), &ctrl.to_le_bytes())
+            .unwrap();
+
+        fake_probe
+            .into_probe()
+            .attach("STM32F407VGTx", Permissions::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn all_counters_implemented() {
+        let mut session = session(0);
+        let mut core = session.core(0).unwrap();
+        core.write_word_32(DwtCyccnt::get_mmio_address(), 1000)
+            .unwrap();
+        core.write_word_32(DwtLsucnt::get_mmio_address(), 5)
+            .unwrap();
+
+        let mut counters = core.performance_counters().unwrap();
+        assert!(counters.has_cycle_counter());
+        assert!(counters.has_profiling_counters());
+        counters.start().unwrap();
+
+        let values = counters.read().unwrap();
+        assert_eq!(values.cycles, 1000);
+        assert_eq!(values.lsu, 5);
+
+        counters.reset().unwrap();
+        assert_eq!(counters.read().unwrap(), Default::default());
+
+        let ctrl = DwtCtrl(core.read_word_32(DwtCtrl::get_mmio_address()).unwrap());
+        assert!(ctrl.cyccntena());
+        assert!(ctrl.lsuevtena());
+    }
+
+    #[test]
+    fn profiling_counters_without_cycle_counter() {
+        // NOCYCCNT is set.
+        let mut session = session(1 << 25);
+        let mut core = session.core(0).unwrap();
+        core.write_word_32(DwtCyccnt::get_mmio_address(), 1000)
+            .unwrap();
+
+        let mut counters = core.performance_counters().unwrap();
+        assert!(!counters.has_cycle_counter());
+        assert!(counters.has_profiling_counters());
+        counters.start().unwrap();
+        assert_eq!(counters.read().unwrap().cycles, 0);
+
+        let ctrl = DwtCtrl(core.read_word_32(DwtCtrl::get_mmio_address()).unwrap());
+        assert!(!ctrl.cyccntena());
+        assert!(ctrl.lsuevtena());
+    }
+
+    #[test]
+    fn no_counters_implemented() {
+        // NOCYCCNT and NOPRFCNT are set.
+        let mut session = session(0b11 << 24);
+        let mut core = session.core(0).unwrap();
+
+        assert!(matches!(
+            core.performance_counters(),
+            Err(Error::NotImplemented(_))
+        ));
+    }
+
+    #[test]
+    fn armv6m_has_no_counters() {
+        let mut session = FakeProbe::with_mocked_core()
+            .into_probe()
+            .attach("nrf51822_xxAC", Permissions::default())
+            .unwrap();
+        let mut core = session.core(0).unwrap();
+
+        assert!(matches!(
+            core.performance_counters(),
+            Err(Error::NotImplemented(_))
+        ));
+    }
+}
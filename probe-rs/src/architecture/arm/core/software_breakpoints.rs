@@ -0,0 +1,215 @@
+//! Software breakpoints for Cortex-M cores.
+//!
+//! Cores like the Cortex-M0 only have two FPB comparators, which are quickly exhausted. Software
+//! breakpoints replace the instruction at the breakpoint address with a `BKPT` instruction
+//! instead, and keep a copy of the original instruction to restore it when the breakpoint is
+//! removed, or when the core steps over it.
+
+use std::collections::BTreeMap;
+
+use crate::{Error, RegisterValue, architecture::arm::memory::ArmMemoryInterface};
+
+/// The Thumb encoding of `BKPT #0`.
+const BKPT: u16 = 0xBE00;
+
+/// The software breakpoints of a core, and the instructions they replaced.
+#[derive(Debug, Default)]
+pub(crate) struct SoftwareBreakpoints {
+    shadowed: BTreeMap<u64, u16>,
+}
+
+impl SoftwareBreakpoints {
+    /// Returns the addresses of all software breakpoints.
+    pub fn addresses(&self) -> Vec<u64> {
+        self.shadowed.keys().copied().collect()
+    }
+
+    /// Returns `true` if a software breakpoint is set at `address`.
+    pub fn contains(&self, address: u64) -> bool {
+        self.shadowed.contains_key(&address)
+    }
+
+    /// Replaces the instruction at `address` with a breakpoint instruction.
+    ///
+    /// The memory at `address` has to be writable by the core. Breakpoints in memory which can
+    /// only be written by a flash algorithm are rejected with
+    /// [`Error::SoftwareBreakpointNotWritable`].
+    pub fn set(&mut self, memory: &mut dyn ArmMemoryInterface, address: u64) -> Result<(), Error> {
+        if address % 2 != 0 {
+            return Err(Error::Other(format!(
+                "Software breakpoint address {address:#010x} is not halfword aligned"
+            )));
+        }
+
+        if self.contains(address) {
+            return Ok(());
+        }
+
+        let original = memory.read_word_16(address)?;
+        write_instruction(memory, address, BKPT)?;
+
+        tracing::debug!("Set SW breakpoint at {address:#010x}, replacing {original:#06x}");
+        self.shadowed.insert(address, original);
+
+        Ok(())
+    }
+
+    /// Restores the original instruction at `address`.
+    pub fn clear(
+        &mut self,
+        memory: &mut dyn ArmMemoryInterface,
+        address: u64,
+    ) -> Result<(), Error> {
+        let Some(original) = self.shadowed.remove(&address) else {
+            return Err(Error::Other(format!(
+                "No software breakpoint found at address {address:#010x}"
+            )));
+        };
+
+        write_instruction(memory, address, original)
+    }
+
+    /// Temporarily restores the original instruction at `program_counter`, so the core can step
+    /// over a software breakpoint.
+    ///
+    /// The breakpoint instruction has to be re-inserted with [`SuspendedBreakpoint::resume`]
+    /// after the step.
+    pub fn suspend(
+        &mut self,
+        memory: &mut dyn ArmMemoryInterface,
+        program_counter: Option<RegisterValue>,
+    ) -> Result<SuspendedBreakpoint, Error> {
+        let Some(program_counter) = program_counter else {
+            return Ok(SuspendedBreakpoint(None));
+        };
+
+        let address = program_counter.try_into()?;
+        match self.shadowed.get(&address) {
+            Some(&original) => {
+                write_instruction(memory, address, original)?;
+                Ok(SuspendedBreakpoint(Some(address)))
+            }
+            None => Ok(SuspendedBreakpoint(None)),
+        }
+    }
+}
+
+/// A software breakpoint which was replaced by its original instruction with
+/// [`SoftwareBreakpoints::suspend`].
+#[must_use = "the breakpoint instruction has to be re-inserted after the step"]
+pub(crate) struct SuspendedBreakpoint(Option<u64>);
+
+impl SuspendedBreakpoint {
+    /// Re-inserts the breakpoint instruction, unless the breakpoint was cleared in the meantime.
+    pub fn resume(
+        self,
+        breakpoints: &SoftwareBreakpoints,
+        memory: &mut dyn ArmMemoryInterface,
+    ) -> Result<(), Error> {
+        match self.0 {
+            Some(address) if breakpoints.contains(address) => {
+                write_instruction(memory, address, BKPT)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Writes a single instruction, and verifies that the memory actually took the new value.
+fn write_instruction(
+    memory: &mut dyn ArmMemoryInterface,
+    address: u64,
+    instruction: u16,
+) -> Result<(), Error> {
+    memory.write_word_16(address, instruction)?;
+    memory.flush()?;
+
+    if memory.read_word_16(address)? != instruction {
+        return Err(Error::SoftwareBreakpointNotWritable(address));
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "builtin-targets"))]
+mod test {
+    use super::BKPT;
+    use crate::{
+        CoreInterface, Error, MemoryInterface, Permissions, Session, probe::fake_probe::FakeProbe,
+    };
+
+    /// The Thumb encoding of `NOP`.
+    const NOP: u16 = 0xBF00;
+
+    fn session(fake_probe: FakeProbe) -> Session {
+        fake_probe
+            .into_probe()
+            .attach("nrf51822_xxAC", Permissions::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn breakpoint_in_ram() {
+        let mut fake_probe = FakeProbe::with_mocked_core();
+        fake_probe
+            .add_memory_region(0x2000_0000..0x2000_1000, true)
+            .unwrap();
+        fake_probe
+            .load_memory(0x2000_0100, &NOP.to_le_bytes())
+            .unwrap();
+
+        let mut session = session(fake_probe);
+        let mut core = session.core(0).unwrap();
+
+        core.set_sw_breakpoint(0x2000_0100).unwrap();
+        assert_eq!(core.read_word_16(0x2000_0100).unwrap(), BKPT);
+        assert_eq!(core.sw_breakpoints(), vec![0x2000_0100]);
+        assert!(core.hw_breakpoints().unwrap().iter().all(Option::is_none));
+
+        core.clear_sw_breakpoint(0x2000_0100).unwrap();
+        assert_eq!(core.read_word_16(0x2000_0100).unwrap(), NOP);
+        assert!(core.sw_breakpoints().is_empty());
+    }
+
+    #[test]
+    fn breakpoint_in_flash_uses_hardware_breakpoint() {
+        let mut fake_probe = FakeProbe::with_mocked_core();
+        fake_probe.add_memory_region(0x0..0x1000, false).unwrap();
+        fake_probe.load_memory(0x100, &NOP.to_le_bytes()).unwrap();
+
+        let mut session = session(fake_probe);
+        let mut core = session.core(0).unwrap();
+
+        core.set_sw_breakpoint(0x100).unwrap();
+        assert_eq!(core.read_word_16(0x100).unwrap(), NOP);
+        assert!(core.sw_breakpoints().is_empty());
+        assert!(core.hw_breakpoints().unwrap().contains(&Some(0x100)));
+
+        core.clear_sw_breakpoint(0x100).unwrap();
+        assert!(core.hw_breakpoints().unwrap().iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn read_only_memory_is_rejected_without_free_hardware_breakpoint() {
+        let mut fake_probe = FakeProbe::with_mocked_core();
+        fake_probe
+            .add_memory_region(0x2000_0000..0x2000_1000, false)
+            .unwrap();
+        fake_probe
+            .load_memory(0x2000_0100, &NOP.to_le_bytes())
+            .unwrap();
+
+        let mut session = session(fake_probe);
+        let mut core = session.core(0).unwrap();
+
+        let units = core.available_breakpoint_units().unwrap();
+        for unit in 0..units {
+            core.set_hw_breakpoint(0x200 + 2 * u64::from(unit)).unwrap();
+        }
+
+        let error = core.set_sw_breakpoint(0x2000_0100).unwrap_err();
+        assert!(matches!(error, Error::Other(_)), "{error:?}");
+        assert_eq!(core.read_word_16(0x2000_0100).unwrap(), NOP);
+        assert!(core.sw_breakpoints().is_empty());
+    }
+}
@@ -0,0 +1,279 @@
+//! Recovery from FAULT responses of a debug port.
+
+use super::{Abort, Ctrl};
+use crate::architecture::arm::{ArmError, DapError};
+
+/// Direct access to the debug port registers needed to recover from a FAULT response.
+///
+/// Implementations must access the registers without any batching, and without running fault
+/// recovery themselves, otherwise a FAULT during recovery could end up in an endless recursion.
+pub trait FaultRecoveryAccess {
+    /// Reads the CTRL/STAT register of the currently selected debug port.
+    fn read_ctrl_stat(&mut self) -> Result<Ctrl, ArmError>;
+
+    /// Writes the ABORT register of the currently selected debug port.
+    fn write_abort(&mut self, abort: Abort) -> Result<(), ArmError>;
+}
+
+/// The sticky error flags in CTRL/STAT which cause a debug port to respond with FAULT.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StickyErrors {
+    /// CTRL/STAT.WDATAERR, the data phase of a write was corrupted.
+    pub write_data_error: bool,
+    /// CTRL/STAT.STICKYERR, an error occurred during an AP transaction.
+    pub sticky_error: bool,
+    /// CTRL/STAT.STICKYORUN, a transaction was started while overrun detection was enabled
+    /// and the previous transaction was not yet completed.
+    pub overrun: bool,
+}
+
+impl StickyErrors {
+    /// Extracts the sticky error flags from a CTRL/STAT value.
+    pub fn from_ctrl(ctrl: &Ctrl) -> Self {
+        Self {
+            write_data_error: ctrl.w_data_err(),
+            sticky_error: ctrl.sticky_err(),
+            overrun: ctrl.sticky_orun(),
+        }
+    }
+
+    /// Returns `true` if no error flag is set.
+    pub fn is_empty(&self) -> bool {
+        !(self.write_data_error || self.sticky_error || self.overrun)
+    }
+
+    /// Returns `true` if the failed transfer can be repeated after clearing the errors.
+    ///
+    /// Overruns and corrupted write data are transient. A sticky error is caused by the
+    /// transaction itself, e.g. a bus fault of a memory AP, so repeating it would fail again.
+    pub fn is_retryable(&self) -> bool {
+        !self.is_empty() && !self.sticky_error
+    }
+
+    /// The ABORT value which clears exactly these errors.
+    pub fn abort(&self) -> Abort {
+        let mut abort = Abort(0);
+        abort.set_wderrclr(self.write_data_error);
+        abort.set_stkerrclr(self.sticky_error);
+        abort.set_orunerrclr(self.overrun);
+        abort
+    }
+}
+
+/// Recovers a debug port from FAULT responses.
+///
+/// After a FAULT, the debug port answers every further transfer with FAULT until the sticky
+/// error flags are cleared. [`FaultRecovery::recover`] reads CTRL/STAT to determine the reason
+/// and clears the flags using ABORT, [`FaultRecovery::run`] additionally repeats the failed
+/// transfer if the error was transient.
+///
+/// # Example
+///
+/// ```
+/// use probe_rs::architecture::arm::dp::FaultRecovery;
+///
+/// // Repeat transfers which failed because of an overrun up to three times.
+/// let recovery = FaultRecovery::new().with_retries(3);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FaultRecovery {
+    retries: usize,
+}
+
+impl FaultRecovery {
+    /// Creates a fault recovery which clears errors, but never repeats a transfer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how often a transfer is repeated after a transient error.
+    #[must_use]
+    pub fn with_retries(self, retries: usize) -> Self {
+        Self { retries }
+    }
+
+    /// Determines why the debug port responded with FAULT and clears the error flags.
+    ///
+    /// Returns the error flags which were set.
+    pub fn recover<A: FaultRecoveryAccess + ?Sized>(
+        &self,
+        access: &mut A,
+    ) -> Result<StickyErrors, ArmError> {
+        let ctrl = access.read_ctrl_stat()?;
+        let errors = StickyErrors::from_ctrl(&ctrl);
+
+        tracing::debug!("DAP FAULT, Ctrl/Stat register value is: {:?}", ctrl);
+
+        if !errors.is_empty() {
+            access.write_abort(errors.abort())?;
+        }
+
+        Ok(errors)
+    }
+
+    /// Clears the error flags `errors` without reading CTRL/STAT first.
+    ///
+    /// This is used when CTRL/STAT can't be read, e.g. because the failed transfer was a read of
+    /// CTRL/STAT itself.
+    pub fn clear<A: FaultRecoveryAccess + ?Sized>(
+        &self,
+        access: &mut A,
+        errors: StickyErrors,
+    ) -> Result<(), ArmError> {
+        tracing::debug!("Clearing {:?}", errors);
+
+        access.write_abort(errors.abort())
+    }
+
+    /// Runs `transfer`, recovering from FAULT responses.
+    ///
+    /// If the error was transient, the transfer is repeated up to the configured number of
+    /// retries. Otherwise, [`DapError::FaultResponse`] is returned after the errors were cleared.
+    pub fn run<A: FaultRecoveryAccess + ?Sized, R>(
+        &self,
+        access: &mut A,
+        mut transfer: impl FnMut(&mut A) -> Result<R, ArmError>,
+    ) -> Result<R, ArmError> {
+        let mut attempt = 0;
+
+        loop {
+            match transfer(access) {
+                Err(ArmError::Dap(DapError::FaultResponse)) => {
+                    let errors = self.recover(access)?;
+
+                    if !errors.is_retryable() || attempt >= self.retries {
+                        tracing::warn!("Transfer failed with FAULT response: {:?}", errors);
+                        return Err(DapError::FaultResponse.into());
+                    }
+
+                    attempt += 1;
+                    tracing::debug!(
+                        "Repeating transfer after {:?} (attempt {})",
+                        errors,
+                        attempt
+                    );
+                }
+                other => return other,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockDp {
+        ctrl: u32,
+        aborts: Vec<u32>,
+    }
+
+    impl FaultRecoveryAccess for MockDp {
+        fn read_ctrl_stat(&mut self) -> Result<Ctrl, ArmError> {
+            Ok(Ctrl(self.ctrl))
+        }
+
+        fn write_abort(&mut self, abort: Abort) -> Result<(), ArmError> {
+            self.aborts.push(abort.into());
+            self.ctrl = 0;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn recover_clears_set_flags() {
+        let mut dp = MockDp {
+            // WDATAERR and STICKYORUN
+            ctrl: 0x82,
+            aborts: vec![],
+        };
+
+        let errors = FaultRecovery::new().recover(&mut dp).unwrap();
+
+        assert_eq!(
+            errors,
+            StickyErrors {
+                write_data_error: true,
+                sticky_error: false,
+                overrun: true,
+            }
+        );
+        assert_eq!(dp.aborts, vec![0x18]);
+    }
+
+    #[test]
+    fn clear_does_not_read_ctrl_stat() {
+        struct WriteOnlyDp(Vec<u32>);
+
+        impl FaultRecoveryAccess for WriteOnlyDp {
+            fn read_ctrl_stat(&mut self) -> Result<Ctrl, ArmError> {
+                Err(DapError::FaultResponse.into())
+            }
+
+            fn write_abort(&mut self, abort: Abort) -> Result<(), ArmError> {
+                self.0.push(abort.into());
+                Ok(())
+            }
+        }
+
+        let mut dp = WriteOnlyDp(vec![]);
+        FaultRecovery::new()
+            .clear(
+                &mut dp,
+                StickyErrors {
+                    write_data_error: false,
+                    sticky_error: true,
+                    overrun: true,
+                },
+            )
+            .unwrap();
+
+        assert_eq!(dp.0, vec![0x14]);
+    }
+
+    #[test]
+    fn run_retries_overrun() {
+        let mut dp = MockDp {
+            // STICKYORUN
+            ctrl: 0x2,
+            aborts: vec![],
+        };
+        let mut calls = 0;
+
+        let result = FaultRecovery::new().with_retries(1).run(&mut dp, |_| {
+            calls += 1;
+            if calls == 1 {
+                Err(DapError::FaultResponse.into())
+            } else {
+                Ok(42)
+            }
+        });
+
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(calls, 2);
+        assert_eq!(dp.aborts, vec![0x10]);
+    }
+
+    #[test]
+    fn run_does_not_retry_sticky_error() {
+        let mut dp = MockDp {
+            // STICKYERR
+            ctrl: 0x20,
+            aborts: vec![],
+        };
+        let mut calls = 0;
+
+        let result: Result<(), ArmError> =
+            FaultRecovery::new().with_retries(3).run(&mut dp, |_| {
+                calls += 1;
+                Err(DapError::FaultResponse.into())
+            });
+
+        assert!(matches!(
+            result,
+            Err(ArmError::Dap(DapError::FaultResponse))
+        ));
+        assert_eq!(calls, 1);
+        assert_eq!(dp.aborts, vec![0x4]);
+    }
+}
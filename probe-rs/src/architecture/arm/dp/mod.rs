@@ -2,6 +2,11 @@
 
 #[macro_use]
 mod register_generation;
+mod fault_recovery;
+mod power;
+
+pub use fault_recovery::{FaultRecovery, FaultRecoveryAccess, StickyErrors};
+pub use power::{DebugPortControl, DebugPowerConfig, PowerDownRecovery, PowerStatus};
 
 use super::{ArmError, DapAccess, DapError, RegisterParseError};
 use bitfield::bitfield;
@@ -546,7 +551,7 @@ bitfield! {
 impl_dpregister!(BASEPTR1, DebugPortVersion::DPv3, 0x0, Some(0x3), "BASEPTR1");
 
 /// The ID of a debug port. Can be used to detect and select devices in a multidrop setup.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DebugPortId {
     /// The revision of the debug port (implementation defined). This is what the designer of the debug port chooses.
     pub revision: u8,
@@ -594,7 +599,7 @@ pub struct RdBuff(pub u32);
 impl_dpregister!(RdBuff, DebugPortVersion::DPv1, 0x0C, "RDBUFF");
 
 /// Specifies if pushed-find operations are implemented or not.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MinDpSupport {
     /// Pushed-find operations are **not** implemented.
     NotImplemented,
@@ -0,0 +1,293 @@
+//! Power management of debug ports.
+//!
+//! A debug port only gives access to the rest of the debug system while the debug and system
+//! power domains are powered. The debugger requests power with CTRL/STAT.CDBGPWRUPREQ and
+//! CTRL/STAT.CSYSPWRUPREQ, and the target acknowledges it with CDBGPWRUPACK and CSYSPWRUPACK.
+
+use std::time::{Duration, Instant};
+
+use super::{Ctrl, DebugPortError, DpAccess, DpAddress};
+use crate::architecture::arm::{ArmError, DapAccess};
+
+/// The configuration of the debug power handshake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DebugPowerConfig {
+    /// How long to wait for the target to acknowledge a power request.
+    pub timeout: Duration,
+    /// Power down the debug and system power domains when detaching from the target.
+    ///
+    /// Disable this to keep the debug logic of the target powered after probe-rs exits, e.g.
+    /// to keep tracing with another tool.
+    pub power_down_on_detach: bool,
+    /// What happens when a debug port loses power during the session, e.g. because the target
+    /// entered a low-power mode.
+    pub recovery: PowerDownRecovery,
+}
+
+impl Default for DebugPowerConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(1),
+            power_down_on_detach: true,
+            recovery: PowerDownRecovery::default(),
+        }
+    }
+}
+
+/// What happens when the debug connection is lost because the target entered a low-power mode,
+/// like STOP or STANDBY, that powers down its debug logic.
+///
+/// The loss is detected when an access port access fails and CTRL/STAT shows a sticky error or a
+/// dropped CDBGPWRUPACK, or when the debug port doesn't respond at all.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PowerDownRecovery {
+    /// The operation fails with the error of the lost connection.
+    #[default]
+    Fail,
+    /// The debug port is powered up again, or reconnected if it doesn't respond. The failed
+    /// operation still returns its error, and the [`Session`](crate::Session) restores the debug
+    /// state of the cores the next time a core is attached.
+    Reconnect {
+        /// How often reconnecting is attempted, e.g. while the target is still asleep.
+        attempts: u32,
+        /// The time to wait between two attempts.
+        delay: Duration,
+    },
+}
+
+/// The power acknowledge flags of a debug port.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PowerStatus {
+    /// CTRL/STAT.CDBGPWRUPACK, the debug power domain is powered.
+    pub debug: bool,
+    /// CTRL/STAT.CSYSPWRUPACK, the system power domain is powered.
+    pub system: bool,
+}
+
+impl PowerStatus {
+    /// Extracts the power acknowledge flags from a CTRL/STAT value.
+    pub fn from_ctrl(ctrl: &Ctrl) -> Self {
+        Self {
+            debug: ctrl.cdbgpwrupack(),
+            system: ctrl.csyspwrupack(),
+        }
+    }
+
+    /// Returns `true` if both power domains are powered.
+    pub fn is_powered(&self) -> bool {
+        self.debug && self.system
+    }
+
+    /// Returns `true` if both power domains are powered down.
+    pub fn is_powered_down(&self) -> bool {
+        !(self.debug || self.system)
+    }
+}
+
+/// Explicit control of the power domains of a debug port.
+///
+/// This is implemented for every [`DapAccess`], including every
+/// [`ArmDebugInterface`](crate::architecture::arm::ArmDebugInterface).
+pub trait DebugPortControl {
+    /// Reads the power acknowledge flags of the debug port.
+    fn power_status(&mut self, dp: DpAddress) -> Result<PowerStatus, ArmError>;
+
+    /// Requests power for the debug and system power domains, and waits up to `timeout` for the
+    /// target to acknowledge it.
+    fn power_up(&mut self, dp: DpAddress, timeout: Duration) -> Result<(), ArmError>;
+
+    /// Removes the power requests, and waits up to `timeout` for the power domains to go away.
+    fn power_down(&mut self, dp: DpAddress, timeout: Duration) -> Result<(), ArmError>;
+
+    /// Powers up the debug port again if the target dropped the power acknowledge.
+    ///
+    /// Returns `true` if the debug port had lost power.
+    fn ensure_powered(&mut self, dp: DpAddress, timeout: Duration) -> Result<bool, ArmError> {
+        if self.power_status(dp)?.is_powered() {
+            return Ok(false);
+        }
+
+        tracing::warn!("Debug port {dp:x?} lost power, powering it up again");
+        self.power_up(dp, timeout)?;
+
+        Ok(true)
+    }
+}
+
+impl<T: ?Sized + DapAccess> DebugPortControl for T {
+    fn power_status(&mut self, dp: DpAddress) -> Result<PowerStatus, ArmError> {
+        let ctrl: Ctrl = self.read_dp_register(dp)?;
+        Ok(PowerStatus::from_ctrl(&ctrl))
+    }
+
+    fn power_up(&mut self, dp: DpAddress, timeout: Duration) -> Result<(), ArmError> {
+        let ctrl = request_power(self, dp, true)?;
+        wait_for_power(self, dp, timeout, PowerStatus::is_powered)?;
+
+        // Some targets only latch the request once the power domains are up.
+        self.write_dp_register(dp, ctrl)?;
+        if !self.power_status(dp)?.is_powered() {
+            tracing::error!("Debug power request failed");
+            return Err(DebugPortError::TargetPowerUpFailed.into());
+        }
+
+        Ok(())
+    }
+
+    fn power_down(&mut self, dp: DpAddress, timeout: Duration) -> Result<(), ArmError> {
+        request_power(self, dp, false)?;
+        wait_for_power(self, dp, timeout, PowerStatus::is_powered_down)
+    }
+}
+
+/// Sets or clears the power requests, keeping the overrun detection and lane mask.
+fn request_power<T: ?Sized + DapAccess>(
+    interface: &mut T,
+    dp: DpAddress,
+    power: bool,
+) -> Result<Ctrl, ArmError> {
+    let current: Ctrl = interface.read_dp_register(dp)?;
+
+    // Don't write back the sticky flags, which are write-one-to-clear on JTAG.
+    let mut ctrl = Ctrl(0);
+    ctrl.set_orun_detect(current.orun_detect());
+    ctrl.set_mask_lane(current.mask_lane());
+    ctrl.set_cdbgpwrupreq(power);
+    ctrl.set_csyspwrupreq(power);
+    interface.write_dp_register(dp, ctrl.clone())?;
+
+    Ok(ctrl)
+}
+
+fn wait_for_power<T: ?Sized + DapAccess>(
+    interface: &mut T,
+    dp: DpAddress,
+    timeout: Duration,
+    done: fn(&PowerStatus) -> bool,
+) -> Result<(), ArmError> {
+    let start = Instant::now();
+    loop {
+        if done(&interface.power_status(dp)?) {
+            return Ok(());
+        }
+        if start.elapsed() >= timeout {
+            return Err(ArmError::Timeout);
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::architecture::arm::{
+        FullyQualifiedApAddress,
+        communication_interface::DapProbe,
+        dp::{DpRegister, DpRegisterAddress},
+    };
+
+    /// A debug port whose power acknowledge follows the requests, unless it is unpowered.
+    struct MockDp {
+        ctrl: u32,
+        unpowered: bool,
+        writes: Vec<u32>,
+    }
+
+    impl DapAccess for MockDp {
+        fn read_raw_dp_register(
+            &mut self,
+            _dp: DpAddress,
+            addr: DpRegisterAddress,
+        ) -> Result<u32, ArmError> {
+            assert_eq!(addr, Ctrl::ADDRESS);
+            Ok(self.ctrl)
+        }
+
+        fn write_raw_dp_register(
+            &mut self,
+            _dp: DpAddress,
+            _addr: DpRegisterAddress,
+            value: u32,
+        ) -> Result<(), ArmError> {
+            self.writes.push(value);
+            // Mirror CDBGPWRUPREQ and CSYSPWRUPREQ to the acknowledge bits.
+            let acks = if self.unpowered {
+                0
+            } else {
+                (value & 0x5000_0000) << 1
+            };
+            self.ctrl = value | acks;
+            Ok(())
+        }
+
+        fn read_raw_ap_register(
+            &mut self,
+            _ap: &FullyQualifiedApAddress,
+            _addr: u64,
+        ) -> Result<u32, ArmError> {
+            Err(ArmError::NotImplemented("the mock has no access ports"))
+        }
+
+        fn write_raw_ap_register(
+            &mut self,
+            _ap: &FullyQualifiedApAddress,
+            _addr: u64,
+            _value: u32,
+        ) -> Result<(), ArmError> {
+            Err(ArmError::NotImplemented("the mock has no access ports"))
+        }
+
+        fn try_dap_probe(&self) -> Option<&dyn DapProbe> {
+            None
+        }
+
+        fn try_dap_probe_mut(&mut self) -> Option<&mut dyn DapProbe> {
+            None
+        }
+    }
+
+    #[test]
+    fn power_up_and_down() {
+        let mut dp = MockDp {
+            // ORUNDETECT, STICKYERR
+            ctrl: 0x21,
+            unpowered: false,
+            writes: vec![],
+        };
+        let dp_address = DpAddress::Default;
+        let timeout = Duration::from_millis(10);
+
+        assert!(!dp.power_status(dp_address).unwrap().is_powered());
+        dp.power_up(dp_address, timeout).unwrap();
+        assert!(dp.power_status(dp_address).unwrap().is_powered());
+        assert_eq!(dp.writes, [0x5000_0001, 0x5000_0001]);
+
+        assert!(!dp.ensure_powered(dp_address, Duration::ZERO).unwrap());
+
+        dp.power_down(dp_address, timeout).unwrap();
+        assert!(dp.power_status(dp_address).unwrap().is_powered_down());
+        assert_eq!(dp.writes[2], 0x1);
+    }
+
+    #[test]
+    fn recover_lost_power() {
+        let mut dp = MockDp {
+            // CDBGPWRUPREQ and CSYSPWRUPREQ, but no acknowledge.
+            ctrl: 0x5000_0000,
+            unpowered: false,
+            writes: vec![],
+        };
+
+        let dp_address = DpAddress::Default;
+
+        assert!(dp.ensure_powered(dp_address, Duration::ZERO).unwrap());
+        assert!(dp.power_status(dp_address).unwrap().is_powered());
+
+        dp.ctrl = 0;
+        dp.unpowered = true;
+        assert!(matches!(
+            dp.power_up(dp_address, Duration::ZERO),
+            Err(ArmError::Timeout)
+        ));
+    }
+}
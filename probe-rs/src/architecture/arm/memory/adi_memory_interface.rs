@@ -5,26 +5,51 @@ use crate::{
     architecture::arm::{
         ArmDebugInterface, ArmError, DapAccess, FullyQualifiedApAddress,
         ap::{
-            AccessPortType, ApAccess, CSW, DataSize,
-            memory_ap::{MemoryAp, MemoryApType},
+            AccessPortType, AddressIncrement, ApAccess, CSW, DataSize,
+            memory_ap::{MemoryAp, MemoryApConfig, MemoryApType},
         },
         memory::ArmMemoryInterface,
     },
     probe::DebugProbeError,
 };
 
+/// The size of the address range in which the TAR auto-increments, if the target doesn't specify
+/// a larger one. The ADI specification guarantees at least 10 bits.
+pub(crate) const DEFAULT_TAR_AUTOINCREMENT_SIZE: u64 = 0x400;
+
 /// Calculate the maximum number of bytes we can write starting at address
-/// before we run into the 10-bit TAR autoincrement limit.
-fn autoincr_max_bytes(address: u64) -> usize {
-    const AUTOINCR_LIMIT: usize = 0x400;
+/// before we run into the TAR autoincrement limit of `autoincrement_size` bytes.
+fn autoincr_max_bytes(address: u64, autoincrement_size: u64) -> usize {
+    ((address + 1).next_multiple_of(autoincrement_size) - address) as usize
+}
 
-    ((address + 1).next_multiple_of(AUTOINCR_LIMIT as _) - address) as usize
+/// The minimum number of word-aligned bytes for which packed transfers are used.
+///
+/// Enabling and disabling packed transfers requires two additional CSW writes, which are only
+/// worth it if they save enough DRW accesses.
+const MIN_PACKED_TRANSFER_BYTES: usize = 8;
+
+/// Splits an 8 or 16 bit transfer of `len` bytes at `address` into an unaligned head and a
+/// word-aligned middle part, which can be transferred using packed transfers.
+///
+/// Returns the length of the head and the middle part in bytes, or `None` if the middle part is
+/// too short to benefit from packed transfers.
+fn packed_split(address: u64, len: usize) -> Option<(usize, usize)> {
+    let head = ((4 - address % 4) % 4) as usize;
+    let middle = len.saturating_sub(head) & !3;
+
+    (middle >= MIN_PACKED_TRANSFER_BYTES).then_some((head, middle))
 }
 
 /// A struct to give access to a targets memory using a certain DAP.
 pub(crate) struct ADIMemoryInterface<'interface, APA> {
     interface: &'interface mut APA,
     memory_ap: MemoryAp,
+    /// Whether the memory AP supports packed transfers, if already known.
+    packed_transfers: Option<bool>,
+    /// The size of the address range in which the TAR auto-increments, transfers are split at
+    /// its boundaries.
+    tar_autoincrement_size: u64,
 }
 
 impl<'interface, APA> ADIMemoryInterface<'interface, APA>
@@ -40,115 +65,146 @@ where
         Ok(Self {
             interface,
             memory_ap,
+            packed_transfers: None,
+            tar_autoincrement_size: DEFAULT_TAR_AUTOINCREMENT_SIZE,
         })
     }
-}
 
-impl<AP> MemoryInterface<ArmError> for ADIMemoryInterface<'_, AP>
-where
-    AP: DapAccess,
-{
-    /// Read a block of 64 bit words at `address`.
-    ///
-    /// The number of words read is `data.len()`.
-    /// The address where the read should be performed at has to be a multiple of 8.
-    /// Returns `ArmError::MemoryNotAligned` if this does not hold true.
-    fn read_64(&mut self, mut address: u64, mut data: &mut [u64]) -> Result<(), ArmError> {
-        if data.is_empty() {
-            return Ok(());
+    /// Sets the size of the address range in which the TAR auto-increments, a power of two of at
+    /// least 1 KiB.
+    pub fn with_tar_autoincrement_size(self, size: u64) -> Self {
+        Self {
+            tar_autoincrement_size: size,
+            ..self
         }
+    }
 
-        if (address % 8) != 0 {
-            return Err(ArmError::alignment_error(address, 8));
+    /// Returns the maximum number of bytes which can be transferred starting at `address`, before
+    /// the TAR auto-increment wraps around.
+    fn autoincr_max_bytes(&self, address: u64) -> usize {
+        autoincr_max_bytes(address, self.tar_autoincrement_size)
+    }
+
+    /// Returns whether the memory AP supports packed transfers.
+    ///
+    /// Support is detected on first use by trying to enable packed transfers in the CSW.
+    fn packed_transfers_supported(&mut self) -> Result<bool, ArmError> {
+        if self.packed_transfers.is_none() {
+            let supported = self
+                .memory_ap
+                .try_set_address_increment(self.interface, AddressIncrement::Packed)?;
+            self.memory_ap
+                .try_set_address_increment(self.interface, AddressIncrement::Single)?;
+
+            tracing::debug!("Packed transfers supported: {}", supported);
+            self.packed_transfers = Some(supported);
         }
 
-        // Fall back to 32-bit accesses if 64-bit accesses are not supported.
-        // In both cases the sequence of words we have to read from DRW is the same:
-        // first the least significant word, then the most significant word.
-        let size = match self.memory_ap.has_large_data_extension() {
-            true => DataSize::U64,
-            false => DataSize::U32,
-        };
+        Ok(self.packed_transfers == Some(true))
+    }
+
+    /// Read word-aligned `data` at `address` using packed transfers of `size`.
+    fn read_packed(
+        &mut self,
+        address: u64,
+        size: DataSize,
+        data: &mut [u8],
+    ) -> Result<(), ArmError> {
         self.memory_ap.try_set_datasize(self.interface, size)?;
+        self.memory_ap
+            .try_set_address_increment(self.interface, AddressIncrement::Packed)?;
+
+        let result = self.read_packed_words(address, data);
+
+        // Always restore single increments, but report a failed transfer before a failed restore.
+        let restored = self
+            .memory_ap
+            .try_set_address_increment(self.interface, AddressIncrement::Single);
+
+        result?;
+        restored?;
+
+        Ok(())
+    }
 
+    fn read_packed_words(&mut self, mut address: u64, mut data: &mut [u8]) -> Result<(), ArmError> {
         while !data.is_empty() {
-            let chunk_size = data.len().min(autoincr_max_bytes(address) / 8);
+            let chunk_size = data.len().min(self.autoincr_max_bytes(address));
 
             tracing::debug!(
-                "Reading chunk with len {} at address {:#08x}",
+                "Reading packed chunk with len {} at address {:#08x}",
                 chunk_size,
                 address
             );
 
-            // autoincrement is limited to the 10 lowest bits, so write TAR every time.
-            self.memory_ap.set_target_address(self.interface, address)?;
+            // Each DRW access transfers a full word, split into multiple bus transfers by the AP.
+            let mut values = vec![0u32; chunk_size / 4];
 
-            let mut buf = vec![0; chunk_size * 2];
-            self.memory_ap.read_data(self.interface, &mut buf)?;
+            // autoincrement is limited to the TAR autoincrement size, so write TAR every time.
+            self.memory_ap.set_target_address(self.interface, address)?;
+            self.memory_ap.read_data(self.interface, &mut values)?;
 
-            for i in 0..chunk_size {
-                data[i] = buf[i * 2] as u64 | ((buf[i * 2 + 1] as u64) << 32);
+            for (bytes, value) in data[..chunk_size].chunks_exact_mut(4).zip(&values) {
+                bytes.copy_from_slice(&value.to_le_bytes());
             }
 
             address = address
-                .checked_add(chunk_size as u64 * 8)
+                .checked_add(chunk_size as u64)
                 .ok_or(ArmError::OutOfBounds)?;
             data = &mut data[chunk_size..];
         }
 
-        tracing::debug!("Finished reading block");
-
         Ok(())
     }
 
-    /// Read a block of 32 bit words at `address`.
-    ///
-    /// The number of words read is `data.len()`.
-    /// The address where the read should be performed at has to be a multiple of 4.
-    /// Returns `ArmError::MemoryNotAligned` if this does not hold true.
-    fn read_32(&mut self, mut address: u64, mut data: &mut [u32]) -> Result<(), ArmError> {
-        if data.is_empty() {
-            return Ok(());
-        }
+    /// Write word-aligned `data` to `address` using packed transfers of `size`.
+    fn write_packed(&mut self, address: u64, size: DataSize, data: &[u8]) -> Result<(), ArmError> {
+        self.memory_ap.try_set_datasize(self.interface, size)?;
+        self.memory_ap
+            .try_set_address_increment(self.interface, AddressIncrement::Packed)?;
 
-        if (address % 4) != 0 {
-            return Err(ArmError::alignment_error(address, 4));
-        }
+        let result = self.write_packed_words(address, data);
 
-        self.memory_ap
-            .try_set_datasize(self.interface, DataSize::U32)?;
+        // Always restore single increments, but report a failed transfer before a failed restore.
+        let restored = self
+            .memory_ap
+            .try_set_address_increment(self.interface, AddressIncrement::Single);
+
+        result?;
+        restored?;
+
+        Ok(())
+    }
 
+    fn write_packed_words(&mut self, mut address: u64, mut data: &[u8]) -> Result<(), ArmError> {
         while !data.is_empty() {
-            let chunk_size = data.len().min(autoincr_max_bytes(address) / 4);
+            let chunk_size = data.len().min(self.autoincr_max_bytes(address));
 
             tracing::debug!(
-                "Reading chunk with len {} at address {:#08x}",
+                "Writing packed chunk with len {} at address {:#08x}",
                 chunk_size,
                 address
             );
 
-            // autoincrement is limited to the 10 lowest bits, so write TAR every time.
+            let values = data[..chunk_size]
+                .chunks_exact(4)
+                .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+                .collect::<Vec<_>>();
+
+            // autoincrement is limited to the TAR autoincrement size, so write TAR every time.
             self.memory_ap.set_target_address(self.interface, address)?;
-            self.memory_ap
-                .read_data(self.interface, &mut data[..chunk_size])?;
+            self.memory_ap.write_data(self.interface, &values)?;
 
             address = address
-                .checked_add(chunk_size as u64 * 4)
+                .checked_add(chunk_size as u64)
                 .ok_or(ArmError::OutOfBounds)?;
-            data = &mut data[chunk_size..];
+            data = &data[chunk_size..];
         }
 
-        tracing::debug!("Finished reading block");
-
         Ok(())
     }
 
-    /// Read a block of 16 bit words at `address`.
-    ///
-    /// The number of words read is `data.len()`.
-    /// The address where the read should be performed at has to be a multiple of 2.
-    /// Returns `ArmError::MemoryNotAligned` if this does not hold true.
-    fn read_16(&mut self, mut address: u64, mut data: &mut [u16]) -> Result<(), ArmError> {
+    fn read_16_unpacked(&mut self, mut address: u64, mut data: &mut [u16]) -> Result<(), ArmError> {
         if self.memory_ap.supports_only_32bit_data_size() {
             return Err(ArmError::UnsupportedTransferWidth(16));
         }
@@ -165,7 +221,7 @@ where
             .try_set_datasize(self.interface, DataSize::U16)?;
 
         while !data.is_empty() {
-            let chunk_size = data.len().min(autoincr_max_bytes(address) / 2);
+            let chunk_size = data.len().min(self.autoincr_max_bytes(address) / 2);
 
             tracing::debug!(
                 "Reading chunk with len {} at address {:#08x}",
@@ -175,7 +231,7 @@ where
 
             let mut values = vec![0; chunk_size];
 
-            // autoincrement is limited to the 10 lowest bits, so write TAR every time.
+            // autoincrement is limited to the TAR autoincrement size, so write TAR every time.
             self.memory_ap.set_target_address(self.interface, address)?;
             self.memory_ap.read_data(self.interface, &mut values)?;
 
@@ -199,10 +255,7 @@ where
         Ok(())
     }
 
-    /// Read a block of 8 bit words at `address`.
-    ///
-    /// The number of words read is `data.len()`.
-    fn read_8(&mut self, mut address: u64, mut data: &mut [u8]) -> Result<(), ArmError> {
+    fn read_8_unpacked(&mut self, mut address: u64, mut data: &mut [u8]) -> Result<(), ArmError> {
         if self.memory_ap.supports_only_32bit_data_size() {
             return Err(ArmError::UnsupportedTransferWidth(8));
         }
@@ -215,7 +268,7 @@ where
             .try_set_datasize(self.interface, DataSize::U8)?;
 
         while !data.is_empty() {
-            let chunk_size = data.len().min(autoincr_max_bytes(address));
+            let chunk_size = data.len().min(self.autoincr_max_bytes(address));
 
             tracing::debug!(
                 "Reading chunk with len {} at address {:#08x}",
@@ -225,7 +278,7 @@ where
 
             let mut values = vec![0; chunk_size];
 
-            // autoincrement is limited to the 10 lowest bits, so write TAR every time.
+            // autoincrement is limited to the TAR autoincrement size, so write TAR every time.
             self.memory_ap.set_target_address(self.interface, address)?;
             self.memory_ap.read_data(self.interface, &mut values)?;
 
@@ -249,6 +302,266 @@ where
         Ok(())
     }
 
+    fn write_16_unpacked(&mut self, mut address: u64, mut data: &[u16]) -> Result<(), ArmError> {
+        if self.memory_ap.supports_only_32bit_data_size() {
+            return Err(ArmError::UnsupportedTransferWidth(16));
+        }
+        if (address % 2) != 0 {
+            return Err(ArmError::alignment_error(address, 2));
+        }
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        tracing::debug!(
+            "Write block with total size {} bytes to address {:#08x}",
+            data.len() * 2,
+            address
+        );
+
+        self.memory_ap
+            .try_set_datasize(self.interface, DataSize::U16)?;
+
+        while !data.is_empty() {
+            let chunk_size = data.len().min(self.autoincr_max_bytes(address) / 2);
+
+            tracing::debug!(
+                "Writing chunk with len {} at address {:#08x}",
+                chunk_size,
+                address
+            );
+
+            // The required shifting logic here is described in C2.2.6 Byte lanes of the ADI v5.2 specification.
+            // All bytes are transfered in their lane, so when we do an access at an address that is not divisible by 4,
+            // we have to shift the word (one or two bytes) to it's correct position.
+            let values = data[..chunk_size]
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (*v as u32) << (((address as usize + i * 2) % 4) * 8))
+                .collect::<Vec<_>>();
+
+            // autoincrement is limited to the TAR autoincrement size, so write TAR every time.
+            self.memory_ap.set_target_address(self.interface, address)?;
+            self.memory_ap.write_data(self.interface, &values)?;
+
+            address = address
+                .checked_add(chunk_size as u64 * 2)
+                .ok_or(ArmError::OutOfBounds)?;
+            data = &data[chunk_size..];
+        }
+
+        tracing::debug!("Finished writing block");
+
+        Ok(())
+    }
+
+    fn write_8_unpacked(&mut self, mut address: u64, mut data: &[u8]) -> Result<(), ArmError> {
+        if self.memory_ap.supports_only_32bit_data_size() {
+            return Err(ArmError::UnsupportedTransferWidth(8));
+        }
+
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        tracing::debug!(
+            "Write block with total size {} bytes to address {:#08x}",
+            data.len(),
+            address
+        );
+
+        self.memory_ap
+            .try_set_datasize(self.interface, DataSize::U8)?;
+
+        while !data.is_empty() {
+            let chunk_size = data.len().min(self.autoincr_max_bytes(address));
+
+            tracing::debug!(
+                "Writing chunk with len {} at address {:#08x}",
+                chunk_size,
+                address
+            );
+
+            // The required shifting logic here is described in C2.2.6 Byte lanes of the ADI v5.2 specification.
+            // All bytes are transfered in their lane, so when we do an access at an address that is not divisible by 4,
+            // we have to shift the word (one or two bytes) to it's correct position.
+            let values = data[..chunk_size]
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (*v as u32) << (((address as usize + i) % 4) * 8))
+                .collect::<Vec<_>>();
+
+            // autoincrement is limited to the TAR autoincrement size, so write TAR every time.
+            self.memory_ap.set_target_address(self.interface, address)?;
+            self.memory_ap.write_data(self.interface, &values)?;
+
+            address = address
+                .checked_add(chunk_size as u64)
+                .ok_or(ArmError::OutOfBounds)?;
+            data = &data[chunk_size..];
+        }
+
+        tracing::debug!("Finished writing block");
+
+        Ok(())
+    }
+}
+
+impl<AP> MemoryInterface<ArmError> for ADIMemoryInterface<'_, AP>
+where
+    AP: DapAccess,
+{
+    /// Read a block of 64 bit words at `address`.
+    ///
+    /// The number of words read is `data.len()`.
+    /// The address where the read should be performed at has to be a multiple of 8.
+    /// Returns `ArmError::MemoryNotAligned` if this does not hold true.
+    fn read_64(&mut self, mut address: u64, mut data: &mut [u64]) -> Result<(), ArmError> {
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        if (address % 8) != 0 {
+            return Err(ArmError::alignment_error(address, 8));
+        }
+
+        // Fall back to 32-bit accesses if 64-bit accesses are not supported.
+        // In both cases the sequence of words we have to read from DRW is the same:
+        // first the least significant word, then the most significant word.
+        let size = match self.memory_ap.has_large_data_extension() {
+            true => DataSize::U64,
+            false => DataSize::U32,
+        };
+        self.memory_ap.try_set_datasize(self.interface, size)?;
+
+        while !data.is_empty() {
+            let chunk_size = data.len().min(self.autoincr_max_bytes(address) / 8);
+
+            tracing::debug!(
+                "Reading chunk with len {} at address {:#08x}",
+                chunk_size,
+                address
+            );
+
+            // autoincrement is limited to the TAR autoincrement size, so write TAR every time.
+            self.memory_ap.set_target_address(self.interface, address)?;
+
+            let mut buf = vec![0; chunk_size * 2];
+            self.memory_ap.read_data(self.interface, &mut buf)?;
+
+            for i in 0..chunk_size {
+                data[i] = buf[i * 2] as u64 | ((buf[i * 2 + 1] as u64) << 32);
+            }
+
+            address = address
+                .checked_add(chunk_size as u64 * 8)
+                .ok_or(ArmError::OutOfBounds)?;
+            data = &mut data[chunk_size..];
+        }
+
+        tracing::debug!("Finished reading block");
+
+        Ok(())
+    }
+
+    /// Read a block of 32 bit words at `address`.
+    ///
+    /// The number of words read is `data.len()`.
+    /// The address where the read should be performed at has to be a multiple of 4.
+    /// Returns `ArmError::MemoryNotAligned` if this does not hold true.
+    fn read_32(&mut self, mut address: u64, mut data: &mut [u32]) -> Result<(), ArmError> {
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        if (address % 4) != 0 {
+            return Err(ArmError::alignment_error(address, 4));
+        }
+
+        self.memory_ap
+            .try_set_datasize(self.interface, DataSize::U32)?;
+
+        while !data.is_empty() {
+            let chunk_size = data.len().min(self.autoincr_max_bytes(address) / 4);
+
+            tracing::debug!(
+                "Reading chunk with len {} at address {:#08x}",
+                chunk_size,
+                address
+            );
+
+            // autoincrement is limited to the TAR autoincrement size, so write TAR every time.
+            self.memory_ap.set_target_address(self.interface, address)?;
+            self.memory_ap
+                .read_data(self.interface, &mut data[..chunk_size])?;
+
+            address = address
+                .checked_add(chunk_size as u64 * 4)
+                .ok_or(ArmError::OutOfBounds)?;
+            data = &mut data[chunk_size..];
+        }
+
+        tracing::debug!("Finished reading block");
+
+        Ok(())
+    }
+
+    /// Read a block of 16 bit words at `address`.
+    ///
+    /// The number of words read is `data.len()`.
+    /// The address where the read should be performed at has to be a multiple of 2.
+    /// Returns `ArmError::MemoryNotAligned` if this does not hold true.
+    fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), ArmError> {
+        if self.memory_ap.supports_only_32bit_data_size() {
+            return Err(ArmError::UnsupportedTransferWidth(16));
+        }
+
+        if (address % 2) != 0 {
+            return Err(ArmError::alignment_error(address, 2));
+        }
+
+        if let Some((head, middle)) = packed_split(address, data.len() * 2) {
+            if self.packed_transfers_supported()? {
+                let (head_data, rest) = data.split_at_mut(head / 2);
+                let (middle_data, tail_data) = rest.split_at_mut(middle / 2);
+
+                self.read_16_unpacked(address, head_data)?;
+
+                let mut bytes = vec![0u8; middle];
+                self.read_packed(address + head as u64, DataSize::U16, &mut bytes)?;
+                for (value, bytes) in middle_data.iter_mut().zip(bytes.chunks_exact(2)) {
+                    *value = u16::from_le_bytes([bytes[0], bytes[1]]);
+                }
+
+                return self.read_16_unpacked(address + (head + middle) as u64, tail_data);
+            }
+        }
+
+        self.read_16_unpacked(address, data)
+    }
+
+    /// Read a block of 8 bit words at `address`.
+    ///
+    /// The number of words read is `data.len()`.
+    fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), ArmError> {
+        if self.memory_ap.supports_only_32bit_data_size() {
+            return Err(ArmError::UnsupportedTransferWidth(8));
+        }
+
+        if let Some((head, middle)) = packed_split(address, data.len()) {
+            if self.packed_transfers_supported()? {
+                let (head_data, rest) = data.split_at_mut(head);
+                let (middle_data, tail_data) = rest.split_at_mut(middle);
+
+                self.read_8_unpacked(address, head_data)?;
+                self.read_packed(address + head as u64, DataSize::U8, middle_data)?;
+                return self.read_8_unpacked(address + (head + middle) as u64, tail_data);
+            }
+        }
+
+        self.read_8_unpacked(address, data)
+    }
+
     fn read(&mut self, address: u64, data: &mut [u8]) -> Result<(), ArmError> {
         let len = data.len();
         if address % 4 == 0 && len % 4 == 0 {
@@ -301,7 +614,7 @@ where
         self.memory_ap.try_set_datasize(self.interface, size)?;
 
         while !data.is_empty() {
-            let chunk_size = data.len().min(autoincr_max_bytes(address) / 8);
+            let chunk_size = data.len().min(self.autoincr_max_bytes(address) / 8);
 
             tracing::debug!(
                 "Writing chunk with len {} at address {:#08x}",
@@ -314,7 +627,7 @@ where
                 .flat_map(|&w| [w as u32, (w >> 32) as u32])
                 .collect();
 
-            // autoincrement is limited to the 10 lowest bits, so write TAR every time.
+            // autoincrement is limited to the TAR autoincrement size, so write TAR every time.
             self.memory_ap.set_target_address(self.interface, address)?;
             self.memory_ap.write_data(self.interface, &values)?;
 
@@ -353,7 +666,7 @@ where
             .try_set_datasize(self.interface, DataSize::U32)?;
 
         while !data.is_empty() {
-            let chunk_size = data.len().min(autoincr_max_bytes(address) / 4);
+            let chunk_size = data.len().min(self.autoincr_max_bytes(address) / 4);
 
             tracing::debug!(
                 "Writing chunk with len {} at address {:#08x}",
@@ -361,7 +674,7 @@ where
                 address
             );
 
-            // autoincrement is limited to the 10 lowest bits, so write TAR every time.
+            // autoincrement is limited to the TAR autoincrement size, so write TAR every time.
             self.memory_ap.set_target_address(self.interface, address)?;
             self.memory_ap
                 .write_data(self.interface, &data[..chunk_size])?;
@@ -382,111 +695,55 @@ where
     /// The number of words written is `data.len()`.
     /// The address where the write should be performed at has to be a multiple of 2.
     /// Returns `ArmError::MemoryNotAligned` if this does not hold true.
-    fn write_16(&mut self, mut address: u64, mut data: &[u16]) -> Result<(), ArmError> {
+    fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), ArmError> {
         if self.memory_ap.supports_only_32bit_data_size() {
             return Err(ArmError::UnsupportedTransferWidth(16));
         }
+
         if (address % 2) != 0 {
             return Err(ArmError::alignment_error(address, 2));
         }
-        if data.is_empty() {
-            return Ok(());
-        }
 
-        tracing::debug!(
-            "Write block with total size {} bytes to address {:#08x}",
-            data.len() * 2,
-            address
-        );
+        if let Some((head, middle)) = packed_split(address, data.len() * 2) {
+            if self.packed_transfers_supported()? {
+                let (head_data, rest) = data.split_at(head / 2);
+                let (middle_data, tail_data) = rest.split_at(middle / 2);
 
-        self.memory_ap
-            .try_set_datasize(self.interface, DataSize::U16)?;
+                self.write_16_unpacked(address, head_data)?;
 
-        while !data.is_empty() {
-            let chunk_size = data.len().min(autoincr_max_bytes(address) / 2);
+                let bytes = middle_data
+                    .iter()
+                    .flat_map(|value| value.to_le_bytes())
+                    .collect::<Vec<_>>();
+                self.write_packed(address + head as u64, DataSize::U16, &bytes)?;
 
-            tracing::debug!(
-                "Writing chunk with len {} at address {:#08x}",
-                chunk_size,
-                address
-            );
-
-            // The required shifting logic here is described in C2.2.6 Byte lanes of the ADI v5.2 specification.
-            // All bytes are transfered in their lane, so when we do an access at an address that is not divisible by 4,
-            // we have to shift the word (one or two bytes) to it's correct position.
-            let values = data[..chunk_size]
-                .iter()
-                .enumerate()
-                .map(|(i, v)| (*v as u32) << (((address as usize + i * 2) % 4) * 8))
-                .collect::<Vec<_>>();
-
-            // autoincrement is limited to the 10 lowest bits, so write TAR every time.
-            self.memory_ap.set_target_address(self.interface, address)?;
-            self.memory_ap.write_data(self.interface, &values)?;
-
-            address = address
-                .checked_add(chunk_size as u64 * 2)
-                .ok_or(ArmError::OutOfBounds)?;
-            data = &data[chunk_size..];
+                return self.write_16_unpacked(address + (head + middle) as u64, tail_data);
+            }
         }
 
-        tracing::debug!("Finished writing block");
-
-        Ok(())
+        self.write_16_unpacked(address, data)
     }
 
     /// Write a block of 8 bit words at `address`.
     ///
     /// The number of words written is `data.len()`.
-    fn write_8(&mut self, mut address: u64, mut data: &[u8]) -> Result<(), ArmError> {
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), ArmError> {
         if self.memory_ap.supports_only_32bit_data_size() {
             return Err(ArmError::UnsupportedTransferWidth(8));
         }
 
-        if data.is_empty() {
-            return Ok(());
-        }
+        if let Some((head, middle)) = packed_split(address, data.len()) {
+            if self.packed_transfers_supported()? {
+                let (head_data, rest) = data.split_at(head);
+                let (middle_data, tail_data) = rest.split_at(middle);
 
-        tracing::debug!(
-            "Write block with total size {} bytes to address {:#08x}",
-            data.len(),
-            address
-        );
-
-        self.memory_ap
-            .try_set_datasize(self.interface, DataSize::U8)?;
-
-        while !data.is_empty() {
-            let chunk_size = data.len().min(autoincr_max_bytes(address));
-
-            tracing::debug!(
-                "Writing chunk with len {} at address {:#08x}",
-                chunk_size,
-                address
-            );
-
-            // The required shifting logic here is described in C2.2.6 Byte lanes of the ADI v5.2 specification.
-            // All bytes are transfered in their lane, so when we do an access at an address that is not divisible by 4,
-            // we have to shift the word (one or two bytes) to it's correct position.
-            let values = data[..chunk_size]
-                .iter()
-                .enumerate()
-                .map(|(i, v)| (*v as u32) << (((address as usize + i) % 4) * 8))
-                .collect::<Vec<_>>();
-
-            // autoincrement is limited to the 10 lowest bits, so write TAR every time.
-            self.memory_ap.set_target_address(self.interface, address)?;
-            self.memory_ap.write_data(self.interface, &values)?;
-
-            address = address
-                .checked_add(chunk_size as u64)
-                .ok_or(ArmError::OutOfBounds)?;
-            data = &data[chunk_size..];
+                self.write_8_unpacked(address, head_data)?;
+                self.write_packed(address + head as u64, DataSize::U8, middle_data)?;
+                return self.write_8_unpacked(address + (head + middle) as u64, tail_data);
+            }
         }
 
-        tracing::debug!("Finished writing block");
-
-        Ok(())
+        self.write_8_unpacked(address, data)
     }
 
     /// Flushes any pending commands when the underlying probe interface implements command queuing.
@@ -525,6 +782,10 @@ where
         self.memory_ap.generic_status(self.interface)
     }
 
+    fn apply_memory_ap_config(&mut self, config: &MemoryApConfig) -> Result<(), ArmError> {
+        self.memory_ap.apply_config(self.interface, config)
+    }
+
     fn update_core_status(&mut self, state: CoreStatus) {
         if let Some(probe) = self.interface.try_dap_probe_mut() {
             // Ignore errors setting the core status
@@ -545,6 +806,8 @@ mod tests {
         },
     };
 
+    use super::autoincr_max_bytes;
+
     impl<'interface> ADIMemoryInterface<'interface, MockMemoryAp> {
         /// Creates a new MemoryInterface for given AccessPort.
         fn new_mock(
@@ -571,6 +834,14 @@ mod tests {
     // DATA8 interpreted as little endian 32-bit words
     const DATA32: &[u32] = &[0x83828180, 0x87868584, 0x8b8a8988, 0x8f8e8d8c];
 
+    #[test]
+    fn autoincrement_boundary() {
+        assert_eq!(autoincr_max_bytes(0, 0x400), 0x400);
+        assert_eq!(autoincr_max_bytes(0x3FC, 0x400), 4);
+        assert_eq!(autoincr_max_bytes(0x400, 0x400), 0x400);
+        assert_eq!(autoincr_max_bytes(0x2000_0400, 0x1000), 0xC00);
+    }
+
     #[test]
     fn read_word_32() {
         let mut mock = MockMemoryAp::with_pattern_and_size(256);
@@ -877,4 +1148,100 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn read_8_packed() {
+        let mut mock = MockMemoryAp::with_pattern_and_size(256);
+        mock.packed_transfers = true;
+        mock.memory[..DATA8.len()].copy_from_slice(DATA8);
+        let mut mi = ADIMemoryInterface::new_mock(&mut mock);
+
+        for address in 0..4 {
+            for len in 0..12 {
+                let mut data = vec![0u8; len];
+                mi.read_8(address, &mut data)
+                    .unwrap_or_else(|_| panic!("read_8 failed, address = {address}, len = {len}"));
+
+                assert_eq!(
+                    data.as_slice(),
+                    &DATA8[address as usize..address as usize + len],
+                    "address = {address}, len = {len}"
+                );
+            }
+        }
+
+        assert_eq!(mi.packed_transfers, Some(true));
+    }
+
+    #[test]
+    fn read_16_packed() {
+        let mut mock = MockMemoryAp::with_pattern_and_size(256);
+        mock.packed_transfers = true;
+        mock.memory[..DATA8.len()].copy_from_slice(DATA8);
+        let mut mi = ADIMemoryInterface::new_mock(&mut mock);
+
+        for address in [0, 2] {
+            for len in 0..6 {
+                let mut data = vec![0u16; len];
+                mi.read_16(address, &mut data)
+                    .unwrap_or_else(|_| panic!("read_16 failed, address = {address}, len = {len}"));
+
+                assert_eq!(
+                    data.as_slice(),
+                    &DATA16[address as usize / 2..][..len],
+                    "address = {address}, len = {len}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn write_8_packed() {
+        for address in 0..4 {
+            for len in 0..12 {
+                let mut mock = MockMemoryAp::with_pattern_and_size(256);
+                mock.packed_transfers = true;
+                let mut mi = ADIMemoryInterface::new_mock(&mut mock);
+
+                let mut expected = Vec::from(mi.mock_memory());
+                expected[address as usize..(address as usize) + len].copy_from_slice(&DATA8[..len]);
+
+                let data = &DATA8[..len];
+                mi.write_8(address, data)
+                    .unwrap_or_else(|_| panic!("write_8 failed, address = {address}, len = {len}"));
+
+                assert_eq!(
+                    mi.mock_memory(),
+                    expected.as_slice(),
+                    "address = {address}, len = {len}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn write_16_packed() {
+        for address in [0, 2] {
+            for len in 0..6 {
+                let mut mock = MockMemoryAp::with_pattern_and_size(256);
+                mock.packed_transfers = true;
+                let mut mi = ADIMemoryInterface::new_mock(&mut mock);
+
+                let mut expected = Vec::from(mi.mock_memory());
+                expected[address as usize..(address as usize) + len * 2]
+                    .copy_from_slice(&DATA8[..len * 2]);
+
+                let data = &DATA16[..len];
+                mi.write_16(address, data).unwrap_or_else(|_| {
+                    panic!("write_16 failed, address = {address}, len = {len}")
+                });
+
+                assert_eq!(
+                    mi.mock_memory(),
+                    expected.as_slice(),
+                    "address = {address}, len = {len}"
+                );
+            }
+        }
+    }
 }
@@ -3,11 +3,11 @@
 mod adi_memory_interface;
 pub mod romtable;
 
-pub(crate) use adi_memory_interface::ADIMemoryInterface;
+pub(crate) use adi_memory_interface::{ADIMemoryInterface, DEFAULT_TAR_AUTOINCREMENT_SIZE};
 
 use crate::{CoreStatus, memory::MemoryInterface, probe::DebugProbeError};
 
-use super::{ArmDebugInterface, ArmError, FullyQualifiedApAddress};
+use super::{ArmDebugInterface, ArmError, FullyQualifiedApAddress, ap::MemoryApConfig};
 pub use romtable::{Component, ComponentId, CoresightComponent, PeripheralType, RomTable};
 
 /// Trait for accessing memory behind a memory access port,
@@ -25,6 +25,14 @@ pub trait ArmMemoryInterface: MemoryInterface<ArmError> {
     /// Get the current value of the CSW reflected in this probe.
     fn generic_status(&mut self) -> Result<crate::architecture::arm::ap::CSW, ArmError>;
 
+    /// Apply the bus access attributes in `config` to all following memory accesses.
+    ///
+    /// This can be used to mark accesses as non-secure or privileged, e.g. when accessing
+    /// memory protected by TrustZone.
+    fn apply_memory_ap_config(&mut self, _config: &MemoryApConfig) -> Result<(), ArmError> {
+        Err(ArmError::NotImplemented("memory AP configuration"))
+    }
+
     /// Inform the probe of the [`CoreStatus`] of the chip/core attached to
     /// the probe.
     //
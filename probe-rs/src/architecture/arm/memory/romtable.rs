@@ -36,17 +36,58 @@ impl RomTableError {
     }
 }
 
+/// The maximum size of the entry region of a class 0x1 ROM table.
+const CLASS1_ROM_TABLE_ENTRIES_SIZE: u64 = 0xF00;
+
+/// The maximum size of the entry region of a class 0x9 ROM table.
+const CLASS9_ROM_TABLE_ENTRIES_SIZE: u64 = 0x800;
+
+/// The layout of the entries of a ROM table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RomTableFormat {
+    /// A class 0x1 ROM table, with 32-bit entries.
+    Class1,
+    /// A class 0x9 ROM table with 32-bit entries.
+    Class9,
+    /// A class 0x9 ROM table with 64-bit entries, as indicated by DEVID.FORMAT.
+    Class9Wide,
+}
+
+impl RomTableFormat {
+    /// The size of a single entry in bytes.
+    fn entry_size(self) -> u64 {
+        match self {
+            RomTableFormat::Class1 | RomTableFormat::Class9 => 4,
+            RomTableFormat::Class9Wide => 8,
+        }
+    }
+
+    /// The size of the region which can contain entries.
+    fn entries_size(self) -> u64 {
+        match self {
+            RomTableFormat::Class1 => CLASS1_ROM_TABLE_ENTRIES_SIZE,
+            RomTableFormat::Class9 | RomTableFormat::Class9Wide => CLASS9_ROM_TABLE_ENTRIES_SIZE,
+        }
+    }
+}
+
 /// A lazy romtable reader that is used to create an iterator over all romtable entries.
 struct RomTableReader<'probe: 'memory, 'memory> {
     base_address: u64,
+    format: RomTableFormat,
     memory: &'memory mut (dyn ArmMemoryInterface + 'probe),
 }
 
 /// Iterates over a ROM table non recursively.
 impl<'probe: 'memory, 'memory> RomTableReader<'probe, 'memory> {
-    fn new(memory: &'memory mut (dyn ArmMemoryInterface + 'probe), base_address: u64) -> Self {
+    fn new(
+        memory: &'memory mut (dyn ArmMemoryInterface + 'probe),
+        base_address: u64,
+        format: RomTableFormat,
+    ) -> Self {
         RomTableReader {
             base_address,
+            format,
             memory,
         }
     }
@@ -79,36 +120,54 @@ impl Iterator for RomTableIterator<'_, '_, '_> {
     type Item = Result<RomTableEntryRaw, RomTableError>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        let format = self.rom_table_reader.format;
+
+        if self.offset >= format.entries_size() {
+            tracing::debug!("Reached the end of the ROM table entry region, stopping.");
+            return None;
+        }
+
         let component_address = self.rom_table_reader.base_address + self.offset;
         tracing::debug!("Reading rom table entry at {:#010x}", component_address);
 
-        self.offset += 4;
+        self.offset += format.entry_size();
 
-        let mut entry_data = 0u32;
+        let mut entry_data = [0u32; 2];
+        let entry_words = (format.entry_size() / 4) as usize;
 
         if let Err(e) = self
             .rom_table_reader
             .memory
-            .read_32(component_address, std::slice::from_mut(&mut entry_data))
+            .read_32(component_address, &mut entry_data[..entry_words])
         {
             return Some(Err(RomTableError::memory(e)));
         }
 
         // End of entries is marked by an all zero entry
-        if entry_data == 0 {
+        if entry_data == [0, 0] {
             tracing::debug!("Entry consists of all zeroes, stopping.");
             return None;
         }
 
+        if entry_data[1] != 0 {
+            // 64-bit offsets are not supported by the memory interfaces, so we can only use the lower half.
+            tracing::warn!(
+                "ROM table entry at {:#010x} uses a 64-bit offset ({:#010x}_{:08x}), ignoring the upper half.",
+                component_address,
+                entry_data[1],
+                entry_data[0]
+            );
+        }
+
         let entry_data =
-            RomTableEntryRaw::new(self.rom_table_reader.base_address as u32, entry_data);
+            RomTableEntryRaw::new(self.rom_table_reader.base_address as u32, entry_data[0]);
 
         tracing::debug!("ROM Table Entry: {:#x?}", entry_data);
         Some(Ok(entry_data))
     }
 }
 
-/// Encapsulates information about a CoreSight ROM table (class 1).
+/// Encapsulates information about a CoreSight ROM table (class 0x1 or class 0x9).
 #[derive(Clone, Debug, PartialEq)]
 pub struct RomTable {
     /// ALL the entries in the romtable in flattened fashion.
@@ -121,20 +180,43 @@ impl RomTable {
     ///
     /// This does not check whether the data actually signalizes
     /// to contain a ROM table but assumes this was checked beforehand.
+    ///
+    /// Both class 0x1 and class 0x9 ROM tables are supported. The class is determined from the
+    /// component ID registers of the table.
     pub fn try_parse(
         memory: &mut dyn ArmMemoryInterface,
         base_address: u64,
+    ) -> Result<RomTable, RomTableError> {
+        let format = {
+            let mut reader = ComponentInformationReader::new(base_address, memory);
+            match reader.component_class() {
+                Ok(RawComponent::CoreSightComponent) => reader.class9_format()?,
+                _ => RomTableFormat::Class1,
+            }
+        };
+
+        Self::try_parse_with_format(memory, base_address, format)
+    }
+
+    fn try_parse_with_format(
+        memory: &mut dyn ArmMemoryInterface,
+        base_address: u64,
+        format: RomTableFormat,
     ) -> Result<RomTable, RomTableError> {
         // This is required for the collect down below.
         let mut entries = vec![];
 
-        tracing::debug!("Parsing romtable at base_address {:#010x}", base_address);
+        tracing::debug!(
+            "Parsing romtable at base_address {:#010x} ({:?})",
+            base_address,
+            format
+        );
 
         // Read all the raw romtable entries and flatten them.
 
         // This is not a needless collect! It fixes the borrowing issue with &mut Memory that clippy cannot detect!
         use itertools::Itertools;
-        let reader: Vec<_> = RomTableReader::new(memory, base_address)
+        let reader: Vec<_> = RomTableReader::new(memory, base_address, format)
             .entries()
             .try_collect()?;
 
@@ -355,25 +437,42 @@ impl<'probe: 'memory, 'memory> ComponentInformationReader<'probe, 'memory> {
             .map_err(RomTableError::memory)
             .map(|v| (v & DEV_TYPE_MASK) as u8)?;
 
-        const ARCH_ID_OFFSET: u64 = 0xFBC;
-        const ARCH_ID_MASK: u32 = 0xFFFF;
-        const ARCH_ID_PRESENT_BIT: u32 = 1 << 20;
+        const DEVARCH_OFFSET: u64 = 0xFBC;
 
-        let arch_id = self
+        let devarch = self
             .memory
-            .read_word_32(self.base_address + ARCH_ID_OFFSET)
-            .map_err(RomTableError::memory)
-            .map(|v| {
-                if v & ARCH_ID_PRESENT_BIT > 0 {
-                    (v & ARCH_ID_MASK) as u16
-                } else {
-                    0
-                }
-            })?;
+            .read_word_32(self.base_address + DEVARCH_OFFSET)
+            .map_err(RomTableError::memory)?;
 
-        tracing::debug!("Dev type: {:x}, arch id: {:x}", dev_type, arch_id);
+        tracing::debug!("Dev type: {:x}, devarch: {:#010x}", dev_type, devarch);
 
-        Ok(PeripheralID::from_raw(&data, dev_type, arch_id))
+        Ok(PeripheralID::from_raw(&data, dev_type, devarch))
+    }
+
+    /// Determines the entry format of a class 0x9 ROM table from its DEVID register.
+    ///
+    /// This function does a direct memory access and is meant for internal use only.
+    fn class9_format(&mut self) -> Result<RomTableFormat, RomTableError> {
+        const DEVID_OFFSET: u64 = 0xFC8;
+        const DEVID_FORMAT_MASK: u32 = 0xF;
+
+        let devid = self
+            .memory
+            .read_word_32(self.base_address + DEVID_OFFSET)
+            .map_err(RomTableError::memory)?;
+
+        match devid & DEVID_FORMAT_MASK {
+            0 => Ok(RomTableFormat::Class9),
+            1 => Ok(RomTableFormat::Class9Wide),
+            other => {
+                tracing::warn!(
+                    "Unknown ROM table format {:#x} at {:#010x}, assuming 32-bit entries.",
+                    other,
+                    self.base_address
+                );
+                Ok(RomTableFormat::Class9)
+            }
+        }
     }
 
     /// Reads all component properties from a component info table
@@ -433,6 +532,9 @@ pub enum Component {
     ///
     /// A CoreSight component can be a Class 0x9 ROM Table, which can be identified from the DEVARCH.ARCHID having the value 0x0AF7. See also _ROM Table Types on page D2-237_. For detailed information about Class 0x9 ROM Tables, see _Chapter D4 Class 0x9 ROM Tables_.
     CoresightComponent(ComponentId),
+    /// Class 0x9 ROM Table, a CoreSight component with DEVARCH.ARCHID 0x0AF7.
+    /// For detailed information about Class 0x9 ROM Tables, see _Chapter D4 Class 0x9 ROM Tables_.
+    Class9RomTable(ComponentId, RomTable),
     /// Peripheral Test Block.
     PeripheralTestBlock(ComponentId),
     /// Generic IP component.
@@ -479,10 +581,25 @@ impl Component {
                 Component::GenericVerificationComponent(component_id)
             }
             RawComponent::RomTable => {
-                let rom_table = RomTable::try_parse(memory, component_id.component_address)?;
+                let rom_table = parse_nested_rom_table(
+                    memory,
+                    component_id.component_address,
+                    RomTableFormat::Class1,
+                )?;
 
                 Component::Class1RomTable(component_id, rom_table)
             }
+            RawComponent::CoreSightComponent
+                if component_id.peripheral_id.arch_id == CORESIGHT_ROM_TABLE_ARCHID =>
+            {
+                let format =
+                    ComponentInformationReader::new(component_id.component_address, memory)
+                        .class9_format()?;
+                let rom_table =
+                    parse_nested_rom_table(memory, component_id.component_address, format)?;
+
+                Component::Class9RomTable(component_id, rom_table)
+            }
             RawComponent::CoreSightComponent => Component::CoresightComponent(component_id),
             RawComponent::PeripheralTestBlock => Component::PeripheralTestBlock(component_id),
             RawComponent::GenericIPComponent => Component::GenericIPComponent(component_id),
@@ -500,6 +617,7 @@ impl Component {
             Component::GenericVerificationComponent(component_id) => component_id,
             Component::Class1RomTable(component_id, ..) => component_id,
             Component::CoresightComponent(component_id, ..) => component_id,
+            Component::Class9RomTable(component_id, ..) => component_id,
             Component::PeripheralTestBlock(component_id) => component_id,
             Component::GenericIPComponent(component_id) => component_id,
             Component::CoreLinkOrPrimeCellOrSystemComponent(component_id) => component_id,
@@ -507,6 +625,30 @@ impl Component {
     }
 }
 
+/// Parses a ROM table which is nested in another ROM table.
+///
+/// Memory errors while reading the entries of the nested table don't abort the parsing of
+/// the parent table. Instead, the nested table is reported without entries, so that it still
+/// shows up with its identification registers.
+fn parse_nested_rom_table(
+    memory: &mut dyn ArmMemoryInterface,
+    base_address: u64,
+    format: RomTableFormat,
+) -> Result<RomTable, RomTableError> {
+    match RomTable::try_parse_with_format(memory, base_address, format) {
+        Ok(rom_table) => Ok(rom_table),
+        Err(RomTableError::Memory(error)) => {
+            tracing::warn!(
+                "Failed to read the entries of the ROM table at {:#010x}: {}",
+                base_address,
+                error
+            );
+            Ok(RomTable { entries: vec![] })
+        }
+        Err(error) => Err(error),
+    }
+}
+
 /// A Coresight debug component that can be configured with the Probe.
 #[derive(Clone, Debug)]
 pub struct CoresightComponent {
@@ -610,9 +752,11 @@ impl<'a> Iterator for CoresightComponentIter<'a> {
         if let Some(component) = self.components.get(self.current) {
             // If it has children, remember to iterate them next.
             self.children = match &component.component {
-                Component::Class1RomTable(_, v) => Some(Box::new(CoresightComponentIter::new(
-                    v.entries.iter().map(|v| &v.component).collect(),
-                ))),
+                Component::Class1RomTable(_, v) | Component::Class9RomTable(_, v) => {
+                    Some(Box::new(CoresightComponentIter::new(
+                        v.entries.iter().map(|v| &v.component).collect(),
+                    )))
+                }
                 _ => None,
             };
             // Advance the pointer by one.
@@ -658,17 +802,35 @@ pub struct PeripheralID {
     SIZE: u8,
     /// The dev_type of the peripheral
     dev_type: u8,
-    /// The arch_id of the peripheral
+    /// The arch_id of the peripheral, or zero if DEVARCH is not present.
     arch_id: u16,
+    /// The architect of the component, as indicated by DEVARCH.ARCHITECT.
+    architect: Option<jep106::JEP106Code>,
+    /// The architecture revision, as indicated by DEVARCH.REVISION.
+    arch_revision: u8,
+    /// The raw value of the PIDR0 to PIDR7 registers, PIDR0 in the lowest byte.
+    raw_pidr: u64,
+    /// The raw value of the DEVARCH register.
+    raw_devarch: u32,
 }
 
 impl PeripheralID {
     /// Extracts the peripheral ID of the CoreSight component table data.
-    fn from_raw(data: &[u32; 8], dev_type: u8, arch_id: u16) -> Self {
+    ///
+    /// `data` contains PIDR0 to PIDR3 in the first four elements, followed by PIDR4 to PIDR7.
+    fn from_raw(data: &[u32; 8], dev_type: u8, devarch: u32) -> Self {
+        const DEVARCH_PRESENT_BIT: u32 = 1 << 20;
+
         let jep106id = (((data[2] & 0x07) << 4) | ((data[1] >> 4) & 0x0F)) as u8;
         let jep106 = jep106::JEP106Code::new((data[4] & 0x0F) as u8, jep106id);
         let legacy = (data[2] & 0x8) > 1;
 
+        let devarch_present = devarch & DEVARCH_PRESENT_BIT != 0;
+
+        let raw_pidr = data.iter().enumerate().fold(0u64, |acc, (i, value)| {
+            acc | (u64::from(value & 0xFF) << (8 * i))
+        });
+
         PeripheralID {
             REVAND: ((data[3] >> 4) & 0x0F) as u8,
             CMOD: match (data[3] & 0x0F) as u8 {
@@ -680,7 +842,24 @@ impl PeripheralID {
             PART: (((data[1] & 0x0F) << 8) | (data[0] & 0xFF)) as u16,
             SIZE: 2u32.pow((data[4] >> 4) & 0x0F) as u8,
             dev_type,
-            arch_id,
+            arch_id: if devarch_present {
+                (devarch & 0xFFFF) as u16
+            } else {
+                0
+            },
+            architect: devarch_present.then(|| {
+                jep106::JEP106Code::new(
+                    ((devarch >> 28) & 0x0F) as u8,
+                    ((devarch >> 21) & 0x7F) as u8,
+                )
+            }),
+            arch_revision: if devarch_present {
+                ((devarch >> 16) & 0x0F) as u8
+            } else {
+                0
+            },
+            raw_pidr,
+            raw_devarch: devarch,
         }
     }
 
@@ -721,11 +900,74 @@ impl PeripheralID {
         self.REVISION
     }
 
+    /// The architect of the component, if the DEVARCH register is present.
+    ///
+    /// This can differ from the designer, e.g. for vendor implementations of Arm architected components.
+    pub fn architect(&self) -> Option<jep106::JEP106Code> {
+        self.architect
+    }
+
+    /// The architecture revision (DEVARCH.REVISION) of the component.
+    pub fn arch_revision(&self) -> u8 {
+        self.arch_revision
+    }
+
+    /// The raw value of the PIDR0 to PIDR7 registers, with PIDR0 in the lowest byte.
+    pub fn raw_pidr(&self) -> u64 {
+        self.raw_pidr
+    }
+
+    /// The raw value of the DEVARCH register.
+    pub fn raw_devarch(&self) -> u32 {
+        self.raw_devarch
+    }
+
     /// Uses the available data to match it against a table of known components.
     /// If the component is known, some info about it is returned.
     /// If it is not known, None is returned.
-    #[rustfmt::skip]
+    ///
+    /// Components are identified by their peripheral ID first. If that fails, the
+    /// architecture indicated by DEVARCH and DEVTYPE is used.
     pub fn determine_part(&self) -> Option<PartInfo> {
+        self.determine_part_from_pidr()
+            .or_else(|| self.determine_part_from_devarch())
+    }
+
+    /// Identifies components which implement an architecture defined by Arm, independent of their designer.
+    #[rustfmt::skip]
+    fn determine_part_from_devarch(&self) -> Option<PartInfo> {
+        let arm_ltd = jep106::JEP106Code::new(4, 0x3B);
+
+        if self.architect != Some(arm_ltd) {
+            return None;
+        }
+
+        // From IHI0029F: Coresight v3.0 architecture Specification, and the respective architecture reference manuals.
+        match (self.arch_id, self.dev_type) {
+            (0x0A06, _) => Some(PartInfo::new("PMU architecture", PeripheralType::Pmu)),
+            (0x0A17, _) => Some(PartInfo::new("Memory Access Port v2", PeripheralType::MemAp)),
+            (0x0A63, _) => Some(PartInfo::new("STM architecture", PeripheralType::Stm)),
+            (0x0AF7, _) => Some(PartInfo::new("ROM architecture", PeripheralType::Rom)),
+            (0x1A01, _) => Some(PartInfo::new("ITM architecture", PeripheralType::Itm)),
+            (0x1A02, _) => Some(PartInfo::new("DWT architecture", PeripheralType::Dwt)),
+            (0x1A03, _) => Some(PartInfo::new("FPB architecture", PeripheralType::Fbp)),
+            (0x1A14, _) => Some(PartInfo::new("CTI architecture", PeripheralType::Cti)),
+            (0x2A04, _) => Some(PartInfo::new("Processor debug architecture (ARMv8-M)", PeripheralType::Scs)),
+            (0x2A16, _) => Some(PartInfo::new("PMU architecture (ARMv8-A)", PeripheralType::Pmu)),
+            (0x4A13, _) => Some(PartInfo::new("ETM architecture", PeripheralType::Etm)),
+            (0x6A15, _) => Some(PartInfo::new("Processor debug architecture (ARMv8.0-A)", PeripheralType::ProcessorDebug)),
+            (0x7A15, _) => Some(PartInfo::new("Processor debug architecture (ARMv8.1-A)", PeripheralType::ProcessorDebug)),
+            (0x8A15, _) => Some(PartInfo::new("Processor debug architecture (ARMv8.2-A)", PeripheralType::ProcessorDebug)),
+            // Components without a specific architecture, identified by their device type.
+            (_, 0x11) => Some(PartInfo::new("Trace port", PeripheralType::Tpiu)),
+            (_, 0x12) => Some(PartInfo::new("Trace funnel", PeripheralType::TraceFunnel)),
+            (_, 0x21) => Some(PartInfo::new("Trace buffer", PeripheralType::Etb)),
+            _ => None,
+        }
+    }
+
+    #[rustfmt::skip]
+    fn determine_part_from_pidr(&self) -> Option<PartInfo> {
         // Source of the table: https://github.com/blacksphere/blackmagic/blob/master/src/target/adiv5.c#L189
         // Not all are present and this table could be expanded
         match (
@@ -869,6 +1111,8 @@ pub enum PeripheralType {
     MemAp,
     /// Performance monitoring unit
     Pmu,
+    /// External debug interface of an A-profile processor
+    ProcessorDebug,
     /// Non-standard peripheral
     Custom,
 }
@@ -895,6 +1139,44 @@ impl std::fmt::Display for PeripheralType {
             PeripheralType::Custom => write!(f, "(Non-standard peripheral)"),
             PeripheralType::MemAp => write!(f, "MEM-AP (Memory Access Port)"),
             PeripheralType::Pmu => write!(f, "PMU (Performance Monitoring Unit)"),
+            PeripheralType::ProcessorDebug => write!(f, "Processor Debug"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// PIDR of a component with PART 0x123, designed by STMicroelectronics (JEP106 0x20, bank 0).
+    const ST_PIDR: [u32; 8] = [0x23, 0x01, 0x0A, 0x00, 0x00, 0x00, 0x00, 0x00];
+
+    #[test]
+    fn devarch_is_decoded() {
+        // ARCHITECT = Arm, PRESENT, REVISION = 2, ARCHID = 0x1A02 (DWT)
+        let id = PeripheralID::from_raw(&ST_PIDR, 0x00, 0x4772_1A02);
+
+        assert_eq!(id.arch_id(), 0x1A02);
+        assert_eq!(id.arch_revision(), 2);
+        assert_eq!(id.architect(), Some(jep106::JEP106Code::new(4, 0x3B)));
+        assert_eq!(id.raw_devarch(), 0x4772_1A02);
+        assert_eq!(id.raw_pidr(), 0x0A_01_23);
+    }
+
+    #[test]
+    fn vendor_component_is_identified_by_devarch() {
+        let id = PeripheralID::from_raw(&ST_PIDR, 0x00, 0x4772_1A02);
+
+        assert_eq!(id.designer(), Some("STMicroelectronics"));
+        assert!(id.is_of_type(PeripheralType::Dwt));
+    }
+
+    #[test]
+    fn devarch_is_ignored_if_not_present() {
+        let id = PeripheralID::from_raw(&ST_PIDR, 0x00, 0x4760_1A02);
+
+        assert_eq!(id.arch_id(), 0);
+        assert_eq!(id.architect(), None);
+        assert!(id.determine_part().is_none());
+    }
+}
@@ -13,7 +13,7 @@ pub mod sequences;
 pub mod swo;
 pub(crate) mod traits;
 
-pub use self::core::{Dump, armv6m, armv7a, armv7m, armv8a, armv8m};
+pub use self::core::{Dump, armv6m, armv7a, armv7m, armv8a, armv8m, performance_counters};
 use self::{
     ap::AccessPortError,
     dp::DebugPortError,
@@ -167,6 +167,40 @@ impl ArmError {
     pub fn alignment_error(address: u64, alignment: usize) -> Self {
         ArmError::MemoryNotAligned(MemoryNotAlignedError { address, alignment })
     }
+
+    /// Returns whether the error is caused by the debug logic of the target losing power, e.g.
+    /// because the target entered a STOP or STANDBY low-power mode.
+    pub fn is_power_down(&self) -> bool {
+        match self {
+            ArmError::Dap(error) => {
+                matches!(error, DapError::NoAcknowledge | DapError::FaultResponse)
+            }
+            ArmError::DebugPort(error)
+            | ArmError::AccessPort {
+                source: AccessPortError::DebugPort(error),
+                ..
+            } => matches!(
+                error,
+                DebugPortError::TargetPowerUpFailed
+                    | DebugPortError::Dap(DapError::NoAcknowledge | DapError::FaultResponse)
+            ),
+            _ => false,
+        }
+    }
+
+    /// Returns whether the error is caused by a transfer which was corrupted on the wire, e.g.
+    /// because of long or noisy cables.
+    pub fn is_link_error(&self) -> bool {
+        match self {
+            ArmError::Dap(error) => matches!(error, DapError::IncorrectParity),
+            ArmError::DebugPort(error)
+            | ArmError::AccessPort {
+                source: AccessPortError::DebugPort(error),
+                ..
+            } => matches!(error, DebugPortError::Dap(DapError::IncorrectParity)),
+            _ => false,
+        }
+    }
 }
 
 impl From<RomTableError> for ArmError {
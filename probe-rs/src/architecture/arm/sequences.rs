@@ -8,15 +8,16 @@ use std::{
     time::{Duration, Instant},
 };
 
-use probe_rs_target::CoreType;
+use probe_rs_target::{CoreType, DebugFreezeBit};
 
 use crate::{
-    MemoryInterface, MemoryMappedRegister, Session,
+    FreezeConfig, MemoryInterface, MemoryMappedRegister, Session,
     architecture::arm::{
         ArmDebugInterface, DapError, RegisterAddress,
         core::registers::cortex_m::{PC, SP},
         dp::{Ctrl, DLPIDR, DebugPortError, DpRegister, TARGETID},
     },
+    device_info::SecurityState,
     probe::WireProtocol,
 };
 
@@ -27,13 +28,18 @@ use super::{
     communication_interface::DapProbe,
     component::{TraceFunnel, TraceSink},
     core::cortex_m::{Dhcsr, Vtor},
-    dp::{Abort, DPIDR, DpAccess, DpAddress, SelectV1},
+    dp::{
+        Abort, DPIDR, DebugPortControl, DebugPowerConfig, DpAccess, DpAddress, PowerStatus,
+        SelectV1,
+    },
     memory::{
         ArmMemoryInterface,
         romtable::{CoresightComponent, PeripheralType},
     },
 };
 
+pub mod pack;
+
 /// An error occurred when executing an ARM debug sequence
 #[derive(thiserror::Error, Debug)]
 pub enum ArmDebugSequenceError {
@@ -643,7 +649,7 @@ pub trait ArmDebugSequence: Send + Sync + Debug {
             abort.set_stkcmpclr(true);
             interface.write_dp_register(dp, abort)?;
 
-            !(ctrl.csyspwrupack() && ctrl.cdbgpwrupack())
+            !PowerStatus::from_ctrl(&ctrl).is_powered()
         };
 
         if powered_down {
@@ -681,13 +687,10 @@ pub trait ArmDebugSequence: Send + Sync + Debug {
                 Err(e) => return Err(e),
             }
 
+            let timeout = DebugPowerConfig::default().timeout;
             let start = Instant::now();
-            loop {
-                let ctrl = interface.read_dp_register::<Ctrl>(dp)?;
-                if ctrl.csyspwrupack() && ctrl.cdbgpwrupack() {
-                    break;
-                }
-                if start.elapsed() >= Duration::from_secs(1) {
+            while !interface.power_status(dp)?.is_powered() {
+                if start.elapsed() >= timeout {
                     return Err(ArmError::Timeout);
                 }
             }
@@ -701,8 +704,7 @@ pub trait ArmDebugSequence: Send + Sync + Debug {
             // register after powerup.
             interface.write_dp_register(dp, ctrl)?;
 
-            let ctrl_reg: Ctrl = interface.read_dp_register(dp)?;
-            if !(ctrl_reg.csyspwrupack() && ctrl_reg.cdbgpwrupack()) {
+            if !interface.power_status(dp)?.is_powered() {
                 tracing::error!("Debug power request failed");
                 return Err(DebugPortError::TargetPowerUpFailed.into());
             }
@@ -869,6 +871,29 @@ pub trait ArmDebugSequence: Send + Sync + Debug {
         Ok(())
     }
 
+    /// Keeps the debug logic powered while the target is in a low-power mode, e.g. by setting the
+    /// `DBG_STOP` and `DBG_STANDBY` bits of the DBGMCU on STM32 devices.
+    ///
+    /// Executed when the device is unlocked, and again after the connection was recovered from a
+    /// power-down. Empty by default.
+    fn debug_low_power(&self, _interface: &mut dyn ArmMemoryInterface) -> Result<(), ArmError> {
+        Ok(())
+    }
+
+    /// Stops the peripherals selected by `config` while the cores are halted, by setting the
+    /// debug freeze bits of the target description in `bits`.
+    ///
+    /// Executed after debugging was enabled on all cores. Override this if the freeze registers
+    /// are not accessible without further setup.
+    fn debug_freeze(
+        &self,
+        interface: &mut dyn ArmMemoryInterface,
+        bits: &[DebugFreezeBit],
+        config: &FreezeConfig,
+    ) -> Result<(), ArmError> {
+        config.apply(interface, bits)
+    }
+
     /// Executed when the debugger session is disconnected from the core.
     ///
     /// This is based on the `DebugCoreStop` function from the [ARM SVD Debug Description].
@@ -910,15 +935,15 @@ pub trait ArmDebugSequence: Send + Sync + Debug {
         interface.raw_write_register(Ctrl::ADDRESS.into(), 0)?;
 
         // Wait for the power domains to go away
+        let timeout = DebugPowerConfig::default().timeout;
         let start = Instant::now();
         loop {
             let ctrl = interface.raw_read_register(Ctrl::ADDRESS.into())?;
-            let ctrl = Ctrl(ctrl);
-            if !(ctrl.csyspwrupack() || ctrl.cdbgpwrupack()) {
+            if PowerStatus::from_ctrl(&Ctrl(ctrl)).is_powered_down() {
                 return Ok(());
             }
 
-            if start.elapsed() >= Duration::from_secs(1) {
+            if start.elapsed() >= timeout {
                 return Err(ArmError::Timeout);
             }
         }
@@ -1123,6 +1148,18 @@ pub trait ArmDebugSequence: Send + Sync + Debug {
     fn allowed_access_ports(&self) -> Vec<u8> {
         (0..=255).collect()
     }
+
+    /// Determine whether debug access to the device is blocked, e.g. by a readout protection.
+    ///
+    /// This is used to report a secured device when attaching fails, instead of the
+    /// access error. Returns `None` if the state can not be determined by vendor specific means.
+    fn security_state(
+        &self,
+        _interface: &mut dyn ArmDebugInterface,
+        _default_ap: &FullyQualifiedApAddress,
+    ) -> Result<Option<SecurityState>, ArmError> {
+        Ok(None)
+    }
 }
 
 /// Chip-Erase Handling via the Device's Debug Interface
@@ -0,0 +1,585 @@
+//! Debug sequences interpreted from the debug description of a CMSIS-Pack.
+//!
+//! Chips whose pack defines sequences like `ResetCatchSet` or `DebugDeviceUnlock` get them
+//! executed in place of the default implementation of [`ArmDebugSequence`]. Sequences the pack
+//! does not define fall back to [`DefaultArmSequence`].
+
+mod interpreter;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use probe_rs_target::{CoreType, DebugDescription};
+
+use crate::{
+    architecture::arm::{
+        ApAddress, ApV2Address, ArmDebugInterface, ArmError, DapAccess, FullyQualifiedApAddress,
+        RegisterAddress,
+        communication_interface::DapProbe,
+        component::TraceSink,
+        dp::{DpAddress, DpRegisterAddress},
+        memory::{ArmMemoryInterface, CoresightComponent},
+        sequences::{ArmDebugSequence, ArmDebugSequenceError, DefaultArmSequence},
+    },
+    probe::WireProtocol,
+};
+
+use interpreter::{Host, Interpreter};
+
+/// Runs the sequences of a CMSIS-Pack debug description.
+#[derive(Debug)]
+pub struct PackSequence {
+    description: DebugDescription,
+    /// The debug access variables. They keep their values from one sequence to the next, and
+    /// are initialized by the `<debugvars>` on first use.
+    variables: Mutex<Option<HashMap<String, u64>>>,
+}
+
+impl PackSequence {
+    /// Creates a debug sequence for the given debug description.
+    pub fn create(description: DebugDescription) -> Arc<dyn ArmDebugSequence> {
+        Arc::new(Self {
+            description,
+            variables: Mutex::new(None),
+        })
+    }
+
+    /// Runs the sequence `name`. Returns `false` if the description doesn't define it, so that
+    /// the default implementation can be used instead.
+    fn run(&self, name: &str, mut host: SequenceHost<'_>) -> Result<bool, ArmError> {
+        if self.description.sequence(name, None).is_none() {
+            return Ok(false);
+        }
+
+        let mut variables = self.variables.lock().unwrap();
+        let initialize = variables.is_none();
+        let globals = variables.get_or_insert_with(predefined_variables);
+        host.update_variables(globals);
+
+        let mut interpreter = Interpreter::new(&self.description, globals, &mut host);
+        if initialize {
+            if let Err(error) = interpreter.run_debug_vars() {
+                *variables = None;
+                return Err(error);
+            }
+        }
+
+        interpreter.run_sequence(name)
+    }
+}
+
+/// The variables predefined by the debug description language.
+fn predefined_variables() -> HashMap<String, u64> {
+    [
+        // SWD, the protocol is updated once it is known.
+        ("__protocol", 2),
+        // Connected for debugging.
+        ("__connection", 1),
+        ("__dp", 0),
+        ("__ap", 0),
+        ("__apid", 0),
+        ("__traceout", 0),
+        ("__errorcontrol", 0),
+        ("__FlashOp", 0),
+        ("__Result", 0),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value))
+    .collect()
+}
+
+/// The interface a sequence is called with. It determines which functions are available to the
+/// sequence: before the debug port is started, only the wire protocol can be used for example.
+enum Access<'a> {
+    Probe(&'a mut dyn DapProbe),
+    Dap(&'a mut dyn DapAccess),
+    Interface(&'a mut dyn ArmDebugInterface),
+    Memory(&'a mut dyn ArmMemoryInterface),
+}
+
+struct SequenceHost<'a> {
+    access: Access<'a>,
+    dp: DpAddress,
+    /// The access port selected by `__ap` when the sequence starts.
+    ap: Option<FullyQualifiedApAddress>,
+}
+
+impl<'a> SequenceHost<'a> {
+    fn new(access: Access<'a>, dp: DpAddress) -> Self {
+        Self {
+            access,
+            dp,
+            ap: None,
+        }
+    }
+
+    fn with_ap(mut self, ap: &FullyQualifiedApAddress) -> Self {
+        self.dp = ap.dp();
+        self.ap = Some(ap.clone());
+        self
+    }
+
+    fn update_variables(&mut self, variables: &mut HashMap<String, u64>) {
+        if let Some(ap) = &self.ap {
+            let ap = match ap.ap() {
+                ApAddress::V1(ap) => *ap as u64,
+                ApAddress::V2(ApV2Address(address)) => address.unwrap_or(0),
+            };
+            variables.insert("__ap".to_string(), ap);
+        }
+
+        if let Access::Probe(probe) = &self.access {
+            let protocol = match probe.active_protocol() {
+                Some(WireProtocol::Jtag) => 1,
+                _ => 2,
+            };
+            variables.insert("__protocol".to_string(), protocol);
+        }
+    }
+
+    /// The access port selected by `__ap`.
+    fn selected_ap(&self, variables: &HashMap<String, u64>) -> FullyQualifiedApAddress {
+        let ap = variables.get("__ap").copied().unwrap_or(0);
+
+        let is_v2 = self
+            .ap
+            .as_ref()
+            .is_some_and(|ap| matches!(ap.ap(), ApAddress::V2(_)));
+        if is_v2 {
+            FullyQualifiedApAddress::v2_with_dp(self.dp, ApV2Address::new(ap))
+        } else {
+            FullyQualifiedApAddress::v1_with_dp(self.dp, ap as u8)
+        }
+    }
+
+    fn unavailable(function: &str) -> ArmError {
+        ArmDebugSequenceError::custom(format!("{function} is not available in this sequence"))
+            .into()
+    }
+
+    fn debug_interface(&mut self, function: &str) -> Result<&mut dyn ArmDebugInterface, ArmError> {
+        match &mut self.access {
+            Access::Interface(interface) => Ok(&mut **interface),
+            Access::Memory(memory) => Ok(memory.get_arm_debug_interface()?),
+            Access::Probe(_) | Access::Dap(_) => Err(Self::unavailable(function)),
+        }
+    }
+
+    fn read_dp(&mut self, address: u64) -> Result<u32, ArmError> {
+        let dp = self.dp;
+        let address = DpRegisterAddress {
+            address: address as u8,
+            bank: None,
+        };
+
+        match &mut self.access {
+            Access::Probe(probe) => probe.raw_read_register(RegisterAddress::DpRegister(address)),
+            Access::Dap(dap) => dap.read_raw_dp_register(dp, address),
+            Access::Interface(_) | Access::Memory(_) => self
+                .debug_interface("ReadDP")?
+                .read_raw_dp_register(dp, address),
+        }
+    }
+
+    fn write_dp(&mut self, address: u64, value: u32) -> Result<(), ArmError> {
+        let dp = self.dp;
+        let address = DpRegisterAddress {
+            address: address as u8,
+            bank: None,
+        };
+
+        match &mut self.access {
+            Access::Probe(probe) => {
+                probe.raw_write_register(RegisterAddress::DpRegister(address), value)
+            }
+            Access::Dap(dap) => dap.write_raw_dp_register(dp, address, value),
+            Access::Interface(_) | Access::Memory(_) => self
+                .debug_interface("WriteDP")?
+                .write_raw_dp_register(dp, address, value),
+        }
+    }
+
+    fn read_ap(&mut self, ap: &FullyQualifiedApAddress, address: u64) -> Result<u32, ArmError> {
+        if let Access::Dap(dap) = &mut self.access {
+            return dap.read_raw_ap_register(ap, address);
+        }
+
+        self.debug_interface("ReadAP")?
+            .read_raw_ap_register(ap, address)
+    }
+
+    fn write_ap(
+        &mut self,
+        ap: &FullyQualifiedApAddress,
+        address: u64,
+        value: u32,
+    ) -> Result<(), ArmError> {
+        if let Access::Dap(dap) = &mut self.access {
+            return dap.write_raw_ap_register(ap, address, value);
+        }
+
+        self.debug_interface("WriteAP")?
+            .write_raw_ap_register(ap, address, value)
+    }
+
+    fn read_memory(
+        &mut self,
+        ap: &FullyQualifiedApAddress,
+        address: u64,
+        bits: u32,
+    ) -> Result<u64, ArmError> {
+        if let Access::Memory(memory) = &mut self.access {
+            if memory.fully_qualified_address() == *ap {
+                return read_memory(&mut **memory, address, bits);
+            }
+        }
+
+        let mut memory = self.debug_interface("Read")?.memory_interface(ap)?;
+        read_memory(&mut *memory, address, bits)
+    }
+
+    fn write_memory(
+        &mut self,
+        ap: &FullyQualifiedApAddress,
+        address: u64,
+        bits: u32,
+        value: u64,
+    ) -> Result<(), ArmError> {
+        if let Access::Memory(memory) = &mut self.access {
+            if memory.fully_qualified_address() == *ap {
+                return write_memory(&mut **memory, address, bits, value);
+            }
+        }
+
+        let mut memory = self.debug_interface("Write")?.memory_interface(ap)?;
+        write_memory(&mut *memory, address, bits, value)
+    }
+
+    fn swj_pins(&mut self, pin_out: u32, pin_select: u32, pin_wait: u32) -> Result<u32, ArmError> {
+        if let Access::Probe(probe) = &mut self.access {
+            return Ok(probe.swj_pins(pin_out, pin_select, pin_wait)?);
+        }
+
+        Ok(self
+            .debug_interface("DAP_SWJ_Pins")?
+            .swj_pins(pin_out, pin_select, pin_wait)?)
+    }
+
+    fn swj_sequence(&mut self, bit_len: u8, bits: u64) -> Result<(), ArmError> {
+        if let Access::Probe(probe) = &mut self.access {
+            return Ok(probe.swj_sequence(bit_len, bits)?);
+        }
+
+        Ok(self
+            .debug_interface("DAP_SWJ_Sequence")?
+            .swj_sequence(bit_len, bits)?)
+    }
+}
+
+fn read_memory(
+    memory: &mut dyn ArmMemoryInterface,
+    address: u64,
+    bits: u32,
+) -> Result<u64, ArmError> {
+    Ok(match bits {
+        8 => memory.read_word_8(address)? as u64,
+        16 => memory.read_word_16(address)? as u64,
+        32 => memory.read_word_32(address)? as u64,
+        _ => memory.read_word_64(address)?,
+    })
+}
+
+fn write_memory(
+    memory: &mut dyn ArmMemoryInterface,
+    address: u64,
+    bits: u32,
+    value: u64,
+) -> Result<(), ArmError> {
+    match bits {
+        8 => memory.write_word_8(address, value as u8),
+        16 => memory.write_word_16(address, value as u16),
+        32 => memory.write_word_32(address, value as u32),
+        _ => memory.write_word_64(address, value),
+    }
+}
+
+impl Host for SequenceHost<'_> {
+    fn call(
+        &mut self,
+        name: &str,
+        args: &[u64],
+        variables: &HashMap<String, u64>,
+    ) -> Result<u64, ArmError> {
+        let ap = self.selected_ap(variables);
+
+        let result = match (name, args) {
+            ("Read8", [address]) => self.read_memory(&ap, *address, 8)?,
+            ("Read16", [address]) => self.read_memory(&ap, *address, 16)?,
+            ("Read32", [address]) => self.read_memory(&ap, *address, 32)?,
+            ("Read64", [address]) => self.read_memory(&ap, *address, 64)?,
+            ("Write8", [address, value]) => {
+                self.write_memory(&ap, *address, 8, *value)?;
+                0
+            }
+            ("Write16", [address, value]) => {
+                self.write_memory(&ap, *address, 16, *value)?;
+                0
+            }
+            ("Write32", [address, value]) => {
+                self.write_memory(&ap, *address, 32, *value)?;
+                0
+            }
+            ("Write64", [address, value]) => {
+                self.write_memory(&ap, *address, 64, *value)?;
+                0
+            }
+            ("ReadDP", [address]) => self.read_dp(*address)? as u64,
+            ("WriteDP", [address, value]) => {
+                self.write_dp(*address, *value as u32)?;
+                0
+            }
+            ("ReadAP", [address]) => self.read_ap(&ap, *address)? as u64,
+            ("WriteAP", [address, value]) => {
+                self.write_ap(&ap, *address, *value as u32)?;
+                0
+            }
+            ("ReadAccessAP", [address]) => {
+                let apid = variables.get("__apid").copied().unwrap_or(0);
+                let ap = FullyQualifiedApAddress::v1_with_dp(self.dp, apid as u8);
+                self.read_ap(&ap, *address)? as u64
+            }
+            ("WriteAccessAP", [address, value]) => {
+                let apid = variables.get("__apid").copied().unwrap_or(0);
+                let ap = FullyQualifiedApAddress::v1_with_dp(self.dp, apid as u8);
+                self.write_ap(&ap, *address, *value as u32)?;
+                0
+            }
+            ("DAP_WriteABORT", [value]) => {
+                self.write_dp(0, *value as u32)?;
+                0
+            }
+            ("DAP_Delay", [delay]) => {
+                thread::sleep(Duration::from_micros(*delay));
+                0
+            }
+            ("DAP_SWJ_Pins", [pin_out, pin_select, pin_wait]) => {
+                self.swj_pins(*pin_out as u32, *pin_select as u32, *pin_wait as u32)? as u64
+            }
+            ("DAP_SWJ_Sequence", [bit_len, bits]) => {
+                self.swj_sequence(*bit_len as u8, *bits)?;
+                0
+            }
+            ("DAP_JTAG_Sequence", [cycles, tms, tdi]) => {
+                let Access::Probe(probe) = &mut self.access else {
+                    return Err(Self::unavailable(name));
+                };
+                probe.jtag_sequence(*cycles as u8, *tms != 0, *tdi)?;
+                0
+            }
+            ("DAP_SWJ_Clock", [_]) => {
+                tracing::debug!("Ignoring DAP_SWJ_Clock, the speed is configured by the user");
+                0
+            }
+            _ => {
+                return Err(ArmDebugSequenceError::custom(format!(
+                    "Unsupported function {name} with {} arguments",
+                    args.len()
+                ))
+                .into());
+            }
+        };
+
+        Ok(result)
+    }
+}
+
+impl ArmDebugSequence for PackSequence {
+    fn reset_hardware_assert(&self, interface: &mut dyn DapProbe) -> Result<(), ArmError> {
+        let host = SequenceHost::new(Access::Probe(&mut *interface), DpAddress::Default);
+        if self.run("ResetHardwareAssert", host)? {
+            return Ok(());
+        }
+
+        DefaultArmSequence(()).reset_hardware_assert(interface)
+    }
+
+    fn reset_hardware_deassert(
+        &self,
+        probe: &mut dyn ArmDebugInterface,
+        default_ap: &FullyQualifiedApAddress,
+    ) -> Result<(), ArmError> {
+        let host =
+            SequenceHost::new(Access::Interface(&mut *probe), default_ap.dp()).with_ap(default_ap);
+        if self.run("ResetHardwareDeassert", host)? {
+            return Ok(());
+        }
+
+        DefaultArmSequence(()).reset_hardware_deassert(probe, default_ap)
+    }
+
+    fn debug_port_setup(
+        &self,
+        interface: &mut dyn DapProbe,
+        dp: DpAddress,
+    ) -> Result<(), ArmError> {
+        if self.run(
+            "DebugPortSetup",
+            SequenceHost::new(Access::Probe(&mut *interface), dp),
+        )? {
+            return Ok(());
+        }
+
+        DefaultArmSequence(()).debug_port_setup(interface, dp)
+    }
+
+    fn debug_port_start(
+        &self,
+        interface: &mut dyn DapAccess,
+        dp: DpAddress,
+    ) -> Result<(), ArmError> {
+        if self.run(
+            "DebugPortStart",
+            SequenceHost::new(Access::Dap(&mut *interface), dp),
+        )? {
+            return Ok(());
+        }
+
+        DefaultArmSequence(()).debug_port_start(interface, dp)
+    }
+
+    fn debug_port_stop(&self, interface: &mut dyn DapProbe, dp: DpAddress) -> Result<(), ArmError> {
+        if self.run(
+            "DebugPortStop",
+            SequenceHost::new(Access::Probe(&mut *interface), dp),
+        )? {
+            return Ok(());
+        }
+
+        DefaultArmSequence(()).debug_port_stop(interface, dp)
+    }
+
+    fn debug_core_start(
+        &self,
+        interface: &mut dyn ArmDebugInterface,
+        core_ap: &FullyQualifiedApAddress,
+        core_type: CoreType,
+        debug_base: Option<u64>,
+        cti_base: Option<u64>,
+    ) -> Result<(), ArmError> {
+        let host =
+            SequenceHost::new(Access::Interface(&mut *interface), core_ap.dp()).with_ap(core_ap);
+        if self.run("DebugCoreStart", host)? {
+            return Ok(());
+        }
+
+        DefaultArmSequence(()).debug_core_start(interface, core_ap, core_type, debug_base, cti_base)
+    }
+
+    fn debug_core_stop(
+        &self,
+        interface: &mut dyn ArmMemoryInterface,
+        core_type: CoreType,
+    ) -> Result<(), ArmError> {
+        let ap = interface.fully_qualified_address();
+        let host = SequenceHost::new(Access::Memory(&mut *interface), ap.dp()).with_ap(&ap);
+        if self.run("DebugCoreStop", host)? {
+            return Ok(());
+        }
+
+        DefaultArmSequence(()).debug_core_stop(interface, core_type)
+    }
+
+    fn reset_catch_set(
+        &self,
+        core: &mut dyn ArmMemoryInterface,
+        core_type: CoreType,
+        debug_base: Option<u64>,
+    ) -> Result<(), ArmError> {
+        let ap = core.fully_qualified_address();
+        let host = SequenceHost::new(Access::Memory(&mut *core), ap.dp()).with_ap(&ap);
+        if self.run("ResetCatchSet", host)? {
+            return Ok(());
+        }
+
+        DefaultArmSequence(()).reset_catch_set(core, core_type, debug_base)
+    }
+
+    fn reset_catch_clear(
+        &self,
+        core: &mut dyn ArmMemoryInterface,
+        core_type: CoreType,
+        debug_base: Option<u64>,
+    ) -> Result<(), ArmError> {
+        let ap = core.fully_qualified_address();
+        let host = SequenceHost::new(Access::Memory(&mut *core), ap.dp()).with_ap(&ap);
+        if self.run("ResetCatchClear", host)? {
+            return Ok(());
+        }
+
+        DefaultArmSequence(()).reset_catch_clear(core, core_type, debug_base)
+    }
+
+    fn trace_start(
+        &self,
+        interface: &mut dyn ArmDebugInterface,
+        components: &[CoresightComponent],
+        sink: &TraceSink,
+    ) -> Result<(), ArmError> {
+        let dp = interface.current_debug_port().unwrap_or_default();
+        if self.run(
+            "TraceStart",
+            SequenceHost::new(Access::Interface(&mut *interface), dp),
+        )? {
+            return Ok(());
+        }
+
+        DefaultArmSequence(()).trace_start(interface, components, sink)
+    }
+
+    fn reset_system(
+        &self,
+        interface: &mut dyn ArmMemoryInterface,
+        core_type: CoreType,
+        debug_base: Option<u64>,
+    ) -> Result<(), ArmError> {
+        let ap = interface.fully_qualified_address();
+        let host = SequenceHost::new(Access::Memory(&mut *interface), ap.dp()).with_ap(&ap);
+        if self.run("ResetSystem", host)? {
+            return Ok(());
+        }
+
+        DefaultArmSequence(()).reset_system(interface, core_type, debug_base)
+    }
+
+    fn debug_device_unlock(
+        &self,
+        interface: &mut dyn ArmDebugInterface,
+        default_ap: &FullyQualifiedApAddress,
+        permissions: &crate::Permissions,
+    ) -> Result<(), ArmError> {
+        let host = SequenceHost::new(Access::Interface(&mut *interface), default_ap.dp())
+            .with_ap(default_ap);
+        if self.run("DebugDeviceUnlock", host)? {
+            return Ok(());
+        }
+
+        DefaultArmSequence(()).debug_device_unlock(interface, default_ap, permissions)
+    }
+
+    fn recover_support_start(
+        &self,
+        interface: &mut dyn ArmMemoryInterface,
+    ) -> Result<(), ArmError> {
+        let ap = interface.fully_qualified_address();
+        let host = SequenceHost::new(Access::Memory(&mut *interface), ap.dp()).with_ap(&ap);
+        if self.run("RecoverSupportStart", host)? {
+            return Ok(());
+        }
+
+        DefaultArmSequence(()).recover_support_start(interface)
+    }
+}
@@ -0,0 +1,873 @@
+//! An interpreter for the expression language of CMSIS-Pack debug descriptions.
+//!
+//! The language is a small subset of C. All values are 64-bit unsigned integers, variables are
+//! declared with `__var`, and the target is accessed through built-in functions like `Read32` or
+//! `WriteDP`, which are implemented by a [`Host`].
+//!
+//! ref: <https://open-cmsis-pack.github.io/Open-CMSIS-Pack-Spec/main/html/debug_description.html>
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use probe_rs_target::{DebugDescription, DebugSequenceItem};
+
+use crate::architecture::arm::{ArmError, sequences::ArmDebugSequenceError};
+
+/// Nested `Sequence()` calls deeper than this are assumed to be endless recursion.
+const MAX_CALL_DEPTH: usize = 16;
+
+fn error(message: impl Into<String>) -> ArmError {
+    ArmDebugSequenceError::custom(message.into()).into()
+}
+
+/// An argument of a built-in function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Argument {
+    Value(u64),
+    String(String),
+}
+
+impl Argument {
+    fn value(&self, function: &str) -> Result<u64, ArmError> {
+        match self {
+            Argument::Value(value) => Ok(*value),
+            Argument::String(_) => Err(error(format!(
+                "{function}: expected a number, but got a string"
+            ))),
+        }
+    }
+}
+
+/// Implements the built-in functions which access the target.
+pub(super) trait Host {
+    /// Calls the built-in function `name`. `variables` contains the predefined variables like
+    /// `__ap`, which select the accessed port.
+    fn call(
+        &mut self,
+        name: &str,
+        args: &[u64],
+        variables: &HashMap<String, u64>,
+    ) -> Result<u64, ArmError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(u64),
+    Identifier(String),
+    String(String),
+    Var,
+    Punct(&'static str),
+}
+
+/// Operators, longest first so that the tokenizer matches greedily.
+const PUNCTUATION: &[&str] = &[
+    "<<=", ">>=", "==", "!=", "<=", ">=", "&&", "||", "<<", ">>", "+=", "-=", "*=", "/=", "%=",
+    "&=", "|=", "^=", "+", "-", "*", "/", "%", "&", "|", "^", "~", "!", "<", ">", "=", "?", ":",
+    "(", ")", ",", ";",
+];
+
+fn tokenize(code: &str) -> Result<Vec<Token>, ArmError> {
+    let mut tokens = vec![];
+    let mut rest = code;
+
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return Ok(tokens);
+        }
+
+        if let Some(comment) = rest.strip_prefix("//") {
+            rest = comment.split_once('\n').map_or("", |(_, rest)| rest);
+            continue;
+        }
+        if let Some(comment) = rest.strip_prefix("/*") {
+            let Some((_, after)) = comment.split_once("*/") else {
+                return Err(error("Unterminated comment"));
+            };
+            rest = after;
+            continue;
+        }
+
+        let first = rest.chars().next().unwrap();
+        if first.is_ascii_digit() {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(rest.len());
+            let literal = &rest[..end];
+            // Integer suffixes like `UL` are allowed, and ignored.
+            let digits = literal.trim_end_matches(['u', 'U', 'l', 'L']);
+            let value = if let Some(hex) = digits
+                .strip_prefix("0x")
+                .or_else(|| digits.strip_prefix("0X"))
+            {
+                u64::from_str_radix(hex, 16)
+            } else if let Some(binary) = digits
+                .strip_prefix("0b")
+                .or_else(|| digits.strip_prefix("0B"))
+            {
+                u64::from_str_radix(binary, 2)
+            } else {
+                digits.parse()
+            }
+            .map_err(|_| error(format!("Invalid number '{literal}'")))?;
+
+            tokens.push(Token::Number(value));
+            rest = &rest[end..];
+        } else if first.is_ascii_alphabetic() || first == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let identifier = &rest[..end];
+            tokens.push(if identifier == "__var" {
+                Token::Var
+            } else {
+                Token::Identifier(identifier.to_string())
+            });
+            rest = &rest[end..];
+        } else if let Some(string) = rest.strip_prefix('"') {
+            let Some(end) = string.find('"') else {
+                return Err(error("Unterminated string"));
+            };
+            tokens.push(Token::String(string[..end].to_string()));
+            rest = &string[end + 1..];
+        } else if let Some(punct) = PUNCTUATION.iter().find(|punct| rest.starts_with(**punct)) {
+            tokens.push(Token::Punct(punct));
+            rest = &rest[punct.len()..];
+        } else {
+            return Err(error(format!("Unexpected character '{first}'")));
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Number(u64),
+    String(String),
+    Variable(String),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    /// An assignment, with the operator of a compound assignment like `+=`.
+    Assign(Option<&'static str>, String, Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Statement {
+    Declare(String, Option<Expr>),
+    Expr(Expr),
+}
+
+/// Binary operators by precedence, lowest first.
+const BINARY_OPERATORS: &[&[&str]] = &[
+    &["||"],
+    &["&&"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!="],
+    &["<", "<=", ">", ">="],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+const ASSIGNMENT_OPERATORS: &[&str] = &[
+    "=", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "<<=", ">>=",
+];
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn new(code: &str) -> Result<Self, ArmError> {
+        Ok(Self {
+            tokens: tokenize(code)?,
+            position: 0,
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        if self.peek() == Some(&Token::Punct(punct_str(punct))) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), ArmError> {
+        if self.eat(punct) {
+            Ok(())
+        } else {
+            Err(error(format!(
+                "Expected '{punct}', but found {:?}",
+                self.peek()
+            )))
+        }
+    }
+
+    fn statements(&mut self) -> Result<Vec<Statement>, ArmError> {
+        let mut statements = vec![];
+        while self.peek().is_some() {
+            if self.eat(";") {
+                continue;
+            }
+
+            let statement = if self.peek() == Some(&Token::Var) {
+                self.position += 1;
+                let Some(Token::Identifier(name)) = self.next() else {
+                    return Err(error("Expected a variable name after __var"));
+                };
+                let value = if self.eat("=") {
+                    Some(self.expression()?)
+                } else {
+                    None
+                };
+                Statement::Declare(name, value)
+            } else {
+                Statement::Expr(self.expression()?)
+            };
+            self.expect(";")?;
+
+            statements.push(statement);
+        }
+
+        Ok(statements)
+    }
+
+    fn expression(&mut self) -> Result<Expr, ArmError> {
+        let target = self.conditional()?;
+
+        let Some(Token::Punct(operator)) = self.peek() else {
+            return Ok(target);
+        };
+        if !ASSIGNMENT_OPERATORS.contains(operator) {
+            return Ok(target);
+        }
+        let operator = *operator;
+        self.position += 1;
+
+        let Expr::Variable(name) = target else {
+            return Err(error("Only variables can be assigned to"));
+        };
+        let value = self.expression()?;
+
+        let operator = operator.strip_suffix('=').filter(|op| !op.is_empty());
+        Ok(Expr::Assign(operator.map(punct_str), name, Box::new(value)))
+    }
+
+    fn conditional(&mut self) -> Result<Expr, ArmError> {
+        let condition = self.binary(0)?;
+        if !self.eat("?") {
+            return Ok(condition);
+        }
+
+        let if_true = self.expression()?;
+        self.expect(":")?;
+        let if_false = self.conditional()?;
+
+        Ok(Expr::Conditional(
+            Box::new(condition),
+            Box::new(if_true),
+            Box::new(if_false),
+        ))
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, ArmError> {
+        let Some(operators) = BINARY_OPERATORS.get(level) else {
+            return self.unary();
+        };
+
+        let mut lhs = self.binary(level + 1)?;
+        while let Some(Token::Punct(operator)) = self.peek() {
+            let Some(&operator) = operators.iter().find(|op| **op == *operator) else {
+                break;
+            };
+            self.position += 1;
+            let rhs = self.binary(level + 1)?;
+            lhs = Expr::Binary(operator, Box::new(lhs), Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ArmError> {
+        for operator in ["-", "!", "~", "+"] {
+            if self.eat(operator) {
+                let operand = self.unary()?;
+                return Ok(Expr::Unary(punct_str(operator), Box::new(operand)));
+            }
+        }
+
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, ArmError> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Expr::Number(value)),
+            Some(Token::String(string)) => Ok(Expr::String(string)),
+            Some(Token::Identifier(name)) => {
+                if !self.eat("(") {
+                    return Ok(Expr::Variable(name));
+                }
+
+                let mut args = vec![];
+                if !self.eat(")") {
+                    loop {
+                        args.push(self.conditional()?);
+                        if self.eat(")") {
+                            break;
+                        }
+                        self.expect(",")?;
+                    }
+                }
+                Ok(Expr::Call(name, args))
+            }
+            Some(Token::Punct("(")) => {
+                let expr = self.expression()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            token => Err(error(format!("Unexpected token {token:?}"))),
+        }
+    }
+}
+
+/// Maps an operator to the `'static` string in [`PUNCTUATION`].
+fn punct_str(punct: &str) -> &'static str {
+    PUNCTUATION
+        .iter()
+        .find(|candidate| **candidate == punct)
+        .expect("operator is part of PUNCTUATION")
+}
+
+fn parse(code: &str) -> Result<Vec<Statement>, ArmError> {
+    Parser::new(code)?.statements()
+}
+
+fn parse_expression(code: &str) -> Result<Expr, ArmError> {
+    let mut parser = Parser::new(code)?;
+    let expr = parser.expression()?;
+    if let Some(token) = parser.peek() {
+        return Err(error(format!("Unexpected token {token:?}")));
+    }
+
+    Ok(expr)
+}
+
+/// Runs the sequences of a [`DebugDescription`].
+pub(super) struct Interpreter<'a> {
+    description: &'a DebugDescription,
+    /// The predefined variables and the ones declared in `<debugvars>`.
+    globals: &'a mut HashMap<String, u64>,
+    host: &'a mut dyn Host,
+}
+
+/// The variables visible in a sequence.
+struct Scope {
+    locals: HashMap<String, u64>,
+}
+
+impl<'a> Interpreter<'a> {
+    pub(super) fn new(
+        description: &'a DebugDescription,
+        globals: &'a mut HashMap<String, u64>,
+        host: &'a mut dyn Host,
+    ) -> Self {
+        Self {
+            description,
+            globals,
+            host,
+        }
+    }
+
+    /// Declares the variables of the `<debugvars>` element.
+    pub(super) fn run_debug_vars(&mut self) -> Result<(), ArmError> {
+        let statements = parse(&self.description.debug_vars)?;
+
+        // Debug variables are visible to all sequences.
+        let mut scope = Scope {
+            locals: HashMap::new(),
+        };
+        self.run_statements(&statements, &mut scope, 0)?;
+        self.globals.extend(scope.locals);
+
+        Ok(())
+    }
+
+    /// Runs the sequence `name`. Returns `false` if the description does not define it.
+    pub(super) fn run_sequence(&mut self, name: &str) -> Result<bool, ArmError> {
+        self.run_sequence_at_depth(name, 0)
+    }
+
+    fn run_sequence_at_depth(&mut self, name: &str, depth: usize) -> Result<bool, ArmError> {
+        if depth > MAX_CALL_DEPTH {
+            return Err(error(format!(
+                "Sequence {name}: too many nested sequence calls"
+            )));
+        }
+
+        let Some(sequence) = self.description.sequence(name, None) else {
+            return Ok(false);
+        };
+        if sequence.disable {
+            tracing::debug!("Sequence {name} is disabled");
+            return Ok(true);
+        }
+
+        tracing::debug!("Running sequence {name}");
+        let mut scope = Scope {
+            locals: HashMap::new(),
+        };
+        self.run_items(&sequence.items, &mut scope, depth)
+            .map_err(|e| error(format!("Sequence {name}: {e}")))?;
+
+        Ok(true)
+    }
+
+    fn run_items(
+        &mut self,
+        items: &[DebugSequenceItem],
+        scope: &mut Scope,
+        depth: usize,
+    ) -> Result<(), ArmError> {
+        for item in items {
+            match item {
+                DebugSequenceItem::Block { code, .. } => {
+                    let statements = parse(code)?;
+                    self.run_statements(&statements, scope, depth)?;
+                }
+                DebugSequenceItem::Control {
+                    if_condition,
+                    while_condition,
+                    timeout,
+                    items,
+                } => {
+                    if let Some(condition) = if_condition {
+                        let condition = parse_expression(condition)?;
+                        if self.evaluate(&condition, scope, depth)? == 0 {
+                            continue;
+                        }
+                    }
+
+                    let Some(condition) = while_condition else {
+                        self.run_items(items, scope, depth)?;
+                        continue;
+                    };
+
+                    let parsed = parse_expression(condition)?;
+                    let start = Instant::now();
+                    while self.evaluate(&parsed, scope, depth)? != 0 {
+                        self.run_items(items, scope, depth)?;
+
+                        if *timeout != 0 && start.elapsed() > Duration::from_micros(*timeout) {
+                            tracing::warn!("Loop '{condition:?}' timed out after {timeout} µs");
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn run_statements(
+        &mut self,
+        statements: &[Statement],
+        scope: &mut Scope,
+        depth: usize,
+    ) -> Result<(), ArmError> {
+        for statement in statements {
+            match statement {
+                Statement::Declare(name, value) => {
+                    let value = match value {
+                        Some(value) => self.evaluate(value, scope, depth)?,
+                        None => 0,
+                    };
+                    scope.locals.insert(name.clone(), value);
+                }
+                Statement::Expr(expr) => {
+                    self.evaluate(expr, scope, depth)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn variable<'s>(
+        &'s mut self,
+        name: &str,
+        scope: &'s mut Scope,
+    ) -> Result<&'s mut u64, ArmError> {
+        if let Some(value) = scope.locals.get_mut(name) {
+            return Ok(value);
+        }
+
+        self.globals
+            .get_mut(name)
+            .ok_or_else(|| error(format!("Undeclared variable {name}")))
+    }
+
+    fn evaluate(&mut self, expr: &Expr, scope: &mut Scope, depth: usize) -> Result<u64, ArmError> {
+        let value = match expr {
+            Expr::Number(value) => *value,
+            Expr::String(string) => {
+                return Err(error(format!(
+                    "The string \"{string}\" can only be used as a function argument"
+                )));
+            }
+            Expr::Variable(name) => *self.variable(name, scope)?,
+            Expr::Unary(operator, operand) => {
+                let operand = self.evaluate(operand, scope, depth)?;
+                match *operator {
+                    "-" => operand.wrapping_neg(),
+                    "!" => (operand == 0) as u64,
+                    "~" => !operand,
+                    _ => operand,
+                }
+            }
+            Expr::Binary("&&", lhs, rhs) => {
+                (self.evaluate(lhs, scope, depth)? != 0 && self.evaluate(rhs, scope, depth)? != 0)
+                    as u64
+            }
+            Expr::Binary("||", lhs, rhs) => {
+                (self.evaluate(lhs, scope, depth)? != 0 || self.evaluate(rhs, scope, depth)? != 0)
+                    as u64
+            }
+            Expr::Binary(operator, lhs, rhs) => {
+                let lhs = self.evaluate(lhs, scope, depth)?;
+                let rhs = self.evaluate(rhs, scope, depth)?;
+                binary(operator, lhs, rhs)?
+            }
+            Expr::Assign(operator, name, value) => {
+                let value = self.evaluate(value, scope, depth)?;
+                let variable = self.variable(name, scope)?;
+                *variable = match operator {
+                    Some(operator) => binary(operator, *variable, value)?,
+                    None => value,
+                };
+                *variable
+            }
+            Expr::Conditional(condition, if_true, if_false) => {
+                if self.evaluate(condition, scope, depth)? != 0 {
+                    self.evaluate(if_true, scope, depth)?
+                } else {
+                    self.evaluate(if_false, scope, depth)?
+                }
+            }
+            Expr::Call(name, args) => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(match arg {
+                        Expr::String(string) => Argument::String(string.clone()),
+                        arg => Argument::Value(self.evaluate(arg, scope, depth)?),
+                    });
+                }
+                self.call(name, &values, depth)?
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn call(&mut self, name: &str, args: &[Argument], depth: usize) -> Result<u64, ArmError> {
+        match (name, args) {
+            ("Sequence", [Argument::String(sequence)]) => {
+                if !self.run_sequence_at_depth(sequence, depth + 1)? {
+                    tracing::debug!("Sequence {sequence} is not defined, skipping it");
+                }
+                Ok(0)
+            }
+            ("Message", [Argument::Value(kind), Argument::String(format), values @ ..]) => {
+                let message = format_message(format, values);
+                match kind {
+                    0 => tracing::info!("{message}"),
+                    1 => tracing::warn!("{message}"),
+                    _ => return Err(error(message)),
+                }
+                Ok(0)
+            }
+            // There is no user to ask, so queries are answered with their default.
+            ("Query", [_, Argument::String(message), default]) => {
+                tracing::info!("{message}");
+                default.value(name)
+            }
+            ("QueryValue", [Argument::String(message), default]) => {
+                tracing::info!("{message}");
+                default.value(name)
+            }
+            ("LoadDebugInfo", [Argument::String(_)]) => Ok(0),
+            _ => {
+                let values = args
+                    .iter()
+                    .map(|arg| arg.value(name))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let result = self.host.call(name, &values, self.globals);
+
+                match result {
+                    Err(e) if self.globals.get("__errorcontrol").copied().unwrap_or(0) & 1 != 0 => {
+                        tracing::debug!("Ignoring error in {name}: {e}");
+                        Ok(0)
+                    }
+                    result => result,
+                }
+            }
+        }
+    }
+}
+
+fn binary(operator: &str, lhs: u64, rhs: u64) -> Result<u64, ArmError> {
+    let value = match operator {
+        "|" => lhs | rhs,
+        "^" => lhs ^ rhs,
+        "&" => lhs & rhs,
+        "==" => (lhs == rhs) as u64,
+        "!=" => (lhs != rhs) as u64,
+        "<" => (lhs < rhs) as u64,
+        "<=" => (lhs <= rhs) as u64,
+        ">" => (lhs > rhs) as u64,
+        ">=" => (lhs >= rhs) as u64,
+        "<<" => lhs.checked_shl(rhs as u32).unwrap_or(0),
+        ">>" => lhs.checked_shr(rhs as u32).unwrap_or(0),
+        "+" => lhs.wrapping_add(rhs),
+        "-" => lhs.wrapping_sub(rhs),
+        "*" => lhs.wrapping_mul(rhs),
+        "/" => lhs
+            .checked_div(rhs)
+            .ok_or_else(|| error("Division by zero"))?,
+        "%" => lhs
+            .checked_rem(rhs)
+            .ok_or_else(|| error("Division by zero"))?,
+        operator => return Err(error(format!("Unknown operator {operator}"))),
+    };
+
+    Ok(value)
+}
+
+/// Formats the arguments of `Message` into its printf-style format string.
+fn format_message(format: &str, values: &[Argument]) -> String {
+    let mut values = values.iter();
+    let mut message = String::new();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' || chars.next_if_eq(&'%').is_some() {
+            message.push(c);
+            continue;
+        }
+
+        // Skip flags and width, e.g. `%08x`.
+        let mut spec = String::new();
+        while let Some(digit) = chars.next_if(|c| c.is_ascii_digit() || *c == 'l') {
+            spec.push(digit);
+        }
+        let value = match values.next() {
+            Some(Argument::Value(value)) => *value,
+            Some(Argument::String(string)) => {
+                message.push_str(string);
+                chars.next();
+                continue;
+            }
+            None => 0,
+        };
+        match chars.next() {
+            Some('x') => message.push_str(&format!("{value:x}")),
+            Some('X') => message.push_str(&format!("{value:X}")),
+            _ => message.push_str(&value.to_string()),
+        }
+    }
+
+    message
+}
+
+#[cfg(test)]
+mod test {
+    use probe_rs_target::{DebugDescription, DebugSequenceDescription, DebugSequenceItem};
+
+    use super::*;
+
+    /// Records the calls, and returns memory reads from a map.
+    #[derive(Default)]
+    struct MockHost {
+        memory: HashMap<u64, u64>,
+        calls: Vec<(String, Vec<u64>)>,
+    }
+
+    impl Host for MockHost {
+        fn call(
+            &mut self,
+            name: &str,
+            args: &[u64],
+            _variables: &HashMap<String, u64>,
+        ) -> Result<u64, ArmError> {
+            self.calls.push((name.to_string(), args.to_vec()));
+            match (name, args) {
+                ("Read32", [address]) => Ok(self.memory.get(address).copied().unwrap_or(0)),
+                ("Write32", [address, value]) => {
+                    self.memory.insert(*address, *value);
+                    Ok(0)
+                }
+                _ => Err(error(format!("{name} failed"))),
+            }
+        }
+    }
+
+    fn block(code: &str) -> DebugSequenceItem {
+        DebugSequenceItem::Block {
+            atomic: false,
+            code: code.to_string(),
+        }
+    }
+
+    fn description(sequences: Vec<(&str, Vec<DebugSequenceItem>)>) -> DebugDescription {
+        DebugDescription {
+            debug_vars: "__var Unlock = 0xA05F0000;".to_string(),
+            sequences: sequences
+                .into_iter()
+                .map(|(name, items)| DebugSequenceDescription {
+                    name: name.to_string(),
+                    pname: None,
+                    disable: false,
+                    items,
+                })
+                .collect(),
+        }
+    }
+
+    fn run(
+        description: &DebugDescription,
+        host: &mut MockHost,
+        name: &str,
+    ) -> HashMap<String, u64> {
+        let mut globals = HashMap::from([("__errorcontrol".to_string(), 0)]);
+        let mut interpreter = Interpreter::new(description, &mut globals, host);
+        interpreter.run_debug_vars().unwrap();
+        assert!(interpreter.run_sequence(name).unwrap());
+        globals
+    }
+
+    #[test]
+    fn operator_precedence() {
+        let description = description(vec![(
+            "ResetSystem",
+            vec![block(
+                "__var x = 1 + 2 * 3 << 1; // 14\n\
+                 __var y = x == 14 && !(x & 1) ? 0x10 : 0x20;\n\
+                 Write32(0x100, x | y);",
+            )],
+        )]);
+        let mut host = MockHost::default();
+        run(&description, &mut host, "ResetSystem");
+
+        assert_eq!(host.memory[&0x100], 14 | 0x10);
+    }
+
+    #[test]
+    fn compound_assignment_and_globals() {
+        let description = description(vec![(
+            "ResetCatchSet",
+            vec![block(
+                "__var value = Read32(0xE000EDFC); value |= 1; Write32(0xE000EDF0, Unlock | 3); \
+                 Write32(0xE000EDFC, value);",
+            )],
+        )]);
+        let mut host = MockHost::default();
+        host.memory.insert(0xE000_EDFC, 0x100);
+        run(&description, &mut host, "ResetCatchSet");
+
+        assert_eq!(host.memory[&0xE000_EDF0], 0xA05F_0003);
+        assert_eq!(host.memory[&0xE000_EDFC], 0x101);
+    }
+
+    #[test]
+    fn control_while_and_nested_sequences() {
+        let description = description(vec![
+            (
+                "ResetSystem",
+                vec![
+                    block("__var count = 0;"),
+                    DebugSequenceItem::Control {
+                        if_condition: Some("Read32(0x200) == 0".to_string()),
+                        while_condition: Some("count < 3".to_string()),
+                        timeout: 0,
+                        items: vec![block("count += 1; Sequence(\"Increment\");")],
+                    },
+                ],
+            ),
+            (
+                "Increment",
+                vec![block("Write32(0x300, Read32(0x300) + 1);")],
+            ),
+        ]);
+        let mut host = MockHost::default();
+        run(&description, &mut host, "ResetSystem");
+
+        assert_eq!(host.memory[&0x300], 3);
+    }
+
+    #[test]
+    fn error_control_ignores_failed_accesses() {
+        let description = description(vec![(
+            "DebugPortStart",
+            vec![block(
+                "__errorcontrol = 1; WriteDP(0x8, 0); __errorcontrol = 0; Write32(0x10, 1);",
+            )],
+        )]);
+        let mut host = MockHost::default();
+        let globals = run(&description, &mut host, "DebugPortStart");
+
+        assert_eq!(host.memory[&0x10], 1);
+        assert_eq!(globals["__errorcontrol"], 0);
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        let description = description(vec![("ResetSystem", vec![block("WriteDP(0x8, 0);")])]);
+        let mut host = MockHost::default();
+        let mut globals = HashMap::from([("__errorcontrol".to_string(), 0)]);
+
+        let mut interpreter = Interpreter::new(&description, &mut globals, &mut host);
+        assert!(interpreter.run_sequence("ResetSystem").is_err());
+        assert!(!interpreter.run_sequence("ResetHardware").unwrap());
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert!(parse("__var x = ;").is_err());
+        assert!(parse("x = (1 + 2;").is_err());
+        assert!(parse("1 = 2;").is_err());
+        assert!(parse("/* unterminated").is_err());
+    }
+
+    #[test]
+    fn message_formatting() {
+        assert_eq!(
+            format_message(
+                "DP %d: IDR 0x%08X, %s",
+                &[
+                    Argument::Value(1),
+                    Argument::Value(0x2BA0_1477),
+                    Argument::String("ok".to_string())
+                ]
+            ),
+            "DP 1: IDR 0x2BA01477, ok"
+        );
+    }
+}
@@ -11,11 +11,16 @@ use crate::{
     architecture::arm::{
         ArmError, DapError, FullyQualifiedApAddress, RawDapAccess, RegisterAddress,
         ap::AccessPortError,
-        dp::{Abort, Ctrl, DPIDR, DebugPortError, DpRegister, RdBuff},
+        dp::{
+            Abort, Ctrl, DPIDR, DebugPortError, DpRegister, FaultRecovery, FaultRecoveryAccess,
+            RdBuff, StickyErrors,
+        },
     },
     probe::{
         CommandQueue, CommandResult, DebugProbe, DebugProbeError, IoSequenceItem, JtagAccess,
-        JtagSequence, JtagWriteCommand, RawSwdIo, WireProtocol, common::bits_to_byte,
+        JtagSequence, JtagWriteCommand, RawSwdIo, WireProtocol,
+        common::bits_to_byte,
+        swd_trace::{self, SwdTraceRecorder},
     },
 };
 
@@ -270,6 +275,9 @@ fn perform_swd_transfers<P: RawSwdIo>(
         let response = parse_swd_response(&result_bits[response_offset..], transfer.direction);
 
         probe.probe_statistics().report_swd_response(&response);
+        if let Some(trace) = probe.probe_statistics().swd_trace() {
+            trace_swd_transfer(trace, transfer, &result_bits[response_offset..]);
+        }
 
         transfer.status = match response {
             Ok(response) => {
@@ -290,9 +298,33 @@ fn perform_swd_transfers<P: RawSwdIo>(
         result_bits = &result_bits[transfer.swd_response_length()..];
     }
 
+    if let Some(trace) = probe.probe_statistics().swd_trace() {
+        trace.flush();
+    }
+
     Ok(())
 }
 
+/// Logs a SWD transfer, with the response bits starting at the acknowledge.
+fn trace_swd_transfer(trace: &mut SwdTraceRecorder, transfer: &DapTransfer, response: &[bool]) {
+    let ack = bits_to_byte(response[..3].iter().copied()) as u8;
+
+    // There is no data phase if the target does not acknowledge the request.
+    let (data, parity) = match transfer.direction {
+        _ if ack != 0b001 => (None, None),
+        TransferDirection::Read => (
+            Some(bits_to_byte(response[3..35].iter().copied())),
+            Some(response[35]),
+        ),
+        TransferDirection::Write => (
+            Some(transfer.value),
+            Some(swd_trace::data_parity(transfer.value)),
+        ),
+    };
+
+    trace.transfer(transfer.swd_request(), ack, data, parity);
+}
+
 /// Perform a batch of transfers.
 ///
 /// Certain transfers require additional transfers to
@@ -456,9 +488,11 @@ fn perform_raw_transfers_retry<P: DebugProbe + RawSwdIo + JtagAccess>(
                     tracing::debug!("got WAIT on transfer {}, retrying...", successful_transfers);
 
                     // Surface this error, because it indicates there's a low-level protocol problem going on.
-                    clear_overrun_and_sticky_err(probe).inspect_err(|e| {
-                        tracing::error!("error clearing sticky overrun/error bits: {e}");
-                    })?;
+                    FaultRecovery::new()
+                        .clear(&mut RawFaultRecovery(probe), OVERRUN_AND_STICKY_ERROR)
+                        .inspect_err(|e| {
+                            tracing::error!("error clearing sticky overrun/error bits: {e}");
+                        })?;
 
                     // Increase idle cycles of the failed write transfer and the rest of the chunk
                     for transfer in &mut chunk[..] {
@@ -505,17 +539,34 @@ fn perform_raw_transfers_retry<P: DebugProbe + RawSwdIo + JtagAccess>(
     Ok(())
 }
 
-fn clear_overrun_and_sticky_err<P: DebugProbe + RawSwdIo + JtagAccess>(
-    probe: &mut P,
-) -> Result<(), ArmError> {
-    tracing::debug!("Clearing overrun and sticky error");
-    // Build ABORT transfer.
-    write_dp_register(probe, {
-        let mut abort = Abort(0);
-        abort.set_orunerrclr(true);
-        abort.set_stkerrclr(true);
-        abort
-    })
+/// The errors cleared after a WAIT response, or a FAULT response to a read of CTRL/STAT.
+const OVERRUN_AND_STICKY_ERROR: StickyErrors = StickyErrors {
+    write_data_error: false,
+    sticky_error: true,
+    overrun: true,
+};
+
+/// Provides [`FaultRecovery`] with direct access to the DP registers, bypassing the
+/// fault handling of [`RawDapAccess`].
+struct RawFaultRecovery<'a, P>(&'a mut P);
+
+impl<P: DebugProbe + RawSwdIo + JtagAccess> FaultRecoveryAccess for RawFaultRecovery<'_, P> {
+    fn read_ctrl_stat(&mut self) -> Result<Ctrl, ArmError> {
+        let mut transfer = DapTransfer::read(CTRL_PORT);
+        perform_transfers(self.0, std::slice::from_mut(&mut transfer))?;
+
+        match transfer.status {
+            TransferStatus::Ok => Ok(Ctrl::try_from(transfer.value)?),
+            TransferStatus::Failed(e) => Err(e.into()),
+            other => panic!(
+                "Unexpected transfer state after reading register: {other:?}. This is a bug!"
+            ),
+        }
+    }
+
+    fn write_abort(&mut self, abort: Abort) -> Result<(), ArmError> {
+        write_dp_register(self.0, abort)
+    }
 }
 
 fn write_dp_register<P: DebugProbe + RawSwdIo + JtagAccess, R: DpRegister>(
@@ -581,6 +632,16 @@ impl DapTransfer {
         }
     }
 
+    /// The SWD request header, with the start bit in bit 0.
+    fn swd_request(&self) -> u8 {
+        swd_trace::swd_request(
+            self.address.is_ap(),
+            self.direction == TransferDirection::Read,
+            self.address.a2(),
+            self.address.a3(),
+        )
+    }
+
     fn transfer_type(&self) -> TransferType {
         match self.direction {
             TransferDirection::Read => TransferType::Read,
@@ -978,29 +1039,15 @@ impl<Probe: DebugProbe + RawSwdIo + JtagAccess + 'static> RawDapAccess for Probe
                     // We still clear the sticky error, otherwise all future accesses will fail.
                     //
                     // We also assume that we use overrun detection, so we clear the overrun error as well.
-                    clear_overrun_and_sticky_err(self)?;
+                    FaultRecovery::new()
+                        .clear(&mut RawFaultRecovery(self), OVERRUN_AND_STICKY_ERROR)?;
                 } else {
                     // Reading the CTRL/AP register depends on the dpbanksel register, but we don't know
                     // here what the value of it is. So this will fail if dpbanksel is not set to 0,
                     // but there is no way of figuring that out here, because reading the SELECT register
                     // would also fail.
-                    //
-                    // What might happen is that the read fails, but that would then trigger another fault handling,
-                    // so it all ends up working.
                     tracing::debug!("Reading CTRL/AP register to determine reason for FAULT");
-                    let response = RawDapAccess::raw_read_register(self, CTRL_PORT)?;
-                    let ctrl = Ctrl::try_from(response)?;
-                    tracing::debug!(
-                        "Reading DAP register failed. Ctrl/Stat register value is: {:#?}",
-                        ctrl
-                    );
-
-                    // Check the reason for the fault
-                    // Other fault reasons than overrun or write error are not handled yet.
-                    if ctrl.sticky_orun() || ctrl.sticky_err() {
-                        // Clear the error state
-                        clear_overrun_and_sticky_err(self)?;
-                    }
+                    FaultRecovery::new().recover(&mut RawFaultRecovery(self))?;
                 }
 
                 Err(DapError::FaultResponse.into())
@@ -1034,10 +1081,8 @@ impl<Probe: DebugProbe + RawSwdIo + JtagAccess + 'static> RawDapAccess for Probe
                         err
                     );
 
-                    // TODO: The error reason could be investigated by reading the CTRL/STAT register here,
-
                     if err == DapError::FaultResponse {
-                        clear_overrun_and_sticky_err(self)?;
+                        FaultRecovery::new().recover(&mut RawFaultRecovery(self))?;
                     }
 
                     return Err(err.into());
@@ -1063,25 +1108,11 @@ impl<Probe: DebugProbe + RawSwdIo + JtagAccess + 'static> RawDapAccess for Probe
                 // A fault happened during operation.
 
                 // To get a clue about the actual fault we read the ctrl register,
-                // which will have the fault status flags set.
+                // which will have the fault status flags set, and clear the flags.
 
                 // This read might fail because the dpbanksel register is not set to 0.
-                let response = RawDapAccess::raw_read_register(self, CTRL_PORT)?;
-
-                let ctrl = Ctrl::try_from(response)?;
-                tracing::warn!(
-                    "Writing DAP register failed. Ctrl/Stat register value is: {:#?}",
-                    ctrl
-                );
-
-                // Check the reason for the fault
-                // Other fault reasons than overrun or write error are not handled yet.
-                if ctrl.sticky_orun() || ctrl.sticky_err() {
-                    // We did not handle a WAIT state properly
-
-                    // Because we use overrun detection, we now have to clear the overrun error
-                    clear_overrun_and_sticky_err(self)?;
-                }
+                let errors = FaultRecovery::new().recover(&mut RawFaultRecovery(self))?;
+                tracing::warn!("Writing DAP register failed: {:?}", errors);
 
                 Err(DapError::FaultResponse.into())
             }
@@ -1117,9 +1148,8 @@ impl<Probe: DebugProbe + RawSwdIo + JtagAccess + 'static> RawDapAccess for Probe
                         err
                     );
 
-                    // TODO: The error reason could be investigated by reading the CTRL/STAT register here,
                     if err == DapError::FaultResponse {
-                        clear_overrun_and_sticky_err(self)?;
+                        FaultRecovery::new().recover(&mut RawFaultRecovery(self))?;
                     }
 
                     return Err(err.into());
@@ -1609,6 +1639,16 @@ mod test {
         assert_eq!(result, read_value);
     }
 
+    #[test]
+    fn swd_request() {
+        use super::DapTransfer;
+        use crate::architecture::arm::dp::DPIDR;
+
+        assert_eq!(DapTransfer::read(DPIDR::ADDRESS).swd_request(), 0xA5);
+        assert_eq!(DapTransfer::write(Ctrl::ADDRESS, 0).swd_request(), 0xA9);
+        assert_eq!(DapTransfer::read(ApAddress::V1(0xC)).swd_request(), 0x9F);
+    }
+
     #[test]
     fn read_register_jtag() {
         let read_value = 12;
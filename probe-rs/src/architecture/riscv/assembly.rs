@@ -69,6 +69,60 @@ pub fn csrrw(rd: u8, rs1: u8, csr: u16) -> u32 {
     i_type_instruction(opcode, rs1, funct3, rd, csr)
 }
 
+/// Assemble a `vsetvli` instruction, which sets `vl` and `vtype` from `rs1` and an immediate.
+pub fn vsetvli(rd: u8, rs1: u8, vtypei: u16) -> u32 {
+    assert!(vtypei <= 0x7ff);
+
+    let funct3 = 0b111;
+    i_type_instruction(OP_V, rs1, funct3, rd, vtypei)
+}
+
+/// Assemble a `vsetvl` instruction, which sets `vl` from `rs1` and `vtype` from `rs2`.
+pub fn vsetvl(rd: u8, rs1: u8, rs2: u8) -> u32 {
+    let funct3 = 0b111;
+    vector_instruction(0b100_0000, rs2, rs1, funct3, rd)
+}
+
+/// Assemble a `vmv.x.s` instruction, which moves element 0 of the vector register `vs2` into `rd`.
+pub fn vmv_x_s(rd: u8, vs2: u8) -> u32 {
+    // funct6 = 0b010000, unmasked
+    let funct7 = 0b010_0001;
+    // OPMVV
+    let funct3 = 0b010;
+    vector_instruction(funct7, vs2, 0, funct3, rd)
+}
+
+/// Assemble a `vslide1down.vx` instruction, which shifts the elements of `vs2` down by one, and
+/// inserts `rs1` as the last element.
+pub fn vslide1down_vx(vd: u8, vs2: u8, rs1: u8) -> u32 {
+    // funct6 = 0b001111, unmasked
+    let funct7 = 0b001_1111;
+    // OPMVX
+    let funct3 = 0b110;
+    vector_instruction(funct7, vs2, rs1, funct3, vd)
+}
+
+/// The major opcode of the vector extension.
+const OP_V: u8 = 0b101_0111;
+
+/// Assemble an instruction of the vector extension, using the R-type layout.
+///
+/// `funct7` contains `funct6` and the `vm` bit.
+fn vector_instruction(funct7: u8, rs2: u8, rs1: u8, funct3: u8, rd: u8) -> u32 {
+    assert!(funct7 <= 0x7f); // [31:25]
+    assert!(rs2 <= 0x1f); // [24:20]
+    assert!(rs1 <= 0x1f); // [19:15]
+    assert!(funct3 <= 0x7); // [14:12]
+    assert!(rd <= 0x1f); // [11:07]
+
+    ((funct7 as u32) << 25)
+        | ((rs2 as u32) << 20)
+        | ((rs1 as u32) << 15)
+        | ((funct3 as u32) << 12)
+        | ((rd as u32) << 7)
+        | OP_V as u32
+}
+
 /// Assemble an I-type instruction, as specified in the RISC-V ISA
 ///
 /// This function panics if any of the values would have to be truncated.
@@ -88,7 +142,7 @@ fn i_type_instruction(opcode: u8, rs1: u8, funct3: u8, rd: u8, imm: u16) -> u32
 
 #[cfg(test)]
 mod test {
-    use super::{csrr, csrw, lw, sw};
+    use super::{csrr, csrw, lw, sw, vmv_x_s, vsetvl, vsetvli, vslide1down_vx};
 
     #[test]
     fn assemble_csrr() {
@@ -137,4 +191,44 @@ mod test {
 
         assert_eq!(assembled, expected);
     }
+
+    #[test]
+    fn assemble_vsetvli() {
+        // Assembly output of assembly 'vsetvli t0, a0, e32, m1, tu, mu'
+        let expected = 0x010572d7;
+
+        let assembled = vsetvli(5, 10, 0b010_000);
+
+        assert_eq!(assembled, expected);
+    }
+
+    #[test]
+    fn assemble_vsetvl() {
+        // Assembly output of assembly 'vsetvl x0, s0, s1'
+        let expected = 0x80947057;
+
+        let assembled = vsetvl(0, 8, 9);
+
+        assert_eq!(assembled, expected);
+    }
+
+    #[test]
+    fn assemble_vmv_x_s() {
+        // Assembly output of assembly 'vmv.x.s a0, v1'
+        let expected = 0x42102557;
+
+        let assembled = vmv_x_s(10, 1);
+
+        assert_eq!(assembled, expected);
+    }
+
+    #[test]
+    fn assemble_vslide1down_vx() {
+        // Assembly output of assembly 'vslide1down.vx v1, v1, a0'
+        let expected = 0x3e1560d7;
+
+        let assembled = vslide1down_vx(1, 1, 10);
+
+        assert_eq!(assembled, expected);
+    }
 }
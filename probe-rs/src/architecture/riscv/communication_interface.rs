@@ -266,6 +266,9 @@ pub struct RiscvCommunicationInterfaceState {
 
     /// The current value of the `dmcontrol` register.
     current_dmcontrol: Dmcontrol,
+
+    /// Whether the debug module supports selecting multiple harts using `hasel`.
+    hart_array_supported: Option<bool>,
 }
 
 /// Timeout for RISC-V operations.
@@ -316,6 +319,8 @@ impl RiscvCommunicationInterfaceState {
             is_halted: false,
 
             current_dmcontrol: Dmcontrol(0),
+
+            hart_array_supported: None,
         }
     }
 
@@ -818,7 +823,7 @@ impl<'state> RiscvCommunicationInterface<'state> {
         result
     }
 
-    pub(super) fn read_csr(&mut self, address: u16) -> Result<u32, RiscvError> {
+    pub(crate) fn read_csr(&mut self, address: u16) -> Result<u32, RiscvError> {
         // We need to use the "Access Register Command",
         // which has cmdtype 0
 
@@ -1339,7 +1344,8 @@ impl<'state> RiscvCommunicationInterface<'state> {
         })
     }
 
-    pub(crate) fn execute_abstract_command(&mut self, command: u32) -> Result<(), RiscvError> {
+    /// Schedules a write of `dmcontrol` that fulfills the preconditions of abstract commands.
+    fn schedule_abstract_command_preconditions(&mut self) -> Result<(), RiscvError> {
         // ensure that preconditions are fullfileld
         // haltreq      = 0
         // resumereq    = 0
@@ -1350,7 +1356,28 @@ impl<'state> RiscvCommunicationInterface<'state> {
         dmcontrol.set_haltreq(false);
         dmcontrol.set_resumereq(false);
         dmcontrol.set_ackhavereset(false);
-        self.schedule_write_dm_register(dmcontrol)?;
+        self.schedule_write_dm_register(dmcontrol)
+    }
+
+    /// Polls `abstractcs` until the current abstract command is no longer busy.
+    fn wait_for_abstract_command(&mut self) -> Result<Abstractcs, RiscvError> {
+        let start_time = Instant::now();
+
+        loop {
+            let abstractcs = self.read_dm_register::<Abstractcs>()?;
+
+            if !abstractcs.busy() {
+                return Ok(abstractcs);
+            }
+
+            if start_time.elapsed() > RISCV_TIMEOUT {
+                return Err(RiscvError::Timeout);
+            }
+        }
+    }
+
+    pub(crate) fn execute_abstract_command(&mut self, command: u32) -> Result<(), RiscvError> {
+        self.schedule_abstract_command_preconditions()?;
 
         fn do_execute_abstract_command(
             core: &mut RiscvCommunicationInterface,
@@ -1363,21 +1390,8 @@ impl<'state> RiscvCommunicationInterface<'state> {
             core.schedule_write_dm_register(abstractcs_clear)?;
             core.schedule_write_dm_register(command)?;
 
-            let start_time = Instant::now();
-
             // Poll busy flag in abstractcs.
-            let mut abstractcs;
-            loop {
-                abstractcs = core.read_dm_register::<Abstractcs>()?;
-
-                if !abstractcs.busy() {
-                    break;
-                }
-
-                if start_time.elapsed() > RISCV_TIMEOUT {
-                    return Err(RiscvError::Timeout);
-                }
-            }
+            let abstractcs = core.wait_for_abstract_command()?;
 
             tracing::debug!("abstracts: {:?}", abstractcs);
 
@@ -1511,6 +1525,124 @@ impl<'state> RiscvCommunicationInterface<'state> {
         }
     }
 
+    /// Schedules an abstract command without waiting for it to complete, and returns the index
+    /// of the `abstractcs` value read right after it.
+    fn schedule_abstract_command(
+        &mut self,
+        command: AccessRegisterCommand,
+    ) -> Result<DeferredResultIndex, RiscvError> {
+        let mut abstractcs_clear = Abstractcs(0);
+        abstractcs_clear.set_cmderr(0x7);
+
+        self.schedule_write_dm_register(abstractcs_clear)?;
+        self.schedule_write_dm_register(command)?;
+        self.schedule_read_dm_register::<Abstractcs>()
+    }
+
+    /// Checks the `abstractcs` value read after a scheduled abstract command. If the command did
+    /// not complete successfully, waits until the debug module is idle again.
+    fn scheduled_abstract_command_succeeded(
+        &mut self,
+        abstractcs: DeferredResultIndex,
+    ) -> Result<bool, RiscvError> {
+        let abstractcs = Abstractcs(self.dtm.read_deferred_result(abstractcs)?.into_u32());
+        if abstractcs.busy() || AbstractCommandErrorKind::parse(abstractcs).is_err() {
+            tracing::debug!("Batched abstract command failed: {:?}", abstractcs);
+            self.wait_for_abstract_command()?;
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    /// Reads multiple core registers, using abstract commands that are transferred in one batch.
+    ///
+    /// The commands are not polled for completion. If one of them was still busy or failed, that
+    /// register and all following ones are read one by one with [`Self::read_csr`] instead.
+    pub(crate) fn read_csrs(&mut self, addresses: &[u16]) -> Result<Vec<u32>, RiscvError> {
+        let batched = addresses
+            .iter()
+            .take_while(|&&address| {
+                self.check_abstract_cmd_register_support(
+                    RegisterId(address),
+                    CoreRegisterAbstractCmdSupport::READ,
+                )
+            })
+            .count();
+
+        let mut scheduled = Vec::with_capacity(batched);
+        if batched > 0 {
+            self.schedule_abstract_command_preconditions()?;
+        }
+        for &address in &addresses[..batched] {
+            let mut command = AccessRegisterCommand(0);
+            command.set_cmd_type(0);
+            command.set_transfer(true);
+            command.set_aarsize(RiscvBusAccess::A32);
+            command.set_regno(address as u32);
+
+            let abstractcs = self.schedule_abstract_command(command)?;
+            let value = self.schedule_read_dm_register::<Data0>()?;
+            scheduled.push((abstractcs, value));
+        }
+
+        let mut values = Vec::with_capacity(addresses.len());
+        for (abstractcs, value) in scheduled {
+            if !self.scheduled_abstract_command_succeeded(abstractcs)? {
+                break;
+            }
+            values.push(self.dtm.read_deferred_result(value)?.into_u32());
+        }
+
+        for &address in &addresses[values.len()..] {
+            values.push(self.read_csr(address)?);
+        }
+
+        Ok(values)
+    }
+
+    /// Writes multiple core registers, using abstract commands that are transferred in one batch.
+    ///
+    /// Returns the number of registers that were written. If one of the commands was still busy
+    /// or failed, that register and all following ones have to be written again one by one.
+    pub(crate) fn write_csrs(&mut self, registers: &[(u16, u32)]) -> Result<usize, RiscvError> {
+        let batched = registers
+            .iter()
+            .take_while(|&&(address, _)| {
+                self.check_abstract_cmd_register_support(
+                    RegisterId(address),
+                    CoreRegisterAbstractCmdSupport::WRITE,
+                )
+            })
+            .count();
+
+        let mut scheduled = Vec::with_capacity(batched);
+        if batched > 0 {
+            self.schedule_abstract_command_preconditions()?;
+        }
+        for &(address, value) in &registers[..batched] {
+            let mut command = AccessRegisterCommand(0);
+            command.set_cmd_type(0);
+            command.set_transfer(true);
+            command.set_write(true);
+            command.set_aarsize(RiscvBusAccess::A32);
+            command.set_regno(address as u32);
+
+            self.schedule_write_large_dtm_register::<u32, Arg0>(value)?;
+            scheduled.push(self.schedule_abstract_command(command)?);
+        }
+
+        let mut written = 0;
+        for abstractcs in scheduled {
+            if !self.scheduled_abstract_command_succeeded(abstractcs)? {
+                break;
+            }
+            written += 1;
+        }
+
+        Ok(written)
+    }
+
     /// Read the CSR `progbuf` register.
     pub fn read_csr_progbuf(&mut self, address: u16) -> Result<u32, RiscvError> {
         self.halted_access(|core| {
@@ -1578,6 +1710,127 @@ impl<'state> RiscvCommunicationInterface<'state> {
         })
     }
 
+    /// Reads the vector register `vN` as 32-bit elements, element 0 first.
+    ///
+    /// Vector registers can't be accessed with abstract commands. Instead, element 0 is moved
+    /// into `s0` using `vmv.x.s`, and the register is rotated by one element using
+    /// `vslide1down.vx`, which inserts the element again at the top. Once all elements are read,
+    /// the register contains its original value again.
+    pub(crate) fn read_vector_register(
+        &mut self,
+        index: u8,
+        vlenb: u32,
+    ) -> Result<Vec<u32>, RiscvError> {
+        self.vector_access(vlenb, |core| {
+            tracing::debug!("Reading vector register v{index}");
+
+            core.schedule_setup_program_buffer(&[
+                assembly::vmv_x_s(8, index),
+                assembly::vslide1down_vx(index, index, 8),
+            ])?;
+
+            let mut postexec_cmd = AccessRegisterCommand(0);
+            postexec_cmd.set_postexec(true);
+
+            let mut elements = Vec::with_capacity(vlenb as usize / 4);
+            for _ in 0..vlenb / 4 {
+                core.execute_abstract_command(postexec_cmd.0)?;
+                elements.push(core.abstract_cmd_register_read(&registers::S0)?);
+            }
+
+            Ok(elements)
+        })
+    }
+
+    /// Writes the vector register `vN` from 32-bit elements, element 0 first.
+    ///
+    /// The elements are inserted at the top of the register one after another using
+    /// `vslide1down.vx`, see [`Self::read_vector_register`].
+    pub(crate) fn write_vector_register(
+        &mut self,
+        index: u8,
+        vlenb: u32,
+        elements: &[u32],
+    ) -> Result<(), RiscvError> {
+        assert_eq!(elements.len(), vlenb as usize / 4);
+
+        self.vector_access(vlenb, |core| {
+            tracing::debug!("Writing vector register v{index}");
+
+            core.schedule_setup_program_buffer(&[assembly::vslide1down_vx(index, index, 8)])?;
+
+            let mut postexec_cmd = AccessRegisterCommand(0);
+            postexec_cmd.set_postexec(true);
+
+            for &element in elements {
+                core.abstract_cmd_register_write(&registers::S0, element)?;
+                core.execute_abstract_command(postexec_cmd.0)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Prepares the hart for executing vector instructions in the program buffer, and restores
+    /// the vector state afterwards.
+    ///
+    /// The vector unit is enabled in `mstatus` if necessary, and `vtype` and `vl` are set up for
+    /// 32-bit elements, so that a single register contains `vlenb / 4` active elements. `s0` and
+    /// `s1` can be used by `op`.
+    fn vector_access<R>(
+        &mut self,
+        vlenb: u32,
+        op: impl FnOnce(&mut Self) -> Result<R, RiscvError>,
+    ) -> Result<R, RiscvError> {
+        const MSTATUS: u16 = 0x300;
+        const MSTATUS_VS: u32 = 0b11 << 9;
+        const MSTATUS_VS_INITIAL: u32 = 0b01 << 9;
+        const VSTART: u16 = 0x008;
+        const VL: u16 = 0xc20;
+        const VTYPE: u16 = 0xc21;
+        // SEW = 32 bits, LMUL = 1
+        const VTYPE_E32_M1: u16 = 0b010_000;
+
+        self.halted_access(|core| {
+            let s0 = core.save_s0()?;
+            let s1 = core.save_s1()?;
+
+            let mstatus = core.read_csr(MSTATUS)?;
+            if mstatus & MSTATUS_VS == 0 {
+                core.abstract_cmd_register_write(MSTATUS, mstatus | MSTATUS_VS_INITIAL)?;
+            }
+
+            let vstart = core.read_csr(VSTART)?;
+            let vl = core.read_csr(VL)?;
+            let vtype = core.read_csr(VTYPE)?;
+
+            let mut postexec_cmd = AccessRegisterCommand(0);
+            postexec_cmd.set_postexec(true);
+
+            core.abstract_cmd_register_write(&registers::S0, vlenb / 4)?;
+            core.schedule_setup_program_buffer(&[assembly::vsetvli(0, 8, VTYPE_E32_M1)])?;
+            core.execute_abstract_command(postexec_cmd.0)?;
+
+            let result = op(core);
+
+            // Restore the previous configuration, even if `op` failed.
+            core.abstract_cmd_register_write(&registers::S0, vl)?;
+            core.abstract_cmd_register_write(&registers::S1, vtype)?;
+            core.schedule_setup_program_buffer(&[assembly::vsetvl(0, 8, 9)])?;
+            core.execute_abstract_command(postexec_cmd.0)?;
+            core.abstract_cmd_register_write(VSTART, vstart)?;
+
+            if mstatus & MSTATUS_VS == 0 {
+                core.abstract_cmd_register_write(MSTATUS, mstatus)?;
+            }
+
+            core.restore_s1(s1)?;
+            core.restore_s0(s0)?;
+
+            result
+        })
+    }
+
     fn read_word<V: RiscvValue32>(&mut self, address: u32) -> Result<V, crate::Error> {
         let result = match self.state.memory_access_method(V::WIDTH) {
             MemoryAccessMethod::ProgramBuffer => self.perform_memory_read_progbuf(address)?,
@@ -1777,6 +2030,111 @@ impl<'state> RiscvCommunicationInterface<'state> {
         Ok(())
     }
 
+    /// Checks if the debug module can select multiple harts at once, using the hart array.
+    pub fn supports_hart_array(&mut self) -> Result<bool, RiscvError> {
+        if let Some(supported) = self.state.hart_array_supported {
+            return Ok(supported);
+        }
+
+        // `hasel` is hardwired to 0 if the hart array is not implemented.
+        let mut dmcontrol = self.state.current_dmcontrol;
+        dmcontrol.set_dmactive(true);
+        dmcontrol.set_hasel(true);
+        self.schedule_write_dm_register(dmcontrol)?;
+
+        let readback: Dmcontrol = self.read_dm_register()?;
+
+        dmcontrol.set_hasel(false);
+        self.write_dm_register(dmcontrol)?;
+
+        let supported = readback.hasel();
+        tracing::debug!("Support for hart array selection: {supported}");
+        self.state.hart_array_supported = Some(supported);
+
+        Ok(supported)
+    }
+
+    /// Halts the harts in the `harts` bit mask at the same time.
+    ///
+    /// The debug module has to support the hart array, see [`Self::supports_hart_array`].
+    pub fn halt_harts(&mut self, harts: u32, timeout: Duration) -> Result<(), RiscvError> {
+        let mut dmcontrol = self.schedule_select_hart_array(harts)?;
+        dmcontrol.set_haltreq(true);
+        self.schedule_write_dm_register(dmcontrol)?;
+
+        let start = Instant::now();
+        let result = loop {
+            let status: Dmstatus = self.read_dm_register()?;
+            if status.allhalted() {
+                break Ok(());
+            }
+            if start.elapsed() >= timeout {
+                break Err(RiscvError::Timeout);
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        };
+
+        // clear the halt request
+        dmcontrol.set_haltreq(false);
+        self.schedule_write_dm_register(dmcontrol)?;
+        self.deselect_hart_array()?;
+
+        result?;
+        self.state.is_halted = true;
+
+        Ok(())
+    }
+
+    /// Resumes the harts in the `harts` bit mask at the same time.
+    ///
+    /// The debug module has to support the hart array, see [`Self::supports_hart_array`].
+    pub fn resume_harts(&mut self, harts: u32) -> Result<(), RiscvError> {
+        self.state.is_halted = false; // `false` will re-query the DM, so it's safe to write
+
+        let mut dmcontrol = self.schedule_select_hart_array(harts)?;
+        dmcontrol.set_resumereq(true);
+        self.schedule_write_dm_register(dmcontrol)?;
+
+        // check if request has been acknowleged.
+        let status_idx = self.schedule_read_dm_register::<Dmstatus>()?;
+
+        // clear resume request.
+        dmcontrol.set_resumereq(false);
+        self.schedule_write_dm_register(dmcontrol)?;
+        self.deselect_hart_array()?;
+
+        let status = Dmstatus(self.dtm.read_deferred_result(status_idx)?.into_u32());
+        if !status.allresumeack() {
+            return Err(RiscvError::RequestNotAcknowledged);
+        }
+
+        Ok(())
+    }
+
+    /// Selects the harts in the `harts` bit mask, in addition to the hart in `hartsel`.
+    ///
+    /// Returns the `dmcontrol` value which selects the hart array.
+    fn schedule_select_hart_array(&mut self, harts: u32) -> Result<Dmcontrol, RiscvError> {
+        self.schedule_write_dm_register(Hawindowsel(0))?;
+        self.schedule_write_dm_register(Hawindow(harts))?;
+
+        // `hartsel` always selects a hart as well, so point it to one of the harts.
+        let mut dmcontrol = self.state.current_dmcontrol;
+        dmcontrol.set_dmactive(true);
+        dmcontrol.set_hasel(true);
+        dmcontrol.set_hartsel(harts.trailing_zeros());
+
+        Ok(dmcontrol)
+    }
+
+    /// Selects the single hart which was selected before the hart array.
+    fn deselect_hart_array(&mut self) -> Result<(), RiscvError> {
+        let mut dmcontrol = self.state.current_dmcontrol;
+        dmcontrol.set_hasel(false);
+        dmcontrol.set_hartsel(self.state.last_selected_hart);
+        self.write_dm_register(dmcontrol)
+    }
+
     pub(crate) fn reset_hart_and_halt(&mut self, timeout: Duration) -> Result<(), RiscvError> {
         tracing::debug!("Resetting core, setting hartreset bit");
 
@@ -2523,3 +2881,201 @@ memory_mapped_bitfield_register! { pub struct Confstrptr0(u32); 0x19, "confstrpt
 memory_mapped_bitfield_register! { pub struct Confstrptr1(u32); 0x1a, "confstrptr1", impl From; }
 memory_mapped_bitfield_register! { pub struct Confstrptr2(u32); 0x1b, "confstrptr2", impl From; }
 memory_mapped_bitfield_register! { pub struct Confstrptr3(u32); 0x1c, "confstrptr3", impl From; }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::probe::{CommandQueue, CommandResult, DeferredResultSet};
+
+    /// A debug module that executes the access register commands on a set of registers.
+    #[derive(Debug, Default)]
+    struct MockDebugModule {
+        registers: HashMap<u16, u32>,
+        data0: u32,
+        cmderr: u32,
+        /// The number of the command whose completion is reported late, counting from 1.
+        slow_command: Option<usize>,
+        commands: usize,
+        /// Whether the last command was slow, and its completion was not reported yet.
+        busy: bool,
+        queue: CommandQueue<()>,
+        results: DeferredResultSet<CommandResult>,
+    }
+
+    impl MockDebugModule {
+        fn read(&mut self, address: u64) -> u32 {
+            match address {
+                Data0::ADDRESS_OFFSET => self.data0,
+                Abstractcs::ADDRESS_OFFSET => {
+                    let mut abstractcs = Abstractcs(0);
+                    abstractcs.set_cmderr(self.cmderr);
+                    if std::mem::take(&mut self.busy) {
+                        abstractcs.0 |= 1 << 12;
+                    }
+                    abstractcs.0
+                }
+                _ => 0,
+            }
+        }
+
+        fn write(&mut self, address: u64, value: u32) {
+            match address {
+                Data0::ADDRESS_OFFSET => self.data0 = value,
+                Abstractcs::ADDRESS_OFFSET => {
+                    self.cmderr &= !Abstractcs(value).cmderr();
+                }
+                AccessRegisterCommand::ADDRESS_OFFSET => {
+                    self.commands += 1;
+                    self.busy = self.slow_command == Some(self.commands);
+
+                    let regno = (value & 0xffff) as u16;
+                    if value & (1 << 16) != 0 {
+                        self.registers.insert(regno, self.data0);
+                    } else {
+                        self.data0 = self.registers.get(&regno).copied().unwrap_or_default();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        fn schedule(&mut self, result: CommandResult) -> DeferredResultIndex {
+            let index = self.queue.schedule(());
+            self.results.push(&index, result);
+            self.queue.consume(1);
+            index
+        }
+    }
+
+    impl DtmAccess for MockDebugModule {
+        fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+            Ok(())
+        }
+
+        fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+            Ok(())
+        }
+
+        fn clear_error_state(&mut self) -> Result<(), RiscvError> {
+            Ok(())
+        }
+
+        fn read_deferred_result(
+            &mut self,
+            index: DeferredResultIndex,
+        ) -> Result<CommandResult, RiscvError> {
+            Ok(self.results.take(index).unwrap())
+        }
+
+        fn execute(&mut self) -> Result<(), RiscvError> {
+            Ok(())
+        }
+
+        fn schedule_write(
+            &mut self,
+            address: u64,
+            value: u32,
+        ) -> Result<Option<DeferredResultIndex>, RiscvError> {
+            self.write(address, value);
+            Ok(None)
+        }
+
+        fn schedule_read(&mut self, address: u64) -> Result<DeferredResultIndex, RiscvError> {
+            let value = self.read(address);
+            Ok(self.schedule(CommandResult::U32(value)))
+        }
+
+        fn read_with_timeout(
+            &mut self,
+            address: u64,
+            _timeout: Duration,
+        ) -> Result<u32, RiscvError> {
+            Ok(self.read(address))
+        }
+
+        fn write_with_timeout(
+            &mut self,
+            address: u64,
+            value: u32,
+            _timeout: Duration,
+        ) -> Result<Option<u32>, RiscvError> {
+            self.write(address, value);
+            Ok(None)
+        }
+
+        fn read_idcode(&mut self) -> Result<Option<u32>, DebugProbeError> {
+            Ok(None)
+        }
+    }
+
+    fn debug_module(slow_command: Option<usize>) -> MockDebugModule {
+        MockDebugModule {
+            registers: HashMap::from([(0x1000, 1), (0x1001, 2), (0x1002, 3), (0x7b1, 4)]),
+            slow_command,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn batched_csr_reads() {
+        let mut state = RiscvCommunicationInterfaceState::new();
+        let dm = debug_module(None);
+        let mut interface = RiscvCommunicationInterface::new(Box::new(dm), &mut state);
+
+        let values = interface.read_csrs(&[0x7b1, 0x1000, 0x1002]).unwrap();
+        assert_eq!(values, [4, 1, 3]);
+    }
+
+    #[test]
+    fn batched_csr_reads_fall_back_after_a_busy_command() {
+        let mut state = RiscvCommunicationInterfaceState::new();
+        let dm = debug_module(Some(2));
+        let mut interface = RiscvCommunicationInterface::new(Box::new(dm), &mut state);
+
+        let values = interface
+            .read_csrs(&[0x1000, 0x1001, 0x1002, 0x7b1])
+            .unwrap();
+        assert_eq!(values, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn batched_csr_writes() {
+        let mut state = RiscvCommunicationInterfaceState::new();
+        let dm = debug_module(None);
+        let mut interface = RiscvCommunicationInterface::new(Box::new(dm), &mut state);
+
+        let registers = [(0x1000, 10), (0x1001, 20), (0x7b1, 40)];
+        assert_eq!(interface.write_csrs(&registers).unwrap(), registers.len());
+        assert_eq!(
+            interface.read_csrs(&[0x1000, 0x1001, 0x7b1]).unwrap(),
+            [10, 20, 40]
+        );
+    }
+
+    #[test]
+    fn batched_csr_writes_stop_at_a_busy_command() {
+        let mut state = RiscvCommunicationInterfaceState::new();
+        let dm = debug_module(Some(2));
+        let mut interface = RiscvCommunicationInterface::new(Box::new(dm), &mut state);
+
+        let registers = [(0x1000, 10), (0x1001, 20), (0x7b1, 40)];
+        // Only the writes before the busy command are known to be done, the caller writes the
+        // rest one by one.
+        assert_eq!(interface.write_csrs(&registers).unwrap(), 1);
+    }
+
+    #[test]
+    fn batched_csr_access_skips_unsupported_registers() {
+        let mut state = RiscvCommunicationInterfaceState::new();
+        // The register can only be read with an abstract command.
+        state
+            .abstract_cmd_register_info
+            .insert(RegisterId(0x1001), CoreRegisterAbstractCmdSupport::READ);
+        let dm = debug_module(None);
+        let mut interface = RiscvCommunicationInterface::new(Box::new(dm), &mut state);
+
+        // The batch stops before the register that can't be written with an abstract command.
+        let registers = [(0x1000, 10), (0x1001, 20), (0x7b1, 40)];
+        assert_eq!(interface.write_csrs(&registers).unwrap(), 1);
+    }
+}
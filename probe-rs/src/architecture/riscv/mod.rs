@@ -2,7 +2,7 @@
 
 use crate::{
     CoreInterface, CoreRegister, CoreStatus, CoreType, Error, HaltReason, InstructionSet,
-    MemoryInterface, MemoryMappedRegister,
+    MemoryInterface, MemoryMappedRegister, TriggerKind, VectorCatchCondition,
     architecture::riscv::sequences::RiscvDebugSequence,
     core::{
         Architecture, BreakpointCause, CoreInformation, CoreRegisters, RegisterId, RegisterValue,
@@ -15,7 +15,7 @@ use crate::{
 };
 use bitfield::bitfield;
 use communication_interface::{AbstractCommandErrorKind, RiscvCommunicationInterface, RiscvError};
-use registers::{FP, RA, RISCV_CORE_REGISTERS, SP};
+use registers::{FP, RA, SP, riscv_core_registers, vector_register_index};
 use std::{
     sync::Arc,
     time::{Duration, Instant},
@@ -196,16 +196,158 @@ impl<'state> Riscv32<'state> {
         Ok(tselect_index)
     }
 
+    /// Finds the triggers which implement a condition of the same kind as `kind`.
+    fn triggers_of_kind(&mut self, kind: TriggerKind) -> Result<Vec<u32>, Error> {
+        let tselect = 0x7a0;
+        let tdata1 = 0x7a1;
+
+        let trigger_type = trigger_type(kind);
+        let mut triggers = vec![];
+
+        for index in 0..self.available_breakpoint_units()? {
+            self.write_csr(tselect, index)?;
+            let config = self.read_csr(tdata1)?;
+
+            if config >> 28 == trigger_type && TrapTrigger(config).dmode() && trigger_in_use(config)
+            {
+                triggers.push(index);
+            }
+        }
+
+        Ok(triggers)
+    }
+
+    /// Configures the first free trigger which supports the type of `config`.
+    ///
+    /// Triggers are allocated starting with the highest index, so the triggers with a low index
+    /// stay available for breakpoints.
+    fn configure_trigger(&mut self, config: u32, data: Option<u32>) -> Result<(), Error> {
+        let tselect = 0x7a0;
+        let tdata1 = 0x7a1;
+        let tdata2 = 0x7a2;
+
+        for index in (0..self.available_breakpoint_units()?).rev() {
+            self.write_csr(tselect, index)?;
+
+            if trigger_in_use(self.read_csr(tdata1)?) {
+                continue;
+            }
+
+            self.write_csr(tdata1, 0)?;
+            if let Some(data) = data {
+                self.write_csr(tdata2, data)?;
+            }
+            self.write_csr(tdata1, config)?;
+
+            // Triggers which don't support the requested type change it to a supported one.
+            if self.read_csr(tdata1)? >> 28 == config >> 28 {
+                tracing::debug!("Configured trigger {index}: {config:#010x}");
+                return Ok(());
+            }
+
+            self.write_csr(tdata1, 0)?;
+        }
+
+        Err(Error::Other(format!(
+            "No available trigger supports type {}",
+            config >> 28
+        )))
+    }
+
     fn on_halted(&mut self) -> Result<(), Error> {
         let status = self.status()?;
         tracing::debug!("Core halted: {:#?}", status);
 
         if status.is_halted() {
             self.sequence.on_halt(&mut self.interface)?;
+
+            if self.state.vlenb.is_none() {
+                let vlenb = self.determine_vlenb().unwrap_or_else(|error| {
+                    tracing::warn!("Failed to determine the vector register length: {error}");
+                    0
+                });
+                self.state.vlenb = Some(vlenb);
+            }
         }
 
         Ok(())
     }
+
+    /// Determines the length of a vector register in bytes, or 0 if the hart doesn't implement
+    /// the vector extension.
+    fn determine_vlenb(&mut self) -> Result<u32, RiscvError> {
+        const VLENB: u16 = 0xc22;
+
+        // Bit 21 is the V extension. The embedded vector extensions (Zve*) are not listed in
+        // `misa`, so `vlenb` is checked in any case.
+        let misa = Misa(self.read_csr(0x301)?);
+        let v_extension = misa.extensions() & (1 << 21) != 0;
+
+        let vlenb = match self.read_csr(VLENB) {
+            Ok(vlenb) => vlenb,
+            Err(RiscvError::AbstractCommand(
+                AbstractCommandErrorKind::NotSupported | AbstractCommandErrorKind::Exception,
+            )) if !v_extension => 0,
+            Err(error) => return Err(error),
+        };
+
+        tracing::debug!("Vector register length: {} bits", vlenb * 8);
+        if vlenb > 16 {
+            tracing::warn!(
+                "The vector registers of this hart are {} bits long, only vector registers of up to 128 bits are supported. Only the vector CSRs are available.",
+                vlenb * 8
+            );
+        }
+
+        Ok(vlenb)
+    }
+
+    /// Reads the vector register `vN`.
+    ///
+    /// Only a `VLEN` of up to 128 bits is supported, the largest [`RegisterValue`].
+    fn read_vector_register(&mut self, index: u8) -> Result<RegisterValue, Error> {
+        let vlenb = self.state.vlenb.unwrap_or_default();
+        let elements = match vlenb {
+            4 | 8 | 16 => self.interface.read_vector_register(index, vlenb)?,
+            _ => {
+                return Err(Error::NotImplemented(
+                    "vector registers with a VLEN above 128 bits",
+                ));
+            }
+        };
+
+        // Element 0 is stored in the least significant bits.
+        let value = elements
+            .iter()
+            .rev()
+            .fold(0u128, |value, &element| (value << 32) | element as u128);
+
+        Ok(match vlenb {
+            4 => RegisterValue::U32(value as u32),
+            8 => RegisterValue::U64(value as u64),
+            _ => RegisterValue::U128(value),
+        })
+    }
+
+    /// Writes the vector register `vN`.
+    fn write_vector_register(&mut self, index: u8, value: RegisterValue) -> Result<(), Error> {
+        let vlenb = self.state.vlenb.unwrap_or_default();
+        if !matches!(vlenb, 4 | 8 | 16) {
+            return Err(Error::NotImplemented(
+                "vector registers with a VLEN above 128 bits",
+            ));
+        }
+
+        let value: u128 = value.try_into()?;
+        let elements = (0..vlenb / 4)
+            .map(|element| (value >> (element * 32)) as u32)
+            .collect::<Vec<_>>();
+
+        self.interface
+            .write_vector_register(index, vlenb, &elements)?;
+
+        Ok(())
+    }
 }
 
 impl CoreInterface for Riscv32<'_> {
@@ -249,7 +391,7 @@ impl CoreInterface for Riscv32<'_> {
                 // Core halted after single step
                 4 => HaltReason::Step,
                 // Core halted directly after reset
-                5 => HaltReason::Exception,
+                5 => HaltReason::Exception(None),
                 // Reserved for future use in specification
                 _ => HaltReason::Unknown,
             };
@@ -282,6 +424,17 @@ impl CoreInterface for Riscv32<'_> {
         Ok(())
     }
 
+    fn prepare_group_resume(&mut self) -> Result<(), Error> {
+        if !self.state.pc_written {
+            // Step over possible breakpoints at the current instruction, like `run` does.
+            self.step()?;
+        }
+
+        self.state.semihosting_command = None;
+
+        Ok(())
+    }
+
     fn reset(&mut self) -> Result<(), Error> {
         self.reset_and_halt(Duration::from_secs(1))?;
         self.resume_core()?;
@@ -373,6 +526,10 @@ impl CoreInterface for Riscv32<'_> {
     }
 
     fn read_core_reg(&mut self, address: RegisterId) -> Result<RegisterValue, crate::Error> {
+        if let Some(index) = vector_register_index(address) {
+            return self.read_vector_register(index);
+        }
+
         self.read_csr(address.0)
             .map(|v| v.into())
             .map_err(|e| e.into())
@@ -383,6 +540,10 @@ impl CoreInterface for Riscv32<'_> {
         address: RegisterId,
         value: RegisterValue,
     ) -> Result<(), crate::Error> {
+        if let Some(index) = vector_register_index(address) {
+            return self.write_vector_register(index, value);
+        }
+
         let value: u32 = value.try_into()?;
 
         if address == self.program_counter().id {
@@ -392,6 +553,56 @@ impl CoreInterface for Riscv32<'_> {
         self.write_csr(address.0, value).map_err(|e| e.into())
     }
 
+    fn read_core_regs(&mut self, addresses: &[RegisterId]) -> Result<Vec<RegisterValue>, Error> {
+        // Vector registers are accessed through the program buffer, so they can't be batched.
+        if addresses
+            .iter()
+            .any(|&address| vector_register_index(address).is_some())
+        {
+            return addresses
+                .iter()
+                .map(|&address| self.read_core_reg(address))
+                .collect();
+        }
+
+        let addresses = addresses
+            .iter()
+            .map(|address| address.0)
+            .collect::<Vec<_>>();
+        let values = self.interface.read_csrs(&addresses)?;
+
+        Ok(values.into_iter().map(RegisterValue::from).collect())
+    }
+
+    fn write_core_regs(&mut self, registers: &[(RegisterId, RegisterValue)]) -> Result<(), Error> {
+        if registers
+            .iter()
+            .any(|&(address, _)| vector_register_index(address).is_some())
+        {
+            for &(address, value) in registers {
+                self.write_core_reg(address, value)?;
+            }
+            return Ok(());
+        }
+
+        let registers = registers
+            .iter()
+            .map(|&(address, value)| Ok((address.0, value.try_into()?)))
+            .collect::<Result<Vec<(u16, u32)>, Error>>()?;
+
+        let pc = self.program_counter().id.0;
+        if registers.iter().any(|&(address, _)| address == pc) {
+            self.state.pc_written = true;
+        }
+
+        let written = self.interface.write_csrs(&registers)?;
+        for &(address, value) in &registers[written..] {
+            self.write_csr(address, value)?;
+        }
+
+        Ok(())
+    }
+
     fn available_breakpoint_units(&mut self) -> Result<u32, crate::Error> {
         match self.state.hw_breakpoints {
             Some(bp) => Ok(bp),
@@ -566,8 +777,101 @@ impl CoreInterface for Riscv32<'_> {
         Ok(())
     }
 
+    fn set_trigger(&mut self, kind: TriggerKind) -> Result<(), Error> {
+        let was_running = !self.core_halted()?;
+        if was_running {
+            self.halt(Duration::from_millis(100))?;
+        }
+
+        self.clear_trigger(kind)?;
+
+        let mask = |mask: u64| {
+            u32::try_from(mask)
+                .map_err(|_| Error::Other(format!("Trigger mask {mask:#x} exceeds 32 bits")))
+        };
+
+        let (config, data) = match kind {
+            TriggerKind::InstructionCount(count) => {
+                if !(1..=0x3fff).contains(&count) {
+                    return Err(Error::Other(format!(
+                        "Instruction count {count} is not in the range 1..=16383"
+                    )));
+                }
+
+                let mut icount = Icount(0);
+                icount.set_count(count);
+                icount.set_m(true);
+                icount.set_s(true);
+                icount.set_u(true);
+                (icount.0, None)
+            }
+            TriggerKind::Interrupt(interrupts) => (TrapTrigger(0).0, Some(mask(interrupts)?)),
+            TriggerKind::Exception(exceptions) => (TrapTrigger(0).0, Some(mask(exceptions)?)),
+        };
+
+        let mut trigger = TrapTrigger(config);
+        trigger.set_type(trigger_type(kind));
+        trigger.set_dmode(true);
+        // Enter debug mode
+        trigger.set_action(1);
+        if !matches!(kind, TriggerKind::InstructionCount(_)) {
+            trigger.set_m(true);
+            trigger.set_s(true);
+            trigger.set_u(true);
+        }
+
+        self.configure_trigger(trigger.0, data)?;
+
+        if was_running {
+            self.resume_core()?;
+        }
+
+        Ok(())
+    }
+
+    fn clear_trigger(&mut self, kind: TriggerKind) -> Result<(), Error> {
+        let was_running = !self.core_halted()?;
+        if was_running {
+            self.halt(Duration::from_millis(100))?;
+        }
+
+        let tselect = 0x7a0;
+        let tdata1 = 0x7a1;
+
+        for index in self.triggers_of_kind(kind)? {
+            tracing::debug!("Clearing trigger {index}");
+            self.write_csr(tselect, index)?;
+            self.write_csr(tdata1, 0)?;
+        }
+
+        if was_running {
+            self.resume_core()?;
+        }
+
+        Ok(())
+    }
+
+    fn enable_vector_catch(&mut self, condition: VectorCatchCondition) -> Result<(), Error> {
+        let exceptions = match condition {
+            VectorCatchCondition::HardFault => FAULT_EXCEPTIONS,
+            VectorCatchCondition::All => ALL_EXCEPTIONS,
+            _ => return Err(Error::NotImplemented("vector catch")),
+        };
+
+        self.set_trigger(TriggerKind::Exception(exceptions))
+    }
+
+    fn disable_vector_catch(&mut self, condition: VectorCatchCondition) -> Result<(), Error> {
+        match condition {
+            VectorCatchCondition::HardFault | VectorCatchCondition::All => {
+                self.clear_trigger(TriggerKind::Exception(0))
+            }
+            _ => Err(Error::NotImplemented("vector catch")),
+        }
+    }
+
     fn registers(&self) -> &'static CoreRegisters {
-        &RISCV_CORE_REGISTERS
+        riscv_core_registers(self.state.vlenb.unwrap_or_default())
     }
 
     fn program_counter(&self) -> &'static CoreRegister {
@@ -673,6 +977,10 @@ pub struct RiscvCoreState {
 
     /// The semihosting command that was decoded at the current program counter
     semihosting_command: Option<SemihostingCommand>,
+
+    /// The length of a vector register in bytes, `Some(0)` if the hart doesn't implement the
+    /// vector extension. `None` until it is determined on the first halt.
+    vlenb: Option<u32>,
 }
 
 impl RiscvCoreState {
@@ -682,6 +990,7 @@ impl RiscvCoreState {
             hw_breakpoints: None,
             pc_written: false,
             semihosting_command: None,
+            vlenb: None,
         }
     }
 }
@@ -744,6 +1053,30 @@ impl Dmcontrol {
     }
 }
 
+memory_mapped_bitfield_register! {
+    /// `hawindowsel` register, located at address 0x14
+    ///
+    /// Selects the window of 32 harts which is shown in the `hawindow` register.
+    pub struct Hawindowsel(u32);
+    0x14, "hawindowsel",
+    impl From;
+
+    /// The index of the selected window.
+    pub hawindowsel, set_hawindowsel: 14, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// `hawindow` register, located at address 0x15
+    ///
+    /// Selects harts of the window selected by `hawindowsel` as part of the hart array.
+    pub struct Hawindow(u32);
+    0x15, "hawindow",
+    impl From;
+
+    /// One bit for each hart of the window, set if the hart is part of the hart array.
+    pub maskdata, set_maskdata: 31, 0;
+}
+
 memory_mapped_bitfield_register! {
     /// Readonly `dmstatus` register.
     ///
@@ -915,6 +1248,71 @@ bitfield! {
     load, set_load: 0;
 }
 
+bitfield! {
+    /// The `tdata1` layout of an instruction count trigger (`icount`, type 3).
+    struct Icount(u32);
+    impl Debug;
+
+    type_, set_type: 31, 28;
+    dmode, set_dmode: 27;
+    hit, set_hit: 24;
+    count, set_count: 23, 10;
+    m, set_m: 9;
+    s, set_s: 7;
+    u, set_u: 6;
+    action, set_action: 5, 0;
+}
+
+bitfield! {
+    /// The `tdata1` layout of an interrupt (`itrigger`, type 4) or exception trigger
+    /// (`etrigger`, type 5).
+    struct TrapTrigger(u32);
+    impl Debug;
+
+    type_, set_type: 31, 28;
+    dmode, set_dmode: 27;
+    hit, set_hit: 26;
+    m, set_m: 9;
+    s, set_s: 7;
+    u, set_u: 6;
+    action, set_action: 5, 0;
+}
+
+/// The exceptions caught for [`VectorCatchCondition::HardFault`]: misaligned accesses, access
+/// faults, page faults and illegal instructions.
+const FAULT_EXCEPTIONS: u64 = 0xB0F7;
+
+/// The exceptions caught for [`VectorCatchCondition::All`]. Breakpoints are excluded, because
+/// `ebreak` already enters debug mode.
+const ALL_EXCEPTIONS: u64 = 0xFFF7;
+
+/// The `tdata1` type of the trigger implementing `kind`.
+fn trigger_type(kind: TriggerKind) -> u32 {
+    match kind {
+        TriggerKind::InstructionCount(_) => 3,
+        TriggerKind::Interrupt(_) => 4,
+        TriggerKind::Exception(_) => 5,
+    }
+}
+
+/// Returns `true` if the trigger with the given `tdata1` value is configured.
+fn trigger_in_use(tdata1: u32) -> bool {
+    match tdata1 >> 28 {
+        // No trigger, or a disabled trigger
+        0 | 15 => false,
+        2 => {
+            let mcontrol = Mcontrol(tdata1);
+            mcontrol.execute() || mcontrol.store() || mcontrol.load()
+        }
+        // The mode bits are at the same position for icount, itrigger and etrigger.
+        3..=5 => {
+            let trigger = TrapTrigger(tdata1);
+            trigger.m() || trigger.s() || trigger.u()
+        }
+        _ => tdata1 & 0x07ff_ffff != 0,
+    }
+}
+
 memory_mapped_bitfield_register! {
     /// Isa and Extensions (see RISC-V Privileged Spec, 3.1.1)
     pub struct Misa(u32);
@@ -240,3 +240,143 @@ static RISCV_REGISTERS_SET: &[CoreRegister] = &[
     PC,
     // TODO: Add FPU registers
 ];
+
+/// The RISC-V core registers, including the vector registers of a hart with `VLEN = 32`.
+pub static RISCV_WITH_VECTOR_32_CORE_REGISTERS: LazyLock<CoreRegisters> =
+    LazyLock::new(|| with_vector_registers(&RISCV_VECTOR_32_REGISTERS));
+
+/// The RISC-V core registers, including the vector registers of a hart with `VLEN = 64`.
+pub static RISCV_WITH_VECTOR_64_CORE_REGISTERS: LazyLock<CoreRegisters> =
+    LazyLock::new(|| with_vector_registers(&RISCV_VECTOR_64_REGISTERS));
+
+/// The RISC-V core registers, including the vector registers of a hart with `VLEN = 128`.
+pub static RISCV_WITH_VECTOR_128_CORE_REGISTERS: LazyLock<CoreRegisters> =
+    LazyLock::new(|| with_vector_registers(&RISCV_VECTOR_128_REGISTERS));
+
+/// The RISC-V core registers, including only the vector CSRs of a hart with `VLEN` above 128
+/// bits.
+pub static RISCV_WITH_VECTOR_STATUS_CORE_REGISTERS: LazyLock<CoreRegisters> =
+    LazyLock::new(|| with_vector_registers(&[]));
+
+/// Returns the register file of a hart, depending on the length of its vector registers in bytes.
+///
+/// The vector registers `v0`-`v31` are only included for `VLEN` up to 128 bits, larger
+/// registers can not be represented as a [`RegisterValue`](crate::RegisterValue). Harts with a
+/// larger `VLEN` only get the vector CSRs.
+pub(crate) fn riscv_core_registers(vlenb: u32) -> &'static CoreRegisters {
+    match vlenb {
+        0 => &RISCV_CORE_REGISTERS,
+        4 => &RISCV_WITH_VECTOR_32_CORE_REGISTERS,
+        8 => &RISCV_WITH_VECTOR_64_CORE_REGISTERS,
+        16 => &RISCV_WITH_VECTOR_128_CORE_REGISTERS,
+        _ => &RISCV_WITH_VECTOR_STATUS_CORE_REGISTERS,
+    }
+}
+
+fn with_vector_registers(vector_registers: &'static [CoreRegister]) -> CoreRegisters {
+    CoreRegisters::new(
+        RISCV_REGISTERS_SET
+            .iter()
+            .chain(RISCV_VECTOR_STATUS_REGISTERS)
+            .chain(vector_registers)
+            .collect(),
+    )
+}
+
+/// The vector CSRs. They are accessed like any other CSR, using their CSR number as register ID.
+static RISCV_VECTOR_STATUS_REGISTERS: &[CoreRegister] = &[
+    CoreRegister {
+        roles: &[RegisterRole::Core("vstart"), RegisterRole::VectorStatus],
+        id: RegisterId(0x008),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("vxsat"), RegisterRole::VectorStatus],
+        id: RegisterId(0x009),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("vxrm"), RegisterRole::VectorStatus],
+        id: RegisterId(0x00A),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("vcsr"), RegisterRole::VectorStatus],
+        id: RegisterId(0x00F),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("vl"), RegisterRole::VectorStatus],
+        id: RegisterId(0xC20),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("vtype"), RegisterRole::VectorStatus],
+        id: RegisterId(0xC21),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("vlenb"), RegisterRole::VectorStatus],
+        id: RegisterId(0xC22),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+];
+
+/// The register ID of `v0`. The other vector registers use the following IDs.
+///
+/// Vector registers can't be accessed using the abstract register access command, the IDs are
+/// only used inside probe-rs. They are taken from a range which is reserved in the debug
+/// specification.
+pub(crate) const VECTOR_REGISTER_BASE: u16 = 0x1040;
+
+/// Returns the index of the vector register with the given ID, if it is one.
+pub(crate) fn vector_register_index(id: RegisterId) -> Option<u8> {
+    let index = id.0.checked_sub(VECTOR_REGISTER_BASE)?;
+
+    (index < 32).then_some(index as u8)
+}
+
+static RISCV_VECTOR_32_REGISTERS: LazyLock<Vec<CoreRegister>> =
+    LazyLock::new(|| vector_registers(32));
+
+static RISCV_VECTOR_64_REGISTERS: LazyLock<Vec<CoreRegister>> =
+    LazyLock::new(|| vector_registers(64));
+
+static RISCV_VECTOR_128_REGISTERS: LazyLock<Vec<CoreRegister>> =
+    LazyLock::new(|| vector_registers(128));
+
+fn vector_registers(vlen: usize) -> Vec<CoreRegister> {
+    VECTOR_REGISTER_ROLES
+        .iter()
+        .zip(VECTOR_REGISTER_BASE..)
+        .map(|(roles, id)| CoreRegister {
+            roles,
+            id: RegisterId(id),
+            data_type: RegisterDataType::UnsignedInteger(vlen),
+            unwind_rule: UnwindRule::Clear,
+        })
+        .collect()
+}
+
+static VECTOR_REGISTER_ROLES: [[RegisterRole; 2]; 32] = {
+    const NAMES: [&str; 32] = [
+        "v0", "v1", "v2", "v3", "v4", "v5", "v6", "v7", "v8", "v9", "v10", "v11", "v12", "v13",
+        "v14", "v15", "v16", "v17", "v18", "v19", "v20", "v21", "v22", "v23", "v24", "v25", "v26",
+        "v27", "v28", "v29", "v30", "v31",
+    ];
+
+    let mut roles = [[RegisterRole::Vector; 2]; 32];
+    let mut index = 0;
+    while index < NAMES.len() {
+        roles[index][0] = RegisterRole::Core(NAMES[index]);
+        index += 1;
+    }
+    roles
+};
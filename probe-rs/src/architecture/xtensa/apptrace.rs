@@ -0,0 +1,327 @@
+//! Host side of Espressif's application tracing (apptrace) for Xtensa cores.
+//!
+//! The `app_trace` component of ESP-IDF sends data to the host through the TRAX trace memory of
+//! the core. The memory is split into two blocks: while the target fills one of them, the host
+//! reads the other one. When its block is full, or the application flushes it, the target
+//! exchanges the blocks and publishes the length of the data in the control register.
+//!
+//! The control block of the protocol lives in the TRAX registers of the debug module, so the
+//! trace can be read while the core is running, the same way RTT is polled.
+//!
+//! Each write of the application is stored in the block with a 4 byte header: the allocated size
+//! and the written size, as little endian `u16`s. Bit 15 of both holds the core that wrote the data.
+
+use crate::architecture::xtensa::{
+    communication_interface::{XtensaCommunicationInterface, XtensaError},
+    xdm::NexusRegister,
+};
+
+const NARADR_TRAXID: u8 = 0x00;
+const NARADR_TRAXDATA: u8 = 0x03;
+const NARADR_TRAXADDR: u8 = 0x04;
+const NARADR_DELAYCNT: u8 = 0x07;
+
+/// The size of the header ESP-IDF stores in front of the data of each write.
+const USER_BLOCK_HEADER_SIZE: usize = 4;
+
+/// The bit of the user block sizes which holds the core that wrote the block.
+const USER_BLOCK_CORE_BIT: u16 = 1 << 15;
+
+/// The `TRAXID` register. Reads as zero if the core has no TRAX module.
+#[derive(Copy, Clone, Debug)]
+struct TraxId(u32);
+
+impl NexusRegister for TraxId {
+    const ADDRESS: u8 = NARADR_TRAXID;
+    const NAME: &'static str = "TRAXID";
+
+    fn from_bits(bits: u32) -> Result<Self, XtensaError> {
+        Ok(Self(bits))
+    }
+
+    fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+/// The `TRAXADDR` register, the word offset of the next `TRAXDATA` access.
+#[derive(Copy, Clone, Debug)]
+struct TraxAddress(u32);
+
+impl NexusRegister for TraxAddress {
+    const ADDRESS: u8 = NARADR_TRAXADDR;
+    const NAME: &'static str = "TRAXADDR";
+
+    fn from_bits(bits: u32) -> Result<Self, XtensaError> {
+        Ok(Self(bits))
+    }
+
+    fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+/// The `TRAXDATA` register. Each access increments `TRAXADDR`.
+#[derive(Copy, Clone, Debug)]
+struct TraxData(u32);
+
+impl NexusRegister for TraxData {
+    const ADDRESS: u8 = NARADR_TRAXDATA;
+    const NAME: &'static str = "TRAXDATA";
+
+    fn from_bits(bits: u32) -> Result<Self, XtensaError> {
+        Ok(Self(bits))
+    }
+
+    fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+bitfield::bitfield! {
+    /// The apptrace control register, stored in the otherwise unused `DELAYCNT` TRAX register.
+    #[derive(Copy, Clone)]
+    pub struct AppTraceControl(u32);
+    impl Debug;
+
+    /// The number of bytes in the block the host can read. Cleared by the host to acknowledge the
+    /// block.
+    pub block_len,    set_block_len   : 14, 0;
+
+    /// Incremented by the target each time the blocks are exchanged.
+    pub block_id,     set_block_id    : 21, 15;
+
+    /// Set by the host when it sends data to the target.
+    pub host_data,    set_host_data   : 22;
+
+    /// Set while a host is reading the trace. The target does not send data without a host.
+    pub host_connect, set_host_connect: 23;
+}
+
+impl NexusRegister for AppTraceControl {
+    const ADDRESS: u8 = NARADR_DELAYCNT;
+    const NAME: &'static str = "AppTraceControl";
+
+    fn from_bits(bits: u32) -> Result<Self, XtensaError> {
+        Ok(Self(bits))
+    }
+
+    fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+/// A connection to the apptrace control block of a core.
+///
+/// Use [`Session::get_xtensa_interface`](crate::Session::get_xtensa_interface) to get the
+/// interface of the core, then poll [`AppTrace::read`] to receive the trace.
+#[derive(Debug)]
+pub struct AppTrace {
+    /// The ID of the last block read by the host.
+    block_id: Option<u32>,
+}
+
+impl AppTrace {
+    /// Locates the apptrace control block and tells the target that a host is connected.
+    pub fn attach(interface: &mut XtensaCommunicationInterface<'_>) -> Result<Self, XtensaError> {
+        let trax_id = interface.xdm.read_nexus_register::<TraxId>()?;
+        if trax_id.0 == 0 {
+            return Err(XtensaError::NoTrax);
+        }
+
+        let control = interface.xdm.read_nexus_register::<AppTraceControl>()?;
+        tracing::debug!("Attaching to apptrace: {:?}", control);
+
+        Self::acknowledge(interface, control.block_id())?;
+
+        Ok(Self { block_id: None })
+    }
+
+    /// Reads the block of trace data that the target handed over to the host, if any, and
+    /// appends the data written by the application to `data`.
+    ///
+    /// Returns the number of bytes appended.
+    pub fn read(
+        &mut self,
+        interface: &mut XtensaCommunicationInterface<'_>,
+        data: &mut Vec<u8>,
+    ) -> Result<usize, XtensaError> {
+        let control = interface.xdm.read_nexus_register::<AppTraceControl>()?;
+
+        if !control.host_connect() {
+            // The target was reset and forgot about us.
+            tracing::debug!("Reconnecting to apptrace");
+            self.block_id = None;
+            Self::acknowledge(interface, control.block_id())?;
+            return Ok(0);
+        }
+
+        let len = control.block_len() as usize;
+        if len == 0 {
+            return Ok(0);
+        }
+
+        // The block handed over to the host is mapped at the start of the trace memory.
+        interface.xdm.schedule_write_nexus_register(TraxAddress(0));
+        let words = (0..len.div_ceil(4))
+            .map(|_| interface.xdm.schedule_read_nexus_register::<TraxData>())
+            .collect::<Vec<_>>();
+
+        let mut block = Vec::with_capacity(words.len());
+        for word in words {
+            block.push(interface.xdm.read_deferred_result(word)?.into_u32());
+        }
+
+        Self::acknowledge(interface, control.block_id())?;
+
+        Ok(self.take_block(control, &block, data))
+    }
+
+    /// Decodes the block described by `control`, read from the trace memory as `words`, and
+    /// appends the data written by the application to `data`.
+    ///
+    /// Returns the number of bytes appended.
+    fn take_block(&mut self, control: AppTraceControl, words: &[u32], data: &mut Vec<u8>) -> usize {
+        if let Some(block_id) = self.block_id {
+            let expected = (block_id + 1) % (AppTraceControl::MAX_BLOCK_ID + 1);
+            if control.block_id() != expected {
+                tracing::warn!(
+                    "Apptrace blocks were lost before block {}",
+                    control.block_id()
+                );
+            }
+        }
+        self.block_id = Some(control.block_id());
+
+        let mut block = words
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect::<Vec<_>>();
+        block.truncate(control.block_len() as usize);
+
+        let start = data.len();
+        decode_user_blocks(&block, data);
+
+        data.len() - start
+    }
+
+    /// Tells the target that the host disconnected, so that it stops sending data.
+    pub fn detach(
+        self,
+        interface: &mut XtensaCommunicationInterface<'_>,
+    ) -> Result<(), XtensaError> {
+        let control = interface.xdm.read_nexus_register::<AppTraceControl>()?;
+
+        let mut update = AppTraceControl(0);
+        update.set_block_id(control.block_id());
+        interface.xdm.write_nexus_register(update)
+    }
+
+    /// Marks block `block_id` as read, which allows the target to exchange the blocks again.
+    fn acknowledge(
+        interface: &mut XtensaCommunicationInterface<'_>,
+        block_id: u32,
+    ) -> Result<(), XtensaError> {
+        let mut control = AppTraceControl(0);
+        control.set_block_id(block_id);
+        control.set_host_connect(true);
+        interface.xdm.write_nexus_register(control)
+    }
+}
+
+impl AppTraceControl {
+    const MAX_BLOCK_ID: u32 = 0x7F;
+}
+
+/// Appends the data of the user blocks in `block` to `data`, without their headers.
+fn decode_user_blocks(mut block: &[u8], data: &mut Vec<u8>) {
+    while block.len() >= USER_BLOCK_HEADER_SIZE {
+        let size = u16::from_le_bytes([block[0], block[1]]) & !USER_BLOCK_CORE_BIT;
+        let written = u16::from_le_bytes([block[2], block[3]]) & !USER_BLOCK_CORE_BIT;
+        block = &block[USER_BLOCK_HEADER_SIZE..];
+
+        let size = usize::from(size);
+        if size > block.len() {
+            tracing::warn!("Apptrace user block of {size} bytes exceeds the trace block, skipping");
+            return;
+        }
+
+        // The size written is only updated once the application has finished writing.
+        let written = usize::from(written).min(size);
+        data.extend_from_slice(&block[..written]);
+        block = &block[size..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_register_layout() {
+        let mut control = AppTraceControl(0);
+        control.set_block_id(AppTraceControl::MAX_BLOCK_ID);
+        control.set_block_len(0x4000);
+        control.set_host_connect(true);
+
+        assert_eq!(control.0, 0x00BF_C000);
+        assert!(!control.host_data());
+    }
+
+    #[test]
+    fn decode_block() {
+        // A block as it is written by ESP-IDF: `esp_apptrace_write` calls from core 0 and core 1,
+        // each with a header of the allocated and written size, with the core in bit 15.
+        let block = [
+            0x06, 0x00, 0x06, 0x00, b'H', b'e', b'l', b'l', b'o', b' ', //
+            0x07, 0x80, 0x07, 0x80, b'a', b'p', b'p', b't', b'r', b'c', b'\n',
+        ];
+        let mut words = block
+            .chunks(4)
+            .map(|chunk| {
+                let mut word = [0; 4];
+                word[..chunk.len()].copy_from_slice(chunk);
+                u32::from_le_bytes(word)
+            })
+            .collect::<Vec<_>>();
+        // The unused end of the last word is left over from an earlier block.
+        *words.last_mut().unwrap() |= 0xAA00_0000;
+
+        let mut control = AppTraceControl(0);
+        control.set_block_len(block.len() as u32);
+        control.set_block_id(5);
+        control.set_host_connect(true);
+
+        let mut apptrace = AppTrace { block_id: Some(4) };
+        let mut data = b"previous ".to_vec();
+        let len = apptrace.take_block(control, &words, &mut data);
+
+        assert_eq!(len, 13);
+        assert_eq!(data, b"previous Hello apptrc\n");
+        assert_eq!(apptrace.block_id, Some(5));
+    }
+
+    #[test]
+    fn decode_block_with_unfinished_write() {
+        // The second write was allocated, but not finished when the block was exchanged.
+        let block = [
+            0x02, 0x00, 0x02, 0x00, b'o', b'k', //
+            0x04, 0x00, 0x00, 0x00, 0, 0, 0, 0,
+        ];
+
+        let mut data = vec![];
+        decode_user_blocks(&block, &mut data);
+        assert_eq!(data, b"ok");
+    }
+
+    #[test]
+    fn decode_block_with_invalid_size() {
+        let block = [
+            0x02, 0x00, 0x02, 0x00, b'o', b'k', 0x40, 0x00, 0x40, 0x00, b'x',
+        ];
+
+        let mut data = vec![];
+        decode_user_blocks(&block, &mut data);
+        assert_eq!(data, b"ok");
+    }
+}
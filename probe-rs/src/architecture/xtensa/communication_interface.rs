@@ -9,7 +9,7 @@ use std::{
 use zerocopy::IntoBytes;
 
 use crate::{
-    BreakpointCause, Error as ProbeRsError, HaltReason, MemoryInterface,
+    BreakpointCause, Error as ProbeRsError, ExceptionCause, HaltReason, MemoryInterface,
     architecture::xtensa::{
         arch::{CpuRegister, Register, SpecialRegister, instruction::Instruction},
         register_cache::RegisterCache,
@@ -44,6 +44,15 @@ pub enum XtensaError {
 
     /// The result index of a batched command is not available.
     BatchedResultNotAvailable,
+
+    /// A watchpoint of {1} bytes at {0:#x} is not supported.
+    UnsupportedWatchpoint(u64, usize),
+
+    /// The core has no watchpoint unit {0}.
+    InvalidWatchpointUnit(usize),
+
+    /// The core has no TRAX module.
+    NoTrax,
 }
 
 impl From<XtensaError> for ProbeRsError {
@@ -122,11 +131,19 @@ pub struct MemoryRegionProperties {
     pub fast_memory_access: bool,
 }
 
+/// The maximum number of data breakpoints of an Xtensa core, as defined by the Debug Option.
+pub const MAX_WATCHPOINT_UNITS: u32 = 2;
+
 /// Properties of an Xtensa CPU core.
 pub struct XtensaCoreProperties {
     /// The number of hardware breakpoints the target supports. CPU-specific configuration value.
     pub hw_breakpoint_num: u32,
 
+    /// The number of data breakpoints (watchpoints) the target supports. CPU-specific configuration value.
+    ///
+    /// Values above [`MAX_WATCHPOINT_UNITS`] are ignored.
+    pub hw_watchpoint_num: u32,
+
     /// The interrupt level at which debug exceptions are generated. CPU-specific configuration value.
     pub debug_level: DebugLevel,
 
@@ -141,6 +158,7 @@ impl Default for XtensaCoreProperties {
     fn default() -> Self {
         Self {
             hw_breakpoint_num: 2,
+            hw_watchpoint_num: 2,
             debug_level: DebugLevel::L6,
             memory_ranges: HashMap::new(),
             window_option_properties: WindowProperties::lx(64),
@@ -305,6 +323,16 @@ impl<'probe> XtensaCommunicationInterface<'probe> {
         self.core_properties.hw_breakpoint_num
     }
 
+    /// Returns the number of data breakpoints the target supports.
+    ///
+    /// On the Xtensa architecture this is the `NDBREAK` configuration parameter, which is at most
+    /// [`MAX_WATCHPOINT_UNITS`].
+    pub fn available_watchpoint_units(&self) -> u32 {
+        self.core_properties
+            .hw_watchpoint_num
+            .min(MAX_WATCHPOINT_UNITS)
+    }
+
     /// Returns whether the core is halted.
     pub fn core_halted(&mut self) -> Result<bool, XtensaError> {
         if !self.state.is_halted {
@@ -1100,7 +1128,7 @@ impl DebugCause {
         } else if is_breakpoint {
             HaltReason::Breakpoint(BreakpointCause::Software)
         } else if is_dbreak_exception {
-            HaltReason::Watchpoint
+            HaltReason::Watchpoint(None)
         } else if is_debug_interrupt {
             HaltReason::Request
         } else {
@@ -1109,6 +1137,31 @@ impl DebugCause {
     }
 }
 
+/// The `EXCCAUSE` (Exception Cause) register.
+#[derive(Copy, Clone, Debug)]
+pub struct ExcCause(pub u32);
+u32_register!(ExcCause, SpecialRegister::ExcCause);
+
+impl ExcCause {
+    /// Decodes the cause of the last exception.
+    pub fn cause(&self) -> ExceptionCause {
+        match self.0 {
+            0 => ExceptionCause::IllegalInstruction,
+            1 => ExceptionCause::Syscall,
+            2 | 12 | 14 => ExceptionCause::InstructionFetchError,
+            3 | 13 | 15 => ExceptionCause::LoadStoreError,
+            6 => ExceptionCause::IntegerDivideByZero,
+            8 | 18 | 26 => ExceptionCause::Privileged,
+            9 => ExceptionCause::LoadStoreAlignment,
+            20 => ExceptionCause::InstructionFetchProhibited,
+            28 => ExceptionCause::LoadProhibited,
+            29 => ExceptionCause::StoreProhibited,
+            32..=39 => ExceptionCause::CoprocessorDisabled((self.0 - 32) as u8),
+            other => ExceptionCause::Other(other),
+        }
+    }
+}
+
 bitfield::bitfield! {
     /// The `PS` (Program Status) register.
     ///
@@ -1145,6 +1198,22 @@ u32_register!(ProgramStatus, Register::CurrentPs);
 pub struct IBreakEn(pub u32);
 u32_register!(IBreakEn, SpecialRegister::IBreakEnable);
 
+bitfield::bitfield! {
+    /// A `DBREAKC` (Data Breakpoint Control) register.
+    #[derive(Copy, Clone)]
+    pub struct DBreakC(u32);
+    impl Debug;
+
+    /// Address mask. Cleared low bits are ignored when comparing with `DBREAKA`.
+    pub mask,       set_mask      : 5, 0;
+
+    /// Break on loads
+    pub load_break, set_load_break: 30;
+
+    /// Break on stores
+    pub store_break, set_store_break: 31;
+}
+
 /// The `ICOUNT` (Instruction Counter) register.
 #[derive(Copy, Clone, Debug)]
 pub struct ICount(pub u32);
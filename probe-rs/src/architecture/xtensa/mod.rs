@@ -6,14 +6,17 @@ use probe_rs_target::{Architecture, CoreType, InstructionSet};
 use zerocopy::IntoBytes;
 
 use crate::{
-    CoreInformation, CoreInterface, CoreRegister, CoreStatus, Error, HaltReason, MemoryInterface,
+    CoreInformation, CoreInterface, CoreRegister, CoreStatus, Error, ExceptionCause, ExceptionInfo,
+    HaltReason, MemoryInterface, WatchpointAccess,
     architecture::xtensa::{
+        apptrace::AppTrace,
         arch::{
             CpuRegister, Register, SpecialRegister,
             instruction::{Instruction, InstructionEncoding},
         },
         communication_interface::{
-            DebugCause, IBreakEn, ProgramStatus, WindowProperties, XtensaCommunicationInterface,
+            DBreakC, DebugCause, ExcCause, IBreakEn, MAX_WATCHPOINT_UNITS, ProgramStatus,
+            WindowProperties, XtensaCommunicationInterface, XtensaError,
         },
         registers::{FP, PC, RA, SP, XTENSA_CORE_REGISTERS},
         sequences::XtensaDebugSequence,
@@ -29,6 +32,7 @@ use crate::{
 pub(crate) mod arch;
 pub(crate) mod xdm;
 
+pub mod apptrace;
 pub mod communication_interface;
 pub(crate) mod register_cache;
 pub mod registers;
@@ -57,6 +61,9 @@ pub struct XtensaCoreState {
 
     /// Whether the registers have been spilled to the stack.
     spilled: bool,
+
+    /// The connection to the application trace, once it has been read.
+    apptrace: Option<AppTrace>,
 }
 
 impl XtensaCoreState {
@@ -69,6 +76,7 @@ impl XtensaCoreState {
             pc_written: false,
             semihosting_command: None,
             spilled: false,
+            apptrace: None,
         }
     }
 
@@ -91,6 +99,10 @@ pub struct Xtensa<'probe> {
 impl<'probe> Xtensa<'probe> {
     const IBREAKA_REGS: [SpecialRegister; 2] =
         [SpecialRegister::IBreakA0, SpecialRegister::IBreakA1];
+    const DBREAKA_REGS: [SpecialRegister; MAX_WATCHPOINT_UNITS as usize] =
+        [SpecialRegister::DBreakA0, SpecialRegister::DBreakA1];
+    const DBREAKC_REGS: [SpecialRegister; MAX_WATCHPOINT_UNITS as usize] =
+        [SpecialRegister::DBreakC0, SpecialRegister::DBreakC1];
 
     /// Create a new Xtensa interface for a particular core.
     pub fn new(
@@ -199,12 +211,81 @@ impl<'probe> Xtensa<'probe> {
                     // Re-enable the breakpoint
                     self.set_hw_breakpoint(bp_unit, pc_value as u64)?;
                 }
+            } else if debug_cause.dbreak_exception() {
+                // The access that triggered the watchpoint has not been executed yet.
+                if let Some(&register) = Self::DBREAKC_REGS.get(debug_cause.dbreak_num() as usize) {
+                    let control = self.interface.read_register_untyped(register)?;
+                    // Disable the watchpoint
+                    self.interface.write_register_untyped(register, 0)?;
+                    // Single step
+                    let ps = self.current_ps()?;
+                    self.interface.step(1, ps.intlevel())?;
+                    // Re-enable the watchpoint
+                    self.interface.write_register_untyped(register, control)?;
+                }
             }
         }
 
         Ok(())
     }
 
+    /// Reads the program counter and the 24-bit instruction it points to.
+    fn instruction_at_pc(&mut self) -> Result<(u64, u32), Error> {
+        let pc: u64 = self.read_core_reg(self.program_counter().id)?.try_into()?;
+
+        let mut instruction = [0u8; 3];
+        self.read_8(pc, &mut instruction)?;
+        let instruction = u32::from_le_bytes([instruction[0], instruction[1], instruction[2], 0]);
+
+        Ok((pc, instruction))
+    }
+
+    /// Check if the current breakpoint was hit while handling an exception, and decode the
+    /// exception.
+    ///
+    /// ESP-IDF executes `BREAK 1, 0` for unhandled kernel exceptions, `BREAK 1, 4` for double
+    /// exceptions and `BREAK 1, 15` in its panic handler when a debugger is attached.
+    fn check_for_exception(&mut self) -> Result<Option<ExceptionInfo>, Error> {
+        const fn encode_break(s: u8, t: u8) -> u32 {
+            let InstructionEncoding::Narrow(bytes) = Instruction::Break(s, t).encode();
+            bytes
+        }
+        const EXCEPTION_BREAK: u32 = encode_break(1, 0);
+        const DOUBLE_EXCEPTION_BREAK: u32 = encode_break(1, 4);
+        const PANIC_BREAK: u32 = encode_break(1, 15);
+
+        let (_, instruction) = self.instruction_at_pc()?;
+
+        let exception = match instruction {
+            DOUBLE_EXCEPTION_BREAK => {
+                let address = self
+                    .interface
+                    .read_register_untyped(SpecialRegister::Depc)?;
+
+                ExceptionInfo {
+                    cause: ExceptionCause::DoubleException,
+                    address: Some(address as u64),
+                }
+            }
+            EXCEPTION_BREAK | PANIC_BREAK => {
+                let cause = self.interface.read_register::<ExcCause>()?;
+                let address = self
+                    .interface
+                    .read_register_untyped(SpecialRegister::Epc1)?;
+
+                ExceptionInfo {
+                    cause: cause.cause(),
+                    address: Some(address as u64),
+                }
+            }
+            _ => return Ok(None),
+        };
+
+        tracing::debug!("Halted on an exception: {exception}");
+
+        Ok(Some(exception))
+    }
+
     /// Check if the current breakpoint is a semihosting call
     // OpenOCD implementation: https://github.com/espressif/openocd-esp32/blob/93dd01511fd13d4a9fb322cd9b600c337becef9e/src/target/espressif/esp_xtensa_semihosting.c#L42-L103
     fn check_for_semihosting(&mut self) -> Result<Option<SemihostingCommand>, Error> {
@@ -218,16 +299,7 @@ impl<'probe> Xtensa<'probe> {
             return Ok(Some(command));
         }
 
-        let pc: u64 = self.read_core_reg(self.program_counter().id)?.try_into()?;
-
-        let mut actual_instruction = [0u8; 3];
-        self.read_8(pc, &mut actual_instruction)?;
-        let actual_instruction = u32::from_le_bytes([
-            actual_instruction[0],
-            actual_instruction[1],
-            actual_instruction[2],
-            0,
-        ]);
+        let (pc, actual_instruction) = self.instruction_at_pc()?;
 
         tracing::debug!("Semihosting check pc={pc:#x} instruction={actual_instruction:#010x}");
 
@@ -299,6 +371,32 @@ impl<'probe> Xtensa<'probe> {
         Ok(self.interface.read_register::<DebugCause>()?)
     }
 
+    /// Returns the address watched by the data breakpoint that halted the core.
+    fn watchpoint_address(&mut self, debug_cause: DebugCause) -> Result<Option<u64>, Error> {
+        let Some(register) = Self::DBREAKA_REGS.get(debug_cause.dbreak_num() as usize) else {
+            return Ok(None);
+        };
+
+        let address = self.interface.read_register_untyped(*register)?;
+
+        Ok(Some(address as u64))
+    }
+
+    /// Returns the `DBREAKA` and `DBREAKC` registers of watchpoint unit `unit_index`.
+    fn dbreak_registers(
+        &self,
+        unit_index: usize,
+    ) -> Result<(SpecialRegister, SpecialRegister), XtensaError> {
+        if unit_index >= self.interface.available_watchpoint_units() as usize {
+            return Err(XtensaError::InvalidWatchpointUnit(unit_index));
+        }
+
+        Ok((
+            Self::DBREAKA_REGS[unit_index],
+            Self::DBREAKC_REGS[unit_index],
+        ))
+    }
+
     fn spill_registers(&mut self) -> Result<(), Error> {
         if self.state.spilled {
             return Ok(());
@@ -470,7 +568,11 @@ impl CoreInterface for Xtensa<'_> {
                 // Check if the breakpoint is a semihosting call
                 if let Some(cmd) = self.check_for_semihosting()? {
                     reason = HaltReason::Breakpoint(BreakpointCause::Semihosting(cmd));
+                } else if let Some(exception) = self.check_for_exception()? {
+                    reason = HaltReason::Exception(Some(exception));
                 }
+            } else if reason == HaltReason::Watchpoint(None) {
+                reason = HaltReason::Watchpoint(self.watchpoint_address(debug_cause)?);
             }
 
             CoreStatus::Halted(reason)
@@ -618,6 +720,82 @@ impl CoreInterface for Xtensa<'_> {
         })
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        Ok(self.interface.available_watchpoint_units())
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<u64>>, Error> {
+        self.halted_access(|this| {
+            let mut watchpoints = Vec::with_capacity(this.available_watchpoint_units()? as usize);
+
+            for i in 0..this.available_watchpoint_units()? as usize {
+                let control = this
+                    .interface
+                    .read_register_untyped(Self::DBREAKC_REGS[i])
+                    .map(DBreakC)?;
+
+                let watchpoint = if control.load_break() || control.store_break() {
+                    let address = this
+                        .interface
+                        .read_register_untyped(Self::DBREAKA_REGS[i])?;
+
+                    Some(address as u64)
+                } else {
+                    None
+                };
+
+                watchpoints.push(watchpoint);
+            }
+
+            Ok(watchpoints)
+        })
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        addr: u64,
+        length: usize,
+        access: WatchpointAccess,
+    ) -> Result<(), Error> {
+        let (dbreaka, dbreakc) = self.dbreak_registers(unit_index)?;
+
+        // DBREAK can only watch a naturally aligned, power-of-two sized region of up to 64 bytes.
+        if !length.is_power_of_two() || length > 64 || addr % length as u64 != 0 {
+            return Err(XtensaError::UnsupportedWatchpoint(addr, length).into());
+        }
+
+        let mut control = DBreakC(0);
+        // Compare all address bits above the watched region.
+        control.set_mask(!(length as u32 - 1) & 0x3F);
+        control.set_load_break(matches!(
+            access,
+            WatchpointAccess::Read | WatchpointAccess::ReadWrite
+        ));
+        control.set_store_break(matches!(
+            access,
+            WatchpointAccess::Write | WatchpointAccess::ReadWrite
+        ));
+
+        self.halted_access(|this| {
+            this.interface
+                .write_register_untyped(dbreaka, addr as u32)?;
+            this.interface.write_register_untyped(dbreakc, control.0)?;
+
+            Ok(())
+        })
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        let (_, dbreakc) = self.dbreak_registers(unit_index)?;
+
+        self.halted_access(|this| {
+            this.interface.write_register_untyped(dbreakc, 0)?;
+
+            Ok(())
+        })
+    }
+
     fn registers(&self) -> &'static CoreRegisters {
         &XTENSA_CORE_REGISTERS
     }
@@ -677,6 +855,30 @@ impl CoreInterface for Xtensa<'_> {
         self.interface.leave_debug_mode()?;
         Ok(())
     }
+
+    fn read_apptrace(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let apptrace = match self.state.apptrace.as_mut() {
+            Some(apptrace) => apptrace,
+            None => match AppTrace::attach(&mut self.interface) {
+                Ok(apptrace) => self.state.apptrace.insert(apptrace),
+                Err(XtensaError::NoTrax) => return Ok(None),
+                Err(error) => return Err(error.into()),
+            },
+        };
+
+        let mut data = vec![];
+        apptrace.read(&mut self.interface, &mut data)?;
+
+        Ok(Some(data))
+    }
+
+    fn stop_apptrace(&mut self) -> Result<(), Error> {
+        if let Some(apptrace) = self.state.apptrace.take() {
+            apptrace.detach(&mut self.interface)?;
+        }
+
+        Ok(())
+    }
 }
 
 struct RegisterFile {
@@ -452,7 +452,7 @@ impl<'probe> Xdm<'probe> {
         self.schedule_dbg_read(R::ADDRESS)
     }
 
-    fn read_nexus_register<R: NexusRegister>(&mut self) -> Result<R, XtensaError> {
+    pub(super) fn read_nexus_register<R: NexusRegister>(&mut self) -> Result<R, XtensaError> {
         let bits_reader = self.schedule_read_nexus_register::<R>();
 
         let bits = self.read_deferred_result(bits_reader)?.into_u32();
@@ -0,0 +1,82 @@
+//! Cancelling long-running operations, like flashing or large memory reads.
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+/// A token that cancels the long-running operations it is passed to.
+///
+/// Clones share the same state, so a clone can be kept e.g. by a GUI, and cancelled from another
+/// thread while the operation runs. A cancelled operation stops at the next point where the target
+/// is left in a consistent state, and returns a [`CancelledError`].
+///
+/// ```
+/// use probe_rs::CancellationToken;
+///
+/// let token = CancellationToken::new();
+/// let handle = token.clone();
+///
+/// handle.cancel();
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the operations this token was passed to.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Returns a [`CancelledError`] if the token was cancelled.
+    pub fn error_if_cancelled(&self) -> Result<(), CancelledError> {
+        if self.is_cancelled() {
+            Err(CancelledError)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The operation was cancelled.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub struct CancelledError;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, MemoryInterface, test::MockMemory};
+
+    #[test]
+    fn read_stops_once_cancelled() {
+        let contents = (0..3 * 4096 + 8).map(|i| i as u8).collect::<Vec<_>>();
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, contents.clone());
+
+        let token = CancellationToken::new();
+        let mut data = vec![0; contents.len()];
+        memory
+            .read_cancellable(0x2000_0000, &mut data, &token)
+            .unwrap();
+        assert_eq!(data, contents);
+
+        token.clone().cancel();
+        assert!(matches!(
+            memory.read_cancellable(0x2000_0000, &mut data, &token),
+            Err(Error::Cancelled(CancelledError))
+        ));
+    }
+}
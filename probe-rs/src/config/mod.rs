@@ -21,20 +21,44 @@
 //! targets can be created via the [`Registry::from_builtin_families`] function.
 //! To add a target at runtime, use [Registry::add_target_family]. The target family
 //! is a [`ChipFamily`] struct, usually read from a target description YAML file.
+//!
+//! Additional target description files can also be loaded from directories and URLs, which are
+//! added with [`Registry::add_search_path`] or the `PROBE_RS_TARGETS_PATH` and
+//! `PROBE_RS_TARGETS_URL` environment variables.
+//!
+//! A chip can reference the CMSIS-SVD file describing its peripherals. The parsed
+//! [`Peripherals`] are cached by the registry, see [`Registry::peripherals`].
+//!
+//! For chips without a target description, [`crate::vendor::scan_target`] collects what can be
+//! read from the chip itself, and [`ChipScan::to_chip_family`] turns it into a skeleton target
+//! description.
 
 mod chip_info;
+mod peripherals;
 pub(crate) mod registry;
+pub(crate) mod scan;
+mod search_path;
 mod target;
 
 pub use probe_rs_target::{
-    Chip, ChipFamily, Core, CoreType, Endian, FlashProperties, GenericRegion, InstructionSet,
-    MemoryAccess, MemoryRange, MemoryRegion, NvmRegion, PageInfo, RamRegion, RawFlashAlgorithm,
-    ScanChainElement, SectorDescription, SectorInfo, TargetDescriptionSource,
+    AddressAlias, Chip, ChipFamily, Core, CoreType, DebugFreezeBit, DebugFreezeKind, Endian,
+    FlashProperties, GenericRegion, InstructionSet, MemoryAccess, MemoryRange, MemoryRegion,
+    NvmRegion, OptionBytes, OptionBytesField, OptionBytesStep, PageInfo, RamRegion,
+    RawFlashAlgorithm, ResetStrategy, ScanChainElement, SectorDescription, SectorInfo,
+    TargetDescriptionSource, Watchdog, WatchdogWrite,
 };
 
+pub use peripherals::{
+    Access, EnumeratedValue, Field, ModifiedWriteValues, Peripheral, Peripherals, Register,
+};
 pub use registry::{Registry, RegistryError};
+pub use scan::{
+    CfiEraseRegion, CfiFlash, ChipScan, ScannedCore, ScannedMemory, ScannedMemoryKind, SfdpFlash,
+};
+pub use search_path::{SearchPath, TARGETS_PATH_ENV, TARGETS_URL_ENV};
 pub use target::{DebugSequence, Target, TargetSelector};
 
 // Crate-internal API
 pub(crate) use chip_info::ChipInfo;
+pub(crate) use peripherals::PeripheralCache;
 pub(crate) use target::CoreExt;
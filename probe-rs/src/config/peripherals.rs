@@ -0,0 +1,545 @@
+//! Peripheral descriptions from CMSIS-SVD files.
+//!
+//! SVD files of larger chips are several megabytes of XML, so they are only parsed once. The
+//! [`Registry`](super::Registry) keeps the parsed [`Peripherals`] in memory, and stores them in a
+//! compact binary form in its cache directory.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::RegistryError;
+
+/// The peripherals of a device, as described by its SVD file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Peripherals {
+    /// The name of the device.
+    pub device: String,
+    /// The peripherals of the device.
+    pub peripherals: Vec<Peripheral>,
+}
+
+impl Peripherals {
+    /// Parses the contents of an SVD file.
+    #[cfg(feature = "svd")]
+    pub fn from_svd(xml: &str) -> Result<Self, String> {
+        let device =
+            svd_parser::parse_with_config(xml, &svd_parser::Config::default().expand(true))
+                .map_err(|error| format!("{error:#}"))?;
+
+        Ok(svd::convert_device(&device))
+    }
+
+    /// Returns the peripheral with the given name, ignoring case.
+    pub fn peripheral(&self, name: &str) -> Option<&Peripheral> {
+        self.peripherals
+            .iter()
+            .find(|peripheral| peripheral.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A peripheral of a device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Peripheral {
+    /// The name of the peripheral.
+    pub name: String,
+    /// The name of the group of peripherals this peripheral belongs to, e.g. `GPIO`.
+    pub group_name: Option<String>,
+    /// The description of the peripheral.
+    pub description: Option<String>,
+    /// The base address of the peripheral.
+    pub base_address: u64,
+    /// The registers of the peripheral, including the registers of its clusters.
+    pub registers: Vec<Register>,
+}
+
+impl Peripheral {
+    /// Returns the register with the given name, ignoring case.
+    pub fn register(&self, name: &str) -> Option<&Register> {
+        self.registers
+            .iter()
+            .find(|register| register.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A register of a peripheral.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Register {
+    /// The name of the register.
+    pub name: String,
+    /// The description of the register.
+    pub description: Option<String>,
+    /// The absolute address of the register.
+    pub address: u64,
+    /// The size of the register in bits.
+    pub size: u32,
+    /// The access of the register, with the default of the device applied.
+    pub access: Option<Access>,
+    /// Whether reading the register has side effects.
+    pub read_action: bool,
+    /// The value of the register after a reset.
+    pub reset_value: Option<u64>,
+    /// The fields of the register.
+    pub fields: Vec<Field>,
+}
+
+impl Register {
+    /// Returns the field with the given name, ignoring case.
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        self.fields
+            .iter()
+            .find(|field| field.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A bit field of a register.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Field {
+    /// The name of the field.
+    pub name: String,
+    /// The description of the field.
+    pub description: Option<String>,
+    /// The position of the least significant bit of the field.
+    pub bit_offset: u32,
+    /// The number of bits of the field.
+    pub bit_width: u32,
+    /// The access of the field, with the access of its register applied.
+    pub access: Option<Access>,
+    /// Whether reading the field has side effects.
+    pub read_action: bool,
+    /// How writing the field modifies it.
+    pub modified_write_values: Option<ModifiedWriteValues>,
+    /// The named values of the field. Values which are only used for writing come last.
+    pub enumerated_values: Vec<EnumeratedValue>,
+}
+
+impl Field {
+    /// Returns the mask of the field within its register.
+    pub fn mask(&self) -> u64 {
+        1_u64
+            .checked_shl(self.bit_width)
+            .unwrap_or(0)
+            .wrapping_sub(1)
+            << self.bit_offset
+    }
+
+    /// Extracts the value of the field from the value of its register.
+    pub fn extract(&self, register_value: u64) -> u64 {
+        (register_value & self.mask()) >> self.bit_offset
+    }
+
+    /// Returns the named value for `value`, if there is one.
+    pub fn enumerated_value(&self, value: u64) -> Option<&EnumeratedValue> {
+        self.enumerated_values
+            .iter()
+            .find(|enumerated| enumerated.value == value)
+    }
+}
+
+/// A named value of a field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnumeratedValue {
+    /// The name of the value.
+    pub name: String,
+    /// The description of the value.
+    pub description: Option<String>,
+    /// The value.
+    pub value: u64,
+}
+
+/// The access of a register or field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Access {
+    /// Read only.
+    ReadOnly,
+    /// Write only.
+    WriteOnly,
+    /// Read and write.
+    ReadWrite,
+    /// Only the first write after a reset has an effect, reads are undefined.
+    WriteOnce,
+    /// Only the first write after a reset has an effect.
+    ReadWriteOnce,
+}
+
+impl Access {
+    /// Whether the register or field can be read.
+    pub fn can_read(self) -> bool {
+        matches!(
+            self,
+            Access::ReadOnly | Access::ReadWrite | Access::ReadWriteOnce
+        )
+    }
+
+    /// Whether the register or field can be written.
+    pub fn can_write(self) -> bool {
+        !matches!(self, Access::ReadOnly)
+    }
+}
+
+/// How writing a field modifies it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ModifiedWriteValues {
+    /// Writing a one clears the bit.
+    OneToClear,
+    /// Writing a one sets the bit.
+    OneToSet,
+    /// Writing a one toggles the bit.
+    OneToToggle,
+    /// Writing a zero clears the bit.
+    ZeroToClear,
+    /// Writing a zero sets the bit.
+    ZeroToSet,
+    /// Writing a zero toggles the bit.
+    ZeroToToggle,
+    /// Any write clears the field.
+    Clear,
+    /// Any write sets the field.
+    Set,
+    /// Writes store the written value.
+    Modify,
+}
+
+/// Loads [`Peripherals`] from SVD files, and caches them.
+///
+/// Clones share the loaded peripherals, so a [`Session`](crate::Session) does not parse an SVD
+/// file again which was already loaded through its [`Registry`](super::Registry).
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PeripheralCache {
+    cache_dir: Option<PathBuf>,
+    loaded: Arc<Mutex<HashMap<String, Arc<Peripherals>>>>,
+}
+
+impl PeripheralCache {
+    pub(crate) fn set_cache_dir(&mut self, cache_dir: PathBuf) {
+        self.cache_dir = Some(cache_dir);
+    }
+
+    /// Returns the peripherals of the SVD file at `location`, which is a path or a URL.
+    pub(crate) fn load(&self, location: &str) -> Result<Arc<Peripherals>, RegistryError> {
+        if let Some(peripherals) = self.loaded.lock().unwrap().get(location) {
+            return Ok(peripherals.clone());
+        }
+
+        let peripherals = Arc::new(load(location, self.cache_dir.as_deref())?);
+
+        self.loaded
+            .lock()
+            .unwrap()
+            .insert(location.to_string(), peripherals.clone());
+
+        Ok(peripherals)
+    }
+}
+
+#[cfg(feature = "svd")]
+fn load(location: &str, cache_dir: Option<&Path>) -> Result<Peripherals, RegistryError> {
+    use std::{
+        collections::hash_map::DefaultHasher,
+        fs,
+        hash::{Hash, Hasher},
+        time::SystemTime,
+    };
+
+    /// Changes whenever the cached form of the peripherals changes.
+    const CACHE_VERSION: u32 = 1;
+
+    let remote = location.starts_with("http://") || location.starts_with("https://");
+
+    // Local files are cached by their modification time, so that an edited file is parsed again.
+    let mut hasher = DefaultHasher::new();
+    (CACHE_VERSION, location).hash(&mut hasher);
+    if !remote {
+        let metadata = fs::metadata(location)?;
+        (metadata.len(), metadata.modified().ok()).hash(&mut hasher);
+    }
+    let cache_file =
+        cache_dir.map(|directory| directory.join(format!("{:016x}.svd.bin", hasher.finish())));
+
+    if let Some(cache_file) = &cache_file {
+        let fresh = !remote
+            || fs::metadata(cache_file)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .is_some_and(|age| age < super::search_path::CACHE_LIFETIME);
+
+        let cached = fs::read(cache_file)
+            .ok()
+            .filter(|_| fresh)
+            .and_then(|bytes| {
+                bincode::serde::decode_from_slice(&bytes, bincode::config::standard()).ok()
+            });
+
+        if let Some((peripherals, _)) = cached {
+            tracing::debug!("Using cached peripherals of {location}");
+            return Ok(peripherals);
+        }
+    }
+
+    let xml = if remote {
+        super::search_path::fetch(location)?
+    } else {
+        fs::read_to_string(location)?
+    };
+
+    tracing::debug!("Parsing SVD file {location}");
+    let peripherals = Peripherals::from_svd(&xml)
+        .map_err(|error| RegistryError::Svd(location.to_string(), error))?;
+
+    if let Some(cache_file) = &cache_file {
+        let stored = bincode::serde::encode_to_vec(&peripherals, bincode::config::standard())
+            .map_err(std::io::Error::other)
+            .and_then(|bytes| {
+                cache_file
+                    .parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|_| fs::write(cache_file, bytes))
+            });
+        if let Err(error) = stored {
+            tracing::warn!("Failed to cache the peripherals of {location}: {error}");
+        }
+    }
+
+    Ok(peripherals)
+}
+
+#[cfg(not(feature = "svd"))]
+fn load(location: &str, _cache_dir: Option<&Path>) -> Result<Peripherals, RegistryError> {
+    Err(RegistryError::Svd(
+        location.to_string(),
+        "probe-rs was built without the `svd` feature".to_string(),
+    ))
+}
+
+#[cfg(feature = "svd")]
+mod svd {
+    use svd_parser::svd;
+
+    use super::*;
+
+    pub(super) fn convert_device(device: &svd::Device) -> Peripherals {
+        let defaults = &device.default_register_properties;
+
+        Peripherals {
+            device: device.name.clone(),
+            peripherals: device
+                .peripherals
+                .iter()
+                .map(|peripheral| Peripheral {
+                    name: peripheral.name.clone(),
+                    group_name: peripheral.group_name.clone(),
+                    description: peripheral.description.clone(),
+                    base_address: peripheral.base_address,
+                    registers: peripheral
+                        .all_registers()
+                        .map(|register| {
+                            convert_register(peripheral.base_address, register, defaults)
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    fn convert_register(
+        base_address: u64,
+        register: &svd::Register,
+        defaults: &svd::RegisterProperties,
+    ) -> Register {
+        let access = register
+            .properties
+            .access
+            .or(defaults.access)
+            .map(convert_access);
+        let register_modified_write_values = register.modified_write_values;
+
+        Register {
+            name: register.name.clone(),
+            description: register.description.clone(),
+            address: base_address + register.address_offset as u64,
+            size: register.properties.size.or(defaults.size).unwrap_or(32),
+            access,
+            read_action: register.read_action.is_some(),
+            reset_value: register.properties.reset_value.or(defaults.reset_value),
+            fields: register
+                .fields()
+                .map(|field| {
+                    // Values which are only used for writing are still useful to set the
+                    // field by name.
+                    let mut enumerated_values = field
+                        .enumerated_values
+                        .iter()
+                        .filter(|values| values.usage != Some(svd::Usage::Write))
+                        .chain(
+                            field
+                                .enumerated_values
+                                .iter()
+                                .filter(|values| values.usage == Some(svd::Usage::Write)),
+                        )
+                        .flat_map(|values| &values.values)
+                        .filter_map(|value| {
+                            Some(EnumeratedValue {
+                                name: value.name.clone(),
+                                description: value.description.clone(),
+                                value: value.value?,
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    enumerated_values.dedup();
+
+                    Field {
+                        name: field.name.clone(),
+                        description: field.description.clone(),
+                        bit_offset: field.bit_offset(),
+                        bit_width: field.bit_width(),
+                        access: field.access.map(convert_access).or(access),
+                        read_action: field.read_action.is_some(),
+                        modified_write_values: field
+                            .modified_write_values
+                            .or(register_modified_write_values)
+                            .map(convert_modified_write_values),
+                        enumerated_values,
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    fn convert_access(access: svd::Access) -> Access {
+        match access {
+            svd::Access::ReadOnly => Access::ReadOnly,
+            svd::Access::WriteOnly => Access::WriteOnly,
+            svd::Access::ReadWrite => Access::ReadWrite,
+            svd::Access::WriteOnce => Access::WriteOnce,
+            svd::Access::ReadWriteOnce => Access::ReadWriteOnce,
+        }
+    }
+
+    fn convert_modified_write_values(values: svd::ModifiedWriteValues) -> ModifiedWriteValues {
+        match values {
+            svd::ModifiedWriteValues::OneToClear => ModifiedWriteValues::OneToClear,
+            svd::ModifiedWriteValues::OneToSet => ModifiedWriteValues::OneToSet,
+            svd::ModifiedWriteValues::OneToToggle => ModifiedWriteValues::OneToToggle,
+            svd::ModifiedWriteValues::ZeroToClear => ModifiedWriteValues::ZeroToClear,
+            svd::ModifiedWriteValues::ZeroToSet => ModifiedWriteValues::ZeroToSet,
+            svd::ModifiedWriteValues::ZeroToToggle => ModifiedWriteValues::ZeroToToggle,
+            svd::ModifiedWriteValues::Clear => ModifiedWriteValues::Clear,
+            svd::ModifiedWriteValues::Set => ModifiedWriteValues::Set,
+            svd::ModifiedWriteValues::Modify => ModifiedWriteValues::Modify,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "svd"))]
+mod tests {
+    use super::*;
+
+    const SVD: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<device schemaVersion="1.3" xmlns:xs="http://www.w3.org/2001/XMLSchema-instance">
+  <name>TEST</name>
+  <addressUnitBits>8</addressUnitBits>
+  <width>32</width>
+  <size>32</size>
+  <access>read-write</access>
+  <peripherals>
+    <peripheral>
+      <name>TIMER0</name>
+      <groupName>TIMER</groupName>
+      <baseAddress>0x40008000</baseAddress>
+      <registers>
+        <register>
+          <name>STATUS</name>
+          <addressOffset>0x10</addressOffset>
+          <access>read-only</access>
+          <fields>
+            <field>
+              <name>STATE</name>
+              <bitOffset>1</bitOffset>
+              <bitWidth>2</bitWidth>
+              <enumeratedValues>
+                <enumeratedValue><name>Stopped</name><value>0</value></enumeratedValue>
+                <enumeratedValue><name>Running</name><value>1</value></enumeratedValue>
+              </enumeratedValues>
+            </field>
+          </fields>
+        </register>
+        <register>
+          <name>EVENTS</name>
+          <addressOffset>0x14</addressOffset>
+          <readAction>clear</readAction>
+          <fields>
+            <field>
+              <name>COMPARE</name>
+              <bitOffset>0</bitOffset>
+              <bitWidth>1</bitWidth>
+              <modifiedWriteValues>oneToClear</modifiedWriteValues>
+            </field>
+          </fields>
+        </register>
+      </registers>
+    </peripheral>
+  </peripherals>
+</device>"#;
+
+    #[test]
+    fn peripherals_from_svd() {
+        let peripherals = Peripherals::from_svd(SVD).unwrap();
+        assert_eq!(peripherals.device, "TEST");
+
+        let timer = peripherals.peripheral("timer0").unwrap();
+        assert_eq!(timer.group_name.as_deref(), Some("TIMER"));
+
+        let status = timer.register("STATUS").unwrap();
+        assert_eq!(status.address, 0x4000_8010);
+        assert_eq!(status.size, 32);
+        assert_eq!(status.access, Some(Access::ReadOnly));
+
+        let state = status.field("STATE").unwrap();
+        assert_eq!(state.access, Some(Access::ReadOnly));
+        assert_eq!(state.mask(), 0b110);
+        assert_eq!(state.extract(0b010), 1);
+        assert_eq!(state.enumerated_value(1).unwrap().name, "Running");
+
+        let events = timer.register("EVENTS").unwrap();
+        assert!(events.read_action);
+        assert_eq!(events.access, Some(Access::ReadWrite));
+        assert_eq!(
+            events.fields[0].modified_write_values,
+            Some(ModifiedWriteValues::OneToClear)
+        );
+    }
+
+    #[test]
+    fn parsed_peripherals_are_cached() {
+        let directory =
+            std::env::temp_dir().join(format!("probe-rs-svd-cache-{}", std::process::id()));
+        let svd_file = directory.join("test.svd");
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(&svd_file, SVD).unwrap();
+        let location = svd_file.to_str().unwrap();
+
+        let parsed = load(location, Some(&directory)).unwrap();
+        let cached_files = std::fs::read_dir(&directory)
+            .unwrap()
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_string_lossy()
+                    .ends_with(".svd.bin")
+            })
+            .count();
+
+        let cached = load(location, Some(&directory));
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(cached_files, 1);
+        assert_eq!(cached.unwrap(), parsed);
+    }
+}
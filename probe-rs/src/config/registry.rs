@@ -1,10 +1,14 @@
 //! Internal target registry
 
+use super::peripherals::{PeripheralCache, Peripherals};
+use super::search_path::{self, SearchPath};
 use super::{Chip, ChipFamily, ChipInfo, Core, Target, TargetDescriptionSource};
 use crate::config::CoreType;
 use probe_rs_target::{CoreAccessOptions, RiscvCoreAccessOptions};
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
 
 /// Error type for all errors which occur when working
 /// with the internal registry of targets.
@@ -24,6 +28,10 @@ pub enum RegistryError {
     Yaml(#[from] serde_yaml::Error),
     /// Invalid chip family definition ({0.name}): {1}
     InvalidChipFamilyDefinition(Box<ChipFamily>, String),
+    /// Failed to download the target descriptions from '{0}': {1}
+    Download(String, String),
+    /// Failed to load the SVD file '{0}': {1}
+    Svd(String, String),
 }
 
 fn add_generic_targets(vec: &mut Vec<ChipFamily>) {
@@ -34,6 +42,8 @@ fn add_generic_targets(vec: &mut Vec<ChipFamily>) {
             generated_from_pack: false,
             pack_file_release: None,
             chip_detection: vec![],
+            debug_freeze: vec![],
+            watchdogs: vec![],
             variants: vec![
                 Chip::generic_arm("Cortex-M0", CoreType::Armv6m),
                 Chip::generic_arm("Cortex-M0+", CoreType::Armv6m),
@@ -49,6 +59,8 @@ fn add_generic_targets(vec: &mut Vec<ChipFamily>) {
             generated_from_pack: false,
             pack_file_release: None,
             chip_detection: vec![],
+            debug_freeze: vec![],
+            watchdogs: vec![],
             variants: vec![Chip::generic_arm("Cortex-M3", CoreType::Armv7m)],
             flash_algorithms: vec![],
             source: TargetDescriptionSource::Generic,
@@ -59,6 +71,8 @@ fn add_generic_targets(vec: &mut Vec<ChipFamily>) {
             generated_from_pack: false,
             pack_file_release: None,
             chip_detection: vec![],
+            debug_freeze: vec![],
+            watchdogs: vec![],
             variants: vec![
                 Chip::generic_arm("Cortex-M4", CoreType::Armv7em),
                 Chip::generic_arm("Cortex-M7", CoreType::Armv7em),
@@ -72,6 +86,8 @@ fn add_generic_targets(vec: &mut Vec<ChipFamily>) {
             generated_from_pack: false,
             pack_file_release: None,
             chip_detection: vec![],
+            debug_freeze: vec![],
+            watchdogs: vec![],
             variants: vec![
                 Chip::generic_arm("Cortex-M23", CoreType::Armv8m),
                 Chip::generic_arm("Cortex-M33", CoreType::Armv8m),
@@ -87,6 +103,8 @@ fn add_generic_targets(vec: &mut Vec<ChipFamily>) {
             pack_file_release: None,
             generated_from_pack: false,
             chip_detection: vec![],
+            debug_freeze: vec![],
+            watchdogs: vec![],
             variants: vec![Chip {
                 name: "riscv".to_owned(),
                 part: None,
@@ -106,6 +124,13 @@ fn add_generic_targets(vec: &mut Vec<ChipFamily>) {
                 rtt_scan_ranges: None,
                 jtag: None,
                 default_binary_format: None,
+                option_bytes: None,
+                address_aliases: vec![],
+                uf2_family_ids: vec![],
+                debug_description: None,
+                debug_freeze: vec![],
+                reset_strategies: vec![],
+                watchdogs: vec![],
             }],
             flash_algorithms: vec![],
             source: TargetDescriptionSource::Generic,
@@ -118,6 +143,15 @@ fn add_generic_targets(vec: &mut Vec<ChipFamily>) {
 pub struct Registry {
     /// All the available chips.
     families: Vec<ChipFamily>,
+    /// Additional locations of target descriptions.
+    search_paths: Vec<SearchPath>,
+    /// The directory in which downloaded target descriptions are cached.
+    cache_dir: Option<PathBuf>,
+    /// The families loaded from the search paths. They are only loaded once a target is looked
+    /// up, so that unused remote indexes are never downloaded.
+    search_path_families: OnceLock<Vec<ChipFamily>>,
+    /// The peripherals loaded from SVD files.
+    peripheral_cache: PeripheralCache,
 }
 
 #[cfg(feature = "builtin-targets")]
@@ -137,7 +171,7 @@ fn builtin_targets() -> Vec<ChipFamily> {
 impl Registry {
     /// Create a new registry.
     pub fn new() -> Self {
-        Self { families: vec![] }
+        Self::default()
     }
 
     /// Add a target from the built-in targets.
@@ -150,12 +184,67 @@ impl Registry {
         // Additionally, validation for existing targets is done in the tests `validate_generic_targets` and
         // `validate_builtin` as well, to ensure we do not ship broken target definitions.
 
-        Self { families }
+        Self {
+            families,
+            ..Self::default()
+        }
+    }
+
+    /// Adds a location from which additional target descriptions are loaded.
+    ///
+    /// The target descriptions are loaded on the first lookup of a target. Families from a
+    /// search path replace built-in families with the same name, and families from later search
+    /// paths replace those from earlier ones.
+    pub fn add_search_path(&mut self, search_path: impl Into<SearchPath>) {
+        self.search_paths.push(search_path.into());
+        self.search_path_families = OnceLock::new();
+    }
+
+    /// Adds the search paths from the `PROBE_RS_TARGETS_PATH` and `PROBE_RS_TARGETS_URL`
+    /// environment variables.
+    pub fn add_search_paths_from_env(&mut self) {
+        for search_path in search_path::search_paths_from_env() {
+            self.add_search_path(search_path);
+        }
+    }
+
+    /// Sets the directory in which target descriptions downloaded from remote search paths are
+    /// cached.
+    ///
+    /// Without a cache directory, remote target descriptions are downloaded on every start. The
+    /// parsed contents of SVD files are cached in the same directory.
+    pub fn set_cache_dir(&mut self, cache_dir: impl Into<PathBuf>) {
+        let cache_dir = cache_dir.into();
+        self.peripheral_cache.set_cache_dir(cache_dir.clone());
+        self.cache_dir = Some(cache_dir);
+    }
+
+    /// Returns the peripherals described by the SVD file at `location`, which is a path or a URL.
+    ///
+    /// The file is only parsed once, later calls and sessions created from this registry use
+    /// the cached peripherals.
+    pub fn peripherals(&self, location: &str) -> Result<Arc<Peripherals>, RegistryError> {
+        self.peripheral_cache.load(location)
+    }
+
+    pub(crate) fn peripheral_cache(&self) -> &PeripheralCache {
+        &self.peripheral_cache
+    }
+
+    fn search_path_families(&self) -> &[ChipFamily] {
+        self.search_path_families
+            .get_or_init(|| search_path::load(&self.search_paths, self.cache_dir.as_deref()))
     }
 
     /// Returns the list of chip families.
-    pub fn families(&self) -> &[ChipFamily] {
-        &self.families
+    pub fn families(&self) -> impl Iterator<Item = &ChipFamily> {
+        let loaded = self.search_path_families();
+
+        loaded.iter().chain(self.families.iter().filter(|family| {
+            !loaded
+                .iter()
+                .any(|loaded| loaded.name.eq_ignore_ascii_case(&family.name))
+        }))
     }
 
     /// Returns a particular target by its name.
@@ -174,7 +263,7 @@ impl Registry {
         let mut selected_family_and_chip = None;
         let mut exact_matches = 0;
         let mut partial_matches = Vec::new();
-        for family in self.families.iter() {
+        for family in self.families() {
             for (variant, package) in family
                 .variants
                 .iter()
@@ -260,7 +349,7 @@ impl Registry {
     pub fn get_targets_by_family_name(&self, name: &str) -> Result<Vec<String>, RegistryError> {
         let mut found_family = None;
         let mut exact_matches = 0;
-        for family in self.families.iter() {
+        for family in self.families() {
             if match_name_prefix(&family.name, name) {
                 if family.name.len() == name.len() {
                     tracing::debug!("Exact match for family name: {}", family.name);
@@ -290,7 +379,7 @@ impl Registry {
 
         let mut targets = Vec::new();
 
-        for family in &self.families {
+        for family in self.families() {
             for (variant, package) in family
                 .variants
                 .iter()
@@ -313,7 +402,7 @@ impl Registry {
             ChipInfo::Arm(chip_info) => {
                 // Try get the corresponding chip.
 
-                let families = self.families.iter().filter(|f| {
+                let families = self.families().filter(|f| {
                     f.manufacturer
                         .map(|m| m == chip_info.manufacturer)
                         .unwrap_or(false)
@@ -361,6 +450,12 @@ impl Registry {
 
         let family_name = family.name.clone();
 
+        // Load the search paths now, so that they can't replace the family later.
+        self.search_path_families();
+        if let Some(loaded) = self.search_path_families.get_mut() {
+            loaded.retain(|old_family| !old_family.name.eq_ignore_ascii_case(&family_name));
+        }
+
         self.families
             .retain(|old_family| !old_family.name.eq_ignore_ascii_case(&family_name));
 
@@ -391,7 +486,7 @@ fn match_name_prefix(pattern: &str, name: &str) -> bool {
     true
 }
 
-fn validate_family(family: &ChipFamily) -> Result<(), String> {
+pub(super) fn validate_family(family: &ChipFamily) -> Result<(), String> {
     family.validate()?;
 
     // We can't have this in the `validate` method as we need information that is not available in
@@ -405,6 +500,7 @@ fn validate_family(family: &ChipFamily) -> Result<(), String> {
 
 #[cfg(test)]
 mod tests {
+    use crate::config::ResetStrategy;
     use crate::flashing::FlashAlgorithm;
 
     use super::*;
@@ -470,6 +566,29 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn validate_reset_strategies() {
+        let mut families = vec![];
+        add_generic_targets(&mut families);
+        let [armv6m, armv7em] = ["Generic ARMv6-M", "Generic ARMv7E-M"]
+            .map(|name| families.iter().find(|family| family.name == name).unwrap());
+
+        let mut family = armv7em.clone();
+        family.variants[0].reset_strategies =
+            vec![ResetStrategy::VectReset, ResetStrategy::HardwareReset];
+        family.validate().unwrap();
+
+        family.variants[0]
+            .reset_strategies
+            .push(ResetStrategy::VectReset);
+        assert!(family.validate().is_err());
+
+        // VECTRESET only exists on ARMv7-M.
+        let mut family = armv6m.clone();
+        family.variants[0].reset_strategies = vec![ResetStrategy::VectReset];
+        assert!(family.validate().is_err());
+    }
+
     #[test]
     fn validate_builtin() {
         let registry = Registry::from_builtin_families();
@@ -536,4 +655,122 @@ mod tests {
 
         Ok(())
     }
+
+    #[cfg(feature = "builtin-targets")]
+    #[test]
+    fn debug_freeze_bits_of_stm32_families() -> TestResult {
+        let registry = Registry::from_builtin_families();
+
+        for name in ["STM32F205RB", "STM32F407VG", "STM32F767ZI"] {
+            let target = registry.get_target_by_name(name)?;
+            assert!(
+                target
+                    .debug_freeze
+                    .iter()
+                    .any(|bit| bit.peripheral == "IWDG" && bit.address == 0xE004_2008),
+                "{name} does not stop the IWDG"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "builtin-targets")]
+    #[test]
+    fn family_watchdogs() -> TestResult {
+        let registry = Registry::from_builtin_families();
+
+        let target = registry.get_target_by_name("S32K144HAxxxLHx")?;
+        assert_eq!(target.watchdogs.len(), 1);
+        assert!(target.watchdogs[0].run_on_target);
+        assert_eq!(target.watchdogs[0].disable.len(), 3);
+
+        for name in ["CH32V003", "CH32V307"] {
+            let target = registry.get_target_by_name(name)?;
+            assert_eq!(target.watchdogs.len(), 1, "{name} has no watchdog");
+            assert_eq!(target.watchdogs[0].name, "IWDG");
+            assert!(target.watchdogs[0].disable.is_empty());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn variant_debug_freeze_bits_replace_the_family_bits() -> TestResult {
+        let mut registry = Registry::new();
+        registry.add_target_family_from_yaml(
+            r#"
+name: FREEZE_FAM
+debug_freeze:
+- peripheral: IWDG
+  kind: watchdog
+  address: 0xe0042008
+  bit: 12
+variants:
+- name: FAMILY_BITS
+  cores:
+  - name: core0
+    type: armv7em
+    core_access_options: !Arm
+      ap: !v1 0
+  memory_map:
+  - !Ram
+    range:
+      start: 0x20000000
+      end: 0x20010000
+    cores:
+    - core0
+- name: OWN_BITS
+  cores:
+  - name: core0
+    type: armv7em
+    core_access_options: !Arm
+      ap: !v1 0
+  memory_map:
+  - !Ram
+    range:
+      start: 0x20000000
+      end: 0x20010000
+    cores:
+    - core0
+  debug_freeze:
+  - peripheral: TIM2
+    kind: timer
+    address: 0xe0042008
+    bit: 0
+"#,
+        )?;
+
+        let target = registry.get_target_by_name("FAMILY_BITS")?;
+        assert_eq!(target.debug_freeze.len(), 1);
+        assert_eq!(target.debug_freeze[0].peripheral, "IWDG");
+
+        let target = registry.get_target_by_name("OWN_BITS")?;
+        assert_eq!(target.debug_freeze.len(), 1);
+        assert_eq!(target.debug_freeze[0].peripheral, "TIM2");
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_targets_from_search_path() -> TestResult {
+        let mut registry = Registry::new();
+        registry.add_search_path(std::path::Path::new("tests/scan_chain_test.yaml"));
+
+        let target = registry.get_target_by_name("NO_JTAG_INFO")?;
+        assert_eq!(target.jtag, None);
+
+        // Explicitly added families take precedence over the search paths.
+        let file = std::fs::read_to_string("tests/scan_chain_test.yaml")?;
+        let family_name = registry.add_target_family_from_yaml(&file)?;
+        assert_eq!(
+            registry
+                .families()
+                .filter(|family| family.name == family_name)
+                .count(),
+            1
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,407 @@
+//! Best-effort target descriptions for chips which are not known to the registry.
+
+use std::{collections::HashMap, ops::Range};
+
+use jep106::JEP106Code;
+use probe_rs_target::{
+    ApAddress as TargetApAddress, ArmCoreAccessOptions, Chip, ChipFamily, Core, CoreAccessOptions,
+    CoreType, MemoryRegion, NvmRegion, RamRegion, TargetDescriptionSource,
+};
+
+use crate::{
+    Error,
+    architecture::arm::{ApAddress, ApV2Address, FullyQualifiedApAddress},
+};
+
+/// What could be read from a connected chip, see [`crate::vendor::scan_target`].
+#[derive(Debug, Clone, Default)]
+pub struct ChipScan {
+    /// The name of the target the chip was identified as, if it is already known to the registry.
+    pub known_target: Option<String>,
+    /// The manufacturer, as read from the ROM table.
+    pub manufacturer: Option<JEP106Code>,
+    /// The part number, as read from the ROM table.
+    pub part: Option<u16>,
+    /// The cores found in the ROM tables.
+    pub cores: Vec<ScannedCore>,
+    /// The memory ranges which could be read from the first core's access port.
+    pub memory: Vec<ScannedMemory>,
+    /// The CFI flash devices which answered a query.
+    pub cfi_flash: Vec<CfiFlash>,
+    /// The serial flash devices described by their SFDP tables, see [`SfdpFlash::parse`].
+    pub sfdp_flash: Vec<SfdpFlash>,
+}
+
+/// A core found in a ROM table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScannedCore {
+    /// The name of the core, as reported by the CPUID register, e.g. `Cortex-M4`.
+    pub cpu: String,
+    /// The core type.
+    pub core_type: CoreType,
+    /// The access port through which the core is accessed.
+    pub access_port: FullyQualifiedApAddress,
+}
+
+/// The kind of a scanned memory range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScannedMemoryKind {
+    /// The range is read-only from the debugger's point of view, and most likely flash.
+    Nvm,
+    /// The range is most likely RAM.
+    Ram,
+}
+
+/// A memory range which could be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScannedMemory {
+    /// The kind of memory.
+    pub kind: ScannedMemoryKind,
+    /// The address range. The size is determined by reading at power of two offsets, and may be
+    /// too large if the memory is mirrored.
+    pub range: Range<u64>,
+}
+
+/// The geometry of a memory mapped flash, read with a CFI query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CfiFlash {
+    /// The base address of the flash.
+    pub base_address: u64,
+    /// The size of the flash in bytes.
+    pub size: u64,
+    /// The erase block regions, in address order.
+    pub erase_regions: Vec<CfiEraseRegion>,
+}
+
+/// A region of equally sized erase blocks of a CFI flash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CfiEraseRegion {
+    /// The number of blocks.
+    pub blocks: u32,
+    /// The size of each block in bytes.
+    pub block_size: u64,
+}
+
+/// The geometry of a serial flash, read from its SFDP (JESD216) tables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SfdpFlash {
+    /// The address at which the flash is mapped.
+    pub base_address: u64,
+    /// The size of the flash in bytes.
+    pub size: u64,
+    /// The sizes of the supported erase operations in bytes, in the order of the erase types.
+    pub erase_sizes: Vec<u64>,
+}
+
+impl SfdpFlash {
+    /// The signature at the start of the SFDP header.
+    const SIGNATURE: &[u8; 4] = b"SFDP";
+    /// The parameter ID of the JEDEC basic flash parameter table.
+    const BASIC_FLASH_PARAMETERS: u16 = 0xFF00;
+
+    /// Parses the SFDP tables of a serial flash mapped at `base_address`.
+    ///
+    /// Serial flashes can only be queried through their flash controller, which is specific to
+    /// each chip, so `data` is the SFDP address space read by other means, starting at address 0.
+    pub fn parse(base_address: u64, data: &[u8]) -> Result<Self, Error> {
+        if data.get(..4) != Some(Self::SIGNATURE.as_slice()) {
+            return Err(Error::Other("The SFDP signature is missing".to_string()));
+        }
+        let Some((header, parameter_headers)) = data.split_at_checked(8) else {
+            return Err(Error::Other("The SFDP header is truncated".to_string()));
+        };
+        let header_count = header[6] as usize + 1;
+
+        for header in parameter_headers.chunks_exact(8).take(header_count) {
+            let id = u16::from_le_bytes([header[0], header[7]]);
+            if id != Self::BASIC_FLASH_PARAMETERS {
+                continue;
+            }
+
+            let dwords = header[3] as usize;
+            let pointer = u32::from_le_bytes([header[4], header[5], header[6], 0]) as usize;
+            let table = data
+                .get(pointer..pointer + dwords * 4)
+                .ok_or_else(|| {
+                    Error::Other(format!(
+                        "The basic flash parameter table at {pointer:#x} is truncated"
+                    ))
+                })?
+                .chunks_exact(4)
+                .map(|dword| u32::from_le_bytes([dword[0], dword[1], dword[2], dword[3]]))
+                .collect::<Vec<_>>();
+
+            return Self::from_basic_flash_parameters(base_address, &table);
+        }
+
+        Err(Error::Other(
+            "The SFDP tables have no basic flash parameter table".to_string(),
+        ))
+    }
+
+    /// Reads the density and the erase types from the basic flash parameter table.
+    fn from_basic_flash_parameters(base_address: u64, table: &[u32]) -> Result<Self, Error> {
+        if table.len() < 9 {
+            return Err(Error::Other(format!(
+                "The basic flash parameter table has only {} DWORDs",
+                table.len()
+            )));
+        }
+
+        // The density is the number of bits, minus one, or a power of two if bit 31 is set.
+        let density = table[1];
+        let bits = if density & 0x8000_0000 == 0 {
+            Some(density as u64 + 1)
+        } else {
+            1u64.checked_shl(density & 0x7FFF_FFFF)
+        };
+        let size = bits
+            .map(|bits| bits / 8)
+            .filter(|&size| size > 0)
+            .ok_or_else(|| Error::Other(format!("Invalid SFDP flash density {density:#010x}")))?;
+
+        // DWORDs 8 and 9 hold the four erase types, each with the size as a power of two in
+        // the lower byte. A size of zero means that the erase type is not supported.
+        let erase_sizes = [table[7], table[7] >> 16, table[8], table[8] >> 16]
+            .into_iter()
+            .map(|erase_type| erase_type & 0xFF)
+            .filter(|&exponent| exponent != 0)
+            .map(|exponent| {
+                1u64.checked_shl(exponent).ok_or_else(|| {
+                    Error::Other(format!("Invalid SFDP erase size of 2^{exponent} bytes"))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            base_address,
+            size,
+            erase_sizes,
+        })
+    }
+}
+
+impl ChipScan {
+    /// Creates a target description skeleton from the scan.
+    ///
+    /// The skeleton has no flash algorithms, and its memory map is only a guess, so it has to be
+    /// refined before it can be used to flash the chip.
+    pub fn to_chip_family(&self, name: &str) -> ChipFamily {
+        let cores = self
+            .cores
+            .iter()
+            .enumerate()
+            .map(|(index, core)| Core {
+                name: format!("core{index}"),
+                core_type: core.core_type,
+                core_access_options: CoreAccessOptions::Arm(ArmCoreAccessOptions {
+                    ap: match core.access_port.ap() {
+                        ApAddress::V1(ap) => TargetApAddress::V1(*ap),
+                        ApAddress::V2(ApV2Address(address)) => {
+                            TargetApAddress::V2(address.unwrap_or(0))
+                        }
+                    },
+                    ..Default::default()
+                }),
+            })
+            .collect::<Vec<_>>();
+        let core_names = cores
+            .iter()
+            .map(|core| core.name.clone())
+            .collect::<Vec<_>>();
+
+        let mut memory_map = self
+            .memory
+            .iter()
+            .enumerate()
+            .map(|(index, memory)| match memory.kind {
+                ScannedMemoryKind::Nvm => MemoryRegion::Nvm(NvmRegion {
+                    name: Some(format!("NVM{index}")),
+                    range: memory.range.clone(),
+                    cores: core_names.clone(),
+                    is_alias: false,
+                    access: None,
+                }),
+                ScannedMemoryKind::Ram => MemoryRegion::Ram(RamRegion {
+                    name: Some(format!("RAM{index}")),
+                    range: memory.range.clone(),
+                    cores: core_names.clone(),
+                    access: None,
+                }),
+            })
+            .collect::<Vec<_>>();
+
+        memory_map.extend(self.cfi_flash.iter().enumerate().map(|(index, flash)| {
+            MemoryRegion::Nvm(NvmRegion {
+                name: Some(format!("CFI{index}")),
+                range: flash.base_address..flash.base_address + flash.size,
+                cores: core_names.clone(),
+                is_alias: false,
+                access: None,
+            })
+        }));
+
+        memory_map.extend(self.sfdp_flash.iter().enumerate().map(|(index, flash)| {
+            MemoryRegion::Nvm(NvmRegion {
+                name: Some(format!("SFDP{index}")),
+                range: flash.base_address..flash.base_address + flash.size,
+                cores: core_names.clone(),
+                is_alias: false,
+                access: None,
+            })
+        }));
+
+        ChipFamily {
+            name: name.to_string(),
+            manufacturer: self.manufacturer,
+            generated_from_pack: false,
+            pack_file_release: None,
+            chip_detection: vec![],
+            debug_freeze: vec![],
+            watchdogs: vec![],
+            variants: vec![Chip {
+                name: name.to_string(),
+                part: self.part,
+                svd: None,
+                documentation: HashMap::new(),
+                package_variants: vec![],
+                cores,
+                memory_map,
+                flash_algorithms: vec![],
+                rtt_scan_ranges: None,
+                jtag: None,
+                default_binary_format: None,
+                option_bytes: None,
+                address_aliases: vec![],
+                uf2_family_ids: vec![],
+                debug_description: None,
+                debug_freeze: vec![],
+                reset_strategies: vec![],
+                watchdogs: vec![],
+            }],
+            flash_algorithms: vec![],
+            source: TargetDescriptionSource::External,
+        }
+    }
+}
+
+/// Returns the core type for the name of a core, as reported by its CPUID register.
+pub(crate) fn core_type_from_cpu_name(name: &str) -> Option<CoreType> {
+    let core_type = match name {
+        "Cortex-M0" | "Cortex-M0+" | "Cortex-M1" => CoreType::Armv6m,
+        "Cortex-M3" => CoreType::Armv7m,
+        "Cortex-M4" | "Cortex-M7" => CoreType::Armv7em,
+        "Cortex-M23" | "Cortex-M33" | "Cortex-M35P" | "Cortex-M52" | "Cortex-M55"
+        | "Cortex-M85" => CoreType::Armv8m,
+        _ => return None,
+    };
+
+    Some(core_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::architecture::arm::dp::DpAddress;
+
+    #[test]
+    fn skeleton_from_scan() {
+        let scan = ChipScan {
+            manufacturer: Some(JEP106Code::new(0, 0x20)),
+            part: Some(0x450),
+            cores: vec![ScannedCore {
+                cpu: "Cortex-M4".to_string(),
+                core_type: CoreType::Armv7em,
+                access_port: FullyQualifiedApAddress::v1_with_dp(DpAddress::Default, 0),
+            }],
+            memory: vec![
+                ScannedMemory {
+                    kind: ScannedMemoryKind::Nvm,
+                    range: 0x0800_0000..0x0810_0000,
+                },
+                ScannedMemory {
+                    kind: ScannedMemoryKind::Ram,
+                    range: 0x2000_0000..0x2002_0000,
+                },
+            ],
+            ..Default::default()
+        };
+
+        let family = scan.to_chip_family("Unknown");
+        family.validate().unwrap();
+
+        let chip = &family.variants[0];
+        assert_eq!(chip.part, Some(0x450));
+        assert_eq!(chip.cores[0].name, "core0");
+        assert_eq!(chip.memory_map.len(), 2);
+        assert_eq!(chip.memory_map[1].address_range(), 0x2000_0000..0x2002_0000);
+    }
+
+    /// SFDP tables with a basic flash parameter table of 9 DWORDs at 0x30, and a vendor table.
+    fn sfdp_tables(density: u32) -> Vec<u8> {
+        let mut data = vec![0xFF; 0x30];
+        data[0..8].copy_from_slice(&[b'S', b'F', b'D', b'P', 0x06, 0x01, 0x01, 0xFF]);
+        // A vendor specific table, which is skipped.
+        data[8..16].copy_from_slice(&[0x84, 0x00, 0x01, 0x02, 0x60, 0x00, 0x00, 0xEF]);
+        data[16..24].copy_from_slice(&[0x00, 0x06, 0x01, 0x09, 0x30, 0x00, 0x00, 0xFF]);
+
+        let mut table = [0u32; 9];
+        table[1] = density;
+        // 4 KiB with opcode 0x20, 32 KiB with 0x52, 64 KiB with 0xD8, and no fourth erase type.
+        table[7] = 0x520F_200C;
+        table[8] = 0x0000_D810;
+        data.extend(table.iter().flat_map(|dword| dword.to_le_bytes()));
+
+        data
+    }
+
+    #[test]
+    fn sfdp_basic_flash_parameters() {
+        // 128 Mbit
+        let flash = SfdpFlash::parse(0x9000_0000, &sfdp_tables(0x07FF_FFFF)).unwrap();
+        assert_eq!(
+            flash,
+            SfdpFlash {
+                base_address: 0x9000_0000,
+                size: 16 * 1024 * 1024,
+                erase_sizes: vec![4 * 1024, 32 * 1024, 64 * 1024],
+            }
+        );
+
+        // 2^34 bits
+        let flash = SfdpFlash::parse(0x9000_0000, &sfdp_tables(0x8000_0022)).unwrap();
+        assert_eq!(flash.size, 2 * 1024 * 1024 * 1024);
+    }
+
+    #[test]
+    fn invalid_sfdp_tables() {
+        assert!(SfdpFlash::parse(0, b"SFD").is_err());
+        assert!(SfdpFlash::parse(0, b"SFDP\x06\x01\x01").is_err());
+
+        // The density doesn't fit into 64 bits.
+        assert!(SfdpFlash::parse(0, &sfdp_tables(0x8000_0040)).is_err());
+
+        // The basic flash parameter table is truncated.
+        let mut data = sfdp_tables(0x07FF_FFFF);
+        data.truncate(0x40);
+        assert!(SfdpFlash::parse(0, &data).is_err());
+
+        // There is only the vendor table.
+        let mut data = sfdp_tables(0x07FF_FFFF);
+        data[6] = 0;
+        assert!(SfdpFlash::parse(0, &data).is_err());
+    }
+
+    #[test]
+    fn core_types() {
+        assert_eq!(
+            core_type_from_cpu_name("Cortex-M0+"),
+            Some(CoreType::Armv6m)
+        );
+        assert_eq!(
+            core_type_from_cpu_name("Cortex-M33"),
+            Some(CoreType::Armv8m)
+        );
+        assert_eq!(core_type_from_cpu_name("0xc08"), None);
+    }
+}
@@ -0,0 +1,360 @@
+//! Additional locations from which target descriptions are loaded at runtime.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use probe_rs_target::{ChipFamily, TargetDescriptionSource};
+
+use super::RegistryError;
+
+/// The environment variable with additional directories of target description files, in the
+/// format of the `PATH` variable of the platform.
+pub const TARGETS_PATH_ENV: &str = "PROBE_RS_TARGETS_PATH";
+
+/// The environment variable with additional URLs of target description files or indexes,
+/// separated by whitespace.
+pub const TARGETS_URL_ENV: &str = "PROBE_RS_TARGETS_URL";
+
+/// How long a downloaded target description is used before it is downloaded again.
+pub(super) const CACHE_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A location from which the [`Registry`](super::Registry) loads additional target descriptions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchPath {
+    /// A directory containing target description YAML files, or a single target description file.
+    Local(PathBuf),
+    /// The URL of a target description file, or of an index.
+    ///
+    /// An index is a YAML list of the URLs of target description files. Relative URLs are
+    /// resolved against the URL of the index.
+    Remote(String),
+}
+
+impl From<&str> for SearchPath {
+    fn from(value: &str) -> Self {
+        if value.starts_with("http://") || value.starts_with("https://") {
+            SearchPath::Remote(value.to_string())
+        } else {
+            SearchPath::Local(PathBuf::from(value))
+        }
+    }
+}
+
+impl From<String> for SearchPath {
+    fn from(value: String) -> Self {
+        SearchPath::from(value.as_str())
+    }
+}
+
+impl From<PathBuf> for SearchPath {
+    fn from(value: PathBuf) -> Self {
+        SearchPath::Local(value)
+    }
+}
+
+impl From<&Path> for SearchPath {
+    fn from(value: &Path) -> Self {
+        SearchPath::Local(value.to_path_buf())
+    }
+}
+
+/// Returns the search paths configured in the environment.
+pub(crate) fn search_paths_from_env() -> Vec<SearchPath> {
+    let mut search_paths = vec![];
+
+    if let Some(paths) = std::env::var_os(TARGETS_PATH_ENV) {
+        search_paths.extend(
+            std::env::split_paths(&paths)
+                .filter(|path| !path.as_os_str().is_empty())
+                .map(SearchPath::Local),
+        );
+    }
+
+    if let Ok(urls) = std::env::var(TARGETS_URL_ENV) {
+        search_paths.extend(
+            urls.split_whitespace()
+                .map(|url| SearchPath::Remote(url.to_string())),
+        );
+    }
+
+    search_paths
+}
+
+/// Loads the target families from all search paths.
+///
+/// Search paths which can not be loaded are skipped with a warning, so that a single broken
+/// location does not make the built-in targets unusable.
+pub(crate) fn load(search_paths: &[SearchPath], cache_dir: Option<&Path>) -> Vec<ChipFamily> {
+    let mut families = vec![];
+
+    for search_path in search_paths {
+        let result = match search_path {
+            SearchPath::Local(path) => load_local(path, &mut families),
+            SearchPath::Remote(url) => load_remote(url, cache_dir, &mut families),
+        };
+
+        if let Err(error) = result {
+            tracing::warn!("Failed to load targets from {search_path:?}: {error}");
+        }
+    }
+
+    families
+}
+
+fn load_local(path: &Path, families: &mut Vec<ChipFamily>) -> Result<(), RegistryError> {
+    if path.is_file() {
+        return add_family(
+            &fs::read_to_string(path)?,
+            &path.display().to_string(),
+            families,
+        );
+    }
+
+    let mut files = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    files.retain(|file| file.extension().is_some_and(|e| e == "yaml" || e == "yml"));
+    files.sort();
+
+    for file in files {
+        let origin = file.display().to_string();
+        if let Err(error) = fs::read_to_string(&file)
+            .map_err(RegistryError::from)
+            .and_then(|yaml| add_family(&yaml, &origin, families))
+        {
+            tracing::warn!("Skipping target description {origin}: {error}");
+        }
+    }
+
+    Ok(())
+}
+
+fn load_remote(
+    url: &str,
+    cache_dir: Option<&Path>,
+    families: &mut Vec<ChipFamily>,
+) -> Result<(), RegistryError> {
+    let contents = fetch_cached(url, cache_dir)?;
+
+    // An index is a list of URLs, a target description is a map.
+    let Ok(entries) = serde_yaml::from_str::<Vec<String>>(&contents) else {
+        return add_family(&contents, url, families);
+    };
+
+    for entry in entries {
+        let entry_url = resolve_url(url, &entry);
+        if let Err(error) = fetch_cached(&entry_url, cache_dir)
+            .and_then(|yaml| add_family(&yaml, &entry_url, families))
+        {
+            tracing::warn!("Skipping target description {entry_url}: {error}");
+        }
+    }
+
+    Ok(())
+}
+
+fn add_family(
+    yaml: &str,
+    origin: &str,
+    families: &mut Vec<ChipFamily>,
+) -> Result<(), RegistryError> {
+    let mut family: ChipFamily = serde_yaml::from_str(yaml)?;
+    family.source = TargetDescriptionSource::External;
+
+    // SVD files are referenced relative to the target description.
+    for chip in &mut family.variants {
+        if let Some(svd) = &mut chip.svd {
+            *svd = resolve_location(origin, svd);
+        }
+    }
+
+    super::registry::validate_family(&family).map_err(|error| {
+        RegistryError::InvalidChipFamilyDefinition(Box::new(family.clone()), error)
+    })?;
+
+    tracing::debug!("Loaded target family {} from {origin}", family.name);
+
+    // A later search path overrides the families of an earlier one.
+    families.retain(|existing| !existing.name.eq_ignore_ascii_case(&family.name));
+    families.push(family);
+
+    Ok(())
+}
+
+/// Resolves `entry` against the location of the file `base`, which is a path or a URL.
+fn resolve_location(base: &str, entry: &str) -> String {
+    if base.starts_with("http://") || base.starts_with("https://") {
+        return resolve_url(base, entry);
+    }
+
+    if entry.starts_with("http://")
+        || entry.starts_with("https://")
+        || Path::new(entry).is_absolute()
+    {
+        return entry.to_string();
+    }
+
+    Path::new(base)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(entry)
+        .display()
+        .to_string()
+}
+
+fn resolve_url(base: &str, entry: &str) -> String {
+    if entry.starts_with("http://") || entry.starts_with("https://") {
+        return entry.to_string();
+    }
+
+    let directory = base
+        .rsplit_once('/')
+        .map_or(base, |(directory, _)| directory);
+    format!("{directory}/{}", entry.trim_start_matches("./"))
+}
+
+/// Returns the contents of `url`, from the cache if it was downloaded recently.
+///
+/// If the download fails, an outdated cached copy is used instead.
+fn fetch_cached(url: &str, cache_dir: Option<&Path>) -> Result<String, RegistryError> {
+    let cache_file = cache_dir.map(|directory| {
+        let mut hasher = DefaultHasher::new();
+        url.hash(&mut hasher);
+        directory.join(format!("{:016x}.yaml", hasher.finish()))
+    });
+
+    if let Some(cache_file) = &cache_file {
+        let age = fs::metadata(cache_file)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+
+        if age.is_some_and(|age| age < CACHE_LIFETIME) {
+            tracing::debug!("Using cached copy of {url}");
+            return Ok(fs::read_to_string(cache_file)?);
+        }
+    }
+
+    match fetch(url) {
+        Ok(contents) => {
+            if let Some(cache_file) = &cache_file {
+                let stored = cache_file
+                    .parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|_| fs::write(cache_file, &contents));
+                if let Err(error) = stored {
+                    tracing::warn!("Failed to cache {url}: {error}");
+                }
+            }
+
+            Ok(contents)
+        }
+        Err(error) => match cache_file.and_then(|file| fs::read_to_string(file).ok()) {
+            Some(contents) => {
+                tracing::warn!("Failed to download {url}, using an outdated copy: {error}");
+                Ok(contents)
+            }
+            None => Err(error),
+        },
+    }
+}
+
+#[cfg(feature = "remote-targets")]
+pub(super) fn fetch(url: &str) -> Result<String, RegistryError> {
+    // The blocking client must not run on the thread of an async runtime.
+    std::thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                reqwest::blocking::get(url)
+                    .and_then(|response| response.error_for_status())
+                    .and_then(|response| response.text())
+            })
+            .join()
+            .expect("The download thread panicked")
+    })
+    .map_err(|error| RegistryError::Download(url.to_string(), error.to_string()))
+}
+
+#[cfg(not(feature = "remote-targets"))]
+pub(super) fn fetch(url: &str) -> Result<String, RegistryError> {
+    Err(RegistryError::Download(
+        url.to_string(),
+        "probe-rs was built without the `remote-targets` feature".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_path_kind() {
+        assert_eq!(
+            SearchPath::from("https://example.com/targets/index.yaml"),
+            SearchPath::Remote("https://example.com/targets/index.yaml".to_string())
+        );
+        assert_eq!(
+            SearchPath::from("/home/user/targets"),
+            SearchPath::Local(PathBuf::from("/home/user/targets"))
+        );
+    }
+
+    #[test]
+    fn index_entries_are_resolved() {
+        let index = "https://example.com/targets/index.yaml";
+
+        assert_eq!(
+            resolve_url(index, "./nRF52.yaml"),
+            "https://example.com/targets/nRF52.yaml"
+        );
+        assert_eq!(
+            resolve_url(index, "https://mirror.example.com/STM32.yaml"),
+            "https://mirror.example.com/STM32.yaml"
+        );
+    }
+
+    #[test]
+    fn svd_locations_are_resolved() {
+        assert_eq!(
+            resolve_location("https://example.com/targets/nRF52.yaml", "svd/nRF52.svd"),
+            "https://example.com/targets/svd/nRF52.svd"
+        );
+        assert_eq!(
+            PathBuf::from(resolve_location("targets/nRF52.yaml", "nRF52.svd")),
+            Path::new("targets").join("nRF52.svd")
+        );
+        assert_eq!(
+            resolve_location("targets/nRF52.yaml", "https://example.com/nRF52.svd"),
+            "https://example.com/nRF52.svd"
+        );
+    }
+
+    #[test]
+    fn cached_copies_are_used() {
+        let cache_dir =
+            std::env::temp_dir().join(format!("probe-rs-target-cache-{}", std::process::id()));
+        let url = "https://example.invalid/targets.yaml";
+
+        // Without a cached copy, the download error is reported.
+        assert!(fetch_cached(url, Some(&cache_dir)).is_err());
+
+        let mut hasher = DefaultHasher::new();
+        url.hash(&mut hasher);
+        fs::create_dir_all(&cache_dir).unwrap();
+        fs::write(
+            cache_dir.join(format!("{:016x}.yaml", hasher.finish())),
+            "name: Test",
+        )
+        .unwrap();
+
+        let cached = fetch_cached(url, Some(&cache_dir));
+        fs::remove_dir_all(&cache_dir).unwrap();
+
+        assert_eq!(cached.unwrap(), "name: Test");
+    }
+}
@@ -5,7 +5,7 @@ use crate::{
         arm::{
             ApV2Address, FullyQualifiedApAddress,
             dp::DpAddress,
-            sequences::{ArmDebugSequence, DefaultArmSequence},
+            sequences::{ArmDebugSequence, DefaultArmSequence, pack::PackSequence},
         },
         riscv::sequences::{DefaultRiscvSequence, RiscvDebugSequence},
         xtensa::sequences::{DefaultXtensaSequence, XtensaDebugSequence},
@@ -13,7 +13,8 @@ use crate::{
     rtt::ScanRegion,
 };
 use probe_rs_target::{
-    Architecture, Chip, ChipFamily, Jtag, MemoryAccess, MemoryRange as _, NvmRegion,
+    AddressAlias, Architecture, Chip, ChipFamily, DebugFreezeBit, Jtag, MemoryAccess,
+    MemoryRange as _, NvmRegion, OptionBytes, ResetStrategy, Watchdog,
 };
 use std::sync::Arc;
 
@@ -45,6 +46,20 @@ pub struct Target {
     pub jtag: Option<Jtag>,
     /// The default executable format for the target.
     pub default_format: Option<String>,
+    /// The option bytes of the target, if they can be modified.
+    pub option_bytes: Option<OptionBytes>,
+    /// Address ranges that alias other memory.
+    pub address_aliases: Vec<AddressAlias>,
+    /// The UF2 family IDs of the target.
+    pub uf2_family_ids: Vec<u32>,
+    /// The path or URL of the SVD file describing the peripherals of the target.
+    pub svd: Option<String>,
+    /// The bits that stop peripherals while a core is halted.
+    pub debug_freeze: Vec<DebugFreezeBit>,
+    /// The ways to reset the target, in the order they are tried.
+    pub reset_strategies: Vec<ResetStrategy>,
+    /// The watchdogs that have to be serviced while the target is flashed.
+    pub watchdogs: Vec<Watchdog>,
 }
 
 impl std::fmt::Debug for Target {
@@ -116,7 +131,12 @@ impl Target {
             // Default to the architecture of the first core, which is okay if
             // there is no mixed architectures.
             match chip.cores[0].core_type.architecture() {
-                Architecture::Arm => DebugSequence::Arm(DefaultArmSequence::create()),
+                Architecture::Arm => match &chip.debug_description {
+                    Some(description) if !description.sequences.is_empty() => {
+                        DebugSequence::Arm(PackSequence::create(description.clone()))
+                    }
+                    _ => DebugSequence::Arm(DefaultArmSequence::create()),
+                },
                 Architecture::Riscv => DebugSequence::Riscv(DefaultRiscvSequence::create()),
                 Architecture::Xtensa => DebugSequence::Xtensa(DefaultXtensaSequence::create()),
             }
@@ -139,6 +159,21 @@ impl Target {
             rtt_scan_regions,
             jtag: chip.jtag.clone(),
             default_format: chip.default_binary_format.clone(),
+            option_bytes: chip.option_bytes.clone(),
+            address_aliases: chip.address_aliases.clone(),
+            uf2_family_ids: chip.uf2_family_ids.clone(),
+            svd: chip.svd.clone(),
+            debug_freeze: if chip.debug_freeze.is_empty() {
+                family.debug_freeze.clone()
+            } else {
+                chip.debug_freeze.clone()
+            },
+            reset_strategies: chip.reset_strategies.clone(),
+            watchdogs: if chip.watchdogs.is_empty() {
+                family.watchdogs.clone()
+            } else {
+                chip.watchdogs.clone()
+            },
         }
     }
 
@@ -211,7 +246,7 @@ pub enum TargetSelector {
     /// targets.
     Unspecified(String),
     /// Directly specify a target.
-    Specified(Target),
+    Specified(Box<Target>),
     /// Try to automatically identify the target,
     /// by reading identifying information from
     /// the probe and / or target.
@@ -253,7 +288,7 @@ impl From<()> for TargetSelector {
 
 impl From<Target> for TargetSelector {
     fn from(target: Target) -> Self {
-        TargetSelector::Specified(target)
+        TargetSelector::Specified(Box::new(target))
     }
 }
 
@@ -1,27 +1,34 @@
 use crate::{
-    CoreType, Endian, InstructionSet, MemoryInterface, Target,
+    CancellationToken, CoreType, Endian, InstructionSet, MemoryInterface, MemoryMapPolicy, Target,
     architecture::{
-        arm::sequences::ArmDebugSequence, riscv::sequences::RiscvDebugSequence,
+        arm::{performance_counters::PerformanceCounters, sequences::ArmDebugSequence},
+        riscv::sequences::RiscvDebugSequence,
         xtensa::sequences::XtensaDebugSequence,
     },
-    config::DebugSequence,
+    config::{DebugSequence, ResetStrategy},
     error::Error,
-    memory::CoreMemoryInterface,
 };
 pub use probe_rs_target::{Architecture, CoreAccessOptions};
 use probe_rs_target::{
     ArmCoreAccessOptions, MemoryRegion, RiscvCoreAccessOptions, XtensaCoreAccessOptions,
 };
-use std::{sync::Arc, time::Duration};
+use std::{
+    ops::Range,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
 
 pub mod core_state;
 pub mod core_status;
+pub mod debug_freeze;
 pub mod dump;
+pub(crate) mod fill;
 pub mod memory_mapped_registers;
 pub mod registers;
 
 pub use core_state::*;
 pub use core_status::*;
+pub use debug_freeze::FreezeConfig;
 pub use memory_mapped_registers::MemoryMappedRegister;
 pub use registers::*;
 
@@ -32,6 +39,18 @@ pub struct CoreInformation {
     pub pc: u64,
 }
 
+/// The point in time at which a core was observed to be halted.
+///
+/// The host time allows ordering halts of different cores, the cycle count gives the
+/// time on the target, as far as the core provides a cycle counter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HaltTimestamp {
+    /// The host time at which the halt was observed.
+    pub host: SystemTime,
+    /// The cycle counter of the core, if available.
+    pub cycle_count: Option<u64>,
+}
+
 /// A generic interface to control a MCU core.
 pub trait CoreInterface: MemoryInterface {
     /// Wait until the core is halted. If the core does not halt on its own,
@@ -64,6 +83,17 @@ pub trait CoreInterface: MemoryInterface {
     /// [`reset`]: Core::reset
     fn reset_and_halt(&mut self, timeout: Duration) -> Result<CoreInformation, Error>;
 
+    /// Reset the core using `strategy`, and then continue to execute instructions.
+    ///
+    /// Cores without support for other strategies are only reset with
+    /// [`ResetStrategy::Sequence`], which is the same as [`reset`](CoreInterface::reset).
+    fn reset_with(&mut self, strategy: ResetStrategy) -> Result<(), Error> {
+        match strategy {
+            ResetStrategy::Sequence => self.reset(),
+            _ => Err(Error::NotImplemented("reset strategies")),
+        }
+    }
+
     /// Steps one instruction and then enters halted state again.
     fn step(&mut self) -> Result<CoreInformation, Error>;
 
@@ -80,6 +110,37 @@ pub trait CoreInterface: MemoryInterface {
         value: registers::RegisterValue,
     ) -> Result<(), Error>;
 
+    /// Read the values of multiple core registers, in the order of `addresses`.
+    ///
+    /// Cores whose debug interface can queue register transfers override this, to read all
+    /// registers with fewer round trips to the probe. The default implementation reads them one by
+    /// one.
+    fn read_core_regs(
+        &mut self,
+        addresses: &[registers::RegisterId],
+    ) -> Result<Vec<registers::RegisterValue>, Error> {
+        addresses
+            .iter()
+            .map(|&address| self.read_core_reg(address))
+            .collect()
+    }
+
+    /// Write the values of multiple core registers, in the order of `registers`.
+    ///
+    /// Cores whose debug interface can queue register transfers override this, to write all
+    /// registers with fewer round trips to the probe. The default implementation writes them one
+    /// by one.
+    fn write_core_regs(
+        &mut self,
+        registers: &[(registers::RegisterId, registers::RegisterValue)],
+    ) -> Result<(), Error> {
+        for &(address, value) in registers {
+            self.write_core_reg(address, value)?;
+        }
+
+        Ok(())
+    }
+
     /// Returns all the available breakpoint units of the core.
     fn available_breakpoint_units(&mut self) -> Result<u32, Error>;
 
@@ -121,6 +182,50 @@ pub trait CoreInterface: MemoryInterface {
         Ok(())
     }
 
+    /// Set a software breakpoint by replacing the instruction at `address` with a breakpoint
+    /// instruction.
+    fn set_sw_breakpoint(&mut self, _address: u64) -> Result<(), Error> {
+        Err(Error::NotImplemented("software breakpoints"))
+    }
+
+    /// Remove the software breakpoint at `address`, restoring the original instruction.
+    fn clear_sw_breakpoint(&mut self, _address: u64) -> Result<(), Error> {
+        Err(Error::NotImplemented("software breakpoints"))
+    }
+
+    /// Returns the addresses of all software breakpoints set by probe-rs.
+    fn sw_breakpoints(&self) -> Vec<u64> {
+        Vec::new()
+    }
+
+    /// Returns the number of data watchpoint units of the core.
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        Err(Error::NotImplemented("watchpoints"))
+    }
+
+    /// Returns the address watched by each watchpoint unit. A value of `None` indicates that the
+    /// unit is unused.
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<u64>>, Error> {
+        Err(Error::NotImplemented("watchpoints"))
+    }
+
+    /// Configures watchpoint unit `unit_index` to halt the core on `access` to the `length`
+    /// bytes at `addr`.
+    fn set_hw_watchpoint(
+        &mut self,
+        _unit_index: usize,
+        _addr: u64,
+        _length: usize,
+        _access: WatchpointAccess,
+    ) -> Result<(), Error> {
+        Err(Error::NotImplemented("watchpoints"))
+    }
+
+    /// Clears the watchpoint configured in unit `unit_index`.
+    fn clear_hw_watchpoint(&mut self, _unit_index: usize) -> Result<(), Error> {
+        Err(Error::NotImplemented("watchpoints"))
+    }
+
     /// Get the `Architecture` of the Core.
     fn architecture(&self) -> Architecture;
 
@@ -169,6 +274,13 @@ pub trait CoreInterface: MemoryInterface {
         Ok(())
     }
 
+    /// Prepares the core to be resumed by a request which resumes multiple cores at once.
+    ///
+    /// This does everything [`CoreInterface::run`] does, except for the actual resume request.
+    fn prepare_group_resume(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
     /// Enables vector catching for the given `condition`
     fn enable_vector_catch(&mut self, _condition: VectorCatchCondition) -> Result<(), Error> {
         Err(Error::NotImplemented("vector catch"))
@@ -179,10 +291,46 @@ pub trait CoreInterface: MemoryInterface {
         Err(Error::NotImplemented("vector catch"))
     }
 
+    /// Configures a trigger which halts the core when the condition `kind` is met.
+    fn set_trigger(&mut self, _kind: TriggerKind) -> Result<(), Error> {
+        Err(Error::NotImplemented("triggers"))
+    }
+
+    /// Removes the triggers of the same kind as `kind`.
+    fn clear_trigger(&mut self, _kind: TriggerKind) -> Result<(), Error> {
+        Err(Error::NotImplemented("triggers"))
+    }
+
     /// Check if the integer size is 64-bit
     fn is_64_bit(&self) -> bool {
         false
     }
+
+    /// Read the cycle counter of the core.
+    ///
+    /// Returns `None` if the core has no cycle counter, or if it is not enabled.
+    fn cycle_count(&mut self) -> Result<Option<u64>, Error> {
+        Ok(None)
+    }
+
+    /// Read the SWO data received by the probe since the last read, without waiting.
+    ///
+    /// Returns `None` if the core can't emit SWO data.
+    fn read_swo(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        Ok(None)
+    }
+
+    /// Read the application trace data sent by the core since the last read, without waiting.
+    ///
+    /// Returns `None` if the core has no application trace.
+    fn read_apptrace(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        Ok(None)
+    }
+
+    /// Tell the core that the host stopped reading the application trace.
+    fn stop_apptrace(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 /// Generic core handle representing a physical core on an MCU.
@@ -195,19 +343,124 @@ pub struct Core<'probe> {
     id: usize,
     name: &'probe str,
     target: &'probe Target,
+    memory_map_policy: MemoryMapPolicy,
+    hw_breakpoint_record: &'probe mut Vec<Option<u64>>,
 
     inner: Box<dyn CoreInterface + 'probe>,
 }
 
-impl CoreMemoryInterface for Core<'_> {
-    type ErrorType = Error;
+// Accesses are checked against the memory map before they are passed on to the core.
+impl MemoryInterface for Core<'_> {
+    fn supports_native_64bit_access(&mut self) -> bool {
+        self.inner.supports_native_64bit_access()
+    }
+
+    fn read_word_64(&mut self, address: u64) -> Result<u64, Error> {
+        self.check_memory_map(address, 8)?;
+        self.inner.read_word_64(address)
+    }
+
+    fn read_word_32(&mut self, address: u64) -> Result<u32, Error> {
+        self.check_memory_map(address, 4)?;
+        self.inner.read_word_32(address)
+    }
+
+    fn read_word_16(&mut self, address: u64) -> Result<u16, Error> {
+        self.check_memory_map(address, 2)?;
+        self.inner.read_word_16(address)
+    }
+
+    fn read_word_8(&mut self, address: u64) -> Result<u8, Error> {
+        self.check_memory_map(address, 1)?;
+        self.inner.read_word_8(address)
+    }
+
+    fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), Error> {
+        self.check_memory_map(address, data.len() * 8)?;
+        self.inner.read_64(address, data)
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), Error> {
+        self.check_memory_map(address, data.len() * 4)?;
+        self.inner.read_32(address, data)
+    }
+
+    fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), Error> {
+        self.check_memory_map(address, data.len() * 2)?;
+        self.inner.read_16(address, data)
+    }
+
+    fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), Error> {
+        self.check_memory_map(address, data.len())?;
+        self.inner.read_8(address, data)
+    }
+
+    fn read(&mut self, address: u64, data: &mut [u8]) -> Result<(), Error> {
+        self.check_memory_map(address, data.len())?;
+        self.inner.read(address, data)
+    }
+
+    fn write_word_64(&mut self, address: u64, data: u64) -> Result<(), Error> {
+        self.check_memory_map(address, 8)?;
+        self.inner.write_word_64(address, data)
+    }
 
-    fn memory(&self) -> &dyn MemoryInterface<Self::ErrorType> {
-        self.inner.as_ref()
+    fn write_word_32(&mut self, address: u64, data: u32) -> Result<(), Error> {
+        self.check_memory_map(address, 4)?;
+        self.inner.write_word_32(address, data)
     }
 
-    fn memory_mut(&mut self) -> &mut dyn MemoryInterface<Self::ErrorType> {
-        self.inner.as_mut()
+    fn write_word_16(&mut self, address: u64, data: u16) -> Result<(), Error> {
+        self.check_memory_map(address, 2)?;
+        self.inner.write_word_16(address, data)
+    }
+
+    fn write_word_8(&mut self, address: u64, data: u8) -> Result<(), Error> {
+        self.check_memory_map(address, 1)?;
+        self.inner.write_word_8(address, data)
+    }
+
+    fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), Error> {
+        self.check_memory_map(address, data.len() * 8)?;
+        self.inner.write_64(address, data)
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), Error> {
+        self.check_memory_map(address, data.len() * 4)?;
+        self.inner.write_32(address, data)
+    }
+
+    fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), Error> {
+        self.check_memory_map(address, data.len() * 2)?;
+        self.inner.write_16(address, data)
+    }
+
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), Error> {
+        self.check_memory_map(address, data.len())?;
+        self.inner.write_8(address, data)
+    }
+
+    fn write(&mut self, address: u64, data: &[u8]) -> Result<(), Error> {
+        self.check_memory_map(address, data.len())?;
+        self.inner.write(address, data)
+    }
+
+    fn fill_32(&mut self, address: u64, pattern: u32, count: usize) -> Result<(), Error> {
+        self.check_memory_map(address, count * 4)?;
+        if count >= fill::STUB_FILL_MIN_WORDS
+            && fill::fill_32_with_stub(self, address, pattern, count)?
+        {
+            return Ok(());
+        }
+        self.inner.fill_32(address, pattern, count)
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, Error> {
+        self.inner.supports_8bit_transfers()
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
     }
 }
 
@@ -222,16 +475,40 @@ impl<'probe> Core<'probe> {
         id: usize,
         name: &'probe str,
         target: &'probe Target,
+        hw_breakpoint_record: &'probe mut Vec<Option<u64>>,
         core: impl CoreInterface + 'probe,
     ) -> Core<'probe> {
         Self {
             id,
             name,
             target,
+            memory_map_policy: MemoryMapPolicy::default(),
+            hw_breakpoint_record,
             inner: Box::new(core),
         }
     }
 
+    /// Sets how memory accesses outside the memory map of the target are handled.
+    pub fn set_memory_map_policy(&mut self, policy: MemoryMapPolicy) {
+        self.memory_map_policy = policy;
+    }
+
+    /// Checks an access of `len` bytes at `address` against the memory regions of this core.
+    fn check_memory_map(&self, address: u64, len: usize) -> Result<(), Error> {
+        if self.memory_map_policy == MemoryMapPolicy::Allow {
+            return Ok(());
+        }
+
+        let ranges = self
+            .target
+            .memory_map
+            .iter()
+            .filter(|r| r.cores().iter().any(|m| m == self.name))
+            .map(MemoryRegion::address_range)
+            .chain(system_address_space(self.core_type()));
+        check_memory_access(self.memory_map_policy, ranges, address, len)
+    }
+
     /// Returns the memory regions associated with this core.
     pub fn memory_regions(&self) -> impl Iterator<Item = &MemoryRegion> {
         self.target
@@ -240,6 +517,14 @@ impl<'probe> Core<'probe> {
             .filter(|r| r.cores().iter().any(|m| m == self.name))
     }
 
+    /// Stops or releases the peripherals selected by `config` while this core is halted.
+    ///
+    /// The peripherals are described by the debug freeze bits of the target description. This
+    /// does nothing if the target description has none.
+    pub fn freeze_peripherals(&mut self, config: FreezeConfig) -> Result<(), Error> {
+        config.apply(self.inner.as_mut(), &self.target.debug_freeze)
+    }
+
     /// Returns the target descriptor of the current `Session`.
     pub fn target(&self) -> &Target {
         self.target
@@ -256,6 +541,7 @@ impl<'probe> Core<'probe> {
             id,
             core_state: CoreState::new(ResolvedCoreOptions::new(target, options)),
             specific_state: SpecificCoreState::from_core_type(core_type),
+            hw_breakpoints: Vec::new(),
         }
     }
 
@@ -271,6 +557,28 @@ impl<'probe> Core<'probe> {
         self.inner.wait_for_core_halted(timeout)
     }
 
+    /// Wait until the core is halted, like [`Core::wait_for_core_halted`], but stop with
+    /// [`Error::Cancelled`] once `token` is cancelled.
+    #[tracing::instrument(skip(self, token))]
+    pub fn wait_for_core_halted_cancellable(
+        &mut self,
+        timeout: Duration,
+        token: &CancellationToken,
+    ) -> Result<(), Error> {
+        let start = Instant::now();
+        while !self.inner.core_halted()? {
+            token.error_if_cancelled()?;
+            if start.elapsed() >= timeout {
+                return Err(Error::Timeout);
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        // The core is halted, this returns right away and updates the state of the core.
+        self.inner
+            .wait_for_core_halted(timeout.saturating_sub(start.elapsed()))
+    }
+
     /// Check if the core is halted. If the core does not halt on its own,
     /// a [`DebugProbeError::Timeout`](crate::probe::DebugProbeError::Timeout) error will be returned.
     pub fn core_halted(&mut self) -> Result<bool, Error> {
@@ -293,10 +601,25 @@ impl<'probe> Core<'probe> {
     /// Reset the core, and then continue to execute instructions. If the core
     /// should be halted after reset, use the [`reset_and_halt`] function.
     ///
+    /// If the target lists [reset strategies](Target::reset_strategies), they are tried in order
+    /// until one succeeds.
+    ///
     /// [`reset_and_halt`]: Core::reset_and_halt
     #[tracing::instrument(skip(self))]
     pub fn reset(&mut self) -> Result<(), Error> {
-        self.inner.reset()
+        if self.target.reset_strategies.is_empty() {
+            return self.inner.reset();
+        }
+
+        self.reset_with_target_strategies()
+    }
+
+    /// Reset the core using `strategy`, and then continue to execute instructions.
+    ///
+    /// Unlike [`reset`](Core::reset), this does not fall back to other strategies.
+    #[tracing::instrument(skip(self))]
+    pub fn reset_with(&mut self, strategy: ResetStrategy) -> Result<(), Error> {
+        self.inner.reset_with(strategy)
     }
 
     /// Reset the core, and then immediately halt. To continue execution after
@@ -305,7 +628,33 @@ impl<'probe> Core<'probe> {
     /// [`reset`]: Core::reset
     #[tracing::instrument(skip(self))]
     pub fn reset_and_halt(&mut self, timeout: Duration) -> Result<CoreInformation, Error> {
-        self.inner.reset_and_halt(timeout)
+        if self.target.reset_strategies.is_empty() {
+            return self.inner.reset_and_halt(timeout);
+        }
+
+        self.inner.reset_catch_set()?;
+        let result = self
+            .reset_with_target_strategies()
+            .and_then(|()| self.inner.wait_for_core_halted(timeout));
+        let cleared = self.inner.reset_catch_clear();
+        if let Err(error) = result {
+            // The reset error is the relevant one, don't hide it behind the cleanup.
+            if let Err(clear_error) = cleared {
+                tracing::warn!("Failed to clear the reset catch: {clear_error}");
+            }
+            return Err(error);
+        }
+        cleared?;
+
+        self.inner.halt(timeout)
+    }
+
+    /// Tries the reset strategies of the target in order, and returns the error of the last one
+    /// if none of them succeeds.
+    fn reset_with_target_strategies(&mut self) -> Result<(), Error> {
+        reset_with_strategies(&self.target.reset_strategies, |strategy| {
+            self.inner.reset_with(strategy)
+        })
     }
 
     /// Steps one instruction and then enters halted state again.
@@ -371,6 +720,28 @@ impl<'probe> Core<'probe> {
         self.inner.write_core_reg(address, value.into())
     }
 
+    /// Read the values of multiple core registers, in the order of `addresses`.
+    ///
+    /// This needs fewer round trips to the probe than reading the registers one by one with
+    /// [`read_core_reg`](Self::read_core_reg) on cores that support it, e.g. when all registers
+    /// are refreshed after the core halted.
+    #[tracing::instrument(skip(self))]
+    pub fn read_core_regs(
+        &mut self,
+        addresses: &[registers::RegisterId],
+    ) -> Result<Vec<registers::RegisterValue>, Error> {
+        self.inner.read_core_regs(addresses)
+    }
+
+    /// Write the values of multiple core registers, in the order of `registers`.
+    #[tracing::instrument(skip(self))]
+    pub fn write_core_regs(
+        &mut self,
+        registers: &[(registers::RegisterId, registers::RegisterValue)],
+    ) -> Result<(), Error> {
+        self.inner.write_core_regs(registers)
+    }
+
     /// Returns all the available breakpoint units of the core.
     pub fn available_breakpoint_units(&mut self) -> Result<u32, Error> {
         self.inner.available_breakpoint_units()
@@ -443,7 +814,10 @@ impl<'probe> Core<'probe> {
 
         // Actually set the breakpoint. Even if it has been set, set it again so it will be active.
         self.inner
-            .set_hw_breakpoint(breakpoint_comparator_index, address)
+            .set_hw_breakpoint(breakpoint_comparator_index, address)?;
+        self.record_hw_breakpoint(breakpoint_comparator_index, Some(address));
+
+        Ok(())
     }
 
     /// Set a hardware breakpoint
@@ -464,7 +838,31 @@ impl<'probe> Core<'probe> {
             addr
         );
 
-        self.inner.set_hw_breakpoint(unit_index, addr)
+        self.inner.set_hw_breakpoint(unit_index, addr)?;
+        self.record_hw_breakpoint(unit_index, Some(addr));
+
+        Ok(())
+    }
+
+    /// Remembers the breakpoint of a unit, so it can be restored after a power-down.
+    fn record_hw_breakpoint(&mut self, unit_index: usize, address: Option<u64>) {
+        if self.hw_breakpoint_record.len() <= unit_index {
+            self.hw_breakpoint_record.resize(unit_index + 1, None);
+        }
+        self.hw_breakpoint_record[unit_index] = address;
+    }
+
+    /// Sets the hardware breakpoints that were set through probe-rs again, e.g. after the
+    /// breakpoint units were reset because the target powered down.
+    pub(crate) fn restore_hw_breakpoints(&mut self) -> Result<(), Error> {
+        let breakpoints = self.hw_breakpoint_record.clone();
+        for (unit_index, address) in breakpoints.into_iter().enumerate() {
+            if let Some(address) = address {
+                self.set_hw_breakpoint_unit(unit_index, address)?;
+            }
+        }
+
+        Ok(())
     }
 
     /// Set a hardware breakpoint
@@ -487,6 +885,7 @@ impl<'probe> Core<'probe> {
         match bp_position {
             Some(bp_position) => {
                 self.inner.clear_hw_breakpoint(bp_position)?;
+                self.record_hw_breakpoint(bp_position, None);
                 Ok(())
             }
             None => Err(Error::Other(format!(
@@ -508,6 +907,141 @@ impl<'probe> Core<'probe> {
         Ok(())
     }
 
+    /// Set a software breakpoint
+    ///
+    /// This function replaces the instruction at `address` with a breakpoint instruction. The
+    /// original instruction is kept, and restored when the breakpoint is cleared or the core
+    /// steps over it. This is useful when all hardware breakpoints are in use, e.g. on cores
+    /// with only two FPB comparators.
+    ///
+    /// The core can not write to flash, so breakpoints in non-volatile memory, or in other memory
+    /// which does not take the breakpoint instruction, fall back to a hardware breakpoint.
+    #[tracing::instrument(skip(self))]
+    pub fn set_sw_breakpoint(&mut self, address: u64) -> Result<(), Error> {
+        let in_nvm = self
+            .memory_regions()
+            .any(|region| region.is_nvm() && region.contains(address));
+
+        let result = if in_nvm {
+            Err(Error::SoftwareBreakpointNotWritable(address))
+        } else {
+            self.inner.set_sw_breakpoint(address)
+        };
+
+        match result {
+            Err(Error::SoftwareBreakpointNotWritable(address)) => {
+                tracing::debug!(
+                    "Memory at {address:#010x} is not writable, using a hardware breakpoint instead"
+                );
+                self.set_hw_breakpoint(address)
+            }
+            result => result,
+        }
+    }
+
+    /// Clear a software breakpoint
+    ///
+    /// This function restores the original instruction at `address`, or clears the hardware
+    /// breakpoint which [`Core::set_sw_breakpoint`] used instead.
+    #[tracing::instrument(skip(self))]
+    pub fn clear_sw_breakpoint(&mut self, address: u64) -> Result<(), Error> {
+        if self.inner.sw_breakpoints().contains(&address) {
+            self.inner.clear_sw_breakpoint(address)
+        } else {
+            self.clear_hw_breakpoint(address)
+        }
+    }
+
+    /// Returns the addresses of all software breakpoints set by probe-rs.
+    ///
+    /// Breakpoints which fell back to a hardware breakpoint are not included.
+    pub fn sw_breakpoints(&self) -> Vec<u64> {
+        self.inner.sw_breakpoints()
+    }
+
+    /// Clear all software breakpoints set by probe-rs.
+    #[tracing::instrument(skip(self))]
+    pub fn clear_all_sw_breakpoints(&mut self) -> Result<(), Error> {
+        for address in self.inner.sw_breakpoints() {
+            self.inner.clear_sw_breakpoint(address)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the number of data watchpoint units of the core.
+    pub fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        self.inner.available_watchpoint_units()
+    }
+
+    /// Returns the address watched by each watchpoint unit. A value of `None` indicates that the
+    /// unit is unused.
+    pub fn hw_watchpoints(&mut self) -> Result<Vec<Option<u64>>, Error> {
+        self.inner.hw_watchpoints()
+    }
+
+    /// Set a hardware watchpoint
+    ///
+    /// This function will try to set a watchpoint which halts the core on `access` to the
+    /// `length` bytes at `address`. When the watchpoint is hit, the core halts with
+    /// [`HaltReason::Watchpoint`].
+    ///
+    /// Which lengths and alignments are supported depends on the architecture. The amount of
+    /// watchpoints is chip specific, and can be queried using
+    /// [`available_watchpoint_units`](Self::available_watchpoint_units).
+    #[tracing::instrument(skip(self))]
+    pub fn set_hw_watchpoint(
+        &mut self,
+        address: u64,
+        length: usize,
+        access: WatchpointAccess,
+    ) -> Result<(), Error> {
+        // Reuse the unit if a watchpoint is set at this address already, else find a free unit.
+        let watchpoints = self.inner.hw_watchpoints()?;
+        let unit_index = match watchpoints.iter().position(|&wp| wp == Some(address)) {
+            Some(unit_index) => unit_index,
+            None => watchpoints
+                .iter()
+                .position(|wp| wp.is_none())
+                .ok_or_else(|| Error::Other("No available hardware watchpoints".to_string()))?,
+        };
+
+        tracing::debug!("Trying to set HW watchpoint #{unit_index} at address {address:#010x}");
+
+        self.inner
+            .set_hw_watchpoint(unit_index, address, length, access)
+    }
+
+    /// Clear a hardware watchpoint
+    ///
+    /// This function will try to clear the watchpoint at `address` if there exists a watchpoint
+    /// at that address.
+    #[tracing::instrument(skip(self))]
+    pub fn clear_hw_watchpoint(&mut self, address: u64) -> Result<(), Error> {
+        let wp_position = self
+            .inner
+            .hw_watchpoints()?
+            .iter()
+            .position(|wp| *wp == Some(address));
+
+        match wp_position {
+            Some(wp_position) => self.inner.clear_hw_watchpoint(wp_position),
+            None => Err(Error::Other(format!(
+                "No watchpoint found at address {address:#010x}"
+            ))),
+        }
+    }
+
+    /// Clear all hardware watchpoints which are configured on the target.
+    #[tracing::instrument(skip(self))]
+    pub fn clear_all_hw_watchpoints(&mut self) -> Result<(), Error> {
+        for (unit_index, watchpoint) in self.inner.hw_watchpoints()?.into_iter().enumerate() {
+            if watchpoint.is_some() {
+                self.inner.clear_hw_watchpoint(unit_index)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Returns the architecture of the core.
     pub fn architecture(&self) -> Architecture {
         self.inner.architecture()
@@ -538,6 +1072,57 @@ impl<'probe> Core<'probe> {
         self.inner.floating_point_register_count()
     }
 
+    /// Read the cycle counter of the core.
+    ///
+    /// Returns `None` if the core has no cycle counter, or if it is not enabled.
+    pub fn cycle_count(&mut self) -> Result<Option<u64>, Error> {
+        self.inner.cycle_count()
+    }
+
+    /// Read the SWO data received by the probe since the last read, without waiting.
+    ///
+    /// Tracing must have been configured with [`Session::setup_tracing`](crate::Session::setup_tracing)
+    /// first. Returns `None` if the core can't emit SWO data.
+    pub fn read_swo(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        self.inner.read_swo()
+    }
+
+    /// Read the application trace data sent by the core since the last read, without waiting.
+    ///
+    /// This is the `app_trace` of ESP-IDF on Xtensa cores, see
+    /// [`AppTrace`](crate::architecture::xtensa::apptrace::AppTrace). The first read tells the
+    /// application that a host is connected. Returns `None` if the core has no application trace.
+    pub fn read_apptrace(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        self.inner.read_apptrace()
+    }
+
+    /// Tell the core that the host stopped reading the application trace, so the application
+    /// does not wait for it.
+    pub fn stop_apptrace(&mut self) -> Result<(), Error> {
+        self.inner.stop_apptrace()
+    }
+
+    /// Access the DWT profiling counters of a Cortex-M core.
+    ///
+    /// Returns an error for other cores, or if the DWT of the core implements no counters.
+    pub fn performance_counters(&mut self) -> Result<PerformanceCounters<'_, 'probe>, Error> {
+        PerformanceCounters::new(self)
+    }
+
+    /// Captures the current host time and cycle count of the core.
+    ///
+    /// This should be called as soon as a halt is detected. Errors while reading the cycle counter
+    /// are ignored, as the timestamp is only informational.
+    pub fn halt_timestamp(&mut self) -> HaltTimestamp {
+        let host = SystemTime::now();
+        let cycle_count = self.cycle_count().unwrap_or_else(|error| {
+            tracing::debug!("Failed to read cycle counter of core {}: {error}", self.id);
+            None
+        });
+
+        HaltTimestamp { host, cycle_count }
+    }
+
     pub(crate) fn reset_catch_set(&mut self) -> Result<(), Error> {
         self.inner.reset_catch_set()
     }
@@ -550,6 +1135,10 @@ impl<'probe> Core<'probe> {
         self.inner.debug_core_stop()
     }
 
+    pub(crate) fn prepare_group_resume(&mut self) -> Result<(), Error> {
+        self.inner.prepare_group_resume()
+    }
+
     /// Enables vector catching for the given `condition`
     pub fn enable_vector_catch(&mut self, condition: VectorCatchCondition) -> Result<(), Error> {
         self.inner.enable_vector_catch(condition)
@@ -560,12 +1149,86 @@ impl<'probe> Core<'probe> {
         self.inner.disable_vector_catch(condition)
     }
 
+    /// Configures a trigger which halts the core when the condition `kind` is met.
+    ///
+    /// Only one trigger of each kind can be active, setting a trigger replaces a previously set
+    /// trigger of the same kind.
+    pub fn set_trigger(&mut self, kind: TriggerKind) -> Result<(), Error> {
+        self.inner.set_trigger(kind)
+    }
+
+    /// Removes the trigger of the same kind as `kind`. The parameters of `kind` are ignored.
+    pub fn clear_trigger(&mut self, kind: TriggerKind) -> Result<(), Error> {
+        self.inner.clear_trigger(kind)
+    }
+
     /// Check if the integer size is 64-bit
     pub fn is_64_bit(&self) -> bool {
         self.inner.is_64_bit()
     }
 }
 
+/// The address space of the registers that are part of the core itself, and therefore not
+/// described by the memory map of the target, e.g. the Private Peripheral Bus of Cortex-M cores
+/// with the SCS, DWT and ITM.
+fn system_address_space(core_type: CoreType) -> Option<Range<u64>> {
+    core_type.is_cortex_m().then_some(0xE000_0000..0xE010_0000)
+}
+
+/// Checks an access of `len` bytes at `address` against `ranges`, according to `policy`.
+fn check_memory_access(
+    policy: MemoryMapPolicy,
+    ranges: impl Iterator<Item = Range<u64>> + Clone,
+    address: u64,
+    len: usize,
+) -> Result<(), Error> {
+    if policy == MemoryMapPolicy::Allow || len == 0 {
+        return Ok(());
+    }
+
+    // An access may span several adjacent regions.
+    let end = address.saturating_add(len as u64);
+    let mut covered = address;
+    while covered < end {
+        match ranges.clone().find(|range| range.contains(&covered)) {
+            Some(range) => covered = range.end,
+            None => break,
+        }
+    }
+
+    if covered >= end {
+        return Ok(());
+    }
+
+    let error = Error::OutsideMemoryMap {
+        address,
+        len: len as u64,
+    };
+    if policy == MemoryMapPolicy::Warn {
+        tracing::warn!("{error}");
+        return Ok(());
+    }
+
+    Err(error)
+}
+
+/// Resets with the first of `strategies` that succeeds, in order. Returns the error of the last
+/// strategy if all of them fail.
+fn reset_with_strategies(
+    strategies: &[ResetStrategy],
+    mut reset_with: impl FnMut(ResetStrategy) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut result = Ok(());
+    for &strategy in strategies {
+        result = reset_with(strategy);
+        match &result {
+            Ok(()) => return Ok(()),
+            Err(error) => tracing::warn!("Reset with {strategy:?} failed: {error}"),
+        }
+    }
+    result
+}
+
 impl CoreInterface for Core<'_> {
     fn wait_for_core_halted(&mut self, timeout: Duration) -> Result<(), Error> {
         self.wait_for_core_halted(timeout)
@@ -614,6 +1277,20 @@ impl CoreInterface for Core<'_> {
         self.write_core_reg(address, value)
     }
 
+    fn read_core_regs(
+        &mut self,
+        addresses: &[registers::RegisterId],
+    ) -> Result<Vec<registers::RegisterValue>, Error> {
+        self.read_core_regs(addresses)
+    }
+
+    fn write_core_regs(
+        &mut self,
+        registers: &[(registers::RegisterId, registers::RegisterValue)],
+    ) -> Result<(), Error> {
+        self.write_core_regs(registers)
+    }
+
     fn available_breakpoint_units(&mut self) -> Result<u32, Error> {
         self.available_breakpoint_units()
     }
@@ -659,6 +1336,41 @@ impl CoreInterface for Core<'_> {
         self.inner.hw_breakpoints_enabled()
     }
 
+    fn set_sw_breakpoint(&mut self, address: u64) -> Result<(), Error> {
+        self.set_sw_breakpoint(address)
+    }
+
+    fn clear_sw_breakpoint(&mut self, address: u64) -> Result<(), Error> {
+        self.clear_sw_breakpoint(address)
+    }
+
+    fn sw_breakpoints(&self) -> Vec<u64> {
+        self.sw_breakpoints()
+    }
+
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        self.available_watchpoint_units()
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<u64>>, Error> {
+        self.hw_watchpoints()
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        addr: u64,
+        length: usize,
+        access: WatchpointAccess,
+    ) -> Result<(), Error> {
+        self.inner
+            .set_hw_watchpoint(unit_index, addr, length, access)
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        self.inner.clear_hw_watchpoint(unit_index)
+    }
+
     fn architecture(&self) -> Architecture {
         self.architecture()
     }
@@ -700,6 +1412,10 @@ impl CoreInterface for Core<'_> {
     fn is_64_bit(&self) -> bool {
         self.is_64_bit()
     }
+
+    fn cycle_count(&mut self) -> Result<Option<u64>, Error> {
+        self.cycle_count()
+    }
 }
 
 pub enum ResolvedCoreOptions {
@@ -742,6 +1458,13 @@ impl ResolvedCoreOptions {
             Self::Xtensa { options, .. } => options.jtag_tap.unwrap_or(0),
         }
     }
+
+    fn riscv_hart(&self) -> Option<u32> {
+        match self {
+            Self::Riscv { options, .. } => Some(options.hart_id.unwrap_or_default()),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Debug for ResolvedCoreOptions {
@@ -765,3 +1488,107 @@ impl std::fmt::Debug for ResolvedCoreOptions {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MEMORY_MAP: [Range<u64>; 2] = [0x0..0x1000, 0x2000_0000..0x2000_1000];
+
+    fn check(policy: MemoryMapPolicy, address: u64, len: usize) -> Result<(), Error> {
+        let ranges = MEMORY_MAP
+            .into_iter()
+            .chain(system_address_space(CoreType::Armv7em));
+        check_memory_access(policy, ranges, address, len)
+    }
+
+    #[test]
+    fn accesses_inside_the_memory_map_are_allowed() {
+        for policy in [
+            MemoryMapPolicy::Allow,
+            MemoryMapPolicy::Warn,
+            MemoryMapPolicy::Enforce,
+        ] {
+            assert!(check(policy, 0x2000_0ffc, 4).is_ok());
+            assert!(check(policy, 0x0ffc, 4).is_ok());
+        }
+    }
+
+    #[test]
+    fn accesses_outside_the_memory_map_are_enforced() {
+        assert!(matches!(
+            check(MemoryMapPolicy::Enforce, 0x4000_0000, 4),
+            Err(Error::OutsideMemoryMap {
+                address: 0x4000_0000,
+                len: 4
+            })
+        ));
+        // Partially outside.
+        assert!(check(MemoryMapPolicy::Enforce, 0x2000_0ffc, 8).is_err());
+    }
+
+    #[test]
+    fn accesses_outside_the_memory_map_are_performed_with_warn_and_allow() {
+        assert!(check(MemoryMapPolicy::Warn, 0x4000_0000, 4).is_ok());
+        assert!(check(MemoryMapPolicy::Allow, 0x4000_0000, 4).is_ok());
+    }
+
+    #[test]
+    fn accesses_spanning_adjacent_regions_are_allowed() {
+        let ranges = [0x0..0x1000, 0x1000..0x2000].into_iter();
+        assert!(check_memory_access(MemoryMapPolicy::Enforce, ranges, 0xff0, 0x20).is_ok());
+    }
+
+    #[test]
+    fn core_registers_are_always_accessible() {
+        // DWT_CTRL and SCB->CPUID are not part of the memory map of the target.
+        assert!(check(MemoryMapPolicy::Enforce, 0xE000_1000, 4).is_ok());
+        assert!(check(MemoryMapPolicy::Enforce, 0xE000_ED00, 4).is_ok());
+
+        let ranges = MEMORY_MAP
+            .into_iter()
+            .chain(system_address_space(CoreType::Riscv));
+        assert!(check_memory_access(MemoryMapPolicy::Enforce, ranges, 0xE000_ED00, 4).is_err());
+    }
+
+    #[test]
+    fn reset_strategies_are_tried_in_order() {
+        let strategies = [
+            ResetStrategy::VectReset,
+            ResetStrategy::SysResetReq,
+            ResetStrategy::HardwareReset,
+        ];
+
+        let mut tried = vec![];
+        reset_with_strategies(&strategies, |strategy| {
+            tried.push(strategy);
+            match strategy {
+                ResetStrategy::VectReset => Err(Error::NotImplemented("VECTRESET")),
+                _ => Ok(()),
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            tried,
+            [ResetStrategy::VectReset, ResetStrategy::SysResetReq]
+        );
+    }
+
+    #[test]
+    fn last_reset_error_is_returned() {
+        let mut tried = vec![];
+        let result = reset_with_strategies(
+            &[ResetStrategy::SysResetReq, ResetStrategy::HardwareReset],
+            |strategy| {
+                tried.push(strategy);
+                Err(Error::Other(format!("{strategy:?}")))
+            },
+        );
+
+        assert!(matches!(result, Err(Error::Other(message)) if message == "HardwareReset"));
+        assert_eq!(
+            tried,
+            [ResetStrategy::SysResetReq, ResetStrategy::HardwareReset]
+        );
+    }
+}
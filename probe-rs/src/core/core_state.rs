@@ -23,6 +23,9 @@ pub(crate) struct CombinedCoreState {
     pub(crate) specific_state: SpecificCoreState,
 
     pub(crate) id: usize,
+
+    /// The hardware breakpoints set through probe-rs, by unit index.
+    pub(crate) hw_breakpoints: Vec<Option<u64>>,
 }
 
 impl CombinedCoreState {
@@ -38,6 +41,11 @@ impl CombinedCoreState {
         self.core_state.core_access_options.jtag_tap_index()
     }
 
+    /// Returns the index of the hart, if this is a RISC-V core.
+    pub fn riscv_hart(&self) -> Option<u32> {
+        self.core_state.core_access_options.riscv_hart()
+    }
+
     pub(crate) fn attach_arm<'probe>(
         &'probe mut self,
         target: &'probe Target,
@@ -61,12 +69,14 @@ impl CombinedCoreState {
                 self.id,
                 name,
                 target,
+                &mut self.hw_breakpoints,
                 crate::architecture::arm::armv6m::Armv6m::new(memory, s, debug_sequence)?,
             ),
             SpecificCoreState::Armv7a(s) => Core::new(
                 self.id,
                 name,
                 target,
+                &mut self.hw_breakpoints,
                 crate::architecture::arm::armv7a::Armv7a::new(
                     memory,
                     s,
@@ -78,12 +88,14 @@ impl CombinedCoreState {
                 self.id,
                 name,
                 target,
+                &mut self.hw_breakpoints,
                 crate::architecture::arm::armv7m::Armv7m::new(memory, s, debug_sequence)?,
             ),
             SpecificCoreState::Armv8a(s) => Core::new(
                 self.id,
                 name,
                 target,
+                &mut self.hw_breakpoints,
                 crate::architecture::arm::armv8a::Armv8a::new(
                     memory,
                     s,
@@ -96,6 +108,7 @@ impl CombinedCoreState {
                 self.id,
                 name,
                 target,
+                &mut self.hw_breakpoints,
                 crate::architecture::arm::armv8m::Armv8m::new(memory, s, debug_sequence)?,
             ),
             _ => {
@@ -189,6 +202,7 @@ impl CombinedCoreState {
             self.id,
             name,
             target,
+            &mut self.hw_breakpoints,
             crate::architecture::riscv::Riscv32::new(interface, s, debug_sequence)?,
         ))
     }
@@ -220,6 +234,7 @@ impl CombinedCoreState {
             self.id,
             name,
             target,
+            &mut self.hw_breakpoints,
             crate::architecture::xtensa::Xtensa::new(interface, s, debug_sequence)?,
         ))
     }
@@ -56,10 +56,11 @@ pub enum HaltReason {
     /// Core halted due to a breakpoint. The cause is `Unknown` if we cannot distinguish between a hardware and software breakpoint.
     Breakpoint(BreakpointCause),
     /// Core halted due to an exception, e.g. an
-    /// an interrupt.
-    Exception,
-    /// Core halted due to a data watchpoint
-    Watchpoint,
+    /// an interrupt. Contains the details of the exception, if the architecture reports them.
+    Exception(Option<ExceptionInfo>),
+    /// Core halted due to a data watchpoint. Contains the watched address, if the architecture
+    /// reports which watchpoint was hit.
+    Watchpoint(Option<u64>),
     /// Core halted after single step
     Step,
     /// Core halted because of a debugger request
@@ -72,6 +73,66 @@ pub enum HaltReason {
     Unknown,
 }
 
+/// Details of an exception which halted the core.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct ExceptionInfo {
+    /// What caused the exception.
+    pub cause: ExceptionCause,
+    /// The address of the instruction which caused the exception, if known.
+    pub address: Option<u64>,
+}
+
+impl std::fmt::Display for ExceptionInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.address {
+            Some(address) => write!(f, "{:?} at {address:#010x}", self.cause),
+            None => write!(f, "{:?}", self.cause),
+        }
+    }
+}
+
+/// The cause of an exception.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ExceptionCause {
+    /// An illegal instruction was executed.
+    IllegalInstruction,
+    /// A system call instruction was executed.
+    Syscall,
+    /// An error occurred while fetching an instruction.
+    InstructionFetchError,
+    /// An error occurred during a load or store.
+    LoadStoreError,
+    /// A load or store used an unaligned address.
+    LoadStoreAlignment,
+    /// An integer division by zero.
+    IntegerDivideByZero,
+    /// A privileged instruction was executed in user mode.
+    Privileged,
+    /// An instruction was fetched from a region which does not allow execution.
+    InstructionFetchProhibited,
+    /// A load from a region which does not allow loads.
+    LoadProhibited,
+    /// A store to a region which does not allow stores.
+    StoreProhibited,
+    /// An instruction of the given disabled coprocessor was executed.
+    CoprocessorDisabled(u8),
+    /// An exception occurred while handling an exception.
+    DoubleException,
+    /// An architecture specific cause, which is not decoded by probe-rs.
+    Other(u32),
+}
+
+/// The kind of data access a watchpoint triggers on.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum WatchpointAccess {
+    /// Trigger on loads.
+    Read,
+    /// Trigger on stores.
+    Write,
+    /// Trigger on loads and stores.
+    ReadWrite,
+}
+
 /// When a core hits an exception, we halt the core.
 ///
 /// `VectorCatchCondition` describes which event exactly should trigger a halt.
@@ -86,3 +147,21 @@ pub enum VectorCatchCondition {
     /// We encountered any exception.
     All,
 }
+
+/// A condition which halts the core, implemented by a trigger of the debug module.
+///
+/// Triggers are set using [`Core::set_trigger`](crate::Core::set_trigger). They are currently
+/// only supported on RISC-V cores.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TriggerKind {
+    /// Halt after the given number of instructions have been executed.
+    InstructionCount(u32),
+    /// Halt when one of the interrupts in the mask is taken.
+    ///
+    /// Bit `n` of the mask selects the interrupt with cause `n`.
+    Interrupt(u64),
+    /// Halt when one of the exceptions in the mask is taken.
+    ///
+    /// Bit `n` of the mask selects the exception with cause `n`.
+    Exception(u64),
+}
@@ -0,0 +1,156 @@
+//! Stopping peripherals while a core is halted.
+
+use std::collections::BTreeMap;
+
+use probe_rs_target::{DebugFreezeBit, DebugFreezeKind};
+
+use crate::memory::{InvalidDataLengthError, MemoryInterface, MemoryNotAlignedError};
+
+/// Selects the peripherals that are stopped while a core is halted.
+///
+/// The peripherals are selected from the debug freeze bits of the target description, by their
+/// kind or by their name. Bits of peripherals that are not selected are cleared, so these
+/// peripherals keep running.
+///
+/// The default configuration stops watchdogs, timers and CAN controllers, and is applied when a
+/// session is attached.
+///
+/// # Example
+///
+/// ```
+/// use probe_rs::{FreezeConfig, config::DebugFreezeKind};
+///
+/// // Keep the timers running, but stop the watchdogs and the RTC.
+/// let config = FreezeConfig::none()
+///     .freeze_kind(DebugFreezeKind::Watchdog)
+///     .freeze_peripheral("RTC");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreezeConfig {
+    kinds: Vec<DebugFreezeKind>,
+    peripherals: Vec<String>,
+    excluded: Vec<String>,
+}
+
+impl Default for FreezeConfig {
+    fn default() -> Self {
+        Self::none()
+            .freeze_kind(DebugFreezeKind::Watchdog)
+            .freeze_kind(DebugFreezeKind::Timer)
+            .freeze_kind(DebugFreezeKind::Can)
+    }
+}
+
+impl FreezeConfig {
+    /// Constructs a configuration which keeps all peripherals running.
+    pub fn none() -> Self {
+        Self {
+            kinds: vec![],
+            peripherals: vec![],
+            excluded: vec![],
+        }
+    }
+
+    /// Stops all peripherals of the given kind.
+    #[must_use]
+    pub fn freeze_kind(mut self, kind: DebugFreezeKind) -> Self {
+        if !self.kinds.contains(&kind) {
+            self.kinds.push(kind);
+        }
+        self
+    }
+
+    /// Stops the peripheral with the given name, e.g. `TIM2`.
+    #[must_use]
+    pub fn freeze_peripheral(mut self, name: impl Into<String>) -> Self {
+        self.peripherals.push(name.into());
+        self
+    }
+
+    /// Keeps the peripheral with the given name running, even if its kind is stopped.
+    #[must_use]
+    pub fn keep_running(mut self, name: impl Into<String>) -> Self {
+        self.excluded.push(name.into());
+        self
+    }
+
+    /// Returns whether the peripheral of `bit` is stopped.
+    pub fn freezes(&self, bit: &DebugFreezeBit) -> bool {
+        let named = |names: &[String]| {
+            names
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(&bit.peripheral))
+        };
+
+        if named(&self.excluded) {
+            return false;
+        }
+
+        self.kinds.contains(&bit.kind) || named(&self.peripherals)
+    }
+
+    /// Sets or clears the freeze bits in `bits`, with one read-modify-write per register.
+    pub(crate) fn apply<ERR>(
+        &self,
+        memory: &mut (impl MemoryInterface<ERR> + ?Sized),
+        bits: &[DebugFreezeBit],
+    ) -> Result<(), ERR>
+    where
+        ERR: std::error::Error + From<InvalidDataLengthError> + From<MemoryNotAlignedError>,
+    {
+        let mut registers = BTreeMap::<u64, (u32, u32)>::new();
+        for bit in bits {
+            let (mask, value) = registers.entry(bit.address).or_default();
+            *mask |= bit.mask();
+            if self.freezes(bit) {
+                *value |= bit.mask();
+            }
+        }
+
+        for (address, (mask, value)) in registers {
+            let current = memory.read_word_32(address)?;
+            let new = (current & !mask) | value;
+            if new != current {
+                tracing::debug!("Writing {new:#010x} to debug freeze register {address:#010x}");
+                memory.write_word_32(address, new)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bit(peripheral: &str, kind: DebugFreezeKind) -> DebugFreezeBit {
+        DebugFreezeBit {
+            peripheral: peripheral.to_string(),
+            kind,
+            address: 0xE004_2008,
+            bit: 0,
+        }
+    }
+
+    #[test]
+    fn default_freezes_watchdogs_timers_and_can() {
+        let config = FreezeConfig::default();
+
+        assert!(config.freezes(&bit("IWDG", DebugFreezeKind::Watchdog)));
+        assert!(config.freezes(&bit("TIM2", DebugFreezeKind::Timer)));
+        assert!(config.freezes(&bit("CAN1", DebugFreezeKind::Can)));
+        assert!(!config.freezes(&bit("RTC", DebugFreezeKind::Rtc)));
+    }
+
+    #[test]
+    fn excluded_peripherals_keep_running() {
+        let config = FreezeConfig::default()
+            .keep_running("tim2")
+            .freeze_peripheral("RTC");
+
+        assert!(!config.freezes(&bit("TIM2", DebugFreezeKind::Timer)));
+        assert!(config.freezes(&bit("TIM3", DebugFreezeKind::Timer)));
+        assert!(config.freezes(&bit("RTC", DebugFreezeKind::Rtc)));
+    }
+}
@@ -8,12 +8,12 @@ use crate::architecture::arm::core::registers::cortex_m::{
 use crate::architecture::riscv::registers::RISCV_CORE_REGISTERS;
 use crate::architecture::xtensa::arch::{Register as XtensaRegister, SpecialRegister};
 use crate::architecture::xtensa::registers::XTENSA_CORE_REGISTERS;
-use crate::{Core, CoreRegisters, CoreType, Error, InstructionSet, MemoryInterface};
+use crate::{Core, CoreRegisters, CoreType, Error, InstructionSet, MemoryInterface, RegisterRole};
 use crate::{RegisterId, RegisterValue};
-use object::elf::PT_NOTE;
+use object::elf::{PF_R, PF_W, PF_X, PT_NOTE};
 use object::read::elf::ProgramHeader;
 use object::{Object, ObjectSegment};
-use probe_rs_target::MemoryRange;
+use probe_rs_target::{MemoryAccess, MemoryRange, MemoryRegion};
 use scroll::Cread;
 use serde::{Deserialize, Serialize};
 use std::array;
@@ -138,9 +138,106 @@ impl Processor for RiscvProcessor {
     }
 }
 
+/// The version of the core dump format written by [`CoreDump::store`].
+///
+/// Core dumps are stored as MessagePack with named fields, so fields can be added without
+/// breaking older readers. The version is bumped whenever the meaning of the data changes:
+///
+/// - Version 1 contains the registers, the dumped memory and the core type.
+/// - Version 2 adds the memory region metadata and peripheral register snapshots. Floating point
+///   registers are included for cores with an enabled FPU.
+///
+/// Dumps without a version field are version 1 dumps.
+pub const CORE_DUMP_FORMAT_VERSION: u32 = 2;
+
+fn legacy_format_version() -> u32 {
+    1
+}
+
+/// The kind of memory a region of a core dump was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CoreDumpRegionKind {
+    /// RAM of the target.
+    Ram,
+    /// Non-volatile memory, e.g. flash.
+    Nvm,
+    /// Any other memory, or memory that is not described by the memory map.
+    Generic,
+}
+
+/// Describes a memory range included in a core dump.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoreDumpRegion {
+    /// The name of the memory region of the target, if known.
+    pub name: Option<String>,
+    /// The address range that was dumped.
+    pub range: Range<u64>,
+    /// The kind of memory the range was read from.
+    pub kind: CoreDumpRegionKind,
+    /// The access permissions of the memory the range was read from.
+    pub access: MemoryAccess,
+}
+
+impl CoreDumpRegion {
+    /// Describes `range` using the first region of `memory_map` which contains its start address.
+    fn describe<'a>(
+        range: Range<u64>,
+        mut memory_map: impl Iterator<Item = &'a MemoryRegion>,
+    ) -> Self {
+        let Some(region) = memory_map.find(|region| region.contains(range.start)) else {
+            return Self {
+                name: None,
+                range,
+                kind: CoreDumpRegionKind::Generic,
+                access: MemoryAccess::default(),
+            };
+        };
+
+        let (name, kind, access) = match region {
+            MemoryRegion::Ram(region) => (&region.name, CoreDumpRegionKind::Ram, region.access()),
+            MemoryRegion::Nvm(region) => (&region.name, CoreDumpRegionKind::Nvm, region.access()),
+            MemoryRegion::Generic(region) => {
+                (&region.name, CoreDumpRegionKind::Generic, region.access())
+            }
+        };
+
+        Self {
+            name: name.clone(),
+            range,
+            kind,
+            access,
+        }
+    }
+}
+
+/// The value of a peripheral register at the time the core dump was created.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeripheralSnapshot {
+    /// The name of the register, e.g. `SCB.CFSR`.
+    pub name: String,
+    /// The address of the register.
+    pub address: u64,
+    /// The value of the register.
+    pub value: u32,
+}
+
+/// Describes what to include in a core dump, in addition to the registers of the core.
+#[derive(Debug, Clone, Default)]
+pub struct CoreDumpOptions {
+    /// Memory ranges that should be dumped.
+    pub ranges: Vec<Range<u64>>,
+    /// Peripheral registers that should be read, as pairs of name and address.
+    pub peripherals: Vec<(String, u64)>,
+}
+
 /// A snapshot representation of a core state.
+///
+/// See [`CORE_DUMP_FORMAT_VERSION`] for the stored format.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CoreDump {
+    /// The version of the format this core dump was stored in.
+    #[serde(default = "legacy_format_version")]
+    pub format_version: u32,
     /// The registers we dumped from the core.
     pub registers: HashMap<RegisterId, RegisterValue>,
     /// The memory we dumped from the core.
@@ -155,6 +252,12 @@ pub struct CoreDump {
     pub fpu_support: bool,
     /// The number of floating point registers.
     pub floating_point_register_count: Option<usize>,
+    /// The memory regions the dumped memory was read from, in the same order as `data`.
+    #[serde(default)]
+    pub regions: Vec<CoreDumpRegion>,
+    /// The peripheral registers captured with the core dump.
+    #[serde(default)]
+    pub peripherals: Vec<PeripheralSnapshot>,
 }
 
 impl CoreDump {
@@ -164,27 +267,68 @@ impl CoreDump {
     /// * `core`: The core to dump.
     /// * `ranges`: Memory ranges that should be dumped.
     pub fn dump_core(core: &mut Core, ranges: Vec<Range<u64>>) -> Result<Self, Error> {
+        Self::dump_core_with_options(
+            core,
+            CoreDumpOptions {
+                ranges,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Dump the core info with the current state, including the peripheral registers selected
+    /// in `options`. The dumped ranges are described using the memory map of the core.
+    pub fn dump_core_with_options(
+        core: &mut Core,
+        options: CoreDumpOptions,
+    ) -> Result<Self, Error> {
+        let fpu_support = core.fpu_support()?;
+
         let mut registers = HashMap::new();
         for register in core.registers().all_registers() {
+            let is_fpu_register = register.register_has_role(RegisterRole::FloatingPoint)
+                || register.register_has_role(RegisterRole::FloatingPointStatus);
+            if is_fpu_register && !fpu_support {
+                continue;
+            }
+
             let value = core.read_core_reg(register.id())?;
             registers.insert(register.id(), value);
         }
 
         let mut data = Vec::new();
-        for range in ranges {
+        let mut regions = Vec::new();
+        for range in options.ranges {
             let mut values = vec![0; (range.end - range.start) as usize];
             core.read(range.start, &mut values)?;
+            regions.push(CoreDumpRegion::describe(
+                range.clone(),
+                core.memory_regions(),
+            ));
             data.push((range, values));
         }
 
+        let mut peripherals = Vec::new();
+        for (name, address) in options.peripherals {
+            let value = core.read_word_32(address)?;
+            peripherals.push(PeripheralSnapshot {
+                name,
+                address,
+                value,
+            });
+        }
+
         Ok(CoreDump {
+            format_version: CORE_DUMP_FORMAT_VERSION,
             registers,
             data,
             instruction_set: core.instruction_set()?,
             supports_native_64bit_access: core.supports_native_64bit_access(),
             core_type: core.core_type(),
-            fpu_support: core.fpu_support()?,
+            fpu_support,
             floating_point_register_count: Some(core.floating_point_register_count()?),
+            regions,
+            peripherals,
         })
     }
 
@@ -217,7 +361,23 @@ impl CoreDump {
         } else if let Ok(elf) = object::read::elf::ElfFile64::parse(data) {
             Self::load_elf(elf)
         } else {
-            rmp_serde::from_slice(data).map_err(CoreDumpError::DecodingCoreDump)
+            let mut dump: Self =
+                rmp_serde::from_slice(data).map_err(CoreDumpError::DecodingCoreDump)?;
+
+            if dump.format_version > CORE_DUMP_FORMAT_VERSION {
+                return Err(CoreDumpError::UnsupportedFormatVersion(dump.format_version));
+            }
+
+            // Version 1 dumps don't describe their memory, so describe it as generic memory.
+            if dump.regions.len() != dump.data.len() {
+                dump.regions = dump
+                    .data
+                    .iter()
+                    .map(|(range, _)| CoreDumpRegion::describe(range.clone(), std::iter::empty()))
+                    .collect();
+            }
+
+            Ok(dump)
         }
     }
 
@@ -239,16 +399,30 @@ impl CoreDump {
 
         // The memory is in a Load segment.
         let mut data = Vec::new();
+        let mut regions = Vec::new();
         // `elf.segments()` returns PT_LOAD segments only.
         for segment in elf.segments() {
-            let address: u64 = segment.elf_program_header().p_vaddr(endianness).into();
-            let size: u64 = segment.elf_program_header().p_memsz(endianness).into();
+            let header = segment.elf_program_header();
+            let address: u64 = header.p_vaddr(endianness).into();
+            let size: u64 = header.p_memsz(endianness).into();
+            let flags = header.p_flags(endianness);
             let memory = segment.data()?;
             tracing::debug!(
                 "Adding memory segment: {:#x} - {:#x}",
                 address,
                 address + size
             );
+            regions.push(CoreDumpRegion {
+                name: None,
+                range: address..address + size,
+                kind: CoreDumpRegionKind::Generic,
+                access: MemoryAccess {
+                    read: flags & PF_R != 0,
+                    write: flags & PF_W != 0,
+                    execute: flags & PF_X != 0,
+                    boot: false,
+                },
+            });
             data.push((address..address + size, memory.to_vec()));
         }
 
@@ -293,6 +467,7 @@ impl CoreDump {
         }
 
         Ok(Self {
+            format_version: CORE_DUMP_FORMAT_VERSION,
             registers,
             data,
             instruction_set: processor.instruction_set(),
@@ -300,6 +475,8 @@ impl CoreDump {
             core_type: processor.core_type(),
             fpu_support: false,
             floating_point_register_count: None,
+            regions,
+            peripherals: Vec::new(),
         })
     }
 
@@ -313,6 +490,16 @@ impl CoreDump {
         self.instruction_set
     }
 
+    /// Returns the value of a captured register, if it is part of the core dump.
+    pub fn register_value(&self, id: RegisterId) -> Option<RegisterValue> {
+        self.registers.get(&id).cloned()
+    }
+
+    /// Returns the captured value of the peripheral register with the given name.
+    pub fn peripheral(&self, name: &str) -> Option<&PeripheralSnapshot> {
+        self.peripherals.iter().find(|p| p.name == name)
+    }
+
     /// Retrieve a memory range that contains the requested address and size, from the coredump.
     fn get_memory_from_coredump(
         &self,
@@ -438,43 +625,90 @@ impl MemoryInterface for CoreDump {
     }
 
     fn write_word_64(&mut self, _address: u64, _data: u64) -> Result<(), crate::Error> {
-        todo!()
+        Err(read_only())
     }
 
     fn write_word_32(&mut self, _address: u64, _data: u32) -> Result<(), crate::Error> {
-        todo!()
+        Err(read_only())
     }
 
     fn write_word_16(&mut self, _address: u64, _data: u16) -> Result<(), crate::Error> {
-        todo!()
+        Err(read_only())
     }
 
     fn write_word_8(&mut self, _address: u64, _data: u8) -> Result<(), crate::Error> {
-        todo!()
+        Err(read_only())
     }
 
     fn write_64(&mut self, _address: u64, _data: &[u64]) -> Result<(), crate::Error> {
-        todo!()
+        Err(read_only())
     }
 
     fn write_32(&mut self, _address: u64, _data: &[u32]) -> Result<(), crate::Error> {
-        todo!()
+        Err(read_only())
     }
 
     fn write_16(&mut self, _address: u64, _data: &[u16]) -> Result<(), crate::Error> {
-        todo!()
+        Err(read_only())
     }
 
     fn write_8(&mut self, _address: u64, _data: &[u8]) -> Result<(), crate::Error> {
-        todo!()
+        Err(read_only())
     }
 
     fn supports_8bit_transfers(&self) -> Result<bool, crate::Error> {
-        todo!()
+        Ok(true)
     }
 
     fn flush(&mut self) -> Result<(), crate::Error> {
-        todo!()
+        Ok(())
+    }
+}
+
+fn read_only() -> crate::Error {
+    crate::Error::Other("Core dumps are read-only and cannot be written to.".to_string())
+}
+
+/// A read-only stand-in for a [`Session`](crate::Session), backed by core dumps.
+///
+/// The cores of the session are [`CoreDump`]s, which implement [`MemoryInterface`] and carry the
+/// registers of the core, so they can be used to unwind the stack like a halted core.
+#[derive(Debug, Clone)]
+pub struct CoreDumpSession {
+    cores: Vec<CoreDump>,
+}
+
+impl CoreDumpSession {
+    /// Opens the core dump at `path` as a single core session.
+    pub fn open(path: &Path) -> Result<Self, CoreDumpError> {
+        Ok(Self::new(vec![CoreDump::load(path)?]))
+    }
+
+    /// Creates a session from already loaded core dumps. The index of a dump in `cores` is the
+    /// index of the core in the session.
+    pub fn new(cores: Vec<CoreDump>) -> Self {
+        Self { cores }
+    }
+
+    /// Returns a list of the dumped cores and their types.
+    pub fn list_cores(&self) -> Vec<(usize, CoreType)> {
+        self.cores
+            .iter()
+            .enumerate()
+            .map(|(index, core)| (index, core.core_type()))
+            .collect()
+    }
+
+    /// Returns the dump of the core with the given index.
+    pub fn core(&mut self, core_index: usize) -> Result<&mut CoreDump, CoreDumpError> {
+        self.cores
+            .get_mut(core_index)
+            .ok_or(CoreDumpError::CoreNotFound(core_index))
+    }
+
+    /// Returns the memory regions dumped for any of the cores.
+    pub fn memory_regions(&self) -> impl Iterator<Item = &CoreDumpRegion> {
+        self.cores.iter().flat_map(|core| core.regions.iter())
     }
 }
 
@@ -499,4 +733,133 @@ pub enum CoreDumpError {
     /// Invalid ELF file.
     #[error("Invalid ELF file.")]
     ElfCoreDumpFormat(#[from] object::read::Error),
+    /// The core dump was stored in a newer, unsupported version of the format.
+    #[error(
+        "The core dump uses format version {0}, but only versions up to {CORE_DUMP_FORMAT_VERSION} are supported."
+    )]
+    UnsupportedFormatVersion(u32),
+    /// The core dump session does not contain the requested core.
+    #[error("The core dump does not contain core {0}.")]
+    CoreNotFound(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dump() -> CoreDump {
+        CoreDump {
+            format_version: CORE_DUMP_FORMAT_VERSION,
+            registers: HashMap::new(),
+            data: vec![(0x2000_0000..0x2000_0004, vec![1, 2, 3, 4])],
+            instruction_set: InstructionSet::Thumb2,
+            supports_native_64bit_access: false,
+            core_type: CoreType::Armv7em,
+            fpu_support: true,
+            floating_point_register_count: Some(32),
+            regions: vec![CoreDumpRegion {
+                name: Some("RAM".to_string()),
+                range: 0x2000_0000..0x2000_0004,
+                kind: CoreDumpRegionKind::Ram,
+                access: MemoryAccess::default(),
+            }],
+            peripherals: vec![PeripheralSnapshot {
+                name: "SCB.CFSR".to_string(),
+                address: 0xE000_ED28,
+                value: 0x0001_0000,
+            }],
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = rmp_serde::to_vec_named(&dump()).unwrap();
+        let mut loaded = CoreDump::load_raw(&data).unwrap();
+
+        assert_eq!(loaded.format_version, CORE_DUMP_FORMAT_VERSION);
+        assert_eq!(loaded.regions, dump().regions);
+        assert_eq!(loaded.peripheral("SCB.CFSR").unwrap().value, 0x0001_0000);
+        assert_eq!(loaded.read_word_32(0x2000_0000).unwrap(), 0x0403_0201);
+        assert!(loaded.write_word_32(0x2000_0000, 0).is_err());
+    }
+
+    /// The layout of version 1 core dumps.
+    #[derive(Serialize)]
+    struct CoreDumpV1 {
+        registers: HashMap<RegisterId, RegisterValue>,
+        data: Vec<(Range<u64>, Vec<u8>)>,
+        instruction_set: InstructionSet,
+        supports_native_64bit_access: bool,
+        core_type: CoreType,
+        fpu_support: bool,
+        floating_point_register_count: Option<usize>,
+    }
+
+    #[test]
+    fn version_1_is_loaded() {
+        let v1 = CoreDumpV1 {
+            registers: HashMap::from([(RegisterId(15), RegisterValue::U32(0x1000_0100))]),
+            data: vec![
+                (0x2000_0000..0x2000_0004, vec![1, 2, 3, 4]),
+                (0x1000_0000..0x1000_0002, vec![5, 6]),
+            ],
+            instruction_set: InstructionSet::Thumb2,
+            supports_native_64bit_access: false,
+            core_type: CoreType::Armv6m,
+            fpu_support: false,
+            floating_point_register_count: None,
+        };
+        let data = rmp_serde::to_vec_named(&v1).unwrap();
+
+        let mut loaded = CoreDump::load_raw(&data).unwrap();
+
+        assert_eq!(loaded.format_version, 1);
+        assert_eq!(
+            loaded.registers.get(&RegisterId(15)),
+            Some(&RegisterValue::U32(0x1000_0100))
+        );
+        assert_eq!(loaded.core_type, CoreType::Armv6m);
+        assert!(loaded.peripherals.is_empty());
+        assert_eq!(
+            loaded
+                .regions
+                .iter()
+                .map(|region| (region.range.clone(), region.kind))
+                .collect::<Vec<_>>(),
+            [
+                (0x2000_0000..0x2000_0004, CoreDumpRegionKind::Generic),
+                (0x1000_0000..0x1000_0002, CoreDumpRegionKind::Generic),
+            ]
+        );
+        assert_eq!(loaded.read_word_32(0x2000_0000).unwrap(), 0x0403_0201);
+    }
+
+    #[test]
+    fn stored_version_1_dump_is_loaded() {
+        // Stored by an earlier version of probe-rs, and used by the unwind tests.
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../probe-rs-debug/tests/debug-unwind-tests/RP2040_full_unwind.coredump");
+
+        let loaded = CoreDump::load(&path).unwrap();
+
+        assert_eq!(loaded.format_version, 1);
+        assert_eq!(loaded.core_type, CoreType::Armv6m);
+        assert!(!loaded.registers.is_empty());
+        assert_eq!(loaded.regions.len(), loaded.data.len());
+        for (region, (range, _)) in loaded.regions.iter().zip(&loaded.data) {
+            assert_eq!(&region.range, range);
+        }
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let mut dump = dump();
+        dump.format_version = CORE_DUMP_FORMAT_VERSION + 1;
+        let data = rmp_serde::to_vec_named(&dump).unwrap();
+
+        assert!(matches!(
+            CoreDump::load_raw(&data),
+            Err(CoreDumpError::UnsupportedFormatVersion(_))
+        ));
+    }
 }
@@ -0,0 +1,128 @@
+//! Filling large memory areas with a stub running on the core.
+
+use std::time::Duration;
+
+use probe_rs_target::{MemoryRange, MemoryRegion};
+
+use crate::{Core, Error, MemoryInterface, RegisterValue};
+
+/// Fills with at least this many words are done by the stub, if the core can run it.
+///
+/// Below this, downloading and starting the stub takes longer than writing the words.
+pub(crate) const STUB_FILL_MIN_WORDS: usize = 4096;
+
+/// The value of xPSR with only the Thumb bit set.
+const XPSR_THUMB: u32 = 1 << 24;
+
+/// Fills `r2` words at `r0` with `r1`, with interrupts disabled, and halts on a breakpoint.
+///
+/// ```text
+///     cpsid i
+///     cmp   r2, #0
+///     beq   done
+/// loop:
+///     stmia r0!, {r1}
+///     subs  r2, #1
+///     bne   loop
+/// done:
+///     bkpt  #0
+/// ```
+pub(crate) const THUMB_FILL_STUB: [u8; 14] = [
+    0x72, 0xB6, // cpsid i
+    0x00, 0x2A, // cmp   r2, #0
+    0x02, 0xD0, // beq   done
+    0x02, 0xC0, // stmia r0!, {r1}
+    0x01, 0x3A, // subs  r2, #1
+    0xFC, 0xD1, // bne   loop
+    0x00, 0xBE, // bkpt  #0
+];
+
+/// Fills `count` words at `address` with `pattern` using [`THUMB_FILL_STUB`].
+///
+/// The stub is placed at the start of a RAM region that doesn't overlap the filled range. The RAM
+/// and the registers it uses are restored afterwards.
+///
+/// Returns `false` if the stub can't be used, because the core isn't a halted Cortex-M core, or
+/// there is no RAM for it.
+pub(crate) fn fill_32_with_stub(
+    core: &mut Core<'_>,
+    address: u64,
+    pattern: u32,
+    count: usize,
+) -> Result<bool, Error> {
+    if address % 4 != 0 || !core.core_type().is_cortex_m() || !core.core_halted()? {
+        return Ok(false);
+    }
+    let (Ok(start), Ok(words)) = (u32::try_from(address), u32::try_from(count)) else {
+        return Ok(false);
+    };
+
+    let fill_range = address..address + 4 * count as u64;
+    let Some(stub_address) = core.memory_regions().find_map(|region| match region {
+        MemoryRegion::Ram(ram) => {
+            let stub = ram.range.start..ram.range.start + THUMB_FILL_STUB.len() as u64;
+            (stub.end <= ram.range.end && !stub.intersects_range(&fill_range)).then_some(stub.start)
+        }
+        _ => None,
+    }) else {
+        return Ok(false);
+    };
+
+    let registers = core.registers();
+    let mut arguments = vec![
+        (
+            registers.argument_register(0).id(),
+            RegisterValue::from(start),
+        ),
+        (
+            registers.argument_register(1).id(),
+            RegisterValue::from(pattern),
+        ),
+        (
+            registers.argument_register(2).id(),
+            RegisterValue::from(words),
+        ),
+        (
+            core.program_counter().id(),
+            RegisterValue::from(stub_address as u32),
+        ),
+    ];
+    // Only the Thumb bit is set, so the stub doesn't start in the middle of an IT block.
+    if let Some(psr) = registers.psr() {
+        arguments.push((psr.id(), RegisterValue::from(XPSR_THUMB)));
+    }
+
+    // PRIMASK is changed by the stub.
+    let mut saved_ids = arguments.iter().map(|&(id, _)| id).collect::<Vec<_>>();
+    saved_ids.extend(
+        registers
+            .other_by_name("EXTRA")
+            .map(|register| register.id()),
+    );
+
+    tracing::debug!("Filling {count} words at {address:#010x} with a stub at {stub_address:#010x}");
+
+    let saved_registers = core.read_core_regs(&saved_ids)?;
+    let mut saved_ram = [0; THUMB_FILL_STUB.len()];
+    core.read_8(stub_address, &mut saved_ram)?;
+
+    core.write_8(stub_address, &THUMB_FILL_STUB)?;
+    core.write_core_regs(&arguments)?;
+
+    // The stub stores a word in about 4 cycles, this allows for clocks down to 2 MHz.
+    let timeout = Duration::from_millis(100) + Duration::from_micros(2 * count as u64);
+    core.run()?;
+    let result = core.wait_for_core_halted(timeout);
+    if result.is_err() {
+        core.halt(Duration::from_millis(100))?;
+    }
+
+    core.write_8(stub_address, &saved_ram)?;
+    let restore = saved_ids
+        .into_iter()
+        .zip(saved_registers)
+        .collect::<Vec<_>>();
+    core.write_core_regs(&restore)?;
+
+    result.map(|()| true)
+}
@@ -46,6 +46,10 @@ pub enum RegisterRole {
     FloatingPoint,
     /// Floating Point Status register
     FloatingPointStatus,
+    /// Vector register
+    Vector,
+    /// Vector configuration and status register, like `vl` and `vtype`
+    VectorStatus,
     /// Other architecture specific roles, e.g. "saved", "temporary", "variable", etc.
     Other(&'static str),
 }
@@ -65,6 +69,8 @@ impl Display for RegisterRole {
             RegisterRole::ReturnAddress => write!(f, "LR"),
             RegisterRole::FloatingPoint => write!(f, "FPU"),
             RegisterRole::FloatingPointStatus => write!(f, "FPSR"),
+            RegisterRole::Vector => write!(f, "VEC"),
+            RegisterRole::VectorStatus => write!(f, "VSR"),
             RegisterRole::Other(name) => write!(f, "{name}"),
         }
     }
@@ -471,7 +477,8 @@ impl CoreRegisters {
         CoreRegisters(core_registers)
     }
 
-    /// Returns an iterator over the descriptions of all the non-FPU registers of this core.
+    /// Returns an iterator over the descriptions of all the non-FPU and non-vector registers of
+    /// this core.
     pub fn core_registers(&self) -> impl Iterator<Item = &CoreRegister> {
         self.0
             .iter()
@@ -479,7 +486,10 @@ impl CoreRegisters {
                 !r.roles.iter().any(|role| {
                     matches!(
                         role,
-                        RegisterRole::FloatingPoint | RegisterRole::FloatingPointStatus
+                        RegisterRole::FloatingPoint
+                            | RegisterRole::FloatingPointStatus
+                            | RegisterRole::Vector
+                            | RegisterRole::VectorStatus
                     )
                 })
             })
@@ -615,6 +625,29 @@ impl CoreRegisters {
         }
     }
 
+    /// Returns an iterator over the descriptions of the vector registers of this core, or `None`
+    /// if the core has no vector registers.
+    pub fn vector_registers(&self) -> Option<impl Iterator<Item = &CoreRegister>> {
+        let mut vector_registers = self
+            .0
+            .iter()
+            .filter(|r| r.register_has_role(RegisterRole::Vector))
+            .peekable();
+        if vector_registers.peek().is_some() {
+            Some(vector_registers.cloned())
+        } else {
+            None
+        }
+    }
+
+    /// Returns an iterator over the vector configuration and status registers of this core.
+    pub fn vector_status_registers(&self) -> impl Iterator<Item = &CoreRegister> {
+        self.0
+            .iter()
+            .filter(|r| r.register_has_role(RegisterRole::VectorStatus))
+            .cloned()
+    }
+
     /// Returns the nth fpu register.
     ///
     /// # Panics
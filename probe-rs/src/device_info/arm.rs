@@ -0,0 +1,343 @@
+//! Information about ARM debug ports, access ports and CoreSight components.
+
+use crate::architecture::arm::{
+    ApAddress, ApV2Address, ArmDebugInterface, ArmError, FullyQualifiedApAddress,
+    ap::{ApClass, ApRegister, ApType, IDR},
+    component::Scs,
+    dp::{Ctrl, DLPIDR, DPIDR, DebugPortId, DebugPortVersion, DpAddress, DpRegister, TARGETID},
+    memory::{
+        ArmMemoryInterface, Component, ComponentId, CoresightComponent, PeripheralType, RomTable,
+    },
+};
+
+/// Information about an ARM debug port and all components which are accessible through it.
+#[derive(Debug, Clone)]
+pub struct DebugPortInfo {
+    /// The address of the debug port.
+    pub address: DpAddress,
+    /// The ID of the debug port, as read from DPIDR.
+    pub id: DebugPortId,
+    /// The TARGETID register. Only available on DPv2 and newer.
+    pub targetid: Option<u32>,
+    /// The DLPIDR register, containing the instance ID. Only available on DPv2 and newer.
+    pub dlpidr: Option<u32>,
+    /// The access ports of the debug port.
+    pub access_ports: Vec<AccessPortInfo>,
+}
+
+/// Information about an access port.
+#[derive(Debug, Clone)]
+pub enum AccessPortInfo {
+    /// A memory access port.
+    MemoryAp {
+        /// The address of the access port.
+        address: FullyQualifiedApAddress,
+        /// The bus type of the memory access port.
+        ap_type: ApType,
+        /// The components behind the memory access port.
+        state: MemoryApState,
+    },
+    /// The root memory space of an ADIv6 debug port.
+    ApV2Root {
+        /// The root ROM table.
+        component: ComponentInfo,
+    },
+    /// An access port which is not a memory access port, e.g. a JTAG-AP or a vendor specific AP.
+    Other {
+        /// The address of the access port.
+        address: FullyQualifiedApAddress,
+        /// The raw value of the IDR register.
+        idr: u32,
+    },
+}
+
+/// The state of a memory access port.
+#[derive(Debug, Clone)]
+pub enum MemoryApState {
+    /// The memory access port cannot be used, because the `DeviceEn` bit is not set.
+    Disabled,
+    /// The ROM table, or the single component, the memory access port points to.
+    Accessible(ComponentInfo),
+    /// An error occurred while reading the components.
+    Error(String),
+}
+
+/// The class of a CoreSight component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentKind {
+    /// Generic verification component.
+    GenericVerification,
+    /// Class 0x1 ROM table.
+    Class1RomTable,
+    /// Class 0x9 ROM table.
+    Class9RomTable,
+    /// CoreSight component.
+    CoreSight,
+    /// Peripheral test block.
+    PeripheralTestBlock,
+    /// Generic IP component.
+    GenericIp,
+    /// CoreLink, PrimeCell or system component with no standard register layout.
+    CoreLinkOrPrimeCellOrSystem,
+}
+
+impl From<&Component> for ComponentKind {
+    fn from(component: &Component) -> Self {
+        match component {
+            Component::GenericVerificationComponent(_) => ComponentKind::GenericVerification,
+            Component::Class1RomTable(_, _) => ComponentKind::Class1RomTable,
+            Component::Class9RomTable(_, _) => ComponentKind::Class9RomTable,
+            Component::CoresightComponent(_) => ComponentKind::CoreSight,
+            Component::PeripheralTestBlock(_) => ComponentKind::PeripheralTestBlock,
+            Component::GenericIPComponent(_) => ComponentKind::GenericIp,
+            Component::CoreLinkOrPrimeCellOrSystemComponent(_) => {
+                ComponentKind::CoreLinkOrPrimeCellOrSystem
+            }
+        }
+    }
+}
+
+/// Information about a CoreSight component.
+#[derive(Debug, Clone)]
+pub struct ComponentInfo {
+    /// The identification of the component, including its address and peripheral ID.
+    pub id: ComponentId,
+    /// The class of the component.
+    pub kind: ComponentKind,
+    /// The CPUID of the core, if the component is a System Control Space.
+    pub cpu: Option<CpuInfo>,
+    /// A vendor specific device identification register which is part of the component,
+    /// e.g. the DID register of the Atmel DSU.
+    pub device_id: Option<u32>,
+    /// The memory access port, if the component is an ADIv6 memory access port.
+    pub memory_ap: Option<Box<MemoryApState>>,
+    /// The components referenced by this component, if it is a ROM table.
+    pub children: Vec<ComponentInfo>,
+}
+
+impl ComponentInfo {
+    fn new(component: &Component) -> Self {
+        Self {
+            id: component.id().clone(),
+            kind: ComponentKind::from(component),
+            cpu: None,
+            device_id: None,
+            memory_ap: None,
+            children: vec![],
+        }
+    }
+}
+
+/// The core information read from the CPUID register.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuInfo {
+    /// The name of the implementer of the core.
+    pub implementer: String,
+    /// The variant number of the core.
+    pub variant: u32,
+    /// The name of the core.
+    pub part: String,
+    /// The revision number of the core.
+    pub revision: u32,
+}
+
+/// Reads the IDs of the debug port at `dp`, and walks the ROM tables of all its access ports.
+pub fn read_debug_port_info(
+    interface: &mut dyn ArmDebugInterface,
+    dp: DpAddress,
+) -> Result<DebugPortInfo, ArmError> {
+    let dpidr = interface.read_raw_dp_register(dp, DPIDR::ADDRESS)?;
+    let id = DebugPortId::from(DPIDR(dpidr));
+
+    let (targetid, dlpidr) = if id.version >= DebugPortVersion::DPv2 {
+        let targetid = interface.read_raw_dp_register(dp, TARGETID::ADDRESS)?;
+        let dlpidr = interface.read_raw_dp_register(dp, DLPIDR::ADDRESS)?;
+
+        // Read from the CTRL/STAT register, to ensure that the dpbanksel field is set to zero.
+        // This helps with error handling later, because it means the CTRL/AP register can be
+        // read in case of an error.
+        let _ = interface.read_raw_dp_register(dp, Ctrl::ADDRESS)?;
+
+        (Some(targetid), Some(dlpidr))
+    } else {
+        (None, None)
+    };
+
+    let mut access_ports = vec![];
+    if id.version != DebugPortVersion::DPv3 {
+        for address in interface.access_ports(dp)? {
+            access_ports.push(read_access_port_info(interface, address)?);
+        }
+    } else {
+        let address = FullyQualifiedApAddress::v2_with_dp(dp, ApV2Address::root());
+        let component = {
+            let mut memory = interface.memory_interface(&address)?;
+            let base_address = memory.base_address()?;
+            Component::try_parse(&mut *memory, base_address)?
+        };
+        let component = read_component_info(interface, &component, &address)?;
+        access_ports.push(AccessPortInfo::ApV2Root { component });
+    }
+
+    Ok(DebugPortInfo {
+        address: dp,
+        id,
+        targetid,
+        dlpidr,
+        access_ports,
+    })
+}
+
+fn read_access_port_info(
+    interface: &mut dyn ArmDebugInterface,
+    address: FullyQualifiedApAddress,
+) -> Result<AccessPortInfo, ArmError> {
+    let raw_idr = interface.read_raw_ap_register(&address, IDR::ADDRESS)?;
+    let idr: IDR = raw_idr.try_into()?;
+
+    if idr.CLASS != ApClass::MemAp {
+        return Ok(AccessPortInfo::Other {
+            address,
+            idr: raw_idr,
+        });
+    }
+
+    let state = read_memory_ap_state(interface, &address);
+    Ok(AccessPortInfo::MemoryAp {
+        address,
+        ap_type: idr.TYPE,
+        state,
+    })
+}
+
+fn read_memory_ap_state(
+    interface: &mut dyn ArmDebugInterface,
+    address: &FullyQualifiedApAddress,
+) -> MemoryApState {
+    match read_memory_ap(interface, address) {
+        Ok(Some(component)) => MemoryApState::Accessible(component),
+        Ok(None) => MemoryApState::Disabled,
+        Err(error) => MemoryApState::Error(error.to_string()),
+    }
+}
+
+/// Reads the components behind a memory access port, or `None` if the access port is disabled.
+fn read_memory_ap(
+    interface: &mut dyn ArmDebugInterface,
+    address: &FullyQualifiedApAddress,
+) -> Result<Option<ComponentInfo>, ArmError> {
+    let component = {
+        let mut memory = interface.memory_interface(address)?;
+
+        // Check if the AP is accessible
+        let csw = memory.generic_status()?;
+        if !csw.DeviceEn {
+            return Ok(None);
+        }
+
+        let base_address = memory.base_address()?;
+        Component::try_parse(&mut *memory, base_address)?
+    };
+
+    read_component_info(interface, &component, address).map(Some)
+}
+
+fn read_component_info(
+    interface: &mut dyn ArmDebugInterface,
+    component: &Component,
+    access_port: &FullyQualifiedApAddress,
+) -> Result<ComponentInfo, ArmError> {
+    let mut info = ComponentInfo::new(component);
+
+    match component {
+        Component::Class1RomTable(id, table) | Component::Class9RomTable(id, table) => {
+            info.device_id = read_vendor_device_id(interface, id, access_port)?;
+            info.children = read_rom_table_entries(interface, table, access_port)?;
+        }
+        Component::CoresightComponent(id) | Component::GenericIPComponent(id) => {
+            let Some(part) = id.peripheral_id().determine_part() else {
+                return Ok(info);
+            };
+
+            match part.peripheral_type() {
+                PeripheralType::Scs => {
+                    let coresight_component =
+                        CoresightComponent::new(component.clone(), access_port.clone());
+                    let cpuid = Scs::new(interface, &coresight_component).cpuid()?;
+
+                    info.cpu = Some(CpuInfo {
+                        implementer: cpuid.implementer_name(),
+                        variant: cpuid.variant(),
+                        part: cpuid.part_name(),
+                        revision: cpuid.revision(),
+                    });
+                }
+                PeripheralType::MemAp => {
+                    let state = match access_port.ap() {
+                        ApAddress::V2(ApV2Address(None)) => {
+                            let address = FullyQualifiedApAddress::v2_with_dp(
+                                access_port.dp(),
+                                ApV2Address::new(id.component_address()),
+                            );
+                            read_memory_ap_state(interface, &address)
+                        }
+                        _ => MemoryApState::Error(
+                            "Nested memory APs are not yet supported.".to_string(),
+                        ),
+                    };
+                    info.memory_ap = Some(Box::new(state));
+                }
+                PeripheralType::Rom => {
+                    let table = {
+                        let mut memory = interface.memory_interface(access_port)?;
+                        RomTable::try_parse(
+                            memory.as_mut() as &mut dyn ArmMemoryInterface,
+                            id.component_address(),
+                        )?
+                    };
+
+                    info.device_id = read_vendor_device_id(interface, id, access_port)?;
+                    info.children = read_rom_table_entries(interface, &table, access_port)?;
+                }
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+
+    Ok(info)
+}
+
+fn read_rom_table_entries(
+    interface: &mut dyn ArmDebugInterface,
+    table: &RomTable,
+    access_port: &FullyQualifiedApAddress,
+) -> Result<Vec<ComponentInfo>, ArmError> {
+    table
+        .entries()
+        .map(|entry| read_component_info(interface, entry.component(), access_port))
+        .collect()
+}
+
+/// Reads vendor specific identification registers, which are located next to some ROM tables.
+fn read_vendor_device_id(
+    interface: &mut dyn ArmDebugInterface,
+    id: &ComponentId,
+    access_port: &FullyQualifiedApAddress,
+) -> Result<Option<u32>, ArmError> {
+    let Some(part) = id.peripheral_id().determine_part() else {
+        return Ok(None);
+    };
+
+    if part.peripheral_type() == PeripheralType::Custom && part.name() == "Atmel DSU" {
+        use crate::vendor::microchip::sequences::atsam::DsuDid;
+
+        let did = interface
+            .memory_interface(access_port)?
+            .read_word_32(DsuDid::ADDRESS)?;
+
+        return Ok(Some(did));
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,147 @@
+//! Collects information about an attached device.
+//!
+//! [`DeviceInfo`] gathers everything probe-rs can find out about a device into a single
+//! structured report: the IDs of the debug and access ports, the CoreSight components found in
+//! the ROM tables, the memory layout of the target, and vendor specific information like the
+//! unique device ID or the silicon revision.
+
+pub mod arm;
+
+use std::collections::BTreeSet;
+
+use probe_rs_target::CoreAccessOptions;
+
+use crate::{
+    Error, Session, Target, architecture::arm::dp::DpAddress, config::MemoryRegion,
+    core::Architecture, vendor,
+};
+
+/// A structured report of the information probe-rs can read from an attached device.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceInfo {
+    /// The ARM debug ports of the device.
+    pub arm_debug_ports: Vec<arm::DebugPortInfo>,
+    /// The IDCODE of the RISC-V debug transport module.
+    pub riscv_idcode: Option<u32>,
+    /// The IDCODE of the Xtensa debug module.
+    pub xtensa_idcode: Option<u32>,
+    /// The target the session is attached to, as described by the target description.
+    pub target: Option<TargetInfo>,
+    /// Information read from vendor specific registers.
+    pub vendor: Option<VendorDeviceInfo>,
+}
+
+impl DeviceInfo {
+    /// Collects all available information about the device the session is attached to.
+    pub fn from_session(session: &mut Session) -> Result<Self, Error> {
+        let mut info = DeviceInfo {
+            target: Some(TargetInfo::from_target(session.target())),
+            ..Default::default()
+        };
+
+        match session.architecture() {
+            Architecture::Arm => {
+                let dp_addresses = arm_debug_ports(session.target());
+                let interface = session.get_arm_interface()?;
+                for dp in dp_addresses {
+                    info.arm_debug_ports
+                        .push(arm::read_debug_port_info(interface, dp)?);
+                }
+            }
+            Architecture::Riscv => {
+                info.riscv_idcode = session.get_riscv_interface(0)?.read_idcode()?;
+            }
+            Architecture::Xtensa => {
+                info.xtensa_idcode = Some(session.get_xtensa_interface(0)?.read_idcode()?);
+            }
+        }
+
+        info.vendor = vendor::try_read_device_info(session)?;
+
+        Ok(info)
+    }
+}
+
+/// The name and memory layout of a target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetInfo {
+    /// The name of the target.
+    pub name: String,
+    /// The total size of all non-volatile memory regions, in bytes.
+    pub nvm_size: u64,
+    /// The total size of all RAM regions, in bytes.
+    pub ram_size: u64,
+}
+
+impl TargetInfo {
+    fn from_target(target: &Target) -> Self {
+        let size = |filter: fn(&MemoryRegion) -> bool| {
+            target
+                .memory_map
+                .iter()
+                .filter(|region| filter(region))
+                .map(|region| {
+                    let range = region.address_range();
+                    range.end - range.start
+                })
+                .sum::<u64>()
+        };
+
+        Self {
+            name: target.name.clone(),
+            nvm_size: size(MemoryRegion::is_nvm),
+            ram_size: size(MemoryRegion::is_ram),
+        }
+    }
+}
+
+/// Device information which can only be read using vendor specific registers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VendorDeviceInfo {
+    /// The unique ID of the device.
+    pub unique_id: Option<Vec<u8>>,
+    /// The part number or name of the device, as reported by the device itself.
+    pub part: Option<String>,
+    /// The silicon revision of the device.
+    pub silicon_revision: Option<String>,
+    /// The size of the non-volatile memory, as reported by the device itself.
+    pub nvm_size: Option<u64>,
+    /// The size of the RAM, as reported by the device itself.
+    pub ram_size: Option<u64>,
+    /// Whether debug access to the device is restricted.
+    pub security_state: Option<SecurityState>,
+}
+
+/// The debug access protection state of a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecurityState {
+    /// Debug access is not restricted.
+    Unlocked,
+    /// Debug access is restricted, e.g. by a readout protection.
+    Locked,
+}
+
+/// A procedure which makes a secured device accessible again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecurityRecovery {
+    /// Erase the whole device, which also removes the protection.
+    ///
+    /// This requires the [`Permissions::allow_erase_all`](crate::Permissions::allow_erase_all)
+    /// permission.
+    EraseAll,
+}
+
+/// Returns the addresses of all ARM debug ports used by the cores of the target.
+fn arm_debug_ports(target: &Target) -> BTreeSet<DpAddress> {
+    target
+        .cores
+        .iter()
+        .filter_map(|core| match &core.core_access_options {
+            CoreAccessOptions::Arm(options) => Some(match options.targetsel {
+                None => DpAddress::Default,
+                Some(x) => DpAddress::Multidrop(x),
+            }),
+            _ => None,
+        })
+        .collect()
+}
@@ -1,8 +1,10 @@
 use crate::architecture::arm::ArmError;
 use crate::architecture::riscv::communication_interface::RiscvError;
 use crate::architecture::xtensa::communication_interface::XtensaError;
+use crate::cancellation::CancelledError;
 use crate::config::RegistryError;
 use crate::core::memory_mapped_registers::RegisterAddressOutOfBounds;
+use crate::device_info::SecurityRecovery;
 use crate::memory::{InvalidDataLengthError, MemoryNotAlignedError};
 use crate::probe::DebugProbeError;
 
@@ -23,12 +25,32 @@ pub enum Error {
     CoreNotFound(usize),
     /// Unable to load specification for chip
     ChipNotFound(#[from] RegistryError),
+    /// Failed to load the peripherals of the target.
+    Peripherals(#[source] RegistryError),
+    /// The access of {len} bytes at address {address:#010x} is outside the memory map of the target.
+    OutsideMemoryMap {
+        /// The address of the access.
+        address: u64,
+        /// The number of bytes accessed.
+        len: u64,
+    },
     /// An operation was not performed because the required permissions were not given: {0}.
     ///
     /// This can for example happen when the core is locked and needs to be erased to be unlocked.
     /// Then the correct permission needs to be given to automatically unlock the core to prevent accidental erases.
     #[ignore_extra_doc_attributes]
     MissingPermissions(String),
+    /// The device is secured, debug access is blocked.
+    ///
+    /// If the device supports a recovery procedure, it is returned in `available_recovery`.
+    /// Recovery usually erases the device.
+    #[ignore_extra_doc_attributes]
+    DeviceSecured {
+        /// The procedure which makes the device accessible again, if any.
+        available_recovery: Option<SecurityRecovery>,
+    },
+    /// The memory at address {0:#010x} is not writable by the core, so no software breakpoint can be set there.
+    SoftwareBreakpointNotWritable(u64),
     /// An error that is not architecture specific occurred: {0}
     GenericCoreError(String),
     /// Errors accessing core register: {0}
@@ -56,6 +78,9 @@ pub enum Error {
     /// The data buffer had an invalid length.
     #[error(transparent)]
     InvalidDataLength(#[from] InvalidDataLengthError),
+    /// The operation was cancelled.
+    #[error(transparent)]
+    Cancelled(#[from] CancelledError),
     /// Failed to write CPU register {register}.
     WriteRegister {
         /// The name of the register that was tried to be written.
@@ -82,3 +107,30 @@ impl From<ArmError> for Error {
         }
     }
 }
+
+impl Error {
+    /// Returns whether the error is caused by a transfer which was corrupted on the wire, see
+    /// [`ArmError::is_link_error`].
+    pub fn is_link_error(&self) -> bool {
+        matches!(self, Error::Arm(error) if error.is_link_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::architecture::arm::{DapError, dp::DebugPortError};
+
+    #[test]
+    fn link_errors() {
+        assert!(Error::from(ArmError::Dap(DapError::IncorrectParity)).is_link_error());
+        assert!(
+            Error::from(ArmError::DebugPort(DebugPortError::Dap(
+                DapError::IncorrectParity
+            )))
+            .is_link_error()
+        );
+        assert!(!Error::from(ArmError::Dap(DapError::FaultResponse)).is_link_error());
+        assert!(!Error::from(ArmError::Timeout).is_link_error());
+    }
+}
@@ -138,6 +138,55 @@ impl FlashLayout {
     pub fn data_blocks(&self) -> &[FlashDataBlockSpan] {
         &self.data_blocks
     }
+
+    /// Removes the sectors in which all pages are marked in `unchanged_pages`, together with
+    /// their pages, fills and data blocks.
+    ///
+    /// `unchanged_pages` contains one entry for each page of the layout.
+    pub(super) fn remove_unchanged_sectors(&mut self, unchanged_pages: &[bool]) {
+        fn contains(sector: &FlashSector, address: u64) -> bool {
+            (sector.address..sector.address + sector.size).contains(&address)
+        }
+
+        let pages = &self.pages;
+        self.sectors.retain(|sector| {
+            pages
+                .iter()
+                .zip(unchanged_pages)
+                .any(|(page, unchanged)| !unchanged && contains(sector, page.address))
+        });
+
+        let mut page_indices = vec![None; self.pages.len()];
+        let mut kept_pages = Vec::new();
+        for (index, page) in std::mem::take(&mut self.pages).into_iter().enumerate() {
+            if self
+                .sectors
+                .iter()
+                .any(|sector| contains(sector, page.address))
+            {
+                page_indices[index] = Some(kept_pages.len());
+                kept_pages.push(page);
+            }
+        }
+        self.pages = kept_pages;
+
+        self.fills
+            .retain_mut(|fill| match page_indices[fill.page_index] {
+                Some(index) => {
+                    fill.page_index = index;
+                    true
+                }
+                None => false,
+            });
+
+        let sectors = &self.sectors;
+        self.data_blocks.retain(|block| {
+            sectors.iter().any(|sector| {
+                block.address < sector.address + sector.size
+                    && sector.address < block.address + block.size
+            })
+        });
+    }
 }
 
 /// A block of data that is to be written to flash.
@@ -163,6 +212,8 @@ impl FlashDataBlockSpan {
 #[derive(Default)]
 pub(super) struct FlashBuilder {
     pub(super) data: BTreeMap<u64, Vec<u8>>,
+    /// The address ranges in which the regions restore their unwritten bytes.
+    restore_unwritten: Vec<Range<u64>>,
 }
 
 impl FlashBuilder {
@@ -170,9 +221,26 @@ impl FlashBuilder {
     pub(super) fn new() -> Self {
         Self {
             data: BTreeMap::new(),
+            restore_unwritten: Vec::new(),
         }
     }
 
+    /// Restores the bytes of the flash regions overlapping `range` that are erased, but not
+    /// written.
+    ///
+    /// The affected sectors are read before they are erased, and the bytes that are not part
+    /// of the staged data are written back together with it.
+    pub(super) fn restore_unwritten_bytes(&mut self, range: Range<u64>) {
+        self.restore_unwritten.push(range);
+    }
+
+    /// Check whether the region at `range` restores its unwritten bytes.
+    pub(crate) fn restores_unwritten_bytes(&self, range: &Range<u64>) -> bool {
+        self.restore_unwritten
+            .iter()
+            .any(|restored| restored.start < range.end && range.start < restored.end)
+    }
+
     /// Stages a chunk of data to be programmed.
     ///
     /// The chunk can cross flash boundaries as long as one flash region connects to another flash region.
@@ -1144,4 +1212,58 @@ mod tests {
             }
         )
     }
+
+    #[test]
+    fn restore_unwritten_bytes_per_region() {
+        let mut flash_builder = FlashBuilder::new();
+        flash_builder.restore_unwritten_bytes(0x1000..0x1004);
+
+        assert!(flash_builder.restores_unwritten_bytes(&(0x0..0x2000)));
+        assert!(flash_builder.restores_unwritten_bytes(&(0x1003..0x2000)));
+        assert!(!flash_builder.restores_unwritten_bytes(&(0x0..0x1000)));
+        assert!(!flash_builder.restores_unwritten_bytes(&(0x1004..0x2000)));
+    }
+
+    #[test]
+    fn remove_unchanged_sectors() {
+        let (region, flash_algorithm) = assemble_demo_flash1();
+        let mut flash_builder = FlashBuilder::new();
+        flash_builder.add_data(0, &[1; 0x1000]).unwrap();
+        flash_builder.add_data(0x1000, &[2]).unwrap();
+        let mut flash_layout = flash_builder
+            .build_sectors_and_pages(&region, &flash_algorithm, true)
+            .unwrap();
+
+        // The first sector is up to date, the second one has to be programmed.
+        let mut unchanged_pages = vec![true; 8];
+        unchanged_pages[4] = false;
+        flash_layout.remove_unchanged_sectors(&unchanged_pages);
+
+        assert_eq!(
+            flash_layout.sectors,
+            vec![FlashSector {
+                address: 0x1000,
+                size: 0x1000,
+            }]
+        );
+        assert_eq!(
+            flash_layout
+                .pages
+                .iter()
+                .map(|page| page.address)
+                .collect::<Vec<_>>(),
+            vec![0x1000, 0x1400, 0x1800, 0x1C00]
+        );
+        assert_eq!(flash_layout.fills[0].address, 0x1001);
+        assert_eq!(flash_layout.fills[0].page_index, 0);
+        assert_eq!(flash_layout.fills[3].page_index, 3);
+        // Both writes are merged into one block, which extends into the second sector.
+        assert_eq!(
+            flash_layout.data_blocks,
+            vec![FlashDataBlockSpan {
+                address: 0,
+                size: 0x1001,
+            }]
+        );
+    }
 }
@@ -0,0 +1,187 @@
+//! Checkpoints that allow an interrupted download to be resumed.
+//!
+//! While programming, the address ranges of all sectors that were erased and programmed
+//! completely are recorded in a file. A later download of the same image can skip these
+//! sectors.
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::crc;
+
+/// The contents of a checkpoint file.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct CheckpointState {
+    /// Identifies the target and image the checkpoint belongs to.
+    image: u32,
+    /// The sorted, non-overlapping ranges of completed sectors.
+    completed: Vec<Range<u64>>,
+}
+
+/// Tracks which sectors of an image have been programmed.
+pub(super) struct DownloadCheckpoint {
+    path: PathBuf,
+    state: CheckpointState,
+}
+
+impl DownloadCheckpoint {
+    /// Opens the checkpoint at `path` for the image identified by `image`.
+    ///
+    /// If `resume` is `true` and the file records the progress of the same image, the
+    /// recorded sectors are considered completed. Otherwise the checkpoint starts empty.
+    pub(super) fn open(path: &Path, image: u32, resume: bool) -> Self {
+        let mut state = CheckpointState {
+            image,
+            completed: vec![],
+        };
+
+        if resume {
+            match std::fs::read_to_string(path) {
+                Ok(contents) => match serde_yaml::from_str::<CheckpointState>(&contents) {
+                    Ok(saved) if saved.image == image => state = saved,
+                    Ok(_) => tracing::warn!(
+                        "The download checkpoint {} belongs to a different image, starting from the beginning",
+                        path.display()
+                    ),
+                    Err(error) => tracing::warn!(
+                        "Failed to parse the download checkpoint {}: {error}",
+                        path.display()
+                    ),
+                },
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                    tracing::info!("No download checkpoint found, starting from the beginning");
+                }
+                Err(error) => tracing::warn!(
+                    "Failed to read the download checkpoint {}: {error}",
+                    path.display()
+                ),
+            }
+        }
+
+        let checkpoint = Self {
+            path: path.to_path_buf(),
+            state,
+        };
+        checkpoint.save();
+        checkpoint
+    }
+
+    /// Returns whether no sectors are recorded as completed.
+    pub(super) fn is_empty(&self) -> bool {
+        self.state.completed.is_empty()
+    }
+
+    /// Returns whether the sector at `range` was completed before.
+    pub(super) fn is_completed(&self, range: &Range<u64>) -> bool {
+        self.state
+            .completed
+            .iter()
+            .any(|completed| completed.start <= range.start && range.end <= completed.end)
+    }
+
+    /// Records that the sector at `range` has been erased and programmed.
+    pub(super) fn complete(&mut self, range: Range<u64>) {
+        if self.is_completed(&range) {
+            return;
+        }
+
+        let completed = &mut self.state.completed;
+        let index = completed.partition_point(|completed| completed.start < range.start);
+        completed.insert(index, range);
+
+        // Merge adjacent ranges, to keep the file small for images with many sectors.
+        let mut merged: Vec<Range<u64>> = Vec::with_capacity(completed.len());
+        for range in completed.drain(..) {
+            match merged.last_mut() {
+                Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        *completed = merged;
+
+        self.save();
+    }
+
+    /// Removes the checkpoint file after the download has finished.
+    pub(super) fn finish(self) {
+        if let Err(error) = std::fs::remove_file(&self.path) {
+            tracing::warn!(
+                "Failed to remove the download checkpoint {}: {error}",
+                self.path.display()
+            );
+        }
+    }
+
+    /// Writes the checkpoint to its file. Failing to do so does not abort the download, it
+    /// just cannot be resumed.
+    fn save(&self) {
+        let result = serde_yaml::to_string(&self.state)
+            .map_err(std::io::Error::other)
+            .and_then(|contents| {
+                if let Some(parent) = self.path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+
+                // Replace the file atomically, so an interruption never leaves a truncated file.
+                let mut temporary = self.path.clone().into_os_string();
+                temporary.push(".tmp");
+                std::fs::write(&temporary, contents)?;
+                std::fs::rename(&temporary, &self.path)
+            });
+
+        if let Err(error) = result {
+            tracing::warn!(
+                "Failed to write the download checkpoint {}: {error}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+/// Computes an identifier for the data that is downloaded to `target_name`.
+pub(super) fn image_id<'data>(
+    target_name: &str,
+    data: impl Iterator<Item = (u64, &'data [u8])>,
+) -> u32 {
+    let mut id = crc::crc32(0, target_name.as_bytes());
+    for (address, data) in data {
+        id = crc::crc32(id, &address.to_le_bytes());
+        id = crc::crc32(id, data);
+    }
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_completed_sectors() {
+        let mut checkpoint = DownloadCheckpoint {
+            path: std::env::temp_dir().join(format!("probe-rs-checkpoint-{}", std::process::id())),
+            state: CheckpointState::default(),
+        };
+
+        checkpoint.complete(0x2000..0x3000);
+        checkpoint.complete(0x0..0x1000);
+        checkpoint.complete(0x1000..0x2000);
+        checkpoint.complete(0x8000..0x9000);
+
+        assert_eq!(
+            checkpoint.state.completed,
+            vec![0x0..0x3000, 0x8000..0x9000]
+        );
+        assert!(checkpoint.is_completed(&(0x1000..0x2000)));
+        assert!(!checkpoint.is_completed(&(0x3000..0x4000)));
+
+        let resumed = DownloadCheckpoint::open(&checkpoint.path, 0, true);
+        assert_eq!(resumed.state, checkpoint.state);
+
+        let other_image = DownloadCheckpoint::open(&checkpoint.path, 1, true);
+        assert!(other_image.state.completed.is_empty());
+
+        other_image.finish();
+    }
+}
@@ -0,0 +1,84 @@
+//! CRC-32 computations used to verify flash contents on the target.
+//!
+//! Both the `Crc32()` flash algorithm function and the RAM stub below take the start address,
+//! the length and a previous CRC value in `r0`-`r2`, and return the updated CRC-32 (IEEE 802.3)
+//! of the memory in `r0`. Passing the result back in as the previous value continues the
+//! computation, the same way [`crc32`] does on the host.
+
+/// A CRC-32 routine for memory mapped flash, for cores that run Thumb code.
+///
+/// Only uses Thumb-1 instructions, so it runs on every Cortex-M core. It is called like a flash
+/// algorithm function, and returns to the breakpoint at the start of the algorithm.
+///
+/// ```text
+///     mvns  r2, r2
+///     ldr   r3, =0xEDB88320
+/// next_byte:
+///     cmp   r1, #0
+///     beq   done
+///     ldrb  r4, [r0]
+///     adds  r0, #1
+///     eors  r2, r4
+///     movs  r4, #8
+/// next_bit:
+///     lsrs  r2, r2, #1
+///     bcc   no_xor
+///     eors  r2, r3
+/// no_xor:
+///     subs  r4, #1
+///     bne   next_bit
+///     subs  r1, #1
+///     b     next_byte
+/// done:
+///     mvns  r0, r2
+///     bx    lr
+/// ```
+pub(super) const THUMB_CRC32_STUB: [u8; 40] = [
+    0xD2, 0x43, // mvns  r2, r2
+    0x08, 0x4B, // ldr   r3, [pc, #32]
+    0x00, 0x29, // cmp   r1, #0
+    0x0A, 0xD0, // beq   done
+    0x04, 0x78, // ldrb  r4, [r0]
+    0x01, 0x30, // adds  r0, #1
+    0x62, 0x40, // eors  r2, r4
+    0x08, 0x24, // movs  r4, #8
+    0x52, 0x08, // lsrs  r2, r2, #1
+    0x00, 0xD3, // bcc   no_xor
+    0x5A, 0x40, // eors  r2, r3
+    0x01, 0x3C, // subs  r4, #1
+    0xFA, 0xD1, // bne   next_bit
+    0x01, 0x39, // subs  r1, #1
+    0xF2, 0xE7, // b     next_byte
+    0xD0, 0x43, // mvns  r0, r2
+    0x70, 0x47, // bx    lr
+    0xC0, 0x46, // nop
+    0x20, 0x83, 0xB8, 0xED, // .word 0xEDB88320
+];
+
+/// Continues the CRC-32 computation of `crc` over `data`. Start with a `crc` of 0.
+pub(super) fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let carry = crc & 1 != 0;
+            crc >>= 1;
+            if carry {
+                crc ^= 0xEDB8_8320;
+            }
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::crc32;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(0, b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(crc32(0, b"1234"), b"56789"), 0xCBF4_3926);
+        assert_eq!(crc32(0, &[]), 0);
+    }
+}
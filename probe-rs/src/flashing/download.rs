@@ -2,16 +2,18 @@ use object::{
     Endianness, Object, ObjectSection, elf::FileHeader32, elf::FileHeader64, elf::PT_LOAD,
     read::elf::ElfFile, read::elf::FileHeader, read::elf::ProgramHeader,
 };
-use probe_rs_target::{InstructionSet, MemoryRange};
+use probe_rs_target::{AddressAlias, InstructionSet, MemoryRange};
 use serde::{Deserialize, Serialize};
 
 use std::{
     fs::File,
+    ops::Range,
     path::{Path, PathBuf},
     str::FromStr,
 };
 
 use super::*;
+use crate::CancellationToken;
 use crate::session::Session;
 
 /// Extended options for flashing a binary file.
@@ -39,6 +41,9 @@ pub struct IdfOptions {
 pub struct ElfOptions {
     /// Sections to skip flashing
     pub skip_sections: Vec<String>,
+    /// Additional address aliases, used to place data linked for an alias at its physical
+    /// address. These take precedence over the aliases of the target.
+    pub address_aliases: Vec<AddressAlias>,
 }
 
 /// A finite list of all the available binary formats probe-rs understands.
@@ -76,14 +81,10 @@ impl FromStr for FormatKind {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match &s.to_lowercase()[..] {
-            "bin" | "binary" => Ok(Self::Bin),
-            "hex" | "ihex" | "intelhex" => Ok(Self::Hex),
-            "elf" => Ok(Self::Elf),
-            "uf2" => Ok(Self::Uf2),
-            "idf" | "esp-idf" | "espidf" => Ok(Self::Idf),
-            _ => Err(format!("Format '{s}' is unknown.")),
-        }
+        [Self::Bin, Self::Hex, Self::Elf, Self::Uf2, Self::Idf]
+            .into_iter()
+            .find(|kind| kind.names().iter().any(|name| name.eq_ignore_ascii_case(s)))
+            .ok_or_else(|| format!("Format '{s}' is unknown."))
     }
 }
 
@@ -153,6 +154,35 @@ pub enum FileDownloadError {
     /// Target {0} does not support the esp-idf format
     IdfUnsupported(String),
 
+    /// The UF2 file is invalid: {0}.
+    InvalidUf2(String),
+
+    /// The UF2 file contains no data for the target {target}, only for the families {families:#010x?}.
+    Uf2FamilyMismatch {
+        /// The name of the target.
+        target: String,
+        /// The family IDs found in the file.
+        families: Vec<u32>,
+    },
+
+    /// The data at {address:#010x}..{end:#010x} crosses the boundary of the address alias {alias:#010x?}.
+    AliasBoundary {
+        /// The start address of the data.
+        address: u64,
+        /// The end address of the data.
+        end: u64,
+        /// The aliased address range.
+        alias: Range<u64>,
+    },
+
+    /// The data at {address:#010x} is remapped to {target:#010x}, which is outside of the 32 bit address space.
+    AliasOutOfRange {
+        /// The address of the data in the image.
+        address: u64,
+        /// The address the data is remapped to.
+        target: u64,
+    },
+
     /// No loadable segments were found in the ELF file.
     #[ignore_extra_doc_attributes]
     ///
@@ -221,6 +251,31 @@ pub struct DownloadOptions<'p> {
     pub verify: bool,
     /// Disable double buffering when loading flash.
     pub disable_double_buffering: bool,
+    /// Before flashing, read back the flash contents of each sector, and only erase and program
+    /// the sectors whose contents differ from the image.
+    ///
+    /// This has no effect if the chip is erased as a whole.
+    pub incremental: bool,
+    /// Records the sectors that have been erased and programmed completely in this file, so an
+    /// interrupted download can be resumed. The file is removed once the download succeeded.
+    pub checkpoint: Option<PathBuf>,
+    /// Skips the sectors that the [`checkpoint`](DownloadOptions::checkpoint) file records as
+    /// programmed by an earlier, interrupted download of the same image.
+    ///
+    /// The contents of the flash are not checked. If the flash was modified since the
+    /// interruption, the affected sectors will not be up to date.
+    pub resume: bool,
+    /// How the watchdogs listed in the target description are handled, so that they don't reset
+    /// the target while it is flashed.
+    ///
+    /// Refreshed watchdogs are only serviced between calls of the flash algorithm, so a single
+    /// call that takes longer than the watchdog timeout, like a long chip erase, can still reset
+    /// the target.
+    pub watchdog: WatchdogMode,
+    /// Cancels the download once cancelled, between the erase and program operations, so the
+    /// flash algorithm isn't interrupted. The download can be resumed from a
+    /// [`checkpoint`](DownloadOptions::checkpoint).
+    pub cancellation: CancellationToken,
 }
 
 impl DownloadOptions<'_> {
@@ -228,15 +283,23 @@ impl DownloadOptions<'_> {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Enables or disables incremental flashing, see [`DownloadOptions::incremental`].
+    pub fn incremental(mut self, incremental: bool) -> Self {
+        self.incremental = incremental;
+        self
+    }
 }
 
 /// Builds a new flash loader for the given target and path. This
 /// will check the path for validity and check what pages have to be
 /// flashed etc.
+///
+/// `format` can be a [`Format`], or a loader of a custom format from an [`ImageFormatRegistry`].
 pub fn build_loader(
     session: &mut Session,
     path: impl AsRef<Path>,
-    format: Format,
+    format: impl ImageLoader,
     image_instruction_set: Option<InstructionSet>,
 ) -> Result<FlashLoader, FileDownloadError> {
     // Create the flash loader
@@ -274,7 +337,8 @@ pub fn download_file_with_options(
     format: impl Into<Format>,
     options: DownloadOptions,
 ) -> Result<(), FileDownloadError> {
-    let loader = build_loader(session, path, format.into(), None)?;
+    let format: Format = format.into();
+    let loader = build_loader(session, path, format, None)?;
 
     loader
         .commit(session, options)
@@ -1,3 +1,4 @@
+use crate::CancelledError;
 use crate::config::{NvmRegion, RamRegion, TargetDescriptionSource};
 use crate::error;
 use std::ops::Range;
@@ -204,4 +205,17 @@ pub enum FlashError {
         /// The operation that caused the stack overflow.
         operation: &'static str,
     },
+    /// Servicing a watchdog of the target failed.
+    #[error("Failed to service the watchdog {name}.")]
+    Watchdog {
+        /// The name of the watchdog.
+        name: String,
+        /// The source error of this error.
+        #[source]
+        source: Box<dyn std::error::Error + 'static + Send + Sync>,
+    },
+    /// Flashing was cancelled with the [`CancellationToken`](crate::CancellationToken) of the
+    /// [`DownloadOptions`](crate::flashing::DownloadOptions).
+    #[error(transparent)]
+    Cancelled(#[from] CancelledError),
 }
@@ -42,6 +42,8 @@ pub struct FlashAlgorithm {
     pub pc_read: Option<u64>,
     /// Address of the `BlankCheck()` entry point. Optional.
     pub pc_blank_check: Option<u64>,
+    /// Address of the (non-standard) `Crc32()` entry point. Optional.
+    pub pc_crc: Option<u64>,
     /// Initial value of the R9 register for calling flash algo entry points, which
     /// determines where the position-independent data resides.
     pub static_base: u64,
@@ -405,6 +407,7 @@ impl FlashAlgorithm {
             pc_verify: raw.pc_verify.map(|v| code_start + v),
             pc_read: raw.pc_read.map(|v| code_start + v),
             pc_blank_check: raw.pc_blank_check.map(|v| code_start + v),
+            pc_crc: raw.pc_crc.map(|v| code_start + v),
             static_base: code_start + raw.data_section_offset,
             stack_top,
             stack_size,
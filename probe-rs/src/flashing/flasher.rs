@@ -2,16 +2,24 @@ use probe_rs_target::{RawFlashAlgorithm, TransferEncoding};
 use tracing::Level;
 use zerocopy::IntoBytes;
 
-use super::{FlashAlgorithm, FlashBuilder, FlashError, FlashPage, FlashProgress};
+use super::checkpoint::DownloadCheckpoint;
+use super::crc::{self, THUMB_CRC32_STUB};
+use super::watchdog::{WatchdogSequence, WatchdogService};
+use super::{
+    DownloadOptions, FlashAlgorithm, FlashBuilder, FlashError, FlashPage, FlashProgress,
+    PipelineStats,
+};
 use crate::config::NvmRegion;
 use crate::error::Error;
 use crate::flashing::encoder::FlashEncoder;
 use crate::flashing::{FlashLayout, FlashSector};
 use crate::memory::MemoryInterface;
 use crate::rtt::{self, Rtt, ScanRegion};
+use crate::{CancellationToken, CoreStatus, Target};
 use crate::{Core, InstructionSet, core::CoreRegisters, session::Session};
-use crate::{CoreStatus, Target};
+use std::collections::VecDeque;
 use std::marker::PhantomData;
+use std::ops::Range;
 use std::{
     fmt::Debug,
     time::{Duration, Instant},
@@ -104,6 +112,8 @@ impl FlashData {
 pub(super) struct LoadedRegion {
     pub region: NvmRegion,
     pub data: FlashData,
+    /// Whether the bytes of the erased sectors that are not written are restored.
+    pub restore_unwritten_bytes: bool,
 }
 
 impl LoadedRegion {
@@ -120,6 +130,10 @@ pub(super) struct Flasher {
     pub(super) flash_algorithm: FlashAlgorithm,
     pub(super) loaded: bool,
     pub(super) regions: Vec<LoadedRegion>,
+    /// The watchdogs that are serviced before each erase and program call.
+    pub(super) watchdog: WatchdogService,
+    /// Checked before each erase and program call.
+    pub(super) cancellation: CancellationToken,
 }
 
 /// The byte used to fill the stack when checking for stack overflows.
@@ -142,6 +156,8 @@ impl Flasher {
             flash_algorithm,
             loaded: false,
             regions: Vec::new(),
+            watchdog: WatchdogService::default(),
+            cancellation: CancellationToken::default(),
         })
     }
 
@@ -209,16 +225,8 @@ impl Flasher {
             // Fill the stack with known data.
             let stack_bottom = algo.stack_top - algo.stack_size;
             if algo.stack_size & 3 == 0 {
-                let fill = vec![
-                    u32::from_ne_bytes([
-                        STACK_FILL_BYTE,
-                        STACK_FILL_BYTE,
-                        STACK_FILL_BYTE,
-                        STACK_FILL_BYTE
-                    ]);
-                    algo.stack_size as usize / 4
-                ];
-                core.write_32(stack_bottom, &fill)
+                let pattern = u32::from_ne_bytes([STACK_FILL_BYTE; 4]);
+                core.fill_32(stack_bottom, pattern, algo.stack_size as usize / 4)
                     .map_err(FlashError::Core)?;
             } else {
                 let fill = vec![STACK_FILL_BYTE; algo.stack_size as usize];
@@ -252,6 +260,8 @@ impl Flasher {
             rtt: None,
             progress,
             flash_algorithm: &self.flash_algorithm,
+            watchdog: &self.watchdog,
+            cancellation: &self.cancellation,
             _operation: PhantomData,
         };
 
@@ -356,26 +366,43 @@ impl Flasher {
 
     /// Program the contents of given `FlashBuilder` to the flash.
     ///
-    /// If `restore_unwritten_bytes` is `true`, all bytes of a sector,
+    /// In the regions that restore unwritten bytes, all bytes of a sector,
     /// that are not to be written during flashing will be read from the flash first
     /// and written again once the sector is erased.
+    ///
+    /// If a `checkpoint` is given, the sectors it records as completed are skipped, and
+    /// newly completed sectors are recorded in it.
     pub(super) fn program(
         &mut self,
         session: &mut Session,
         progress: &FlashProgress,
-        restore_unwritten_bytes: bool,
+        options: &DownloadOptions,
         enable_double_buffering: bool,
         skip_erasing: bool,
-        verify: bool,
+        checkpoint: Option<&mut DownloadCheckpoint>,
     ) -> Result<(), FlashError> {
         tracing::debug!("Starting program procedure.");
 
+        let restore_unwritten_bytes = self
+            .regions
+            .iter()
+            .any(|region| region.restore_unwritten_bytes);
+
         tracing::debug!("Double Buffering enabled: {:?}", enable_double_buffering);
         tracing::debug!(
             "Restoring unwritten bytes enabled: {:?}",
             restore_unwritten_bytes
         );
 
+        // Sectors are only skipped if they would be erased, otherwise their contents are unknown.
+        if options.incremental && !skip_erasing {
+            self.skip_unchanged_sectors(session, progress)?;
+        }
+
+        if let Some(checkpoint) = checkpoint.as_deref() {
+            self.skip_completed_sectors(checkpoint);
+        }
+
         if restore_unwritten_bytes {
             self.fill_unwritten(session, progress)?;
         }
@@ -387,20 +414,115 @@ impl Flasher {
         }
 
         // Flash all necessary pages.
-        self.do_program(session, progress, enable_double_buffering)?;
+        self.do_program(session, progress, enable_double_buffering, checkpoint)?;
 
-        if verify && !self.verify(session, progress, !restore_unwritten_bytes)? {
+        if options.verify && !self.verify(session, progress, false)? {
             return Err(FlashError::Verify);
         }
 
         Ok(())
     }
 
-    /// Fills all the unwritten bytes in `layout`.
+    /// Removes the sectors whose contents already match the image from the flash layout, so
+    /// they are neither erased nor programmed.
     ///
-    /// If `restore_unwritten_bytes` is `true`, all bytes of the layout's page,
-    /// that are not to be written during flashing will be read from the flash first
-    /// and written again once the page is programmed.
+    /// Bytes which are not part of the image are ignored when comparing the contents.
+    fn skip_unchanged_sectors(
+        &mut self,
+        session: &mut Session,
+        progress: &FlashProgress,
+    ) -> Result<(), FlashError> {
+        fn unchanged_pages(
+            regions: &[LoadedRegion],
+            mut read: impl FnMut(u64, &mut [u8]) -> Result<(), FlashError>,
+        ) -> Result<Vec<Vec<bool>>, FlashError> {
+            let mut unchanged = Vec::with_capacity(regions.len());
+            for region in regions {
+                let layout = region.data.layout();
+                let mut region_unchanged = Vec::with_capacity(layout.pages.len());
+                for (idx, page) in layout.pages.iter().enumerate() {
+                    let data = page.data();
+                    let mut read_back = vec![0; data.len()];
+                    read(page.address(), &mut read_back)?;
+
+                    // Fills are not part of the image, so their contents are allowed to differ.
+                    for fill in layout.fills() {
+                        if fill.page_index() != idx {
+                            continue;
+                        }
+
+                        let fill_offset = (fill.address() - page.address()) as usize;
+                        let fill_size = fill.size() as usize;
+                        read_back[fill_offset..][..fill_size]
+                            .copy_from_slice(&data[fill_offset..][..fill_size]);
+                    }
+
+                    region_unchanged.push(data == read_back);
+                }
+                unchanged.push(region_unchanged);
+            }
+
+            Ok(unchanged)
+        }
+
+        let unchanged = if self.flash_algorithm.pc_read.is_some() {
+            self.run_verify(session, progress, |active, data| {
+                unchanged_pages(data, |address, data| active.read_flash(address, data))
+            })?
+        } else {
+            // Not using a flash algorithm function, so there's no need to go
+            // through ActiveFlasher.
+            let mut core = session.core(0).map_err(FlashError::Core)?;
+            unchanged_pages(&self.regions, |address, data| {
+                core.read(address, data).map_err(FlashError::Core)
+            })?
+        };
+
+        for (region, unchanged_pages) in self.regions.iter_mut().zip(unchanged) {
+            let layout = region.data.layout_mut();
+            let sectors = layout.sectors().len();
+            layout.remove_unchanged_sectors(&unchanged_pages);
+
+            tracing::info!(
+                "Skipping {} of {} sectors, their contents are up to date",
+                sectors - layout.sectors().len(),
+                sectors
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Removes the sectors which `checkpoint` records as completed from the flash layout.
+    fn skip_completed_sectors(&mut self, checkpoint: &DownloadCheckpoint) {
+        for region in self.regions.iter_mut() {
+            let layout = region.data.layout_mut();
+            let completed_pages = layout
+                .pages()
+                .iter()
+                .map(|page| {
+                    layout.sectors().iter().any(|sector| {
+                        let range = sector.address()..sector.address() + sector.size();
+                        range.contains(&page.address()) && checkpoint.is_completed(&range)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let sectors = layout.sectors().len();
+            layout.remove_unchanged_sectors(&completed_pages);
+
+            tracing::info!(
+                "Skipping {} of {} sectors, they were programmed by an interrupted download",
+                sectors - layout.sectors().len(),
+                sectors
+            );
+        }
+    }
+
+    /// Fills all the unwritten bytes in the layouts of the regions that restore them.
+    ///
+    /// All bytes of the layout's pages that are not to be written during flashing
+    /// will be read from the flash first and written again once the page is programmed.
     pub(super) fn fill_unwritten(
         &mut self,
         session: &mut Session,
@@ -414,6 +536,10 @@ impl Flasher {
             mut read: impl FnMut(u64, &mut [u8]) -> Result<(), FlashError>,
         ) -> Result<(), FlashError> {
             for region in regions.iter_mut() {
+                if !region.restore_unwritten_bytes {
+                    continue;
+                }
+
                 let layout = region.data.layout_mut();
                 for fill in layout.fills.iter() {
                     let t = Instant::now();
@@ -424,7 +550,7 @@ impl Flasher {
 
                     read(fill.address(), page_slice)?;
 
-                    progress.page_filled(fill.size(), t.elapsed());
+                    progress.page_filled(fill.address(), fill.size(), t.elapsed());
                 }
             }
 
@@ -455,15 +581,18 @@ impl Flasher {
     }
 
     /// Verifies all the to-be-written bytes of this flasher.
+    ///
+    /// Fills are only verified in the regions that restore unwritten bytes, unless
+    /// `ignore_all_fills` is `true`.
     pub(super) fn verify(
         &mut self,
         session: &mut Session,
         progress: &FlashProgress,
-        ignore_filled: bool,
+        ignore_all_fills: bool,
     ) -> Result<bool, FlashError> {
         progress.started_verifying();
 
-        let result = self.do_verify(session, progress, ignore_filled);
+        let result = self.do_verify(session, progress, ignore_all_fills);
 
         match result.is_ok() {
             true => progress.finished_verifying(),
@@ -477,7 +606,7 @@ impl Flasher {
         &mut self,
         session: &mut Session,
         progress: &FlashProgress,
-        ignore_filled: bool,
+        ignore_all_fills: bool,
     ) -> Result<bool, FlashError> {
         let encoding = self.flash_algorithm.transfer_encoding;
         if let Some(verify) = self.flash_algorithm.pc_verify {
@@ -487,6 +616,7 @@ impl Flasher {
                     tracing::debug!("Verify using CMSIS function");
 
                     // Prefer Verify as we may use compression
+                    let ignore_filled = ignore_all_fills || !region.restore_unwritten_bytes;
                     let flash_encoder = region.data.encoder(encoding, ignore_filled);
 
                     for page in flash_encoder.pages() {
@@ -527,64 +657,26 @@ impl Flasher {
                             return Ok(false);
                         }
 
-                        progress.page_verified(bytes.len() as u64, start.elapsed());
+                        progress.page_verified(address, bytes.len() as u64, start.elapsed());
                     }
                 }
                 Ok(true)
             })
+        } else if self.is_crc_supported(session) {
+            tracing::debug!("Verify by comparing the CRC of the flash contents");
+
+            self.run_verify(session, progress, |active, data| {
+                let crc_function = active.load_crc_function()?;
+                compare_crc(data, progress, ignore_all_fills, |address, size, crc| {
+                    active.crc32(crc_function, address, size, crc)
+                })
+            })
         } else {
             tracing::debug!("Verify by reading back flash contents");
 
-            fn compare_flash(
-                regions: &[LoadedRegion],
-                progress: &FlashProgress,
-                ignore_filled: bool,
-                mut read: impl FnMut(u64, &mut [u8]) -> Result<(), FlashError>,
-            ) -> Result<bool, FlashError> {
-                for region in regions {
-                    let layout = region.data.layout();
-                    for (idx, page) in layout.pages.iter().enumerate() {
-                        let start = Instant::now();
-                        let address = page.address();
-                        let data = page.data();
-
-                        let mut read_back = vec![0; data.len()];
-                        read(address, &mut read_back)?;
-
-                        if ignore_filled {
-                            // "Unfill" fill regions. These don't get flashed, so their contents are
-                            // allowed to differ. We mask these bytes with default flash content here,
-                            // just for the verification process.
-                            for fill in layout.fills() {
-                                if fill.page_index() != idx {
-                                    continue;
-                                }
-
-                                let fill_offset = (fill.address() - address) as usize;
-                                let fill_size = fill.size() as usize;
-
-                                let default_bytes = &data[fill_offset..][..fill_size];
-                                read_back[fill_offset..][..fill_size]
-                                    .copy_from_slice(default_bytes);
-                            }
-                        }
-                        if data != read_back {
-                            tracing::debug!(
-                                "Verification failed for page at address {:#010x}",
-                                address
-                            );
-                            return Ok(false);
-                        }
-
-                        progress.page_verified(data.len() as u64, start.elapsed());
-                    }
-                }
-                Ok(true)
-            }
-
             if self.flash_algorithm.pc_read.is_some() {
                 self.run_verify(session, progress, |active, data| {
-                    compare_flash(data, progress, ignore_filled, |address, data| {
+                    compare_flash(data, progress, ignore_all_fills, |address, data| {
                         active.read_flash(address, data)
                     })
                 })
@@ -592,13 +684,31 @@ impl Flasher {
                 // Not using a flash algorithm function, so there's no need to go
                 // through ActiveFlasher.
                 let mut core = session.core(0).map_err(FlashError::Core)?;
-                compare_flash(&self.regions, progress, ignore_filled, |address, data| {
-                    core.read(address, data).map_err(FlashError::Core)
-                })
+                compare_flash(
+                    &self.regions,
+                    progress,
+                    ignore_all_fills,
+                    |address, data| core.read(address, data).map_err(FlashError::Core),
+                )
             }
         }
     }
 
+    /// Returns whether the CRC of the flash contents can be calculated on the target, either by
+    /// the flash algorithm or by a RAM stub.
+    fn is_crc_supported(&self, session: &Session) -> bool {
+        if self.flash_algorithm.pc_crc.is_some() {
+            return true;
+        }
+
+        // The stub reads the flash directly, and is loaded into the first page buffer.
+        let core_type = session.target().cores[self.core_index].core_type;
+        self.flash_algorithm.pc_read.is_none()
+            && core_type.is_cortex_m()
+            && !self.flash_algorithm.page_buffers.is_empty()
+            && self.flash_algorithm.flash_properties.page_size as usize >= THUMB_CRC32_STUB.len()
+    }
+
     /// Perform an erase of all sectors given in `flash_layout`.
     fn sector_erase(
         &mut self,
@@ -636,12 +746,13 @@ impl Flasher {
         session: &mut Session,
         progress: &FlashProgress,
         enable_double_buffering: bool,
+        checkpoint: Option<&mut DownloadCheckpoint>,
     ) -> Result<(), FlashError> {
         progress.started_programming();
         let program_result = if self.double_buffering_supported() && enable_double_buffering {
-            self.program_double_buffer(session, progress)
+            self.program_double_buffer(session, progress, checkpoint)
         } else {
-            self.program_simple(session, progress)
+            self.program_simple(session, progress, checkpoint)
         };
 
         match program_result.is_ok() {
@@ -657,10 +768,13 @@ impl Flasher {
         &mut self,
         session: &mut Session,
         progress: &FlashProgress,
+        checkpoint: Option<&mut DownloadCheckpoint>,
     ) -> Result<(), FlashError> {
         let encoding = self.flash_algorithm.transfer_encoding;
         self.run_program(session, progress, |active, data| {
-            for region in data.iter_mut() {
+            let mut programmed_sectors = ProgrammedSectors::new(checkpoint, data);
+
+            for (region_index, region) in data.iter_mut().enumerate() {
                 tracing::debug!(
                     "    programming region: {:#010X?} ({} bytes)",
                     region.region.range,
@@ -674,8 +788,11 @@ impl Flasher {
                             page_address: page.address(),
                             source: Box::new(error),
                         })?;
+                    programmed_sectors
+                        .programmed(region_index, page.address() + page.size() as u64);
                 }
             }
+            programmed_sectors.finished();
             Ok(())
         })
     }
@@ -693,10 +810,20 @@ impl Flasher {
         &mut self,
         session: &mut Session,
         progress: &FlashProgress,
+        checkpoint: Option<&mut DownloadCheckpoint>,
     ) -> Result<(), FlashError> {
         let encoding = self.flash_algorithm.transfer_encoding;
-        self.run_program(session, progress, |active, data| {
-            for region in data.iter_mut() {
+        let stats = self.run_program(session, progress, |active, data| {
+            let mut stats = PipelineStats::default();
+            let mut programmed_sectors = ProgrammedSectors::new(checkpoint, data);
+
+            let mut current_buf = 0;
+            let mut t = Instant::now();
+            let mut last_page = None;
+
+            // The pipeline is kept running across regions, so the first page of a region is
+            // transferred while the last page of the previous one is programmed.
+            for (region_index, region) in data.iter_mut().enumerate() {
                 tracing::debug!(
                     "    programming region: {:#010X?} ({} bytes)",
                     region.region.range,
@@ -704,19 +831,33 @@ impl Flasher {
                 );
                 let flash_encoder = region.data.encoder(encoding, false);
 
-                let mut current_buf = 0;
-                let mut t = Instant::now();
-                let mut last_page_address = 0;
                 for page in flash_encoder.pages() {
                     // At the start of each loop cycle load the next page buffer into RAM.
+                    let transfer_start = Instant::now();
                     let buffer_address = active.load_page_buffer(page.data(), current_buf)?;
+                    stats.transfer_time += transfer_start.elapsed();
 
                     // Then wait for the active RAM -> Flash copy process to finish.
                     // Also check if it finished properly. If it didn't, return an error.
-                    active.wait_for_write_end(last_page_address)?;
+                    if let Some((last_region_index, last_page_address, last_page_end)) = last_page {
+                        let wait_start = Instant::now();
+                        active.wait_for_write_end(last_page_address)?;
+                        stats.flash_wait_time += wait_start.elapsed();
+                        programmed_sectors.programmed(last_region_index, last_page_end);
+                    }
 
-                    last_page_address = page.address();
-                    progress.page_programmed(page.size() as u64, t.elapsed());
+                    // The core is halted now, and the other buffer is free for a watchdog stub.
+                    active.check_cancelled()?;
+                    active.service_watchdogs(1 - current_buf)?;
+
+                    last_page = Some((
+                        region_index,
+                        page.address(),
+                        page.address() + page.size() as u64,
+                    ));
+                    progress.page_programmed(page.address(), page.size() as u64, t.elapsed());
+                    stats.pages += 1;
+                    stats.bytes += page.size() as u64;
 
                     t = Instant::now();
 
@@ -734,11 +875,26 @@ impl Flasher {
                         current_buf = 1;
                     }
                 }
+            }
 
+            if let Some((_, last_page_address, _)) = last_page {
+                let wait_start = Instant::now();
                 active.wait_for_write_end(last_page_address)?;
+                stats.flash_wait_time += wait_start.elapsed();
             }
-            Ok(())
-        })
+            programmed_sectors.finished();
+
+            Ok(stats)
+        })?;
+
+        if stats.is_link_bound() {
+            tracing::info!("Programming was limited by the debug probe connection: {stats}");
+        } else {
+            tracing::info!("Programming was limited by the flash: {stats}");
+        }
+        progress.pipeline_stats(stats);
+
+        Ok(())
     }
 
     pub(crate) fn add_region(
@@ -755,11 +911,170 @@ impl Flasher {
         self.regions.push(LoadedRegion {
             region,
             data: FlashData::Raw(layout),
+            restore_unwritten_bytes,
         });
         Ok(())
     }
 }
 
+/// Records sectors in a download checkpoint once all of their pages have been programmed.
+struct ProgrammedSectors<'c> {
+    checkpoint: Option<&'c mut DownloadCheckpoint>,
+    /// The sectors that are not completely programmed yet, with the index of their region, in
+    /// programming order.
+    pending: VecDeque<(usize, Range<u64>)>,
+}
+
+impl<'c> ProgrammedSectors<'c> {
+    fn new(checkpoint: Option<&'c mut DownloadCheckpoint>, regions: &[LoadedRegion]) -> Self {
+        let mut pending = VecDeque::new();
+        if checkpoint.is_some() {
+            for (region_index, region) in regions.iter().enumerate() {
+                for sector in region.data.layout().sectors() {
+                    pending.push_back((
+                        region_index,
+                        sector.address()..sector.address() + sector.size(),
+                    ));
+                }
+            }
+        }
+
+        Self {
+            checkpoint,
+            pending,
+        }
+    }
+
+    /// Records that the pages of the region with index `region_index` have been programmed up
+    /// to `end`.
+    fn programmed(&mut self, region_index: usize, end: u64) {
+        let Some(checkpoint) = self.checkpoint.as_deref_mut() else {
+            return;
+        };
+
+        while let Some((sector_region_index, sector)) = self.pending.front() {
+            if *sector_region_index > region_index
+                || (*sector_region_index == region_index && sector.end > end)
+            {
+                break;
+            }
+
+            checkpoint.complete(sector.clone());
+            self.pending.pop_front();
+        }
+    }
+
+    /// Records that all pages have been programmed.
+    fn finished(&mut self) {
+        self.programmed(usize::MAX, u64::MAX);
+    }
+}
+
+/// Compares the flash contents to the pages of `regions`, using `read` to read the flash.
+fn compare_flash(
+    regions: &[LoadedRegion],
+    progress: &FlashProgress,
+    ignore_all_fills: bool,
+    mut read: impl FnMut(u64, &mut [u8]) -> Result<(), FlashError>,
+) -> Result<bool, FlashError> {
+    for region in regions {
+        let ignore_filled = ignore_all_fills || !region.restore_unwritten_bytes;
+        let layout = region.data.layout();
+        for (idx, page) in layout.pages.iter().enumerate() {
+            let start = Instant::now();
+            let address = page.address();
+            let data = page.data();
+
+            let mut read_back = vec![0; data.len()];
+            read(address, &mut read_back)?;
+
+            if ignore_filled {
+                // "Unfill" fill regions. These don't get flashed, so their contents are
+                // allowed to differ. We mask these bytes with default flash content here,
+                // just for the verification process.
+                for fill in layout.fills() {
+                    if fill.page_index() != idx {
+                        continue;
+                    }
+
+                    let fill_offset = (fill.address() - address) as usize;
+                    let fill_size = fill.size() as usize;
+
+                    let default_bytes = &data[fill_offset..][..fill_size];
+                    read_back[fill_offset..][..fill_size].copy_from_slice(default_bytes);
+                }
+            }
+            if data != read_back {
+                tracing::debug!("Verification failed for page at address {:#010x}", address);
+                return Ok(false);
+            }
+
+            progress.page_verified(address, data.len() as u64, start.elapsed());
+        }
+    }
+    Ok(true)
+}
+
+/// Compares the CRC of the flash contents to the CRC of the pages of `regions`, using `crc32`
+/// to calculate the CRC of a flash range on the target.
+fn compare_crc(
+    regions: &[LoadedRegion],
+    progress: &FlashProgress,
+    ignore_all_fills: bool,
+    mut crc32: impl FnMut(u64, u64, u32) -> Result<u32, FlashError>,
+) -> Result<bool, FlashError> {
+    for region in regions {
+        let ignore_filled = ignore_all_fills || !region.restore_unwritten_bytes;
+        let layout = region.data.layout();
+        for (idx, page) in layout.pages.iter().enumerate() {
+            let start = Instant::now();
+            let address = page.address();
+            let data = page.data();
+
+            // The ranges of the page to compare, relative to its start. Fills don't get flashed,
+            // so they are left out if their contents are allowed to differ.
+            let mut ranges = vec![];
+            let mut offset = 0;
+            if ignore_filled {
+                for fill in layout
+                    .fills()
+                    .iter()
+                    .filter(|fill| fill.page_index() == idx)
+                {
+                    let fill_offset = (fill.address() - address) as usize;
+                    if fill_offset > offset {
+                        ranges.push(offset..fill_offset);
+                    }
+                    offset = fill_offset + fill.size() as usize;
+                }
+            }
+            if offset < data.len() {
+                ranges.push(offset..data.len());
+            }
+
+            let mut expected = 0;
+            let mut actual = 0;
+            for range in ranges {
+                expected = crc::crc32(expected, &data[range.clone()]);
+                actual = crc32(address + range.start as u64, range.len() as u64, actual)?;
+            }
+
+            if expected != actual {
+                tracing::debug!(
+                    "Verification failed for page at address {:#010x}: CRC is {:#010x}, expected {:#010x}",
+                    address,
+                    actual,
+                    expected
+                );
+                return Ok(false);
+            }
+
+            progress.page_verified(address, data.len() as u64, start.elapsed());
+        }
+    }
+    Ok(true)
+}
+
 struct Registers {
     pc: u32,
     r0: Option<u32>,
@@ -792,12 +1107,16 @@ pub(super) struct ActiveFlasher<'op, 'p, O: Operation> {
     rtt: Option<Rtt>,
     progress: &'op FlashProgress<'p>,
     flash_algorithm: &'op FlashAlgorithm,
+    watchdog: &'op WatchdogService,
+    cancellation: &'op CancellationToken,
     _operation: PhantomData<O>,
 }
 
 impl<O: Operation> ActiveFlasher<'_, '_, O> {
     #[tracing::instrument(name = "Call to flash algorithm init", skip(self, clock))]
     pub(super) fn init(&mut self, clock: Option<u32>) -> Result<(), FlashError> {
+        self.service_watchdogs(0)?;
+
         let algo = &self.flash_algorithm;
 
         // Skip init routine if not present.
@@ -863,6 +1182,66 @@ impl<O: Operation> ActiveFlasher<'_, '_, O> {
         Ok(())
     }
 
+    /// Returns [`FlashError::Cancelled`] if the download was cancelled.
+    fn check_cancelled(&self) -> Result<(), FlashError> {
+        Ok(self.cancellation.error_if_cancelled()?)
+    }
+
+    /// Services the watchdogs of the target, see [`WatchdogMode`](super::WatchdogMode).
+    ///
+    /// Stubs that run on the core are loaded into the page buffer `free_buffer`, which must not
+    /// hold data that is still needed.
+    pub(super) fn service_watchdogs(&mut self, free_buffer: usize) -> Result<(), FlashError> {
+        let watchdog = self.watchdog;
+        for sequence in &watchdog.sequences {
+            tracing::debug!("Servicing the watchdog {}", sequence.name);
+            self.service_watchdog(sequence, free_buffer)
+                .map_err(|source| FlashError::Watchdog {
+                    name: sequence.name.clone(),
+                    source,
+                })?;
+        }
+
+        Ok(())
+    }
+
+    fn service_watchdog(
+        &mut self,
+        sequence: &WatchdogSequence,
+        free_buffer: usize,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let Some(stub) = &sequence.stub else {
+            for write in &sequence.writes {
+                match write.width {
+                    8 => self.core.write_word_8(write.address, write.value as u8),
+                    16 => self.core.write_word_16(write.address, write.value as u16),
+                    _ => self.core.write_word_32(write.address, write.value),
+                }?;
+            }
+            return Ok(());
+        };
+
+        if stub.len() as u32 > self.flash_algorithm.flash_properties.page_size {
+            return Err("The stub does not fit into a page buffer".into());
+        }
+
+        let buffer_address = self.load_page_buffer(stub, free_buffer)?;
+        self.call_function_and_wait(
+            &Registers {
+                // Stay in Thumb mode.
+                pc: into_reg(buffer_address + 1)?,
+                r0: None,
+                r1: None,
+                r2: None,
+                r3: None,
+            },
+            false,
+            Duration::from_millis(100),
+        )?;
+
+        Ok(())
+    }
+
     fn call_function_and_wait(
         &mut self,
         registers: &Registers,
@@ -1068,6 +1447,8 @@ impl<O: Operation> ActiveFlasher<'_, '_, O> {
 
             let mut read_address = address;
             for slice in data.chunks_mut(page_size as usize) {
+                self.check_cancelled()?;
+
                 // Call ReadFlash to load from flash to RAM. The function has a similar signature
                 // to the program_page function.
                 let result = self
@@ -1104,7 +1485,12 @@ impl<O: Operation> ActiveFlasher<'_, '_, O> {
 
             Ok(())
         } else {
-            self.core.read(address, data).map_err(FlashError::Core)
+            self.core
+                .read_cancellable(address, data, self.cancellation)
+                .map_err(|error| match error {
+                    Error::Cancelled(error) => FlashError::Cancelled(error),
+                    error => FlashError::Core(error),
+                })
         }
     }
 
@@ -1178,6 +1564,8 @@ impl ActiveFlasher<'_, '_, Erase> {
             return Err(FlashError::ChipEraseNotSupported);
         };
 
+        self.check_cancelled()?;
+        self.service_watchdogs(0)?;
         let result = self
             .call_function_and_wait(
                 &Registers {
@@ -1211,6 +1599,8 @@ impl ActiveFlasher<'_, '_, Erase> {
         tracing::info!("Erasing sector at address {:#010x}", address);
         let t1 = Instant::now();
 
+        self.check_cancelled()?;
+        self.service_watchdogs(0)?;
         let error_code = self.call_function_and_wait(
             &Registers {
                 pc: into_reg(self.flash_algorithm.pc_erase_sector)?,
@@ -1236,7 +1626,8 @@ impl ActiveFlasher<'_, '_, Erase> {
                 error_code,
             })
         } else {
-            self.progress.sector_erased(sector.size(), t1.elapsed());
+            self.progress
+                .sector_erased(sector.address(), sector.size(), t1.elapsed());
             Ok(())
         }
     }
@@ -1251,6 +1642,7 @@ impl ActiveFlasher<'_, '_, Erase> {
         );
         let t1 = Instant::now();
 
+        self.check_cancelled()?;
         if let Some(blank_check) = self.flash_algorithm.pc_blank_check {
             let error_code = self.call_function_and_wait(
                 &Registers {
@@ -1283,7 +1675,8 @@ impl ActiveFlasher<'_, '_, Erase> {
                     error_code,
                 })
             } else {
-                self.progress.sector_erased(sector.size(), t1.elapsed());
+                self.progress
+                    .sector_erased(sector.address(), sector.size(), t1.elapsed());
                 Ok(())
             }
         } else {
@@ -1304,6 +1697,52 @@ impl ActiveFlasher<'_, '_, Erase> {
     }
 }
 
+impl ActiveFlasher<'_, '_, Verify> {
+    /// Prepares the CRC calculation on the target, and returns the address of the function to
+    /// call.
+    ///
+    /// If the flash algorithm has no `Crc32()` function, a stub is loaded into the first page
+    /// buffer instead.
+    pub(super) fn load_crc_function(&mut self) -> Result<u64, FlashError> {
+        if let Some(crc) = self.flash_algorithm.pc_crc {
+            return Ok(crc);
+        }
+
+        let buffer_address = self.load_page_buffer(&THUMB_CRC32_STUB, 0)?;
+
+        // Stay in Thumb mode.
+        Ok(buffer_address + 1)
+    }
+
+    /// Continues the CRC calculation of `crc` over `size` bytes of flash starting at `address`,
+    /// using the function returned by [`Self::load_crc_function`].
+    pub(super) fn crc32(
+        &mut self,
+        crc_function: u64,
+        address: u64,
+        size: u64,
+        crc: u32,
+    ) -> Result<u32, FlashError> {
+        tracing::debug!(
+            "Calculating the CRC of {} bytes at address {:#010x}",
+            size,
+            address
+        );
+
+        self.call_function_and_wait(
+            &Registers {
+                pc: into_reg(crc_function)?,
+                r0: Some(into_reg(address)?),
+                r1: Some(into_reg(size)?),
+                r2: Some(crc),
+                r3: None,
+            },
+            false,
+            Duration::from_secs(30),
+        )
+    }
+}
+
 impl ActiveFlasher<'_, '_, Program> {
     pub(super) fn program_page(&mut self, page: &FlashPage) -> Result<(), FlashError> {
         let t1 = Instant::now();
@@ -1317,6 +1756,9 @@ impl ActiveFlasher<'_, '_, Program> {
             bytes.len()
         );
 
+        self.check_cancelled()?;
+        self.service_watchdogs(0)?;
+
         // Transfer the bytes to RAM.
         let begin_data = self.load_page_buffer(bytes, 0)?;
 
@@ -1326,7 +1768,7 @@ impl ActiveFlasher<'_, '_, Program> {
         tracing::info!("Flashing took: {:?}", t1.elapsed());
 
         self.progress
-            .page_programmed(page.size() as u64, t1.elapsed());
+            .page_programmed(page.address(), page.size() as u64, t1.elapsed());
         Ok(())
     }
 
@@ -0,0 +1,174 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use super::{Format, FormatKind, ImageLoader};
+
+/// A firmware image format which can be selected by name.
+///
+/// Formats are collected in an [`ImageFormatRegistry`]. Besides the built-in formats, external
+/// code can register its own formats, e.g. for proprietary update containers. The
+/// [`ImageLoader`] returned by [`ImageFormat::loader`] decides where each chunk of the image is
+/// placed in memory, by adding it to the [`FlashLoader`](super::FlashLoader) using
+/// [`FlashLoader::add_data`](super::FlashLoader::add_data). Erasing, programming and verifying
+/// is then done by the standard flash loader.
+pub trait ImageFormat: Debug + Send + Sync {
+    /// The names the format can be selected by. The first name is the canonical one.
+    ///
+    /// Names are matched case-insensitively.
+    fn names(&self) -> &[&str];
+
+    /// Creates a loader for the format, using the default options of the format.
+    fn loader(&self) -> Box<dyn ImageLoader>;
+}
+
+impl ImageFormat for FormatKind {
+    fn names(&self) -> &[&str] {
+        match self {
+            FormatKind::Bin => &["bin", "binary"],
+            FormatKind::Hex => &["hex", "ihex", "intelhex"],
+            FormatKind::Elf => &["elf"],
+            FormatKind::Uf2 => &["uf2"],
+            FormatKind::Idf => &["idf", "esp-idf", "espidf"],
+        }
+    }
+
+    fn loader(&self) -> Box<dyn ImageLoader> {
+        Box::new(Format::from(*self))
+    }
+}
+
+/// A registry of the available [`ImageFormat`]s.
+///
+/// The registry created by [`ImageFormatRegistry::new`] contains all built-in formats.
+///
+/// ```
+/// use probe_rs::flashing::ImageFormatRegistry;
+///
+/// let registry = ImageFormatRegistry::new();
+///
+/// assert!(registry.get("ihex").is_some());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ImageFormatRegistry {
+    formats: Vec<Arc<dyn ImageFormat>>,
+}
+
+impl Default for ImageFormatRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ImageFormatRegistry {
+    /// Creates a registry containing the built-in formats.
+    pub fn new() -> Self {
+        let formats = [
+            FormatKind::Elf,
+            FormatKind::Hex,
+            FormatKind::Bin,
+            FormatKind::Uf2,
+            FormatKind::Idf,
+        ]
+        .into_iter()
+        .map(|kind| Arc::new(kind) as Arc<dyn ImageFormat>)
+        .collect();
+
+        Self { formats }
+    }
+
+    /// Creates a registry without any formats.
+    pub fn empty() -> Self {
+        Self {
+            formats: Vec::new(),
+        }
+    }
+
+    /// Adds a format to the registry.
+    ///
+    /// If a name of the format is already used by another format, the new format takes
+    /// precedence. This can be used to replace built-in formats.
+    pub fn register(&mut self, format: impl ImageFormat + 'static) {
+        self.formats.push(Arc::new(format));
+    }
+
+    /// Looks up a format by one of its names.
+    pub fn get(&self, name: &str) -> Option<Arc<dyn ImageFormat>> {
+        self.formats
+            .iter()
+            .rev()
+            .find(|format| {
+                format
+                    .names()
+                    .iter()
+                    .any(|candidate| candidate.eq_ignore_ascii_case(name))
+            })
+            .cloned()
+    }
+
+    /// Looks up a format by name and creates a loader for it.
+    pub fn loader(&self, name: &str) -> Result<Box<dyn ImageLoader>, String> {
+        self.get(name)
+            .map(|format| format.loader())
+            .ok_or_else(|| format!("Format '{name}' is unknown."))
+    }
+
+    /// Returns the canonical names of all registered formats.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.formats
+            .iter()
+            .filter_map(|format| format.names().first().copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flashing::{FileDownloadError, FlashLoader, ImageReader};
+    use crate::session::Session;
+
+    #[derive(Debug)]
+    struct CustomFormat;
+
+    struct CustomLoader;
+
+    impl ImageLoader for CustomLoader {
+        fn load(
+            &self,
+            _flash_loader: &mut FlashLoader,
+            _session: &mut Session,
+            _file: &mut dyn ImageReader,
+        ) -> Result<(), FileDownloadError> {
+            Ok(())
+        }
+    }
+
+    impl ImageFormat for CustomFormat {
+        fn names(&self) -> &[&str] {
+            &["ota", "hex"]
+        }
+
+        fn loader(&self) -> Box<dyn ImageLoader> {
+            Box::new(CustomLoader)
+        }
+    }
+
+    #[test]
+    fn builtin_formats_are_registered() {
+        let registry = ImageFormatRegistry::new();
+
+        for name in ["elf", "BIN", "intelhex", "uf2", "esp-idf"] {
+            assert!(registry.get(name).is_some(), "{name} is not registered");
+        }
+        assert!(registry.get("ota").is_none());
+    }
+
+    #[test]
+    fn registered_formats_take_precedence() {
+        let mut registry = ImageFormatRegistry::new();
+        registry.register(CustomFormat);
+
+        assert_eq!(registry.get("ota").unwrap().names()[0], "ota");
+        assert_eq!(registry.get("hex").unwrap().names()[0], "ota");
+        assert_eq!(registry.get("ihex").unwrap().names()[0], "hex");
+    }
+}
@@ -2,7 +2,7 @@ use espflash::flasher::{FlashData, FlashSettings, FlashSize};
 use espflash::image_format::idf::IdfBootloaderFormat;
 use ihex::Record;
 use probe_rs_target::{
-    InstructionSet, MemoryRange, MemoryRegion, NvmRegion, RawFlashAlgorithm,
+    AddressAlias, InstructionSet, MemoryRange, MemoryRegion, NvmRegion, RawFlashAlgorithm,
     TargetDescriptionSource,
 };
 use std::io::{Read, Seek, SeekFrom};
@@ -11,17 +11,64 @@ use std::str::FromStr;
 use std::time::Duration;
 
 use super::builder::FlashBuilder;
+use super::checkpoint::{self, DownloadCheckpoint};
+use super::download::ExtractedFlashData;
+use super::uf2::extract_from_uf2;
+use super::watchdog::WatchdogService;
 use super::{
     BinOptions, DownloadOptions, ElfOptions, FileDownloadError, FlashError, Flasher, IdfOptions,
     extract_from_elf,
 };
 use crate::Target;
 use crate::config::DebugSequence;
-use crate::flashing::progress::ProgressOperation;
+use crate::flashing::progress::{ProgressOperation, ProgressUpdate};
 use crate::flashing::{FlashLayout, FlashProgress, Format};
 use crate::memory::MemoryInterface;
 use crate::session::Session;
 
+/// Halts all cores except the one with `core_index`, and returns the cores that were running.
+///
+/// Cores that can't be accessed are ignored.
+fn park_other_cores(session: &mut Session, core_index: usize) -> Result<Vec<usize>, FlashError> {
+    let mut parked = vec![];
+
+    for (index, _) in session.list_cores() {
+        if index == core_index {
+            continue;
+        }
+
+        let mut core = match session.core(index) {
+            Ok(core) => core,
+            Err(error) => {
+                tracing::debug!("Not parking inaccessible core {index}: {error}");
+                continue;
+            }
+        };
+
+        if !core.core_halted().map_err(FlashError::Core)? {
+            tracing::debug!("Parking core {index} while flashing");
+            core.halt(Duration::from_millis(500))
+                .map_err(FlashError::Core)?;
+            parked.push(index);
+        }
+    }
+
+    Ok(parked)
+}
+
+/// Resumes the cores halted by [`park_other_cores`].
+fn resume_cores(session: &mut Session, cores: &[usize]) -> Result<(), FlashError> {
+    for &index in cores {
+        tracing::debug!("Resuming parked core {index}");
+        session
+            .core(index)
+            .and_then(|mut core| core.run())
+            .map_err(FlashError::Core)?;
+    }
+
+    Ok(())
+}
+
 /// Helper trait for object safety.
 pub trait ImageReader: Read + Seek {}
 impl<T> ImageReader for T where T: Read + Seek {}
@@ -39,6 +86,17 @@ pub trait ImageLoader {
     ) -> Result<(), FileDownloadError>;
 }
 
+impl<T: ImageLoader + ?Sized> ImageLoader for Box<T> {
+    fn load(
+        &self,
+        flash_loader: &mut FlashLoader,
+        session: &mut Session,
+        file: &mut dyn ImageReader,
+    ) -> Result<(), FileDownloadError> {
+        (**self).load(flash_loader, session, file)
+    }
+}
+
 impl ImageLoader for Format {
     fn load(
         &self,
@@ -83,6 +141,46 @@ impl ImageLoader for BinLoader {
     }
 }
 
+/// Moves `section` to its physical address, if it was linked for one of the `aliases`.
+fn remap_alias<'a>(
+    section: &mut ExtractedFlashData<'_>,
+    aliases: impl Iterator<Item = &'a AddressAlias>,
+) -> Result<(), FileDownloadError> {
+    let address = u64::from(section.address);
+    let range = address..address + section.data.len() as u64;
+
+    for alias in aliases {
+        if let Some(physical) = alias.translate(&range) {
+            tracing::info!(
+                "Remapping data at {address:#010X} to {:#010X}",
+                physical.start
+            );
+            // All of the data has to fit into the 32 bit address space.
+            let (Ok(start), Ok(_)) = (
+                u32::try_from(physical.start),
+                u32::try_from(physical.end - 1),
+            ) else {
+                return Err(FileDownloadError::AliasOutOfRange {
+                    address,
+                    target: physical.start,
+                });
+            };
+            section.address = start;
+            return Ok(());
+        }
+
+        if alias.range.intersects_range(&range) {
+            return Err(FileDownloadError::AliasBoundary {
+                address,
+                end: range.end,
+                alias: alias.range.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
 /// Prepares the data sections that have to be loaded into flash from an ELF file.
 /// This will validate the ELF file and transform all its data into sections but no flash loader commands yet.
 struct ElfLoader(ElfOptions);
@@ -91,20 +189,29 @@ impl ImageLoader for ElfLoader {
     fn load(
         &self,
         flash_loader: &mut FlashLoader,
-        _session: &mut Session,
+        session: &mut Session,
         file: &mut dyn ImageReader,
     ) -> Result<(), FileDownloadError> {
         const VECTOR_TABLE_SECTION_NAME: &str = ".vector_table";
         let mut elf_buffer = Vec::new();
         file.read_to_end(&mut elf_buffer)?;
 
-        let extracted_data = extract_from_elf(&elf_buffer, &self.0)?;
+        let mut extracted_data = extract_from_elf(&elf_buffer, &self.0)?;
 
         if extracted_data.is_empty() {
             tracing::warn!("No loadable segments were found in the ELF file.");
             return Err(FileDownloadError::NoLoadableSegments);
         }
 
+        let aliases = self
+            .0
+            .address_aliases
+            .iter()
+            .chain(session.target().address_aliases.iter());
+        for section in &mut extracted_data {
+            remap_alias(section, aliases.clone())?;
+        }
+
         tracing::info!("Found {} loadable sections:", extracted_data.len());
 
         for section in &extracted_data {
@@ -181,28 +288,34 @@ impl ImageLoader for Uf2Loader {
     fn load(
         &self,
         flash_loader: &mut FlashLoader,
-        _session: &mut Session,
+        session: &mut Session,
         file: &mut dyn ImageReader,
     ) -> Result<(), FileDownloadError> {
         let mut uf2_buffer = Vec::new();
         file.read_to_end(&mut uf2_buffer)?;
 
-        let (converted, family_to_target) = uf2_decode::convert_from_uf2(&uf2_buffer).unwrap();
-        let target_addresses = family_to_target.values();
-        let num_sections = family_to_target.len();
-
-        if let Some(target_address) = target_addresses.min() {
-            tracing::info!("Found {} loadable sections:", num_sections);
-            if num_sections > 1 {
-                tracing::warn!("More than 1 section found in UF2 file.  Using first section.");
-            }
-            flash_loader.add_data(*target_address, &converted)?;
+        let target = session.target();
+        let segments = extract_from_uf2(&uf2_buffer, &target.uf2_family_ids, &target.name)?;
 
-            Ok(())
-        } else {
+        if segments.is_empty() {
             tracing::warn!("No loadable segments were found in the UF2 file.");
-            Err(FileDownloadError::NoLoadableSegments)
+            return Err(FileDownloadError::NoLoadableSegments);
+        }
+
+        tracing::info!("Found {} loadable sections:", segments.len());
+        for segment in &segments {
+            tracing::info!(
+                "    {:#010X} ({} bytes)",
+                segment.address,
+                segment.data.len()
+            );
         }
+
+        for segment in segments {
+            flash_loader.add_data(segment.address, &segment.data)?;
+        }
+
+        Ok(())
     }
 }
 
@@ -382,6 +495,18 @@ impl FlashLoader {
         self.builder.add_data(address, data)
     }
 
+    /// Restores the bytes that are erased, but not written, in the flash regions overlapping
+    /// `range`, as if [`DownloadOptions::keep_unwritten_bytes`] was set for them.
+    ///
+    /// This allows writing data that does not cover whole sectors, e.g. a configuration
+    /// block, without losing the surrounding contents of the sectors, while the other regions
+    /// are still programmed without reading them first.
+    pub fn restore_unwritten_bytes(&mut self, range: Range<u64>) -> Result<(), FlashError> {
+        self.check_data_in_memory_map(range.clone())?;
+        self.builder.restore_unwritten_bytes(range);
+        Ok(())
+    }
+
     pub(super) fn get_region_for_address(
         memory_map: &[MemoryRegion],
         address: u64,
@@ -399,7 +524,7 @@ impl FlashLoader {
         &mut self,
         session: &mut Session,
         file: &mut T,
-        format: Format,
+        format: impl ImageLoader,
         image_instruction_set: Option<InstructionSet>,
     ) -> Result<(), FileDownloadError> {
         if let Some(instr_set) = image_instruction_set {
@@ -467,6 +592,21 @@ impl FlashLoader {
         Ok(())
     }
 
+    /// Writes all the stored data chunks to flash, reporting the state of each operation to
+    /// `handler`.
+    ///
+    /// This is a shorthand for [`FlashLoader::commit`] with a [`FlashProgress::structured`]
+    /// progress reporter, which replaces the reporter in `options`.
+    pub fn program_with_progress<'p>(
+        &self,
+        session: &mut Session,
+        mut options: DownloadOptions<'p>,
+        handler: impl Fn(ProgressUpdate) + 'p,
+    ) -> Result<(), FlashError> {
+        options.progress = Some(FlashProgress::structured(handler));
+        self.commit(session, options)
+    }
+
     /// Writes all the stored data chunks to flash.
     ///
     /// Requires a session with an attached target that has a known flash algorithm.
@@ -495,6 +635,22 @@ impl FlashLoader {
             .clone()
             .unwrap_or_else(FlashProgress::empty);
 
+        let mut checkpoint = options.checkpoint.as_deref().map(|path| {
+            let image = checkpoint::image_id(&session.target().name, self.data());
+            DownloadCheckpoint::open(path, image, options.resume)
+        });
+
+        // Erasing the whole chip would also erase the sectors that are already programmed.
+        if options.do_chip_erase && checkpoint.as_ref().is_some_and(|c| !c.is_empty()) {
+            tracing::warn!("Resuming an interrupted download, erasing sectors individually.");
+            options.do_chip_erase = false;
+        }
+
+        for flasher in algos.iter_mut() {
+            flasher.watchdog = WatchdogService::new(&session.target().watchdogs, options.watchdog)?;
+            flasher.cancellation = options.cancellation.clone();
+        }
+
         self.initialize(&mut algos, session, &progress, &mut options)?;
 
         let mut do_chip_erase = options.do_chip_erase;
@@ -504,30 +660,30 @@ impl FlashLoader {
         for mut flasher in algos {
             tracing::debug!("Flashing ranges for algo: {}", flasher.flash_algorithm.name);
 
-            if do_chip_erase {
-                tracing::debug!("    Doing chip erase...");
-                flasher.run_erase_all(session, &progress)?;
-                do_chip_erase = false;
-                did_chip_erase = true;
-            }
-
-            let mut do_use_double_buffering = flasher.double_buffering_supported();
-            if do_use_double_buffering && options.disable_double_buffering {
-                tracing::info!(
-                    "Disabled double-buffering support for loader via passed option, though target supports it."
-                );
-                do_use_double_buffering = false;
-            }
+            let chip_erase = std::mem::take(&mut do_chip_erase);
+            let skip_erase = options.skip_erase || did_chip_erase || chip_erase;
+            did_chip_erase |= chip_erase;
 
-            // Program the data.
-            flasher.program(
+            // The other cores must not interfere with the flash algorithm.
+            let parked_cores = park_other_cores(session, flasher.core_index)?;
+            let result = Self::run_flasher(
+                &mut flasher,
                 session,
                 &progress,
-                options.keep_unwritten_bytes,
-                do_use_double_buffering,
-                options.skip_erase || did_chip_erase,
-                options.verify,
-            )?;
+                &options,
+                chip_erase,
+                skip_erase,
+                checkpoint.as_mut(),
+            );
+            let resumed = resume_cores(session, &parked_cores);
+            if let Err(error) = result {
+                // The flashing error is the relevant one, don't hide it behind the cleanup.
+                if let Err(resume_error) = resumed {
+                    tracing::warn!("Failed to resume the parked cores: {resume_error}");
+                }
+                return Err(error);
+            }
+            resumed?;
         }
 
         tracing::debug!("Committing RAM!");
@@ -603,6 +759,7 @@ impl FlashLoader {
                     data.len()
                 );
                 // Write data to memory.
+                options.cancellation.error_if_cancelled()?;
                 core.write(address, data).map_err(FlashError::Core)?;
             }
         }
@@ -611,9 +768,47 @@ impl FlashLoader {
             self.verify_ram(session)?;
         }
 
+        if let Some(checkpoint) = checkpoint {
+            checkpoint.finish();
+        }
+
         Ok(())
     }
 
+    /// Erases and programs the regions of a single flash algorithm.
+    fn run_flasher(
+        flasher: &mut Flasher,
+        session: &mut Session,
+        progress: &FlashProgress,
+        options: &DownloadOptions,
+        chip_erase: bool,
+        skip_erase: bool,
+        checkpoint: Option<&mut DownloadCheckpoint>,
+    ) -> Result<(), FlashError> {
+        if chip_erase {
+            tracing::debug!("    Doing chip erase...");
+            flasher.run_erase_all(session, progress)?;
+        }
+
+        let mut do_use_double_buffering = flasher.double_buffering_supported();
+        if do_use_double_buffering && options.disable_double_buffering {
+            tracing::info!(
+                "Disabled double-buffering support for loader via passed option, though target supports it."
+            );
+            do_use_double_buffering = false;
+        }
+
+        // Program the data.
+        flasher.program(
+            session,
+            progress,
+            options,
+            do_use_double_buffering,
+            skip_erase,
+            checkpoint,
+        )
+    }
+
     fn prepare_plan(
         &self,
         session: &mut Session,
@@ -681,15 +876,22 @@ impl FlashLoader {
             }
 
             let region = region.clone();
+            let restore_unwritten_bytes =
+                restore_unwritten_bytes || self.builder.restores_unwritten_bytes(&region.range);
 
             let Some(core_name) = region.cores.first() else {
                 return Err(FlashError::NoNvmCoreAccess(region));
             };
 
             let target = session.target();
-            let core = target.core_index_by_name(core_name).unwrap();
             let algo = Self::get_flash_algorithm_for_region(&region, target, core_name)?;
 
+            // Some algorithms can only run on a specific core.
+            let core = match algo.core_index {
+                Some(core_index) => core_index,
+                None => target.core_index_by_name(core_name).unwrap(),
+            };
+
             // We don't usually have more than a handful of regions, linear search should be fine.
             tracing::debug!("     -- using algorithm: {}", algo.name);
             if let Some(entry) = algos
@@ -740,22 +942,27 @@ impl FlashLoader {
             let mut erase_size = 0;
             let mut program_size = 0;
 
+            let mut restore_unwritten_bytes = false;
+
             for region in flasher.regions.iter_mut() {
                 let layout = region.flash_layout();
                 phase_layout.merge_from(layout.clone());
 
                 erase_size += layout.sectors().iter().map(|s| s.size()).sum::<u64>();
-                fill_size += layout.fills().iter().map(|s| s.size()).sum::<u64>();
+                if region.restore_unwritten_bytes {
+                    fill_size += layout.fills().iter().map(|s| s.size()).sum::<u64>();
+                    restore_unwritten_bytes = true;
+                }
                 program_size += region
                     .data
                     .encoder(
                         flasher.flash_algorithm.transfer_encoding,
-                        !options.keep_unwritten_bytes,
+                        !region.restore_unwritten_bytes,
                     )
                     .program_size();
             }
 
-            if options.keep_unwritten_bytes {
+            if restore_unwritten_bytes {
                 progress.add_progress_bar(ProgressOperation::Fill, Some(fill_size));
             }
             if !options.do_chip_erase {
@@ -875,3 +1082,67 @@ impl FlashLoader {
             .map(|(address, data)| (*address, data.as_slice()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(address: u32, data: &[u8]) -> ExtractedFlashData<'_> {
+        ExtractedFlashData {
+            section_names: vec![".text".to_string()],
+            address,
+            data,
+        }
+    }
+
+    #[test]
+    fn remap_alias_moves_contained_data() {
+        let aliases = [AddressAlias {
+            range: 0x0000_0000..0x0010_0000,
+            target: 0x0800_0000,
+        }];
+        let data = [0u8; 0x100];
+
+        let mut inside = section(0x1000, &data);
+        remap_alias(&mut inside, aliases.iter()).unwrap();
+        assert_eq!(inside.address, 0x0800_1000);
+
+        let mut outside = section(0x2000_0000, &data);
+        remap_alias(&mut outside, aliases.iter()).unwrap();
+        assert_eq!(outside.address, 0x2000_0000);
+    }
+
+    #[test]
+    fn remap_alias_rejects_data_crossing_the_alias() {
+        let aliases = [AddressAlias {
+            range: 0x0000_0000..0x0010_0000,
+            target: 0x0800_0000,
+        }];
+        let data = [0u8; 0x100];
+
+        let mut crossing = section(0x000F_FF80, &data);
+        assert!(matches!(
+            remap_alias(&mut crossing, aliases.iter()),
+            Err(FileDownloadError::AliasBoundary { .. })
+        ));
+    }
+
+    #[test]
+    fn remap_alias_rejects_addresses_above_4_gib() {
+        let aliases = [AddressAlias {
+            range: 0x0000_0000..0x0010_0000,
+            target: 0xFFFF_FF00,
+        }];
+        let data = [0u8; 0x100];
+
+        let mut fits = section(0x0, &data);
+        remap_alias(&mut fits, aliases.iter()).unwrap();
+        assert_eq!(fits.address, 0xFFFF_FF00);
+
+        let mut too_high = section(0x80, &data);
+        assert!(matches!(
+            remap_alias(&mut too_high, aliases.iter()),
+            Err(FileDownloadError::AliasOutOfRange { .. })
+        ));
+    }
+}
@@ -51,14 +51,21 @@
 //!
 
 mod builder;
+mod checkpoint;
+mod crc;
 mod download;
 mod encoder;
 mod erase;
 mod error;
 mod flash_algorithm;
 mod flasher;
+mod format;
 mod loader;
+pub mod option_bytes;
 mod progress;
+pub mod simulator;
+mod uf2;
+mod watchdog;
 
 use builder::*;
 use flasher::*;
@@ -68,5 +75,7 @@ pub use download::*;
 pub use erase::*;
 pub use error::*;
 pub use flash_algorithm::*;
+pub use format::*;
 pub use loader::*;
 pub use progress::*;
+pub use watchdog::WatchdogMode;
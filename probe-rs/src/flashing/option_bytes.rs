@@ -0,0 +1,210 @@
+//! Reading and writing the option bytes of a target.
+//!
+//! Option bytes, or user configuration registers, hold settings like the readout protection
+//! level, brown-out reset thresholds or whether the watchdog starts automatically. Their layout
+//! and the register sequence that programs them are part of the target description, see
+//! [`OptionBytes`].
+//!
+//! Use [`Session::option_bytes`] to access them.
+
+use std::time::{Duration, Instant};
+
+use crate::config::{OptionBytes, OptionBytesStep};
+use crate::memory::MemoryInterface;
+use crate::{Core, Session};
+
+/// Describes any error that happened while accessing the option bytes.
+#[derive(thiserror::Error, Debug)]
+pub enum OptionBytesError {
+    /// The target description does not describe the option bytes.
+    #[error("The target {0} does not describe its option bytes.")]
+    NotSupported(String),
+    /// The option bytes have no field with the given name.
+    #[error("The option bytes have no field called {0}.")]
+    UnknownField(String),
+    /// The value does not fit into the field.
+    #[error("The value {value:#x} does not fit into the {bits} bits of the field {field}.")]
+    ValueTooLarge {
+        /// The name of the field.
+        field: String,
+        /// The value that should have been written.
+        value: u32,
+        /// The number of bits of the field.
+        bits: u8,
+    },
+    /// A register did not reach the expected value in time.
+    #[error(
+        "Timed out waiting for the register at {address:#010x} while writing the option bytes."
+    )]
+    Timeout {
+        /// The address of the register.
+        address: u64,
+    },
+    /// An error occurred while accessing the target.
+    #[error("An error occurred while accessing the option bytes.")]
+    Core(#[from] crate::Error),
+}
+
+/// The value of a field of the option bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionByteValue {
+    /// The name of the field.
+    pub name: String,
+    /// The description of the field, if any.
+    pub description: Option<String>,
+    /// The value of the field.
+    pub value: u32,
+    /// The number of bits of the field.
+    pub bit_width: u8,
+}
+
+/// Access to the option bytes of a target, returned by [`Session::option_bytes`].
+pub struct OptionBytesAccess<'session> {
+    session: &'session mut Session,
+    option_bytes: OptionBytes,
+}
+
+impl<'session> OptionBytesAccess<'session> {
+    pub(crate) fn new(session: &'session mut Session) -> Result<Self, OptionBytesError> {
+        let target = session.target();
+        let Some(option_bytes) = target.option_bytes.clone() else {
+            return Err(OptionBytesError::NotSupported(target.name.clone()));
+        };
+
+        Ok(Self {
+            session,
+            option_bytes,
+        })
+    }
+
+    /// Returns the description of the option bytes.
+    pub fn description(&self) -> &OptionBytes {
+        &self.option_bytes
+    }
+
+    /// Reads the raw option bytes, as 32-bit words.
+    pub fn read_raw(&mut self) -> Result<Vec<u32>, OptionBytesError> {
+        let mut core = self.session.core(0)?;
+
+        let mut words = vec![0; self.option_bytes.size as usize / 4];
+        core.read_32(self.option_bytes.address, &mut words)?;
+
+        Ok(words)
+    }
+
+    /// Reads the values of all fields.
+    pub fn read(&mut self) -> Result<Vec<OptionByteValue>, OptionBytesError> {
+        let words = self.read_raw()?;
+
+        Ok(self
+            .option_bytes
+            .fields
+            .iter()
+            .map(|field| OptionByteValue {
+                name: field.name.clone(),
+                description: field.description.clone(),
+                value: field.extract(words[field.offset as usize / 4]),
+                bit_width: field.bit_width,
+            })
+            .collect())
+    }
+
+    /// Reads the value of the field called `name`.
+    pub fn read_field(&mut self, name: &str) -> Result<u32, OptionBytesError> {
+        let field = self
+            .option_bytes
+            .field(name)
+            .ok_or_else(|| OptionBytesError::UnknownField(name.to_string()))?
+            .clone();
+
+        let words = self.read_raw()?;
+
+        Ok(field.extract(words[field.offset as usize / 4]))
+    }
+
+    /// Changes the fields in `values`, given as pairs of field name and value, and leaves the
+    /// other fields unchanged.
+    ///
+    /// Nothing is written if the option bytes already contain the values. Depending on the
+    /// target, the new values may only take effect after a reset.
+    pub fn write(&mut self, values: &[(&str, u32)]) -> Result<(), OptionBytesError> {
+        let old_words = self.read_raw()?;
+        let mut words = old_words.clone();
+
+        for &(name, value) in values {
+            let field = self
+                .option_bytes
+                .field(name)
+                .ok_or_else(|| OptionBytesError::UnknownField(name.to_string()))?;
+
+            if value & !field.mask() != 0 {
+                return Err(OptionBytesError::ValueTooLarge {
+                    field: field.name.clone(),
+                    value,
+                    bits: field.bit_width,
+                });
+            }
+
+            let word = &mut words[field.offset as usize / 4];
+            *word = field.insert(*word, value);
+        }
+
+        if words == old_words {
+            tracing::info!("The option bytes already contain the requested values");
+            return Ok(());
+        }
+
+        let option_bytes = &self.option_bytes;
+        let mut core = self.session.core(0)?;
+
+        let result = run_steps(&mut core, &option_bytes.unlock).and_then(|_| {
+            let address = option_bytes.write_address.unwrap_or(option_bytes.address);
+            tracing::debug!("Writing option bytes {words:#010x?} to {address:#010x}");
+            core.write_32(address, &words)?;
+            core.flush()?;
+
+            run_steps(&mut core, &option_bytes.program)
+        });
+
+        // Lock the option bytes again, even if writing them failed.
+        let lock_result = run_steps(&mut core, &option_bytes.lock);
+
+        result.and(lock_result)
+    }
+}
+
+/// Performs the register accesses of `steps`, in order.
+fn run_steps(core: &mut Core<'_>, steps: &[OptionBytesStep]) -> Result<(), OptionBytesError> {
+    for step in steps {
+        match *step {
+            OptionBytesStep::Write { address, value } => {
+                core.write_word_32(address, value)?;
+            }
+            OptionBytesStep::Modify {
+                address,
+                mask,
+                value,
+            } => {
+                let old = core.read_word_32(address)?;
+                core.write_word_32(address, (old & !mask) | (value & mask))?;
+            }
+            OptionBytesStep::WaitFor {
+                address,
+                mask,
+                value,
+                timeout_ms,
+            } => {
+                let timeout = Duration::from_millis(timeout_ms as u64);
+                let start = Instant::now();
+                while core.read_word_32(address)? & mask != value & mask {
+                    if start.elapsed() > timeout {
+                        return Err(OptionBytesError::Timeout { address });
+                    }
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use super::FlashLayout;
-use std::{sync::Arc, time::Duration};
+use std::{cell::RefCell, collections::HashMap, sync::Arc, time::Duration};
 
 /// A structure to manage the flashing procedure progress reporting.
 ///
@@ -27,6 +27,32 @@ impl<'a> FlashProgress<'a> {
         }
     }
 
+    /// Create a new `FlashProgress` structure which reports the overall state of each operation
+    /// to `handler`, instead of the individual events.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use probe_rs::flashing::FlashProgress;
+    ///
+    /// let progress = FlashProgress::structured(|update| {
+    ///     println!(
+    ///         "{:?}: {} of {:?} bytes, ETA {:?}",
+    ///         update.operation, update.completed, update.total, update.eta
+    ///     )
+    /// });
+    /// ```
+    pub fn structured(handler: impl Fn(ProgressUpdate) + 'a) -> Self {
+        let tracker = RefCell::new(ProgressTracker::default());
+
+        Self::new(move |event| {
+            let update = tracker.borrow_mut().update(event);
+            if let Some(update) = update {
+                handler(update);
+            }
+        })
+    }
+
     /// Create a new `FlashProgress` structure with an empty handler.
     pub fn empty() -> Self {
         Self {
@@ -59,9 +85,16 @@ impl<'a> FlashProgress<'a> {
     }
 
     /// Signal that the procedure has made progress.
-    pub(super) fn progressed(&self, operation: ProgressOperation, size: u64, time: Duration) {
+    pub(super) fn progressed(
+        &self,
+        operation: ProgressOperation,
+        address: u64,
+        size: u64,
+        time: Duration,
+    ) {
         self.emit(ProgressEvent::Progress {
             operation,
+            address,
             size,
             time,
         });
@@ -82,6 +115,11 @@ impl<'a> FlashProgress<'a> {
         self.emit(ProgressEvent::DiagnosticMessage { message });
     }
 
+    /// Signal how the time was spent while programming with double buffering.
+    pub(super) fn pipeline_stats(&self, stats: PipelineStats) {
+        self.emit(ProgressEvent::PipelineStats(stats));
+    }
+
     // --- Methods for emitting events for a specific operation.
 
     /// Signal that the erasing procedure started.
@@ -105,23 +143,23 @@ impl<'a> FlashProgress<'a> {
     }
 
     /// Signal that the sector erasing procedure has made progress.
-    pub(super) fn sector_erased(&self, size: u64, time: Duration) {
-        self.progressed(ProgressOperation::Erase, size, time);
+    pub(super) fn sector_erased(&self, address: u64, size: u64, time: Duration) {
+        self.progressed(ProgressOperation::Erase, address, size, time);
     }
 
     /// Signal that the page filling procedure has made progress.
-    pub(super) fn page_filled(&self, size: u64, time: Duration) {
-        self.progressed(ProgressOperation::Fill, size, time);
+    pub(super) fn page_filled(&self, address: u64, size: u64, time: Duration) {
+        self.progressed(ProgressOperation::Fill, address, size, time);
     }
 
     /// Signal that the page programming procedure has made progress.
-    pub(super) fn page_programmed(&self, size: u64, time: Duration) {
-        self.progressed(ProgressOperation::Program, size, time);
+    pub(super) fn page_programmed(&self, address: u64, size: u64, time: Duration) {
+        self.progressed(ProgressOperation::Program, address, size, time);
     }
 
     /// Signal that the page filling procedure has made progress.
-    pub(super) fn page_verified(&self, size: u64, time: Duration) {
-        self.progressed(ProgressOperation::Verify, size, time);
+    pub(super) fn page_verified(&self, address: u64, size: u64, time: Duration) {
+        self.progressed(ProgressOperation::Verify, address, size, time);
     }
 
     /// Signal that the erasing procedure failed.
@@ -166,7 +204,7 @@ impl<'a> FlashProgress<'a> {
 }
 
 /// The operation that is currently in progress.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ProgressOperation {
     /// Reading back flash contents to restore erased regions that should be kept unchanged.
     Fill,
@@ -217,6 +255,8 @@ pub enum ProgressEvent {
     Progress {
         /// The operation that made progress.
         operation: ProgressOperation,
+        /// The address of the page or sector.
+        address: u64,
         /// The size of the page in bytes.
         size: u64,
         /// The time it took to perform the operation.
@@ -234,4 +274,216 @@ pub enum ProgressEvent {
         /// The message that was emitted.
         message: String,
     },
+
+    /// Programming with double buffering has finished, and the time spent in each stage of the
+    /// pipeline is known.
+    PipelineStats(PipelineStats),
+}
+
+/// Where the time went while programming with double buffering.
+///
+/// The next page is transferred into the RAM of the target while the flash algorithm programs
+/// the previous one. If the transfer takes longer than programming the page, the connection to
+/// the probe limits the programming speed, not the flash.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PipelineStats {
+    /// The number of programmed pages.
+    pub pages: usize,
+    /// The number of programmed bytes.
+    pub bytes: u64,
+    /// The time spent transferring page data into the RAM of the target.
+    pub transfer_time: Duration,
+    /// The time spent waiting for the flash algorithm after the next page was transferred.
+    pub flash_wait_time: Duration,
+}
+
+impl PipelineStats {
+    /// Returns `true` if transferring the data took longer than programming it.
+    pub fn is_link_bound(&self) -> bool {
+        self.transfer_time > self.flash_wait_time
+    }
+}
+
+impl std::fmt::Display for PipelineStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "programmed {} pages ({} bytes), spent {:?} transferring data and {:?} waiting for the flash",
+            self.pages, self.bytes, self.transfer_time, self.flash_wait_time
+        )
+    }
+}
+
+/// The state of an operation, reported by [`FlashProgress::structured`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProgressUpdate {
+    /// The operation this update is for.
+    pub operation: ProgressOperation,
+    /// Whether the operation is running, or has ended.
+    pub status: ProgressStatus,
+    /// The number of bytes processed since the operation started.
+    pub completed: u64,
+    /// The address of the page or sector processed by this update, for
+    /// [`ProgressStatus::InProgress`].
+    pub address: Option<u64>,
+    /// The byte offset of the page or sector processed by this update within the operation,
+    /// i.e. the number of bytes processed before it, for [`ProgressStatus::InProgress`].
+    pub offset: Option<u64>,
+    /// The total number of bytes the operation processes, if known.
+    pub total: Option<u64>,
+    /// The average speed of the operation in bytes per second, if known.
+    pub bytes_per_second: Option<f64>,
+    /// The estimated time until the operation is finished, if known.
+    pub eta: Option<Duration>,
+}
+
+/// The status of an operation in a [`ProgressUpdate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressStatus {
+    /// The operation started.
+    Started,
+    /// The operation made progress.
+    InProgress,
+    /// The operation finished successfully.
+    Finished,
+    /// The operation failed.
+    Failed,
+}
+
+/// Accumulates [`ProgressEvent`]s into the state of each operation.
+#[derive(Debug, Default)]
+struct ProgressTracker {
+    operations: HashMap<ProgressOperation, OperationProgress>,
+}
+
+#[derive(Debug, Default)]
+struct OperationProgress {
+    completed: u64,
+    total: Option<u64>,
+    elapsed: Duration,
+}
+
+impl ProgressTracker {
+    /// Records `event`, and returns the new state of the operation it is for.
+    fn update(&mut self, event: ProgressEvent) -> Option<ProgressUpdate> {
+        let mut block = None;
+        let (operation, status) = match event {
+            ProgressEvent::AddProgressBar { operation, total } => {
+                let progress = self.operations.entry(operation).or_default();
+                // Some operations are split into multiple progress bars, one per flash algorithm.
+                if let Some(total) = total {
+                    progress.total = Some(progress.total.unwrap_or(0) + total);
+                }
+                return None;
+            }
+            ProgressEvent::Started(operation) => (operation, ProgressStatus::Started),
+            ProgressEvent::Progress {
+                operation,
+                address,
+                size,
+                time,
+            } => {
+                let progress = self.operations.entry(operation).or_default();
+                block = Some((address, progress.completed));
+                progress.completed += size;
+                progress.elapsed += time;
+                (operation, ProgressStatus::InProgress)
+            }
+            ProgressEvent::Finished(operation) => (operation, ProgressStatus::Finished),
+            ProgressEvent::Failed(operation) => (operation, ProgressStatus::Failed),
+            ProgressEvent::FlashLayoutReady { .. }
+            | ProgressEvent::DiagnosticMessage { .. }
+            | ProgressEvent::PipelineStats(_) => {
+                return None;
+            }
+        };
+
+        let progress = self.operations.entry(operation).or_default();
+
+        let bytes_per_second = if progress.completed > 0 && !progress.elapsed.is_zero() {
+            Some(progress.completed as f64 / progress.elapsed.as_secs_f64())
+        } else {
+            None
+        };
+
+        let eta = match (status, progress.total, bytes_per_second) {
+            (ProgressStatus::Finished, _, _) => Some(Duration::ZERO),
+            (_, Some(total), Some(speed)) => Some(Duration::from_secs_f64(
+                total.saturating_sub(progress.completed) as f64 / speed,
+            )),
+            _ => None,
+        };
+
+        Some(ProgressUpdate {
+            operation,
+            status,
+            completed: progress.completed,
+            address: block.map(|(address, _)| address),
+            offset: block.map(|(_, offset)| offset),
+            total: progress.total,
+            bytes_per_second,
+            eta,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracker_accumulates_progress() {
+        let mut tracker = ProgressTracker::default();
+
+        for total in [1024, 3072] {
+            assert!(
+                tracker
+                    .update(ProgressEvent::AddProgressBar {
+                        operation: ProgressOperation::Program,
+                        total: Some(total),
+                    })
+                    .is_none()
+            );
+        }
+
+        let started = tracker
+            .update(ProgressEvent::Started(ProgressOperation::Program))
+            .unwrap();
+        assert_eq!(started.status, ProgressStatus::Started);
+        assert_eq!(started.total, Some(4096));
+        assert_eq!(started.eta, None);
+
+        let update = tracker
+            .update(ProgressEvent::Progress {
+                operation: ProgressOperation::Program,
+                address: 0x0800_0000,
+                size: 1024,
+                time: Duration::from_millis(500),
+            })
+            .unwrap();
+        assert_eq!(update.completed, 1024);
+        assert_eq!(update.address, Some(0x0800_0000));
+        assert_eq!(update.offset, Some(0));
+        assert_eq!(update.bytes_per_second, Some(2048.0));
+        assert_eq!(update.eta, Some(Duration::from_millis(1500)));
+
+        let update = tracker
+            .update(ProgressEvent::Progress {
+                operation: ProgressOperation::Program,
+                address: 0x0800_0400,
+                size: 1024,
+                time: Duration::from_millis(500),
+            })
+            .unwrap();
+        assert_eq!(update.completed, 2048);
+        assert_eq!(update.address, Some(0x0800_0400));
+        assert_eq!(update.offset, Some(1024));
+
+        let finished = tracker
+            .update(ProgressEvent::Finished(ProgressOperation::Program))
+            .unwrap();
+        assert_eq!(finished.status, ProgressStatus::Finished);
+        assert_eq!(finished.eta, Some(Duration::ZERO));
+        assert_eq!(finished.address, None);
+    }
 }
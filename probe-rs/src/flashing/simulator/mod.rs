@@ -0,0 +1,655 @@
+//! Runs flash algorithms in a simulated core, without any hardware.
+//!
+//! The simulation loads the algorithm the same way [`FlashLoader`](super::FlashLoader) would,
+//! and calls `Init`, `EraseSector`, `ProgramPage`, `Verify` and `UnInit` with the same
+//! arguments. RAM and flash are simulated as described by the target, every other address is
+//! treated as a peripheral register that reads back the value last written to it.
+//!
+//! This catches algorithms that overflow their stack, overwrite their own code, use
+//! instructions the core does not have, or return error codes for a simple erase and program
+//! sequence. Algorithms that wait for a peripheral to change state will run into the
+//! instruction limit, as the simulation does not model any peripherals.
+//!
+//! Only Cortex-M cores are supported.
+
+mod thumb;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Range;
+
+use probe_rs_target::{MemoryRange, MemoryRegion, RawFlashAlgorithm};
+
+use super::{FlashAlgorithm, FlashError};
+use crate::Target;
+use thumb::{Cpu, Stop};
+
+/// The size of the blocks RAM and flash are allocated in.
+const BLOCK_SIZE: u32 = 4096;
+
+/// The operation passed to `Init` and `UnInit`.
+const OPERATION_ERASE: u32 = 1;
+const OPERATION_PROGRAM: u32 = 2;
+const OPERATION_VERIFY: u32 = 3;
+
+/// An error that prevented the simulation from starting.
+#[derive(thiserror::Error, Debug)]
+pub enum SimulationError {
+    /// The flash algorithm could not be placed in the RAM of the target.
+    #[error("The flash algorithm could not be loaded.")]
+    Assemble(#[from] FlashError),
+    /// The core the algorithm runs on cannot be simulated.
+    #[error("Simulating flash algorithms for {0} cores is not supported.")]
+    UnsupportedCore(String),
+    /// Big endian algorithms cannot be simulated.
+    #[error("Simulating big endian flash algorithms is not supported.")]
+    BigEndian,
+}
+
+/// A fault that stopped the simulated core.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum SimulationFault {
+    /// The core executed an instruction the simulation does not implement.
+    #[error("Undefined or unsupported instruction {opcode:#x} at {address:#010x}")]
+    UndefinedInstruction {
+        /// The address of the instruction.
+        address: u64,
+        /// The instruction.
+        opcode: u32,
+    },
+    /// The core tried to execute code outside of RAM and flash.
+    #[error("Instruction fetch from {address:#010x}, which is neither RAM nor flash")]
+    InvalidFetch {
+        /// The address of the instruction.
+        address: u64,
+    },
+}
+
+/// How a simulated call of an algorithm function ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallOutcome {
+    /// The function returned the given value.
+    Returned(u32),
+    /// The function did not return within the instruction limit.
+    InstructionLimit,
+    /// The core faulted.
+    Fault(SimulationFault),
+}
+
+/// A simulated call of an algorithm function.
+#[derive(Debug, Clone)]
+pub struct SimulatedCall {
+    /// The name of the function, e.g. `EraseSector`.
+    pub function: &'static str,
+    /// The arguments passed in `r0` to `r2`.
+    pub arguments: [u32; 3],
+    /// How the call ended.
+    pub outcome: CallOutcome,
+    /// The return value that indicates success.
+    pub expected: u32,
+    /// The number of executed instructions.
+    pub instructions: u64,
+}
+
+impl SimulatedCall {
+    /// Returns whether the function returned the expected value.
+    pub fn is_success(&self) -> bool {
+        self.outcome == CallOutcome::Returned(self.expected)
+    }
+}
+
+/// The results of a simulation.
+#[derive(Debug, Clone)]
+pub struct SimulationReport {
+    /// The calls of algorithm functions, in order. The simulation stops after the first call
+    /// that fails.
+    pub calls: Vec<SimulatedCall>,
+    /// The address the algorithm was loaded to.
+    pub load_address: u64,
+    /// The initial stack pointer.
+    pub stack_top: u64,
+    /// The configured stack size.
+    pub stack_size: u64,
+    /// The maximum stack usage over all calls.
+    pub max_stack_usage: u64,
+    /// The page buffers.
+    pub page_buffers: Vec<u64>,
+    /// Problems with the placement of the algorithm in RAM.
+    pub layout_issues: Vec<String>,
+    /// The addresses of all words in the code of the algorithm that were written.
+    pub code_writes: Vec<u64>,
+    /// The addresses of all peripheral registers that were accessed.
+    pub peripheral_accesses: Vec<u64>,
+    /// Whether the programmed page could be read back from the simulated flash. `None` if the
+    /// page was not programmed successfully.
+    ///
+    /// Algorithms that program flash through peripheral registers instead of writing to the
+    /// flash addresses always leave the simulated flash erased.
+    pub programmed_data_visible: Option<bool>,
+}
+
+impl SimulationReport {
+    /// Returns whether the algorithm used more stack than it has.
+    pub fn stack_overflow(&self) -> bool {
+        self.max_stack_usage > self.stack_size
+    }
+
+    /// Returns whether all calls succeeded without any problems.
+    pub fn is_success(&self) -> bool {
+        self.calls.iter().all(SimulatedCall::is_success)
+            && !self.stack_overflow()
+            && self.layout_issues.is_empty()
+            && self.code_writes.is_empty()
+    }
+}
+
+/// Simulates erasing and programming the first sector of `raw_algorithm` on `target`.
+///
+/// Every call is aborted after `max_instructions` instructions.
+pub fn simulate_flash_algorithm(
+    target: &Target,
+    raw_algorithm: &RawFlashAlgorithm,
+    max_instructions: u64,
+) -> Result<SimulationReport, SimulationError> {
+    if raw_algorithm.big_endian {
+        return Err(SimulationError::BigEndian);
+    }
+
+    let core_index = match raw_algorithm.core_index {
+        Some(core_index) => core_index,
+        None => raw_algorithm
+            .cores
+            .first()
+            .and_then(|name| target.core_index_by_name(name))
+            .unwrap_or(0),
+    };
+    let core = &target.cores[core_index];
+    if !core.core_type.is_cortex_m() {
+        return Err(SimulationError::UnsupportedCore(format!(
+            "{:?}",
+            core.core_type
+        )));
+    }
+
+    let algorithm = FlashAlgorithm::assemble_from_raw_with_core(raw_algorithm, &core.name, target)?;
+
+    let mut simulation = Simulation::new(target, &algorithm, max_instructions);
+    simulation.run();
+
+    let code_end = code_end(&algorithm);
+    let stack_bottom = algorithm.stack_top - algorithm.stack_size;
+    let page_size = algorithm.flash_properties.page_size as u64;
+
+    let mut layout_issues = Vec::new();
+    for &buffer in &algorithm.page_buffers {
+        let range = buffer..buffer + page_size;
+        if range.intersects_range(&(algorithm.load_address..code_end)) {
+            layout_issues.push(format!(
+                "The page buffer at {buffer:#010x} overlaps the code of the algorithm"
+            ));
+        }
+        if range.intersects_range(&(stack_bottom..algorithm.stack_top)) {
+            layout_issues.push(format!(
+                "The page buffer at {buffer:#010x} overlaps the stack of the algorithm"
+            ));
+        }
+        if !simulation.memory.is_ram_range(&range) {
+            layout_issues.push(format!(
+                "The page buffer at {buffer:#010x} is not located in RAM"
+            ));
+        }
+    }
+
+    Ok(SimulationReport {
+        calls: simulation.calls,
+        load_address: algorithm.load_address,
+        stack_top: algorithm.stack_top,
+        stack_size: algorithm.stack_size,
+        max_stack_usage: algorithm
+            .stack_top
+            .saturating_sub(simulation.min_stack_pointer as u64),
+        page_buffers: algorithm.page_buffers.clone(),
+        layout_issues,
+        code_writes: simulation
+            .memory
+            .code_writes
+            .iter()
+            .map(|&address| address as u64)
+            .collect(),
+        peripheral_accesses: simulation
+            .memory
+            .peripheral_registers
+            .keys()
+            .map(|&address| address as u64)
+            .collect(),
+        programmed_data_visible: simulation.programmed_data_visible,
+    })
+}
+
+/// The end of the code of `algorithm`. The data section, which the algorithm may write to,
+/// starts at the static base.
+fn code_end(algorithm: &FlashAlgorithm) -> u64 {
+    let blob_end = algorithm.load_address + algorithm.instructions.len() as u64 * 4;
+
+    algorithm
+        .static_base
+        .clamp(algorithm.load_address, blob_end)
+}
+
+/// The state of a running simulation.
+struct Simulation<'algo> {
+    algorithm: &'algo FlashAlgorithm,
+    cpu: Cpu,
+    memory: Memory,
+    max_instructions: u64,
+    min_stack_pointer: u32,
+    calls: Vec<SimulatedCall>,
+    programmed_data_visible: Option<bool>,
+}
+
+impl<'algo> Simulation<'algo> {
+    fn new(target: &Target, algorithm: &'algo FlashAlgorithm, max_instructions: u64) -> Self {
+        let to_u32 = |range: Range<u64>| range.start as u32..range.end.min(u32::MAX as u64) as u32;
+        let regions = |select: fn(&MemoryRegion) -> bool| {
+            target
+                .memory_map
+                .iter()
+                .filter(|region| select(region))
+                .map(|region| to_u32(region.address_range()))
+                .collect::<Vec<_>>()
+        };
+
+        let mut memory = Memory::new(
+            regions(MemoryRegion::is_ram),
+            regions(MemoryRegion::is_nvm),
+            algorithm.flash_properties.erased_byte_value,
+        );
+        for (i, word) in algorithm.instructions.iter().enumerate() {
+            memory.write(algorithm.load_address as u32 + i as u32 * 4, 4, *word);
+        }
+        memory.code = to_u32(algorithm.load_address..code_end(algorithm));
+
+        Self {
+            algorithm,
+            cpu: Cpu::new(),
+            memory,
+            max_instructions,
+            min_stack_pointer: algorithm.stack_top as u32,
+            calls: Vec::new(),
+            programmed_data_visible: None,
+        }
+    }
+
+    /// Runs the erase, program and verify sequence, until a call fails.
+    fn run(&mut self) {
+        let algorithm = self.algorithm;
+        let Some(sector) = algorithm.iter_sectors().next() else {
+            return;
+        };
+        let Some(page) = algorithm.iter_pages().next() else {
+            return;
+        };
+        let flash_start = algorithm.flash_properties.address_range.start as u32;
+        let sector_address = sector.base_address as u32;
+        let page_address = page.base_address as u32;
+        let page_size = page.size;
+        let buffer = algorithm.page_buffers[0] as u32;
+
+        let _ = self.with_init(flash_start, OPERATION_ERASE, |simulation| {
+            simulation.call(
+                "EraseSector",
+                algorithm.pc_erase_sector,
+                [sector_address, 0, 0],
+                0,
+            )
+        });
+
+        let pattern = (0..page_size).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        for (offset, byte) in pattern.iter().enumerate() {
+            self.memory.write(buffer + offset as u32, 1, *byte as u32);
+        }
+
+        let programmed = self.with_init(flash_start, OPERATION_PROGRAM, |simulation| {
+            simulation.call(
+                "ProgramPage",
+                algorithm.pc_program_page,
+                [page_address, page_size, buffer],
+                0,
+            )
+        });
+        if programmed != Some(true) {
+            return;
+        }
+
+        self.programmed_data_visible = Some(pattern.iter().enumerate().all(|(offset, byte)| {
+            self.memory.read(page_address + offset as u32, 1) == *byte as u32
+        }));
+
+        if let Some(pc_verify) = algorithm.pc_verify {
+            let _ = self.with_init(flash_start, OPERATION_VERIFY, |simulation| {
+                simulation.call(
+                    "Verify",
+                    pc_verify,
+                    [page_address, page_size, buffer],
+                    page_address + page_size,
+                )
+            });
+        }
+    }
+
+    /// Calls `Init`, then `f`, then `UnInit`, as long as the previous calls succeeded. Returns
+    /// whether all calls succeeded, or `None` if an earlier call failed.
+    fn with_init(
+        &mut self,
+        address: u32,
+        operation: u32,
+        f: impl FnOnce(&mut Self) -> bool,
+    ) -> Option<bool> {
+        if !self.calls.iter().all(SimulatedCall::is_success) {
+            return None;
+        }
+
+        // The flasher sets up the static base and stack pointer when it calls `Init`.
+        self.cpu.r[9] = self.algorithm.static_base as u32;
+        self.cpu.r[13] = self.algorithm.stack_top as u32;
+
+        if let Some(pc_init) = self.algorithm.pc_init {
+            if !self.call("Init", pc_init, [address, 0, operation], 0) {
+                return Some(false);
+            }
+        }
+
+        if !f(self) {
+            return Some(false);
+        }
+
+        if let Some(pc_uninit) = self.algorithm.pc_uninit {
+            if !self.call("UnInit", pc_uninit, [operation, 0, 0], 0) {
+                return Some(false);
+            }
+        }
+
+        Some(true)
+    }
+
+    /// Calls the function at `address`, and returns whether it returned `expected`.
+    fn call(
+        &mut self,
+        function: &'static str,
+        address: u64,
+        arguments: [u32; 3],
+        expected: u32,
+    ) -> bool {
+        self.cpu.r[..3].copy_from_slice(&arguments);
+        self.cpu.r[14] = self.algorithm.load_address as u32 + 1;
+        self.cpu.r[15] = address as u32 & !1;
+
+        let mut instructions = 0;
+        let outcome = loop {
+            if instructions == self.max_instructions {
+                break CallOutcome::InstructionLimit;
+            }
+            instructions += 1;
+
+            match self.cpu.step(&mut self.memory) {
+                Ok(Some(Stop::Breakpoint)) => break CallOutcome::Returned(self.cpu.r[0]),
+                Ok(None) => {}
+                Err(fault) => break CallOutcome::Fault(fault),
+            }
+            self.min_stack_pointer = self.min_stack_pointer.min(self.cpu.r[13]);
+        };
+
+        tracing::debug!("{function}{arguments:#x?}: {outcome:?} after {instructions} instructions");
+
+        let call = SimulatedCall {
+            function,
+            arguments,
+            outcome,
+            expected,
+            instructions,
+        };
+        let success = call.is_success();
+        self.calls.push(call);
+
+        success
+    }
+}
+
+/// The memory of the simulated target.
+struct Memory {
+    ram: Vec<Range<u32>>,
+    flash: Vec<Range<u32>>,
+    erased_byte_value: u8,
+    /// The allocated blocks of RAM and flash, by start address.
+    blocks: HashMap<u32, Vec<u8>>,
+    /// The values of the peripheral registers that were accessed, by word address.
+    peripheral_registers: BTreeMap<u32, u32>,
+    code: Range<u32>,
+    code_writes: BTreeSet<u32>,
+}
+
+impl Memory {
+    fn new(ram: Vec<Range<u32>>, flash: Vec<Range<u32>>, erased_byte_value: u8) -> Self {
+        Self {
+            ram,
+            flash,
+            erased_byte_value,
+            blocks: HashMap::new(),
+            peripheral_registers: BTreeMap::new(),
+            code: 0..0,
+            code_writes: BTreeSet::new(),
+        }
+    }
+
+    fn is_ram(&self, address: u32) -> bool {
+        self.ram.iter().any(|range| range.contains(&address))
+    }
+
+    fn is_flash(&self, address: u32) -> bool {
+        self.flash.iter().any(|range| range.contains(&address))
+    }
+
+    fn is_ram_range(&self, range: &Range<u64>) -> bool {
+        self.ram
+            .iter()
+            .any(|ram| (ram.start as u64..ram.end as u64).contains_range(range))
+    }
+
+    /// Returns the byte at `address`, if it is RAM or flash.
+    fn byte(&mut self, address: u32) -> Option<&mut u8> {
+        let fill = if self.is_ram(address) {
+            0
+        } else if self.is_flash(address) {
+            self.erased_byte_value
+        } else {
+            return None;
+        };
+
+        let block = self
+            .blocks
+            .entry(address - address % BLOCK_SIZE)
+            .or_insert_with(|| vec![fill; BLOCK_SIZE as usize]);
+
+        Some(&mut block[(address % BLOCK_SIZE) as usize])
+    }
+
+    /// Fetches the halfword at `address` for execution.
+    fn fetch(&mut self, address: u32) -> Result<u16, SimulationFault> {
+        if !self.is_ram(address) && !self.is_flash(address) {
+            return Err(SimulationFault::InvalidFetch {
+                address: address as u64,
+            });
+        }
+
+        Ok(self.read(address, 2) as u16)
+    }
+
+    /// Reads `size` bytes from `address`, little endian.
+    fn read(&mut self, address: u32, size: u32) -> u32 {
+        if self.byte(address).is_none() {
+            let word_address = address & !3;
+            let word = *self.peripheral_registers.entry(word_address).or_insert(0);
+            return (word >> ((address & 3) * 8)) & size_mask(size);
+        }
+
+        (0..size).fold(0, |value, i| {
+            let byte = self.byte(address.wrapping_add(i)).map_or(0, |byte| *byte);
+            value | (byte as u32) << (8 * i)
+        })
+    }
+
+    /// Writes the lowest `size` bytes of `value` to `address`, little endian.
+    fn write(&mut self, address: u32, size: u32, value: u32) {
+        if self.byte(address).is_none() {
+            let word_address = address & !3;
+            let shift = (address & 3) * 8;
+            let mask = size_mask(size) << shift;
+            let word = self.peripheral_registers.entry(word_address).or_insert(0);
+            *word = (*word & !mask) | ((value << shift) & mask);
+            return;
+        }
+
+        for i in 0..size {
+            let address = address.wrapping_add(i);
+            if self.code.contains(&address) {
+                self.code_writes.insert(address & !3);
+            }
+            if let Some(byte) = self.byte(address) {
+                *byte = (value >> (8 * i)) as u8;
+            }
+        }
+    }
+}
+
+fn size_mask(size: u32) -> u32 {
+    if size >= 4 {
+        u32::MAX
+    } else {
+        (1 << (size * 8)) - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::crc::THUMB_CRC32_STUB;
+    use super::*;
+
+    /// The RAM the stubs are loaded to.
+    const STUB_RAM: Range<u32> = 0x2000_0000..0x2000_1000;
+
+    #[test]
+    fn runs_crc_stub() {
+        let mut memory = Memory::new(vec![STUB_RAM], vec![], 0xFF);
+
+        // A breakpoint to return to, followed by the stub.
+        memory.write(0x2000_0000, 2, 0xBE00);
+        for (offset, byte) in THUMB_CRC32_STUB.iter().enumerate() {
+            memory.write(0x2000_0004 + offset as u32, 1, *byte as u32);
+        }
+        for (offset, byte) in b"123456789".iter().enumerate() {
+            memory.write(0x2000_0100 + offset as u32, 1, *byte as u32);
+        }
+
+        let mut cpu = Cpu::new();
+        cpu.r[0] = 0x2000_0100;
+        cpu.r[1] = 9;
+        cpu.r[13] = 0x2000_1000;
+        cpu.r[14] = 0x2000_0001;
+        cpu.r[15] = 0x2000_0004;
+
+        let mut steps = 0;
+        while cpu.step(&mut memory).unwrap().is_none() {
+            steps += 1;
+            assert!(steps < 10_000, "The stub did not return");
+        }
+
+        assert_eq!(cpu.r[0], 0xCBF4_3926);
+        assert!(memory.peripheral_registers.is_empty());
+    }
+
+    #[test]
+    fn runs_watchdog_stub() {
+        use probe_rs_target::WatchdogWrite;
+
+        let mut memory = Memory::new(vec![STUB_RAM], vec![], 0xFF);
+        let writes = [
+            (0x4005_200E, 0xC520, 16),
+            (0x4005_200E, 0xD928, 16),
+            (0x4005_2000, 0x01D2, 16),
+            (0x4004_8100, 0x12, 8),
+        ]
+        .map(|(address, value, width)| WatchdogWrite {
+            address,
+            value,
+            width,
+        });
+
+        // A breakpoint to return to, followed by the stub.
+        memory.write(0x2000_0000, 2, 0xBE00);
+        let stub = super::super::watchdog::thumb_write_stub(&writes).unwrap();
+        for (offset, byte) in stub.iter().enumerate() {
+            memory.write(0x2000_0004 + offset as u32, 1, *byte as u32);
+        }
+
+        let mut cpu = Cpu::new();
+        cpu.r[13] = 0x2000_1000;
+        cpu.r[14] = 0x2000_0001;
+        cpu.r[15] = 0x2000_0004;
+
+        let mut steps = 0;
+        while cpu.step(&mut memory).unwrap().is_none() {
+            steps += 1;
+            assert!(steps < 100, "The stub did not return");
+        }
+
+        assert_eq!(
+            memory.peripheral_registers,
+            BTreeMap::from([
+                (0x4004_8100, 0x12),
+                (0x4005_2000, 0x01D2),
+                (0x4005_200C, 0xD928_0000),
+            ])
+        );
+    }
+
+    #[test]
+    fn runs_fill_stub() {
+        let mut memory = Memory::new(vec![STUB_RAM], vec![], 0xFF);
+        for (offset, byte) in crate::core::fill::THUMB_FILL_STUB.iter().enumerate() {
+            memory.write(0x2000_0000 + offset as u32, 1, *byte as u32);
+        }
+
+        let mut cpu = Cpu::new();
+        cpu.r[0] = 0x2000_0100;
+        cpu.r[1] = 0xDEAD_BEEF;
+        cpu.r[2] = 64;
+        cpu.r[15] = 0x2000_0000;
+
+        let mut steps = 0;
+        while cpu.step(&mut memory).unwrap().is_none() {
+            steps += 1;
+            assert!(steps < 1000, "The stub did not halt");
+        }
+
+        assert_eq!(memory.read(0x2000_00FC, 4), 0);
+        for offset in 0..64 {
+            assert_eq!(memory.read(0x2000_0100 + 4 * offset, 4), 0xDEAD_BEEF);
+        }
+        assert_eq!(memory.read(0x2000_0200, 4), 0);
+    }
+
+    #[test]
+    fn peripheral_registers_read_back_writes() {
+        let flash = 0x0..0x1000;
+        let mut memory = Memory::new(vec![], vec![flash], 0xFF);
+
+        assert_eq!(memory.read(0x100, 4), 0xFFFF_FFFF);
+        assert_eq!(memory.read(0x4000_0000, 4), 0);
+
+        memory.write(0x4000_0001, 1, 0xAB);
+        assert_eq!(memory.read(0x4000_0000, 4), 0xAB00);
+        assert_eq!(
+            memory.peripheral_registers.keys().collect::<Vec<_>>(),
+            [&0x4000_0000]
+        );
+    }
+}
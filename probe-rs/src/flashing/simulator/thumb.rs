@@ -0,0 +1,1097 @@
+//! An interpreter for the Thumb instructions used by flash algorithms.
+//!
+//! Covers ARMv6-M and the integer subset of ARMv7-M. Floating point, coprocessor and DSP
+//! instructions are reported as undefined.
+
+use super::Memory;
+use super::SimulationFault;
+
+/// Why the interpreter stopped executing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Stop {
+    /// A `BKPT` instruction was executed.
+    Breakpoint,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShiftType {
+    Lsl,
+    Lsr,
+    Asr,
+    Ror,
+    Rrx,
+}
+
+impl ShiftType {
+    /// Decodes the type and amount of a shift by an immediate.
+    fn decode_immediate(kind: u32, imm5: u32) -> (Self, u32) {
+        match kind {
+            0 => (Self::Lsl, imm5),
+            1 => (Self::Lsr, if imm5 == 0 { 32 } else { imm5 }),
+            2 => (Self::Asr, if imm5 == 0 { 32 } else { imm5 }),
+            _ if imm5 == 0 => (Self::Rrx, 1),
+            _ => (Self::Ror, imm5),
+        }
+    }
+
+    /// Decodes the type of a shift by a register.
+    fn decode_register(kind: u32) -> Self {
+        match kind {
+            0 => Self::Lsl,
+            1 => Self::Lsr,
+            2 => Self::Asr,
+            _ => Self::Ror,
+        }
+    }
+}
+
+/// Shifts `value`, and returns the result and the carry out.
+fn shift_c(value: u32, kind: ShiftType, amount: u32, carry_in: bool) -> (u32, bool) {
+    if amount == 0 && kind != ShiftType::Rrx {
+        return (value, carry_in);
+    }
+
+    match kind {
+        ShiftType::Lsl => match amount {
+            1..32 => (value << amount, (value >> (32 - amount)) & 1 != 0),
+            32 => (0, value & 1 != 0),
+            _ => (0, false),
+        },
+        ShiftType::Lsr => match amount {
+            1..32 => (value >> amount, (value >> (amount - 1)) & 1 != 0),
+            32 => (0, value >> 31 != 0),
+            _ => (0, false),
+        },
+        ShiftType::Asr => match amount {
+            1..32 => (
+                ((value as i32) >> amount) as u32,
+                (value >> (amount - 1)) & 1 != 0,
+            ),
+            _ => {
+                let result = ((value as i32) >> 31) as u32;
+                (result, result & 1 != 0)
+            }
+        },
+        ShiftType::Ror => {
+            let result = value.rotate_right(amount % 32);
+            (result, result >> 31 != 0)
+        }
+        ShiftType::Rrx => (((carry_in as u32) << 31) | (value >> 1), value & 1 != 0),
+    }
+}
+
+/// Adds `x`, `y` and the carry, and returns the result, the carry out and the overflow flag.
+fn add_with_carry(x: u32, y: u32, carry_in: bool) -> (u32, bool, bool) {
+    let (partial, carry1) = x.overflowing_add(y);
+    let (result, carry2) = partial.overflowing_add(carry_in as u32);
+    let overflow = ((x ^ result) & (y ^ result)) >> 31 != 0;
+
+    (result, carry1 || carry2, overflow)
+}
+
+/// Expands the modified immediate constant of a 32-bit data processing instruction.
+fn expand_immediate_c(imm12: u32, carry_in: bool) -> (u32, bool) {
+    if imm12 >> 10 == 0 {
+        let imm8 = imm12 & 0xFF;
+        let value = match (imm12 >> 8) & 3 {
+            0 => imm8,
+            1 => (imm8 << 16) | imm8,
+            2 => (imm8 << 24) | (imm8 << 8),
+            _ => imm8 * 0x0101_0101,
+        };
+        (value, carry_in)
+    } else {
+        let value = (0x80 | (imm12 & 0x7F)).rotate_right(imm12 >> 7);
+        (value, value >> 31 != 0)
+    }
+}
+
+fn sign_extend(value: u32, bits: u32) -> u32 {
+    let shift = 32 - bits;
+    (((value << shift) as i32) >> shift) as u32
+}
+
+/// The data processing operations shared by the 16-bit and 32-bit encodings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DataOp {
+    And,
+    Bic,
+    Orr,
+    Orn,
+    Eor,
+    Add,
+    Adc,
+    Sbc,
+    Sub,
+    Rsb,
+    Mov,
+    Mvn,
+    Tst,
+    Teq,
+    Cmp,
+    Cmn,
+}
+
+/// The register state of the simulated core.
+pub(super) struct Cpu {
+    /// The core registers. `r[15]` holds the address of the current instruction.
+    pub(super) r: [u32; 16],
+    n: bool,
+    z: bool,
+    c: bool,
+    v: bool,
+    /// The state of the current IT block.
+    it: u8,
+    /// Whether the current instruction is inside an IT block.
+    in_it: bool,
+    /// The address of the next instruction.
+    next_pc: u32,
+}
+
+impl Cpu {
+    pub(super) fn new() -> Self {
+        Self {
+            r: [0; 16],
+            n: false,
+            z: false,
+            c: false,
+            v: false,
+            it: 0,
+            in_it: false,
+            next_pc: 0,
+        }
+    }
+
+    /// Executes a single instruction.
+    pub(super) fn step(&mut self, memory: &mut Memory) -> Result<Option<Stop>, SimulationFault> {
+        let pc = self.r[15];
+        let hw1 = memory.fetch(pc)?;
+        let is_32bit = hw1 >> 11 >= 0b11101;
+        let hw2 = if is_32bit {
+            memory.fetch(pc.wrapping_add(2))?
+        } else {
+            0
+        };
+
+        self.next_pc = pc.wrapping_add(if is_32bit { 4 } else { 2 });
+        self.in_it = self.it != 0;
+
+        let execute = !self.in_it || self.condition_passed((self.it >> 4) as u32);
+        let in_it = self.in_it;
+
+        let result = if !execute {
+            Ok(None)
+        } else if is_32bit {
+            self.execute_32bit(memory, hw1 as u32, hw2 as u32)
+                .map(|_| None)
+        } else {
+            self.execute_16bit(memory, hw1 as u32)
+        };
+
+        let Ok(stop) = result else {
+            return Err(SimulationFault::UndefinedInstruction {
+                address: pc as u64,
+                opcode: if is_32bit {
+                    ((hw1 as u32) << 16) | hw2 as u32
+                } else {
+                    hw1 as u32
+                },
+            });
+        };
+
+        if in_it {
+            self.it = if self.it & 0x7 == 0 {
+                0
+            } else {
+                (self.it & 0xE0) | ((self.it << 1) & 0x1F)
+            };
+        }
+        self.r[15] = self.next_pc;
+
+        Ok(stop)
+    }
+
+    fn condition_passed(&self, cond: u32) -> bool {
+        match cond {
+            0x0 => self.z,
+            0x1 => !self.z,
+            0x2 => self.c,
+            0x3 => !self.c,
+            0x4 => self.n,
+            0x5 => !self.n,
+            0x6 => self.v,
+            0x7 => !self.v,
+            0x8 => self.c && !self.z,
+            0x9 => !self.c || self.z,
+            0xA => self.n == self.v,
+            0xB => self.n != self.v,
+            0xC => !self.z && self.n == self.v,
+            0xD => self.z || self.n != self.v,
+            _ => true,
+        }
+    }
+
+    /// Reads a register. Reading the PC returns the address of the instruction plus 4.
+    fn reg(&self, n: u32) -> u32 {
+        if n == 15 {
+            self.r[15].wrapping_add(4)
+        } else {
+            self.r[n as usize]
+        }
+    }
+
+    /// The PC aligned to a word, as used by PC relative loads.
+    fn aligned_pc(&self) -> u32 {
+        self.reg(15) & !3
+    }
+
+    /// Writes a register. Writing the PC branches.
+    fn set_reg(&mut self, n: u32, value: u32) {
+        if n == 15 {
+            self.branch(value);
+        } else {
+            self.r[n as usize] = value;
+        }
+    }
+
+    fn branch(&mut self, address: u32) {
+        self.next_pc = address & !1;
+    }
+
+    fn set_nz(&mut self, result: u32) {
+        self.n = result >> 31 != 0;
+        self.z = result == 0;
+    }
+
+    /// Performs a data processing operation, and writes the result to `rd` unless it is a
+    /// comparison.
+    fn data_op(
+        &mut self,
+        op: DataOp,
+        rd: u32,
+        n: u32,
+        m: u32,
+        shifter_carry: bool,
+        set_flags: bool,
+    ) {
+        let (result, carry, overflow) = match op {
+            DataOp::And | DataOp::Tst => (n & m, shifter_carry, self.v),
+            DataOp::Bic => (n & !m, shifter_carry, self.v),
+            DataOp::Orr => (n | m, shifter_carry, self.v),
+            DataOp::Orn => (n | !m, shifter_carry, self.v),
+            DataOp::Eor | DataOp::Teq => (n ^ m, shifter_carry, self.v),
+            DataOp::Mov => (m, shifter_carry, self.v),
+            DataOp::Mvn => (!m, shifter_carry, self.v),
+            DataOp::Add | DataOp::Cmn => add_with_carry(n, m, false),
+            DataOp::Adc => add_with_carry(n, m, self.c),
+            DataOp::Sub | DataOp::Cmp => add_with_carry(n, !m, true),
+            DataOp::Sbc => add_with_carry(n, !m, self.c),
+            DataOp::Rsb => add_with_carry(!n, m, true),
+        };
+
+        let is_comparison = matches!(op, DataOp::Tst | DataOp::Teq | DataOp::Cmp | DataOp::Cmn);
+        if !is_comparison {
+            self.set_reg(rd, result);
+        }
+
+        if set_flags || is_comparison {
+            self.set_nz(result);
+            self.c = carry;
+            self.v = overflow;
+        }
+    }
+
+    fn load(&mut self, memory: &mut Memory, rt: u32, address: u32, size: u32, signed: bool) {
+        let value = memory.read(address, size);
+        let value = if signed {
+            sign_extend(value, size * 8)
+        } else {
+            value
+        };
+        self.set_reg(rt, value);
+    }
+
+    fn store(&mut self, memory: &mut Memory, rt: u32, address: u32, size: u32) {
+        memory.write(address, size, self.reg(rt));
+    }
+
+    /// Loads the registers in `list` from increasing addresses starting at `address`, and
+    /// returns the address after the last register.
+    fn load_multiple(&mut self, memory: &mut Memory, mut address: u32, list: u32) -> u32 {
+        for reg in 0..16 {
+            if list & (1 << reg) != 0 {
+                let value = memory.read(address, 4);
+                self.set_reg(reg, value);
+                address = address.wrapping_add(4);
+            }
+        }
+        address
+    }
+
+    /// Stores the registers in `list` to increasing addresses starting at `address`, and
+    /// returns the address after the last register.
+    fn store_multiple(&mut self, memory: &mut Memory, mut address: u32, list: u32) -> u32 {
+        for reg in 0..16 {
+            if list & (1 << reg) != 0 {
+                memory.write(address, 4, self.reg(reg));
+                address = address.wrapping_add(4);
+            }
+        }
+        address
+    }
+
+    fn execute_16bit(&mut self, memory: &mut Memory, hw: u32) -> Result<Option<Stop>, Undefined> {
+        let set_flags = !self.in_it;
+        let low3 = |shift: u32| (hw >> shift) & 7;
+
+        match hw >> 11 {
+            // Shift by immediate.
+            0b00000..=0b00010 => {
+                let (kind, amount) = ShiftType::decode_immediate(hw >> 11, (hw >> 6) & 0x1F);
+                let (result, carry) = shift_c(self.reg(low3(3)), kind, amount, self.c);
+                self.set_reg(low3(0), result);
+                if set_flags {
+                    self.set_nz(result);
+                    self.c = carry;
+                }
+            }
+            // Add or subtract a register or a 3-bit immediate.
+            0b00011 => {
+                let n = self.reg(low3(3));
+                let m = if hw & 0x400 != 0 {
+                    low3(6)
+                } else {
+                    self.reg(low3(6))
+                };
+                let op = if hw & 0x200 != 0 {
+                    DataOp::Sub
+                } else {
+                    DataOp::Add
+                };
+                self.data_op(op, low3(0), n, m, self.c, set_flags);
+            }
+            // Move, compare, add or subtract an 8-bit immediate.
+            0b00100..=0b00111 => {
+                let rdn = low3(8);
+                let imm8 = hw & 0xFF;
+                let op = match (hw >> 11) & 3 {
+                    0 => DataOp::Mov,
+                    1 => DataOp::Cmp,
+                    2 => DataOp::Add,
+                    _ => DataOp::Sub,
+                };
+                self.data_op(op, rdn, self.reg(rdn), imm8, self.c, set_flags);
+            }
+            0b01000 => {
+                if hw & 0x400 == 0 {
+                    self.execute_16bit_data_processing(hw, set_flags);
+                } else {
+                    self.execute_16bit_special(hw)?;
+                }
+            }
+            // Load from a literal pool.
+            0b01001 => {
+                let address = self.aligned_pc().wrapping_add((hw & 0xFF) * 4);
+                self.load(memory, low3(8), address, 4, false);
+            }
+            // Load or store with a register offset.
+            0b01010 | 0b01011 => {
+                let address = self.reg(low3(3)).wrapping_add(self.reg(low3(6)));
+                let rt = low3(0);
+                match (hw >> 9) & 7 {
+                    0 => self.store(memory, rt, address, 4),
+                    1 => self.store(memory, rt, address, 2),
+                    2 => self.store(memory, rt, address, 1),
+                    3 => self.load(memory, rt, address, 1, true),
+                    4 => self.load(memory, rt, address, 4, false),
+                    5 => self.load(memory, rt, address, 2, false),
+                    6 => self.load(memory, rt, address, 1, false),
+                    _ => self.load(memory, rt, address, 2, true),
+                }
+            }
+            // Load or store with an immediate offset.
+            0b01100..=0b10001 => {
+                let imm5 = (hw >> 6) & 0x1F;
+                let base = self.reg(low3(3));
+                let rt = low3(0);
+                match hw >> 11 {
+                    0b01100 => self.store(memory, rt, base.wrapping_add(imm5 * 4), 4),
+                    0b01101 => self.load(memory, rt, base.wrapping_add(imm5 * 4), 4, false),
+                    0b01110 => self.store(memory, rt, base.wrapping_add(imm5), 1),
+                    0b01111 => self.load(memory, rt, base.wrapping_add(imm5), 1, false),
+                    0b10000 => self.store(memory, rt, base.wrapping_add(imm5 * 2), 2),
+                    _ => self.load(memory, rt, base.wrapping_add(imm5 * 2), 2, false),
+                }
+            }
+            // Load or store relative to the SP.
+            0b10010 => self.store(memory, low3(8), self.r[13].wrapping_add((hw & 0xFF) * 4), 4),
+            0b10011 => self.load(
+                memory,
+                low3(8),
+                self.r[13].wrapping_add((hw & 0xFF) * 4),
+                4,
+                false,
+            ),
+            // Generate a PC or SP relative address.
+            0b10100 => self.set_reg(low3(8), self.aligned_pc().wrapping_add((hw & 0xFF) * 4)),
+            0b10101 => self.set_reg(low3(8), self.r[13].wrapping_add((hw & 0xFF) * 4)),
+            0b10110 | 0b10111 => return self.execute_16bit_misc(memory, hw),
+            // Store and load multiple, incrementing after.
+            0b11000 => {
+                let rn = low3(8);
+                let end = self.store_multiple(memory, self.reg(rn), hw & 0xFF);
+                self.set_reg(rn, end);
+            }
+            0b11001 => {
+                let rn = low3(8);
+                let end = self.load_multiple(memory, self.reg(rn), hw & 0xFF);
+                if hw & (1 << rn) == 0 {
+                    self.set_reg(rn, end);
+                }
+            }
+            // Conditional branch, `UDF` and `SVC`.
+            0b11010 | 0b11011 => {
+                let cond = (hw >> 8) & 0xF;
+                if cond >= 0xE {
+                    return Err(Undefined);
+                }
+                if self.condition_passed(cond) {
+                    let offset = sign_extend((hw & 0xFF) << 1, 9);
+                    self.branch(self.reg(15).wrapping_add(offset));
+                }
+            }
+            // Unconditional branch.
+            0b11100 => {
+                let offset = sign_extend((hw & 0x7FF) << 1, 12);
+                self.branch(self.reg(15).wrapping_add(offset));
+            }
+            _ => return Err(Undefined),
+        }
+
+        Ok(None)
+    }
+
+    fn execute_16bit_data_processing(&mut self, hw: u32, set_flags: bool) {
+        let rdn = hw & 7;
+        let rm = (hw >> 3) & 7;
+        let n = self.reg(rdn);
+        let m = self.reg(rm);
+
+        let shift = |kind, cpu: &mut Self| {
+            let (result, carry) = shift_c(n, kind, m & 0xFF, cpu.c);
+            cpu.set_reg(rdn, result);
+            if set_flags {
+                cpu.set_nz(result);
+                cpu.c = carry;
+            }
+        };
+
+        match (hw >> 6) & 0xF {
+            0x0 => self.data_op(DataOp::And, rdn, n, m, self.c, set_flags),
+            0x1 => self.data_op(DataOp::Eor, rdn, n, m, self.c, set_flags),
+            0x2 => shift(ShiftType::Lsl, self),
+            0x3 => shift(ShiftType::Lsr, self),
+            0x4 => shift(ShiftType::Asr, self),
+            0x5 => self.data_op(DataOp::Adc, rdn, n, m, self.c, set_flags),
+            0x6 => self.data_op(DataOp::Sbc, rdn, n, m, self.c, set_flags),
+            0x7 => shift(ShiftType::Ror, self),
+            0x8 => self.data_op(DataOp::Tst, rdn, n, m, self.c, true),
+            0x9 => self.data_op(DataOp::Rsb, rdn, m, 0, self.c, set_flags),
+            0xA => self.data_op(DataOp::Cmp, rdn, n, m, self.c, true),
+            0xB => self.data_op(DataOp::Cmn, rdn, n, m, self.c, true),
+            0xC => self.data_op(DataOp::Orr, rdn, n, m, self.c, set_flags),
+            0xD => {
+                let result = n.wrapping_mul(m);
+                self.set_reg(rdn, result);
+                if set_flags {
+                    self.set_nz(result);
+                }
+            }
+            0xE => self.data_op(DataOp::Bic, rdn, n, m, self.c, set_flags),
+            _ => self.data_op(DataOp::Mvn, rdn, n, m, self.c, set_flags),
+        }
+    }
+
+    /// Executes the data processing instructions on high registers and `BX`/`BLX`.
+    fn execute_16bit_special(&mut self, hw: u32) -> Result<(), Undefined> {
+        let rdn = ((hw >> 4) & 8) | (hw & 7);
+        let rm = (hw >> 3) & 0xF;
+
+        match (hw >> 8) & 3 {
+            0 => {
+                let result = self.reg(rdn).wrapping_add(self.reg(rm));
+                self.set_reg(rdn, result);
+            }
+            1 => self.data_op(DataOp::Cmp, rdn, self.reg(rdn), self.reg(rm), self.c, true),
+            2 => self.set_reg(rdn, self.reg(rm)),
+            _ => {
+                let target = self.reg(rm);
+                if hw & 0x80 != 0 {
+                    self.r[14] = self.next_pc | 1;
+                }
+                self.branch(target);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn execute_16bit_misc(
+        &mut self,
+        memory: &mut Memory,
+        hw: u32,
+    ) -> Result<Option<Stop>, Undefined> {
+        let rd = hw & 7;
+        let rm = (hw >> 3) & 7;
+
+        if hw & 0xFF00 == 0xB000 {
+            // Adjust the SP.
+            let imm = (hw & 0x7F) * 4;
+            self.r[13] = if hw & 0x80 != 0 {
+                self.r[13].wrapping_sub(imm)
+            } else {
+                self.r[13].wrapping_add(imm)
+            };
+        } else if hw & 0xF500 == 0xB100 {
+            // CBZ and CBNZ
+            let offset = (((hw >> 9) & 1) << 6) | (((hw >> 3) & 0x1F) << 1);
+            if (self.reg(rd) == 0) != (hw & 0x800 != 0) {
+                self.branch(self.reg(15).wrapping_add(offset));
+            }
+        } else if hw & 0xFF00 == 0xB200 {
+            let m = self.reg(rm);
+            let result = match (hw >> 6) & 3 {
+                0 => sign_extend(m & 0xFFFF, 16),
+                1 => sign_extend(m & 0xFF, 8),
+                2 => m & 0xFFFF,
+                _ => m & 0xFF,
+            };
+            self.set_reg(rd, result);
+        } else if hw & 0xFE00 == 0xB400 {
+            // PUSH
+            let list = (hw & 0xFF) | ((hw & 0x100) << 6);
+            let start = self.r[13].wrapping_sub(4 * list.count_ones());
+            self.store_multiple(memory, start, list);
+            self.r[13] = start;
+        } else if hw & 0xFFE8 == 0xB660 {
+            // CPS, interrupts are not simulated.
+        } else if hw & 0xFF00 == 0xBA00 {
+            let m = self.reg(rm);
+            let result = match (hw >> 6) & 3 {
+                0 => m.swap_bytes(),
+                1 => ((m & 0x00FF_00FF) << 8) | ((m >> 8) & 0x00FF_00FF),
+                3 => sign_extend(((m & 0xFF) << 8) | ((m >> 8) & 0xFF), 16),
+                _ => return Err(Undefined),
+            };
+            self.set_reg(rd, result);
+        } else if hw & 0xFE00 == 0xBC00 {
+            // POP
+            let list = (hw & 0xFF) | ((hw & 0x100) << 7);
+            let end = self.load_multiple(memory, self.r[13], list);
+            self.r[13] = end;
+        } else if hw & 0xFF00 == 0xBE00 {
+            return Ok(Some(Stop::Breakpoint));
+        } else if hw & 0xFF00 == 0xBF00 {
+            if hw & 0xF != 0 {
+                self.it = (hw & 0xFF) as u8;
+            }
+            // Otherwise a hint like NOP, WFI or SEV.
+        } else {
+            return Err(Undefined);
+        }
+
+        Ok(None)
+    }
+
+    fn execute_32bit(&mut self, memory: &mut Memory, hw1: u32, hw2: u32) -> Result<(), Undefined> {
+        match (hw1 >> 11) & 3 {
+            0b01 => {
+                if hw1 & 0xFE40 == 0xE800 {
+                    self.execute_load_store_multiple(memory, hw1, hw2)
+                } else if hw1 & 0xFE40 == 0xE840 {
+                    self.execute_load_store_dual(memory, hw1, hw2)
+                } else if hw1 & 0xFE00 == 0xEA00 {
+                    self.execute_shifted_register(hw1, hw2)
+                } else {
+                    Err(Undefined)
+                }
+            }
+            0b10 => {
+                if hw2 & 0x8000 != 0 {
+                    self.execute_branch_misc(hw1, hw2)
+                } else if hw1 & 0x200 == 0 {
+                    self.execute_modified_immediate(hw1, hw2)
+                } else {
+                    self.execute_plain_immediate(hw1, hw2)
+                }
+            }
+            _ => {
+                if hw1 & 0xFE00 == 0xF800 {
+                    self.execute_load_store_single(memory, hw1, hw2)
+                } else if hw1 & 0xFF00 == 0xFA00 {
+                    self.execute_data_processing_register(hw1, hw2)
+                } else if hw1 & 0xFF80 == 0xFB00 {
+                    self.execute_multiply(hw1, hw2)
+                } else if hw1 & 0xFF80 == 0xFB80 {
+                    self.execute_long_multiply_divide(hw1, hw2)
+                } else {
+                    Err(Undefined)
+                }
+            }
+        }
+    }
+
+    fn execute_load_store_multiple(
+        &mut self,
+        memory: &mut Memory,
+        hw1: u32,
+        hw2: u32,
+    ) -> Result<(), Undefined> {
+        let rn = hw1 & 0xF;
+        let writeback = hw1 & 0x20 != 0;
+        let load = hw1 & 0x10 != 0;
+        let list = hw2;
+        let base = self.reg(rn);
+        let size = 4 * list.count_ones();
+
+        let (start, new_base) = match (hw1 >> 7) & 3 {
+            // Increment after
+            0b01 => (base, base.wrapping_add(size)),
+            // Decrement before
+            0b10 => (base.wrapping_sub(size), base.wrapping_sub(size)),
+            _ => return Err(Undefined),
+        };
+
+        if load {
+            self.load_multiple(memory, start, list);
+            if writeback && list & (1 << rn) == 0 {
+                self.set_reg(rn, new_base);
+            }
+        } else {
+            self.store_multiple(memory, start, list);
+            if writeback {
+                self.set_reg(rn, new_base);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Executes `LDRD`, `STRD`, the exclusive accesses and the table branches.
+    fn execute_load_store_dual(
+        &mut self,
+        memory: &mut Memory,
+        hw1: u32,
+        hw2: u32,
+    ) -> Result<(), Undefined> {
+        let rn = hw1 & 0xF;
+        let rt = hw2 >> 12;
+
+        if hw1 & 0xFFF0 == 0xE8D0 && hw2 & 0xFFE0 == 0xF000 {
+            // TBB and TBH
+            let base = self.reg(rn);
+            let index = self.reg(hw2 & 0xF);
+            let offset = if hw2 & 0x10 != 0 {
+                memory.read(base.wrapping_add(index << 1), 2)
+            } else {
+                memory.read(base.wrapping_add(index), 1)
+            };
+            self.branch(self.reg(15).wrapping_add(offset * 2));
+        } else if hw1 & 0xFFF0 == 0xE850 {
+            // LDREX
+            let address = self.reg(rn).wrapping_add((hw2 & 0xFF) * 4);
+            self.load(memory, rt, address, 4, false);
+        } else if hw1 & 0xFFF0 == 0xE840 {
+            // STREX, which always succeeds.
+            let address = self.reg(rn).wrapping_add((hw2 & 0xFF) * 4);
+            self.store(memory, rt, address, 4);
+            self.set_reg((hw2 >> 8) & 0xF, 0);
+        } else if hw1 & 0x120 != 0 {
+            // LDRD and STRD
+            let rt2 = (hw2 >> 8) & 0xF;
+            let imm = (hw2 & 0xFF) * 4;
+            let base = if rn == 15 {
+                self.aligned_pc()
+            } else {
+                self.reg(rn)
+            };
+            let offset_address = if hw1 & 0x80 != 0 {
+                base.wrapping_add(imm)
+            } else {
+                base.wrapping_sub(imm)
+            };
+            let address = if hw1 & 0x100 != 0 {
+                offset_address
+            } else {
+                base
+            };
+
+            if hw1 & 0x10 != 0 {
+                self.load(memory, rt, address, 4, false);
+                self.load(memory, rt2, address.wrapping_add(4), 4, false);
+            } else {
+                self.store(memory, rt, address, 4);
+                self.store(memory, rt2, address.wrapping_add(4), 4);
+            }
+
+            if hw1 & 0x20 != 0 {
+                self.set_reg(rn, offset_address);
+            }
+        } else {
+            return Err(Undefined);
+        }
+
+        Ok(())
+    }
+
+    fn decode_data_op(op: u32, rn: u32, rd: u32, set_flags: bool) -> Option<DataOp> {
+        let op = match op {
+            0b0000 if rd == 15 && set_flags => DataOp::Tst,
+            0b0000 => DataOp::And,
+            0b0001 => DataOp::Bic,
+            0b0010 if rn == 15 => DataOp::Mov,
+            0b0010 => DataOp::Orr,
+            0b0011 if rn == 15 => DataOp::Mvn,
+            0b0011 => DataOp::Orn,
+            0b0100 if rd == 15 && set_flags => DataOp::Teq,
+            0b0100 => DataOp::Eor,
+            0b1000 if rd == 15 && set_flags => DataOp::Cmn,
+            0b1000 => DataOp::Add,
+            0b1010 => DataOp::Adc,
+            0b1011 => DataOp::Sbc,
+            0b1101 if rd == 15 && set_flags => DataOp::Cmp,
+            0b1101 => DataOp::Sub,
+            0b1110 => DataOp::Rsb,
+            _ => return None,
+        };
+
+        Some(op)
+    }
+
+    fn execute_shifted_register(&mut self, hw1: u32, hw2: u32) -> Result<(), Undefined> {
+        let rn = hw1 & 0xF;
+        let rd = (hw2 >> 8) & 0xF;
+        let set_flags = hw1 & 0x10 != 0;
+        let op = Self::decode_data_op((hw1 >> 5) & 0xF, rn, rd, set_flags).ok_or(Undefined)?;
+
+        let imm5 = (((hw2 >> 12) & 7) << 2) | ((hw2 >> 6) & 3);
+        let (kind, amount) = ShiftType::decode_immediate((hw2 >> 4) & 3, imm5);
+        let (m, carry) = shift_c(self.reg(hw2 & 0xF), kind, amount, self.c);
+
+        self.data_op(op, rd, self.reg(rn), m, carry, set_flags);
+
+        Ok(())
+    }
+
+    fn execute_modified_immediate(&mut self, hw1: u32, hw2: u32) -> Result<(), Undefined> {
+        let rn = hw1 & 0xF;
+        let rd = (hw2 >> 8) & 0xF;
+        let set_flags = hw1 & 0x10 != 0;
+        let op = Self::decode_data_op((hw1 >> 5) & 0xF, rn, rd, set_flags).ok_or(Undefined)?;
+
+        let imm12 = (((hw1 >> 10) & 1) << 11) | (((hw2 >> 12) & 7) << 8) | (hw2 & 0xFF);
+        let (imm, carry) = expand_immediate_c(imm12, self.c);
+
+        self.data_op(op, rd, self.reg(rn), imm, carry, set_flags);
+
+        Ok(())
+    }
+
+    fn execute_plain_immediate(&mut self, hw1: u32, hw2: u32) -> Result<(), Undefined> {
+        let rn = hw1 & 0xF;
+        let rd = (hw2 >> 8) & 0xF;
+        let imm12 = (((hw1 >> 10) & 1) << 11) | (((hw2 >> 12) & 7) << 8) | (hw2 & 0xFF);
+        let imm16 = ((hw1 & 0xF) << 12) | imm12;
+        let lsb = (((hw2 >> 12) & 7) << 2) | ((hw2 >> 6) & 3);
+        let width_or_msb = hw2 & 0x1F;
+
+        match (hw1 >> 4) & 0x1F {
+            // ADDW and ADR
+            0b00000 => {
+                let base = if rn == 15 {
+                    self.aligned_pc()
+                } else {
+                    self.reg(rn)
+                };
+                self.set_reg(rd, base.wrapping_add(imm12));
+            }
+            // MOVW
+            0b00100 => self.set_reg(rd, imm16),
+            // SUBW and ADR
+            0b01010 => {
+                let base = if rn == 15 {
+                    self.aligned_pc()
+                } else {
+                    self.reg(rn)
+                };
+                self.set_reg(rd, base.wrapping_sub(imm12));
+            }
+            // MOVT
+            0b01100 => self.set_reg(rd, (self.reg(rd) & 0xFFFF) | (imm16 << 16)),
+            // SBFX
+            0b10100 => {
+                let width = width_or_msb + 1;
+                let value = self.reg(rn) >> lsb;
+                self.set_reg(rd, sign_extend(value, width));
+            }
+            // BFI and BFC
+            0b10110 => {
+                if width_or_msb < lsb {
+                    return Err(Undefined);
+                }
+                let width = width_or_msb - lsb + 1;
+                let mask = (u32::MAX >> (32 - width)) << lsb;
+                let value = if rn == 15 { 0 } else { self.reg(rn) << lsb };
+                self.set_reg(rd, (self.reg(rd) & !mask) | (value & mask));
+            }
+            // UBFX
+            0b11100 => {
+                let width = width_or_msb + 1;
+                let value = self.reg(rn) >> lsb;
+                self.set_reg(rd, value & (u32::MAX >> (32 - width)));
+            }
+            _ => return Err(Undefined),
+        }
+
+        Ok(())
+    }
+
+    fn execute_branch_misc(&mut self, hw1: u32, hw2: u32) -> Result<(), Undefined> {
+        let s = (hw1 >> 10) & 1;
+        let j1 = (hw2 >> 13) & 1;
+        let j2 = (hw2 >> 11) & 1;
+
+        match hw2 & 0x5000 {
+            0x0000 => {
+                if (hw1 >> 7) & 7 != 0b111 {
+                    // Conditional branch
+                    let imm = (s << 20) | (j2 << 19) | (j1 << 18) | ((hw1 & 0x3F) << 12);
+                    let offset = sign_extend(imm | ((hw2 & 0x7FF) << 1), 21);
+                    if self.condition_passed((hw1 >> 6) & 0xF) {
+                        self.branch(self.reg(15).wrapping_add(offset));
+                    }
+                } else if hw1 & 0xFFF0 == 0xF3E0 {
+                    // MRS, the special registers are all zero.
+                    self.set_reg((hw2 >> 8) & 0xF, 0);
+                }
+                // Otherwise MSR, a hint or a barrier, which have no effect here.
+            }
+            0x1000 | 0x5000 => {
+                let i1 = !(j1 ^ s) & 1;
+                let i2 = !(j2 ^ s) & 1;
+                let imm = (s << 24) | (i1 << 23) | (i2 << 22) | ((hw1 & 0x3FF) << 12);
+                let offset = sign_extend(imm | ((hw2 & 0x7FF) << 1), 25);
+                if hw2 & 0x4000 != 0 {
+                    self.r[14] = self.next_pc | 1;
+                }
+                self.branch(self.reg(15).wrapping_add(offset));
+            }
+            _ => return Err(Undefined),
+        }
+
+        Ok(())
+    }
+
+    fn execute_load_store_single(
+        &mut self,
+        memory: &mut Memory,
+        hw1: u32,
+        hw2: u32,
+    ) -> Result<(), Undefined> {
+        let size = match (hw1 >> 5) & 3 {
+            0 => 1,
+            1 => 2,
+            2 => 4,
+            _ => return Err(Undefined),
+        };
+        let signed = hw1 & 0x100 != 0;
+        let load = hw1 & 0x10 != 0;
+        let rn = hw1 & 0xF;
+        let rt = hw2 >> 12;
+
+        if !load && (signed || rn == 15) {
+            return Err(Undefined);
+        }
+
+        let address = if rn == 15 {
+            // Literal
+            let imm12 = hw2 & 0xFFF;
+            if hw1 & 0x80 != 0 {
+                self.aligned_pc().wrapping_add(imm12)
+            } else {
+                self.aligned_pc().wrapping_sub(imm12)
+            }
+        } else if hw1 & 0x80 != 0 {
+            self.reg(rn).wrapping_add(hw2 & 0xFFF)
+        } else if hw2 & 0x800 != 0 {
+            let imm8 = hw2 & 0xFF;
+            let base = self.reg(rn);
+            let offset_address = if hw2 & 0x200 != 0 {
+                base.wrapping_add(imm8)
+            } else {
+                base.wrapping_sub(imm8)
+            };
+            if hw2 & 0x100 != 0 {
+                self.set_reg(rn, offset_address);
+            }
+            if hw2 & 0x400 != 0 {
+                offset_address
+            } else {
+                base
+            }
+        } else if (hw2 >> 6) & 0x3F == 0 {
+            let offset = self.reg(hw2 & 0xF) << ((hw2 >> 4) & 3);
+            self.reg(rn).wrapping_add(offset)
+        } else {
+            return Err(Undefined);
+        };
+
+        if load {
+            if rt == 15 && size != 4 {
+                // A preload hint
+                return Ok(());
+            }
+            self.load(memory, rt, address, size, signed);
+        } else {
+            self.store(memory, rt, address, size);
+        }
+
+        Ok(())
+    }
+
+    fn execute_data_processing_register(&mut self, hw1: u32, hw2: u32) -> Result<(), Undefined> {
+        let rn = hw1 & 0xF;
+        let rd = (hw2 >> 8) & 0xF;
+        let rm = hw2 & 0xF;
+
+        if hw1 & 0x80 == 0 && hw2 & 0xF0F0 == 0xF000 {
+            // Shift by a register
+            let kind = ShiftType::decode_register((hw1 >> 5) & 3);
+            let (result, carry) = shift_c(self.reg(rn), kind, self.reg(rm) & 0xFF, self.c);
+            self.set_reg(rd, result);
+            if hw1 & 0x10 != 0 {
+                self.set_nz(result);
+                self.c = carry;
+            }
+        } else if hw1 & 0x80 == 0 && hw2 & 0xF080 == 0xF080 {
+            // Extend, and optionally add
+            let rotated = self.reg(rm).rotate_right(((hw2 >> 4) & 3) * 8);
+            let extended = match (hw1 >> 4) & 7 {
+                0b000 => sign_extend(rotated & 0xFFFF, 16),
+                0b001 => rotated & 0xFFFF,
+                0b100 => sign_extend(rotated & 0xFF, 8),
+                0b101 => rotated & 0xFF,
+                _ => return Err(Undefined),
+            };
+            let base = if rn == 15 { 0 } else { self.reg(rn) };
+            self.set_reg(rd, base.wrapping_add(extended));
+        } else if hw1 & 0xFFF0 == 0xFA90 && hw2 & 0xF0C0 == 0xF080 {
+            let m = self.reg(rm);
+            let result = match (hw2 >> 4) & 3 {
+                0 => m.swap_bytes(),
+                1 => ((m & 0x00FF_00FF) << 8) | ((m >> 8) & 0x00FF_00FF),
+                2 => m.reverse_bits(),
+                _ => sign_extend(((m & 0xFF) << 8) | ((m >> 8) & 0xFF), 16),
+            };
+            self.set_reg(rd, result);
+        } else if hw1 & 0xFFF0 == 0xFAB0 && hw2 & 0xF0F0 == 0xF080 {
+            self.set_reg(rd, self.reg(rm).leading_zeros());
+        } else {
+            return Err(Undefined);
+        }
+
+        Ok(())
+    }
+
+    fn execute_multiply(&mut self, hw1: u32, hw2: u32) -> Result<(), Undefined> {
+        let product = self.reg(hw1 & 0xF).wrapping_mul(self.reg(hw2 & 0xF));
+        let ra = hw2 >> 12;
+        let rd = (hw2 >> 8) & 0xF;
+
+        let result = match ((hw1 >> 4) & 7, (hw2 >> 4) & 3) {
+            (0, 0) if ra == 15 => product,
+            (0, 0) => self.reg(ra).wrapping_add(product),
+            (0, 1) => self.reg(ra).wrapping_sub(product),
+            _ => return Err(Undefined),
+        };
+        self.set_reg(rd, result);
+
+        Ok(())
+    }
+
+    fn execute_long_multiply_divide(&mut self, hw1: u32, hw2: u32) -> Result<(), Undefined> {
+        let n = self.reg(hw1 & 0xF);
+        let m = self.reg(hw2 & 0xF);
+        let rd_lo = hw2 >> 12;
+        let rd_hi = (hw2 >> 8) & 0xF;
+        let accumulator = ((self.reg(rd_hi) as u64) << 32) | self.reg(rd_lo) as u64;
+
+        let result = match ((hw1 >> 4) & 7, (hw2 >> 4) & 0xF) {
+            (0b001, 0b1111) => {
+                // SDIV, division by zero results in zero.
+                let result = (n as i32).checked_div(m as i32).unwrap_or(0);
+                self.set_reg(rd_hi, result as u32);
+                return Ok(());
+            }
+            (0b011, 0b1111) => {
+                // UDIV
+                self.set_reg(rd_hi, n.checked_div(m).unwrap_or(0));
+                return Ok(());
+            }
+            (0b000, 0b0000) => (n as i32 as i64 * m as i32 as i64) as u64,
+            (0b010, 0b0000) => n as u64 * m as u64,
+            (0b100, 0b0000) => accumulator.wrapping_add((n as i32 as i64 * m as i32 as i64) as u64),
+            (0b110, 0b0000) => accumulator.wrapping_add(n as u64 * m as u64),
+            _ => return Err(Undefined),
+        };
+
+        self.set_reg(rd_lo, result as u32);
+        self.set_reg(rd_hi, (result >> 32) as u32);
+
+        Ok(())
+    }
+}
+
+/// Marks an instruction the interpreter does not implement.
+struct Undefined;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modified_immediates() {
+        assert_eq!(expand_immediate_c(0x0AB, false), (0x0000_00AB, false));
+        assert_eq!(expand_immediate_c(0x1AB, false), (0x00AB_00AB, false));
+        assert_eq!(expand_immediate_c(0x2AB, false), (0xAB00_AB00, false));
+        assert_eq!(expand_immediate_c(0x3AB, false), (0xABAB_ABAB, false));
+        // 0x80 rotated right by 8
+        assert_eq!(expand_immediate_c(0x400, false), (0x8000_0000, true));
+    }
+
+    #[test]
+    fn add_with_carry_flags() {
+        assert_eq!(add_with_carry(1, 2, false), (3, false, false));
+        assert_eq!(add_with_carry(u32::MAX, 1, false), (0, true, false));
+        assert_eq!(
+            add_with_carry(0x7FFF_FFFF, 1, false),
+            (0x8000_0000, false, true)
+        );
+        // 5 - 3
+        assert_eq!(add_with_carry(5, !3, true), (2, true, false));
+    }
+
+    #[test]
+    fn shifts() {
+        assert_eq!(shift_c(0x8000_0001, ShiftType::Lsl, 1, false), (2, true));
+        assert_eq!(
+            shift_c(0x8000_0001, ShiftType::Lsr, 1, false),
+            (0x4000_0000, true)
+        );
+        assert_eq!(
+            shift_c(0x8000_0000, ShiftType::Asr, 32, false),
+            (u32::MAX, true)
+        );
+        assert_eq!(shift_c(1, ShiftType::Rrx, 1, true), (0x8000_0000, true));
+    }
+}
@@ -0,0 +1,224 @@
+//! Parsing of [UF2](https://github.com/microsoft/uf2) files.
+//!
+//! A UF2 file is a sequence of 512 byte blocks. Every block carries up to 476 bytes of data,
+//! the address the data belongs at and, optionally, the family ID of the chip the data is meant
+//! for. Files can contain images for several families, e.g. for both cores of a dual-core chip.
+
+use super::FileDownloadError;
+
+const BLOCK_SIZE: usize = 512;
+const MAX_PAYLOAD_SIZE: usize = 476;
+
+const MAGIC_START0: u32 = 0x0A32_4655;
+const MAGIC_START1: u32 = 0x9E5D_5157;
+const MAGIC_END: u32 = 0x0AB1_6F30;
+
+const FLAG_NOT_MAIN_FLASH: u32 = 0x0000_0001;
+const FLAG_FILE_CONTAINER: u32 = 0x0000_1000;
+const FLAG_FAMILY_ID_PRESENT: u32 = 0x0000_2000;
+
+/// A contiguous chunk of data from a UF2 file.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Uf2Segment {
+    pub(super) address: u64,
+    pub(super) data: Vec<u8>,
+}
+
+struct Uf2Block<'data> {
+    address: u64,
+    family_id: Option<u32>,
+    data: &'data [u8],
+}
+
+fn read_u32(block: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(block[offset..offset + 4].try_into().unwrap())
+}
+
+/// Parses the block with the given index. Returns `None` for blocks that do not contain data
+/// for the main flash.
+fn parse_block(index: usize, block: &[u8]) -> Result<Option<Uf2Block<'_>>, FileDownloadError> {
+    if read_u32(block, 0) != MAGIC_START0
+        || read_u32(block, 4) != MAGIC_START1
+        || read_u32(block, BLOCK_SIZE - 4) != MAGIC_END
+    {
+        return Err(FileDownloadError::InvalidUf2(format!(
+            "block {index} has an invalid magic number"
+        )));
+    }
+
+    let flags = read_u32(block, 8);
+    if flags & (FLAG_NOT_MAIN_FLASH | FLAG_FILE_CONTAINER) != 0 {
+        tracing::debug!("Skipping UF2 block {index} with flags {flags:#010x}");
+        return Ok(None);
+    }
+
+    let payload_size = read_u32(block, 16) as usize;
+    if payload_size > MAX_PAYLOAD_SIZE {
+        return Err(FileDownloadError::InvalidUf2(format!(
+            "block {index} claims to contain {payload_size} bytes"
+        )));
+    }
+
+    Ok(Some(Uf2Block {
+        address: read_u32(block, 12) as u64,
+        family_id: (flags & FLAG_FAMILY_ID_PRESENT != 0).then(|| read_u32(block, 28)),
+        data: &block[32..][..payload_size],
+    }))
+}
+
+/// Extracts the data from the UF2 file in `uf2_data` that is meant for one of the families in
+/// `family_ids`.
+///
+/// Blocks without a family ID are always used. If `family_ids` is empty, the data of all
+/// families is used. Adjacent blocks are merged into a single segment.
+pub(super) fn extract_from_uf2(
+    uf2_data: &[u8],
+    family_ids: &[u32],
+    target_name: &str,
+) -> Result<Vec<Uf2Segment>, FileDownloadError> {
+    if uf2_data.len() % BLOCK_SIZE != 0 {
+        return Err(FileDownloadError::InvalidUf2(format!(
+            "the file size is not a multiple of {BLOCK_SIZE} bytes"
+        )));
+    }
+
+    let mut blocks = Vec::new();
+    for (index, block) in uf2_data.chunks_exact(BLOCK_SIZE).enumerate() {
+        if let Some(block) = parse_block(index, block)? {
+            blocks.push(block);
+        }
+    }
+
+    let mut families = blocks
+        .iter()
+        .filter_map(|block| block.family_id)
+        .collect::<Vec<_>>();
+    families.sort_unstable();
+    families.dedup();
+
+    if family_ids.is_empty() {
+        if families.len() > 1 {
+            tracing::warn!(
+                "The UF2 file contains data for the families {families:#010x?}, but the target does not specify its family. Using all of them."
+            );
+        }
+    } else if !families.is_empty() {
+        blocks.retain(|block| {
+            block
+                .family_id
+                .is_none_or(|family_id| family_ids.contains(&family_id))
+        });
+
+        if blocks.is_empty() {
+            return Err(FileDownloadError::Uf2FamilyMismatch {
+                target: target_name.to_string(),
+                families,
+            });
+        }
+    }
+
+    blocks.sort_by_key(|block| block.address);
+
+    let mut segments: Vec<Uf2Segment> = Vec::new();
+    for block in blocks {
+        match segments.last_mut() {
+            Some(segment) if segment.address + segment.data.len() as u64 == block.address => {
+                segment.data.extend_from_slice(block.data);
+            }
+            _ => segments.push(Uf2Segment {
+                address: block.address,
+                data: block.data.to_vec(),
+            }),
+        }
+    }
+
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(address: u32, family_id: Option<u32>, data: &[u8]) -> Vec<u8> {
+        let flags = if family_id.is_some() {
+            FLAG_FAMILY_ID_PRESENT
+        } else {
+            0
+        };
+
+        let mut block = vec![0; BLOCK_SIZE];
+        block[0..4].copy_from_slice(&MAGIC_START0.to_le_bytes());
+        block[4..8].copy_from_slice(&MAGIC_START1.to_le_bytes());
+        block[8..12].copy_from_slice(&flags.to_le_bytes());
+        block[12..16].copy_from_slice(&address.to_le_bytes());
+        block[16..20].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        block[28..32].copy_from_slice(&family_id.unwrap_or(0).to_le_bytes());
+        block[32..][..data.len()].copy_from_slice(data);
+        block[BLOCK_SIZE - 4..].copy_from_slice(&MAGIC_END.to_le_bytes());
+        block
+    }
+
+    #[test]
+    fn merges_adjacent_blocks() {
+        let file = [
+            block(0x1000_0004, None, &[3, 4]),
+            block(0x1000_0000, None, &[1, 2, 0, 0]),
+            block(0x1000_0100, None, &[5]),
+        ]
+        .concat();
+
+        let segments = extract_from_uf2(&file, &[], "test").unwrap();
+
+        assert_eq!(
+            segments,
+            [
+                Uf2Segment {
+                    address: 0x1000_0000,
+                    data: vec![1, 2, 0, 0, 3, 4],
+                },
+                Uf2Segment {
+                    address: 0x1000_0100,
+                    data: vec![5],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn selects_target_family() {
+        let file = [
+            block(0x1000_0000, Some(0xE48B_FF56), &[1]),
+            block(0x0000_0000, Some(0xADA5_2840), &[2]),
+        ]
+        .concat();
+
+        let segments = extract_from_uf2(&file, &[0xE48B_FF56], "test").unwrap();
+        assert_eq!(
+            segments,
+            [Uf2Segment {
+                address: 0x1000_0000,
+                data: vec![1],
+            }]
+        );
+
+        assert!(matches!(
+            extract_from_uf2(&file, &[0x5775_5A57], "test"),
+            Err(FileDownloadError::Uf2FamilyMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_blocks() {
+        let mut file = block(0x1000_0000, None, &[1]);
+        file[0] = 0;
+
+        assert!(matches!(
+            extract_from_uf2(&file, &[], "test"),
+            Err(FileDownloadError::InvalidUf2(_))
+        ));
+        assert!(matches!(
+            extract_from_uf2(&[0; 100], &[], "test"),
+            Err(FileDownloadError::InvalidUf2(_))
+        ));
+    }
+}
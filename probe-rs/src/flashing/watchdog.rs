@@ -0,0 +1,232 @@
+//! Servicing the watchdogs of a target while it is flashed.
+//!
+//! Target descriptions list the register writes that refresh or disable each watchdog. They are
+//! performed before every call of the flash algorithm that erases or programs the flash, either
+//! by the debugger, or by a stub running on the core for unlock sequences with tight timing.
+
+use std::str::FromStr;
+
+use probe_rs_target::{Watchdog, WatchdogWrite};
+use serde::{Deserialize, Serialize};
+
+use super::FlashError;
+
+/// How the watchdogs of a target are handled while it is flashed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchdogMode {
+    /// Leave the watchdogs alone.
+    #[default]
+    Ignore,
+    /// Refresh the watchdogs between flash operations.
+    Refresh,
+    /// Disable the watchdogs between flash operations. Watchdogs that cannot be disabled are
+    /// refreshed instead.
+    Disable,
+}
+
+impl FromStr for WatchdogMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ignore" => Ok(Self::Ignore),
+            "refresh" => Ok(Self::Refresh),
+            "disable" => Ok(Self::Disable),
+            _ => Err(format!(
+                "Watchdog mode '{s}' is unknown, expected ignore, refresh or disable."
+            )),
+        }
+    }
+}
+
+/// The register writes that keep the watchdogs of a target from resetting it while it is flashed.
+#[derive(Debug, Default)]
+pub(super) struct WatchdogService {
+    pub(super) sequences: Vec<WatchdogSequence>,
+}
+
+/// The writes that service a single watchdog.
+#[derive(Debug)]
+pub(super) struct WatchdogSequence {
+    /// The name of the watchdog.
+    pub(super) name: String,
+    pub(super) writes: Vec<WatchdogWrite>,
+    /// The stub that performs the writes on the core, if they must not be done by the debugger.
+    pub(super) stub: Option<Vec<u8>>,
+}
+
+impl WatchdogService {
+    pub(super) fn new(watchdogs: &[Watchdog], mode: WatchdogMode) -> Result<Self, FlashError> {
+        let mut sequences = Vec::new();
+        for watchdog in watchdogs {
+            let writes = match mode {
+                WatchdogMode::Ignore => continue,
+                WatchdogMode::Disable if !watchdog.disable.is_empty() => &watchdog.disable,
+                WatchdogMode::Disable | WatchdogMode::Refresh => &watchdog.refresh,
+            };
+
+            if writes.is_empty() {
+                tracing::warn!("The watchdog {} cannot be serviced.", watchdog.name);
+                continue;
+            }
+
+            let stub = if watchdog.run_on_target {
+                let stub = thumb_write_stub(writes).map_err(|source| FlashError::Watchdog {
+                    name: watchdog.name.clone(),
+                    source,
+                })?;
+                Some(stub)
+            } else {
+                None
+            };
+
+            sequences.push(WatchdogSequence {
+                name: watchdog.name.clone(),
+                writes: writes.clone(),
+                stub,
+            });
+        }
+
+        Ok(Self { sequences })
+    }
+}
+
+/// Assembles a stub that performs up to four `writes`, for cores that run Thumb code.
+///
+/// All addresses and values are loaded into `r0`-`r7` first, so the stores are executed back to
+/// back. Like the CRC stub, it is called like a flash algorithm function, and returns to the
+/// breakpoint at the start of the algorithm.
+///
+/// ```text
+///     ldr   r0, =address0
+///     ldr   r1, =value0
+///     ldr   r2, =address1
+///     ldr   r3, =value1
+///     strh  r1, [r0]
+///     strh  r3, [r2]
+///     bx    lr
+/// ```
+pub(super) fn thumb_write_stub(
+    writes: &[WatchdogWrite],
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    if writes.len() > 4 {
+        return Err(format!(
+            "The stub only has registers for 4 writes, but {} writes are required",
+            writes.len()
+        )
+        .into());
+    }
+
+    let mut code = Vec::<u16>::new();
+    let pool_offset = (2 * (3 * writes.len() + 1)).next_multiple_of(4);
+
+    for register in 0..2 * writes.len() {
+        let pc = (2 * code.len() + 4) & !3;
+        let literal = pool_offset + 4 * register;
+        // ldr  rN, [pc, #imm]
+        code.push(0x4800 | (register << 8) as u16 | ((literal - pc) / 4) as u16);
+    }
+
+    for (index, write) in writes.iter().enumerate() {
+        let opcode = match write.width {
+            8 => 0x7000,  // strb
+            16 => 0x8000, // strh
+            _ => 0x6000,  // str
+        };
+        let (address, value) = (2 * index as u16, 2 * index as u16 + 1);
+        code.push(opcode | (address << 3) | value);
+    }
+
+    // bx   lr
+    code.push(0x4770);
+    if code.len() % 2 == 1 {
+        // nop
+        code.push(0x46C0);
+    }
+
+    let mut stub: Vec<u8> = code.iter().flat_map(|hw| hw.to_le_bytes()).collect();
+    for write in writes {
+        stub.extend((write.address as u32).to_le_bytes());
+        stub.extend(write.value.to_le_bytes());
+    }
+
+    Ok(stub)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(address: u64, value: u32, width: u8) -> WatchdogWrite {
+        WatchdogWrite {
+            address,
+            value,
+            width,
+        }
+    }
+
+    #[test]
+    fn stub_encoding() {
+        let stub = thumb_write_stub(&[write(0x4005_200E, 0xC520, 16)]).unwrap();
+        assert_eq!(
+            stub,
+            [
+                0x01, 0x48, // ldr   r0, [pc, #4]
+                0x02, 0x49, // ldr   r1, [pc, #8]
+                0x01, 0x80, // strh  r1, [r0]
+                0x70, 0x47, // bx    lr
+                0x0E, 0x20, 0x05, 0x40, // .word 0x4005200E
+                0x20, 0xC5, 0x00, 0x00, // .word 0xC520
+            ]
+        );
+    }
+
+    #[test]
+    fn stub_rejects_too_many_writes() {
+        let writes = [write(0x4005_2000, 0x01D2, 16); 5];
+        assert!(thumb_write_stub(&writes).is_err());
+
+        let watchdogs = [Watchdog {
+            name: "WDOG".to_string(),
+            disable: writes.to_vec(),
+            refresh: vec![],
+            run_on_target: true,
+        }];
+        assert!(matches!(
+            WatchdogService::new(&watchdogs, WatchdogMode::Disable),
+            Err(FlashError::Watchdog { .. })
+        ));
+    }
+
+    #[test]
+    fn disable_falls_back_to_refresh() {
+        let watchdogs = [
+            Watchdog {
+                name: "WDOG".to_string(),
+                disable: vec![write(0x4005_2000, 0x01D2, 16)],
+                refresh: vec![write(0x4005_200C, 0xA602, 16)],
+                run_on_target: true,
+            },
+            Watchdog {
+                name: "IWDG".to_string(),
+                disable: vec![],
+                refresh: vec![write(0x4000_3000, 0xAAAA, 32)],
+                run_on_target: false,
+            },
+        ];
+
+        let service = WatchdogService::new(&watchdogs, WatchdogMode::Disable).unwrap();
+        assert_eq!(service.sequences[0].writes, watchdogs[0].disable);
+        assert!(service.sequences[0].stub.is_some());
+        assert_eq!(service.sequences[1].writes, watchdogs[1].refresh);
+        assert!(service.sequences[1].stub.is_none());
+
+        assert!(
+            WatchdogService::new(&watchdogs, WatchdogMode::Ignore)
+                .unwrap()
+                .sequences
+                .is_empty()
+        );
+    }
+}
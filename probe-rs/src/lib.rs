@@ -77,8 +77,10 @@
 
 pub mod architecture;
 pub mod config;
+pub mod device_info;
 pub mod vendor;
 
+mod cancellation;
 mod core;
 mod error;
 pub mod flashing;
@@ -93,19 +95,26 @@ mod session;
 #[cfg(any(test, feature = "test"))]
 pub mod test;
 
+pub use crate::cancellation::{CancellationToken, CancelledError};
 pub use crate::config::{CoreType, Endian, InstructionSet, Target};
-pub use crate::core::dump::CoreDump;
-pub use crate::core::dump::CoreDumpError;
+pub use crate::core::dump::{
+    CORE_DUMP_FORMAT_VERSION, CoreDump, CoreDumpError, CoreDumpOptions, CoreDumpRegion,
+    CoreDumpRegionKind, CoreDumpSession, PeripheralSnapshot,
+};
 pub use crate::core::registers::RegisterDataType;
 pub use crate::core::registers::UnwindRule;
 pub use crate::core::{
     Architecture, BreakpointCause, Core, CoreInformation, CoreInterface, CoreRegister,
-    CoreRegisters, CoreState, CoreStatus, HaltReason, MemoryMappedRegister, RegisterId,
-    RegisterRole, RegisterValue, SpecificCoreState, VectorCatchCondition,
+    CoreRegisters, CoreState, CoreStatus, ExceptionCause, ExceptionInfo, FreezeConfig, HaltReason,
+    HaltTimestamp, MemoryMappedRegister, RegisterId, RegisterRole, RegisterValue,
+    SpecificCoreState, TriggerKind, VectorCatchCondition, WatchpointAccess,
 };
 pub use crate::error::Error;
-pub use crate::memory::MemoryInterface;
-pub use crate::session::{Permissions, Session, SessionConfig};
+pub use crate::memory::{MemoryInterface, MemoryMapPolicy, ReadCache};
+pub use crate::session::{
+    AttachOptions, HaltOnAttach, Lease, LeaseError, LeasePriority, Permissions, PowerDownRecovery,
+    Session, SessionConfig, SharedSession, Stakeholder,
+};
 
 #[doc = include_str!("../../README.md")]
 #[cfg(doctest)]
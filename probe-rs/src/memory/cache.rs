@@ -0,0 +1,480 @@
+//! A host side cache for memory reads.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    ops::Range,
+};
+
+use flate2::Crc;
+
+use super::MemoryInterface;
+use crate::Error;
+
+/// A host side cache for reading memory which does not change on its own, like flash.
+///
+/// Reading large amounts of memory, e.g. when scrolling through a dump of an external flash, is
+/// slow. The cache keeps the data of every block that was read once, and serves further reads
+/// of the block from the host. The cached data is protected by a CRC, and every few hits of a
+/// block, a single word of the block is read from the target again to detect changes.
+///
+/// The cache has no way to know when the memory changes. It has to be invalidated using
+/// [`ReadCache::invalidate`] or [`ReadCache::invalidate_range`] whenever the memory is written,
+/// or the target is reset.
+///
+/// ```no_run
+/// # use probe_rs::{MemoryInterface, ReadCache};
+/// # fn example(core: &mut impl MemoryInterface) -> Result<(), probe_rs::Error> {
+/// let mut cache = ReadCache::new();
+///
+/// let mut data = vec![0; 1024 * 1024];
+/// cache.read(core, 0x9000_0000, &mut data)?;
+///
+/// // Served from the cache, except for a few spot checks.
+/// cache.read(core, 0x9000_0000, &mut data)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ReadCache {
+    block_size: u64,
+    spot_check_interval: u32,
+    blocks: HashMap<u64, CachedBlock>,
+    free_slots: Vec<u64>,
+    storage: Storage,
+}
+
+#[derive(Debug)]
+struct CachedBlock {
+    slot: u64,
+    crc: u32,
+    hits: u32,
+}
+
+/// Where the cached data is kept.
+#[derive(Debug)]
+enum Storage {
+    Memory(Vec<u8>),
+    File { file: File, len: u64 },
+}
+
+impl Storage {
+    fn len(&self) -> u64 {
+        match self {
+            Storage::Memory(data) => data.len() as u64,
+            Storage::File { len, .. } => *len,
+        }
+    }
+
+    fn load(&mut self, offset: u64, buffer: &mut [u8]) -> std::io::Result<()> {
+        match self {
+            Storage::Memory(data) => {
+                buffer.copy_from_slice(&data[offset as usize..][..buffer.len()]);
+                Ok(())
+            }
+            Storage::File { file, .. } => {
+                file.seek(SeekFrom::Start(offset))?;
+                file.read_exact(buffer)
+            }
+        }
+    }
+
+    fn store(&mut self, offset: u64, buffer: &[u8]) -> std::io::Result<()> {
+        match self {
+            Storage::Memory(data) => {
+                let end = offset as usize + buffer.len();
+                if data.len() < end {
+                    data.resize(end, 0);
+                }
+                data[offset as usize..end].copy_from_slice(buffer);
+                Ok(())
+            }
+            Storage::File { file, len } => {
+                file.seek(SeekFrom::Start(offset))?;
+                file.write_all(buffer)?;
+                *len = (*len).max(offset + buffer.len() as u64);
+                Ok(())
+            }
+        }
+    }
+
+    fn clear(&mut self) -> std::io::Result<()> {
+        match self {
+            Storage::Memory(data) => {
+                data.clear();
+                Ok(())
+            }
+            Storage::File { file, len } => {
+                *len = 0;
+                file.set_len(0)
+            }
+        }
+    }
+}
+
+impl Default for ReadCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReadCache {
+    /// The default size of a cached block.
+    pub const DEFAULT_BLOCK_SIZE: u64 = 4096;
+
+    /// Creates a cache which keeps the data in host memory.
+    pub fn new() -> Self {
+        Self::with_storage(Storage::Memory(Vec::new()))
+    }
+
+    /// Creates a cache which keeps the data in `file`, to cache more data than fits into host
+    /// memory.
+    ///
+    /// The file is truncated, and must be opened for reading and writing.
+    pub fn with_backing_file(file: File) -> std::io::Result<Self> {
+        file.set_len(0)?;
+
+        Ok(Self::with_storage(Storage::File { file, len: 0 }))
+    }
+
+    fn with_storage(storage: Storage) -> Self {
+        Self {
+            block_size: Self::DEFAULT_BLOCK_SIZE,
+            spot_check_interval: 8,
+            blocks: HashMap::new(),
+            free_slots: Vec::new(),
+            storage,
+        }
+    }
+
+    /// Sets the size of the cached blocks. Memory is always read from the target in whole blocks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is not a power of two, or smaller than 4.
+    #[must_use]
+    pub fn with_block_size(mut self, block_size: u64) -> Self {
+        assert!(
+            block_size.is_power_of_two() && block_size >= 4,
+            "The block size must be a power of two and at least 4 bytes"
+        );
+
+        self.invalidate();
+        self.block_size = block_size;
+        self
+    }
+
+    /// Sets after how many hits of a block a word of the block is read from the target again,
+    /// to check that the cached data is still valid. `0` disables the spot checks.
+    #[must_use]
+    pub fn with_spot_check_interval(mut self, hits: u32) -> Self {
+        self.spot_check_interval = hits;
+        self
+    }
+
+    /// Returns the number of bytes which are currently cached.
+    pub fn cached_bytes(&self) -> u64 {
+        self.blocks.len() as u64 * self.block_size
+    }
+
+    /// Reads memory, using cached data where available.
+    ///
+    /// If a block can't be read as a whole, e.g. because it extends past the end of a memory
+    /// region, the data is read from `memory` directly and not cached.
+    pub fn read<M: MemoryInterface + ?Sized>(
+        &mut self,
+        memory: &mut M,
+        address: u64,
+        data: &mut [u8],
+    ) -> Result<(), Error> {
+        let end = address + data.len() as u64;
+        let mut block_address = address & !(self.block_size - 1);
+        let mut block = vec![0; self.block_size as usize];
+
+        while block_address < end {
+            if let Err(error) = self.load_block(memory, block_address, &mut block) {
+                tracing::debug!("Not caching block at {block_address:#010x}: {error}");
+                return memory.read(address, data);
+            }
+
+            let start = block_address.max(address);
+            let stop = (block_address + self.block_size).min(end);
+            data[(start - address) as usize..(stop - address) as usize].copy_from_slice(
+                &block[(start - block_address) as usize..(stop - block_address) as usize],
+            );
+
+            block_address += self.block_size;
+        }
+
+        Ok(())
+    }
+
+    /// Drops all cached data.
+    pub fn invalidate(&mut self) {
+        self.blocks.clear();
+        self.free_slots.clear();
+
+        if let Err(error) = self.storage.clear() {
+            tracing::warn!("Failed to clear the read cache: {error}");
+        }
+    }
+
+    /// Drops the cached data of all blocks overlapping `range`.
+    pub fn invalidate_range(&mut self, range: Range<u64>) {
+        let block_size = self.block_size;
+        let blocks = &mut self.blocks;
+        let free_slots = &mut self.free_slots;
+
+        blocks.retain(|&address, block| {
+            let overlaps = address < range.end && range.start < address + block_size;
+            if overlaps {
+                free_slots.push(block.slot);
+            }
+            !overlaps
+        });
+    }
+
+    fn load_block<M: MemoryInterface + ?Sized>(
+        &mut self,
+        memory: &mut M,
+        address: u64,
+        buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        if let Some(cached) = self.blocks.get_mut(&address) {
+            cached.hits += 1;
+            let (slot, crc, hits) = (cached.slot, cached.crc, cached.hits);
+
+            match self.storage.load(slot, buffer) {
+                Ok(()) if crc32(buffer) == crc => {
+                    if self.spot_check(memory, address, hits, buffer)? {
+                        return Ok(());
+                    }
+                    tracing::debug!("Memory at {address:#010x} changed, reading it again");
+                }
+                Ok(()) => tracing::warn!("Cached data of {address:#010x} is corrupted"),
+                Err(error) => {
+                    tracing::warn!("Failed to load cached data of {address:#010x}: {error}")
+                }
+            }
+
+            self.invalidate_range(address..address + 1);
+        }
+
+        memory.read(address, buffer)?;
+
+        let slot = self.free_slots.pop().unwrap_or_else(|| self.storage.len());
+        match self.storage.store(slot, buffer) {
+            Ok(()) => {
+                self.blocks.insert(
+                    address,
+                    CachedBlock {
+                        slot,
+                        crc: crc32(buffer),
+                        hits: 0,
+                    },
+                );
+            }
+            Err(error) => tracing::warn!("Failed to cache data of {address:#010x}: {error}"),
+        }
+
+        Ok(())
+    }
+
+    /// Reads a word of the block from the target, and compares it to the cached data.
+    ///
+    /// The checked word rotates through the block with each check.
+    fn spot_check<M: MemoryInterface + ?Sized>(
+        &self,
+        memory: &mut M,
+        address: u64,
+        hits: u32,
+        cached: &[u8],
+    ) -> Result<bool, Error> {
+        if self.spot_check_interval == 0 || hits % self.spot_check_interval != 0 {
+            return Ok(true);
+        }
+
+        let check = u64::from(hits / self.spot_check_interval);
+        let offset = (check % (self.block_size / 4) * 4) as usize;
+
+        let mut word = [0; 4];
+        memory.read(address + offset as u64, &mut word)?;
+
+        Ok(word == cached[offset..offset + 4])
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Memory which counts the bytes read from it.
+    struct CountingMemory {
+        data: Vec<u8>,
+        bytes_read: usize,
+    }
+
+    impl CountingMemory {
+        fn new(size: usize) -> Self {
+            Self {
+                data: (0..size).map(|i| i as u8).collect(),
+                bytes_read: 0,
+            }
+        }
+    }
+
+    impl MemoryInterface for CountingMemory {
+        fn supports_native_64bit_access(&mut self) -> bool {
+            false
+        }
+
+        fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), Error> {
+            let mut bytes = vec![0; data.len() * 8];
+            self.read_8(address, &mut bytes)?;
+            for (value, bytes) in data.iter_mut().zip(bytes.chunks_exact(8)) {
+                *value = u64::from_le_bytes(bytes.try_into().unwrap());
+            }
+            Ok(())
+        }
+
+        fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), Error> {
+            let mut bytes = vec![0; data.len() * 4];
+            self.read_8(address, &mut bytes)?;
+            for (value, bytes) in data.iter_mut().zip(bytes.chunks_exact(4)) {
+                *value = u32::from_le_bytes(bytes.try_into().unwrap());
+            }
+            Ok(())
+        }
+
+        fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), Error> {
+            let mut bytes = vec![0; data.len() * 2];
+            self.read_8(address, &mut bytes)?;
+            for (value, bytes) in data.iter_mut().zip(bytes.chunks_exact(2)) {
+                *value = u16::from_le_bytes(bytes.try_into().unwrap());
+            }
+            Ok(())
+        }
+
+        fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), Error> {
+            let source = self
+                .data
+                .get(address as usize..address as usize + data.len())
+                .ok_or_else(|| Error::Other("Out of range".to_string()))?;
+            data.copy_from_slice(source);
+            self.bytes_read += data.len();
+            Ok(())
+        }
+
+        fn read(&mut self, address: u64, data: &mut [u8]) -> Result<(), Error> {
+            self.read_8(address, data)
+        }
+
+        fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), Error> {
+            let bytes = data
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect::<Vec<_>>();
+            self.write_8(address, &bytes)
+        }
+
+        fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), Error> {
+            let bytes = data
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect::<Vec<_>>();
+            self.write_8(address, &bytes)
+        }
+
+        fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), Error> {
+            let bytes = data
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect::<Vec<_>>();
+            self.write_8(address, &bytes)
+        }
+
+        fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), Error> {
+            self.data[address as usize..][..data.len()].copy_from_slice(data);
+            Ok(())
+        }
+
+        fn supports_8bit_transfers(&self) -> Result<bool, Error> {
+            Ok(true)
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn repeated_reads_are_cached() {
+        let mut memory = CountingMemory::new(256);
+        let mut cache = ReadCache::new()
+            .with_block_size(64)
+            .with_spot_check_interval(0);
+
+        let mut data = [0; 100];
+        cache.read(&mut memory, 10, &mut data).unwrap();
+        assert_eq!(data[0], 10);
+        assert_eq!(data[99], 109);
+        assert_eq!(memory.bytes_read, 128);
+
+        cache.read(&mut memory, 20, &mut data).unwrap();
+        assert_eq!(data[0], 20);
+        assert_eq!(memory.bytes_read, 128);
+    }
+
+    #[test]
+    fn spot_check_detects_changes() {
+        let mut memory = CountingMemory::new(64);
+        let mut cache = ReadCache::new()
+            .with_block_size(64)
+            .with_spot_check_interval(1);
+
+        let mut data = [0; 8];
+        cache.read(&mut memory, 0, &mut data).unwrap();
+
+        // The first spot check reads the second word of the block.
+        memory.write_8(4, &[0xaa]).unwrap();
+        cache.read(&mut memory, 0, &mut data).unwrap();
+        assert_eq!(data[4], 0xaa);
+    }
+
+    #[test]
+    fn invalidated_range_is_read_again() {
+        let mut memory = CountingMemory::new(256);
+        let mut cache = ReadCache::new()
+            .with_block_size(64)
+            .with_spot_check_interval(0);
+
+        let mut data = [0; 128];
+        cache.read(&mut memory, 0, &mut data).unwrap();
+
+        memory.write_8(70, &[0xaa]).unwrap();
+        cache.invalidate_range(70..71);
+        cache.read(&mut memory, 0, &mut data).unwrap();
+
+        assert_eq!(data[70], 0xaa);
+        assert_eq!(memory.bytes_read, 192);
+    }
+
+    #[test]
+    fn partial_blocks_are_read_directly() {
+        let mut memory = CountingMemory::new(100);
+        let mut cache = ReadCache::new().with_block_size(64);
+
+        let mut data = [0; 20];
+        cache.read(&mut memory, 70, &mut data).unwrap();
+
+        assert_eq!(data[0], 70);
+        assert_eq!(cache.cached_bytes(), 0);
+    }
+}
@@ -1,7 +1,20 @@
-use crate::error::Error;
+mod cache;
+
+pub use cache::ReadCache;
+
+use crate::{
+    cancellation::{CancellationToken, CancelledError},
+    error::Error,
+};
 
 use scroll::Pread;
 
+/// The number of bytes transferred between checks of the [`CancellationToken`] in
+/// [`MemoryInterface::read_cancellable`] and [`MemoryInterface::write_cancellable`].
+///
+/// This is a multiple of 8, so the chunks keep the alignment of the first one.
+const CANCELLABLE_CHUNK_SIZE: usize = 4096;
+
 /// {function_name} was called with data length that is not a multiple of {alignment}
 #[derive(Debug, thiserror::Error, docsplay::Display)]
 pub struct InvalidDataLengthError {
@@ -28,6 +41,50 @@ pub struct MemoryNotAlignedError {
     pub alignment: usize,
 }
 
+/// How memory accesses of a [`Core`](crate::Core) outside the memory map of the target
+/// description are handled.
+///
+/// Checking the accesses catches wild pointers, e.g. from scripts or debugger clients, before an
+/// access to unmapped memory hangs the bus. Peripherals are only accessible if the target
+/// description declares them, e.g. as generic memory regions. The registers of the core itself,
+/// e.g. the Private Peripheral Bus of Cortex-M cores, are always accessible.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryMapPolicy {
+    /// Accesses are not checked.
+    #[default]
+    Allow,
+    /// Accesses outside the memory map are logged as a warning, and performed.
+    Warn,
+    /// Accesses outside the memory map are rejected with [`Error::OutsideMemoryMap`].
+    Enforce,
+}
+
+impl std::str::FromStr for MemoryMapPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "allow" => Ok(Self::Allow),
+            "warn" => Ok(Self::Warn),
+            "enforce" => Ok(Self::Enforce),
+            _ => Err(format!(
+                "'{s}' is not a memory map policy, use one of: allow, warn, enforce"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for MemoryMapPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Allow => "allow",
+            Self::Warn => "warn",
+            Self::Enforce => "enforce",
+        })
+    }
+}
+
 /// An interface to be implemented for drivers that allow target memory access.
 pub trait MemoryInterface<ERR = Error>
 where
@@ -220,6 +277,25 @@ where
     /// Write a block of 8bit words at `address`.
     fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), ERR>;
 
+    /// Write `count` 32bit words with the value `pattern` at `address` in the target's endianness.
+    ///
+    /// The address where the write should be performed at has to be a multiple of 4.
+    /// Returns [`Error::MemoryNotAligned`] if this does not hold true.
+    fn fill_32(&mut self, mut address: u64, pattern: u32, count: usize) -> Result<(), ERR> {
+        // Write in chunks, so filling large areas doesn't need a buffer of the same size.
+        let chunk = vec![pattern; count.min(1024)];
+        let mut remaining = count;
+        while remaining > 0 {
+            let words = remaining.min(chunk.len());
+            self.write_32(address, &chunk[..words])?;
+
+            address += 4 * words as u64;
+            remaining -= words;
+        }
+
+        Ok(())
+    }
+
     /// Writes bytes using 64 bit memory access. Address must be 64 bit aligned
     /// and data must be an exact multiple of 8.
     fn write_mem_64bit(&mut self, address: u64, data: &[u8]) -> Result<(), ERR> {
@@ -319,6 +395,46 @@ where
         Ok(())
     }
 
+    /// Reads data from `address` like [`MemoryInterface::read`], in chunks, and stops with a
+    /// [`CancelledError`] between chunks once `token` is cancelled.
+    fn read_cancellable(
+        &mut self,
+        mut address: u64,
+        data: &mut [u8],
+        token: &CancellationToken,
+    ) -> Result<(), ERR>
+    where
+        ERR: From<CancelledError>,
+    {
+        for chunk in data.chunks_mut(CANCELLABLE_CHUNK_SIZE) {
+            token.error_if_cancelled()?;
+            self.read(address, chunk)?;
+            address += chunk.len() as u64;
+        }
+
+        Ok(())
+    }
+
+    /// Writes data to `address` like [`MemoryInterface::write`], in chunks, and stops with a
+    /// [`CancelledError`] between chunks once `token` is cancelled.
+    fn write_cancellable(
+        &mut self,
+        mut address: u64,
+        data: &[u8],
+        token: &CancellationToken,
+    ) -> Result<(), ERR>
+    where
+        ERR: From<CancelledError>,
+    {
+        for chunk in data.chunks(CANCELLABLE_CHUNK_SIZE) {
+            token.error_if_cancelled()?;
+            self.write(address, chunk)?;
+            address += chunk.len() as u64;
+        }
+
+        Ok(())
+    }
+
     /// Returns whether the current platform supports native 8bit transfers.
     fn supports_8bit_transfers(&self) -> Result<bool, ERR>;
 
@@ -452,6 +568,12 @@ where
             .map_err(Error::from)
     }
 
+    fn fill_32(&mut self, address: u64, pattern: u32, count: usize) -> Result<(), Error> {
+        self.memory_mut()
+            .fill_32(address, pattern, count)
+            .map_err(Error::from)
+    }
+
     fn write(&mut self, address: u64, data: &[u8]) -> Result<(), Error> {
         self.memory_mut().write(address, data).map_err(Error::from)
     }
@@ -2,21 +2,27 @@
 pub(crate) mod common;
 pub(crate) mod usb_util;
 
+pub mod bench;
 pub mod blackmagic;
+pub mod boundary_scan;
 pub mod ch347usbjtag;
 pub mod cmsisdap;
 pub mod espusbjtag;
 pub mod fake_probe;
 pub mod ftdi;
 pub mod glasgow;
+#[cfg(target_os = "linux")]
+pub mod gpiod;
 pub mod jlink;
 pub mod list;
 pub mod sifliuart;
 pub mod stlink;
+pub mod swd_trace;
+pub mod trace;
 pub mod wlink;
 
 use crate::architecture::arm::sequences::{ArmDebugSequence, DefaultArmSequence};
-use crate::architecture::arm::{ArmDebugInterface, ArmError, DapError};
+use crate::architecture::arm::{ArmDebugInterface, ArmError, DapError, Pins};
 use crate::architecture::arm::{RegisterAddress, SwoAccess, communication_interface::DapProbe};
 use crate::architecture::riscv::communication_interface::{RiscvError, RiscvInterfaceBuilder};
 use crate::architecture::xtensa::communication_interface::{
@@ -36,6 +42,10 @@ use std::any::Any;
 use std::collections::HashMap;
 use std::fmt;
 use std::sync::Arc;
+use swd_trace::SwdTraceRecorder;
+use trace::{ProbeTrace, TraceRecorder};
+
+pub use common::IdCode;
 
 /// Used to log warnings when the measured target voltage is
 /// lower than 1.4V, if at all measurable.
@@ -316,6 +326,50 @@ impl Probe {
         }
     }
 
+    /// Opens a probe which plays back a recorded [`ProbeTrace`], instead of talking to a real probe.
+    ///
+    /// The same commands have to be sent to the probe as during the recording, so the same
+    /// operations have to be performed with the same options.
+    pub fn replay_trace(trace: ProbeTrace) -> Result<Self, DebugProbeError> {
+        match trace.probe.as_str() {
+            "CMSIS-DAP" => Ok(Probe::new(cmsisdap::CmsisDap::replay(trace)?)),
+            other => Err(DebugProbeError::Other(format!(
+                "Replaying traces of {other} probes is not supported."
+            ))),
+        }
+    }
+
+    /// Starts recording all transactions of the probe with `recorder`, e.g. to attach them to a bug report.
+    ///
+    /// The recording continues after attaching to the target, until the probe is dropped. It should be
+    /// started right after opening the probe, so the trace can be replayed with [`Probe::replay_trace`].
+    pub fn record_trace(&mut self, recorder: TraceRecorder) -> Result<(), DebugProbeError> {
+        self.inner.record_trace(recorder)
+    }
+
+    /// Starts logging every SWD transfer of the probe with `recorder`, to debug the SWD protocol.
+    ///
+    /// This is supported by probes which implement SWD with raw bit sequences, e.g. J-Link and
+    /// FTDI probes, and by CMSIS-DAP probes, which report the transfers they executed.
+    pub fn record_swd_trace(&mut self, recorder: SwdTraceRecorder) -> Result<(), DebugProbeError> {
+        self.inner.record_swd_trace(recorder)
+    }
+
+    /// Returns `true` if the probe found the flash of the attached target to be read protected.
+    pub(crate) fn target_flash_protected(&self) -> bool {
+        self.inner.target_flash_protected()
+    }
+
+    /// Returns `true` if the probe can mass-erase the attached target.
+    pub(crate) fn has_erase_all(&self) -> bool {
+        self.inner.has_erase_all()
+    }
+
+    /// Mass-erases the attached target with a vendor command of the probe.
+    pub(crate) fn erase_all(&mut self) -> Result<(), DebugProbeError> {
+        self.inner.erase_all()
+    }
+
     /// Get the human readable name for the probe.
     pub fn get_name(&self) -> String {
         self.inner.get_name().to_string()
@@ -572,6 +626,22 @@ impl Probe {
         self.inner.try_as_jtag_probe()
     }
 
+    /// Scans the JTAG chain, and returns the TAPs found on it.
+    ///
+    /// The probe has to be attached using the JTAG protocol.
+    pub fn scan_jtag_chain(&mut self) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+        if !self.attached {
+            return Err(DebugProbeError::NotAttached);
+        }
+
+        match self.inner.try_as_jtag_probe() {
+            Some(probe) => probe.scan_jtag_chain(),
+            None => Err(DebugProbeError::InterfaceNotAvailable {
+                interface_name: "JTAG",
+            }),
+        }
+    }
+
     /// Gets a SWO interface from the debug probe.
     ///
     /// This does not work on all probes.
@@ -593,6 +663,26 @@ impl Probe {
         self.inner.try_as_dap_probe()
     }
 
+    /// Drive the pins selected in `pin_select` to the levels in `pin_out`, and return
+    /// the state of all pins read back afterwards.
+    ///
+    /// This works without attaching to a target, which is useful during board bring-up.
+    /// See [`Session::control_pins`] for the semantics of the pin bits.
+    pub fn control_pins(
+        &mut self,
+        pin_out: Pins,
+        pin_select: Pins,
+    ) -> Result<Pins, DebugProbeError> {
+        let Some(dap_probe) = self.try_as_dap_probe() else {
+            return Err(DebugProbeError::CommandNotSupportedByProbe {
+                command_name: "swj_pins",
+            });
+        };
+        let pins = dap_probe.swj_pins(pin_out.0 as u32, pin_select.0 as u32, 0)?;
+
+        Ok(Pins(pins as u8))
+    }
+
     /// Try reading the target voltage of via the connected voltage pin.
     ///
     /// This does not work on all probes.
@@ -600,6 +690,13 @@ impl Probe {
         self.inner.get_target_voltage()
     }
 
+    /// Reads the firmware version of the probe, and whether it should be updated.
+    ///
+    /// Returns `Ok(None)` if the probe does not report its firmware version.
+    pub fn firmware_info(&mut self) -> Result<Option<FirmwareInfo>, DebugProbeError> {
+        self.inner.firmware_info()
+    }
+
     /// Try to convert the probe into a concrete probe type.
     pub fn try_into<P: DebugProbe>(&mut self) -> Option<&mut P> {
         (self.inner.as_mut() as &mut dyn Any).downcast_mut::<P>()
@@ -780,6 +877,52 @@ pub trait DebugProbe: Any + Send + fmt::Debug {
     fn get_target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
         Ok(None)
     }
+
+    /// Reads the firmware version of the probe, see [`Probe::firmware_info`]. Returns `Ok(None)`
+    /// if the probe doesn't report its firmware version.
+    fn firmware_info(&mut self) -> Result<Option<FirmwareInfo>, DebugProbeError> {
+        Ok(None)
+    }
+
+    /// Returns `true` if the probe found the flash of the attached target to be read protected.
+    ///
+    /// Only probes which can remove the protection with [`DebugProbe::erase_all`] check this.
+    fn target_flash_protected(&self) -> bool {
+        false
+    }
+
+    /// Returns `true` if the probe can mass-erase the attached target with [`DebugProbe::erase_all`].
+    fn has_erase_all(&self) -> bool {
+        false
+    }
+
+    /// Mass-erases the flash of the attached target with a vendor command of the probe, which
+    /// also removes its read protection.
+    ///
+    /// This is not available on all probes.
+    fn erase_all(&mut self) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::CommandNotSupportedByProbe {
+            command_name: "erase_all",
+        })
+    }
+
+    /// Starts recording the transactions of the probe, see [`Probe::record_trace`].
+    ///
+    /// This is not available on all probes.
+    fn record_trace(&mut self, _recorder: TraceRecorder) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::CommandNotSupportedByProbe {
+            command_name: "record_trace",
+        })
+    }
+
+    /// Starts logging the SWD transfers of the probe, see [`Probe::record_swd_trace`].
+    ///
+    /// This is not available on all probes.
+    fn record_swd_trace(&mut self, _recorder: SwdTraceRecorder) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::CommandNotSupportedByProbe {
+            command_name: "record_swd_trace",
+        })
+    }
 }
 
 impl PartialEq for dyn ProbeFactory {
@@ -868,6 +1011,81 @@ impl DebugProbeInfo {
     pub fn probe_type(&self) -> String {
         self.probe_factory.to_string()
     }
+
+    /// Opens the probe to read its firmware version, see [`Probe::firmware_info`].
+    pub fn read_firmware_info(&self) -> Result<Option<FirmwareInfo>, DebugProbeError> {
+        self.open()?.firmware_info()
+    }
+}
+
+/// Whether the firmware of a probe should be updated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FirmwareStatus {
+    /// The firmware is supported, and no update is needed.
+    Supported,
+    /// The firmware works, but has known issues or lacks features which an update fixes.
+    UpdateRecommended,
+    /// It is not known which firmware versions work well.
+    Unknown,
+}
+
+/// The firmware version of a probe, and the versions which are supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirmwareInfo {
+    /// The version of the firmware, as reported by the probe.
+    pub version: String,
+    /// The oldest firmware version which works with probe-rs, if known.
+    pub minimum: Option<String>,
+    /// The firmware version which is recommended, if known.
+    pub recommended: Option<String>,
+    /// The tool to update the firmware with, if known.
+    pub update_tool: Option<String>,
+    /// Whether the firmware should be updated.
+    pub status: FirmwareStatus,
+}
+
+impl FirmwareInfo {
+    /// Creates the info for the firmware `version`, with an unknown status.
+    pub fn new(version: impl Into<String>) -> Self {
+        Self {
+            version: version.into(),
+            minimum: None,
+            recommended: None,
+            update_tool: None,
+            status: FirmwareStatus::Unknown,
+        }
+    }
+
+    /// Returns a message which asks the user to update the firmware, if an update is recommended.
+    pub fn update_advisory(&self) -> Option<String> {
+        if self.status != FirmwareStatus::UpdateRecommended {
+            return None;
+        }
+
+        let mut advisory = format!("The probe firmware {} is outdated", self.version);
+        if let Some(recommended) = &self.recommended {
+            advisory.push_str(&format!(", please update it to {recommended} or newer"));
+        }
+        if let Some(tool) = &self.update_tool {
+            advisory.push_str(&format!(" using {tool}"));
+        }
+        advisory.push('.');
+
+        Some(advisory)
+    }
+}
+
+impl std::fmt::Display for FirmwareInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.version)?;
+        match (self.status, &self.recommended) {
+            (FirmwareStatus::UpdateRecommended, Some(recommended)) => {
+                write!(f, " (update to {recommended} recommended)")
+            }
+            (FirmwareStatus::UpdateRecommended, None) => write!(f, " (update recommended)"),
+            _ => Ok(()),
+        }
+    }
 }
 
 /// An error which can occur while parsing a [`DebugProbeSelector`].
@@ -890,6 +1108,9 @@ pub enum DebugProbeSelectorParseError {
 /// If SERIALNUMBER exists (i.e. the selector contains a second color) and is empty,
 /// probe-rs will select probes that have no serial number, or where the serial number is empty.
 ///
+/// A probe attached to a host which runs `probe-rs serve` is selected with
+/// "remote://HOST:PORT/SELECTOR", see [`DebugProbeSelector::remote`].
+///
 /// ## Example:
 ///
 /// ```
@@ -945,11 +1166,52 @@ impl DebugProbeSelector {
                 })
                 .unwrap_or(true)
     }
+
+    /// Splits the selector of a probe attached to a remote host, e.g.
+    /// `remote://lab-pi:3000/1366:1015`, into the address of the host (`lab-pi:3000`) and the
+    /// selector of the probe on that host. Without a selector, the remote host picks the probe.
+    ///
+    /// Returns `None` if the probe is not attached to a remote host.
+    pub fn remote(&self) -> Option<(&str, Option<DebugProbeSelector>)> {
+        if (self.vendor_id, self.product_id) != (0, 0) {
+            return None;
+        }
+        let remote = self.serial_number.as_deref()?.strip_prefix("remote://")?;
+        let (address, selector) = remote.split_once('/').unwrap_or((remote, ""));
+        // The selector was validated when it was parsed.
+        let selector = (!selector.is_empty())
+            .then(|| DebugProbeSelector::try_from(selector).ok())
+            .flatten();
+        Some((address, selector))
+    }
 }
 
 impl TryFrom<&str> for DebugProbeSelector {
     type Error = DebugProbeSelectorParseError;
     fn try_from(value: &str) -> Result<Self, Self::Error> {
+        // Probes without a VID:PID, like GPIO pins, are selected with a URL, e.g.
+        // `gpiod://gpiochip0?swclk=11&swdio=8`.
+        let is_url = value
+            .split_once("://")
+            .is_some_and(|(scheme, _)| scheme.chars().all(|c| c.is_ascii_alphabetic()));
+        if is_url {
+            // The selector of a probe on a remote host must be valid, even though only the remote
+            // host uses it.
+            if let Some(remote) = value.strip_prefix("remote://") {
+                match remote.split_once('/') {
+                    Some((_, selector)) if !selector.is_empty() => {
+                        DebugProbeSelector::try_from(selector)?;
+                    }
+                    _ => {}
+                }
+            }
+            return Ok(DebugProbeSelector {
+                vendor_id: 0,
+                product_id: 0,
+                serial_number: Some(value.to_string()),
+            });
+        }
+
         // Split into at most 3 parts: VID, PID, Serial.
         // We limit the number of splits to allow for colons in the
         // serial number (EspJtag uses MAC address)
@@ -1171,6 +1433,27 @@ impl Default for SwdSettings {
     }
 }
 
+/// A TAP found on the JTAG scan chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JtagChainItem {
+    /// The IDCODE of the TAP.
+    ///
+    /// TAPs which don't implement the IDCODE register are in BYPASS after reset, and have no
+    /// IDCODE.
+    pub idcode: Option<IdCode>,
+    /// The length of the instruction register of the TAP.
+    pub ir_len: u8,
+}
+
+impl From<JtagChainItem> for ScanChainElement {
+    fn from(item: JtagChainItem) -> Self {
+        ScanChainElement {
+            name: item.idcode.map(|idcode| idcode.to_string()),
+            ir_len: Some(item.ir_len),
+        }
+    }
+}
+
 /// The state of a bitbanging JTAG driver.
 ///
 /// This struct tracks the state of the JTAG state machine,  which TAP is currently selected, and
@@ -1231,6 +1514,9 @@ pub(crate) struct ProbeStatistics {
 
     /// Number of SWD FAULT responses encountered.
     num_faults: usize,
+
+    /// Log of the SWD transfers, if enabled.
+    swd_trace: Option<SwdTraceRecorder>,
 }
 
 impl ProbeStatistics {
@@ -1246,6 +1532,23 @@ impl ProbeStatistics {
         self.num_io_calls += 1;
     }
 
+    /// Starts logging the SWD transfers to `recorder`.
+    pub fn record_swd_trace(
+        &mut self,
+        mut recorder: SwdTraceRecorder,
+    ) -> Result<(), DebugProbeError> {
+        recorder.begin().map_err(|error| {
+            DebugProbeError::Other(format!("Failed to record SWD trace: {error}"))
+        })?;
+        self.swd_trace = Some(recorder);
+        Ok(())
+    }
+
+    /// Returns the log of the SWD transfers, if enabled.
+    pub fn swd_trace(&mut self) -> Option<&mut SwdTraceRecorder> {
+        self.swd_trace.as_mut()
+    }
+
     pub fn report_swd_response<T>(&mut self, response: &Result<T, DapError>) {
         match response {
             Err(DapError::FaultResponse) => self.num_faults += 1,
@@ -1291,6 +1594,17 @@ pub trait JtagAccess: DebugProbe {
     /// The measured scan chain will be stored in the probe's internal state.
     fn scan_chain(&mut self) -> Result<&[ScanChainElement], DebugProbeError>;
 
+    /// Scans the JTAG chain, and returns the TAPs found on it, including their IDCODEs.
+    ///
+    /// Unlike [`Self::scan_chain`], this always drives the JTAG interface. IR lengths configured
+    /// using [`Self::set_scan_chain`] are only used if they match the IR scan, otherwise the IR
+    /// lengths are guessed from the IR scan.
+    fn scan_jtag_chain(&mut self) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "scan_jtag_chain",
+        })
+    }
+
     /// Shifts a number of bits through the TAP.
     fn shift_raw_sequence(&mut self, sequence: JtagSequence) -> Result<BitVec, DebugProbeError>;
 
@@ -1786,4 +2100,58 @@ mod test {
         assert!(matches);
         assert!(!matches_with_serial);
     }
+
+    #[test]
+    fn url_selector() {
+        let selector: DebugProbeSelector = "gpiod://gpiochip0?swclk=11&swdio=8".try_into().unwrap();
+
+        assert_eq!(selector.vendor_id, 0);
+        assert_eq!(selector.product_id, 0);
+        assert_eq!(
+            selector.serial_number.as_deref(),
+            Some("gpiod://gpiochip0?swclk=11&swdio=8")
+        );
+
+        let parsed: DebugProbeSelector = selector.to_string().try_into().unwrap();
+        assert_eq!(parsed, selector);
+    }
+
+    #[test]
+    fn remote_selector() {
+        let selector: DebugProbeSelector = "remote://lab-pi:3000/1366:1015".try_into().unwrap();
+        let (address, probe) = selector.remote().unwrap();
+        assert_eq!(address, "lab-pi:3000");
+        let probe = probe.unwrap();
+        assert_eq!((probe.vendor_id, probe.product_id), (0x1366, 0x1015));
+
+        let selector: DebugProbeSelector = "remote://lab-pi:3000".try_into().unwrap();
+        assert_eq!(selector.remote(), Some(("lab-pi:3000", None)));
+        let selector: DebugProbeSelector = "remote://lab-pi:3000/".try_into().unwrap();
+        assert_eq!(selector.remote(), Some(("lab-pi:3000", None)));
+
+        let selector: DebugProbeSelector = "gpiod://gpiochip0?swclk=11&swdio=8".try_into().unwrap();
+        assert_eq!(selector.remote(), None);
+        let selector: DebugProbeSelector = "1366:1015:remote://x".try_into().unwrap();
+        assert_eq!(selector.remote(), None);
+
+        assert!(DebugProbeSelector::try_from("remote://lab-pi:3000/1366").is_err());
+    }
+
+    #[test]
+    fn firmware_update_advisory() {
+        let mut firmware = FirmwareInfo::new("V2J29");
+        assert_eq!(firmware.update_advisory(), None);
+        assert_eq!(firmware.to_string(), "V2J29");
+
+        firmware.recommended = Some("V2J32".to_string());
+        firmware.update_tool = Some("STM32CubeProgrammer".to_string());
+        firmware.status = FirmwareStatus::UpdateRecommended;
+        assert_eq!(
+            firmware.update_advisory().as_deref(),
+            Some(
+                "The probe firmware V2J29 is outdated, please update it to V2J32 or newer using STM32CubeProgrammer."
+            )
+        );
+        assert_eq!(firmware.to_string(), "V2J29 (update to V2J32 recommended)");
+    }
 }
@@ -0,0 +1,225 @@
+//! Measuring the memory access performance of a debug probe and target.
+//!
+//! The measurements only use the [`MemoryInterface`], so they work with every probe backend. The
+//! conversion between bytes and words is done outside of the measured time.
+
+use std::time::{Duration, Instant};
+
+use crate::{Error, MemoryInterface};
+
+/// The width of the memory accesses used for a measurement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AccessWidth {
+    /// 8-bit accesses.
+    U8,
+    /// 16-bit accesses.
+    U16,
+    /// 32-bit accesses.
+    U32,
+    /// 64-bit accesses.
+    U64,
+}
+
+impl AccessWidth {
+    /// Returns the access width with the given number of bits, if it exists.
+    pub fn from_bits(bits: u32) -> Option<Self> {
+        match bits {
+            8 => Some(Self::U8),
+            16 => Some(Self::U16),
+            32 => Some(Self::U32),
+            64 => Some(Self::U64),
+            _ => None,
+        }
+    }
+
+    /// The number of bits of a single access.
+    pub fn bits(self) -> u32 {
+        self.bytes() as u32 * 8
+    }
+
+    /// The number of bytes of a single access.
+    pub fn bytes(self) -> usize {
+        match self {
+            Self::U8 => 1,
+            Self::U16 => 2,
+            Self::U32 => 4,
+            Self::U64 => 8,
+        }
+    }
+}
+
+/// The number of bytes transferred in a measured amount of time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Throughput {
+    /// The number of bytes transferred.
+    pub bytes: usize,
+    /// How long the transfer took.
+    pub duration: Duration,
+}
+
+impl Throughput {
+    /// Returns the throughput in bytes per second.
+    pub fn bytes_per_second(&self) -> f64 {
+        self.bytes as f64 / self.duration.as_secs_f64()
+    }
+}
+
+/// Writes `data` to `address` using accesses of `width`, and measures how long it takes.
+///
+/// The length of `data` has to be a multiple of the access width.
+pub fn measure_write<M: MemoryInterface + ?Sized>(
+    memory: &mut M,
+    address: u64,
+    width: AccessWidth,
+    data: &[u8],
+) -> Result<Throughput, Error> {
+    check_length(width, data.len())?;
+
+    let start;
+    match width {
+        AccessWidth::U8 => {
+            start = Instant::now();
+            memory.write_8(address, data)?;
+        }
+        AccessWidth::U16 => {
+            let words = data
+                .chunks_exact(2)
+                .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+                .collect::<Vec<_>>();
+            start = Instant::now();
+            memory.write_16(address, &words)?;
+        }
+        AccessWidth::U32 => {
+            let words = data
+                .chunks_exact(4)
+                .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+                .collect::<Vec<_>>();
+            start = Instant::now();
+            memory.write_32(address, &words)?;
+        }
+        AccessWidth::U64 => {
+            let words = data
+                .chunks_exact(8)
+                .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+                .collect::<Vec<_>>();
+            start = Instant::now();
+            memory.write_64(address, &words)?;
+        }
+    }
+
+    Ok(Throughput {
+        bytes: data.len(),
+        duration: start.elapsed(),
+    })
+}
+
+/// Reads `data.len()` bytes from `address` using accesses of `width`, and measures how long it
+/// takes.
+///
+/// The length of `data` has to be a multiple of the access width.
+pub fn measure_read<M: MemoryInterface + ?Sized>(
+    memory: &mut M,
+    address: u64,
+    width: AccessWidth,
+    data: &mut [u8],
+) -> Result<Throughput, Error> {
+    check_length(width, data.len())?;
+
+    let words = data.len() / width.bytes();
+    let duration;
+    match width {
+        AccessWidth::U8 => {
+            let start = Instant::now();
+            memory.read_8(address, data)?;
+            duration = start.elapsed();
+        }
+        AccessWidth::U16 => {
+            let mut buffer = vec![0u16; words];
+            let start = Instant::now();
+            memory.read_16(address, &mut buffer)?;
+            duration = start.elapsed();
+            for (bytes, word) in data.chunks_exact_mut(2).zip(buffer) {
+                bytes.copy_from_slice(&word.to_le_bytes());
+            }
+        }
+        AccessWidth::U32 => {
+            let mut buffer = vec![0u32; words];
+            let start = Instant::now();
+            memory.read_32(address, &mut buffer)?;
+            duration = start.elapsed();
+            for (bytes, word) in data.chunks_exact_mut(4).zip(buffer) {
+                bytes.copy_from_slice(&word.to_le_bytes());
+            }
+        }
+        AccessWidth::U64 => {
+            let mut buffer = vec![0u64; words];
+            let start = Instant::now();
+            memory.read_64(address, &mut buffer)?;
+            duration = start.elapsed();
+            for (bytes, word) in data.chunks_exact_mut(8).zip(buffer) {
+                bytes.copy_from_slice(&word.to_le_bytes());
+            }
+        }
+    }
+
+    Ok(Throughput {
+        bytes: data.len(),
+        duration,
+    })
+}
+
+/// Measures the average duration of a single 32-bit read of `address`, which is dominated by the
+/// round trip time between the host and the probe.
+pub fn measure_latency<M: MemoryInterface + ?Sized>(
+    memory: &mut M,
+    address: u64,
+    iterations: u32,
+) -> Result<Duration, Error> {
+    let iterations = iterations.max(1);
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        memory.read_word_32(address)?;
+    }
+
+    Ok(start.elapsed() / iterations)
+}
+
+fn check_length(width: AccessWidth, length: usize) -> Result<(), Error> {
+    if length % width.bytes() != 0 {
+        return Err(Error::Other(format!(
+            "The length {length} is not a multiple of the {}-bit access width",
+            width.bits()
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::MockMemory;
+
+    #[test]
+    fn read_words_as_little_endian_bytes() {
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let mut data = [0; 8];
+        let throughput =
+            measure_read(&mut memory, 0x2000_0000, AccessWidth::U32, &mut data).unwrap();
+
+        assert_eq!(data, [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(throughput.bytes, 8);
+    }
+
+    #[test]
+    fn length_must_match_width() {
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, vec![0; 8]);
+
+        let mut data = [0; 6];
+        assert!(measure_read(&mut memory, 0x2000_0000, AccessWidth::U32, &mut data).is_err());
+    }
+}
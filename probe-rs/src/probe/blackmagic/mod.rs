@@ -25,7 +25,7 @@ use crate::{
         AutoImplementJtagAccess, DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector,
         IoSequenceItem, JtagAccess, JtagDriverState, ProbeCreationError, ProbeError, ProbeFactory,
         ProbeStatistics, RawJtagIo, RawSwdIo, SwdSettings, WireProtocol,
-        blackmagic::arm::BlackMagicProbeArmDebug,
+        blackmagic::arm::BlackMagicProbeArmDebug, swd_trace::SwdTraceRecorder,
     },
 };
 use bitvec::vec::BitVec;
@@ -85,7 +85,7 @@ enum RemoteCommand<'a> {
     Handshake(&'a mut [u8]),
     GetAccelerators,
     HighLevelCheck,
-    GetVoltage,
+    GetVoltage(&'a mut [u8]),
     GetSpeedKhz,
     SetNrst(bool),
     SetPower(bool),
@@ -259,6 +259,7 @@ impl RemoteCommand<'_> {
     fn response_buffer(&mut self) -> Option<&mut [u8]> {
         match self {
             RemoteCommand::Handshake(data) => Some(data),
+            RemoteCommand::GetVoltage(data) => Some(data),
             RemoteCommand::MemReadV0P { data, .. } => Some(data),
             RemoteCommand::MemReadV1 { data, .. } => Some(data),
             RemoteCommand::MemReadV3 { data, .. } => Some(data),
@@ -286,7 +287,7 @@ impl std::string::ToString for RemoteCommand<'_> {
     fn to_string(&self) -> String {
         match self {
             RemoteCommand::Handshake(_) => "+#!GA#".to_string(),
-            RemoteCommand::GetVoltage => " !GV#".to_string(),
+            RemoteCommand::GetVoltage(_) => " !GV#".to_string(),
             RemoteCommand::GetSpeedKhz => "!Gf#".to_string(),
             RemoteCommand::SetSpeedHz(speed) => {
                 format!("!GF{speed:08x}#")
@@ -700,7 +701,7 @@ impl BlackMagicProbe {
         };
 
         probe.command(RemoteCommand::SetNrst(false)).ok();
-        probe.command(RemoteCommand::GetVoltage).ok();
+        probe.target_voltage().ok();
         probe.command(RemoteCommand::SetSpeedHz(400_0000)).ok();
         probe.command(RemoteCommand::GetSpeedKhz).ok();
 
@@ -855,6 +856,15 @@ impl BlackMagicProbe {
         }
     }
 
+    /// Reads the target voltage, which the probe reports as text, e.g. `3.3V`.
+    fn target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
+        let mut response = [0u8; 16];
+        let len = self.command(RemoteCommand::GetVoltage(&mut response))?.0 as usize;
+        let voltage = parse_voltage(&response[..len]);
+        tracing::debug!("Target voltage: {:?}", voltage);
+        Ok(voltage)
+    }
+
     fn get_speed(&mut self) -> Result<u32, DebugProbeError> {
         let speed = self.command(RemoteCommand::SpeedKhz)?.0.try_into().unwrap();
         Ok(speed)
@@ -1086,11 +1096,10 @@ impl DebugProbe for BlackMagicProbe {
     }
 
     fn target_reset(&mut self) -> Result<(), DebugProbeError> {
-        // TODO we could add this by using a GPIO. However, different probes may connect
-        // different pins (if any) to the reset line, so we would need to make this configurable.
-        Err(DebugProbeError::NotImplemented {
-            function_name: "target_reset",
-        })
+        // The probe drives the nRST pin of its debug connector.
+        self.target_reset_assert()?;
+        std::thread::sleep(Duration::from_millis(10));
+        self.target_reset_deassert()
     }
 
     fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
@@ -1185,6 +1194,25 @@ impl DebugProbe for BlackMagicProbe {
     fn has_xtensa_interface(&self) -> bool {
         true
     }
+
+    fn get_target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
+        self.target_voltage()
+    }
+
+    fn record_swd_trace(&mut self, recorder: SwdTraceRecorder) -> Result<(), DebugProbeError> {
+        self.probe_statistics.record_swd_trace(recorder)
+    }
+}
+
+/// Parses the voltage reported by the probe. Probes which can not measure the target voltage
+/// report `ABSENT!` or an empty string.
+fn parse_voltage(response: &[u8]) -> Option<f32> {
+    std::str::from_utf8(response)
+        .ok()?
+        .trim()
+        .strip_suffix('V')?
+        .parse()
+        .ok()
 }
 
 impl AutoImplementJtagAccess for BlackMagicProbe {}
@@ -1554,3 +1582,16 @@ impl ProbeFactory for BlackMagicProbeFactory {
         }]
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn voltage() {
+        assert_eq!(parse_voltage(b"3.3V"), Some(3.3));
+        assert_eq!(parse_voltage(b"0.0V"), Some(0.0));
+        assert_eq!(parse_voltage(b"ABSENT!"), None);
+        assert_eq!(parse_voltage(b""), None);
+    }
+}
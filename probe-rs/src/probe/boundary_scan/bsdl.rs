@@ -0,0 +1,520 @@
+//! Parsing of the parts of BSDL (Boundary Scan Description Language, IEEE 1149.1) files which are
+//! needed to drive and sample the pins of a device.
+
+use std::collections::HashMap;
+
+/// Errors when parsing a BSDL file.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum BsdlError {
+    /// The BSDL file does not contain an entity.
+    MissingEntity,
+
+    /// The BSDL file does not define the attribute {0}.
+    MissingAttribute(&'static str),
+
+    /// The value '{value}' of the attribute {attribute} is invalid.
+    InvalidAttribute {
+        /// The name of the attribute.
+        attribute: &'static str,
+        /// The invalid value.
+        value: String,
+    },
+
+    /// The boundary register cell '{0}' is invalid.
+    InvalidCell(String),
+
+    /// The cell {cell} is outside of the boundary register, which has {length} cells.
+    CellOutOfRange {
+        /// The number of the cell.
+        cell: usize,
+        /// The length of the boundary register.
+        length: usize,
+    },
+}
+
+/// The function of a boundary register cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellFunction {
+    /// Captures the level of an input pin.
+    Input,
+    /// Captures the level of a clock input pin.
+    Clock,
+    /// Captures the level of a pin, without being able to drive it.
+    ObserveOnly,
+    /// Drives an output pin which is always enabled.
+    Output2,
+    /// Drives an output pin which can be disabled with a control cell.
+    Output3,
+    /// Drives and captures a bidirectional pin.
+    Bidir,
+    /// Enables or disables the outputs of other cells.
+    Control,
+    /// A control cell which is forced to its disable value when the device is reset.
+    ControlR,
+    /// A cell which is not connected to a pin.
+    Internal,
+}
+
+impl CellFunction {
+    fn parse(function: &str) -> Option<Self> {
+        Some(match function.to_ascii_lowercase().as_str() {
+            "input" => Self::Input,
+            "clock" => Self::Clock,
+            "observe_only" => Self::ObserveOnly,
+            "output2" => Self::Output2,
+            "output3" => Self::Output3,
+            "bidir" => Self::Bidir,
+            "control" => Self::Control,
+            "controlr" => Self::ControlR,
+            "internal" => Self::Internal,
+            _ => return None,
+        })
+    }
+
+    /// Returns `true` if the cell captures the level of its pin.
+    pub fn is_input(self) -> bool {
+        matches!(
+            self,
+            Self::Input | Self::Clock | Self::ObserveOnly | Self::Bidir
+        )
+    }
+
+    /// Returns `true` if the cell drives its pin.
+    pub fn is_output(self) -> bool {
+        matches!(self, Self::Output2 | Self::Output3 | Self::Bidir)
+    }
+}
+
+/// The control cell which enables the output of a boundary register cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ControlCell {
+    /// The number of the control cell.
+    pub cell: usize,
+    /// The value of the control cell which disables the output.
+    pub disable_value: bool,
+}
+
+/// A cell of the boundary register.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoundaryCell {
+    /// The number of the cell, which is its bit position in the boundary register.
+    ///
+    /// Cell 0 is the one closest to TDO.
+    pub number: usize,
+    /// The type of the cell, e.g. `BC_1`.
+    pub cell_type: String,
+    /// The port connected to the cell, if any.
+    pub port: Option<String>,
+    /// The function of the cell.
+    pub function: CellFunction,
+    /// The safe value of the cell, if it has one.
+    pub safe_value: Option<bool>,
+    /// The control cell which enables the output, for three-state outputs.
+    pub control: Option<ControlCell>,
+}
+
+/// The boundary scan description of a device, parsed from a BSDL file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bsdl {
+    /// The name of the entity, usually the name of the device and its package.
+    pub entity: String,
+    /// The length of the instruction register.
+    pub instruction_length: u32,
+    /// The opcodes of the instructions, by their (upper case) name.
+    ///
+    /// Undefined bits (`X`) are zero.
+    pub opcodes: HashMap<String, Vec<u32>>,
+    /// The IDCODE of the device, with a mask of the defined bits.
+    pub idcode: Option<(u32, u32)>,
+    /// The cells of the boundary register, ordered by their number.
+    pub cells: Vec<BoundaryCell>,
+}
+
+impl Bsdl {
+    /// Parses the contents of a BSDL file.
+    pub fn parse(source: &str) -> Result<Self, BsdlError> {
+        let source = normalize(source);
+
+        let entity = entity_name(&source).ok_or(BsdlError::MissingEntity)?;
+
+        let instruction_length = attribute(&source, "INSTRUCTION_LENGTH")?;
+        let instruction_length: u32 = instruction_length
+            .parse()
+            .ok()
+            .filter(|length| (1..=32).contains(length))
+            .ok_or(BsdlError::InvalidAttribute {
+                attribute: "INSTRUCTION_LENGTH",
+                value: instruction_length,
+            })?;
+
+        let opcodes = attribute(&source, "INSTRUCTION_OPCODE")?;
+        let opcodes =
+            parse_opcodes(&opcodes, instruction_length).ok_or(BsdlError::InvalidAttribute {
+                attribute: "INSTRUCTION_OPCODE",
+                value: opcodes,
+            })?;
+
+        let idcode = match attribute(&source, "IDCODE_REGISTER") {
+            Ok(idcode) => Some(parse_bits(&idcode, 32).ok_or(BsdlError::InvalidAttribute {
+                attribute: "IDCODE_REGISTER",
+                value: idcode,
+            })?),
+            Err(_) => None,
+        };
+
+        let boundary_length = attribute(&source, "BOUNDARY_LENGTH")?;
+        let boundary_length: usize =
+            boundary_length
+                .parse()
+                .map_err(|_| BsdlError::InvalidAttribute {
+                    attribute: "BOUNDARY_LENGTH",
+                    value: boundary_length,
+                })?;
+
+        let mut cells = parse_cells(&attribute(&source, "BOUNDARY_REGISTER")?)?;
+        cells.sort_by_key(|cell| cell.number);
+
+        let referenced_cells = cells.iter().flat_map(|cell| {
+            std::iter::once(cell.number).chain(cell.control.as_ref().map(|control| control.cell))
+        });
+        for cell in referenced_cells {
+            if cell >= boundary_length {
+                return Err(BsdlError::CellOutOfRange {
+                    cell,
+                    length: boundary_length,
+                });
+            }
+        }
+
+        // Every bit of the boundary register has to be described by exactly one cell.
+        let complete = cells
+            .iter()
+            .enumerate()
+            .all(|(index, cell)| cell.number == index);
+        if cells.len() != boundary_length || !complete {
+            return Err(BsdlError::InvalidAttribute {
+                attribute: "BOUNDARY_LENGTH",
+                value: boundary_length.to_string(),
+            });
+        }
+
+        Ok(Self {
+            entity,
+            instruction_length,
+            opcodes,
+            idcode,
+            cells,
+        })
+    }
+
+    /// Returns the first opcode of the instruction `name`.
+    pub fn opcode(&self, name: &str) -> Option<u32> {
+        self.opcodes
+            .get(&name.to_ascii_uppercase())
+            .and_then(|opcodes| opcodes.first().copied())
+    }
+
+    /// Returns `true` if `idcode` matches the IDCODE in the BSDL file, or if it has none.
+    pub fn matches_idcode(&self, idcode: u32) -> bool {
+        match self.idcode {
+            Some((value, mask)) => idcode & mask == value,
+            None => true,
+        }
+    }
+
+    /// Returns the length of the boundary register.
+    pub fn boundary_length(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+/// Removes the VHDL comments, which start with `--` and end at the end of the line, and replaces
+/// all runs of whitespace with a single space.
+fn normalize(source: &str) -> String {
+    source
+        .lines()
+        .flat_map(|line| {
+            line.split_once("--")
+                .map_or(line, |(code, _)| code)
+                .split_whitespace()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn entity_name(source: &str) -> Option<String> {
+    let words = source.split_whitespace().collect::<Vec<_>>();
+    words.windows(3).find_map(|words| match words {
+        [entity, name, is]
+            if entity.eq_ignore_ascii_case("entity") && is.eq_ignore_ascii_case("is") =>
+        {
+            Some(name.to_string())
+        }
+        _ => None,
+    })
+}
+
+/// Returns the value of the attribute `name`, with concatenated strings joined.
+fn attribute(source: &str, name: &'static str) -> Result<String, BsdlError> {
+    let upper = source.to_ascii_uppercase();
+    let needle = format!("ATTRIBUTE {name} ");
+
+    // Attributes are also declared with `attribute NAME : type;`, so search for the definition.
+    let mut start = 0;
+    let value_start = loop {
+        let position = upper[start..]
+            .find(&needle)
+            .ok_or(BsdlError::MissingAttribute(name))?
+            + start;
+        start = position + needle.len();
+
+        let definition = upper[start..].trim_start();
+        if definition.starts_with("OF ") {
+            let is = upper[start..]
+                .find(" IS ")
+                .ok_or(BsdlError::MissingAttribute(name))?;
+            break start + is + " IS ".len();
+        }
+    };
+
+    let mut value = String::new();
+    let mut in_string = false;
+    for c in source[value_start..].chars() {
+        match c {
+            '"' => in_string = !in_string,
+            ';' if !in_string => return Ok(value.trim().to_string()),
+            '&' if !in_string => {}
+            c if in_string || !c.is_whitespace() => value.push(c),
+            _ => {}
+        }
+    }
+
+    Err(BsdlError::MissingAttribute(name))
+}
+
+/// Splits `list` at the commas which are not enclosed in parentheses.
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    let mut depth = 0i32;
+    let mut start = 0;
+    let mut items = Vec::new();
+    for (index, c) in list.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(&list[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&list[start..]);
+
+    items
+        .into_iter()
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+/// Splits `NAME (CONTENTS)` into its name and contents.
+fn split_entry(entry: &str) -> Option<(&str, &str)> {
+    let (name, contents) = entry.split_once('(')?;
+    let contents = contents.trim_end().strip_suffix(')')?;
+    Some((name.trim(), contents))
+}
+
+/// Parses a bit pattern like `0001X`, returning the value and the mask of the defined bits.
+fn parse_bits(pattern: &str, length: u32) -> Option<(u32, u32)> {
+    if pattern.len() != length as usize {
+        return None;
+    }
+
+    let mut value = 0;
+    let mut mask = 0;
+    for c in pattern.chars() {
+        value <<= 1;
+        mask <<= 1;
+        match c {
+            '0' => mask |= 1,
+            '1' => {
+                value |= 1;
+                mask |= 1;
+            }
+            'x' | 'X' => {}
+            _ => return None,
+        }
+    }
+    Some((value, mask))
+}
+
+fn parse_opcodes(opcodes: &str, length: u32) -> Option<HashMap<String, Vec<u32>>> {
+    split_list(opcodes)
+        .map(|entry| {
+            let (name, patterns) = split_entry(entry)?;
+            let patterns = split_list(patterns)
+                .map(|pattern| parse_bits(pattern, length).map(|(value, _)| value))
+                .collect::<Option<_>>()?;
+            Some((name.to_ascii_uppercase(), patterns))
+        })
+        .collect()
+}
+
+fn parse_cells(cells: &str) -> Result<Vec<BoundaryCell>, BsdlError> {
+    split_list(cells)
+        .map(|entry| parse_cell(entry).ok_or_else(|| BsdlError::InvalidCell(entry.to_string())))
+        .collect()
+}
+
+fn parse_cell(entry: &str) -> Option<BoundaryCell> {
+    let (number, fields) = split_entry(entry)?;
+    let fields = split_list(fields).collect::<Vec<_>>();
+
+    let (cell_type, port, function, safe_value, control) = match fields.as_slice() {
+        [cell_type, port, function, safe] => (cell_type, port, function, safe, None),
+        [cell_type, port, function, safe, control, disable, _result] => {
+            let control = ControlCell {
+                cell: control.parse().ok()?,
+                disable_value: parse_value(disable)??,
+            };
+            (cell_type, port, function, safe, Some(control))
+        }
+        _ => return None,
+    };
+
+    Some(BoundaryCell {
+        number: number.parse().ok()?,
+        cell_type: cell_type.to_string(),
+        port: (*port != "*").then(|| port.to_string()),
+        function: CellFunction::parse(function)?,
+        safe_value: parse_value(safe_value)?,
+        control,
+    })
+}
+
+/// Parses a cell value, which is `0`, `1` or undefined (`X`).
+fn parse_value(value: &str) -> Option<Option<bool>> {
+    match value {
+        "0" => Some(Some(false)),
+        "1" => Some(Some(true)),
+        "x" | "X" => Some(None),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BSDL: &str = r#"
+        -- A made up device with a bidirectional and an input pin.
+        entity TEST_DEVICE is
+            generic (PHYSICAL_PIN_MAP : string := "QFN8");
+
+            port (
+                PA0 : inout bit;
+                D   : in bit_vector(0 to 1);
+                TCK, TMS, TDI : in bit;
+                TDO : out bit
+            );
+
+            use STD_1149_1_2001.all;
+
+            attribute COMPONENT_CONFORMANCE of TEST_DEVICE : entity is "STD_1149_1_2001";
+
+            attribute INSTRUCTION_LENGTH of TEST_DEVICE : entity is 4;
+            attribute INSTRUCTION_OPCODE of TEST_DEVICE : entity is
+                "EXTEST  (0000)," &
+                "SAMPLE  (0010, 0011)," &   -- SAMPLE has two opcodes
+                "PRELOAD (0010)," &
+                "IDCODE  (1110)," &
+                "BYPASS  (1111)";
+
+            attribute IDCODE_REGISTER of TEST_DEVICE : entity is
+                "XXXX" &        -- version
+                "1011101000000000" &
+                "01000111011" &
+                "1";
+
+            attribute BOUNDARY_LENGTH of TEST_DEVICE : entity is 5;
+            attribute BOUNDARY_REGISTER of TEST_DEVICE : entity is
+            --  num  cell   port   function  safe  ccell  disval  rslt
+                "4 (BC_1, *,     control,  1),                       " &
+                "3 (BC_1, PA0,   output3,  X,    4,     1,      Z)," &
+                "2 (BC_1, PA0,   input,    X),                       " &
+                "1 (BC_1, D(1),  input,    X)," &
+                "0 (BC_4, D(0),  input,    X)";
+        end TEST_DEVICE;
+    "#;
+
+    #[test]
+    fn parse() {
+        let bsdl = Bsdl::parse(BSDL).unwrap();
+
+        assert_eq!(bsdl.entity, "TEST_DEVICE");
+        assert_eq!(bsdl.instruction_length, 4);
+        assert_eq!(bsdl.opcode("extest"), Some(0b0000));
+        assert_eq!(bsdl.opcode("SAMPLE"), Some(0b0010));
+        assert_eq!(bsdl.opcodes["SAMPLE"], vec![0b0010, 0b0011]);
+        assert_eq!(bsdl.opcode("BYPASS"), Some(0b1111));
+        assert_eq!(bsdl.opcode("CLAMP"), None);
+
+        assert!(bsdl.matches_idcode(0x4BA0_0477));
+        assert!(bsdl.matches_idcode(0x1BA0_0477));
+        assert!(!bsdl.matches_idcode(0x4BA0_0476));
+
+        assert_eq!(bsdl.boundary_length(), 5);
+        assert_eq!(
+            bsdl.cells[3],
+            BoundaryCell {
+                number: 3,
+                cell_type: "BC_1".to_string(),
+                port: Some("PA0".to_string()),
+                function: CellFunction::Output3,
+                safe_value: None,
+                control: Some(ControlCell {
+                    cell: 4,
+                    disable_value: true,
+                }),
+            }
+        );
+        assert_eq!(bsdl.cells[4].port, None);
+        assert_eq!(bsdl.cells[4].safe_value, Some(true));
+        assert_eq!(bsdl.cells[1].port.as_deref(), Some("D(1)"));
+        assert_eq!(bsdl.cells[0].cell_type, "BC_4");
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(matches!(
+            Bsdl::parse("attribute INSTRUCTION_LENGTH of X : entity is 4;"),
+            Err(BsdlError::MissingEntity)
+        ));
+        assert!(matches!(
+            Bsdl::parse(&BSDL.replace("is 5;", "is 6;")),
+            Err(BsdlError::InvalidAttribute {
+                attribute: "BOUNDARY_LENGTH",
+                ..
+            })
+        ));
+        assert!(matches!(
+            Bsdl::parse(&BSDL.replace("input,    X)\"", "input)\"")),
+            Err(BsdlError::InvalidCell(_))
+        ));
+        assert!(matches!(
+            Bsdl::parse(&BSDL.replace("INSTRUCTION_LENGTH", "LENGTH")),
+            Err(BsdlError::MissingAttribute("INSTRUCTION_LENGTH"))
+        ));
+    }
+
+    #[test]
+    fn cells_out_of_range() {
+        assert!(matches!(
+            Bsdl::parse(&BSDL.replace("X,    4,", "X,    7,")),
+            Err(BsdlError::CellOutOfRange { cell: 7, length: 5 })
+        ));
+        assert!(matches!(
+            Bsdl::parse(&BSDL.replace("\"0 (BC_4", "\"5 (BC_4")),
+            Err(BsdlError::CellOutOfRange { cell: 5, length: 5 })
+        ));
+    }
+}
@@ -0,0 +1,321 @@
+//! JTAG boundary scan, to sample and drive the pins of a device with the SAMPLE and EXTEST
+//! instructions of IEEE 1149.1, e.g. to test the solder joints of a board.
+//!
+//! The boundary register of the device is described by its BSDL file, see [`Bsdl`]. The scan works
+//! with every probe which implements [`JtagAccess`]; other devices on the scan chain are put into
+//! BYPASS by the probe, after the device was selected with [`JtagAccess::select_target`].
+
+mod bsdl;
+
+pub use bsdl::{BoundaryCell, Bsdl, BsdlError, CellFunction, ControlCell};
+
+use crate::probe::{DebugProbeError, JtagAccess};
+
+/// Errors of a boundary scan.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum BoundaryScanError {
+    /// The BSDL file does not define the {0} instruction.
+    MissingInstruction(&'static str),
+
+    /// The device has no port named '{0}'.
+    UnknownPort(String),
+
+    /// The port '{0}' is not an output, and cannot be driven.
+    NotAnOutput(String),
+
+    /// The output of the port '{0}' cannot be disabled.
+    CannotDisable(String),
+
+    /// Communicating with the probe failed.
+    Probe(#[from] DebugProbeError),
+}
+
+/// The state of a port of the device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortState {
+    /// The name of the port.
+    pub name: String,
+    /// The level of the pin, if the port has an input cell.
+    pub level: Option<bool>,
+    /// The level the pin is driven to, or `None` if the port is not an output or its output is
+    /// disabled.
+    pub driven: Option<bool>,
+}
+
+/// Samples and drives the pins of a device on the JTAG scan chain.
+///
+/// Once [`Self::extest`] was used, the pins stay disconnected from the device until the TAP is
+/// reset, e.g. with [`JtagAccess::tap_reset`].
+#[derive(Debug)]
+pub struct BoundaryScan {
+    bsdl: Bsdl,
+    /// The values which are shifted into the boundary register.
+    values: Vec<bool>,
+    extest: bool,
+}
+
+impl BoundaryScan {
+    /// Creates a boundary scan of the device described by `bsdl`, with all cells set to their safe
+    /// values.
+    pub fn new(bsdl: Bsdl) -> Self {
+        let values = bsdl
+            .cells
+            .iter()
+            .map(|cell| cell.safe_value.unwrap_or(false))
+            .collect();
+
+        Self {
+            bsdl,
+            values,
+            extest: false,
+        }
+    }
+
+    /// Returns the description of the device.
+    pub fn bsdl(&self) -> &Bsdl {
+        &self.bsdl
+    }
+
+    /// Drives `port` to `level`, or disables its output if `level` is `None`.
+    ///
+    /// The pin is driven by the next [`Self::extest`].
+    pub fn drive(&mut self, port: &str, level: Option<bool>) -> Result<(), BoundaryScanError> {
+        let mut found = false;
+        let mut driven = false;
+        for cell in &self.bsdl.cells {
+            if !cell
+                .port
+                .as_ref()
+                .is_some_and(|name| name.eq_ignore_ascii_case(port))
+            {
+                continue;
+            }
+            found = true;
+            if !cell.function.is_output() {
+                continue;
+            }
+            driven = true;
+
+            match (level, cell.control) {
+                (Some(level), control) => {
+                    self.values[cell.number] = level;
+                    if let Some(control) = control {
+                        self.values[control.cell] = !control.disable_value;
+                    }
+                }
+                (None, Some(control)) => self.values[control.cell] = control.disable_value,
+                (None, None) => return Err(BoundaryScanError::CannotDisable(port.to_string())),
+            }
+        }
+
+        match (found, driven) {
+            (false, _) => Err(BoundaryScanError::UnknownPort(port.to_string())),
+            (true, false) => Err(BoundaryScanError::NotAnOutput(port.to_string())),
+            (true, true) => Ok(()),
+        }
+    }
+
+    /// Samples the pins with the SAMPLE instruction, while the device keeps operating normally.
+    ///
+    /// The reported driven levels are the ones of the device itself.
+    pub fn sample(
+        &mut self,
+        probe: &mut dyn JtagAccess,
+    ) -> Result<Vec<PortState>, BoundaryScanError> {
+        let sample = self.opcode("SAMPLE")?;
+
+        let captured = self.shift(probe, sample)?;
+        self.extest = false;
+
+        Ok(self.port_states(&captured, &captured))
+    }
+
+    /// Drives the pins with the EXTEST instruction, and samples them afterwards.
+    ///
+    /// This disconnects the pins from the device.
+    pub fn extest(
+        &mut self,
+        probe: &mut dyn JtagAccess,
+    ) -> Result<Vec<PortState>, BoundaryScanError> {
+        let extest = self.opcode("EXTEST")?;
+
+        if !self.extest {
+            // Load the values before selecting EXTEST, which drives the pins immediately.
+            let preload = self
+                .opcode("PRELOAD")
+                .or_else(|_| self.opcode("SAMPLE"))
+                .map_err(|_| BoundaryScanError::MissingInstruction("PRELOAD"))?;
+            self.shift(probe, preload)?;
+        } else {
+            self.shift(probe, extest)?;
+        }
+        self.extest = true;
+
+        // The pins are captured before the shifted values are driven, so scan again.
+        let captured = self.shift(probe, extest)?;
+
+        Ok(self.port_states(&captured, &self.values))
+    }
+
+    fn opcode(&self, name: &'static str) -> Result<u32, BoundaryScanError> {
+        self.bsdl
+            .opcode(name)
+            .ok_or(BoundaryScanError::MissingInstruction(name))
+    }
+
+    /// Selects `instruction`, and shifts the values through the boundary register.
+    fn shift(
+        &self,
+        probe: &mut dyn JtagAccess,
+        instruction: u32,
+    ) -> Result<Vec<bool>, DebugProbeError> {
+        let mut data = vec![0u8; self.values.len().div_ceil(8)];
+        for (index, _) in self.values.iter().enumerate().filter(|(_, value)| **value) {
+            data[index / 8] |= 1 << (index % 8);
+        }
+
+        let captured = probe.write_register(instruction, &data, self.values.len() as u32)?;
+
+        Ok(captured.iter().by_vals().collect())
+    }
+
+    /// Returns the states of the ports, with the levels from `captured` and the driven levels
+    /// from `outputs`.
+    fn port_states(&self, captured: &[bool], outputs: &[bool]) -> Vec<PortState> {
+        let mut states: Vec<PortState> = Vec::new();
+
+        for cell in &self.bsdl.cells {
+            let Some(port) = &cell.port else {
+                continue;
+            };
+
+            let index = match states.iter().position(|state| state.name == *port) {
+                Some(index) => index,
+                None => {
+                    states.push(PortState {
+                        name: port.clone(),
+                        level: None,
+                        driven: None,
+                    });
+                    states.len() - 1
+                }
+            };
+            let state = &mut states[index];
+
+            if cell.function.is_input() {
+                state.level = captured.get(cell.number).copied();
+            }
+            if cell.function.is_output() {
+                let enabled = cell.control.is_none_or(|control| {
+                    outputs.get(control.cell) != Some(&control.disable_value)
+                });
+                if enabled {
+                    state.driven = outputs.get(cell.number).copied();
+                }
+            }
+        }
+
+        states
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn bsdl() -> Bsdl {
+        let cells = [
+            (0, Some("D"), CellFunction::Input, None, None),
+            (1, Some("PA0"), CellFunction::Input, None, None),
+            (
+                2,
+                Some("PA0"),
+                CellFunction::Output3,
+                None,
+                Some(ControlCell {
+                    cell: 3,
+                    disable_value: true,
+                }),
+            ),
+            (3, None, CellFunction::Control, Some(true), None),
+            (4, Some("LED"), CellFunction::Output2, Some(false), None),
+        ];
+
+        Bsdl {
+            entity: "TEST".to_string(),
+            instruction_length: 4,
+            opcodes: Default::default(),
+            idcode: None,
+            cells: cells
+                .into_iter()
+                .map(
+                    |(number, port, function, safe_value, control)| BoundaryCell {
+                        number,
+                        cell_type: "BC_1".to_string(),
+                        port: port.map(str::to_string),
+                        function,
+                        safe_value,
+                        control,
+                    },
+                )
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn drive() {
+        let mut scan = BoundaryScan::new(bsdl());
+        assert_eq!(scan.values, [false, false, false, true, false]);
+
+        scan.drive("pa0", Some(true)).unwrap();
+        scan.drive("LED", Some(true)).unwrap();
+        assert_eq!(scan.values, [false, false, true, false, true]);
+
+        scan.drive("PA0", None).unwrap();
+        assert_eq!(scan.values, [false, false, true, true, true]);
+
+        assert!(matches!(
+            scan.drive("LED", None),
+            Err(BoundaryScanError::CannotDisable(_))
+        ));
+        assert!(matches!(
+            scan.drive("D", Some(true)),
+            Err(BoundaryScanError::NotAnOutput(_))
+        ));
+        assert!(matches!(
+            scan.drive("PB0", Some(true)),
+            Err(BoundaryScanError::UnknownPort(_))
+        ));
+    }
+
+    #[test]
+    fn port_states() {
+        let scan = BoundaryScan::new(bsdl());
+
+        let captured = [true, false, true, false, false];
+        assert_eq!(
+            scan.port_states(&captured, &captured),
+            [
+                PortState {
+                    name: "D".to_string(),
+                    level: Some(true),
+                    driven: None,
+                },
+                PortState {
+                    name: "PA0".to_string(),
+                    level: Some(false),
+                    driven: Some(true),
+                },
+                PortState {
+                    name: "LED".to_string(),
+                    level: None,
+                    driven: Some(false),
+                },
+            ]
+        );
+
+        // The control cell disables the output of PA0.
+        let outputs = [false, false, true, true, true];
+        assert_eq!(scan.port_states(&captured, &outputs)[1].driven, None);
+    }
+}
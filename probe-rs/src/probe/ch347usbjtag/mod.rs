@@ -14,7 +14,7 @@ use crate::{
 
 use super::{
     AutoImplementJtagAccess, DebugProbeError, IoSequenceItem, JtagDriverState, ProbeStatistics,
-    RawJtagIo, RawSwdIo, SwdSettings,
+    RawJtagIo, RawSwdIo, SwdSettings, swd_trace::SwdTraceRecorder,
 };
 
 /// A factory for creating [`Ch347UsbJtag`] instances.
@@ -226,4 +226,8 @@ impl DebugProbe for Ch347UsbJtag {
     > {
         Ok(XtensaCommunicationInterface::new(self, state))
     }
+
+    fn record_swd_trace(&mut self, recorder: SwdTraceRecorder) -> Result<(), DebugProbeError> {
+        self.probe_statistics.record_swd_trace(recorder)
+    }
 }
@@ -41,6 +41,9 @@ info_command!(0x07, TargetBoardVendorCommand, Option<String>);
 
 info_command!(0x08, TargetBoardNameCommand, Option<String>);
 
+// The version of the firmware of the probe, which is only reported by CMSIS-DAP 2.1 and newer.
+info_command!(0x09, ProductFirmwareVersionCommand, Option<String>);
+
 info_command!(0xF0, CapabilitiesCommand, Capabilities);
 
 #[derive(Copy, Clone, Debug)]
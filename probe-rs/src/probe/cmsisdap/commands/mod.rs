@@ -6,6 +6,8 @@ pub mod swo;
 pub mod transfer;
 
 use crate::probe::cmsisdap::commands::general::info::PacketSizeCommand;
+use crate::probe::cmsisdap::swo_stream::SwoStream;
+use crate::probe::trace::{TraceError, TraceRecorder, TraceReplay};
 use crate::probe::usb_util::InterfaceExt;
 use crate::probe::{ProbeError, WireProtocol};
 use std::io::ErrorKind;
@@ -91,6 +93,9 @@ pub enum SendError {
 
     /// Timeout in USB communication.
     Timeout,
+
+    /// Replaying the probe trace failed.
+    Replay(#[from] TraceError),
 }
 
 impl From<std::io::Error> for SendError {
@@ -171,52 +176,121 @@ pub enum RequestError {
 
 pub enum CmsisDapDevice {
     /// CMSIS-DAP v1 over HID.
-    /// Stores a HID device handle and maximum HID report size.
+    /// Stores a HID device handle and maximum HID report size,
+    /// and the recorder of the probe trace, if it is being recorded.
     V1 {
         handle: hidapi::HidDevice,
         report_size: usize,
+        recorder: Option<TraceRecorder>,
     },
 
     /// CMSIS-DAP v2 over WinUSB/Bulk.
     /// Stores an usb device handle, out/in EP addresses, maximum DAP packet size,
-    /// and an optional SWO streaming EP address and SWO maximum packet size.
+    /// and an optional SWO streaming EP address and SWO maximum packet size,
+    /// and the recorder of the probe trace, if it is being recorded.
     V2 {
         handle: nusb::Interface,
         out_ep: u8,
         in_ep: u8,
         max_packet_size: usize,
         swo_ep: Option<(u8, usize)>,
+        recorder: Option<TraceRecorder>,
+    },
+
+    /// A recorded probe trace, which is played back instead of talking to a probe.
+    /// Stores the maximum DAP packet size, which is determined from the trace like
+    /// for a real probe.
+    Replay {
+        replay: TraceReplay,
+        packet_size: usize,
     },
 }
 
 impl CmsisDapDevice {
     /// Read from the probe into `buf`, returning the number of bytes read on success.
-    fn read(&self, buf: &mut [u8]) -> Result<usize, SendError> {
-        match self {
-            CmsisDapDevice::V1 { handle, .. } => {
-                match handle.read_timeout(buf, USB_TIMEOUT.as_millis() as i32)? {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, SendError> {
+        let (result, recorder) = match self {
+            CmsisDapDevice::V1 {
+                handle, recorder, ..
+            } => {
+                let result = match handle.read_timeout(buf, USB_TIMEOUT.as_millis() as i32) {
                     // Timeout is not indicated by error, but by returning 0 read bytes
-                    0 => Err(SendError::Timeout),
-                    n => Ok(n),
-                }
+                    Ok(0) => Err(SendError::Timeout),
+                    Ok(n) => Ok(n),
+                    Err(error) => Err(error.into()),
+                };
+                (result, recorder)
             }
-            CmsisDapDevice::V2 { handle, in_ep, .. } => {
-                Ok(handle.read_bulk(*in_ep, buf, USB_TIMEOUT)?)
+            CmsisDapDevice::V2 {
+                handle,
+                in_ep,
+                recorder,
+                ..
+            } => (
+                handle
+                    .read_bulk(*in_ep, buf, USB_TIMEOUT)
+                    .map_err(SendError::from),
+                recorder,
+            ),
+            CmsisDapDevice::Replay { replay, .. } => {
+                let response = replay.response().ok_or(SendError::Timeout)?;
+                let n = response.len().min(buf.len());
+                buf[..n].copy_from_slice(&response[..n]);
+                return Ok(n);
             }
+        };
+
+        if let Some(recorder) = recorder {
+            recorder.response(result.as_ref().ok().map(|n| &buf[..*n]));
         }
+        result
     }
 
     /// Write `buf` to the probe, returning the number of bytes written on success.
-    fn write(&self, buf: &[u8]) -> Result<usize, SendError> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, SendError> {
         match self {
-            CmsisDapDevice::V1 { handle, .. } => Ok(handle.write(buf)?),
-            CmsisDapDevice::V2 { handle, out_ep, .. } => {
+            CmsisDapDevice::V1 {
+                handle, recorder, ..
+            } => {
+                let n = handle.write(buf)?;
+                if let Some(recorder) = recorder {
+                    recorder.command(buf);
+                }
+                Ok(n)
+            }
+            CmsisDapDevice::V2 {
+                handle,
+                out_ep,
+                recorder,
+                ..
+            } => {
                 // Skip first byte as it's set to 0 for HID transfers
-                Ok(handle.write_bulk(*out_ep, &buf[1..], USB_TIMEOUT)?)
+                let n = handle.write_bulk(*out_ep, &buf[1..], USB_TIMEOUT)?;
+                if let Some(recorder) = recorder {
+                    recorder.command(buf);
+                }
+                Ok(n)
+            }
+            CmsisDapDevice::Replay { replay, .. } => {
+                replay.command(buf)?;
+                Ok(buf.len())
             }
         }
     }
 
+    /// Start recording the commands sent to the probe and its responses.
+    ///
+    /// Replaying a trace can not be recorded.
+    pub(super) fn record_trace(&mut self, new_recorder: TraceRecorder) -> bool {
+        match self {
+            CmsisDapDevice::V1 { recorder, .. } | CmsisDapDevice::V2 { recorder, .. } => {
+                *recorder = Some(new_recorder);
+                true
+            }
+            CmsisDapDevice::Replay { .. } => false,
+        }
+    }
+
     /// Drain any pending data from the probe, ensuring future responses are
     /// synchronised to requests. Swallows any errors, which are expected if
     /// there is no pending data to read.
@@ -251,6 +325,9 @@ impl CmsisDapDevice {
                     }
                 }
             }
+
+            // The trace does not contain any pending data.
+            CmsisDapDevice::Replay { .. } => {}
         }
     }
 
@@ -269,6 +346,12 @@ impl CmsisDapDevice {
             } => {
                 *max_packet_size = packet_size;
             }
+            CmsisDapDevice::Replay {
+                packet_size: replay_packet_size,
+                ..
+            } => {
+                *replay_packet_size = packet_size;
+            }
         }
     }
 
@@ -308,34 +391,21 @@ impl CmsisDapDevice {
     /// Check if SWO streaming is supported by this device.
     pub(super) fn swo_streaming_supported(&self) -> bool {
         match self {
-            CmsisDapDevice::V1 { .. } => false,
+            CmsisDapDevice::V1 { .. } | CmsisDapDevice::Replay { .. } => false,
             CmsisDapDevice::V2 { swo_ep, .. } => swo_ep.is_some(),
         }
     }
 
-    /// Read from the SWO streaming endpoint.
+    /// Start receiving from the SWO streaming endpoint in the background.
     ///
     /// Returns SWOModeNotAvailable if this device does not support SWO streaming.
-    ///
-    /// On timeout, returns a zero-length buffer.
-    pub(super) fn read_swo_stream(&self, timeout: Duration) -> Result<Vec<u8>, CmsisDapError> {
+    pub(super) fn start_swo_stream(&self) -> Result<SwoStream, CmsisDapError> {
         match self {
-            CmsisDapDevice::V1 { .. } => Err(CmsisDapError::SwoModeNotAvailable),
+            CmsisDapDevice::V1 { .. } | CmsisDapDevice::Replay { .. } => {
+                Err(CmsisDapError::SwoModeNotAvailable)
+            }
             CmsisDapDevice::V2 { handle, swo_ep, .. } => match swo_ep {
-                Some((ep, len)) => {
-                    let mut buf = vec![0u8; *len];
-                    match handle.read_bulk(*ep, &mut buf, timeout) {
-                        Ok(n) => {
-                            buf.truncate(n);
-                            Ok(buf)
-                        }
-                        Err(e) if e.kind() == ErrorKind::TimedOut => {
-                            buf.truncate(0);
-                            Ok(buf)
-                        }
-                        Err(e) => Err(CmsisDapError::SwoReadError(e)),
-                    }
-                }
+                Some((ep, len)) => Ok(SwoStream::start(handle.clone(), *ep, *len)),
                 None => Err(CmsisDapError::SwoModeNotAvailable),
             },
         }
@@ -423,21 +493,81 @@ pub(crate) fn send_command<Req: Request>(
     })
 }
 
+/// Sends `requests` to the probe, with up to `queue_depth` requests in flight.
+///
+/// The probe buffers up to `packet_count` commands, so sending the next request
+/// before the response to the previous one has been received hides the latency
+/// of the USB round trip. The responses are returned in the order of the requests.
+pub(crate) fn send_commands<Req: Request>(
+    device: &mut CmsisDapDevice,
+    requests: &[Req],
+    queue_depth: usize,
+) -> Result<Vec<Req::Response>, CmsisDapError> {
+    let mut responses = Vec::with_capacity(requests.len());
+    let mut sent = 0;
+    while responses.len() < requests.len() {
+        let result = send_commands_inner(device, requests, queue_depth, &mut sent, &mut responses);
+        if let Err(source) = result {
+            // Discard the responses to the requests which are still in flight,
+            // so the next command is synchronised with its response again.
+            if sent > responses.len() + 1 {
+                device.drain();
+            }
+            return Err(CmsisDapError::Send {
+                command_id: Req::COMMAND_ID,
+                source,
+            });
+        }
+    }
+
+    Ok(responses)
+}
+
+fn send_commands_inner<Req: Request>(
+    device: &mut CmsisDapDevice,
+    requests: &[Req],
+    queue_depth: usize,
+    sent: &mut usize,
+    responses: &mut Vec<Req::Response>,
+) -> Result<(), SendError> {
+    // Fill the queue of the probe, then send the next request whenever a response is received.
+    while *sent < requests.len() && *sent - responses.len() < queue_depth.max(1) {
+        write_request(device, &requests[*sent])?;
+        *sent += 1;
+    }
+
+    let response = read_response(device, &requests[responses.len()])?;
+    responses.push(response);
+    Ok(())
+}
+
 fn send_command_inner<Req: Request>(
     device: &mut CmsisDapDevice,
     request: &Req,
 ) -> Result<Req::Response, SendError> {
-    // Size the buffer for the maximum packet size.
-    // On v1, we always send this full-sized report, while
-    // on v2 we can truncate to just the required data.
-    // Add one byte for HID report ID.
-    let buffer_len: usize = match device {
+    write_request(device, request)?;
+    read_response(device, request)
+}
+
+/// Size of the buffer for the maximum packet size, plus one byte for the HID report ID.
+fn buffer_len(device: &CmsisDapDevice) -> usize {
+    match device {
         CmsisDapDevice::V1 { report_size, .. } => *report_size + 1,
         CmsisDapDevice::V2 {
             max_packet_size, ..
         } => *max_packet_size + 1,
-    };
-    let mut buffer = vec![0; buffer_len];
+        CmsisDapDevice::Replay { packet_size, .. } => *packet_size + 1,
+    }
+}
+
+fn write_request<Req: Request>(
+    device: &mut CmsisDapDevice,
+    request: &Req,
+) -> Result<(), SendError> {
+    // Size the buffer for the maximum packet size.
+    // On v1, we always send this full-sized report, while
+    // on v2 we can truncate to just the required data.
+    let mut buffer = vec![0; buffer_len(device)];
 
     // Leave byte 0 as the HID report, and write the command and request to the buffer.
     buffer[1] = Req::COMMAND_ID as u8;
@@ -455,6 +585,15 @@ fn send_command_inner<Req: Request>(
     let _ = device.write(&buffer[..size])?;
     trace_buffer("Transmit buffer", &buffer[..size]);
 
+    Ok(())
+}
+
+fn read_response<Req: Request>(
+    device: &mut CmsisDapDevice,
+    request: &Req,
+) -> Result<Req::Response, SendError> {
+    let mut buffer = vec![0; buffer_len(device)];
+
     // Read back response.
     let bytes_read = device.read(&mut buffer)?;
     let response_data = &buffer[..bytes_read];
@@ -4,6 +4,7 @@ use std::iter;
 
 use super::{CommandId, Request, SendError};
 use crate::architecture::arm::RegisterAddress;
+use crate::probe::swd_trace::{SwdTraceRecorder, data_parity, swd_request};
 use scroll::{LE, Pread, Pwrite};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -237,6 +238,36 @@ impl Request for TransferRequest {
     }
 }
 
+impl TransferRequest {
+    /// Logs the transfers which were executed by the probe to the SWD trace.
+    pub(crate) fn trace(&self, response: &TransferResponse, trace: &mut SwdTraceRecorder) {
+        let last = &response.last_transfer_response;
+        let count = response.transfers.len();
+
+        for (i, (request, transfer)) in self.transfers.iter().zip(&response.transfers).enumerate() {
+            let is_last = i + 1 == count;
+            let ack = if is_last { last.ack } else { Ack::Ok };
+            let read = request.RnW == RW::R;
+
+            let data = match ack {
+                Ack::Ok if read => transfer.data,
+                Ack::Ok => request.data,
+                _ => None,
+            };
+            // The probe checks the parity of reads, and reports a mismatch as protocol error.
+            let parity_error = read && is_last && last.protocol_error;
+            let parity = data.map(|data| data_parity(data) != parity_error);
+
+            trace.transfer(
+                swd_request(request.APnDP, read, request.A2, request.A3),
+                ack as u8,
+                data,
+                parity,
+            );
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Ack {
     /// TODO: ??????????????????????? Docs are weird?
@@ -341,7 +372,7 @@ impl Request for TransferBlockRequest {
         }
 
         Ok(TransferBlockResponse {
-            _transfer_count: transfer_count,
+            transfer_count,
             transfer_response,
             transfer_data: data,
         })
@@ -349,6 +380,29 @@ impl Request for TransferBlockRequest {
 }
 
 impl TransferBlockRequest {
+    /// Logs the transfers which were executed by the probe to the SWD trace.
+    pub(crate) fn trace(&self, response: &TransferBlockResponse, trace: &mut SwdTraceRecorder) {
+        let access = self.transfer_request;
+        let read = access.r_n_w == RW::R;
+        let request = swd_request(access.ap_n_dp, read, access.a2, access.a3);
+
+        let executed = usize::from(response.transfer_count);
+        let data = if read {
+            &response.transfer_data
+        } else {
+            &self.transfer_data
+        };
+        for &data in data.iter().take(executed) {
+            trace.transfer(request, Ack::Ok as u8, Some(data), Some(data_parity(data)));
+        }
+
+        // The transfer which failed, if the block was aborted.
+        let ack = response.transfer_response & 0b111;
+        if ack != Ack::Ok as u8 && executed < usize::from(self.transfer_count) {
+            trace.transfer(request, ack, None, None);
+        }
+    }
+
     pub(crate) fn write_request(address: RegisterAddress, data: Vec<u32>) -> Self {
         let inner = InnerTransferBlockRequest {
             ap_n_dp: address.is_ap(),
@@ -402,7 +456,89 @@ impl InnerTransferBlockRequest {
 
 #[derive(Debug)]
 pub(crate) struct TransferBlockResponse {
-    _transfer_count: u16,
+    /// The number of transfers which were executed.
+    pub transfer_count: u16,
     pub transfer_response: u8,
     pub transfer_data: Vec<u32>,
 }
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::architecture::arm::dp::{Ctrl, DPIDR, DpRegister, RdBuff};
+    use crate::probe::swd_trace::load_swd_trace;
+
+    /// A trace file in memory.
+    #[derive(Clone, Default)]
+    struct TraceFile(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for TraceFile {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn record(
+        trace: impl FnOnce(&mut SwdTraceRecorder),
+    ) -> Vec<(u8, u8, Option<u32>, Option<bool>)> {
+        let file = TraceFile::default();
+        let mut recorder = SwdTraceRecorder::new(file.clone());
+        recorder.begin().unwrap();
+        trace(&mut recorder);
+        recorder.flush();
+
+        let bytes = file.0.lock().unwrap().clone();
+        load_swd_trace(&bytes[..])
+            .unwrap()
+            .into_iter()
+            .map(|t| (t.request, t.ack, t.data, t.parity))
+            .collect()
+    }
+
+    #[test]
+    fn trace_transfers() {
+        let mut request = TransferRequest::read(DPIDR::ADDRESS);
+        request.add_write(Ctrl::ADDRESS.into(), 0x5000_0000);
+        request.add_read(RdBuff::ADDRESS.into());
+
+        // The write is answered with WAIT, the last read is not executed.
+        let response = request
+            .parse_response(&[2, 0x02, 0x77, 0x14, 0xA0, 0x2B])
+            .unwrap();
+
+        assert_eq!(
+            record(|trace| request.trace(&response, trace)),
+            [
+                (0xA5, 0b001, Some(0x2BA0_1477), Some(false)),
+                (0xA9, 0b010, None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn trace_block_transfers() {
+        let request = TransferBlockRequest::write_request(RdBuff::ADDRESS.into(), vec![1, 3, 7]);
+
+        // The second write is answered with FAULT.
+        let response = TransferBlockResponse {
+            transfer_count: 1,
+            transfer_response: 0x04,
+            transfer_data: Vec::new(),
+        };
+
+        assert_eq!(
+            record(|trace| request.trace(&response, trace)),
+            [
+                (0x99, 0b001, Some(1), Some(true)),
+                (0x99, 0b100, None, None)
+            ]
+        );
+    }
+}
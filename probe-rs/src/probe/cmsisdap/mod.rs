@@ -1,5 +1,7 @@
 //! CMSIS-DAP probe implementation.
 mod commands;
+mod queue;
+mod swo_stream;
 mod tools;
 
 use crate::{
@@ -9,7 +11,7 @@ use crate::{
             ArmCommunicationInterface, ArmDebugInterface, ArmError, DapError, Pins, RawDapAccess,
             RegisterAddress, SwoAccess, SwoConfig, SwoMode,
             communication_interface::DapProbe,
-            dp::{Abort, Ctrl, DpRegister},
+            dp::{Abort, Ctrl, DpRegister, FaultRecovery, FaultRecoveryAccess},
             sequences::ArmDebugSequence,
             swo::poll_interval_from_buf_size,
         },
@@ -23,11 +25,17 @@ use crate::{
     },
     probe::{
         AutoImplementJtagAccess, BatchCommand, DebugProbe, DebugProbeError, DebugProbeInfo,
-        DebugProbeSelector, JtagAccess, JtagDriverState, ProbeFactory, WireProtocol,
+        DebugProbeSelector, FirmwareInfo, FirmwareStatus, JtagAccess, JtagDriverState,
+        ProbeFactory, WireProtocol,
         cmsisdap::commands::{
             CmsisDapError, RequestError,
-            general::info::{CapabilitiesCommand, PacketCountCommand, SWOTraceBufferSizeCommand},
+            general::info::{
+                CapabilitiesCommand, FirmwareVersionCommand, PacketCountCommand,
+                ProductFirmwareVersionCommand, SWOTraceBufferSizeCommand,
+            },
         },
+        swd_trace::SwdTraceRecorder,
+        trace::{ProbeTrace, TraceRecorder, TraceReplay},
     },
 };
 
@@ -62,10 +70,16 @@ use commands::{
 };
 use probe_rs_target::ScanChainElement;
 
-use std::{fmt::Write, sync::Arc, time::Duration};
+use std::{
+    fmt::Write,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use bitvec::prelude::*;
 
+use queue::QueueDepth;
+
 use super::common::{ScanChainError, extract_idcodes, extract_ir_lengths};
 
 /// A factory for creating [`CmsisDap`] probes.
@@ -102,16 +116,21 @@ pub struct CmsisDap {
     capabilities: Capabilities,
     swo_buffer_size: Option<usize>,
     swo_active: bool,
-    swo_streaming: bool,
+    /// Receives the SWO data, if SWO is streamed from a dedicated endpoint.
+    swo_stream: Option<swo_stream::SwoStream>,
     connected: bool,
 
     /// Speed in kHz
     speed_khz: u32,
 
     batch: Vec<BatchCommand>,
+    queue_depth: QueueDepth,
 
     jtag_state: JtagDriverState,
     jtag_buffer: JtagBuffer,
+
+    /// Log of the SWD transfers, if enabled.
+    swd_trace: Option<SwdTraceRecorder>,
 }
 
 impl std::fmt::Debug for CmsisDap {
@@ -120,10 +139,11 @@ impl std::fmt::Debug for CmsisDap {
             .field("protocol", &self.protocol)
             .field("packet_size", &self.packet_size)
             .field("packet_count", &self.packet_count)
+            .field("queue_depth", &self.queue_depth)
             .field("capabilities", &self.capabilities)
             .field("swo_buffer_size", &self.swo_buffer_size)
             .field("swo_active", &self.swo_active)
-            .field("swo_streaming", &self.swo_streaming)
+            .field("swo_streaming", &self.swo_stream.is_some())
             .field("speed_khz", &self.speed_khz)
             .finish()
     }
@@ -160,15 +180,25 @@ impl CmsisDap {
             capabilities: caps,
             swo_buffer_size,
             swo_active: false,
-            swo_streaming: false,
+            swo_stream: None,
             connected: false,
             speed_khz: 1_000,
             batch: Vec::new(),
+            queue_depth: QueueDepth::new(packet_count),
+            swd_trace: None,
             jtag_state: JtagDriverState::default(),
             jtag_buffer: JtagBuffer::new(packet_size - 1),
         })
     }
 
+    /// Opens a recorded probe trace, which is played back instead of talking to a probe.
+    pub(crate) fn replay(trace: ProbeTrace) -> Result<Self, DebugProbeError> {
+        Self::new_from_device(CmsisDapDevice::Replay {
+            replay: TraceReplay::new(trace),
+            packet_size: 64,
+        })
+    }
+
     /// Set maximum JTAG/SWD clock frequency to use, in Hz.
     ///
     /// The actual clock frequency used by the device might be lower.
@@ -431,76 +461,14 @@ impl CmsisDap {
         })
     }
 
-    /// Read the CTRL register from the currently selected debug port.
-    ///
-    /// According to the ARM specification, this *should* never fail.
-    /// In practice, it can unfortunately happen.
-    ///
-    /// To avoid an endless recursion in this cases, this function is provided
-    /// as an alternative to [`Self::process_batch()`]. This function will return any errors,
-    /// and not retry any transfers.
-    fn read_ctrl_register(&mut self) -> Result<Ctrl, ArmError> {
-        let response =
-            commands::send_command(&mut self.device, &TransferRequest::read(Ctrl::ADDRESS))
-                .map_err(DebugProbeError::from)?;
-
-        // We can assume that the single transfer is always executed,
-        // no need to check here.
-
-        if response.last_transfer_response.protocol_error {
-            // TODO: What does this protocol error mean exactly?
-            //       Should be verified in CMSIS-DAP spec
-            Err(DapError::Protocol(
-                self.protocol
-                    .expect("A wire protocol should have been selected by now"),
-            )
-            .into())
-        } else {
-            if response.last_transfer_response.ack != Ack::Ok {
-                tracing::debug!(
-                    "Error reading debug port CTRL register: {:?}. This should never fail!",
-                    response.last_transfer_response.ack
-                );
-            }
-
-            match response.last_transfer_response.ack {
-                Ack::Ok => {
-                    Ok(Ctrl(response.transfers[0].data.expect(
-                        "CMSIS-DAP probe should always return data for a read.",
-                    )))
-                }
-                Ack::Wait => Err(DapError::WaitResponse.into()),
-                Ack::Fault => Err(DapError::FaultResponse.into()),
-                Ack::NoAck => Err(DapError::NoAcknowledge.into()),
-            }
+    /// Logs SWD transfers with `trace`, if the SWD transfers are recorded.
+    fn trace_swd(&mut self, trace: impl FnOnce(&mut SwdTraceRecorder)) {
+        if self.protocol != Some(WireProtocol::Swd) {
+            return;
         }
-    }
-
-    fn write_abort(&mut self, abort: Abort) -> Result<(), ArmError> {
-        let response = commands::send_command(
-            &mut self.device,
-            &TransferRequest::write(Abort::ADDRESS, abort.into()),
-        )
-        .map_err(DebugProbeError::from)?;
-
-        // We can assume that the single transfer is always executed,
-        // no need to check here.
-
-        if response.last_transfer_response.protocol_error {
-            // TODO: What does this protocol error mean exactly?
-            //       Should be verified in CMSIS-DAP spec
-            Err(DapError::Protocol(
-                self.protocol
-                    .expect("A wire protocol should have been selected by now"),
-            )
-            .into())
-        } else {
-            match response.last_transfer_response.ack {
-                Ack::Ok => Ok(()),
-                Ack::Wait => Err(DapError::WaitResponse.into()),
-                Ack::Fault => Err(DapError::FaultResponse.into()),
-                Ack::NoAck => Err(DapError::NoAcknowledge.into()),
-            }
+        if let Some(recorder) = self.swd_trace.as_mut() {
+            trace(recorder);
+            recorder.flush();
         }
     }
 
@@ -540,6 +508,7 @@ impl CmsisDap {
 
             let response = commands::send_command(&mut self.device, &transfers)
                 .map_err(DebugProbeError::from)?;
+            self.trace_swd(|trace| transfers.trace(&response, trace));
 
             let count = response.transfers.len();
 
@@ -579,21 +548,10 @@ impl CmsisDap {
                         batch.len()
                     );
 
-                    // To avoid a potential endless recursion,
-                    // call a separate function to read the ctrl register,
-                    // which doesn't use the batch API.
-                    let ctrl = self.read_ctrl_register()?;
-
-                    tracing::trace!("Ctrl/Stat register value is: {:?}", ctrl);
-
-                    if ctrl.sticky_err() {
-                        // Clear sticky error flags.
-                        self.write_abort({
-                            let mut abort = Abort(0);
-                            abort.set_stkerrclr(ctrl.sticky_err());
-                            abort
-                        })?;
-                    }
+                    // To avoid a potential endless recursion, the fault recovery
+                    // reads the ctrl register and clears the errors without
+                    // using the batch API.
+                    FaultRecovery::new().recover(self)?;
 
                     let successful = count.saturating_sub(1);
                     tracing::trace!("draining {:?} and retries left {:?}", successful, retry);
@@ -620,6 +578,52 @@ impl CmsisDap {
         Err(DapError::FaultResponse.into())
     }
 
+    /// Send block transfer requests, queueing as many of them in the probe as
+    /// [`Self::queue_depth`] allows.
+    fn send_block_requests(
+        &mut self,
+        requests: &[TransferBlockRequest],
+    ) -> Result<Vec<TransferBlockResponse>, ArmError> {
+        let depth = self.queue_depth.get();
+        let start = Instant::now();
+        let responses = match commands::send_commands(&mut self.device, requests, depth) {
+            Ok(responses) => responses,
+            Err(error) => {
+                if depth > 1 {
+                    self.queue_depth.failed();
+                }
+                return Err(DebugProbeError::from(error).into());
+            }
+        };
+        self.queue_depth.update(requests.len(), start.elapsed());
+        self.trace_swd(|trace| {
+            for (request, response) in requests.iter().zip(&responses) {
+                request.trace(response, trace);
+            }
+        });
+
+        for (i, (request, response)) in requests.iter().zip(&responses).enumerate() {
+            tracing::debug!(
+                "Transfer block: chunk={}, len={} bytes",
+                i,
+                request.transfer_count * 4
+            );
+
+            if response.transfer_response != 1 {
+                return Err(DebugProbeError::from(CmsisDapError::ErrorResponse(
+                    RequestError::BlockTransfer {
+                        dap_index: request.dap_index,
+                        transfer_count: request.transfer_count,
+                        transfer_request: request.transfer_request,
+                    },
+                ))
+                .into());
+            }
+        }
+
+        Ok(responses)
+    }
+
     /// Add a BatchCommand to our current batch.
     ///
     /// If the BatchCommand is a Read, this will immediately process the batch
@@ -634,8 +638,8 @@ impl CmsisDap {
 
         // We always immediately process any reads, which means there will never
         // be more than one read in a batch. We also process whenever the batch
-        // is as long as can fit in one packet.
-        let max_writes = (self.packet_size as usize - 3) / (1 + 4);
+        // is as long as can fit in one packet, or as the transfer count allows.
+        let max_writes = ((self.packet_size as usize - 3) / (1 + 4)).min(u8::MAX as usize);
         if command_is_read || self.batch.len() == max_writes {
             self.process_batch()
         } else {
@@ -799,6 +803,81 @@ impl CmsisDap {
     }
 }
 
+impl FaultRecoveryAccess for CmsisDap {
+    /// Read the CTRL register from the currently selected debug port.
+    ///
+    /// According to the ARM specification, this *should* never fail.
+    /// In practice, it can unfortunately happen.
+    ///
+    /// To avoid an endless recursion in this cases, this function is provided
+    /// as an alternative to [`Self::process_batch()`]. This function will return any errors,
+    /// and not retry any transfers.
+    fn read_ctrl_stat(&mut self) -> Result<Ctrl, ArmError> {
+        let request = TransferRequest::read(Ctrl::ADDRESS);
+        let response =
+            commands::send_command(&mut self.device, &request).map_err(DebugProbeError::from)?;
+        self.trace_swd(|trace| request.trace(&response, trace));
+
+        // We can assume that the single transfer is always executed,
+        // no need to check here.
+
+        if response.last_transfer_response.protocol_error {
+            // TODO: What does this protocol error mean exactly?
+            //       Should be verified in CMSIS-DAP spec
+            Err(DapError::Protocol(
+                self.protocol
+                    .expect("A wire protocol should have been selected by now"),
+            )
+            .into())
+        } else {
+            if response.last_transfer_response.ack != Ack::Ok {
+                tracing::debug!(
+                    "Error reading debug port CTRL register: {:?}. This should never fail!",
+                    response.last_transfer_response.ack
+                );
+            }
+
+            match response.last_transfer_response.ack {
+                Ack::Ok => {
+                    Ok(Ctrl(response.transfers[0].data.expect(
+                        "CMSIS-DAP probe should always return data for a read.",
+                    )))
+                }
+                Ack::Wait => Err(DapError::WaitResponse.into()),
+                Ack::Fault => Err(DapError::FaultResponse.into()),
+                Ack::NoAck => Err(DapError::NoAcknowledge.into()),
+            }
+        }
+    }
+
+    fn write_abort(&mut self, abort: Abort) -> Result<(), ArmError> {
+        let request = TransferRequest::write(Abort::ADDRESS, abort.into());
+        let response =
+            commands::send_command(&mut self.device, &request).map_err(DebugProbeError::from)?;
+        self.trace_swd(|trace| request.trace(&response, trace));
+
+        // We can assume that the single transfer is always executed,
+        // no need to check here.
+
+        if response.last_transfer_response.protocol_error {
+            // TODO: What does this protocol error mean exactly?
+            //       Should be verified in CMSIS-DAP spec
+            Err(DapError::Protocol(
+                self.protocol
+                    .expect("A wire protocol should have been selected by now"),
+            )
+            .into())
+        } else {
+            match response.last_transfer_response.ack {
+                Ack::Ok => Ok(()),
+                Ack::Wait => Err(DapError::WaitResponse.into()),
+                Ack::Fault => Err(DapError::FaultResponse.into()),
+                Ack::NoAck => Err(DapError::NoAcknowledge.into()),
+            }
+        }
+    }
+}
+
 impl DebugProbe for CmsisDap {
     fn get_name(&self) -> &str {
         "CMSIS-DAP"
@@ -821,6 +900,18 @@ impl DebugProbe for CmsisDap {
         Ok(speed_khz)
     }
 
+    fn firmware_info(&mut self) -> Result<Option<FirmwareInfo>, DebugProbeError> {
+        let protocol_version =
+            commands::send_command(&mut self.device, &FirmwareVersionCommand {})?;
+        // Older probes don't know the ID, and may reject it instead of returning an empty string.
+        let product_version =
+            commands::send_command(&mut self.device, &ProductFirmwareVersionCommand {})
+                .ok()
+                .flatten();
+
+        Ok(firmware_info(protocol_version, product_version))
+    }
+
     /// Enters debug mode.
     #[tracing::instrument(skip(self))]
     fn attach(&mut self) -> Result<(), DebugProbeError> {
@@ -955,6 +1046,36 @@ impl DebugProbe for CmsisDap {
         Some(self)
     }
 
+    fn record_swd_trace(&mut self, mut recorder: SwdTraceRecorder) -> Result<(), DebugProbeError> {
+        recorder.begin().map_err(|error| {
+            DebugProbeError::Other(format!("Failed to record SWD trace: {error}"))
+        })?;
+        self.swd_trace = Some(recorder);
+        Ok(())
+    }
+
+    fn record_trace(&mut self, mut recorder: TraceRecorder) -> Result<(), DebugProbeError> {
+        recorder
+            .begin(self.get_name())
+            .map_err(|error| DebugProbeError::Other(format!("Failed to record trace: {error}")))?;
+        if !self.device.record_trace(recorder) {
+            return Err(DebugProbeError::CommandNotSupportedByProbe {
+                command_name: "record_trace",
+            });
+        }
+
+        // A replay opens the trace like a probe, so the trace starts with the same
+        // commands which are sent when opening the probe.
+        self.device.find_packet_size()?;
+        commands::send_command(&mut self.device, &PacketCountCommand {})?;
+        commands::send_command(&mut self.device, &CapabilitiesCommand {})?;
+        if self.swo_buffer_size.is_some() {
+            commands::send_command(&mut self.device, &SWOTraceBufferSizeCommand {})?;
+        }
+
+        Ok(())
+    }
+
     fn has_riscv_interface(&self) -> bool {
         // This probe is intended for RISC-V.
         true
@@ -1010,39 +1131,21 @@ impl RawDapAccess for CmsisDap {
     ) -> Result<(), ArmError> {
         self.process_batch()?;
 
-        // the overhead for a single packet is 6 bytes
-        //
-        // [0]: HID overhead
-        // [1]: Category
-        // [2]: DAP Index
-        // [3]: Len 1
-        // [4]: Len 2
-        // [5]: Request type
+        // The request has 5 bytes of overhead:
         //
+        // [0]: Command
+        // [1]: DAP Index
+        // [2]: Len 1
+        // [3]: Len 2
+        // [4]: Request type
+        let max_packet_size_words = (self.packet_size as usize - 5) / 4;
 
-        let max_packet_size_words = (self.packet_size - 6) / 4;
+        let requests = values
+            .chunks(max_packet_size_words)
+            .map(|chunk| TransferBlockRequest::write_request(address, Vec::from(chunk)))
+            .collect::<Vec<_>>();
 
-        let data_chunk_len = max_packet_size_words as usize;
-
-        for (i, chunk) in values.chunks(data_chunk_len).enumerate() {
-            let request = TransferBlockRequest::write_request(address, Vec::from(chunk));
-
-            tracing::debug!("Transfer block: chunk={}, len={} bytes", i, chunk.len() * 4);
-
-            let resp: TransferBlockResponse = commands::send_command(&mut self.device, &request)
-                .map_err(DebugProbeError::from)?;
-
-            if resp.transfer_response != 1 {
-                return Err(DebugProbeError::from(CmsisDapError::ErrorResponse(
-                    RequestError::BlockTransfer {
-                        dap_index: request.dap_index,
-                        transfer_count: request.transfer_count,
-                        transfer_request: request.transfer_request,
-                    },
-                ))
-                .into());
-            }
-        }
+        self.send_block_requests(&requests)?;
 
         Ok(())
     }
@@ -1054,40 +1157,22 @@ impl RawDapAccess for CmsisDap {
     ) -> Result<(), ArmError> {
         self.process_batch()?;
 
-        // the overhead for a single packet is 6 bytes
-        //
-        // [0]: HID overhead
-        // [1]: Category
-        // [2]: DAP Index
-        // [3]: Len 1
-        // [4]: Len 2
-        // [5]: Request type
+        // The response has 4 bytes of overhead:
         //
-
-        let max_packet_size_words = (self.packet_size - 6) / 4;
-
-        let data_chunk_len = max_packet_size_words as usize;
-
-        for (i, chunk) in values.chunks_mut(data_chunk_len).enumerate() {
-            let request = TransferBlockRequest::read_request(address, chunk.len() as u16);
-
-            tracing::debug!("Transfer block: chunk={}, len={} bytes", i, chunk.len() * 4);
-
-            let resp: TransferBlockResponse = commands::send_command(&mut self.device, &request)
-                .map_err(DebugProbeError::from)?;
-
-            if resp.transfer_response != 1 {
-                return Err(DebugProbeError::from(CmsisDapError::ErrorResponse(
-                    RequestError::BlockTransfer {
-                        dap_index: request.dap_index,
-                        transfer_count: request.transfer_count,
-                        transfer_request: request.transfer_request,
-                    },
-                ))
-                .into());
-            }
-
-            chunk.clone_from_slice(&resp.transfer_data[..]);
+        // [0]: Command
+        // [1]: Len 1
+        // [2]: Len 2
+        // [3]: Response
+        let max_packet_size_words = (self.packet_size as usize - 4) / 4;
+
+        let requests = values
+            .chunks(max_packet_size_words)
+            .map(|chunk| TransferBlockRequest::read_request(address, chunk.len() as u16))
+            .collect::<Vec<_>>();
+
+        let responses = self.send_block_requests(&requests)?;
+        for (chunk, response) in values.chunks_mut(max_packet_size_words).zip(responses) {
+            chunk.clone_from_slice(&response.transfer_data[..]);
         }
 
         Ok(())
@@ -1205,18 +1290,19 @@ impl SwoAccess for CmsisDap {
 
         // Stop any ongoing trace
         self.stop_swo_capture()?;
+        self.swo_stream = None;
 
         // Set transport. If the dedicated endpoint is available and we have opened
         // the probe in V2 mode and it has an SWO endpoint, request that, otherwise
         // request the DAP_SWO_Data polling mode.
-        if caps.swo_streaming_trace_implemented && self.device.swo_streaming_supported() {
+        let streaming =
+            caps.swo_streaming_trace_implemented && self.device.swo_streaming_supported();
+        if streaming {
             tracing::debug!("Starting SWO capture with streaming transport");
             self.set_swo_transport(swo::TransportRequest::WinUsbEndpoint)?;
-            self.swo_streaming = true;
         } else {
             tracing::debug!("Starting SWO capture with polled transport");
             self.set_swo_transport(swo::TransportRequest::DataCommand)?;
-            self.swo_streaming = false;
         }
 
         // Set mode. We've already checked that the requested mode is listed as supported.
@@ -1237,6 +1323,15 @@ impl SwoAccess for CmsisDap {
             );
         }
 
+        // Start receiving before the capture, so no data is lost.
+        if streaming {
+            self.swo_stream = Some(
+                self.device
+                    .start_swo_stream()
+                    .map_err(DebugProbeError::from)?,
+            );
+        }
+
         self.start_swo_capture()?;
 
         self.swo_active = true;
@@ -1246,17 +1341,15 @@ impl SwoAccess for CmsisDap {
     fn disable_swo(&mut self) -> Result<(), ArmError> {
         tracing::debug!("Stopping SWO capture");
         self.stop_swo_capture()?;
+        self.swo_stream = None;
         self.swo_active = false;
         Ok(())
     }
 
     fn read_swo_timeout(&mut self, timeout: Duration) -> Result<Vec<u8>, ArmError> {
         if self.swo_active {
-            if self.swo_streaming {
-                let buffer = self
-                    .device
-                    .read_swo_stream(timeout)
-                    .map_err(DebugProbeError::from)?;
+            if let Some(swo_stream) = &self.swo_stream {
+                let buffer = swo_stream.read(timeout).map_err(DebugProbeError::from)?;
                 tracing::trace!("SWO streaming buffer: {:?}", buffer);
                 Ok(buffer)
             } else {
@@ -1272,7 +1365,8 @@ impl SwoAccess for CmsisDap {
     fn swo_poll_interval_hint(&mut self, config: &SwoConfig) -> Option<Duration> {
         let caps = self.capabilities;
         if caps.swo_streaming_trace_implemented && self.device.swo_streaming_supported() {
-            // Streaming reads block waiting for new data so any polling interval is fine
+            // Streamed data is received in the background and reads block waiting
+            // for new data, so any polling interval is fine
             Some(Duration::from_secs(0))
         } else {
             match self.swo_buffer_size {
@@ -1290,6 +1384,39 @@ impl SwoAccess for CmsisDap {
     }
 }
 
+/// The firmware info of a probe, from the DAP_Info protocol version and product firmware version.
+///
+/// Probes which report the product firmware version implement CMSIS-DAP 2.1 or newer. Otherwise,
+/// only the implemented protocol version is known. Firmware which implements v2 transfers data
+/// in bulk, which is much faster than over HID with v1.
+fn firmware_info(
+    protocol_version: Option<String>,
+    product_version: Option<String>,
+) -> Option<FirmwareInfo> {
+    let non_empty = |version: Option<String>| {
+        version
+            .map(|version| version.trim_end_matches('\0').trim().to_string())
+            .filter(|version| !version.is_empty())
+    };
+
+    if let Some(product_version) = non_empty(product_version) {
+        let mut info = FirmwareInfo::new(product_version);
+        info.status = FirmwareStatus::Supported;
+        return Some(info);
+    }
+
+    let mut info = FirmwareInfo::new(non_empty(protocol_version)?);
+    info.minimum = Some("1.0.0".to_string());
+    info.recommended = Some("2.0.0".to_string());
+    info.status = if info.version.starts_with("1.") {
+        FirmwareStatus::UpdateRecommended
+    } else {
+        FirmwareStatus::Supported
+    };
+
+    Some(info)
+}
+
 impl Drop for CmsisDap {
     fn drop(&mut self) {
         tracing::debug!("Detaching from CMSIS-DAP probe");
@@ -1314,3 +1441,33 @@ impl From<ScanChainError> for CmsisDapError {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn product_firmware_version_is_preferred() {
+        let info = firmware_info(Some("2.1.0\0".to_string()), Some("v0.39\0".to_string())).unwrap();
+
+        assert_eq!(info.version, "v0.39");
+        assert_eq!(info.status, FirmwareStatus::Supported);
+        assert_eq!(info.recommended, None);
+    }
+
+    #[test]
+    fn protocol_version_without_product_firmware_version() {
+        let info = firmware_info(Some("1.10\0".to_string()), None).unwrap();
+        assert_eq!(info.version, "1.10");
+        assert_eq!(info.status, FirmwareStatus::UpdateRecommended);
+
+        let info = firmware_info(Some("2.0.0".to_string()), Some("\0".to_string())).unwrap();
+        assert_eq!(info.version, "2.0.0");
+        assert_eq!(info.status, FirmwareStatus::Supported);
+    }
+
+    #[test]
+    fn no_version() {
+        assert_eq!(firmware_info(None, None), None);
+    }
+}
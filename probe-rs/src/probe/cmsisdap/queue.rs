@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+/// A deeper queue is only kept if it lowers the time per packet by at least 1/`MIN_IMPROVEMENT`.
+const MIN_IMPROVEMENT: u32 = 10;
+
+/// The number of block transfer requests which are sent to the probe before waiting for a response.
+///
+/// The depth starts at a single request, and is raised up to the number of packets the probe can
+/// buffer as long as this lowers the measured time per packet. Some probes report more buffers than
+/// they can handle, so the depth is reset to a single request if a queued transfer fails.
+#[derive(Debug, Clone)]
+pub(crate) struct QueueDepth {
+    depth: usize,
+    max_depth: usize,
+    time_per_packet: Option<Duration>,
+}
+
+impl QueueDepth {
+    pub(crate) fn new(packet_count: u8) -> Self {
+        Self {
+            depth: 1,
+            max_depth: usize::from(packet_count).max(1),
+            time_per_packet: None,
+        }
+    }
+
+    /// The number of requests to send before waiting for a response.
+    pub(crate) fn get(&self) -> usize {
+        self.depth
+    }
+
+    /// Adjusts the depth after `packets` were transferred in `elapsed` time.
+    pub(crate) fn update(&mut self, packets: usize, elapsed: Duration) {
+        // Transfers which do not fill the queue say nothing about its depth.
+        if packets <= self.depth {
+            return;
+        }
+
+        let time_per_packet = elapsed / packets as u32;
+        match self.time_per_packet {
+            Some(previous)
+                if time_per_packet + time_per_packet / MIN_IMPROVEMENT > previous
+                    && self.depth > 1 =>
+            {
+                // The last increase did not help, so stay with the previous depth.
+                self.depth -= 1;
+                self.max_depth = self.depth;
+                tracing::debug!("Using a queue depth of {} packets", self.depth);
+            }
+            _ if self.depth < self.max_depth => {
+                self.depth += 1;
+                self.time_per_packet = Some(time_per_packet);
+            }
+            _ => {}
+        }
+    }
+
+    /// Stops queueing requests after a queued transfer failed.
+    pub(crate) fn failed(&mut self) {
+        if self.depth > 1 {
+            tracing::warn!(
+                "Queued transfer with {} packets failed, no longer queueing transfers",
+                self.depth
+            );
+        }
+        self.depth = 1;
+        self.max_depth = 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn raise_while_faster() {
+        let mut depth = QueueDepth::new(4);
+        assert_eq!(depth.get(), 1);
+
+        depth.update(8, Duration::from_millis(8));
+        assert_eq!(depth.get(), 2);
+        depth.update(8, Duration::from_millis(4));
+        assert_eq!(depth.get(), 3);
+
+        // No improvement, go back to two packets and stay there.
+        depth.update(8, Duration::from_millis(4));
+        assert_eq!(depth.get(), 2);
+        depth.update(8, Duration::from_millis(1));
+        assert_eq!(depth.get(), 2);
+    }
+
+    #[test]
+    fn limited_by_packet_count() {
+        let mut depth = QueueDepth::new(2);
+        depth.update(8, Duration::from_millis(8));
+        depth.update(8, Duration::from_millis(2));
+        assert_eq!(depth.get(), 2);
+
+        // Short transfers are ignored.
+        let mut depth = QueueDepth::new(2);
+        depth.update(1, Duration::from_millis(8));
+        assert_eq!(depth.get(), 1);
+    }
+
+    #[test]
+    fn reset_after_failure() {
+        let mut depth = QueueDepth::new(4);
+        depth.update(8, Duration::from_millis(8));
+        depth.failed();
+        assert_eq!(depth.get(), 1);
+        depth.update(8, Duration::from_millis(1));
+        assert_eq!(depth.get(), 1);
+    }
+}
@@ -0,0 +1,252 @@
+//! Background reception of SWO data from the streaming endpoint of CMSIS-DAP v2 probes.
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use async_io::{Timer, block_on};
+use futures_lite::FutureExt;
+use nusb::transfer::RequestBuffer;
+
+use super::commands::CmsisDapError;
+
+/// The amount of SWO data which is buffered until it is read, 4 MiB or one second at 32 Mbaud.
+const BUFFER_SIZE: usize = 4 * 1024 * 1024;
+
+/// The number of USB transfers which are kept in flight, so the probe can always send data.
+const TRANSFERS_IN_FLIGHT: usize = 16;
+
+/// How often the receive thread checks whether it should stop.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Receives SWO data from the streaming endpoint of the probe, in a background thread.
+///
+/// The thread keeps several USB transfers in flight, so no data is lost while the data is processed, even at
+/// multi-Mbaud rates. The received data is kept in a ring buffer until it is read.
+pub(super) struct SwoStream {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    data_available: Condvar,
+    stop: AtomicBool,
+}
+
+struct State {
+    buffer: SwoBuffer,
+    /// The error which stopped the reception. It is reported by every read once the buffered data
+    /// has been read.
+    error: Option<io::Error>,
+}
+
+impl Shared {
+    /// Stops the reception because of `error`, and wakes up the readers to report it.
+    fn fail(&self, error: io::Error) {
+        tracing::error!("Receiving SWO data failed: {error}");
+        self.state.lock().unwrap().error = Some(error);
+        self.data_available.notify_all();
+    }
+}
+
+impl SwoStream {
+    /// Starts receiving from the endpoint `endpoint` of `handle`, with transfers of `transfer_size` bytes.
+    pub(super) fn start(handle: nusb::Interface, endpoint: u8, transfer_size: usize) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                buffer: SwoBuffer::new(BUFFER_SIZE),
+                error: None,
+            }),
+            data_available: Condvar::new(),
+            stop: AtomicBool::new(false),
+        });
+
+        let thread_shared = shared.clone();
+        let thread = std::thread::Builder::new()
+            .name("CMSIS-DAP SWO".to_string())
+            .spawn(move || receive(&handle, endpoint, transfer_size, &thread_shared))
+            .inspect_err(|error| {
+                shared.fail(io::Error::new(
+                    error.kind(),
+                    format!("Failed to start the SWO receive thread: {error}"),
+                ))
+            })
+            .ok();
+
+        Self { shared, thread }
+    }
+
+    /// Returns all received data, waiting at most `timeout` for data if none was received yet.
+    ///
+    /// Once the reception has failed, the remaining data is returned, followed by the error.
+    pub(super) fn read(&self, timeout: Duration) -> Result<Vec<u8>, CmsisDapError> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.buffer.is_empty() && state.error.is_none() {
+            state = self
+                .shared
+                .data_available
+                .wait_timeout(state, timeout)
+                .unwrap()
+                .0;
+        }
+
+        let (data, dropped) = state.buffer.take();
+        if dropped > 0 {
+            tracing::warn!(
+                "SWO data was not read fast enough, dropped {} bytes",
+                dropped
+            );
+        }
+
+        match &state.error {
+            Some(error) if data.is_empty() => Err(CmsisDapError::SwoReadError(io::Error::new(
+                error.kind(),
+                error.to_string(),
+            ))),
+            _ => Ok(data),
+        }
+    }
+}
+
+impl Drop for SwoStream {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn receive(handle: &nusb::Interface, endpoint: u8, transfer_size: usize, shared: &Shared) {
+    let mut queue = handle.bulk_in_queue(endpoint);
+    for _ in 0..TRANSFERS_IN_FLIGHT {
+        queue.submit(RequestBuffer::new(transfer_size));
+    }
+
+    while !shared.stop.load(Ordering::Relaxed) {
+        // Wake up regularly to check whether the stream was stopped.
+        let completion = block_on(async { Some(queue.next_complete().await) }.or(async {
+            Timer::after(STOP_POLL_INTERVAL).await;
+            None
+        }));
+        let Some(completion) = completion else {
+            continue;
+        };
+
+        if let Err(error) = completion.status {
+            shared.fail(error.into());
+            break;
+        }
+
+        if !completion.data.is_empty() {
+            shared.state.lock().unwrap().buffer.push(&completion.data);
+            shared.data_available.notify_all();
+        }
+        queue.submit(RequestBuffer::reuse(completion.data, transfer_size));
+    }
+
+    queue.cancel_all();
+    while queue.pending() > 0 {
+        let _ = block_on(queue.next_complete());
+    }
+}
+
+/// A ring buffer for the received SWO data, which drops the oldest data when it is full.
+struct SwoBuffer {
+    data: VecDeque<u8>,
+    capacity: usize,
+    dropped: usize,
+}
+
+impl SwoBuffer {
+    fn new(capacity: usize) -> Self {
+        Self {
+            data: VecDeque::new(),
+            capacity,
+            dropped: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn push(&mut self, data: &[u8]) {
+        let overflow = (self.data.len() + data.len()).saturating_sub(self.capacity);
+        if overflow > 0 {
+            let from_buffer = overflow.min(self.data.len());
+            self.data.drain(..from_buffer);
+            self.dropped += overflow;
+        }
+
+        let skip = data.len().saturating_sub(self.capacity);
+        self.data.extend(&data[skip..]);
+    }
+
+    /// Takes the buffered data, and the number of bytes dropped since the last call.
+    fn take(&mut self) -> (Vec<u8>, usize) {
+        let data = self.data.drain(..).collect();
+        (data, std::mem::take(&mut self.dropped))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn buffer() {
+        let mut buffer = SwoBuffer::new(8);
+        buffer.push(&[1, 2, 3]);
+        buffer.push(&[4, 5]);
+        assert_eq!(buffer.take(), (vec![1, 2, 3, 4, 5], 0));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn buffer_overflow() {
+        let mut buffer = SwoBuffer::new(4);
+        buffer.push(&[1, 2, 3]);
+        buffer.push(&[4, 5]);
+        assert_eq!(buffer.take(), (vec![2, 3, 4, 5], 1));
+
+        buffer.push(&[1, 2, 3, 4, 5, 6]);
+        assert_eq!(buffer.take(), (vec![3, 4, 5, 6], 2));
+    }
+
+    #[test]
+    fn read_after_failure() {
+        let stream = SwoStream {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    buffer: SwoBuffer::new(8),
+                    error: None,
+                }),
+                data_available: Condvar::new(),
+                stop: AtomicBool::new(false),
+            }),
+            thread: None,
+        };
+
+        stream.shared.state.lock().unwrap().buffer.push(&[1, 2, 3]);
+        stream
+            .shared
+            .fail(io::Error::new(io::ErrorKind::BrokenPipe, "stall"));
+
+        // The data received before the failure is still returned.
+        assert_eq!(stream.read(Duration::ZERO).unwrap(), vec![1, 2, 3]);
+
+        // Every following read reports the error, instead of waiting for data which never arrives.
+        for _ in 0..2 {
+            let error = stream.read(Duration::from_secs(10)).unwrap_err();
+            assert!(matches!(
+                error,
+                CmsisDapError::SwoReadError(error) if error.kind() == io::ErrorKind::BrokenPipe
+            ));
+        }
+    }
+}
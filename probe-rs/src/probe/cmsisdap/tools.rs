@@ -223,6 +223,7 @@ pub fn open_v2_device(
                         in_ep: eps[1].address(),
                         swo_ep,
                         max_packet_size: eps[1].max_packet_size(),
+                        recorder: None,
                     }));
                 }
                 Err(_) => continue,
@@ -362,6 +363,7 @@ pub fn open_device_from_selector(
                 // common size for CMSIS-DAPv1 HID devices. We'll request the
                 // actual size to use from the probe later.
                 report_size: 64,
+                recorder: None,
             })
         }
         _ => {
@@ -8,7 +8,8 @@ use probe_rs_target::ScanChainElement;
 
 use crate::probe::{
     AutoImplementJtagAccess, BatchExecutionError, ChainParams, CommandQueue, CommandResult,
-    DebugProbeError, DeferredResultSet, JtagAccess, JtagCommand, JtagSequence, RawJtagIo,
+    DebugProbeError, DeferredResultSet, JtagAccess, JtagChainItem, JtagCommand, JtagSequence,
+    RawJtagIo,
 };
 
 pub(crate) fn bits_to_byte(bits: impl IntoIterator<Item = bool>) -> u32 {
@@ -64,6 +65,12 @@ impl std::fmt::Display for IdCode {
     }
 }
 
+impl From<IdCode> for u32 {
+    fn from(idcode: IdCode) -> Self {
+        idcode.0
+    }
+}
+
 impl IdCode {
     /// Returns `true` iff the IDCODE's least significant bit is `1`
     /// and the 7-bit `manufacturer_identity` is set to one of the non-reserved values in the range `[1,126]`.
@@ -544,6 +551,84 @@ fn prepare_write_register(
     shift_dr(protocol, data, len as usize, capture)
 }
 
+/// Scans the JTAG chain for TAPs, their IDCODEs and IR lengths.
+///
+/// The chain parameters must be reset before, so the whole chain is scanned.
+fn detect_jtag_chain(probe: &mut impl RawJtagIo) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+    const MAX_CHAIN: usize = 8;
+
+    probe.reset_jtag_state_machine()?;
+
+    let input = [0xFF; 4 * MAX_CHAIN];
+
+    shift_dr(probe, &input, input.len() * 8, true)?;
+    let response = probe.read_captured_bits()?;
+
+    tracing::debug!("DR: {:?}", response);
+
+    let idcodes = extract_idcodes(&response)?;
+
+    tracing::info!(
+        "JTAG DR scan complete, found {} TAPs. {:?}",
+        idcodes.len(),
+        idcodes
+    );
+
+    tracing::debug!("Scanning JTAG chain for IR lengths");
+
+    // First shift out all ones
+    let input = vec![0xff; idcodes.len()];
+    shift_ir(probe, &input, input.len() * 8, true)?;
+    let response = probe.read_captured_bits()?;
+
+    tracing::debug!("IR scan: {}", response);
+
+    probe.reset_jtag_state_machine()?;
+
+    // Next, shift out same amount of zeros, then ones to make sure the IRs contain BYPASS.
+    let input = std::iter::repeat_n(0, idcodes.len())
+        .chain(input.iter().copied())
+        .collect::<Vec<_>>();
+    shift_ir(probe, &input, input.len() * 8, true)?;
+    let response_zeros = probe.read_captured_bits()?;
+
+    tracing::debug!("IR scan: {}", response_zeros);
+
+    let response = response.as_bitslice();
+    let response = common_sequence(response, response_zeros.as_bitslice());
+
+    tracing::debug!("IR scan: {}", response);
+
+    let ir_lens = extract_ir_lengths(
+        response,
+        idcodes.len(),
+        probe
+            .state()
+            .expected_scan_chain
+            .as_ref()
+            .map(|chain| {
+                chain
+                    .iter()
+                    .filter_map(|s| s.ir_len)
+                    .map(|s| s as usize)
+                    .collect::<Vec<usize>>()
+            })
+            .as_deref(),
+    )?;
+
+    tracing::info!("Found {} TAPs on reset scan", idcodes.len());
+    tracing::debug!("Detected IR lens: {:?}", ir_lens);
+
+    Ok(idcodes
+        .into_iter()
+        .zip(ir_lens)
+        .map(|(idcode, ir_len)| JtagChainItem {
+            idcode,
+            ir_len: ir_len as u8,
+        })
+        .collect())
+}
+
 impl<Probe: AutoImplementJtagAccess> JtagAccess for Probe {
     fn shift_raw_sequence(&mut self, sequence: JtagSequence) -> Result<BitVec, DebugProbeError> {
         self.shift_bits(
@@ -584,83 +669,24 @@ impl<Probe: AutoImplementJtagAccess> JtagAccess for Probe {
             return Ok(self.state().scan_chain.as_slice());
         }
 
-        const MAX_CHAIN: usize = 8;
-
-        self.reset_jtag_state_machine()?;
-
         self.state_mut().chain_params = ChainParams::default();
 
-        let input = [0xFF; 4 * MAX_CHAIN];
-
-        shift_dr(self, &input, input.len() * 8, true)?;
-        let response = self.read_captured_bits()?;
-
-        tracing::debug!("DR: {:?}", response);
-
-        let idcodes = extract_idcodes(&response)?;
-
-        tracing::info!(
-            "JTAG DR scan complete, found {} TAPs. {:?}",
-            idcodes.len(),
-            idcodes
-        );
-
-        tracing::debug!("Scanning JTAG chain for IR lengths");
-
-        // First shift out all ones
-        let input = vec![0xff; idcodes.len()];
-        shift_ir(self, &input, input.len() * 8, true)?;
-        let response = self.read_captured_bits()?;
-
-        tracing::debug!("IR scan: {}", response);
-
-        self.reset_jtag_state_machine()?;
+        let chain = detect_jtag_chain(self)?;
+        self.state_mut().scan_chain = chain.into_iter().map(ScanChainElement::from).collect();
 
-        // Next, shift out same amount of zeros, then ones to make sure the IRs contain BYPASS.
-        let input = std::iter::repeat_n(0, idcodes.len())
-            .chain(input.iter().copied())
-            .collect::<Vec<_>>();
-        shift_ir(self, &input, input.len() * 8, true)?;
-        let response_zeros = self.read_captured_bits()?;
-
-        tracing::debug!("IR scan: {}", response_zeros);
-
-        let response = response.as_bitslice();
-        let response = common_sequence(response, response_zeros.as_bitslice());
-
-        tracing::debug!("IR scan: {}", response);
-
-        let ir_lens = extract_ir_lengths(
-            response,
-            idcodes.len(),
-            self.state()
-                .expected_scan_chain
-                .as_ref()
-                .map(|chain| {
-                    chain
-                        .iter()
-                        .filter_map(|s| s.ir_len)
-                        .map(|s| s as usize)
-                        .collect::<Vec<usize>>()
-                })
-                .as_deref(),
-        )?;
+        Ok(self.state().scan_chain.as_slice())
+    }
 
-        tracing::info!("Found {} TAPs on reset scan", idcodes.len());
-        tracing::debug!("Detected IR lens: {:?}", ir_lens);
+    fn scan_jtag_chain(&mut self) -> Result<Vec<JtagChainItem>, DebugProbeError> {
+        // Scanning needs the whole chain, restore the selected TAP afterwards.
+        let chain_params = std::mem::take(&mut self.state_mut().chain_params);
+        let result = detect_jtag_chain(self);
+        self.state_mut().chain_params = chain_params;
 
-        let chain = idcodes
-            .into_iter()
-            .zip(ir_lens)
-            .map(|(idcode, irlen)| ScanChainElement {
-                ir_len: Some(irlen as u8),
-                name: idcode.map(|i| i.to_string()),
-            })
-            .collect::<Vec<_>>();
+        let chain = result?;
+        self.state_mut().scan_chain = chain.iter().copied().map(ScanChainElement::from).collect();
 
-        self.state_mut().scan_chain = chain;
-
-        Ok(self.state().scan_chain.as_slice())
+        Ok(chain)
     }
 
     fn tap_reset(&mut self) -> Result<(), DebugProbeError> {
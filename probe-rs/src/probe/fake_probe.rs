@@ -2,11 +2,16 @@
 use crate::{
     MemoryInterface, MemoryMappedRegister,
     architecture::arm::{
-        ArmDebugInterface, ArmError, DapAccess, FullyQualifiedApAddress, RawDapAccess,
-        RegisterAddress, SwoAccess,
+        ApAddress, ApV2Address, ArmDebugInterface, ArmError, DapAccess, FullyQualifiedApAddress,
+        RawDapAccess, RegisterAddress, SwoAccess,
         ap::memory_ap::mock::MockMemoryAp,
+        armv7m::{FpCtrl, FpRev1CompX},
         armv8m::Dhcsr,
         communication_interface::{DapProbe, SwdSequence},
+        core::{
+            Dfsr,
+            cortex_m::{Dcrdr, Dcrsr},
+        },
         dp::{DpAddress, DpRegisterAddress},
         memory::{ADIMemoryInterface, ArmMemoryInterface},
         sequences::ArmDebugSequence,
@@ -21,12 +26,20 @@ use object::{
 use probe_rs_target::MemoryRange;
 use std::{
     cell::RefCell,
-    collections::{BTreeSet, VecDeque},
+    collections::{BTreeSet, HashMap, VecDeque},
     fmt::Debug,
+    io::{self, BufRead, BufWriter, Write},
+    ops::Range,
     path::Path,
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
+/// The number of instruction address comparators of the simulated Flash Patch and Breakpoint unit.
+const NUM_BREAKPOINTS: usize = 4;
+
+/// The DCRSR register selector of the program counter.
+const PROGRAM_COUNTER: u32 = 15;
+
 /// This is a mock probe which can be used for mocking things in tests or for dry runs.
 #[expect(clippy::type_complexity)]
 pub struct FakeProbe {
@@ -40,6 +53,9 @@ pub struct FakeProbe {
 
     operations: RefCell<VecDeque<Operation>>,
 
+    /// If set, all DAP register operations are appended to this log.
+    recording: Option<Arc<Mutex<Vec<Operation>>>>,
+
     memory_ap: MockedAp,
 }
 
@@ -68,6 +84,14 @@ impl LoadableSegment {
     }
 }
 
+/// A region of simulated memory, backed by a buffer.
+struct MemoryRegion {
+    range: Range<u64>,
+    data: Vec<u8>,
+    /// Writes to read-only regions are ignored, like writes to flash memory without a flash algorithm.
+    writable: bool,
+}
+
 struct MockCore {
     dhcsr: Dhcsr,
 
@@ -77,6 +101,18 @@ struct MockCore {
     program_binary: Option<Vec<u8>>,
     loadable_segments: Vec<LoadableSegment>,
     endianness: Endianness,
+
+    /// Simulated memory, which takes precedence over the program binary.
+    memory_regions: Vec<MemoryRegion>,
+    /// The base address of the ROM table, reported as the base address of the memory AP.
+    rom_table_base: Option<u64>,
+
+    /// The core registers, by their DCRSR register selector.
+    registers: HashMap<u32, u32>,
+    dcrdr: u32,
+    dfsr: Dfsr,
+    fp_ctrl: FpCtrl,
+    fp_comp: [FpRev1CompX; NUM_BREAKPOINTS],
 }
 
 impl MockCore {
@@ -87,167 +123,337 @@ impl MockCore {
             program_binary: None,
             loadable_segments: Vec::new(),
             endianness: Endianness::Little,
+            memory_regions: Vec::new(),
+            rom_table_base: None,
+            registers: HashMap::new(),
+            dcrdr: 0,
+            dfsr: Dfsr::from(0),
+            // FPB version 1, with `NUM_BREAKPOINTS` instruction address comparators.
+            fp_ctrl: FpCtrl::from((NUM_BREAKPOINTS as u32) << 4),
+            fp_comp: [FpRev1CompX::from(0); NUM_BREAKPOINTS],
         }
     }
-}
 
-impl SwdSequence for &mut MockCore {
-    fn swj_sequence(&mut self, _bit_len: u8, _bits: u64) -> Result<(), DebugProbeError> {
-        todo!()
-    }
+    fn read_byte(&self, address: u64) -> u8 {
+        if let Some(region) = self
+            .memory_regions
+            .iter()
+            .find(|region| region.range.contains(&address))
+        {
+            return region.data[(address - region.range.start) as usize];
+        }
 
-    fn swj_pins(
-        &mut self,
-        _pin_out: u32,
-        _pin_select: u32,
-        _pin_wait: u32,
-    ) -> Result<u32, DebugProbeError> {
-        todo!()
+        let Some(program_binary) = &self.program_binary else {
+            return 0;
+        };
+        self.loadable_segments
+            .iter()
+            .find(|segment| segment.contains(address, 1))
+            .map(|segment| program_binary[segment.load_addr(address) as usize])
+            .unwrap_or(0)
     }
-}
 
-impl MemoryInterface<ArmError> for &mut MockCore {
-    fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), ArmError> {
-        let mut curr_seg: Option<&LoadableSegment> = None;
+    fn read_bytes(&self, address: u64, data: &mut [u8]) {
+        for (offset, value) in data.iter_mut().enumerate() {
+            *value = self.read_byte(address + offset as u64);
+        }
+    }
 
-        for (offset, val) in data.iter_mut().enumerate() {
+    /// Writes to simulated memory. Writes to read-only or unmapped memory are ignored.
+    fn write_bytes(&mut self, address: u64, data: &[u8]) {
+        for (offset, value) in data.iter().enumerate() {
             let address = address + offset as u64;
-            println!("Read {address:#010x} = 0");
-
-            match self.program_binary {
-                Some(ref program_binary) => {
-                    if !curr_seg.is_some_and(|seg| seg.contains(address, 1)) {
-                        curr_seg = self
-                            .loadable_segments
-                            .iter()
-                            .find(|&seg| seg.contains(address, 1));
-                    }
-                    match curr_seg {
-                        Some(seg) => {
-                            *val = program_binary[seg.load_addr(address) as usize];
-                        }
-                        None => *val = 0,
-                    }
+            match self.region_mut(address) {
+                Some(region) if region.writable => {
+                    region.data[(address - region.range.start) as usize] = *value;
                 }
-                None => *val = 0,
+                Some(_) => tracing::debug!("MockCore: Ignoring write to read-only {address:#010x}"),
+                None => tracing::debug!("MockCore: Ignoring write to unmapped {address:#010x}"),
             }
         }
+    }
+
+    /// Loads `data` into simulated memory, even if it is read-only.
+    fn load_bytes(&mut self, address: u64, data: &[u8]) -> Result<(), DebugProbeError> {
+        for (offset, value) in data.iter().enumerate() {
+            let address = address + offset as u64;
+            let region = self.region_mut(address).ok_or_else(|| {
+                DebugProbeError::Other(format!("MockCore: No memory region at {address:#010x}"))
+            })?;
+            region.data[(address - region.range.start) as usize] = *value;
+        }
 
         Ok(())
     }
 
-    fn read_16(&mut self, _address: u64, _data: &mut [u16]) -> Result<(), ArmError> {
-        todo!()
+    fn region_mut(&mut self, address: u64) -> Option<&mut MemoryRegion> {
+        self.memory_regions
+            .iter_mut()
+            .find(|region| region.range.contains(&address))
     }
 
-    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
-        let mut curr_seg: Option<&LoadableSegment> = None;
-
-        for (offset, val) in data.iter_mut().enumerate() {
-            const U32_BYTES: usize = 4;
-            let address = address + (offset * U32_BYTES) as u64;
+    fn word_to_bytes(&self, value: u32) -> [u8; 4] {
+        if self.endianness == Endianness::Little {
+            value.to_le_bytes()
+        } else {
+            value.to_be_bytes()
+        }
+    }
 
-            match address {
-                // DHCSR
-                Dhcsr::ADDRESS_OFFSET => {
-                    let mut dhcsr: u32 = self.dhcsr.into();
+    fn word_from_bytes(&self, bytes: [u8; 4]) -> u32 {
+        if self.endianness == Endianness::Little {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        }
+    }
 
-                    if self.is_halted {
-                        dhcsr |= 1 << 17;
-                    }
+    /// Reads a word, which is either a simulated debug register or memory.
+    fn read_word(&self, address: u64) -> u32 {
+        if let Some(index) = self.fp_comp_index(address) {
+            return self.fp_comp[index].into();
+        }
 
-                    // Always set S_REGRDY, and say that a register value can
-                    // be read.
-                    dhcsr |= 1 << 16;
+        match address {
+            Dhcsr::ADDRESS_OFFSET => {
+                let mut dhcsr: u32 = self.dhcsr.into();
 
-                    *val = dhcsr;
-                    println!("Read  DHCSR: {address:#x} = {val:#x}");
+                if self.is_halted {
+                    dhcsr |= 1 << 17;
                 }
 
-                address => {
-                    println!("Read {address:#010x} = 0");
-
-                    match self.program_binary {
-                        Some(ref program_binary) => {
-                            if !curr_seg.is_some_and(|seg| seg.contains(address, U32_BYTES as u64))
-                            {
-                                curr_seg = self
-                                    .loadable_segments
-                                    .iter()
-                                    .find(|&seg| seg.contains(address, U32_BYTES as u64));
-                            }
-                            match curr_seg {
-                                Some(seg) => {
-                                    let from = seg.load_addr(address) as usize;
-                                    let to = from + U32_BYTES;
-
-                                    let u32_as_bytes: [u8; U32_BYTES] =
-                                        program_binary[from..to].try_into().unwrap();
-
-                                    // Convert to _host_ (native) endianness.
-                                    *val = if self.endianness == Endianness::Little {
-                                        u32::from_le_bytes(u32_as_bytes)
-                                    } else {
-                                        u32::from_be_bytes(u32_as_bytes)
-                                    };
-                                }
-                                None => *val = 0,
-                            }
-                        }
-                        None => *val = 0,
+                // Always set S_REGRDY, and say that a register value can
+                // be read.
+                dhcsr |= 1 << 16;
+
+                println!("Read  DHCSR: {address:#x} = {dhcsr:#x}");
+                dhcsr
+            }
+            Dcrdr::ADDRESS_OFFSET => self.dcrdr,
+            Dfsr::ADDRESS_OFFSET => self.dfsr.into(),
+            FpCtrl::ADDRESS_OFFSET => self.fp_ctrl.into(),
+            address => {
+                let mut bytes = [0; 4];
+                self.read_bytes(address, &mut bytes);
+                self.word_from_bytes(bytes)
+            }
+        }
+    }
+
+    /// Writes a word, which is either a simulated debug register or memory.
+    fn write_word(&mut self, address: u64, word: u32) {
+        if let Some(index) = self.fp_comp_index(address) {
+            self.fp_comp[index] = FpRev1CompX::from(word);
+            return;
+        }
+
+        match address {
+            Dhcsr::ADDRESS_OFFSET => {
+                let dbg_key = (word >> 16) & 0xffff;
+
+                if dbg_key == 0xa05f {
+                    // Mask out dbg key
+                    self.dhcsr = Dhcsr::from(word & 0xffff);
+                    println!("Write DHCSR = {word:#010x}");
+
+                    if self.dhcsr.c_halt() {
+                        self.halt(false);
+                    } else if self.dhcsr.c_debugen() && self.dhcsr.c_step() {
+                        tracing::debug!("MockCore: Single step requested, setting s_halt");
+                        self.step();
+                    } else {
+                        self.resume();
                     }
                 }
             }
+            Dcrsr::ADDRESS_OFFSET => {
+                let register = word & 0x7f;
+                if word & (1 << 16) != 0 {
+                    self.registers.insert(register, self.dcrdr);
+                } else {
+                    self.dcrdr = self.registers.get(&register).copied().unwrap_or(0);
+                }
+            }
+            Dcrdr::ADDRESS_OFFSET => self.dcrdr = word,
+            // The bits of the DFSR are cleared by writing ones.
+            Dfsr::ADDRESS_OFFSET => self.dfsr = Dfsr::from(u32::from(self.dfsr) & !word),
+            FpCtrl::ADDRESS_OFFSET => {
+                // Writes without the key bit are ignored.
+                if word & 0b10 != 0 {
+                    self.fp_ctrl.set_enable(word & 1 != 0);
+                }
+            }
+            address => {
+                println!("Write {address:#010x} = {word:#010x}");
+                let bytes = self.word_to_bytes(word);
+                self.write_bytes(address, &bytes);
+            }
         }
+    }
 
-        Ok(())
+    fn fp_comp_index(&self, address: u64) -> Option<usize> {
+        let offset = address.checked_sub(FpRev1CompX::ADDRESS_OFFSET)?;
+        let index = (offset / 4) as usize;
+        (offset % 4 == 0 && index < NUM_BREAKPOINTS).then_some(index)
     }
 
-    fn read_64(&mut self, _address: u64, _data: &mut [u64]) -> Result<(), ArmError> {
-        todo!()
+    fn program_counter(&self) -> u32 {
+        self.registers.get(&PROGRAM_COUNTER).copied().unwrap_or(0)
     }
 
-    fn write_8(&mut self, _address: u64, _data: &[u8]) -> Result<(), ArmError> {
+    /// The addresses of the enabled hardware breakpoints.
+    fn breakpoints(&self) -> Vec<u32> {
+        if !self.fp_ctrl.enable() {
+            return Vec::new();
+        }
+
+        let mut breakpoints = Vec::new();
+        for comparator in self.fp_comp.iter().filter(|comparator| comparator.enable()) {
+            let address = comparator.comp() << 2;
+            // The REPLACE field selects the halfword(s) of the word at `address`.
+            if comparator.replace() & 0b01 != 0 {
+                breakpoints.push(address);
+            }
+            if comparator.replace() & 0b10 != 0 {
+                breakpoints.push(address | 0b10);
+            }
+        }
+        breakpoints
+    }
+
+    fn halt(&mut self, at_breakpoint: bool) {
+        self.is_halted = true;
+        if at_breakpoint {
+            self.dfsr.set_bkpt(true);
+        } else {
+            self.dfsr.set_halted(true);
+        }
+    }
+
+    /// Executes a single (16-bit) instruction.
+    fn step(&mut self) {
+        let program_counter = self.program_counter().wrapping_add(2);
+        self.registers.insert(PROGRAM_COUNTER, program_counter);
+        self.halt(false);
+    }
+
+    /// The simulated core does not execute instructions. It runs until the next breakpoint after the program counter
+    /// (wrapping around at the end of the address space), or forever if no breakpoint is set.
+    fn resume(&mut self) {
+        self.is_halted = false;
+
+        let program_counter = self.program_counter();
+        let breakpoints = self.breakpoints();
+        let next_breakpoint = breakpoints
+            .iter()
+            .filter(|address| **address > program_counter)
+            .min()
+            .or_else(|| breakpoints.iter().min());
+
+        if let Some(address) = next_breakpoint {
+            tracing::debug!("MockCore: Halting at breakpoint {address:#010x}");
+            self.registers.insert(PROGRAM_COUNTER, *address);
+            self.halt(true);
+        }
+    }
+}
+
+impl SwdSequence for &mut MockCore {
+    fn swj_sequence(&mut self, _bit_len: u8, _bits: u64) -> Result<(), DebugProbeError> {
         todo!()
     }
 
-    fn write_16(&mut self, _address: u64, _data: &[u16]) -> Result<(), ArmError> {
+    fn swj_pins(
+        &mut self,
+        _pin_out: u32,
+        _pin_select: u32,
+        _pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
         todo!()
     }
+}
 
-    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
-        for (i, word) in data.iter().enumerate() {
-            let address = address + (i as u64 * 4);
+impl MemoryInterface<ArmError> for &mut MockCore {
+    fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), ArmError> {
+        self.read_bytes(address, data);
+
+        Ok(())
+    }
 
-            match address {
-                // DHCSR
-                Dhcsr::ADDRESS_OFFSET => {
-                    let dbg_key = (*word >> 16) & 0xffff;
+    fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), ArmError> {
+        for (offset, value) in data.iter_mut().enumerate() {
+            let mut bytes = [0; 2];
+            self.read_bytes(address + (offset * 2) as u64, &mut bytes);
+            *value = if self.endianness == Endianness::Little {
+                u16::from_le_bytes(bytes)
+            } else {
+                u16::from_be_bytes(bytes)
+            };
+        }
 
-                    if dbg_key == 0xa05f {
-                        // Mask out dbg key
-                        self.dhcsr = Dhcsr::from(*word & 0xffff);
-                        println!("Write DHCSR = {word:#010x}");
+        Ok(())
+    }
 
-                        let request_halt = self.dhcsr.c_halt();
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
+        for (offset, value) in data.iter_mut().enumerate() {
+            *value = self.read_word(address + (offset * 4) as u64);
+        }
 
-                        self.is_halted = request_halt;
+        Ok(())
+    }
 
-                        if !self.dhcsr.c_halt() && self.dhcsr.c_debugen() && self.dhcsr.c_step() {
-                            tracing::debug!("MockCore: Single step requested, setting s_halt");
-                            self.is_halted = true;
-                        }
-                    }
-                }
-                _ => println!("Write {address:#010x} = {word:#010x}"),
-            }
+    fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), ArmError> {
+        for (offset, value) in data.iter_mut().enumerate() {
+            let mut bytes = [0; 8];
+            self.read_bytes(address + (offset * 8) as u64, &mut bytes);
+            *value = if self.endianness == Endianness::Little {
+                u64::from_le_bytes(bytes)
+            } else {
+                u64::from_be_bytes(bytes)
+            };
         }
 
         Ok(())
     }
 
-    fn write_64(&mut self, _address: u64, _data: &[u64]) -> Result<(), ArmError> {
-        todo!()
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), ArmError> {
+        self.write_bytes(address, data);
+
+        Ok(())
+    }
+
+    fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), ArmError> {
+        for (offset, value) in data.iter().enumerate() {
+            let bytes = if self.endianness == Endianness::Little {
+                value.to_le_bytes()
+            } else {
+                value.to_be_bytes()
+            };
+            self.write_bytes(address + (offset * 2) as u64, &bytes);
+        }
+
+        Ok(())
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
+        for (offset, word) in data.iter().enumerate() {
+            self.write_word(address + (offset * 4) as u64, *word);
+        }
+
+        Ok(())
+    }
+
+    fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), ArmError> {
+        for (offset, value) in data.iter().enumerate() {
+            let bytes = if self.endianness == Endianness::Little {
+                value.to_le_bytes()
+            } else {
+                value.to_be_bytes()
+            };
+            self.write_bytes(address + (offset * 8) as u64, &bytes);
+        }
+
+        Ok(())
     }
 
     fn flush(&mut self) -> Result<(), ArmError> {
@@ -259,13 +465,13 @@ impl MemoryInterface<ArmError> for &mut MockCore {
     }
 
     fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
-        todo!()
+        Ok(true)
     }
 }
 
 impl ArmMemoryInterface for &mut MockCore {
     fn base_address(&mut self) -> Result<u64, ArmError> {
-        todo!()
+        Ok(self.rom_table_base.unwrap_or(0))
     }
 
     fn fully_qualified_address(&self) -> FullyQualifiedApAddress {
@@ -283,6 +489,7 @@ impl ArmMemoryInterface for &mut MockCore {
     fn update_core_status(&mut self, _state: crate::CoreStatus) {}
 }
 
+/// A DAP register operation, which a [`FakeProbe`] expects or has recorded.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Operation {
     ReadRawApRegister {
@@ -290,6 +497,192 @@ pub enum Operation {
         address: u64,
         result: u32,
     },
+    WriteRawApRegister {
+        ap: FullyQualifiedApAddress,
+        address: u64,
+        value: u32,
+    },
+    ReadRawDpRegister {
+        dp: DpAddress,
+        address: DpRegisterAddress,
+        result: u32,
+    },
+    WriteRawDpRegister {
+        dp: DpAddress,
+        address: DpRegisterAddress,
+        value: u32,
+    },
+}
+
+/// The first line of a file with recorded [`Operation`]s.
+const OPERATIONS_HEADER: &str = "# probe-rs DAP operations v1";
+
+/// Writes `operations` to a file, e.g. operations recorded with [`FakeProbe::record_operations`].
+///
+/// The file has one operation per line, and can be read back with [`load_operations`] to replay the operations
+/// with [`FakeProbe::expect_operations`]:
+///
+/// ```text
+/// # probe-rs DAP operations v1
+/// # rap|wap dp ap address value, rdp|wdp dp address bank value
+/// rap - v1:0 fc 24770011
+/// wdp 01002927 8 - 00000f00
+/// ```
+///
+/// The DP is `-` for the default DP, or the `TARGETSEL` value of a multidrop DP. Numbers are hexadecimal.
+pub fn save_operations(operations: &[Operation], writer: impl Write) -> io::Result<()> {
+    let mut writer = BufWriter::new(writer);
+    writeln!(writer, "{OPERATIONS_HEADER}")?;
+    writeln!(
+        writer,
+        "# rap|wap dp ap address value, rdp|wdp dp address bank value"
+    )?;
+    for operation in operations {
+        operation.write(&mut writer)?;
+    }
+    writer.flush()
+}
+
+/// Reads operations written by [`save_operations`].
+pub fn load_operations(reader: impl BufRead) -> io::Result<Vec<Operation>> {
+    let mut lines = reader.lines();
+    if lines.next().transpose()?.as_deref() != Some(OPERATIONS_HEADER) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a file with DAP operations",
+        ));
+    }
+
+    let mut operations = Vec::new();
+    for line in lines {
+        let line = line?;
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        let operation = Operation::parse(&line).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid DAP operation '{line}'"),
+            )
+        })?;
+        operations.push(operation);
+    }
+    Ok(operations)
+}
+
+impl Operation {
+    fn parse(line: &str) -> Option<Self> {
+        fn hex<T: TryFrom<u64>>(field: &str) -> Option<T> {
+            u64::from_str_radix(field, 16).ok()?.try_into().ok()
+        }
+
+        let mut fields = line.split_whitespace();
+        let kind = fields.next()?;
+        let dp = match fields.next()? {
+            "-" => DpAddress::Default,
+            targetsel => DpAddress::Multidrop(hex(targetsel)?),
+        };
+
+        let operation = match kind {
+            "rap" | "wap" => {
+                let ap = match fields.next()?.split_once(':')? {
+                    ("v1", ap) => FullyQualifiedApAddress::v1_with_dp(dp, hex(ap)?),
+                    ("v2", "-") => FullyQualifiedApAddress::v2_with_dp(dp, ApV2Address::root()),
+                    ("v2", base) => {
+                        FullyQualifiedApAddress::v2_with_dp(dp, ApV2Address::new(hex(base)?))
+                    }
+                    _ => return None,
+                };
+                let address = hex(fields.next()?)?;
+                let value = hex(fields.next()?)?;
+
+                if kind == "rap" {
+                    Operation::ReadRawApRegister {
+                        ap,
+                        address,
+                        result: value,
+                    }
+                } else {
+                    Operation::WriteRawApRegister { ap, address, value }
+                }
+            }
+            "rdp" | "wdp" => {
+                let address = DpRegisterAddress {
+                    address: hex(fields.next()?)?,
+                    bank: match fields.next()? {
+                        "-" => None,
+                        bank => Some(hex(bank)?),
+                    },
+                };
+                let value = hex(fields.next()?)?;
+
+                if kind == "rdp" {
+                    Operation::ReadRawDpRegister {
+                        dp,
+                        address,
+                        result: value,
+                    }
+                } else {
+                    Operation::WriteRawDpRegister { dp, address, value }
+                }
+            }
+            _ => return None,
+        };
+
+        fields.next().is_none().then_some(operation)
+    }
+
+    fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        fn write_dp(writer: &mut impl Write, dp: DpAddress) -> io::Result<()> {
+            match dp {
+                DpAddress::Default => write!(writer, " -"),
+                DpAddress::Multidrop(targetsel) => write!(writer, " {targetsel:08x}"),
+            }
+        }
+
+        match self {
+            Operation::ReadRawApRegister {
+                ap,
+                address,
+                result: value,
+            }
+            | Operation::WriteRawApRegister { ap, address, value } => {
+                let kind = if matches!(self, Operation::ReadRawApRegister { .. }) {
+                    "rap"
+                } else {
+                    "wap"
+                };
+                write!(writer, "{kind}")?;
+                write_dp(writer, ap.dp())?;
+                match ap.ap() {
+                    ApAddress::V1(ap) => write!(writer, " v1:{ap:x}")?,
+                    ApAddress::V2(ApV2Address(None)) => write!(writer, " v2:-")?,
+                    ApAddress::V2(ApV2Address(Some(base))) => write!(writer, " v2:{base:x}")?,
+                }
+                writeln!(writer, " {address:x} {value:08x}")
+            }
+            Operation::ReadRawDpRegister {
+                dp,
+                address,
+                result: value,
+            }
+            | Operation::WriteRawDpRegister { dp, address, value } => {
+                let kind = if matches!(self, Operation::ReadRawDpRegister { .. }) {
+                    "rdp"
+                } else {
+                    "wdp"
+                };
+                write!(writer, "{kind}")?;
+                write_dp(writer, *dp)?;
+                write!(writer, " {:x}", address.address)?;
+                match address.bank {
+                    Some(bank) => write!(writer, " {bank:x}")?,
+                    None => write!(writer, " -")?,
+                }
+                writeln!(writer, " {value:08x}")
+            }
+        }
+    }
 }
 
 impl Debug for FakeProbe {
@@ -312,6 +705,7 @@ impl FakeProbe {
             dap_register_write_handler: None,
 
             operations: RefCell::new(VecDeque::new()),
+            recording: None,
 
             memory_ap: MockedAp::MemoryAp(MockMemoryAp::with_pattern()),
         }
@@ -379,34 +773,270 @@ impl FakeProbe {
         self.operations.borrow_mut().pop_front()
     }
 
-    fn read_raw_ap_register(
+    fn record(&self, operation: Operation) {
+        if let Some(recording) = &self.recording {
+            recording.lock().unwrap().push(operation);
+        }
+    }
+
+    /// Replays the next expected operation, or simulates it with the mocked memory AP if no operations are expected.
+    fn replay_or_simulate(
         &mut self,
-        expected_ap: &FullyQualifiedApAddress,
-        expected_address: u64,
+        operation: Operation,
+        simulate: impl FnOnce(&mut MockMemoryAp) -> Result<u32, ArmError>,
     ) -> Result<u32, ArmError> {
-        let operation = self.next_operation();
+        let result = match (self.next_operation(), &operation) {
+            (
+                Some(Operation::ReadRawApRegister {
+                    ap,
+                    address,
+                    result,
+                }),
+                Operation::ReadRawApRegister {
+                    ap: expected_ap,
+                    address: expected_address,
+                    ..
+                },
+            ) if &ap == expected_ap && address == *expected_address => result,
+            (
+                Some(Operation::ReadRawDpRegister {
+                    dp,
+                    address,
+                    result,
+                }),
+                Operation::ReadRawDpRegister {
+                    dp: expected_dp,
+                    address: expected_address,
+                    ..
+                },
+            ) if &dp == expected_dp && &address == expected_address => result,
+            (Some(expected), operation) if &expected == operation => 0,
+            (Some(expected), operation) => {
+                return Err(DebugProbeError::Other(format!(
+                    "Expected the operation {expected:?}, but got {operation:?}"
+                ))
+                .into());
+            }
+            (None, operation) => match &mut self.memory_ap {
+                MockedAp::MemoryAp(memory_ap) => simulate(memory_ap)?,
+                MockedAp::Core(_) => {
+                    return Err(DebugProbeError::Other(format!(
+                        "No more operations expected, but got {operation:?}"
+                    ))
+                    .into());
+                }
+            },
+        };
 
-        match operation {
-            Some(Operation::ReadRawApRegister {
+        self.record(match operation {
+            Operation::ReadRawApRegister { ap, address, .. } => Operation::ReadRawApRegister {
                 ap,
                 address,
                 result,
-            }) => {
-                assert_eq!(&ap, expected_ap);
-                assert_eq!(address, expected_address);
+            },
+            Operation::ReadRawDpRegister { dp, address, .. } => Operation::ReadRawDpRegister {
+                dp,
+                address,
+                result,
+            },
+            operation => operation,
+        });
 
-                Ok(result)
-            }
-            None => panic!(
-                "No more operations expected, but got read_raw_ap_register ap={expected_ap:?}, address:{expected_address}"
-            ),
-            //other => panic!("Unexpected operation: {:?}", other),
-        }
+        Ok(result)
+    }
+
+    fn read_raw_ap_register(
+        &mut self,
+        ap: &FullyQualifiedApAddress,
+        address: u64,
+    ) -> Result<u32, ArmError> {
+        self.replay_or_simulate(
+            Operation::ReadRawApRegister {
+                ap: ap.clone(),
+                address,
+                result: 0,
+            },
+            |memory_ap| memory_ap.read_raw_ap_register(ap, address),
+        )
+    }
+
+    fn write_raw_ap_register(
+        &mut self,
+        ap: &FullyQualifiedApAddress,
+        address: u64,
+        value: u32,
+    ) -> Result<(), ArmError> {
+        self.replay_or_simulate(
+            Operation::WriteRawApRegister {
+                ap: ap.clone(),
+                address,
+                value,
+            },
+            |memory_ap| {
+                memory_ap
+                    .write_raw_ap_register(ap, address, value)
+                    .map(|_| 0)
+            },
+        )?;
+
+        Ok(())
     }
 
+    fn read_raw_dp_register(
+        &mut self,
+        dp: DpAddress,
+        address: DpRegisterAddress,
+    ) -> Result<u32, ArmError> {
+        self.replay_or_simulate(
+            Operation::ReadRawDpRegister {
+                dp,
+                address,
+                result: 0,
+            },
+            |memory_ap| memory_ap.read_raw_dp_register(dp, address),
+        )
+    }
+
+    fn write_raw_dp_register(
+        &mut self,
+        dp: DpAddress,
+        address: DpRegisterAddress,
+        value: u32,
+    ) -> Result<(), ArmError> {
+        self.replay_or_simulate(
+            Operation::WriteRawDpRegister { dp, address, value },
+            |memory_ap| {
+                memory_ap
+                    .write_raw_dp_register(dp, address, value)
+                    .map(|_| 0)
+            },
+        )?;
+
+        Ok(())
+    }
+
+    /// Expects `operation` as the next DAP register operation, and returns its result.
+    ///
+    /// Once all expected operations are used up, a [`FakeProbe`] without a mocked core simulates a memory AP.
     pub fn expect_operation(&self, operation: Operation) {
         self.operations.borrow_mut().push_back(operation);
     }
+
+    /// Expects the `operations` in order, e.g. to replay operations recorded with [`FakeProbe::record_operations`].
+    pub fn expect_operations(&self, operations: impl IntoIterator<Item = Operation>) {
+        self.operations.borrow_mut().extend(operations);
+    }
+
+    /// Records all DAP register operations from now on, with their results.
+    ///
+    /// The returned log is shared with the probe, so it can be inspected after the probe was moved into a session.
+    pub fn record_operations(&mut self) -> Arc<Mutex<Vec<Operation>>> {
+        self.recording
+            .get_or_insert_with(|| Arc::new(Mutex::new(Vec::new())))
+            .clone()
+    }
+
+    fn mocked_core(&mut self) -> Result<&mut MockCore, DebugProbeError> {
+        match &mut self.memory_ap {
+            MockedAp::Core(core) => Ok(core),
+            MockedAp::MemoryAp(_) => Err(DebugProbeError::Other(
+                "The FakeProbe has no mocked core, use FakeProbe::with_mocked_core()".to_string(),
+            )),
+        }
+    }
+
+    /// Adds a zero filled region of simulated memory to the mocked core.
+    ///
+    /// Simulated memory takes precedence over the program binary. Writes to read-only regions are ignored.
+    pub fn add_memory_region(
+        &mut self,
+        range: Range<u64>,
+        writable: bool,
+    ) -> Result<(), DebugProbeError> {
+        let size = (range.end - range.start) as usize;
+        self.mocked_core()?.memory_regions.push(MemoryRegion {
+            range,
+            data: vec![0; size],
+            writable,
+        });
+
+        Ok(())
+    }
+
+    /// Loads `data` into the simulated memory at `address`, even if it is read-only.
+    ///
+    /// Fails if the memory is not part of a region added with [`FakeProbe::add_memory_region`].
+    pub fn load_memory(&mut self, address: u64, data: &[u8]) -> Result<(), DebugProbeError> {
+        self.mocked_core()?.load_bytes(address, data)
+    }
+
+    /// Adds the CoreSight identification registers of a component of `class` at `base`, with the peripheral ID
+    /// `peripheral_id`.
+    pub fn add_component(
+        &mut self,
+        base: u64,
+        class: u8,
+        peripheral_id: u64,
+    ) -> Result<(), DebugProbeError> {
+        let core = self.mocked_core()?;
+        if !core
+            .memory_regions
+            .iter()
+            .any(|region| region.range.contains(&base))
+        {
+            self.add_memory_region(base..base + 0x1000, false)?;
+        }
+
+        // PIDR4-PIDR7, followed by PIDR0-PIDR3, hold one byte of the peripheral ID each.
+        let peripheral_id_bytes = peripheral_id.to_le_bytes();
+        for (index, byte) in peripheral_id_bytes[4..]
+            .iter()
+            .chain(&peripheral_id_bytes[..4])
+            .enumerate()
+        {
+            self.load_word(base + 0xFD0 + 4 * index as u64, u32::from(*byte))?;
+        }
+
+        // CIDR0-CIDR3 hold the preamble `0xB105_000D`, with the component class in CIDR1.
+        let component_id = 0xB105_000D | (u32::from(class & 0xF) << 12);
+        for (index, byte) in component_id.to_le_bytes().iter().enumerate() {
+            self.load_word(base + 0xFF0 + 4 * index as u64, u32::from(*byte))?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds a ROM table at `base`, which lists the CoreSight components at the addresses in `components`.
+    ///
+    /// The first ROM table added is the base address of the memory AP.
+    pub fn add_rom_table(&mut self, base: u64, components: &[u64]) -> Result<(), DebugProbeError> {
+        // Component class 1 is a ROM table.
+        self.add_component(base, 0x1, 0)?;
+
+        for (index, component) in components.iter().enumerate() {
+            // 32-bit entries with the offset of the component relative to the ROM table, and the present bit.
+            let offset = component.wrapping_sub(base) as u32;
+            self.load_word(base + 4 * index as u64, (offset & !0xFFF) | 0b11)?;
+        }
+        self.load_word(base + 4 * components.len() as u64, 0)?;
+
+        self.mocked_core()?.rom_table_base.get_or_insert(base);
+
+        Ok(())
+    }
+
+    fn load_word(&mut self, address: u64, value: u32) -> Result<(), DebugProbeError> {
+        let core = self.mocked_core()?;
+        let bytes = core.word_to_bytes(value);
+        core.load_bytes(address, &bytes)
+    }
+
+    /// Sets a core register of the mocked core, e.g. the program counter, by its DCRSR register selector.
+    pub fn set_core_register(&mut self, register: u32, value: u32) -> Result<(), DebugProbeError> {
+        self.mocked_core()?.registers.insert(register, value);
+
+        Ok(())
+    }
 }
 
 fn core_with_binary<T: FileHeader>(elf_file: ElfFile<T>) -> MockCore {
@@ -574,6 +1204,8 @@ impl RawDapAccess for FakeProbe {
     }
 }
 
+impl DapProbe for FakeProbe {}
+
 #[derive(Debug)]
 struct FakeArmInterface {
     probe: Box<FakeProbe>,
@@ -668,54 +1300,62 @@ impl SwoAccess for FakeArmInterface {
 impl DapAccess for FakeArmInterface {
     fn read_raw_dp_register(
         &mut self,
-        _dp: DpAddress,
-        _address: DpRegisterAddress,
+        dp: DpAddress,
+        address: DpRegisterAddress,
     ) -> Result<u32, ArmError> {
-        todo!()
+        self.probe.read_raw_dp_register(dp, address)
     }
 
     fn write_raw_dp_register(
         &mut self,
-        _dp: DpAddress,
-        _address: DpRegisterAddress,
-        _value: u32,
+        dp: DpAddress,
+        address: DpRegisterAddress,
+        value: u32,
     ) -> Result<(), ArmError> {
-        todo!()
+        self.probe.write_raw_dp_register(dp, address, value)
     }
 
     fn read_raw_ap_register(
         &mut self,
-        _ap: &FullyQualifiedApAddress,
-        _address: u64,
+        ap: &FullyQualifiedApAddress,
+        address: u64,
     ) -> Result<u32, ArmError> {
-        self.probe.read_raw_ap_register(_ap, _address)
+        self.probe.read_raw_ap_register(ap, address)
     }
 
     fn read_raw_ap_register_repeated(
         &mut self,
-        _ap: &FullyQualifiedApAddress,
-        _address: u64,
-        _values: &mut [u32],
+        ap: &FullyQualifiedApAddress,
+        address: u64,
+        values: &mut [u32],
     ) -> Result<(), ArmError> {
-        todo!()
+        for value in values {
+            *value = self.probe.read_raw_ap_register(ap, address)?;
+        }
+
+        Ok(())
     }
 
     fn write_raw_ap_register(
         &mut self,
-        _ap: &FullyQualifiedApAddress,
-        _address: u64,
-        _value: u32,
+        ap: &FullyQualifiedApAddress,
+        address: u64,
+        value: u32,
     ) -> Result<(), ArmError> {
-        todo!()
+        self.probe.write_raw_ap_register(ap, address, value)
     }
 
     fn write_raw_ap_register_repeated(
         &mut self,
-        _ap: &FullyQualifiedApAddress,
-        _address: u64,
-        _values: &[u32],
+        ap: &FullyQualifiedApAddress,
+        address: u64,
+        values: &[u32],
     ) -> Result<(), ArmError> {
-        todo!()
+        for value in values {
+            self.probe.write_raw_ap_register(ap, address, *value)?;
+        }
+
+        Ok(())
     }
 
     fn try_dap_probe(&self) -> Option<&dyn DapProbe> {
@@ -729,8 +1369,22 @@ impl DapAccess for FakeArmInterface {
 
 #[cfg(all(test, feature = "builtin-targets"))]
 mod test {
-    use super::FakeProbe;
-    use crate::Permissions;
+    use super::{FakeProbe, Operation, PROGRAM_COUNTER, load_operations, save_operations};
+    use crate::{
+        MemoryInterface, Permissions, RegisterId, Session,
+        architecture::arm::{
+            ApV2Address, FullyQualifiedApAddress,
+            ap::{ApRegister, TAR},
+            dp::{DpAddress, DpRegisterAddress},
+        },
+    };
+
+    fn session(fake_probe: FakeProbe) -> Session {
+        fake_probe
+            .into_probe()
+            .attach("nrf51822_xxAC", Permissions::default())
+            .unwrap()
+    }
 
     #[test]
     fn create_session_with_fake_probe() {
@@ -742,4 +1396,181 @@ mod test {
             .attach("nrf51822_xxAC", Permissions::default())
             .unwrap();
     }
+
+    #[test]
+    fn simulated_memory() {
+        let mut fake_probe = FakeProbe::with_mocked_core();
+        fake_probe
+            .add_memory_region(0x2000_0000..0x2000_0100, true)
+            .unwrap();
+        fake_probe.add_memory_region(0x0..0x100, false).unwrap();
+        fake_probe.load_memory(0x2000_0000, &[1, 2, 3, 4]).unwrap();
+        fake_probe.load_memory(0x10, &[5, 6, 7, 8]).unwrap();
+
+        let mut session = session(fake_probe);
+        let mut core = session.core(0).unwrap();
+
+        assert_eq!(core.read_word_32(0x2000_0000).unwrap(), 0x0403_0201);
+        core.write_word_32(0x2000_0004, 0xDEAD_BEEF).unwrap();
+        assert_eq!(core.read_word_32(0x2000_0004).unwrap(), 0xDEAD_BEEF);
+
+        // Writes to read-only memory are ignored.
+        core.write_word_32(0x10, 0).unwrap();
+        assert_eq!(core.read_word_32(0x10).unwrap(), 0x0807_0605);
+    }
+
+    #[test]
+    fn load_memory_outside_of_regions_fails() {
+        let mut fake_probe = FakeProbe::with_mocked_core();
+        fake_probe.add_memory_region(0x0..0x100, true).unwrap();
+
+        fake_probe.load_memory(0xFC, &[0; 4]).unwrap();
+        assert!(fake_probe.load_memory(0xFE, &[0; 4]).is_err());
+    }
+
+    #[test]
+    fn mocked_core_is_required() {
+        let mut fake_probe = FakeProbe::new();
+
+        assert!(fake_probe.add_memory_region(0x0..0x100, true).is_err());
+        assert!(fake_probe.set_core_register(PROGRAM_COUNTER, 0).is_err());
+    }
+
+    #[test]
+    fn rom_table() {
+        let mut fake_probe = FakeProbe::with_mocked_core();
+        fake_probe
+            .add_rom_table(0xE00F_F000, &[0xE000_E000])
+            .unwrap();
+
+        let mut session = session(fake_probe);
+        let mut core = session.core(0).unwrap();
+
+        // The entry of the SCS, relative to the ROM table and marked as present.
+        assert_eq!(core.read_word_32(0xE00F_F000).unwrap(), 0xFFF0_F003);
+        assert_eq!(core.read_word_32(0xE00F_F004).unwrap(), 0);
+        // CIDR1 holds the component class of a ROM table.
+        assert_eq!(core.read_word_32(0xE00F_FFF4).unwrap(), 0x10);
+    }
+
+    #[test]
+    fn core_registers() {
+        let mut fake_probe = FakeProbe::with_mocked_core();
+        // R0 has the DCRSR register selector 0.
+        fake_probe.set_core_register(0, 0x1234_5678).unwrap();
+
+        let mut session = session(fake_probe);
+        let mut core = session.core(0).unwrap();
+        core.halt(std::time::Duration::from_millis(100)).unwrap();
+
+        let r0 = RegisterId(0);
+        assert_eq!(core.read_core_reg::<u32>(r0).unwrap(), 0x1234_5678);
+
+        core.write_core_reg(r0, 0x8765_4321u32).unwrap();
+        assert_eq!(core.read_core_reg::<u32>(r0).unwrap(), 0x8765_4321);
+    }
+
+    fn operations() -> Vec<Operation> {
+        let multidrop = DpAddress::Multidrop(0x0100_2927);
+
+        vec![
+            Operation::WriteRawDpRegister {
+                dp: multidrop,
+                address: DpRegisterAddress {
+                    address: 0x8,
+                    bank: None,
+                },
+                value: 0xF00,
+            },
+            Operation::ReadRawDpRegister {
+                dp: DpAddress::Default,
+                address: DpRegisterAddress {
+                    address: 0x4,
+                    bank: Some(2),
+                },
+                result: 0x2BA0_1477,
+            },
+            Operation::WriteRawApRegister {
+                ap: FullyQualifiedApAddress::v2_with_dp(multidrop, ApV2Address::new(0x2000)),
+                address: TAR::ADDRESS,
+                value: 0x2000_0000,
+            },
+            Operation::ReadRawApRegister {
+                ap: FullyQualifiedApAddress::v2_with_default_dp(ApV2Address::root()),
+                address: 0xFC,
+                result: 0x2477_0011,
+            },
+        ]
+    }
+
+    #[test]
+    fn save_and_load_operations() {
+        let operations = operations();
+
+        let mut file = Vec::new();
+        save_operations(&operations, &mut file).unwrap();
+        assert_eq!(
+            String::from_utf8(file.clone()).unwrap(),
+            "# probe-rs DAP operations v1\n\
+             # rap|wap dp ap address value, rdp|wdp dp address bank value\n\
+             wdp 01002927 8 - 00000f00\n\
+             rdp - 4 2 2ba01477\n\
+             wap 01002927 v2:2000 d04 20000000\n\
+             rap - v2:- fc 24770011\n"
+        );
+
+        assert_eq!(load_operations(file.as_slice()).unwrap(), operations);
+    }
+
+    #[test]
+    fn load_invalid_operations() {
+        assert!(load_operations("rap - v1:0 fc 0\n".as_bytes()).is_err());
+        assert!(
+            load_operations("# probe-rs DAP operations v1\nrap - v3:0 fc 0\n".as_bytes()).is_err()
+        );
+    }
+
+    #[test]
+    fn record_and_replay_operations() {
+        let ap = FullyQualifiedApAddress::v1_with_default_dp(0);
+
+        let mut fake_probe = FakeProbe::new();
+        let recording = fake_probe.record_operations();
+        fake_probe
+            .write_raw_ap_register(&ap, TAR::ADDRESS, 0x2000_0000)
+            .unwrap();
+        assert_eq!(
+            fake_probe.read_raw_ap_register(&ap, TAR::ADDRESS).unwrap(),
+            0x2000_0000
+        );
+
+        let mut file = Vec::new();
+        save_operations(&recording.lock().unwrap(), &mut file).unwrap();
+
+        // Replay the recording with a probe which fails on any operation that was not recorded.
+        let mut fake_probe = FakeProbe::with_mocked_core();
+        fake_probe.expect_operations(load_operations(file.as_slice()).unwrap());
+        fake_probe
+            .write_raw_ap_register(&ap, TAR::ADDRESS, 0x2000_0000)
+            .unwrap();
+        assert_eq!(
+            fake_probe.read_raw_ap_register(&ap, TAR::ADDRESS).unwrap(),
+            0x2000_0000
+        );
+        assert!(fake_probe.read_raw_ap_register(&ap, TAR::ADDRESS).is_err());
+    }
+
+    #[test]
+    fn diverging_operation_fails() {
+        let ap = FullyQualifiedApAddress::v1_with_default_dp(0);
+
+        let mut fake_probe = FakeProbe::with_mocked_core();
+        fake_probe.expect_operations(operations());
+
+        assert!(
+            fake_probe
+                .write_raw_ap_register(&ap, TAR::ADDRESS, 0)
+                .is_err()
+        );
+    }
 }
@@ -13,6 +13,12 @@ use tracing::{debug, trace, warn};
 use crate::probe::DebugProbeError;
 use crate::probe::usb_util::InterfaceExt;
 
+/// The number of USB packets which are received in a single bulk transfer.
+const READ_PACKETS_PER_TRANSFER: usize = 8;
+
+/// The maximum size of a single bulk transfer to the device, like the default of libftdi.
+const WRITE_CHUNK_SIZE: usize = 4096;
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum ChipType {
     Am,
@@ -187,28 +193,36 @@ impl FtdiContext {
 
                 tracing::debug!("Read {:02x?} bytes from USB", &self.read_buffer[..read]);
 
-                if read <= 2 {
-                    // No more data to read.
-                    break;
-                }
+                // The transfer may contain several packets, each starting with two status bytes.
+                let mut received = false;
+                for packet in self.read_buffer[..read].chunks(self.max_packet_size) {
+                    if packet.len() <= 2 {
+                        continue;
+                    }
+
+                    let (status, read_data) = packet.split_at(2);
 
-                let (status, read_data) = self.read_buffer[..read].split_at(2);
+                    tracing::debug!("Status: {:02X?} [{} data]", status, packet.len());
+                    received = true;
 
-                tracing::debug!("Status: {:02X?} [{} data]", status, read);
+                    let copy = read_data.len().min(data.len());
+                    let (to_buffer, to_save) = read_data.split_at(copy);
 
-                let copy = read_data.len().min(data.len());
-                let (to_buffer, to_save) = read_data.split_at(copy);
+                    if copy > 0 {
+                        data[..copy].copy_from_slice(to_buffer);
+                        data = &mut data[copy..];
+                        tracing::debug!("Copied {} bytes from USB", copy);
+                        total += copy;
+                    }
 
-                if copy > 0 {
-                    data[..copy].copy_from_slice(to_buffer);
-                    data = &mut data[copy..];
-                    tracing::debug!("Copied {} bytes from USB", copy);
-                    total += copy;
+                    if !to_save.is_empty() {
+                        tracing::debug!("Queued {} bytes from USB", to_save.len());
+                        self.read_queue.extend(to_save);
+                    }
                 }
 
-                if !to_save.is_empty() {
-                    tracing::debug!("Queued {} bytes from USB", to_save.len());
-                    self.read_queue.extend(to_save);
+                if !received || !self.read_queue.is_empty() {
+                    // No more data to read, or the buffer is full.
                     break;
                 }
             }
@@ -221,7 +235,7 @@ impl FtdiContext {
 
     fn write_data(&mut self, data: &[u8]) -> io::Result<usize> {
         let mut total = 0;
-        for chunk in data.chunks(self.max_packet_size) {
+        for chunk in data.chunks(WRITE_CHUNK_SIZE) {
             total +=
                 self.handle
                     .write_bulk(self.interface.write_ep(), chunk, self.usb_write_timeout)?;
@@ -428,7 +442,8 @@ impl Device {
                 usb_read_timeout: Duration::from_secs(5),
                 usb_write_timeout: Duration::from_secs(5),
                 read_queue: VecDeque::new(),
-                read_buffer: vec![0; max_packet_size].into_boxed_slice(),
+                read_buffer: vec![0; max_packet_size * READ_PACKETS_PER_TRANSFER]
+                    .into_boxed_slice(),
                 max_packet_size,
                 bitbang: None,
             },
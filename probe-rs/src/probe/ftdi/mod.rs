@@ -17,6 +17,7 @@ use crate::{
         AutoImplementJtagAccess, DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector,
         IoSequenceItem, JtagAccess, JtagDriverState, ProbeCreationError, ProbeFactory,
         ProbeStatistics, RawJtagIo, RawSwdIo, SwdSettings, WireProtocol,
+        swd_trace::SwdTraceRecorder,
     },
 };
 use bitvec::prelude::*;
@@ -33,14 +34,27 @@ mod ftdaye;
 use command_compacter::Command;
 use ftdaye::{ChipType, error::FtdiError};
 
+/// The default latency timer, in milliseconds. Every command buffer ends with Send Immediate, so
+/// the timer only delays responses which are not requested explicitly.
+const DEFAULT_LATENCY_TIMER_MS: u8 = 1;
+
+/// The speed is not lowered below this while tuning the clock, in kHz.
+const MIN_TUNED_SPEED_KHZ: u32 = 100;
+
 #[derive(Debug)]
 struct JtagAdapter {
     device: ftdaye::Device,
     speed_khz: u32,
+    /// Whether the clock is tuned to the fastest speed the target responds reliably to.
+    tune_speed: bool,
+    latency_timer_ms: u8,
 
     command: Command,
     commands: Vec<u8>,
+    /// The number of captured bits in each byte of the responses which were not read yet.
     in_bit_counts: Vec<usize>,
+    /// The number of response bytes of the commands which were not sent yet.
+    unsent_responses: usize,
     in_bits: BitVec,
     ftdi: FtdiProperties,
 }
@@ -58,9 +72,12 @@ impl JtagAdapter {
         Ok(Self {
             device,
             speed_khz: 1000,
+            tune_speed: false,
+            latency_timer_ms: DEFAULT_LATENCY_TIMER_MS,
             command: Command::default(),
             commands: vec![],
             in_bit_counts: vec![],
+            unsent_responses: 0,
             in_bits: BitVec::new(),
             ftdi,
         })
@@ -70,7 +87,7 @@ impl JtagAdapter {
         self.device.usb_reset()?;
         // 0x0B configures pins for JTAG
         self.device.set_bitmode(0x0b, ftdaye::BitMode::Mpsse)?;
-        self.device.set_latency_timer(1)?;
+        self.device.set_latency_timer(self.latency_timer_ms)?;
         self.device.usb_purge_buffers()?;
 
         let mut junk = vec![];
@@ -110,11 +127,26 @@ impl JtagAdapter {
         self.speed_khz
     }
 
+    /// Sets the speed which is used when attaching. A speed of 0 kHz tunes the clock.
     fn set_speed_khz(&mut self, speed_khz: u32) -> u32 {
         self.speed_khz = speed_khz;
+        self.tune_speed = speed_khz == 0;
         self.speed_khz
     }
 
+    fn set_latency_timer(&mut self, latency_ms: u8) -> Result<(), FtdiError> {
+        if latency_ms == 0 {
+            return Err(FtdiError::Other(
+                "The latency timer must be at least 1 ms".to_string(),
+            ));
+        }
+
+        self.device.set_latency_timer(latency_ms)?;
+        self.latency_timer_ms = latency_ms;
+
+        Ok(())
+    }
+
     fn apply_clock_speed(&mut self, speed_khz: u32) -> Result<u32, FtdiError> {
         // Disable divide-by-5 mode if available
         if self.ftdi.has_divide_by_5 {
@@ -124,14 +156,8 @@ impl JtagAdapter {
             self.device.enable_divide_by_5()?;
         }
 
-        // If `speed_khz` is not a divisor of the maximum supported speed, we need to round up
-        let is_exact = self.ftdi.max_clock % speed_khz == 0;
-
-        // If `speed_khz` is 0, use the maximum supported speed
-        let divisor =
-            (self.ftdi.max_clock.checked_div(speed_khz).unwrap_or(1) - is_exact as u32).min(0xFFFF);
-
-        let actual_speed = self.ftdi.max_clock / (divisor + 1);
+        let divisor = clock_divisor(self.ftdi.max_clock, speed_khz);
+        let actual_speed = self.ftdi.max_clock / (u32::from(divisor) + 1);
 
         tracing::info!(
             "Setting speed to {} kHz (divisor: {}, actual speed: {} kHz)",
@@ -140,22 +166,24 @@ impl JtagAdapter {
             actual_speed
         );
 
-        self.device.configure_clock_divider(divisor as u16)?;
+        self.device.configure_clock_divider(divisor)?;
 
         self.speed_khz = actual_speed;
         Ok(actual_speed)
     }
 
+    /// Reads the responses to the commands which were sent.
     fn read_response(&mut self) -> Result<(), DebugProbeError> {
-        if self.in_bit_counts.is_empty() {
+        let expected = self.in_bit_counts.len() - self.unsent_responses;
+        if expected == 0 {
             return Ok(());
         }
 
         let mut t0 = Instant::now();
         let timeout = Duration::from_millis(10);
 
-        let mut reply = Vec::with_capacity(self.in_bit_counts.len());
-        while reply.len() < self.in_bit_counts.len() {
+        let mut reply = Vec::with_capacity(expected);
+        while reply.len() < expected {
             let read = self
                 .device
                 .read_to_end(&mut reply)
@@ -166,24 +194,20 @@ impl JtagAdapter {
             }
 
             if t0.elapsed() > timeout {
-                tracing::warn!(
-                    "Read {} bytes, expected {}",
-                    reply.len(),
-                    self.in_bit_counts.len()
-                );
+                tracing::warn!("Read {} bytes, expected {}", reply.len(), expected);
                 return Err(DebugProbeError::Timeout);
             }
         }
 
-        if reply.len() != self.in_bit_counts.len() {
+        if reply.len() != expected {
             return Err(DebugProbeError::Other(format!(
                 "Read more data than expected. Expected {} bytes, got {} bytes",
-                self.in_bit_counts.len(),
+                expected,
                 reply.len()
             )));
         }
 
-        for (byte, count) in reply.into_iter().zip(self.in_bit_counts.drain(..)) {
+        for (byte, count) in reply.into_iter().zip(self.in_bit_counts.drain(..expected)) {
             let bits = byte >> (8 - count);
             self.in_bits
                 .extend_from_bitslice(&bits.view_bits::<Lsb0>()[..count]);
@@ -202,13 +226,16 @@ impl JtagAdapter {
 
     fn append_command(&mut self, command: Command) -> Result<(), DebugProbeError> {
         tracing::trace!("Appending {:?}", command);
-        // 1 byte is reserved for the send immediate command
+        // 1 byte is reserved for the send immediate command. The responses to a full
+        // buffer are read when the next buffer is sent, so the probe executes the next
+        // commands while the responses are transferred.
         if self.commands.len() + command.len() + 1 >= self.ftdi.buffer_size {
             self.send_buffer()?;
-            self.read_response()?;
         }
 
+        let responses = self.in_bit_counts.len();
         command.add_captured_bits(&mut self.in_bit_counts);
+        self.unsent_responses += self.in_bit_counts.len() - responses;
         command.encode(&mut self.commands);
 
         Ok(())
@@ -235,6 +262,13 @@ impl JtagAdapter {
             return Ok(());
         }
 
+        // The chip stops executing commands when its transmit buffer is full, so the
+        // responses to the previous buffer are read first if they would not fit together.
+        let sent_responses = self.in_bit_counts.len() - self.unsent_responses;
+        if sent_responses > 0 && sent_responses + self.unsent_responses > self.ftdi.buffer_size {
+            self.read_response()?;
+        }
+
         // Send Immediate: This will make the FTDI chip flush its buffer back to the PC.
         // See https://www.ftdichip.com/Support/Documents/AppNotes/AN_108_Command_Processor_for_MPSSE_and_MCU_Host_Bus_Emulation_Modes.pdf
         // section 5.1
@@ -247,6 +281,7 @@ impl JtagAdapter {
             .map_err(FtdiError::from)?;
 
         self.commands.clear();
+        self.unsent_responses = 0;
 
         Ok(())
     }
@@ -258,6 +293,25 @@ impl JtagAdapter {
     }
 }
 
+/// The clock divisor for the fastest speed up to `speed_khz`, for a chip with a maximum clock of
+/// `max_clock` kHz. A speed of 0 kHz selects the maximum clock.
+fn clock_divisor(max_clock: u32, speed_khz: u32) -> u16 {
+    if speed_khz == 0 {
+        return 0;
+    }
+
+    // Round the speed down if it doesn't divide the maximum clock.
+    let divisor = max_clock.div_ceil(speed_khz).saturating_sub(1);
+    divisor.min(0xFFFF) as u16
+}
+
+/// The speeds which are tried when tuning the clock, fastest first.
+fn tuning_speeds(max_clock: u32) -> impl Iterator<Item = u32> {
+    let min_speed = MIN_TUNED_SPEED_KHZ.min(max_clock);
+    std::iter::successors(Some(max_clock), |speed| Some(speed / 2))
+        .take_while(move |speed| *speed >= min_speed)
+}
+
 /// A factory for creating [`FtdiProbe`] instances.
 #[derive(Debug)]
 pub struct FtdiProbeFactory;
@@ -335,6 +389,9 @@ impl DebugProbe for FtdiProbe {
         tracing::debug!("Attaching...");
 
         self.adapter.attach()?;
+        if self.adapter.tune_speed {
+            self.tune_speed()?;
+        }
         self.select_target(0)
     }
 
@@ -414,6 +471,46 @@ impl DebugProbe for FtdiProbe {
     fn has_xtensa_interface(&self) -> bool {
         true
     }
+
+    fn record_swd_trace(&mut self, recorder: SwdTraceRecorder) -> Result<(), DebugProbeError> {
+        self.probe_statistics.record_swd_trace(recorder)
+    }
+}
+
+impl FtdiProbe {
+    /// Sets the latency timer of the FTDI chip, in milliseconds.
+    ///
+    /// The chip sends partially filled USB packets to the host when the timer expires. Responses
+    /// are requested explicitly, so the default of 1 ms only needs to be changed for USB hubs or
+    /// hosts that can't keep up with the packet rate.
+    pub fn set_latency_timer(&mut self, latency_ms: u8) -> Result<(), DebugProbeError> {
+        Ok(self.adapter.set_latency_timer(latency_ms)?)
+    }
+
+    /// Selects the fastest clock at which the JTAG scan chain reads back the same twice.
+    fn tune_speed(&mut self) -> Result<(), DebugProbeError> {
+        let mut speeds = tuning_speeds(self.adapter.ftdi.max_clock).peekable();
+        while let Some(speed) = speeds.next() {
+            let actual_speed = self.adapter.apply_clock_speed(speed)?;
+
+            let first = self.scan_jtag_chain();
+            let second = self.scan_jtag_chain();
+            match (first, second) {
+                (Ok(first), Ok(second)) if !first.is_empty() && first == second => {
+                    tracing::info!("Tuned the clock to {actual_speed} kHz");
+                    return Ok(());
+                }
+                _ if speeds.peek().is_none() => {
+                    tracing::warn!(
+                        "The JTAG scan chain is unstable at all speeds, using {actual_speed} kHz"
+                    );
+                }
+                _ => tracing::debug!("The JTAG scan chain is unstable at {actual_speed} kHz"),
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl AutoImplementJtagAccess for FtdiProbe {}
@@ -617,3 +714,43 @@ fn list_ftdi_devices() -> Vec<DebugProbeInfo> {
         Err(_) => vec![],
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exact_speeds() {
+        assert_eq!(clock_divisor(30_000, 30_000), 0);
+        assert_eq!(clock_divisor(30_000, 15_000), 1);
+        assert_eq!(clock_divisor(30_000, 1_000), 29);
+        assert_eq!(clock_divisor(6_000, 1_000), 5);
+    }
+
+    #[test]
+    fn speeds_are_rounded_down() {
+        // 30 MHz / 5 = 6 MHz is the fastest clock up to 7 MHz.
+        assert_eq!(clock_divisor(30_000, 7_000), 4);
+        assert_eq!(clock_divisor(30_000, 40_000), 0);
+    }
+
+    #[test]
+    fn slowest_speed_is_limited_by_the_divisor() {
+        assert_eq!(clock_divisor(30_000, 0), 0);
+        assert_eq!(clock_divisor(30_000, 1), 29_999);
+        assert_eq!(clock_divisor(u32::MAX, 1), 0xFFFF);
+    }
+
+    #[test]
+    fn tuning_starts_at_the_maximum_clock() {
+        assert_eq!(
+            tuning_speeds(30_000).collect::<Vec<_>>(),
+            [30_000, 15_000, 7_500, 3_750, 1_875, 937, 468, 234, 117]
+        );
+        assert_eq!(
+            tuning_speeds(6_000).collect::<Vec<_>>(),
+            [6_000, 3_000, 1_500, 750, 375, 187]
+        );
+        assert_eq!(tuning_speeds(50).collect::<Vec<_>>(), [50]);
+    }
+}
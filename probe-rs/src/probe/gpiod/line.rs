@@ -0,0 +1,221 @@
+//! Access to GPIO lines with the GPIO character device of the Linux kernel (uAPI v2).
+//!
+//! See `include/uapi/linux/gpio.h` in the kernel sources for the definitions.
+
+use std::fs::File;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::path::Path;
+
+const GPIO_V2_LINES_MAX: usize = 64;
+const GPIO_MAX_NAME_SIZE: usize = 32;
+const GPIO_V2_LINE_NUM_ATTRS_MAX: usize = 10;
+
+const GPIO_V2_LINE_FLAG_INPUT: u64 = 1 << 2;
+const GPIO_V2_LINE_FLAG_OUTPUT: u64 = 1 << 3;
+const GPIO_V2_LINE_FLAG_OPEN_DRAIN: u64 = 1 << 6;
+
+const GPIO_V2_LINE_ATTR_ID_FLAGS: u32 = 1;
+const GPIO_V2_LINE_ATTR_ID_OUTPUT_VALUES: u32 = 2;
+
+const GPIO_V2_GET_LINE_IOCTL: u32 = iowr(0x07, size_of::<LineRequest>());
+const GPIO_V2_LINE_SET_CONFIG_IOCTL: u32 = iowr(0x0D, size_of::<LineConfig>());
+const GPIO_V2_LINE_GET_VALUES_IOCTL: u32 = iowr(0x0E, size_of::<LineValues>());
+const GPIO_V2_LINE_SET_VALUES_IOCTL: u32 = iowr(0x0F, size_of::<LineValues>());
+
+/// The `_IOWR` macro of the kernel, for the GPIO ioctls.
+const fn iowr(nr: u32, size: usize) -> u32 {
+    (3 << 30) | ((size as u32) << 16) | (0xB4 << 8) | nr
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct LineAttribute {
+    id: u32,
+    padding: u32,
+    value: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct LineConfigAttribute {
+    attr: LineAttribute,
+    mask: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct LineConfig {
+    flags: u64,
+    num_attrs: u32,
+    padding: [u32; 5],
+    attrs: [LineConfigAttribute; GPIO_V2_LINE_NUM_ATTRS_MAX],
+}
+
+impl LineConfig {
+    fn push(&mut self, id: u32, value: u64, mask: u64) {
+        if mask == 0 {
+            return;
+        }
+        self.attrs[self.num_attrs as usize] = LineConfigAttribute {
+            attr: LineAttribute {
+                id,
+                padding: 0,
+                value,
+            },
+            mask,
+        };
+        self.num_attrs += 1;
+    }
+}
+
+#[repr(C)]
+struct LineRequest {
+    offsets: [u32; GPIO_V2_LINES_MAX],
+    consumer: [u8; GPIO_MAX_NAME_SIZE],
+    config: LineConfig,
+    num_lines: u32,
+    event_buffer_size: u32,
+    padding: [u32; 5],
+    fd: i32,
+}
+
+#[repr(C)]
+#[derive(Debug, Default)]
+struct LineValues {
+    bits: u64,
+    mask: u64,
+}
+
+const _: () = assert!(size_of::<LineConfig>() == 272);
+const _: () = assert!(size_of::<LineRequest>() == 592);
+
+/// A set of requested GPIO lines.
+///
+/// The lines are addressed by bit masks, where bit `n` is the `n`th requested line.
+#[derive(Debug)]
+pub(super) struct Lines {
+    fd: OwnedFd,
+    all: u64,
+    open_drain: u64,
+    inputs: u64,
+    values: u64,
+}
+
+impl Lines {
+    /// Requests the lines `offsets` of the GPIO chip `chip`, as outputs with the initial `values`.
+    ///
+    /// The outputs in `open_drain` are configured as open drain outputs.
+    pub(super) fn request(
+        chip: &Path,
+        offsets: &[u32],
+        open_drain: u64,
+        values: u64,
+    ) -> io::Result<Self> {
+        let chip = File::open(chip)?;
+
+        let mut lines = Self {
+            // Replaced by the file descriptor of the lines below.
+            fd: chip.into(),
+            all: (1 << offsets.len()) - 1,
+            open_drain,
+            inputs: 0,
+            values,
+        };
+
+        let mut request = LineRequest {
+            offsets: [0; GPIO_V2_LINES_MAX],
+            consumer: [0; GPIO_MAX_NAME_SIZE],
+            config: lines.config(),
+            num_lines: offsets.len() as u32,
+            event_buffer_size: 0,
+            padding: [0; 5],
+            fd: -1,
+        };
+        request.offsets[..offsets.len()].copy_from_slice(offsets);
+        request.consumer[..8].copy_from_slice(b"probe-rs");
+
+        lines.ioctl(GPIO_V2_GET_LINE_IOCTL, &mut request)?;
+        // SAFETY: The kernel returned a new file descriptor for the requested lines, which is
+        // owned by us now.
+        lines.fd = unsafe { OwnedFd::from_raw_fd(request.fd) };
+
+        Ok(lines)
+    }
+
+    /// Sets the lines in `mask` to `values`. Lines which are inputs keep the value for when they
+    /// become outputs again.
+    pub(super) fn set(&mut self, values: u64, mask: u64) -> io::Result<()> {
+        self.values = (self.values & !mask) | (values & mask);
+
+        let mut values = LineValues {
+            bits: self.values,
+            mask: mask & !self.inputs,
+        };
+        if values.mask == 0 {
+            return Ok(());
+        }
+        self.ioctl(GPIO_V2_LINE_SET_VALUES_IOCTL, &mut values)
+    }
+
+    /// Reads the current levels of the lines in `mask`.
+    pub(super) fn get(&mut self, mask: u64) -> io::Result<u64> {
+        let mut values = LineValues { bits: 0, mask };
+        self.ioctl(GPIO_V2_LINE_GET_VALUES_IOCTL, &mut values)?;
+        Ok(values.bits & mask)
+    }
+
+    /// Makes the lines in `inputs` inputs, and all others outputs.
+    pub(super) fn set_inputs(&mut self, inputs: u64) -> io::Result<()> {
+        if inputs == self.inputs {
+            return Ok(());
+        }
+        self.inputs = inputs;
+
+        let mut config = self.config();
+        self.ioctl(GPIO_V2_LINE_SET_CONFIG_IOCTL, &mut config)
+    }
+
+    fn config(&self) -> LineConfig {
+        let outputs = self.all & !self.inputs;
+
+        let mut config = LineConfig {
+            flags: GPIO_V2_LINE_FLAG_OUTPUT,
+            ..Default::default()
+        };
+        config.push(
+            GPIO_V2_LINE_ATTR_ID_FLAGS,
+            GPIO_V2_LINE_FLAG_INPUT,
+            self.inputs,
+        );
+        config.push(
+            GPIO_V2_LINE_ATTR_ID_FLAGS,
+            GPIO_V2_LINE_FLAG_OUTPUT | GPIO_V2_LINE_FLAG_OPEN_DRAIN,
+            self.open_drain & outputs,
+        );
+        config.push(GPIO_V2_LINE_ATTR_ID_OUTPUT_VALUES, self.values, outputs);
+        config
+    }
+
+    fn ioctl<T>(&self, request: u32, argument: &mut T) -> io::Result<()> {
+        // SAFETY: `argument` is the structure which the kernel expects for `request`.
+        let result = unsafe { libc::ioctl(self.fd.as_raw_fd(), request as _, argument as *mut T) };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ioctl_numbers() {
+        assert_eq!(GPIO_V2_GET_LINE_IOCTL, 0xC250_B407);
+        assert_eq!(GPIO_V2_LINE_SET_CONFIG_IOCTL, 0xC110_B40D);
+        assert_eq!(GPIO_V2_LINE_GET_VALUES_IOCTL, 0xC010_B40E);
+        assert_eq!(GPIO_V2_LINE_SET_VALUES_IOCTL, 0xC010_B40F);
+    }
+}
@@ -0,0 +1,619 @@
+//! SWD bit-banged on the GPIO pins of a Linux host, e.g. on the header of a Raspberry Pi.
+//!
+//! The pins are accessed through the GPIO character device of the kernel. As they can not be
+//! detected, the probe is never listed, and has to be selected with the GPIO chip and the line
+//! offsets of the pins, e.g. `gpiod://gpiochip0?swclk=11&swdio=8&nreset=25`. The reset pin is
+//! optional.
+//!
+//! Bit-banging is limited by the speed of the GPIO accesses. An SPI controller of the host can
+//! shift the bits instead, selected with its spidev device, e.g.
+//! `gpiod://gpiochip0?spidev=spidev0.0&nreset=25`. SCLK is connected to SWCLK, MISO to SWDIO,
+//! and MOSI to SWDIO through a resistor of about 1 kΩ, so the target can drive SWDIO while MOSI
+//! is driven too.
+
+mod line;
+mod spi;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bitvec::vec::BitVec;
+
+use self::line::Lines;
+use self::spi::SpiDevice;
+use crate::architecture::arm::{
+    ArmCommunicationInterface, ArmDebugInterface, ArmError, communication_interface::DapProbe,
+    sequences::ArmDebugSequence,
+};
+use crate::probe::{
+    AutoImplementJtagAccess, DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector,
+    IoSequenceItem, JtagDriverState, ProbeCreationError, ProbeError, ProbeFactory, ProbeStatistics,
+    RawJtagIo, RawSwdIo, SwdSettings, WireProtocol, swd_trace::SwdTraceRecorder,
+};
+
+const SELECTOR_SCHEME: &str = "gpiod://";
+
+// The bits of the requested lines.
+const SWCLK: u64 = 1 << 0;
+const SWDIO: u64 = 1 << 1;
+const NRESET: u64 = 1 << 2;
+
+// The bits of the pins in `swj_pins`.
+const PIN_SWCLK: u32 = 1 << 0;
+const PIN_SWDIO: u32 = 1 << 1;
+const PIN_NRESET: u32 = 1 << 7;
+
+const DEFAULT_SPEED_KHZ: u32 = 1000;
+
+/// Errors of the GPIO probe.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum GpioError {
+    /// Invalid GPIO probe selector '{0}', expected e.g. 'gpiod://gpiochip0?swclk=11&swdio=8'.
+    InvalidSelector(String),
+
+    /// The GPIO probe selector does not specify the '{0}' pin.
+    MissingPin(&'static str),
+
+    /// Accessing the GPIO lines failed.
+    Gpio(#[from] std::io::Error),
+}
+
+impl ProbeError for GpioError {}
+
+/// The GPIO chip and lines, parsed from the serial number of the selector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GpioPins {
+    chip: PathBuf,
+    swd: SwdPins,
+    nreset: Option<u32>,
+}
+
+/// How the SWD bits are shifted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SwdPins {
+    /// Bit-banged on the GPIO lines `swclk` and `swdio`.
+    Gpio { swclk: u32, swdio: u32 },
+    /// Shifted by the SPI controller of the spidev device.
+    Spi(PathBuf),
+}
+
+impl GpioPins {
+    fn parse(selector: &str) -> Result<Self, GpioError> {
+        let invalid = || GpioError::InvalidSelector(selector.to_string());
+
+        let pins = selector.strip_prefix(SELECTOR_SCHEME).ok_or_else(invalid)?;
+        let (chip, pins) = pins.split_once('?').unwrap_or((pins, ""));
+
+        let chip = if chip.starts_with('/') {
+            PathBuf::from(chip)
+        } else if let Some(number) = chip.strip_prefix("chip") {
+            PathBuf::from(format!("/dev/gpiochip{number}"))
+        } else if !chip.is_empty() {
+            PathBuf::from("/dev").join(chip)
+        } else {
+            return Err(invalid());
+        };
+
+        let (mut swclk, mut swdio, mut nreset, mut spidev) = (None, None, None, None);
+        for pin in pins.split('&').filter(|pin| !pin.is_empty()) {
+            let (name, value) = pin.split_once('=').ok_or_else(invalid)?;
+            if name == "spidev" {
+                spidev = Some(device_path(value));
+                continue;
+            }
+
+            let offset = value.parse().map_err(|_| invalid())?;
+            match name {
+                "swclk" => swclk = Some(offset),
+                "swdio" => swdio = Some(offset),
+                "nreset" => nreset = Some(offset),
+                _ => return Err(invalid()),
+            }
+        }
+
+        let swd = match spidev {
+            // The SPI controller drives SWCLK and SWDIO.
+            Some(_) if swclk.is_some() || swdio.is_some() => return Err(invalid()),
+            Some(spidev) => SwdPins::Spi(spidev),
+            None => SwdPins::Gpio {
+                swclk: swclk.ok_or(GpioError::MissingPin("swclk"))?,
+                swdio: swdio.ok_or(GpioError::MissingPin("swdio"))?,
+            },
+        };
+
+        Ok(Self { chip, swd, nreset })
+    }
+}
+
+/// The path of a device, which is either absolute or in `/dev`.
+fn device_path(device: &str) -> PathBuf {
+    if device.starts_with('/') {
+        PathBuf::from(device)
+    } else {
+        PathBuf::from("/dev").join(device)
+    }
+}
+
+/// A factory for creating [`GpioProbe`] instances.
+#[derive(Debug)]
+pub struct GpioProbeFactory;
+
+impl std::fmt::Display for GpioProbeFactory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("GPIO")
+    }
+}
+
+impl ProbeFactory for GpioProbeFactory {
+    fn open(&self, selector: &DebugProbeSelector) -> Result<Box<dyn DebugProbe>, DebugProbeError> {
+        let Some(serial_number) = gpio_selector(selector) else {
+            return Err(DebugProbeError::ProbeCouldNotBeCreated(
+                ProbeCreationError::NotFound,
+            ));
+        };
+
+        let pins = GpioPins::parse(serial_number)?;
+        tracing::debug!("Opening GPIO probe {:?}", pins);
+        let probe = GpioProbe::new(&pins)?;
+        Ok(probe.into_probe())
+    }
+
+    fn list_probes(&self) -> Vec<DebugProbeInfo> {
+        // The pins which are connected to a target can not be detected.
+        Vec::new()
+    }
+
+    fn list_probes_filtered(&self, selector: Option<&DebugProbeSelector>) -> Vec<DebugProbeInfo> {
+        let Some(serial_number) = selector.and_then(gpio_selector) else {
+            return Vec::new();
+        };
+
+        vec![DebugProbeInfo {
+            identifier: "GPIO SWD".to_owned(),
+            vendor_id: 0,
+            product_id: 0,
+            serial_number: Some(serial_number.to_owned()),
+            hid_interface: None,
+            probe_factory: &Self,
+        }]
+    }
+}
+
+/// Returns the serial number of the selector, if it selects GPIO pins.
+fn gpio_selector(selector: &DebugProbeSelector) -> Option<&str> {
+    if selector.vendor_id != 0 || selector.product_id != 0 {
+        return None;
+    }
+    selector
+        .serial_number
+        .as_deref()
+        .filter(|serial_number| serial_number.starts_with(SELECTOR_SCHEME))
+}
+
+/// How the SWD bits are shifted by the [`GpioProbe`].
+#[derive(Debug)]
+enum SwdDriver {
+    /// Bit-banged on the SWCLK and SWDIO lines, which are requested along with nRESET.
+    Gpio(Lines),
+    /// Shifted by an SPI controller. Only the nRESET line is requested, if it is used.
+    Spi {
+        device: SpiDevice,
+        lines: Option<Lines>,
+    },
+}
+
+/// A probe which bit-bangs SWD on GPIO pins of the host, or shifts it with an SPI controller.
+#[derive(Debug)]
+pub struct GpioProbe {
+    driver: SwdDriver,
+    /// The bit of the nRESET line in the requested lines, 0 if there is no reset pin.
+    nreset: u64,
+    speed_khz: u32,
+    half_period: Duration,
+    jtag_state: JtagDriverState,
+    swd_settings: SwdSettings,
+    probe_statistics: ProbeStatistics,
+}
+
+impl GpioProbe {
+    fn new(pins: &GpioPins) -> Result<Box<Self>, GpioError> {
+        let (driver, nreset) = match &pins.swd {
+            SwdPins::Gpio { swclk, swdio } => {
+                let mut offsets = vec![*swclk, *swdio];
+                offsets.extend(pins.nreset);
+
+                // SWCLK idles low, SWDIO and nRESET high.
+                let lines = Lines::request(&pins.chip, &offsets, NRESET, SWDIO | NRESET)?;
+                (SwdDriver::Gpio(lines), NRESET)
+            }
+            SwdPins::Spi(spidev) => {
+                let device = SpiDevice::open(spidev)?;
+                let lines = pins
+                    .nreset
+                    .map(|nreset| Lines::request(&pins.chip, &[nreset], 1, 1))
+                    .transpose()?;
+                (SwdDriver::Spi { device, lines }, 1)
+            }
+        };
+
+        let mut probe = Box::new(Self {
+            driver,
+            nreset: if pins.nreset.is_some() { nreset } else { 0 },
+            speed_khz: 0,
+            half_period: Duration::ZERO,
+            jtag_state: JtagDriverState::default(),
+            swd_settings: SwdSettings::default(),
+            probe_statistics: ProbeStatistics::default(),
+        });
+        probe.set_speed_khz(DEFAULT_SPEED_KHZ);
+
+        Ok(probe)
+    }
+
+    fn set_speed_khz(&mut self, speed_khz: u32) -> u32 {
+        self.speed_khz = speed_khz.max(1);
+        self.half_period = Duration::from_nanos(500_000 / u64::from(self.speed_khz));
+        self.speed_khz
+    }
+
+    /// The requested lines with the nRESET line, if there is one.
+    fn reset_lines(&mut self) -> Option<&mut Lines> {
+        if self.nreset == 0 {
+            return None;
+        }
+        match &mut self.driver {
+            SwdDriver::Gpio(lines) => Some(lines),
+            SwdDriver::Spi { lines, .. } => lines.as_mut(),
+        }
+    }
+
+    fn set_reset(&mut self, asserted: bool) -> Result<(), DebugProbeError> {
+        let nreset = self.nreset;
+        let Some(lines) = self.reset_lines() else {
+            return Err(DebugProbeError::CommandNotSupportedByProbe {
+                command_name: "target_reset",
+            });
+        };
+        lines
+            .set(if asserted { 0 } else { nreset }, nreset)
+            .map_err(GpioError::from)?;
+        Ok(())
+    }
+}
+
+/// Waits for half a clock period. The GPIO accesses are slow enough for most speeds, so this only
+/// waits for the remaining time since `start`.
+fn wait_half_period(start: Instant, half_period: Duration) {
+    while start.elapsed() < half_period {
+        std::hint::spin_loop();
+    }
+}
+
+/// Clocks one bit on the GPIO lines. Outputs are driven while SWCLK is low, inputs are sampled
+/// before the rising edge.
+fn clock(
+    lines: &mut Lines,
+    half_period: Duration,
+    output: Option<bool>,
+) -> Result<bool, GpioError> {
+    let start = Instant::now();
+    match output {
+        Some(bit) => {
+            lines.set_inputs(0)?;
+            lines.set(if bit { SWDIO } else { 0 }, SWCLK | SWDIO)?;
+        }
+        None => {
+            lines.set_inputs(SWDIO)?;
+            lines.set(0, SWCLK)?;
+        }
+    }
+    wait_half_period(start, half_period);
+
+    let bit = match output {
+        Some(bit) => bit,
+        None => lines.get(SWDIO)? != 0,
+    };
+
+    let start = Instant::now();
+    lines.set(SWCLK, SWCLK)?;
+    wait_half_period(start, half_period);
+
+    Ok(bit)
+}
+
+/// Packs the bits of an SWD sequence into bytes for the SPI controller, which shifts them MSB
+/// first.
+///
+/// Inputs are shifted out as 1, which the target overrides through the resistor. The last byte is
+/// padded with 0, which are idle cycles for the target.
+fn pack_spi_bits(items: &[Option<bool>]) -> Vec<u8> {
+    let mut bytes = vec![0; items.len().div_ceil(8)];
+    for (i, item) in items.iter().enumerate() {
+        if item.unwrap_or(true) {
+            bytes[i / 8] |= 0x80 >> (i % 8);
+        }
+    }
+    bytes
+}
+
+/// Unpacks the bits of an SWD sequence from the bytes received by the SPI controller. The outputs
+/// are returned as they were driven.
+fn unpack_spi_bits(items: &[Option<bool>], received: &[u8]) -> Vec<bool> {
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| item.unwrap_or(received[i / 8] & (0x80 >> (i % 8)) != 0))
+        .collect()
+}
+
+impl DebugProbe for GpioProbe {
+    fn get_name(&self) -> &str {
+        "GPIO SWD"
+    }
+
+    fn speed_khz(&self) -> u32 {
+        self.speed_khz
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        Ok(self.set_speed_khz(speed_khz))
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        Ok(())
+    }
+
+    fn detach(&mut self) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.set_reset(true)?;
+        std::thread::sleep(Duration::from_millis(10));
+        self.set_reset(false)
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        self.set_reset(true)
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        self.set_reset(false)
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        match protocol {
+            WireProtocol::Swd => Ok(()),
+            _ => Err(DebugProbeError::UnsupportedProtocol(protocol)),
+        }
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        Some(WireProtocol::Swd)
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+
+    fn has_arm_interface(&self) -> bool {
+        true
+    }
+
+    fn try_get_arm_debug_interface<'probe>(
+        self: Box<Self>,
+        sequence: Arc<dyn ArmDebugSequence>,
+    ) -> Result<Box<dyn ArmDebugInterface + 'probe>, (Box<dyn DebugProbe>, ArmError)> {
+        Ok(ArmCommunicationInterface::create(self, sequence, true))
+    }
+
+    fn record_swd_trace(&mut self, recorder: SwdTraceRecorder) -> Result<(), DebugProbeError> {
+        self.probe_statistics.record_swd_trace(recorder)
+    }
+}
+
+impl RawSwdIo for GpioProbe {
+    fn swd_io<S>(&mut self, swdio: S) -> Result<Vec<bool>, DebugProbeError>
+    where
+        S: IntoIterator<Item = IoSequenceItem>,
+    {
+        self.probe_statistics.report_io();
+
+        let items = swdio
+            .into_iter()
+            .map(|item| match item {
+                IoSequenceItem::Output(bit) => Some(bit),
+                IoSequenceItem::Input => None,
+            })
+            .collect::<Vec<_>>();
+
+        let bits = match &mut self.driver {
+            SwdDriver::Gpio(lines) => items
+                .iter()
+                .map(|output| clock(lines, self.half_period, *output))
+                .collect::<Result<Vec<_>, _>>()?,
+            SwdDriver::Spi { device, .. } => {
+                let received = device
+                    .transfer(&pack_spi_bits(&items), self.speed_khz * 1000)
+                    .map_err(GpioError::from)?;
+                unpack_spi_bits(&items, &received)
+            }
+        };
+
+        Ok(bits)
+    }
+
+    fn swj_pins(
+        &mut self,
+        pin_out: u32,
+        pin_select: u32,
+        _pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
+        // With an SPI controller, SWCLK and SWDIO are not GPIO lines.
+        let (swd_pins, swd_lines) = match self.driver {
+            SwdDriver::Gpio(_) => (PIN_SWCLK | PIN_SWDIO, SWCLK | SWDIO),
+            SwdDriver::Spi { .. } => (0, 0),
+        };
+        let nreset = self.nreset;
+        let nreset_pin = if nreset != 0 { PIN_NRESET } else { 0 };
+        if pin_select & !(swd_pins | nreset_pin) != 0 {
+            return Err(DebugProbeError::CommandNotSupportedByProbe {
+                command_name: "swj_pins",
+            });
+        }
+        let pin_lines = [
+            (PIN_SWCLK, SWCLK & swd_lines),
+            (PIN_SWDIO, SWDIO & swd_lines),
+            (PIN_NRESET, nreset),
+        ];
+
+        let mut values = 0;
+        let mut mask = 0;
+        for (pin, line) in pin_lines {
+            if pin_select & pin != 0 {
+                mask |= line;
+                if pin_out & pin != 0 {
+                    values |= line;
+                }
+            }
+        }
+
+        let lines = match &mut self.driver {
+            SwdDriver::Gpio(lines) => lines,
+            SwdDriver::Spi {
+                lines: Some(lines), ..
+            } => lines,
+            SwdDriver::Spi { lines: None, .. } => return Ok(0),
+        };
+        if mask & SWDIO & swd_lines != 0 {
+            lines.set_inputs(0).map_err(GpioError::from)?;
+        }
+        lines.set(values, mask).map_err(GpioError::from)?;
+
+        let levels = lines.get(swd_lines | nreset).map_err(GpioError::from)?;
+        let mut pins = 0;
+        for (pin, line) in pin_lines {
+            if line != 0 && levels & line != 0 {
+                pins |= pin;
+            }
+        }
+
+        Ok(pins)
+    }
+
+    fn swd_settings(&self) -> &SwdSettings {
+        &self.swd_settings
+    }
+
+    fn probe_statistics(&mut self) -> &mut ProbeStatistics {
+        &mut self.probe_statistics
+    }
+}
+
+impl RawJtagIo for GpioProbe {
+    fn shift_bit(&mut self, _tms: bool, _tdi: bool, _capture: bool) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::UnsupportedProtocol(WireProtocol::Jtag))
+    }
+
+    fn read_captured_bits(&mut self) -> Result<BitVec, DebugProbeError> {
+        Err(DebugProbeError::UnsupportedProtocol(WireProtocol::Jtag))
+    }
+
+    fn state_mut(&mut self) -> &mut JtagDriverState {
+        &mut self.jtag_state
+    }
+
+    fn state(&self) -> &JtagDriverState {
+        &self.jtag_state
+    }
+}
+
+impl AutoImplementJtagAccess for GpioProbe {}
+impl DapProbe for GpioProbe {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_selector() {
+        assert_eq!(
+            GpioPins::parse("gpiod://chip0?swclk=11&swdio=8").unwrap(),
+            GpioPins {
+                chip: PathBuf::from("/dev/gpiochip0"),
+                swd: SwdPins::Gpio {
+                    swclk: 11,
+                    swdio: 8
+                },
+                nreset: None,
+            }
+        );
+        assert_eq!(
+            GpioPins::parse("gpiod:///dev/gpiochip4?swdio=8&swclk=11&nreset=25").unwrap(),
+            GpioPins {
+                chip: PathBuf::from("/dev/gpiochip4"),
+                swd: SwdPins::Gpio {
+                    swclk: 11,
+                    swdio: 8
+                },
+                nreset: Some(25),
+            }
+        );
+        assert_eq!(
+            GpioPins::parse("gpiod://gpiochip0?spidev=spidev0.0&nreset=25").unwrap(),
+            GpioPins {
+                chip: PathBuf::from("/dev/gpiochip0"),
+                swd: SwdPins::Spi(PathBuf::from("/dev/spidev0.0")),
+                nreset: Some(25),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_invalid_selector() {
+        assert!(matches!(
+            GpioPins::parse("gpiod://chip0?swclk=11"),
+            Err(GpioError::MissingPin("swdio"))
+        ));
+        assert!(matches!(
+            GpioPins::parse("gpiod://chip0?swclk=11&swdio=8&tck=3"),
+            Err(GpioError::InvalidSelector(_))
+        ));
+        assert!(matches!(
+            GpioPins::parse("gpiod://?swclk=11&swdio=8"),
+            Err(GpioError::InvalidSelector(_))
+        ));
+        assert!(matches!(
+            GpioPins::parse("gpiod://chip0?spidev=spidev0.0&swclk=11"),
+            Err(GpioError::InvalidSelector(_))
+        ));
+    }
+
+    #[test]
+    fn spi_bits() {
+        // A request, the turnaround and the acknowledge of a read.
+        let items = [
+            Some(true),
+            Some(false),
+            Some(true),
+            Some(false),
+            Some(false),
+            Some(true),
+            Some(false),
+            Some(true),
+            None,
+            None,
+            None,
+            None,
+        ];
+
+        assert_eq!(pack_spi_bits(&items), [0b1010_0101, 0b1111_0000]);
+        assert_eq!(
+            unpack_spi_bits(&items, &[0b0000_0000, 0b0100_0000]),
+            [
+                true, false, true, false, false, true, false, true, false, true, false, false
+            ]
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! Shifting bits with the SPI controller of a Linux host, through the spidev device.
+//!
+//! See `include/uapi/linux/spi/spidev.h` in the kernel sources for the definitions.
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+
+/// The default size of the transfer buffer of the spidev driver.
+const MAX_TRANSFER_SIZE: usize = 4096;
+
+const SPI_MODE_0: u8 = 0;
+
+const SPI_IOC_MESSAGE_1: u32 = iow(0, size_of::<SpiTransfer>());
+const SPI_IOC_WR_MODE: u32 = iow(1, size_of::<u8>());
+const SPI_IOC_WR_BITS_PER_WORD: u32 = iow(3, size_of::<u8>());
+
+/// The `_IOW` macro of the kernel, for the spidev ioctls.
+const fn iow(nr: u32, size: usize) -> u32 {
+    (1 << 30) | ((size as u32) << 16) | ((b'k' as u32) << 8) | nr
+}
+
+#[repr(C)]
+#[derive(Debug, Default)]
+struct SpiTransfer {
+    tx_buf: u64,
+    rx_buf: u64,
+    len: u32,
+    speed_hz: u32,
+    delay_usecs: u16,
+    bits_per_word: u8,
+    cs_change: u8,
+    tx_nbits: u8,
+    rx_nbits: u8,
+    word_delay_usecs: u8,
+    padding: u8,
+}
+
+const _: () = assert!(size_of::<SpiTransfer>() == 32);
+
+/// An SPI device, which shifts bytes MSB first in SPI mode 0.
+#[derive(Debug)]
+pub(super) struct SpiDevice {
+    file: File,
+}
+
+impl SpiDevice {
+    /// Opens the spidev device `path`, e.g. `/dev/spidev0.0`.
+    pub(super) fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let device = Self { file };
+
+        // SCLK idles low, and the bits are sampled on its rising edge.
+        let mut mode = SPI_MODE_0;
+        device.ioctl(SPI_IOC_WR_MODE, &mut mode)?;
+        let mut bits_per_word = 8u8;
+        device.ioctl(SPI_IOC_WR_BITS_PER_WORD, &mut bits_per_word)?;
+
+        Ok(device)
+    }
+
+    /// Shifts out `data` at `speed_hz`, and returns the bytes which were shifted in at the same
+    /// time.
+    pub(super) fn transfer(&mut self, data: &[u8], speed_hz: u32) -> io::Result<Vec<u8>> {
+        let mut received = vec![0; data.len()];
+
+        for (tx, rx) in data
+            .chunks(MAX_TRANSFER_SIZE)
+            .zip(received.chunks_mut(MAX_TRANSFER_SIZE))
+        {
+            let mut transfer = SpiTransfer {
+                tx_buf: tx.as_ptr() as u64,
+                rx_buf: rx.as_mut_ptr() as u64,
+                len: tx.len() as u32,
+                speed_hz,
+                bits_per_word: 8,
+                ..Default::default()
+            };
+            self.ioctl(SPI_IOC_MESSAGE_1, &mut transfer)?;
+        }
+
+        Ok(received)
+    }
+
+    fn ioctl<T>(&self, request: u32, argument: &mut T) -> io::Result<()> {
+        // SAFETY: `argument` is the structure which the kernel expects for `request`, and the
+        // buffers of a transfer are valid for its length.
+        let result =
+            unsafe { libc::ioctl(self.file.as_raw_fd(), request as _, argument as *mut T) };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ioctl_numbers() {
+        assert_eq!(SPI_IOC_MESSAGE_1, 0x4020_6B00);
+        assert_eq!(SPI_IOC_WR_MODE, 0x4001_6B01);
+        assert_eq!(SPI_IOC_WR_BITS_PER_WORD, 0x4001_6B03);
+    }
+}
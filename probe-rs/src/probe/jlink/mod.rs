@@ -44,9 +44,9 @@ use crate::{
         riscv::{communication_interface::RiscvInterfaceBuilder, dtm::jtag_dtm::JtagDtmBuilder},
     },
     probe::{
-        DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector, IoSequenceItem,
-        JtagDriverState, ProbeFactory, ProbeStatistics, RawJtagIo, RawSwdIo, SwdSettings,
-        WireProtocol,
+        DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector, FirmwareInfo,
+        IoSequenceItem, JtagDriverState, ProbeFactory, ProbeStatistics, RawJtagIo, RawSwdIo,
+        SwdSettings, WireProtocol, swd_trace::SwdTraceRecorder,
     },
 };
 
@@ -1131,6 +1131,14 @@ impl DebugProbe for JLink {
         Ok(ArmCommunicationInterface::create(self, sequence, true))
     }
 
+    fn firmware_info(&mut self) -> Result<Option<FirmwareInfo>, DebugProbeError> {
+        // The version string contains the build date, which is not comparable to a known version.
+        let mut info = FirmwareInfo::new(self.read_firmware_version()?);
+        info.update_tool = Some("J-Link Commander (SEGGER J-Link Software Pack)".to_string());
+
+        Ok(Some(info))
+    }
+
     fn get_target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
         // Convert the integer millivolts value from self.handle to volts as an f32.
         Ok(Some((self.read_target_voltage()? as f32) / 1000f32))
@@ -1154,6 +1162,10 @@ impl DebugProbe for JLink {
     fn has_xtensa_interface(&self) -> bool {
         self.supported_protocols.contains(&WireProtocol::Jtag)
     }
+
+    fn record_swd_trace(&mut self, recorder: SwdTraceRecorder) -> Result<(), DebugProbeError> {
+        self.probe_statistics.record_swd_trace(recorder)
+    }
 }
 
 impl RawSwdIo for JLink {
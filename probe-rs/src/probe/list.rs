@@ -124,6 +124,8 @@ impl AllProbesLister {
         &sifliuart::SifliUartFactory,
         &glasgow::GlasgowFactory,
         &ch347usbjtag::Ch347UsbJtagFactory,
+        #[cfg(target_os = "linux")]
+        &super::gpiod::GpioProbeFactory,
     ];
 
     /// Create a new lister with all built-in probe drivers.
@@ -7,21 +7,25 @@ mod usb_interface;
 use crate::{
     MemoryInterface,
     architecture::arm::{
-        ArmError, DapAccess, FullyQualifiedApAddress, Pins, SwoAccess, SwoConfig, SwoMode,
+        ArmError, DapAccess, DapError, FullyQualifiedApAddress, Pins, SwoAccess, SwoConfig,
+        SwoMode,
         ap::{
             AccessPortType,
             memory_ap::{MemoryAp, MemoryApType},
             v1::valid_access_ports,
         },
         communication_interface::{ArmDebugInterface, DapProbe, SwdSequence},
-        dp::{DpAddress, DpRegisterAddress},
+        dp::{
+            Abort, Ctrl, DpAddress, DpRegister, DpRegisterAddress, FaultRecovery,
+            FaultRecoveryAccess,
+        },
         memory::ArmMemoryInterface,
         sequences::ArmDebugSequence,
         valid_32bit_arm_address,
     },
     probe::{
-        DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector, Probe, ProbeError,
-        ProbeFactory, WireProtocol,
+        DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector, FirmwareInfo,
+        FirmwareStatus, Probe, ProbeError, ProbeFactory, WireProtocol,
     },
 };
 
@@ -312,6 +316,10 @@ impl DebugProbe for StLink<StLinkUsbDevice> {
         Ok(Box::new(interface))
     }
 
+    fn firmware_info(&mut self) -> Result<Option<FirmwareInfo>, DebugProbeError> {
+        Ok(Some(self.firmware()))
+    }
+
     fn get_target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
         let mut buf = [0; 8];
         self.device
@@ -428,6 +436,29 @@ impl<D: StLinkUsb> StLink<D> {
             || self.hw_version >= 3
     }
 
+    /// Returns the firmware version, e.g. `V2J37`, and whether it should be updated.
+    fn firmware(&self) -> FirmwareInfo {
+        // Older V2 firmware lacks support for multiple APs and banked DP registers.
+        let (minimum, recommended) = if self.hw_version < 3 {
+            (Self::MIN_JTAG_VERSION, Self::MIN_JTAG_VERSION_DP_BANK_SEL)
+        } else {
+            (Self::MIN_JTAG_VERSION_V3, Self::MIN_JTAG_VERSION_V3)
+        };
+        let version = |jtag_version| format!("V{}J{}", self.hw_version, jtag_version);
+
+        FirmwareInfo {
+            version: version(self.jtag_version),
+            minimum: Some(version(minimum)),
+            recommended: Some(version(recommended)),
+            update_tool: Some("STM32CubeProgrammer".to_string()),
+            status: if self.jtag_version < recommended {
+                FirmwareStatus::UpdateRecommended
+            } else {
+                FirmwareStatus::Supported
+            },
+        }
+    }
+
     /// Commands the ST-Link to enter idle mode.
     /// Internal helper.
     fn enter_idle(&mut self) -> Result<(), StlinkError> {
@@ -812,7 +843,7 @@ impl<D: StLinkUsb> StLink<D> {
     }
 
     /// Reads the DAP register on the specified port and address.
-    fn read_register(&mut self, port: u16, addr: u8) -> Result<u32, DebugProbeError> {
+    fn read_register(&mut self, port: u16, addr: u8) -> Result<u32, StlinkError> {
         let port = port.to_le_bytes();
 
         let cmd = &[
@@ -830,7 +861,7 @@ impl<D: StLinkUsb> StLink<D> {
     }
 
     /// Writes a value to the DAP register on the specified port and address.
-    fn write_register(&mut self, port: u16, addr: u8, value: u32) -> Result<(), DebugProbeError> {
+    fn write_register(&mut self, port: u16, addr: u8, value: u32) -> Result<(), StlinkError> {
         let port = port.to_le_bytes();
         let bytes = value.to_le_bytes();
 
@@ -1341,7 +1372,11 @@ impl DapAccess for StlinkArmDebug {
         address: DpRegisterAddress,
     ) -> Result<u32, ArmError> {
         self.select_dp_and_dp_bank(dp, address)?;
-        let result = self.probe.read_register(DP_PORT, address.into())?;
+        let result = FaultRecovery::new().run(&mut *self.probe, |probe| {
+            probe
+                .read_register(DP_PORT, address.into())
+                .map_err(dap_error)
+        })?;
 
         tracing::Span::current().record("value", result);
 
@@ -1359,7 +1394,11 @@ impl DapAccess for StlinkArmDebug {
     ) -> Result<(), ArmError> {
         self.select_dp_and_dp_bank(dp, address)?;
 
-        self.probe.write_register(DP_PORT, address.into(), value)?;
+        FaultRecovery::new().run(&mut *self.probe, |probe| {
+            probe
+                .write_register(DP_PORT, address.into(), value)
+                .map_err(dap_error)
+        })?;
         Ok(())
     }
 
@@ -1375,9 +1414,12 @@ impl DapAccess for StlinkArmDebug {
         }
         self.select_ap_and_ap_bank(ap, address)?;
 
-        let value = self
-            .probe
-            .read_register(ap.ap_v1()? as u16, (address & 0xFF) as u8)?;
+        let port = ap.ap_v1()? as u16;
+        let value = FaultRecovery::new().run(&mut *self.probe, |probe| {
+            probe
+                .read_register(port, (address & 0xFF) as u8)
+                .map_err(dap_error)
+        })?;
 
         Ok(value)
     }
@@ -1395,8 +1437,12 @@ impl DapAccess for StlinkArmDebug {
         }
         self.select_ap_and_ap_bank(ap, address)?;
 
-        self.probe
-            .write_register(ap.ap_v1()? as u16, (address & 0xFF) as u8, value)?;
+        let port = ap.ap_v1()? as u16;
+        FaultRecovery::new().run(&mut *self.probe, |probe| {
+            probe
+                .write_register(port, (address & 0xFF) as u8, value)
+                .map_err(dap_error)
+        })?;
 
         Ok(())
     }
@@ -1845,6 +1891,36 @@ impl ArmMemoryInterface for StLinkMemoryInterface<'_> {
     }
 }
 
+impl<D: StLinkUsb> FaultRecoveryAccess for StLink<D> {
+    fn read_ctrl_stat(&mut self) -> Result<Ctrl, ArmError> {
+        let value = self
+            .read_register(DP_PORT, Ctrl::ADDRESS.into())
+            .map_err(dap_error)?;
+
+        Ok(Ctrl::try_from(value)?)
+    }
+
+    fn write_abort(&mut self, abort: Abort) -> Result<(), ArmError> {
+        self.write_register(DP_PORT, Abort::ADDRESS.into(), abort.into())
+            .map_err(dap_error)
+    }
+}
+
+/// Converts an error of a DAP register access into an [`ArmError`], so that FAULT responses
+/// can be handled by [`FaultRecovery`].
+fn dap_error(e: StlinkError) -> ArmError {
+    match e {
+        StlinkError::CommandFailed(
+            Status::SwdApFault
+            | Status::SwdDpFault
+            | Status::SwdApWdataError
+            | Status::SwdApStickyError
+            | Status::SwdApStickyorunError,
+        ) => DapError::FaultResponse.into(),
+        e => DebugProbeError::from(e).into(),
+    }
+}
+
 fn is_wait_error(e: &StlinkError) -> bool {
     matches!(
         e,
@@ -0,0 +1,335 @@
+//! Tracing of the SWD transfers of probes.
+//!
+//! Every transfer is logged with its request header, acknowledge, data and parity bit, as seen on
+//! the wire. This makes protocol issues visible without a logic analyzer. Probes which run the
+//! transfers in their firmware, like CMSIS-DAP probes, log the transfers as reported by the
+//! probe, with the parity bit derived from the data. Traces are stored as a
+//! text file with one transfer per line:
+//!
+//! ```text
+//! # probe-rs SWD trace v1
+//! # elapsed_ns request ack data parity
+//! 1041250 a5 1 2ba01477 0
+//! ```
+//!
+//! The request is the header byte with the start bit in bit 0, the acknowledge the three ACK bits
+//! with the first bit in bit 0. Data and parity are `-` if the transfer did not have a data phase.
+
+use std::fmt;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::time::{Duration, Instant};
+
+const HEADER: &str = "# probe-rs SWD trace v1";
+
+/// A single SWD transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwdTransfer {
+    /// The time since the start of the trace.
+    pub elapsed: Duration,
+    /// The request header, with the start bit in bit 0.
+    pub request: u8,
+    /// The acknowledge bits, with the first bit in bit 0.
+    pub ack: u8,
+    /// The data of the data phase, if there was one.
+    pub data: Option<u32>,
+    /// The parity bit of the data phase, if there was one.
+    pub parity: Option<bool>,
+}
+
+impl SwdTransfer {
+    /// Returns `true` if the transfer accesses an AP register.
+    pub fn is_ap(&self) -> bool {
+        self.request & (1 << 1) != 0
+    }
+
+    /// Returns `true` if the transfer is a read.
+    pub fn is_read(&self) -> bool {
+        self.request & (1 << 2) != 0
+    }
+
+    /// Returns the register address, `A[3:2]` of the request.
+    pub fn address(&self) -> u8 {
+        (self.request >> 1) & 0b1100
+    }
+
+    /// Returns `true` if the request header has a valid start, stop, park and parity bit.
+    pub fn is_request_valid(&self) -> bool {
+        let parity = (self.request >> 1) & 0b1111;
+        self.request & 0b1100_0001 == 0b1000_0001
+            && (parity.count_ones() % 2 == 1) == (self.request & (1 << 5) != 0)
+    }
+
+    /// Returns `true` if the parity bit matches the data, or if there was no data phase.
+    pub fn is_parity_valid(&self) -> bool {
+        match (self.data, self.parity) {
+            (Some(data), Some(parity)) => (data.count_ones() % 2 == 1) == parity,
+            _ => true,
+        }
+    }
+
+    /// Returns `true` if the transfer was acknowledged with OK, and the request and parity are valid.
+    pub fn is_ok(&self) -> bool {
+        self.ack == 0b001 && self.is_request_valid() && self.is_parity_valid()
+    }
+
+    fn ack_name(&self) -> &'static str {
+        match self.ack {
+            0b001 => "OK",
+            0b010 => "WAIT",
+            0b100 => "FAULT",
+            0b111 => "NO ACK",
+            _ => "INVALID",
+        }
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+        let elapsed = fields.next()?.parse().ok()?;
+        let request = u8::from_str_radix(fields.next()?, 16).ok()?;
+        let ack = fields.next()?.parse().ok()?;
+        let data = match fields.next()? {
+            "-" => None,
+            data => Some(u32::from_str_radix(data, 16).ok()?),
+        };
+        let parity = match fields.next()? {
+            "-" => None,
+            "0" => Some(false),
+            "1" => Some(true),
+            _ => return None,
+        };
+
+        Some(Self {
+            elapsed: Duration::from_nanos(elapsed),
+            request,
+            ack,
+            data,
+            parity,
+        })
+    }
+
+    fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        write!(
+            writer,
+            "{} {:02x} {}",
+            self.elapsed.as_nanos(),
+            self.request,
+            self.ack
+        )?;
+        match self.data {
+            Some(data) => write!(writer, " {data:08x}")?,
+            None => write!(writer, " -")?,
+        }
+        match self.parity {
+            Some(parity) => writeln!(writer, " {}", u8::from(parity)),
+            None => writeln!(writer, " -"),
+        }
+    }
+}
+
+/// Shows the transfer decoded, e.g. `    1.041250 ms  DP R 0x0  OK      0x2BA01477`.
+impl fmt::Display for SwdTransfer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>12.6} ms  {} {} 0x{:X}  {:<7}",
+            self.elapsed.as_secs_f64() * 1000.0,
+            if self.is_ap() { "AP" } else { "DP" },
+            if self.is_read() { "R" } else { "W" },
+            self.address(),
+            self.ack_name(),
+        )?;
+        if let Some(data) = self.data {
+            write!(f, " 0x{data:08X}")?;
+        }
+        if !self.is_request_valid() {
+            write!(f, "  invalid request {:#04x}", self.request)?;
+        }
+        if !self.is_parity_valid() {
+            write!(f, "  parity error")?;
+        }
+        Ok(())
+    }
+}
+
+/// The SWD request header of an access, with the start bit in bit 0.
+pub(crate) fn swd_request(ap_n_dp: bool, read: bool, a2: bool, a3: bool) -> u8 {
+    let parity = ap_n_dp ^ read ^ a2 ^ a3;
+
+    // Start and park bit set, stop bit cleared.
+    0b1000_0001
+        | (u8::from(ap_n_dp) << 1)
+        | (u8::from(read) << 2)
+        | (u8::from(a2) << 3)
+        | (u8::from(a3) << 4)
+        | (u8::from(parity) << 5)
+}
+
+/// The parity bit of the data phase for `data`.
+pub(crate) fn data_parity(data: u32) -> bool {
+    data.count_ones() % 2 == 1
+}
+
+/// Reads a trace written by a [`SwdTraceRecorder`].
+pub fn load_swd_trace(reader: impl BufRead) -> io::Result<Vec<SwdTransfer>> {
+    let mut lines = reader.lines();
+    if lines.next().transpose()?.as_deref() != Some(HEADER) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a SWD trace file",
+        ));
+    }
+
+    let mut transfers = Vec::new();
+    for line in lines {
+        let line = line?;
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        let transfer = SwdTransfer::parse(&line).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid SWD trace line '{line}'"),
+            )
+        })?;
+        transfers.push(transfer);
+    }
+    Ok(transfers)
+}
+
+/// Records the SWD transfers of a probe to a trace file, while the probe is in use.
+///
+/// Pass the recorder to [`Probe::record_swd_trace`](crate::probe::Probe::record_swd_trace).
+pub struct SwdTraceRecorder {
+    writer: BufWriter<Box<dyn Write + Send>>,
+    start: Instant,
+}
+
+impl fmt::Debug for SwdTraceRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SwdTraceRecorder")
+            .field("start", &self.start)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SwdTraceRecorder {
+    /// Creates a recorder which writes the trace to `writer`, usually a file.
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: BufWriter::new(Box::new(writer)),
+            start: Instant::now(),
+        }
+    }
+
+    /// Starts the recording.
+    pub(crate) fn begin(&mut self) -> io::Result<()> {
+        self.start = Instant::now();
+        writeln!(self.writer, "{HEADER}")?;
+        writeln!(self.writer, "# elapsed_ns request ack data parity")
+    }
+
+    /// Records a transfer.
+    pub(crate) fn transfer(
+        &mut self,
+        request: u8,
+        ack: u8,
+        data: Option<u32>,
+        parity: Option<bool>,
+    ) {
+        let transfer = SwdTransfer {
+            elapsed: self.start.elapsed(),
+            request,
+            ack,
+            data,
+            parity,
+        };
+        if let Err(error) = transfer.write(&mut self.writer) {
+            tracing::warn!("Failed to write the SWD trace: {error}");
+        }
+    }
+
+    /// Writes the recorded transfers to the file, after a batch of transfers.
+    pub(crate) fn flush(&mut self) {
+        if let Err(error) = self.writer.flush() {
+            tracing::warn!("Failed to write the SWD trace: {error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let transfers = [
+            SwdTransfer {
+                elapsed: Duration::from_nanos(1_041_250),
+                request: 0xA5,
+                ack: 0b001,
+                data: Some(0x2BA0_1477),
+                parity: Some(false),
+            },
+            SwdTransfer {
+                elapsed: Duration::from_nanos(1_100_000),
+                request: 0xA9,
+                ack: 0b010,
+                data: None,
+                parity: None,
+            },
+        ];
+
+        let mut file = format!("{HEADER}\n# elapsed_ns request ack data parity\n").into_bytes();
+        for transfer in &transfers {
+            transfer.write(&mut file).unwrap();
+        }
+        assert_eq!(
+            String::from_utf8_lossy(&file).lines().nth(2),
+            Some("1041250 a5 1 2ba01477 0")
+        );
+
+        assert_eq!(load_swd_trace(&file[..]).unwrap(), transfers);
+        assert!(load_swd_trace(&b"1041250 a5 1 - -\n"[..]).is_err());
+    }
+
+    #[test]
+    fn decode() {
+        // A read of DPIDR.
+        let read = SwdTransfer {
+            elapsed: Duration::from_nanos(1_041_250),
+            request: 0xA5,
+            ack: 0b001,
+            data: Some(0x2BA0_1477),
+            parity: Some(false),
+        };
+        assert!(!read.is_ap());
+        assert!(read.is_read());
+        assert_eq!(read.address(), 0x0);
+        assert!(read.is_request_valid());
+        assert!(read.is_parity_valid());
+        assert!(read.is_ok());
+        assert_eq!(
+            read.to_string(),
+            "    1.041250 ms  DP R 0x0  OK      0x2BA01477"
+        );
+
+        // A write of the AP register 0xC with a wrong parity bit.
+        let write = SwdTransfer {
+            elapsed: Duration::from_nanos(2_000_000),
+            request: 0xBB,
+            ack: 0b100,
+            data: Some(1),
+            parity: Some(false),
+        };
+        assert!(write.is_ap());
+        assert!(!write.is_read());
+        assert_eq!(write.address(), 0xC);
+        assert!(write.is_request_valid());
+        assert!(!write.is_parity_valid());
+        assert!(!write.is_ok());
+        assert_eq!(
+            write.to_string(),
+            "    2.000000 ms  AP W 0xC  FAULT   0x00000001  parity error"
+        );
+    }
+}
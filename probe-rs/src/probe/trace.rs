@@ -0,0 +1,397 @@
+//! Recording and replay of the low-level transactions of a debug probe.
+//!
+//! A trace contains every command sent to the probe, the response of the probe and the timing of both. Traces are
+//! stored in a compact binary file, which users can attach to bug reports. Replaying the trace with
+//! [`Probe::replay_trace`](crate::probe::Probe::replay_trace) feeds the recorded responses back into probe-rs,
+//! which reproduces protocol issues without the hardware of the user.
+
+use std::collections::VecDeque;
+use std::io::{self, BufWriter, Read, Write};
+use std::time::{Duration, Instant};
+
+/// The magic bytes at the start of a trace file.
+const MAGIC: &[u8; 8] = b"PRSTRACE";
+
+/// The version of the trace file format.
+const VERSION: u8 = 1;
+
+/// A trace of the transactions of a debug probe, see the [module level documentation](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProbeTrace {
+    /// The name of the probe driver which recorded the trace, e.g. `CMSIS-DAP`.
+    pub probe: String,
+    /// The transactions, in the order they were sent to the probe.
+    pub transactions: Vec<Transaction>,
+}
+
+/// A command sent to the probe and its response.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transaction {
+    /// The time since the start of the recording, when the command was sent.
+    pub elapsed: Duration,
+    /// The time until the response was received.
+    pub duration: Duration,
+    /// The raw bytes of the command.
+    pub command: Vec<u8>,
+    /// The raw bytes of the response, or `None` if no response was received.
+    pub response: Option<Vec<u8>>,
+}
+
+impl ProbeTrace {
+    /// Reads a trace from a trace file.
+    pub fn load(mut reader: impl Read) -> io::Result<Self> {
+        let probe = read_header(&mut reader)?;
+        let mut transactions = Vec::new();
+        while let Some(transaction) = read_transaction(&mut reader)? {
+            transactions.push(transaction);
+        }
+        Ok(Self {
+            probe,
+            transactions,
+        })
+    }
+
+    /// Writes the trace to a trace file.
+    pub fn save(&self, writer: impl Write) -> io::Result<()> {
+        let mut writer = BufWriter::new(writer);
+        write_header(&mut writer, &self.probe)?;
+        for transaction in &self.transactions {
+            write_transaction(&mut writer, transaction)?;
+        }
+        writer.flush()
+    }
+}
+
+/// Records the transactions of a probe to a trace file, while the probe is in use.
+///
+/// Pass the recorder to [`Probe::record_trace`](crate::probe::Probe::record_trace). Transactions are written as
+/// they happen, so the trace contains everything up to a crash of the program.
+pub struct TraceRecorder {
+    writer: BufWriter<Box<dyn Write + Send>>,
+    start: Instant,
+    /// The commands waiting for a response, oldest first.
+    pending: VecDeque<(Instant, Vec<u8>)>,
+}
+
+impl std::fmt::Debug for TraceRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TraceRecorder")
+            .field("start", &self.start)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TraceRecorder {
+    /// Creates a recorder which writes the trace to `writer`, usually a file.
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: BufWriter::new(Box::new(writer)),
+            start: Instant::now(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Starts the recording for the probe driver `probe`.
+    pub(crate) fn begin(&mut self, probe: &str) -> io::Result<()> {
+        self.start = Instant::now();
+        self.pending.clear();
+        write_header(&mut self.writer, probe)
+    }
+
+    /// Records a command which was sent to the probe.
+    pub(crate) fn command(&mut self, command: &[u8]) {
+        self.pending.push_back((Instant::now(), command.to_vec()));
+    }
+
+    /// Records the response to the oldest command without a response, or `None` if receiving it failed.
+    pub(crate) fn response(&mut self, response: Option<&[u8]>) {
+        let Some((sent, command)) = self.pending.pop_front() else {
+            return;
+        };
+
+        let transaction = Transaction {
+            elapsed: sent.duration_since(self.start),
+            duration: sent.elapsed(),
+            command,
+            response: response.map(<[u8]>::to_vec),
+        };
+        if let Err(error) = write_transaction(&mut self.writer, &transaction) {
+            tracing::warn!("Failed to write the probe trace: {error}");
+        }
+    }
+}
+
+/// The reason a replayed trace did not match the commands sent to the probe.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum TraceError {
+    /// The command {command:02X?} was sent after the end of the recorded trace.
+    Ended {
+        /// The command which was sent.
+        command: Vec<u8>,
+    },
+
+    /// Transaction {index} diverged from the recorded trace. The command {command:02X?} was sent instead of {recorded:02X?}.
+    Diverged {
+        /// The index of the transaction in the trace.
+        index: usize,
+        /// The command which was sent.
+        command: Vec<u8>,
+        /// The command in the trace.
+        recorded: Vec<u8>,
+    },
+}
+
+/// Plays back the responses of a recorded trace, as long as the same commands are sent.
+#[derive(Debug, Default)]
+pub(crate) struct TraceReplay {
+    transactions: VecDeque<Transaction>,
+    index: usize,
+    /// The responses to the commands which were sent, oldest first.
+    pending: VecDeque<Option<Vec<u8>>>,
+}
+
+impl TraceReplay {
+    pub(crate) fn new(trace: ProbeTrace) -> Self {
+        Self {
+            transactions: trace.transactions.into(),
+            index: 0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Checks that `command` is the next command of the trace.
+    ///
+    /// Trailing zeros are ignored, since they depend on the packet size of the transport.
+    pub(crate) fn command(&mut self, command: &[u8]) -> Result<(), TraceError> {
+        let Some(transaction) = self.transactions.pop_front() else {
+            return Err(TraceError::Ended {
+                command: trim_zeros(command).to_vec(),
+            });
+        };
+
+        let index = self.index;
+        self.index += 1;
+        if trim_zeros(command) != trim_zeros(&transaction.command) {
+            return Err(TraceError::Diverged {
+                index,
+                command: trim_zeros(command).to_vec(),
+                recorded: trim_zeros(&transaction.command).to_vec(),
+            });
+        }
+
+        self.pending.push_back(transaction.response);
+        Ok(())
+    }
+
+    /// Returns the recorded response to the oldest command without a response, or `None` if the
+    /// probe did not respond.
+    pub(crate) fn response(&mut self) -> Option<Vec<u8>> {
+        self.pending.pop_front().flatten()
+    }
+}
+
+fn trim_zeros(bytes: &[u8]) -> &[u8] {
+    let length = bytes
+        .iter()
+        .rposition(|byte| *byte != 0)
+        .map_or(0, |i| i + 1);
+    &bytes[..length]
+}
+
+fn write_header(writer: &mut impl Write, probe: &str) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[VERSION])?;
+    write_bytes(writer, probe.as_bytes())
+}
+
+fn read_header(reader: &mut impl Read) -> io::Result<String> {
+    let mut magic = [0; MAGIC.len()];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a probe trace file",
+        ));
+    }
+
+    let mut version = [0; 1];
+    reader.read_exact(&mut version)?;
+    if version[0] != VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported probe trace version {}", version[0]),
+        ));
+    }
+
+    let probe = read_bytes(reader)?;
+    String::from_utf8(probe).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// Writes a transaction as its timing in microseconds, followed by the command and the response.
+fn write_transaction(writer: &mut impl Write, transaction: &Transaction) -> io::Result<()> {
+    writer.write_all(&(transaction.elapsed.as_micros() as u64).to_le_bytes())?;
+    writer.write_all(&(transaction.duration.as_micros() as u32).to_le_bytes())?;
+    write_bytes(writer, &transaction.command)?;
+    match &transaction.response {
+        Some(response) => {
+            writer.write_all(&[1])?;
+            write_bytes(writer, response)
+        }
+        None => writer.write_all(&[0]),
+    }
+}
+
+/// Reads a transaction, or returns `None` at the end of the trace.
+fn read_transaction(reader: &mut impl Read) -> io::Result<Option<Transaction>> {
+    let mut elapsed = [0; 8];
+    match reader.read_exact(&mut elapsed) {
+        Ok(()) => {}
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(error) => return Err(error),
+    }
+    let mut duration = [0; 4];
+    reader.read_exact(&mut duration)?;
+    let command = read_bytes(reader)?;
+    let mut has_response = [0; 1];
+    reader.read_exact(&mut has_response)?;
+    let response = if has_response[0] != 0 {
+        Some(read_bytes(reader)?)
+    } else {
+        None
+    };
+
+    Ok(Some(Transaction {
+        elapsed: Duration::from_micros(u64::from_le_bytes(elapsed)),
+        duration: Duration::from_micros(u32::from_le_bytes(duration).into()),
+        command,
+        response,
+    }))
+}
+
+/// Writes the length of `bytes`, the length without trailing zeros and the bytes without the trailing zeros.
+///
+/// USB packets are padded with zeros, so this makes traces a lot smaller.
+fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    let trimmed = trim_zeros(bytes);
+    writer.write_all(&(bytes.len() as u16).to_le_bytes())?;
+    writer.write_all(&(trimmed.len() as u16).to_le_bytes())?;
+    writer.write_all(trimmed)
+}
+
+fn read_bytes(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut length = [0; 2];
+    reader.read_exact(&mut length)?;
+    let mut trimmed_length = [0; 2];
+    reader.read_exact(&mut trimmed_length)?;
+
+    let length = u16::from_le_bytes(length) as usize;
+    let trimmed_length = u16::from_le_bytes(trimmed_length) as usize;
+    if trimmed_length > length {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "corrupted probe trace",
+        ));
+    }
+
+    let mut bytes = vec![0; length];
+    reader.read_exact(&mut bytes[..trimmed_length])?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn trace() -> ProbeTrace {
+        ProbeTrace {
+            probe: "CMSIS-DAP".to_string(),
+            transactions: vec![
+                Transaction {
+                    elapsed: Duration::from_micros(10),
+                    duration: Duration::from_micros(250),
+                    command: vec![0, 0x00, 0xFF, 0, 0, 0],
+                    response: Some(vec![0x00, 0x02, 0x40, 0x00, 0, 0, 0, 0]),
+                },
+                Transaction {
+                    elapsed: Duration::from_millis(3),
+                    duration: Duration::from_secs(1),
+                    command: vec![0, 0x00, 0xFF],
+                    response: None,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn save_and_load() {
+        let trace = trace();
+        let mut file = Vec::new();
+        trace.save(&mut file).unwrap();
+
+        assert_eq!(ProbeTrace::load(file.as_slice()).unwrap(), trace);
+    }
+
+    /// A file in memory, which can be read while the recorder owns it.
+    #[derive(Clone, Default)]
+    struct SharedFile(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for SharedFile {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn record() {
+        let trace = trace();
+        let file = SharedFile::default();
+        let mut recorder = TraceRecorder::new(file.clone());
+        recorder.begin(&trace.probe).unwrap();
+        for transaction in &trace.transactions {
+            recorder.command(&transaction.command);
+            recorder.response(transaction.response.as_deref());
+        }
+        drop(recorder);
+
+        let recorded = ProbeTrace::load(file.0.lock().unwrap().as_slice()).unwrap();
+        assert_eq!(recorded.probe, trace.probe);
+        assert_eq!(recorded.transactions.len(), 2);
+        for (recorded, transaction) in recorded.transactions.iter().zip(&trace.transactions) {
+            assert_eq!(recorded.command, transaction.command);
+            assert_eq!(recorded.response, transaction.response);
+        }
+    }
+
+    #[test]
+    fn replay() {
+        let mut replay = TraceReplay::new(trace());
+
+        // Trailing zeros are ignored.
+        replay.command(&[0, 0x00, 0xFF]).unwrap();
+        assert_eq!(
+            replay.response(),
+            Some(vec![0x00, 0x02, 0x40, 0x00, 0, 0, 0, 0])
+        );
+
+        assert!(matches!(
+            replay.command(&[0, 0x01]),
+            Err(TraceError::Diverged { index: 1, .. })
+        ));
+        assert!(matches!(
+            replay.command(&[0, 0x00, 0xFF]),
+            Err(TraceError::Ended { .. })
+        ));
+    }
+
+    #[test]
+    fn load_invalid_file() {
+        assert_eq!(
+            ProbeTrace::load(&b"PRSTRACX\x01"[..]).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
@@ -9,6 +9,8 @@ pub enum CommandId {
     Control = 0x0D,
     /// Config chip, flash protection, etc
     ConfigChip = 0x01,
+    /// Flash programming
+    Program = 0x02,
     /// Chip reset
     Reset = 0x0b,
     /// Set chip type and connection speed
@@ -296,7 +298,7 @@ impl WchLinkCommand for ResetTarget {
     }
 }
 
-/// Check flash protection status
+/// Check flash protection status, responds with [`FLASH_PROTECTED`] or [`FLASH_UNPROTECTED`]
 #[derive(Debug)]
 pub struct CheckFlashProtection;
 impl WchLinkCommand for CheckFlashProtection {
@@ -308,7 +310,12 @@ impl WchLinkCommand for CheckFlashProtection {
     }
 }
 
-/// Unprotect flash
+/// The flash is read protected
+pub const FLASH_PROTECTED: u8 = 0x01;
+/// The flash is not read protected
+pub const FLASH_UNPROTECTED: u8 = 0x02;
+
+/// Unprotect flash, this erases the flash if it was read protected
 #[derive(Debug)]
 pub struct UnprotectFlash;
 impl WchLinkCommand for UnprotectFlash {
@@ -319,3 +326,15 @@ impl WchLinkCommand for UnprotectFlash {
         vec![0x02]
     }
 }
+
+/// Erase the whole code flash
+#[derive(Debug)]
+pub struct EraseFlash;
+impl WchLinkCommand for EraseFlash {
+    const COMMAND_ID: CommandId = CommandId::Program;
+    type Response = ();
+
+    fn payload(&self) -> Vec<u8> {
+        vec![0x01]
+    }
+}
@@ -18,8 +18,8 @@ use crate::{
         dtm::jtag_dtm::JtagDtmBuilder,
     },
     probe::{
-        DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector, JtagSequence, ProbeError,
-        ProbeFactory, WireProtocol,
+        DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector, FirmwareInfo,
+        FirmwareStatus, JtagSequence, ProbeError, ProbeFactory, WireProtocol,
     },
 };
 
@@ -175,6 +175,7 @@ impl ProbeFactory for WchLinkFactory {
             last_dmi_read: None,
             speed: Speed::default(),
             idle_cycles: 0,
+            flash_protected: false,
         };
 
         wlink.init()?;
@@ -202,6 +203,8 @@ pub struct WchLink {
     last_dmi_read: Option<(u8, u32, u8)>,
     speed: commands::Speed,
     idle_cycles: u8,
+    /// The flash of the attached chip is read protected
+    flash_protected: bool,
 }
 
 impl fmt::Debug for WchLink {
@@ -216,6 +219,7 @@ impl fmt::Debug for WchLink {
             .field("last_dmi_read", &self.last_dmi_read)
             .field("speed", &self.speed)
             .field("idle_cycles", &self.idle_cycles)
+            .field("flash_protected", &self.flash_protected)
             .finish()
     }
 }
@@ -260,6 +264,20 @@ impl WchLink {
         Ok(())
     }
 
+    /// Returns `true` if the flash of the attached chip is read protected.
+    fn check_flash_protection(&mut self) -> Result<bool, DebugProbeError> {
+        match self.device.send_command(commands::CheckFlashProtection)? {
+            commands::FLASH_PROTECTED => Ok(true),
+            commands::FLASH_UNPROTECTED => Ok(false),
+            other => {
+                tracing::warn!(
+                    "Unknown flash protection status {other:#04x}, assuming unprotected"
+                );
+                Ok(false)
+            }
+        }
+    }
+
     fn dmi_op_read(&mut self, addr: u8) -> Result<(u8, u32, u8), DebugProbeError> {
         let resp = self.device.send_command(commands::DmiOp::read(addr))?;
 
@@ -290,6 +308,17 @@ impl DebugProbe for WchLink {
         self.speed.to_khz()
     }
 
+    fn firmware_info(&mut self) -> Result<Option<FirmwareInfo>, DebugProbeError> {
+        // Older firmware is rejected when the probe is opened.
+        Ok(Some(FirmwareInfo {
+            version: format!("{}.{}", self.v_major, self.v_minor),
+            minimum: Some("2.7".to_string()),
+            recommended: None,
+            update_tool: Some("WCH-LinkUtility".to_string()),
+            status: FirmwareStatus::Supported,
+        }))
+    }
+
     fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
         let speed =
             Speed::from_khz(speed_khz).ok_or(DebugProbeError::UnsupportedSpeed(speed_khz))?;
@@ -315,10 +344,46 @@ impl DebugProbe for WchLink {
 
         self.chip_id = resp.chip_id;
 
-        if self.chip_family.support_flash_protect() {
-            self.device.send_command(commands::CheckFlashProtection)?;
+        // Unprotecting the flash erases it, so this is left to `erase_all`.
+        self.flash_protected =
+            self.chip_family.support_flash_protect() && self.check_flash_protection()?;
+        if self.flash_protected {
+            tracing::warn!("The flash of the chip is read protected");
+        }
+
+        Ok(())
+    }
+
+    fn target_flash_protected(&self) -> bool {
+        self.flash_protected
+    }
+
+    fn has_erase_all(&self) -> bool {
+        self.chip_family.support_flash_protect()
+    }
+
+    fn erase_all(&mut self) -> Result<(), DebugProbeError> {
+        if !self.has_erase_all() {
+            return Err(DebugProbeError::CommandNotSupportedByProbe {
+                command_name: "erase_all",
+            });
+        }
+
+        if self.flash_protected {
+            tracing::info!("Removing the read protection of the flash");
             self.device.send_command(commands::UnprotectFlash)?;
         }
+        self.device.send_command(commands::EraseFlash)?;
+
+        // The chip has to be reset for the new protection state to take effect.
+        self.device.send_command(commands::ResetTarget)?;
+        std::thread::sleep(Duration::from_millis(20));
+        self.device.send_command(commands::AttachChip)?;
+
+        self.flash_protected = self.check_flash_protection()?;
+        if self.flash_protected {
+            return Err(WchLinkError::StillProtected.into());
+        }
 
         Ok(())
     }
@@ -571,6 +636,8 @@ pub(crate) enum WchLinkError {
     UnknownChip(u8),
     /// Unsupported operation.
     UnsupportedOperation,
+    /// The flash is still read protected after erasing it.
+    StillProtected,
 }
 
 impl ProbeError for WchLinkError {}
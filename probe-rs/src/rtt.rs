@@ -46,9 +46,14 @@
 //! # });
 //! ```
 
+mod batch;
 mod channel;
+pub use batch::read_up_channels_batched;
 pub use channel::*;
 
+pub mod decoder;
+pub mod terminal;
+
 use crate::Session;
 use crate::{Core, MemoryInterface, config::MemoryRegion};
 use std::ops::Range;
@@ -434,6 +439,84 @@ impl Rtt {
         let is_64_bit = core.is_64_bit();
         RttControlBlockHeader::minimal_header_size(is_64_bit)
     }
+
+    /// Checks whether the control block has been cleared or reinitialized since attaching,
+    /// e.g. because the target was reset.
+    ///
+    /// See [`control_block_status`] for details.
+    pub fn control_block_status(&self, core: &mut Core) -> Result<ControlBlockStatus, Error> {
+        control_block_status(core, self.ptr, &self.up_channels, &self.down_channels)
+    }
+
+    /// Attaches to the control block again after it has been reinitialized.
+    ///
+    /// The channels are detected again, and the modes the host has set for the up channels are
+    /// applied to the channels with the same numbers.
+    pub fn reattach(&mut self, core: &mut Core) -> Result<(), Error> {
+        let mut rtt = Self::attach_at(core, self.ptr)?;
+
+        for channel in rtt.up_channels.iter_mut() {
+            let configured_mode = self
+                .up_channels
+                .iter()
+                .find(|previous| previous.number() == channel.number())
+                .and_then(|previous| previous.0.configured_mode());
+
+            if let Some(mode) = configured_mode {
+                channel.set_mode(core, mode)?;
+            }
+        }
+
+        *self = rtt;
+
+        Ok(())
+    }
+}
+
+/// The state of an RTT control block, compared to when it was attached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlBlockStatus {
+    /// The control block and its channels are unchanged.
+    Unchanged,
+    /// The control block no longer starts with the RTT ID. This usually means that the target
+    /// was reset and has not initialized RTT again yet, or that the control block was cleared.
+    Missing,
+    /// The control block was initialized again, so the attached channels are stale and have to
+    /// be detected again, e.g. with [`Rtt::reattach`].
+    Reinitialized,
+}
+
+/// Checks whether the control block at `ptr`, which `up_channels` and `down_channels` were
+/// attached from, has been cleared or reinitialized since.
+///
+/// Reinitialization is detected by changed channel buffer descriptors, by a read pointer of an
+/// up channel that was not moved by the host, and by a channel mode that differs from the one
+/// set by the host.
+pub fn control_block_status<'c>(
+    core: &mut Core,
+    ptr: u64,
+    up_channels: impl IntoIterator<Item = &'c UpChannel>,
+    down_channels: impl IntoIterator<Item = &'c DownChannel>,
+) -> Result<ControlBlockStatus, Error> {
+    let mut id = [0; Rtt::RTT_ID.len()];
+    core.read(ptr, &mut id)?;
+    if id != Rtt::RTT_ID {
+        return Ok(ControlBlockStatus::Missing);
+    }
+
+    for channel in up_channels {
+        if channel.0.is_stale(core)? {
+            return Ok(ControlBlockStatus::Reinitialized);
+        }
+    }
+
+    for channel in down_channels {
+        if channel.0.is_stale(core)? {
+            return Ok(ControlBlockStatus::Reinitialized);
+        }
+    }
+
+    Ok(ControlBlockStatus::Unchanged)
 }
 
 /// Used to specify which memory regions to scan for the RTT control block.
@@ -500,6 +583,12 @@ pub enum Error {
 
     /// Channel {0} does not exist.
     MissingChannel(usize),
+
+    /// The target did not read from the down channel in time, only {0} bytes were written.
+    WriteTimeout(usize),
+
+    /// Writing to the down channel was cancelled after {0} bytes were written.
+    WriteCancelled(usize),
 }
 
 fn display_list(list: &[u64]) -> String {
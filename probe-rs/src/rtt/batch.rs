@@ -0,0 +1,146 @@
+use std::ops::Range;
+
+use probe_rs_target::RegionMergeIterator;
+use zerocopy::IntoBytes;
+
+use crate::rtt::{Error, UpChannel};
+use crate::{Core, MemoryInterface};
+
+/// Memory areas that are at most this far apart are read in a single transfer, since reading a
+/// few unused bytes is cheaper than starting another transfer on most probes.
+const MAX_GAP: u64 = 1024;
+
+/// Copies of target memory areas, taken with as few transfers as possible.
+#[derive(Debug, Default)]
+pub(crate) struct MemorySnapshot {
+    areas: Vec<(u64, Vec<u8>)>,
+}
+
+impl MemorySnapshot {
+    /// Reads `range` from the target with a single 32-bit block transfer.
+    fn read(&mut self, core: &mut Core, range: Range<u64>) -> Result<(), Error> {
+        let start = range.start & !3;
+        let end = range.end.next_multiple_of(4);
+
+        let mut words = vec![0u32; ((end - start) / 4) as usize];
+        core.read_32(start, &mut words)?;
+        self.areas.push((start, words.as_bytes().to_vec()));
+
+        Ok(())
+    }
+
+    /// Returns the contents of `range`, if it was read.
+    pub(crate) fn get(&self, range: Range<u64>) -> Option<&[u8]> {
+        self.areas.iter().find_map(|(start, data)| {
+            let end = start + data.len() as u64;
+            if *start <= range.start && range.end <= end {
+                Some(&data[(range.start - start) as usize..(range.end - start) as usize])
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Reads the available data of several up channels with as few memory transfers as possible.
+///
+/// Instead of reading the read and write pointers and the data of each channel separately, the
+/// channel descriptors and the complete data buffers are read in large 32-bit block transfers,
+/// usually just one, and the data is extracted on the host. Only the read pointers of channels
+/// that had data are written back individually. This increases the throughput considerably on
+/// probes with a high per-transfer overhead, at the cost of reading unused buffer space.
+///
+/// Each channel's data is copied into the buffer passed alongside it. Returns the number of
+/// bytes read for each channel, in the order the channels were passed.
+pub fn read_up_channels_batched<'a>(
+    core: &mut Core,
+    channels: impl IntoIterator<Item = (&'a mut UpChannel, &'a mut [u8])>,
+) -> Result<Vec<usize>, Error> {
+    let mut channels = channels.into_iter().collect::<Vec<_>>();
+    if channels.is_empty() {
+        return Ok(vec![]);
+    }
+
+    for (channel, _) in channels.iter() {
+        channel.0.validate_core_id(core)?;
+    }
+
+    // The descriptors of the up channels are an array in the control block.
+    let mut descriptors = channels[0].0.0.descriptor_range();
+    for (channel, _) in channels.iter() {
+        let range = channel.0.descriptor_range();
+        descriptors.start = descriptors.start.min(range.start);
+        descriptors.end = descriptors.end.max(range.end);
+    }
+
+    let mut buffers = channels
+        .iter()
+        .map(|(channel, _)| channel.0.buffer_range())
+        .filter(|range| !range.is_empty())
+        .collect::<Vec<_>>();
+    buffers.sort_by_key(|range| range.start);
+
+    // The write pointers must be read before the buffers. Otherwise, data that the target writes
+    // after its buffer location was read, but before the write pointer was read, would be
+    // reported with stale contents. Buffers that follow the descriptors in memory are read in
+    // the same transfer, the others afterwards.
+    let mut first = descriptors.clone();
+    let mut later: Vec<Range<u64>> = vec![];
+    for buffer in buffers {
+        if buffer.start >= descriptors.end && can_merge(core, &first, &buffer) {
+            first.end = first.end.max(buffer.end);
+            continue;
+        }
+
+        match later.last_mut() {
+            Some(last) if can_merge(core, last, &buffer) => last.end = last.end.max(buffer.end),
+            _ => later.push(buffer),
+        }
+    }
+
+    let mut snapshot = MemorySnapshot::default();
+    snapshot.read(core, first)?;
+    for range in later {
+        snapshot.read(core, range)?;
+    }
+
+    channels
+        .iter_mut()
+        .map(|(channel, buf)| channel.read_snapshot(core, &snapshot, buf))
+        .collect()
+}
+
+/// Returns whether `next`, which starts after `range`, is close enough to be read in the same
+/// transfer, and whether the memory in between can be read safely.
+fn can_merge(core: &Core, range: &Range<u64>, next: &Range<u64>) -> bool {
+    if next.start > range.end + MAX_GAP {
+        return false;
+    }
+
+    let merged = range.start.min(next.start) & !3..range.end.max(next.end).next_multiple_of(4);
+    core.target()
+        .memory_map
+        .iter()
+        .filter_map(|region| region.as_ram_region())
+        .merge_consecutive()
+        .any(|region| region.range.start <= merged.start && merged.end <= region.range.end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MemorySnapshot;
+
+    #[test]
+    fn snapshot_lookup() {
+        let snapshot = MemorySnapshot {
+            areas: vec![
+                (0x2000_0000, vec![0, 1, 2, 3, 4, 5, 6, 7]),
+                (0x2000_1000, vec![8; 4]),
+            ],
+        };
+
+        assert_eq!(snapshot.get(0x2000_0002..0x2000_0005), Some(&[2, 3, 4][..]));
+        assert_eq!(snapshot.get(0x2000_1000..0x2000_1004), Some(&[8; 4][..]));
+        assert_eq!(snapshot.get(0x2000_0006..0x2000_0009), None);
+    }
+}
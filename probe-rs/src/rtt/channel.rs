@@ -1,9 +1,12 @@
 use crate::rtt::Error;
-use crate::{Core, MemoryInterface};
+use crate::rtt::batch::MemorySnapshot;
+use crate::{CancellationToken, Core, MemoryInterface};
 use probe_rs_target::RegionMergeIterator;
 use std::cmp::min;
 use std::ffi::CStr;
 use std::num::NonZeroU64;
+use std::ops::Range;
+use std::time::{Duration, Instant};
 use zerocopy::{FromBytes, Immutable, KnownLayout};
 
 /// Trait for channel information shared between up and down channels.
@@ -97,6 +100,67 @@ impl RttChannelBuffer {
         }
     }
 
+    pub fn read_offset(&self) -> u64 {
+        match self {
+            RttChannelBuffer::Buffer32(x) => u64::from(x.read_offset),
+            RttChannelBuffer::Buffer64(x) => x.read_offset,
+        }
+    }
+
+    pub fn flags(&self) -> u64 {
+        match self {
+            RttChannelBuffer::Buffer32(x) => u64::from(x.flags),
+            RttChannelBuffer::Buffer64(x) => x.flags,
+        }
+    }
+
+    /// Reads the current contents of the channel buffer descriptor at `ptr` from the target.
+    pub fn read_descriptor(&self, core: &mut Core, ptr: u64) -> Result<RttChannelBuffer, Error> {
+        Ok(match self {
+            RttChannelBuffer::Buffer32(_) => {
+                let mut block = [0u32; 6];
+                core.read_32(ptr, &mut block)?;
+                RttChannelBufferInner {
+                    standard_name_pointer: block[0],
+                    buffer_start_pointer: block[1],
+                    size_of_buffer: block[2],
+                    write_offset: block[3],
+                    read_offset: block[4],
+                    flags: block[5],
+                }
+                .into()
+            }
+            RttChannelBuffer::Buffer64(_) => {
+                let mut block = [0u64; 6];
+                core.read_64(ptr, &mut block)?;
+                RttChannelBufferInner {
+                    standard_name_pointer: block[0],
+                    buffer_start_pointer: block[1],
+                    size_of_buffer: block[2],
+                    write_offset: block[3],
+                    read_offset: block[4],
+                    flags: block[5],
+                }
+                .into()
+            }
+        })
+    }
+
+    /// Parses (write_buffer_ptr, read_buffer_ptr) from the raw bytes of the descriptor, as read
+    /// by `read_32`.
+    pub fn parse_buffer_offsets(&self, descriptor: &[u8]) -> Option<(u64, u64)> {
+        Some(match self {
+            RttChannelBuffer::Buffer32(_) => {
+                let (inner, _) = RttChannelBufferInner::<u32>::read_from_prefix(descriptor).ok()?;
+                (u64::from(inner.write_offset), u64::from(inner.read_offset))
+            }
+            RttChannelBuffer::Buffer64(_) => {
+                let (inner, _) = RttChannelBufferInner::<u64>::read_from_prefix(descriptor).ok()?;
+                (inner.write_offset, inner.read_offset)
+            }
+        })
+    }
+
     /// return (write_buffer_ptr, read_buffer_ptr)
     pub fn read_buffer_offsets(&self, core: &mut Core, ptr: u64) -> Result<(u64, u64), Error> {
         Ok(match self {
@@ -185,6 +249,8 @@ pub(crate) struct Channel {
     metadata_ptr: u64,
     info: RttChannelBuffer,
     last_read_ptr: Option<u64>,
+    /// The mode last set by the host, if any.
+    configured_mode: Option<ChannelMode>,
 }
 
 // Chanels must follow this data layout when reading/writing memory in order to be compatible with
@@ -220,6 +286,7 @@ impl Channel {
             name: None,
             info,
             last_read_ptr: None,
+            configured_mode: None,
         };
 
         // It's possible that the channel is not initialized with the magic string written last.
@@ -267,22 +334,101 @@ impl Channel {
     /// Changes the channel mode on the target to the specified mode.
     ///
     /// See [`ChannelMode`] for more information on what the modes mean.
-    pub fn set_mode(&self, core: &mut Core, mode: ChannelMode) -> Result<(), Error> {
+    pub fn set_mode(&mut self, core: &mut Core, mode: ChannelMode) -> Result<(), Error> {
         tracing::debug!("Setting RTT channel {} mode to {:?}", self.number, mode);
         self.validate_core_id(core)?;
         let flags = self.info.read_flags(core, self.metadata_ptr)?;
 
         let new_flags = ChannelMode::set(mode, flags);
         self.info.write_flags(core, self.metadata_ptr, new_flags)?;
+        self.configured_mode = Some(mode);
 
         Ok(())
     }
 
+    /// Returns the address range of the channel buffer descriptor in the control block.
+    pub(crate) fn descriptor_range(&self) -> Range<u64> {
+        self.metadata_ptr..self.metadata_ptr + self.info.size() as u64
+    }
+
+    /// Returns the address range of the data buffer of the channel.
+    pub(crate) fn buffer_range(&self) -> Range<u64> {
+        let start = self.info.buffer_start_pointer();
+        start..start + self.info.size_of_buffer()
+    }
+
+    /// Returns the mode last set by the host, if any.
+    pub(crate) fn configured_mode(&self) -> Option<ChannelMode> {
+        self.configured_mode
+    }
+
+    /// Returns whether the channel buffer descriptor on the target no longer matches the state
+    /// of this channel, e.g. because the target reinitialized RTT after a reset.
+    pub(crate) fn is_stale(&self, core: &mut Core) -> Result<bool, Error> {
+        self.validate_core_id(core)?;
+        let current = self.info.read_descriptor(core, self.metadata_ptr)?;
+
+        if current.standard_name_pointer() != self.info.standard_name_pointer()
+            || current.buffer_start_pointer() != self.info.buffer_start_pointer()
+            || current.size_of_buffer() != self.info.size_of_buffer()
+        {
+            return Ok(true);
+        }
+
+        // Only the host moves the read pointer of up channels.
+        if self
+            .last_read_ptr
+            .is_some_and(|last_read_ptr| current.read_offset() != last_read_ptr)
+        {
+            return Ok(true);
+        }
+
+        // Initializing RTT resets the mode that was set by the host.
+        if let Some(mode) = self.configured_mode {
+            let current_mode = ChannelMode::try_from(current.flags() & ChannelMode::MASK).ok();
+            if current_mode != Some(mode) {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
     fn read_pointers(&self, core: &mut Core, channel_kind: &str) -> Result<(u64, u64), Error> {
         self.validate_core_id(core)?;
 
         let (write, read) = self.info.read_buffer_offsets(core, self.metadata_ptr)?;
+        self.validate_pointers(core, write, read, channel_kind)
+    }
 
+    /// Reads (write_buffer_ptr, read_buffer_ptr) from a snapshot of the control block.
+    fn snapshot_pointers(
+        &self,
+        core: &mut Core,
+        snapshot: &MemorySnapshot,
+        channel_kind: &str,
+    ) -> Result<(u64, u64), Error> {
+        self.validate_core_id(core)?;
+
+        let (write, read) = snapshot
+            .get(self.descriptor_range())
+            .and_then(|descriptor| self.info.parse_buffer_offsets(descriptor))
+            .ok_or_else(|| {
+                Error::Other(anyhow::anyhow!(
+                    "The descriptor of {channel_kind}channel {} is missing from the snapshot",
+                    self.number
+                ))
+            })?;
+        self.validate_pointers(core, write, read, channel_kind)
+    }
+
+    fn validate_pointers(
+        &self,
+        core: &mut Core,
+        write: u64,
+        read: u64,
+        channel_kind: &str,
+    ) -> Result<(u64, u64), Error> {
         // Validate whether the buffers are sensible
         let validate = |which, value| {
             let buffer_offset_larger_than_size_of_buffer = value >= self.info.size_of_buffer();
@@ -330,6 +476,10 @@ impl Channel {
     }
 }
 
+/// How long [`DownChannel::write_all`] waits before checking for free space in a full buffer
+/// again.
+const WRITE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 /// RTT up (target to host) channel.
 #[derive(Debug)]
 pub struct UpChannel(pub(crate) Channel);
@@ -361,13 +511,62 @@ impl UpChannel {
     /// Changes the channel mode on the target to the specified mode.
     ///
     /// See [`ChannelMode`] for more information on what the modes mean.
-    pub fn set_mode(&self, core: &mut Core, mode: ChannelMode) -> Result<(), Error> {
+    pub fn set_mode(&mut self, core: &mut Core, mode: ChannelMode) -> Result<(), Error> {
         self.0.set_mode(core, mode)
     }
 
-    fn read_core(&mut self, core: &mut Core, mut buf: &mut [u8]) -> Result<(u64, usize), Error> {
-        let (write, mut read) = self.0.read_pointers(core, "up ")?;
+    fn read_core(&mut self, core: &mut Core, buf: &mut [u8]) -> Result<(u64, usize), Error> {
+        let (write, read) = self.0.read_pointers(core, "up ")?;
+        let buffer_start = self.0.info.buffer_start_pointer();
+
+        self.read_buffer(write, read, buf, |offset, data| {
+            core.read(buffer_start + offset, data)?;
+            Ok(())
+        })
+    }
+
+    /// Reads the channel data from a snapshot of the target memory, taken by
+    /// [`read_up_channels_batched`](crate::rtt::read_up_channels_batched), and moves the read
+    /// pointer on the target.
+    pub(crate) fn read_snapshot(
+        &mut self,
+        core: &mut Core,
+        snapshot: &MemorySnapshot,
+        buf: &mut [u8],
+    ) -> Result<usize, Error> {
+        let (write, read) = self.0.snapshot_pointers(core, snapshot, "up ")?;
+        let buffer = snapshot.get(self.0.buffer_range()).ok_or_else(|| {
+            Error::Other(anyhow::anyhow!(
+                "The buffer of up channel {} is missing from the snapshot",
+                self.0.number
+            ))
+        })?;
+
+        let (read, total) = self.read_buffer(write, read, buf, |offset, data| {
+            let offset = offset as usize;
+            data.copy_from_slice(&buffer[offset..][..data.len()]);
+            Ok(())
+        })?;
 
+        if total > 0 {
+            self.0
+                .info
+                .write_read_buffer_ptr(core, self.0.metadata_ptr, read)?;
+        }
+
+        Ok(total)
+    }
+
+    /// Copies the data between `read` and `write` into `buf`, using `fetch` to read from the
+    /// channel buffer at the given offset, and returns the new read pointer and the number of
+    /// bytes read.
+    fn read_buffer(
+        &mut self,
+        write: u64,
+        mut read: u64,
+        mut buf: &mut [u8],
+        mut fetch: impl FnMut(u64, &mut [u8]) -> Result<(), Error>,
+    ) -> Result<(u64, usize), Error> {
         let mut total = 0;
 
         if let Some(ptr) = self.0.last_read_ptr {
@@ -384,7 +583,7 @@ impl UpChannel {
                 break;
             }
 
-            core.read(self.0.info.buffer_start_pointer() + read, &mut buf[..count])?;
+            fetch(read, &mut buf[..count])?;
 
             total += count;
             read += count as u64;
@@ -513,6 +712,56 @@ impl DownChannel {
         Ok(total)
     }
 
+    /// Writes all of `buf` into the channel buffer, waiting for the target to make space
+    /// available if the buffer is full.
+    ///
+    /// Returns [`Error::WriteTimeout`] if the target does not consume the data within `timeout`.
+    /// The data written before that remains in the buffer.
+    pub fn write_all(
+        &mut self,
+        core: &mut Core,
+        buf: &[u8],
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        self.write_all_with_cancel(core, buf, timeout, &CancellationToken::new())
+    }
+
+    /// Like [`write_all`](DownChannel::write_all), but stops waiting with
+    /// [`Error::WriteCancelled`] once `token` is cancelled.
+    ///
+    /// `token` is checked whenever the buffer is full.
+    pub fn write_all_with_cancel(
+        &mut self,
+        core: &mut Core,
+        mut buf: &[u8],
+        timeout: Duration,
+        token: &CancellationToken,
+    ) -> Result<(), Error> {
+        let start = Instant::now();
+        let mut written = 0;
+
+        while !buf.is_empty() {
+            let count = self.write(core, buf)?;
+            written += count;
+            buf = &buf[count..];
+
+            if count > 0 || buf.is_empty() {
+                continue;
+            }
+
+            // The buffer is full, give the target some time to read from it.
+            if token.is_cancelled() {
+                return Err(Error::WriteCancelled(written));
+            }
+            if start.elapsed() >= timeout {
+                return Err(Error::WriteTimeout(written));
+            }
+            std::thread::sleep(WRITE_POLL_INTERVAL);
+        }
+
+        Ok(())
+    }
+
     /// Calculates amount of contiguous space available for writing
     fn writable_contiguous(&self, write: u64, read: u64) -> usize {
         (if read > write {
@@ -0,0 +1,316 @@
+//! Decoders that turn the raw data of an RTT up channel into text.
+//!
+//! The data read from an [`UpChannel`](super::UpChannel) is usually in one of a few formats:
+//! plain text, arbitrary binary data or [defmt](https://defmt.ferrous-systems.com/) frames.
+//! Each format has a [`StreamDecoder`] that can be fed the data in chunks of any size, as they
+//! are read from the target.
+//!
+//! ## Example
+//!
+//! ```no_run
+//! use probe_rs::rtt::decoder::{StreamDecoder, TextDecoder};
+//! # use probe_rs::rtt::Rtt;
+//! # fn print_channel(core: &mut probe_rs::Core, rtt: &mut Rtt) -> Result<(), probe_rs::rtt::Error> {
+//!
+//! let mut decoder = TextDecoder::default();
+//! let mut buf = [0u8; 1024];
+//!
+//! if let Some(input) = rtt.up_channel(0) {
+//!     let count = input.read(core, &mut buf)?;
+//!     print!("{}", decoder.decode(&buf[..count])?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::Write;
+
+use super::Error;
+
+#[cfg(feature = "defmt")]
+pub use self::defmt::{DefmtDecoder, DefmtTable};
+
+/// Decodes a stream of data received over an RTT channel.
+pub trait StreamDecoder {
+    /// Decodes the next chunk of data received from the target.
+    ///
+    /// Data that does not form a complete unit yet, e.g. an incomplete defmt frame, is kept
+    /// until the rest of it is passed to the next call.
+    fn decode(&mut self, data: &[u8]) -> Result<String, Error>;
+}
+
+/// Decodes UTF-8 text.
+///
+/// Invalid sequences are replaced with `U+FFFD REPLACEMENT CHARACTER`. A sequence that is split
+/// across two chunks is decoded once it is complete.
+#[derive(Debug, Default)]
+pub struct TextDecoder {
+    /// The start of a multi-byte sequence that ended the previous chunk.
+    incomplete: Vec<u8>,
+}
+
+impl StreamDecoder for TextDecoder {
+    fn decode(&mut self, data: &[u8]) -> Result<String, Error> {
+        self.incomplete.extend_from_slice(data);
+
+        // Keep a trailing incomplete sequence for the next chunk.
+        let complete = self.incomplete.len() - incomplete_suffix(&self.incomplete);
+
+        let text = String::from_utf8_lossy(&self.incomplete[..complete]).into_owned();
+        self.incomplete.drain(..complete);
+
+        Ok(text)
+    }
+}
+
+/// Returns the length of the multi-byte sequence at the end of `bytes` that is missing
+/// continuation bytes.
+fn incomplete_suffix(bytes: &[u8]) -> usize {
+    let len = bytes.len();
+    for start in (len.saturating_sub(3)..len).rev() {
+        let byte = bytes[start];
+        if byte & 0xc0 == 0x80 {
+            // Continuation byte, the sequence starts further back.
+            continue;
+        }
+
+        let sequence_len = match byte {
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => 1,
+        };
+        return if len - start < sequence_len {
+            len - start
+        } else {
+            0
+        };
+    }
+    0
+}
+
+/// Formats binary data as a list of hexadecimal bytes, e.g. `0x01 0xab`.
+#[derive(Debug, Default)]
+pub struct HexdumpDecoder;
+
+impl StreamDecoder for HexdumpDecoder {
+    fn decode(&mut self, data: &[u8]) -> Result<String, Error> {
+        let mut formatted = String::with_capacity(data.len() * 5);
+        for byte in data {
+            if !formatted.is_empty() {
+                formatted.push(' ');
+            }
+            // Width of 4 allows 0xFF to be printed.
+            write!(formatted, "{byte:#04x}").expect("Writing to String cannot fail");
+        }
+        Ok(formatted)
+    }
+}
+
+#[cfg(feature = "defmt")]
+mod defmt {
+    use std::fmt::{self, Write};
+    use std::sync::Arc;
+
+    use anyhow::{Context, anyhow};
+    use defmt_decoder::{
+        DecodeError, Locations, Table,
+        log::format::{Formatter, FormatterConfig, FormatterFormat},
+    };
+
+    use super::StreamDecoder;
+    use crate::rtt::Error;
+
+    struct DefmtTableInner {
+        table: Table,
+        locs: Option<Locations>,
+    }
+
+    /// The defmt table and location information of a firmware.
+    ///
+    /// The table is shared by all defmt channels of the firmware, cloning it is cheap.
+    #[derive(Clone)]
+    pub struct DefmtTable {
+        inner: Arc<DefmtTableInner>,
+    }
+
+    impl DefmtTable {
+        /// Reads the defmt table from the ELF file in `elf`.
+        ///
+        /// Returns `Ok(None)` if the firmware does not use defmt.
+        pub fn try_from_bytes(elf: &[u8]) -> Result<Option<Self>, Error> {
+            let Some(table) = Table::parse(elf).with_context(|| "Failed to parse defmt data")?
+            else {
+                return Ok(None);
+            };
+
+            let locs = table
+                .get_locations(elf)
+                .with_context(|| "Failed to parse defmt data")?;
+
+            let locs = if !table.is_empty() && locs.is_empty() {
+                tracing::warn!(
+                    "Insufficient DWARF info; compile your program with `debug = 2` to enable location info."
+                );
+                None
+            } else if table.indices().all(|idx| locs.contains_key(&(idx as u64))) {
+                Some(locs)
+            } else {
+                tracing::warn!("Location info is incomplete; it will be omitted from the output.");
+                None
+            };
+
+            Ok(Some(Self {
+                inner: Arc::new(DefmtTableInner { table, locs }),
+            }))
+        }
+
+        /// Returns the parsed defmt table.
+        pub fn table(&self) -> &Table {
+            &self.inner.table
+        }
+    }
+
+    impl fmt::Debug for DefmtTable {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("DefmtTable").finish_non_exhaustive()
+        }
+    }
+
+    /// Decodes defmt frames and formats them as log lines.
+    pub struct DefmtDecoder {
+        formatter: Formatter,
+        // Fields are dropped in declaration order. `decoder` is holding a reference to the
+        // table in `table`, so it must be dropped first.
+        decoder: Box<dyn defmt_decoder::StreamDecoder>,
+        table: DefmtTable,
+    }
+
+    impl DefmtDecoder {
+        /// Creates a decoder for the frames of a firmware with the defmt table `table`.
+        ///
+        /// `log_format` is either `oneline` (the default), `full` or a custom
+        /// [defmt log format](https://defmt.ferrous-systems.com/custom-log-output).
+        pub fn new(
+            table: DefmtTable,
+            show_timestamps: bool,
+            show_location: bool,
+            log_format: Option<&str>,
+        ) -> Self {
+            let has_timestamp = table.table().has_timestamp();
+
+            let format = match log_format {
+                None | Some("oneline") => FormatterFormat::OneLine {
+                    with_location: show_location,
+                },
+                Some("full") => FormatterFormat::Default {
+                    with_location: show_location,
+                },
+                Some(format) => FormatterFormat::Custom(format),
+            };
+
+            let decoder = table.inner.table.new_stream_decoder();
+
+            Self {
+                formatter: Formatter::new(FormatterConfig {
+                    format,
+                    is_timestamp_available: has_timestamp && show_timestamps,
+                }),
+                decoder: unsafe {
+                    // Extend lifetime to 'static. We can do this because we hold a reference to
+                    // the table for the lifetime of the decoder.
+                    std::mem::transmute::<
+                        Box<dyn defmt_decoder::StreamDecoder + '_>,
+                        Box<dyn defmt_decoder::StreamDecoder + 'static>,
+                    >(decoder)
+                },
+                table,
+            }
+        }
+    }
+
+    impl fmt::Debug for DefmtDecoder {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("DefmtDecoder").finish_non_exhaustive()
+        }
+    }
+
+    impl StreamDecoder for DefmtDecoder {
+        fn decode(&mut self, data: &[u8]) -> Result<String, Error> {
+            let DefmtTableInner { table, locs } = &*self.table.inner;
+            self.decoder.received(data);
+
+            let mut formatted = String::new();
+            loop {
+                match self.decoder.decode() {
+                    Ok(frame) => {
+                        let loc = locs.as_ref().and_then(|locs| locs.get(&frame.index()));
+                        let (file, line, module) = if let Some(loc) = loc {
+                            (
+                                loc.file.display().to_string(),
+                                Some(loc.line.try_into().unwrap()),
+                                Some(loc.module.as_str()),
+                            )
+                        } else {
+                            (
+                                format!(
+                                    "└─ <invalid location: defmt frame-index: {}>",
+                                    frame.index()
+                                ),
+                                None,
+                                None,
+                            )
+                        };
+                        let s = self
+                            .formatter
+                            .format_frame(frame, Some(&file), line, module);
+                        writeln!(formatted, "{s}").expect("Writing to String cannot fail");
+                    }
+                    Err(DecodeError::UnexpectedEof) => break,
+                    Err(DecodeError::Malformed) if table.encoding().can_recover() => {
+                        // If recovery is possible, skip the current frame and continue with new data.
+                    }
+                    Err(DecodeError::Malformed) => {
+                        return Err(Error::Other(anyhow!(
+                            "Unrecoverable error while decoding Defmt \
+                            data. Some data may have been lost: {}",
+                            DecodeError::Malformed
+                        )));
+                    }
+                }
+            }
+
+            Ok(formatted)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_decoder_joins_split_characters() {
+        let mut decoder = TextDecoder::default();
+        let text = "grüße €".as_bytes();
+
+        // Split in the middle of the '€'.
+        let split = text.len() - 1;
+        assert_eq!(decoder.decode(&text[..split]).unwrap(), "grüße ");
+        assert_eq!(decoder.decode(&text[split..]).unwrap(), "€");
+    }
+
+    #[test]
+    fn text_decoder_replaces_invalid_bytes() {
+        let mut decoder = TextDecoder::default();
+        assert_eq!(decoder.decode(b"a\xffb").unwrap(), "a\u{fffd}b");
+    }
+
+    #[test]
+    fn hexdump_decoder() {
+        assert_eq!(
+            HexdumpDecoder.decode(&[0x01, 0xab, 0xff]).unwrap(),
+            "0x01 0xab 0xff"
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! Virtual terminals multiplexed over a single RTT up channel.
+//!
+//! SEGGER's RTT implementation allows a target to write to up to 16 virtual terminals over one
+//! channel, usually channel 0. Switching to another terminal is announced by
+//! [`TERMINAL_SWITCH`], followed by the ID of the terminal as an ASCII hex digit (`0`-`9`,
+//! `A`-`F`). Data that follows belongs to that terminal until the next switch.
+//!
+//! Since `0xFF` never occurs in UTF-8 encoded text, terminal switches can be detected reliably in
+//! text channels. Binary channels, like defmt, don't use this convention.
+
+/// The byte that announces a switch to another virtual terminal.
+pub const TERMINAL_SWITCH: u8 = 0xff;
+
+/// Data that the target wrote to a virtual terminal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerminalOutput {
+    /// The ID of the terminal, from `0` to `15`.
+    pub terminal: u8,
+    /// The data written to the terminal.
+    pub data: Vec<u8>,
+}
+
+/// Splits the data of an up channel into the output of its virtual terminals.
+///
+/// The current terminal is tracked across calls to [`split`](VirtualTerminals::split), so the
+/// data can be passed in chunks of any size, as it is read from the target.
+#[derive(Debug, Default)]
+pub struct VirtualTerminals {
+    current: u8,
+    /// Whether the previous chunk ended with [`TERMINAL_SWITCH`].
+    switch_pending: bool,
+}
+
+impl VirtualTerminals {
+    /// Creates a demultiplexer that starts with terminal 0, which is what the target uses until
+    /// it switches terminals.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the terminal that data is currently written to.
+    pub fn current(&self) -> u8 {
+        self.current
+    }
+
+    /// Splits `data` into the output of the individual terminals, in the order it was written.
+    ///
+    /// A [`TERMINAL_SWITCH`] byte that is not followed by a valid terminal ID is passed through
+    /// unchanged.
+    pub fn split(&mut self, data: &[u8]) -> Vec<TerminalOutput> {
+        let mut output: Vec<TerminalOutput> = vec![];
+        let mut push = |terminal: u8, bytes: &[u8]| {
+            if bytes.is_empty() {
+                return;
+            }
+            match output.last_mut() {
+                Some(last) if last.terminal == terminal => last.data.extend_from_slice(bytes),
+                _ => output.push(TerminalOutput {
+                    terminal,
+                    data: bytes.to_vec(),
+                }),
+            }
+        };
+
+        let mut bytes = data.iter().copied();
+        while let Some(byte) = bytes.next() {
+            let switch_id = if std::mem::take(&mut self.switch_pending) {
+                Some(byte)
+            } else if byte == TERMINAL_SWITCH {
+                match bytes.next() {
+                    Some(id) => Some(id),
+                    None => {
+                        // The ID follows in the next chunk.
+                        self.switch_pending = true;
+                        break;
+                    }
+                }
+            } else {
+                None
+            };
+
+            match switch_id {
+                Some(id) => match terminal_id(id) {
+                    Some(terminal) => self.current = terminal,
+                    None => push(self.current, &[TERMINAL_SWITCH, id]),
+                },
+                None => push(self.current, &[byte]),
+            }
+        }
+
+        output
+    }
+}
+
+/// Parses the ASCII hex digit that identifies a terminal.
+fn terminal_id(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(terminal: u8, data: &[u8]) -> TerminalOutput {
+        TerminalOutput {
+            terminal,
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn splits_terminals() {
+        let mut terminals = VirtualTerminals::new();
+
+        assert_eq!(
+            terminals.split(b"log\n\xff1panic\n\xff0more log\n"),
+            vec![
+                output(0, b"log\n"),
+                output(1, b"panic\n"),
+                output(0, b"more log\n")
+            ]
+        );
+        assert_eq!(terminals.current(), 0);
+
+        assert_eq!(terminals.split(b"\xffFx"), vec![output(15, b"x")]);
+    }
+
+    #[test]
+    fn switch_split_across_chunks() {
+        let mut terminals = VirtualTerminals::new();
+
+        assert_eq!(terminals.split(b"a\xff"), vec![output(0, b"a")]);
+        assert_eq!(terminals.split(b"2b"), vec![output(2, b"b")]);
+        assert_eq!(terminals.current(), 2);
+    }
+
+    #[test]
+    fn invalid_switch_is_passed_through() {
+        let mut terminals = VirtualTerminals::new();
+
+        assert_eq!(terminals.split(b"a\xffzb"), vec![output(0, b"a\xffzb")]);
+    }
+}
@@ -1,6 +1,9 @@
 //! ARM semihosting support.
 //!
 //! Specification: <https://github.com/ARM-software/abi-aa/blob/2024Q3/semihosting/semihosting.rst>
+//!
+//! RISC-V uses the same operations, only the instruction sequence which triggers a semihosting
+//! call is different: <https://github.com/riscv-non-isa/riscv-semihosting>
 
 use std::num::NonZeroU32;
 
@@ -32,9 +35,36 @@ pub enum SemihostingCommand {
     /// The target indicated that it would like to write to the console.
     Write(WriteRequest),
 
+    /// The target requests to read from a file on the host.
+    Read(ReadRequest),
+
+    /// The target requests to change the position in a file on the host.
+    Seek(SeekRequest),
+
     /// The target indicated that it would like to read the value of errno.
     Errno(ErrnoRequest),
 
+    /// The target requests the length of a file on the host.
+    FileLength(FileLengthRequest),
+
+    /// The target requests to delete a file on the host.
+    Remove(RemoveRequest),
+
+    /// The target requests to rename a file on the host.
+    Rename(RenameRequest),
+
+    /// The target requests a name for a temporary file on the host.
+    TmpNam(TmpNamRequest),
+
+    /// The target requests to run a command on the host. This is always refused.
+    System(SystemRequest),
+
+    /// The target requests the number of ticks elapsed since it started.
+    Elapsed(ElapsedRequest),
+
+    /// The target requests the frequency of the ticks reported by [`SemihostingCommand::Elapsed`].
+    TickFreq(TickFreqRequest),
+
     /// The target indicated that it would like to run a semihosting operation which we don't support yet.
     Unknown(UnknownCommandDetails),
 }
@@ -113,7 +143,8 @@ impl GetCommandLineRequest {
 
 /// A request to open a file on the host.
 ///
-/// Note that this is not implemented by probe-rs yet.
+/// The target is told that the request failed, unless [`OpenRequest::respond_with_handle`] is
+/// called.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct OpenRequest {
     path: ZeroTerminatedString,
@@ -141,9 +172,7 @@ impl OpenRequest {
     }
 }
 
-/// A request to open a file on the host.
-///
-/// Note that this is not implemented by probe-rs yet.
+/// A request to close a file on the host.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct CloseRequest {
     pointer: u32,
@@ -197,6 +226,63 @@ impl WriteRequest {
     }
 }
 
+/// A request to read from a file
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct ReadRequest {
+    handle: u32,
+    buffer: u32,
+    len: u32,
+}
+impl ReadRequest {
+    /// Returns the handle of the file to read from
+    pub fn file_handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Returns the number of bytes the target wants to read
+    pub fn buffer_len(&self) -> u32 {
+        self.len
+    }
+
+    /// Writes the bytes read from the file to the target.
+    ///
+    /// If `data` is shorter than the requested length, the target is told that the end of the
+    /// file was reached.
+    pub fn write_data(&self, core: &mut dyn CoreInterface, data: &[u8]) -> Result<(), Error> {
+        if data.len() > self.len as usize {
+            return Err(Error::Other("buffer not large enough".to_string()));
+        }
+
+        core.write_8(self.buffer as u64, data)?;
+
+        // On exit, the RETURN REGISTER contains the number of bytes not filled in.
+        write_status(core, (self.len as usize - data.len()) as i32)
+    }
+}
+
+/// A request to change the position in a file
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct SeekRequest {
+    handle: u32,
+    position: u32,
+}
+impl SeekRequest {
+    /// Returns the handle of the file
+    pub fn file_handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Returns the new position, as offset from the start of the file
+    pub fn position(&self) -> u32 {
+        self.position
+    }
+
+    /// Responds with success to the target.
+    pub fn success(&self, core: &mut dyn CoreInterface) -> Result<(), Error> {
+        write_status(core, 0)
+    }
+}
+
 /// A request to read the errno
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct ErrnoRequest {}
@@ -208,6 +294,136 @@ impl ErrnoRequest {
     }
 }
 
+/// A request to read the length of a file
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct FileLengthRequest {
+    pointer: u32,
+}
+impl FileLengthRequest {
+    /// Returns the handle of the file
+    pub fn file_handle(&self, core: &mut dyn CoreInterface) -> Result<u32, Error> {
+        core.read_word_32(self.pointer as u64)
+    }
+
+    /// Writes the length of the file to the target.
+    ///
+    /// The target is told that the request failed if the length doesn't fit into the result.
+    pub fn write_length(&self, core: &mut dyn CoreInterface, length: u64) -> Result<(), Error> {
+        match i32::try_from(length) {
+            Ok(length) => write_status(core, length),
+            Err(_) => write_status(core, -1),
+        }
+    }
+}
+
+/// A request to delete a file
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct RemoveRequest {
+    path: ZeroTerminatedString,
+}
+impl RemoveRequest {
+    /// Reads the path from the target.
+    pub fn path(&self, core: &mut dyn CoreInterface) -> Result<String, Error> {
+        self.path.read(core)
+    }
+
+    /// Responds with success to the target.
+    pub fn success(&self, core: &mut dyn CoreInterface) -> Result<(), Error> {
+        write_status(core, 0)
+    }
+}
+
+/// A request to rename a file
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct RenameRequest {
+    from: ZeroTerminatedString,
+    to: ZeroTerminatedString,
+}
+impl RenameRequest {
+    /// Reads the current path of the file from the target.
+    pub fn from_path(&self, core: &mut dyn CoreInterface) -> Result<String, Error> {
+        self.from.read(core)
+    }
+
+    /// Reads the new path of the file from the target.
+    pub fn to_path(&self, core: &mut dyn CoreInterface) -> Result<String, Error> {
+        self.to.read(core)
+    }
+
+    /// Responds with success to the target.
+    pub fn success(&self, core: &mut dyn CoreInterface) -> Result<(), Error> {
+        write_status(core, 0)
+    }
+}
+
+/// A request for the name of a temporary file
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct TmpNamRequest {
+    buffer: u32,
+    id: u32,
+    len: u32,
+}
+impl TmpNamRequest {
+    /// Returns the identifier of the name, the same identifier always gets the same name.
+    pub fn id(&self) -> u8 {
+        self.id as u8
+    }
+
+    /// Writes the name to the target.
+    ///
+    /// The target is told that the request failed if its buffer is too small for the name.
+    pub fn write_name(&self, core: &mut dyn CoreInterface, name: &str) -> Result<(), Error> {
+        let mut buf = name.as_bytes().to_vec();
+        buf.push(0);
+        if buf.len() > self.len as usize {
+            return write_status(core, -1);
+        }
+
+        core.write_8(self.buffer as u64, &buf)?;
+        write_status(core, 0)
+    }
+}
+
+/// A request to run a command on the host
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct SystemRequest {
+    command: ZeroTerminatedString,
+}
+impl SystemRequest {
+    /// Reads the command from the target.
+    pub fn command(&self, core: &mut dyn CoreInterface) -> Result<String, Error> {
+        self.command.read(core)
+    }
+}
+
+/// A request to read the number of elapsed ticks
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct ElapsedRequest {
+    pointer: u32,
+}
+impl ElapsedRequest {
+    /// Writes the number of elapsed ticks to the target.
+    pub fn write_ticks(&self, core: &mut dyn CoreInterface, ticks: u64) -> Result<(), Error> {
+        // The tick count is stored as a 64-bit value, least significant word first.
+        core.write_32(self.pointer as u64, &[ticks as u32, (ticks >> 32) as u32])?;
+        write_status(core, 0)
+    }
+}
+
+/// A request to read the tick frequency
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct TickFreqRequest {}
+impl TickFreqRequest {
+    /// Writes the number of ticks per second to the target.
+    pub fn write_frequency(
+        &self,
+        core: &mut dyn CoreInterface,
+        frequency: u32,
+    ) -> Result<(), Error> {
+        write_status(core, frequency as i32)
+    }
+}
+
 fn write_status(core: &mut dyn CoreInterface, value: i32) -> Result<(), crate::Error> {
     let reg = core.registers().get_argument_register(0).unwrap();
     core.write_core_reg(reg.into(), RegisterValue::U32(value as u32))?;
@@ -298,7 +514,6 @@ impl ZeroTerminatedString {
 }
 
 /// Decodes a semihosting syscall without running the requested action.
-/// Only supports SYS_EXIT, SYS_EXIT_EXTENDED and SYS_GET_CMDLINE at the moment
 pub fn decode_semihosting_syscall(
     core: &mut dyn CoreInterface,
 ) -> Result<SemihostingCommand, Error> {
@@ -323,7 +538,16 @@ pub fn decode_semihosting_syscall(
     const SYS_WRITEC: u32 = 0x03;
     const SYS_WRITE0: u32 = 0x04;
     const SYS_WRITE: u32 = 0x05;
+    const SYS_READ: u32 = 0x06;
+    const SYS_SEEK: u32 = 0x0A;
+    const SYS_FLEN: u32 = 0x0C;
+    const SYS_TMPNAM: u32 = 0x0D;
+    const SYS_REMOVE: u32 = 0x0E;
+    const SYS_RENAME: u32 = 0x0F;
+    const SYS_SYSTEM: u32 = 0x12;
     const SYS_ERRNO: u32 = 0x13;
+    const SYS_ELAPSED: u32 = 0x30;
+    const SYS_TICKFREQ: u32 = 0x31;
 
     Ok(match (operation, parameter) {
         (SYS_EXIT, SYS_EXIT_ADP_STOPPED_APPLICATIONEXIT) => SemihostingCommand::ExitSuccess,
@@ -425,8 +649,99 @@ pub fn decode_semihosting_syscall(
             SemihostingCommand::Write(WriteRequest { handle, bytes, len })
         }
 
+        (SYS_READ, pointer) => {
+            let [handle, buffer, len] = param3(core, pointer)?;
+            // signal to target: status = failure, in case the application does not answer this request
+            write_status(core, -1)?;
+            SemihostingCommand::Read(ReadRequest {
+                handle,
+                buffer,
+                len,
+            })
+        }
+
+        (SYS_SEEK, pointer) => {
+            let mut buf = [0; 2];
+            core.read_32(pointer as u64, &mut buf)?;
+            let [handle, position] = buf;
+            // signal to target: status = failure, in case the application does not answer this request
+            write_status(core, -1)?;
+            SemihostingCommand::Seek(SeekRequest { handle, position })
+        }
+
+        (SYS_FLEN, pointer) => {
+            // signal to target: status = failure, in case the application does not answer this request
+            write_status(core, -1)?;
+            SemihostingCommand::FileLength(FileLengthRequest { pointer })
+        }
+
+        (SYS_TMPNAM, pointer) => {
+            let [buffer, id, len] = param3(core, pointer)?;
+            // signal to target: status = failure, in case the application does not answer this request
+            write_status(core, -1)?;
+            SemihostingCommand::TmpNam(TmpNamRequest { buffer, id, len })
+        }
+
+        (SYS_REMOVE, pointer) => {
+            let mut buf = [0; 2];
+            core.read_32(pointer as u64, &mut buf)?;
+            let [address, length] = buf;
+            // signal to target: status = failure, in case the application does not answer this request
+            write_status(core, -1)?;
+            SemihostingCommand::Remove(RemoveRequest {
+                path: ZeroTerminatedString {
+                    address,
+                    length: Some(length),
+                },
+            })
+        }
+
+        (SYS_RENAME, pointer) => {
+            let mut buf = [0; 4];
+            core.read_32(pointer as u64, &mut buf)?;
+            let [from, from_len, to, to_len] = buf;
+            // signal to target: status = failure, in case the application does not answer this request
+            write_status(core, -1)?;
+            SemihostingCommand::Rename(RenameRequest {
+                from: ZeroTerminatedString {
+                    address: from,
+                    length: Some(from_len),
+                },
+                to: ZeroTerminatedString {
+                    address: to,
+                    length: Some(to_len),
+                },
+            })
+        }
+
+        (SYS_SYSTEM, pointer) => {
+            let mut buf = [0; 2];
+            core.read_32(pointer as u64, &mut buf)?;
+            let [address, length] = buf;
+            // The status is the exit code of the command, which is never run.
+            write_status(core, -1)?;
+            SemihostingCommand::System(SystemRequest {
+                command: ZeroTerminatedString {
+                    address,
+                    length: Some(length),
+                },
+            })
+        }
+
         (SYS_ERRNO, 0) => SemihostingCommand::Errno(ErrnoRequest {}),
 
+        (SYS_ELAPSED, pointer) => {
+            // signal to target: status = failure, in case the application does not answer this request
+            write_status(core, -1)?;
+            SemihostingCommand::Elapsed(ElapsedRequest { pointer })
+        }
+
+        (SYS_TICKFREQ, 0) => {
+            // signal to target: status = failure, in case the application does not answer this request
+            write_status(core, -1)?;
+            SemihostingCommand::TickFreq(TickFreqRequest {})
+        }
+
         _ => {
             // signal to target: status = failure, in case the application does not answer this request
             // It is not guaranteed that a value of -1 will be treated as an error by the target, but it is a common value to indicate an error.
@@ -448,3 +763,176 @@ fn param3(core: &mut dyn CoreInterface, pointer: u32) -> Result<[u32; 3], crate:
     core.read_32(pointer as u64, &mut buf)?;
     Ok(buf)
 }
+
+#[cfg(all(test, feature = "builtin-targets"))]
+mod test {
+    use super::*;
+    use crate::{Core, Permissions, Session, probe::fake_probe::FakeProbe};
+
+    /// The address of the parameter block of the semihosting call.
+    const PARAMETERS: u32 = 0x2000_0000;
+    /// The address of the strings referenced by the parameter block.
+    const STRINGS: u32 = 0x2000_0080;
+
+    /// A session whose RAM holds the parameter block `parameters`, followed by `strings`.
+    fn session(parameters: &[u32], strings: &[u8]) -> Session {
+        let mut fake_probe = FakeProbe::with_mocked_core();
+        fake_probe
+            .add_memory_region(PARAMETERS as u64..PARAMETERS as u64 + 0x100, true)
+            .unwrap();
+        let parameters = parameters
+            .iter()
+            .flat_map(|parameter| parameter.to_le_bytes())
+            .collect::<Vec<_>>();
+        fake_probe
+            .load_memory(PARAMETERS as u64, &parameters)
+            .unwrap();
+        fake_probe.load_memory(STRINGS as u64, strings).unwrap();
+
+        fake_probe
+            .into_probe()
+            .attach("nrf51822_xxAC", Permissions::default())
+            .unwrap()
+    }
+
+    fn decode(core: &mut Core<'_>, operation: u32, parameter: u32) -> SemihostingCommand {
+        core.halt(std::time::Duration::from_millis(100)).unwrap();
+        core.write_core_reg(
+            core.registers().get_argument_register(0).unwrap().id(),
+            operation,
+        )
+        .unwrap();
+        core.write_core_reg(
+            core.registers().get_argument_register(1).unwrap().id(),
+            parameter,
+        )
+        .unwrap();
+
+        decode_semihosting_syscall(core).unwrap()
+    }
+
+    /// The result of the semihosting call, as seen by the target.
+    fn status(core: &mut Core<'_>) -> i32 {
+        let value: u32 = core
+            .read_core_reg(core.registers().get_argument_register(0).unwrap().id())
+            .unwrap();
+        value as i32
+    }
+
+    #[test]
+    fn file_length() {
+        let mut session = session(&[0x1_0000], &[]);
+        let mut core = session.core(0).unwrap();
+
+        let SemihostingCommand::FileLength(request) = decode(&mut core, 0x0C, PARAMETERS) else {
+            panic!("expected a FileLength request");
+        };
+        assert_eq!(status(&mut core), -1);
+        assert_eq!(request.file_handle(&mut core).unwrap(), 0x1_0000);
+
+        request.write_length(&mut core, 1234).unwrap();
+        assert_eq!(status(&mut core), 1234);
+
+        // A length which doesn't fit into the result is reported as a failure.
+        request.write_length(&mut core, 1 << 32).unwrap();
+        assert_eq!(status(&mut core), -1);
+    }
+
+    #[test]
+    fn tmpnam() {
+        let mut session = session(&[STRINGS, 7, 16], &[]);
+        let mut core = session.core(0).unwrap();
+
+        let SemihostingCommand::TmpNam(request) = decode(&mut core, 0x0D, PARAMETERS) else {
+            panic!("expected a TmpNam request");
+        };
+        assert_eq!(status(&mut core), -1);
+        assert_eq!(request.id(), 7);
+
+        request.write_name(&mut core, "tmp-007").unwrap();
+        assert_eq!(status(&mut core), 0);
+        let mut name = [0; 8];
+        core.read(STRINGS as u64, &mut name).unwrap();
+        assert_eq!(&name, b"tmp-007\0");
+
+        // The name and its terminator don't fit into the buffer.
+        request
+            .write_name(&mut core, "a-name-which-is-too-long")
+            .unwrap();
+        assert_eq!(status(&mut core), -1);
+    }
+
+    #[test]
+    fn remove() {
+        let mut session = session(&[STRINGS, 8], b"file.txt\0");
+        let mut core = session.core(0).unwrap();
+
+        let SemihostingCommand::Remove(request) = decode(&mut core, 0x0E, PARAMETERS) else {
+            panic!("expected a Remove request");
+        };
+        assert_eq!(status(&mut core), -1);
+        assert_eq!(request.path(&mut core).unwrap(), "file.txt");
+
+        request.success(&mut core).unwrap();
+        assert_eq!(status(&mut core), 0);
+    }
+
+    #[test]
+    fn rename() {
+        let mut session = session(&[STRINGS, 3, STRINGS + 4, 3], b"old\0new\0");
+        let mut core = session.core(0).unwrap();
+
+        let SemihostingCommand::Rename(request) = decode(&mut core, 0x0F, PARAMETERS) else {
+            panic!("expected a Rename request");
+        };
+        assert_eq!(status(&mut core), -1);
+        assert_eq!(request.from_path(&mut core).unwrap(), "old");
+        assert_eq!(request.to_path(&mut core).unwrap(), "new");
+
+        request.success(&mut core).unwrap();
+        assert_eq!(status(&mut core), 0);
+    }
+
+    #[test]
+    fn system_is_refused() {
+        let mut session = session(&[STRINGS, 5], b"rm -r\0");
+        let mut core = session.core(0).unwrap();
+
+        let SemihostingCommand::System(request) = decode(&mut core, 0x12, PARAMETERS) else {
+            panic!("expected a System request");
+        };
+        assert_eq!(status(&mut core), -1);
+        assert_eq!(request.command(&mut core).unwrap(), "rm -r");
+    }
+
+    #[test]
+    fn elapsed() {
+        let mut session = session(&[], &[]);
+        let mut core = session.core(0).unwrap();
+
+        let SemihostingCommand::Elapsed(request) = decode(&mut core, 0x30, PARAMETERS) else {
+            panic!("expected an Elapsed request");
+        };
+        assert_eq!(status(&mut core), -1);
+
+        request.write_ticks(&mut core, 0x1_2345_6789).unwrap();
+        assert_eq!(status(&mut core), 0);
+        let mut ticks = [0; 2];
+        core.read_32(PARAMETERS as u64, &mut ticks).unwrap();
+        assert_eq!(ticks, [0x2345_6789, 0x1]);
+    }
+
+    #[test]
+    fn tick_frequency() {
+        let mut session = session(&[], &[]);
+        let mut core = session.core(0).unwrap();
+
+        let SemihostingCommand::TickFreq(request) = decode(&mut core, 0x31, 0) else {
+            panic!("expected a TickFreq request");
+        };
+        assert_eq!(status(&mut core), -1);
+
+        request.write_frequency(&mut core, 1_000_000).unwrap();
+        assert_eq!(status(&mut core), 1_000_000);
+    }
+}
@@ -1,11 +1,12 @@
 use crate::{
-    Core, CoreType, Error,
+    Core, CoreType, Error, FreezeConfig, MemoryMapPolicy,
     architecture::{
         arm::{
-            ArmError, SwoReader,
+            ArmError, FullyQualifiedApAddress, Pins, SwoReader,
+            ap::{ApRegister, IDR},
             communication_interface::ArmDebugInterface,
             component::{TraceSink, get_arm_components},
-            dp::DpAddress,
+            dp::{DebugPowerConfig, DpAddress},
             memory::CoresightComponent,
             sequences::{ArmDebugSequence, DefaultArmSequence},
         },
@@ -16,15 +17,25 @@ use crate::{
             XtensaCommunicationInterface, XtensaDebugInterfaceState, XtensaError,
         },
     },
-    config::{CoreExt, DebugSequence, RegistryError, Target, TargetSelector, registry::Registry},
-    core::{Architecture, CombinedCoreState},
+    config::{
+        CoreExt, DebugSequence, PeripheralCache, Peripherals, RegistryError, ScanChainElement,
+        Target, TargetSelector, registry::Registry,
+    },
+    core::{Architecture, CombinedCoreState, CoreAccessOptions},
+    device_info::{SecurityRecovery, SecurityState},
+    flashing::option_bytes::{OptionBytesAccess, OptionBytesError},
     probe::{
         AttachMethod, DebugProbeError, Probe, ProbeCreationError, WireProtocol,
         fake_probe::FakeProbe, list::Lister,
     },
 };
 use std::ops::DerefMut;
-use std::{fmt, sync::Arc, time::Duration};
+use std::{collections::BTreeMap, fmt, sync::Arc, time::Duration};
+
+mod shared;
+
+pub use crate::architecture::arm::dp::PowerDownRecovery;
+pub use shared::{Lease, LeaseError, LeasePriority, SharedSession, Stakeholder};
 
 /// The `Session` struct represents an active debug session.
 ///
@@ -37,9 +48,9 @@ use std::{fmt, sync::Arc, time::Duration};
 ///
 /// # Usage
 /// The Session is the common handle that gives a user exclusive access to an active probe.
-/// You can create and share a session between threads to enable multiple stakeholders (e.g. GDB and RTT) to access the target taking turns, by using `Arc<FairMutex<Session>>`.
+/// You can share a session between threads to enable multiple stakeholders (e.g. GDB and RTT) to access the target taking turns, by using a [`SharedSession`].
 ///
-/// If you do so, make sure that both threads sleep in between tasks such that other stakeholders may take their turn.
+/// If you do so, make sure that the stakeholders only hold their [`Lease`] while accessing the target, such that other stakeholders may take their turn.
 ///
 /// To get access to a single [Core] from the `Session`, the [Session::core()] method can be used.
 /// Please see the [Session::core()] method for more usage guidelines.
@@ -50,6 +61,10 @@ pub struct Session {
     interfaces: ArchitectureInterface,
     cores: Vec<CombinedCoreState>,
     configured_trace_sink: Option<TraceSink>,
+    peripheral_cache: PeripheralCache,
+    memory_map_policy: MemoryMapPolicy,
+    power_down_recovery: PowerDownRecovery,
+    speed_floor_khz: Option<u32>,
 }
 
 /// The `SessionConfig` struct is used to configure a new `Session` during auto-attach.
@@ -67,6 +82,8 @@ pub struct SessionConfig {
     pub speed: Option<u32>,
     /// WireProtocol to use
     pub protocol: Option<WireProtocol>,
+    /// What to do with each core after attaching
+    pub attach_options: AttachOptions,
 }
 
 enum JtagInterface {
@@ -179,6 +196,7 @@ impl Session {
             Self::attach_jtag(probe, target, attach_method, permissions, cores)?
         };
 
+        session.peripheral_cache = registry.peripheral_cache().clone();
         session.clear_all_hw_breakpoints()?;
 
         Ok(session)
@@ -230,6 +248,9 @@ impl Session {
         }
 
         probe.attach_to_unspecified()?;
+        Self::unlock_protected_flash(&mut probe, &permissions)?;
+
+        populate_scan_chain(&mut probe, &target);
         if probe.protocol() == Some(WireProtocol::Jtag) {
             if let Some(probe) = probe.try_as_jtag_probe() {
                 if let Ok(chain) = probe.scan_chain() {
@@ -246,6 +267,7 @@ impl Session {
             .try_into_arm_debug_interface(sequence_handle.clone())
             .map_err(|(_, err)| err)?;
 
+        Self::configure_memory_aps(&mut *interface, &target);
         interface.select_debug_port(default_dp)?;
 
         let unlock_span = tracing::debug_span!("debug_device_unlock").entered();
@@ -260,13 +282,42 @@ impl Session {
             // In case this happens after unlock. Try to re-attach the probe once.
             Err(ArmError::ReAttachRequired) => {
                 Self::reattach_arm_interface(&mut interface, &sequence_handle)?;
+                Self::configure_memory_aps(&mut *interface, &target);
+            }
+            Err(e @ ArmError::MissingPermissions(_)) => return Err(Error::Arm(e)),
+            Err(e) => {
+                return Err(secured_device_error(
+                    &mut *interface,
+                    &sequence_handle,
+                    &default_memory_ap,
+                )
+                .unwrap_or(Error::Arm(e)));
             }
-            Err(e) => return Err(Error::Arm(e)),
         }
 
         // For each core, setup debugging
         for core in &cores {
-            core.enable_arm_debug(&mut *interface)?;
+            if let Err(e) = core.enable_arm_debug(&mut *interface) {
+                return Err(secured_device_error(
+                    &mut *interface,
+                    &sequence_handle,
+                    &default_memory_ap,
+                )
+                .unwrap_or(e));
+            }
+        }
+
+        if !target.debug_freeze.is_empty() && permissions.debug_freeze() {
+            let _span = tracing::debug_span!("debug_freeze").entered();
+
+            let mut memory = interface.memory_interface(&default_memory_ap)?;
+            if let Err(error) = sequence_handle.debug_freeze(
+                &mut *memory,
+                &target.debug_freeze,
+                &FreezeConfig::default(),
+            ) {
+                tracing::warn!("Failed to stop peripherals while halted: {error}");
+            }
         }
 
         if attach_method == AttachMethod::UnderReset {
@@ -298,6 +349,10 @@ impl Session {
                 interfaces: ArchitectureInterface::Arm(interface),
                 cores,
                 configured_trace_sink: None,
+                peripheral_cache: PeripheralCache::default(),
+                memory_map_policy: MemoryMapPolicy::default(),
+                power_down_recovery: PowerDownRecovery::default(),
+                speed_floor_khz: None,
             };
 
             {
@@ -321,15 +376,37 @@ impl Session {
                 interfaces: ArchitectureInterface::Arm(interface),
                 cores,
                 configured_trace_sink: None,
+                peripheral_cache: PeripheralCache::default(),
+                memory_map_policy: MemoryMapPolicy::default(),
+                power_down_recovery: PowerDownRecovery::default(),
+                speed_floor_khz: None,
             })
         }
     }
 
+    /// Some probes (WCH-Link) detect read protected flash themselves when attaching. Removing the
+    /// protection erases the flash, so this needs the permission to erase all.
+    fn unlock_protected_flash(probe: &mut Probe, permissions: &Permissions) -> Result<(), Error> {
+        if !probe.target_flash_protected() {
+            return Ok(());
+        }
+
+        if permissions.erase_all().is_err() {
+            return Err(Error::DeviceSecured {
+                available_recovery: Some(SecurityRecovery::EraseAll),
+            });
+        }
+        tracing::info!("The flash of the target is read protected, erasing it to unlock it");
+        probe.erase_all()?;
+
+        Ok(())
+    }
+
     fn attach_jtag(
         mut probe: Probe,
         target: Target,
         _attach_method: AttachMethod,
-        _permissions: Permissions,
+        permissions: Permissions,
         cores: Vec<CombinedCoreState>,
     ) -> Result<Self, Error> {
         // While we still don't support mixed architectures
@@ -344,6 +421,8 @@ impl Session {
         }
 
         probe.attach_to_unspecified()?;
+        Self::unlock_protected_flash(&mut probe, &permissions)?;
+        populate_scan_chain(&mut probe, &target);
 
         // We try to guess the TAP number. Normally we trust the scan chain, but some probes are
         // only quasi-JTAG (wch-link), so we'll have to work with at least 1, but if we're guessing
@@ -408,6 +487,10 @@ impl Session {
             interfaces,
             cores,
             configured_trace_sink: None,
+            peripheral_cache: PeripheralCache::default(),
+            memory_map_policy: MemoryMapPolicy::default(),
+            power_down_recovery: PowerDownRecovery::default(),
+            speed_floor_khz: None,
         };
 
         // Wait for the cores to be halted.
@@ -471,9 +554,13 @@ impl Session {
         session_config: SessionConfig,
     ) -> Result<Session, Error> {
         // Attach to a chip.
-        Self::auto_probe(&session_config)
+        let mut session = Self::auto_probe(&session_config)
             .await?
-            .attach(target, session_config.permissions)
+            .attach(target, session_config.permissions)?;
+
+        session.apply_attach_options(&session_config.attach_options)?;
+
+        Ok(session)
     }
 
     /// Automatically creates a session with the first connected probe found
@@ -485,9 +572,13 @@ impl Session {
         registry: &Registry,
     ) -> Result<Session, Error> {
         // Attach to a chip.
-        Self::auto_probe(&session_config)
+        let mut session = Self::auto_probe(&session_config)
             .await?
-            .attach_with_registry(target, session_config.permissions, registry)
+            .attach_with_registry(target, session_config.permissions, registry)?;
+
+        session.apply_attach_options(&session_config.attach_options)?;
+
+        Ok(session)
     }
 
     /// Lists the available cores with their number and their type.
@@ -498,6 +589,9 @@ impl Session {
     /// Get access to the session when all cores are halted.
     ///
     /// Any previously running cores will be resumed once the closure is executed.
+    ///
+    /// RISC-V harts which share a debug module are halted and resumed at the same time, if the
+    /// debug module supports selecting multiple harts.
     pub fn halted_access<R>(
         &mut self,
         f: impl FnOnce(&mut Self) -> Result<R, Error>,
@@ -511,20 +605,146 @@ impl Session {
             if c.core_halted()? {
                 tracing::info!("Core {core} already halted");
             } else {
-                tracing::info!("Halting core {core}...");
                 resume_state.push(core);
-                c.halt(Duration::from_millis(100))?;
             }
         }
 
+        for (cores, harts) in self.riscv_hart_groups(&resume_state)? {
+            tracing::info!("Halting cores {cores:?} together...");
+            self.get_riscv_interface(cores[0])?
+                .halt_harts(harts, Duration::from_millis(100))?;
+        }
+
+        // Cores which were halted together are already halted, but still need to run their
+        // halt handling.
+        for &core in &resume_state {
+            tracing::info!("Halting core {core}...");
+            self.core(core)?.halt(Duration::from_millis(100))?;
+        }
+
         let r = f(self);
 
-        for core in resume_state {
+        self.resume_cores(&resume_state)?;
+
+        r
+    }
+
+    /// Resumes the given cores.
+    ///
+    /// RISC-V harts which share a debug module are resumed together, see
+    /// [`Session::riscv_hart_groups`].
+    fn resume_cores(&mut self, cores: &[usize]) -> Result<(), Error> {
+        let mut remaining = cores.to_vec();
+
+        for (group, harts) in self.riscv_hart_groups(cores)? {
+            for &core in &group {
+                self.core(core)?.prepare_group_resume()?;
+            }
+
+            tracing::debug!("Resuming cores {group:?} together...");
+            self.get_riscv_interface(group[0])?.resume_harts(harts)?;
+
+            remaining.retain(|core| !group.contains(core));
+        }
+
+        for core in remaining {
             tracing::debug!("Resuming core...");
             self.core(core)?.run()?;
         }
 
-        r
+        Ok(())
+    }
+
+    /// Groups the given RISC-V cores by their debug module, to halt or resume them together.
+    ///
+    /// Only debug modules which support the hart array, and have more than one of the given
+    /// cores, form a group. Each group is returned with the bit mask of its harts.
+    fn riscv_hart_groups(&mut self, cores: &[usize]) -> Result<Vec<(Vec<usize>, u32)>, Error> {
+        let mut groups = BTreeMap::<usize, (Vec<usize>, u32)>::new();
+        for &core in cores {
+            let Some(state) = self.cores.get(core) else {
+                continue;
+            };
+            // The hart array is only used for the first window of 32 harts.
+            let Some(hart @ 0..32) = state.riscv_hart() else {
+                continue;
+            };
+
+            let (group, harts) = groups.entry(state.jtag_tap_index()).or_default();
+            group.push(core);
+            *harts |= 1 << hart;
+        }
+
+        let mut result = vec![];
+        for (group, harts) in groups.into_values() {
+            if group.len() > 1 && self.get_riscv_interface(group[0])?.supports_hart_array()? {
+                result.push((group, harts));
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Brings the cores into the state requested by the given [`AttachOptions`].
+    ///
+    /// All cores which should halt at their reset vector are caught together, using a single
+    /// reset, see [`Session::catch_first_instruction`]. Because this resets the whole system,
+    /// cores which should only be halted are halted afterwards.
+    pub fn apply_attach_options(&mut self, options: &AttachOptions) -> Result<(), Error> {
+        let reset_vector_cores = options.cores_with(HaltOnAttach::ResetVector);
+        if !reset_vector_cores.is_empty() {
+            self.catch_first_instruction(&reset_vector_cores, Duration::from_millis(500))?;
+        }
+
+        for core_index in options.cores_with(HaltOnAttach::Halt) {
+            let mut core = self.core(core_index)?;
+            if !core.core_halted()? {
+                tracing::debug!("Halting core {core_index} after attaching");
+                core.halt(Duration::from_millis(100))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resets the target and halts the given cores on the first instruction they execute.
+    ///
+    /// Reset catch is enabled on all given cores before the reset is triggered through the first
+    /// core in `cores`, so the cores are halted in sync and no code runs before the debugger gets
+    /// control. This is useful for debugging early boot code.
+    ///
+    /// Reset catch is disabled again on all cores, even if one of them fails to halt within
+    /// `timeout`.
+    pub fn catch_first_instruction(
+        &mut self,
+        cores: &[usize],
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        let Some(&reset_core) = cores.first() else {
+            return Ok(());
+        };
+
+        for &core_index in cores {
+            self.core(core_index)?.reset_catch_set()?;
+        }
+
+        let reset_result = self.core(reset_core)?.reset();
+
+        let mut result = reset_result;
+        for &core_index in cores {
+            let mut core = self.core(core_index)?;
+
+            let halted = if result.is_ok() {
+                core.wait_for_core_halted(timeout)
+            } else {
+                Ok(())
+            };
+            let cleared = core.reset_catch_clear();
+
+            result = result.and(halted).and(cleared);
+        }
+
+        result
     }
 
     fn interface_idx(&self, core: usize) -> Result<usize, Error> {
@@ -552,13 +772,27 @@ impl Session {
     // By design, this is called frequently in a session, therefore we limit tracing level to "trace" to avoid spamming the logs.
     #[tracing::instrument(level = "trace", skip(self), name = "attach_to_core")]
     pub fn core(&mut self, core_index: usize) -> Result<Core<'_>, Error> {
-        let combined_state = self
-            .cores
-            .get_mut(core_index)
-            .ok_or(Error::CoreNotFound(core_index))?;
+        if core_index >= self.cores.len() {
+            return Err(Error::CoreNotFound(core_index));
+        }
 
+        if self.power_down_recovery != PowerDownRecovery::Fail {
+            if let ArchitectureInterface::Arm(interface) = &mut self.interfaces {
+                if interface.take_power_loss() {
+                    tracing::warn!("The target lost power, restoring the debug state");
+                    self.restore_debug_state()?;
+                }
+            }
+        }
+
+        let combined_state = &mut self.cores[core_index];
+        let memory_map_policy = self.memory_map_policy;
         self.interfaces
             .attach(&self.target, combined_state)
+            .map(|mut core| {
+                core.set_memory_map_policy(memory_map_policy);
+                core
+            })
             .map_err(|e| {
                 if matches!(e, Error::Xtensa(XtensaError::CoreDisabled)) {
                     // If the core is disabled, we can't attach to it.
@@ -614,6 +848,22 @@ impl Session {
         Ok(SwoReader::new(interface))
     }
 
+    /// Drive the debug probe pins selected in `pin_select` to the levels in `pin_out`,
+    /// and return the state of all pins read back afterwards.
+    ///
+    /// Pass an empty `pin_select` to only read the pin state. Probes which cannot read
+    /// the pins back report all bits as set. Some probes, like the ST-Link and J-Link,
+    /// only support driving nRESET.
+    ///
+    /// This method is only supported for ARM-based targets, and will
+    /// return [ArmError::NoArmTarget] otherwise.
+    pub fn control_pins(&mut self, pin_out: Pins, pin_select: Pins) -> Result<Pins, ArmError> {
+        let interface = self.get_arm_interface()?;
+        let pins = interface.swj_pins(pin_out.0 as u32, pin_select.0 as u32, 0)?;
+
+        Ok(Pins(pins as u8))
+    }
+
     /// Get the Arm probe interface.
     pub fn get_arm_interface(&mut self) -> Result<&mut dyn ArmDebugInterface, ArmError> {
         let interface = match &mut self.interfaces {
@@ -659,6 +909,19 @@ impl Session {
         Err(XtensaError::NoXtensaTarget.into())
     }
 
+    /// Applies the memory AP options of the cores of `target` to the interface.
+    fn configure_memory_aps(interface: &mut dyn ArmDebugInterface, target: &Target) {
+        for core in &target.cores {
+            if let CoreAccessOptions::Arm(options) = &core.core_access_options {
+                if let (Some(memory_ap), Some(size)) =
+                    (core.memory_ap(), options.tar_autoincrement_size)
+                {
+                    interface.set_tar_autoincrement_size(&memory_ap, size);
+                }
+            }
+        }
+    }
+
     #[tracing::instrument(skip_all)]
     fn reattach_arm_interface(
         interface: &mut Box<dyn ArmDebugInterface>,
@@ -710,9 +973,12 @@ impl Session {
     }
 
     /// Check if the connected device has a debug erase sequence defined
+    ///
+    /// On JTAG targets, this checks if the probe can erase the device with a vendor command.
     pub fn has_sequence_erase_all(&self) -> bool {
-        match &self.target.debug_sequence {
-            DebugSequence::Arm(seq) => seq.debug_erase_sequence().is_some(),
+        match (&self.target.debug_sequence, &self.interfaces) {
+            (DebugSequence::Arm(seq), _) => seq.debug_erase_sequence().is_some(),
+            (_, ArchitectureInterface::Jtag(probe, _)) => probe.has_erase_all(),
             // Currently, debug_erase_sequence is ARM (and ATSAM) specific
             _ => false,
         }
@@ -720,6 +986,8 @@ impl Session {
 
     /// Erase all flash memory using the Device's Debug Erase Sequence if any
     ///
+    /// On JTAG targets, the erase command of the probe is used instead, if it has one.
+    ///
     /// # Returns
     /// Ok(()) if the device provides a custom erase sequence and it succeeded.
     ///
@@ -727,10 +995,28 @@ impl Session {
     /// NotImplemented if no custom erase sequence exists
     /// Err(e) if the custom erase sequence failed
     pub fn sequence_erase_all(&mut self) -> Result<(), Error> {
-        let ArchitectureInterface::Arm(ref mut interface) = self.interfaces else {
-            return Err(Error::NotImplemented(
-                "Debug Erase Sequence is not implemented for non-ARM targets.",
-            ));
+        let interface = match self.interfaces {
+            ArchitectureInterface::Arm(ref mut interface) => interface,
+            ArchitectureInterface::Jtag(ref mut probe, _) => {
+                if !probe.has_erase_all() {
+                    return Err(Error::NotImplemented(
+                        "Debug Erase Sequence is not implemented for non-ARM targets.",
+                    ));
+                }
+
+                tracing::info!("Erasing the device with the probe");
+                probe.erase_all()?;
+
+                // The probe resets the target after erasing it.
+                for core_id in 0..self.cores.len() {
+                    let mut core = self.core(core_id)?;
+                    if !core.core_halted()? {
+                        core.halt(Duration::from_millis(100))?;
+                    }
+                }
+                tracing::info!("Device Erased Successfully");
+                return Ok(());
+            }
         };
 
         let DebugSequence::Arm(ref debug_sequence) = self.target.debug_sequence else {
@@ -749,6 +1035,12 @@ impl Session {
             // In case this happens after unlock. Try to re-attach the probe once.
             Err(ArmError::ReAttachRequired) => {
                 Self::reattach_arm_interface(interface, debug_sequence)?;
+                Self::configure_memory_aps(&mut **interface, &self.target);
+                interface.configure_debug_power(DebugPowerConfig {
+                    recovery: self.power_down_recovery,
+                    ..DebugPowerConfig::default()
+                });
+                interface.configure_speed_fallback(self.speed_floor_khz);
                 // For re-setup debugging on all cores
                 for core_state in &self.cores {
                     core_state.enable_arm_debug(interface.deref_mut())?;
@@ -760,6 +1052,13 @@ impl Session {
         Ok(())
     }
 
+    /// Returns access to the option bytes of the target.
+    ///
+    /// Fails if the target description does not describe the option bytes.
+    pub fn option_bytes(&mut self) -> Result<OptionBytesAccess<'_>, OptionBytesError> {
+        OptionBytesAccess::new(self)
+    }
+
     /// Reads all the available ARM CoresightComponents of the currently attached target.
     ///
     /// This will recursively parse the Romtable of the attached target
@@ -778,6 +1077,134 @@ impl Session {
         &self.target
     }
 
+    /// Returns the peripherals of the target, if its target description references an SVD file.
+    ///
+    /// The SVD file is parsed on the first call, and cached by the [`Registry`] the session
+    /// was created with.
+    pub fn peripherals(&self) -> Result<Option<Arc<Peripherals>>, Error> {
+        let Some(svd) = &self.target.svd else {
+            return Ok(None);
+        };
+
+        self.peripheral_cache
+            .load(svd)
+            .map(Some)
+            .map_err(Error::Peripherals)
+    }
+
+    /// Sets how memory accesses of the cores outside the memory map of the target description
+    /// are handled. By default, accesses are not checked.
+    pub fn set_memory_map_policy(&mut self, policy: MemoryMapPolicy) {
+        self.memory_map_policy = policy;
+    }
+
+    /// Returns how memory accesses outside the memory map of the target description are handled.
+    pub fn memory_map_policy(&self) -> MemoryMapPolicy {
+        self.memory_map_policy
+    }
+
+    /// Sets what happens when the debug connection is lost because the target entered a
+    /// low-power mode. By default, the operation fails.
+    ///
+    /// This replaces the [`DebugPowerConfig`] of the debug interface.
+    pub fn set_power_down_recovery(&mut self, recovery: PowerDownRecovery) {
+        self.power_down_recovery = recovery;
+
+        if let ArchitectureInterface::Arm(interface) = &mut self.interfaces {
+            interface.configure_debug_power(DebugPowerConfig {
+                recovery,
+                ..DebugPowerConfig::default()
+            });
+        }
+    }
+
+    /// Returns what happens when the debug connection is lost because the target entered a
+    /// low-power mode.
+    pub fn power_down_recovery(&self) -> PowerDownRecovery {
+        self.power_down_recovery
+    }
+
+    /// Lowers the protocol speed when transfers keep failing during the session because they are
+    /// corrupted on the wire, down to `floor_khz`. By default, the speed is not changed.
+    ///
+    /// The operation during which the speed is lowered still returns its error.
+    pub fn set_speed_fallback(&mut self, floor_khz: Option<u32>) {
+        self.speed_floor_khz = floor_khz;
+
+        if let ArchitectureInterface::Arm(interface) = &mut self.interfaces {
+            interface.configure_speed_fallback(floor_khz);
+        }
+    }
+
+    /// Returns the lowest protocol speed in kHz the speed is lowered to during the session, if
+    /// lowering the speed is enabled.
+    pub fn speed_fallback(&self) -> Option<u32> {
+        self.speed_floor_khz
+    }
+
+    /// Reconnects to the target after its debug logic lost power, e.g. in a STOP or STANDBY
+    /// low-power mode, and restores the debug state, see [`Session::restore_debug_state`].
+    ///
+    /// With [`PowerDownRecovery::Reconnect`], the debug interface detects the power loss itself
+    /// and [`Session::core`] restores the debug state. Use this to recover manually from an
+    /// [`ArmError`] for which [`ArmError::is_power_down`] returns true.
+    pub fn recover_from_power_down(&mut self) -> Result<(), Error> {
+        let ArchitectureInterface::Arm(interface) = &mut self.interfaces else {
+            return Err(Error::NotImplemented(
+                "recovering from a power-down on non-ARM targets",
+            ));
+        };
+
+        interface.reinitialize()?;
+        // The state is restored right away.
+        interface.take_power_loss();
+
+        self.restore_debug_state()?;
+
+        tracing::info!("Reconnected to the target after a power-down");
+        Ok(())
+    }
+
+    /// Restores the debug state of the target after its debug logic lost power.
+    ///
+    /// This keeps the debug logic powered in low-power modes if the debug sequence supports it,
+    /// sets up debugging of all cores and restores the hardware breakpoints set through probe-rs.
+    fn restore_debug_state(&mut self) -> Result<(), Error> {
+        let ArchitectureInterface::Arm(interface) = &mut self.interfaces else {
+            return Ok(());
+        };
+
+        let DebugSequence::Arm(sequence) = &self.target.debug_sequence else {
+            unreachable!("Mismatch between architecture and sequence type!");
+        };
+
+        let default_memory_ap = self.target.default_core().memory_ap().ok_or_else(|| {
+            Error::Other("Unable to reconnect, no memory AP configured".to_string())
+        })?;
+
+        {
+            let mut memory = interface.memory_interface(&default_memory_ap)?;
+            sequence.debug_low_power(&mut *memory)?;
+        }
+
+        for core in &self.cores {
+            core.enable_arm_debug(&mut **interface)?;
+        }
+
+        for combined_state in &mut self.cores {
+            let mut core = self.interfaces.attach(&self.target, combined_state)?;
+            core.restore_hw_breakpoints()?;
+        }
+
+        Ok(())
+    }
+
+    /// Uses the SVD file at `location`, a path or a URL, instead of the one referenced by the
+    /// target description.
+    pub fn set_svd(&mut self, location: impl Into<String>) {
+        self.target.svd = Some(location.into());
+    }
+
     /// Configure the target and probe for serial wire view (SWV) tracing.
     pub fn setup_tracing(
         &mut self,
@@ -857,14 +1284,17 @@ impl Session {
         }
     }
 
-    /// Clears all hardware breakpoints on all cores
+    /// Clears all hardware and software breakpoints on all cores
     pub fn clear_all_hw_breakpoints(&mut self) -> Result<(), Error> {
         self.halted_access(|session| {
             { 0..session.cores.len() }.try_for_each(|core| {
                 tracing::info!("Clearing breakpoints for core {core}");
 
                 match session.core(core) {
-                    Ok(mut core) => core.clear_all_hw_breakpoints(),
+                    Ok(mut core) => {
+                        core.clear_all_hw_breakpoints()?;
+                        core.clear_all_sw_breakpoints()
+                    }
                     Err(Error::CoreDisabled(_)) => Ok(()),
                     Err(err) => Err(err),
                 }
@@ -874,12 +1304,12 @@ impl Session {
 
     /// Resume all cores
     pub fn resume_all_cores(&mut self) -> Result<(), Error> {
-        // Resume cores
+        let mut halted_cores = vec![];
         for core_id in 0..self.cores.len() {
             match self.core(core_id) {
                 Ok(mut core) => {
                     if core.core_halted()? {
-                        core.run()?;
+                        halted_cores.push(core_id);
                     }
                 }
                 Err(Error::CoreDisabled(i)) => tracing::debug!("Core {i} is disabled"),
@@ -887,7 +1317,8 @@ impl Session {
             }
         }
 
-        Ok(())
+        // Resume cores
+        self.resume_cores(&halted_cores)
     }
 }
 
@@ -919,6 +1350,83 @@ impl Drop for Session {
     }
 }
 
+/// Checks if attaching failed because the device is secured.
+///
+/// The debug sequence is asked for the security state first. Vendor unlock procedures erase
+/// the device when `allow_erase_all` is given, so this is the recovery offered for them.
+/// Without vendor specific information, an access port which reads back an IDR of zero is
+/// taken as a locked device, which can only be recovered by a debug erase sequence.
+fn secured_device_error(
+    interface: &mut dyn ArmDebugInterface,
+    sequence: &Arc<dyn ArmDebugSequence>,
+    default_ap: &FullyQualifiedApAddress,
+) -> Option<Error> {
+    let available_recovery = match sequence.security_state(interface, default_ap) {
+        Ok(Some(SecurityState::Locked)) => Some(SecurityRecovery::EraseAll),
+        Ok(Some(SecurityState::Unlocked)) => return None,
+        Ok(None) => {
+            if interface
+                .read_raw_ap_register(default_ap, IDR::ADDRESS)
+                .ok()?
+                != 0
+            {
+                return None;
+            }
+            sequence
+                .debug_erase_sequence()
+                .map(|_| SecurityRecovery::EraseAll)
+        }
+        Err(e) => {
+            tracing::debug!("Failed to determine the security state of the device: {e}");
+            return None;
+        }
+    };
+
+    Some(Error::DeviceSecured { available_recovery })
+}
+
+/// Scans the JTAG chain if the target description doesn't describe it.
+///
+/// The detected TAPs are logged, and their IR lengths are configured as the expected scan chain,
+/// which is used when the chain is scanned again.
+fn populate_scan_chain(probe: &mut Probe, target: &Target) {
+    let has_scan_chain = target
+        .jtag
+        .as_ref()
+        .is_some_and(|jtag| jtag.scan_chain.is_some());
+    if has_scan_chain || probe.protocol() != Some(WireProtocol::Jtag) {
+        return;
+    }
+
+    let chain = match probe.scan_jtag_chain() {
+        Ok(chain) => chain,
+        Err(error) => {
+            tracing::debug!("Failed to scan the JTAG chain: {error}");
+            return;
+        }
+    };
+
+    for (index, tap) in chain.iter().enumerate() {
+        match tap.idcode {
+            Some(idcode) => tracing::info!(
+                "JTAG TAP {index}: IDCODE {idcode}, IR length {}",
+                tap.ir_len
+            ),
+            None => tracing::info!("JTAG TAP {index}: no IDCODE, IR length {}", tap.ir_len),
+        }
+    }
+
+    let scan_chain = chain
+        .into_iter()
+        .map(ScanChainElement::from)
+        .collect::<Vec<_>>();
+    if let Some(probe) = probe.try_as_jtag_probe() {
+        if let Err(error) = probe.set_scan_chain(&scan_chain) {
+            tracing::debug!("Failed to configure the detected scan chain: {error}");
+        }
+    }
+}
+
 /// Determine the [Target] from a [TargetSelector].
 ///
 /// If the selector is [TargetSelector::Unspecified], the target will be looked up in the registry.
@@ -932,7 +1440,7 @@ fn get_target_from_selector(
 ) -> Result<(Probe, Target), Error> {
     let target = match target {
         TargetSelector::Unspecified(name) => registry.get_target_by_name(name)?,
-        TargetSelector::Specified(target) => target,
+        TargetSelector::Specified(target) => *target,
         TargetSelector::Auto => {
             // At this point we do not know what the target is, so we cannot use the chip specific reset sequence.
             // Thus, we try just using a normal reset for target detection if we want to do so under reset.
@@ -962,6 +1470,63 @@ fn get_target_from_selector(
     Ok((probe, target))
 }
 
+/// What to do with a core when attaching to it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HaltOnAttach {
+    /// Leave the core running.
+    #[default]
+    Run,
+    /// Halt the core wherever it is currently executing.
+    Halt,
+    /// Reset the target and halt the core on the first instruction at its reset vector.
+    ResetVector,
+}
+
+/// The `AttachOptions` struct configures the state of each core after attaching to a target.
+///
+/// Cores without an explicit setting are left running.
+///
+/// # Example
+///
+/// ```
+/// use probe_rs::{AttachOptions, HaltOnAttach};
+///
+/// let options = AttachOptions::new()
+///     .halt_on_attach(0, HaltOnAttach::ResetVector)
+///     .halt_on_attach(1, HaltOnAttach::Halt);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AttachOptions {
+    cores: BTreeMap<usize, HaltOnAttach>,
+}
+
+impl AttachOptions {
+    /// Constructs a new attach options object which leaves all cores running.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets what to do with the core with the given index when attaching.
+    #[must_use]
+    pub fn halt_on_attach(mut self, core_index: usize, halt: HaltOnAttach) -> Self {
+        self.cores.insert(core_index, halt);
+        self
+    }
+
+    /// Returns what to do with the core with the given index when attaching.
+    pub fn core(&self, core_index: usize) -> HaltOnAttach {
+        self.cores.get(&core_index).copied().unwrap_or_default()
+    }
+
+    fn cores_with(&self, halt: HaltOnAttach) -> Vec<usize> {
+        self.cores
+            .iter()
+            .filter(|(_, h)| **h == halt)
+            .map(|(core_index, _)| *core_index)
+            .collect()
+    }
+}
+
 /// The `Permissions` struct represents what a [Session] is allowed to do with a target.
 /// Some operations can be irreversible, so need to be explicitly allowed by the user.
 ///
@@ -977,6 +1542,8 @@ fn get_target_from_selector(
 pub struct Permissions {
     /// When set to true, all memory of the chip may be erased or reset to factory default
     erase_all: bool,
+    /// When set to true, the debug freeze bits of the target are not changed when attaching
+    no_debug_freeze: bool,
 }
 
 impl Permissions {
@@ -998,6 +1565,22 @@ impl Permissions {
         }
     }
 
+    /// Don't change the debug freeze bits of the target description when attaching, so that the
+    /// peripherals keep running while a core is halted, unless the firmware stops them itself.
+    ///
+    /// See [`FreezeConfig`] for the peripherals which are stopped otherwise.
+    #[must_use]
+    pub fn disable_debug_freeze(self) -> Self {
+        Self {
+            no_debug_freeze: true,
+            ..self
+        }
+    }
+
+    pub(crate) fn debug_freeze(&self) -> bool {
+        !self.no_debug_freeze
+    }
+
     pub(crate) fn erase_all(&self) -> Result<(), MissingPermissions> {
         if self.erase_all {
             Ok(())
@@ -0,0 +1,496 @@
+//! Sharing a [`Session`] between several stakeholders.
+//!
+//! Each stakeholder, e.g. a GDB server, an RTT reader or a SWO poller, takes a [`Lease`] on the
+//! session while it accesses the target, and returns it by dropping the lease. When the session is
+//! released, it is handed to the waiting stakeholder with the highest [`LeasePriority`], in the
+//! order the leases were requested. Stakeholders that waited longer than the starvation limit are
+//! served first, regardless of their priority.
+
+use std::{
+    cmp::Reverse,
+    fmt,
+    ops::{Deref, DerefMut},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread::{self, ThreadId},
+    time::{Duration, Instant},
+};
+
+use parking_lot::{Condvar, Mutex, MutexGuard};
+
+use crate::Session;
+
+/// How long a stakeholder waits for a lease before it is served ahead of stakeholders with a
+/// higher priority.
+const DEFAULT_STARVATION_LIMIT: Duration = Duration::from_millis(500);
+
+/// The priority of a [`Stakeholder`] when several of them wait for a lease.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LeasePriority {
+    /// Polling that can be delayed, e.g. reading SWO data.
+    Background,
+    /// Regular accesses, e.g. reading RTT channels.
+    Normal,
+    /// Accesses a user is waiting for, e.g. requests of a GDB client.
+    Interactive,
+}
+
+/// An error that occurred while waiting for a lease on a [`SharedSession`].
+#[derive(Debug, thiserror::Error)]
+pub enum LeaseError {
+    /// The stakeholder requested a lease while it already holds one.
+    #[error("The stakeholder '{0}' already holds a lease on the session.")]
+    AlreadyHeld(String),
+    /// The lease is held by another stakeholder on the same thread, so it can never be released
+    /// while waiting.
+    #[error(
+        "The stakeholder '{requester}' waits for a lease held by '{holder}' on the same thread, which would never be released."
+    )]
+    Deadlock {
+        /// The stakeholder that requested the lease.
+        requester: String,
+        /// The stakeholder that holds the lease.
+        holder: String,
+    },
+    /// No lease was granted before the timeout expired.
+    #[error("Timed out waiting for a lease on the session held by '{holder}'.")]
+    Timeout {
+        /// The stakeholder that holds the lease, or is about to take it.
+        holder: String,
+    },
+}
+
+/// A [`Session`] that is shared between several stakeholders.
+///
+/// This replaces wrapping the session in an `Arc<FairMutex<Session>>`. Cloning a `SharedSession`
+/// shares the same session.
+///
+/// ```no_run
+/// use probe_rs::{LeasePriority, Session, SharedSession};
+/// use std::time::Duration;
+///
+/// fn run(session: Session) -> Result<(), Box<dyn std::error::Error>> {
+///     let shared = SharedSession::new(session);
+///
+///     let rtt = shared.stakeholder("RTT", LeasePriority::Normal);
+///     std::thread::spawn(move || -> Result<(), probe_rs::Error> {
+///         loop {
+///             let mut session = rtt.lease().unwrap();
+///             let core = session.core(0)?;
+///             // Poll the RTT channels with `core`, then give the others a turn.
+///             drop(core);
+///             drop(session);
+///             std::thread::sleep(Duration::from_millis(10));
+///         }
+///     });
+///
+///     let gdb = shared.stakeholder("GDB", LeasePriority::Interactive);
+///     let mut session = gdb.lease()?;
+///     session.core(0)?.halt(Duration::from_millis(100))?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct SharedSession {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    session: Mutex<Session>,
+    arbiter: Arbiter,
+    next_stakeholder: AtomicUsize,
+}
+
+impl SharedSession {
+    /// Shares `session`, with the default starvation limit of 500 ms.
+    pub fn new(session: Session) -> Self {
+        Self::with_starvation_limit(session, DEFAULT_STARVATION_LIMIT)
+    }
+
+    /// Shares `session`. Stakeholders that waited for a lease longer than `starvation_limit` are
+    /// served before stakeholders with a higher priority.
+    pub fn with_starvation_limit(session: Session, starvation_limit: Duration) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                session: Mutex::new(session),
+                arbiter: Arbiter::new(starvation_limit),
+                next_stakeholder: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Registers a stakeholder that accesses the session, e.g. the GDB server.
+    ///
+    /// The `name` is used in errors and logs.
+    pub fn stakeholder(&self, name: impl Into<String>, priority: LeasePriority) -> Stakeholder {
+        Stakeholder {
+            shared: self.shared.clone(),
+            id: self.shared.next_stakeholder.fetch_add(1, Ordering::Relaxed),
+            name: name.into(),
+            priority,
+        }
+    }
+
+    /// Returns the name of the stakeholder that currently holds the lease.
+    pub fn holder(&self) -> Option<String> {
+        self.shared
+            .arbiter
+            .state
+            .lock()
+            .holder
+            .as_ref()
+            .map(|holder| holder.name.clone())
+    }
+
+    /// Returns the session, if there are no other clones of this `SharedSession` and no
+    /// stakeholders left.
+    pub fn try_into_inner(self) -> Result<Session, Self> {
+        match Arc::try_unwrap(self.shared) {
+            Ok(shared) => Ok(shared.session.into_inner()),
+            Err(shared) => Err(Self { shared }),
+        }
+    }
+}
+
+impl fmt::Debug for SharedSession {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedSession")
+            .field("holder", &self.holder())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A user of a [`SharedSession`], created with [`SharedSession::stakeholder`].
+pub struct Stakeholder {
+    shared: Arc<Shared>,
+    id: usize,
+    name: String,
+    priority: LeasePriority,
+}
+
+impl Stakeholder {
+    /// The name of the stakeholder.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The priority of the stakeholder.
+    pub fn priority(&self) -> LeasePriority {
+        self.priority
+    }
+
+    /// Waits until the session is available and takes a lease on it.
+    ///
+    /// Returns an error instead of blocking forever if the lease is held on the same thread.
+    pub fn lease(&self) -> Result<Lease<'_>, LeaseError> {
+        self.acquire(None)
+    }
+
+    /// Waits up to `timeout` for a lease on the session.
+    pub fn lease_timeout(&self, timeout: Duration) -> Result<Lease<'_>, LeaseError> {
+        self.acquire(Some(Instant::now() + timeout))
+    }
+
+    /// Takes a lease on the session if it is available right now.
+    pub fn try_lease(&self) -> Result<Option<Lease<'_>>, LeaseError> {
+        match self.acquire(Some(Instant::now())) {
+            Ok(lease) => Ok(Some(lease)),
+            Err(LeaseError::Timeout { .. }) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn acquire(&self, deadline: Option<Instant>) -> Result<Lease<'_>, LeaseError> {
+        self.shared
+            .arbiter
+            .acquire(self.id, &self.name, self.priority, deadline)?;
+        tracing::trace!("{} took the lease on the session", self.name);
+
+        Ok(Lease {
+            stakeholder: self,
+            session: self.shared.session.lock(),
+        })
+    }
+}
+
+impl fmt::Debug for Stakeholder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Stakeholder")
+            .field("name", &self.name)
+            .field("priority", &self.priority)
+            .finish()
+    }
+}
+
+/// Exclusive access to the session of a [`SharedSession`], which is returned when dropped.
+///
+/// Keep leases short, so other stakeholders can take their turn.
+pub struct Lease<'a> {
+    stakeholder: &'a Stakeholder,
+    session: MutexGuard<'a, Session>,
+}
+
+impl Deref for Lease<'_> {
+    type Target = Session;
+
+    fn deref(&self) -> &Session {
+        &self.session
+    }
+}
+
+impl DerefMut for Lease<'_> {
+    fn deref_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+}
+
+impl Drop for Lease<'_> {
+    fn drop(&mut self) {
+        tracing::trace!(
+            "{} returned the lease on the session",
+            self.stakeholder.name
+        );
+        self.stakeholder.shared.arbiter.release();
+    }
+}
+
+impl fmt::Debug for Lease<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lease")
+            .field("stakeholder", &self.stakeholder.name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Decides which stakeholder holds the lease.
+struct Arbiter {
+    state: Mutex<ArbiterState>,
+    /// Notified when the lease is granted to a waiting stakeholder.
+    granted: Condvar,
+    starvation_limit: Duration,
+}
+
+#[derive(Default)]
+struct ArbiterState {
+    holder: Option<Holder>,
+    waiting: Vec<Waiter>,
+    /// The ticket of the waiter the lease was handed to, which hasn't taken it yet.
+    granted: Option<u64>,
+    next_ticket: u64,
+}
+
+struct Holder {
+    stakeholder: usize,
+    name: String,
+    thread: ThreadId,
+}
+
+struct Waiter {
+    ticket: u64,
+    priority: LeasePriority,
+    since: Instant,
+}
+
+impl Waiter {
+    /// The waiter with the highest rank is served first.
+    fn rank(&self, starvation_limit: Duration) -> (bool, Option<LeasePriority>, Reverse<u64>) {
+        // Starved waiters are served in order, regardless of their priority.
+        let starved = self.since.elapsed() >= starvation_limit;
+        let priority = (!starved).then_some(self.priority);
+        (starved, priority, Reverse(self.ticket))
+    }
+}
+
+impl Arbiter {
+    fn new(starvation_limit: Duration) -> Self {
+        Self {
+            state: Mutex::new(ArbiterState::default()),
+            granted: Condvar::new(),
+            starvation_limit,
+        }
+    }
+
+    fn acquire(
+        &self,
+        stakeholder: usize,
+        name: &str,
+        priority: LeasePriority,
+        deadline: Option<Instant>,
+    ) -> Result<(), LeaseError> {
+        let thread = thread::current().id();
+        let mut state = self.state.lock();
+
+        if let Some(holder) = &state.holder {
+            if holder.thread == thread {
+                return Err(if holder.stakeholder == stakeholder {
+                    LeaseError::AlreadyHeld(name.to_string())
+                } else {
+                    LeaseError::Deadlock {
+                        requester: name.to_string(),
+                        holder: holder.name.clone(),
+                    }
+                });
+            }
+        }
+
+        let holder = Holder {
+            stakeholder,
+            name: name.to_string(),
+            thread,
+        };
+
+        if state.holder.is_none() && state.waiting.is_empty() {
+            state.holder = Some(holder);
+            return Ok(());
+        }
+
+        let ticket = state.next_ticket;
+        state.next_ticket += 1;
+        state.waiting.push(Waiter {
+            ticket,
+            priority,
+            since: Instant::now(),
+        });
+
+        loop {
+            if state.granted == Some(ticket) {
+                state.granted = None;
+                state.waiting.retain(|waiter| waiter.ticket != ticket);
+                state.holder = Some(holder);
+                return Ok(());
+            }
+
+            match deadline {
+                None => self.granted.wait(&mut state),
+                Some(deadline) => {
+                    if self.granted.wait_until(&mut state, deadline).timed_out()
+                        && state.granted != Some(ticket)
+                    {
+                        state.waiting.retain(|waiter| waiter.ticket != ticket);
+                        let holder = state
+                            .holder
+                            .as_ref()
+                            .map_or_else(String::new, |holder| holder.name.clone());
+                        return Err(LeaseError::Timeout { holder });
+                    }
+                }
+            }
+        }
+    }
+
+    fn release(&self) {
+        let mut state = self.state.lock();
+        state.holder = None;
+
+        let next = state
+            .waiting
+            .iter()
+            .max_by_key(|waiter| waiter.rank(self.starvation_limit))
+            .map(|waiter| waiter.ticket);
+        if next.is_some() {
+            state.granted = next;
+            self.granted.notify_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait_for_waiters(arbiter: &Arbiter, count: usize) {
+        while arbiter.state.lock().waiting.len() < count {
+            thread::yield_now();
+        }
+    }
+
+    /// Queues a background and an interactive stakeholder while the lease is held, and returns
+    /// the order they were served in.
+    fn serving_order(starvation_limit: Duration) -> Vec<LeasePriority> {
+        let arbiter = Arbiter::new(starvation_limit);
+        let served = Mutex::new(Vec::new());
+
+        arbiter
+            .acquire(0, "GDB", LeasePriority::Interactive, None)
+            .unwrap();
+
+        thread::scope(|s| {
+            for (id, priority) in [
+                (1, LeasePriority::Background),
+                (2, LeasePriority::Interactive),
+            ] {
+                let (arbiter, served) = (&arbiter, &served);
+                s.spawn(move || {
+                    arbiter.acquire(id, "waiter", priority, None).unwrap();
+                    served.lock().push(priority);
+                    arbiter.release();
+                });
+                wait_for_waiters(arbiter, id);
+            }
+
+            thread::sleep(Duration::from_millis(10));
+            arbiter.release();
+        });
+
+        served.into_inner()
+    }
+
+    #[test]
+    fn higher_priority_is_served_first() {
+        assert_eq!(
+            serving_order(Duration::from_secs(60)),
+            [LeasePriority::Interactive, LeasePriority::Background]
+        );
+    }
+
+    #[test]
+    fn starved_stakeholders_are_served_in_order() {
+        assert_eq!(
+            serving_order(Duration::ZERO),
+            [LeasePriority::Background, LeasePriority::Interactive]
+        );
+    }
+
+    #[test]
+    fn same_thread_is_detected() {
+        let arbiter = Arbiter::new(DEFAULT_STARVATION_LIMIT);
+        arbiter
+            .acquire(0, "GDB", LeasePriority::Interactive, None)
+            .unwrap();
+
+        assert!(matches!(
+            arbiter.acquire(0, "GDB", LeasePriority::Interactive, None),
+            Err(LeaseError::AlreadyHeld(name)) if name == "GDB"
+        ));
+        assert!(matches!(
+            arbiter.acquire(1, "RTT", LeasePriority::Normal, None),
+            Err(LeaseError::Deadlock { requester, holder }) if requester == "RTT" && holder == "GDB"
+        ));
+    }
+
+    #[test]
+    fn timeout_names_the_holder() {
+        let arbiter = Arbiter::new(DEFAULT_STARVATION_LIMIT);
+        arbiter
+            .acquire(0, "GDB", LeasePriority::Interactive, None)
+            .unwrap();
+
+        thread::scope(|s| {
+            s.spawn(|| {
+                let deadline = Instant::now() + Duration::from_millis(10);
+                assert!(matches!(
+                    arbiter.acquire(1, "SWO", LeasePriority::Background, Some(deadline)),
+                    Err(LeaseError::Timeout { holder }) if holder == "GDB"
+                ));
+            });
+        });
+
+        assert!(arbiter.state.lock().waiting.is_empty());
+        arbiter.release();
+        arbiter
+            .acquire(1, "SWO", LeasePriority::Background, Some(Instant::now()))
+            .unwrap();
+    }
+}
@@ -1,4 +1,4 @@
-use probe_rs::rtt::{Rtt, RttChannel, ScanRegion};
+use probe_rs::rtt::{ControlBlockStatus, Rtt, RttChannel, ScanRegion};
 use probe_rs::{Permissions, probe::list::Lister};
 use probe_rs::{config::TargetSelector, probe::DebugProbeInfo};
 
@@ -8,6 +8,10 @@ use std::io::prelude::*;
 use std::io::{stdin, stdout};
 use std::sync::mpsc::{Receiver, channel};
 use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the control block is checked for being reinitialized, e.g. after a target reset.
+const CONTROL_BLOCK_CHECK_INTERVAL: Duration = Duration::from_millis(500);
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 enum ProbeInfo {
@@ -204,7 +208,40 @@ fn main() -> Result<()> {
         core.reset()?;
     }
 
+    let mut last_control_block_check = Instant::now();
+    let mut control_block_present = true;
+
     loop {
+        if last_control_block_check.elapsed() >= CONTROL_BLOCK_CHECK_INTERVAL {
+            last_control_block_check = Instant::now();
+
+            match rtt
+                .control_block_status(&mut core)
+                .context("Error checking the RTT control block")?
+            {
+                ControlBlockStatus::Unchanged => control_block_present = true,
+                ControlBlockStatus::Missing => {
+                    if control_block_present {
+                        eprintln!(
+                            "\nRTT control block was cleared, waiting for the target to initialize it"
+                        );
+                    }
+                    control_block_present = false;
+                }
+                ControlBlockStatus::Reinitialized => {
+                    eprintln!("\nRTT control block was reinitialized, re-attaching");
+                    rtt.reattach(&mut core)
+                        .context("Error re-attaching to RTT")?;
+                    control_block_present = true;
+                }
+            }
+        }
+
+        if !control_block_present {
+            thread::sleep(Duration::from_millis(10));
+            continue;
+        }
+
         if let Some(up_channel) = rtt.up_channel(up_channel) {
             let count = match up_channel.read(&mut core, up_buf.as_mut()) {
                 Ok(count) => count,
@@ -93,6 +93,8 @@ pub fn cmd_elf(
             manufacturer: None,
             generated_from_pack: false,
             chip_detection: vec![],
+            debug_freeze: vec![],
+            watchdogs: vec![],
             pack_file_release: None,
             variants: vec![Chip {
                 cores: vec![Core {
@@ -103,6 +105,7 @@ pub fn cmd_elf(
                         targetsel: None,
                         debug_base: None,
                         cti_base: None,
+                        tar_autoincrement_size: None,
                         jtag_tap: None,
                     }),
                 }],
@@ -133,6 +136,13 @@ pub fn cmd_elf(
                 rtt_scan_ranges: None,
                 jtag: None,
                 default_binary_format: None,
+                option_bytes: None,
+                address_aliases: vec![],
+                uf2_family_ids: vec![],
+                debug_description: None,
+                debug_freeze: vec![],
+                reset_strategies: vec![],
+                watchdogs: vec![],
             }],
             flash_algorithms: vec![algorithm],
             source: TargetDescriptionSource::BuiltIn,
@@ -393,6 +403,8 @@ mod test {
             manufacturer: None,
             generated_from_pack: false,
             chip_detection: vec![],
+            debug_freeze: vec![],
+            watchdogs: vec![],
             pack_file_release: None,
             variants: vec![chip],
             flash_algorithms: vec![],
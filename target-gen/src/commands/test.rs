@@ -96,6 +96,7 @@ pub fn cmd_test(
         permissions,
         speed,
         protocol,
+        ..Default::default()
     };
 
     let lister = Lister::new();
@@ -154,6 +155,9 @@ pub fn cmd_test(
                 println!("{prefix}: {message}");
             }
         }
+        ProgressEvent::PipelineStats(stats) => {
+            println!("Double buffering: {stats}");
+        }
         _ => (),
     });
 
@@ -0,0 +1,200 @@
+//! Extraction of the debug sequences from the debug description of a `.pdsc` file.
+//!
+//! `<debugvars>` and `<sequences>` can be placed on every level of the device hierarchy, and are
+//! inherited by the levels below. A sequence on a lower level replaces the inherited sequence
+//! with the same name and processor.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use probe_rs_target::{DebugDescription, DebugSequenceDescription, DebugSequenceItem};
+use roxmltree::{Document, Node};
+
+/// Extracts the debug descriptions of all devices and variants in a `.pdsc` file, by name.
+///
+/// Devices without debug sequences are not part of the result.
+pub fn extract_debug_descriptions(pdsc: &str) -> Result<HashMap<String, DebugDescription>> {
+    let document = Document::parse(pdsc).context("Failed to parse the .pdsc file")?;
+
+    let mut descriptions = HashMap::new();
+    for devices in document
+        .root_element()
+        .children()
+        .filter(|node| node.has_tag_name("devices"))
+    {
+        visit(devices, &DebugDescription::default(), &mut descriptions)?;
+    }
+
+    descriptions.retain(|_, description: &mut DebugDescription| !description.sequences.is_empty());
+
+    Ok(descriptions)
+}
+
+fn visit(
+    node: Node<'_, '_>,
+    inherited: &DebugDescription,
+    descriptions: &mut HashMap<String, DebugDescription>,
+) -> Result<()> {
+    for child in node.children().filter(Node::is_element) {
+        let name_attribute = match child.tag_name().name() {
+            "family" | "subFamily" => None,
+            "device" => Some("Dname"),
+            "variant" => Some("Dvariant"),
+            _ => continue,
+        };
+
+        let description = merge(inherited, child)?;
+
+        if let Some(name) = name_attribute.and_then(|attribute| child.attribute(attribute)) {
+            descriptions.insert(name.to_string(), description.clone());
+        }
+
+        visit(child, &description, descriptions)?;
+    }
+
+    Ok(())
+}
+
+/// Applies the debug description elements of `node` on top of the inherited description.
+fn merge(inherited: &DebugDescription, node: Node<'_, '_>) -> Result<DebugDescription> {
+    let mut description = inherited.clone();
+
+    let debug_vars = node
+        .children()
+        .filter(|child| child.has_tag_name("debugvars"))
+        .map(text)
+        .collect::<Vec<_>>();
+    if !debug_vars.is_empty() {
+        description.debug_vars = debug_vars.join("\n");
+    }
+
+    for sequences in node
+        .children()
+        .filter(|child| child.has_tag_name("sequences"))
+    {
+        for sequence in sequences
+            .children()
+            .filter(|child| child.has_tag_name("sequence"))
+        {
+            let sequence = parse_sequence(sequence)?;
+            description.sequences.retain(|existing| {
+                existing.name != sequence.name || existing.pname != sequence.pname
+            });
+            description.sequences.push(sequence);
+        }
+    }
+
+    Ok(description)
+}
+
+fn parse_sequence(node: Node<'_, '_>) -> Result<DebugSequenceDescription> {
+    let name = node
+        .attribute("name")
+        .context("Found a <sequence> without a name")?;
+
+    Ok(DebugSequenceDescription {
+        name: name.to_string(),
+        pname: node.attribute("Pname").map(str::to_string),
+        disable: flag(node.attribute("disable")),
+        items: parse_items(node).with_context(|| format!("Failed to parse sequence {name}"))?,
+    })
+}
+
+fn parse_items(node: Node<'_, '_>) -> Result<Vec<DebugSequenceItem>> {
+    let mut items = vec![];
+
+    for child in node.children().filter(Node::is_element) {
+        match child.tag_name().name() {
+            "block" => items.push(DebugSequenceItem::Block {
+                atomic: flag(child.attribute("atomic")),
+                code: text(child),
+            }),
+            "control" => {
+                let timeout = child
+                    .attribute("timeout")
+                    .map(parse_int::parse::<u64>)
+                    .transpose()
+                    .context("Invalid timeout of a <control> element")?
+                    .unwrap_or(0);
+
+                items.push(DebugSequenceItem::Control {
+                    if_condition: child.attribute("if").map(str::to_string),
+                    while_condition: child.attribute("while").map(str::to_string),
+                    timeout,
+                    items: parse_items(child)?,
+                });
+            }
+            other => log::debug!("Ignoring unknown element <{other}> in a debug sequence"),
+        }
+    }
+
+    Ok(items)
+}
+
+fn text(node: Node<'_, '_>) -> String {
+    node.children()
+        .filter_map(|child| child.text())
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn flag(value: Option<&str>) -> bool {
+    matches!(value, Some("1" | "true"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PDSC: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<package>
+  <devices>
+    <family Dfamily="Test Family" Dvendor="Test:0">
+      <debugvars>__var Unlock = 1;</debugvars>
+      <sequences>
+        <sequence name="ResetSystem">
+          <block>Write32(0xE000ED0C, 0x05FA0004);</block>
+        </sequence>
+        <sequence name="DebugDeviceUnlock">
+          <control if="Unlock" while="(Read32(0x40000000) &amp; 1) == 0" timeout="1000000">
+            <block atomic="1">Write32(0x40000000, 1);</block>
+          </control>
+        </sequence>
+      </sequences>
+      <device Dname="Plain"/>
+      <device Dname="Special">
+        <sequences>
+          <sequence name="ResetSystem" disable="1"/>
+        </sequences>
+      </device>
+    </family>
+  </devices>
+</package>"#;
+
+    #[test]
+    fn sequences_are_inherited_and_overridden() {
+        let descriptions = extract_debug_descriptions(PDSC).unwrap();
+
+        let plain = &descriptions["Plain"];
+        assert_eq!(plain.debug_vars, "__var Unlock = 1;");
+        assert_eq!(plain.sequences.len(), 2);
+        assert!(!plain.sequence("ResetSystem", None).unwrap().disable);
+        assert_eq!(
+            plain.sequence("DebugDeviceUnlock", None).unwrap().items,
+            vec![DebugSequenceItem::Control {
+                if_condition: Some("Unlock".to_string()),
+                while_condition: Some("(Read32(0x40000000) & 1) == 0".to_string()),
+                timeout: 1_000_000,
+                items: vec![DebugSequenceItem::Block {
+                    atomic: true,
+                    code: "Write32(0x40000000, 1);".to_string(),
+                }],
+            }]
+        );
+
+        let special = &descriptions["Special"];
+        assert_eq!(special.sequences.len(), 2);
+        assert!(special.sequence("ResetSystem", None).unwrap().disable);
+    }
+}
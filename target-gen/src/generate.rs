@@ -1,3 +1,4 @@
+use crate::debug_description::extract_debug_descriptions;
 use anyhow::{Context, Error, Result, anyhow, bail};
 use cmsis_pack::pdsc::{AccessPort, Algorithm, Core, Device, Package, Processor};
 use cmsis_pack::{pack_index::PdscRef, utils::FromElem};
@@ -70,6 +71,7 @@ where
 
 pub(crate) fn extract_families<T>(
     pdsc: Package,
+    pdsc_source: &str,
     mut kind: Kind<T>,
     families: &mut Vec<ChipFamily>,
     only_supported_familes: bool,
@@ -77,13 +79,22 @@ pub(crate) fn extract_families<T>(
 where
     T: std::io::Seek + std::io::Read,
 {
+    // The debug sequences are not part of `Package`, so they are read from the raw XML.
+    let debug_descriptions = match extract_debug_descriptions(pdsc_source) {
+        Ok(descriptions) => descriptions,
+        Err(error) => {
+            log::warn!("Failed to extract the debug sequences: {error:?}");
+            HashMap::new()
+        }
+    };
+
     // Forge a definition file for each device in the .pdsc file.
     let mut devices = pdsc.devices.0.into_iter().collect::<Vec<_>>();
     devices.sort_by(|a, b| a.0.cmp(&b.0));
 
     // Only process this, if this belongs to a supported family.
     let registry = Registry::from_builtin_families();
-    let currently_supported_chip_families = registry.families();
+    let currently_supported_chip_families = registry.families().collect::<Vec<_>>();
 
     for (device_name, device) in devices {
         if only_supported_familes
@@ -109,6 +120,8 @@ where
                 manufacturer: try_parse_vendor(device.vendor.as_deref()),
                 generated_from_pack: true,
                 chip_detection: vec![],
+                debug_freeze: vec![],
+                watchdogs: vec![],
                 pack_file_release: Some(pdsc.releases.latest_release().version.clone()),
                 variants: Vec::new(),
                 flash_algorithms: Vec::new(),
@@ -164,6 +177,8 @@ where
         let mut memory_map = get_mem_map(&device, &cores);
         patch_memmap(&mut memory_map);
 
+        let debug_description = debug_descriptions.get(&device_name).cloned();
+
         family.variants.push(Chip {
             name: device_name,
             part: None,
@@ -176,6 +191,13 @@ where
             rtt_scan_ranges: None,
             jtag: None, // TODO, parse scan chain from sdf
             default_binary_format: None,
+            option_bytes: None,
+            address_aliases: vec![],
+            uf2_family_ids: vec![],
+            debug_description,
+            debug_freeze: vec![],
+            reset_strategies: vec![],
+            watchdogs: vec![],
         });
     }
 
@@ -211,6 +233,7 @@ fn create_core(processor: &Processor) -> Result<ProbeCore> {
                 targetsel: None,
                 debug_base: None,
                 cti_base: None,
+                tar_autoincrement_size: None,
                 jtag_tap: None,
             }),
             Architecture::Riscv => CoreAccessOptions::Riscv(RiscvCoreAccessOptions {
@@ -248,8 +271,10 @@ pub fn visit_dirs(path: &Path, families: &mut Vec<ChipFamily>) -> Result<()> {
 
             let package = Package::from_path(path)
                 .context(format!("Failed to open .pdsc file {}.", path.display()))?;
+            let pdsc = fs::read_to_string(path)
+                .context(format!("Failed to read .pdsc file {}.", path.display()))?;
 
-            extract_families::<fs::File>(package, Kind::Directory(path), families, false)
+            extract_families::<fs::File>(package, &pdsc, Kind::Directory(path), families, false)
                 .context(format!("Failed to process .pdsc file {}.", path.display()))?;
         }
 
@@ -298,7 +323,7 @@ pub fn visit_file(path: &Path, families: &mut Vec<ChipFamily>) -> Result<()> {
 
     drop(pdsc_file);
 
-    extract_families(package, Kind::Archive(&mut archive), families, false)
+    extract_families(package, &pdsc, Kind::Archive(&mut archive), families, false)
 }
 
 pub async fn visit_arm_files(families: &mut Vec<ChipFamily>, filter: Option<String>) -> Result<()> {
@@ -415,6 +440,7 @@ pub(crate) async fn visit_arm_file(
 
     match extract_families(
         package,
+        &pdsc,
         Kind::Archive(&mut archive),
         &mut families,
         only_supported_familes,
@@ -1,5 +1,6 @@
 pub mod algorithm_binary;
 pub mod commands;
+pub mod debug_description;
 pub mod fetch;
 pub mod flash_device;
 pub mod generate;